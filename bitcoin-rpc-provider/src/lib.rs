@@ -163,6 +163,42 @@ fn rpc_err_to_manager_err(e: bitcoincore_rpc::Error) -> ManagerError {
     Error::RpcError(e).into()
 }
 
+/// Returns the spendable UTXOs of the wallet, which exclude the ones locked
+/// with `lockunspent`.
+fn list_spendable_utxos(client: &Client) -> Result<Vec<Utxo>, ManagerError> {
+    let utxo_res = client
+        .list_unspent(None, None, None, Some(false), None)
+        .map_err(rpc_err_to_manager_err)?;
+    let utxos = utxo_res
+        .iter()
+        .filter(|x| x.spendable)
+        .map(|x| {
+            Ok(Utxo {
+                tx_out: TxOut {
+                    value: x.amount.to_sat(),
+                    script_pubkey: x.script_pub_key.clone(),
+                },
+                outpoint: OutPoint {
+                    txid: x.txid,
+                    vout: x.vout,
+                },
+                address: x
+                    .address
+                    .as_ref()
+                    .map(|x| x.clone().assume_checked())
+                    .ok_or(Error::InvalidState)?,
+                redeem_script: x
+                    .redeem_script
+                    .as_ref()
+                    .cloned()
+                    .unwrap_or(ScriptBuf::new()),
+                reserved: false,
+            })
+        })
+        .collect::<Result<Vec<Utxo>, Error>>()?;
+    Ok(utxos)
+}
+
 fn enc_err_to_manager_err(_e: EncodeError) -> ManagerError {
     Error::BitcoinError.into()
 }
@@ -287,36 +323,10 @@ impl Wallet for BitcoinCoreProvider {
         lock_utxos: bool,
     ) -> Result<Vec<Utxo>, ManagerError> {
         let client = self.client.lock().unwrap();
-        let utxo_res = client
-            .list_unspent(None, None, None, Some(false), None)
-            .map_err(rpc_err_to_manager_err)?;
-        let mut utxo_pool: Vec<UtxoWrap> = utxo_res
-            .iter()
-            .filter(|x| x.spendable)
-            .map(|x| {
-                Ok(UtxoWrap(Utxo {
-                    tx_out: TxOut {
-                        value: x.amount.to_sat(),
-                        script_pubkey: x.script_pub_key.clone(),
-                    },
-                    outpoint: OutPoint {
-                        txid: x.txid,
-                        vout: x.vout,
-                    },
-                    address: x
-                        .address
-                        .as_ref()
-                        .map(|x| x.clone().assume_checked())
-                        .ok_or(Error::InvalidState)?,
-                    redeem_script: x
-                        .redeem_script
-                        .as_ref()
-                        .cloned()
-                        .unwrap_or(ScriptBuf::new()),
-                    reserved: false,
-                }))
-            })
-            .collect::<Result<Vec<UtxoWrap>, Error>>()?;
+        let mut utxo_pool: Vec<UtxoWrap> = list_spendable_utxos(&client)?
+            .into_iter()
+            .map(UtxoWrap)
+            .collect();
         // TODO(tibo): properly compute the cost of change
         let selection = select_coins(amount, 20, &mut utxo_pool).ok_or(Error::NotEnoughCoins)?;
 
@@ -330,6 +340,10 @@ impl Wallet for BitcoinCoreProvider {
         Ok(selection.into_iter().map(|x| x.0).collect())
     }
 
+    fn list_utxos(&self) -> Result<Vec<Utxo>, ManagerError> {
+        list_spendable_utxos(&self.client.lock().unwrap())
+    }
+
     fn import_address(&self, address: &Address) -> Result<(), ManagerError> {
         self.client
             .lock()
@@ -390,7 +404,22 @@ impl Wallet for BitcoinCoreProvider {
         Ok(())
     }
 
-    fn unreserve_utxos(&self, outpoints: &[OutPoint]) -> Result<(), ManagerError> {
+    fn reserve_utxos(&self, outpoints: &[OutPoint]) -> Result<(), ManagerError> {
+        match self
+            .client
+            .lock()
+            .unwrap()
+            .lock_unspent(outpoints)
+            .map_err(rpc_err_to_manager_err)?
+        {
+            true => Ok(()),
+            false => Err(ManagerError::StorageError(format!(
+                "Failed to lock utxos: {outpoints:?}"
+            ))),
+        }
+    }
+
+    fn release_utxos(&self, outpoints: &[OutPoint]) -> Result<(), ManagerError> {
         match self
             .client
             .lock()
@@ -493,6 +522,31 @@ impl Blockchain for BitcoinCoreProvider {
             },
         }
     }
+    fn get_min_relay_fee_rate(&self) -> Result<u64, ManagerError> {
+        let network_info = self
+            .client
+            .lock()
+            .unwrap()
+            .get_network_info()
+            .map_err(rpc_err_to_manager_err)?;
+        Ok(sat_per_kvb_to_sat_per_vb(network_info.relay_fee.to_sat()))
+    }
+
+    fn get_incremental_relay_fee_rate(&self) -> Result<u64, ManagerError> {
+        let network_info = self
+            .client
+            .lock()
+            .unwrap()
+            .get_network_info()
+            .map_err(rpc_err_to_manager_err)?;
+        Ok(sat_per_kvb_to_sat_per_vb(
+            network_info.incremental_fee.to_sat(),
+        ))
+    }
+}
+
+fn sat_per_kvb_to_sat_per_vb(fee_rate: u64) -> u64 {
+    (fee_rate + 999) / 1000
 }
 
 impl FeeEstimator for BitcoinCoreProvider {
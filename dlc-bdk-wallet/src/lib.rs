@@ -0,0 +1,424 @@
+//! # dlc-bdk-wallet
+//! Implementation of the [`Wallet`] trait of the `dlc-manager` on top of a
+//! [`bdk::Wallet`], see the crate Readme for more information.
+
+#![crate_name = "dlc_bdk_wallet"]
+// Coding conventions
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+use std::collections::HashSet;
+use std::sync::{Mutex, MutexGuard};
+
+use bdk::database::BatchDatabase;
+use bdk::wallet::coin_selection::{BranchAndBoundCoinSelection, CoinSelectionAlgorithm};
+use bdk::wallet::AddressIndex;
+use bdk::{FeeRate, LocalUtxo, SignOptions, Utxo as BdkUtxo, WeightedUtxo};
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, OutPoint, Script, ScriptBuf};
+use dlc_manager::error::Error;
+use dlc_manager::{Utxo, Wallet};
+
+/// Adapter implementing the [`Wallet`] trait for a [`bdk::Wallet`].
+pub struct BdkWallet<D: BatchDatabase> {
+    wallet: Mutex<bdk::Wallet<D>>,
+    reserved_utxos: Mutex<HashSet<OutPoint>>,
+}
+
+impl<D: BatchDatabase> BdkWallet<D> {
+    /// Creates a new adapter for the given wallet, with no reserved UTXOs.
+    pub fn new(wallet: bdk::Wallet<D>) -> Self {
+        BdkWallet {
+            wallet: Mutex::new(wallet),
+            reserved_utxos: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Returns the wrapped wallet, for example to synchronize it with the
+    /// blockchain. The wallet must not be kept locked while calling the
+    /// methods of the [`Wallet`] trait.
+    pub fn wallet(&self) -> MutexGuard<'_, bdk::Wallet<D>> {
+        self.wallet.lock().unwrap()
+    }
+
+    /// Returns the UTXOs that are currently reserved for the funding of
+    /// contracts. They should be marked as unspendable when building other
+    /// transactions with the wrapped wallet.
+    pub fn get_reserved_utxos(&self) -> Vec<OutPoint> {
+        self.reserved_utxos
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+impl<D: BatchDatabase> Wallet for BdkWallet<D> {
+    fn get_new_address(&self) -> Result<Address, Error> {
+        Ok(self
+            .wallet()
+            .get_address(AddressIndex::New)
+            .map_err(wallet_error)?
+            .address)
+    }
+
+    fn get_new_change_address(&self) -> Result<Address, Error> {
+        Ok(self
+            .wallet()
+            .get_internal_address(AddressIndex::New)
+            .map_err(wallet_error)?
+            .address)
+    }
+
+    fn get_utxos_for_amount(
+        &self,
+        amount: u64,
+        fee_rate: u64,
+        lock_utxos: bool,
+    ) -> Result<Vec<Utxo>, Error> {
+        let wallet = self.wallet();
+        let mut reserved_utxos = self.reserved_utxos.lock().unwrap();
+        let unspent = wallet.list_unspent().map_err(wallet_error)?;
+        let utxos = unspent
+            .into_iter()
+            .filter(|x| {
+                !reserved_utxos.contains(&x.outpoint) && is_supported(&x.txout.script_pubkey)
+            })
+            .map(|x| WeightedUtxo {
+                satisfaction_weight: dlc::util::get_single_key_max_witness_len(
+                    &x.txout.script_pubkey,
+                ),
+                utxo: BdkUtxo::Local(x),
+            })
+            .collect::<Vec<_>>();
+        // The drain script is only used to compute the weight of a potential
+        // change output, which will pay to an internal address.
+        let drain_script = wallet
+            .get_internal_address(AddressIndex::Peek(0))
+            .map_err(wallet_error)?
+            .script_pubkey();
+        let selection = BranchAndBoundCoinSelection::default()
+            .coin_select(
+                &*wallet.database(),
+                Vec::new(),
+                utxos,
+                FeeRate::from_sat_per_vb(fee_rate as f32),
+                amount,
+                &drain_script,
+            )
+            .map_err(wallet_error)?;
+        let mut res = Vec::new();
+        for utxo in selection.selected {
+            let local_utxo: LocalUtxo = match utxo {
+                BdkUtxo::Local(l) => l,
+                BdkUtxo::Foreign { .. } => unreachable!("Only local UTXOs are provided"),
+            };
+            let address = Address::from_script(&local_utxo.txout.script_pubkey, wallet.network())
+                .map_err(|e| Error::WalletError(Box::new(e)))?;
+            if lock_utxos {
+                reserved_utxos.insert(local_utxo.outpoint);
+            }
+            res.push(Utxo {
+                tx_out: local_utxo.txout,
+                outpoint: local_utxo.outpoint,
+                address,
+                redeem_script: ScriptBuf::new(),
+                reserved: lock_utxos,
+            });
+        }
+        Ok(res)
+    }
+
+    fn list_utxos(&self) -> Result<Vec<Utxo>, Error> {
+        let wallet = self.wallet();
+        let reserved_utxos = self.reserved_utxos.lock().unwrap();
+        wallet
+            .list_unspent()
+            .map_err(wallet_error)?
+            .into_iter()
+            .filter(|x| {
+                !reserved_utxos.contains(&x.outpoint) && is_supported(&x.txout.script_pubkey)
+            })
+            .map(|x| {
+                let address = Address::from_script(&x.txout.script_pubkey, wallet.network())
+                    .map_err(|e| Error::WalletError(Box::new(e)))?;
+                Ok(Utxo {
+                    tx_out: x.txout,
+                    outpoint: x.outpoint,
+                    address,
+                    redeem_script: ScriptBuf::new(),
+                    reserved: false,
+                })
+            })
+            .collect()
+    }
+
+    fn import_address(&self, _: &Address) -> Result<(), Error> {
+        // Addresses that do not belong to the descriptors of the wallet cannot
+        // be tracked by BDK.
+        Ok(())
+    }
+
+    fn sign_psbt_input(
+        &self,
+        psbt: &mut PartiallySignedTransaction,
+        input_index: usize,
+    ) -> Result<(), Error> {
+        self.sign_psbt(psbt, &[input_index])
+    }
+
+    fn sign_psbt(
+        &self,
+        psbt: &mut PartiallySignedTransaction,
+        input_indexes: &[usize],
+    ) -> Result<(), Error> {
+        let wallet = self.wallet();
+        for input_index in input_indexes {
+            let tx_out = psbt
+                .inputs
+                .get(*input_index)
+                .and_then(|x| x.witness_utxo.clone())
+                .ok_or_else(|| {
+                    Error::InvalidParameters(format!("No witness UTXO for input {}", input_index))
+                })?;
+            if !wallet
+                .is_mine(&tx_out.script_pubkey)
+                .map_err(wallet_error)?
+            {
+                return Err(Error::InvalidParameters(format!(
+                    "Input {} does not belong to the wallet",
+                    input_index
+                )));
+            }
+        }
+
+        // Sign a copy of the PSBT so that only the requested inputs are
+        // updated, leaving the other inputs (including other inputs of the
+        // wallet) untouched.
+        let mut to_sign = psbt.clone();
+        for input in to_sign.inputs.iter_mut() {
+            // Funding inputs are always provided with a redeem script, which
+            // is empty for native segwit outputs.
+            if input.redeem_script.as_ref().map_or(false, |x| x.is_empty()) {
+                input.redeem_script = None;
+            }
+        }
+        let sign_options = SignOptions {
+            // Inputs spending contract outputs are only provided with their
+            // witness UTXO.
+            trust_witness_utxo: true,
+            ..SignOptions::default()
+        };
+        wallet
+            .sign(&mut to_sign, sign_options)
+            .map_err(wallet_error)?;
+        for input_index in input_indexes {
+            let signed = &to_sign.inputs[*input_index];
+            if signed.final_script_witness.is_none() {
+                return Err(Error::WalletError(
+                    format!("Could not sign input {}", input_index).into(),
+                ));
+            }
+            psbt.inputs[*input_index].final_script_sig = signed.final_script_sig.clone();
+            psbt.inputs[*input_index].final_script_witness = signed.final_script_witness.clone();
+        }
+        Ok(())
+    }
+
+    fn reserve_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error> {
+        self.reserved_utxos
+            .lock()
+            .unwrap()
+            .extend(outpoints.iter().cloned());
+        Ok(())
+    }
+
+    fn release_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error> {
+        let mut reserved_utxos = self.reserved_utxos.lock().unwrap();
+        for outpoint in outpoints {
+            reserved_utxos.remove(outpoint);
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether the given script can be used to fund a contract, which is
+/// currently limited to native segwit v0 and taproot single key outputs.
+fn is_supported(script_pubkey: &Script) -> bool {
+    script_pubkey.is_v0_p2wpkh() || script_pubkey.is_v1_p2tr()
+}
+
+fn wallet_error(e: bdk::Error) -> Error {
+    Error::WalletError(Box::new(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::database::{BatchOperations, Database, MemoryDatabase};
+    use bdk::KeychainKind;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Network, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+
+    const DESCRIPTOR: &str = "wpkh(tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/84'/1'/0'/0/*)";
+    const CHANGE_DESCRIPTOR: &str = "wpkh(tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/84'/1'/0'/1/*)";
+
+    fn get_funded_wallet(values: &[u64]) -> (BdkWallet<MemoryDatabase>, Vec<OutPoint>) {
+        let script_pubkey = bdk::Wallet::new(
+            DESCRIPTOR,
+            Some(CHANGE_DESCRIPTOR),
+            Network::Regtest,
+            MemoryDatabase::new(),
+        )
+        .unwrap()
+        .get_address(AddressIndex::Peek(0))
+        .unwrap()
+        .script_pubkey();
+        let mut database = MemoryDatabase::new();
+        database
+            .set_script_pubkey(&script_pubkey, KeychainKind::External, 0)
+            .unwrap();
+        let mut outpoints = Vec::new();
+        for (i, value) in values.iter().enumerate() {
+            let outpoint = OutPoint {
+                txid: Txid::from_byte_array([i as u8 + 1; 32]),
+                vout: 0,
+            };
+            database
+                .set_utxo(&LocalUtxo {
+                    outpoint,
+                    txout: TxOut {
+                        value: *value,
+                        script_pubkey: script_pubkey.clone(),
+                    },
+                    keychain: KeychainKind::External,
+                    is_spent: false,
+                })
+                .unwrap();
+            outpoints.push(outpoint);
+        }
+        let wallet = bdk::Wallet::new(
+            DESCRIPTOR,
+            Some(CHANGE_DESCRIPTOR),
+            Network::Regtest,
+            database,
+        )
+        .unwrap();
+        (BdkWallet::new(wallet), outpoints)
+    }
+
+    fn get_psbt(
+        wallet: &BdkWallet<MemoryDatabase>,
+        outpoints: &[OutPoint],
+    ) -> PartiallySignedTransaction {
+        let address = wallet.get_new_address().unwrap();
+        let tx = Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: outpoints
+                .iter()
+                .map(|x| TxIn {
+                    previous_output: *x,
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: 10000,
+                script_pubkey: address.script_pubkey(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        let unspent = wallet.wallet().list_unspent().unwrap();
+        for (input, outpoint) in psbt.inputs.iter_mut().zip(outpoints) {
+            input.witness_utxo = unspent
+                .iter()
+                .find(|x| &x.outpoint == outpoint)
+                .map(|x| x.txout.clone());
+            input.redeem_script = Some(ScriptBuf::new());
+        }
+        psbt
+    }
+
+    #[test]
+    fn change_addresses_are_internal() {
+        let (wallet, _) = get_funded_wallet(&[]);
+        let address = wallet.get_new_address().unwrap();
+        let change_address = wallet.get_new_change_address().unwrap();
+
+        assert_ne!(address, change_address);
+        assert_eq!(
+            Some(KeychainKind::Internal),
+            wallet
+                .wallet()
+                .database()
+                .get_path_from_script_pubkey(&change_address.script_pubkey())
+                .unwrap()
+                .map(|(keychain, _)| keychain)
+        );
+    }
+
+    #[test]
+    fn reserved_utxos_are_not_selected() {
+        let (wallet, _) = get_funded_wallet(&[100000, 200000]);
+
+        let utxos = wallet.get_utxos_for_amount(150000, 1, true).unwrap();
+        assert_eq!(1, utxos.len());
+        assert_eq!(200000, utxos[0].tx_out.value);
+        assert!(wallet.get_reserved_utxos().contains(&utxos[0].outpoint));
+
+        wallet
+            .get_utxos_for_amount(150000, 1, false)
+            .expect_err("Should not select reserved UTXOs");
+
+        wallet.release_utxos(&[utxos[0].outpoint]).unwrap();
+        wallet.get_utxos_for_amount(150000, 1, false).unwrap();
+        assert!(wallet.get_reserved_utxos().is_empty());
+    }
+
+    #[test]
+    fn only_requested_input_is_signed() {
+        let (wallet, outpoints) = get_funded_wallet(&[100000, 200000]);
+        let mut psbt = get_psbt(&wallet, &outpoints);
+
+        wallet.sign_psbt_input(&mut psbt, 1).unwrap();
+
+        assert!(psbt.inputs[0].final_script_witness.is_none());
+        assert_eq!(
+            2,
+            psbt.inputs[1].final_script_witness.as_ref().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn all_requested_inputs_are_signed() {
+        let (wallet, outpoints) = get_funded_wallet(&[100000, 200000]);
+        let mut psbt = get_psbt(&wallet, &outpoints);
+
+        wallet.sign_psbt(&mut psbt, &[0, 1]).unwrap();
+
+        assert!(psbt.inputs.iter().all(|x| x.final_script_witness.is_some()));
+    }
+
+    #[test]
+    fn foreign_input_is_not_signed() {
+        let (wallet, outpoints) = get_funded_wallet(&[100000]);
+        let mut psbt = get_psbt(&wallet, &outpoints);
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 100000,
+            script_pubkey: ScriptBuf::new_v0_p2wpkh(&bitcoin::WPubkeyHash::all_zeros()),
+        });
+
+        wallet
+            .sign_psbt_input(&mut psbt, 0)
+            .expect_err("Should not sign foreign input");
+    }
+}
@@ -0,0 +1,432 @@
+//! # dlc-bitcoind-provider
+//! Implementation of the [`Blockchain`] and [`WalletBlockchainProvider`]
+//! traits on top of the RPC interface of Bitcoin Core, that never requires
+//! private keys to be held by `bitcoind`, see the crate Readme for more
+//! information.
+
+#![crate_name = "dlc_bitcoind_provider"]
+// Coding conventions
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+pub mod subscription;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use bitcoin::{Address, Block, Network, OutPoint, Script, Transaction, TxOut, Txid};
+use bitcoincore_rpc::json::{EstimateMode, GetTransactionResult};
+use bitcoincore_rpc::jsonrpc::serde_json::{json, Value};
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use dlc_manager::error::Error;
+use dlc_manager::{Blockchain, Utxo};
+use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
+use log::{error, warn};
+use simple_wallet::WalletBlockchainProvider;
+
+/// The minimum fee rate, in satoshis per 1000 weight units, returned by the
+/// [`FeeEstimator`] implementation.
+pub const MIN_FEERATE: u32 = 253;
+
+/// Error code returned by `bitcoind` for unknown transactions.
+const RPC_INVALID_ADDRESS_OR_KEY: i32 = -5;
+
+/// Configuration of a [`BitcoindProvider`].
+#[derive(Clone, Debug)]
+pub struct BitcoindConfig {
+    /// The url of the RPC interface, e.g. `http://localhost:8332`.
+    pub url: String,
+    /// The authentication method for the RPC interface.
+    pub auth: Auth,
+    /// The name of the watch-only wallet in which transactions are looked up,
+    /// if any.
+    pub wallet: Option<String>,
+    /// The duration for which fee estimates are cached before being queried
+    /// again.
+    pub fee_estimates_ttl: Duration,
+}
+
+impl BitcoindConfig {
+    /// Returns a configuration for the given url and authentication, without
+    /// wallet.
+    pub fn new(url: &str, auth: Auth) -> Self {
+        BitcoindConfig {
+            url: url.to_string(),
+            auth,
+            wallet: None,
+            fee_estimates_ttl: Duration::from_secs(60),
+        }
+    }
+
+    fn get_rpc_url(&self) -> String {
+        let url = self.url.trim_end_matches('/');
+        match &self.wallet {
+            Some(wallet) => format!("{}/wallet/{}", url, wallet),
+            None => url.to_string(),
+        }
+    }
+}
+
+/// Provider of blockchain information backed by `bitcoind`.
+pub struct BitcoindProvider {
+    client: Client,
+    network: Network,
+    config: BitcoindConfig,
+    fee_rates: Mutex<HashMap<u16, (Instant, u32)>>,
+}
+
+fn rpc_error(error: bitcoincore_rpc::Error) -> Error {
+    Error::BlockchainError(error.to_string())
+}
+
+/// Returns whether the given error was returned because the requested
+/// transaction is unknown to `bitcoind`.
+fn is_unknown_tx_error(error: &bitcoincore_rpc::Error) -> bool {
+    match error {
+        bitcoincore_rpc::Error::JsonRpc(bitcoincore_rpc::jsonrpc::Error::Rpc(e)) => {
+            e.code == RPC_INVALID_ADDRESS_OR_KEY
+        }
+        _ => false,
+    }
+}
+
+fn parse_network(chain: &str) -> Result<Network, Error> {
+    match chain {
+        "main" => Ok(Network::Bitcoin),
+        "test" => Ok(Network::Testnet),
+        "regtest" => Ok(Network::Regtest),
+        "signet" => Ok(Network::Signet),
+        _ => Err(Error::BlockchainError(format!(
+            "Unknown Bitcoin network {}",
+            chain
+        ))),
+    }
+}
+
+/// Returns the number of blocks within which a transaction paying the fee
+/// rate returned for the given target should confirm, and the estimation mode
+/// to use.
+fn get_block_target(confirmation_target: ConfirmationTarget) -> (u16, EstimateMode) {
+    match confirmation_target {
+        ConfirmationTarget::MinAllowedAnchorChannelRemoteFee => (1008, EstimateMode::Economical),
+        ConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee
+        | ConfirmationTarget::AnchorChannelFee
+        | ConfirmationTarget::ChannelCloseMinimum => (144, EstimateMode::Economical),
+        ConfirmationTarget::NonAnchorChannelFee => (18, EstimateMode::Conservative),
+        ConfirmationTarget::OnChainSweep => (6, EstimateMode::Conservative),
+    }
+}
+
+/// Converts a fee rate in satoshis per kilo virtual bytes to satoshis per 1000
+/// weight units.
+fn sat_per_kvb_to_sat_per_kw(fee_rate: u64) -> u32 {
+    u32::max((fee_rate / 4) as u32, MIN_FEERATE)
+}
+
+fn sat_per_kvb_to_sat_per_vb(fee_rate: u64) -> u64 {
+    (fee_rate + 999) / 1000
+}
+
+/// Returns the number of confirmations reported by a wallet, which are
+/// negative for transactions conflicting with the best chain.
+fn wallet_confirmations(confirmations: i32) -> u32 {
+    u32::try_from(confirmations).unwrap_or(0)
+}
+
+impl BitcoindProvider {
+    /// Creates a new provider with the given configuration, querying the
+    /// network served by `bitcoind`.
+    pub fn new(config: BitcoindConfig) -> Result<Self, Error> {
+        let client = Client::new(&config.get_rpc_url(), config.auth.clone()).map_err(rpc_error)?;
+        let network = parse_network(&client.get_blockchain_info().map_err(rpc_error)?.chain)?;
+        Ok(BitcoindProvider {
+            client,
+            network,
+            config,
+            fee_rates: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the underlying RPC client.
+    pub fn get_client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Creates and loads a descriptor wallet without private keys with the
+    /// given name, to be set as [`BitcoindConfig::wallet`].
+    pub fn create_watch_only_wallet(&self, name: &str) -> Result<(), Error> {
+        // Arguments: wallet_name, disable_private_keys, blank, passphrase,
+        // avoid_reuse, descriptors.
+        self.client
+            .call::<Value>(
+                "createwallet",
+                &[
+                    name.into(),
+                    true.into(),
+                    true.into(),
+                    "".into(),
+                    false.into(),
+                    true.into(),
+                ],
+            )
+            .map_err(rpc_error)?;
+        Ok(())
+    }
+
+    /// Imports the given output descriptors in the watch-only wallet. If
+    /// `rescan_from` is set, the blocks mined after the given unix timestamp
+    /// are rescanned for transactions matching the descriptors, otherwise
+    /// only the transactions seen from now on are tracked.
+    pub fn watch_descriptors(
+        &self,
+        descriptors: &[&str],
+        rescan_from: Option<u64>,
+    ) -> Result<(), Error> {
+        self.check_wallet()?;
+        let timestamp: Value = match rescan_from {
+            Some(timestamp) => timestamp.into(),
+            None => "now".into(),
+        };
+        let requests = descriptors
+            .iter()
+            .map(|descriptor| {
+                Ok(json!({
+                    "desc": self.add_checksum(descriptor)?,
+                    "timestamp": timestamp,
+                }))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let results = self
+            .client
+            .call::<Vec<Value>>("importdescriptors", &[requests.into()])
+            .map_err(rpc_error)?;
+
+        match results
+            .iter()
+            .find(|x| !x["success"].as_bool().unwrap_or(false))
+        {
+            Some(failed) => Err(Error::BlockchainError(format!(
+                "Could not import descriptor: {}",
+                failed["error"]
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Tracks the transactions paying to or spending from the given address
+    /// in the watch-only wallet.
+    pub fn watch_address(&self, address: &Address) -> Result<(), Error> {
+        self.watch_descriptors(&[&format!("addr({})", address)], None)
+    }
+
+    /// Tracks the transactions paying to or spending from the given script in
+    /// the watch-only wallet.
+    pub fn watch_script(&self, script: &Script) -> Result<(), Error> {
+        self.watch_descriptors(&[&format!("raw({:x})", script)], None)
+    }
+
+    fn check_wallet(&self) -> Result<(), Error> {
+        if self.config.wallet.is_none() {
+            return Err(Error::InvalidState(
+                "No watch-only wallet configured.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn add_checksum(&self, descriptor: &str) -> Result<String, Error> {
+        let info = self
+            .client
+            .call::<Value>("getdescriptorinfo", &[descriptor.into()])
+            .map_err(rpc_error)?;
+        let checksum = info["checksum"]
+            .as_str()
+            .ok_or_else(|| Error::BlockchainError("Invalid descriptor info.".to_string()))?;
+        Ok(format!("{}#{}", descriptor, checksum))
+    }
+
+    /// Returns the transaction with the given id from the watch-only wallet,
+    /// or `None` if it is not tracked by it.
+    fn get_wallet_transaction(&self, txid: &Txid) -> Result<Option<GetTransactionResult>, Error> {
+        if self.config.wallet.is_none() {
+            return Ok(None);
+        }
+        match self.client.get_transaction(txid, Some(true)) {
+            Ok(tx) => Ok(Some(tx)),
+            Err(e) if is_unknown_tx_error(&e) => Ok(None),
+            Err(e) => Err(rpc_error(e)),
+        }
+    }
+
+    fn get_fee_rate(&self, confirmation_target: ConfirmationTarget) -> Result<u32, Error> {
+        let (block_target, estimate_mode) = get_block_target(confirmation_target);
+        if let Some((fetched_at, fee_rate)) = self.fee_rates.lock().unwrap().get(&block_target) {
+            if fetched_at.elapsed() < self.config.fee_estimates_ttl {
+                return Ok(*fee_rate);
+            }
+        }
+        let fee_rate = match self
+            .client
+            .estimate_smart_fee(block_target, Some(estimate_mode))
+            .map_err(rpc_error)?
+            .fee_rate
+        {
+            Some(fee_rate) => sat_per_kvb_to_sat_per_kw(fee_rate.to_sat()),
+            None => MIN_FEERATE,
+        };
+        self.fee_rates
+            .lock()
+            .unwrap()
+            .insert(block_target, (Instant::now(), fee_rate));
+        Ok(fee_rate)
+    }
+}
+
+impl Blockchain for BitcoindProvider {
+    fn send_transaction(&self, transaction: &Transaction) -> Result<(), Error> {
+        if self.config.wallet.is_some() {
+            // Tracking the outputs makes it possible to retrieve the
+            // transaction and the ones spending from it without transaction
+            // index.
+            for output in &transaction.output {
+                if let Err(e) = self.watch_script(&output.script_pubkey) {
+                    warn!(
+                        "Could not watch output of transaction {}: {}",
+                        transaction.txid(),
+                        e
+                    );
+                }
+            }
+        }
+        self.client
+            .send_raw_transaction(transaction)
+            .map_err(rpc_error)?;
+        Ok(())
+    }
+
+    fn get_network(&self) -> Result<Network, Error> {
+        Ok(self.network)
+    }
+
+    fn get_blockchain_height(&self) -> Result<u64, Error> {
+        self.client.get_block_count().map_err(rpc_error)
+    }
+
+    fn get_block_at_height(&self, height: u64) -> Result<Block, Error> {
+        let block_hash = self.client.get_block_hash(height).map_err(rpc_error)?;
+        self.client.get_block(&block_hash).map_err(rpc_error)
+    }
+
+    fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, Error> {
+        if let Some(tx) = self.get_wallet_transaction(tx_id)? {
+            return tx
+                .transaction()
+                .map_err(|e| Error::BlockchainError(e.to_string()));
+        }
+        self.client
+            .get_raw_transaction(tx_id, None)
+            .map_err(rpc_error)
+    }
+
+    fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, Error> {
+        if let Some(tx) = self.get_wallet_transaction(tx_id)? {
+            return Ok(wallet_confirmations(tx.info.confirmations));
+        }
+        match self.client.get_raw_transaction_info(tx_id, None) {
+            Ok(tx) => Ok(tx.confirmations.unwrap_or(0)),
+            Err(e) if is_unknown_tx_error(&e) => Ok(0),
+            Err(e) => Err(rpc_error(e)),
+        }
+    }
+
+    fn get_min_relay_fee_rate(&self) -> Result<u64, Error> {
+        let network_info = self.client.get_network_info().map_err(rpc_error)?;
+        Ok(sat_per_kvb_to_sat_per_vb(network_info.relay_fee.to_sat()))
+    }
+
+    fn get_incremental_relay_fee_rate(&self) -> Result<u64, Error> {
+        let network_info = self.client.get_network_info().map_err(rpc_error)?;
+        Ok(sat_per_kvb_to_sat_per_vb(
+            network_info.incremental_fee.to_sat(),
+        ))
+    }
+}
+
+impl WalletBlockchainProvider for BitcoindProvider {
+    fn get_utxos_for_address(&self, address: &Address) -> Result<Vec<Utxo>, Error> {
+        self.check_wallet()?;
+        let utxos = self
+            .client
+            .list_unspent(None, None, Some(&[address]), Some(false), None)
+            .map_err(rpc_error)?;
+        Ok(utxos
+            .into_iter()
+            .map(|x| Utxo {
+                tx_out: TxOut {
+                    value: x.amount.to_sat(),
+                    script_pubkey: x.script_pub_key,
+                },
+                outpoint: OutPoint {
+                    txid: x.txid,
+                    vout: x.vout,
+                },
+                address: address.clone(),
+                redeem_script: x.redeem_script.unwrap_or_default(),
+                reserved: false,
+            })
+            .collect())
+    }
+
+    fn is_output_spent(&self, txid: &Txid, vout: u32) -> Result<bool, Error> {
+        let tx_out = self
+            .client
+            .get_tx_out(txid, vout, Some(true))
+            .map_err(rpc_error)?;
+        Ok(tx_out.is_none())
+    }
+}
+
+impl FeeEstimator for BitcoindProvider {
+    fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
+        match self.get_fee_rate(confirmation_target) {
+            Ok(fee_rate) => fee_rate,
+            Err(e) => {
+                error!("Error querying fee estimate: {}", e);
+                MIN_FEERATE
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_rates_are_converted_test() {
+        assert_eq!(2500, sat_per_kvb_to_sat_per_kw(10_000));
+        assert_eq!(MIN_FEERATE, sat_per_kvb_to_sat_per_kw(1_000));
+        assert_eq!(2, sat_per_kvb_to_sat_per_vb(1_001));
+    }
+
+    #[test]
+    fn conflicted_transactions_have_no_confirmations_test() {
+        assert_eq!(0, wallet_confirmations(-3));
+        assert_eq!(3, wallet_confirmations(3));
+    }
+
+    #[test]
+    fn wallet_is_added_to_rpc_url_test() {
+        let mut config = BitcoindConfig::new("http://localhost:8332/", Auth::None);
+        assert_eq!("http://localhost:8332", config.get_rpc_url());
+        config.wallet = Some("watch".to_string());
+        assert_eq!("http://localhost:8332/wallet/watch", config.get_rpc_url());
+    }
+}
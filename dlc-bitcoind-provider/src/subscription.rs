@@ -0,0 +1,173 @@
+//! #BlockSubscriber
+//! Notifies a [`BlockListener`] of the blocks connected by `bitcoind`, either
+//! through long polling of the `waitfornewblock` RPC, or through the
+//! `hashblock` ZMQ notifications (requires the `zmq` feature).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "zmq")]
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[cfg(feature = "zmq")]
+use bitcoin::hashes::Hash;
+use bitcoin::BlockHash;
+use bitcoincore_rpc::jsonrpc::serde_json::Value;
+use bitcoincore_rpc::{Client, RpcApi};
+use dlc_manager::error::Error;
+use log::error;
+
+use crate::{rpc_error, BitcoindConfig};
+
+/// Receives the notifications of a [`BlockSubscriber`].
+pub trait BlockListener: Send + Sync {
+    /// Called when the tip of the best chain changes to the block with the
+    /// given hash and height.
+    fn on_new_block(&self, block_hash: &BlockHash, height: u64);
+}
+
+enum Source {
+    LongPoll,
+    #[cfg(feature = "zmq")]
+    Zmq(Mutex<zmq::Socket>),
+}
+
+/// Waits for new blocks and notifies a [`BlockListener`] of them. Each
+/// subscriber uses its own RPC connection, so that waiting for blocks does not
+/// delay the other requests made to `bitcoind`.
+pub struct BlockSubscriber {
+    client: Client,
+    source: Source,
+    timeout: Duration,
+}
+
+impl BlockSubscriber {
+    /// Creates a subscriber waiting for new blocks using the `waitfornewblock`
+    /// RPC, returning at most every `timeout` to check whether it should stop.
+    pub fn new_long_poll(config: &BitcoindConfig, timeout: Duration) -> Result<Self, Error> {
+        Ok(BlockSubscriber {
+            client: get_client(config)?,
+            source: Source::LongPoll,
+            timeout,
+        })
+    }
+
+    /// Creates a subscriber receiving the `hashblock` notifications published
+    /// by `bitcoind` at the given endpoint (e.g. `tcp://127.0.0.1:28332`),
+    /// returning at most every `timeout` to check whether it should stop.
+    #[cfg(feature = "zmq")]
+    pub fn new_zmq(
+        config: &BitcoindConfig,
+        endpoint: &str,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let socket = zmq::Context::new()
+            .socket(zmq::SUB)
+            .and_then(|socket| {
+                socket.connect(endpoint)?;
+                socket.set_subscribe(b"hashblock")?;
+                socket.set_rcvtimeo(timeout.as_millis() as i32)?;
+                Ok(socket)
+            })
+            .map_err(zmq_error)?;
+        Ok(BlockSubscriber {
+            client: get_client(config)?,
+            source: Source::Zmq(Mutex::new(socket)),
+            timeout,
+        })
+    }
+
+    /// Waits for a new block for at most the configured timeout, returning
+    /// its hash and height if one was connected, or `None` otherwise. With
+    /// long polling, `last_block_hash` is the hash of the last block that was
+    /// returned.
+    pub fn wait_for_new_block(
+        &self,
+        last_block_hash: Option<&BlockHash>,
+    ) -> Result<Option<(BlockHash, u64)>, Error> {
+        match &self.source {
+            Source::LongPoll => {
+                let tip = self
+                    .client
+                    .call::<Value>(
+                        "waitfornewblock",
+                        &[(self.timeout.as_millis() as u64).into()],
+                    )
+                    .map_err(rpc_error)?;
+                let block_hash: BlockHash = tip["hash"]
+                    .as_str()
+                    .and_then(|x| x.parse().ok())
+                    .ok_or_else(|| invalid_response("hash"))?;
+                let height = tip["height"]
+                    .as_u64()
+                    .ok_or_else(|| invalid_response("height"))?;
+                if Some(&block_hash) == last_block_hash {
+                    return Ok(None);
+                }
+                Ok(Some((block_hash, height)))
+            }
+            #[cfg(feature = "zmq")]
+            Source::Zmq(socket) => {
+                let message = match socket.lock().unwrap().recv_multipart(0) {
+                    Ok(message) => message,
+                    Err(zmq::Error::EAGAIN) => return Ok(None),
+                    Err(e) => return Err(zmq_error(e)),
+                };
+                let block_hash = match message.get(1) {
+                    Some(hash) if hash.len() == 32 => {
+                        // Hashes are published in the reverse order of their
+                        // serialization.
+                        let mut bytes = [0u8; 32];
+                        bytes.copy_from_slice(hash);
+                        bytes.reverse();
+                        BlockHash::from_byte_array(bytes)
+                    }
+                    _ => return Err(invalid_response("hashblock")),
+                };
+                let height = self
+                    .client
+                    .get_block_header_info(&block_hash)
+                    .map_err(rpc_error)?
+                    .height as u64;
+                Ok(Some((block_hash, height)))
+            }
+        }
+    }
+
+    /// Notifies the given listener of new blocks until `stop` is set, logging
+    /// the errors encountered. Meant to be run in a dedicated thread.
+    pub fn run(&self, listener: &dyn BlockListener, stop: &AtomicBool) {
+        let mut last_block_hash = match self.client.get_best_block_hash() {
+            Ok(block_hash) => Some(block_hash),
+            Err(e) => {
+                error!("Error getting best block hash: {}", e);
+                None
+            }
+        };
+        while !stop.load(Ordering::Relaxed) {
+            match self.wait_for_new_block(last_block_hash.as_ref()) {
+                Ok(Some((block_hash, height))) => {
+                    listener.on_new_block(&block_hash, height);
+                    last_block_hash = Some(block_hash);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    error!("Error waiting for new block: {}", e);
+                    std::thread::sleep(self.timeout);
+                }
+            }
+        }
+    }
+}
+
+fn get_client(config: &BitcoindConfig) -> Result<Client, Error> {
+    Client::new(config.url.trim_end_matches('/'), config.auth.clone()).map_err(rpc_error)
+}
+
+fn invalid_response(field: &str) -> Error {
+    Error::BlockchainError(format!("Invalid {} in block notification", field))
+}
+
+#[cfg(feature = "zmq")]
+fn zmq_error(error: zmq::Error) -> Error {
+    Error::BlockchainError(format!("ZMQ error: {}", error))
+}
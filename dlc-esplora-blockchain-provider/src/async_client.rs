@@ -0,0 +1,241 @@
+//! #AsyncEsploraBlockchainProvider
+//! Asynchronous version of the
+//! [`EsploraBlockchainProvider`](crate::EsploraBlockchainProvider).
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::{Address, Block, Network, OutPoint, Transaction, Txid};
+use dlc_manager::error::Error;
+use dlc_manager::Utxo;
+use lightning::chain::chaininterface::ConfirmationTarget;
+use reqwest::StatusCode;
+
+use crate::{
+    block_hash_path, broadcast_path, cache_fee_estimates, decode_block, decode_error, decode_tx,
+    fee_estimates_path, get_cached_fee_estimates, get_confirmations, get_fee_rate, get_spender,
+    get_stale_fee_estimates, normalize_host, outspend_path, parse_height, raw_block_path,
+    raw_tx_path, request_error, status_error, tip_height_path, to_utxos, tx_status_path,
+    utxos_path, CachedFeeEstimates, EsploraConfig, FeeEstimates, OutSpendResponse, TxStatus,
+    UtxoResponse, MIN_FEERATE,
+};
+
+/// Asynchronous client for an Esplora server, to be used within a tokio
+/// runtime. Refer to the documentation of [`dlc_manager::Blockchain`] and of
+/// [`simple_wallet::WalletBlockchainProvider`] for the semantic of each
+/// method.
+pub struct AsyncEsploraBlockchainProvider {
+    host: String,
+    network: Network,
+    client: reqwest::Client,
+    config: EsploraConfig,
+    fee_estimates: CachedFeeEstimates,
+}
+
+impl AsyncEsploraBlockchainProvider {
+    /// Creates a new provider for the Esplora server at the given host (e.g.
+    /// `https://blockstream.info/api/`), serving data for the given network.
+    pub fn new(host: &str, network: Network, config: EsploraConfig) -> Result<Self, Error> {
+        let host = normalize_host(host)?;
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(request_error)?;
+        Ok(AsyncEsploraBlockchainProvider {
+            host,
+            network,
+            client,
+            config,
+            fee_estimates: Mutex::new(None),
+        })
+    }
+
+    /// Broadcast the given transaction to the bitcoin network.
+    pub async fn send_transaction(&self, transaction: &Transaction) -> Result<(), Error> {
+        let path = broadcast_path(&self.host);
+        let response = self
+            .client
+            .post(&path)
+            .body(serialize_hex(transaction))
+            .send()
+            .await
+            .map_err(request_error)?;
+        check_status(&path, response).await?;
+        Ok(())
+    }
+
+    /// Returns the network served by the server.
+    pub fn get_network(&self) -> Network {
+        self.network
+    }
+
+    /// Returns the height of the blockchain.
+    pub async fn get_blockchain_height(&self) -> Result<u64, Error> {
+        parse_height(&self.get_text(&tip_height_path(&self.host)).await?)
+    }
+
+    /// Returns the block at given height.
+    pub async fn get_block_at_height(&self, height: u64) -> Result<Block, Error> {
+        let block_hash = self.get_text(&block_hash_path(&self.host, height)).await?;
+        decode_block(
+            &self
+                .get_bytes(&raw_block_path(&self.host, block_hash.trim()))
+                .await?,
+        )
+    }
+
+    /// Get the transaction with given id.
+    pub async fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, Error> {
+        decode_tx(&self.get_bytes(&raw_tx_path(&self.host, tx_id)).await?)
+    }
+
+    /// Get the number of confirmation for the transaction with given id.
+    pub async fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, Error> {
+        let path = tx_status_path(&self.host, tx_id);
+        let response = self.client.get(&path).send().await.map_err(request_error)?;
+        // Unknown transactions have no confirmations.
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(0);
+        }
+        let tx_status: TxStatus = check_status(&path, response)
+            .await?
+            .json()
+            .await
+            .map_err(decode_error)?;
+        if !tx_status.confirmed {
+            return Ok(0);
+        }
+        Ok(get_confirmations(
+            &tx_status,
+            self.get_blockchain_height().await?,
+        ))
+    }
+
+    /// Returns the unspent outputs paying to the given address.
+    pub async fn get_utxos_for_address(&self, address: &Address) -> Result<Vec<Utxo>, Error> {
+        let utxos: Vec<UtxoResponse> = self
+            .get(&utxos_path(&self.host, address))
+            .await?
+            .json()
+            .await
+            .map_err(decode_error)?;
+        Ok(to_utxos(address, utxos))
+    }
+
+    /// Returns whether the given output was spent.
+    pub async fn is_output_spent(&self, txid: &Txid, vout: u32) -> Result<bool, Error> {
+        let outspend: OutSpendResponse = self
+            .get(&outspend_path(&self.host, txid, vout))
+            .await?
+            .json()
+            .await
+            .map_err(decode_error)?;
+        Ok(outspend.spent)
+    }
+
+    /// Returns the id of the transaction spending the given output, or `None`
+    /// if the output is unspent.
+    pub async fn get_output_spender(&self, outpoint: &OutPoint) -> Result<Option<Txid>, Error> {
+        let outspend: OutSpendResponse = self
+            .get(&outspend_path(&self.host, &outpoint.txid, outpoint.vout))
+            .await?
+            .json()
+            .await
+            .map_err(decode_error)?;
+        Ok(get_spender(outspend))
+    }
+
+    /// Returns the fee estimates of the server, in satoshis per virtual byte
+    /// indexed by confirmation target in number of blocks. Estimates are
+    /// cached for [`EsploraConfig::fee_estimates_ttl`].
+    pub async fn get_fee_estimates(&self) -> Result<HashMap<u16, f64>, Error> {
+        if let Some(fee_estimates) =
+            get_cached_fee_estimates(&self.fee_estimates, self.config.fee_estimates_ttl)
+        {
+            return Ok(fee_estimates);
+        }
+        let fee_estimates: FeeEstimates = self
+            .get(&fee_estimates_path(&self.host))
+            .await?
+            .json()
+            .await
+            .map_err(decode_error)?;
+        cache_fee_estimates(&self.fee_estimates, &fee_estimates);
+        Ok(fee_estimates)
+    }
+
+    /// Returns the fee rate, in satoshis per 1000 weight units, for the given
+    /// target, with the same semantic as the
+    /// [`FeeEstimator`](lightning::chain::chaininterface::FeeEstimator)
+    /// implementation of the blocking provider.
+    pub async fn get_est_sat_per_1000_weight(
+        &self,
+        confirmation_target: ConfirmationTarget,
+    ) -> u32 {
+        match self
+            .get_fee_estimates()
+            .await
+            .ok()
+            .or_else(|| get_stale_fee_estimates(&self.fee_estimates))
+        {
+            Some(fee_estimates) => get_fee_rate(&fee_estimates, confirmation_target),
+            None => MIN_FEERATE,
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<reqwest::Response, Error> {
+        let response = self.client.get(path).send().await.map_err(request_error)?;
+        check_status(path, response).await
+    }
+
+    async fn get_text(&self, path: &str) -> Result<String, Error> {
+        self.get(path).await?.text().await.map_err(request_error)
+    }
+
+    async fn get_bytes(&self, path: &str) -> Result<Vec<u8>, Error> {
+        Ok(self
+            .get(path)
+            .await?
+            .bytes()
+            .await
+            .map_err(request_error)?
+            .to_vec())
+    }
+}
+
+async fn check_status(path: &str, response: reqwest::Response) -> Result<reqwest::Response, Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(status_error(path, status, &body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{tip_height_mock, tx_status_mock, txid};
+
+    #[tokio::test]
+    async fn transaction_confirmations_test() {
+        let _tip_mock = tip_height_mock();
+        let _status_mock = tx_status_mock();
+
+        let provider = AsyncEsploraBlockchainProvider::new(
+            &mockito::server_url(),
+            Network::Regtest,
+            EsploraConfig::default(),
+        )
+        .expect("Error creating provider");
+
+        assert_eq!(
+            10,
+            provider
+                .get_transaction_confirmations(&txid())
+                .await
+                .expect("Error getting confirmations")
+        );
+    }
+}
@@ -0,0 +1,517 @@
+//! # dlc-esplora-blockchain-provider
+//! Implementation of the [`Blockchain`] and [`WalletBlockchainProvider`]
+//! traits on top of the HTTP API of an Esplora server, see the crate Readme
+//! for more information.
+
+#![crate_name = "dlc_esplora_blockchain_provider"]
+// Coding conventions
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+#[cfg(feature = "async")]
+pub mod async_client;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::consensus::Decodable;
+use bitcoin::{Address, Block, Network, OutPoint, ScriptBuf, Transaction, TxOut, Txid};
+use dlc_manager::error::Error;
+use dlc_manager::{Blockchain, Utxo};
+use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
+use reqwest::StatusCode;
+use simple_wallet::WalletBlockchainProvider;
+
+/// The minimum fee rate, in satoshis per 1000 weight units, returned by the
+/// [`FeeEstimator`] implementation.
+pub const MIN_FEERATE: u32 = 253;
+
+/// Configuration of an Esplora client.
+#[derive(Clone, Debug)]
+pub struct EsploraConfig {
+    /// The timeout applied to each request.
+    pub timeout: Duration,
+    /// The duration for which fee estimates are cached before being fetched
+    /// again.
+    pub fee_estimates_ttl: Duration,
+}
+
+impl Default for EsploraConfig {
+    fn default() -> Self {
+        EsploraConfig {
+            timeout: Duration::from_secs(30),
+            fee_estimates_ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct TxStatus {
+    confirmed: bool,
+    block_height: Option<u64>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct UtxoResponse {
+    txid: Txid,
+    vout: u32,
+    value: u64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct OutSpendResponse {
+    spent: bool,
+    txid: Option<Txid>,
+}
+
+/// Fee rates in satoshis per virtual byte, indexed by confirmation target in
+/// number of blocks.
+type FeeEstimates = HashMap<u16, f64>;
+
+/// Fee estimates along with the time at which they were fetched.
+type CachedFeeEstimates = Mutex<Option<(Instant, FeeEstimates)>>;
+
+fn tip_height_path(host: &str) -> String {
+    format!("{}blocks/tip/height", host)
+}
+
+fn block_hash_path(host: &str, height: u64) -> String {
+    format!("{}block-height/{}", host, height)
+}
+
+fn raw_block_path(host: &str, block_hash: &str) -> String {
+    format!("{}block/{}/raw", host, block_hash)
+}
+
+fn raw_tx_path(host: &str, txid: &Txid) -> String {
+    format!("{}tx/{}/raw", host, txid)
+}
+
+fn tx_status_path(host: &str, txid: &Txid) -> String {
+    format!("{}tx/{}/status", host, txid)
+}
+
+fn broadcast_path(host: &str) -> String {
+    format!("{}tx", host)
+}
+
+fn utxos_path(host: &str, address: &Address) -> String {
+    format!("{}address/{}/utxo", host, address)
+}
+
+fn outspend_path(host: &str, txid: &Txid, vout: u32) -> String {
+    format!("{}tx/{}/outspend/{}", host, txid, vout)
+}
+
+/// Returns the id of the spending transaction reported by the server, if the
+/// output is spent.
+fn get_spender(outspend: OutSpendResponse) -> Option<Txid> {
+    if outspend.spent {
+        outspend.txid
+    } else {
+        None
+    }
+}
+
+fn fee_estimates_path(host: &str) -> String {
+    format!("{}fee-estimates", host)
+}
+
+fn normalize_host(host: &str) -> Result<String, Error> {
+    if host.is_empty() {
+        return Err(Error::InvalidParameters("Invalid host".to_string()));
+    }
+    if host.ends_with('/') {
+        Ok(host.to_string())
+    } else {
+        Ok(format!("{}/", host))
+    }
+}
+
+fn status_error(path: &str, status: StatusCode, body: &str) -> Error {
+    Error::BlockchainError(format!(
+        "Request to {} failed with status {}: {}",
+        path, status, body
+    ))
+}
+
+fn request_error(error: reqwest::Error) -> Error {
+    Error::IOError(std::io::Error::new(std::io::ErrorKind::Other, error))
+}
+
+fn decode_error<E: std::fmt::Display>(error: E) -> Error {
+    Error::BlockchainError(error.to_string())
+}
+
+fn parse_height(text: &str) -> Result<u64, Error> {
+    text.trim().parse().map_err(decode_error)
+}
+
+fn decode_block(raw_block: &[u8]) -> Result<Block, Error> {
+    Block::consensus_decode(&mut std::io::Cursor::new(raw_block)).map_err(decode_error)
+}
+
+fn decode_tx(raw_tx: &[u8]) -> Result<Transaction, Error> {
+    Transaction::consensus_decode(&mut std::io::Cursor::new(raw_tx)).map_err(decode_error)
+}
+
+/// Returns the number of confirmations of a transaction with the given status
+/// when the chain tip is at the given height.
+fn get_confirmations(tx_status: &TxStatus, tip_height: u64) -> u32 {
+    match (tx_status.confirmed, tx_status.block_height) {
+        (true, Some(block_height)) if block_height <= tip_height => {
+            (tip_height - block_height + 1) as u32
+        }
+        _ => 0,
+    }
+}
+
+fn to_utxos(address: &Address, utxos: Vec<UtxoResponse>) -> Vec<Utxo> {
+    utxos
+        .into_iter()
+        .map(|x| Utxo {
+            tx_out: TxOut {
+                value: x.value,
+                script_pubkey: address.script_pubkey(),
+            },
+            outpoint: OutPoint {
+                txid: x.txid,
+                vout: x.vout,
+            },
+            address: address.clone(),
+            redeem_script: ScriptBuf::new(),
+            reserved: false,
+        })
+        .collect()
+}
+
+/// Returns the number of blocks within which a transaction paying the fee
+/// rate returned for the given target should confirm.
+fn get_block_target(confirmation_target: ConfirmationTarget) -> u16 {
+    match confirmation_target {
+        ConfirmationTarget::MinAllowedAnchorChannelRemoteFee => 1008,
+        ConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee
+        | ConfirmationTarget::AnchorChannelFee
+        | ConfirmationTarget::ChannelCloseMinimum => 144,
+        ConfirmationTarget::NonAnchorChannelFee => 18,
+        ConfirmationTarget::OnChainSweep => 6,
+    }
+}
+
+/// Returns the fee rate, in satoshis per 1000 weight units, for the given
+/// target. Esplora only provides estimates for some targets, so the estimate
+/// for the closest lower target is used, or [`MIN_FEERATE`] if there is none.
+fn get_fee_rate(fee_estimates: &FeeEstimates, confirmation_target: ConfirmationTarget) -> u32 {
+    let block_target = get_block_target(confirmation_target);
+    let sats_per_vbyte = fee_estimates
+        .iter()
+        .filter(|(target, _)| **target <= block_target)
+        .max_by_key(|(target, _)| **target)
+        .map_or(0.0, |(_, fee_rate)| *fee_rate);
+    u32::max((sats_per_vbyte * 250.0).round() as u32, MIN_FEERATE)
+}
+
+/// Returns the cached fee estimates if they are not older than `ttl`.
+fn get_cached_fee_estimates(cache: &CachedFeeEstimates, ttl: Duration) -> Option<FeeEstimates> {
+    cache
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|(fetched_at, _)| fetched_at.elapsed() < ttl)
+        .map(|(_, fee_estimates)| fee_estimates.clone())
+}
+
+/// Returns the cached fee estimates regardless of their age, used when they
+/// could not be refreshed.
+fn get_stale_fee_estimates(cache: &CachedFeeEstimates) -> Option<FeeEstimates> {
+    cache
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|(_, fee_estimates)| fee_estimates.clone())
+}
+
+fn cache_fee_estimates(cache: &CachedFeeEstimates, fee_estimates: &FeeEstimates) {
+    *cache.lock().unwrap() = Some((Instant::now(), fee_estimates.clone()));
+}
+
+/// Blocking client for an Esplora server. Must not be used from within an
+/// asynchronous runtime, see [`async_client::AsyncEsploraBlockchainProvider`]
+/// for that purpose (requires the `async` feature).
+pub struct EsploraBlockchainProvider {
+    host: String,
+    network: Network,
+    client: reqwest::blocking::Client,
+    config: EsploraConfig,
+    fee_estimates: CachedFeeEstimates,
+}
+
+impl EsploraBlockchainProvider {
+    /// Creates a new provider for the Esplora server at the given host (e.g.
+    /// `https://blockstream.info/api/`), serving data for the given network.
+    pub fn new(host: &str, network: Network, config: EsploraConfig) -> Result<Self, Error> {
+        let host = normalize_host(host)?;
+        let client = reqwest::blocking::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(request_error)?;
+        Ok(EsploraBlockchainProvider {
+            host,
+            network,
+            client,
+            config,
+            fee_estimates: Mutex::new(None),
+        })
+    }
+
+    /// Returns the fee estimates of the server, in satoshis per virtual byte
+    /// indexed by confirmation target in number of blocks. Estimates are
+    /// cached for [`EsploraConfig::fee_estimates_ttl`].
+    pub fn get_fee_estimates(&self) -> Result<HashMap<u16, f64>, Error> {
+        if let Some(fee_estimates) =
+            get_cached_fee_estimates(&self.fee_estimates, self.config.fee_estimates_ttl)
+        {
+            return Ok(fee_estimates);
+        }
+        let fee_estimates: FeeEstimates = self
+            .get(&fee_estimates_path(&self.host))?
+            .json()
+            .map_err(decode_error)?;
+        cache_fee_estimates(&self.fee_estimates, &fee_estimates);
+        Ok(fee_estimates)
+    }
+
+    fn get(&self, path: &str) -> Result<reqwest::blocking::Response, Error> {
+        let response = self.client.get(path).send().map_err(request_error)?;
+        check_status(path, response)
+    }
+
+    fn get_text(&self, path: &str) -> Result<String, Error> {
+        self.get(path)?.text().map_err(request_error)
+    }
+
+    fn get_bytes(&self, path: &str) -> Result<Vec<u8>, Error> {
+        Ok(self.get(path)?.bytes().map_err(request_error)?.to_vec())
+    }
+}
+
+fn check_status(
+    path: &str,
+    response: reqwest::blocking::Response,
+) -> Result<reqwest::blocking::Response, Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().unwrap_or_default();
+    Err(status_error(path, status, &body))
+}
+
+impl Blockchain for EsploraBlockchainProvider {
+    fn send_transaction(&self, transaction: &Transaction) -> Result<(), Error> {
+        let path = broadcast_path(&self.host);
+        let response = self
+            .client
+            .post(&path)
+            .body(serialize_hex(transaction))
+            .send()
+            .map_err(request_error)?;
+        check_status(&path, response)?;
+        Ok(())
+    }
+
+    fn get_network(&self) -> Result<Network, Error> {
+        Ok(self.network)
+    }
+
+    fn get_blockchain_height(&self) -> Result<u64, Error> {
+        parse_height(&self.get_text(&tip_height_path(&self.host))?)
+    }
+
+    fn get_block_at_height(&self, height: u64) -> Result<Block, Error> {
+        let block_hash = self.get_text(&block_hash_path(&self.host, height))?;
+        decode_block(&self.get_bytes(&raw_block_path(&self.host, block_hash.trim()))?)
+    }
+
+    fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, Error> {
+        decode_tx(&self.get_bytes(&raw_tx_path(&self.host, tx_id))?)
+    }
+
+    fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, Error> {
+        let path = tx_status_path(&self.host, tx_id);
+        let response = self.client.get(&path).send().map_err(request_error)?;
+        // Unknown transactions have no confirmations.
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(0);
+        }
+        let tx_status: TxStatus = check_status(&path, response)?
+            .json()
+            .map_err(decode_error)?;
+        if !tx_status.confirmed {
+            return Ok(0);
+        }
+        Ok(get_confirmations(&tx_status, self.get_blockchain_height()?))
+    }
+
+    fn get_output_spender(&self, outpoint: &OutPoint) -> Result<Option<Txid>, Error> {
+        let outspend: OutSpendResponse = self
+            .get(&outspend_path(&self.host, &outpoint.txid, outpoint.vout))?
+            .json()
+            .map_err(decode_error)?;
+        Ok(get_spender(outspend))
+    }
+}
+
+impl WalletBlockchainProvider for EsploraBlockchainProvider {
+    fn get_utxos_for_address(&self, address: &Address) -> Result<Vec<Utxo>, Error> {
+        let utxos: Vec<UtxoResponse> = self
+            .get(&utxos_path(&self.host, address))?
+            .json()
+            .map_err(decode_error)?;
+        Ok(to_utxos(address, utxos))
+    }
+
+    fn is_output_spent(&self, txid: &Txid, vout: u32) -> Result<bool, Error> {
+        let outspend: OutSpendResponse = self
+            .get(&outspend_path(&self.host, txid, vout))?
+            .json()
+            .map_err(decode_error)?;
+        Ok(outspend.spent)
+    }
+}
+
+impl FeeEstimator for EsploraBlockchainProvider {
+    fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
+        match self
+            .get_fee_estimates()
+            .ok()
+            .or_else(|| get_stale_fee_estimates(&self.fee_estimates))
+        {
+            Some(fee_estimates) => get_fee_rate(&fee_estimates, confirmation_target),
+            None => MIN_FEERATE,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use mockito::{mock, Mock};
+
+    pub(crate) fn txid() -> Txid {
+        Txid::from_byte_array([1; 32])
+    }
+
+    pub(crate) fn tip_height_mock() -> Mock {
+        mock("GET", tip_height_path("/").as_str())
+            .with_body("110")
+            .create()
+    }
+
+    pub(crate) fn tx_status_mock() -> Mock {
+        mock("GET", tx_status_path("/", &txid()).as_str())
+            .with_body(r#"{"confirmed":true,"block_height":101}"#)
+            .create()
+    }
+
+    fn get_provider() -> EsploraBlockchainProvider {
+        EsploraBlockchainProvider::new(
+            &mockito::server_url(),
+            Network::Regtest,
+            EsploraConfig::default(),
+        )
+        .expect("Error creating provider")
+    }
+
+    #[test]
+    fn transaction_confirmations_test() {
+        let _tip_mock = tip_height_mock();
+        let _status_mock = tx_status_mock();
+
+        assert_eq!(
+            10,
+            get_provider()
+                .get_transaction_confirmations(&txid())
+                .expect("Error getting confirmations")
+        );
+    }
+
+    #[test]
+    fn unknown_transaction_has_no_confirmations_test() {
+        let unknown_txid = Txid::from_byte_array([2; 32]);
+        let _m = mock("GET", tx_status_path("/", &unknown_txid).as_str())
+            .with_status(404)
+            .create();
+
+        assert_eq!(
+            0,
+            get_provider()
+                .get_transaction_confirmations(&unknown_txid)
+                .expect("Error getting confirmations")
+        );
+    }
+
+    #[test]
+    fn output_spender_test() {
+        let spender = Txid::from_byte_array([2; 32]);
+        let _spent_mock = mock("GET", outspend_path("/", &txid(), 0).as_str())
+            .with_body(format!(r#"{{"spent":true,"txid":"{}","vin":0}}"#, spender))
+            .create();
+        let _unspent_mock = mock("GET", outspend_path("/", &txid(), 1).as_str())
+            .with_body(r#"{"spent":false}"#)
+            .create();
+        let provider = get_provider();
+
+        assert_eq!(
+            Some(spender),
+            provider
+                .get_output_spender(&OutPoint::new(txid(), 0))
+                .expect("Error getting spender")
+        );
+        assert_eq!(
+            None,
+            provider
+                .get_output_spender(&OutPoint::new(txid(), 1))
+                .expect("Error getting spender")
+        );
+    }
+
+    #[test]
+    fn fee_estimates_are_cached_test() {
+        let m = mock("GET", fee_estimates_path("/").as_str())
+            .with_body(r#"{"1":20.5,"6":10.0,"144":2.0,"1008":1.0}"#)
+            .expect(1)
+            .create();
+        let provider = get_provider();
+
+        assert_eq!(
+            2500,
+            provider.get_est_sat_per_1000_weight(ConfirmationTarget::OnChainSweep)
+        );
+        assert_eq!(
+            2500,
+            provider.get_est_sat_per_1000_weight(ConfirmationTarget::NonAnchorChannelFee)
+        );
+        assert_eq!(
+            500,
+            provider.get_est_sat_per_1000_weight(ConfirmationTarget::ChannelCloseMinimum)
+        );
+        m.assert();
+    }
+
+    #[test]
+    fn fee_rate_defaults_to_minimum_test() {
+        assert_eq!(
+            MIN_FEERATE,
+            get_fee_rate(&FeeEstimates::new(), ConfirmationTarget::OnChainSweep)
+        );
+    }
+}
@@ -2,6 +2,8 @@ use bitcoin::hashes::Hash;
 use bitcoin::OutPoint;
 use bitcoin::Script;
 use bitcoin::WPubkeyHash;
+#[cfg(feature = "parallel")]
+use criterion::BenchmarkId;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use dlc::create_dlc_transactions;
 use dlc::DlcTransactions;
@@ -55,8 +57,8 @@ fn max_value() -> u32 {
     BASE.pow(NB_DIGITS as u32) - 1
 }
 
-fn create_contract_descriptor() -> ContractDescriptor {
-    let difference_params = if USE_DIFF_PARAMS {
+fn create_contract_descriptor(use_diff_params: bool) -> ContractDescriptor {
+    let difference_params = if use_diff_params {
         Some(DifferenceParams {
             max_error_exp: MAX_ERROR_EXP,
             min_support_exp: MIN_SUPPORT_EXP,
@@ -161,8 +163,8 @@ fn create_oracle_announcements() -> Vec<OracleAnnouncement> {
         }}}).collect()
 }
 
-fn create_contract_info() -> ContractInfo {
-    let contract_descriptor = create_contract_descriptor();
+fn create_contract_info(use_diff_params: bool) -> ContractInfo {
+    let contract_descriptor = create_contract_descriptor(use_diff_params);
     let oracle_announcements = create_oracle_announcements();
     ContractInfo {
         contract_descriptor,
@@ -203,7 +205,19 @@ fn create_transactions(payouts: &[Payout]) -> DlcTransactions {
         input_amount: 300000000,
         collateral: 100000000,
     };
-    create_dlc_transactions(&offer_params, &accept_params, payouts, 1000, 2, 0, 1000, 3).unwrap()
+    create_dlc_transactions(
+        &offer_params,
+        &accept_params,
+        payouts,
+        1000,
+        2,
+        0,
+        1000,
+        3,
+        false,
+        dlc::FeeSplit::Equal,
+    )
+    .unwrap()
 }
 
 fn accept_seckey() -> SecretKey {
@@ -220,7 +234,7 @@ fn offer_seckey() -> SecretKey {
 
 /// Benchmark to measure the adaptor signature creation time.
 pub fn sign_bench(c: &mut Criterion) {
-    let contract_info = create_contract_info();
+    let contract_info = create_contract_info(USE_DIFF_PARAMS);
     let dlc_transactions = create_transactions(&contract_info.get_payouts(200000000).unwrap());
     let fund_output_value = dlc_transactions.get_fund_output().value;
 
@@ -246,7 +260,7 @@ pub fn sign_bench(c: &mut Criterion) {
 
 /// Benchmark to measure the adaptor signature verification time.
 pub fn verify_bench(c: &mut Criterion) {
-    let contract_info = create_contract_info();
+    let contract_info = create_contract_info(USE_DIFF_PARAMS);
     let dlc_transactions = create_transactions(&contract_info.get_payouts(200000000).unwrap());
     let fund_output_value = dlc_transactions.get_fund_output().value;
 
@@ -284,9 +298,127 @@ pub fn verify_bench(c: &mut Criterion) {
     });
 }
 
+/// Returns the numbers of threads with which to run the scaling benchmark:
+/// powers of two up to the available parallelism, as well as the available
+/// parallelism itself.
+#[cfg(feature = "parallel")]
+fn get_thread_counts() -> Vec<usize> {
+    let max_threads = std::thread::available_parallelism()
+        .map(|x| x.get())
+        .unwrap_or(1);
+    let mut counts: Vec<usize> = std::iter::successors(Some(1), |x| Some(x * 2))
+        .take_while(|x| *x < max_threads)
+        .collect();
+    counts.push(max_threads);
+    counts
+}
+
+/// Benchmark to measure how the adaptor signature creation and verification
+/// times, as well as the adaptor point computation time, decrease with the
+/// number of threads used. The benchmark is run both without and with
+/// difference parameters, to cover the `MultiOracleTrie` and
+/// `MultiOracleTrieWithDiff` paths.
+#[cfg(feature = "parallel")]
+pub fn thread_scaling_bench(c: &mut Criterion) {
+    thread_scaling_bench_helper(c, "multi_oracle_trie", false);
+    thread_scaling_bench_helper(c, "multi_oracle_trie_with_diff", true);
+}
+
+#[cfg(feature = "parallel")]
+fn thread_scaling_bench_helper(c: &mut Criterion, group_name: &str, use_diff_params: bool) {
+    let contract_info = create_contract_info(use_diff_params);
+    let dlc_transactions = create_transactions(&contract_info.get_payouts(200000000).unwrap());
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+
+    let seckey = accept_seckey();
+    let pubkey = secp256k1_zkp::PublicKey::from_secret_key(SECP256K1, &seckey);
+    let adaptor_info = contract_info
+        .get_adaptor_info(
+            SECP256K1,
+            TOTAL_COLLATERAL,
+            &seckey,
+            &dlc_transactions.funding_script_pubkey,
+            fund_output_value,
+            &dlc_transactions.cets,
+            0,
+        )
+        .unwrap();
+    let adaptor_signatures = &adaptor_info.1;
+
+    let mut group = c.benchmark_group(format!("thread_scaling/{}", group_name));
+    for nb_threads in get_thread_counts() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(nb_threads)
+            .build()
+            .unwrap();
+        group.bench_with_input(BenchmarkId::new("sign", nb_threads), &pool, |b, pool| {
+            b.iter(|| {
+                pool.install(|| {
+                    black_box(
+                        contract_info
+                            .get_adaptor_info(
+                                SECP256K1,
+                                TOTAL_COLLATERAL,
+                                &seckey,
+                                &dlc_transactions.funding_script_pubkey,
+                                fund_output_value,
+                                &dlc_transactions.cets,
+                                0,
+                            )
+                            .unwrap(),
+                    )
+                })
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("verify", nb_threads), &pool, |b, pool| {
+            b.iter(|| {
+                pool.install(|| {
+                    black_box(
+                        contract_info
+                            .verify_adaptor_info(
+                                SECP256K1,
+                                &pubkey,
+                                &dlc_transactions.funding_script_pubkey,
+                                fund_output_value,
+                                &dlc_transactions.cets,
+                                adaptor_signatures,
+                                0,
+                                &adaptor_info.0,
+                            )
+                            .unwrap(),
+                    )
+                })
+            });
+        });
+        group.bench_with_input(
+            BenchmarkId::new("adaptor_points", nb_threads),
+            &pool,
+            |b, pool| {
+                b.iter(|| {
+                    pool.install(|| {
+                        black_box(
+                            contract_info
+                                .get_adaptor_points(SECP256K1, &adaptor_info.0)
+                                .unwrap(),
+                        )
+                    })
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+#[cfg(not(feature = "parallel"))]
 criterion_group! {
     name = sign_verify_bench;
     config = Criterion::default().measurement_time(std::time::Duration::new(120, 0)).sample_size(10);
     targets = sign_bench, verify_bench
 }
+#[cfg(feature = "parallel")]
+criterion_group! {
+    name = sign_verify_bench;
+    config = Criterion::default().measurement_time(std::time::Duration::new(120, 0)).sample_size(10);
+    targets = sign_bench, verify_bench, thread_scaling_bench
+}
 criterion_main!(sign_verify_bench);
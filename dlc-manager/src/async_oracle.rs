@@ -0,0 +1,128 @@
+//! #AsyncOracle an asynchronous version of the [`Oracle`] trait, for
+//! implementations fetching information from remote oracles.
+
+use crate::error::Error;
+use crate::Oracle;
+use async_trait::async_trait;
+use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
+use secp256k1_zkp::XOnlyPublicKey;
+use std::ops::Deref;
+
+/// Asynchronous version of the [`Oracle`] trait. Refer to the documentation
+/// of [`Oracle`] for the semantic of each method.
+#[async_trait]
+pub trait AsyncOracle: Send + Sync {
+    /// Returns the public key of the oracle.
+    fn get_public_key(&self) -> XOnlyPublicKey;
+    /// Returns the announcement for the event with the given id if found.
+    async fn get_announcement(&self, event_id: &str) -> Result<OracleAnnouncement, Error>;
+    /// Returns the attestation for the event with the given id if found.
+    async fn get_attestation(&self, event_id: &str) -> Result<OracleAttestation, Error>;
+    /// Returns the attestations for the events with the given ids, in the same
+    /// order. The default implementation calls [`AsyncOracle::get_attestation`]
+    /// concurrently for each event.
+    async fn get_attestations(&self, event_ids: &[&str]) -> Vec<Result<OracleAttestation, Error>> {
+        futures::future::join_all(event_ids.iter().map(|x| self.get_attestation(x))).await
+    }
+}
+
+/// Exposes an [`Oracle`] implementation as an [`AsyncOracle`]. Calls are
+/// forwarded to the wrapped oracle and executed on the polling thread, so
+/// this should only be used with oracles whose operations do not block for
+/// long (e.g. oracles serving information from memory).
+pub struct SyncOracleAdaptor<O: Deref>
+where
+    O::Target: Oracle,
+{
+    oracle: O,
+}
+
+impl<O: Deref> SyncOracleAdaptor<O>
+where
+    O::Target: Oracle,
+{
+    /// Creates a new adaptor wrapping the given oracle.
+    pub fn new(oracle: O) -> Self {
+        Self { oracle }
+    }
+
+    /// Returns a reference to the wrapped oracle.
+    pub fn get_inner(&self) -> &O {
+        &self.oracle
+    }
+}
+
+#[async_trait]
+impl<O: Deref + Send + Sync> AsyncOracle for SyncOracleAdaptor<O>
+where
+    O::Target: Oracle,
+{
+    fn get_public_key(&self) -> XOnlyPublicKey {
+        self.oracle.get_public_key()
+    }
+
+    async fn get_announcement(&self, event_id: &str) -> Result<OracleAnnouncement, Error> {
+        self.oracle.get_announcement(event_id)
+    }
+
+    async fn get_attestation(&self, event_id: &str) -> Result<OracleAttestation, Error> {
+        self.oracle.get_attestation(event_id)
+    }
+
+    async fn get_attestations(&self, event_ids: &[&str]) -> Vec<Result<OracleAttestation, Error>> {
+        self.oracle.get_attestations(event_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dlc_messages::oracle_msgs::{EnumEventDescriptor, EventDescriptor};
+    use futures::executor::block_on;
+    use mocks::dlc_manager::async_oracle::{AsyncOracle, SyncOracleAdaptor};
+    use mocks::dlc_manager::Oracle;
+    use mocks::mock_oracle_provider::MockOracle;
+    use std::sync::Arc;
+
+    const EVENT_ID: &str = "event";
+
+    fn get_oracle() -> MockOracle {
+        let mut oracle = MockOracle::new();
+        let descriptor = EventDescriptor::EnumEvent(EnumEventDescriptor {
+            outcomes: vec!["a".to_string(), "b".to_string()],
+        });
+        oracle.add_event(EVENT_ID, &descriptor, 123);
+        oracle.add_attestation(EVENT_ID, &["a".to_string()]);
+        oracle
+    }
+
+    #[test]
+    fn announcements_and_attestations_round_trip_through_adaptor() {
+        let oracle = Arc::new(get_oracle());
+        let adaptor = SyncOracleAdaptor::new(oracle.clone());
+
+        assert_eq!(oracle.get_public_key(), adaptor.get_public_key());
+        assert_eq!(
+            oracle.get_announcement(EVENT_ID).unwrap(),
+            block_on(adaptor.get_announcement(EVENT_ID)).unwrap()
+        );
+        assert_eq!(
+            oracle.get_attestation(EVENT_ID).unwrap(),
+            block_on(adaptor.get_attestation(EVENT_ID)).unwrap()
+        );
+        block_on(adaptor.get_announcement("unknown")).expect_err("Event should not exist");
+    }
+
+    #[test]
+    fn attestations_are_returned_in_order() {
+        let oracle = Arc::new(get_oracle());
+        let adaptor = SyncOracleAdaptor::new(oracle.clone());
+
+        let res = block_on(adaptor.get_attestations(&["unknown", EVENT_ID]));
+        assert_eq!(2, res.len());
+        assert!(res[0].is_err());
+        assert_eq!(
+            &oracle.get_attestation(EVENT_ID).unwrap(),
+            res[1].as_ref().unwrap()
+        );
+    }
+}
@@ -0,0 +1,701 @@
+//! #AsyncStorage an asynchronous version of the [`Storage`] trait, for
+//! implementations backed by remote databases.
+
+use crate::chain_monitor::{ChainMonitor, HeaderCache};
+use crate::channel::offered_channel::OfferedChannel;
+use crate::channel::punishment::PunishmentData;
+use crate::channel::signed_channel::{SignedChannel, SignedChannelStateType};
+use crate::channel::watchtower::{JusticeBlob, LOCATOR_LEN};
+use crate::channel::{Channel, ChannelEvent};
+use crate::contract::{
+    offered_contract::OfferedContract, signed_contract::SignedContract, Contract, ContractEvent,
+    PreClosedContract,
+};
+use crate::error::Error;
+use crate::product_catalog::ProductDefinition;
+use crate::{ChannelId, ContractId, Storage, StorageBatch, StorageSnapshot};
+use async_trait::async_trait;
+use secp256k1_zkp::PublicKey;
+use std::ops::Deref;
+use std::time::Duration;
+
+/// Asynchronous version of the [`Storage`] trait. Refer to the documentation
+/// of [`Storage`] for the semantic of each method.
+#[async_trait]
+pub trait AsyncStorage: Send + Sync {
+    /// Returns the contract with given id if found.
+    async fn get_contract(&self, id: &ContractId) -> Result<Option<Contract>, Error>;
+    /// Return all contracts
+    async fn get_contracts(&self) -> Result<Vec<Contract>, Error>;
+    /// Returns all the contracts entered with the node with the given public
+    /// key. The default implementation filters the result of
+    /// [`AsyncStorage::get_contracts`].
+    async fn get_contracts_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Contract>, Error> {
+        Ok(self
+            .get_contracts()
+            .await?
+            .into_iter()
+            .filter(|c| &c.get_counter_party_id() == counter_party)
+            .collect())
+    }
+    /// Returns the contract with the given temporary id, if found. See
+    /// [`Storage::get_contract_by_temporary_id`], whose behavior the
+    /// default implementation mirrors by filtering the result of
+    /// [`AsyncStorage::get_contracts`].
+    async fn get_contract_by_temporary_id(
+        &self,
+        temporary_id: &ContractId,
+    ) -> Result<Option<Contract>, Error> {
+        let (amended, others): (Vec<_>, Vec<_>) = self
+            .get_contracts()
+            .await?
+            .into_iter()
+            .filter(|c| &c.get_temporary_id() == temporary_id)
+            .partition(|c| matches!(c, Contract::Amended(_)));
+        Ok(others.into_iter().chain(amended).next())
+    }
+    /// Returns all the contracts whose metadata contains the given label. The
+    /// default implementation filters the result of
+    /// [`AsyncStorage::get_contracts`].
+    async fn get_contracts_by_label(&self, label: &str) -> Result<Vec<Contract>, Error> {
+        Ok(self
+            .get_contracts()
+            .await?
+            .into_iter()
+            .filter(|c| c.get_metadata().map_or(false, |m| m.has_label(label)))
+            .collect())
+    }
+    /// Create a record for the given contract, failing if a contract with the
+    /// same id already exists.
+    async fn create_contract(&self, contract: &OfferedContract) -> Result<(), Error>;
+    /// Create a record for the given contract, overwriting any existing
+    /// contract with the same id.
+    async fn replace_contract(&self, contract: &OfferedContract) -> Result<(), Error> {
+        self.update_contract(&Contract::Offered(contract.clone()))
+            .await
+    }
+    /// Delete the record for the contract with the given id.
+    async fn delete_contract(&self, id: &ContractId) -> Result<(), Error>;
+    /// Update the given contract.
+    async fn update_contract(&self, contract: &Contract) -> Result<(), Error>;
+    /// Returns the set of contracts in offered state.
+    async fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error>;
+    /// Returns the set of contracts whose offer expired before being accepted.
+    /// The default implementation filters the result of
+    /// [`AsyncStorage::get_contracts`].
+    async fn get_expired_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        Ok(self
+            .get_contracts()
+            .await?
+            .into_iter()
+            .filter_map(|c| match c {
+                Contract::Expired(o) => Some(o),
+                _ => None,
+            })
+            .collect())
+    }
+    /// Returns the set of contracts in signed state.
+    async fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error>;
+    /// Returns the set of confirmed contracts.
+    async fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, Error>;
+    /// Returns the set of contracts whos broadcasted cet has not been verified
+    /// to be confirmed on blockchain
+    async fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, Error>;
+    /// Update the state of the channel and optionally its associated contract
+    /// atomically.
+    async fn upsert_channel(
+        &self,
+        channel: Channel,
+        contract: Option<Contract>,
+    ) -> Result<(), Error>;
+    /// Delete the channel with given [`ChannelId`] if any.
+    async fn delete_channel(&self, channel_id: &ChannelId) -> Result<(), Error>;
+    /// Returns the channel with given [`ChannelId`] if any.
+    async fn get_channel(&self, channel_id: &ChannelId) -> Result<Option<Channel>, Error>;
+    /// Returns all the channels opened with the node with the given public
+    /// key. The default implementation filters the offered and signed
+    /// channels.
+    async fn get_channels_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Channel>, Error> {
+        let mut res = self
+            .get_offered_channels()
+            .await?
+            .into_iter()
+            .filter(|c| &c.counter_party == counter_party)
+            .map(Channel::Offered)
+            .collect::<Vec<_>>();
+        res.extend(
+            self.get_signed_channels(None)
+                .await?
+                .into_iter()
+                .filter(|c| &c.counter_party == counter_party)
+                .map(Channel::Signed),
+        );
+        Ok(res)
+    }
+    /// Returns the channel whose current contract has the given id, if any.
+    /// The default implementation searches the offered and signed channels.
+    async fn get_channel_by_contract_id(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<Channel>, Error> {
+        if let Some(channel) = self
+            .get_signed_channels(None)
+            .await?
+            .into_iter()
+            .find(|c| c.get_contract_id().as_ref() == Some(contract_id))
+        {
+            return Ok(Some(Channel::Signed(channel)));
+        }
+        Ok(self
+            .get_offered_channels()
+            .await?
+            .into_iter()
+            .find(|c| &c.offered_contract_id == contract_id)
+            .map(Channel::Offered))
+    }
+    /// Returns the set of [`SignedChannel`] in the store. Returns only the one
+    /// with matching `channel_state` if set.
+    async fn get_signed_channels(
+        &self,
+        channel_state: Option<SignedChannelStateType>,
+    ) -> Result<Vec<SignedChannel>, Error>;
+    /// Returns the set of channels in offer state.
+    async fn get_offered_channels(&self) -> Result<Vec<OfferedChannel>, Error>;
+    /// Writes the [`ChainMonitor`] data to the store.
+    async fn persist_chain_monitor(&self, monitor: &ChainMonitor) -> Result<(), Error>;
+    /// Returns the latest [`ChainMonitor`] in the store if any.
+    async fn get_chain_monitor(&self) -> Result<Option<ChainMonitor>, Error>;
+    /// Removes the closed, refunded and failed contracts that reached that
+    /// state more than `older_than` ago. The default implementation returns an
+    /// error as pruning is not supported.
+    async fn prune_closed_contracts(&self, _older_than: Duration) -> Result<usize, Error> {
+        Err(Error::StorageError(
+            "Pruning closed contracts is not supported by this storage".to_string(),
+        ))
+    }
+    /// Records that a message was received from the given peer at the given
+    /// time. The default implementation does not record anything.
+    async fn update_peer_last_seen(
+        &self,
+        _peer_id: &PublicKey,
+        _timestamp: u64,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the last time a message was received from the given peer. The
+    /// default implementation always returns `None`.
+    async fn get_peer_last_seen(&self, _peer_id: &PublicKey) -> Result<Option<u64>, Error> {
+        Ok(None)
+    }
+    /// Records that the contract with the given temporary id was created from
+    /// an offer with the given hash. The default implementation does not
+    /// record anything.
+    async fn upsert_offer_hash(
+        &self,
+        _offer_hash: &[u8; 32],
+        _temporary_id: &ContractId,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the temporary id of the contract last created from an offer with
+    /// the given hash, if any. The default implementation always returns
+    /// `None`.
+    async fn get_contract_id_by_offer_hash(
+        &self,
+        _offer_hash: &[u8; 32],
+    ) -> Result<Option<ContractId>, Error> {
+        Ok(None)
+    }
+    /// Stores the data required to punish the broadcast of a revoked channel
+    /// state. The default implementation does not record anything.
+    async fn upsert_punishment_data(&self, _data: &PunishmentData) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the punishment data stored for the given channel and update
+    /// index, if any. The default implementation always returns `None`.
+    async fn get_punishment_data(
+        &self,
+        _channel_id: &ChannelId,
+        _update_idx: u64,
+    ) -> Result<Option<PunishmentData>, Error> {
+        Ok(None)
+    }
+    /// Returns the punishment data of at most `count` of the most recently
+    /// revoked states of the given channel, most recent first. The default
+    /// implementation always returns an empty list.
+    async fn get_latest_punishment_data(
+        &self,
+        _channel_id: &ChannelId,
+        _count: usize,
+    ) -> Result<Vec<PunishmentData>, Error> {
+        Ok(Vec::new())
+    }
+    /// Stores the given [`JusticeBlob`]. The default implementation does not
+    /// record anything.
+    async fn upsert_justice_blob(&self, _blob: &JusticeBlob) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the [`JusticeBlob`] with the given locator, if any. The default
+    /// implementation always returns `None`.
+    async fn get_justice_blob(
+        &self,
+        _locator: &[u8; LOCATOR_LEN],
+    ) -> Result<Option<JusticeBlob>, Error> {
+        Ok(None)
+    }
+    /// Writes the [`HeaderCache`] used to detect chain reorganizations to the
+    /// store. The default implementation does not record anything.
+    async fn persist_header_cache(&self, _header_cache: &HeaderCache) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the latest [`HeaderCache`] in the store if any. The default
+    /// implementation always returns `None`.
+    async fn get_header_cache(&self) -> Result<Option<HeaderCache>, Error> {
+        Ok(None)
+    }
+    /// Stores the definition of the product settled by the oracle event with
+    /// the given id. The default implementation does not record anything.
+    async fn upsert_product_definition(
+        &self,
+        _event_id: &str,
+        _product: &ProductDefinition,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the definition of the product settled by the oracle event with
+    /// the given id, if any. The default implementation always returns `None`.
+    async fn get_product_definition(
+        &self,
+        _event_id: &str,
+    ) -> Result<Option<ProductDefinition>, Error> {
+        Ok(None)
+    }
+    /// Appends the given event to the event log of the contract with the
+    /// given temporary id. The default implementation does not record
+    /// anything.
+    async fn append_contract_event(
+        &self,
+        _temporary_id: &ContractId,
+        _event: &ContractEvent,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the event log of the contract with the given id or temporary id,
+    /// oldest first. The default implementation always returns an empty log.
+    async fn get_contract_history(&self, _id: &ContractId) -> Result<Vec<ContractEvent>, Error> {
+        Ok(Vec::new())
+    }
+    /// Appends the given event to the event log of the channel with the given
+    /// temporary id. The default implementation does not record anything.
+    async fn append_channel_event(
+        &self,
+        _temporary_id: &ChannelId,
+        _event: &ChannelEvent,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the event log of the channel with the given id or temporary id,
+    /// oldest first. The default implementation always returns an empty log.
+    async fn get_channel_history(&self, _id: &ChannelId) -> Result<Vec<ChannelEvent>, Error> {
+        Ok(Vec::new())
+    }
+    /// Writes all the records contained in the given [`StorageBatch`]
+    /// atomically. The default implementation writes them one after the other.
+    async fn write_batch(&self, batch: StorageBatch) -> Result<(), Error> {
+        for contract in &batch.new_contracts {
+            self.create_contract(contract).await?;
+        }
+        for contract in &batch.contracts {
+            self.update_contract(contract).await?;
+        }
+        for channel in batch.channels {
+            self.upsert_channel(channel, None).await?;
+        }
+        for (temporary_id, event) in &batch.contract_events {
+            self.append_contract_event(temporary_id, event).await?;
+        }
+        for (temporary_id, event) in &batch.channel_events {
+            self.append_channel_event(temporary_id, event).await?;
+        }
+        if let Some(chain_monitor) = &batch.chain_monitor {
+            self.persist_chain_monitor(chain_monitor).await?;
+        }
+        Ok(())
+    }
+    /// Returns a consistent [`StorageSnapshot`] of the contracts, channels and
+    /// chain monitor in the store. The default implementation reads the
+    /// records one after the other and only includes offered and signed
+    /// channels.
+    async fn read_snapshot(&self) -> Result<StorageSnapshot, Error> {
+        let mut channels = self
+            .get_offered_channels()
+            .await?
+            .into_iter()
+            .map(Channel::Offered)
+            .collect::<Vec<_>>();
+        channels.extend(
+            self.get_signed_channels(None)
+                .await?
+                .into_iter()
+                .map(Channel::Signed),
+        );
+        Ok(StorageSnapshot::new(
+            self.get_contracts().await?,
+            channels,
+            self.get_chain_monitor().await?,
+        ))
+    }
+    /// Ensures that all the records written so far are persisted on durable
+    /// storage. The default implementation does nothing.
+    async fn flush(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Exposes a [`Storage`] implementation as an [`AsyncStorage`]. Calls are
+/// forwarded to the wrapped storage and executed on the polling thread, so
+/// this should only be used with storages whose operations do not block for
+/// long (e.g. embedded databases).
+pub struct SyncStorageAdaptor<S: Deref>
+where
+    S::Target: Storage,
+{
+    storage: S,
+}
+
+impl<S: Deref> SyncStorageAdaptor<S>
+where
+    S::Target: Storage,
+{
+    /// Creates a new adaptor wrapping the given storage.
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Returns a reference to the wrapped storage.
+    pub fn get_inner(&self) -> &S {
+        &self.storage
+    }
+}
+
+#[async_trait]
+impl<S: Deref + Send + Sync> AsyncStorage for SyncStorageAdaptor<S>
+where
+    S::Target: Storage,
+{
+    async fn get_contract(&self, id: &ContractId) -> Result<Option<Contract>, Error> {
+        self.storage.get_contract(id)
+    }
+
+    async fn get_contracts(&self) -> Result<Vec<Contract>, Error> {
+        self.storage.get_contracts()
+    }
+
+    async fn get_contracts_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Contract>, Error> {
+        self.storage.get_contracts_by_counterparty(counter_party)
+    }
+
+    async fn get_contract_by_temporary_id(
+        &self,
+        temporary_id: &ContractId,
+    ) -> Result<Option<Contract>, Error> {
+        self.storage.get_contract_by_temporary_id(temporary_id)
+    }
+
+    async fn get_contracts_by_label(&self, label: &str) -> Result<Vec<Contract>, Error> {
+        self.storage.get_contracts_by_label(label)
+    }
+
+    async fn create_contract(&self, contract: &OfferedContract) -> Result<(), Error> {
+        self.storage.create_contract(contract)
+    }
+
+    async fn replace_contract(&self, contract: &OfferedContract) -> Result<(), Error> {
+        self.storage.replace_contract(contract)
+    }
+
+    async fn delete_contract(&self, id: &ContractId) -> Result<(), Error> {
+        self.storage.delete_contract(id)
+    }
+
+    async fn update_contract(&self, contract: &Contract) -> Result<(), Error> {
+        self.storage.update_contract(contract)
+    }
+
+    async fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        self.storage.get_contract_offers()
+    }
+
+    async fn get_expired_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        self.storage.get_expired_offers()
+    }
+
+    async fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        self.storage.get_signed_contracts()
+    }
+
+    async fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        self.storage.get_confirmed_contracts()
+    }
+
+    async fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, Error> {
+        self.storage.get_preclosed_contracts()
+    }
+
+    async fn upsert_channel(
+        &self,
+        channel: Channel,
+        contract: Option<Contract>,
+    ) -> Result<(), Error> {
+        self.storage.upsert_channel(channel, contract)
+    }
+
+    async fn delete_channel(&self, channel_id: &ChannelId) -> Result<(), Error> {
+        self.storage.delete_channel(channel_id)
+    }
+
+    async fn get_channel(&self, channel_id: &ChannelId) -> Result<Option<Channel>, Error> {
+        self.storage.get_channel(channel_id)
+    }
+
+    async fn get_channels_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Channel>, Error> {
+        self.storage.get_channels_by_counterparty(counter_party)
+    }
+
+    async fn get_channel_by_contract_id(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<Channel>, Error> {
+        self.storage.get_channel_by_contract_id(contract_id)
+    }
+
+    async fn get_signed_channels(
+        &self,
+        channel_state: Option<SignedChannelStateType>,
+    ) -> Result<Vec<SignedChannel>, Error> {
+        self.storage.get_signed_channels(channel_state)
+    }
+
+    async fn get_offered_channels(&self) -> Result<Vec<OfferedChannel>, Error> {
+        self.storage.get_offered_channels()
+    }
+
+    async fn persist_chain_monitor(&self, monitor: &ChainMonitor) -> Result<(), Error> {
+        self.storage.persist_chain_monitor(monitor)
+    }
+
+    async fn get_chain_monitor(&self) -> Result<Option<ChainMonitor>, Error> {
+        self.storage.get_chain_monitor()
+    }
+
+    async fn prune_closed_contracts(&self, older_than: Duration) -> Result<usize, Error> {
+        self.storage.prune_closed_contracts(older_than)
+    }
+
+    async fn update_peer_last_seen(
+        &self,
+        peer_id: &PublicKey,
+        timestamp: u64,
+    ) -> Result<(), Error> {
+        self.storage.update_peer_last_seen(peer_id, timestamp)
+    }
+
+    async fn get_peer_last_seen(&self, peer_id: &PublicKey) -> Result<Option<u64>, Error> {
+        self.storage.get_peer_last_seen(peer_id)
+    }
+
+    async fn upsert_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+        temporary_id: &ContractId,
+    ) -> Result<(), Error> {
+        self.storage.upsert_offer_hash(offer_hash, temporary_id)
+    }
+
+    async fn get_contract_id_by_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+    ) -> Result<Option<ContractId>, Error> {
+        self.storage.get_contract_id_by_offer_hash(offer_hash)
+    }
+
+    async fn upsert_punishment_data(&self, data: &PunishmentData) -> Result<(), Error> {
+        self.storage.upsert_punishment_data(data)
+    }
+
+    async fn get_punishment_data(
+        &self,
+        channel_id: &ChannelId,
+        update_idx: u64,
+    ) -> Result<Option<PunishmentData>, Error> {
+        self.storage.get_punishment_data(channel_id, update_idx)
+    }
+
+    async fn get_latest_punishment_data(
+        &self,
+        channel_id: &ChannelId,
+        count: usize,
+    ) -> Result<Vec<PunishmentData>, Error> {
+        self.storage.get_latest_punishment_data(channel_id, count)
+    }
+
+    async fn upsert_justice_blob(&self, blob: &JusticeBlob) -> Result<(), Error> {
+        self.storage.upsert_justice_blob(blob)
+    }
+
+    async fn get_justice_blob(
+        &self,
+        locator: &[u8; LOCATOR_LEN],
+    ) -> Result<Option<JusticeBlob>, Error> {
+        self.storage.get_justice_blob(locator)
+    }
+
+    async fn persist_header_cache(&self, header_cache: &HeaderCache) -> Result<(), Error> {
+        self.storage.persist_header_cache(header_cache)
+    }
+
+    async fn get_header_cache(&self) -> Result<Option<HeaderCache>, Error> {
+        self.storage.get_header_cache()
+    }
+
+    async fn upsert_product_definition(
+        &self,
+        event_id: &str,
+        product: &ProductDefinition,
+    ) -> Result<(), Error> {
+        self.storage.upsert_product_definition(event_id, product)
+    }
+
+    async fn get_product_definition(
+        &self,
+        event_id: &str,
+    ) -> Result<Option<ProductDefinition>, Error> {
+        self.storage.get_product_definition(event_id)
+    }
+
+    async fn append_contract_event(
+        &self,
+        temporary_id: &ContractId,
+        event: &ContractEvent,
+    ) -> Result<(), Error> {
+        self.storage.append_contract_event(temporary_id, event)
+    }
+
+    async fn get_contract_history(&self, id: &ContractId) -> Result<Vec<ContractEvent>, Error> {
+        self.storage.get_contract_history(id)
+    }
+
+    async fn append_channel_event(
+        &self,
+        temporary_id: &ChannelId,
+        event: &ChannelEvent,
+    ) -> Result<(), Error> {
+        self.storage.append_channel_event(temporary_id, event)
+    }
+
+    async fn get_channel_history(&self, id: &ChannelId) -> Result<Vec<ChannelEvent>, Error> {
+        self.storage.get_channel_history(id)
+    }
+
+    async fn write_batch(&self, batch: StorageBatch) -> Result<(), Error> {
+        self.storage.write_batch(batch)
+    }
+
+    async fn read_snapshot(&self) -> Result<StorageSnapshot, Error> {
+        self.storage.read_snapshot()
+    }
+
+    async fn flush(&self) -> Result<(), Error> {
+        self.storage.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use mocks::dlc_manager::async_storage::{AsyncStorage, SyncStorageAdaptor};
+    use mocks::dlc_manager::chain_monitor::ChainMonitor;
+    use mocks::dlc_manager::contract::{offered_contract::OfferedContract, Contract};
+    use mocks::dlc_manager::Storage;
+    use mocks::memory_storage_provider::MemoryStorage;
+    use secp256k1_zkp::PublicKey;
+    use std::sync::Arc;
+
+    fn pubkey() -> PublicKey {
+        "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166"
+            .parse()
+            .unwrap()
+    }
+
+    fn offered_contract() -> OfferedContract {
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        OfferedContract::try_from_offer_dlc(&offer, pubkey(), [0u8; 32]).unwrap()
+    }
+
+    #[test]
+    fn contracts_round_trip_through_adaptor() {
+        let storage = Arc::new(MemoryStorage::new());
+        let adaptor = SyncStorageAdaptor::new(storage.clone());
+        let offered_contract = offered_contract();
+        let id = offered_contract.id;
+
+        block_on(adaptor.create_contract(&offered_contract)).unwrap();
+        assert!(matches!(
+            storage.get_contract(&id).unwrap(),
+            Some(Contract::Offered(_))
+        ));
+        assert!(matches!(
+            block_on(adaptor.get_contract(&id)).unwrap(),
+            Some(Contract::Offered(c)) if c.id == id
+        ));
+        assert_eq!(1, block_on(adaptor.get_contract_offers()).unwrap().len());
+        assert_eq!(
+            1,
+            block_on(adaptor.get_contracts_by_counterparty(&pubkey()))
+                .unwrap()
+                .len()
+        );
+
+        block_on(adaptor.update_contract(&Contract::Rejected(offered_contract))).unwrap();
+        assert!(matches!(
+            block_on(adaptor.get_contract(&id)).unwrap(),
+            Some(Contract::Rejected(_))
+        ));
+        assert!(block_on(adaptor.get_contract_offers()).unwrap().is_empty());
+
+        block_on(adaptor.delete_contract(&id)).unwrap();
+        assert!(block_on(adaptor.get_contract(&id)).unwrap().is_none());
+        assert!(storage.get_contracts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn chain_monitor_round_trips_through_adaptor() {
+        let storage = Arc::new(MemoryStorage::new());
+        let adaptor = SyncStorageAdaptor::new(storage.clone());
+        assert!(block_on(adaptor.get_chain_monitor()).unwrap().is_none());
+
+        let chain_monitor = ChainMonitor::new(123);
+        block_on(adaptor.persist_chain_monitor(&chain_monitor)).unwrap();
+        assert_eq!(
+            Some(chain_monitor.clone()),
+            storage.get_chain_monitor().unwrap()
+        );
+        assert_eq!(
+            Some(chain_monitor),
+            block_on(adaptor.get_chain_monitor()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! #Cancellation tokens used to abort long running operations such as
+//! accepting an offer for a contract with a large number of outcomes.
+
+use crate::error::Error;
+use crate::progress::{ProgressHandler, ProgressReporter};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Token shared between an operation and its caller, allowing the caller to
+/// request the operation to be aborted. Operations check the token between
+/// signing steps and return [`Error::Cancelled`] without persisting anything
+/// once it has been cancelled. Tokens can also carry a [`ProgressHandler`]
+/// to which the operation reports its progress.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    progress_reporter: Option<ProgressReporter>,
+}
+
+impl CancellationToken {
+    /// Creates a new token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new token reporting the progress of the operations on the
+    /// object with the given id to the given handler.
+    pub fn with_progress_handler(id: [u8; 32], handler: Arc<dyn ProgressHandler>) -> Self {
+        CancellationToken {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            progress_reporter: Some(ProgressReporter { id, handler }),
+        }
+    }
+
+    pub(crate) fn progress_reporter(&self) -> Option<&ProgressReporter> {
+        self.progress_reporter.as_ref()
+    }
+
+    /// Requests the operations using this token to be aborted.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether [`CancellationToken::cancel`] was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns [`Error::Cancelled`] if the token was cancelled.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// Keeps track of the tokens of the operations in progress, keyed by the id of
+/// the contract or channel they operate on.
+#[derive(Default)]
+pub(crate) struct CancellationRegistry {
+    tokens: Mutex<HashMap<[u8; 32], CancellationToken>>,
+    progress_handler: Option<Arc<dyn ProgressHandler>>,
+}
+
+impl CancellationRegistry {
+    /// Sets the handler to which registered operations report their progress.
+    pub(crate) fn set_progress_handler(&mut self, handler: Arc<dyn ProgressHandler>) {
+        self.progress_handler = Some(handler);
+    }
+
+    /// Registers a new token for an operation on the object with the given id.
+    /// The token is unregistered when the returned value is dropped.
+    pub(crate) fn register(&self, id: [u8; 32]) -> RegisteredToken<'_> {
+        let token = match &self.progress_handler {
+            Some(handler) => CancellationToken::with_progress_handler(id, handler.clone()),
+            None => CancellationToken::new(),
+        };
+        self.tokens.lock().unwrap().insert(id, token.clone());
+        RegisteredToken {
+            registry: self,
+            id,
+            token,
+        }
+    }
+
+    /// Cancels the operation in progress on the object with the given id, if
+    /// any. Returns whether an operation was cancelled.
+    pub(crate) fn cancel(&self, id: &[u8; 32]) -> bool {
+        match self.tokens.lock().unwrap().get(id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A [`CancellationToken`] registered in a [`CancellationRegistry`].
+pub(crate) struct RegisteredToken<'a> {
+    registry: &'a CancellationRegistry,
+    id: [u8; 32],
+    token: CancellationToken,
+}
+
+impl Deref for RegisteredToken<'_> {
+    type Target = CancellationToken;
+
+    fn deref(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for RegisteredToken<'_> {
+    fn drop(&mut self) {
+        let mut tokens = self.registry.tokens.lock().unwrap();
+        // Only remove the token if it was not replaced by a later operation.
+        if tokens
+            .get(&self.id)
+            .map_or(false, |t| Arc::ptr_eq(&t.cancelled, &self.token.cancelled))
+        {
+            tokens.remove(&self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelled_token_fails_check() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+
+        clone.cancel();
+
+        assert!(token.is_cancelled());
+        assert!(matches!(token.check(), Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn registered_token_is_removed_on_drop() {
+        let registry = CancellationRegistry::default();
+        {
+            let token = registry.register([1u8; 32]);
+            assert!(registry.cancel(&[1u8; 32]));
+            assert!(token.is_cancelled());
+        }
+        assert!(!registry.cancel(&[1u8; 32]));
+    }
+}
@@ -2,29 +2,56 @@
 
 use std::collections::HashMap;
 
-use bitcoin::{Block, BlockHash, Transaction, Txid};
+use bitcoin::block::Header;
+use bitcoin::{Block, BlockHash, OutPoint, Transaction, Txid};
 use dlc_messages::ser_impls::{
     read_ecdsa_adaptor_signature, read_hash_map, read_vec, write_ecdsa_adaptor_signature,
     write_hash_map, write_vec,
 };
 use lightning::ln::msgs::DecodeError;
 use lightning::util::ser::{Readable, Writeable, Writer};
-use secp256k1_zkp::EcdsaAdaptorSignature;
+use secp256k1_zkp::{EcdsaAdaptorSignature, PublicKey};
 
+use crate::channel::signed_channel::SignedChannel;
+use crate::contract::signed_contract::SignedContract;
 use crate::ChannelId;
 
-const NB_SAVED_BLOCK_HASHES: usize = 6;
+pub(crate) const NB_SAVED_BLOCK_HASHES: usize = 6;
 
 /// A `ChainMonitor` keeps a list of transaction ids to watch for in the blockchain,
 /// and some associated information used to apply an action when the id is seen.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ChainMonitor {
     watched_tx: HashMap<Txid, ChannelInfo>,
     pub(crate) last_height: u64,
     pub(crate) last_block_hashes: Vec<BlockHash>,
+    claimable_outputs: Vec<ClaimableOutput>,
+    processed_txs: Vec<ProcessedTx>,
 }
 
-impl_dlc_writeable!(ChainMonitor, { (watched_tx, { cb_writeable, write_hash_map, read_hash_map}), (last_height, writeable), (last_block_hashes, { cb_writeable, write_vec, read_vec}) });
+impl_dlc_writeable!(ChainMonitor, { (watched_tx, { cb_writeable, write_hash_map, read_hash_map}), (last_height, writeable), (last_block_hashes, { cb_writeable, write_vec, read_vec}), (claimable_outputs, { cb_writeable, write_vec, read_claimable_outputs }), (processed_txs, { cb_writeable, write_vec, read_processed_txs }) });
+
+// Chain monitors persisted before claimable outputs were tracked do not
+// include them.
+fn read_claimable_outputs<R: lightning::io::Read>(
+    reader: &mut R,
+) -> Result<Vec<ClaimableOutput>, DecodeError> {
+    match read_vec(reader) {
+        Err(DecodeError::ShortRead) => Ok(Vec::new()),
+        res => res,
+    }
+}
+
+// Chain monitors persisted before processed transactions were recorded do not
+// include them.
+fn read_processed_txs<R: lightning::io::Read>(
+    reader: &mut R,
+) -> Result<Vec<ProcessedTx>, DecodeError> {
+    match read_vec(reader) {
+        Err(DecodeError::ShortRead) => Ok(Vec::new()),
+        res => res,
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct ChannelInfo {
@@ -34,6 +61,84 @@ pub(crate) struct ChannelInfo {
 
 impl_dlc_writeable!(ChannelInfo, { (channel_id, writeable), (tx_type, writeable) });
 
+/// An output of a channel transaction paying the local party once its relative
+/// time lock expires, to be claimed to the wallet at that time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ClaimableOutput {
+    pub channel_id: ChannelId,
+    pub txid: Txid,
+    pub vout: u32,
+    pub value: u64,
+    pub own_per_update_point: PublicKey,
+    pub counter_per_update_point: PublicKey,
+    pub csv_timelock: u32,
+}
+
+impl_dlc_writeable!(ClaimableOutput, {
+    (channel_id, writeable),
+    (txid, writeable),
+    (vout, writeable),
+    (value, writeable),
+    (own_per_update_point, writeable),
+    (counter_per_update_point, writeable),
+    (csv_timelock, writeable)
+});
+
+impl ClaimableOutput {
+    pub(crate) fn outpoint(&self) -> OutPoint {
+        OutPoint {
+            txid: self.txid,
+            vout: self.vout,
+        }
+    }
+}
+
+/// A watched transaction included in one of the last processed blocks, with
+/// the state of its channel and of the confirmed contracts of the channel
+/// before it was processed, so that its effects can be reverted if the block
+/// gets reorganized out of the best chain.
+#[derive(Clone)]
+pub(crate) struct ProcessedTx {
+    pub txid: Txid,
+    pub height: u64,
+    pub prev_channel: SignedChannel,
+    pub prev_contracts: Vec<SignedContract>,
+}
+
+impl_dlc_writeable!(ProcessedTx, {
+    (txid, writeable),
+    (height, writeable),
+    (prev_channel, writeable),
+    (prev_contracts, vec)
+});
+
+// Channels and contracts do not implement these traits, they are compared
+// using their serialization.
+impl PartialEq for ProcessedTx {
+    fn eq(&self, other: &Self) -> bool {
+        self.txid == other.txid
+            && self.height == other.height
+            && self.prev_channel.encode() == other.prev_channel.encode()
+            && self
+                .prev_contracts
+                .iter()
+                .map(|x| x.encode())
+                .eq(other.prev_contracts.iter().map(|x| x.encode()))
+    }
+}
+
+impl Eq for ProcessedTx {}
+
+impl std::fmt::Debug for ProcessedTx {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessedTx")
+            .field("txid", &self.txid)
+            .field("height", &self.height)
+            .field("channel_id", &self.prev_channel.channel_id)
+            .finish()
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) enum TxType {
     Revoked {
@@ -56,13 +161,18 @@ impl_dlc_writeable_enum!(TxType,;
     (1, Current), (2, CollaborativeClose)
 );
 
+/// The type of a revoked channel transaction.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub(crate) enum RevokedTxType {
+pub enum RevokedTxType {
+    /// A buffer transaction of a channel holding a single contract.
     Buffer,
+    /// A settle transaction.
     Settle,
+    /// A buffer transaction of a channel holding several contracts.
+    MultiBuffer,
 }
 
-impl_dlc_writeable_enum!(RevokedTxType,;;;(0, Buffer), (1, Settle));
+impl_dlc_writeable_enum!(RevokedTxType,;;;(0, Buffer), (1, Settle), (2, MultiBuffer));
 
 impl ChainMonitor {
     /// Returns a new [`ChainMonitor`] with fields properly initialized.
@@ -71,6 +181,8 @@ impl ChainMonitor {
             watched_tx: HashMap::new(),
             last_height: init_height,
             last_block_hashes: Vec::with_capacity(NB_SAVED_BLOCK_HASHES),
+            claimable_outputs: Vec::new(),
+            processed_txs: Vec::new(),
         }
     }
 
@@ -82,6 +194,42 @@ impl ChainMonitor {
         self.watched_tx.remove(txid);
     }
 
+    pub(crate) fn is_watched(&self, txid: &Txid) -> bool {
+        self.watched_tx.contains_key(txid)
+    }
+
+    pub(crate) fn add_claimable_output(&mut self, output: ClaimableOutput) {
+        if !self
+            .claimable_outputs
+            .iter()
+            .any(|x| x.outpoint() == output.outpoint())
+        {
+            self.claimable_outputs.push(output);
+        }
+    }
+
+    pub(crate) fn remove_claimable_output(&mut self, outpoint: &OutPoint) {
+        self.claimable_outputs.retain(|x| x.outpoint() != *outpoint);
+    }
+
+    pub(crate) fn get_claimable_outputs(&self) -> Vec<ClaimableOutput> {
+        self.claimable_outputs.clone()
+    }
+
+    /// Returns the outpoints of the outputs of closed channels that will be
+    /// claimed to the wallet once their relative time lock expires, with the
+    /// id of their channel and their value.
+    pub fn get_pending_claims(&self) -> Vec<(ChannelId, OutPoint, u64)> {
+        self.claimable_outputs
+            .iter()
+            .map(|x| (x.channel_id, x.outpoint(), x.value))
+            .collect()
+    }
+
+    pub(crate) fn add_processed_tx(&mut self, processed_tx: ProcessedTx) {
+        self.processed_txs.push(processed_tx);
+    }
+
     pub(crate) fn process_block(
         &self,
         block: &Block,
@@ -114,5 +262,220 @@ impl ChainMonitor {
         if self.last_block_hashes.len() > NB_SAVED_BLOCK_HASHES {
             self.last_block_hashes.remove(0);
         }
+        let last_height = self.last_height;
+        self.processed_txs
+            .retain(|x| x.height + NB_SAVED_BLOCK_HASHES as u64 > last_height);
+    }
+
+    pub(crate) fn get_watched_txs(&self) -> Vec<(Txid, ChannelInfo)> {
+        self.watched_tx
+            .iter()
+            .map(|(txid, info)| (*txid, info.clone()))
+            .collect()
+    }
+
+    /// Marks all the blocks up to the given height as processed without
+    /// going through them. The saved block hashes are discarded as they are
+    /// not contiguous with the new height anymore.
+    pub(crate) fn fast_forward(&mut self, height: u64) {
+        self.last_height = height;
+        self.last_block_hashes.clear();
+        self.processed_txs.clear();
+    }
+
+    /// Marks the blocks above the given height as not processed, so that the
+    /// blocks of the new best chain get processed after a reorganization.
+    /// Returns the transactions processed in the discarded blocks, most
+    /// recent first, and stops tracking the outputs they created.
+    pub(crate) fn rewind(&mut self, height: u64) -> Vec<ProcessedTx> {
+        let nb_discarded = self.last_height.saturating_sub(height) as usize;
+        self.last_height = self.last_height.min(height);
+        let nb_kept = self.last_block_hashes.len().saturating_sub(nb_discarded);
+        self.last_block_hashes.truncate(nb_kept);
+
+        let (mut discarded, kept): (Vec<_>, Vec<_>) = self
+            .processed_txs
+            .drain(..)
+            .partition(|x| x.height > height);
+        self.processed_txs = kept;
+        self.claimable_outputs
+            .retain(|x| !discarded.iter().any(|y| y.txid == x.txid));
+        discarded.reverse();
+        discarded
+    }
+}
+
+/// A header of a block seen by the [`ChainMonitor`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedHeader {
+    /// The hash of the block.
+    pub block_hash: BlockHash,
+    /// The hash of the previous block.
+    pub prev_blockhash: BlockHash,
+    /// The height of the block.
+    pub height: u64,
+}
+
+impl_dlc_writeable!(CachedHeader, {
+    (block_hash, writeable),
+    (prev_blockhash, writeable),
+    (height, writeable)
+});
+
+/// A short tree of the headers of the last blocks seen by the
+/// [`ChainMonitor`], used to detect chain reorganizations. Headers of blocks
+/// that were reorganized out are kept until they get too old, so that the
+/// cache can hold several concurrent chain tips.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeaderCache {
+    headers: HashMap<BlockHash, CachedHeader>,
+    best_tip: Option<BlockHash>,
+}
+
+impl_dlc_writeable!(HeaderCache, { (headers, { cb_writeable, write_hash_map, read_hash_map }), (best_tip, option) });
+
+impl HeaderCache {
+    /// Returns an empty [`HeaderCache`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the header of the tip of the best chain, if any.
+    pub fn get_best_tip(&self) -> Option<&CachedHeader> {
+        self.best_tip.as_ref().and_then(|x| self.headers.get(x))
+    }
+
+    /// Returns the headers of the cached blocks without any known child,
+    /// including the tip of the best chain.
+    pub fn get_tips(&self) -> Vec<&CachedHeader> {
+        self.headers
+            .values()
+            .filter(|x| {
+                !self
+                    .headers
+                    .values()
+                    .any(|y| y.prev_blockhash == x.block_hash)
+            })
+            .collect()
+    }
+
+    /// Returns the cached headers of the best chain, starting from its tip.
+    pub fn get_best_chain(&self) -> Vec<&CachedHeader> {
+        let mut chain = Vec::new();
+        let mut cur = self.get_best_tip();
+        while let Some(header) = cur {
+            chain.push(header);
+            cur = self.headers.get(&header.prev_blockhash);
+        }
+        chain
+    }
+
+    /// Adds the header of the block at the given height, making it the tip of
+    /// the best chain, and discards the headers that are too old for a
+    /// reorganization to be handled.
+    pub(crate) fn connect_block(&mut self, header: &Header, height: u64) {
+        let block_hash = header.block_hash();
+        self.headers.insert(
+            block_hash,
+            CachedHeader {
+                block_hash,
+                prev_blockhash: header.prev_blockhash,
+                height,
+            },
+        );
+        self.best_tip = Some(block_hash);
+        self.headers
+            .retain(|_, x| x.height + NB_SAVED_BLOCK_HASHES as u64 > height);
+    }
+
+    /// Makes the cached block with the given hash the tip of the best chain,
+    /// keeping the headers of the disconnected blocks as a stale branch.
+    pub(crate) fn disconnect_to(&mut self, block_hash: &BlockHash) {
+        if self.headers.contains_key(block_hash) {
+            self.best_tip = Some(*block_hash);
+        }
+    }
+
+    /// Discards all the cached headers.
+    pub(crate) fn clear(&mut self) {
+        self.headers.clear();
+        self.best_tip = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::block::Version;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{CompactTarget, TxMerkleNode};
+
+    fn get_header(prev_blockhash: BlockHash, nonce: u32) -> Header {
+        Header {
+            version: Version::ONE,
+            prev_blockhash,
+            merkle_root: TxMerkleNode::all_zeros(),
+            time: 0,
+            bits: CompactTarget::from_consensus(0),
+            nonce,
+        }
+    }
+
+    #[test]
+    fn header_cache_keeps_concurrent_tips() {
+        let mut header_cache = HeaderCache::new();
+        let fork_point = get_header(BlockHash::all_zeros(), 0);
+        let stale = get_header(fork_point.block_hash(), 1);
+        let best = get_header(fork_point.block_hash(), 2);
+
+        header_cache.connect_block(&fork_point, 1);
+        header_cache.connect_block(&stale, 2);
+        header_cache.disconnect_to(&fork_point.block_hash());
+        header_cache.connect_block(&best, 2);
+
+        assert_eq!(
+            best.block_hash(),
+            header_cache.get_best_tip().unwrap().block_hash
+        );
+        assert_eq!(2, header_cache.get_tips().len());
+        assert_eq!(
+            vec![best.block_hash(), fork_point.block_hash()],
+            header_cache
+                .get_best_chain()
+                .iter()
+                .map(|x| x.block_hash)
+                .collect::<Vec<_>>()
+        );
+        let decoded: HeaderCache =
+            Readable::read(&mut lightning::io::Cursor::new(header_cache.encode())).unwrap();
+        assert_eq!(header_cache, decoded);
+    }
+
+    #[test]
+    fn header_cache_discards_old_headers() {
+        let mut header_cache = HeaderCache::new();
+        let mut prev_blockhash = BlockHash::all_zeros();
+        for height in 1..=10 {
+            let header = get_header(prev_blockhash, height as u32);
+            header_cache.connect_block(&header, height);
+            prev_blockhash = header.block_hash();
+        }
+
+        assert_eq!(NB_SAVED_BLOCK_HASHES, header_cache.get_best_chain().len());
+    }
+
+    #[test]
+    fn rewind_discards_blocks_above_fork_point() {
+        let mut chain_monitor = ChainMonitor::new(10);
+        for i in 0..3 {
+            chain_monitor.increment_height(&BlockHash::from_byte_array([i; 32]));
+        }
+
+        assert!(chain_monitor.rewind(11).is_empty());
+        assert_eq!(11, chain_monitor.last_height);
+        assert_eq!(
+            vec![BlockHash::from_byte_array([0; 32])],
+            chain_monitor.last_block_hashes
+        );
     }
 }
@@ -0,0 +1,215 @@
+//! #ChainWatcher
+//! A component punishing the broadcast of revoked channel transactions that
+//! only requires access to a [`Blockchain`] and a [`Storage`], so that it can
+//! run in its own thread or task independently of the calls made to
+//! [`crate::manager::Manager::periodic_check`].
+//!
+//! The watcher acts as a local watchtower: once registered as the
+//! [`WatchtowerClient`] of the manager, it stores the [`JusticeBlob`] produced
+//! for each revocation, and looks for the revoked transactions they refer to
+//! in each new block.
+
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use bitcoin::{Network, Transaction, Txid};
+use hex::DisplayHex;
+use log::{error, info};
+use secp256k1_zkp::{All, Secp256k1};
+
+use crate::channel::signed_channel::SignedChannelState;
+use crate::channel::watchtower::{get_locator, JusticeBlob, JusticeData, WatchtowerClient};
+use crate::channel::Channel;
+use crate::error::Error;
+use crate::{Blockchain, Storage};
+
+/// Scans the blockchain for revoked channel transactions, broadcasting the
+/// transactions punishing them.
+pub struct ChainWatcher<B: Deref, S: Deref>
+where
+    B::Target: Blockchain,
+    S::Target: Storage,
+{
+    secp: Secp256k1<All>,
+    blockchain: B,
+    store: S,
+    fee_rate_per_vb: u64,
+    last_height: Mutex<u64>,
+}
+
+impl<B: Deref, S: Deref> ChainWatcher<B, S>
+where
+    B::Target: Blockchain,
+    S::Target: Storage,
+{
+    /// Creates a new [`ChainWatcher`] scanning the blocks following
+    /// `last_height` and paying the given fee rate for punishment
+    /// transactions. The height of the last scanned block is not persisted, so
+    /// on restart `last_height` should be at most the height returned by
+    /// [`ChainWatcher::get_last_height`] before stopping.
+    pub fn new(blockchain: B, store: S, last_height: u64, fee_rate_per_vb: u64) -> Self {
+        ChainWatcher {
+            secp: Secp256k1::new(),
+            blockchain,
+            store,
+            fee_rate_per_vb,
+            last_height: Mutex::new(last_height),
+        }
+    }
+
+    /// Returns the height of the last block that was scanned.
+    pub fn get_last_height(&self) -> u64 {
+        *self.last_height.lock().unwrap()
+    }
+
+    /// Scans the blocks mined since the last call, broadcasting a punishment
+    /// transaction for each revoked transaction found in them. Returns the
+    /// ids of the broadcast punishment transactions.
+    pub fn check_for_revoked_transactions(&self) -> Result<Vec<Txid>, Error> {
+        let mut last_height = self.last_height.lock().unwrap();
+        let cur_height = self.blockchain.get_blockchain_height()?;
+        if cur_height <= *last_height {
+            return Ok(Vec::new());
+        }
+
+        let network = self.blockchain.get_network()?;
+        let mut punished = Vec::new();
+        for height in *last_height + 1..=cur_height {
+            let block = self.blockchain.get_block_at_height(height)?;
+            for tx in &block.txdata {
+                if let Some(punishment_txid) = self.process_tx(tx, network)? {
+                    punished.push(punishment_txid);
+                }
+            }
+            *last_height = height;
+        }
+
+        Ok(punished)
+    }
+
+    /// Calls [`ChainWatcher::check_for_revoked_transactions`] every
+    /// `poll_interval` until `stop` is set, logging the errors encountered.
+    /// Meant to be run in a dedicated thread.
+    pub fn run(&self, poll_interval: Duration, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            if let Err(e) = self.check_for_revoked_transactions() {
+                error!("Error checking for revoked transactions: {}", e);
+            }
+            std::thread::sleep(poll_interval);
+        }
+    }
+
+    fn process_tx(&self, tx: &Transaction, network: Network) -> Result<Option<Txid>, Error> {
+        let txid = tx.txid();
+        let blob = match self.store.get_justice_blob(&get_locator(&txid))? {
+            Some(blob) => blob,
+            None => return Ok(None),
+        };
+        // Locators are only a prefix of the txid, a blob failing to decrypt
+        // refers to another transaction.
+        let justice_data = match blob.decrypt(&txid) {
+            Ok(justice_data) => justice_data,
+            Err(_) => return Ok(None),
+        };
+
+        let mut signed_channel = match self.store.get_channel(&justice_data.channel_id)? {
+            Some(Channel::Signed(signed_channel)) => signed_channel,
+            _ => {
+                error!(
+                    "Could not retrieve channel {} of revoked transaction {}",
+                    justice_data.channel_id.to_lower_hex_string(),
+                    txid
+                );
+                return Ok(None);
+            }
+        };
+        if let SignedChannelState::ClosedPunished { .. } = signed_channel.state {
+            return Ok(None);
+        }
+
+        let punishment_tx = broadcast_justice_transaction(
+            &self.secp,
+            &self.blockchain,
+            &justice_data,
+            tx,
+            network,
+            self.fee_rate_per_vb,
+        )?;
+        info!(
+            "Punished revoked transaction {} of channel {} with transaction {}",
+            txid,
+            justice_data.channel_id.to_lower_hex_string(),
+            punishment_tx.txid()
+        );
+
+        signed_channel.state = SignedChannelState::ClosedPunished {
+            punishment_txid: punishment_tx.txid(),
+        };
+        self.store
+            .upsert_channel(Channel::Signed(signed_channel), None)?;
+
+        Ok(Some(punishment_tx.txid()))
+    }
+}
+
+impl<B: Deref, S: Deref> WatchtowerClient for ChainWatcher<B, S>
+where
+    B: Send + Sync,
+    S: Send + Sync,
+    B::Target: Blockchain,
+    S::Target: Storage,
+{
+    fn on_revocation(&self, blob: &JusticeBlob) -> Result<(), Error> {
+        self.store.upsert_justice_blob(blob)
+    }
+}
+
+/// Creates, signs and broadcasts the transaction punishing the given revoked
+/// transaction.
+pub(crate) fn broadcast_justice_transaction<B: Deref>(
+    secp: &Secp256k1<All>,
+    blockchain: &B,
+    justice_data: &JusticeData,
+    revoked_tx: &Transaction,
+    network: Network,
+    fee_rate_per_vb: u64,
+) -> Result<Transaction, Error>
+where
+    B::Target: Blockchain,
+{
+    let punishment_tx =
+        justice_data.create_justice_transaction(secp, revoked_tx, network, fee_rate_per_vb)?;
+    blockchain.send_transaction(&punishment_tx)?;
+    Ok(punishment_tx)
+}
+
+#[cfg(test)]
+mod test {
+    use mocks::dlc_manager::chain_watcher::ChainWatcher;
+    use mocks::dlc_manager::channel::watchtower::{JusticeBlob, WatchtowerClient, LOCATOR_LEN};
+    use mocks::dlc_manager::Storage;
+    use mocks::memory_storage_provider::MemoryStorage;
+    use mocks::mock_blockchain::MockBlockchain;
+    use std::sync::Arc;
+
+    #[test]
+    fn revocations_are_stored_and_scanned_blocks_skipped() {
+        let store = Arc::new(MemoryStorage::new());
+        let watcher = ChainWatcher::new(Arc::new(MockBlockchain::new()), store.clone(), 10, 2);
+        let blob = JusticeBlob {
+            locator: [1; LOCATOR_LEN],
+            encrypted_data: vec![2; 64],
+        };
+
+        watcher.on_revocation(&blob).unwrap();
+
+        assert_eq!(
+            Some(blob),
+            store.get_justice_blob(&[1; LOCATOR_LEN]).unwrap()
+        );
+        assert!(watcher.check_for_revoked_transactions().unwrap().is_empty());
+        assert_eq!(10, watcher.get_last_height());
+    }
+}
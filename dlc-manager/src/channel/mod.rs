@@ -3,7 +3,8 @@
 use dlc_messages::channel::{AcceptChannel, SignChannel};
 use secp256k1_zkp::PublicKey;
 
-use crate::ChannelId;
+use crate::error::Error;
+use crate::{ChannelId, ContractId};
 
 use self::{
     accepted_channel::AcceptedChannel, offered_channel::OfferedChannel,
@@ -13,12 +14,142 @@ use self::{
 pub mod accepted_channel;
 pub mod offered_channel;
 pub mod party_points;
+pub mod punishment;
 pub mod ser;
 pub mod signed_channel;
 mod utils;
+pub mod watchtower;
+
+/// Status of the quiescence negotiation for a channel, used to ensure that
+/// both parties agree on which one of them performs the next channel update.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Quiescence {
+    /// The local party sent a [`dlc_messages::channel::Stop`] message and is
+    /// waiting for the counter party to reply.
+    StopSent,
+    /// Both parties sent a [`dlc_messages::channel::Stop`] message and no
+    /// update other than the one started by the initiator can be made.
+    Quiescent {
+        /// Whether the local party is the one allowed to start the next update.
+        is_initiator: bool,
+    },
+}
+
+/// Limits enforced on the updates of DLC channels offered by counter parties,
+/// so that they do not result in transactions that cannot be broadcast or in
+/// the local party locking more funds than it is willing to. Updates that
+/// violate the policy are rejected. The default policy does not enforce any
+/// limit.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChannelPolicy {
+    /// The minimum balance that each party must keep in the channel, outside
+    /// of the contracts set up in it.
+    pub min_reserve: u64,
+    /// The value below which a balance output of a settle or buffer
+    /// transaction is considered dust. Balances that are neither zero nor
+    /// above this limit are rejected, as their output would be discarded.
+    pub dust_limit: u64,
+    /// The maximum total collateral of the contracts set up in the channel.
+    pub max_contracts_value: Option<u64>,
+}
+
+/// The maximum relative locktime, in blocks, that can be encoded in the
+/// nSequence field of a transaction input.
+const MAX_CET_NSEQUENCE: u32 = 0xFFFF;
+
+/// Timing and trust parameters of a DLC channel, proposed by the offer party
+/// in the [`dlc_messages::channel::OfferChannel`] message and persisted with
+/// the channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct ChannelConfig {
+    /// The relative locktime, in blocks, of the outputs of the buffer and
+    /// settle transactions, during which the broadcast of a revoked one can be
+    /// punished.
+    pub cet_nsequence: u32,
+    /// The time, in seconds, given to the counter party to answer a channel
+    /// update before the channel is force closed.
+    pub peer_timeout: u64,
+    /// Whether the channel can be updated before its fund transaction is
+    /// confirmed, trusting the counter party not to double spend its inputs.
+    /// Both parties need to enable it for a channel to be zero-conf.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub zero_conf: bool,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        ChannelConfig {
+            cet_nsequence: crate::manager::CET_NSEQUENCE,
+            peer_timeout: crate::manager::PEER_TIMEOUT,
+            zero_conf: false,
+        }
+    }
+}
+
+impl ChannelConfig {
+    /// Checks that the relative locktime is a strictly positive number of
+    /// blocks that can be encoded in an nSequence, and that the peer timeout
+    /// is strictly positive.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.cet_nsequence == 0 || self.cet_nsequence > MAX_CET_NSEQUENCE {
+            return Err(Error::InvalidParameters(format!(
+                "CET nSequence must be between 1 and {} blocks, got {}.",
+                MAX_CET_NSEQUENCE, self.cet_nsequence
+            )));
+        }
+        if self.peer_timeout == 0 {
+            return Err(Error::InvalidParameters(
+                "Peer timeout must be strictly positive.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that the configuration offered by the counter party is valid
+    /// and acceptable given the local one, which requires each of its timing
+    /// values to be at least the local one and at most twice it, and zero-conf
+    /// to be enabled locally if it is offered.
+    pub fn check_offered(&self, offered: &ChannelConfig) -> Result<(), Error> {
+        offered.validate()?;
+        if offered.zero_conf && !self.zero_conf {
+            return Err(Error::InvalidParameters(
+                "Offered zero-conf channel but zero-conf is not enabled.".to_string(),
+            ));
+        }
+        let cet_nsequence_range = self.cet_nsequence..=self.cet_nsequence.saturating_mul(2);
+        if !cet_nsequence_range.contains(&offered.cet_nsequence) {
+            return Err(Error::InvalidParameters(format!(
+                "Offered CET nSequence {} is not between {} and {}.",
+                offered.cet_nsequence,
+                cet_nsequence_range.start(),
+                cet_nsequence_range.end()
+            )));
+        }
+        let peer_timeout_range = self.peer_timeout..=self.peer_timeout.saturating_mul(2);
+        if !peer_timeout_range.contains(&offered.peer_timeout) {
+            return Err(Error::InvalidParameters(format!(
+                "Offered peer timeout {} is not between {} and {}.",
+                offered.peer_timeout,
+                peer_timeout_range.start(),
+                peer_timeout_range.end()
+            )));
+        }
+        Ok(())
+    }
+}
 
 /// Enumeration containing the possible state a DLC channel can be in.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 #[allow(clippy::large_enum_variant)]
 pub enum Channel {
     /// A channel that has been offered.
@@ -52,6 +183,19 @@ impl std::fmt::Debug for Channel {
 }
 
 impl Channel {
+    /// Returns a human readable name for the state of the channel, including
+    /// the state of signed channels.
+    pub fn get_state_name(&self) -> String {
+        match self {
+            Channel::Offered(_) => "offered".to_string(),
+            Channel::Accepted(_) => "accepted".to_string(),
+            Channel::Signed(s) => s.get_state_name(),
+            Channel::FailedAccept(_) => "failed accept".to_string(),
+            Channel::FailedSign(_) => "failed sign".to_string(),
+            Channel::Cancelled(_) => "cancelled".to_string(),
+        }
+    }
+
     /// Returns the public key of the counter party's node.
     pub fn get_counter_party_id(&self) -> PublicKey {
         match self {
@@ -63,11 +207,27 @@ impl Channel {
             Channel::Cancelled(o) => o.counter_party,
         }
     }
+
+    /// Returns the id of the contract currently associated with the channel,
+    /// if any.
+    pub fn get_contract_id(&self) -> Option<ContractId> {
+        match self {
+            Channel::Offered(o) | Channel::Cancelled(o) => Some(o.offered_contract_id),
+            Channel::Accepted(a) => Some(a.accepted_contract_id),
+            Channel::Signed(s) => s.get_contract_id(),
+            Channel::FailedAccept(_) | Channel::FailedSign(_) => None,
+        }
+    }
 }
 
 /// A channel that failed when validating an
 /// [`dlc_messages::channel::AcceptChannel`] message.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct FailedAccept {
     /// The [`secp256k1_zkp::PublicKey`] of the counter party.
     pub counter_party: PublicKey,
@@ -83,6 +243,11 @@ pub struct FailedAccept {
 /// A channel that failed when validating an
 /// [`dlc_messages::channel::SignChannel`] message.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct FailedSign {
     /// The [`secp256k1_zkp::PublicKey`] of the counter party.
     pub counter_party: PublicKey,
@@ -95,6 +260,46 @@ pub struct FailedSign {
     pub sign_message: SignChannel,
 }
 
+/// An entry of the append-only event log of a channel, recording the state
+/// the channel was moved to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct ChannelEvent {
+    /// The name of the state the channel was moved to, as returned by
+    /// [`Channel::get_state_name`].
+    pub state: String,
+    /// The time at which the event happened, in seconds since the unix epoch.
+    pub timestamp: u64,
+    /// The message or operation that triggered the event.
+    pub trigger: String,
+}
+
+impl ChannelEvent {
+    /// Returns the event to record when a channel previously in the state
+    /// named `previous` (`None` if the channel is being created) is moved to
+    /// the state of `channel`, or `None` if the state did not change.
+    pub fn from_update(
+        previous: Option<&str>,
+        channel: &Channel,
+        timestamp: u64,
+        trigger: &str,
+    ) -> Option<ChannelEvent> {
+        let state = channel.get_state_name();
+        if previous == Some(state.as_str()) {
+            return None;
+        }
+        Some(ChannelEvent {
+            state,
+            timestamp,
+            trigger: trigger.to_string(),
+        })
+    }
+}
+
 impl Channel {
     /// Returns the temporary [`crate::ChannelId`] for the channel.
     pub fn get_temporary_id(&self) -> ChannelId {
@@ -108,6 +313,15 @@ impl Channel {
         }
     }
 
+    /// Returns the id under which the events of the channel are logged: its
+    /// temporary id, or its id if the temporary one is not known.
+    pub fn get_history_id(&self) -> ChannelId {
+        match self {
+            Channel::FailedSign(f) => f.channel_id,
+            _ => self.get_temporary_id(),
+        }
+    }
+
     /// Returns the [`crate::ChannelId`] for the channel.
     pub fn get_id(&self) -> ChannelId {
         match self {
@@ -120,3 +334,52 @@ impl Channel {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ChannelConfig;
+
+    #[test]
+    fn offered_config_outside_local_bounds_is_rejected() {
+        let local = ChannelConfig::default();
+        let offered = |cet_nsequence, peer_timeout| ChannelConfig {
+            cet_nsequence,
+            peer_timeout,
+            zero_conf: false,
+        };
+
+        local
+            .check_offered(&local)
+            .expect("identical config to be accepted");
+        local
+            .check_offered(&offered(local.cet_nsequence * 2, local.peer_timeout))
+            .expect("twice the local delay to be accepted");
+        local
+            .check_offered(&offered(local.cet_nsequence - 1, local.peer_timeout))
+            .expect_err("shorter delay to be rejected");
+        local
+            .check_offered(&offered(local.cet_nsequence, local.peer_timeout * 2 + 1))
+            .expect_err("longer timeout to be rejected");
+        local
+            .check_offered(&offered(0x10000, local.peer_timeout))
+            .expect_err("delay not fitting in an nSequence to be rejected");
+    }
+
+    #[test]
+    fn zero_conf_is_only_accepted_if_enabled_locally() {
+        let zero_conf = ChannelConfig {
+            zero_conf: true,
+            ..Default::default()
+        };
+
+        ChannelConfig::default()
+            .check_offered(&zero_conf)
+            .expect_err("zero-conf to be rejected");
+        zero_conf
+            .check_offered(&zero_conf)
+            .expect("zero-conf to be accepted");
+        zero_conf
+            .check_offered(&ChannelConfig::default())
+            .expect("regular channel to be accepted");
+    }
+}
@@ -1,8 +1,8 @@
 //! # A channel is offered when an offer was made or received. This module contains
 //! the model for it and method for working with it.
 
-use dlc::PartyParams;
-use dlc_messages::channel::OfferChannel;
+use dlc::{FeeSplit, PartyParams};
+use dlc_messages::{channel::OfferChannel, ZERO_CONF_CHANNEL_FLAG};
 // use dlc_messages::channel::OfferChannel;
 use secp256k1_zkp::PublicKey;
 
@@ -11,7 +11,7 @@ use crate::{
     error::Error, ChannelId, ContractId, KeysId,
 };
 
-use super::party_points::PartyBasePoints;
+use super::{party_points::PartyBasePoints, ChannelConfig};
 
 #[derive(Clone, Debug)]
 #[cfg_attr(
@@ -41,14 +41,33 @@ pub struct OfferedChannel {
     pub counter_party: PublicKey,
     /// The nSequence value to use for the CETs.
     pub cet_nsequence: u32,
+    /// The time, in seconds, given to each party to answer a channel update.
+    pub peer_timeout: u64,
+    /// Whether the channel can be updated before its fund transaction is
+    /// confirmed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub zero_conf: bool,
 }
 
 impl OfferedChannel {
+    /// Returns the [`ChannelConfig`] offered for the channel.
+    pub fn get_config(&self) -> ChannelConfig {
+        ChannelConfig {
+            cet_nsequence: self.cet_nsequence,
+            peer_timeout: self.peer_timeout,
+            zero_conf: self.zero_conf,
+        }
+    }
+
     pub(crate) fn get_offer_channel_msg(&self, offered_contract: &OfferedContract) -> OfferChannel {
         let party_points = &self.party_points;
         OfferChannel {
             protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
-            contract_flags: 0,
+            contract_flags: if self.zero_conf {
+                ZERO_CONF_CHANNEL_FLAG
+            } else {
+                0
+            },
             chain_hash: crate::conversion_utils::BITCOIN_CHAINHASH,
             temporary_contract_id: offered_contract.id,
             temporary_channel_id: self.temporary_channel_id,
@@ -68,7 +87,9 @@ impl OfferedChannel {
             refund_locktime: offered_contract.refund_locktime,
             fee_rate_per_vb: offered_contract.fee_rate_per_vb,
             fund_output_serial_id: offered_contract.fund_output_serial_id,
-            cet_nsequence: crate::manager::CET_NSEQUENCE,
+            cet_nsequence: self.cet_nsequence,
+            timestamp: None,
+            peer_timeout: Some(self.peer_timeout),
         }
     }
 
@@ -94,6 +115,10 @@ impl OfferedChannel {
             is_offer_party: false,
             counter_party,
             cet_nsequence: offer_channel.cet_nsequence,
+            peer_timeout: offer_channel
+                .peer_timeout
+                .unwrap_or(crate::manager::PEER_TIMEOUT),
+            zero_conf: offer_channel.contract_flags & ZERO_CONF_CHANNEL_FLAG != 0,
         };
 
         let (inputs, input_amount) = get_tx_input_infos(&offer_channel.funding_inputs)?;
@@ -122,6 +147,11 @@ impl OfferedChannel {
             funding_inputs: offer_channel.funding_inputs.clone(),
             total_collateral: offer_channel.contract_info.get_total_collateral(),
             keys_id,
+            anchor_outputs: false,
+            fee_split: FeeSplit::Equal,
+            expiry: None,
+            metadata: None,
+            taproot: false,
         };
 
         Ok((channel, contract))
@@ -0,0 +1,29 @@
+//! # Data required to punish the broadcast of revoked channel states, stored
+//! separately from the channel so that it can be retrieved without
+//! deserializing the whole channel.
+
+use bitcoin::Txid;
+use secp256k1_zkp::SecretKey;
+
+use crate::ChannelId;
+
+/// The data revealed by the counter party when revoking a state of a channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PunishmentData {
+    /// The id of the channel the revoked state belongs to.
+    pub channel_id: ChannelId,
+    /// The update index of the revoked state.
+    pub update_idx: u64,
+    /// The per update secret revealed by the counter party for the revoked
+    /// state.
+    pub counter_per_update_secret: SecretKey,
+    /// The id of the buffer or settle transaction of the revoked state.
+    pub revoked_txid: Txid,
+}
+
+impl_dlc_writeable!(PunishmentData, {
+    (channel_id, writeable),
+    (update_idx, writeable),
+    (counter_per_update_secret, writeable),
+    (revoked_txid, writeable)
+});
@@ -3,7 +3,7 @@ use super::accepted_channel::AcceptedChannel;
 use super::offered_channel::OfferedChannel;
 use super::party_points::PartyBasePoints;
 use super::signed_channel::{SignedChannel, SignedChannelState};
-use super::{FailedAccept, FailedSign};
+use super::{ChannelConfig, ChannelEvent, FailedAccept, FailedSign};
 
 use dlc_messages::ser_impls::{
     read_ecdsa_adaptor_signature, read_string, write_ecdsa_adaptor_signature, write_string,
@@ -12,7 +12,9 @@ use lightning::ln::msgs::DecodeError;
 use lightning::util::ser::{Readable, Writeable, Writer};
 
 impl_dlc_writeable!(PartyBasePoints, { (own_basepoint, writeable), (publish_basepoint, writeable), (revocation_basepoint, writeable) });
-impl_dlc_writeable!(OfferedChannel, { (offered_contract_id, writeable), (temporary_channel_id, writeable), (party_points, writeable), (per_update_point, writeable), (offer_per_update_seed, writeable), (is_offer_party, writeable), (counter_party, writeable), (cet_nsequence, writeable) });
+impl_dlc_writeable!(ChannelEvent, { (state, string), (timestamp, writeable), (trigger, string) });
+impl_dlc_writeable!(ChannelConfig, { (cet_nsequence, writeable), (peer_timeout, writeable), (zero_conf, writeable) });
+impl_dlc_writeable!(OfferedChannel, { (offered_contract_id, writeable), (temporary_channel_id, writeable), (party_points, writeable), (per_update_point, writeable), (offer_per_update_seed, writeable), (is_offer_party, writeable), (counter_party, writeable), (cet_nsequence, writeable), (peer_timeout, {cb_writeable, Writeable::write, read_peer_timeout}), (zero_conf, {cb_writeable, Writeable::write, read_bool_or_false}) });
 impl_dlc_writeable!(AcceptedChannel, {
     (accepted_contract_id, writeable),
     (offer_base_points, writeable),
@@ -25,7 +27,8 @@ impl_dlc_writeable!(AcceptedChannel, {
     (channel_id, writeable),
     (accept_per_update_seed, writeable),
     (accept_buffer_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}),
-    (counter_party, writeable)
+    (counter_party, writeable),
+    (config, {cb_writeable, Writeable::write, read_channel_config})
 });
 impl_dlc_writeable!(SignedChannel, {
     (channel_id, writeable),
@@ -45,7 +48,9 @@ impl_dlc_writeable!(SignedChannel, {
     (roll_back_state, option),
     (own_per_update_seed, writeable),
     (counter_party_commitment_secrets, writeable),
-    (fee_rate_per_vb, writeable)
+    (fee_rate_per_vb, writeable),
+    (config, {cb_writeable, Writeable::write, read_channel_config}),
+    (unconfirmed_funding, {cb_writeable, Writeable::write, read_bool_or_false})
 });
 
 impl_dlc_writeable_enum!(
@@ -61,9 +66,41 @@ impl_dlc_writeable_enum!(
     (8, RenewConfirmed, {(contract_id, writeable), (offer_per_update_point, writeable), (accept_per_update_point, writeable), (buffer_transaction, writeable), (buffer_script_pubkey, writeable), (offer_buffer_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}), (accept_buffer_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}), (timeout, writeable), (own_payout, writeable), (keys_id, writeable)}),
     (9, Closing, {(buffer_transaction, writeable), (signed_cet, writeable), (contract_id, writeable), (attestations, vec), (keys_id, writeable)}),
     (10, ClosedPunished, { (punishment_txid, writeable) }),
-    (11, CollaborativeCloseOffered, { (counter_payout, writeable), (offer_signature, writeable), (close_tx, writeable), (timeout, writeable), (keys_id, writeable) })
+    (11, CollaborativeCloseOffered, { (counter_payout, writeable), (offer_signature, writeable), (close_tx, writeable), (timeout, writeable), (keys_id, writeable) }),
+    (15, ContractsEstablished, {(contract_ids, vec), (own_balance, writeable), (counter_balance, writeable), (own_buffer_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}), (counter_buffer_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}), (buffer_transaction, writeable), (is_offer, writeable), (keys_id, writeable)}),
+    (16, ContractUpdateOffered, {(contract_ids, vec), (added_contract_id, option), (settled_contract_id, option), (own_balance, writeable), (counter_balance, writeable), (offer_next_per_update_point, writeable), (is_offer, writeable), (timeout, writeable), (keys_id, writeable)}),
+    (17, ContractUpdateAccepted, {(contract_ids, vec), (added_contract_id, option), (settled_contract_id, option), (own_balance, writeable), (counter_balance, writeable), (offer_per_update_point, writeable), (accept_per_update_point, writeable), (buffer_transaction, writeable), (buffer_script_pubkey, writeable), (accept_buffer_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}), (timeout, writeable), (keys_id, writeable)}),
+    (18, ContractUpdateConfirmed, {(contract_ids, vec), (added_contract_id, option), (settled_contract_id, option), (own_balance, writeable), (counter_balance, writeable), (offer_per_update_point, writeable), (accept_per_update_point, writeable), (buffer_transaction, writeable), (buffer_script_pubkey, writeable), (offer_buffer_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}), (accept_buffer_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}), (timeout, writeable), (keys_id, writeable)}),
+    (19, ContractsClosing, {(buffer_transaction, writeable), (contract_ids, vec), (is_offer, writeable), (keys_id, writeable)}),
+    (20, FundingDoubleSpent, { (double_spend_txid, writeable) })
     ;;(12, Closed), (13, CounterClosed), (14, CollaborativelyClosed)
 );
 
 impl_dlc_writeable!(FailedAccept, {(temporary_channel_id, writeable), (error_message, {cb_writeable, write_string, read_string}), (accept_message, writeable), (counter_party, writeable)});
 impl_dlc_writeable!(FailedSign, {(channel_id, writeable), (error_message, {cb_writeable, write_string, read_string}), (sign_message, writeable), (counter_party, writeable)});
+
+/// Reads a field that was appended to the serialization of a channel,
+/// returning the given default value for channels serialized before it was.
+fn read_appended_field<R: lightning::io::Read, T: Readable>(
+    reader: &mut R,
+    default: T,
+) -> Result<T, DecodeError> {
+    match T::read(reader) {
+        Err(DecodeError::ShortRead) => Ok(default),
+        res => res,
+    }
+}
+
+fn read_peer_timeout<R: lightning::io::Read>(reader: &mut R) -> Result<u64, DecodeError> {
+    read_appended_field(reader, crate::manager::PEER_TIMEOUT)
+}
+
+fn read_channel_config<R: lightning::io::Read>(
+    reader: &mut R,
+) -> Result<ChannelConfig, DecodeError> {
+    read_appended_field(reader, ChannelConfig::default())
+}
+
+fn read_bool_or_false<R: lightning::io::Read>(reader: &mut R) -> Result<bool, DecodeError> {
+    read_appended_field(reader, false)
+}
@@ -10,7 +10,7 @@ use secp256k1_zkp::{ecdsa::Signature, EcdsaAdaptorSignature, PublicKey};
 
 use crate::{ChannelId, ContractId, KeysId};
 
-use super::party_points::PartyBasePoints;
+use super::{party_points::PartyBasePoints, ChannelConfig};
 
 macro_rules! typed_enum {
     (
@@ -89,6 +89,11 @@ macro_rules! typed_enum {
 
 typed_enum!(
     #[derive(Eq, PartialEq, Clone, Debug)]
+    #[cfg_attr(
+        feature = "serde",
+        derive(serde::Serialize, serde::Deserialize),
+        serde(rename_all = "camelCase")
+    )]
     /// Contains the possible states in which a [`SignedChannel`] can be.
     pub enum SignedChannelState {
         /// A [`SignedChannel`] is in `Established` state when a contract is fully
@@ -319,6 +324,163 @@ typed_enum!(
         /// A [`SignedChannel`] is in `CollaborativelyClosed` state when it was
         /// collaboratively closed.
         CollaborativelyClosed,
+        /// A [`SignedChannel`] is in `ContractsEstablished` state when one or
+        /// more contracts are setup inside the channel, each of them locking
+        /// part of the channel collateral in an output of the buffer
+        /// transaction.
+        ContractsEstablished {
+            /// The [`crate::ContractId`] of the contracts currently setup in the
+            /// channel, in the order of the buffer transaction outputs locking
+            /// them.
+            contract_ids: Vec<ContractId>,
+            /// The part of the collateral of the local party that is not locked
+            /// in any contract.
+            own_balance: u64,
+            /// The part of the collateral of the counter party that is not
+            /// locked in any contract.
+            counter_balance: u64,
+            /// The adaptor signature created by the counter party for the buffer
+            /// transaction.
+            counter_buffer_adaptor_signature: EcdsaAdaptorSignature,
+            /// The adaptor signature created by the local party for the buffer
+            /// transaction.
+            own_buffer_adaptor_signature: EcdsaAdaptorSignature,
+            /// The buffer transaction for the current channel state.
+            buffer_transaction: Transaction,
+            /// Whether the local party is the one that initiated the latest channel
+            /// state change.
+            is_offer: bool,
+            /// Keys Id for generating the signers
+            keys_id: KeysId,
+        },
+        /// A [`SignedChannel`] is in `ContractUpdateOffered` state when the local
+        /// party has sent or received a
+        /// [`dlc_messages::channel::AddContractOffer`] or
+        /// [`dlc_messages::channel::SettleContractOffer`] message.
+        ContractUpdateOffered {
+            /// The [`crate::ContractId`] of the contracts that remain setup in
+            /// the channel after the update.
+            contract_ids: Vec<ContractId>,
+            /// The temporary [`crate::ContractId`] of the contract offered to be
+            /// added to the channel, if any.
+            added_contract_id: Option<ContractId>,
+            /// The [`crate::ContractId`] of the contract offered to be settled,
+            /// if any.
+            settled_contract_id: Option<ContractId>,
+            /// The balance of the local party after the update.
+            own_balance: u64,
+            /// The balance of the counter party after the update.
+            counter_balance: u64,
+            /// The per update point to be used by the offer party for the setup
+            /// of the next channel state.
+            offer_next_per_update_point: PublicKey,
+            /// Indicates whether the local party offered the update or not.
+            is_offer: bool,
+            /// The UNIX epoch at which the counter party will be considered
+            /// unresponsive and the channel will be forced closed.
+            timeout: u64,
+            /// Keys Id for generating the signers
+            keys_id: KeysId,
+        },
+        /// A [`SignedChannel`] is in `ContractUpdateAccepted` state when the local
+        /// party has sent a [`dlc_messages::channel::ContractUpdateAccept`]
+        /// message.
+        ContractUpdateAccepted {
+            /// The [`crate::ContractId`] of the contracts that remain setup in
+            /// the channel after the update.
+            contract_ids: Vec<ContractId>,
+            /// The temporary [`crate::ContractId`] of the contract being added
+            /// to the channel, if any.
+            added_contract_id: Option<ContractId>,
+            /// The [`crate::ContractId`] of the contract being settled, if any.
+            settled_contract_id: Option<ContractId>,
+            /// The balance of the local party after the update.
+            own_balance: u64,
+            /// The balance of the counter party after the update.
+            counter_balance: u64,
+            /// The per update point to be used by the offer party for the setup
+            /// of the next channel state.
+            offer_per_update_point: PublicKey,
+            /// The per update point to be used by the accept party for the setup
+            /// of the next channel state.
+            accept_per_update_point: PublicKey,
+            /// The buffer transaction.
+            buffer_transaction: Transaction,
+            /// The script pubkey of the buffer transaction outputs locking the
+            /// contracts.
+            buffer_script_pubkey: ScriptBuf,
+            /// The adaptor signature for the buffer transaction generated by
+            /// the accept party.
+            accept_buffer_adaptor_signature: EcdsaAdaptorSignature,
+            /// The UNIX epoch at which the counter party will be considered
+            /// unresponsive and the channel will be forced closed.
+            timeout: u64,
+            /// Keys Id for generating the signers
+            keys_id: KeysId,
+        },
+        /// A [`SignedChannel`] is in `ContractUpdateConfirmed` state when the
+        /// local party has sent a [`dlc_messages::channel::ContractUpdateConfirm`]
+        /// message.
+        ContractUpdateConfirmed {
+            /// The [`crate::ContractId`] of the contracts that remain setup in
+            /// the channel after the update.
+            contract_ids: Vec<ContractId>,
+            /// The temporary [`crate::ContractId`] of the contract being added
+            /// to the channel, if any.
+            added_contract_id: Option<ContractId>,
+            /// The [`crate::ContractId`] of the contract being settled, if any.
+            settled_contract_id: Option<ContractId>,
+            /// The balance of the local party after the update.
+            own_balance: u64,
+            /// The balance of the counter party after the update.
+            counter_balance: u64,
+            /// The per update point to be used by the offer party for the setup
+            /// of the next channel state.
+            offer_per_update_point: PublicKey,
+            /// The per update point to be used by the accept party for the setup
+            /// of the next channel state.
+            accept_per_update_point: PublicKey,
+            /// The buffer transaction.
+            buffer_transaction: Transaction,
+            /// The script pubkey of the buffer transaction outputs locking the
+            /// contracts.
+            buffer_script_pubkey: ScriptBuf,
+            /// The adaptor signature for the buffer transaction generated by
+            /// the offer party.
+            offer_buffer_adaptor_signature: EcdsaAdaptorSignature,
+            /// The adaptor signature for the buffer transaction generated by
+            /// the accept party.
+            accept_buffer_adaptor_signature: EcdsaAdaptorSignature,
+            /// The UNIX epoch at which the counter party will be considered
+            /// unresponsive and the channel will be forced closed.
+            timeout: u64,
+            /// Keys Id for generating the signers
+            keys_id: KeysId,
+        },
+        /// A [`SignedChannel`] is in `ContractsClosing` state when the buffer
+        /// transaction of a channel in `ContractsEstablished` state was
+        /// broadcast, and the CETs of its contracts are broadcast as their
+        /// outcomes get attested.
+        ContractsClosing {
+            /// The buffer transaction that was broadcast.
+            buffer_transaction: Transaction,
+            /// The [`crate::ContractId`] of the contracts whose CET was not yet
+            /// broadcast.
+            contract_ids: Vec<ContractId>,
+            /// Whether the local party was the offer party of the buffer
+            /// transaction.
+            is_offer: bool,
+            /// Keys Id for generating the signers
+            keys_id: KeysId,
+        },
+        /// A [`SignedChannel`] is in `FundingDoubleSpent` state when it was
+        /// used before the confirmation of its fund transaction, and a
+        /// transaction spending the same inputs got confirmed instead.
+        FundingDoubleSpent {
+            /// The id of the transaction that double spent the fund
+            /// transaction.
+            double_spend_txid: Txid,
+        },
     },
     /// Enum automatically generated associating a number to each signed channel
     /// state.
@@ -326,6 +488,12 @@ typed_enum!(
 );
 
 impl SignedChannel {
+    /// Returns a human readable name for the state of the channel, as returned
+    /// by [`super::Channel::get_state_name`].
+    pub fn get_state_name(&self) -> String {
+        format!("signed ({})", self.state)
+    }
+
     /// Returns the contract id associated with the channel if in a state where
     /// a contract is established or under establishment.
     pub fn get_contract_id(&self) -> Option<ContractId> {
@@ -344,6 +512,31 @@ impl SignedChannel {
         }
     }
 
+    /// Returns the ids of the contracts setup in the channel if in a state
+    /// where one or more contracts are established alongside each other, or
+    /// are being updated.
+    pub fn get_contract_ids(&self) -> Vec<ContractId> {
+        match &self.state {
+            SignedChannelState::ContractsEstablished { contract_ids, .. }
+            | SignedChannelState::ContractUpdateOffered { contract_ids, .. }
+            | SignedChannelState::ContractUpdateAccepted { contract_ids, .. }
+            | SignedChannelState::ContractUpdateConfirmed { contract_ids, .. }
+            | SignedChannelState::ContractsClosing { contract_ids, .. } => contract_ids.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns whether the channel is in a state in which no update is in
+    /// progress (either `Established`, `ContractsEstablished` or `Settled`).
+    pub fn is_in_stable_state(&self) -> bool {
+        matches!(
+            self.state,
+            SignedChannelState::Established { .. }
+                | SignedChannelState::ContractsEstablished { .. }
+                | SignedChannelState::Settled { .. }
+        )
+    }
+
     /// Returns the contract's [`keys_id`] if it has one available.
     /// This is used to derive keys
     pub fn keys_id(&self) -> Option<KeysId> {
@@ -363,12 +556,23 @@ impl SignedChannel {
             SignedChannelState::ClosedPunished { .. } => None,
             SignedChannelState::CollaborativeCloseOffered { keys_id, .. } => Some(*keys_id),
             SignedChannelState::CollaborativelyClosed => None,
+            SignedChannelState::ContractsEstablished { keys_id, .. } => Some(*keys_id),
+            SignedChannelState::ContractUpdateOffered { keys_id, .. } => Some(*keys_id),
+            SignedChannelState::ContractUpdateAccepted { keys_id, .. } => Some(*keys_id),
+            SignedChannelState::ContractUpdateConfirmed { keys_id, .. } => Some(*keys_id),
+            SignedChannelState::ContractsClosing { keys_id, .. } => Some(*keys_id),
+            SignedChannelState::FundingDoubleSpent { .. } => None,
         }
     }
 }
 
 /// A channel that had a successful setup.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct SignedChannel {
     /// The [`crate::ChannelId`] for the channel.
     pub channel_id: ChannelId,
@@ -405,7 +609,22 @@ pub struct SignedChannel {
     /// state, is `None`.
     pub roll_back_state: Option<SignedChannelState>,
     /// Structure storing the previous commitment secrets from the counter party.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_writeable",
+            deserialize_with = "crate::serde_utils::deserialize_writeable"
+        )
+    )]
     pub counter_party_commitment_secrets: CounterpartyCommitmentSecrets,
     /// The current fee rate to be used to create transactions.
     pub fee_rate_per_vb: u64,
+    /// The timing parameters of the channel.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub config: ChannelConfig,
+    /// Set for zero-conf channels until their fund transaction is confirmed.
+    /// While set, the channel is used trusting the counter party not to
+    /// double spend the inputs of the fund transaction.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unconfirmed_funding: bool,
 }
@@ -0,0 +1,303 @@
+//! # Export of the data required by third party watchtowers to punish the
+//! broadcast of revoked channel states while the local node is offline.
+//!
+//! For each revocation, the manager produces a [`JusticeBlob`] encrypted using
+//! a key derived from the id of the revoked transaction, and identified by a
+//! locator that is also derived from it. A watchtower can thus only decrypt
+//! the blob once the revoked transaction is broadcast, at which point it can
+//! build and broadcast the punishment transaction using [`JusticeData`].
+//!
+//! As the key of the counter party is only revealed when the revoked
+//! transaction is broadcast, the punishment transaction cannot be signed in
+//! advance, and the blob contains the local keys required to sign it. These
+//! keys only control the outputs of the revoked transaction, but a watchtower
+//! decrypting a blob is able to send the punished funds to an address other
+//! than the one it contains, and should thus be trusted accordingly.
+
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{Address, Network, ScriptBuf, Transaction, Txid};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use dlc_messages::ser_impls::{read_ecdsa_adaptor_signature, write_ecdsa_adaptor_signature};
+use lightning::util::ser::{Readable, Writeable};
+use secp256k1_zkp::{
+    ecdsa::Signature, All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey,
+};
+
+use crate::chain_monitor::RevokedTxType;
+use crate::error::Error;
+use crate::ChannelId;
+
+use super::party_points::PartyBasePoints;
+
+/// The size in bytes of a [`JusticeBlob`] locator.
+pub const LOCATOR_LEN: usize = 16;
+
+/// Receives the [`JusticeBlob`] produced each time the counter party revokes
+/// a state of a channel, to forward them to a watchtower.
+pub trait WatchtowerClient: Send + Sync {
+    /// Called each time a state of a channel is revoked by the counter party.
+    fn on_revocation(&self, blob: &JusticeBlob) -> Result<(), Error>;
+}
+
+/// The data required to build and sign the transaction punishing the
+/// broadcast of a revoked channel transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JusticeData {
+    /// The id of the channel the revoked state belongs to.
+    pub channel_id: ChannelId,
+    /// The id of the revoked buffer or settle transaction.
+    pub revoked_txid: Txid,
+    /// The type of the revoked transaction.
+    pub revoked_tx_type: RevokedTxType,
+    /// Whether the local party is the offer party of the channel.
+    pub is_offer: bool,
+    /// The base points of the local party.
+    pub own_points: PartyBasePoints,
+    /// The base points of the counter party.
+    pub counter_points: PartyBasePoints,
+    /// The per update point of the local party for the revoked state.
+    pub own_per_update_point: PublicKey,
+    /// The per update point of the counter party for the revoked state.
+    pub counter_per_update_point: PublicKey,
+    /// The adaptor signature given to the counter party for the revoked
+    /// transaction, used to recover its publish key once it is broadcast.
+    pub own_adaptor_signature: EcdsaAdaptorSignature,
+    /// Whether the funding public key of the local party is lower than the
+    /// one of the counter party, which determines the position of its
+    /// signature in the witness of the revoked transaction.
+    pub own_fund_pk_is_lower: bool,
+    /// The local secret key for the revoked state.
+    pub own_sk: SecretKey,
+    /// The revocation secret key of the counter party for the revoked state.
+    pub counter_revoke_sk: SecretKey,
+    /// The script to which the punished funds are sent.
+    pub destination_script: ScriptBuf,
+    /// The relative locktime of the outputs of the revoked transaction.
+    pub csv_timelock: u32,
+}
+
+impl_dlc_writeable!(JusticeData, {
+    (channel_id, writeable),
+    (revoked_txid, writeable),
+    (revoked_tx_type, writeable),
+    (is_offer, writeable),
+    (own_points, writeable),
+    (counter_points, writeable),
+    (own_per_update_point, writeable),
+    (counter_per_update_point, writeable),
+    (own_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}),
+    (own_fund_pk_is_lower, writeable),
+    (own_sk, writeable),
+    (counter_revoke_sk, writeable),
+    (destination_script, writeable),
+    (csv_timelock, writeable)
+});
+
+impl JusticeData {
+    /// Creates and signs the transaction punishing the broadcast of the given
+    /// revoked transaction, paying the given fee rate.
+    pub fn create_justice_transaction(
+        &self,
+        secp: &Secp256k1<All>,
+        revoked_tx: &Transaction,
+        network: Network,
+        fee_rate_per_vb: u64,
+    ) -> Result<Transaction, Error> {
+        if revoked_tx.txid() != self.revoked_txid {
+            return Err(Error::InvalidParameters(format!(
+                "Expected revoked transaction {} but got {}.",
+                self.revoked_txid,
+                revoked_tx.txid()
+            )));
+        }
+
+        let own_revocation_params = self.own_points.get_revokable_params(
+            secp,
+            &self.counter_points.revocation_basepoint,
+            &self.own_per_update_point,
+        );
+        let counter_revocation_params = self.counter_points.get_revokable_params(
+            secp,
+            &self.own_points.revocation_basepoint,
+            &self.counter_per_update_point,
+        );
+
+        let witness_index = if self.own_fund_pk_is_lower { 1 } else { 2 };
+        let witness = revoked_tx
+            .input
+            .first()
+            .and_then(|x| x.witness.nth(witness_index))
+            .ok_or_else(|| {
+                Error::InvalidParameters("Revoked transaction has no valid witness.".to_string())
+            })?;
+        let own_sig = Signature::from_der(&witness[..witness.len().saturating_sub(1)])?;
+
+        let counter_sk = self.own_adaptor_signature.recover(
+            secp,
+            &own_sig,
+            &counter_revocation_params.publish_pk.inner,
+        )?;
+
+        let (offer_params, accept_params) = if self.is_offer {
+            (&own_revocation_params, &counter_revocation_params)
+        } else {
+            (&counter_revocation_params, &own_revocation_params)
+        };
+
+        let dest_address = Address::from_script(&self.destination_script, network)
+            .map_err(|_| Error::InvalidParameters("Invalid destination script.".to_string()))?;
+
+        let tx = match self.revoked_tx_type {
+            RevokedTxType::Buffer => dlc::channel::create_and_sign_punish_buffer_transaction(
+                secp,
+                offer_params,
+                accept_params,
+                &self.own_sk,
+                &counter_sk,
+                &self.counter_revoke_sk,
+                revoked_tx,
+                &dest_address,
+                0,
+                fee_rate_per_vb,
+            )?,
+            RevokedTxType::Settle => dlc::channel::create_and_sign_punish_settle_transaction(
+                secp,
+                offer_params,
+                accept_params,
+                &self.own_sk,
+                &counter_sk,
+                &self.counter_revoke_sk,
+                revoked_tx,
+                &dest_address,
+                self.csv_timelock,
+                0,
+                fee_rate_per_vb,
+                self.is_offer,
+            )?,
+            RevokedTxType::MultiBuffer => {
+                dlc::channel::create_and_sign_punish_multi_contract_buffer_transaction(
+                    secp,
+                    offer_params,
+                    accept_params,
+                    &self.own_sk,
+                    &counter_sk,
+                    &self.counter_revoke_sk,
+                    revoked_tx,
+                    &dest_address,
+                    self.csv_timelock,
+                    0,
+                    fee_rate_per_vb,
+                    self.is_offer,
+                )?
+            }
+        };
+
+        Ok(tx)
+    }
+}
+
+/// A [`JusticeData`] encrypted using a key derived from the id of the revoked
+/// transaction it refers to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JusticeBlob {
+    /// The first [`LOCATOR_LEN`] bytes of the id of the revoked transaction,
+    /// used by watchtowers to find the blob to decrypt when a transaction is
+    /// broadcast.
+    pub locator: [u8; LOCATOR_LEN],
+    /// The encrypted [`JusticeData`].
+    pub encrypted_data: Vec<u8>,
+}
+
+impl_dlc_writeable!(JusticeBlob, {
+    (locator, writeable),
+    (encrypted_data, writeable)
+});
+
+impl JusticeBlob {
+    /// Encrypts the given [`JusticeData`].
+    pub fn encrypt(data: &JusticeData) -> Result<Self, Error> {
+        let encrypted_data = get_cipher(&data.revoked_txid)
+            .encrypt(&Nonce::default(), data.encode().as_slice())
+            .map_err(|_| Error::InvalidState("Could not encrypt justice data.".to_string()))?;
+        Ok(JusticeBlob {
+            locator: get_locator(&data.revoked_txid),
+            encrypted_data,
+        })
+    }
+
+    /// Returns the [`JusticeData`] contained in the blob, given the id of the
+    /// revoked transaction it refers to.
+    pub fn decrypt(&self, revoked_txid: &Txid) -> Result<JusticeData, Error> {
+        let data = get_cipher(revoked_txid)
+            .decrypt(&Nonce::default(), self.encrypted_data.as_slice())
+            .map_err(|_| Error::InvalidParameters("Could not decrypt justice blob.".to_string()))?;
+        let justice_data: JusticeData = Readable::read(&mut lightning::io::Cursor::new(&data))
+            .map_err(|_| Error::InvalidParameters("Invalid justice data.".to_string()))?;
+        if justice_data.revoked_txid != *revoked_txid {
+            return Err(Error::InvalidParameters(
+                "Justice data does not refer to the given transaction.".to_string(),
+            ));
+        }
+        Ok(justice_data)
+    }
+}
+
+/// Returns the locator of the [`JusticeBlob`] referring to the given revoked
+/// transaction.
+pub fn get_locator(revoked_txid: &Txid) -> [u8; LOCATOR_LEN] {
+    let mut locator = [0u8; LOCATOR_LEN];
+    locator.copy_from_slice(&revoked_txid.as_byte_array()[..LOCATOR_LEN]);
+    locator
+}
+
+// The key being unique to each revoked transaction, a constant nonce can be
+// used.
+fn get_cipher(revoked_txid: &Txid) -> ChaCha20Poly1305 {
+    let key = sha256::Hash::hash(revoked_txid.as_byte_array());
+    ChaCha20Poly1305::new(Key::from_slice(key.as_byte_array()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::{Message, SECP256K1};
+
+    fn get_justice_data() -> JusticeData {
+        let sk = SecretKey::from_slice(&[1; 32]).unwrap();
+        let pk = PublicKey::from_secret_key(SECP256K1, &sk);
+        let points = PartyBasePoints {
+            own_basepoint: pk,
+            publish_basepoint: pk,
+            revocation_basepoint: pk,
+        };
+        let msg = Message::from_slice(&[2; 32]).unwrap();
+        JusticeData {
+            channel_id: [3; 32],
+            revoked_txid: Txid::from_byte_array([4; 32]),
+            revoked_tx_type: RevokedTxType::Buffer,
+            is_offer: true,
+            own_points: points.clone(),
+            counter_points: points,
+            own_per_update_point: pk,
+            counter_per_update_point: pk,
+            own_adaptor_signature: EcdsaAdaptorSignature::encrypt(SECP256K1, &msg, &sk, &pk),
+            own_fund_pk_is_lower: true,
+            own_sk: sk,
+            counter_revoke_sk: sk,
+            destination_script: ScriptBuf::new(),
+            csv_timelock: 288,
+        }
+    }
+
+    #[test]
+    fn justice_blob_can_only_be_decrypted_with_revoked_txid() {
+        let data = get_justice_data();
+        let blob = JusticeBlob::encrypt(&data).unwrap();
+
+        assert_eq!(get_locator(&data.revoked_txid), blob.locator);
+        assert_eq!(data, blob.decrypt(&data.revoked_txid).unwrap());
+        blob.decrypt(&Txid::from_byte_array([5; 32]))
+            .expect_err("decryption with another txid to fail");
+    }
+}
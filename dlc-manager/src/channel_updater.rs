@@ -3,11 +3,13 @@
 use std::ops::Deref;
 
 use crate::{
+    cancellation::CancellationToken,
     channel::{
         accepted_channel::AcceptedChannel,
         offered_channel::OfferedChannel,
         party_points::PartyBasePoints,
         signed_channel::{SignedChannel, SignedChannelState},
+        ChannelConfig, ChannelPolicy,
     },
     contract::{
         accepted_contract::AcceptedContract, contract_info::ContractInfo,
@@ -20,25 +22,36 @@ use crate::{
     },
     error::Error,
     utils::get_new_temporary_id,
-    Blockchain, ContractSigner, ContractSignerProvider, Time, Wallet,
+    Blockchain, ContractId, ContractSigner, ContractSignerProvider, KeysId, Time, Wallet,
+};
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::{
+    sighash::EcdsaSighashType, OutPoint, Script, ScriptBuf, Sequence, Transaction, TxIn, Witness,
 };
-use bitcoin::{OutPoint, Script, ScriptBuf, Sequence, Transaction, TxIn, Witness};
 use dlc::{
-    channel::{get_tx_adaptor_signature, verify_tx_adaptor_signature, DlcChannelTransactions},
-    PartyParams,
+    channel::{
+        get_tx_adaptor_signature, verify_tx_adaptor_signature, ChannelContractParams,
+        DlcChannelTransactions, MultiContractChannelTransactions, RevokeParams,
+    },
+    FeeSplit, PartyParams,
 };
 use dlc_messages::{
     channel::{
-        AcceptChannel, CollaborativeCloseOffer, Reject, RenewAccept, RenewConfirm, RenewFinalize,
-        RenewOffer, SettleAccept, SettleConfirm, SettleFinalize, SettleOffer, SignChannel,
+        AcceptChannel, AddContractOffer, CollaborativeCloseOffer, ContractUpdateAccept,
+        ContractUpdateConfirm, ContractUpdateFinalize, Reject, RenewAccept, RenewConfirm,
+        RenewFinalize, RenewOffer, SettleAccept, SettleConfirm, SettleContractOffer,
+        SettleFinalize, SettleOffer, SignChannel,
     },
     oracle_msgs::{OracleAnnouncement, OracleAttestation},
-    FundingSignatures,
+    CetAdaptorSignatures, FundingSignatures,
 };
 use lightning::ln::chan_utils::{
     build_commitment_secret, derive_private_key, CounterpartyCommitmentSecrets,
 };
-use secp256k1_zkp::{All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey, Signing};
+use secp256k1_zkp::{
+    ecdsa::Signature, All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey, Signing,
+    Verification,
+};
 
 const INITIAL_UPDATE_NUMBER: u64 = (1 << 48) - 1;
 
@@ -64,6 +77,30 @@ macro_rules! get_signed_channel_state {
 }
 pub(crate) use get_signed_channel_state;
 
+/// Anchor outputs are only supported for contracts outside of channels, as
+/// the buffer and settle transactions would also need to include one, and so
+/// is taproot funding, as channel transactions spend a P2WSH fund output. The
+/// fees of channel transactions are always split equally, as the collateral
+/// of each party changes with each settlement.
+fn check_channel_contract_input(contract_input: &ContractInput) -> Result<(), Error> {
+    if contract_input.anchor_outputs {
+        return Err(Error::InvalidParameters(
+            "Anchor outputs are not supported for channels".to_string(),
+        ));
+    }
+    if contract_input.taproot {
+        return Err(Error::InvalidParameters(
+            "Taproot funding is not supported for channels".to_string(),
+        ));
+    }
+    if contract_input.fee_split != FeeSplit::Equal {
+        return Err(Error::InvalidParameters(
+            "Only equal fee splits are supported for channels".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 /// Creates an [`OfferedChannel`] and an associated [`OfferedContract`] using
 /// the given parameter.
 pub fn offer_channel<C: Signing, W: Deref, SP: Deref, B: Deref, T: Deref, X: ContractSigner>(
@@ -71,7 +108,7 @@ pub fn offer_channel<C: Signing, W: Deref, SP: Deref, B: Deref, T: Deref, X: Con
     contract: &ContractInput,
     counter_party: &PublicKey,
     oracle_announcements: &[Vec<OracleAnnouncement>],
-    cet_nsequence: u32,
+    config: &ChannelConfig,
     refund_delay: u32,
     wallet: &W,
     signer_provider: &SP,
@@ -84,12 +121,20 @@ where
     B::Target: Blockchain,
     T::Target: Time,
 {
+    check_channel_contract_input(contract)?;
     let id = get_new_temporary_id();
     let keys_id = signer_provider.derive_signer_key_id(true, id);
     let signer = signer_provider.derive_contract_signer(keys_id)?;
     let (offer_params, funding_inputs_info) = crate::utils::get_party_params(
         secp,
         contract.offer_collateral,
+        crate::utils::get_approximate_fee(
+            contract.fee_rate,
+            FeeSplit::Equal,
+            true,
+            contract.offer_collateral,
+            contract.accept_collateral,
+        )?,
         contract.fee_rate,
         wallet,
         &signer,
@@ -130,7 +175,9 @@ where
         offer_per_update_seed: Some(PublicKey::from_secret_key(secp, &per_update_seed)),
         is_offer_party: true,
         counter_party: *counter_party,
-        cet_nsequence,
+        cet_nsequence: config.cet_nsequence,
+        peer_timeout: config.peer_timeout,
+        zero_conf: config.zero_conf,
     };
 
     Ok((offered_channel, offered_contract))
@@ -146,6 +193,7 @@ pub fn accept_channel_offer<W: Deref, SP: Deref, B: Deref, X: ContractSigner>(
     wallet: &W,
     signer_provider: &SP,
     blockchain: &B,
+    cancellation: &CancellationToken,
 ) -> Result<(AcceptedChannel, AcceptedContract, AcceptChannel), Error>
 where
     W::Target: Wallet,
@@ -160,6 +208,13 @@ where
     let (accept_params, funding_inputs) = crate::utils::get_party_params(
         secp,
         total_collateral - offered_contract.offer_params.collateral,
+        crate::utils::get_approximate_fee(
+            offered_contract.fee_rate_per_vb,
+            FeeSplit::Equal,
+            false,
+            offered_contract.offer_params.collateral,
+            total_collateral - offered_contract.offer_params.collateral,
+        )?,
         offered_contract.fee_rate_per_vb,
         wallet,
         &signer,
@@ -231,15 +286,20 @@ where
         &offer_revoke_params.publish_pk.inner,
     )?;
 
-    let (accepted_contract, adaptor_sigs) = accept_contract_internal(
-        secp,
-        offered_contract,
+    let (accepted_contract, adaptor_sigs) = crate::utils::release_on_cancellation(
+        wallet,
         &accept_params,
-        &funding_inputs,
-        &own_secret_key,
-        buffer_transaction.output[0].value,
-        Some(&buffer_script_pubkey),
-        &dlc_transactions,
+        accept_contract_internal(
+            secp,
+            offered_contract,
+            &accept_params,
+            &funding_inputs,
+            &own_secret_key,
+            buffer_transaction.output[0].value,
+            Some(&buffer_script_pubkey),
+            &dlc_transactions,
+            cancellation,
+        ),
     )?;
 
     let accepted_channel = AcceptedChannel {
@@ -255,13 +315,14 @@ where
         accept_per_update_seed: PublicKey::from_secret_key(secp, &per_update_seed),
         accept_buffer_adaptor_signature: buffer_adaptor_signature,
         counter_party: offered_contract.counter_party,
+        config: offered_channel.get_config(),
     };
 
     let accept_channel = accepted_channel.get_accept_channel_msg(
         &accepted_contract,
         &buffer_adaptor_signature,
         &adaptor_sigs,
-    );
+    )?;
 
     Ok((accepted_channel, accepted_contract, accept_channel))
 }
@@ -275,9 +336,9 @@ pub fn verify_and_sign_accepted_channel<W: Deref, SP: Deref, X: ContractSigner>(
     offered_channel: &OfferedChannel,
     offered_contract: &OfferedContract,
     accept_channel: &AcceptChannel,
-    cet_nsequence: u32,
     wallet: &W,
     signer_provider: &SP,
+    cancellation: &CancellationToken,
 ) -> Result<(SignedChannel, SignedContract, SignChannel), Error>
 where
     W::Target: Wallet,
@@ -341,7 +402,7 @@ where
         0,
         offered_contract.cet_locktime,
         offered_contract.fund_output_serial_id,
-        Sequence(cet_nsequence),
+        Sequence(offered_channel.cet_nsequence),
     )?;
 
     let channel_id = crate::utils::compute_id(
@@ -366,6 +427,7 @@ where
         Some(accept_revoke_params.own_pk.inner),
         &dlc_transactions,
         Some(channel_id),
+        cancellation,
     )?;
 
     verify_tx_adaptor_signature(
@@ -421,13 +483,15 @@ where
             .accepted_contract
             .offered_contract
             .fee_rate_per_vb,
+        config: offered_channel.get_config(),
+        unconfirmed_funding: offered_channel.zero_conf,
     };
 
     let sign_channel = SignChannel {
         channel_id,
         cet_adaptor_signatures: (&cet_adaptor_signatures as &[_]).into(),
         buffer_adaptor_signature: own_buffer_adaptor_signature,
-        refund_signature: signed_contract.offer_refund_signature,
+        refund_signature: signed_contract.get_offer_refund_signature()?,
         funding_signatures: signed_contract.funding_signatures.clone(),
     };
 
@@ -443,6 +507,7 @@ pub fn verify_signed_channel<W: Deref>(
     accepted_contract: &AcceptedContract,
     sign_channel: &SignChannel,
     wallet: &W,
+    cancellation: &CancellationToken,
 ) -> Result<(SignedChannel, SignedContract, Transaction), Error>
 where
     W::Target: Wallet,
@@ -477,6 +542,7 @@ where
         Some(counter_own_pk),
         wallet,
         Some(accepted_channel.channel_id),
+        cancellation,
     )?;
 
     let signed_channel = SignedChannel {
@@ -518,6 +584,8 @@ where
             .accepted_contract
             .offered_contract
             .fee_rate_per_vb,
+        config: accepted_channel.config,
+        unconfirmed_funding: accepted_channel.config.zero_conf,
     };
 
     Ok((signed_channel, signed_contract, signed_fund_tx))
@@ -577,11 +645,56 @@ where
     Ok(settle_channel_offer)
 }
 
+/// Checks that the balances of the parties resulting from a channel update
+/// offered by the counter party are not dust according to the given
+/// [`ChannelPolicy`].
+fn check_policy_dust_limit(policy: &ChannelPolicy, balances: &[u64]) -> Result<(), Error> {
+    for balance in balances {
+        if *balance > 0 && *balance < policy.dust_limit {
+            return Err(Error::ChannelPolicyViolation(format!(
+                "Balance {} is below the dust limit {}.",
+                balance, policy.dust_limit
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the balances of the parties resulting from a channel update
+/// offered by the counter party keep the reserve and are not dust according to
+/// the given [`ChannelPolicy`].
+fn check_policy_balances(policy: &ChannelPolicy, balances: &[u64]) -> Result<(), Error> {
+    for balance in balances {
+        if *balance < policy.min_reserve {
+            return Err(Error::ChannelPolicyViolation(format!(
+                "Balance {} is below the channel reserve {}.",
+                balance, policy.min_reserve
+            )));
+        }
+    }
+    check_policy_dust_limit(policy, balances)
+}
+
+/// Checks that the total collateral of the contracts set up in a channel by
+/// an update offered by the counter party does not exceed the maximum allowed
+/// by the given [`ChannelPolicy`].
+fn check_policy_contracts_value(policy: &ChannelPolicy, contracts_value: u64) -> Result<(), Error> {
+    match policy.max_contracts_value {
+        Some(max) if contracts_value > max => Err(Error::ChannelPolicyViolation(format!(
+            "Contracts value {} is greater than the maximum {}.",
+            contracts_value, max
+        ))),
+        _ => Ok(()),
+    }
+}
+
 /// Updates the state of the given [`SignedChannel`] using the given [`SettleOffer`]
-/// message.
+/// message, after checking that the resulting payouts comply with the given
+/// [`ChannelPolicy`].
 pub fn on_settle_offer(
     signed_channel: &mut SignedChannel,
     settle_offer: &SettleOffer,
+    policy: &ChannelPolicy,
 ) -> Result<(), Error> {
     let keys_id = if let SignedChannelState::Established { keys_id, .. } = signed_channel.state {
         keys_id
@@ -591,6 +704,17 @@ pub fn on_settle_offer(
         ));
     };
 
+    let counter_payout = (signed_channel.own_params.collateral
+        + signed_channel.counter_params.collateral)
+        .checked_sub(settle_offer.counter_payout)
+        .ok_or_else(|| {
+            Error::InvalidParameters(
+                "Settle offer payout is greater than the channel collateral.".to_string(),
+            )
+        })?;
+
+    check_policy_balances(policy, &[settle_offer.counter_payout, counter_payout])?;
+
     let mut new_state = SignedChannelState::SettledReceived {
         own_payout: settle_offer.counter_payout,
         counter_next_per_update_point: settle_offer.next_per_update_point,
@@ -997,6 +1121,7 @@ where
     SP::Target: ContractSignerProvider<Signer = X>,
     T::Target: Time,
 {
+    check_channel_contract_input(contract_input)?;
     let id = get_new_temporary_id();
     let keys_id = signed_channel
         .keys_id()
@@ -1017,6 +1142,14 @@ where
 
     offered_contract.fee_rate_per_vb = signed_channel.fee_rate_per_vb;
 
+    check_renewal_terms(
+        secp,
+        signed_channel,
+        &offered_contract,
+        counter_payout,
+        time.unix_time_now(),
+    )?;
+
     let per_update_seed =
         signer_provider.get_secret_key_for_pubkey(&signed_channel.own_per_update_seed)?;
 
@@ -1054,12 +1187,76 @@ where
     Ok((msg, offered_contract))
 }
 
+/// Checks that the terms of a contract offered to renew the given channel can
+/// be used in it. The renewed contract can reference a different event, payout
+/// curve and maturity than the previous one, but must lock the collateral of
+/// the channel and be refundable after its oracle events mature.
+fn check_renewal_terms<C: Verification>(
+    secp: &Secp256k1<C>,
+    signed_channel: &SignedChannel,
+    offered_contract: &OfferedContract,
+    counter_payout: u64,
+    now: u64,
+) -> Result<(), Error> {
+    let channel_collateral =
+        signed_channel.own_params.collateral + signed_channel.counter_params.collateral;
+    if offered_contract.total_collateral != channel_collateral {
+        return Err(Error::InvalidParameters(format!(
+            "Renewed contract total collateral {} does not match the channel collateral {}.",
+            offered_contract.total_collateral, channel_collateral
+        )));
+    }
+
+    if counter_payout > channel_collateral {
+        return Err(Error::InvalidParameters(format!(
+            "Counter payout {} is greater than the channel collateral {}.",
+            counter_payout, channel_collateral
+        )));
+    }
+
+    if offered_contract.contract_info.len() != 1 {
+        return Err(Error::InvalidParameters(
+            "Renewed contract must have a single contract info.".to_string(),
+        ));
+    }
+
+    if (offered_contract.refund_locktime as u64) <= now {
+        return Err(Error::InvalidParameters(format!(
+            "Renewed contract refund locktime {} is not in the future.",
+            offered_contract.refund_locktime
+        )));
+    }
+
+    offered_contract.validate()?;
+
+    // The CET locktime of a renewed contract is the time of the renewal, which
+    // can be after the maturity of the events when rolling over a contract, so
+    // only the refund locktime is checked against the announcements.
+    for announcement in offered_contract
+        .contract_info
+        .iter()
+        .flat_map(|x| x.oracle_announcements.iter())
+    {
+        crate::utils::validate_announcement(
+            secp,
+            announcement,
+            0,
+            offered_contract.refund_locktime,
+        )?;
+    }
+
+    Ok(())
+}
+
 /// Update the state of the given [`SignedChannel`] from the given [`RenewOffer`].
 /// Expects the channel to be in one of [`SignedChannelState::Settled`] or
-/// [`SignedChannelState::Established`] state.
-pub fn on_renew_offer<T: Deref>(
+/// [`SignedChannelState::Established`] state, and the terms of the offered
+/// contract to be valid for the channel.
+pub fn on_renew_offer<C: Verification, T: Deref>(
+    secp: &Secp256k1<C>,
     signed_channel: &mut SignedChannel,
     renew_offer: &RenewOffer,
+    policy: &ChannelPolicy,
     peer_timeout: u64,
     time: &T,
 ) -> Result<OfferedContract, Error>
@@ -1092,8 +1289,23 @@ where
         cet_locktime: renew_offer.cet_locktime,
         refund_locktime: renew_offer.refund_locktime,
         keys_id,
+        anchor_outputs: false,
+        fee_split: FeeSplit::Equal,
+        expiry: None,
+        metadata: None,
+        taproot: false,
     };
 
+    check_renewal_terms(
+        secp,
+        signed_channel,
+        &offered_contract,
+        renew_offer.counter_payout,
+        time.unix_time_now(),
+    )?;
+
+    check_policy_contracts_value(policy, offered_contract.total_collateral)?;
+
     let mut state = SignedChannelState::RenewOffered {
         offered_contract_id: offered_contract.id,
         counter_payout: renew_offer.counter_payout,
@@ -1122,6 +1334,7 @@ pub fn accept_channel_renewal<SP: Deref, T: Deref>(
     peer_timeout: u64,
     signer_provider: &SP,
     time: &T,
+    cancellation: &CancellationToken,
 ) -> Result<(AcceptedContract, RenewAccept), Error>
 where
     SP::Target: ContractSignerProvider,
@@ -1207,6 +1420,7 @@ where
         buffer_transaction.output[0].value,
         Some(&buffer_script_pubkey),
         &dlc_transactions,
+        cancellation,
     )?;
 
     let state = SignedChannelState::RenewAccepted {
@@ -1228,7 +1442,7 @@ where
         next_per_update_point: accept_per_update_point,
         buffer_adaptor_signature,
         cet_adaptor_signatures: (&adaptor_sigs as &[_]).into(),
-        refund_signature: accepted_contract.accept_refund_signature,
+        refund_signature: accepted_contract.get_accept_refund_signature()?,
     };
 
     Ok((accepted_contract, renew_accept))
@@ -1248,6 +1462,7 @@ pub fn verify_renew_accept_and_confirm<W: Deref, SP: Deref, X: ContractSigner, T
     wallet: &W,
     signer_provider: &SP,
     time: &T,
+    cancellation: &CancellationToken,
 ) -> Result<(SignedContract, RenewConfirm), Error>
 where
     W::Target: Wallet,
@@ -1325,6 +1540,7 @@ where
         Some(accept_revoke_params.own_pk.inner),
         &dlc_transactions,
         Some(signed_channel.channel_id),
+        cancellation,
     )?;
 
     verify_tx_adaptor_signature(
@@ -1366,7 +1582,7 @@ where
         per_update_secret: prev_per_update_secret,
         buffer_adaptor_signature: own_buffer_adaptor_signature,
         cet_adaptor_signatures: (&cet_adaptor_signatures as &[_]).into(),
-        refund_signature: signed_contract.offer_refund_signature,
+        refund_signature: signed_contract.get_offer_refund_signature()?,
     };
 
     Ok((signed_contract, renew_confirm))
@@ -1383,6 +1599,7 @@ pub fn verify_renew_confirm_and_finalize<W: Deref, SP: Deref>(
     renew_confirm: &RenewConfirm,
     wallet: &W,
     signer_provider: &SP,
+    cancellation: &CancellationToken,
 ) -> Result<(SignedContract, RenewFinalize), Error>
 where
     W::Target: Wallet,
@@ -1436,6 +1653,7 @@ where
         Some(counter_own_pk),
         wallet,
         Some(signed_channel.channel_id),
+        cancellation,
     )?;
 
     signed_channel.state = SignedChannelState::Established {
@@ -1549,138 +1767,1391 @@ pub fn reject_renew_offer(signed_channel: &mut SignedChannel) -> Result<Reject,
     })
 }
 
-/// Creates a [`CollaborativeCloseOffer`] message and update the state of the
-/// given [`SignedChannel`].
-pub fn offer_collaborative_close<C: Signing, SP: Deref, T: Deref>(
-    secp: &Secp256k1<C>,
-    signed_channel: &mut SignedChannel,
-    counter_payout: u64,
-    signer_provider: &SP,
-    time: &T,
-) -> Result<(CollaborativeCloseOffer, Transaction), Error>
-where
-    SP::Target: ContractSignerProvider,
-    T::Target: Time,
-{
-    if counter_payout
-        > signed_channel.counter_params.collateral + signed_channel.own_params.collateral
-    {
-        return Err(Error::InvalidParameters(
-            "Counter payout is greater than total collateral".to_string(),
-        ));
+/// A contract setup in a channel holding several contracts, with the
+/// parameters of its accept party.
+struct ChannelContract {
+    offered_contract: OfferedContract,
+    accept_params: PartyParams,
+}
+
+/// Returns the contracts of the next state of the channel, in the order of the
+/// buffer transaction outputs locking them: the contracts remaining from the
+/// current state followed by the added one, if any.
+fn get_channel_contracts(
+    signed_channel: &SignedChannel,
+    contracts: &[SignedContract],
+    added_contract: Option<&OfferedContract>,
+) -> Vec<ChannelContract> {
+    let mut channel_contracts: Vec<_> = contracts
+        .iter()
+        .map(|c| ChannelContract {
+            offered_contract: c.accepted_contract.offered_contract.clone(),
+            accept_params: c.accepted_contract.accept_params.clone(),
+        })
+        .collect();
+
+    if let Some(offered_contract) = added_contract {
+        let mut accept_params = if offered_contract.is_offer_party {
+            signed_channel.counter_params.clone()
+        } else {
+            signed_channel.own_params.clone()
+        };
+        accept_params.collateral =
+            offered_contract.total_collateral - offered_contract.offer_params.collateral;
+        channel_contracts.push(ChannelContract {
+            offered_contract: offered_contract.clone(),
+            accept_params,
+        });
     }
 
-    let total_collateral =
-        signed_channel.own_params.collateral + signed_channel.counter_params.collateral;
-    let offer_payout = total_collateral - counter_payout;
-    let fund_output_value = signed_channel.fund_tx.output[signed_channel.fund_output_index].value;
+    channel_contracts
+}
 
-    let close_tx = dlc::channel::create_collaborative_close_transaction(
-        &signed_channel.own_params,
-        offer_payout,
-        &signed_channel.counter_params,
-        counter_payout,
-        OutPoint {
-            txid: signed_channel.fund_tx.txid(),
-            vout: signed_channel.fund_output_index as u32,
-        },
-        fund_output_value,
-    );
+/// Returns the ids of the contracts of the next state of the channel, the id
+/// of the added contract, if any, being derived from its temporary id.
+fn get_next_contract_ids(
+    signed_channel: &SignedChannel,
+    contract_ids: &[ContractId],
+    added_contract_id: &Option<ContractId>,
+) -> Vec<ContractId> {
+    let mut next_contract_ids = contract_ids.to_vec();
+    if let Some(temporary_id) = added_contract_id {
+        next_contract_ids.push(crate::utils::compute_id(
+            signed_channel.fund_tx.txid(),
+            signed_channel.fund_output_index as u16,
+            temporary_id,
+        ));
+    }
+    next_contract_ids
+}
 
-    let keys_id = signed_channel
-        .keys_id()
-        .ok_or(Error::InvalidState("No keys_id available".to_string()))?;
-    let contract_signer = signer_provider.derive_contract_signer(keys_id)?;
+/// Returns the revocation parameters of the offer and accept parties of a
+/// channel update.
+fn get_update_revoke_params(
+    secp: &Secp256k1<All>,
+    signed_channel: &SignedChannel,
+    is_offer: bool,
+    offer_per_update_point: &PublicKey,
+    accept_per_update_point: &PublicKey,
+) -> (RevokeParams, RevokeParams) {
+    let (offer_points, accept_points) = if is_offer {
+        (&signed_channel.own_points, &signed_channel.counter_points)
+    } else {
+        (&signed_channel.counter_points, &signed_channel.own_points)
+    };
 
-    let close_signature = dlc::util::get_raw_sig_for_tx_input(
-        secp,
-        &close_tx,
-        0,
+    (
+        offer_points.get_revokable_params(
+            secp,
+            &accept_points.revocation_basepoint,
+            offer_per_update_point,
+        ),
+        accept_points.get_revokable_params(
+            secp,
+            &offer_points.revocation_basepoint,
+            accept_per_update_point,
+        ),
+    )
+}
+
+/// Creates the buffer transaction of the next channel state, together with the
+/// CETs and refund transaction of each of the given contracts.
+fn create_channel_contracts_transactions(
+    signed_channel: &SignedChannel,
+    offer_revoke_params: &RevokeParams,
+    accept_revoke_params: &RevokeParams,
+    contracts: &[ChannelContract],
+    offer_balance: u64,
+    accept_balance: u64,
+    cet_nsequence: u32,
+) -> Result<MultiContractChannelTransactions, Error> {
+    let payouts = contracts
+        .iter()
+        .map(|c| {
+            c.offered_contract.contract_info[0].get_payouts(c.offered_contract.total_collateral)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let contract_params: Vec<_> = contracts
+        .iter()
+        .zip(payouts.iter())
+        .map(|(c, payouts)| ChannelContractParams {
+            offer_params: &c.offered_contract.offer_params,
+            accept_params: &c.accept_params,
+            payouts,
+            refund_lock_time: c.offered_contract.refund_locktime,
+            cet_lock_time: c.offered_contract.cet_locktime,
+        })
+        .collect();
+
+    Ok(dlc::channel::create_multi_contract_channel_transactions(
+        offer_revoke_params,
+        accept_revoke_params,
+        &signed_channel.fund_tx,
         &signed_channel.fund_script_pubkey,
-        fund_output_value,
-        &contract_signer.get_secret_key()?,
-    )?;
+        &contract_params,
+        offer_balance,
+        accept_balance,
+        signed_channel.fee_rate_per_vb,
+        Sequence(cet_nsequence),
+    )?)
+}
 
-    let mut state = SignedChannelState::CollaborativeCloseOffered {
-        counter_payout,
-        offer_signature: close_signature,
-        close_tx: close_tx.clone(),
-        timeout: time.unix_time_now() + super::manager::PEER_TIMEOUT,
-        keys_id: signed_channel
-            .keys_id()
-            .ok_or(Error::InvalidState("No keys_id available".to_string()))?,
-    };
-    std::mem::swap(&mut state, &mut signed_channel.state);
-    signed_channel.roll_back_state = Some(state);
+/// Returns the refund signature of the local party for each contract of the
+/// given transactions.
+fn get_channel_contracts_refund_signatures(
+    secp: &Secp256k1<All>,
+    txs: &MultiContractChannelTransactions,
+    own_sk: &SecretKey,
+) -> Result<Vec<Signature>, Error> {
+    txs.dlc_transactions
+        .iter()
+        .enumerate()
+        .map(|(i, dlc_transactions)| {
+            Ok(dlc::util::get_raw_sig_for_tx_input(
+                secp,
+                &dlc_transactions.refund,
+                0,
+                &txs.buffer_script_pubkey,
+                txs.buffer_transaction.output[i].value,
+                own_sk,
+            )?)
+        })
+        .collect()
+}
 
-    Ok((
-        CollaborativeCloseOffer {
-            channel_id: signed_channel.channel_id,
-            counter_payout,
-            close_signature,
-        },
-        close_tx,
-    ))
+/// Returns the adaptor signatures for the CETs and the refund signature of
+/// the local party for each of the given contracts.
+fn sign_channel_contracts(
+    secp: &Secp256k1<All>,
+    contracts: &[ChannelContract],
+    txs: &MultiContractChannelTransactions,
+    own_sk: &SecretKey,
+    cancellation: &CancellationToken,
+) -> Result<(Vec<CetAdaptorSignatures>, Vec<Signature>), Error> {
+    let mut cet_adaptor_signatures = Vec::with_capacity(contracts.len());
+    for (i, (contract, dlc_transactions)) in contracts
+        .iter()
+        .zip(txs.dlc_transactions.iter())
+        .enumerate()
+    {
+        cancellation.check()?;
+        let (_, adaptor_sigs) = contract.offered_contract.contract_info[0].get_adaptor_info(
+            secp,
+            contract.offered_contract.total_collateral,
+            own_sk,
+            &txs.buffer_script_pubkey,
+            txs.buffer_transaction.output[i].value,
+            &dlc_transactions.cets,
+            0,
+        )?;
+        cet_adaptor_signatures.push((&adaptor_sigs as &[_]).into());
+    }
+
+    let refund_signatures = get_channel_contracts_refund_signatures(secp, txs, own_sk)?;
+
+    Ok((cet_adaptor_signatures, refund_signatures))
 }
 
-/// Validates the given [`CollaborativeCloseOffer`] and updates the state of the
-/// channel.
-pub fn on_collaborative_close_offer<T: Deref>(
-    signed_channel: &mut SignedChannel,
-    close_offer: &CollaborativeCloseOffer,
-    peer_timeout: u64,
-    time: &T,
-) -> Result<(), Error>
-where
-    T::Target: Time,
-{
-    let total_collateral =
-        signed_channel.own_params.collateral + signed_channel.counter_params.collateral;
+/// Verifies the adaptor signatures for the CETs and the refund signature of
+/// the counter party for each of the given contracts, returning the adaptor
+/// information of each contract.
+fn verify_channel_contracts(
+    secp: &Secp256k1<All>,
+    contracts: &[ChannelContract],
+    txs: &MultiContractChannelTransactions,
+    counter_pk: &PublicKey,
+    cet_adaptor_signatures: &[CetAdaptorSignatures],
+    refund_signatures: &[Signature],
+    cancellation: &CancellationToken,
+) -> Result<Vec<AdaptorInfo>, Error> {
+    if cet_adaptor_signatures.len() != contracts.len() || refund_signatures.len() != contracts.len()
+    {
+        return Err(Error::InvalidParameters(format!(
+            "Expected signatures for {} contracts but got {} CET adaptor signature sets and {} refund signatures.",
+            contracts.len(),
+            cet_adaptor_signatures.len(),
+            refund_signatures.len()
+        )));
+    }
 
-    if close_offer.counter_payout > total_collateral {
-        return Err(Error::InvalidParameters("Received collaborative close offer with counter payout greater than total collateral, ignoring.".to_string()));
+    let mut adaptor_infos = Vec::with_capacity(contracts.len());
+    for (i, (contract, dlc_transactions)) in contracts
+        .iter()
+        .zip(txs.dlc_transactions.iter())
+        .enumerate()
+    {
+        cancellation.check()?;
+        let input_value = txs.buffer_transaction.output[i].value;
+        dlc::verify_tx_input_sig(
+            secp,
+            &refund_signatures[i],
+            &dlc_transactions.refund,
+            0,
+            &txs.buffer_script_pubkey,
+            input_value,
+            counter_pk,
+        )?;
+        let adaptor_sigs: Vec<_> = (&cet_adaptor_signatures[i]).into();
+        let (adaptor_info, _) = contract.offered_contract.contract_info[0]
+            .verify_and_get_adaptor_info(
+                secp,
+                contract.offered_contract.total_collateral,
+                counter_pk,
+                &txs.buffer_script_pubkey,
+                input_value,
+                &dlc_transactions.cets,
+                &adaptor_sigs,
+                0,
+            )?;
+        adaptor_infos.push(adaptor_info);
     }
 
-    if signed_channel.roll_back_state.is_some() {
-        return Err(Error::InvalidState(
-            "Received collaborative close offer in state with rollback, ignoring.".to_string(),
-        ));
+    Ok(adaptor_infos)
+}
+
+/// Returns a [`SignedContract`] for each of the given contracts, using the
+/// transactions of the next channel state and the signatures of both parties.
+fn get_signed_channel_contracts(
+    signed_channel: &SignedChannel,
+    contracts: Vec<ChannelContract>,
+    txs: MultiContractChannelTransactions,
+    adaptor_infos: Vec<AdaptorInfo>,
+    counter_cet_adaptor_signatures: &[CetAdaptorSignatures],
+    counter_refund_signatures: &[Signature],
+    own_refund_signatures: &[Signature],
+) -> Vec<SignedContract> {
+    contracts
+        .into_iter()
+        .zip(txs.dlc_transactions)
+        .zip(adaptor_infos)
+        .enumerate()
+        .map(|(i, ((contract, dlc_transactions), adaptor_info))| {
+            let counter_adaptor_signatures: Vec<EcdsaAdaptorSignature> =
+                (&counter_cet_adaptor_signatures[i]).into();
+            let is_offer_party = contract.offered_contract.is_offer_party;
+            let (offer_refund_signature, accept_refund_signature) = if is_offer_party {
+                (own_refund_signatures[i], counter_refund_signatures[i])
+            } else {
+                (counter_refund_signatures[i], own_refund_signatures[i])
+            };
+            let (offer_adaptor_signatures, accept_adaptor_signatures) = if is_offer_party {
+                (None, Some(counter_adaptor_signatures))
+            } else {
+                (Some(counter_adaptor_signatures), None)
+            };
+            SignedContract {
+                accepted_contract: AcceptedContract {
+                    offered_contract: contract.offered_contract,
+                    accept_params: contract.accept_params,
+                    funding_inputs: Vec::new(),
+                    adaptor_infos: vec![adaptor_info],
+                    adaptor_signatures: accept_adaptor_signatures,
+                    accept_refund_signature: Some(accept_refund_signature),
+                    dlc_transactions,
+                    #[cfg(feature = "taproot")]
+                    accept_taproot_signatures: None,
+                },
+                adaptor_signatures: offer_adaptor_signatures,
+                offer_refund_signature: Some(offer_refund_signature),
+                funding_signatures: FundingSignatures {
+                    funding_signatures: Vec::new(),
+                },
+                channel_id: Some(signed_channel.channel_id),
+                #[cfg(feature = "taproot")]
+                offer_taproot_signatures: None,
+            }
+        })
+        .collect()
+}
+
+/// Returns the contracts currently setup in the channel, the balances of the
+/// local and counter parties and the keys id of the channel, which is expected
+/// to be in [`SignedChannelState::Settled`] or
+/// [`SignedChannelState::ContractsEstablished`] state. As balances are not
+/// recorded in `Settled` state, the balance of the party receiving the update
+/// offer is then the one stated in the offer, the other party owning the rest
+/// of the channel collateral.
+fn get_channel_balances(
+    signed_channel: &SignedChannel,
+    receiver_balance: u64,
+    is_offer: bool,
+) -> Result<(Vec<ContractId>, u64, u64, KeysId), Error> {
+    match &signed_channel.state {
+        SignedChannelState::Settled { keys_id, .. } => {
+            let channel_collateral =
+                signed_channel.own_params.collateral + signed_channel.counter_params.collateral;
+            if receiver_balance > channel_collateral {
+                return Err(Error::InvalidParameters(format!(
+                    "Balance {} is greater than the channel collateral {}.",
+                    receiver_balance, channel_collateral
+                )));
+            }
+            let offerer_balance = channel_collateral - receiver_balance;
+            let (own_balance, counter_balance) = if is_offer {
+                (offerer_balance, receiver_balance)
+            } else {
+                (receiver_balance, offerer_balance)
+            };
+            Ok((Vec::new(), own_balance, counter_balance, *keys_id))
+        }
+        SignedChannelState::ContractsEstablished {
+            contract_ids,
+            own_balance,
+            counter_balance,
+            keys_id,
+            ..
+        } => {
+            let expected = if is_offer {
+                *counter_balance
+            } else {
+                *own_balance
+            };
+            if receiver_balance != expected {
+                return Err(Error::InvalidParameters(format!(
+                    "Stated balance {} does not match the channel balance {}.",
+                    receiver_balance, expected
+                )));
+            }
+            Ok((
+                contract_ids.clone(),
+                *own_balance,
+                *counter_balance,
+                *keys_id,
+            ))
+        }
+        _ => Err(Error::InvalidState(
+            "Channel is not in Settled or ContractsEstablished state.".to_string(),
+        )),
     }
+}
 
-    let offer_payout = total_collateral - close_offer.counter_payout;
-    let fund_output_value = signed_channel.fund_tx.output[signed_channel.fund_output_index].value;
+/// Checks that the terms of a contract offered to be added to a channel can be
+/// used in it, given the balances of the offer and accept parties of the
+/// contract.
+fn check_added_contract_terms<C: Verification>(
+    secp: &Secp256k1<C>,
+    offered_contract: &OfferedContract,
+    offer_balance: u64,
+    accept_balance: u64,
+    now: u64,
+) -> Result<(), Error> {
+    let offer_collateral = offered_contract.offer_params.collateral;
+    let accept_collateral = offered_contract
+        .total_collateral
+        .checked_sub(offer_collateral)
+        .ok_or_else(|| {
+            Error::InvalidParameters(
+                "Offer collateral is greater than the total collateral.".to_string(),
+            )
+        })?;
 
-    let close_tx = dlc::channel::create_collaborative_close_transaction(
-        &signed_channel.counter_params,
-        offer_payout,
-        &signed_channel.own_params,
-        close_offer.counter_payout,
-        OutPoint {
-            txid: signed_channel.fund_tx.txid(),
-            vout: signed_channel.fund_output_index as u32,
-        },
-        fund_output_value,
-    );
+    if offer_collateral > offer_balance || accept_collateral > accept_balance {
+        return Err(Error::InvalidParameters(format!(
+            "Contract collaterals {} and {} exceed the channel balances {} and {}.",
+            offer_collateral, accept_collateral, offer_balance, accept_balance
+        )));
+    }
 
-    let mut state = SignedChannelState::CollaborativeCloseOffered {
-        counter_payout: close_offer.counter_payout,
-        offer_signature: close_offer.close_signature,
-        close_tx,
-        timeout: time.unix_time_now() + peer_timeout,
-        keys_id: signed_channel
-            .keys_id()
-            .ok_or(Error::InvalidState("No keys_id available".to_string()))?,
-    };
+    if offered_contract.contract_info.len() != 1 {
+        return Err(Error::InvalidParameters(
+            "Contracts added to a channel must have a single contract info.".to_string(),
+        ));
+    }
 
-    std::mem::swap(&mut state, &mut signed_channel.state);
-    signed_channel.roll_back_state = Some(state);
+    if (offered_contract.refund_locktime as u64) <= now {
+        return Err(Error::InvalidParameters(format!(
+            "Added contract refund locktime {} is not in the future.",
+            offered_contract.refund_locktime
+        )));
+    }
+
+    offered_contract.validate()?;
+
+    for announcement in offered_contract
+        .contract_info
+        .iter()
+        .flat_map(|x| x.oracle_announcements.iter())
+    {
+        crate::utils::validate_announcement(
+            secp,
+            announcement,
+            offered_contract.cet_locktime,
+            offered_contract.refund_locktime,
+        )?;
+    }
 
     Ok(())
 }
 
-/// Accept an offer to collaboratively close the channel, signing the
-/// closing transaction and returning it.
-pub fn accept_collaborative_close_offer<C: Signing, SP: Deref>(
+/// Returns the per update point to be used by the local party for the next
+/// channel state.
+fn get_next_per_update_point<SP: Deref>(
+    secp: &Secp256k1<All>,
+    signed_channel: &SignedChannel,
+    signer_provider: &SP,
+) -> Result<PublicKey, Error>
+where
+    SP::Target: ContractSignerProvider,
+{
+    let per_update_seed =
+        signer_provider.get_secret_key_for_pubkey(&signed_channel.own_per_update_seed)?;
+
+    let per_update_secret = SecretKey::from_slice(&build_commitment_secret(
+        per_update_seed.as_ref(),
+        signed_channel.update_idx - 1,
+    ))?;
+
+    Ok(PublicKey::from_secret_key(secp, &per_update_secret))
+}
+
+/// Creates an [`AddContractOffer`] message and [`OfferedContract`] to add a
+/// contract to the given channel, locking part of the balances of the parties.
+/// Expects the channel to be in [`SignedChannelState::Settled`] or
+/// [`SignedChannelState::ContractsEstablished`] state.
+pub fn add_contract_offer<SP: Deref, T: Deref>(
+    secp: &Secp256k1<All>,
+    signed_channel: &mut SignedChannel,
+    contract_input: &ContractInput,
+    oracle_announcements: Vec<Vec<OracleAnnouncement>>,
+    counter_balance: u64,
+    refund_delay: u32,
+    peer_timeout: u64,
+    cet_nsequence: u32,
+    signer_provider: &SP,
+    time: &T,
+) -> Result<(AddContractOffer, OfferedContract), Error>
+where
+    SP::Target: ContractSignerProvider,
+    T::Target: Time,
+{
+    check_channel_contract_input(contract_input)?;
+    let (contract_ids, own_balance, counter_balance, keys_id) =
+        get_channel_balances(signed_channel, counter_balance, true)?;
+
+    let mut offer_params = signed_channel.own_params.clone();
+    offer_params.collateral = contract_input.offer_collateral;
+
+    let mut offered_contract = OfferedContract::new(
+        get_new_temporary_id(),
+        contract_input,
+        oracle_announcements,
+        &offer_params,
+        &[],
+        &signed_channel.counter_party,
+        refund_delay,
+        time.unix_time_now() as u32,
+        keys_id,
+    );
+
+    offered_contract.fund_output_serial_id = 0;
+    offered_contract.fee_rate_per_vb = signed_channel.fee_rate_per_vb;
+
+    check_added_contract_terms(
+        secp,
+        &offered_contract,
+        own_balance,
+        counter_balance,
+        time.unix_time_now(),
+    )?;
+
+    let next_per_update_point = get_next_per_update_point(secp, signed_channel, signer_provider)?;
+
+    let mut state = SignedChannelState::ContractUpdateOffered {
+        contract_ids,
+        added_contract_id: Some(offered_contract.id),
+        settled_contract_id: None,
+        own_balance: own_balance - contract_input.offer_collateral,
+        counter_balance: counter_balance - contract_input.accept_collateral,
+        offer_next_per_update_point: next_per_update_point,
+        is_offer: true,
+        timeout: time.unix_time_now() + peer_timeout,
+        keys_id,
+    };
+
+    std::mem::swap(&mut signed_channel.state, &mut state);
+    signed_channel.roll_back_state = Some(state);
+
+    let msg = AddContractOffer {
+        channel_id: signed_channel.channel_id,
+        temporary_contract_id: offered_contract.id,
+        offer_collateral: contract_input.offer_collateral,
+        accept_collateral: contract_input.accept_collateral,
+        counter_balance,
+        next_per_update_point,
+        contract_info: (&offered_contract).into(),
+        cet_locktime: offered_contract.cet_locktime,
+        refund_locktime: offered_contract.refund_locktime,
+        cet_nsequence,
+    };
+
+    Ok((msg, offered_contract))
+}
+
+/// Update the state of the given [`SignedChannel`] from the given
+/// [`AddContractOffer`], returning the [`OfferedContract`] to be added to the
+/// channel. Expects the channel to be in [`SignedChannelState::Settled`] or
+/// [`SignedChannelState::ContractsEstablished`] state.
+pub fn on_add_contract_offer<T: Deref>(
+    secp: &Secp256k1<All>,
+    signed_channel: &mut SignedChannel,
+    add_contract_offer: &AddContractOffer,
+    policy: &ChannelPolicy,
+    peer_timeout: u64,
+    time: &T,
+) -> Result<OfferedContract, Error>
+where
+    T::Target: Time,
+{
+    let (contract_ids, own_balance, counter_balance, keys_id) =
+        get_channel_balances(signed_channel, add_contract_offer.counter_balance, false)?;
+
+    let mut offer_params = signed_channel.counter_params.clone();
+    offer_params.collateral = add_contract_offer.offer_collateral;
+
+    let total_collateral = add_contract_offer
+        .offer_collateral
+        .checked_add(add_contract_offer.accept_collateral)
+        .ok_or_else(|| Error::InvalidParameters("Invalid contract collateral.".to_string()))?;
+
+    let offered_contract = OfferedContract {
+        id: add_contract_offer.temporary_contract_id,
+        is_offer_party: false,
+        contract_info: crate::conversion_utils::get_contract_info_and_announcements(
+            &add_contract_offer.contract_info,
+        )?,
+        counter_party: signed_channel.counter_party,
+        offer_params,
+        total_collateral,
+        funding_inputs: Vec::new(),
+        fund_output_serial_id: 0,
+        fee_rate_per_vb: signed_channel.fee_rate_per_vb,
+        cet_locktime: add_contract_offer.cet_locktime,
+        refund_locktime: add_contract_offer.refund_locktime,
+        keys_id,
+        anchor_outputs: false,
+        fee_split: FeeSplit::Equal,
+        expiry: None,
+        metadata: None,
+        taproot: false,
+    };
+
+    check_added_contract_terms(
+        secp,
+        &offered_contract,
+        counter_balance,
+        own_balance,
+        time.unix_time_now(),
+    )?;
+
+    let own_balance = own_balance - add_contract_offer.accept_collateral;
+    let counter_balance = counter_balance - add_contract_offer.offer_collateral;
+    let channel_collateral =
+        signed_channel.own_params.collateral + signed_channel.counter_params.collateral;
+
+    check_policy_balances(policy, &[own_balance, counter_balance])?;
+    check_policy_contracts_value(
+        policy,
+        channel_collateral.saturating_sub(own_balance + counter_balance),
+    )?;
+
+    let mut state = SignedChannelState::ContractUpdateOffered {
+        contract_ids,
+        added_contract_id: Some(offered_contract.id),
+        settled_contract_id: None,
+        own_balance,
+        counter_balance,
+        offer_next_per_update_point: add_contract_offer.next_per_update_point,
+        is_offer: false,
+        timeout: time.unix_time_now() + peer_timeout,
+        keys_id,
+    };
+
+    std::mem::swap(&mut signed_channel.state, &mut state);
+    signed_channel.roll_back_state = Some(state);
+
+    Ok(offered_contract)
+}
+
+/// Returns the established contract ids of the given channel without the one
+/// to be settled, as well as the collateral of the contract, checking that the
+/// payout offered to settle it does not exceed it. Expects the channel to be in
+/// [`SignedChannelState::ContractsEstablished`] state.
+fn get_settle_contract_terms(
+    signed_channel: &SignedChannel,
+    contract: &SignedContract,
+    counter_payout: u64,
+) -> Result<(Vec<ContractId>, u64, u64, KeysId), Error> {
+    let (own_balance, counter_balance, keys_id, contract_ids) = get_signed_channel_state!(
+        signed_channel,
+        ContractsEstablished,
+        own_balance,
+        counter_balance,
+        keys_id | contract_ids
+    )?;
+
+    let contract_id = contract.accepted_contract.get_contract_id();
+    if !contract_ids.contains(&contract_id) {
+        return Err(Error::InvalidParameters(format!(
+            "Contract {:02x?} is not established in the channel.",
+            contract_id
+        )));
+    }
+
+    let total_collateral = contract.accepted_contract.offered_contract.total_collateral;
+    if counter_payout > total_collateral {
+        return Err(Error::InvalidParameters(format!(
+            "Payout {} is greater than the contract collateral {}.",
+            counter_payout, total_collateral
+        )));
+    }
+
+    let remaining_ids = contract_ids
+        .iter()
+        .filter(|x| **x != contract_id)
+        .cloned()
+        .collect();
+
+    Ok((remaining_ids, own_balance, counter_balance, keys_id))
+}
+
+/// Creates a [`SettleContractOffer`] message to settle the given contract of
+/// the channel, returning its collateral to the balances of the parties.
+/// Expects the channel to be in [`SignedChannelState::ContractsEstablished`]
+/// state.
+pub fn settle_contract_offer<SP: Deref, T: Deref>(
+    secp: &Secp256k1<All>,
+    signed_channel: &mut SignedChannel,
+    contract: &SignedContract,
+    counter_payout: u64,
+    peer_timeout: u64,
+    signer_provider: &SP,
+    time: &T,
+) -> Result<SettleContractOffer, Error>
+where
+    SP::Target: ContractSignerProvider,
+    T::Target: Time,
+{
+    let (contract_ids, own_balance, counter_balance, keys_id) =
+        get_settle_contract_terms(signed_channel, contract, counter_payout)?;
+
+    let next_per_update_point = get_next_per_update_point(secp, signed_channel, signer_provider)?;
+    let contract_id = contract.accepted_contract.get_contract_id();
+    let own_payout = contract.accepted_contract.offered_contract.total_collateral - counter_payout;
+
+    let mut state = SignedChannelState::ContractUpdateOffered {
+        contract_ids,
+        added_contract_id: None,
+        settled_contract_id: Some(contract_id),
+        own_balance: own_balance + own_payout,
+        counter_balance: counter_balance + counter_payout,
+        offer_next_per_update_point: next_per_update_point,
+        is_offer: true,
+        timeout: time.unix_time_now() + peer_timeout,
+        keys_id,
+    };
+
+    std::mem::swap(&mut signed_channel.state, &mut state);
+    signed_channel.roll_back_state = Some(state);
+
+    Ok(SettleContractOffer {
+        channel_id: signed_channel.channel_id,
+        contract_id,
+        counter_payout,
+        next_per_update_point,
+    })
+}
+
+/// Update the state of the given [`SignedChannel`] from the given
+/// [`SettleContractOffer`]. Expects the channel to be in
+/// [`SignedChannelState::ContractsEstablished`] state and the given contract
+/// to be the one offered to be settled.
+pub fn on_settle_contract_offer<T: Deref>(
+    signed_channel: &mut SignedChannel,
+    settle_contract_offer: &SettleContractOffer,
+    contract: &SignedContract,
+    policy: &ChannelPolicy,
+    peer_timeout: u64,
+    time: &T,
+) -> Result<(), Error>
+where
+    T::Target: Time,
+{
+    let (contract_ids, own_balance, counter_balance, keys_id) = get_settle_contract_terms(
+        signed_channel,
+        contract,
+        settle_contract_offer.counter_payout,
+    )?;
+
+    let counter_payout = contract.accepted_contract.offered_contract.total_collateral
+        - settle_contract_offer.counter_payout;
+    let own_balance = own_balance + settle_contract_offer.counter_payout;
+    let counter_balance = counter_balance + counter_payout;
+
+    // Settling a contract only increases the balances, so the reserve is not
+    // checked to avoid locking funds in contracts of channels below it.
+    check_policy_dust_limit(policy, &[own_balance, counter_balance])?;
+
+    let mut state = SignedChannelState::ContractUpdateOffered {
+        contract_ids,
+        added_contract_id: None,
+        settled_contract_id: Some(settle_contract_offer.contract_id),
+        own_balance,
+        counter_balance,
+        offer_next_per_update_point: settle_contract_offer.next_per_update_point,
+        is_offer: false,
+        timeout: time.unix_time_now() + peer_timeout,
+        keys_id,
+    };
+
+    std::mem::swap(&mut signed_channel.state, &mut state);
+    signed_channel.roll_back_state = Some(state);
+
+    Ok(())
+}
+
+/// Creates a [`ContractUpdateAccept`] message to accept the offered update of
+/// the contracts of the channel, signing the buffer transaction and the
+/// contracts of the next channel state. Expects the channel to be in
+/// [`SignedChannelState::ContractUpdateOffered`] state, `contracts` to be the
+/// remaining contracts of the channel and `added_contract` the offered
+/// contract, if any.
+pub fn accept_contract_update<SP: Deref, T: Deref>(
+    secp: &Secp256k1<All>,
+    signed_channel: &mut SignedChannel,
+    contracts: &[SignedContract],
+    added_contract: Option<&OfferedContract>,
+    cet_nsequence: u32,
+    peer_timeout: u64,
+    signer_provider: &SP,
+    time: &T,
+    cancellation: &CancellationToken,
+) -> Result<ContractUpdateAccept, Error>
+where
+    SP::Target: ContractSignerProvider,
+    T::Target: Time,
+{
+    let (
+        offer_per_update_point,
+        own_balance,
+        counter_balance,
+        is_offer,
+        added_contract_id,
+        settled_contract_id,
+        keys_id,
+        contract_ids,
+    ) = get_signed_channel_state!(
+        signed_channel,
+        ContractUpdateOffered,
+        offer_next_per_update_point,
+        own_balance,
+        counter_balance,
+        is_offer,
+        added_contract_id,
+        settled_contract_id,
+        keys_id | contract_ids
+    )?;
+
+    if is_offer {
+        return Err(Error::InvalidState(
+            "Cannot accept own contract update offer.".to_string(),
+        ));
+    }
+
+    let contract_ids = contract_ids.clone();
+    let contract_signer = signer_provider.derive_contract_signer(keys_id)?;
+    let own_base_secret_key =
+        signer_provider.get_secret_key_for_pubkey(&signed_channel.own_points.own_basepoint)?;
+
+    let accept_per_update_point = get_next_per_update_point(secp, signed_channel, signer_provider)?;
+
+    let (offer_revoke_params, accept_revoke_params) = get_update_revoke_params(
+        secp,
+        signed_channel,
+        false,
+        &offer_per_update_point,
+        &accept_per_update_point,
+    );
+
+    let contracts = get_channel_contracts(signed_channel, contracts, added_contract);
+    let txs = create_channel_contracts_transactions(
+        signed_channel,
+        &offer_revoke_params,
+        &accept_revoke_params,
+        &contracts,
+        counter_balance,
+        own_balance,
+        cet_nsequence,
+    )?;
+
+    let buffer_adaptor_signature = get_tx_adaptor_signature(
+        secp,
+        &txs.buffer_transaction,
+        signed_channel.fund_tx.output[signed_channel.fund_output_index].value,
+        &signed_channel.fund_script_pubkey,
+        &contract_signer.get_secret_key()?,
+        &offer_revoke_params.publish_pk.inner,
+    )?;
+
+    let own_secret_key = derive_private_key(secp, &accept_per_update_point, &own_base_secret_key);
+
+    let (cet_adaptor_signatures, refund_signatures) =
+        sign_channel_contracts(secp, &contracts, &txs, &own_secret_key, cancellation)?;
+
+    signed_channel.state = SignedChannelState::ContractUpdateAccepted {
+        contract_ids,
+        added_contract_id,
+        settled_contract_id,
+        own_balance,
+        counter_balance,
+        offer_per_update_point,
+        accept_per_update_point,
+        buffer_transaction: txs.buffer_transaction,
+        buffer_script_pubkey: txs.buffer_script_pubkey,
+        accept_buffer_adaptor_signature: buffer_adaptor_signature,
+        timeout: time.unix_time_now() + peer_timeout,
+        keys_id,
+    };
+
+    Ok(ContractUpdateAccept {
+        channel_id: signed_channel.channel_id,
+        next_per_update_point: accept_per_update_point,
+        buffer_adaptor_signature,
+        cet_adaptor_signatures,
+        refund_signatures,
+    })
+}
+
+/// Creates a [`ContractUpdateConfirm`] message from the given [`SignedChannel`]
+/// and [`ContractUpdateAccept`] message, verifying the message and returning
+/// the contracts of the next channel state. Expects the channel to be in
+/// [`SignedChannelState::ContractUpdateOffered`] state with the local party as
+/// offer party.
+pub fn verify_contract_update_accept_and_confirm<SP: Deref, T: Deref>(
+    secp: &Secp256k1<All>,
+    contract_update_accept: &ContractUpdateAccept,
+    signed_channel: &mut SignedChannel,
+    contracts: &[SignedContract],
+    added_contract: Option<&OfferedContract>,
+    cet_nsequence: u32,
+    peer_timeout: u64,
+    signer_provider: &SP,
+    time: &T,
+    cancellation: &CancellationToken,
+) -> Result<(Vec<SignedContract>, ContractUpdateConfirm), Error>
+where
+    SP::Target: ContractSignerProvider,
+    T::Target: Time,
+{
+    let (
+        offer_per_update_point,
+        own_balance,
+        counter_balance,
+        is_offer,
+        added_contract_id,
+        settled_contract_id,
+        keys_id,
+        contract_ids,
+    ) = get_signed_channel_state!(
+        signed_channel,
+        ContractUpdateOffered,
+        offer_next_per_update_point,
+        own_balance,
+        counter_balance,
+        is_offer,
+        added_contract_id,
+        settled_contract_id,
+        keys_id | contract_ids
+    )?;
+
+    if !is_offer {
+        return Err(Error::InvalidState(
+            "Received contract update accept for an update offered by the counter party."
+                .to_string(),
+        ));
+    }
+
+    let contract_ids = contract_ids.clone();
+    let contract_signer = signer_provider.derive_contract_signer(keys_id)?;
+    let own_base_secret_key =
+        signer_provider.get_secret_key_for_pubkey(&signed_channel.own_points.own_basepoint)?;
+    let per_update_seed =
+        signer_provider.get_secret_key_for_pubkey(&signed_channel.own_per_update_seed)?;
+
+    let prev_per_update_secret = SecretKey::from_slice(&build_commitment_secret(
+        per_update_seed.as_ref(),
+        signed_channel.update_idx,
+    ))?;
+
+    let (offer_revoke_params, accept_revoke_params) = get_update_revoke_params(
+        secp,
+        signed_channel,
+        true,
+        &offer_per_update_point,
+        &contract_update_accept.next_per_update_point,
+    );
+
+    let contracts = get_channel_contracts(signed_channel, contracts, added_contract);
+    let txs = create_channel_contracts_transactions(
+        signed_channel,
+        &offer_revoke_params,
+        &accept_revoke_params,
+        &contracts,
+        own_balance,
+        counter_balance,
+        cet_nsequence,
+    )?;
+
+    let fund_output_value = signed_channel.fund_tx.output[signed_channel.fund_output_index].value;
+
+    verify_tx_adaptor_signature(
+        secp,
+        &txs.buffer_transaction,
+        fund_output_value,
+        &signed_channel.fund_script_pubkey,
+        &signed_channel.counter_params.fund_pubkey,
+        &offer_revoke_params.publish_pk.inner,
+        &contract_update_accept.buffer_adaptor_signature,
+    )?;
+
+    let adaptor_infos = verify_channel_contracts(
+        secp,
+        &contracts,
+        &txs,
+        &accept_revoke_params.own_pk.inner,
+        &contract_update_accept.cet_adaptor_signatures,
+        &contract_update_accept.refund_signatures,
+        cancellation,
+    )?;
+
+    let own_secret_key = derive_private_key(secp, &offer_per_update_point, &own_base_secret_key);
+
+    let (cet_adaptor_signatures, refund_signatures) =
+        sign_channel_contracts(secp, &contracts, &txs, &own_secret_key, cancellation)?;
+
+    let own_buffer_adaptor_signature = get_tx_adaptor_signature(
+        secp,
+        &txs.buffer_transaction,
+        fund_output_value,
+        &signed_channel.fund_script_pubkey,
+        &contract_signer.get_secret_key()?,
+        &accept_revoke_params.publish_pk.inner,
+    )?;
+
+    let state = SignedChannelState::ContractUpdateConfirmed {
+        contract_ids,
+        added_contract_id,
+        settled_contract_id,
+        own_balance,
+        counter_balance,
+        offer_per_update_point,
+        accept_per_update_point: contract_update_accept.next_per_update_point,
+        buffer_transaction: txs.buffer_transaction.clone(),
+        buffer_script_pubkey: txs.buffer_script_pubkey.clone(),
+        offer_buffer_adaptor_signature: own_buffer_adaptor_signature,
+        accept_buffer_adaptor_signature: contract_update_accept.buffer_adaptor_signature,
+        timeout: time.unix_time_now() + peer_timeout,
+        keys_id,
+    };
+
+    let signed_contracts = get_signed_channel_contracts(
+        signed_channel,
+        contracts,
+        txs,
+        adaptor_infos,
+        &contract_update_accept.cet_adaptor_signatures,
+        &contract_update_accept.refund_signatures,
+        &refund_signatures,
+    );
+
+    signed_channel.state = state;
+
+    let msg = ContractUpdateConfirm {
+        channel_id: signed_channel.channel_id,
+        per_update_secret: prev_per_update_secret,
+        buffer_adaptor_signature: own_buffer_adaptor_signature,
+        cet_adaptor_signatures,
+        refund_signatures,
+    };
+
+    Ok((signed_contracts, msg))
+}
+
+/// Creates a [`ContractUpdateFinalize`] message from the given
+/// [`SignedChannel`] and [`ContractUpdateConfirm`] message, verifying the
+/// message, updating the state of the channel and returning the contracts of
+/// the new channel state. Expects the channel to be in
+/// [`SignedChannelState::ContractUpdateAccepted`] state.
+pub fn verify_contract_update_confirm_and_finalize<SP: Deref>(
+    secp: &Secp256k1<All>,
+    signed_channel: &mut SignedChannel,
+    contracts: &[SignedContract],
+    added_contract: Option<&OfferedContract>,
+    contract_update_confirm: &ContractUpdateConfirm,
+    cet_nsequence: u32,
+    signer_provider: &SP,
+    cancellation: &CancellationToken,
+) -> Result<(Vec<SignedContract>, ContractUpdateFinalize), Error>
+where
+    SP::Target: ContractSignerProvider,
+{
+    let (
+        own_balance,
+        counter_balance,
+        offer_per_update_point,
+        accept_per_update_point,
+        accept_buffer_adaptor_signature,
+        added_contract_id,
+        keys_id,
+        contract_ids,
+        buffer_transaction,
+    ) = get_signed_channel_state!(
+        signed_channel,
+        ContractUpdateAccepted,
+        own_balance,
+        counter_balance,
+        offer_per_update_point,
+        accept_per_update_point,
+        accept_buffer_adaptor_signature,
+        added_contract_id,
+        keys_id | contract_ids,
+        buffer_transaction
+    )?;
+
+    let next_contract_ids = get_next_contract_ids(signed_channel, contract_ids, &added_contract_id);
+    let buffer_transaction = buffer_transaction.clone();
+
+    let (offer_revoke_params, accept_revoke_params) = get_update_revoke_params(
+        secp,
+        signed_channel,
+        false,
+        &offer_per_update_point,
+        &accept_per_update_point,
+    );
+
+    let contracts = get_channel_contracts(signed_channel, contracts, added_contract);
+    let txs = create_channel_contracts_transactions(
+        signed_channel,
+        &offer_revoke_params,
+        &accept_revoke_params,
+        &contracts,
+        counter_balance,
+        own_balance,
+        cet_nsequence,
+    )?;
+
+    if txs.buffer_transaction != buffer_transaction {
+        return Err(Error::InvalidState(
+            "Contracts do not match the accepted channel state.".to_string(),
+        ));
+    }
+
+    verify_tx_adaptor_signature(
+        secp,
+        &buffer_transaction,
+        signed_channel.fund_tx.output[signed_channel.fund_output_index].value,
+        &signed_channel.fund_script_pubkey,
+        &signed_channel.counter_params.fund_pubkey,
+        &accept_revoke_params.publish_pk.inner,
+        &contract_update_confirm.buffer_adaptor_signature,
+    )?;
+
+    let adaptor_infos = verify_channel_contracts(
+        secp,
+        &contracts,
+        &txs,
+        &offer_revoke_params.own_pk.inner,
+        &contract_update_confirm.cet_adaptor_signatures,
+        &contract_update_confirm.refund_signatures,
+        cancellation,
+    )?;
+
+    let own_base_secret_key =
+        signer_provider.get_secret_key_for_pubkey(&signed_channel.own_points.own_basepoint)?;
+    let own_secret_key = derive_private_key(secp, &accept_per_update_point, &own_base_secret_key);
+    let own_refund_signatures =
+        get_channel_contracts_refund_signatures(secp, &txs, &own_secret_key)?;
+
+    let signed_contracts = get_signed_channel_contracts(
+        signed_channel,
+        contracts,
+        txs,
+        adaptor_infos,
+        &contract_update_confirm.cet_adaptor_signatures,
+        &contract_update_confirm.refund_signatures,
+        &own_refund_signatures,
+    );
+
+    signed_channel.state = SignedChannelState::ContractsEstablished {
+        contract_ids: next_contract_ids,
+        own_balance,
+        counter_balance,
+        own_buffer_adaptor_signature: accept_buffer_adaptor_signature,
+        counter_buffer_adaptor_signature: contract_update_confirm.buffer_adaptor_signature,
+        buffer_transaction,
+        is_offer: false,
+        keys_id,
+    };
+
+    signed_channel.update_idx -= 1;
+
+    signed_channel
+        .counter_party_commitment_secrets
+        .provide_secret(
+            signed_channel.update_idx + 1,
+            *contract_update_confirm.per_update_secret.as_ref(),
+        )
+        .map_err(|_| Error::InvalidParameters("Provided secret was invalid".to_string()))?;
+
+    signed_channel.counter_per_update_point = offer_per_update_point;
+    signed_channel.own_per_update_point = accept_per_update_point;
+
+    let per_update_seed =
+        signer_provider.get_secret_key_for_pubkey(&signed_channel.own_per_update_seed)?;
+
+    let prev_per_update_secret = SecretKey::from_slice(&build_commitment_secret(
+        per_update_seed.as_ref(),
+        signed_channel.update_idx + 1,
+    ))?;
+
+    let msg = ContractUpdateFinalize {
+        channel_id: signed_channel.channel_id,
+        per_update_secret: prev_per_update_secret,
+    };
+
+    Ok((signed_contracts, msg))
+}
+
+/// Verify the given [`ContractUpdateFinalize`] and update the state of the
+/// channel. Expects the channel to be in
+/// [`SignedChannelState::ContractUpdateConfirmed`] state.
+pub fn contract_update_on_finalize(
+    signed_channel: &mut SignedChannel,
+    contract_update_finalize: &ContractUpdateFinalize,
+) -> Result<(), Error> {
+    let update_idx = signed_channel.update_idx;
+
+    establish_confirmed_contract_update(signed_channel)?;
+
+    signed_channel
+        .counter_party_commitment_secrets
+        .provide_secret(
+            update_idx,
+            *contract_update_finalize.per_update_secret.as_ref(),
+        )
+        .map_err(|_| Error::InvalidParameters("Provided secret was invalid".to_string()))?;
+
+    signed_channel.roll_back_state = None;
+
+    Ok(())
+}
+
+/// Update the state of a channel in
+/// [`SignedChannelState::ContractUpdateConfirmed`] state to the state agreed
+/// upon in the update, without waiting for the [`ContractUpdateFinalize`]
+/// message of the counter party. Used when the channel needs to be closed, as
+/// the per update secret of the previous state was already revealed.
+pub fn establish_confirmed_contract_update(
+    signed_channel: &mut SignedChannel,
+) -> Result<(), Error> {
+    let (
+        own_balance,
+        counter_balance,
+        offer_per_update_point,
+        accept_per_update_point,
+        offer_buffer_adaptor_signature,
+        accept_buffer_adaptor_signature,
+        added_contract_id,
+        keys_id,
+        contract_ids,
+        buffer_transaction,
+    ) = get_signed_channel_state!(
+        signed_channel,
+        ContractUpdateConfirmed,
+        own_balance,
+        counter_balance,
+        offer_per_update_point,
+        accept_per_update_point,
+        offer_buffer_adaptor_signature,
+        accept_buffer_adaptor_signature,
+        added_contract_id,
+        keys_id | contract_ids,
+        buffer_transaction
+    )?;
+
+    let state = SignedChannelState::ContractsEstablished {
+        contract_ids: get_next_contract_ids(signed_channel, contract_ids, &added_contract_id),
+        own_balance,
+        counter_balance,
+        own_buffer_adaptor_signature: offer_buffer_adaptor_signature,
+        counter_buffer_adaptor_signature: accept_buffer_adaptor_signature,
+        buffer_transaction: buffer_transaction.clone(),
+        is_offer: true,
+        keys_id,
+    };
+
+    signed_channel.own_per_update_point = offer_per_update_point;
+    signed_channel.counter_per_update_point = accept_per_update_point;
+    signed_channel.state = state;
+    signed_channel.update_idx -= 1;
+
+    Ok(())
+}
+
+/// Creates a [`Reject`] message and rolls back the state of the channel. Expects
+/// the channel to be in [`SignedChannelState::ContractUpdateOffered`] state and
+/// the local party not to be the offer party.
+pub fn reject_contract_update_offer(signed_channel: &mut SignedChannel) -> Result<Reject, Error> {
+    let is_offer = get_signed_channel_state!(signed_channel, ContractUpdateOffered, is_offer)?;
+
+    if is_offer {
+        return Err(Error::InvalidState(
+            "Cannot reject own contract update offer.".to_string(),
+        ));
+    }
+
+    signed_channel.state = signed_channel
+        .roll_back_state
+        .take()
+        .expect("to have a rollback state");
+
+    Ok(Reject {
+        channel_id: signed_channel.channel_id,
+    })
+}
+
+/// Creates a [`CollaborativeCloseOffer`] message and update the state of the
+/// given [`SignedChannel`].
+pub fn offer_collaborative_close<C: Signing, SP: Deref, T: Deref>(
+    secp: &Secp256k1<C>,
+    signed_channel: &mut SignedChannel,
+    counter_payout: u64,
+    signer_provider: &SP,
+    time: &T,
+) -> Result<(CollaborativeCloseOffer, Transaction), Error>
+where
+    SP::Target: ContractSignerProvider,
+    T::Target: Time,
+{
+    if counter_payout
+        > signed_channel.counter_params.collateral + signed_channel.own_params.collateral
+    {
+        return Err(Error::InvalidParameters(
+            "Counter payout is greater than total collateral".to_string(),
+        ));
+    }
+
+    let total_collateral =
+        signed_channel.own_params.collateral + signed_channel.counter_params.collateral;
+    let offer_payout = total_collateral - counter_payout;
+    let fund_output_value = signed_channel.fund_tx.output[signed_channel.fund_output_index].value;
+
+    let close_tx = dlc::channel::create_collaborative_close_transaction(
+        &signed_channel.own_params,
+        offer_payout,
+        &signed_channel.counter_params,
+        counter_payout,
+        OutPoint {
+            txid: signed_channel.fund_tx.txid(),
+            vout: signed_channel.fund_output_index as u32,
+        },
+        fund_output_value,
+    );
+
+    let keys_id = signed_channel
+        .keys_id()
+        .ok_or(Error::InvalidState("No keys_id available".to_string()))?;
+    let contract_signer = signer_provider.derive_contract_signer(keys_id)?;
+
+    let close_signature = dlc::util::get_raw_sig_for_tx_input(
+        secp,
+        &close_tx,
+        0,
+        &signed_channel.fund_script_pubkey,
+        fund_output_value,
+        &contract_signer.get_secret_key()?,
+    )?;
+
+    let mut state = SignedChannelState::CollaborativeCloseOffered {
+        counter_payout,
+        offer_signature: close_signature,
+        close_tx: close_tx.clone(),
+        timeout: time.unix_time_now() + signed_channel.config.peer_timeout,
+        keys_id: signed_channel
+            .keys_id()
+            .ok_or(Error::InvalidState("No keys_id available".to_string()))?,
+    };
+    std::mem::swap(&mut state, &mut signed_channel.state);
+    signed_channel.roll_back_state = Some(state);
+
+    Ok((
+        CollaborativeCloseOffer {
+            channel_id: signed_channel.channel_id,
+            counter_payout,
+            close_signature,
+        },
+        close_tx,
+    ))
+}
+
+/// Validates the given [`CollaborativeCloseOffer`] and updates the state of the
+/// channel.
+pub fn on_collaborative_close_offer<T: Deref>(
+    signed_channel: &mut SignedChannel,
+    close_offer: &CollaborativeCloseOffer,
+    peer_timeout: u64,
+    time: &T,
+) -> Result<(), Error>
+where
+    T::Target: Time,
+{
+    let total_collateral =
+        signed_channel.own_params.collateral + signed_channel.counter_params.collateral;
+
+    if close_offer.counter_payout > total_collateral {
+        return Err(Error::InvalidParameters("Received collaborative close offer with counter payout greater than total collateral, ignoring.".to_string()));
+    }
+
+    if signed_channel.roll_back_state.is_some() {
+        return Err(Error::InvalidState(
+            "Received collaborative close offer in state with rollback, ignoring.".to_string(),
+        ));
+    }
+
+    let offer_payout = total_collateral - close_offer.counter_payout;
+    let fund_output_value = signed_channel.fund_tx.output[signed_channel.fund_output_index].value;
+
+    let close_tx = dlc::channel::create_collaborative_close_transaction(
+        &signed_channel.counter_params,
+        offer_payout,
+        &signed_channel.own_params,
+        close_offer.counter_payout,
+        OutPoint {
+            txid: signed_channel.fund_tx.txid(),
+            vout: signed_channel.fund_output_index as u32,
+        },
+        fund_output_value,
+    );
+
+    let mut state = SignedChannelState::CollaborativeCloseOffered {
+        counter_payout: close_offer.counter_payout,
+        offer_signature: close_offer.close_signature,
+        close_tx,
+        timeout: time.unix_time_now() + peer_timeout,
+        keys_id: signed_channel
+            .keys_id()
+            .ok_or(Error::InvalidState("No keys_id available".to_string()))?,
+    };
+
+    std::mem::swap(&mut state, &mut signed_channel.state);
+    signed_channel.roll_back_state = Some(state);
+
+    Ok(())
+}
+
+/// Accept an offer to collaboratively close the channel, signing the
+/// closing transaction and returning it.
+pub fn accept_collaborative_close_offer<C: Signing, SP: Deref>(
     secp: &Secp256k1<C>,
     signed_channel: &mut SignedChannel,
     signer_provider: &SP,
@@ -1717,6 +3188,129 @@ where
     Ok(close_tx)
 }
 
+/// Returns a [`PartiallySignedTransaction`] for the collaborative close
+/// transaction of the given [`SignedChannel`], containing the signature of the
+/// counter party, so that the local signature can be produced by an external
+/// signer. Expects the channel to be in
+/// [`SignedChannelState::CollaborativeCloseOffered`] state with the offer having
+/// been received from the counter party.
+pub fn get_collaborative_close_psbt<C: Verification>(
+    secp: &Secp256k1<C>,
+    signed_channel: &SignedChannel,
+) -> Result<PartiallySignedTransaction, Error> {
+    let (offer_signature, close_tx) = get_signed_channel_state!(
+        signed_channel,
+        CollaborativeCloseOffered,
+        offer_signature | close_tx
+    )?;
+
+    let fund_output = signed_channel.fund_tx.output[signed_channel.fund_output_index].clone();
+
+    dlc::verify_tx_input_sig(
+        secp,
+        &offer_signature,
+        close_tx,
+        0,
+        &signed_channel.fund_script_pubkey,
+        fund_output.value,
+        &signed_channel.counter_params.fund_pubkey,
+    )
+    .map_err(|_| {
+        Error::InvalidState(
+            "Collaborative close offer was not received from the counter party.".to_string(),
+        )
+    })?;
+
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(close_tx.clone())
+        .map_err(|_| Error::InvalidState("Tried to create PSBT from signed tx".to_string()))?;
+
+    psbt.inputs[0].witness_utxo = Some(fund_output);
+    psbt.inputs[0].witness_script = Some(signed_channel.fund_script_pubkey.clone());
+    psbt.inputs[0].partial_sigs.insert(
+        bitcoin::PublicKey::new(signed_channel.counter_params.fund_pubkey),
+        bitcoin::ecdsa::Signature::sighash_all(offer_signature),
+    );
+
+    Ok(psbt)
+}
+
+/// Accept an offer to collaboratively close the channel using a
+/// [`PartiallySignedTransaction`] obtained from [`get_collaborative_close_psbt`]
+/// and containing the signature of the local party for the funding output,
+/// returning the fully signed closing transaction.
+pub fn accept_collaborative_close_offer_with_psbt<C: Verification>(
+    secp: &Secp256k1<C>,
+    signed_channel: &mut SignedChannel,
+    psbt: &PartiallySignedTransaction,
+) -> Result<Transaction, Error> {
+    let (offer_signature, close_tx) = get_signed_channel_state!(
+        signed_channel,
+        CollaborativeCloseOffered,
+        offer_signature | close_tx
+    )?;
+
+    if &psbt.unsigned_tx != close_tx {
+        return Err(Error::InvalidParameters(
+            "PSBT does not match the collaborative close transaction.".to_string(),
+        ));
+    }
+
+    let own_fund_pubkey = signed_channel.own_params.fund_pubkey;
+    let counter_fund_pubkey = signed_channel.counter_params.fund_pubkey;
+    let fund_out_amount = signed_channel.fund_tx.output[signed_channel.fund_output_index].value;
+
+    let own_signature = psbt
+        .inputs
+        .first()
+        .and_then(|x| {
+            x.partial_sigs
+                .get(&bitcoin::PublicKey::new(own_fund_pubkey))
+        })
+        .ok_or_else(|| {
+            Error::InvalidParameters("PSBT is missing the local party signature.".to_string())
+        })?;
+
+    if own_signature.hash_ty != EcdsaSighashType::All {
+        return Err(Error::InvalidParameters(
+            "Local party signature must use SIGHASH_ALL.".to_string(),
+        ));
+    }
+
+    dlc::verify_tx_input_sig(
+        secp,
+        &own_signature.sig,
+        close_tx,
+        0,
+        &signed_channel.fund_script_pubkey,
+        fund_out_amount,
+        &own_fund_pubkey,
+    )?;
+
+    let own_sig = own_signature.to_vec();
+    let counter_sig = bitcoin::ecdsa::Signature::sighash_all(offer_signature).to_vec();
+
+    let mut close_tx = close_tx.clone();
+
+    close_tx.input[0].witness = if own_fund_pubkey < counter_fund_pubkey {
+        Witness::from_slice(&[
+            Vec::new(),
+            own_sig,
+            counter_sig,
+            signed_channel.fund_script_pubkey.to_bytes(),
+        ])
+    } else {
+        Witness::from_slice(&[
+            Vec::new(),
+            counter_sig,
+            own_sig,
+            signed_channel.fund_script_pubkey.to_bytes(),
+        ])
+    };
+
+    signed_channel.state = SignedChannelState::CollaborativelyClosed;
+    Ok(close_tx)
+}
+
 fn get_settle_tx_and_adaptor_sig(
     secp: &Secp256k1<All>,
     own_next_per_update_point: &PublicKey,
@@ -1807,8 +3401,9 @@ fn get_settle_tx_and_adaptor_sig(
 
 /// Update the state of the channel if currently in a state that can be rejected.
 pub fn on_reject(signed_channel: &mut SignedChannel) -> Result<(), Error> {
-    if let SignedChannelState::Established { .. } | SignedChannelState::Settled { .. } =
-        signed_channel.state
+    if let SignedChannelState::Established { .. }
+    | SignedChannelState::Settled { .. }
+    | SignedChannelState::ContractsEstablished { .. } = signed_channel.state
     {
         return Ok(());
     }
@@ -1823,6 +3418,10 @@ pub fn on_reject(signed_channel: &mut SignedChannel) -> Result<(), Error> {
         rollback = true;
     }
 
+    if let SignedChannelState::ContractUpdateOffered { is_offer, .. } = signed_channel.state {
+        rollback = is_offer;
+    }
+
     if rollback {
         signed_channel.state = signed_channel
             .roll_back_state
@@ -2016,3 +3615,289 @@ where
     signed_channel.state = SignedChannelState::Closed;
     Ok(settle_tx)
 }
+
+/// Sign the buffer transaction of a channel in
+/// [`SignedChannelState::ContractsEstablished`] state and update the state of
+/// the channel to [`SignedChannelState::ContractsClosing`]. The contracts are
+/// closed once the buffer transaction is confirmed and their outcome attested
+/// (see [`get_signed_channel_contract_cet`]).
+pub fn initiate_unilateral_close_contracts_established_channel<SP: Deref>(
+    secp: &Secp256k1<All>,
+    signed_channel: &mut SignedChannel,
+    signer_provider: &SP,
+) -> Result<(), Error>
+where
+    SP::Target: ContractSignerProvider,
+{
+    let (buffer_adaptor_signature, is_offer, keys_id, buffer_transaction, contract_ids) = get_signed_channel_state!(
+        signed_channel,
+        ContractsEstablished,
+        counter_buffer_adaptor_signature,
+        is_offer,
+        keys_id | buffer_transaction,
+        contract_ids
+    )?;
+
+    let mut buffer_transaction = buffer_transaction.clone();
+    let contract_ids = contract_ids.clone();
+
+    let publish_base_secret =
+        signer_provider.get_secret_key_for_pubkey(&signed_channel.own_points.publish_basepoint)?;
+
+    let publish_sk = derive_private_key(
+        secp,
+        &signed_channel.own_per_update_point,
+        &publish_base_secret,
+    );
+
+    let counter_buffer_signature = buffer_adaptor_signature.decrypt(&publish_sk)?;
+
+    let fund_sk = signer_provider.derive_contract_signer(keys_id)?;
+
+    dlc::util::sign_multi_sig_input(
+        secp,
+        &mut buffer_transaction,
+        &counter_buffer_signature,
+        &signed_channel.counter_params.fund_pubkey,
+        &fund_sk.get_secret_key()?,
+        &signed_channel.fund_script_pubkey,
+        signed_channel.fund_tx.output[signed_channel.fund_output_index].value,
+        0,
+    )?;
+
+    signed_channel.state = SignedChannelState::ContractsClosing {
+        buffer_transaction,
+        contract_ids,
+        is_offer,
+        keys_id,
+    };
+
+    Ok(())
+}
+
+/// Returns the signed CET closing the given contract of a channel in
+/// [`SignedChannelState::ContractsClosing`] state, for the outcome attested in
+/// the given attestations.
+pub fn get_signed_channel_contract_cet<SP: Deref>(
+    secp: &Secp256k1<All>,
+    signed_channel: &SignedChannel,
+    contract: &SignedContract,
+    contract_info: &ContractInfo,
+    adaptor_info: &AdaptorInfo,
+    attestations: &[(usize, OracleAttestation)],
+    signer_provider: &SP,
+) -> Result<Transaction, Error>
+where
+    SP::Target: ContractSignerProvider,
+{
+    let (is_offer, buffer_transaction) = get_signed_channel_state!(
+        signed_channel,
+        ContractsClosing,
+        is_offer | buffer_transaction
+    )?;
+
+    let (offer_per_update_point, accept_per_update_point) = if is_offer {
+        (
+            &signed_channel.own_per_update_point,
+            &signed_channel.counter_per_update_point,
+        )
+    } else {
+        (
+            &signed_channel.counter_per_update_point,
+            &signed_channel.own_per_update_point,
+        )
+    };
+
+    let (offer_revoke_params, accept_revoke_params) = get_update_revoke_params(
+        secp,
+        signed_channel,
+        is_offer,
+        offer_per_update_point,
+        accept_per_update_point,
+    );
+
+    let (range_info, oracle_sigs) =
+        crate::utils::get_range_info_and_oracle_sigs(contract_info, adaptor_info, attestations)?;
+
+    let mut cet = contract.accepted_contract.dlc_transactions.cets[range_info.cet_index].clone();
+
+    let input_value = buffer_transaction
+        .output
+        .get(cet.input[0].previous_output.vout as usize)
+        .ok_or_else(|| {
+            Error::InvalidState("CET does not spend the buffer transaction.".to_string())
+        })?
+        .value;
+
+    let counter_pk = if is_offer {
+        &accept_revoke_params.own_pk
+    } else {
+        &offer_revoke_params.own_pk
+    };
+
+    let adaptor_sigs = if contract.accepted_contract.offered_contract.is_offer_party {
+        contract.accepted_contract.adaptor_signatures.as_ref()
+    } else {
+        contract.adaptor_signatures.as_ref()
+    }
+    .ok_or_else(|| Error::InvalidState("Contract has no adaptor signatures.".to_string()))?;
+
+    let base_secret =
+        signer_provider.get_secret_key_for_pubkey(&signed_channel.own_points.own_basepoint)?;
+    let own_sk = derive_private_key(secp, &signed_channel.own_per_update_point, &base_secret);
+
+    dlc::channel::sign_cet(
+        secp,
+        &mut cet,
+        input_value,
+        &offer_revoke_params,
+        &accept_revoke_params,
+        &own_sk,
+        counter_pk,
+        &adaptor_sigs[range_info.adaptor_index],
+        &oracle_sigs,
+    )?;
+
+    Ok(cet)
+}
+
+#[cfg(test)]
+mod tests {
+    use dlc_messages::channel::{AddContractOffer, RenewOffer, SettleOffer};
+    use lightning::util::ser::Readable;
+    use mocks::dlc_manager::channel::signed_channel::{SignedChannel, SignedChannelState};
+    use mocks::dlc_manager::channel::ChannelPolicy;
+    use mocks::mock_time::MockTime;
+
+    fn get_renew_offer(counter_payout: u64, refund_locktime: u32) -> RenewOffer {
+        let offer_dlc: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        RenewOffer {
+            channel_id: [1; 32],
+            temporary_contract_id: [2; 32],
+            counter_payout,
+            next_per_update_point: offer_dlc.offer_params.fund_pubkey,
+            contract_info: offer_dlc.contract_info,
+            cet_locktime: offer_dlc.cet_locktime,
+            refund_locktime,
+            cet_nsequence: 288,
+        }
+    }
+
+    #[test]
+    fn renew_offer_with_invalid_terms_is_rejected() {
+        let mut signed_channel: SignedChannel = Readable::read(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/SignedChannelEstablished"),
+        ))
+        .unwrap();
+        let time = std::sync::Arc::new(MockTime {});
+        mocks::mock_time::set_time(1000);
+
+        let on_renew_offer = |signed_channel: &mut SignedChannel, renew_offer: RenewOffer| {
+            mocks::dlc_manager::channel_updater::on_renew_offer(
+                secp256k1_zkp::SECP256K1,
+                signed_channel,
+                &renew_offer,
+                &ChannelPolicy::default(),
+                100,
+                &time,
+            )
+        };
+
+        on_renew_offer(&mut signed_channel, get_renew_offer(u64::MAX, u32::MAX))
+            .expect_err("counter payout greater than the collateral to be rejected");
+        on_renew_offer(&mut signed_channel, get_renew_offer(0, 1000))
+            .expect_err("refund locktime in the past to be rejected");
+
+        assert!(matches!(
+            signed_channel.state,
+            SignedChannelState::Established { .. }
+        ));
+        assert!(signed_channel.roll_back_state.is_none());
+    }
+
+    #[test]
+    fn add_contract_offer_exceeding_balances_is_rejected() {
+        let mut signed_channel: SignedChannel = Readable::read(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/SignedChannelSettled"),
+        ))
+        .unwrap();
+        let time = std::sync::Arc::new(MockTime {});
+        mocks::mock_time::set_time(1000);
+
+        let renew_offer = get_renew_offer(0, u32::MAX);
+        let channel_collateral =
+            signed_channel.own_params.collateral + signed_channel.counter_params.collateral;
+        let add_contract_offer = AddContractOffer {
+            channel_id: renew_offer.channel_id,
+            temporary_contract_id: renew_offer.temporary_contract_id,
+            offer_collateral: 0,
+            accept_collateral: channel_collateral,
+            counter_balance: channel_collateral - 1,
+            next_per_update_point: renew_offer.next_per_update_point,
+            contract_info: renew_offer.contract_info,
+            cet_locktime: renew_offer.cet_locktime,
+            refund_locktime: renew_offer.refund_locktime,
+            cet_nsequence: renew_offer.cet_nsequence,
+        };
+
+        mocks::dlc_manager::channel_updater::on_add_contract_offer(
+            secp256k1_zkp::SECP256K1,
+            &mut signed_channel,
+            &add_contract_offer,
+            &ChannelPolicy::default(),
+            100,
+            &time,
+        )
+        .expect_err("collateral greater than the balance to be rejected");
+
+        assert!(matches!(
+            signed_channel.state,
+            SignedChannelState::Settled { .. }
+        ));
+        assert!(signed_channel.roll_back_state.is_none());
+    }
+
+    #[test]
+    fn settle_offer_violating_policy_is_rejected() {
+        let mut signed_channel: SignedChannel = Readable::read(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/SignedChannelEstablished"),
+        ))
+        .unwrap();
+
+        let channel_collateral =
+            signed_channel.own_params.collateral + signed_channel.counter_params.collateral;
+        let settle_offer = SettleOffer {
+            channel_id: signed_channel.channel_id,
+            counter_payout: channel_collateral - 500,
+            next_per_update_point: get_renew_offer(0, 0).next_per_update_point,
+        };
+
+        let policy = ChannelPolicy {
+            min_reserve: 1000,
+            ..Default::default()
+        };
+        mocks::dlc_manager::channel_updater::on_settle_offer(
+            &mut signed_channel,
+            &settle_offer,
+            &policy,
+        )
+        .expect_err("balance below the reserve to be rejected");
+
+        let policy = ChannelPolicy {
+            dust_limit: 1000,
+            ..Default::default()
+        };
+        mocks::dlc_manager::channel_updater::on_settle_offer(
+            &mut signed_channel,
+            &settle_offer,
+            &policy,
+        )
+        .expect_err("balance below the dust limit to be rejected");
+
+        assert!(matches!(
+            signed_channel.state,
+            SignedChannelState::Established { .. }
+        ));
+    }
+}
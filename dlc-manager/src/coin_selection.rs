@@ -0,0 +1,363 @@
+//! #Coin selection
+//!
+//! Strategies to select the UTXOs funding the contracts and channels of a
+//! [`Manager`](crate::manager::Manager), enabling applications to control
+//! the creation of change outputs and the consolidation of their UTXOs.
+//! By default, the selection is left to [`Wallet::get_utxos_for_amount`].
+
+use std::ops::Deref;
+
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, OutPoint};
+
+use crate::error::Error;
+use crate::{Utxo, Wallet};
+
+/// Weight of a transaction input, excluding its witness.
+const TX_INPUT_BASE_WEIGHT: usize = 164;
+
+/// Weight of a P2WPKH change output.
+const CHANGE_OUTPUT_WEIGHT: usize = 124;
+
+/// Maximum number of branches explored by the branch and bound search.
+const BNB_MAX_TRIES: u32 = 100_000;
+
+/// Selects, among the UTXOs available in the wallet, the ones funding a
+/// contract or a channel.
+pub trait CoinSelector: Send + Sync {
+    /// Returns the UTXOs to use, among the given ones, to fund the given
+    /// amount at the given fee rate (in satoshis per virtual byte). The fees
+    /// due to the inputs are not included in `amount`, and are to be covered
+    /// by the selected UTXOs.
+    fn select_coins(&self, utxos: &[Utxo], amount: u64, fee_rate: u64) -> Result<Vec<Utxo>, Error>;
+}
+
+/// Selects the UTXOs with the largest values first, minimizing the number of
+/// inputs of the funding transaction.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LargestFirst;
+
+impl CoinSelector for LargestFirst {
+    fn select_coins(&self, utxos: &[Utxo], amount: u64, fee_rate: u64) -> Result<Vec<Utxo>, Error> {
+        let candidates = get_candidates(utxos, fee_rate)?;
+        let mut total = 0;
+        let mut selected = Vec::new();
+        for (utxo, effective_value) in candidates {
+            if total >= amount {
+                break;
+            }
+            total += effective_value;
+            selected.push(utxo.clone());
+        }
+        if total < amount {
+            return Err(not_enough_utxos(amount));
+        }
+        Ok(selected)
+    }
+}
+
+/// Searches for a set of UTXOs avoiding the creation of a change output,
+/// falling back to [`LargestFirst`] if there is none.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BranchAndBound;
+
+impl CoinSelector for BranchAndBound {
+    fn select_coins(&self, utxos: &[Utxo], amount: u64, fee_rate: u64) -> Result<Vec<Utxo>, Error> {
+        let candidates = get_candidates(utxos, fee_rate)?;
+        let cost_of_change = dlc::util::weight_to_fee(
+            CHANGE_OUTPUT_WEIGHT + TX_INPUT_BASE_WEIGHT + dlc::P2WPKH_WITNESS_SIZE,
+            fee_rate,
+        )?;
+        match branch_and_bound(&candidates, amount, amount + cost_of_change) {
+            Some(selected) => Ok(selected),
+            None => LargestFirst.select_coins(utxos, amount, fee_rate),
+        }
+    }
+}
+
+/// Only selects sets of UTXOs exceeding the funded amount by less than
+/// `max_excess`, which is given up as fees instead of creating a change
+/// output, so that the UTXOs of the wallet cannot be linked to its future
+/// transactions. Fails if there is no such set.
+#[derive(Clone, Copy, Debug)]
+pub struct NoChange {
+    /// The maximum amount, in satoshis, paid in excess of the required fees.
+    pub max_excess: u64,
+}
+
+impl Default for NoChange {
+    /// Uses the dust limit of the funding transaction outputs as maximum
+    /// excess, under which change outputs are not created.
+    fn default() -> Self {
+        NoChange { max_excess: 1000 }
+    }
+}
+
+impl CoinSelector for NoChange {
+    fn select_coins(&self, utxos: &[Utxo], amount: u64, fee_rate: u64) -> Result<Vec<Utxo>, Error> {
+        let candidates = get_candidates(utxos, fee_rate)?;
+        branch_and_bound(&candidates, amount, amount + self.max_excess).ok_or_else(|| {
+            Error::InvalidParameters(format!(
+                "No set of UTXOs funds {} sats without change",
+                amount
+            ))
+        })
+    }
+}
+
+/// Returns the UTXOs with a positive effective value, i.e. worth more than
+/// the fee required to spend them, along with that value, sorted by
+/// decreasing value.
+fn get_candidates(utxos: &[Utxo], fee_rate: u64) -> Result<Vec<(&Utxo, u64)>, Error> {
+    let mut candidates = Vec::new();
+    for utxo in utxos.iter().filter(|x| !x.reserved) {
+        let input_weight = TX_INPUT_BASE_WEIGHT
+            + dlc::util::get_single_key_max_witness_len(&utxo.tx_out.script_pubkey);
+        let input_fee = dlc::util::weight_to_fee(input_weight, fee_rate)?;
+        if utxo.tx_out.value > input_fee {
+            candidates.push((utxo, utxo.tx_out.value - input_fee));
+        }
+    }
+    candidates.sort_by(|a, b| b.1.cmp(&a.1));
+    Ok(candidates)
+}
+
+/// Searches for the set of candidates whose total effective value is the
+/// closest to `target` while lying between `target` and `upper_bound`.
+fn branch_and_bound(
+    candidates: &[(&Utxo, u64)],
+    target: u64,
+    upper_bound: u64,
+) -> Option<Vec<Utxo>> {
+    let mut remaining = vec![0; candidates.len() + 1];
+    for i in (0..candidates.len()).rev() {
+        remaining[i] = remaining[i + 1] + candidates[i].1;
+    }
+    let mut search = BnbSearch {
+        candidates,
+        remaining,
+        target,
+        upper_bound,
+        tries: BNB_MAX_TRIES,
+        selected: Vec::new(),
+        best: None,
+    };
+    search.explore(0, 0);
+    search.best.map(|(_, indexes)| {
+        indexes
+            .into_iter()
+            .map(|i| candidates[i].0.clone())
+            .collect()
+    })
+}
+
+struct BnbSearch<'a, 'b> {
+    candidates: &'a [(&'b Utxo, u64)],
+    /// The total effective value of the candidates from each index onwards.
+    remaining: Vec<u64>,
+    target: u64,
+    upper_bound: u64,
+    tries: u32,
+    selected: Vec<usize>,
+    best: Option<(u64, Vec<usize>)>,
+}
+
+impl<'a, 'b> BnbSearch<'a, 'b> {
+    fn explore(&mut self, index: usize, total: u64) {
+        if self.tries == 0 || total > self.upper_bound {
+            return;
+        }
+        self.tries -= 1;
+        if total >= self.target {
+            if self.best.as_ref().map_or(true, |(best, _)| total < *best) {
+                self.best = Some((total, self.selected.clone()));
+            }
+            return;
+        }
+        if index == self.candidates.len() || total + self.remaining[index] < self.target {
+            return;
+        }
+        self.selected.push(index);
+        self.explore(index + 1, total + self.candidates[index].1);
+        self.selected.pop();
+        self.explore(index + 1, total);
+    }
+}
+
+fn not_enough_utxos(amount: u64) -> Error {
+    Error::InvalidParameters(format!("Not enough UTXOs to fund {} sats", amount))
+}
+
+/// A [`Wallet`] selecting the UTXOs it provides using a [`CoinSelector`],
+/// among the ones listed by the wrapped wallet, if one is set.
+pub(crate) struct SelectingWallet<'a, W: Deref>
+where
+    W::Target: Wallet,
+{
+    pub(crate) wallet: &'a W,
+    pub(crate) coin_selector: Option<&'a dyn CoinSelector>,
+}
+
+impl<'a, W: Deref> Wallet for SelectingWallet<'a, W>
+where
+    W::Target: Wallet,
+{
+    fn get_new_address(&self) -> Result<Address, Error> {
+        self.wallet.get_new_address()
+    }
+
+    fn get_new_change_address(&self) -> Result<Address, Error> {
+        self.wallet.get_new_change_address()
+    }
+
+    fn get_utxos_for_amount(
+        &self,
+        amount: u64,
+        fee_rate: u64,
+        lock_utxos: bool,
+    ) -> Result<Vec<Utxo>, Error> {
+        let coin_selector = match self.coin_selector {
+            Some(coin_selector) => coin_selector,
+            None => {
+                return self
+                    .wallet
+                    .get_utxos_for_amount(amount, fee_rate, lock_utxos)
+            }
+        };
+        let utxos = self.wallet.list_utxos()?;
+        let selected = coin_selector.select_coins(&utxos, amount, fee_rate)?;
+        if lock_utxos {
+            let outpoints = selected.iter().map(|x| x.outpoint).collect::<Vec<_>>();
+            self.wallet.reserve_utxos(&outpoints)?;
+        }
+        Ok(selected)
+    }
+
+    fn list_utxos(&self) -> Result<Vec<Utxo>, Error> {
+        self.wallet.list_utxos()
+    }
+
+    fn import_address(&self, address: &Address) -> Result<(), Error> {
+        self.wallet.import_address(address)
+    }
+
+    fn sign_psbt_input(
+        &self,
+        psbt: &mut PartiallySignedTransaction,
+        input_index: usize,
+    ) -> Result<(), Error> {
+        self.wallet.sign_psbt_input(psbt, input_index)
+    }
+
+    fn sign_psbt(
+        &self,
+        psbt: &mut PartiallySignedTransaction,
+        input_indexes: &[usize],
+    ) -> Result<(), Error> {
+        self.wallet.sign_psbt(psbt, input_indexes)
+    }
+
+    fn reserve_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error> {
+        self.wallet.reserve_utxos(outpoints)
+    }
+
+    fn release_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error> {
+        self.wallet.release_utxos(outpoints)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Network, ScriptBuf, TxOut, Txid, WPubkeyHash};
+
+    const FEE_RATE: u64 = 2;
+
+    fn get_utxos(values: &[u64]) -> Vec<Utxo> {
+        let script_pubkey = ScriptBuf::new_v0_p2wpkh(&WPubkeyHash::all_zeros());
+        let address = Address::from_script(&script_pubkey, Network::Regtest).unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Utxo {
+                tx_out: TxOut {
+                    value: *value,
+                    script_pubkey: script_pubkey.clone(),
+                },
+                outpoint: OutPoint {
+                    txid: Txid::all_zeros(),
+                    vout: i as u32,
+                },
+                address: address.clone(),
+                redeem_script: ScriptBuf::new(),
+                reserved: false,
+            })
+            .collect()
+    }
+
+    fn input_fee() -> u64 {
+        dlc::util::weight_to_fee(
+            TX_INPUT_BASE_WEIGHT
+                + dlc::util::get_single_key_max_witness_len(
+                    &get_utxos(&[0])[0].tx_out.script_pubkey,
+                ),
+            FEE_RATE,
+        )
+        .unwrap()
+    }
+
+    fn values(utxos: &[Utxo]) -> Vec<u64> {
+        let mut values = utxos.iter().map(|x| x.tx_out.value).collect::<Vec<_>>();
+        values.sort_unstable();
+        values
+    }
+
+    #[test]
+    fn largest_first_selects_largest_utxos() {
+        let utxos = get_utxos(&[10_000, 50_000, 30_000, 20_000]);
+        let selected = LargestFirst.select_coins(&utxos, 60_000, FEE_RATE).unwrap();
+        assert_eq!(vec![30_000, 50_000], values(&selected));
+    }
+
+    #[test]
+    fn reserved_utxos_are_not_selected() {
+        let mut utxos = get_utxos(&[10_000, 50_000]);
+        utxos[1].reserved = true;
+        LargestFirst
+            .select_coins(&utxos, 20_000, FEE_RATE)
+            .expect_err("only reserved UTXOs are sufficient");
+    }
+
+    #[test]
+    fn branch_and_bound_avoids_change() {
+        let fee = input_fee();
+        let utxos = get_utxos(&[100_000, 30_000 + fee, 20_000 + fee, 15_000]);
+        let selected = BranchAndBound
+            .select_coins(&utxos, 50_000, FEE_RATE)
+            .unwrap();
+        assert_eq!(vec![20_000 + fee, 30_000 + fee], values(&selected));
+    }
+
+    #[test]
+    fn branch_and_bound_falls_back_to_largest_first() {
+        let utxos = get_utxos(&[100_000, 80_000]);
+        let selected = BranchAndBound
+            .select_coins(&utxos, 50_000, FEE_RATE)
+            .unwrap();
+        assert_eq!(vec![100_000], values(&selected));
+    }
+
+    #[test]
+    fn no_change_fails_without_matching_utxos() {
+        let fee = input_fee();
+        let utxos = get_utxos(&[100_000, 50_500 + fee]);
+        let selected = NoChange::default()
+            .select_coins(&utxos, 50_000, FEE_RATE)
+            .unwrap();
+        assert_eq!(vec![50_500 + fee], values(&selected));
+        NoChange::default()
+            .select_coins(&utxos, 40_000, FEE_RATE)
+            .expect_err("all selections require change");
+    }
+}
@@ -2,6 +2,7 @@
 
 use super::offered_contract::OfferedContract;
 use super::AdaptorInfo;
+use crate::error::Error;
 use bitcoin::Transaction;
 use dlc::{DlcTransactions, PartyParams};
 use dlc_messages::{AcceptDlc, FundingInput};
@@ -12,6 +13,11 @@ use std::fmt::Write as _;
 
 /// An AcceptedContract represents a contract in the accepted state.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct AcceptedContract {
     /// The offered contract that was accepted.
     pub offered_contract: OfferedContract,
@@ -25,17 +31,23 @@ pub struct AcceptedContract {
     /// The adaptor signatures of the accepting party. Note that the accepting
     /// party does not keep them thus an option is used.
     pub adaptor_signatures: Option<Vec<EcdsaAdaptorSignature>>,
-    /// The signature for the refund transaction from the accepting party.
-    pub accept_refund_signature: Signature,
+    /// The signature for the refund transaction from the accepting party, set
+    /// unless the contract is funded with a taproot output.
+    pub accept_refund_signature: Option<Signature>,
     /// The bitcoin set of bitcoin transactions for the contract.
     pub dlc_transactions: DlcTransactions,
+    /// The signatures of the accepting party, set only if the contract is
+    /// funded with a taproot output.
+    #[cfg(feature = "taproot")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub accept_taproot_signatures: Option<super::TaprootSignatures>,
 }
 
 impl AcceptedContract {
     /// Returns the contract id for the contract computed as specified here:
     /// <https://github.com/discreetlogcontracts/dlcspecs/blob/master/Protocol.md#requirements-2>
     pub fn get_contract_id(&self) -> [u8; 32] {
-        crate::utils::compute_id(
+        super::compute_contract_id(
             self.dlc_transactions.fund.txid(),
             self.dlc_transactions.get_fund_output_index() as u16,
             &self.offered_contract.id,
@@ -54,11 +66,21 @@ impl AcceptedContract {
         string_id
     }
 
+    /// Returns the refund signature of the accepting party, which is not set
+    /// for contracts funded with a taproot output.
+    pub(crate) fn get_accept_refund_signature(&self) -> Result<Signature, Error> {
+        self.accept_refund_signature.ok_or_else(|| {
+            Error::InvalidState(
+                "Contract funded with a taproot output has no ECDSA refund signature".to_string(),
+            )
+        })
+    }
+
     pub(crate) fn get_accept_contract_msg(
         &self,
         ecdsa_adaptor_signatures: &[EcdsaAdaptorSignature],
-    ) -> AcceptDlc {
-        AcceptDlc {
+    ) -> Result<AcceptDlc, Error> {
+        Ok(AcceptDlc {
             protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
             temporary_contract_id: self.offered_contract.id,
             accept_collateral: self.accept_params.collateral,
@@ -69,9 +91,12 @@ impl AcceptedContract {
             change_spk: self.accept_params.change_script_pubkey.clone(),
             change_serial_id: self.accept_params.change_serial_id,
             cet_adaptor_signatures: ecdsa_adaptor_signatures.into(),
-            refund_signature: self.accept_refund_signature,
+            refund_signature: self.get_accept_refund_signature()?,
             negotiation_fields: None,
-        }
+            fee_split: crate::conversion_utils::get_fee_split_field(
+                self.offered_contract.fee_split,
+            ),
+        })
     }
 
     /// Compute the profit and loss for this contract and an assciated cet index
@@ -118,4 +143,14 @@ mod tests {
             -11000000
         );
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip_test() {
+        let buf = include_bytes!("../../../dlc-sled-storage-provider/test_files/Accepted");
+        let accepted_contract: AcceptedContract = Readable::read(&mut Cursor::new(&buf)).unwrap();
+        let json = serde_json::to_string(&accepted_contract).unwrap();
+        let deserialized: AcceptedContract = serde_json::from_str(&json).unwrap();
+        assert_eq!(json, serde_json::to_string(&deserialized).unwrap());
+    }
 }
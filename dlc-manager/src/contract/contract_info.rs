@@ -7,11 +7,8 @@ use crate::ContractSigner;
 use bitcoin::{Script, Transaction};
 use dlc::{OracleInfo, Payout};
 use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement};
-use dlc_trie::{DlcTrie, RangeInfo};
-use secp256k1_zkp::{
-    hashes::sha256, All, EcdsaAdaptorSignature, Message, PublicKey, Secp256k1, SecretKey,
-    Verification,
-};
+use dlc_trie::{precomputed_points, DlcTrie, RangeInfo};
+use secp256k1_zkp::{All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey, Verification};
 use std::ops::Deref;
 
 pub(super) type OracleIndexAndPrefixLength = Vec<(usize, usize)>;
@@ -61,6 +58,19 @@ impl ContractInfo {
         self.oracle_announcements.iter().map(|x| x.into()).collect()
     }
 
+    /// Returns the number of adaptor signatures that each party needs to
+    /// create for the contract.
+    pub fn get_adaptor_signature_count(&self, total_collateral: u64) -> Result<usize, Error> {
+        match &self.contract_descriptor {
+            ContractDescriptor::Enum(e) => {
+                Ok(e.get_adaptor_signature_count(self.oracle_announcements.len(), self.threshold))
+            }
+            ContractDescriptor::Numerical(n) => {
+                n.get_adaptor_signature_count(total_collateral, self.threshold)
+            }
+        }
+    }
+
     /// Uses the provided AdaptorInfo and SecretKey to generate the set of
     /// adaptor signatures for the contract.
     pub fn get_adaptor_signatures<S: Deref>(
@@ -272,10 +282,51 @@ impl ContractInfo {
         }
     }
 
+    /// Generate the adaptor info for the contract without producing the
+    /// adaptor signatures, so that they can be created by an external signer.
+    pub fn generate_adaptor_info(
+        &self,
+        total_collateral: u64,
+        adaptor_index_start: usize,
+    ) -> Result<AdaptorInfo, Error> {
+        match &self.contract_descriptor {
+            ContractDescriptor::Enum(_) => Ok(AdaptorInfo::Enum),
+            ContractDescriptor::Numerical(n) => {
+                n.generate_adaptor_info(total_collateral, self.threshold, adaptor_index_start)
+            }
+        }
+    }
+
+    /// Returns the index of the CET and the adaptor point of each adaptor
+    /// signature described by the given adaptor info, in the order in which
+    /// signatures are expected. CET indexes are relative to the set of CETs of
+    /// this contract info.
+    pub fn get_adaptor_points(
+        &self,
+        secp: &Secp256k1<All>,
+        adaptor_info: &AdaptorInfo,
+    ) -> Result<Vec<(usize, PublicKey)>, Error> {
+        match adaptor_info {
+            AdaptorInfo::Enum => match &self.contract_descriptor {
+                ContractDescriptor::Enum(e) => {
+                    e.get_adaptor_points(secp, &self.get_oracle_infos(), self.threshold)
+                }
+                _ => unreachable!(),
+            },
+            AdaptorInfo::Numerical(trie) => {
+                Ok(trie.get_adaptor_points(&self.precompute_points(secp)?)?)
+            }
+            AdaptorInfo::NumericalWithDifference(trie) => {
+                Ok(trie.get_adaptor_points(&self.precompute_points(secp)?)?)
+            }
+        }
+    }
+
     fn precompute_points<C: Verification>(
         &self,
         secp: &Secp256k1<C>,
     ) -> Result<Vec<Vec<Vec<PublicKey>>>, Error> {
+        let cache = precomputed_points::global();
         self.oracle_announcements
             .iter()
             .map(|x| {
@@ -290,21 +341,14 @@ impl ContractInfo {
                                 "Number of digits and nonces must be equal".to_string(),
                             ));
                         }
-                        let mut d_points = Vec::with_capacity(nb_digits);
-                        for nonce in nonces {
-                            let mut points = Vec::with_capacity(base);
-                            for j in 0..base {
-                                let msg = Message::from_hashed_data::<sha256::Hash>(
-                                    j.to_string().as_bytes(),
-                                );
-                                let sig_point = dlc::secp_utils::schnorrsig_compute_sig_point(
-                                    secp, pubkey, nonce, &msg,
-                                )?;
-                                points.push(sig_point);
-                            }
-                            d_points.push(points);
-                        }
-                        Ok(d_points)
+                        Ok(precomputed_points::get_event_points(
+                            cache.as_ref(),
+                            secp,
+                            pubkey,
+                            &x.oracle_event.event_id,
+                            nonces,
+                            base,
+                        )?)
                     }
                     _ => Err(Error::InvalidParameters(
                         "Expected digit decomposition event.".to_string(),
@@ -1,14 +1,33 @@
 //! #ContractInput
 
 use crate::error::Error;
+use dlc::FeeSplit;
 
+use super::numerical_descriptor::DifferenceParams;
 use super::ContractDescriptor;
 use secp256k1_zkp::XOnlyPublicKey;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+/// The allowed deviation of the outcome attested by an oracle from the
+/// outcomes attested by the other oracles of a numerical contract.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct OracleDifferenceParams {
+    /// The maximum error above which the contract should fail to close, as a
+    /// power of two.
+    pub max_error_exp: usize,
+    /// The minimum error under which the contract should be guaranteed to be
+    /// closeable, as a power of two.
+    pub min_support_exp: usize,
+}
+
 /// Oracle information required for the initial creation of a contract.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
@@ -23,6 +42,12 @@ pub struct OracleInput {
     /// The number of oracles that need to provide attestations satisfying the
     /// contract conditions to be able to close the contract.
     pub threshold: u16,
+    /// The allowed deviation of each oracle, in the order of `public_keys`.
+    /// Only valid for numerical contracts with more than one oracle. As a
+    /// single set of difference parameters is used in the contract, the
+    /// strictest maximum error and the most lenient minimum support are used.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub difference_params: Option<Vec<OracleDifferenceParams>>,
 }
 
 impl OracleInput {
@@ -46,12 +71,56 @@ impl OracleInput {
             ));
         }
 
+        if let Some(params) = &self.difference_params {
+            if self.public_keys.len() < 2 {
+                return Err(Error::InvalidParameters(
+                    "Difference parameters require at least two oracles.".to_string(),
+                ));
+            }
+
+            if params.len() != self.public_keys.len() {
+                return Err(Error::InvalidParameters(
+                    "Expected difference parameters for each oracle.".to_string(),
+                ));
+            }
+
+            if params.iter().any(|x| x.min_support_exp >= x.max_error_exp) {
+                return Err(Error::InvalidParameters(
+                    "Minimum support must be smaller than maximum error.".to_string(),
+                ));
+            }
+
+            if self.get_difference_params(false).is_none() {
+                return Err(Error::InvalidParameters(
+                    "Oracle difference parameters are incompatible.".to_string(),
+                ));
+            }
+        }
+
         Ok(())
     }
+
+    /// Returns the difference parameters satisfying the requirements of all
+    /// the oracles, or `None` if no difference parameters were specified or if
+    /// they cannot be satisfied together.
+    pub fn get_difference_params(&self, maximize_coverage: bool) -> Option<DifferenceParams> {
+        let params = self.difference_params.as_ref()?;
+        let max_error_exp = params.iter().map(|x| x.max_error_exp).min()?;
+        let min_support_exp = params.iter().map(|x| x.min_support_exp).max()?;
+        if min_support_exp >= max_error_exp {
+            return None;
+        }
+
+        Some(DifferenceParams {
+            max_error_exp,
+            min_support_exp,
+            maximize_coverage,
+        })
+    }
 }
 
 /// Represents the contract specifications.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
@@ -64,7 +133,25 @@ pub struct ContractInputInfo {
     pub oracles: OracleInput,
 }
 
-#[derive(Debug)]
+impl ContractInputInfo {
+    /// Returns the contract descriptor to use for the contract, with the
+    /// difference parameters derived from the oracle input if specified.
+    pub fn get_contract_descriptor(&self) -> ContractDescriptor {
+        let mut contract_descriptor = self.contract_descriptor.clone();
+        if let ContractDescriptor::Numerical(n) = &mut contract_descriptor {
+            let maximize_coverage = n
+                .difference_params
+                .as_ref()
+                .map_or(false, |x| x.maximize_coverage);
+            if let Some(params) = self.oracles.get_difference_params(maximize_coverage) {
+                n.difference_params = Some(params);
+            }
+        }
+        contract_descriptor
+    }
+}
+
+#[derive(Clone, Debug)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
@@ -81,6 +168,18 @@ pub struct ContractInput {
     /// The set of contract that make up the DLC (a single DLC can be based
     /// on multiple contracts).
     pub contract_infos: Vec<ContractInputInfo>,
+    /// Whether to add an anchor output to the CETs, enabling to fee bump them
+    /// after they were signed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub anchor_outputs: bool,
+    /// How the fees of the contract transactions are split between the
+    /// parties.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fee_split: FeeSplit,
+    /// Whether to fund the contract with a taproot output instead of a P2WSH
+    /// one, which requires the `taproot` feature.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub taproot: bool,
 }
 
 impl ContractInput {
@@ -94,6 +193,20 @@ impl ContractInput {
 
         for contract_info in &self.contract_infos {
             contract_info.oracles.validate()?;
+            if contract_info.oracles.difference_params.is_some()
+                && !matches!(
+                    contract_info.contract_descriptor,
+                    ContractDescriptor::Numerical(_)
+                )
+            {
+                return Err(Error::InvalidParameters(
+                    "Difference parameters are only supported for numerical contracts.".to_string(),
+                ));
+            }
+        }
+
+        if self.taproot {
+            crate::utils::check_taproot_support(self.contract_infos.len())?;
         }
 
         dlc::util::validate_fee_rate(self.fee_rate)
@@ -144,8 +257,12 @@ mod tests {
                     ],
                     event_id: "1234".to_string(),
                     threshold: 1,
+                    difference_params: None,
                 },
             }],
+            anchor_outputs: false,
+            fee_split: FeeSplit::Equal,
+            taproot: false,
         }
     }
 
@@ -199,4 +316,65 @@ mod tests {
             .validate()
             .expect_err("the contract input to be invalid.");
     }
+
+    fn get_oracle_input(difference_params: &[(usize, usize)]) -> OracleInput {
+        OracleInput {
+            public_keys: (1..=difference_params.len() as u8)
+                .map(|i| {
+                    XOnlyPublicKey::from_keypair(&KeyPair::from_secret_key(
+                        SECP256K1,
+                        &SecretKey::from_slice(&[i; 32]).unwrap(),
+                    ))
+                    .0
+                })
+                .collect(),
+            event_id: "1234".to_string(),
+            threshold: 2,
+            difference_params: Some(
+                difference_params
+                    .iter()
+                    .map(|(max_error_exp, min_support_exp)| OracleDifferenceParams {
+                        max_error_exp: *max_error_exp,
+                        min_support_exp: *min_support_exp,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn per_oracle_difference_params_are_combined() {
+        let oracle_input = get_oracle_input(&[(8, 4), (6, 2), (7, 5)]);
+        oracle_input
+            .validate()
+            .expect("the oracle input to be valid.");
+        let params = oracle_input.get_difference_params(true).unwrap();
+        assert_eq!(6, params.max_error_exp);
+        assert_eq!(5, params.min_support_exp);
+        assert!(params.maximize_coverage);
+    }
+
+    #[test]
+    fn incompatible_oracle_difference_params_are_not_valid() {
+        get_oracle_input(&[(8, 4), (4, 2)])
+            .validate()
+            .expect_err("the oracle input to be invalid.");
+        get_oracle_input(&[(4, 4), (8, 2)])
+            .validate()
+            .expect_err("the oracle input to be invalid.");
+        let mut oracle_input = get_oracle_input(&[(8, 4), (8, 4)]);
+        oracle_input.difference_params.as_mut().unwrap().pop();
+        oracle_input
+            .validate()
+            .expect_err("the oracle input to be invalid.");
+    }
+
+    #[test]
+    fn difference_params_enum_contract_input_is_not_valid() {
+        let mut input = get_base_input();
+        input.contract_infos[0].oracles = get_oracle_input(&[(8, 4), (8, 4)]);
+        input
+            .validate()
+            .expect_err("the contract input to be invalid.");
+    }
 }
@@ -36,6 +36,12 @@ impl EnumDescriptor {
             .collect()
     }
 
+    /// Returns the number of adaptor signatures required for the contract given
+    /// the number of oracles and the threshold.
+    pub fn get_adaptor_signature_count(&self, nb_oracles: usize, threshold: usize) -> usize {
+        self.outcome_payouts.len() * CombinationIterator::new(nb_oracles, threshold).count()
+    }
+
     /// Validate that the descriptor covers all possible outcomes of the given
     /// enum event descriptor.
     pub fn validate(&self, enum_event_descriptor: &EnumEventDescriptor) -> Result<(), Error> {
@@ -121,26 +127,26 @@ impl EnumDescriptor {
         adaptor_sigs: &[EcdsaAdaptorSignature],
         adaptor_sig_start: usize,
     ) -> Result<usize, dlc::Error> {
-        let mut adaptor_sig_index = adaptor_sig_start;
-        let mut callback =
-            |adaptor_point: &PublicKey, cet_index: usize| -> Result<(), dlc::Error> {
-                let sig = adaptor_sigs[adaptor_sig_index];
-                adaptor_sig_index += 1;
-                dlc::verify_cet_adaptor_sig_from_point(
-                    secp,
-                    &sig,
-                    &cets[cet_index],
-                    adaptor_point,
-                    fund_pubkey,
-                    funding_script_pubkey,
-                    fund_output_value,
-                )?;
-                Ok(())
-            };
+        let adaptor_points = self.compute_adaptor_points(secp, oracle_infos, threshold)?;
+        let adaptor_sig_end = adaptor_sig_start + adaptor_points.len();
+        if adaptor_sigs.len() < adaptor_sig_end {
+            return Err(dlc::Error::InvalidArgument);
+        }
 
-        self.iter_outcomes(secp, oracle_infos, threshold, &mut callback)?;
+        let inputs = adaptor_sigs[adaptor_sig_start..adaptor_sig_end]
+            .iter()
+            .zip(adaptor_points.iter())
+            .map(|(sig, (cet_index, adaptor_point))| (sig, &cets[*cet_index], adaptor_point))
+            .collect::<Vec<_>>();
+        dlc::verify_cet_adaptor_sigs_from_points(
+            secp,
+            &inputs,
+            fund_pubkey,
+            funding_script_pubkey,
+            fund_output_value,
+        )?;
 
-        Ok(adaptor_sig_index)
+        Ok(adaptor_sig_end)
     }
 
     /// Verify the given set of adaptor signature and generates the adaptor info.
@@ -206,24 +212,49 @@ impl EnumDescriptor {
         funding_script_pubkey: &Script,
         fund_output_value: u64,
     ) -> Result<Vec<EcdsaAdaptorSignature>, Error> {
-        let mut adaptor_sigs = Vec::new();
+        let adaptor_points = self.compute_adaptor_points(secp, oracle_infos, threshold)?;
+        let inputs = adaptor_points
+            .iter()
+            .map(|(cet_index, adaptor_point)| (&cets[*cet_index], adaptor_point))
+            .collect::<Vec<_>>();
+
+        Ok(dlc::create_cet_adaptor_sigs_from_points(
+            secp,
+            &inputs,
+            fund_privkey,
+            funding_script_pubkey,
+            fund_output_value,
+        )?)
+    }
+
+    /// Returns the index of the CET and the adaptor point of each adaptor
+    /// signature of the contract, in the order in which signatures are
+    /// expected.
+    pub fn get_adaptor_points(
+        &self,
+        secp: &Secp256k1<All>,
+        oracle_infos: &[OracleInfo],
+        threshold: usize,
+    ) -> Result<Vec<(usize, PublicKey)>, Error> {
+        Ok(self.compute_adaptor_points(secp, oracle_infos, threshold)?)
+    }
+
+    fn compute_adaptor_points(
+        &self,
+        secp: &Secp256k1<All>,
+        oracle_infos: &[OracleInfo],
+        threshold: usize,
+    ) -> Result<Vec<(usize, PublicKey)>, dlc::Error> {
+        let mut adaptor_points = Vec::new();
         let mut callback =
             |adaptor_point: &PublicKey, cet_index: usize| -> Result<(), dlc::Error> {
-                let sig = dlc::create_cet_adaptor_sig_from_point(
-                    secp,
-                    &cets[cet_index],
-                    adaptor_point,
-                    fund_privkey,
-                    funding_script_pubkey,
-                    fund_output_value,
-                )?;
-                adaptor_sigs.push(sig);
+                adaptor_points.push((cet_index, *adaptor_point));
                 Ok(())
             };
 
         self.iter_outcomes(secp, oracle_infos, threshold, &mut callback)?;
 
-        Ok(adaptor_sigs)
+        Ok(adaptor_points)
     }
 
     fn iter_outcomes<C: Verification, F>(
@@ -2,14 +2,14 @@
 
 use crate::error::Error;
 use crate::ContractId;
-use bitcoin::Transaction;
+use bitcoin::{Transaction, Txid};
 use dlc_messages::{
     oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation},
     AcceptDlc, SignDlc,
 };
 use dlc_trie::multi_oracle_trie::MultiOracleTrie;
 use dlc_trie::multi_oracle_trie_with_diff::MultiOracleTrieWithDiff;
-use secp256k1_zkp::PublicKey;
+use secp256k1_zkp::{PublicKey, Secp256k1, Verification};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use signed_contract::SignedContract;
@@ -24,10 +24,16 @@ pub mod numerical_descriptor;
 pub mod offered_contract;
 pub mod ser;
 pub mod signed_contract;
+pub mod signing_request;
 pub(crate) mod utils;
 
 #[derive(Clone)]
 /// Enum representing the possible states of a DLC.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub enum Contract {
     /// Initial state where a contract is being proposed.
     Offered(offered_contract::OfferedContract),
@@ -49,11 +55,51 @@ pub enum Contract {
     FailedSign(FailedSignContract),
     /// A contract that was rejected by the party to whom it was offered.
     Rejected(offered_contract::OfferedContract),
+    /// A contract whose offer expired before being accepted.
+    Expired(offered_contract::OfferedContract),
+    /// A contract whose fund output was spent by the confirmed fund
+    /// transaction of a contract amending it or netting it with other
+    /// contracts.
+    Amended(signed_contract::SignedContract),
+    /// A contract closed by a transaction agreed upon by both parties before
+    /// its maturity.
+    CollaborativelyClosed(CollaborativelyClosedContract),
 }
 
 impl std::fmt::Debug for Contract {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let state = match self {
+        f.debug_struct("Contract")
+            .field("state", &self.get_state_name())
+            .finish()
+    }
+}
+
+/// Computes the id of a contract from the id of its fund transaction, the index
+/// of its fund output and its temporary id, as specified here:
+/// <https://github.com/discreetlogcontracts/dlcspecs/blob/master/Protocol.md#requirements-2>
+pub fn compute_contract_id(
+    fund_txid: Txid,
+    fund_output_index: u16,
+    temporary_id: &ContractId,
+) -> ContractId {
+    crate::utils::compute_id(fund_txid, fund_output_index, temporary_id)
+}
+
+/// Recovers the temporary id of a contract from its id, the id of its fund
+/// transaction and the index of its fund output, inverting
+/// [`compute_contract_id`].
+pub fn compute_temporary_contract_id(
+    fund_txid: Txid,
+    fund_output_index: u16,
+    contract_id: &ContractId,
+) -> ContractId {
+    crate::utils::compute_id(fund_txid, fund_output_index, contract_id)
+}
+
+impl Contract {
+    /// Returns a human readable name for the state of the contract.
+    pub fn get_state_name(&self) -> &'static str {
+        match self {
             Contract::Offered(_) => "offered",
             Contract::Accepted(_) => "accepted",
             Contract::Signed(_) => "signed",
@@ -64,24 +110,28 @@ impl std::fmt::Debug for Contract {
             Contract::FailedAccept(_) => "failed accept",
             Contract::FailedSign(_) => "failed sign",
             Contract::Rejected(_) => "rejected",
-        };
-        f.debug_struct("Contract").field("state", &state).finish()
+            Contract::Expired(_) => "expired",
+            Contract::Amended(_) => "amended",
+            Contract::CollaborativelyClosed(_) => "collaboratively closed",
+        }
     }
-}
 
-impl Contract {
     /// Get the id of a contract. Returns the temporary contract id for offered
     /// and failed accept contracts.
     pub fn get_id(&self) -> ContractId {
         match self {
-            Contract::Offered(o) | Contract::Rejected(o) => o.id,
+            Contract::Offered(o) | Contract::Rejected(o) | Contract::Expired(o) => o.id,
             Contract::Accepted(o) => o.get_contract_id(),
-            Contract::Signed(o) | Contract::Confirmed(o) | Contract::Refunded(o) => {
-                o.accepted_contract.get_contract_id()
-            }
+            Contract::Signed(o)
+            | Contract::Confirmed(o)
+            | Contract::Refunded(o)
+            | Contract::Amended(o) => o.accepted_contract.get_contract_id(),
             Contract::FailedAccept(c) => c.offered_contract.id,
             Contract::FailedSign(c) => c.accepted_contract.get_contract_id(),
             Contract::PreClosed(c) => c.signed_contract.accepted_contract.get_contract_id(),
+            Contract::CollaborativelyClosed(c) => {
+                c.signed_contract.accepted_contract.get_contract_id()
+            }
             Contract::Closed(c) => c.contract_id,
         }
     }
@@ -89,14 +139,18 @@ impl Contract {
     /// Returns the temporary contract id of a contract.
     pub fn get_temporary_id(&self) -> ContractId {
         match self {
-            Contract::Offered(o) | Contract::Rejected(o) => o.id,
+            Contract::Offered(o) | Contract::Rejected(o) | Contract::Expired(o) => o.id,
             Contract::Accepted(o) => o.offered_contract.id,
-            Contract::Signed(o) | Contract::Confirmed(o) | Contract::Refunded(o) => {
-                o.accepted_contract.offered_contract.id
-            }
+            Contract::Signed(o)
+            | Contract::Confirmed(o)
+            | Contract::Refunded(o)
+            | Contract::Amended(o) => o.accepted_contract.offered_contract.id,
             Contract::FailedAccept(c) => c.offered_contract.id,
             Contract::FailedSign(c) => c.accepted_contract.offered_contract.id,
             Contract::PreClosed(c) => c.signed_contract.accepted_contract.offered_contract.id,
+            Contract::CollaborativelyClosed(c) => {
+                c.signed_contract.accepted_contract.offered_contract.id
+            }
             Contract::Closed(c) => c.temporary_contract_id,
         }
     }
@@ -104,26 +158,121 @@ impl Contract {
     /// Returns the public key of the counter party's node.
     pub fn get_counter_party_id(&self) -> PublicKey {
         match self {
-            Contract::Offered(o) | Contract::Rejected(o) => o.counter_party,
+            Contract::Offered(o) | Contract::Rejected(o) | Contract::Expired(o) => o.counter_party,
             Contract::Accepted(a) => a.offered_contract.counter_party,
-            Contract::Signed(s) | Contract::Confirmed(s) | Contract::Refunded(s) => {
-                s.accepted_contract.offered_contract.counter_party
-            }
+            Contract::Signed(s)
+            | Contract::Confirmed(s)
+            | Contract::Refunded(s)
+            | Contract::Amended(s) => s.accepted_contract.offered_contract.counter_party,
             Contract::PreClosed(c) => {
                 c.signed_contract
                     .accepted_contract
                     .offered_contract
                     .counter_party
             }
+            Contract::CollaborativelyClosed(c) => {
+                c.signed_contract
+                    .accepted_contract
+                    .offered_contract
+                    .counter_party
+            }
             Contract::Closed(c) => c.counter_party_id,
             Contract::FailedAccept(f) => f.offered_contract.counter_party,
             Contract::FailedSign(f) => f.accepted_contract.offered_contract.counter_party,
         }
     }
+
+    /// Returns the application defined metadata attached to the contract, if
+    /// any.
+    pub fn get_metadata(&self) -> Option<&ContractMetadata> {
+        match self {
+            Contract::Offered(o) | Contract::Rejected(o) | Contract::Expired(o) => {
+                o.metadata.as_ref()
+            }
+            Contract::Accepted(a) => a.offered_contract.metadata.as_ref(),
+            Contract::Signed(s)
+            | Contract::Confirmed(s)
+            | Contract::Refunded(s)
+            | Contract::Amended(s) => s.accepted_contract.offered_contract.metadata.as_ref(),
+            Contract::PreClosed(c) => c
+                .signed_contract
+                .accepted_contract
+                .offered_contract
+                .metadata
+                .as_ref(),
+            Contract::CollaborativelyClosed(c) => c
+                .signed_contract
+                .accepted_contract
+                .offered_contract
+                .metadata
+                .as_ref(),
+            Contract::Closed(c) => c.metadata.as_ref(),
+            Contract::FailedAccept(f) => f.offered_contract.metadata.as_ref(),
+            Contract::FailedSign(f) => f.accepted_contract.offered_contract.metadata.as_ref(),
+        }
+    }
+
+    /// Replaces the application defined metadata attached to the contract.
+    pub fn set_metadata(&mut self, metadata: Option<ContractMetadata>) {
+        let field = match self {
+            Contract::Offered(o) | Contract::Rejected(o) | Contract::Expired(o) => &mut o.metadata,
+            Contract::Accepted(a) => &mut a.offered_contract.metadata,
+            Contract::Signed(s)
+            | Contract::Confirmed(s)
+            | Contract::Refunded(s)
+            | Contract::Amended(s) => &mut s.accepted_contract.offered_contract.metadata,
+            Contract::PreClosed(c) => {
+                &mut c
+                    .signed_contract
+                    .accepted_contract
+                    .offered_contract
+                    .metadata
+            }
+            Contract::CollaborativelyClosed(c) => {
+                &mut c
+                    .signed_contract
+                    .accepted_contract
+                    .offered_contract
+                    .metadata
+            }
+            Contract::Closed(c) => &mut c.metadata,
+            Contract::FailedAccept(f) => &mut f.offered_contract.metadata,
+            Contract::FailedSign(f) => &mut f.accepted_contract.offered_contract.metadata,
+        };
+        *field = metadata;
+    }
+}
+
+/// Application defined information attached to a contract, persisted with it
+/// through all its states.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct ContractMetadata {
+    /// Labels tagging the contract, see
+    /// [`crate::Storage::get_contracts_by_label`].
+    pub labels: Vec<String>,
+    /// Opaque data, not interpreted by the library.
+    pub data: Vec<u8>,
+}
+
+impl ContractMetadata {
+    /// Returns whether the metadata contains the given label.
+    pub fn has_label(&self, label: &str) -> bool {
+        self.labels.iter().any(|l| l == label)
+    }
 }
 
 /// Information about a contract that failed while verifying an accept message.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct FailedAcceptContract {
     /// The offered contract that was accepted.
     pub offered_contract: offered_contract::OfferedContract,
@@ -135,6 +284,11 @@ pub struct FailedAcceptContract {
 
 /// Information about a contract that failed while verifying a sign message.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct FailedSignContract {
     /// The accepted contract that was signed.
     pub accepted_contract: accepted_contract::AcceptedContract,
@@ -146,6 +300,11 @@ pub struct FailedSignContract {
 
 /// Information about a contract that is almost closed by a broadcasted, but not confirmed CET.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct PreClosedContract {
     /// The signed contract that was closed.
     pub signed_contract: SignedContract,
@@ -157,6 +316,11 @@ pub struct PreClosedContract {
 
 /// Information about a contract that was closed by a CET that was confirmed on the blockchain.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct ClosedContract {
     /// The attestations that were used to decrypt the broadcast CET.
     pub attestations: Option<Vec<OracleAttestation>>,
@@ -170,11 +334,194 @@ pub struct ClosedContract {
     pub counter_party_id: PublicKey,
     /// The profit and loss for the given contract
     pub pnl: i64,
+    /// The announcements matching the attestations, kept so that the outcome
+    /// of the contract can be re-verified without querying the oracles.
+    pub announcements: Option<Vec<OracleAnnouncement>>,
+    /// The application defined metadata of the contract.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: Option<ContractMetadata>,
+}
+
+/// Information about a contract closed by a transaction agreed upon by both
+/// parties.
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct CollaborativelyClosedContract {
+    /// The signed contract that was closed.
+    pub signed_contract: SignedContract,
+    /// The transaction closing the contract, signed by both parties.
+    pub close_tx: Transaction,
+    /// The profit and loss for the given contract
+    pub pnl: i64,
+}
+
+impl PreClosedContract {
+    /// Returns the id of the broadcast CET, to be monitored until it gets
+    /// confirmed.
+    pub fn get_cet_txid(&self) -> Txid {
+        self.signed_cet.txid()
+    }
+}
+
+impl ClosedContract {
+    /// Verifies the archived attestations against the archived announcements,
+    /// checking both the announcement signatures and the signatures over the
+    /// attested outcomes. Returns an error if the contract was not closed
+    /// using attestations or if an attestation has no matching announcement.
+    pub fn verify_attestations<C: Verification>(&self, secp: &Secp256k1<C>) -> Result<(), Error> {
+        let (attestations, announcements) = match (&self.attestations, &self.announcements) {
+            (Some(attestations), Some(announcements)) => (attestations, announcements),
+            _ => {
+                return Err(Error::InvalidState(
+                    "Contract was not closed with archived attestations.".to_string(),
+                ))
+            }
+        };
+
+        for attestation in attestations {
+            let announcement = announcements
+                .iter()
+                .find(|o| {
+                    o.oracle_public_key == attestation.oracle_public_key
+                        && o.oracle_event.oracle_nonces == attestation.nonces()
+                })
+                .ok_or_else(|| {
+                    Error::InvalidState("No announcement matching attestation.".to_string())
+                })?;
+            announcement.validate(secp)?;
+            crate::utils::verify_attestation(secp, attestation)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The type of an event in the history of a contract.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum ContractEventType {
+    /// The contract was offered to the counter party.
+    OfferSent,
+    /// An offer for the contract was received from the counter party.
+    OfferReceived,
+    /// The offer was accepted.
+    Accepted,
+    /// The contract was signed by both parties.
+    Signed,
+    /// The funding transaction of the contract was confirmed.
+    Confirmed,
+    /// The oracles attested the outcome of the event and a CET was broadcast.
+    OracleAttested,
+    /// A transaction closing the contract was confirmed.
+    Closed,
+    /// The refund transaction was confirmed.
+    Refunded,
+    /// The offer was rejected.
+    Rejected,
+    /// The contract could not be accepted or signed.
+    Failed,
+    /// The offer expired before being accepted.
+    Expired,
+    /// The contract was replaced by a contract amending or netting it.
+    Amended,
+    /// A transaction closing the contract by mutual agreement was broadcast.
+    CollaborativelyClosed,
+}
+
+/// An entry of the append-only event log of a contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct ContractEvent {
+    /// The type of the event.
+    pub event_type: ContractEventType,
+    /// The time at which the event happened, in seconds since the unix epoch.
+    pub timestamp: u64,
+    /// The message or operation that triggered the event.
+    pub trigger: String,
+}
+
+impl ContractEvent {
+    /// Returns the events to record when a contract previously in the state
+    /// named `previous` (as returned by [`Contract::get_state_name`], `None`
+    /// if the contract is being created) is moved to the state of `contract`,
+    /// or an empty vector if the state did not change.
+    pub fn from_update(
+        previous: Option<&str>,
+        contract: &Contract,
+        timestamp: u64,
+        trigger: &str,
+    ) -> Vec<ContractEvent> {
+        if previous == Some(contract.get_state_name()) {
+            return Vec::new();
+        }
+        let event_types = match contract {
+            Contract::Offered(o) if o.is_offer_party => vec![ContractEventType::OfferSent],
+            Contract::Offered(_) => vec![ContractEventType::OfferReceived],
+            Contract::Accepted(_) => vec![ContractEventType::Accepted],
+            Contract::Signed(_) => vec![ContractEventType::Signed],
+            Contract::Confirmed(_) => vec![ContractEventType::Confirmed],
+            Contract::PreClosed(_) => vec![ContractEventType::OracleAttested],
+            Contract::Closed(c) if c.attestations.is_some() && previous != Some("pre-closed") => {
+                vec![ContractEventType::OracleAttested, ContractEventType::Closed]
+            }
+            Contract::Closed(_) => vec![ContractEventType::Closed],
+            Contract::Refunded(_) => vec![ContractEventType::Refunded],
+            Contract::Rejected(_) => vec![ContractEventType::Rejected],
+            Contract::Expired(_) => vec![ContractEventType::Expired],
+            Contract::Amended(_) => vec![ContractEventType::Amended],
+            Contract::CollaborativelyClosed(_) => vec![ContractEventType::CollaborativelyClosed],
+            Contract::FailedAccept(_) | Contract::FailedSign(_) => {
+                vec![ContractEventType::Failed]
+            }
+        };
+        event_types
+            .into_iter()
+            .map(|event_type| ContractEvent {
+                event_type,
+                timestamp,
+                trigger: trigger.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// The signatures of a party for the transactions of a contract funded with a
+/// taproot output.
+#[cfg(feature = "taproot")]
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct TaprootSignatures {
+    /// The adaptor signatures for the CETs. The local party does not keep its
+    /// own thus an option is used.
+    pub adaptor_signatures: Option<Vec<dlc::taproot::adaptor::SchnorrAdaptorSignature>>,
+    /// The signature for the refund transaction.
+    pub refund_signature: secp256k1_zkp::schnorr::Signature,
 }
 
 /// Information about the adaptor signatures and the CET for which they are
 /// valid.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub enum AdaptorInfo {
     /// For enumeration outcome DLC, no special information needs to be kept.
     Enum,
@@ -242,8 +589,44 @@ impl ContractDescriptor {
                     )),
                 }
             }
-            EventDescriptor::DigitDecompositionEvent(_) => match self {
+            EventDescriptor::DigitDecompositionEvent(first_desc) => match self {
                 ContractDescriptor::Numerical(n) => {
+                    if announcements.len() != n.oracle_numeric_infos.nb_digits.len() {
+                        return Err(Error::InvalidParameters(
+                            "Expected numeric information for each oracle.".to_string(),
+                        ));
+                    }
+                    for (announcement, nb_digits) in announcements
+                        .iter()
+                        .zip(n.oracle_numeric_infos.nb_digits.iter())
+                    {
+                        match &announcement.oracle_event.event_descriptor {
+                            EventDescriptor::DigitDecompositionEvent(desc) => {
+                                if desc.base as usize != n.oracle_numeric_infos.base
+                                    || desc.nb_digits as usize != *nb_digits
+                                {
+                                    return Err(Error::InvalidParameters(
+                                        "Oracle base or number of digits differ from contract."
+                                            .to_string(),
+                                    ));
+                                }
+                                if desc.is_signed != first_desc.is_signed
+                                    || desc.unit != first_desc.unit
+                                    || desc.precision != first_desc.precision
+                                {
+                                    return Err(Error::InvalidParameters(
+                                        "Oracles don't have same unit, sign or precision."
+                                            .to_string(),
+                                    ));
+                                }
+                            }
+                            _ => {
+                                return Err(Error::InvalidParameters(
+                                    "Expected digit decomposition event descriptor.".to_string(),
+                                ))
+                            }
+                        }
+                    }
                     let min_nb_digits = n.oracle_numeric_infos.get_min_nb_digits();
                     let max_value = n
                         .oracle_numeric_infos
@@ -252,6 +635,15 @@ impl ContractDescriptor {
                         .ok_or_else(|| {
                             Error::InvalidParameters("Could not compute max value".to_string())
                         })?;
+                    if let Some(params) = &n.difference_params {
+                        if params.min_support_exp >= params.max_error_exp
+                            || params.max_error_exp > min_nb_digits
+                        {
+                            return Err(Error::InvalidParameters(
+                                "Invalid difference parameters for oracle digits.".to_string(),
+                            ));
+                        }
+                    }
                     n.validate((max_value - 1) as u64)
                 }
                 _ => Err(Error::InvalidParameters(
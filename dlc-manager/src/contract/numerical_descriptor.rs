@@ -77,6 +77,32 @@ impl NumericalDescriptor {
             .collect())
     }
 
+    /// Returns the number of adaptor signatures required for the contract,
+    /// computed by generating the trie without creating any signature.
+    pub fn get_adaptor_signature_count(
+        &self,
+        total_collateral: u64,
+        threshold: usize,
+    ) -> Result<usize, Error> {
+        let range_payouts = self.get_range_payouts(total_collateral)?;
+        let count = match &self.difference_params {
+            Some(params) => {
+                let mut multi_trie = MultiOracleTrieWithDiff::new(
+                    &self.oracle_numeric_infos,
+                    threshold,
+                    params.min_support_exp,
+                    params.max_error_exp,
+                )?;
+                multi_trie.generate(0, &range_payouts)?.len()
+            }
+            None => {
+                let mut trie = MultiOracleTrie::new(&self.oracle_numeric_infos, threshold)?;
+                trie.generate(0, &range_payouts)?.len()
+            }
+        };
+        Ok(count)
+    }
+
     /// Verify the given set of adaptor signatures and generate the adaptor info.
     pub fn verify_and_get_adaptor_info(
         &self,
@@ -183,4 +209,32 @@ impl NumericalDescriptor {
             }
         }
     }
+
+    /// Generate the adaptor info for the contract without producing the
+    /// adaptor signatures.
+    pub fn generate_adaptor_info(
+        &self,
+        total_collateral: u64,
+        threshold: usize,
+        adaptor_index_start: usize,
+    ) -> Result<AdaptorInfo, Error> {
+        let range_payouts = self.get_range_payouts(total_collateral)?;
+        match &self.difference_params {
+            Some(params) => {
+                let mut multi_trie = MultiOracleTrieWithDiff::new(
+                    &self.oracle_numeric_infos,
+                    threshold,
+                    params.min_support_exp,
+                    params.max_error_exp,
+                )?;
+                multi_trie.generate(adaptor_index_start, &range_payouts)?;
+                Ok(AdaptorInfo::NumericalWithDifference(multi_trie))
+            }
+            None => {
+                let mut trie = MultiOracleTrie::new(&self.oracle_numeric_infos, threshold)?;
+                trie.generate(adaptor_index_start, &range_payouts)?;
+                Ok(AdaptorInfo::Numerical(trie))
+            }
+        }
+    }
 }
@@ -1,18 +1,21 @@
 //! #OfferedContract
 
 use crate::conversion_utils::{
-    get_contract_info_and_announcements, get_tx_input_infos, BITCOIN_CHAINHASH, PROTOCOL_VERSION,
+    get_contract_info_and_announcements, get_fee_split, get_fee_split_field, get_tx_input_infos,
+    BITCOIN_CHAINHASH, PROTOCOL_VERSION,
 };
-use crate::utils::get_new_serial_id;
+use crate::utils::{get_new_serial_id, validate_announcement};
 
 use super::contract_info::ContractInfo;
 use super::contract_input::ContractInput;
-use super::ContractDescriptor;
+use super::{ContractDescriptor, ContractMetadata};
 use crate::KeysId;
-use dlc::PartyParams;
+use bitcoin::hashes::{sha256::Hash as Sha256, Hash, HashEngine};
+use dlc::{FeeSplit, PartyParams};
 use dlc_messages::oracle_msgs::OracleAnnouncement;
-use dlc_messages::{FundingInput, OfferDlc};
-use secp256k1_zkp::PublicKey;
+use dlc_messages::{FundingInput, OfferDlc, ANCHOR_OUTPUTS_FLAG, TAPROOT_FUNDING_FLAG};
+use lightning::util::ser::Writeable;
+use secp256k1_zkp::{PublicKey, Secp256k1, Verification};
 
 /// Contains information about a contract that was offered.
 #[derive(Clone, Debug)]
@@ -47,6 +50,25 @@ pub struct OfferedContract {
     pub refund_locktime: u32,
     /// Keys Id for generating the signers
     pub(crate) keys_id: KeysId,
+    /// Whether the CETs include an anchor output.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub anchor_outputs: bool,
+    /// How the fees of the contract transactions are split between the
+    /// parties.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fee_split: FeeSplit,
+    /// The unix time (in seconds) after which the offer expires if it was
+    /// not accepted.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub expiry: Option<u64>,
+    /// The application defined metadata of the contract, which is not shared
+    /// with the counter party.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: Option<ContractMetadata>,
+    /// Whether the contract is funded with a taproot output instead of a
+    /// P2WSH one.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub taproot: bool,
 }
 
 impl OfferedContract {
@@ -57,6 +79,10 @@ impl OfferedContract {
             crate::error::Error::InvalidParameters("Fee rate is too high".to_string())
         })?;
 
+        if self.taproot {
+            crate::utils::check_taproot_support(self.contract_info.len())?;
+        }
+
         for info in &self.contract_info {
             info.validate()?;
             let payouts = match &info.contract_descriptor {
@@ -76,6 +102,33 @@ impl OfferedContract {
         Ok(())
     }
 
+    /// Returns whether the contract is funded by the offer party alone, the
+    /// accept party contributing neither collateral nor fees.
+    pub fn is_single_funded(&self) -> bool {
+        self.total_collateral == self.offer_params.collateral
+            && matches!(
+                self.fee_split,
+                FeeSplit::OfferPaysAll | FeeSplit::Proportional
+            )
+    }
+
+    /// Validate each oracle announcement of the contract using
+    /// [`validate_announcement`] with the locktimes of the contract.
+    pub fn validate_announcements<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+    ) -> Result<(), crate::error::Error> {
+        for announcement in self
+            .contract_info
+            .iter()
+            .flat_map(|x| x.oracle_announcements.iter())
+        {
+            validate_announcement(secp, announcement, self.cet_locktime, self.refund_locktime)?;
+        }
+
+        Ok(())
+    }
+
     /// Creates a new [`OfferedContract`] from the given parameters.
     pub fn new(
         id: [u8; 32],
@@ -101,7 +154,7 @@ impl OfferedContract {
             .iter()
             .zip(oracle_announcements)
             .map(|(x, y)| ContractInfo {
-                contract_descriptor: x.contract_descriptor.clone(),
+                contract_descriptor: x.get_contract_descriptor(),
                 oracle_announcements: y,
                 threshold: x.oracles.threshold as usize,
             })
@@ -119,9 +172,32 @@ impl OfferedContract {
             refund_locktime: latest_maturity + refund_delay,
             counter_party: *counter_party,
             keys_id,
+            anchor_outputs: contract.anchor_outputs,
+            fee_split: contract.fee_split,
+            expiry: None,
+            metadata: None,
+            taproot: contract.taproot,
         }
     }
 
+    /// Returns whether the offer has an expiry that is before the given unix
+    /// time (in seconds).
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiry.map_or(false, |expiry| expiry < now)
+    }
+
+    /// Returns a hash of the terms of the offer and of the counter party,
+    /// ignoring the temporary id, enabling to recognize an offer that was sent
+    /// again with a different temporary id.
+    pub fn get_offer_hash(&self) -> [u8; 32] {
+        let mut offer = OfferDlc::from(self);
+        offer.temporary_contract_id = [0u8; 32];
+        let mut sha = Sha256::engine();
+        sha.input(&self.counter_party.serialize());
+        sha.input(&offer.encode());
+        Sha256::from_engine(sha).to_byte_array()
+    }
+
     /// Convert an [`OfferDlc`] message to an [`OfferedContract`].
     pub fn try_from_offer_dlc(
         offer_dlc: &OfferDlc,
@@ -154,6 +230,15 @@ impl OfferedContract {
             total_collateral: offer_dlc.contract_info.get_total_collateral(),
             counter_party,
             keys_id,
+            anchor_outputs: offer_dlc.contract_flags & ANCHOR_OUTPUTS_FLAG != 0,
+            fee_split: get_fee_split(
+                offer_dlc.fee_split,
+                offer_dlc.offer_collateral,
+                offer_dlc.contract_info.get_total_collateral(),
+            )?,
+            expiry: offer_dlc.expiry,
+            metadata: None,
+            taproot: offer_dlc.contract_flags & TAPROOT_FUNDING_FLAG != 0,
         })
     }
 }
@@ -163,7 +248,7 @@ impl From<&OfferedContract> for OfferDlc {
         OfferDlc {
             protocol_version: PROTOCOL_VERSION,
             temporary_contract_id: offered_contract.id,
-            contract_flags: 0,
+            contract_flags: get_contract_flags(offered_contract),
             chain_hash: BITCOIN_CHAINHASH,
             contract_info: offered_contract.into(),
             funding_pubkey: offered_contract.offer_params.fund_pubkey,
@@ -177,14 +262,202 @@ impl From<&OfferedContract> for OfferDlc {
             refund_locktime: offered_contract.refund_locktime,
             fee_rate_per_vb: offered_contract.fee_rate_per_vb,
             fund_output_serial_id: offered_contract.fund_output_serial_id,
+            recipient_node_id: Some(offered_contract.counter_party),
+            timestamp: None,
+            expiry: offered_contract.expiry,
+            fee_split: get_fee_split_field(offered_contract.fee_split),
         }
     }
 }
 
+fn get_contract_flags(offered_contract: &OfferedContract) -> u8 {
+    let mut contract_flags = 0;
+    if offered_contract.anchor_outputs {
+        contract_flags |= ANCHOR_OUTPUTS_FLAG;
+    }
+    if offered_contract.taproot {
+        contract_flags |= TAPROOT_FUNDING_FLAG;
+    }
+    contract_flags
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn anchor_outputs_flag_round_trips() {
+        use crate::contract::ser::Serializable;
+
+        let offer_dlc: OfferDlc =
+            serde_json::from_str(include_str!("../../test_inputs/offer_contract.json")).unwrap();
+        let counter_party: PublicKey =
+            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443"
+                .parse()
+                .unwrap();
+        let mut offered_contract =
+            OfferedContract::try_from_offer_dlc(&offer_dlc, counter_party, [0; 32]).unwrap();
+        assert!(!offered_contract.anchor_outputs);
+        offered_contract.anchor_outputs = true;
+
+        let offer_dlc = OfferDlc::from(&offered_contract);
+        assert_eq!(ANCHOR_OUTPUTS_FLAG, offer_dlc.contract_flags);
+        assert!(
+            OfferedContract::try_from_offer_dlc(&offer_dlc, counter_party, [0; 32])
+                .unwrap()
+                .anchor_outputs
+        );
+
+        let serialized = offered_contract.serialize().unwrap();
+        let deserialized =
+            OfferedContract::deserialize(&mut std::io::Cursor::new(&serialized)).unwrap();
+        assert!(deserialized.anchor_outputs);
+        assert!(!deserialized.is_offer_party);
+    }
+
+    #[test]
+    fn taproot_flag_round_trips() {
+        use crate::contract::ser::Serializable;
+
+        let offer_dlc: OfferDlc =
+            serde_json::from_str(include_str!("../../test_inputs/offer_contract.json")).unwrap();
+        let counter_party: PublicKey =
+            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443"
+                .parse()
+                .unwrap();
+        let mut offered_contract =
+            OfferedContract::try_from_offer_dlc(&offer_dlc, counter_party, [0; 32]).unwrap();
+        assert!(!offered_contract.taproot);
+        offered_contract.taproot = true;
+
+        let offer_dlc = OfferDlc::from(&offered_contract);
+        assert_eq!(TAPROOT_FUNDING_FLAG, offer_dlc.contract_flags);
+        assert!(
+            OfferedContract::try_from_offer_dlc(&offer_dlc, counter_party, [0; 32])
+                .unwrap()
+                .taproot
+        );
+
+        let serialized = offered_contract.serialize().unwrap();
+        let deserialized =
+            OfferedContract::deserialize(&mut std::io::Cursor::new(&serialized)).unwrap();
+        assert!(deserialized.taproot);
+        assert!(!deserialized.anchor_outputs);
+    }
+
+    #[test]
+    fn expiry_round_trips() {
+        use crate::contract::ser::Serializable;
+
+        let mut offer_dlc: OfferDlc =
+            serde_json::from_str(include_str!("../../test_inputs/offer_contract.json")).unwrap();
+        offer_dlc.expiry = Some(1000);
+        let counter_party: PublicKey =
+            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443"
+                .parse()
+                .unwrap();
+        let offered_contract =
+            OfferedContract::try_from_offer_dlc(&offer_dlc, counter_party, [0; 32]).unwrap();
+        assert_eq!(Some(1000), offered_contract.expiry);
+        assert!(!offered_contract.is_expired(1000));
+        assert!(offered_contract.is_expired(1001));
+        assert_eq!(Some(1000), OfferDlc::from(&offered_contract).expiry);
+
+        let serialized = offered_contract.serialize().unwrap();
+        let deserialized =
+            OfferedContract::deserialize(&mut std::io::Cursor::new(&serialized)).unwrap();
+        assert_eq!(Some(1000), deserialized.expiry);
+        assert!(!deserialized.anchor_outputs);
+    }
+
+    #[test]
+    fn metadata_round_trips() {
+        use crate::contract::ser::Serializable;
+
+        let offer_dlc: OfferDlc =
+            serde_json::from_str(include_str!("../../test_inputs/offer_contract.json")).unwrap();
+        let counter_party: PublicKey =
+            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443"
+                .parse()
+                .unwrap();
+        let mut offered_contract =
+            OfferedContract::try_from_offer_dlc(&offer_dlc, counter_party, [0; 32]).unwrap();
+        let metadata = ContractMetadata {
+            labels: vec!["hedge-q3".to_string(), "user:42".to_string()],
+            data: vec![0xde, 0xad],
+        };
+        offered_contract.metadata = Some(metadata.clone());
+
+        let serialized = offered_contract.serialize().unwrap();
+        let deserialized =
+            OfferedContract::deserialize(&mut std::io::Cursor::new(&serialized)).unwrap();
+        assert_eq!(Some(metadata), deserialized.metadata);
+        assert_eq!(None, deserialized.expiry);
+    }
+
+    fn get_multi_oracle_numerical_offer(nb_digits: &[u16]) -> serde_json::Value {
+        let mut offer: serde_json::Value = serde_json::from_str(include_str!(
+            "../../test_inputs/offer_numerical_bad_first_payout.json"
+        ))
+        .unwrap();
+        let contract_info = &mut offer["contractInfo"][0];
+        let numerical = &mut contract_info["contractDescriptor"]["numerical"];
+        numerical["payoutFunction"]["payoutFunctionPieces"][0]["polynomialPayoutCurvePiece"]
+            ["payoutPoints"][0]["eventOutcome"] = 0.into();
+        numerical["oracleNumericInfos"]["nbDigits"] = nb_digits.into();
+        numerical["differenceParams"] = serde_json::json!({
+            "maxErrorExp": 6,
+            "minSupportExp": 4,
+            "maximizeCoverage": false
+        });
+        let announcement = contract_info["oracleAnnouncements"][0].clone();
+        contract_info["oracleAnnouncements"] = nb_digits
+            .iter()
+            .map(|nb_digits| {
+                let mut announcement = announcement.clone();
+                announcement["oracleEvent"]["eventDescriptor"]["digitDecompositionEvent"]
+                    ["nbDigits"] = (*nb_digits).into();
+                announcement
+            })
+            .collect();
+        contract_info["threshold"] = 2.into();
+        offer
+    }
+
+    #[test]
+    fn offer_numerical_oracles_with_different_nb_digits() {
+        let offer: OfferedContract =
+            serde_json::from_value(get_multi_oracle_numerical_offer(&[10, 12])).unwrap();
+        offer.validate().expect("the offer to be valid.");
+    }
+
+    #[test]
+    fn offer_numerical_oracle_nb_digits_mismatch() {
+        let mut offer = get_multi_oracle_numerical_offer(&[10, 12]);
+        offer["contractInfo"][0]["contractDescriptor"]["numerical"]["oracleNumericInfos"]
+            ["nbDigits"] = serde_json::json!([10, 10]);
+        let offer: OfferedContract = serde_json::from_value(offer).unwrap();
+        assert!(offer.validate().is_err());
+    }
+
+    #[test]
+    fn offer_numerical_oracles_with_different_precision() {
+        let mut offer = get_multi_oracle_numerical_offer(&[10, 12]);
+        offer["contractInfo"][0]["oracleAnnouncements"][1]["oracleEvent"]["eventDescriptor"]
+            ["digitDecompositionEvent"]["precision"] = 1.into();
+        let offer: OfferedContract = serde_json::from_value(offer).unwrap();
+        assert!(offer.validate().is_err());
+    }
+
+    #[test]
+    fn offer_numerical_max_error_larger_than_nb_digits() {
+        let mut offer = get_multi_oracle_numerical_offer(&[10, 12]);
+        offer["contractInfo"][0]["contractDescriptor"]["numerical"]["differenceParams"]
+            ["maxErrorExp"] = 11.into();
+        let offer: OfferedContract = serde_json::from_value(offer).unwrap();
+        assert!(offer.validate().is_err());
+    }
+
     fn validate_offer_test_common(input: &str) {
         let offer: OfferedContract = serde_json::from_str(input).unwrap();
         assert!(offer.validate().is_err());
@@ -8,24 +8,36 @@ use crate::contract::numerical_descriptor::{DifferenceParams, NumericalDescripto
 use crate::contract::offered_contract::OfferedContract;
 use crate::contract::signed_contract::SignedContract;
 use crate::contract::AdaptorInfo;
+#[cfg(feature = "taproot")]
+use crate::contract::TaprootSignatures;
 use crate::contract::{
-    ClosedContract, ContractDescriptor, FailedAcceptContract, FailedSignContract, PreClosedContract,
+    ClosedContract, CollaborativelyClosedContract, ContractDescriptor, ContractEvent,
+    ContractEventType, ContractMetadata, FailedAcceptContract, FailedSignContract,
+    PreClosedContract,
 };
 use crate::payout_curve::{
     HyperbolaPayoutCurvePiece, PayoutFunction, PayoutFunctionPiece, PayoutPoint,
     PolynomialPayoutCurvePiece, RoundingInterval, RoundingIntervals,
 };
-use dlc::DlcTransactions;
+use dlc::{DlcTransactions, FeeSplit};
+use dlc_messages::oracle_msgs::OracleAnnouncement;
 use dlc_messages::ser_impls::{
-    read_ecdsa_adaptor_signatures, read_option_cb, read_usize, read_vec, read_vec_cb,
-    write_ecdsa_adaptor_signatures, write_option_cb, write_usize, write_vec, write_vec_cb,
+    read_ecdsa_adaptor_signatures, read_option, read_option_cb, read_usize, read_vec, read_vec_cb,
+    write_ecdsa_adaptor_signatures, write_option, write_option_cb, write_usize, write_vec,
+    write_vec_cb,
 };
+#[cfg(feature = "taproot")]
+use dlc_messages::ser_impls::{
+    read_schnorr_adaptor_signatures, read_schnorrsig, write_schnorr_adaptor_signatures,
+    write_schnorrsig,
+};
+use dlc_messages::AcceptDlc;
 use dlc_trie::digit_trie::{DigitNodeData, DigitTrieDump};
 use dlc_trie::multi_oracle_trie::{MultiOracleTrie, MultiOracleTrieDump};
 use dlc_trie::multi_oracle_trie_with_diff::{MultiOracleTrieWithDiff, MultiOracleTrieWithDiffDump};
 use dlc_trie::multi_trie::{MultiTrieDump, MultiTrieNodeData, TrieNodeInfo};
 use dlc_trie::{OracleNumericInfo, RangeInfo};
-use lightning::io::Read;
+use lightning::io::{Read, Write};
 use lightning::ln::msgs::DecodeError;
 use lightning::util::ser::{Readable, Writeable, Writer};
 
@@ -36,6 +48,12 @@ where
 {
     /// Serialize the object.
     fn serialize(&self) -> Result<Vec<u8>, lightning::io::Error>;
+    /// Serialize the object into the given writer, without building an
+    /// intermediate buffer.
+    fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<(), lightning::io::Error>;
+    /// Returns the number of bytes written by [`Serializable::serialize_into`],
+    /// computed without allocating the serialized object.
+    fn serialized_size(&self) -> usize;
     /// Deserialize the object.
     fn deserialize<R: Read>(reader: &mut R) -> Result<Self, DecodeError>;
 }
@@ -50,6 +68,14 @@ where
         Ok(buffer)
     }
 
+    fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<(), lightning::io::Error> {
+        self.write(writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.serialized_length()
+    }
+
     fn deserialize<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
         Readable::read(reader)
     }
@@ -86,20 +112,114 @@ impl_dlc_writeable!(EnumDescriptor, {
         {vec_cb, dlc_messages::ser_impls::enum_payout::write, dlc_messages::ser_impls::enum_payout::read}
     )
 });
-impl_dlc_writeable!(OfferedContract, {
-    (id, writeable),
-    (is_offer_party, writeable),
-    (contract_info, vec),
-    (offer_params, { cb_writeable, dlc_messages::ser_impls::party_params::write, dlc_messages::ser_impls::party_params::read }),
-    (total_collateral, writeable),
-    (funding_inputs, vec),
-    (fund_output_serial_id, writeable),
-    (fee_rate_per_vb, writeable),
-    (cet_locktime, writeable),
-    (refund_locktime, writeable),
-    (counter_party, writeable),
-    (keys_id, writeable)
-});
+/// The anchor outputs flag of an [`OfferedContract`] is stored in the second
+/// bit of the byte holding `is_offer_party`, which was written as a bool before
+/// the flag was introduced.
+const OFFERED_CONTRACT_ANCHOR_OUTPUTS_BIT: u8 = 2;
+/// Set in the same byte when the byte is followed by the expiry of the offer.
+const OFFERED_CONTRACT_EXPIRY_BIT: u8 = 4;
+/// Set in the same byte when the fees are not split equally, in which case the
+/// fee split follows the expiry.
+const OFFERED_CONTRACT_FEE_SPLIT_BIT: u8 = 8;
+/// Set in the same byte when the contract has metadata, which follows the fee
+/// split.
+const OFFERED_CONTRACT_METADATA_BIT: u8 = 16;
+/// Set in the same byte when the contract is funded with a taproot output.
+const OFFERED_CONTRACT_TAPROOT_BIT: u8 = 32;
+
+impl Writeable for OfferedContract {
+    fn write<W: Writer>(&self, w: &mut W) -> Result<(), lightning::io::Error> {
+        self.id.write(w)?;
+        let mut flags = self.is_offer_party as u8;
+        if self.anchor_outputs {
+            flags |= OFFERED_CONTRACT_ANCHOR_OUTPUTS_BIT;
+        }
+        if self.expiry.is_some() {
+            flags |= OFFERED_CONTRACT_EXPIRY_BIT;
+        }
+        if self.fee_split != FeeSplit::Equal {
+            flags |= OFFERED_CONTRACT_FEE_SPLIT_BIT;
+        }
+        if self.metadata.is_some() {
+            flags |= OFFERED_CONTRACT_METADATA_BIT;
+        }
+        if self.taproot {
+            flags |= OFFERED_CONTRACT_TAPROOT_BIT;
+        }
+        flags.write(w)?;
+        if let Some(expiry) = self.expiry {
+            expiry.write(w)?;
+        }
+        if self.fee_split != FeeSplit::Equal {
+            dlc_messages::ser_impls::write_fee_split(&self.fee_split, w)?;
+        }
+        if let Some(metadata) = &self.metadata {
+            metadata.write(w)?;
+        }
+        write_vec(&self.contract_info, w)?;
+        dlc_messages::ser_impls::party_params::write(&self.offer_params, w)?;
+        self.total_collateral.write(w)?;
+        write_vec(&self.funding_inputs, w)?;
+        self.fund_output_serial_id.write(w)?;
+        self.fee_rate_per_vb.write(w)?;
+        self.cet_locktime.write(w)?;
+        self.refund_locktime.write(w)?;
+        self.counter_party.write(w)?;
+        self.keys_id.write(w)
+    }
+}
+
+impl Readable for OfferedContract {
+    fn read<R: Read>(r: &mut R) -> Result<Self, DecodeError> {
+        let id = Readable::read(r)?;
+        let flags: u8 = Readable::read(r)?;
+        if flags
+            & !(1
+                | OFFERED_CONTRACT_ANCHOR_OUTPUTS_BIT
+                | OFFERED_CONTRACT_EXPIRY_BIT
+                | OFFERED_CONTRACT_FEE_SPLIT_BIT
+                | OFFERED_CONTRACT_METADATA_BIT
+                | OFFERED_CONTRACT_TAPROOT_BIT)
+            != 0
+        {
+            return Err(DecodeError::InvalidValue);
+        }
+        let expiry = if flags & OFFERED_CONTRACT_EXPIRY_BIT != 0 {
+            Some(Readable::read(r)?)
+        } else {
+            None
+        };
+        let fee_split = if flags & OFFERED_CONTRACT_FEE_SPLIT_BIT != 0 {
+            dlc_messages::ser_impls::read_fee_split(r)?
+        } else {
+            FeeSplit::Equal
+        };
+        let metadata = if flags & OFFERED_CONTRACT_METADATA_BIT != 0 {
+            Some(Readable::read(r)?)
+        } else {
+            None
+        };
+        Ok(OfferedContract {
+            id,
+            is_offer_party: flags & 1 != 0,
+            anchor_outputs: flags & OFFERED_CONTRACT_ANCHOR_OUTPUTS_BIT != 0,
+            expiry,
+            fee_split,
+            metadata,
+            taproot: flags & OFFERED_CONTRACT_TAPROOT_BIT != 0,
+            contract_info: read_vec(r)?,
+            offer_params: dlc_messages::ser_impls::party_params::read(r)?,
+            total_collateral: Readable::read(r)?,
+            funding_inputs: read_vec(r)?,
+            fund_output_serial_id: Readable::read(r)?,
+            fee_rate_per_vb: Readable::read(r)?,
+            cet_locktime: Readable::read(r)?,
+            refund_locktime: Readable::read(r)?,
+            counter_party: Readable::read(r)?,
+            keys_id: Readable::read(r)?,
+        })
+    }
+}
 impl_dlc_writeable_external!(RangeInfo, range_info, { (cet_index, usize), (adaptor_index, usize)});
 impl_dlc_writeable_enum!(AdaptorInfo,;; (0, Numerical, write_multi_oracle_trie, read_multi_oracle_trie), (1, NumericalWithDifference, write_multi_oracle_trie_with_diff, read_multi_oracle_trie_with_diff); (2, Enum));
 impl_dlc_writeable_external!(
@@ -109,37 +229,173 @@ impl_dlc_writeable_external!(
     (refund, writeable),
     (funding_script_pubkey, writeable) }
 );
-impl_dlc_writeable!(AcceptedContract, {
-    (offered_contract, writeable),
-    (accept_params, { cb_writeable, dlc_messages::ser_impls::party_params::write, dlc_messages::ser_impls::party_params::read }),
-    (funding_inputs, vec),
-    (adaptor_infos, vec),
-    (adaptor_signatures, {option_cb, write_ecdsa_adaptor_signatures, read_ecdsa_adaptor_signatures }),
-    (accept_refund_signature, writeable),
-    (dlc_transactions, {cb_writeable, dlc_transactions::write, dlc_transactions::read })
-});
-impl_dlc_writeable!(SignedContract, {
-    (accepted_contract, writeable),
-    (adaptor_signatures, {option_cb, write_ecdsa_adaptor_signatures, read_ecdsa_adaptor_signatures }),
-    (offer_refund_signature, writeable),
-    (funding_signatures, writeable),
-    (channel_id, option)
+#[cfg(feature = "taproot")]
+impl_dlc_writeable!(TaprootSignatures, {
+    (adaptor_signatures, {option_cb, write_schnorr_adaptor_signatures, read_schnorr_adaptor_signatures}),
+    (refund_signature, {cb_writeable, write_schnorrsig, read_schnorrsig})
 });
+
+// The ECDSA refund signature is only written for contracts funded with a P2WSH
+// output, and the signatures of contracts funded with a taproot output are
+// written last, so that the serialization of other contracts is unchanged.
+impl Writeable for AcceptedContract {
+    fn write<W: Writer>(&self, w: &mut W) -> Result<(), lightning::io::Error> {
+        self.offered_contract.write(w)?;
+        dlc_messages::ser_impls::party_params::write(&self.accept_params, w)?;
+        write_vec(&self.funding_inputs, w)?;
+        write_vec(&self.adaptor_infos, w)?;
+        write_option_cb(&self.adaptor_signatures, w, &write_ecdsa_adaptor_signatures)?;
+        if let Some(refund_signature) = &self.accept_refund_signature {
+            refund_signature.write(w)?;
+        }
+        dlc_transactions::write(&self.dlc_transactions, w)?;
+        #[cfg(feature = "taproot")]
+        {
+            if let Some(taproot_signatures) = &self.accept_taproot_signatures {
+                taproot_signatures.write(w)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Readable for AcceptedContract {
+    fn read<R: Read>(r: &mut R) -> Result<Self, DecodeError> {
+        let offered_contract: OfferedContract = Readable::read(r)?;
+        let taproot = offered_contract.taproot;
+        if taproot && !cfg!(feature = "taproot") {
+            return Err(DecodeError::UnknownRequiredFeature);
+        }
+        let accept_params = dlc_messages::ser_impls::party_params::read(r)?;
+        let funding_inputs = read_vec(r)?;
+        let adaptor_infos = read_vec(r)?;
+        let adaptor_signatures = read_option_cb(r, &read_ecdsa_adaptor_signatures)?;
+        let accept_refund_signature = if taproot {
+            None
+        } else {
+            Some(Readable::read(r)?)
+        };
+        let dlc_transactions = dlc_transactions::read(r)?;
+        Ok(AcceptedContract {
+            offered_contract,
+            accept_params,
+            funding_inputs,
+            adaptor_infos,
+            adaptor_signatures,
+            accept_refund_signature,
+            dlc_transactions,
+            #[cfg(feature = "taproot")]
+            accept_taproot_signatures: if taproot {
+                Some(Readable::read(r)?)
+            } else {
+                None
+            },
+        })
+    }
+}
+
+impl Writeable for SignedContract {
+    fn write<W: Writer>(&self, w: &mut W) -> Result<(), lightning::io::Error> {
+        self.accepted_contract.write(w)?;
+        write_option_cb(&self.adaptor_signatures, w, &write_ecdsa_adaptor_signatures)?;
+        if let Some(refund_signature) = &self.offer_refund_signature {
+            refund_signature.write(w)?;
+        }
+        self.funding_signatures.write(w)?;
+        write_option(&self.channel_id, w)?;
+        #[cfg(feature = "taproot")]
+        {
+            if let Some(taproot_signatures) = &self.offer_taproot_signatures {
+                taproot_signatures.write(w)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Readable for SignedContract {
+    fn read<R: Read>(r: &mut R) -> Result<Self, DecodeError> {
+        let accepted_contract: AcceptedContract = Readable::read(r)?;
+        let taproot = accepted_contract.offered_contract.taproot;
+        let adaptor_signatures = read_option_cb(r, &read_ecdsa_adaptor_signatures)?;
+        let offer_refund_signature = if taproot {
+            None
+        } else {
+            Some(Readable::read(r)?)
+        };
+        Ok(SignedContract {
+            accepted_contract,
+            adaptor_signatures,
+            offer_refund_signature,
+            funding_signatures: Readable::read(r)?,
+            channel_id: read_option(r)?,
+            #[cfg(feature = "taproot")]
+            offer_taproot_signatures: if taproot {
+                Some(Readable::read(r)?)
+            } else {
+                None
+            },
+        })
+    }
+}
 impl_dlc_writeable!(PreClosedContract, {
     (signed_contract, writeable),
     (attestations, {option_cb, write_vec, read_vec}),
     (signed_cet, writeable)
 });
+impl_dlc_writeable!(CollaborativelyClosedContract, {
+    (signed_contract, writeable),
+    (close_tx, writeable),
+    (pnl, i64)
+});
 impl_dlc_writeable!(ClosedContract, {
     (attestations, {option_cb, write_vec, read_vec}),
     (signed_cet, writeable),
     (contract_id, writeable),
     (temporary_contract_id, writeable),
     (counter_party_id, writeable),
-    (pnl, i64)
+    (pnl, i64),
+    (announcements, {cb_writeable, write_archived_announcements, read_archived_announcements}),
+    (metadata, {cb_writeable, write_closed_contract_metadata, read_closed_contract_metadata})
+});
+impl_dlc_writeable!(ContractMetadata, {
+    (labels, {cb_writeable, dlc_messages::ser_impls::write_strings, dlc_messages::ser_impls::read_strings}),
+    (data, vec)
 });
-impl_dlc_writeable!(FailedAcceptContract, {(offered_contract, writeable), (accept_message, writeable), (error_message, string)});
+
+// The TLV stream of the accept message extends to the end of the input, so it
+// is written after the error message, which also keeps records written before
+// the stream existed readable.
+impl Writeable for FailedAcceptContract {
+    fn write<W: Writer>(&self, w: &mut W) -> Result<(), lightning::io::Error> {
+        self.offered_contract.write(w)?;
+        self.accept_message.write_fields(w)?;
+        dlc_messages::ser_impls::write_string(&self.error_message, w)?;
+        self.accept_message.write_tlv_stream(w)
+    }
+}
+
+impl Readable for FailedAcceptContract {
+    fn read<R: Read>(r: &mut R) -> Result<Self, DecodeError> {
+        let offered_contract = Readable::read(r)?;
+        let mut accept_message = AcceptDlc::read_fields(r)?;
+        let error_message = dlc_messages::ser_impls::read_string(r)?;
+        accept_message.read_tlv_stream(r)?;
+        Ok(FailedAcceptContract {
+            offered_contract,
+            accept_message,
+            error_message,
+        })
+    }
+}
+
 impl_dlc_writeable!(FailedSignContract, {(accepted_contract, writeable), (sign_message, writeable), (error_message, string)});
+impl_dlc_writeable_enum!(ContractEventType,;;;
+    (0, OfferSent), (1, OfferReceived), (2, Accepted), (3, Signed), (4, Confirmed),
+    (5, OracleAttested), (6, Closed), (7, Refunded), (8, Rejected), (9, Failed), (10, Expired),
+    (11, Amended), (12, CollaborativelyClosed)
+);
+impl_dlc_writeable!(ContractEvent, { (event_type, writeable), (timestamp, writeable), (trigger, string) });
 
 impl_dlc_writeable_external!(DigitTrieDump<Vec<RangeInfo> >, digit_trie_dump_vec_range, { (node_data, {vec_cb, write_digit_node_data_vec_range, read_digit_node_data_vec_range}), (root, {option_cb, write_usize, read_usize}), (base, usize)});
 impl_dlc_writeable_external!(DigitTrieDump<RangeInfo>, digit_trie_dump_range, { (node_data, {vec_cb, write_digit_node_data_range, read_digit_node_data_range}), (root, {option_cb, write_usize, read_usize}), (base, usize)});
@@ -156,6 +412,44 @@ impl_dlc_writeable_external!(MultiTrieDump<RangeInfo>, multi_trie_dump, { (node_
 impl_dlc_writeable_external!(MultiOracleTrieWithDiffDump, multi_oracle_trie_with_diff_dump, { (multi_trie_dump, {cb_writeable, multi_trie_dump::write, multi_trie_dump::read}), (oracle_numeric_infos, {cb_writeable, oracle_params::write, oracle_params::read}) });
 impl_dlc_writeable_external!(TrieNodeInfo, trie_node_info, { (trie_index, usize), (store_index, usize) });
 
+fn write_archived_announcements<W: Writer>(
+    announcements: &Option<Vec<OracleAnnouncement>>,
+    writer: &mut W,
+) -> Result<(), lightning::io::Error> {
+    write_option_cb(announcements, writer, &write_vec)
+}
+
+/// Closed contracts persisted before announcements were archived end right
+/// after the pnl field, so a missing value is read as `None`.
+fn read_archived_announcements<R: Read>(
+    reader: &mut R,
+) -> Result<Option<Vec<OracleAnnouncement>>, DecodeError> {
+    match read_option_cb(reader, &read_vec) {
+        Err(DecodeError::ShortRead) => Ok(None),
+        res => res,
+    }
+}
+
+fn write_closed_contract_metadata<W: Writer>(
+    metadata: &Option<ContractMetadata>,
+    writer: &mut W,
+) -> Result<(), lightning::io::Error> {
+    write_option_cb(metadata, writer, &|m: &ContractMetadata, w: &mut W| {
+        m.write(w)
+    })
+}
+
+/// Closed contracts persisted before metadata was introduced end right after
+/// the archived announcements, so a missing value is read as `None`.
+fn read_closed_contract_metadata<R: Read>(
+    reader: &mut R,
+) -> Result<Option<ContractMetadata>, DecodeError> {
+    match read_option_cb(reader, &|r: &mut R| Readable::read(r)) {
+        Err(DecodeError::ShortRead) => Ok(None),
+        res => res,
+    }
+}
+
 fn write_digit_node_data_trie<W: Writer>(
     input: &DigitNodeData<Vec<TrieNodeInfo>>,
     writer: &mut W,
@@ -273,3 +567,35 @@ fn read_multi_oracle_trie_with_diff<R: Read>(
     let dump = multi_oracle_trie_with_diff_dump::read(reader)?;
     Ok(MultiOracleTrieWithDiff::from_dump(dump))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::{PublicKey, SecretKey, SECP256K1};
+
+    #[test]
+    fn failed_accept_contract_keeps_accept_tlv_stream() {
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../../test_inputs/offer_contract.json")).unwrap();
+        let counter_party =
+            PublicKey::from_secret_key(SECP256K1, &SecretKey::from_slice(&[1; 32]).unwrap());
+        let offered_contract =
+            OfferedContract::try_from_offer_dlc(&offer, counter_party, [0; 32]).unwrap();
+        let mut accept_message: AcceptDlc = serde_json::from_str(include_str!(
+            "../../../dlc-messages/src/test_inputs/accept_msg.json"
+        ))
+        .unwrap();
+        accept_message.fee_split = Some(FeeSplit::Proportional);
+        let failed = FailedAcceptContract {
+            offered_contract,
+            accept_message,
+            error_message: "Invalid adaptor signature".to_string(),
+        };
+
+        let deser = FailedAcceptContract::deserialize(&mut &failed.serialize().unwrap()[..])
+            .expect("to deserialize the failed accept contract");
+
+        assert_eq!(failed.accept_message, deser.accept_message);
+        assert_eq!(failed.error_message, deser.error_message);
+    }
+}
@@ -1,9 +1,11 @@
 //! #SignedContract
 
 use crate::conversion_utils::PROTOCOL_VERSION;
+use crate::error::Error;
 use crate::ChannelId;
 
 use super::accepted_contract::AcceptedContract;
+use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
 use dlc_messages::CetAdaptorSignature;
 use dlc_messages::CetAdaptorSignatures;
 use dlc_messages::FundingSignatures;
@@ -13,27 +15,48 @@ use secp256k1_zkp::EcdsaAdaptorSignature;
 
 /// Contain information about a contract that was fully signed.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct SignedContract {
     /// The accepted contract that was signed.
     pub accepted_contract: AcceptedContract,
     /// The adaptor signatures of the offering party (None if offering party).
     pub adaptor_signatures: Option<Vec<EcdsaAdaptorSignature>>,
-    /// The refund signature of the offering party.
-    pub offer_refund_signature: Signature,
+    /// The refund signature of the offering party, set unless the contract is
+    /// funded with a taproot output.
+    pub offer_refund_signature: Option<Signature>,
     /// The signatures for the funding inputs of the offering party.
     pub funding_signatures: FundingSignatures,
     /// The [`ChannelId`] to which the contract was associated if any.
     pub channel_id: Option<ChannelId>,
+    /// The signatures of the offering party, set only if the contract is
+    /// funded with a taproot output.
+    #[cfg(feature = "taproot")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offer_taproot_signatures: Option<super::TaprootSignatures>,
 }
 
 impl SignedContract {
+    /// Returns the refund signature of the offering party, which is not set
+    /// for contracts funded with a taproot output.
+    pub(crate) fn get_offer_refund_signature(&self) -> Result<Signature, Error> {
+        self.offer_refund_signature.ok_or_else(|| {
+            Error::InvalidState(
+                "Contract funded with a taproot output has no ECDSA refund signature".to_string(),
+            )
+        })
+    }
+
     pub(crate) fn get_sign_dlc(
         &self,
         cet_adaptor_signatures: Vec<EcdsaAdaptorSignature>,
-    ) -> SignDlc {
+    ) -> Result<SignDlc, Error> {
         let contract_id = self.accepted_contract.get_contract_id();
 
-        SignDlc {
+        Ok(SignDlc {
             protocol_version: PROTOCOL_VERSION,
             contract_id,
             cet_adaptor_signatures: CetAdaptorSignatures {
@@ -42,8 +65,30 @@ impl SignedContract {
                     .map(|x| CetAdaptorSignature { signature: x })
                     .collect(),
             },
-            refund_signature: self.offer_refund_signature,
+            refund_signature: self.get_offer_refund_signature()?,
             funding_signatures: self.funding_signatures.clone(),
-        }
+        })
+    }
+
+    /// Returns the announcements of the contract for which one of the given
+    /// attestations was produced.
+    pub(crate) fn get_attested_announcements(
+        &self,
+        attestations: &[OracleAttestation],
+    ) -> Vec<OracleAnnouncement> {
+        let contract_infos = &self.accepted_contract.offered_contract.contract_info;
+        attestations
+            .iter()
+            .filter_map(|a| {
+                contract_infos
+                    .iter()
+                    .flat_map(|c| c.oracle_announcements.iter())
+                    .find(|o| {
+                        o.oracle_public_key == a.oracle_public_key
+                            && o.oracle_event.oracle_nonces == a.nonces()
+                    })
+                    .cloned()
+            })
+            .collect()
     }
 }
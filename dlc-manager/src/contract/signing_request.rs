@@ -0,0 +1,186 @@
+//! # ContractSigningRequest
+
+use super::offered_contract::OfferedContract;
+use super::AdaptorInfo;
+use crate::error::Error;
+use crate::ContractId;
+use bitcoin::{ScriptBuf, Transaction};
+use dlc::{DlcTransactions, PartyParams};
+use dlc_messages::FundingInput;
+use secp256k1_zkp::ecdsa::Signature;
+use secp256k1_zkp::{All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey};
+
+/// The adaptor point with which a CET adaptor signature is to be produced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct CetAdaptorPoint {
+    /// The index of the CET to sign in the set of CETs of the request.
+    pub cet_index: usize,
+    /// The adaptor point to use for the signature.
+    pub adaptor_point: PublicKey,
+}
+
+/// Contains the unsigned transactions of a contract together with the
+/// information required for a signer holding the funding secret key of the
+/// local party to produce its CET adaptor signatures and refund signature.
+/// Used to keep the funding keys outside of the process running the
+/// [`crate::manager::Manager`], for example in a hardware security module.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct ContractSigningRequest {
+    /// The id under which the contract is currently stored.
+    pub contract_id: ContractId,
+    /// The key id from which the funding key of the local party was derived.
+    pub keys_id: [u8; 32],
+    /// The funding public key of the local party.
+    pub fund_pubkey: PublicKey,
+    /// The unsigned funding transaction.
+    pub fund: Transaction,
+    /// The script pubkey of the funding output, used to sign its spending
+    /// transactions.
+    pub funding_script_pubkey: ScriptBuf,
+    /// The value of the funding output.
+    pub fund_output_value: u64,
+    /// The contract execution transactions.
+    pub cets: Vec<Transaction>,
+    /// The adaptor points for which a CET adaptor signature is requested,
+    /// in the order in which the signatures are expected.
+    pub adaptor_points: Vec<CetAdaptorPoint>,
+    /// The refund transaction.
+    pub refund: Transaction,
+}
+
+/// The signatures produced by a signer for a [`ContractSigningRequest`].
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct ContractSignatures {
+    /// The CET adaptor signatures, in the order of the requested adaptor
+    /// points.
+    pub cet_adaptor_signatures: Vec<EcdsaAdaptorSignature>,
+    /// The signature for the refund transaction.
+    pub refund_signature: Signature,
+}
+
+/// A contract being accepted whose signatures from the accepting party are
+/// still to be produced by an external signer.
+#[derive(Clone)]
+pub struct UnsignedAcceptedContract {
+    /// The offered contract being accepted.
+    pub offered_contract: OfferedContract,
+    /// The parameters of the accepting party.
+    pub accept_params: PartyParams,
+    /// The funding inputs provided by the accepting party.
+    pub funding_inputs: Vec<FundingInput>,
+    /// The adaptor information for the contract.
+    pub adaptor_infos: Vec<AdaptorInfo>,
+    /// The set of bitcoin transactions for the contract.
+    pub dlc_transactions: DlcTransactions,
+}
+
+impl ContractSigningRequest {
+    /// Produces the requested signatures using the given funding secret key.
+    /// Meant to be used by signers, which should check the transactions of
+    /// the request before calling it.
+    pub fn sign(
+        &self,
+        secp: &Secp256k1<All>,
+        fund_secret_key: &SecretKey,
+    ) -> Result<ContractSignatures, Error> {
+        if PublicKey::from_secret_key(secp, fund_secret_key) != self.fund_pubkey {
+            return Err(Error::InvalidParameters(
+                "Secret key does not match the funding public key of the request".to_string(),
+            ));
+        }
+
+        let cet_adaptor_signatures = self
+            .adaptor_points
+            .iter()
+            .map(|x| {
+                Ok(dlc::create_cet_adaptor_sig_from_point(
+                    secp,
+                    self.get_cet(x.cet_index)?,
+                    &x.adaptor_point,
+                    fund_secret_key,
+                    &self.funding_script_pubkey,
+                    self.fund_output_value,
+                )?)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let refund_signature = dlc::util::get_raw_sig_for_tx_input(
+            secp,
+            &self.refund,
+            0,
+            &self.funding_script_pubkey,
+            self.fund_output_value,
+            fund_secret_key,
+        )?;
+
+        Ok(ContractSignatures {
+            cet_adaptor_signatures,
+            refund_signature,
+        })
+    }
+
+    /// Verifies that the given signatures were produced for this request by
+    /// the owner of the funding public key.
+    pub fn verify(
+        &self,
+        secp: &Secp256k1<All>,
+        signatures: &ContractSignatures,
+    ) -> Result<(), Error> {
+        if signatures.cet_adaptor_signatures.len() != self.adaptor_points.len() {
+            return Err(Error::InvalidParameters(format!(
+                "Expected {} adaptor signatures, got {}",
+                self.adaptor_points.len(),
+                signatures.cet_adaptor_signatures.len()
+            )));
+        }
+
+        for (point, sig) in self
+            .adaptor_points
+            .iter()
+            .zip(signatures.cet_adaptor_signatures.iter())
+        {
+            dlc::verify_cet_adaptor_sig_from_point(
+                secp,
+                sig,
+                self.get_cet(point.cet_index)?,
+                &point.adaptor_point,
+                &self.fund_pubkey,
+                &self.funding_script_pubkey,
+                self.fund_output_value,
+            )?;
+        }
+
+        dlc::verify_tx_input_sig(
+            secp,
+            &signatures.refund_signature,
+            &self.refund,
+            0,
+            &self.funding_script_pubkey,
+            self.fund_output_value,
+            &self.fund_pubkey,
+        )?;
+
+        Ok(())
+    }
+
+    fn get_cet(&self, cet_index: usize) -> Result<&Transaction, Error> {
+        self.cets.get(cet_index).ok_or_else(|| {
+            Error::InvalidParameters(format!("No CET at index {} in the request", cet_index))
+        })
+    }
+}
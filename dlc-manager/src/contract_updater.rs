@@ -2,26 +2,39 @@
 
 use std::ops::Deref;
 
+use bitcoin::absolute::LockTime;
 use bitcoin::psbt::PartiallySignedTransaction;
-use bitcoin::{consensus::Decodable, Script, Transaction, Witness};
-use dlc::{DlcTransactions, PartyParams};
+use bitcoin::{
+    consensus::Decodable, sighash::EcdsaSighashType, OutPoint, Script, ScriptBuf, Sequence,
+    Transaction, TxIn, TxOut, Witness,
+};
+use dlc::{DlcTransactions, FeeSplit, PartyParams};
 use dlc_messages::FundingInput;
 use dlc_messages::{
     oracle_msgs::{OracleAnnouncement, OracleAttestation},
-    AcceptDlc, FundingSignature, FundingSignatures, OfferDlc, SignDlc, WitnessElement,
+    AcceptDlc, AmendAccept, AmendOffer, AmendSign, FundingSignature, FundingSignatures,
+    MutualCloseOffer, NetAccept, NetOffer, NetSign, OfferDlc, SignDlc, WitnessElement,
 };
 use secp256k1_zkp::{
     ecdsa::Signature, All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey, Signing,
 };
 
+use crate::contract::signing_request::{
+    CetAdaptorPoint, ContractSignatures, ContractSigningRequest, UnsignedAcceptedContract,
+};
 use crate::{
+    cancellation::CancellationToken,
     contract::{
         accepted_contract::AcceptedContract, contract_info::ContractInfo,
         contract_input::ContractInput, offered_contract::OfferedContract,
         signed_contract::SignedContract, AdaptorInfo,
     },
-    conversion_utils::get_tx_input_infos,
-    error::Error,
+    conversion_utils::{
+        check_accepted_fee_split, get_contract_info_and_announcements, get_tx_input_infos,
+    },
+    error::{Error, ResultExt},
+    netting::{get_net_terms, get_netted_collaterals, NetTerms},
+    progress::{self, ProgressStage, ProgressTracker},
     Blockchain, ChannelId, ContractSigner, ContractSignerProvider, Time, Wallet,
 };
 
@@ -52,6 +65,13 @@ where
     let (party_params, funding_inputs_info) = crate::utils::get_party_params(
         secp,
         contract_input.offer_collateral,
+        crate::utils::get_approximate_fee(
+            contract_input.fee_rate,
+            contract_input.fee_split,
+            true,
+            contract_input.offer_collateral,
+            contract_input.accept_collateral,
+        )?,
         contract_input.fee_rate,
         wallet,
         &signer,
@@ -70,7 +90,8 @@ where
         keys_id,
     );
 
-    let offer_msg: OfferDlc = (&offered_contract).into();
+    let mut offer_msg: OfferDlc = (&offered_contract).into();
+    offer_msg.timestamp = Some(time.unix_time_now());
 
     Ok((offered_contract, offer_msg))
 }
@@ -83,18 +104,21 @@ pub fn accept_contract<W: Deref, X: ContractSigner, SP: Deref, B: Deref>(
     wallet: &W,
     signer_provider: &SP,
     blockchain: &B,
+    cancellation: &CancellationToken,
 ) -> Result<(AcceptedContract, AcceptDlc), Error>
 where
     W::Target: Wallet,
     B::Target: Blockchain,
     SP::Target: ContractSignerProvider<Signer = X>,
 {
+    check_not_taproot(offered_contract)?;
     let total_collateral = offered_contract.total_collateral;
 
     let signer = signer_provider.derive_contract_signer(offered_contract.keys_id)?;
     let (accept_params, funding_inputs) = crate::utils::get_party_params(
         secp,
         total_collateral - offered_contract.offer_params.collateral,
+        get_accept_party_approximate_fee(offered_contract)?,
         offered_contract.fee_rate_per_vb,
         wallet,
         &signer,
@@ -110,22 +134,30 @@ where
         0,
         offered_contract.cet_locktime,
         offered_contract.fund_output_serial_id,
-    )?;
+        offered_contract.anchor_outputs,
+        offered_contract.fee_split,
+    )
+    .context(&offered_contract.id, "creating DLC transactions")?;
 
     let fund_output_value = dlc_transactions.get_fund_output().value;
 
-    let (accepted_contract, adaptor_sigs) = accept_contract_internal(
-        secp,
-        offered_contract,
+    let (accepted_contract, adaptor_sigs) = crate::utils::release_on_cancellation(
+        wallet,
         &accept_params,
-        &funding_inputs,
-        &signer.get_secret_key()?,
-        fund_output_value,
-        None,
-        &dlc_transactions,
+        accept_contract_internal(
+            secp,
+            offered_contract,
+            &accept_params,
+            &funding_inputs,
+            &signer.get_secret_key()?,
+            fund_output_value,
+            None,
+            &dlc_transactions,
+            cancellation,
+        ),
     )?;
 
-    let accept_msg: AcceptDlc = accepted_contract.get_accept_contract_msg(&adaptor_sigs);
+    let accept_msg: AcceptDlc = accepted_contract.get_accept_contract_msg(&adaptor_sigs)?;
 
     Ok((accepted_contract, accept_msg))
 }
@@ -139,7 +171,12 @@ pub(crate) fn accept_contract_internal(
     input_value: u64,
     input_script_pubkey: Option<&Script>,
     dlc_transactions: &DlcTransactions,
+    cancellation: &CancellationToken,
 ) -> Result<(AcceptedContract, Vec<EcdsaAdaptorSignature>), crate::Error> {
+    enter_span!(
+        "accept_contract",
+        contract_id = %offered_contract.id.to_lower_hex_string()
+    );
     let total_collateral = offered_contract.total_collateral;
 
     let input_script_pubkey =
@@ -147,7 +184,19 @@ pub(crate) fn accept_contract_internal(
 
     let cet_input = dlc_transactions.cets[0].input[0].clone();
 
-    let (adaptor_info, adaptor_sig) = offered_contract.contract_info[0].get_adaptor_info(
+    cancellation.check()?;
+    let mut tracker = ProgressTracker::new(
+        cancellation,
+        ProgressStage::CreatingAdaptorSignatures,
+        progress::get_total_adaptor_signatures(
+            cancellation,
+            &offered_contract.contract_info,
+            total_collateral,
+        )?,
+    );
+    let (adaptor_info, adaptor_sig) = progress::get_adaptor_info(
+        &mut tracker,
+        &offered_contract.contract_info[0],
         secp,
         offered_contract.total_collateral,
         adaptor_secret_key,
@@ -169,6 +218,7 @@ pub(crate) fn accept_contract_internal(
     let mut cets = cets.clone();
 
     for contract_info in offered_contract.contract_info.iter().skip(1) {
+        cancellation.check()?;
         let payouts = contract_info.get_payouts(total_collateral)?;
 
         let tmp_cets = dlc::create_cets(
@@ -181,7 +231,9 @@ pub(crate) fn accept_contract_internal(
             0,
         );
 
-        let (adaptor_info, adaptor_sig) = contract_info.get_adaptor_info(
+        let (adaptor_info, adaptor_sig) = progress::get_adaptor_info(
+            &mut tracker,
+            contract_info,
             secp,
             offered_contract.total_collateral,
             adaptor_secret_key,
@@ -204,7 +256,8 @@ pub(crate) fn accept_contract_internal(
         input_script_pubkey,
         input_value,
         adaptor_secret_key,
-    )?;
+    )
+    .context(&offered_contract.id, "signing refund transaction")?;
 
     let dlc_transactions = DlcTransactions {
         fund: fund.clone(),
@@ -221,12 +274,222 @@ pub(crate) fn accept_contract_internal(
         accept_params: accept_params.clone(),
         funding_inputs: funding_inputs.to_vec(),
         dlc_transactions,
-        accept_refund_signature: refund_signature,
+        accept_refund_signature: Some(refund_signature),
+        #[cfg(feature = "taproot")]
+        accept_taproot_signatures: None,
     };
 
     Ok((accepted_contract, adaptor_sigs))
 }
 
+/// Creates the accepting party's parameters and DLC transactions for the given
+/// offered contract, together with the adaptor information, without producing
+/// any signature. The accepting party's signatures can then be created by an
+/// external signer using the request returned by [`get_contract_signing_request`],
+/// and the contract completed with [`accept_contract_with_signatures`].
+pub fn prepare_accept_contract<W: Deref, X: ContractSigner, SP: Deref, B: Deref>(
+    secp: &Secp256k1<All>,
+    offered_contract: &OfferedContract,
+    wallet: &W,
+    signer_provider: &SP,
+    blockchain: &B,
+    cancellation: &CancellationToken,
+) -> Result<UnsignedAcceptedContract, Error>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    check_external_signing_support(offered_contract)?;
+    check_not_taproot(offered_contract)?;
+
+    let total_collateral = offered_contract.total_collateral;
+
+    let signer = signer_provider.derive_contract_signer(offered_contract.keys_id)?;
+    let (accept_params, funding_inputs) = crate::utils::get_party_params(
+        secp,
+        total_collateral - offered_contract.offer_params.collateral,
+        get_accept_party_approximate_fee(offered_contract)?,
+        offered_contract.fee_rate_per_vb,
+        wallet,
+        &signer,
+        blockchain,
+    )?;
+
+    crate::utils::release_on_cancellation(
+        wallet,
+        &accept_params,
+        prepare_accept_contract_internal(
+            offered_contract,
+            &accept_params,
+            funding_inputs,
+            cancellation,
+        ),
+    )
+}
+
+fn prepare_accept_contract_internal(
+    offered_contract: &OfferedContract,
+    accept_params: &PartyParams,
+    funding_inputs: Vec<FundingInput>,
+    cancellation: &CancellationToken,
+) -> Result<UnsignedAcceptedContract, Error> {
+    let total_collateral = offered_contract.total_collateral;
+
+    let dlc_transactions = dlc::create_dlc_transactions(
+        &offered_contract.offer_params,
+        accept_params,
+        &offered_contract.contract_info[0].get_payouts(total_collateral)?,
+        offered_contract.refund_locktime,
+        offered_contract.fee_rate_per_vb,
+        0,
+        offered_contract.cet_locktime,
+        offered_contract.fund_output_serial_id,
+        offered_contract.anchor_outputs,
+        offered_contract.fee_split,
+    )
+    .context(&offered_contract.id, "creating DLC transactions")?;
+
+    cancellation.check()?;
+    let adaptor_info =
+        offered_contract.contract_info[0].generate_adaptor_info(total_collateral, 0)?;
+
+    Ok(UnsignedAcceptedContract {
+        offered_contract: offered_contract.clone(),
+        accept_params: accept_params.clone(),
+        funding_inputs,
+        adaptor_infos: vec![adaptor_info],
+        dlc_transactions,
+    })
+}
+
+/// Creates the [`ContractSigningRequest`] for the signatures of the party
+/// owning the given funding public key over the transactions of a contract.
+/// Only contracts with a single [`ContractInfo`] are supported.
+pub fn get_contract_signing_request(
+    secp: &Secp256k1<All>,
+    offered_contract: &OfferedContract,
+    fund_pubkey: &PublicKey,
+    adaptor_infos: &[AdaptorInfo],
+    dlc_transactions: &DlcTransactions,
+) -> Result<ContractSigningRequest, Error> {
+    check_external_signing_support(offered_contract)?;
+
+    let adaptor_points = offered_contract.contract_info[0]
+        .get_adaptor_points(secp, &adaptor_infos[0])?
+        .into_iter()
+        .map(|(cet_index, adaptor_point)| CetAdaptorPoint {
+            cet_index,
+            adaptor_point,
+        })
+        .collect();
+
+    Ok(ContractSigningRequest {
+        contract_id: offered_contract.id,
+        keys_id: offered_contract.keys_id,
+        fund_pubkey: *fund_pubkey,
+        fund: dlc_transactions.fund.clone(),
+        funding_script_pubkey: dlc_transactions.funding_script_pubkey.clone(),
+        fund_output_value: dlc_transactions.get_fund_output().value,
+        cets: dlc_transactions.cets.clone(),
+        adaptor_points,
+        refund: dlc_transactions.refund.clone(),
+    })
+}
+
+fn check_external_signing_support(offered_contract: &OfferedContract) -> Result<(), Error> {
+    if offered_contract.contract_info.len() != 1 {
+        return Err(Error::InvalidParameters(
+            "External signing is only supported for contracts with a single contract info"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that the given contract is funded with a P2WSH output, as contracts
+/// funded with a taproot output use Schnorr signatures and are handled by the
+/// [`crate::taproot_updater`] functions.
+pub(crate) fn check_not_taproot(offered_contract: &OfferedContract) -> Result<(), Error> {
+    if offered_contract.taproot {
+        return Err(Error::InvalidParameters(
+            "Not supported for contracts funded with a taproot output".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn get_accept_party_approximate_fee(
+    offered_contract: &OfferedContract,
+) -> Result<u64, Error> {
+    crate::utils::get_approximate_fee(
+        offered_contract.fee_rate_per_vb,
+        offered_contract.fee_split,
+        false,
+        offered_contract.offer_params.collateral,
+        offered_contract.total_collateral - offered_contract.offer_params.collateral,
+    )
+}
+
+/// Checks that the accept party provided funding inputs, unless the contract is
+/// single funded.
+pub(crate) fn check_accept_funding_inputs(
+    offered_contract: &OfferedContract,
+    funding_inputs: &[FundingInput],
+) -> Result<(), Error> {
+    if funding_inputs.is_empty() && !offered_contract.is_single_funded() {
+        return Err(Error::InvalidParameters(
+            "Accept party did not provide any funding input".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Creates the [`AcceptedContract`] and [`AcceptDlc`] message for a contract
+/// prepared with [`prepare_accept_contract`] using signatures produced by an
+/// external signer, after verifying them.
+pub fn accept_contract_with_signatures(
+    secp: &Secp256k1<All>,
+    unsigned_contract: &UnsignedAcceptedContract,
+    signatures: &ContractSignatures,
+) -> Result<(AcceptedContract, AcceptDlc), Error> {
+    let request = get_contract_signing_request(
+        secp,
+        &unsigned_contract.offered_contract,
+        &unsigned_contract.accept_params.fund_pubkey,
+        &unsigned_contract.adaptor_infos,
+        &unsigned_contract.dlc_transactions,
+    )?;
+    request.verify(secp, signatures)?;
+
+    let UnsignedAcceptedContract {
+        offered_contract,
+        accept_params,
+        funding_inputs,
+        adaptor_infos,
+        dlc_transactions,
+    } = unsigned_contract.clone();
+
+    let accepted_contract = AcceptedContract {
+        offered_contract,
+        adaptor_infos,
+        // Drop own adaptor signatures as no point keeping them.
+        adaptor_signatures: None,
+        accept_params,
+        funding_inputs,
+        dlc_transactions,
+        accept_refund_signature: Some(signatures.refund_signature),
+        #[cfg(feature = "taproot")]
+        accept_taproot_signatures: None,
+    };
+
+    let accept_msg =
+        accepted_contract.get_accept_contract_msg(&signatures.cet_adaptor_signatures)?;
+
+    Ok((accepted_contract, accept_msg))
+}
+
 /// Verifies the information of the accepting party [`Accept` message](dlc_messages::AcceptDlc),
 /// creates a [`SignedContract`], and generates the offering party CET adaptor signatures.
 pub fn verify_accepted_and_sign_contract<W: Deref, X: ContractSigner, SP: Deref>(
@@ -235,11 +498,109 @@ pub fn verify_accepted_and_sign_contract<W: Deref, X: ContractSigner, SP: Deref>
     accept_msg: &AcceptDlc,
     wallet: &W,
     signer_provider: &SP,
+    cancellation: &CancellationToken,
 ) -> Result<(SignedContract, SignDlc), Error>
 where
     W::Target: Wallet,
     SP::Target: ContractSignerProvider<Signer = X>,
 {
+    let (accept_params, cet_adaptor_signatures, dlc_transactions) =
+        get_accept_params_and_transactions(offered_contract, accept_msg)?;
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+
+    let signer = signer_provider.derive_contract_signer(offered_contract.keys_id)?;
+    let (signed_contract, adaptor_sigs) = verify_accepted_and_sign_contract_internal(
+        secp,
+        offered_contract,
+        &accept_params,
+        &accept_msg.funding_inputs,
+        &accept_msg.refund_signature,
+        &cet_adaptor_signatures,
+        fund_output_value,
+        wallet,
+        &signer,
+        None,
+        None,
+        &dlc_transactions,
+        None,
+        cancellation,
+    )?;
+
+    let signed_msg: SignDlc = signed_contract.get_sign_dlc(adaptor_sigs)?;
+
+    Ok((signed_contract, signed_msg))
+}
+
+/// Verifies the information of the accepting party [`Accept` message](dlc_messages::AcceptDlc)
+/// and creates an [`AcceptedContract`] without producing the offering party
+/// signatures. The signatures can then be created by an external signer using
+/// the request returned by [`get_contract_signing_request`], and the contract
+/// completed with [`sign_contract_with_signatures`].
+pub fn verify_accepted_contract(
+    secp: &Secp256k1<All>,
+    offered_contract: &OfferedContract,
+    accept_msg: &AcceptDlc,
+    cancellation: &CancellationToken,
+) -> Result<AcceptedContract, Error> {
+    let (accept_params, cet_adaptor_signatures, dlc_transactions) =
+        get_accept_params_and_transactions(offered_contract, accept_msg)?;
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+
+    verify_accepted_contract_internal(
+        secp,
+        offered_contract,
+        &accept_params,
+        &accept_msg.funding_inputs,
+        &accept_msg.refund_signature,
+        &cet_adaptor_signatures,
+        fund_output_value,
+        None,
+        None,
+        &dlc_transactions,
+        cancellation,
+    )
+}
+
+/// Creates the [`SignedContract`] and [`SignDlc`] message for a contract
+/// verified with [`verify_accepted_contract`] using signatures produced by an
+/// external signer, after verifying them. The funding inputs of the offering
+/// party are signed using the given wallet.
+pub fn sign_contract_with_signatures<W: Deref>(
+    secp: &Secp256k1<All>,
+    accepted_contract: &AcceptedContract,
+    signatures: &ContractSignatures,
+    wallet: &W,
+) -> Result<(SignedContract, SignDlc), Error>
+where
+    W::Target: Wallet,
+{
+    let request = get_contract_signing_request(
+        secp,
+        &accepted_contract.offered_contract,
+        &accepted_contract.offered_contract.offer_params.fund_pubkey,
+        &accepted_contract.adaptor_infos,
+        &accepted_contract.dlc_transactions,
+    )?;
+    request.verify(secp, signatures)?;
+
+    let signed_contract = sign_accepted_contract_internal(
+        accepted_contract.clone(),
+        Some(signatures.refund_signature),
+        wallet,
+        None,
+    )?;
+    let signed_msg = signed_contract.get_sign_dlc(signatures.cet_adaptor_signatures.clone())?;
+
+    Ok((signed_contract, signed_msg))
+}
+
+fn get_accept_params_and_transactions(
+    offered_contract: &OfferedContract,
+    accept_msg: &AcceptDlc,
+) -> Result<(PartyParams, Vec<EcdsaAdaptorSignature>, DlcTransactions), Error> {
+    check_not_taproot(offered_contract)?;
+    check_accepted_fee_split(offered_contract.fee_split, accept_msg.fee_split)?;
+    check_accept_funding_inputs(offered_contract, &accept_msg.funding_inputs)?;
     let (tx_input_infos, input_amount) = get_tx_input_infos(&accept_msg.funding_inputs)?;
 
     let accept_params = PartyParams {
@@ -271,37 +632,38 @@ where
         0,
         offered_contract.cet_locktime,
         offered_contract.fund_output_serial_id,
-    )?;
-    let fund_output_value = dlc_transactions.get_fund_output().value;
-
-    let signer = signer_provider.derive_contract_signer(offered_contract.keys_id)?;
-    let (signed_contract, adaptor_sigs) = verify_accepted_and_sign_contract_internal(
-        secp,
-        offered_contract,
-        &accept_params,
-        &accept_msg.funding_inputs,
-        &accept_msg.refund_signature,
-        &cet_adaptor_signatures,
-        fund_output_value,
-        wallet,
-        &signer,
-        None,
-        None,
-        &dlc_transactions,
-        None,
-    )?;
+        offered_contract.anchor_outputs,
+        offered_contract.fee_split,
+    )
+    .context(&offered_contract.id, "creating DLC transactions")?;
 
-    let signed_msg: SignDlc = signed_contract.get_sign_dlc(adaptor_sigs);
+    Ok((accept_params, cet_adaptor_signatures, dlc_transactions))
+}
 
-    Ok((signed_contract, signed_msg))
+/// Returns the number of inputs of the given fund transaction that precede the
+/// funding inputs of the parties, such as the fund output of an amended
+/// contract.
+fn get_funding_input_offset(
+    fund_psbt: &PartiallySignedTransaction,
+    all_funding_inputs: &[&FundingInput],
+) -> Result<usize, Error> {
+    fund_psbt
+        .inputs
+        .len()
+        .checked_sub(all_funding_inputs.len())
+        .ok_or_else(|| {
+            Error::InvalidState("Fund transaction is missing funding inputs".to_string())
+        })
 }
 
 fn populate_psbt(
     psbt: &mut PartiallySignedTransaction,
     all_funding_inputs: &[&FundingInput],
+    input_offset: usize,
 ) -> Result<(), Error> {
     // add witness utxo to fund_psbt for all inputs
-    for (input_index, x) in all_funding_inputs.iter().enumerate() {
+    for (i, x) in all_funding_inputs.iter().enumerate() {
+        let input_index = i + input_offset;
         let tx = Transaction::consensus_decode(&mut x.prev_tx.as_slice()).map_err(|_| {
             Error::InvalidParameters(
                 "Could not decode funding input previous tx parameter".to_string(),
@@ -314,6 +676,53 @@ fn populate_psbt(
 
         psbt.inputs[input_index].witness_utxo = Some(tx_out.clone());
         psbt.inputs[input_index].redeem_script = Some(x.redeem_script.clone());
+        // Required by signers that do not trust the amount of witness UTXOs.
+        psbt.inputs[input_index].non_witness_utxo = Some(tx);
+    }
+
+    Ok(())
+}
+
+/// Has the wallet sign the fund transaction inputs at the given indexes in a
+/// single round trip, checking that the PSBT was not otherwise modified.
+fn sign_funding_inputs<W: Deref>(
+    wallet: &W,
+    fund_psbt: &mut PartiallySignedTransaction,
+    input_indexes: &[usize],
+) -> Result<(), Error>
+where
+    W::Target: Wallet,
+{
+    // The accept party of a single funded contract has no input to sign.
+    if input_indexes.is_empty() {
+        return Ok(());
+    }
+
+    let original = fund_psbt.clone();
+    wallet.sign_psbt(fund_psbt, input_indexes)?;
+
+    if fund_psbt.unsigned_tx != original.unsigned_tx
+        || fund_psbt.inputs.len() != original.inputs.len()
+    {
+        return Err(Error::WalletError(
+            "The wallet modified the fund transaction".into(),
+        ));
+    }
+
+    for (i, (input, original)) in fund_psbt
+        .inputs
+        .iter()
+        .zip(original.inputs.iter())
+        .enumerate()
+    {
+        if !input_indexes.contains(&i)
+            && (input.final_script_witness != original.final_script_witness
+                || input.final_script_sig != original.final_script_sig)
+        {
+            return Err(Error::WalletError(
+                format!("The wallet modified the fund transaction input {}", i).into(),
+            ));
+        }
     }
 
     Ok(())
@@ -333,53 +742,131 @@ pub(crate) fn verify_accepted_and_sign_contract_internal<W: Deref, X: ContractSi
     counter_adaptor_pk: Option<PublicKey>,
     dlc_transactions: &DlcTransactions,
     channel_id: Option<ChannelId>,
+    cancellation: &CancellationToken,
 ) -> Result<(SignedContract, Vec<EcdsaAdaptorSignature>), Error>
 where
     W::Target: Wallet,
 {
-    let DlcTransactions {
-        fund,
-        cets,
-        refund,
-        funding_script_pubkey,
-    } = dlc_transactions;
-
-    let mut fund_psbt = PartiallySignedTransaction::from_unsigned_tx(fund.clone())
-        .map_err(|_| Error::InvalidState("Tried to create PSBT from signed tx".to_string()))?;
-    let mut cets = cets.clone();
-
-    let input_script_pubkey = input_script_pubkey.unwrap_or_else(|| funding_script_pubkey);
-    let counter_adaptor_pk = counter_adaptor_pk.unwrap_or(accept_params.fund_pubkey);
-
-    dlc::verify_tx_input_sig(
+    enter_span!(
+        "verify_accepted_and_sign_contract",
+        contract_id = %offered_contract.id.to_lower_hex_string(),
+        channel_id = ?channel_id.map(|id| id.to_lower_hex_string())
+    );
+    let accepted_contract = verify_accepted_contract_internal(
         secp,
+        offered_contract,
+        accept_params,
+        funding_inputs_info,
         refund_signature,
-        refund,
-        0,
-        input_script_pubkey,
+        cet_adaptor_signatures,
         input_value,
-        &counter_adaptor_pk,
+        input_script_pubkey,
+        counter_adaptor_pk,
+        dlc_transactions,
+        cancellation,
     )?;
 
-    let (adaptor_info, mut adaptor_index) = offered_contract.contract_info[0]
-        .verify_and_get_adaptor_info(
-            secp,
-            offered_contract.total_collateral,
-            &counter_adaptor_pk,
-            input_script_pubkey,
-            input_value,
-            &cets,
-            cet_adaptor_signatures,
-            0,
-        )?;
+    let input_script_pubkey = input_script_pubkey
+        .unwrap_or_else(|| &accepted_contract.dlc_transactions.funding_script_pubkey);
 
-    let mut adaptor_infos = vec![adaptor_info];
+    let mut own_signatures: Vec<EcdsaAdaptorSignature> = Vec::new();
+
+    for (contract_info, adaptor_info) in offered_contract
+        .contract_info
+        .iter()
+        .zip(accepted_contract.adaptor_infos.iter())
+    {
+        cancellation.check()?;
+        let sigs = contract_info.get_adaptor_signatures(
+            secp,
+            adaptor_info,
+            &signer,
+            input_script_pubkey,
+            input_value,
+            &accepted_contract.dlc_transactions.cets,
+        )?;
+        own_signatures.extend(sigs);
+    }
+
+    cancellation.check()?;
+
+    let offer_refund_signature = dlc::util::get_raw_sig_for_tx_input(
+        secp,
+        &accepted_contract.dlc_transactions.refund,
+        0,
+        input_script_pubkey,
+        input_value,
+        &signer.get_secret_key()?,
+    )
+    .context(&offered_contract.id, "signing refund transaction")?;
+
+    let signed_contract = sign_accepted_contract_internal(
+        accepted_contract,
+        Some(offer_refund_signature),
+        wallet,
+        channel_id,
+    )?;
+
+    Ok((signed_contract, own_signatures))
+}
+
+fn verify_accepted_contract_internal(
+    secp: &Secp256k1<All>,
+    offered_contract: &OfferedContract,
+    accept_params: &PartyParams,
+    funding_inputs_info: &[FundingInput],
+    refund_signature: &Signature,
+    cet_adaptor_signatures: &[EcdsaAdaptorSignature],
+    input_value: u64,
+    input_script_pubkey: Option<&Script>,
+    counter_adaptor_pk: Option<PublicKey>,
+    dlc_transactions: &DlcTransactions,
+    cancellation: &CancellationToken,
+) -> Result<AcceptedContract, Error> {
+    let DlcTransactions {
+        fund,
+        cets,
+        refund,
+        funding_script_pubkey,
+    } = dlc_transactions;
+
+    let mut cets = cets.clone();
+
+    let input_script_pubkey = input_script_pubkey.unwrap_or_else(|| funding_script_pubkey);
+    let counter_adaptor_pk = counter_adaptor_pk.unwrap_or(accept_params.fund_pubkey);
+
+    dlc::verify_tx_input_sig(
+        secp,
+        refund_signature,
+        refund,
+        0,
+        input_script_pubkey,
+        input_value,
+        &counter_adaptor_pk,
+    )
+    .context(&offered_contract.id, "verifying refund signature")?;
+
+    cancellation.check()?;
+    let (adaptor_info, mut adaptor_index) = offered_contract.contract_info[0]
+        .verify_and_get_adaptor_info(
+            secp,
+            offered_contract.total_collateral,
+            &counter_adaptor_pk,
+            input_script_pubkey,
+            input_value,
+            &cets,
+            cet_adaptor_signatures,
+            0,
+        )?;
+
+    let mut adaptor_infos = vec![adaptor_info];
 
     let cet_input = cets[0].input[0].clone();
 
     let total_collateral = offered_contract.offer_params.collateral + accept_params.collateral;
 
     for contract_info in offered_contract.contract_info.iter().skip(1) {
+        cancellation.check()?;
         let payouts = contract_info.get_payouts(total_collateral)?;
 
         let tmp_cets = dlc::create_cets(
@@ -410,41 +897,60 @@ where
         adaptor_infos.push(adaptor_info);
     }
 
-    let mut own_signatures: Vec<EcdsaAdaptorSignature> = Vec::new();
+    let dlc_transactions = DlcTransactions {
+        fund: fund.clone(),
+        cets,
+        refund: refund.clone(),
+        funding_script_pubkey: funding_script_pubkey.clone(),
+    };
 
-    for (contract_info, adaptor_info) in offered_contract
-        .contract_info
-        .iter()
-        .zip(adaptor_infos.iter())
-    {
-        let sigs = contract_info.get_adaptor_signatures(
-            secp,
-            adaptor_info,
-            &signer,
-            input_script_pubkey,
-            input_value,
-            &cets,
-        )?;
-        own_signatures.extend(sigs);
-    }
+    Ok(AcceptedContract {
+        offered_contract: offered_contract.clone(),
+        accept_params: accept_params.clone(),
+        funding_inputs: funding_inputs_info.to_vec(),
+        adaptor_infos,
+        adaptor_signatures: Some(cet_adaptor_signatures.to_vec()),
+        accept_refund_signature: Some(*refund_signature),
+        dlc_transactions,
+        #[cfg(feature = "taproot")]
+        accept_taproot_signatures: None,
+    })
+}
+
+/// Signs the funding inputs of the offering party and creates the
+/// [`SignedContract`] using the given refund signature.
+pub(crate) fn sign_accepted_contract_internal<W: Deref>(
+    accepted_contract: AcceptedContract,
+    offer_refund_signature: Option<Signature>,
+    wallet: &W,
+    channel_id: Option<ChannelId>,
+) -> Result<SignedContract, Error>
+where
+    W::Target: Wallet,
+{
+    let offered_contract = &accepted_contract.offered_contract;
+    let mut fund_psbt = PartiallySignedTransaction::from_unsigned_tx(
+        accepted_contract.dlc_transactions.fund.clone(),
+    )
+    .map_err(|_| Error::InvalidState("Tried to create PSBT from signed tx".to_string()))?;
 
     // get all funding inputs
     let mut all_funding_inputs = offered_contract
         .funding_inputs
         .iter()
-        .chain(funding_inputs_info.iter())
+        .chain(accepted_contract.funding_inputs.iter())
         .collect::<Vec<_>>();
     // sort by serial id
     all_funding_inputs.sort_by_key(|x| x.input_serial_id);
 
-    populate_psbt(&mut fund_psbt, &all_funding_inputs)?;
+    let input_offset = get_funding_input_offset(&fund_psbt, &all_funding_inputs)?;
+    populate_psbt(&mut fund_psbt, &all_funding_inputs, input_offset)?;
 
-    // Vec<Witness>
-    let witnesses: Vec<Witness> = offered_contract
+    let input_indexes = offered_contract
         .funding_inputs
         .iter()
         .map(|x| {
-            let input_index = all_funding_inputs
+            Ok(all_funding_inputs
                 .iter()
                 .position(|y| y == &x)
                 .ok_or_else(|| {
@@ -452,18 +958,22 @@ where
                         "Could not find input for serial id {}",
                         x.input_serial_id
                     ))
-                })?;
+                })?
+                + input_offset)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
 
-            wallet.sign_psbt_input(&mut fund_psbt, input_index)?;
+    sign_funding_inputs(wallet, &mut fund_psbt, &input_indexes)?;
 
-            let witness = fund_psbt.inputs[input_index]
+    let witnesses = input_indexes
+        .iter()
+        .map(|input_index| {
+            fund_psbt.inputs[*input_index]
                 .final_script_witness
                 .clone()
                 .ok_or(Error::InvalidParameters(
                     "No witness from signing psbt input".to_string(),
-                ))?;
-
-            Ok(witness)
+                ))
         })
         .collect::<Result<Vec<_>, Error>>()?;
 
@@ -480,41 +990,15 @@ where
         })
         .collect::<Result<Vec<_>, Error>>()?;
 
-    let offer_refund_signature = dlc::util::get_raw_sig_for_tx_input(
-        secp,
-        refund,
-        0,
-        input_script_pubkey,
-        input_value,
-        &signer.get_secret_key()?,
-    )?;
-
-    let dlc_transactions = DlcTransactions {
-        fund: fund.clone(),
-        cets,
-        refund: refund.clone(),
-        funding_script_pubkey: funding_script_pubkey.clone(),
-    };
-
-    let accepted_contract = AcceptedContract {
-        offered_contract: offered_contract.clone(),
-        accept_params: accept_params.clone(),
-        funding_inputs: funding_inputs_info.to_vec(),
-        adaptor_infos,
-        adaptor_signatures: Some(cet_adaptor_signatures.to_vec()),
-        accept_refund_signature: *refund_signature,
-        dlc_transactions,
-    };
-
-    let signed_contract = SignedContract {
+    Ok(SignedContract {
         accepted_contract,
         adaptor_signatures: None,
         offer_refund_signature,
         funding_signatures: FundingSignatures { funding_signatures },
         channel_id,
-    };
-
-    Ok((signed_contract, own_signatures))
+        #[cfg(feature = "taproot")]
+        offer_taproot_signatures: None,
+    })
 }
 
 /// Verifies the information from the offer party [`Sign` message](dlc_messages::SignDlc),
@@ -525,6 +1009,7 @@ pub fn verify_signed_contract<W: Deref>(
     accepted_contract: &AcceptedContract,
     sign_msg: &SignDlc,
     wallet: &W,
+    cancellation: &CancellationToken,
 ) -> Result<(SignedContract, Transaction), Error>
 where
     W::Target: Wallet,
@@ -541,6 +1026,7 @@ where
         None,
         wallet,
         None,
+        cancellation,
     )
 }
 
@@ -555,45 +1041,59 @@ pub(crate) fn verify_signed_contract_internal<W: Deref>(
     counter_adaptor_pk: Option<PublicKey>,
     wallet: &W,
     channel_id: Option<ChannelId>,
+    cancellation: &CancellationToken,
 ) -> Result<(SignedContract, Transaction), Error>
 where
     W::Target: Wallet,
 {
-    let offered_contract = &accepted_contract.offered_contract;
+    enter_span!(
+        "verify_signed_contract",
+        contract_id = %accepted_contract.get_contract_id_string(),
+        channel_id = ?channel_id.map(|id| id.to_lower_hex_string())
+    );
     let input_script_pubkey = input_script_pubkey
         .unwrap_or_else(|| &accepted_contract.dlc_transactions.funding_script_pubkey);
     let counter_adaptor_pk =
         counter_adaptor_pk.unwrap_or(accepted_contract.offered_contract.offer_params.fund_pubkey);
 
-    dlc::verify_tx_input_sig(
+    verify_offer_signatures(
         secp,
+        accepted_contract,
         refund_signature,
-        &accepted_contract.dlc_transactions.refund,
-        0,
-        input_script_pubkey,
+        cet_adaptor_signatures,
         input_value,
+        input_script_pubkey,
         &counter_adaptor_pk,
+        cancellation,
     )?;
 
-    let mut adaptor_sig_start = 0;
+    let fund_tx = sign_fund_transaction(accepted_contract, funding_signatures, wallet)?;
 
-    for (adaptor_info, contract_info) in accepted_contract
-        .adaptor_infos
-        .iter()
-        .zip(offered_contract.contract_info.iter())
-    {
-        adaptor_sig_start = contract_info.verify_adaptor_info(
-            secp,
-            &counter_adaptor_pk,
-            input_script_pubkey,
-            input_value,
-            &accepted_contract.dlc_transactions.cets,
-            cet_adaptor_signatures,
-            adaptor_sig_start,
-            adaptor_info,
-        )?;
-    }
+    let signed_contract = SignedContract {
+        accepted_contract: accepted_contract.clone(),
+        adaptor_signatures: Some(cet_adaptor_signatures.to_vec()),
+        offer_refund_signature: Some(*refund_signature),
+        funding_signatures: funding_signatures.clone(),
+        channel_id,
+        #[cfg(feature = "taproot")]
+        offer_taproot_signatures: None,
+    };
+
+    Ok((signed_contract, fund_tx))
+}
 
+/// Returns the fund transaction of the given accepted contract with the inputs
+/// of the offer party signed using the given signatures and the inputs of the
+/// accept party signed using the wallet.
+pub(crate) fn sign_fund_transaction<W: Deref>(
+    accepted_contract: &AcceptedContract,
+    funding_signatures: &FundingSignatures,
+    wallet: &W,
+) -> Result<Transaction, Error>
+where
+    W::Target: Wallet,
+{
+    let offered_contract = &accepted_contract.offered_contract;
     let fund_tx = &accepted_contract.dlc_transactions.fund;
     let mut fund_psbt = PartiallySignedTransaction::from_unsigned_tx(fund_tx.clone())
         .map_err(|_| Error::InvalidState("Tried to create PSBT from signed tx".to_string()))?;
@@ -607,7 +1107,8 @@ where
     // sort by serial id
     all_funding_inputs.sort_by_key(|x| x.input_serial_id);
 
-    populate_psbt(&mut fund_psbt, &all_funding_inputs)?;
+    let input_offset = get_funding_input_offset(&fund_psbt, &all_funding_inputs)?;
+    populate_psbt(&mut fund_psbt, &all_funding_inputs, input_offset)?;
 
     for (funding_input, funding_signatures) in offered_contract
         .funding_inputs
@@ -622,7 +1123,8 @@ where
                     "Could not find input for serial id {}",
                     funding_input.input_serial_id
                 ))
-            })?;
+            })?
+            + input_offset;
 
         fund_psbt.inputs[input_index].final_script_witness = Some(Witness::from_slice(
             &funding_signatures
@@ -633,65 +1135,306 @@ where
         ));
     }
 
-    for funding_input in &accepted_contract.funding_inputs {
-        let input_index = all_funding_inputs
-            .iter()
-            .position(|x| x == &funding_input)
-            .ok_or_else(|| {
-                Error::InvalidState(format!(
-                    "Could not find input for serial id {}",
-                    funding_input.input_serial_id
-                ))
-            })?;
-
-        wallet.sign_psbt_input(&mut fund_psbt, input_index)?;
-    }
+    let input_indexes = accepted_contract
+        .funding_inputs
+        .iter()
+        .map(|funding_input| {
+            Ok(all_funding_inputs
+                .iter()
+                .position(|x| x == &funding_input)
+                .ok_or_else(|| {
+                    Error::InvalidState(format!(
+                        "Could not find input for serial id {}",
+                        funding_input.input_serial_id
+                    ))
+                })?
+                + input_offset)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
 
-    let signed_contract = SignedContract {
-        accepted_contract: accepted_contract.clone(),
-        adaptor_signatures: Some(cet_adaptor_signatures.to_vec()),
-        offer_refund_signature: *refund_signature,
-        funding_signatures: funding_signatures.clone(),
-        channel_id,
-    };
+    sign_funding_inputs(wallet, &mut fund_psbt, &input_indexes)?;
 
-    Ok((signed_contract, fund_psbt.extract_tx()))
+    Ok(fund_psbt.extract_tx())
 }
 
-/// Signs and return the CET that can be used to close the given contract.
-pub fn get_signed_cet<C: Signing, S: Deref>(
-    secp: &Secp256k1<C>,
-    contract: &SignedContract,
-    contract_info: &ContractInfo,
-    adaptor_info: &AdaptorInfo,
-    attestations: &[(usize, OracleAttestation)],
-    signer: S,
+/// Returns the fully signed fund transaction of the given contract, so that it
+/// can be broadcast again. Only the accept party has the signatures of all the
+/// funding inputs, and the fund transactions of amended or netted contracts
+/// cannot be rebuilt as they spend the fund outputs of previous contracts.
+pub(crate) fn get_signed_fund_transaction<W: Deref>(
+    signed_contract: &SignedContract,
+    wallet: &W,
 ) -> Result<Transaction, Error>
 where
-    S::Target: ContractSigner,
+    W::Target: Wallet,
 {
-    let (range_info, sigs) =
-        crate::utils::get_range_info_and_oracle_sigs(contract_info, adaptor_info, attestations)?;
-    let mut cet = contract.accepted_contract.dlc_transactions.cets[range_info.cet_index].clone();
-    let offered_contract = &contract.accepted_contract.offered_contract;
-
-    let (adaptor_sigs, other_pubkey) = if offered_contract.is_offer_party {
-        (
-            contract
-                .accepted_contract
-                .adaptor_signatures
-                .as_ref()
-                .unwrap(),
-            &contract.accepted_contract.accept_params.fund_pubkey,
-        )
-    } else {
-        (
-            contract.adaptor_signatures.as_ref().unwrap(),
-            &offered_contract.offer_params.fund_pubkey,
-        )
-    };
-
-    let funding_sk = signer.get_secret_key()?;
+    let accepted_contract = &signed_contract.accepted_contract;
+    let offered_contract = &accepted_contract.offered_contract;
+    let nb_funding_inputs =
+        offered_contract.funding_inputs.len() + accepted_contract.funding_inputs.len();
+    if offered_contract.is_offer_party
+        || signed_contract.channel_id.is_some()
+        || accepted_contract.dlc_transactions.fund.input.len() != nb_funding_inputs
+    {
+        return Err(Error::InvalidState(
+            "Cannot rebuild the signed fund transaction of the contract".to_string(),
+        ));
+    }
+
+    sign_fund_transaction(
+        accepted_contract,
+        &signed_contract.funding_signatures,
+        wallet,
+    )
+}
+
+/// Verifies the refund signature and the CET adaptor signatures of the offer
+/// party for the given accepted contract.
+fn verify_offer_signatures(
+    secp: &Secp256k1<All>,
+    accepted_contract: &AcceptedContract,
+    refund_signature: &Signature,
+    cet_adaptor_signatures: &[EcdsaAdaptorSignature],
+    input_value: u64,
+    input_script_pubkey: &Script,
+    counter_adaptor_pk: &PublicKey,
+    cancellation: &CancellationToken,
+) -> Result<(), Error> {
+    dlc::verify_tx_input_sig(
+        secp,
+        refund_signature,
+        &accepted_contract.dlc_transactions.refund,
+        0,
+        input_script_pubkey,
+        input_value,
+        counter_adaptor_pk,
+    )
+    .context(
+        &accepted_contract.get_contract_id(),
+        "verifying refund signature",
+    )?;
+
+    let mut adaptor_sig_start = 0;
+    let mut tracker = ProgressTracker::new(
+        cancellation,
+        ProgressStage::VerifyingAdaptorSignatures,
+        cet_adaptor_signatures.len(),
+    );
+
+    for (adaptor_info, contract_info) in accepted_contract
+        .adaptor_infos
+        .iter()
+        .zip(accepted_contract.offered_contract.contract_info.iter())
+    {
+        cancellation.check()?;
+        adaptor_sig_start = progress::verify_adaptor_info(
+            &mut tracker,
+            contract_info,
+            secp,
+            counter_adaptor_pk,
+            input_script_pubkey,
+            input_value,
+            &accepted_contract.dlc_transactions.cets,
+            cet_adaptor_signatures,
+            adaptor_sig_start,
+            adaptor_info,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Verifies a complete set of contract negotiation messages on behalf of a
+/// third party (e.g. an auditor) that does not take part in the contract, and
+/// returns the resulting [`SignedContract`]. The refund and CET adaptor
+/// signatures of both parties are verified, as well as the funding signatures
+/// of the offer party for native P2WPKH inputs.
+pub fn verify_contract_messages(
+    secp: &Secp256k1<All>,
+    offer_msg: &OfferDlc,
+    accept_msg: &AcceptDlc,
+    sign_msg: &SignDlc,
+    counter_party: PublicKey,
+) -> Result<SignedContract, Error> {
+    let offered_contract =
+        OfferedContract::try_from_offer_dlc(offer_msg, counter_party, [0u8; 32])?;
+    offered_contract.validate()?;
+
+    if accept_msg.temporary_contract_id != offered_contract.id {
+        return Err(Error::InvalidParameters(
+            "Accept message does not match the offer".to_string(),
+        ));
+    }
+
+    let cancellation = CancellationToken::new();
+    let accepted_contract =
+        verify_accepted_contract(secp, &offered_contract, accept_msg, &cancellation)?;
+
+    if sign_msg.contract_id != accepted_contract.get_contract_id() {
+        return Err(Error::InvalidParameters(
+            "Sign message does not match the contract".to_string(),
+        ));
+    }
+
+    let offer_cet_adaptor_signatures: Vec<_> = (&sign_msg.cet_adaptor_signatures).into();
+    verify_offer_signatures(
+        secp,
+        &accepted_contract,
+        &sign_msg.refund_signature,
+        &offer_cet_adaptor_signatures,
+        accepted_contract.dlc_transactions.get_fund_output().value,
+        &accepted_contract.dlc_transactions.funding_script_pubkey,
+        &accepted_contract.offered_contract.offer_params.fund_pubkey,
+        &cancellation,
+    )?;
+
+    verify_offer_funding_signatures(secp, &accepted_contract, &sign_msg.funding_signatures)?;
+
+    Ok(SignedContract {
+        accepted_contract,
+        adaptor_signatures: Some(offer_cet_adaptor_signatures),
+        offer_refund_signature: Some(sign_msg.refund_signature),
+        funding_signatures: sign_msg.funding_signatures.clone(),
+        channel_id: None,
+        #[cfg(feature = "taproot")]
+        offer_taproot_signatures: None,
+    })
+}
+
+fn verify_offer_funding_signatures(
+    secp: &Secp256k1<All>,
+    accepted_contract: &AcceptedContract,
+    funding_signatures: &FundingSignatures,
+) -> Result<(), Error> {
+    let offered_contract = &accepted_contract.offered_contract;
+    if offered_contract.funding_inputs.len() != funding_signatures.funding_signatures.len() {
+        return Err(Error::InvalidParameters(
+            "Invalid number of funding signatures".to_string(),
+        ));
+    }
+
+    let mut all_funding_inputs = offered_contract
+        .funding_inputs
+        .iter()
+        .chain(accepted_contract.funding_inputs.iter())
+        .collect::<Vec<_>>();
+    all_funding_inputs.sort_by_key(|x| x.input_serial_id);
+
+    for (funding_input, funding_signature) in offered_contract
+        .funding_inputs
+        .iter()
+        .zip(funding_signatures.funding_signatures.iter())
+    {
+        let input_index = all_funding_inputs
+            .iter()
+            .position(|x| x == &funding_input)
+            .ok_or_else(|| {
+                Error::InvalidState(format!(
+                    "Could not find input for serial id {}",
+                    funding_input.input_serial_id
+                ))
+            })?;
+        let prev_tx = Transaction::consensus_decode(&mut funding_input.prev_tx.as_slice())
+            .map_err(|_| {
+                Error::InvalidParameters(
+                    "Could not decode funding input previous tx parameter".to_string(),
+                )
+            })?;
+        let prev_out = prev_tx
+            .output
+            .get(funding_input.prev_tx_vout as usize)
+            .ok_or_else(|| {
+                Error::InvalidParameters(format!(
+                    "Previous tx output not found at index {}",
+                    funding_input.prev_tx_vout
+                ))
+            })?;
+
+        // Only native P2WPKH inputs can be verified without a script interpreter.
+        if !prev_out.script_pubkey.is_v0_p2wpkh() {
+            continue;
+        }
+
+        let invalid_signature =
+            || Error::InvalidParameters("Invalid funding signature".to_string());
+        let (sig, pubkey) = match &funding_signature.witness_elements[..] {
+            [sig, pubkey] => (&sig.witness, &pubkey.witness),
+            _ => return Err(invalid_signature()),
+        };
+        let (sighash_type, der_sig) = sig.split_last().ok_or_else(invalid_signature)?;
+        if *sighash_type != EcdsaSighashType::All.to_u32() as u8 {
+            return Err(invalid_signature());
+        }
+        let signature = Signature::from_der(der_sig).map_err(|_| invalid_signature())?;
+        let pubkey = bitcoin::PublicKey::from_slice(pubkey).map_err(|_| invalid_signature())?;
+        let wpubkey_hash = pubkey.wpubkey_hash().ok_or_else(invalid_signature)?;
+        if ScriptBuf::new_v0_p2wpkh(&wpubkey_hash) != prev_out.script_pubkey {
+            return Err(invalid_signature());
+        }
+
+        dlc::verify_tx_input_sig(
+            secp,
+            &signature,
+            &accepted_contract.dlc_transactions.fund,
+            input_index,
+            &ScriptBuf::new_p2pkh(&pubkey.pubkey_hash()),
+            prev_out.value,
+            &pubkey.inner,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Signs and return the CET that can be used to close the given contract.
+pub fn get_signed_cet<C: Signing, S: Deref>(
+    secp: &Secp256k1<C>,
+    contract: &SignedContract,
+    contract_info: &ContractInfo,
+    adaptor_info: &AdaptorInfo,
+    attestations: &[(usize, OracleAttestation)],
+    signer: S,
+) -> Result<Transaction, Error>
+where
+    S::Target: ContractSigner,
+{
+    enter_span!(
+        "get_signed_cet",
+        contract_id = %contract.accepted_contract.get_contract_id_string()
+    );
+    #[cfg(feature = "taproot")]
+    if contract.accepted_contract.offered_contract.taproot {
+        return crate::taproot_updater::get_signed_cet(
+            &Secp256k1::new(),
+            contract,
+            contract_info,
+            adaptor_info,
+            attestations,
+            signer,
+        );
+    }
+    let (range_info, sigs) =
+        crate::utils::get_range_info_and_oracle_sigs(contract_info, adaptor_info, attestations)?;
+    let mut cet = contract.accepted_contract.dlc_transactions.cets[range_info.cet_index].clone();
+    let offered_contract = &contract.accepted_contract.offered_contract;
+
+    let (adaptor_sigs, other_pubkey) = if offered_contract.is_offer_party {
+        (
+            contract
+                .accepted_contract
+                .adaptor_signatures
+                .as_ref()
+                .unwrap(),
+            &contract.accepted_contract.accept_params.fund_pubkey,
+        )
+    } else {
+        (
+            contract.adaptor_signatures.as_ref().unwrap(),
+            &offered_contract.offer_params.fund_pubkey,
+        )
+    };
+
+    let funding_sk = signer.get_secret_key()?;
 
     dlc::sign_cet(
         secp,
@@ -725,17 +1468,21 @@ where
 {
     let accepted_contract = &contract.accepted_contract;
     let offered_contract = &accepted_contract.offered_contract;
+    #[cfg(feature = "taproot")]
+    if offered_contract.taproot {
+        return crate::taproot_updater::get_signed_refund(&Secp256k1::new(), contract, signer);
+    }
     let funding_script_pubkey = &accepted_contract.dlc_transactions.funding_script_pubkey;
     let fund_output_value = accepted_contract.dlc_transactions.get_fund_output().value;
     let (other_fund_pubkey, other_sig) = if offered_contract.is_offer_party {
         (
             &accepted_contract.accept_params.fund_pubkey,
-            &accepted_contract.accept_refund_signature,
+            accepted_contract.get_accept_refund_signature()?,
         )
     } else {
         (
             &offered_contract.offer_params.fund_pubkey,
-            &contract.offer_refund_signature,
+            contract.get_offer_refund_signature()?,
         )
     };
 
@@ -744,7 +1491,7 @@ where
     dlc::util::sign_multi_sig_input(
         secp,
         &mut refund,
-        other_sig,
+        &other_sig,
         other_fund_pubkey,
         &fund_priv_key,
         funding_script_pubkey,
@@ -754,40 +1501,1563 @@ where
     Ok(refund)
 }
 
-#[cfg(test)]
-mod tests {
-    use std::rc::Rc;
-
-    use mocks::dlc_manager::contract::offered_contract::OfferedContract;
-    use secp256k1_zkp::PublicKey;
+/// Returns a copy of the given contract in which the payout script of the
+/// offer party (if `offer_party` is set) or of the accept party is replaced
+/// with the given one, in the party parameters as well as in the CETs and
+/// refund transaction. The signatures are left untouched and must be updated
+/// using [`sign_payout_update`] and [`apply_payout_update`]. As the fees of the
+/// CETs and refund transaction were paid when funding the contract based on the
+/// size of the previous script, longer scripts are rejected.
+///
+/// The previous CETs and refund transaction are not revoked: they spend the
+/// same fund output and remain fully signed, so either party can still
+/// broadcast them. An update only protects the funds if it completes before
+/// these transactions can be broadcast.
+pub fn update_payout_script(
+    contract: &SignedContract,
+    offer_party: bool,
+    payout_spk: &Script,
+) -> Result<SignedContract, Error> {
+    check_not_taproot(&contract.accepted_contract.offered_contract)?;
+
+    let offer_spk = &contract
+        .accepted_contract
+        .offered_contract
+        .offer_params
+        .payout_script_pubkey;
+    let accept_spk = &contract
+        .accepted_contract
+        .accept_params
+        .payout_script_pubkey;
+    let (previous_spk, other_spk) = if offer_party {
+        (offer_spk, accept_spk)
+    } else {
+        (accept_spk, offer_spk)
+    };
 
-    #[test]
-    fn accept_contract_test() {
-        let offer_dlc =
-            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
-        let dummy_pubkey: PublicKey =
-            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443"
-                .parse()
-                .unwrap();
-        let offered_contract =
-            OfferedContract::try_from_offer_dlc(&offer_dlc, dummy_pubkey, [0; 32]).unwrap();
-        let blockchain = Rc::new(mocks::mock_blockchain::MockBlockchain::new());
-        let fee_rate: u64 = offered_contract.fee_rate_per_vb;
-        let utxo_value: u64 = offered_contract.total_collateral
-            - offered_contract.offer_params.collateral
-            + crate::utils::get_half_common_fee(fee_rate).unwrap();
-        let wallet = Rc::new(mocks::mock_wallet::MockWallet::new(
-            &blockchain,
-            &[utxo_value, 10000],
+    if previous_spk == other_spk || payout_spk == other_spk.as_script() {
+        return Err(Error::InvalidParameters(
+            "Payout scripts of both parties must be different".to_string(),
         ));
+    }
 
-        mocks::dlc_manager::contract_updater::accept_contract(
-            secp256k1_zkp::SECP256K1,
-            &offered_contract,
-            &wallet,
-            &wallet,
-            &blockchain,
+    if payout_spk.len() > previous_spk.len() {
+        return Err(Error::InvalidParameters(format!(
+            "Payout script of {} bytes is longer than the current one of {} bytes",
+            payout_spk.len(),
+            previous_spk.len()
+        )));
+    }
+
+    let mut updated = contract.clone();
+    let accepted_contract = &mut updated.accepted_contract;
+    let dlc_transactions = &mut accepted_contract.dlc_transactions;
+    for tx in dlc_transactions
+        .cets
+        .iter_mut()
+        .chain(std::iter::once(&mut dlc_transactions.refund))
+    {
+        for output in tx
+            .output
+            .iter_mut()
+            .filter(|o| &o.script_pubkey == previous_spk)
+        {
+            output.script_pubkey = payout_spk.to_owned();
+        }
+    }
+
+    if offer_party {
+        accepted_contract
+            .offered_contract
+            .offer_params
+            .payout_script_pubkey = payout_spk.to_owned();
+    } else {
+        accepted_contract.accept_params.payout_script_pubkey = payout_spk.to_owned();
+    }
+
+    Ok(updated)
+}
+
+/// Generates the adaptor signatures for the CETs and the signature for the
+/// refund transaction of the given contract, to be sent to the counter party
+/// after updating a payout script using [`update_payout_script`].
+pub fn sign_payout_update<X: ContractSigner>(
+    secp: &Secp256k1<All>,
+    contract: &SignedContract,
+    signer: &X,
+) -> Result<(Vec<EcdsaAdaptorSignature>, Signature), Error> {
+    let accepted_contract = &contract.accepted_contract;
+    let dlc_transactions = &accepted_contract.dlc_transactions;
+    let funding_script_pubkey = &dlc_transactions.funding_script_pubkey;
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+
+    let mut adaptor_sigs = Vec::new();
+    for (contract_info, adaptor_info) in accepted_contract
+        .offered_contract
+        .contract_info
+        .iter()
+        .zip(accepted_contract.adaptor_infos.iter())
+    {
+        adaptor_sigs.extend(contract_info.get_adaptor_signatures(
+            secp,
+            adaptor_info,
+            signer,
+            funding_script_pubkey,
+            fund_output_value,
+            &dlc_transactions.cets,
+        )?);
+    }
+
+    let refund_signature = dlc::util::get_raw_sig_for_tx_input(
+        secp,
+        &dlc_transactions.refund,
+        0,
+        funding_script_pubkey,
+        fund_output_value,
+        &signer.get_secret_key()?,
+    )
+    .context(
+        &accepted_contract.get_contract_id(),
+        "signing refund transaction",
+    )?;
+
+    Ok((adaptor_sigs, refund_signature))
+}
+
+/// Verifies the signatures of the counter party for the CETs and refund
+/// transaction of a contract whose payout script was updated using
+/// [`update_payout_script`], and returns the contract with its signatures
+/// replaced by the given ones.
+pub fn apply_payout_update(
+    secp: &Secp256k1<All>,
+    mut contract: SignedContract,
+    cet_adaptor_signatures: &[EcdsaAdaptorSignature],
+    refund_signature: &Signature,
+    own_refund_signature: Signature,
+) -> Result<SignedContract, Error> {
+    let accepted_contract = &contract.accepted_contract;
+    let offered_contract = &accepted_contract.offered_contract;
+    let contract_id = accepted_contract.get_contract_id();
+    let (counter_fund_pubkey, counter_adaptor_sigs) = if offered_contract.is_offer_party {
+        (
+            &accepted_contract.accept_params.fund_pubkey,
+            &accepted_contract.adaptor_signatures,
         )
-        .expect("Not to fail");
+    } else {
+        (
+            &offered_contract.offer_params.fund_pubkey,
+            &contract.adaptor_signatures,
+        )
+    };
+
+    if counter_adaptor_sigs.as_ref().map(|x| x.len()) != Some(cet_adaptor_signatures.len()) {
+        return Err(Error::InvalidParameters(
+            "Invalid number of CET adaptor signatures".to_string(),
+        ));
+    }
+
+    let dlc_transactions = &accepted_contract.dlc_transactions;
+    let funding_script_pubkey = &dlc_transactions.funding_script_pubkey;
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+
+    dlc::verify_tx_input_sig(
+        secp,
+        refund_signature,
+        &dlc_transactions.refund,
+        0,
+        funding_script_pubkey,
+        fund_output_value,
+        counter_fund_pubkey,
+    )
+    .context(&contract_id, "verifying refund signature")?;
+
+    let mut adaptor_sig_start = 0;
+    for (adaptor_info, contract_info) in accepted_contract
+        .adaptor_infos
+        .iter()
+        .zip(offered_contract.contract_info.iter())
+    {
+        adaptor_sig_start = contract_info.verify_adaptor_info(
+            secp,
+            counter_fund_pubkey,
+            funding_script_pubkey,
+            fund_output_value,
+            &dlc_transactions.cets,
+            cet_adaptor_signatures,
+            adaptor_sig_start,
+            adaptor_info,
+        )?;
+    }
+
+    if offered_contract.is_offer_party {
+        contract.accepted_contract.adaptor_signatures = Some(cet_adaptor_signatures.to_vec());
+        contract.accepted_contract.accept_refund_signature = Some(*refund_signature);
+        contract.offer_refund_signature = Some(own_refund_signature);
+    } else {
+        contract.adaptor_signatures = Some(cet_adaptor_signatures.to_vec());
+        contract.offer_refund_signature = Some(*refund_signature);
+        contract.accepted_contract.accept_refund_signature = Some(own_refund_signature);
+    }
+
+    Ok(contract)
+}
+
+/// Creates the [`OfferedContract`] of an amendment of the given contract,
+/// replacing it with a contract with the given payouts and collaterals, and
+/// returns it along with the [`AmendOffer`] message to send to the counter
+/// party. Only the offer party of the contract can propose an amendment. The
+/// amended contract keeps the oracle announcements, lock times and fund public
+/// keys of the given contract, and is funded by a transaction spending its fund
+/// output together with additional inputs of both parties.
+pub fn amend_contract<W: Deref, B: Deref, X: ContractSigner, SP: Deref, C: Signing>(
+    secp: &Secp256k1<C>,
+    contract: &SignedContract,
+    contract_input: &ContractInput,
+    wallet: &W,
+    blockchain: &B,
+    signer_provider: &SP,
+) -> Result<(OfferedContract, AmendOffer), Error>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    contract_input.validate()?;
+
+    let offered_contract = &contract.accepted_contract.offered_contract;
+    if !offered_contract.is_offer_party {
+        return Err(Error::InvalidState(
+            "Only the offer party of a contract can amend it".to_string(),
+        ));
+    }
+
+    if contract_input.contract_infos.len() != offered_contract.contract_info.len() {
+        return Err(Error::InvalidParameters(
+            "Amended contract must use the oracles of the contract to amend".to_string(),
+        ));
+    }
+
+    let contract_info = contract_input
+        .contract_infos
+        .iter()
+        .zip(offered_contract.contract_info.iter())
+        .map(|(input, info)| ContractInfo {
+            contract_descriptor: input.get_contract_descriptor(),
+            oracle_announcements: info.oracle_announcements.clone(),
+            threshold: info.threshold,
+        })
+        .collect::<Vec<_>>();
+
+    let additional_collateral = contract_input
+        .offer_collateral
+        .saturating_sub(offered_contract.offer_params.collateral);
+    let signer = signer_provider.derive_contract_signer(offered_contract.keys_id)?;
+    let (party_params, funding_inputs) = crate::utils::get_party_params(
+        secp,
+        additional_collateral,
+        crate::utils::get_approximate_fee(
+            contract_input.fee_rate,
+            offered_contract.fee_split,
+            true,
+            contract_input.offer_collateral,
+            contract_input.accept_collateral,
+        )?,
+        contract_input.fee_rate,
+        wallet,
+        &signer,
+        blockchain,
+    )?;
+    let offer_params = PartyParams {
+        collateral: contract_input.offer_collateral,
+        ..get_amended_party_params(&offered_contract.offer_params, party_params)
+    };
+
+    let amended_contract = get_amended_offered_contract(
+        contract,
+        contract_info,
+        contract_input.offer_collateral + contract_input.accept_collateral,
+        offer_params,
+        funding_inputs,
+        crate::utils::get_new_serial_id(),
+        contract_input.fee_rate,
+    );
+    if let Err(e) = validate_amendment(contract, &amended_contract) {
+        crate::utils::release_party_utxos(wallet, &amended_contract.offer_params)?;
+        return Err(e);
+    }
+
+    let amend_offer = AmendOffer {
+        contract_id: contract.accepted_contract.get_contract_id(),
+        contract_info: (&amended_contract).into(),
+        offer_collateral: amended_contract.offer_params.collateral,
+        funding_inputs: amended_contract.funding_inputs.clone(),
+        change_spk: amended_contract.offer_params.change_script_pubkey.clone(),
+        change_serial_id: amended_contract.offer_params.change_serial_id,
+        fund_output_serial_id: amended_contract.fund_output_serial_id,
+        fee_rate_per_vb: amended_contract.fee_rate_per_vb,
+    };
+
+    Ok((amended_contract, amend_offer))
+}
+
+/// Returns the [`OfferedContract`] of the amendment of the given contract
+/// proposed by the given [`AmendOffer`] message, after checking that it is
+/// valid.
+pub fn get_received_amendment(
+    contract: &SignedContract,
+    amend_offer: &AmendOffer,
+) -> Result<OfferedContract, Error> {
+    let offered_contract = &contract.accepted_contract.offered_contract;
+    let contract_info = get_contract_info_and_announcements(&amend_offer.contract_info)?;
+    let (inputs, input_amount) = get_tx_input_infos(&amend_offer.funding_inputs)?;
+    let offer_params = PartyParams {
+        change_script_pubkey: amend_offer.change_spk.clone(),
+        change_serial_id: amend_offer.change_serial_id,
+        collateral: amend_offer.offer_collateral,
+        inputs,
+        input_amount,
+        ..offered_contract.offer_params.clone()
+    };
+
+    let amended_contract = get_amended_offered_contract(
+        contract,
+        contract_info,
+        amend_offer.get_total_collateral(),
+        offer_params,
+        amend_offer.funding_inputs.clone(),
+        amend_offer.fund_output_serial_id,
+        amend_offer.fee_rate_per_vb,
+    );
+    validate_amendment(contract, &amended_contract)?;
+
+    Ok(amended_contract)
+}
+
+/// Accepts the given amendment of the given contract, selecting the
+/// additional inputs of the accept party, and returns the resulting
+/// [`AcceptedContract`] along with the [`AmendAccept`] message to send to the
+/// counter party.
+pub fn accept_amendment<W: Deref, X: ContractSigner, SP: Deref, B: Deref>(
+    secp: &Secp256k1<All>,
+    contract: &SignedContract,
+    amended_contract: &OfferedContract,
+    wallet: &W,
+    signer_provider: &SP,
+    blockchain: &B,
+    cancellation: &CancellationToken,
+) -> Result<(AcceptedContract, AmendAccept), Error>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    let prev_accept_params = &contract.accepted_contract.accept_params;
+    let accept_collateral =
+        amended_contract.total_collateral - amended_contract.offer_params.collateral;
+
+    let signer = signer_provider.derive_contract_signer(amended_contract.keys_id)?;
+    let (party_params, funding_inputs) = crate::utils::get_party_params(
+        secp,
+        accept_collateral.saturating_sub(prev_accept_params.collateral),
+        get_accept_party_approximate_fee(amended_contract)?,
+        amended_contract.fee_rate_per_vb,
+        wallet,
+        &signer,
+        blockchain,
+    )?;
+    let accept_params = PartyParams {
+        collateral: accept_collateral,
+        ..get_amended_party_params(prev_accept_params, party_params)
+    };
+
+    let (accepted_contract, adaptor_sigs) =
+        crate::utils::release_on_cancellation(wallet, &accept_params, {
+            create_amended_transactions(contract, amended_contract, &accept_params).and_then(
+                |dlc_transactions| {
+                    accept_contract_internal(
+                        secp,
+                        amended_contract,
+                        &accept_params,
+                        &funding_inputs,
+                        &signer.get_secret_key()?,
+                        dlc_transactions.get_fund_output().value,
+                        None,
+                        &dlc_transactions,
+                        cancellation,
+                    )
+                },
+            )
+        })?;
+
+    let amend_accept = AmendAccept {
+        contract_id: contract.accepted_contract.get_contract_id(),
+        funding_inputs,
+        change_spk: accept_params.change_script_pubkey.clone(),
+        change_serial_id: accept_params.change_serial_id,
+        cet_adaptor_signatures: adaptor_sigs.as_slice().into(),
+        refund_signature: accepted_contract.get_accept_refund_signature()?,
+    };
+
+    Ok((accepted_contract, amend_accept))
+}
+
+/// Verifies the [`AmendAccept`] message received for the given amendment of the
+/// given contract, and returns the offer party's [`SignedContract`] for the
+/// amended contract along with the [`AmendSign`] message to send to the
+/// counter party.
+pub fn verify_amendment_accept_and_sign<W: Deref, X: ContractSigner, SP: Deref>(
+    secp: &Secp256k1<All>,
+    contract: &SignedContract,
+    amended_contract: &OfferedContract,
+    amend_accept: &AmendAccept,
+    wallet: &W,
+    signer_provider: &SP,
+    cancellation: &CancellationToken,
+) -> Result<(SignedContract, AmendSign), Error>
+where
+    W::Target: Wallet,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    let prev_accept_params = &contract.accepted_contract.accept_params;
+    let (inputs, input_amount) = get_tx_input_infos(&amend_accept.funding_inputs)?;
+    let accept_params = PartyParams {
+        change_script_pubkey: amend_accept.change_spk.clone(),
+        change_serial_id: amend_accept.change_serial_id,
+        collateral: amended_contract.total_collateral - amended_contract.offer_params.collateral,
+        inputs,
+        input_amount,
+        ..prev_accept_params.clone()
+    };
+
+    let dlc_transactions = create_amended_transactions(contract, amended_contract, &accept_params)?;
+    let signer = signer_provider.derive_contract_signer(amended_contract.keys_id)?;
+    let cet_adaptor_signatures: Vec<_> = (&amend_accept.cet_adaptor_signatures).into();
+    let (signed_contract, adaptor_sigs) = verify_accepted_and_sign_contract_internal(
+        secp,
+        amended_contract,
+        &accept_params,
+        &amend_accept.funding_inputs,
+        &amend_accept.refund_signature,
+        &cet_adaptor_signatures,
+        dlc_transactions.get_fund_output().value,
+        wallet,
+        &signer,
+        None,
+        None,
+        &dlc_transactions,
+        None,
+        cancellation,
+    )?;
+
+    let prev_dlc_transactions = &contract.accepted_contract.dlc_transactions;
+    let fund_input_signature = dlc::util::get_raw_sig_for_tx_input(
+        secp,
+        &signed_contract.accepted_contract.dlc_transactions.fund,
+        0,
+        &prev_dlc_transactions.funding_script_pubkey,
+        prev_dlc_transactions.get_fund_output().value,
+        &signer.get_secret_key()?,
+    )
+    .context(
+        &contract.accepted_contract.get_contract_id(),
+        "signing amended fund transaction",
+    )?;
+
+    let amend_sign = AmendSign {
+        contract_id: contract.accepted_contract.get_contract_id(),
+        cet_adaptor_signatures: adaptor_sigs.as_slice().into(),
+        refund_signature: signed_contract.get_offer_refund_signature()?,
+        funding_signatures: signed_contract.funding_signatures.clone(),
+        fund_input_signature,
+    };
+
+    Ok((signed_contract, amend_sign))
+}
+
+/// Verifies the [`AmendSign`] message received for the given accepted
+/// amendment of the given contract, and returns the accept party's
+/// [`SignedContract`] for the amended contract along with the fully signed
+/// amended fund transaction.
+pub fn verify_amendment_sign<W: Deref, X: ContractSigner, SP: Deref>(
+    secp: &Secp256k1<All>,
+    contract: &SignedContract,
+    accepted_contract: &AcceptedContract,
+    amend_sign: &AmendSign,
+    wallet: &W,
+    signer_provider: &SP,
+    cancellation: &CancellationToken,
+) -> Result<(SignedContract, Transaction), Error>
+where
+    W::Target: Wallet,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    let cet_adaptor_signatures: Vec<_> = (&amend_sign.cet_adaptor_signatures).into();
+    let (signed_contract, mut fund_tx) = verify_signed_contract_internal(
+        secp,
+        accepted_contract,
+        &amend_sign.refund_signature,
+        &cet_adaptor_signatures,
+        &amend_sign.funding_signatures,
+        accepted_contract.dlc_transactions.get_fund_output().value,
+        None,
+        None,
+        wallet,
+        None,
+        cancellation,
+    )?;
+
+    let prev_dlc_transactions = &contract.accepted_contract.dlc_transactions;
+    let offer_fund_pubkey = &contract
+        .accepted_contract
+        .offered_contract
+        .offer_params
+        .fund_pubkey;
+    dlc::verify_tx_input_sig(
+        secp,
+        &amend_sign.fund_input_signature,
+        &fund_tx,
+        0,
+        &prev_dlc_transactions.funding_script_pubkey,
+        prev_dlc_transactions.get_fund_output().value,
+        offer_fund_pubkey,
+    )
+    .context(
+        &contract.accepted_contract.get_contract_id(),
+        "verifying amended fund transaction signature",
+    )?;
+
+    let signer =
+        signer_provider.derive_contract_signer(accepted_contract.offered_contract.keys_id)?;
+    dlc::util::sign_multi_sig_input(
+        secp,
+        &mut fund_tx,
+        &amend_sign.fund_input_signature,
+        offer_fund_pubkey,
+        &signer.get_secret_key()?,
+        &prev_dlc_transactions.funding_script_pubkey,
+        prev_dlc_transactions.get_fund_output().value,
+        0,
+    )?;
+
+    Ok((signed_contract, fund_tx))
+}
+
+/// Returns the given parameters with the fund public key and payout script of
+/// the given previous parameters, which cannot change in an amendment.
+fn get_amended_party_params(prev_params: &PartyParams, params: PartyParams) -> PartyParams {
+    PartyParams {
+        fund_pubkey: prev_params.fund_pubkey,
+        payout_script_pubkey: prev_params.payout_script_pubkey.clone(),
+        payout_serial_id: prev_params.payout_serial_id,
+        ..params
+    }
+}
+
+fn get_amended_offered_contract(
+    contract: &SignedContract,
+    contract_info: Vec<ContractInfo>,
+    total_collateral: u64,
+    offer_params: PartyParams,
+    funding_inputs: Vec<FundingInput>,
+    fund_output_serial_id: u64,
+    fee_rate_per_vb: u64,
+) -> OfferedContract {
+    OfferedContract {
+        contract_info,
+        offer_params,
+        total_collateral,
+        funding_inputs,
+        fund_output_serial_id,
+        fee_rate_per_vb,
+        expiry: None,
+        ..contract.accepted_contract.offered_contract.clone()
+    }
+}
+
+/// Checks that the given amended contract uses the oracle announcements of the
+/// given contract and does not decrease the collateral of either party.
+fn validate_amendment(
+    contract: &SignedContract,
+    amended_contract: &OfferedContract,
+) -> Result<(), Error> {
+    let accepted_contract = &contract.accepted_contract;
+    let offered_contract = &accepted_contract.offered_contract;
+    if contract.channel_id.is_some() {
+        return Err(Error::InvalidState(
+            "Cannot amend a channel contract".to_string(),
+        ));
+    }
+    check_not_taproot(offered_contract)?;
+
+    let same_oracles = amended_contract.contract_info.len() == offered_contract.contract_info.len()
+        && amended_contract
+            .contract_info
+            .iter()
+            .zip(offered_contract.contract_info.iter())
+            .all(|(a, b)| {
+                a.oracle_announcements == b.oracle_announcements && a.threshold == b.threshold
+            });
+    if !same_oracles {
+        return Err(Error::InvalidParameters(
+            "Amended contract must use the oracle announcements of the contract to amend"
+                .to_string(),
+        ));
+    }
+
+    let accept_collateral = amended_contract
+        .total_collateral
+        .checked_sub(amended_contract.offer_params.collateral)
+        .ok_or_else(|| {
+            Error::InvalidParameters(
+                "Offer collateral is greater than the total collateral".to_string(),
+            )
+        })?;
+    if amended_contract.offer_params.collateral < offered_contract.offer_params.collateral
+        || accept_collateral < accepted_contract.accept_params.collateral
+        || amended_contract.total_collateral <= offered_contract.total_collateral
+    {
+        return Err(Error::InvalidParameters(
+            "Amendment must increase the total collateral without decreasing the one of either party"
+                .to_string(),
+        ));
+    }
+
+    amended_contract.validate()
+}
+
+fn create_amended_transactions(
+    contract: &SignedContract,
+    amended_contract: &OfferedContract,
+    accept_params: &PartyParams,
+) -> Result<DlcTransactions, Error> {
+    let accepted_contract = &contract.accepted_contract;
+    dlc::create_amended_dlc_transactions(
+        &accepted_contract.dlc_transactions,
+        accepted_contract.offered_contract.offer_params.collateral,
+        accepted_contract.accept_params.collateral,
+        &amended_contract.offer_params,
+        accept_params,
+        &amended_contract.contract_info[0].get_payouts(amended_contract.total_collateral)?,
+        amended_contract.refund_locktime,
+        amended_contract.fee_rate_per_vb,
+        0,
+        amended_contract.cet_locktime,
+        amended_contract.fund_output_serial_id,
+        amended_contract.anchor_outputs,
+        amended_contract.fee_split,
+    )
+    .context(
+        &accepted_contract.get_contract_id(),
+        "creating amended DLC transactions",
+    )
+}
+
+/// Creates the [`OfferedContract`] of the contract netting the given confirmed
+/// contracts with the same counter party, and returns it along with the
+/// [`NetOffer`] message to send to the counter party. The net contract keeps
+/// the oracle announcements, fund public keys and payout scripts of the first
+/// contract, and is funded by a transaction spending the fund outputs of all
+/// the netted contracts (see [`crate::netting`]).
+pub fn offer_netting<W: Deref>(
+    contracts: &[SignedContract],
+    fee_rate_per_vb: u64,
+    wallet: &W,
+) -> Result<(OfferedContract, NetOffer), Error>
+where
+    W::Target: Wallet,
+{
+    let terms = get_net_terms(contracts, true)?;
+    let net_offer = NetOffer {
+        contract_ids: contracts
+            .iter()
+            .map(|c| c.accepted_contract.get_contract_id())
+            .collect(),
+        change_spk: wallet.get_new_change_address()?.script_pubkey(),
+        change_serial_id: crate::utils::get_new_serial_id(),
+        fund_output_serial_id: crate::utils::get_new_serial_id(),
+        fee_rate_per_vb,
+    };
+    let net_contract = get_net_offered_contract(contracts, terms, &net_offer, true)?;
+
+    Ok((net_contract, net_offer))
+}
+
+/// Returns the [`OfferedContract`] of the contract netting the given contracts
+/// proposed by the given [`NetOffer`] message, after checking that it is valid.
+/// The contracts must be given in the order of the message.
+pub fn get_received_netting(
+    contracts: &[SignedContract],
+    net_offer: &NetOffer,
+) -> Result<OfferedContract, Error> {
+    let same_contracts = contracts.len() == net_offer.contract_ids.len()
+        && contracts
+            .iter()
+            .zip(net_offer.contract_ids.iter())
+            .all(|(c, id)| c.accepted_contract.get_contract_id() == *id);
+    if !same_contracts {
+        return Err(Error::InvalidParameters(
+            "Contracts do not match the ones of the netting offer".to_string(),
+        ));
+    }
+
+    let terms = get_net_terms(contracts, false)?;
+    get_net_offered_contract(contracts, terms, net_offer, false)
+}
+
+/// Accepts the given net contract of the given contracts, and returns the
+/// resulting [`AcceptedContract`] along with the [`NetAccept`] message to send
+/// to the counter party.
+pub fn accept_netting<W: Deref, X: ContractSigner, SP: Deref>(
+    secp: &Secp256k1<All>,
+    contracts: &[SignedContract],
+    net_contract: &OfferedContract,
+    wallet: &W,
+    signer_provider: &SP,
+    cancellation: &CancellationToken,
+) -> Result<(AcceptedContract, NetAccept), Error>
+where
+    W::Target: Wallet,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    let accept_params = PartyParams {
+        change_script_pubkey: wallet.get_new_change_address()?.script_pubkey(),
+        change_serial_id: crate::utils::get_new_serial_id(),
+        collateral: net_contract.total_collateral - net_contract.offer_params.collateral,
+        inputs: Vec::new(),
+        input_amount: 0,
+        ..get_own_params(&contracts[0]).clone()
+    };
+
+    let signer = signer_provider.derive_contract_signer(net_contract.keys_id)?;
+    let dlc_transactions = create_netted_transactions(contracts, net_contract, &accept_params)?;
+    let (accepted_contract, adaptor_sigs) = accept_contract_internal(
+        secp,
+        net_contract,
+        &accept_params,
+        &[],
+        &signer.get_secret_key()?,
+        dlc_transactions.get_fund_output().value,
+        None,
+        &dlc_transactions,
+        cancellation,
+    )?;
+
+    let net_accept = NetAccept {
+        contract_id: contracts[0].accepted_contract.get_contract_id(),
+        change_spk: accept_params.change_script_pubkey.clone(),
+        change_serial_id: accept_params.change_serial_id,
+        cet_adaptor_signatures: adaptor_sigs.as_slice().into(),
+        refund_signature: accepted_contract.get_accept_refund_signature()?,
+    };
+
+    Ok((accepted_contract, net_accept))
+}
+
+/// Verifies the [`NetAccept`] message received for the given net contract of
+/// the given contracts, and returns the offer party's [`SignedContract`] for
+/// the net contract along with the [`NetSign`] message to send to the counter
+/// party.
+pub fn verify_netting_accept_and_sign<W: Deref, X: ContractSigner, SP: Deref>(
+    secp: &Secp256k1<All>,
+    contracts: &[SignedContract],
+    net_contract: &OfferedContract,
+    net_accept: &NetAccept,
+    wallet: &W,
+    signer_provider: &SP,
+    cancellation: &CancellationToken,
+) -> Result<(SignedContract, NetSign), Error>
+where
+    W::Target: Wallet,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    let accept_params = PartyParams {
+        change_script_pubkey: net_accept.change_spk.clone(),
+        change_serial_id: net_accept.change_serial_id,
+        collateral: net_contract.total_collateral - net_contract.offer_params.collateral,
+        inputs: Vec::new(),
+        input_amount: 0,
+        ..get_counter_params(&contracts[0]).clone()
+    };
+
+    let dlc_transactions = create_netted_transactions(contracts, net_contract, &accept_params)?;
+    let signer = signer_provider.derive_contract_signer(net_contract.keys_id)?;
+    let cet_adaptor_signatures: Vec<_> = (&net_accept.cet_adaptor_signatures).into();
+    let (signed_contract, adaptor_sigs) = verify_accepted_and_sign_contract_internal(
+        secp,
+        net_contract,
+        &accept_params,
+        &[],
+        &net_accept.refund_signature,
+        &cet_adaptor_signatures,
+        dlc_transactions.get_fund_output().value,
+        wallet,
+        &signer,
+        None,
+        None,
+        &dlc_transactions,
+        None,
+        cancellation,
+    )?;
+
+    let fund_tx = &signed_contract.accepted_contract.dlc_transactions.fund;
+    let fund_input_signatures = contracts
+        .iter()
+        .enumerate()
+        .map(|(i, contract)| {
+            let accepted_contract = &contract.accepted_contract;
+            let prev_dlc_transactions = &accepted_contract.dlc_transactions;
+            let signer = signer_provider
+                .derive_contract_signer(accepted_contract.offered_contract.keys_id)?;
+            dlc::util::get_raw_sig_for_tx_input(
+                secp,
+                fund_tx,
+                i,
+                &prev_dlc_transactions.funding_script_pubkey,
+                prev_dlc_transactions.get_fund_output().value,
+                &signer.get_secret_key()?,
+            )
+            .context(
+                &accepted_contract.get_contract_id(),
+                "signing net fund transaction",
+            )
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let net_sign = NetSign {
+        contract_id: contracts[0].accepted_contract.get_contract_id(),
+        cet_adaptor_signatures: adaptor_sigs.as_slice().into(),
+        refund_signature: signed_contract.get_offer_refund_signature()?,
+        fund_input_signatures,
+    };
+
+    Ok((signed_contract, net_sign))
+}
+
+/// Verifies the [`NetSign`] message received for the given accepted net
+/// contract of the given contracts, and returns the accept party's
+/// [`SignedContract`] for the net contract along with the fully signed net
+/// fund transaction.
+pub fn verify_netting_sign<W: Deref, X: ContractSigner, SP: Deref>(
+    secp: &Secp256k1<All>,
+    contracts: &[SignedContract],
+    accepted_contract: &AcceptedContract,
+    net_sign: &NetSign,
+    wallet: &W,
+    signer_provider: &SP,
+    cancellation: &CancellationToken,
+) -> Result<(SignedContract, Transaction), Error>
+where
+    W::Target: Wallet,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    if net_sign.fund_input_signatures.len() != contracts.len() {
+        return Err(Error::InvalidParameters(
+            "Expected a fund input signature for each netted contract".to_string(),
+        ));
+    }
+
+    let cet_adaptor_signatures: Vec<_> = (&net_sign.cet_adaptor_signatures).into();
+    let (signed_contract, mut fund_tx) = verify_signed_contract_internal(
+        secp,
+        accepted_contract,
+        &net_sign.refund_signature,
+        &cet_adaptor_signatures,
+        &FundingSignatures {
+            funding_signatures: Vec::new(),
+        },
+        accepted_contract.dlc_transactions.get_fund_output().value,
+        None,
+        None,
+        wallet,
+        None,
+        cancellation,
+    )?;
+
+    for (i, (contract, signature)) in contracts
+        .iter()
+        .zip(net_sign.fund_input_signatures.iter())
+        .enumerate()
+    {
+        let prev_dlc_transactions = &contract.accepted_contract.dlc_transactions;
+        let counter_fund_pubkey = get_counter_fund_pubkey(contract);
+        dlc::verify_tx_input_sig(
+            secp,
+            signature,
+            &fund_tx,
+            i,
+            &prev_dlc_transactions.funding_script_pubkey,
+            prev_dlc_transactions.get_fund_output().value,
+            counter_fund_pubkey,
+        )
+        .context(
+            &contract.accepted_contract.get_contract_id(),
+            "verifying net fund transaction signature",
+        )?;
+
+        let signer = signer_provider
+            .derive_contract_signer(contract.accepted_contract.offered_contract.keys_id)?;
+        dlc::util::sign_multi_sig_input(
+            secp,
+            &mut fund_tx,
+            signature,
+            counter_fund_pubkey,
+            &signer.get_secret_key()?,
+            &prev_dlc_transactions.funding_script_pubkey,
+            prev_dlc_transactions.get_fund_output().value,
+            i,
+        )?;
+    }
+
+    Ok((signed_contract, fund_tx))
+}
+
+fn get_net_offered_contract(
+    contracts: &[SignedContract],
+    terms: NetTerms,
+    net_offer: &NetOffer,
+    is_offer_party: bool,
+) -> Result<OfferedContract, Error> {
+    let base = &contracts[0];
+    let offer_params = if is_offer_party {
+        get_own_params(base)
+    } else {
+        get_counter_params(base)
+    };
+    let total_collateral = terms.get_total_collateral();
+    let net_contract = OfferedContract {
+        is_offer_party,
+        contract_info: vec![terms.contract_info],
+        offer_params: PartyParams {
+            change_script_pubkey: net_offer.change_spk.clone(),
+            change_serial_id: net_offer.change_serial_id,
+            collateral: terms.offer_collateral,
+            inputs: Vec::new(),
+            input_amount: 0,
+            ..offer_params.clone()
+        },
+        total_collateral,
+        funding_inputs: Vec::new(),
+        fund_output_serial_id: net_offer.fund_output_serial_id,
+        fee_rate_per_vb: net_offer.fee_rate_per_vb,
+        cet_locktime: contracts
+            .iter()
+            .map(|c| c.accepted_contract.offered_contract.cet_locktime)
+            .max()
+            .unwrap_or_default(),
+        refund_locktime: contracts
+            .iter()
+            .map(|c| c.accepted_contract.offered_contract.refund_locktime)
+            .max()
+            .unwrap_or_default(),
+        expiry: None,
+        fee_split: FeeSplit::Equal,
+        metadata: None,
+        ..base.accepted_contract.offered_contract.clone()
+    };
+    net_contract.validate()?;
+
+    Ok(net_contract)
+}
+
+fn create_netted_transactions(
+    contracts: &[SignedContract],
+    net_contract: &OfferedContract,
+    accept_params: &PartyParams,
+) -> Result<DlcTransactions, Error> {
+    let prev_contracts = contracts
+        .iter()
+        .map(|c| {
+            let (offer_collateral, accept_collateral) =
+                get_netted_collaterals(c, net_contract.is_offer_party);
+            (
+                &c.accepted_contract.dlc_transactions,
+                offer_collateral,
+                accept_collateral,
+            )
+        })
+        .collect::<Vec<_>>();
+    dlc::create_netted_dlc_transactions(
+        &prev_contracts,
+        &net_contract.offer_params,
+        accept_params,
+        &net_contract.contract_info[0].get_payouts(net_contract.total_collateral)?,
+        net_contract.refund_locktime,
+        net_contract.fee_rate_per_vb,
+        0,
+        net_contract.cet_locktime,
+        net_contract.fund_output_serial_id,
+        net_contract.anchor_outputs,
+        net_contract.fee_split,
+    )
+    .context(&net_contract.id, "creating net DLC transactions")
+}
+
+fn get_own_params(contract: &SignedContract) -> &PartyParams {
+    let accepted_contract = &contract.accepted_contract;
+    if accepted_contract.offered_contract.is_offer_party {
+        &accepted_contract.offered_contract.offer_params
+    } else {
+        &accepted_contract.accept_params
+    }
+}
+
+fn get_counter_params(contract: &SignedContract) -> &PartyParams {
+    let accepted_contract = &contract.accepted_contract;
+    if accepted_contract.offered_contract.is_offer_party {
+        &accepted_contract.accept_params
+    } else {
+        &accepted_contract.offered_contract.offer_params
+    }
+}
+
+/// Returns the transaction closing the given contract by mutual agreement of
+/// the parties, paying `counter_payout` to the counter party and the rest of
+/// the total collateral to the local party. The fees reserved for the CETs
+/// when funding the contract are used to pay for the transaction.
+pub fn get_collaborative_close_transaction(
+    contract: &SignedContract,
+    counter_payout: u64,
+) -> Result<Transaction, Error> {
+    if contract.channel_id.is_some() {
+        return Err(Error::InvalidState(
+            "Cannot collaboratively close a channel contract outside of its channel".to_string(),
+        ));
+    }
+
+    let accepted_contract = &contract.accepted_contract;
+    let offered_contract = &accepted_contract.offered_contract;
+    check_not_taproot(offered_contract)?;
+    let own_payout = offered_contract
+        .total_collateral
+        .checked_sub(counter_payout)
+        .ok_or_else(|| {
+            Error::InvalidParameters("Counter payout is greater than total collateral".to_string())
+        })?;
+    let (offer_payout, accept_payout) = if offered_contract.is_offer_party {
+        (own_payout, counter_payout)
+    } else {
+        (counter_payout, own_payout)
+    };
+    let dlc_transactions = &accepted_contract.dlc_transactions;
+
+    Ok(dlc::channel::create_collaborative_close_transaction(
+        &offered_contract.offer_params,
+        offer_payout,
+        &accepted_contract.accept_params,
+        accept_payout,
+        dlc_transactions.get_fund_outpoint(),
+        dlc_transactions.get_fund_output().value,
+    ))
+}
+
+/// Creates a [`MutualCloseOffer`] message proposing to close the given
+/// contract with the given payout for the counter party, and returns it along
+/// with the proposed closing transaction.
+pub fn offer_collaborative_close<X: ContractSigner>(
+    secp: &Secp256k1<All>,
+    contract: &SignedContract,
+    counter_payout: u64,
+    signer: &X,
+) -> Result<(MutualCloseOffer, Transaction), Error> {
+    let close_tx = get_collaborative_close_transaction(contract, counter_payout)?;
+    let dlc_transactions = &contract.accepted_contract.dlc_transactions;
+    let close_signature = dlc::util::get_raw_sig_for_tx_input(
+        secp,
+        &close_tx,
+        0,
+        &dlc_transactions.funding_script_pubkey,
+        dlc_transactions.get_fund_output().value,
+        &signer.get_secret_key()?,
+    )?;
+
+    let close_offer = MutualCloseOffer {
+        contract_id: contract.accepted_contract.get_contract_id(),
+        counter_payout,
+        close_signature,
+    };
+
+    Ok((close_offer, close_tx))
+}
+
+/// Validates the given [`MutualCloseOffer`] received for the given contract
+/// and returns the closing transaction it proposes.
+pub fn on_collaborative_close_offer(
+    secp: &Secp256k1<All>,
+    contract: &SignedContract,
+    close_offer: &MutualCloseOffer,
+) -> Result<Transaction, Error> {
+    let counter_payout = contract
+        .accepted_contract
+        .offered_contract
+        .total_collateral
+        .checked_sub(close_offer.counter_payout)
+        .ok_or_else(|| {
+            Error::InvalidParameters(
+                "Received collaborative close offer with counter payout greater than total collateral".to_string(),
+            )
+        })?;
+    let close_tx = get_collaborative_close_transaction(contract, counter_payout)?;
+    verify_collaborative_close_signature(secp, contract, &close_tx, &close_offer.close_signature)?;
+
+    Ok(close_tx)
+}
+
+/// Signs the given transaction closing the given contract and adds the given
+/// signature of the counter party to it. Returns the fully signed transaction
+/// along with the signature of the local party.
+pub fn sign_collaborative_close<X: ContractSigner>(
+    secp: &Secp256k1<All>,
+    contract: &SignedContract,
+    close_tx: &Transaction,
+    counter_signature: &Signature,
+    signer: &X,
+) -> Result<(Transaction, Signature), Error> {
+    verify_collaborative_close_signature(secp, contract, close_tx, counter_signature)?;
+
+    let dlc_transactions = &contract.accepted_contract.dlc_transactions;
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+    let secret_key = signer.get_secret_key()?;
+    let own_signature = dlc::util::get_raw_sig_for_tx_input(
+        secp,
+        close_tx,
+        0,
+        &dlc_transactions.funding_script_pubkey,
+        fund_output_value,
+        &secret_key,
+    )?;
+
+    let mut close_tx = close_tx.clone();
+    dlc::util::sign_multi_sig_input(
+        secp,
+        &mut close_tx,
+        counter_signature,
+        get_counter_fund_pubkey(contract),
+        &secret_key,
+        &dlc_transactions.funding_script_pubkey,
+        fund_output_value,
+        0,
+    )?;
+
+    Ok((close_tx, own_signature))
+}
+
+fn verify_collaborative_close_signature(
+    secp: &Secp256k1<All>,
+    contract: &SignedContract,
+    close_tx: &Transaction,
+    signature: &Signature,
+) -> Result<(), Error> {
+    let dlc_transactions = &contract.accepted_contract.dlc_transactions;
+    dlc::verify_tx_input_sig(
+        secp,
+        signature,
+        close_tx,
+        0,
+        &dlc_transactions.funding_script_pubkey,
+        dlc_transactions.get_fund_output().value,
+        get_counter_fund_pubkey(contract),
+    )
+    .context(
+        &contract.accepted_contract.get_contract_id(),
+        "verifying collaborative close signature",
+    )
+}
+
+fn get_counter_fund_pubkey(contract: &SignedContract) -> &PublicKey {
+    let accepted_contract = &contract.accepted_contract;
+    if accepted_contract.offered_contract.is_offer_party {
+        &accepted_contract.accept_params.fund_pubkey
+    } else {
+        &accepted_contract.offered_contract.offer_params.fund_pubkey
+    }
+}
+
+/// Creates and signs a transaction spending the output of the given CET paying
+/// to `payout_script_pubkey` to a new address of the wallet. Its fee is set so
+/// that the CET and the created transaction together pay the given fee rate,
+/// in satoshis per virtual byte, enabling to fee bump the CET (child pays for
+/// parent). `cet_fee` is the fee paid by the CET itself.
+pub fn create_cpfp_transaction<W: Deref>(
+    cet: &Transaction,
+    cet_fee: u64,
+    payout_script_pubkey: &Script,
+    fee_rate_per_vb: u64,
+    wallet: &W,
+) -> Result<Transaction, Error>
+where
+    W::Target: Wallet,
+{
+    let (vout, payout) = cet
+        .output
+        .iter()
+        .enumerate()
+        .find(|(_, x)| x.script_pubkey.as_script() == payout_script_pubkey)
+        .ok_or_else(|| {
+            Error::InvalidState("The CET has no output for the local party".to_string())
+        })?;
+    let destination = wallet.get_new_address()?.script_pubkey();
+    let dust_limit = destination.dust_value().to_sat();
+
+    let sign = |fee: u64| -> Result<Transaction, Error> {
+        let value = payout
+            .value
+            .checked_sub(fee)
+            .filter(|x| *x >= dust_limit)
+            .ok_or_else(|| {
+                Error::InvalidParameters(format!(
+                    "The local payout of {} is too low to pay a fee of {}",
+                    payout.value, fee
+                ))
+            })?;
+        let tx = Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: cet.txid(),
+                    vout: vout as u32,
+                },
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::default(),
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: destination.clone(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx)
+            .map_err(|_| Error::InvalidState("Tried to create PSBT from signed tx".to_string()))?;
+        psbt.inputs[0].witness_utxo = Some(payout.clone());
+        wallet.sign_psbt_input(&mut psbt, 0)?;
+        Ok(psbt.extract_tx())
+    };
+
+    // The transaction is first signed without fee to get its size.
+    let child_vsize = sign(0)?.vsize() as u64;
+    let package_fee = fee_rate_per_vb * (cet.vsize() as u64 + child_vsize);
+    if package_fee <= cet_fee {
+        return Err(Error::InvalidParameters(format!(
+            "The CET already pays a fee rate of at least {} sat/vB",
+            fee_rate_per_vb
+        )));
+    }
+
+    sign(std::cmp::max(
+        package_fee - cet_fee,
+        fee_rate_per_vb * child_vsize,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use mocks::dlc_manager::cancellation::CancellationToken;
+    use mocks::dlc_manager::contract::offered_contract::OfferedContract;
+    use secp256k1_zkp::PublicKey;
+
+    #[test]
+    fn accept_contract_test() {
+        let offer_dlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let dummy_pubkey: PublicKey =
+            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443"
+                .parse()
+                .unwrap();
+        let offered_contract =
+            OfferedContract::try_from_offer_dlc(&offer_dlc, dummy_pubkey, [0; 32]).unwrap();
+        let blockchain = Rc::new(mocks::mock_blockchain::MockBlockchain::new());
+        let fee_rate: u64 = offered_contract.fee_rate_per_vb;
+        let utxo_value: u64 = offered_contract.total_collateral
+            - offered_contract.offer_params.collateral
+            + crate::utils::get_half_common_fee(fee_rate).unwrap();
+        let wallet = Rc::new(mocks::mock_wallet::MockWallet::new(
+            &blockchain,
+            &[utxo_value, 10000],
+        ));
+
+        mocks::dlc_manager::contract_updater::accept_contract(
+            secp256k1_zkp::SECP256K1,
+            &offered_contract,
+            &wallet,
+            &wallet,
+            &blockchain,
+            &CancellationToken::new(),
+        )
+        .expect("Not to fail");
+    }
+
+    #[test]
+    fn accept_single_funded_contract_test() {
+        let offer_dlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let dummy_pubkey: PublicKey =
+            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443"
+                .parse()
+                .unwrap();
+        let mut offered_contract =
+            OfferedContract::try_from_offer_dlc(&offer_dlc, dummy_pubkey, [0; 32]).unwrap();
+        offered_contract.offer_params.collateral = offered_contract.total_collateral;
+        offered_contract.fee_split = dlc::FeeSplit::OfferPaysAll;
+        let blockchain = Rc::new(mocks::mock_blockchain::MockBlockchain::new());
+        let wallet = Rc::new(mocks::mock_wallet::MockWallet::new(&blockchain, &[]));
+
+        let (accepted_contract, accept_msg) =
+            mocks::dlc_manager::contract_updater::accept_contract(
+                secp256k1_zkp::SECP256K1,
+                &offered_contract,
+                &wallet,
+                &wallet,
+                &blockchain,
+                &CancellationToken::new(),
+            )
+            .expect("Not to fail");
+
+        assert!(accept_msg.funding_inputs.is_empty());
+        assert_eq!(0, accept_msg.accept_collateral);
+        assert_eq!(
+            offered_contract.funding_inputs.len(),
+            accepted_contract.dlc_transactions.fund.input.len()
+        );
+    }
+
+    #[test]
+    fn accept_contract_cancelled_test() {
+        let offer_dlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let dummy_pubkey: PublicKey =
+            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443"
+                .parse()
+                .unwrap();
+        let offered_contract =
+            OfferedContract::try_from_offer_dlc(&offer_dlc, dummy_pubkey, [0; 32]).unwrap();
+        let blockchain = Rc::new(mocks::mock_blockchain::MockBlockchain::new());
+        let fee_rate: u64 = offered_contract.fee_rate_per_vb;
+        let utxo_value: u64 = offered_contract.total_collateral
+            - offered_contract.offer_params.collateral
+            + crate::utils::get_half_common_fee(fee_rate).unwrap();
+        let wallet = Rc::new(mocks::mock_wallet::MockWallet::new(
+            &blockchain,
+            &[utxo_value, 10000],
+        ));
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+
+        let res = mocks::dlc_manager::contract_updater::accept_contract(
+            secp256k1_zkp::SECP256K1,
+            &offered_contract,
+            &wallet,
+            &wallet,
+            &blockchain,
+            &cancellation,
+        );
+
+        assert!(matches!(
+            res,
+            Err(mocks::dlc_manager::error::Error::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn update_payout_script_replaces_outputs() {
+        use lightning::util::ser::Readable;
+        use mocks::dlc_manager::contract::signed_contract::SignedContract;
+
+        let contract: SignedContract = Readable::read(&mut std::io::Cursor::new(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Signed"
+        )))
+        .unwrap();
+        let previous_spk = contract
+            .accepted_contract
+            .accept_params
+            .payout_script_pubkey
+            .clone();
+        let payout_spk = bitcoin::ScriptBuf::from(vec![1u8; previous_spk.len()]);
+
+        let updated = mocks::dlc_manager::contract_updater::update_payout_script(
+            &contract,
+            false,
+            &payout_spk,
+        )
+        .expect("to be able to update the payout script");
+
+        let dlc_transactions = &updated.accepted_contract.dlc_transactions;
+        for tx in dlc_transactions
+            .cets
+            .iter()
+            .chain(std::iter::once(&dlc_transactions.refund))
+        {
+            assert!(tx.output.iter().all(|o| o.script_pubkey != previous_spk));
+        }
+        assert_eq!(
+            payout_spk,
+            updated.accepted_contract.accept_params.payout_script_pubkey
+        );
+        assert_eq!(
+            contract.accepted_contract.get_contract_id(),
+            updated.accepted_contract.get_contract_id()
+        );
+
+        let longer_spk = bitcoin::ScriptBuf::from(vec![1u8; previous_spk.len() + 1]);
+        assert!(mocks::dlc_manager::contract_updater::update_payout_script(
+            &contract,
+            false,
+            &longer_spk
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn received_amendment_must_increase_collateral() {
+        use lightning::util::ser::Readable;
+        use mocks::dlc_manager::contract::signed_contract::SignedContract;
+
+        let contract: SignedContract = Readable::read(&mut std::io::Cursor::new(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Signed"
+        )))
+        .unwrap();
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        let amend_offer = dlc_messages::AmendOffer {
+            contract_id: contract.accepted_contract.get_contract_id(),
+            contract_info: offered_contract.into(),
+            offer_collateral: offered_contract.offer_params.collateral,
+            funding_inputs: Vec::new(),
+            change_spk: offered_contract.offer_params.change_script_pubkey.clone(),
+            change_serial_id: offered_contract.offer_params.change_serial_id,
+            fund_output_serial_id: offered_contract.fund_output_serial_id,
+            fee_rate_per_vb: offered_contract.fee_rate_per_vb,
+        };
+
+        mocks::dlc_manager::contract_updater::get_received_amendment(&contract, &amend_offer)
+            .expect_err("an amendment with the same collateral to be rejected");
+
+        let decreased_offer = dlc_messages::AmendOffer {
+            offer_collateral: offered_contract.offer_params.collateral - 1,
+            ..amend_offer.clone()
+        };
+        mocks::dlc_manager::contract_updater::get_received_amendment(&contract, &decreased_offer)
+            .expect_err("an amendment decreasing the offer collateral to be rejected");
+    }
+
+    #[test]
+    fn received_netting_must_net_distinct_contracts() {
+        use lightning::util::ser::Readable;
+        use mocks::dlc_manager::contract::signed_contract::SignedContract;
+
+        let contract: SignedContract = Readable::read(&mut std::io::Cursor::new(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Signed"
+        )))
+        .unwrap();
+        let contract_id = contract.accepted_contract.get_contract_id();
+        let net_offer = dlc_messages::NetOffer {
+            contract_ids: vec![contract_id, contract_id],
+            change_spk: bitcoin::ScriptBuf::new(),
+            change_serial_id: 1,
+            fund_output_serial_id: 2,
+            fee_rate_per_vb: 2,
+        };
+        let contracts = vec![contract.clone(), contract];
+
+        mocks::dlc_manager::contract_updater::get_received_netting(&contracts, &net_offer)
+            .expect_err("netting a contract with itself to be rejected");
+        mocks::dlc_manager::contract_updater::get_received_netting(&contracts[..1], &net_offer)
+            .expect_err("contracts not matching the offer to be rejected");
+    }
+
+    #[test]
+    fn collaborative_close_transaction_splits_collateral() {
+        use lightning::util::ser::Readable;
+        use mocks::dlc_manager::contract::signed_contract::SignedContract;
+
+        let contract: SignedContract = Readable::read(&mut std::io::Cursor::new(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Signed"
+        )))
+        .unwrap();
+        let accepted_contract = &contract.accepted_contract;
+        let offered_contract = &accepted_contract.offered_contract;
+        let total_collateral = offered_contract.total_collateral;
+        let counter_spk = if offered_contract.is_offer_party {
+            &accepted_contract.accept_params.payout_script_pubkey
+        } else {
+            &offered_contract.offer_params.payout_script_pubkey
+        };
+
+        let close_tx = mocks::dlc_manager::contract_updater::get_collaborative_close_transaction(
+            &contract,
+            total_collateral / 4,
+        )
+        .expect("to be able to create the close transaction");
+
+        assert_eq!(
+            accepted_contract.dlc_transactions.get_fund_outpoint(),
+            close_tx.input[0].previous_output
+        );
+        assert_eq!(
+            Some(total_collateral / 4),
+            close_tx
+                .output
+                .iter()
+                .find(|o| &o.script_pubkey == counter_spk)
+                .map(|o| o.value)
+        );
+        assert_eq!(
+            total_collateral,
+            close_tx.output.iter().map(|o| o.value).sum::<u64>()
+        );
+
+        mocks::dlc_manager::contract_updater::get_collaborative_close_transaction(
+            &contract,
+            total_collateral + 1,
+        )
+        .expect_err("a payout greater than the total collateral to be rejected");
+    }
+
+    #[test]
+    fn cpfp_transaction_pays_for_cet() {
+        use lightning::util::ser::Readable;
+        use mocks::dlc_manager::contract::signed_contract::SignedContract;
+
+        let contract: SignedContract = Readable::read(&mut std::io::Cursor::new(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Signed"
+        )))
+        .unwrap();
+        let accepted_contract = &contract.accepted_contract;
+        let payout_spk = &accepted_contract.accept_params.payout_script_pubkey;
+        let get_payout = |cet: &bitcoin::Transaction| {
+            cet.output
+                .iter()
+                .find(|o| &o.script_pubkey == payout_spk)
+                .map(|o| o.value)
+                .unwrap_or(0)
+        };
+        let cet = accepted_contract
+            .dlc_transactions
+            .cets
+            .iter()
+            .max_by_key(|x| get_payout(x))
+            .unwrap();
+        let cet_fee = accepted_contract.dlc_transactions.get_fund_output().value
+            - cet.output.iter().map(|x| x.value).sum::<u64>();
+        let blockchain = Rc::new(mocks::mock_blockchain::MockBlockchain::new());
+        let wallet = Rc::new(mocks::mock_wallet::MockWallet::new(&blockchain, &[]));
+        let fee_rate = cet_fee / cet.vsize() as u64 + 10;
+
+        let child = mocks::dlc_manager::contract_updater::create_cpfp_transaction(
+            cet, cet_fee, payout_spk, fee_rate, &wallet,
+        )
+        .expect("to be able to create the CPFP transaction");
+
+        assert_eq!(cet.txid(), child.input[0].previous_output.txid);
+        let child_fee = get_payout(cet) - child.output[0].value;
+        assert!(
+            cet_fee + child_fee >= fee_rate * (cet.vsize() + child.vsize()) as u64,
+            "the CET and child transaction to pay the target fee rate"
+        );
+
+        mocks::dlc_manager::contract_updater::create_cpfp_transaction(
+            cet, cet_fee, payout_spk, 0, &wallet,
+        )
+        .expect_err("not to bump a CET already paying the target fee rate");
     }
 }
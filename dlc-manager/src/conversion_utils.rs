@@ -10,7 +10,7 @@ use crate::payout_curve::{
     PolynomialPayoutCurvePiece, RoundingInterval, RoundingIntervals,
 };
 use bitcoin::{consensus::encode::Decodable, OutPoint, Transaction};
-use dlc::{EnumerationPayout, Payout, TxInputInfo};
+use dlc::{EnumerationPayout, FeeSplit, Payout, TxInputInfo};
 use dlc_messages::oracle_msgs::{
     MultiOracleInfo, OracleInfo as SerOracleInfo, OracleParams, SingleOracleInfo,
 };
@@ -97,6 +97,43 @@ pub fn get_tx_input_infos(
     Ok((inputs, input_amount))
 }
 
+/// Returns the fee split of an offer, equal if not set, checking that it can
+/// be applied to the collaterals of the offer.
+pub(crate) fn get_fee_split(
+    fee_split: Option<FeeSplit>,
+    offer_collateral: u64,
+    total_collateral: u64,
+) -> Result<FeeSplit, Error> {
+    let fee_split = fee_split.unwrap_or_default();
+    if offer_collateral > total_collateral
+        || (fee_split == FeeSplit::Proportional && total_collateral == 0)
+    {
+        return Err(Error::InvalidParameters);
+    }
+    Ok(fee_split)
+}
+
+/// Returns the fee split field of an offer or accept message, which is left
+/// unset for equal splits so that these messages are unchanged.
+pub(crate) fn get_fee_split_field(fee_split: FeeSplit) -> Option<FeeSplit> {
+    match fee_split {
+        FeeSplit::Equal => None,
+        fee_split => Some(fee_split),
+    }
+}
+
+/// Checks that the fee split of an accept message is the one of the offer it
+/// accepts, so that both parties build the same transactions.
+pub(crate) fn check_accepted_fee_split(
+    offered_fee_split: FeeSplit,
+    accepted_fee_split: Option<FeeSplit>,
+) -> Result<(), Error> {
+    if accepted_fee_split.unwrap_or_default() != offered_fee_split {
+        return Err(Error::InvalidParameters);
+    }
+    Ok(())
+}
+
 pub(crate) fn get_contract_info_and_announcements(
     contract_info: &SerContractInfo,
 ) -> Result<Vec<ContractInfo>, Error> {
@@ -534,6 +571,24 @@ impl From<&OracleParams> for DifferenceParams {
 mod tests {
     use super::*;
 
+    #[test]
+    fn fee_split_validation() {
+        assert_eq!(FeeSplit::Equal, get_fee_split(None, 10, 30).unwrap());
+        assert_eq!(
+            FeeSplit::Proportional,
+            get_fee_split(Some(FeeSplit::Proportional), 10, 30).unwrap()
+        );
+        get_fee_split(Some(FeeSplit::OfferPaysAll), 40, 30)
+            .expect_err("an offer collateral above the total collateral");
+        get_fee_split(Some(FeeSplit::Proportional), 0, 0)
+            .expect_err("a proportional split without collateral");
+
+        check_accepted_fee_split(FeeSplit::Equal, None).unwrap();
+        check_accepted_fee_split(FeeSplit::OfferPaysAll, Some(FeeSplit::OfferPaysAll)).unwrap();
+        check_accepted_fee_split(FeeSplit::OfferPaysAll, None)
+            .expect_err("an accept message ignoring the fee split of the offer");
+    }
+
     #[test]
     fn payout_function_round_trip() {
         let payout_function = PayoutFunction {
@@ -1,4 +1,5 @@
 //! #Error
+use hex::DisplayHex;
 use std::fmt;
 
 /// An error code.
@@ -25,13 +26,131 @@ pub enum Error {
     DlcError(dlc::Error),
     /// An error occurred in the Secp library.
     SecpError(secp256k1_zkp::Error),
+    /// The operation was aborted through its cancellation token.
+    Cancelled,
+    /// A record with the same identifier already exists.
+    AlreadyExists(String),
+    /// A channel update offered by the counter party violates the
+    /// [`crate::channel::ChannelPolicy`] of the local party.
+    ChannelPolicyViolation(String),
+    /// An oracle announcement used in a contract is not valid.
+    InvalidAnnouncement(AnnouncementError),
+    /// An error that occurred while processing a contract or channel, along
+    /// with information about where it occurred.
+    WithContext(ErrorContext, Box<Error>),
+}
+
+/// The reason for which an oracle announcement was deemed invalid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnnouncementError {
+    /// The announcement signature is not valid for the oracle public key.
+    InvalidSignature,
+    /// The number of nonces does not match the number of outcome digits of
+    /// the event.
+    NonceCountMismatch {
+        /// The number of nonces required by the event descriptor.
+        expected: usize,
+        /// The number of nonces in the announcement.
+        actual: usize,
+    },
+    /// The event does not mature between the CET locktime and the refund
+    /// locktime of the contract.
+    MaturityMismatch {
+        /// The maturity of the event.
+        event_maturity: u32,
+        /// The CET locktime of the contract.
+        cet_locktime: u32,
+        /// The refund locktime of the contract.
+        refund_locktime: u32,
+    },
+}
+
+impl fmt::Display for AnnouncementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AnnouncementError::InvalidSignature => write!(f, "invalid announcement signature"),
+            AnnouncementError::NonceCountMismatch { expected, actual } => write!(
+                f,
+                "expected {} nonces but announcement has {}",
+                expected, actual
+            ),
+            AnnouncementError::MaturityMismatch {
+                event_maturity,
+                cet_locktime,
+                refund_locktime,
+            } => write!(
+                f,
+                "event maturity {} is not between CET locktime {} and refund locktime {}",
+                event_maturity, cet_locktime, refund_locktime
+            ),
+        }
+    }
+}
+
+/// Information about the operation during which an error occurred.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// The id of the contract or channel that was being processed, if any.
+    pub id: Option<[u8; 32]>,
+    /// The protocol step that failed, e.g. "verifying refund signature".
+    pub step: &'static str,
+}
+
+impl Error {
+    /// Returns the error without any of the context attached to it, to be used
+    /// when matching on the kind of error that occurred.
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Error::WithContext(_, e) => e.root_cause(),
+            e => e,
+        }
+    }
+
+    /// Returns the contexts attached to the error, outermost first.
+    pub fn contexts(&self) -> Vec<&ErrorContext> {
+        let mut res = Vec::new();
+        let mut error = self;
+        while let Error::WithContext(context, e) = error {
+            res.push(context);
+            error = e;
+        }
+        res
+    }
+}
+
+/// Methods attaching an [`ErrorContext`] to the error of a [`Result`],
+/// converting it to an [`Error`] if required.
+pub trait ResultExt<T> {
+    /// Attaches the given protocol step to the error.
+    fn step(self, step: &'static str) -> Result<T, Error>;
+    /// Attaches the id of the contract or channel being processed and the
+    /// given protocol step to the error.
+    fn context(self, id: &[u8; 32], step: &'static str) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for Result<T, E> {
+    fn step(self, step: &'static str) -> Result<T, Error> {
+        self.map_err(|e| Error::WithContext(ErrorContext { id: None, step }, Box::new(e.into())))
+    }
+
+    fn context(self, id: &[u8; 32], step: &'static str) -> Result<T, Error> {
+        self.map_err(|e| {
+            Error::WithContext(
+                ErrorContext {
+                    id: Some(*id),
+                    step,
+                },
+                Box::new(e.into()),
+            )
+        })
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::Conversion(_) => write!(f, "Conversion error"),
-            Error::IOError(_) => write!(f, "IO error"),
+            Error::IOError(ref e) => write!(f, "IO error {}", e),
             Error::InvalidState(ref s) => write!(f, "Invalid state: {}", s),
             Error::InvalidParameters(ref s) => write!(f, "Invalid parameters were provided: {}", s),
             Error::WalletError(ref e) => write!(f, "Wallet error {}", e),
@@ -39,7 +158,21 @@ impl fmt::Display for Error {
             Error::StorageError(ref s) => write!(f, "Storage error {}", s),
             Error::DlcError(ref e) => write!(f, "Dlc error {}", e),
             Error::OracleError(ref s) => write!(f, "Oracle error {}", s),
-            Error::SecpError(_) => write!(f, "Secp error"),
+            Error::SecpError(ref e) => write!(f, "Secp error {}", e),
+            Error::Cancelled => write!(f, "Operation was cancelled"),
+            Error::AlreadyExists(ref s) => write!(f, "Already exists: {}", s),
+            Error::ChannelPolicyViolation(ref s) => write!(f, "Channel policy violation: {}", s),
+            Error::InvalidAnnouncement(ref e) => write!(f, "Invalid announcement: {}", e),
+            Error::WithContext(ref context, ref e) => match context.id {
+                Some(id) => write!(
+                    f,
+                    "Error {} for {}: {}",
+                    context.step,
+                    id.to_lower_hex_string(),
+                    e
+                ),
+                None => write!(f, "Error {}: {}", context.step, e),
+            },
         }
     }
 }
@@ -88,6 +221,39 @@ impl std::error::Error for Error {
             Error::OracleError(_) => None,
             Error::DlcError(e) => Some(e),
             Error::SecpError(e) => Some(e),
+            Error::Cancelled => None,
+            Error::AlreadyExists(_) => None,
+            Error::ChannelPolicyViolation(_) => None,
+            Error::InvalidAnnouncement(_) => None,
+            Error::WithContext(_, e) => Some(e.as_ref()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_is_included_in_display() {
+        let res: Result<(), _> = Err(secp256k1_zkp::UpstreamError::IncorrectSignature);
+        let error = res
+            .context(&[1u8; 32], "verifying refund signature")
+            .step("accepting contract")
+            .unwrap_err();
+
+        assert_eq!(
+            format!(
+                "Error accepting contract: Error verifying refund signature for {}: Secp error {}",
+                "01".repeat(32),
+                secp256k1_zkp::Error::Upstream(secp256k1_zkp::UpstreamError::IncorrectSignature)
+            ),
+            error.to_string()
+        );
+        assert!(matches!(error.root_cause(), Error::SecpError(_)));
+        assert_eq!(
+            vec!["accepting contract", "verifying refund signature"],
+            error.contexts().iter().map(|c| c.step).collect::<Vec<_>>()
+        );
+    }
+}
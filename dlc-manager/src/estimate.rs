@@ -0,0 +1,453 @@
+//! #Estimate
+//!
+//! Estimation of the costs of contract and channel operations, enabling
+//! applications to display them or to throttle expensive operations before
+//! performing them.
+
+use crate::channel::signed_channel::SignedChannel;
+use crate::contract::contract_info::ContractInfo;
+use crate::contract::contract_input::ContractInput;
+use crate::error::Error;
+use crate::ChannelId;
+use dlc_messages::contract_msgs::ContractDescriptor as SerContractDescriptor;
+use dlc_messages::segmentation::MAX_SEGMENTED_MESSAGE_SIZE;
+use lightning::util::ser::Writeable;
+use std::time::Duration;
+
+/// Serialized size of an ECDSA adaptor signature.
+const ADAPTOR_SIGNATURE_SIZE: usize = 162;
+
+/// Approximate serialized size of a P2WPKH funding input, including a previous
+/// transaction with one input and two outputs.
+const FUNDING_INPUT_SIZE: usize = 244;
+
+/// Serialized size of the witness of a P2WPKH funding input.
+const FUNDING_WITNESS_SIZE: usize = dlc::P2WPKH_WITNESS_SIZE + 1;
+
+/// Approximate size of the offer message, excluding the contract information
+/// and funding inputs.
+const OFFER_BASE_SIZE: usize = 215;
+
+/// Approximate size of the accept message, excluding the funding inputs and
+/// adaptor signatures.
+const ACCEPT_BASE_SIZE: usize = 215;
+
+/// Approximate size of the sign message, excluding the funding witnesses and
+/// adaptor signatures.
+const SIGN_BASE_SIZE: usize = 100;
+
+/// Approximate size of the base points and parameters that channel
+/// establishment messages contain in addition to contract ones.
+const CHANNEL_ESTABLISH_EXTRA_SIZE: usize = 270;
+
+/// Approximate size of the settle offer, accept, confirm and finalize messages,
+/// excluding adaptor signatures.
+const SETTLE_BASE_SIZE: usize = 274;
+
+/// Approximate size of the renew offer, accept, confirm, finalize and revoke
+/// messages, excluding the contract information and adaptor signatures.
+const RENEW_BASE_SIZE: usize = 450;
+
+/// Approximate size of a collaborative close offer message.
+const COLLABORATIVE_CLOSE_OFFER_SIZE: usize = 106;
+
+/// Overhead of the serialization of a contract information beyond its
+/// descriptor and oracle announcements.
+const CONTRACT_INFO_OVERHEAD_SIZE: usize = 16;
+
+/// Approximate time taken on a single core to create or verify an ECDSA
+/// adaptor signature, used to estimate the time required to sign a contract.
+pub const ADAPTOR_SIGNATURE_TIME: Duration = Duration::from_micros(200);
+
+/// An operation whose costs can be estimated.
+#[derive(Clone, Copy, Debug)]
+pub enum Operation<'a> {
+    /// Offering a contract.
+    OfferContract(&'a ContractInput),
+    /// Offering a channel.
+    OfferChannel(&'a ContractInput),
+    /// Settling the channel with the given id.
+    SettleChannel(&'a ChannelId),
+    /// Renewing the channel with the given id with a new contract.
+    RenewChannel(&'a ChannelId, &'a ContractInput),
+    /// Collaboratively closing the channel with the given id.
+    CollaborativeCloseChannel(&'a ChannelId),
+    /// Unilaterally closing the channel with the given id.
+    ForceCloseChannel(&'a ChannelId),
+}
+
+/// The expected costs of an operation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OperationEstimate {
+    /// The fee, in satoshis, paid by the local party for the transactions
+    /// created by the operation, assuming they end up being broadcast and that
+    /// funding is done using a single P2WPKH input.
+    pub on_chain_fee: u64,
+    /// The total size, in bytes, of the messages exchanged by both parties.
+    pub message_size: usize,
+    /// An upper bound of the size, in bytes, of the largest single message
+    /// exchanged, which determines whether it needs to be split into segments
+    /// to be sent (see [`dlc_messages::segmentation::get_nb_segments`]).
+    pub max_message_size: usize,
+    /// The number of adaptor signatures each party needs to create and to
+    /// verify.
+    pub nb_adaptor_signatures: usize,
+    /// The number of CETs each party needs to create.
+    pub nb_cets: usize,
+}
+
+/// The estimated serialized sizes, in bytes, of the messages exchanged to
+/// establish a contract, assuming each party funds it using a single P2WPKH
+/// input.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EstablishMessageSizes {
+    /// The size of the offer message.
+    pub offer: usize,
+    /// The size of the accept message.
+    pub accept: usize,
+    /// The size of the sign message.
+    pub sign: usize,
+}
+
+impl EstablishMessageSizes {
+    /// Returns the total size of the messages.
+    pub fn total(&self) -> usize {
+        self.offer + self.accept + self.sign
+    }
+
+    /// Returns the size of the largest message.
+    pub fn max(&self) -> usize {
+        self.offer.max(self.accept).max(self.sign)
+    }
+
+    /// Returns an error if any of the messages is too large to be sent, even
+    /// when split into segments.
+    pub fn check_sendable(&self) -> Result<(), Error> {
+        if self.max() > MAX_SEGMENTED_MESSAGE_SIZE {
+            return Err(Error::InvalidParameters(format!(
+                "Contract establishment would require a message of about {} bytes, exceeding \
+                 the maximum of {} bytes.",
+                self.max(),
+                MAX_SEGMENTED_MESSAGE_SIZE
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The expected costs of establishing a contract, assuming each party funds it
+/// using a single P2WPKH input.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContractEstimate {
+    /// The virtual size, in bytes, of the funding transaction.
+    pub funding_tx_vsize: usize,
+    /// The fee, in satoshis, paid by the offer party for the funding
+    /// transaction and the CET or refund transaction.
+    pub offer_fee: u64,
+    /// The fee, in satoshis, paid by the accept party for the funding
+    /// transaction and the CET or refund transaction.
+    pub accept_fee: u64,
+    /// The number of CETs of the contract.
+    pub nb_cets: usize,
+    /// The number of adaptor signatures each party needs to create and to
+    /// verify.
+    pub nb_adaptor_signatures: usize,
+    /// The sizes of the messages exchanged to establish the contract.
+    pub message_sizes: EstablishMessageSizes,
+    /// The expected time each party spends creating and verifying adaptor
+    /// signatures on a single core (see [`ADAPTOR_SIGNATURE_TIME`]).
+    pub signing_time: Duration,
+}
+
+/// Returns the expected costs of establishing a contract with the given
+/// contract information.
+pub fn estimate_contract(
+    fee_rate_per_vb: u64,
+    total_collateral: u64,
+    contract_infos: &[ContractInfo],
+) -> Result<ContractEstimate, Error> {
+    let (fund_fee, cet_fee) = dlc::estimate_party_fees(fee_rate_per_vb, 1)?;
+    let funding_tx_weight = dlc::estimate_fund_tx_weight(1, 1)?;
+    let (nb_adaptor_signatures, nb_cets) = get_signing_workload(total_collateral, contract_infos)?;
+    let signing_time = u32::try_from(2 * nb_adaptor_signatures)
+        .ok()
+        .and_then(|n| ADAPTOR_SIGNATURE_TIME.checked_mul(n))
+        .unwrap_or(Duration::MAX);
+
+    Ok(ContractEstimate {
+        funding_tx_vsize: (funding_tx_weight + 3) / 4,
+        offer_fee: fund_fee + cet_fee,
+        accept_fee: fund_fee + cet_fee,
+        nb_cets,
+        nb_adaptor_signatures,
+        message_sizes: get_establish_message_sizes(contract_infos, nb_adaptor_signatures),
+        signing_time,
+    })
+}
+
+/// Returns the estimated sizes of the offer, accept and sign messages of a
+/// contract with the given contract information, without computing any
+/// adaptor signature.
+pub fn estimate_establish_message_sizes(
+    total_collateral: u64,
+    contract_infos: &[ContractInfo],
+) -> Result<EstablishMessageSizes, Error> {
+    let (nb_adaptor_signatures, _) = get_signing_workload(total_collateral, contract_infos)?;
+    Ok(get_establish_message_sizes(
+        contract_infos,
+        nb_adaptor_signatures,
+    ))
+}
+
+/// Returns the estimated costs of offering a contract with the given contract
+/// information.
+pub fn estimate_contract_offer(
+    fee_rate_per_vb: u64,
+    total_collateral: u64,
+    contract_infos: &[ContractInfo],
+) -> Result<OperationEstimate, Error> {
+    let (fund_fee, cet_fee) = dlc::estimate_party_fees(fee_rate_per_vb, 1)?;
+    let (nb_adaptor_signatures, nb_cets) = get_signing_workload(total_collateral, contract_infos)?;
+    let message_sizes = get_establish_message_sizes(contract_infos, nb_adaptor_signatures);
+
+    Ok(OperationEstimate {
+        on_chain_fee: fund_fee + cet_fee,
+        message_size: message_sizes.total(),
+        max_message_size: message_sizes.max(),
+        nb_adaptor_signatures,
+        nb_cets,
+    })
+}
+
+/// Returns the estimated costs of offering a channel with the given contract
+/// information.
+pub fn estimate_channel_offer(
+    fee_rate_per_vb: u64,
+    total_collateral: u64,
+    contract_infos: &[ContractInfo],
+) -> Result<OperationEstimate, Error> {
+    let mut estimate = estimate_contract_offer(fee_rate_per_vb, total_collateral, contract_infos)?;
+    // The buffer transaction requires an additional adaptor signature.
+    estimate.on_chain_fee += dlc::channel::get_channel_extra_fee(fee_rate_per_vb)?;
+    estimate.nb_adaptor_signatures += 1;
+    estimate.message_size += CHANNEL_ESTABLISH_EXTRA_SIZE + 2 * ADAPTOR_SIGNATURE_SIZE;
+    estimate.max_message_size += CHANNEL_ESTABLISH_EXTRA_SIZE + ADAPTOR_SIGNATURE_SIZE;
+    Ok(estimate)
+}
+
+/// Returns the estimated costs of settling the given channel.
+pub fn estimate_settle(signed_channel: &SignedChannel) -> Result<OperationEstimate, Error> {
+    let settle_fee = dlc::channel::get_settle_transaction_fee(signed_channel.fee_rate_per_vb)?;
+
+    Ok(OperationEstimate {
+        on_chain_fee: settle_fee / 2,
+        message_size: SETTLE_BASE_SIZE + 2 * ADAPTOR_SIGNATURE_SIZE,
+        max_message_size: SETTLE_BASE_SIZE + ADAPTOR_SIGNATURE_SIZE,
+        nb_adaptor_signatures: 1,
+        nb_cets: 0,
+    })
+}
+
+/// Returns the estimated costs of renewing the given channel with the given
+/// contract information.
+pub fn estimate_renew(
+    signed_channel: &SignedChannel,
+    total_collateral: u64,
+    contract_infos: &[ContractInfo],
+) -> Result<OperationEstimate, Error> {
+    let fee_rate_per_vb = signed_channel.fee_rate_per_vb;
+    let (_, cet_fee) = dlc::estimate_party_fees(fee_rate_per_vb, 0)?;
+    let extra_fee = dlc::channel::get_channel_extra_fee(fee_rate_per_vb)?;
+    let (nb_cet_adaptor_signatures, nb_cets) =
+        get_signing_workload(total_collateral, contract_infos)?;
+    // The buffer transaction requires an additional adaptor signature.
+    let nb_adaptor_signatures = nb_cet_adaptor_signatures + 1;
+    let contract_infos_size = get_contract_infos_size(contract_infos);
+
+    Ok(OperationEstimate {
+        on_chain_fee: cet_fee + extra_fee,
+        message_size: RENEW_BASE_SIZE
+            + contract_infos_size
+            + 2 * nb_adaptor_signatures * ADAPTOR_SIGNATURE_SIZE,
+        max_message_size: RENEW_BASE_SIZE
+            + contract_infos_size.max(nb_adaptor_signatures * ADAPTOR_SIGNATURE_SIZE),
+        nb_adaptor_signatures,
+        nb_cets,
+    })
+}
+
+/// Returns the estimated costs of closing the given channel, either
+/// collaboratively or unilaterally. In both cases the fees are paid using the
+/// funds reserved in the fund output at channel establishment.
+pub fn estimate_close(
+    signed_channel: &SignedChannel,
+    collaborative: bool,
+) -> Result<OperationEstimate, Error> {
+    let total_collateral =
+        signed_channel.own_params.collateral + signed_channel.counter_params.collateral;
+    let fund_output_value = signed_channel
+        .fund_tx
+        .output
+        .get(signed_channel.fund_output_index)
+        .ok_or_else(|| Error::InvalidState("Could not find fund output.".to_string()))?
+        .value;
+
+    let message_size = if collaborative {
+        COLLABORATIVE_CLOSE_OFFER_SIZE
+    } else {
+        0
+    };
+
+    Ok(OperationEstimate {
+        on_chain_fee: fund_output_value.saturating_sub(total_collateral) / 2,
+        message_size,
+        max_message_size: message_size,
+        nb_adaptor_signatures: 0,
+        nb_cets: 0,
+    })
+}
+
+fn get_signing_workload(
+    total_collateral: u64,
+    contract_infos: &[ContractInfo],
+) -> Result<(usize, usize), Error> {
+    let mut nb_adaptor_signatures = 0;
+    let mut nb_cets = 0;
+    for contract_info in contract_infos {
+        nb_adaptor_signatures += contract_info.get_adaptor_signature_count(total_collateral)?;
+        nb_cets += contract_info.get_payouts(total_collateral)?.len();
+    }
+    Ok((nb_adaptor_signatures, nb_cets))
+}
+
+fn get_establish_message_sizes(
+    contract_infos: &[ContractInfo],
+    nb_adaptor_signatures: usize,
+) -> EstablishMessageSizes {
+    let adaptor_signatures_size = nb_adaptor_signatures * ADAPTOR_SIGNATURE_SIZE;
+    EstablishMessageSizes {
+        offer: OFFER_BASE_SIZE + get_contract_infos_size(contract_infos) + FUNDING_INPUT_SIZE,
+        accept: ACCEPT_BASE_SIZE + FUNDING_INPUT_SIZE + adaptor_signatures_size,
+        sign: SIGN_BASE_SIZE + FUNDING_WITNESS_SIZE + adaptor_signatures_size,
+    }
+}
+
+fn get_contract_infos_size(contract_infos: &[ContractInfo]) -> usize {
+    contract_infos
+        .iter()
+        .map(|c| {
+            SerContractDescriptor::from(&c.contract_descriptor).serialized_length()
+                + c.oracle_announcements
+                    .iter()
+                    .map(|a| a.serialized_length())
+                    .sum::<usize>()
+                + CONTRACT_INFO_OVERHEAD_SIZE
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conversion_utils::get_contract_info_and_announcements;
+
+    fn get_offer_contract_infos() -> (dlc_messages::OfferDlc, Vec<ContractInfo>) {
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let contract_infos = get_contract_info_and_announcements(&offer.contract_info).unwrap();
+        (offer, contract_infos)
+    }
+
+    #[test]
+    fn contract_offer_estimate_test() {
+        let (offer, contract_infos) = get_offer_contract_infos();
+        let total_collateral = offer.contract_info.get_total_collateral();
+
+        let estimate =
+            estimate_contract_offer(offer.fee_rate_per_vb, total_collateral, &contract_infos)
+                .unwrap();
+
+        let expected_adaptor_signatures = contract_infos
+            .iter()
+            .map(|c| c.get_adaptor_signature_count(total_collateral).unwrap())
+            .sum::<usize>();
+        assert_eq!(expected_adaptor_signatures, estimate.nb_adaptor_signatures);
+        assert!(estimate.nb_adaptor_signatures >= estimate.nb_cets);
+        assert!(estimate.message_size > offer.serialized_length());
+        assert!(estimate.on_chain_fee > 0);
+    }
+
+    #[test]
+    fn channel_offer_estimate_exceeds_contract_offer_estimate() {
+        let (offer, contract_infos) = get_offer_contract_infos();
+        let total_collateral = offer.contract_info.get_total_collateral();
+
+        let contract_estimate =
+            estimate_contract_offer(offer.fee_rate_per_vb, total_collateral, &contract_infos)
+                .unwrap();
+        let channel_estimate =
+            estimate_channel_offer(offer.fee_rate_per_vb, total_collateral, &contract_infos)
+                .unwrap();
+
+        assert_eq!(
+            contract_estimate.nb_adaptor_signatures + 1,
+            channel_estimate.nb_adaptor_signatures
+        );
+        assert_eq!(contract_estimate.nb_cets, channel_estimate.nb_cets);
+        assert!(channel_estimate.on_chain_fee > contract_estimate.on_chain_fee);
+        assert!(channel_estimate.message_size > contract_estimate.message_size);
+    }
+
+    #[test]
+    fn establish_message_sizes_match_contract_offer_estimate_test() {
+        let (offer, contract_infos) = get_offer_contract_infos();
+        let total_collateral = offer.contract_info.get_total_collateral();
+
+        let estimate =
+            estimate_contract_offer(offer.fee_rate_per_vb, total_collateral, &contract_infos)
+                .unwrap();
+        let sizes = estimate_establish_message_sizes(total_collateral, &contract_infos).unwrap();
+
+        assert_eq!(estimate.message_size, sizes.total());
+        assert_eq!(estimate.max_message_size, sizes.max());
+        assert!(sizes.accept > estimate.nb_adaptor_signatures * ADAPTOR_SIGNATURE_SIZE);
+        assert!(sizes.sign > estimate.nb_adaptor_signatures * ADAPTOR_SIGNATURE_SIZE);
+        sizes.check_sendable().unwrap();
+    }
+
+    #[test]
+    fn contract_estimate_matches_contract_offer_estimate_test() {
+        let (offer, contract_infos) = get_offer_contract_infos();
+        let total_collateral = offer.contract_info.get_total_collateral();
+
+        let offer_estimate =
+            estimate_contract_offer(offer.fee_rate_per_vb, total_collateral, &contract_infos)
+                .unwrap();
+        let estimate =
+            estimate_contract(offer.fee_rate_per_vb, total_collateral, &contract_infos).unwrap();
+
+        assert_eq!(offer_estimate.on_chain_fee, estimate.offer_fee);
+        assert_eq!(estimate.offer_fee, estimate.accept_fee);
+        assert_eq!(offer_estimate.nb_cets, estimate.nb_cets);
+        assert_eq!(offer_estimate.message_size, estimate.message_sizes.total());
+        assert_eq!(
+            ADAPTOR_SIGNATURE_TIME * 2 * estimate.nb_adaptor_signatures as u32,
+            estimate.signing_time
+        );
+        let (fund_fee, _) = dlc::estimate_party_fees(offer.fee_rate_per_vb, 1).unwrap();
+        assert!(
+            (estimate.funding_tx_vsize as u64 * offer.fee_rate_per_vb).abs_diff(2 * fund_fee)
+                <= offer.fee_rate_per_vb
+        );
+    }
+
+    #[test]
+    fn oversized_establish_messages_are_not_sendable_test() {
+        let sizes = EstablishMessageSizes {
+            accept: MAX_SEGMENTED_MESSAGE_SIZE + 1,
+            ..Default::default()
+        };
+
+        assert!(sizes.check_sendable().is_err());
+    }
+}
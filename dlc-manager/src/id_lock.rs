@@ -0,0 +1,111 @@
+//! #Locks serializing the processing of updates to a given contract or
+//! channel.
+
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+use std::thread::{self, ThreadId};
+
+/// Set of locks keyed by the id of the contract or channel they protect. A
+/// thread holding the lock on an id can lock it again, so that an operation
+/// can call other operations on the same object.
+#[derive(Default)]
+pub(crate) struct IdLocks {
+    // Thread holding each locked id and number of guards it holds on it.
+    held: Mutex<HashMap<[u8; 32], (ThreadId, usize)>>,
+    released: Condvar,
+}
+
+impl IdLocks {
+    /// Blocks until the lock on the given id is available and acquires it. The
+    /// lock is released when the returned guard is dropped.
+    pub(crate) fn lock(&self, id: [u8; 32]) -> IdLockGuard<'_> {
+        let current = thread::current().id();
+        let mut held = self.held.lock().unwrap();
+        loop {
+            match held.get_mut(&id) {
+                None => {
+                    held.insert(id, (current, 1));
+                    break;
+                }
+                Some((owner, count)) if *owner == current => {
+                    *count += 1;
+                    break;
+                }
+                Some(_) => held = self.released.wait(held).unwrap(),
+            }
+        }
+        IdLockGuard { locks: self, id }
+    }
+
+    /// Acquires the locks on all the given ids. Ids are locked in increasing
+    /// order so that two threads locking overlapping sets cannot deadlock.
+    pub(crate) fn lock_all(&self, ids: &[[u8; 32]]) -> Vec<IdLockGuard<'_>> {
+        let mut ids = ids.to_vec();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.into_iter().map(|id| self.lock(id)).collect()
+    }
+}
+
+/// Releases a lock acquired using [`IdLocks::lock`] when dropped.
+pub(crate) struct IdLockGuard<'a> {
+    locks: &'a IdLocks,
+    id: [u8; 32],
+}
+
+impl Drop for IdLockGuard<'_> {
+    fn drop(&mut self) {
+        let mut held = self.locks.held.lock().unwrap();
+        let released = match held.get_mut(&self.id) {
+            Some((_, count)) => {
+                *count -= 1;
+                *count == 0
+            }
+            None => false,
+        };
+        if released {
+            held.remove(&self.id);
+            self.locks.released.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn lock_is_reentrant() {
+        let locks = IdLocks::default();
+        let _first = locks.lock([1; 32]);
+        let _second = locks.lock([1; 32]);
+        let _all = locks.lock_all(&[[1; 32], [2; 32]]);
+    }
+
+    #[test]
+    fn lock_blocks_other_threads_until_released() {
+        let locks = Arc::new(IdLocks::default());
+        let acquired = Arc::new(AtomicBool::new(false));
+        let guard = locks.lock([1; 32]);
+
+        let handle = {
+            let locks = locks.clone();
+            let acquired = acquired.clone();
+            thread::spawn(move || {
+                let _other = locks.lock([2; 32]);
+                let _guard = locks.lock([1; 32]);
+                acquired.store(true, Ordering::SeqCst);
+            })
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(!acquired.load(Ordering::SeqCst));
+        drop(guard);
+        handle.join().unwrap();
+        assert!(acquired.load(Ordering::SeqCst));
+        assert!(locks.held.lock().unwrap().is_empty());
+    }
+}
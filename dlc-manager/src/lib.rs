@@ -12,48 +12,96 @@
 #![deny(unused_imports)]
 #![deny(missing_docs)]
 
+#[cfg(feature = "async")]
 extern crate async_trait;
 extern crate bitcoin;
+extern crate chacha20poly1305;
 extern crate dlc;
 #[macro_use]
 extern crate dlc_messages;
 extern crate core;
 extern crate dlc_trie;
+#[cfg(feature = "async")]
+extern crate futures;
 extern crate lightning;
 extern crate log;
 #[cfg(feature = "fuzztarget")]
 extern crate rand_chacha;
 extern crate secp256k1_zkp;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+
+/// Enters a debug level span, created from the given arguments as with
+/// `tracing::debug_span`, until the end of the enclosing scope when the
+/// `tracing` feature is enabled. Expands to nothing otherwise.
+macro_rules! enter_span {
+    ($($args: tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = {
+            use hex::DisplayHex as _;
+            tracing::debug_span!($($args)*).entered()
+        };
+    };
+}
 
+#[cfg(feature = "async")]
+pub mod async_oracle;
+#[cfg(feature = "async")]
+pub mod async_storage;
+pub mod cancellation;
 pub mod chain_monitor;
+pub mod chain_watcher;
 pub mod channel;
 pub mod channel_updater;
+pub mod coin_selection;
 pub mod contract;
 pub mod contract_updater;
 mod conversion_utils;
 pub mod error;
+pub mod estimate;
+mod id_lock;
 pub mod manager;
+#[cfg(feature = "memory-storage")]
+pub mod memory_storage;
+pub mod netting;
+pub mod offer_builder;
+pub mod option_payoffs;
 pub mod payout_curve;
+pub mod product_catalog;
+pub mod progress;
+#[cfg(feature = "serde")]
+mod serde_utils;
+pub mod state_history;
+pub mod storage_prefix;
+#[cfg(feature = "taproot")]
+pub mod taproot_updater;
 mod utils;
+pub mod watch_only;
 
 use bitcoin::psbt::PartiallySignedTransaction;
 use bitcoin::{Address, Block, OutPoint, ScriptBuf, Transaction, TxOut, Txid};
-use chain_monitor::ChainMonitor;
+use chain_monitor::{ChainMonitor, HeaderCache};
 use channel::offered_channel::OfferedChannel;
+use channel::punishment::PunishmentData;
 use channel::signed_channel::{SignedChannel, SignedChannelStateType};
-use channel::Channel;
+use channel::watchtower::{JusticeBlob, LOCATOR_LEN};
+use channel::{Channel, ChannelEvent};
 use contract::PreClosedContract;
-use contract::{offered_contract::OfferedContract, signed_contract::SignedContract, Contract};
+use contract::{
+    offered_contract::OfferedContract, signed_contract::SignedContract, Contract, ContractEvent,
+};
 use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
 use dlc_messages::ser_impls::{read_address, write_address};
 use error::Error;
 use lightning::ln::msgs::DecodeError;
 use lightning::util::ser::{Readable, Writeable, Writer};
+use product_catalog::ProductDefinition;
 use secp256k1_zkp::{PublicKey, SecretKey, Signing};
 use secp256k1_zkp::{Secp256k1, XOnlyPublicKey};
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::sync::RwLock;
+use std::time::Duration;
 
 /// Type alias for a contract id.
 pub type ContractId = [u8; 32];
@@ -152,13 +200,24 @@ pub trait Wallet {
     fn get_new_address(&self) -> Result<Address, Error>;
     /// Returns a new (unused) change address.
     fn get_new_change_address(&self) -> Result<Address, Error>;
-    /// Get a set of UTXOs to fund the given amount.
+    /// Get a set of UTXOs to fund the given amount. If `lock_utxos` is true,
+    /// the returned UTXOs are reserved as with [`Wallet::reserve_utxos`].
     fn get_utxos_for_amount(
         &self,
         amount: u64,
         fee_rate: u64,
         lock_utxos: bool,
     ) -> Result<Vec<Utxo>, Error>;
+    /// Returns the UTXOs of the wallet that are not reserved, among which a
+    /// [`coin_selection::CoinSelector`] set on the
+    /// [`Manager`](manager::Manager) selects the ones funding contracts. The
+    /// default implementation returns an error, so that wallets that cannot
+    /// list their UTXOs can only be used with their own coin selection.
+    fn list_utxos(&self) -> Result<Vec<Utxo>, Error> {
+        Err(Error::InvalidState(
+            "The wallet does not support listing its UTXOs".to_string(),
+        ))
+    }
     /// Import the provided address.
     fn import_address(&self, address: &Address) -> Result<(), Error>;
     /// Signs a transaction input
@@ -167,10 +226,39 @@ pub trait Wallet {
         psbt: &mut PartiallySignedTransaction,
         input_index: usize,
     ) -> Result<(), Error>;
-    /// Unlock reserved utxo
-    fn unreserve_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error>;
+    /// Signs and finalizes the inputs of the given PSBT at the given indexes,
+    /// which are the inputs funded by the wallet. Each input of the PSBT is
+    /// provided with its previous transaction and output. The unsigned
+    /// transaction and the other inputs must be left unchanged. The default
+    /// implementation signs each input using [`Wallet::sign_psbt_input`];
+    /// wallets relying on an external signer (e.g. a hardware wallet) can
+    /// override it to sign all the inputs in a single round trip.
+    fn sign_psbt(
+        &self,
+        psbt: &mut PartiallySignedTransaction,
+        input_indexes: &[usize],
+    ) -> Result<(), Error> {
+        for input_index in input_indexes {
+            self.sign_psbt_input(psbt, *input_index)?;
+        }
+        Ok(())
+    }
+    /// Reserves the given UTXOs, so that they are not returned by
+    /// [`Wallet::get_utxos_for_amount`] until they are released.
+    fn reserve_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error>;
+    /// Releases the given reserved UTXOs, making them available to fund other
+    /// contracts.
+    fn release_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error>;
 }
 
+/// Minimum relay fee rate, in satoshis per virtual byte, used by default by
+/// Bitcoin Core nodes.
+pub const DEFAULT_MIN_RELAY_FEE_RATE: u64 = 1;
+
+/// Incremental relay fee rate, in satoshis per virtual byte, used by default by
+/// Bitcoin Core nodes.
+pub const DEFAULT_INCREMENTAL_RELAY_FEE_RATE: u64 = 1;
+
 /// Blockchain trait provides access to the bitcoin blockchain.
 pub trait Blockchain {
     /// Broadcast the given transaction to the bitcoin network.
@@ -185,6 +273,247 @@ pub trait Blockchain {
     fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, Error>;
     /// Get the number of confirmation for the transaction with given id.
     fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, Error>;
+    /// Returns the id of the transaction spending the given output, or `None`
+    /// if the output is unspent. Providers that cannot look up the spender of
+    /// an output return an error, which is the default.
+    fn get_output_spender(&self, _outpoint: &OutPoint) -> Result<Option<Txid>, Error> {
+        Err(Error::BlockchainError(
+            "Looking up the spender of an output is not supported".to_string(),
+        ))
+    }
+    /// Returns the minimum fee rate, in satoshis per virtual byte, under which
+    /// transactions are not relayed by the network.
+    fn get_min_relay_fee_rate(&self) -> Result<u64, Error> {
+        Ok(DEFAULT_MIN_RELAY_FEE_RATE)
+    }
+    /// Returns the minimum increase, in satoshis per virtual byte, of the fee
+    /// rate of a transaction replacing another one in the mempool.
+    fn get_incremental_relay_fee_rate(&self) -> Result<u64, Error> {
+        Ok(DEFAULT_INCREMENTAL_RELAY_FEE_RATE)
+    }
+}
+
+/// A set of records to be written to a [`Storage`] in a single atomic
+/// operation, so that a crash cannot leave the store with only part of a state
+/// transition persisted.
+#[derive(Clone, Default)]
+pub struct StorageBatch {
+    /// Contracts to create, with the same semantic as
+    /// [`Storage::create_contract`]. The whole batch fails if one of them
+    /// already exists.
+    pub new_contracts: Vec<OfferedContract>,
+    /// Contracts to create or update, with the same semantic as
+    /// [`Storage::update_contract`].
+    pub contracts: Vec<Contract>,
+    /// Channels to create or update, with the same semantic as
+    /// [`Storage::upsert_channel`].
+    pub channels: Vec<Channel>,
+    /// Events to append to the log of the contract with the given temporary
+    /// id, with the same semantic as [`Storage::append_contract_event`].
+    pub contract_events: Vec<(ContractId, ContractEvent)>,
+    /// Events to append to the log of the channel with the given temporary
+    /// id, with the same semantic as [`Storage::append_channel_event`].
+    pub channel_events: Vec<(ChannelId, ChannelEvent)>,
+    /// The [`ChainMonitor`] to persist if any.
+    pub chain_monitor: Option<ChainMonitor>,
+}
+
+impl StorageBatch {
+    /// Creates an empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the given contract to be created to the batch.
+    pub fn with_new_contract(mut self, contract: OfferedContract) -> Self {
+        self.new_contracts.push(contract);
+        self
+    }
+
+    /// Adds the given contract to the batch.
+    pub fn with_contract(mut self, contract: Contract) -> Self {
+        self.contracts.push(contract);
+        self
+    }
+
+    /// Adds the given channel to the batch.
+    pub fn with_channel(mut self, channel: Channel) -> Self {
+        self.channels.push(channel);
+        self
+    }
+
+    /// Adds the given event to be appended to the log of the contract with the
+    /// given temporary id to the batch.
+    pub fn with_contract_event(mut self, temporary_id: ContractId, event: ContractEvent) -> Self {
+        self.contract_events.push((temporary_id, event));
+        self
+    }
+
+    /// Adds the given event to be appended to the log of the channel with the
+    /// given temporary id to the batch.
+    pub fn with_channel_event(mut self, temporary_id: ChannelId, event: ChannelEvent) -> Self {
+        self.channel_events.push((temporary_id, event));
+        self
+    }
+
+    /// Sets the chain monitor to be persisted with the batch.
+    pub fn with_chain_monitor(mut self, chain_monitor: ChainMonitor) -> Self {
+        self.chain_monitor = Some(chain_monitor);
+        self
+    }
+}
+
+/// A view of the contracts, channels and [`ChainMonitor`] of a [`Storage`]
+/// taken at a single point in time, as returned by [`Storage::read_snapshot`].
+/// Reading from a snapshot is not affected by writes made to the store after
+/// it was taken.
+#[derive(Clone, Default)]
+pub struct StorageSnapshot {
+    contracts: Vec<Contract>,
+    channels: Vec<Channel>,
+    chain_monitor: Option<ChainMonitor>,
+}
+
+impl StorageSnapshot {
+    /// Creates a snapshot from the given records. Implementations of
+    /// [`Storage::read_snapshot`] must ensure that they were all read at the
+    /// same point in time.
+    pub fn new(
+        contracts: Vec<Contract>,
+        channels: Vec<Channel>,
+        chain_monitor: Option<ChainMonitor>,
+    ) -> Self {
+        StorageSnapshot {
+            contracts,
+            channels,
+            chain_monitor,
+        }
+    }
+
+    /// Returns the contract with given id if found.
+    pub fn get_contract(&self, id: &ContractId) -> Option<&Contract> {
+        self.contracts.iter().find(|c| &c.get_id() == id)
+    }
+
+    /// Returns all the contracts.
+    pub fn get_contracts(&self) -> &[Contract] {
+        &self.contracts
+    }
+
+    /// Returns all the contracts entered with the node with the given public
+    /// key.
+    pub fn get_contracts_by_counterparty(&self, counter_party: &PublicKey) -> Vec<&Contract> {
+        self.contracts
+            .iter()
+            .filter(|c| &c.get_counter_party_id() == counter_party)
+            .collect()
+    }
+
+    /// Returns the set of contracts in offered state.
+    pub fn get_contract_offers(&self) -> Vec<&OfferedContract> {
+        self.contracts
+            .iter()
+            .filter_map(|c| match c {
+                Contract::Offered(o) => Some(o),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the set of contracts in signed state.
+    pub fn get_signed_contracts(&self) -> Vec<&SignedContract> {
+        self.contracts
+            .iter()
+            .filter_map(|c| match c {
+                Contract::Signed(s) => Some(s),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the set of confirmed contracts.
+    pub fn get_confirmed_contracts(&self) -> Vec<&SignedContract> {
+        self.contracts
+            .iter()
+            .filter_map(|c| match c {
+                Contract::Confirmed(s) => Some(s),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the set of contracts whose broadcasted cet has not been verified
+    /// to be confirmed on blockchain.
+    pub fn get_preclosed_contracts(&self) -> Vec<&PreClosedContract> {
+        self.contracts
+            .iter()
+            .filter_map(|c| match c {
+                Contract::PreClosed(p) => Some(p),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns all the channels.
+    pub fn get_channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// Returns the channel with given [`ChannelId`] if any.
+    pub fn get_channel(&self, channel_id: &ChannelId) -> Option<&Channel> {
+        self.channels.iter().find(|c| &c.get_id() == channel_id)
+    }
+
+    /// Returns all the channels opened with the node with the given public
+    /// key.
+    pub fn get_channels_by_counterparty(&self, counter_party: &PublicKey) -> Vec<&Channel> {
+        self.channels
+            .iter()
+            .filter(|c| &c.get_counter_party_id() == counter_party)
+            .collect()
+    }
+
+    /// Returns the channel whose current contract has the given id, if any.
+    pub fn get_channel_by_contract_id(&self, contract_id: &ContractId) -> Option<&Channel> {
+        self.channels
+            .iter()
+            .find(|c| c.get_contract_id().as_ref() == Some(contract_id))
+    }
+
+    /// Returns the set of [`SignedChannel`] in the snapshot. Returns only the
+    /// one with matching `channel_state` if set.
+    pub fn get_signed_channels(
+        &self,
+        channel_state: Option<SignedChannelStateType>,
+    ) -> Vec<&SignedChannel> {
+        self.channels
+            .iter()
+            .filter_map(|c| match c {
+                Channel::Signed(s) => Some(s),
+                _ => None,
+            })
+            .filter(|s| match &channel_state {
+                Some(state) => s.state.is_of_type(state),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Returns the set of channels in offer state.
+    pub fn get_offered_channels(&self) -> Vec<&OfferedChannel> {
+        self.channels
+            .iter()
+            .filter_map(|c| match c {
+                Channel::Offered(o) => Some(o),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the [`ChainMonitor`] if any.
+    pub fn get_chain_monitor(&self) -> Option<&ChainMonitor> {
+        self.chain_monitor.as_ref()
+    }
 }
 
 /// Storage trait provides functionalities to store and retrieve DLCs.
@@ -193,14 +522,73 @@ pub trait Storage {
     fn get_contract(&self, id: &ContractId) -> Result<Option<Contract>, Error>;
     /// Return all contracts
     fn get_contracts(&self) -> Result<Vec<Contract>, Error>;
-    /// Create a record for the given contract.
+    /// Returns all the contracts entered with the node with the given public
+    /// key. The default implementation filters the result of
+    /// [`Storage::get_contracts`] and should be overridden by implementations
+    /// able to index contracts by counter party.
+    fn get_contracts_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Contract>, Error> {
+        Ok(self
+            .get_contracts()?
+            .into_iter()
+            .filter(|c| &c.get_counter_party_id() == counter_party)
+            .collect())
+    }
+    /// Returns all the contracts whose metadata contains the given label. The
+    /// default implementation filters the result of [`Storage::get_contracts`].
+    fn get_contracts_by_label(&self, label: &str) -> Result<Vec<Contract>, Error> {
+        Ok(self
+            .get_contracts()?
+            .into_iter()
+            .filter(|c| c.get_metadata().map_or(false, |m| m.has_label(label)))
+            .collect())
+    }
+    /// Returns the contract with the given temporary id, the id it was offered
+    /// with, if found. As amending a contract keeps its temporary id, a
+    /// contract in [`Contract::Amended`] state is only returned if no other
+    /// contract has the same temporary id. The default implementation filters
+    /// the result of [`Storage::get_contracts`].
+    fn get_contract_by_temporary_id(
+        &self,
+        temporary_id: &ContractId,
+    ) -> Result<Option<Contract>, Error> {
+        let (amended, others): (Vec<_>, Vec<_>) = self
+            .get_contracts()?
+            .into_iter()
+            .filter(|c| &c.get_temporary_id() == temporary_id)
+            .partition(|c| matches!(c, Contract::Amended(_)));
+        Ok(others.into_iter().chain(amended).next())
+    }
+    /// Create a record for the given contract. Returns
+    /// [`Error::AlreadyExists`] without modifying the store if a contract with
+    /// the same id is already present.
     fn create_contract(&self, contract: &OfferedContract) -> Result<(), Error>;
+    /// Create a record for the given contract, overwriting any existing
+    /// contract with the same id.
+    fn replace_contract(&self, contract: &OfferedContract) -> Result<(), Error> {
+        self.update_contract(&Contract::Offered(contract.clone()))
+    }
     /// Delete the record for the contract with the given id.
     fn delete_contract(&self, id: &ContractId) -> Result<(), Error>;
     /// Update the given contract.
     fn update_contract(&self, contract: &Contract) -> Result<(), Error>;
     /// Returns the set of contracts in offered state.
     fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error>;
+    /// Returns the set of contracts whose offer expired before being accepted.
+    /// The default implementation filters the result of
+    /// [`Storage::get_contracts`].
+    fn get_expired_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        Ok(self
+            .get_contracts()?
+            .into_iter()
+            .filter_map(|c| match c {
+                Contract::Expired(o) => Some(o),
+                _ => None,
+            })
+            .collect())
+    }
     /// Returns the set of contracts in signed state.
     fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error>;
     /// Returns the set of confirmed contracts.
@@ -215,6 +603,50 @@ pub trait Storage {
     fn delete_channel(&self, channel_id: &ChannelId) -> Result<(), Error>;
     /// Returns the channel with given [`ChannelId`] if any.
     fn get_channel(&self, channel_id: &ChannelId) -> Result<Option<Channel>, Error>;
+    /// Returns all the channels opened with the node with the given public
+    /// key. The default implementation filters the result of
+    /// [`Storage::get_offered_channels`] and [`Storage::get_signed_channels`],
+    /// and should be overridden by implementations able to index channels by
+    /// counter party.
+    fn get_channels_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Channel>, Error> {
+        let mut res = self
+            .get_offered_channels()?
+            .into_iter()
+            .filter(|c| &c.counter_party == counter_party)
+            .map(Channel::Offered)
+            .collect::<Vec<_>>();
+        res.extend(
+            self.get_signed_channels(None)?
+                .into_iter()
+                .filter(|c| &c.counter_party == counter_party)
+                .map(Channel::Signed),
+        );
+        Ok(res)
+    }
+    /// Returns the channel whose current contract has the given id, if any.
+    /// The default implementation searches the offered and signed channels,
+    /// and should be overridden by implementations able to index channels by
+    /// contract id.
+    fn get_channel_by_contract_id(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<Channel>, Error> {
+        if let Some(channel) = self
+            .get_signed_channels(None)?
+            .into_iter()
+            .find(|c| c.get_contract_id().as_ref() == Some(contract_id))
+        {
+            return Ok(Some(Channel::Signed(channel)));
+        }
+        Ok(self
+            .get_offered_channels()?
+            .into_iter()
+            .find(|c| &c.offered_contract_id == contract_id)
+            .map(Channel::Offered))
+    }
     /// Returns the set of [`SignedChannel`] in the store. Returns only the one
     /// with matching `channel_state` if set.
     fn get_signed_channels(
@@ -227,6 +659,190 @@ pub trait Storage {
     fn persist_chain_monitor(&self, monitor: &ChainMonitor) -> Result<(), Error>;
     /// Returns the latest [`ChainMonitor`] in the store if any.
     fn get_chain_monitor(&self) -> Result<Option<ChainMonitor>, Error>;
+    /// Removes the closed, refunded and failed contracts that reached that
+    /// state more than `older_than` ago from the set of stored contracts,
+    /// returning the number of contracts that were pruned. Depending on the
+    /// implementation, pruned contracts can be archived or deleted. The default
+    /// implementation returns an error as pruning is not supported.
+    fn prune_closed_contracts(&self, _older_than: Duration) -> Result<usize, Error> {
+        Err(Error::StorageError(
+            "Pruning closed contracts is not supported by this storage".to_string(),
+        ))
+    }
+    /// Records that a message was received from the given peer at the given
+    /// time (in seconds since the unix epoch). The default implementation does
+    /// not record anything.
+    fn update_peer_last_seen(&self, _peer_id: &PublicKey, _timestamp: u64) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the last time (in seconds since the unix epoch) a message was
+    /// received from the given peer, if any was recorded. The default
+    /// implementation always returns `None`.
+    fn get_peer_last_seen(&self, _peer_id: &PublicKey) -> Result<Option<u64>, Error> {
+        Ok(None)
+    }
+    /// Records that the contract with the given temporary id was created from
+    /// an offer with the given hash (see [`OfferedContract::get_offer_hash`]).
+    /// The default implementation does not record anything.
+    fn upsert_offer_hash(
+        &self,
+        _offer_hash: &[u8; 32],
+        _temporary_id: &ContractId,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the temporary id of the contract last created from an offer with
+    /// the given hash, if any. The default implementation always returns
+    /// `None`.
+    fn get_contract_id_by_offer_hash(
+        &self,
+        _offer_hash: &[u8; 32],
+    ) -> Result<Option<ContractId>, Error> {
+        Ok(None)
+    }
+    /// Stores the data required to punish the broadcast of a revoked channel
+    /// state, replacing any previous data stored for the same channel and
+    /// update index. The default implementation does not record anything.
+    fn upsert_punishment_data(&self, _data: &PunishmentData) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the punishment data stored for the given channel and update
+    /// index, if any. The default implementation always returns `None`.
+    fn get_punishment_data(
+        &self,
+        _channel_id: &ChannelId,
+        _update_idx: u64,
+    ) -> Result<Option<PunishmentData>, Error> {
+        Ok(None)
+    }
+    /// Returns the punishment data of at most `count` of the most recently
+    /// revoked states of the given channel, most recent first. The default
+    /// implementation always returns an empty list.
+    fn get_latest_punishment_data(
+        &self,
+        _channel_id: &ChannelId,
+        _count: usize,
+    ) -> Result<Vec<PunishmentData>, Error> {
+        Ok(Vec::new())
+    }
+    /// Stores the given [`JusticeBlob`], replacing any blob with the same
+    /// locator. The default implementation does not record anything.
+    fn upsert_justice_blob(&self, _blob: &JusticeBlob) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the [`JusticeBlob`] with the given locator, if any. The default
+    /// implementation always returns `None`.
+    fn get_justice_blob(&self, _locator: &[u8; LOCATOR_LEN]) -> Result<Option<JusticeBlob>, Error> {
+        Ok(None)
+    }
+    /// Writes the [`HeaderCache`] used to detect chain reorganizations to the
+    /// store. The default implementation does not record anything.
+    fn persist_header_cache(&self, _header_cache: &HeaderCache) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the latest [`HeaderCache`] in the store if any. The default
+    /// implementation always returns `None`.
+    fn get_header_cache(&self) -> Result<Option<HeaderCache>, Error> {
+        Ok(None)
+    }
+    /// Stores the definition of the product settled by the oracle event with
+    /// the given id, replacing any previous one. The default implementation
+    /// does not record anything.
+    fn upsert_product_definition(
+        &self,
+        _event_id: &str,
+        _product: &ProductDefinition,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the definition of the product settled by the oracle event with
+    /// the given id, if any. The default implementation always returns `None`.
+    fn get_product_definition(&self, _event_id: &str) -> Result<Option<ProductDefinition>, Error> {
+        Ok(None)
+    }
+    /// Appends the given event to the event log of the contract with the
+    /// given temporary id. The default implementation does not record
+    /// anything.
+    fn append_contract_event(
+        &self,
+        _temporary_id: &ContractId,
+        _event: &ContractEvent,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the event log of the contract with the given id or temporary id,
+    /// oldest first. The default implementation always returns an empty log.
+    fn get_contract_history(&self, _id: &ContractId) -> Result<Vec<ContractEvent>, Error> {
+        Ok(Vec::new())
+    }
+    /// Appends the given event to the event log of the channel with the given
+    /// temporary id. The default implementation does not record anything.
+    fn append_channel_event(
+        &self,
+        _temporary_id: &ChannelId,
+        _event: &ChannelEvent,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the event log of the channel with the given id or temporary id,
+    /// oldest first. The default implementation always returns an empty log.
+    fn get_channel_history(&self, _id: &ChannelId) -> Result<Vec<ChannelEvent>, Error> {
+        Ok(Vec::new())
+    }
+    /// Writes all the records contained in the given [`StorageBatch`]
+    /// atomically. The default implementation writes them one after the other
+    /// and should be overridden by implementations supporting transactions.
+    fn write_batch(&self, batch: StorageBatch) -> Result<(), Error> {
+        for contract in &batch.new_contracts {
+            self.create_contract(contract)?;
+        }
+        for contract in &batch.contracts {
+            self.update_contract(contract)?;
+        }
+        for channel in batch.channels {
+            self.upsert_channel(channel, None)?;
+        }
+        for (temporary_id, event) in &batch.contract_events {
+            self.append_contract_event(temporary_id, event)?;
+        }
+        for (temporary_id, event) in &batch.channel_events {
+            self.append_channel_event(temporary_id, event)?;
+        }
+        if let Some(chain_monitor) = &batch.chain_monitor {
+            self.persist_chain_monitor(chain_monitor)?;
+        }
+        Ok(())
+    }
+    /// Returns a [`StorageSnapshot`] of the contracts, channels and chain
+    /// monitor in the store, so that composite queries do not observe writes
+    /// made in between their reads. The default implementation reads the
+    /// records one after the other, only includes offered and signed channels,
+    /// and should be overridden by implementations able to provide a
+    /// consistent view.
+    fn read_snapshot(&self) -> Result<StorageSnapshot, Error> {
+        let mut channels = self
+            .get_offered_channels()?
+            .into_iter()
+            .map(Channel::Offered)
+            .collect::<Vec<_>>();
+        channels.extend(
+            self.get_signed_channels(None)?
+                .into_iter()
+                .map(Channel::Signed),
+        );
+        Ok(StorageSnapshot::new(
+            self.get_contracts()?,
+            channels,
+            self.get_chain_monitor()?,
+        ))
+    }
+    /// Ensures that all the records written so far are persisted on durable
+    /// storage. Called by the manager after storing signatures that were
+    /// or are about to be sent to a counter party. The default implementation
+    /// does nothing.
+    fn flush(&self) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// Oracle trait provides access to oracle information.
@@ -237,6 +853,13 @@ pub trait Oracle {
     fn get_announcement(&self, event_id: &str) -> Result<OracleAnnouncement, Error>;
     /// Returns the attestation for the event with the given id if found.
     fn get_attestation(&self, event_id: &str) -> Result<OracleAttestation, Error>;
+    /// Returns the attestations for the events with the given ids, in the same
+    /// order. Used by the manager to fetch all the attestations it needs at
+    /// once, implementations can override it to batch requests. The default
+    /// implementation calls [`Oracle::get_attestation`] for each event.
+    fn get_attestations(&self, event_ids: &[&str]) -> Vec<Result<OracleAttestation, Error>> {
+        event_ids.iter().map(|x| self.get_attestation(x)).collect()
+    }
 }
 
 /// Represents a UTXO.
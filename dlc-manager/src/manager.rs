@@ -1,46 +1,84 @@
 //! #Manager a component to create and update DLCs.
 
 use super::{
-    Blockchain, CachedContractSignerProvider, ContractSigner, Oracle, Storage, Time, Wallet,
+    Blockchain, CachedContractSignerProvider, ContractSigner, Oracle, Storage, StorageBatch, Time,
+    Wallet,
+};
+#[cfg(feature = "async")]
+use crate::async_oracle::AsyncOracle;
+use crate::cancellation::{CancellationRegistry, CancellationToken};
+use crate::chain_monitor::{
+    ChainMonitor, ChannelInfo, ClaimableOutput, HeaderCache, ProcessedTx, RevokedTxType, TxType,
 };
-use crate::chain_monitor::{ChainMonitor, ChannelInfo, RevokedTxType, TxType};
 use crate::channel::offered_channel::OfferedChannel;
+use crate::channel::punishment::PunishmentData;
 use crate::channel::signed_channel::{SignedChannel, SignedChannelState, SignedChannelStateType};
-use crate::channel::Channel;
+use crate::channel::watchtower::{JusticeBlob, JusticeData, WatchtowerClient};
+use crate::channel::{Channel, ChannelConfig, ChannelEvent, ChannelPolicy, Quiescence};
 use crate::channel_updater::get_signed_channel_state;
 use crate::channel_updater::verify_signed_channel;
+use crate::coin_selection::{CoinSelector, SelectingWallet};
+use crate::contract::signing_request::{
+    ContractSignatures, ContractSigningRequest, UnsignedAcceptedContract,
+};
 use crate::contract::{
     accepted_contract::AcceptedContract, contract_info::ContractInfo,
     contract_input::ContractInput, contract_input::OracleInput, offered_contract::OfferedContract,
-    signed_contract::SignedContract, AdaptorInfo, ClosedContract, Contract, FailedAcceptContract,
+    signed_contract::SignedContract, AdaptorInfo, ClosedContract, CollaborativelyClosedContract,
+    Contract, ContractEvent, ContractEventType, ContractMetadata, FailedAcceptContract,
     FailedSignContract, PreClosedContract,
 };
-use crate::contract_updater::{accept_contract, verify_accepted_and_sign_contract};
-use crate::error::Error;
+use crate::contract_updater::{
+    accept_amendment, accept_contract, accept_contract_with_signatures, accept_netting,
+    amend_contract, apply_payout_update, create_cpfp_transaction, get_contract_signing_request,
+    get_received_amendment, get_received_netting, offer_collaborative_close, offer_netting,
+    on_collaborative_close_offer, prepare_accept_contract, sign_collaborative_close,
+    sign_contract_with_signatures, sign_payout_update, update_payout_script,
+    verify_accepted_and_sign_contract, verify_accepted_contract, verify_amendment_accept_and_sign,
+    verify_amendment_sign, verify_netting_accept_and_sign, verify_netting_sign,
+};
+use crate::error::{Error, ResultExt};
+use crate::estimate::{self, ContractEstimate, Operation, OperationEstimate};
+use crate::id_lock::IdLocks;
+use crate::offer_builder::{OfferBuilder, OfferDryRun};
+use crate::progress::{ProgressHandler, PROGRESS_BATCH_SIZE};
+use crate::state_history::{self, StateGraphFormat};
+use crate::utils::{release_party_utxos, verify_attestation};
 use crate::{ChannelId, ContractId, ContractSignerProvider};
 use bitcoin::absolute::Height;
-use bitcoin::consensus::Decodable;
+use bitcoin::psbt::PartiallySignedTransaction;
 use bitcoin::Address;
-use bitcoin::{OutPoint, Transaction};
+use bitcoin::{Block, OutPoint, ScriptBuf, Transaction, Txid};
+use dlc::{channel::RevokeParams, PartyParams};
 use dlc_messages::channel::{
-    AcceptChannel, CollaborativeCloseOffer, OfferChannel, Reject, RenewAccept, RenewConfirm,
-    RenewFinalize, RenewOffer, SettleAccept, SettleConfirm, SettleFinalize, SettleOffer,
-    SignChannel,
+    AcceptChannel, AddContractOffer, CollaborativeCloseOffer, ContractUpdateAccept,
+    ContractUpdateConfirm, ContractUpdateFinalize, OfferChannel, Reject, RejectChannelOffer,
+    RenewAccept, RenewConfirm, RenewFinalize, RenewOffer, Resume, SettleAccept, SettleConfirm,
+    SettleContractOffer, SettleFinalize, SettleOffer, SignChannel, Stop,
 };
 use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
-use dlc_messages::{AcceptDlc, Message as DlcMessage, OfferDlc, SignDlc};
+#[cfg(feature = "taproot")]
+use dlc_messages::taproot::{AcceptTaprootDlc, SignTaprootDlc};
+use dlc_messages::{
+    AcceptDlc, AmendAccept, AmendOffer, AmendSign, Message as DlcMessage, MutualCloseAccept,
+    MutualCloseOffer, NetAccept, NetOffer, NetSign, OfferDlc, Ping, Pong, RejectOffer, SignDlc,
+    UpdatePayoutAccept, UpdatePayoutOffer, ANCHOR_OUTPUTS_FLAG, TAPROOT_FUNDING_FLAG,
+};
 use hex::DisplayHex;
-use lightning::chain::chaininterface::FeeEstimator;
+use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
 use lightning::ln::chan_utils::{
     build_commitment_secret, derive_private_key, derive_private_revocation_key,
 };
-use log::{error, warn};
+use log::{debug, error, info, warn};
 use secp256k1_zkp::XOnlyPublicKey;
-use secp256k1_zkp::{ecdsa::Signature, All, PublicKey, Secp256k1, SecretKey};
-use std::collections::HashMap;
+use secp256k1_zkp::{
+    ecdsa::Signature, All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey,
+};
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::string::ToString;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// The number of confirmations required before moving the the confirmed state.
 pub const NB_CONFIRMATIONS: u32 = 6;
@@ -58,7 +96,341 @@ type ClosableContractInfo<'a> = Option<(
     Vec<(usize, OracleAttestation)>,
 )>;
 
-/// Used to create and update DLCs.
+type AttestationCache = HashMap<(XOnlyPublicKey, String), OracleAttestation>;
+
+/// A contract waiting for the local party's signatures to be produced by an
+/// external signer.
+enum PendingSigning {
+    /// The local party is accepting an offered contract.
+    Accept(UnsignedAcceptedContract),
+    /// The local party offered the contract and verified the accept message.
+    Sign(AcceptedContract),
+}
+
+/// An amendment of a confirmed contract being negotiated with the counter
+/// party.
+enum PendingAmendment {
+    /// The local party proposed the amendment.
+    Offered(OfferedContract),
+    /// The counter party proposed the amendment, which was not accepted yet.
+    Received(OfferedContract),
+    /// The local party accepted the amendment proposed by the counter party.
+    Accepted(AcceptedContract),
+}
+
+/// A netting of confirmed contracts being negotiated with the counter party,
+/// along with the ids of the netted contracts.
+enum PendingNetting {
+    /// The local party proposed the netting.
+    Offered(Vec<ContractId>, OfferedContract),
+    /// The counter party proposed the netting, which was not accepted yet.
+    Received(Vec<ContractId>, OfferedContract),
+    /// The local party accepted the netting proposed by the counter party.
+    Accepted(Vec<ContractId>, AcceptedContract),
+}
+
+/// A collaborative close of a contract being negotiated with the counter
+/// party.
+enum PendingCollaborativeClose {
+    /// The local party proposed to close the contract with the given
+    /// transaction.
+    Offered { close_tx: Transaction },
+    /// The counter party proposed to close the contract with the given
+    /// transaction, which was not accepted yet.
+    Received {
+        close_tx: Transaction,
+        counter_signature: Signature,
+    },
+}
+
+/// Contracts and channels to write to the [`Storage`] in a single batch, each
+/// along with the name of the state it was in before the write (`None` for
+/// new objects), from which the events appended to their log in the same
+/// batch are derived.
+#[derive(Default)]
+struct StateUpdate {
+    new_contracts: Vec<OfferedContract>,
+    contracts: Vec<(Option<String>, Contract)>,
+    channels: Vec<(Option<String>, Channel)>,
+    chain_monitor: Option<ChainMonitor>,
+}
+
+impl StateUpdate {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_new_contract(mut self, contract: OfferedContract) -> Self {
+        self.new_contracts.push(contract);
+        self
+    }
+
+    fn with_contract(mut self, previous: Option<&str>, contract: Contract) -> Self {
+        self.contracts
+            .push((previous.map(|s| s.to_string()), contract));
+        self
+    }
+
+    fn with_channel(mut self, previous: Option<&str>, channel: Channel) -> Self {
+        self.channels
+            .push((previous.map(|s| s.to_string()), channel));
+        self
+    }
+
+    fn with_chain_monitor(mut self, chain_monitor: ChainMonitor) -> Self {
+        self.chain_monitor = Some(chain_monitor);
+        self
+    }
+}
+
+/// Returns whether the contract contains adaptor signatures that were or are
+/// about to be sent to the counter party, and must therefore not be lost.
+fn contract_holds_signatures(contract: &Contract) -> bool {
+    matches!(contract, Contract::Accepted(_) | Contract::Signed(_))
+}
+
+/// Returns whether the channel contains signatures that were or are about to
+/// be sent to the counter party, and must therefore not be lost.
+fn channel_holds_signatures(channel: &Channel) -> bool {
+    matches!(channel, Channel::Accepted(_) | Channel::Signed(_))
+}
+
+/// Returns the earliest time at which an action can be required on the
+/// contract, that is the earliest maturity of its oracle events or its refund
+/// lock time.
+fn get_contract_deadline(contract: &SignedContract) -> u64 {
+    let refund_locktime = contract
+        .accepted_contract
+        .dlc_transactions
+        .refund
+        .lock_time
+        .to_consensus_u32() as u64;
+    contract
+        .accepted_contract
+        .offered_contract
+        .contract_info
+        .iter()
+        .flat_map(|x| &x.oracle_announcements)
+        .map(|x| x.oracle_event.event_maturity_epoch as u64)
+        .fold(refund_locktime, u64::min)
+}
+
+/// Returns the ids of the contracts and channels whose state is updated when
+/// processing the given message.
+fn get_message_object_ids(msg: &DlcMessage) -> Vec<[u8; 32]> {
+    match msg {
+        DlcMessage::Offer(_)
+        | DlcMessage::OfferChannel(_)
+        | DlcMessage::Ping(_)
+        | DlcMessage::Pong(_) => Vec::new(),
+        DlcMessage::Accept(a) => vec![a.temporary_contract_id],
+        DlcMessage::RejectOffer(r) => vec![r.temporary_contract_id],
+        DlcMessage::Sign(s) => vec![s.contract_id],
+        #[cfg(feature = "taproot")]
+        DlcMessage::AcceptTaproot(a) => vec![a.temporary_contract_id],
+        #[cfg(feature = "taproot")]
+        DlcMessage::SignTaproot(s) => vec![s.contract_id],
+        DlcMessage::UpdatePayoutOffer(u) => vec![u.contract_id],
+        DlcMessage::UpdatePayoutAccept(u) => vec![u.contract_id],
+        DlcMessage::AmendOffer(a) => vec![a.contract_id],
+        DlcMessage::AmendAccept(a) => vec![a.contract_id],
+        DlcMessage::AmendSign(a) => vec![a.contract_id],
+        DlcMessage::MutualCloseOffer(c) => vec![c.contract_id],
+        DlcMessage::MutualCloseAccept(c) => vec![c.contract_id],
+        DlcMessage::NetOffer(n) => n.contract_ids.clone(),
+        DlcMessage::NetAccept(n) => vec![n.contract_id],
+        DlcMessage::NetSign(n) => vec![n.contract_id],
+        DlcMessage::AcceptChannel(a) => vec![a.temporary_channel_id],
+        DlcMessage::RejectChannelOffer(r) => vec![r.temporary_channel_id],
+        DlcMessage::SignChannel(s) => vec![s.channel_id],
+        DlcMessage::SettleOffer(s) => vec![s.channel_id],
+        DlcMessage::SettleAccept(s) => vec![s.channel_id],
+        DlcMessage::SettleConfirm(s) => vec![s.channel_id],
+        DlcMessage::SettleFinalize(s) => vec![s.channel_id],
+        DlcMessage::RenewOffer(r) => vec![r.channel_id],
+        DlcMessage::RenewAccept(r) => vec![r.channel_id],
+        DlcMessage::RenewConfirm(r) => vec![r.channel_id],
+        DlcMessage::RenewFinalize(r) => vec![r.channel_id],
+        DlcMessage::AddContractOffer(a) => vec![a.channel_id],
+        DlcMessage::SettleContractOffer(s) => vec![s.channel_id],
+        DlcMessage::ContractUpdateAccept(a) => vec![a.channel_id],
+        DlcMessage::ContractUpdateConfirm(c) => vec![c.channel_id],
+        DlcMessage::ContractUpdateFinalize(f) => vec![f.channel_id],
+        DlcMessage::CollaborativeCloseOffer(c) => vec![c.channel_id],
+        DlcMessage::Reject(r) => vec![r.channel_id],
+        DlcMessage::Stop(s) => vec![s.channel_id],
+        DlcMessage::Resume(r) => vec![r.channel_id],
+    }
+}
+
+fn is_budget_exhausted(deadline: Option<Instant>) -> bool {
+    deadline.map_or(false, |d| Instant::now() >= d)
+}
+
+/// Returns the contract of a newly established channel, which zero-conf
+/// channels consider confirmed so that the channel can be updated right away.
+fn get_established_channel_contract(
+    signed_channel: &SignedChannel,
+    signed_contract: SignedContract,
+) -> Contract {
+    if signed_channel.unconfirmed_funding {
+        Contract::Confirmed(signed_contract)
+    } else {
+        Contract::Signed(signed_contract)
+    }
+}
+
+/// Returns the state a contract signed during an update of the contracts of a
+/// channel was in before it: offered if it is the contract added by the
+/// update, confirmed if it was already established in the channel.
+fn contract_update_previous_state(
+    signed_contract: &SignedContract,
+    added_contract_id: Option<ContractId>,
+) -> &'static str {
+    if Some(signed_contract.accepted_contract.offered_contract.id) == added_contract_id {
+        "offered"
+    } else {
+        "confirmed"
+    }
+}
+
+/// Parameters used to detect that the counter party of a contract approaching
+/// maturity is unreachable.
+#[derive(Clone, Copy, Debug)]
+pub struct PeerLivenessConfig {
+    /// Time in seconds after which a peer from which no message was received
+    /// is considered unreachable.
+    pub unreachable_after: u64,
+    /// Time in seconds before the maturity of a contract from which the
+    /// reachability of its counter party is checked.
+    pub maturity_window: u64,
+}
+
+/// Parameters used to detect offers that would make the local node enter a
+/// contract with itself, for example when an oracle and a trader are operated
+/// by the same deployment.
+#[derive(Clone, Debug, Default)]
+pub struct SelfDealingConfig {
+    /// Public keys of the oracles operated by the local node. Offers depending
+    /// on an announcement signed with one of them are rejected.
+    pub own_oracle_keys: Vec<XOnlyPublicKey>,
+    /// Whether to reject offers whose funding public key is one for which the
+    /// [`ContractSignerProvider`] can provide the secret key.
+    pub reject_own_funding_keys: bool,
+}
+
+/// Summary of the inconsistencies between the store and the blockchain that
+/// were repaired by [`Manager::recover`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// Contracts that were stored again under the id derived from their fund
+    /// transaction.
+    pub restored_contract_ids: Vec<ContractId>,
+    /// Signed contracts whose fund transaction was found confirmed in the
+    /// blockchain.
+    pub confirmed_contracts: Vec<ContractId>,
+    /// Signed channels whose current transaction was not watched anymore.
+    pub rewatched_channels: Vec<ChannelId>,
+    /// Confirmed contracts that were closed by a CET or refunded while the
+    /// node was offline.
+    pub resolved_contracts: Vec<ContractId>,
+    /// Signed contracts whose unconfirmed fund transaction was broadcast
+    /// again.
+    pub rebroadcast_contracts: Vec<ContractId>,
+}
+
+/// Events generated by the [`Manager`] that require the attention of the
+/// application.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ManagerEvent {
+    /// No message was received from the counter party of contracts that are
+    /// approaching maturity for longer than allowed by the
+    /// [`PeerLivenessConfig`].
+    PeerUnreachable {
+        /// The id of the unreachable peer.
+        peer_id: PublicKey,
+        /// The last time a message was received from the peer.
+        last_seen: u64,
+        /// The ids of the contracts approaching maturity entered with the peer.
+        contract_ids: Vec<ContractId>,
+    },
+    /// A contract offer was received.
+    OfferReceived {
+        /// The temporary id of the offered contract.
+        contract_id: ContractId,
+        /// The id of the node that offered the contract.
+        counter_party: PublicKey,
+    },
+    /// The fund transaction of a contract was confirmed.
+    ContractConfirmed {
+        /// The id of the contract.
+        contract_id: ContractId,
+    },
+    /// The oracles attested to the outcome of a contract, for which a CET was
+    /// broadcast.
+    ContractMatured {
+        /// The id of the contract.
+        contract_id: ContractId,
+    },
+    /// A contract was closed, either by a confirmed CET or by a transaction
+    /// agreed upon by both parties.
+    ContractClosed {
+        /// The id of the contract.
+        contract_id: ContractId,
+    },
+    /// The refund transaction of a contract was broadcast.
+    ContractRefunded {
+        /// The id of the contract.
+        contract_id: ContractId,
+    },
+    /// A punishment transaction was broadcast in response to the counter
+    /// party publishing a revoked state of a channel.
+    ChannelPunished {
+        /// The id of the channel.
+        channel_id: ChannelId,
+        /// The id of the punishment transaction.
+        punishment_txid: Txid,
+    },
+}
+
+impl ManagerEvent {
+    /// Returns the event to notify the application of when a contract goes
+    /// through the given [`ContractEvent`], if any.
+    fn from_contract_event(contract: &Contract, event: &ContractEvent) -> Option<ManagerEvent> {
+        let contract_id = contract.get_id();
+        match event.event_type {
+            ContractEventType::OfferReceived => Some(ManagerEvent::OfferReceived {
+                contract_id,
+                counter_party: contract.get_counter_party_id(),
+            }),
+            ContractEventType::Confirmed => Some(ManagerEvent::ContractConfirmed { contract_id }),
+            ContractEventType::OracleAttested => {
+                Some(ManagerEvent::ContractMatured { contract_id })
+            }
+            ContractEventType::Closed | ContractEventType::CollaborativelyClosed => {
+                Some(ManagerEvent::ContractClosed { contract_id })
+            }
+            ContractEventType::Refunded => Some(ManagerEvent::ContractRefunded { contract_id }),
+            _ => None,
+        }
+    }
+}
+
+/// Receives the [`ManagerEvent`]s as they are generated, see
+/// [`Manager::add_event_handler`].
+pub trait EventHandler: Send + Sync {
+    /// Called from the thread that generated the event, once the update that
+    /// caused it was written to the storage.
+    fn handle_event(&self, event: &ManagerEvent);
+}
+
+/// Used to create and update DLCs. Apart from configuration setters, methods
+/// take `&self` so that a manager can be shared between threads, letting
+/// periodic checks and queries run alongside message processing. Processing a
+/// message, accepting or rejecting an offer, or checking a contract or channel
+/// during a periodic check holds a lock on the id of the contract or channel
+/// concerned, so that updates to the same object are applied one at a time
+/// while updates to different objects run in parallel.
 pub struct Manager<
     W: Deref,
     SP: Deref,
@@ -83,9 +455,39 @@ pub struct Manager<
     blockchain: B,
     store: S,
     secp: Secp256k1<All>,
-    chain_monitor: ChainMonitor,
+    chain_monitor: Mutex<ChainMonitor>,
+    header_cache: Mutex<HeaderCache>,
     time: T,
     fee_estimator: F,
+    node_id: Option<PublicKey>,
+    quiescence: Mutex<HashMap<ChannelId, Quiescence>>,
+    max_message_age: Option<u64>,
+    offer_validity: Option<u64>,
+    peer_liveness: Option<PeerLivenessConfig>,
+    /// Last seen time of the peers reported as unreachable, so that they are
+    /// only reported again once a message was received from them.
+    reported_unreachable_peers: Mutex<HashMap<PublicKey, u64>>,
+    pending_events: Mutex<Vec<ManagerEvent>>,
+    event_handlers: Vec<Arc<dyn EventHandler>>,
+    min_relay_fee_rate: Option<u64>,
+    offer_fee_rate_target: Option<ConfirmationTarget>,
+    incremental_relay_fee_rate: Option<u64>,
+    cancellation_registry: CancellationRegistry,
+    id_locks: IdLocks,
+    periodic_check_budget: Option<Duration>,
+    fast_sync_threshold: Option<u64>,
+    self_dealing: Option<SelfDealingConfig>,
+    channel_policy: ChannelPolicy,
+    channel_config: ChannelConfig,
+    watchtower_client: Option<Arc<dyn WatchtowerClient>>,
+    coin_selector: Option<Arc<dyn CoinSelector>>,
+    reject_payout_updates: bool,
+    pending_payout_updates: Mutex<HashMap<ContractId, ScriptBuf>>,
+    pending_amendments: Mutex<HashMap<ContractId, PendingAmendment>>,
+    pending_nettings: Mutex<HashMap<ContractId, PendingNetting>>,
+    pending_collaborative_closes: Mutex<HashMap<ContractId, PendingCollaborativeClose>>,
+    external_signing: bool,
+    pending_signing: Mutex<HashMap<ContractId, (PendingSigning, ContractSigningRequest)>>,
 }
 
 macro_rules! get_object_in_state {
@@ -153,6 +555,35 @@ macro_rules! get_signed_channel_rollback_state {
     }};
 }
 
+/// Locks the contract with the given id and returns its latest stored version
+/// along with the lock guard if it is still in the given state, as it can have
+/// been updated by a message processed concurrently since it was listed.
+macro_rules! lock_contract_in_state {
+    ($manager: ident, $contract_id: expr, $state: ident) => {{
+        let contract_id = $contract_id;
+        let guard = $manager.id_locks.lock(contract_id);
+        match $manager.store.get_contract(&contract_id)? {
+            Some(Contract::$state(c)) => Some((guard, c)),
+            _ => None,
+        }
+    }};
+}
+
+/// Locks the signed channel with the given id and returns its latest stored
+/// version along with the lock guard if it is still in the given state.
+macro_rules! lock_signed_channel_in_state {
+    ($manager: ident, $channel_id: expr, $state: ident) => {{
+        let channel_id = $channel_id;
+        let guard = $manager.id_locks.lock(channel_id);
+        match $manager.store.get_channel(&channel_id)? {
+            Some(Channel::Signed(c)) if c.state.is_of_type(&SignedChannelStateType::$state) => {
+                Some((guard, c))
+            }
+            _ => None,
+        }
+    }};
+}
+
 macro_rules! check_for_timed_out_channels {
     ($manager: ident, $state: ident) => {
         let channels = $manager
@@ -160,6 +591,11 @@ macro_rules! check_for_timed_out_channels {
             .get_signed_channels(Some(SignedChannelStateType::$state))?;
 
         for channel in channels {
+            let (_guard, channel) =
+                match lock_signed_channel_in_state!($manager, channel.channel_id, $state) {
+                    Some(locked) => locked,
+                    None => continue,
+                };
             if let SignedChannelState::$state { timeout, .. } = channel.state {
                 let is_timed_out = timeout < $manager.time.unix_time_now();
                 if is_timed_out {
@@ -198,6 +634,7 @@ where
         let chain_monitor = store
             .get_chain_monitor()?
             .unwrap_or(ChainMonitor::new(init_height));
+        let header_cache = store.get_header_cache()?.unwrap_or_default();
 
         let signer_provider = Arc::new(CachedContractSignerProvider::new(signer_provider));
 
@@ -210,36 +647,636 @@ where
             oracles,
             time,
             fee_estimator,
-            chain_monitor,
+            chain_monitor: Mutex::new(chain_monitor),
+            header_cache: Mutex::new(header_cache),
+            node_id: None,
+            quiescence: Mutex::new(HashMap::new()),
+            max_message_age: None,
+            offer_validity: None,
+            peer_liveness: None,
+            reported_unreachable_peers: Mutex::new(HashMap::new()),
+            pending_events: Mutex::new(Vec::new()),
+            event_handlers: Vec::new(),
+            min_relay_fee_rate: None,
+            offer_fee_rate_target: None,
+            incremental_relay_fee_rate: None,
+            cancellation_registry: CancellationRegistry::default(),
+            id_locks: IdLocks::default(),
+            periodic_check_budget: None,
+            fast_sync_threshold: None,
+            self_dealing: None,
+            channel_policy: ChannelPolicy::default(),
+            channel_config: ChannelConfig::default(),
+            watchtower_client: None,
+            coin_selector: None,
+            reject_payout_updates: false,
+            pending_payout_updates: Mutex::new(HashMap::new()),
+            pending_amendments: Mutex::new(HashMap::new()),
+            pending_nettings: Mutex::new(HashMap::new()),
+            pending_collaborative_closes: Mutex::new(HashMap::new()),
+            external_signing: false,
+            pending_signing: Mutex::new(HashMap::new()),
         })
     }
 
+    /// Sets the node id of the local node. Once set, received offers that name
+    /// a different recipient are rejected. This is a best-effort filter: offers
+    /// are not signed, so it does not prevent another node from accepting an
+    /// offer that was not meant for it.
+    pub fn set_node_id(&mut self, node_id: PublicKey) {
+        self.node_id = Some(node_id);
+    }
+
+    /// Sets the maximum age (in seconds) of received contract and channel
+    /// offers. Once set, offers created more than `max_age` seconds ago, or that
+    /// don't include a creation timestamp, are rejected.
+    pub fn set_max_message_age(&mut self, max_age: u64) {
+        self.max_message_age = Some(max_age);
+    }
+
+    /// Sets the validity period (in seconds) of the contract offers sent by
+    /// the manager. Once set, sent offers include an expiry after which they
+    /// cannot be accepted anymore, and are moved to the [`Contract::Expired`]
+    /// state by [`Manager::periodic_check`], releasing their funding inputs.
+    pub fn set_offer_validity(&mut self, validity: u64) {
+        self.offer_validity = Some(validity);
+    }
+
+    /// Enables checking, on every call to [`Manager::periodic_check`], that the
+    /// counter parties of contracts approaching maturity are reachable. A
+    /// [`ManagerEvent::PeerUnreachable`] event is generated for each of them
+    /// from which no message was received for longer than allowed by the given
+    /// configuration, once per time the peer was last seen. Sending [`Ping`] messages regularly (see
+    /// [`Manager::ping`]) ensures that reachable peers are seen.
+    pub fn set_peer_liveness_config(&mut self, config: PeerLivenessConfig) {
+        self.peer_liveness = Some(config);
+    }
+
+    /// Enables rejecting received contract and channel offers that would make
+    /// the local node enter a contract with itself, as described by the given
+    /// configuration.
+    pub fn set_self_dealing_config(&mut self, config: SelfDealingConfig) {
+        self.self_dealing = Some(config);
+    }
+
+    /// Sets the [`ChannelPolicy`] enforced on the channel updates offered by
+    /// counter parties. Offers violating it are answered with a [`Reject`]
+    /// message.
+    pub fn set_channel_policy(&mut self, policy: ChannelPolicy) {
+        self.channel_policy = policy;
+    }
+
+    /// Sets the [`ChannelConfig`] used when offering and accepting channels
+    /// without specifying one, returning an error if it is invalid.
+    pub fn set_channel_config(&mut self, config: ChannelConfig) -> Result<(), Error> {
+        config.validate()?;
+        self.channel_config = config;
+        Ok(())
+    }
+
+    /// Sets the [`WatchtowerClient`] to which a [`JusticeBlob`] is sent each
+    /// time the counter party revokes a state of a channel.
+    pub fn set_watchtower_client(&mut self, client: Arc<dyn WatchtowerClient>) {
+        self.watchtower_client = Some(client);
+    }
+
+    /// Sets the [`CoinSelector`] selecting, among the UTXOs listed by the
+    /// wallet, the ones funding the contracts and channels offered or
+    /// accepted from now on, instead of [`Wallet::get_utxos_for_amount`].
+    pub fn set_coin_selector(&mut self, coin_selector: Arc<dyn CoinSelector>) {
+        self.coin_selector = Some(coin_selector);
+    }
+
+    /// Returns the wallet to use to select the UTXOs funding a contract or a
+    /// channel.
+    fn funding_wallet(&self) -> SelectingWallet<'_, W> {
+        SelectingWallet {
+            wallet: &self.wallet,
+            coin_selector: self.coin_selector.as_deref(),
+        }
+    }
+
+    /// Returns a [`JusticeBlob`] for each revoked state of the channel with
+    /// the given id, for example to back up to a newly added watchtower the
+    /// revocations that happened before it was set.
+    pub fn get_justice_blobs(&self, channel_id: &ChannelId) -> Result<Vec<JusticeBlob>, Error> {
+        let signed_channel = get_channel_in_state!(self, channel_id, Signed, None::<PublicKey>)?;
+        let revoked_txs = self
+            .chain_monitor
+            .lock()
+            .unwrap()
+            .get_watched_txs()
+            .into_iter()
+            .filter(|(_, info)| info.channel_id == *channel_id);
+
+        let mut blobs = Vec::new();
+        for (txid, info) in revoked_txs {
+            if let TxType::Revoked {
+                update_idx,
+                own_adaptor_signature,
+                is_offer,
+                revoked_tx_type,
+            } = info.tx_type
+            {
+                let counter_per_update_secret =
+                    match self.store.get_punishment_data(channel_id, update_idx)? {
+                        Some(punishment_data) => punishment_data.counter_per_update_secret,
+                        None => match signed_channel
+                            .counter_party_commitment_secrets
+                            .get_secret(update_idx)
+                        {
+                            Some(secret) => SecretKey::from_slice(&secret)?,
+                            None => continue,
+                        },
+                    };
+                let justice_data = self.get_justice_data(
+                    &signed_channel,
+                    update_idx,
+                    own_adaptor_signature,
+                    is_offer,
+                    revoked_tx_type,
+                    &counter_per_update_secret,
+                    txid,
+                    self.wallet.get_new_address()?.script_pubkey(),
+                )?;
+                blobs.push(JusticeBlob::encrypt(&justice_data)?);
+            }
+        }
+
+        Ok(blobs)
+    }
+
+    /// Sets whether [`UpdatePayoutOffer`] messages received from counter
+    /// parties should be rejected. They are accepted by default.
+    pub fn set_reject_payout_updates(&mut self, reject: bool) {
+        self.reject_payout_updates = reject;
+    }
+
+    /// Sets whether the signatures of the local party for contracts it
+    /// offered should be produced by an external signer. When enabled,
+    /// received [`AcceptDlc`] messages are verified and a
+    /// [`ContractSigningRequest`] made available through
+    /// [`Self::get_signing_requests`] instead of being answered, the reply
+    /// being returned by [`Self::submit_contract_signatures`]. Contracts
+    /// offered by counter parties can be accepted through an external signer
+    /// using [`Self::prepare_accept_contract_offer`] regardless of this
+    /// setting. Disabled by default.
+    ///
+    /// Note that only the signatures exchanged while setting up contracts are
+    /// requested from external signers, closing transactions and DLC channels
+    /// still requiring the [`ContractSigner`] to provide secret keys.
+    pub fn set_external_signing(&mut self, external_signing: bool) {
+        self.external_signing = external_signing;
+    }
+
+    /// Sets the handler notified of the progress of the creation and
+    /// verification of adaptor signatures, when accepting an offer or
+    /// processing a [`SignDlc`] message. When a handler is set, signatures are
+    /// processed in batches of [`PROGRESS_BATCH_SIZE`], between which the
+    /// handler is notified and cancellation requested through
+    /// [`Self::cancel_operation`] is checked.
+    pub fn set_progress_handler(&mut self, handler: Arc<dyn ProgressHandler>) {
+        self.cancellation_registry.set_progress_handler(handler);
+    }
+
+    /// Registers a handler to which [`ManagerEvent`]s are passed as they are
+    /// generated. Once a handler is registered, events are no longer queued
+    /// to be returned by [`Self::get_and_clear_pending_events`].
+    pub fn add_event_handler(&mut self, handler: Arc<dyn EventHandler>) {
+        self.event_handlers.push(handler);
+    }
+
+    /// Sets the minimum relay fee rate, in satoshis per virtual byte, to use
+    /// instead of the one returned by the [`Blockchain`]. Contract and channel
+    /// offers with a lower fee rate are rejected.
+    pub fn set_min_relay_fee_rate(&mut self, fee_rate_per_vb: u64) {
+        self.min_relay_fee_rate = Some(fee_rate_per_vb);
+    }
+
+    /// Sets the incremental relay fee rate, in satoshis per virtual byte, to use
+    /// instead of the one returned by the [`Blockchain`] when computing the fee
+    /// rate of replacement transactions.
+    pub fn set_incremental_relay_fee_rate(&mut self, fee_rate_per_vb: u64) {
+        self.incremental_relay_fee_rate = Some(fee_rate_per_vb);
+    }
+
+    /// Makes new contract and channel offers use the fee rate returned by the
+    /// fee estimator for the given confirmation target, instead of the fee
+    /// rate of their [`ContractInput`].
+    pub fn set_offer_fee_rate_target(&mut self, target: ConfirmationTarget) {
+        self.offer_fee_rate_target = Some(target);
+    }
+
+    /// Sets the maximum time spent by a call to [`Manager::periodic_check`]
+    /// processing contracts. Contracts that are not checked before the budget
+    /// is exhausted are checked by subsequent calls. Contracts that reached
+    /// their maturity or refund lock time are always processed first.
+    pub fn set_periodic_check_budget(&mut self, budget: Duration) {
+        self.periodic_check_budget = Some(budget);
+    }
+
+    /// Enables fast syncing of the watched transactions. When more than
+    /// `nb_blocks` blocks were added to the chain since the last check, the
+    /// [`Blockchain`] is queried for the confirmations of each watched
+    /// transaction instead of going through every missed block. The
+    /// [`Blockchain`] must be able to report the confirmations of any
+    /// transaction, not only of those of the wallet.
+    pub fn set_fast_sync_threshold(&mut self, nb_blocks: u64) {
+        self.fast_sync_threshold = Some(nb_blocks);
+    }
+
+    /// Returns the minimum fee rate, in satoshis per virtual byte, at which
+    /// transactions are relayed.
+    pub fn get_min_relay_fee_rate(&self) -> Result<u64, Error> {
+        match self.min_relay_fee_rate {
+            Some(fee_rate) => Ok(fee_rate),
+            None => self.blockchain.get_min_relay_fee_rate(),
+        }
+    }
+
+    /// Returns the fee rate, in satoshis per virtual byte, estimated by the fee
+    /// estimator for the given confirmation target. The returned fee rate is
+    /// never lower than the minimum relay fee rate.
+    pub fn get_estimated_fee_rate(&self, target: ConfirmationTarget) -> Result<u64, Error> {
+        let fee_rate_per_vb = (self.fee_estimator.get_est_sat_per_1000_weight(target) / 250) as u64;
+        Ok(std::cmp::max(
+            fee_rate_per_vb,
+            self.get_min_relay_fee_rate()?,
+        ))
+    }
+
+    /// Returns the minimum fee rate, in satoshis per virtual byte, that a
+    /// transaction replacing one with the given fee rate must pay to be relayed.
+    pub fn get_replacement_fee_rate(&self, previous_fee_rate_per_vb: u64) -> Result<u64, Error> {
+        let incremental_fee_rate = match self.incremental_relay_fee_rate {
+            Some(fee_rate) => fee_rate,
+            None => self.blockchain.get_incremental_relay_fee_rate()?,
+        };
+        Ok(std::cmp::max(
+            previous_fee_rate_per_vb.saturating_add(incremental_fee_rate),
+            self.get_min_relay_fee_rate()?,
+        ))
+    }
+
+    /// Returns the contract input to use for a new offer, with its fee rate
+    /// replaced by the estimated one if an offer fee rate target is set.
+    fn get_offer_contract_input(
+        &self,
+        contract_input: &ContractInput,
+    ) -> Result<ContractInput, Error> {
+        let mut contract_input = contract_input.clone();
+        if let Some(target) = self.offer_fee_rate_target {
+            contract_input.fee_rate = self.get_estimated_fee_rate(target)?;
+        }
+        Ok(contract_input)
+    }
+
+    fn check_fee_rate(&self, fee_rate_per_vb: u64) -> Result<(), Error> {
+        let min_relay_fee_rate = self.get_min_relay_fee_rate()?;
+        if fee_rate_per_vb < min_relay_fee_rate {
+            return Err(Error::InvalidParameters(format!(
+                "Fee rate {} is below the minimum relay fee rate of {}",
+                fee_rate_per_vb, min_relay_fee_rate
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn check_self_dealing(
+        &self,
+        contract: &OfferedContract,
+        funding_pubkey: &PublicKey,
+    ) -> Result<(), Error> {
+        let config = match &self.self_dealing {
+            Some(config) => config,
+            None => return Ok(()),
+        };
+
+        if let Some(announcement) = contract
+            .contract_info
+            .iter()
+            .flat_map(|c| &c.oracle_announcements)
+            .find(|a| config.own_oracle_keys.contains(&a.oracle_public_key))
+        {
+            return Err(Error::InvalidParameters(format!(
+                "Offer depends on own oracle {}",
+                announcement.oracle_public_key
+            )));
+        }
+
+        if config.reject_own_funding_keys
+            && self
+                .signer_provider
+                .get_secret_key_for_pubkey(funding_pubkey)
+                .is_ok()
+        {
+            return Err(Error::InvalidParameters(
+                "Offer funding public key belongs to the local wallet".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Aborts the operation in progress on the contract or channel with the
+    /// given id, such as [`Manager::accept_contract_offer`],
+    /// [`Manager::accept_channel`] or [`Manager::accept_renew_offer`]. The
+    /// operation returns [`Error::Cancelled`] without updating the storage and
+    /// releases the utxos it reserved. Returns `false` if no cancellable
+    /// operation is in progress for the given id.
+    pub fn cancel_operation(&self, id: &[u8; 32]) -> bool {
+        self.cancellation_registry.cancel(id)
+    }
+
+    /// Returns the state graph of the contract with the given id, built from
+    /// its event log recorded in the [`Storage`].
+    pub fn export_contract_state_graph(
+        &self,
+        contract_id: &ContractId,
+        format: StateGraphFormat,
+    ) -> Result<String, Error> {
+        let events = self.store.get_contract_history(contract_id)?;
+        Ok(state_history::export_contract_state_graph(
+            contract_id,
+            &events,
+            format,
+        ))
+    }
+
+    /// Returns the state graph of the channel with the given id, built from
+    /// its event log recorded in the [`Storage`].
+    pub fn export_channel_state_graph(
+        &self,
+        channel_id: &ChannelId,
+        format: StateGraphFormat,
+    ) -> Result<String, Error> {
+        let events = self.store.get_channel_history(channel_id)?;
+        Ok(state_history::export_channel_state_graph(
+            channel_id, &events, format,
+        ))
+    }
+
+    fn create_contract(&self, contract: &OfferedContract, trigger: &str) -> Result<(), Error> {
+        enter_span!(
+            "create_contract",
+            contract_id = %contract.id.to_lower_hex_string(),
+            trigger
+        );
+        self.write_batch(
+            StateUpdate::new().with_new_contract(contract.clone()),
+            trigger,
+        )
+    }
+
+    /// Updates the given contract, which was in the state named `previous`
+    /// before the update.
+    fn update_contract(
+        &self,
+        previous: &str,
+        contract: &Contract,
+        trigger: &str,
+    ) -> Result<(), Error> {
+        enter_span!(
+            "update_contract",
+            contract_id = %contract.get_id().to_lower_hex_string(),
+            state = contract.get_state_name(),
+            trigger
+        );
+        self.write_batch(
+            StateUpdate::new().with_contract(Some(previous), contract.clone()),
+            trigger,
+        )
+    }
+
+    /// Creates or updates the given channel, which was in the state named
+    /// `previous` before the update (`None` if it is being created).
+    fn upsert_channel(
+        &self,
+        previous: Option<&str>,
+        channel: Channel,
+        trigger: &str,
+    ) -> Result<(), Error> {
+        enter_span!(
+            "upsert_channel",
+            channel_id = %channel.get_id().to_lower_hex_string(),
+            trigger
+        );
+        self.write_batch(StateUpdate::new().with_channel(previous, channel), trigger)
+    }
+
+    /// Writes the given update to the [`Storage`] in a single batch, along
+    /// with the events it adds to the log of its contracts and channels, and
+    /// notifies the application of the resulting [`ManagerEvent`]s.
+    fn write_batch(&self, update: StateUpdate, trigger: &str) -> Result<(), Error> {
+        enter_span!(
+            "write_batch",
+            nb_channels = update.channels.len(),
+            nb_contracts = update.contracts.len() + update.new_contracts.len(),
+            trigger
+        );
+        let timestamp = self.time.unix_time_now();
+        let flush = update
+            .channels
+            .iter()
+            .any(|(_, c)| channel_holds_signatures(c))
+            || update
+                .contracts
+                .iter()
+                .any(|(_, c)| contract_holds_signatures(c));
+        let mut batch = StorageBatch::new();
+        let mut manager_events = Vec::new();
+        let new_contracts = update
+            .new_contracts
+            .into_iter()
+            .map(|c| (None, Contract::Offered(c), true));
+        let contracts = update.contracts.into_iter().map(|(p, c)| (p, c, false));
+        for (previous, contract, is_new) in new_contracts.chain(contracts) {
+            for event in
+                ContractEvent::from_update(previous.as_deref(), &contract, timestamp, trigger)
+            {
+                manager_events.extend(ManagerEvent::from_contract_event(&contract, &event));
+                batch = batch.with_contract_event(contract.get_temporary_id(), event);
+            }
+            batch = match contract {
+                Contract::Offered(c) if is_new => batch.with_new_contract(c),
+                c => batch.with_contract(c),
+            };
+        }
+        for (previous, channel) in update.channels {
+            if let Some(event) =
+                ChannelEvent::from_update(previous.as_deref(), &channel, timestamp, trigger)
+            {
+                if let Channel::Signed(SignedChannel {
+                    state: SignedChannelState::ClosedPunished { punishment_txid },
+                    channel_id,
+                    ..
+                }) = &channel
+                {
+                    manager_events.push(ManagerEvent::ChannelPunished {
+                        channel_id: *channel_id,
+                        punishment_txid: *punishment_txid,
+                    });
+                }
+                batch = batch.with_channel_event(channel.get_history_id(), event);
+            }
+            batch = batch.with_channel(channel);
+        }
+        if let Some(chain_monitor) = update.chain_monitor {
+            batch = batch.with_chain_monitor(chain_monitor);
+        }
+        self.store.write_batch(batch)?;
+        if flush {
+            self.store.flush()?;
+        }
+        for event in manager_events {
+            self.notify(event);
+        }
+        Ok(())
+    }
+
+    /// Passes the given event to the registered event handlers, or queues it
+    /// to be returned by [`Self::get_and_clear_pending_events`] if there are
+    /// none.
+    fn notify(&self, event: ManagerEvent) {
+        if self.event_handlers.is_empty() {
+            self.pending_events.lock().unwrap().push(event);
+            return;
+        }
+        for handler in &self.event_handlers {
+            handler.handle_event(&event);
+        }
+    }
+
+    /// Returns a [`Ping`] message to send to a peer to check that it is
+    /// reachable.
+    pub fn ping(&self) -> Ping {
+        Ping {
+            nonce: self.time.unix_time_now(),
+        }
+    }
+
+    /// Returns the events generated since the last call to this function,
+    /// unless an [`EventHandler`] was registered.
+    pub fn get_and_clear_pending_events(&self) -> Vec<ManagerEvent> {
+        std::mem::take(&mut *self.pending_events.lock().unwrap())
+    }
+
     /// Get the store from the Manager to access contracts.
     pub fn get_store(&self) -> &S {
         &self.store
     }
 
+    /// Returns the outputs of unilaterally closed channels that are waiting
+    /// for their relative time lock to expire to be claimed to the wallet,
+    /// with the id of their channel and their value.
+    pub fn get_pending_claims(&self) -> Vec<(ChannelId, OutPoint, u64)> {
+        self.chain_monitor.lock().unwrap().get_pending_claims()
+    }
+
     #[doc(hidden)]
     pub fn get_mut_store(&mut self) -> &mut S {
         &mut self.store
     }
 
+    /// Returns a JSON representation of the contract with the given id,
+    /// including its descriptors, payout curves, oracle information and
+    /// funding inputs, for inspection by debugging or support tools.
+    #[cfg(feature = "use-serde")]
+    pub fn export_contract(&self, contract_id: &ContractId) -> Result<serde_json::Value, Error> {
+        let contract = self
+            .store
+            .get_contract(contract_id)?
+            .ok_or_else(|| Error::InvalidParameters("Unknown contract id.".to_string()))?;
+        serde_json::to_value(&contract)
+            .map_err(|e| Error::InvalidState(format!("Could not serialize contract: {}", e)))
+    }
+
+    /// Replaces the application defined metadata of the contract with the
+    /// given id, which can be in any state. The metadata is kept locally and
+    /// never sent to the counter party.
+    pub fn set_contract_metadata(
+        &self,
+        contract_id: &ContractId,
+        metadata: Option<ContractMetadata>,
+    ) -> Result<(), Error> {
+        let _guard = self.id_locks.lock(*contract_id);
+        let mut contract = self
+            .store
+            .get_contract(contract_id)?
+            .ok_or_else(|| Error::InvalidParameters("Unknown contract id.".to_string()))?;
+        let previous = contract.get_state_name();
+        contract.set_metadata(metadata);
+        self.update_contract(previous, &contract, "metadata update")
+    }
+
+    /// Stores the offered contract contained in the given JSON, as produced
+    /// by [`Manager::export_contract`], and returns its temporary id. Fails if
+    /// the JSON does not describe a valid contract in offered state, or if a
+    /// contract with the same id is already stored.
+    #[cfg(feature = "use-serde")]
+    pub fn import_contract_offer(&self, value: serde_json::Value) -> Result<ContractId, Error> {
+        let offered_contract = match serde_json::from_value(value) {
+            Ok(Contract::Offered(o)) => o,
+            Ok(c) => {
+                return Err(Error::InvalidParameters(format!(
+                    "Expected an offered contract, got a {} one.",
+                    c.get_state_name()
+                )))
+            }
+            Err(e) => {
+                return Err(Error::InvalidParameters(format!(
+                    "Could not deserialize contract: {}",
+                    e
+                )))
+            }
+        };
+        offered_contract.validate()?;
+        self.create_contract(&offered_contract, "import_contract_offer")?;
+        Ok(offered_contract.id)
+    }
+
     /// Function called to pass a DlcMessage to the Manager.
     pub fn on_dlc_message(
-        &mut self,
+        &self,
         msg: &DlcMessage,
         counter_party: PublicKey,
     ) -> Result<Option<DlcMessage>, Error> {
+        enter_span!(
+            "on_dlc_message",
+            counter_party = %counter_party,
+            message_type = lightning::ln::wire::Type::type_id(msg)
+        );
+        if let Err(e) = self
+            .store
+            .update_peer_last_seen(&counter_party, self.time.unix_time_now())
+        {
+            warn!(
+                "Could not record last seen time of peer {}: {}",
+                counter_party, e
+            );
+        }
+
+        let _guards = self.id_locks.lock_all(&get_message_object_ids(msg));
         match msg {
             DlcMessage::Offer(o) => {
                 self.on_offer_message(o, counter_party)?;
                 Ok(None)
             }
-            DlcMessage::Accept(a) => Ok(Some(self.on_accept_message(a, &counter_party)?)),
+            DlcMessage::Accept(a) => self.on_accept_message(a, &counter_party),
             DlcMessage::Sign(s) => {
                 self.on_sign_message(s, &counter_party)?;
                 Ok(None)
             }
+            #[cfg(feature = "taproot")]
+            DlcMessage::AcceptTaproot(a) => Ok(Some(DlcMessage::SignTaproot(
+                self.on_accept_taproot_message(a, &counter_party)?,
+            ))),
+            #[cfg(feature = "taproot")]
+            DlcMessage::SignTaproot(s) => {
+                self.on_sign_taproot_message(s, &counter_party)?;
+                Ok(None)
+            }
             DlcMessage::OfferChannel(o) => {
                 self.on_offer_channel(o, counter_party)?;
                 Ok(None)
@@ -279,6 +1316,28 @@ where
                 self.on_renew_finalize(r, &counter_party)?;
                 Ok(None)
             }
+            DlcMessage::AddContractOffer(a) => {
+                match self.on_add_contract_offer(a, &counter_party)? {
+                    Some(msg) => Ok(Some(DlcMessage::Reject(msg))),
+                    None => Ok(None),
+                }
+            }
+            DlcMessage::SettleContractOffer(s) => {
+                match self.on_settle_contract_offer(s, &counter_party)? {
+                    Some(msg) => Ok(Some(DlcMessage::Reject(msg))),
+                    None => Ok(None),
+                }
+            }
+            DlcMessage::ContractUpdateAccept(a) => Ok(Some(DlcMessage::ContractUpdateConfirm(
+                self.on_contract_update_accept(a, &counter_party)?,
+            ))),
+            DlcMessage::ContractUpdateConfirm(c) => Ok(Some(DlcMessage::ContractUpdateFinalize(
+                self.on_contract_update_confirm(c, &counter_party)?,
+            ))),
+            DlcMessage::ContractUpdateFinalize(f) => {
+                self.on_contract_update_finalize(f, &counter_party)?;
+                Ok(None)
+            }
             DlcMessage::CollaborativeCloseOffer(c) => {
                 self.on_collaborative_close_offer(c, &counter_party)?;
                 Ok(None)
@@ -287,15 +1346,67 @@ where
                 self.on_reject(r, &counter_party)?;
                 Ok(None)
             }
-        }
-    }
-
-    /// Function called to create a new DLC. The offered contract will be stored
+            DlcMessage::Stop(s) => self.on_stop(s, &counter_party),
+            DlcMessage::Resume(r) => {
+                self.on_resume(r, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::Ping(p) => Ok(Some(DlcMessage::Pong(Pong { nonce: p.nonce }))),
+            DlcMessage::Pong(_) => Ok(None),
+            DlcMessage::UpdatePayoutOffer(u) => Ok(Some(DlcMessage::UpdatePayoutAccept(
+                self.on_update_payout_offer(u, &counter_party)?,
+            ))),
+            DlcMessage::UpdatePayoutAccept(u) => {
+                self.on_update_payout_accept(u, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::RejectOffer(r) => {
+                self.on_reject_offer(r, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::RejectChannelOffer(r) => {
+                self.on_reject_channel_offer(r, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::AmendOffer(a) => {
+                self.on_amend_offer(a, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::AmendAccept(a) => Ok(Some(DlcMessage::AmendSign(
+                self.on_amend_accept(a, &counter_party)?,
+            ))),
+            DlcMessage::AmendSign(a) => {
+                self.on_amend_sign(a, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::MutualCloseOffer(c) => {
+                self.on_mutual_close_offer(c, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::MutualCloseAccept(c) => {
+                self.on_mutual_close_accept(c, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::NetOffer(n) => {
+                self.on_net_offer(n, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::NetAccept(n) => Ok(Some(DlcMessage::NetSign(
+                self.on_net_accept(n, &counter_party)?,
+            ))),
+            DlcMessage::NetSign(n) => {
+                self.on_net_sign(n, &counter_party)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Function called to create a new DLC. The offered contract will be stored
     /// and an OfferDlc message returned.
     ///
     /// This function will fetch the oracle announcements from the oracle.
     pub fn send_offer(
-        &mut self,
+        &self,
         contract_input: &ContractInput,
         counter_party: PublicKey,
     ) -> Result<OfferDlc, Error> {
@@ -314,46 +1425,111 @@ where
     /// This function allows to pass the oracle announcements directly instead of
     /// fetching them from the oracle.
     pub fn send_offer_with_announcements(
-        &mut self,
+        &self,
         contract_input: &ContractInput,
         counter_party: PublicKey,
         oracle_announcements: Vec<Vec<OracleAnnouncement>>,
     ) -> Result<OfferDlc, Error> {
-        let (offered_contract, offer_msg) = crate::contract_updater::offer_contract(
+        let contract_input = self.get_offer_contract_input(contract_input)?;
+        self.check_fee_rate(contract_input.fee_rate)?;
+
+        let (mut offered_contract, mut offer_msg) = crate::contract_updater::offer_contract(
             &self.secp,
-            contract_input,
+            &contract_input,
             oracle_announcements,
             REFUND_DELAY,
             &counter_party,
-            &self.wallet,
+            &&self.funding_wallet(),
             &self.blockchain,
             &self.time,
             &self.signer_provider,
         )?;
 
+        if let Some(validity) = self.offer_validity {
+            let expiry = self.time.unix_time_now() + validity;
+            offered_contract.expiry = Some(expiry);
+            offer_msg.expiry = Some(expiry);
+        }
+
         offered_contract.validate()?;
 
-        self.store.create_contract(&offered_contract)?;
+        self.create_contract(&offered_contract, "offer_contract")?;
 
         Ok(offer_msg)
     }
 
+    /// Rejects the contract offer with the given id, moving the contract to
+    /// the [`Contract::Rejected`] state. Returns the [`RejectOffer`] message to
+    /// be sent to the offering node, enabling it to release the inputs it
+    /// reserved for the contract, as well as its public key.
+    pub fn reject_contract_offer(
+        &self,
+        contract_id: &ContractId,
+        reason: &str,
+    ) -> Result<(RejectOffer, PublicKey), Error> {
+        let _guard = self.id_locks.lock(*contract_id);
+        let offered_contract =
+            get_contract_in_state!(self, contract_id, Offered, None as Option<PublicKey>)?;
+
+        if offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Cannot reject contract offered by us.".to_string(),
+            ));
+        }
+
+        if self
+            .store
+            .get_offered_channels()?
+            .iter()
+            .any(|c| c.offered_contract_id == *contract_id)
+        {
+            return Err(Error::InvalidState(
+                "Contract belongs to a channel offer, use reject_channel_offer instead."
+                    .to_string(),
+            ));
+        }
+
+        let counter_party = offered_contract.counter_party;
+        self.update_contract(
+            "offered",
+            &Contract::Rejected(offered_contract),
+            "reject_contract_offer",
+        )?;
+
+        let msg = RejectOffer {
+            temporary_contract_id: *contract_id,
+            reason: reason.to_string(),
+        };
+        Ok((msg, counter_party))
+    }
+
     /// Function to call to accept a DLC for which an offer was received.
     pub fn accept_contract_offer(
-        &mut self,
+        &self,
         contract_id: &ContractId,
     ) -> Result<(ContractId, PublicKey, AcceptDlc), Error> {
+        let _guard = self.id_locks.lock(*contract_id);
         let offered_contract =
             get_contract_in_state!(self, contract_id, Offered, None as Option<PublicKey>)?;
+        self.check_offer_not_expired(&offered_contract)?;
+        offered_contract.validate_announcements(&self.secp)?;
+        // Avoid computing adaptor signatures for a message that cannot be sent.
+        estimate::estimate_establish_message_sizes(
+            offered_contract.total_collateral,
+            &offered_contract.contract_info,
+        )?
+        .check_sendable()?;
 
         let counter_party = offered_contract.counter_party;
 
+        let cancellation = self.cancellation_registry.register(*contract_id);
         let (accepted_contract, accept_msg) = accept_contract(
             &self.secp,
             &offered_contract,
-            &self.wallet,
+            &&self.funding_wallet(),
             &self.signer_provider,
             &self.blockchain,
+            &cancellation,
         )?;
 
         self.wallet.import_address(&Address::p2wsh(
@@ -363,2194 +1539,7207 @@ where
 
         let contract_id = accepted_contract.get_contract_id();
 
-        self.store
-            .update_contract(&Contract::Accepted(accepted_contract))?;
+        self.update_contract(
+            "offered",
+            &Contract::Accepted(accepted_contract),
+            "accept_contract_offer",
+        )?;
 
         Ok((contract_id, counter_party, accept_msg))
     }
 
-    /// Function to call to check the state of the currently executing DLCs and
-    /// update them if possible.
-    pub fn periodic_check(&mut self, check_channels: bool) -> Result<(), Error> {
-        self.check_signed_contracts()?;
-        self.check_confirmed_contracts()?;
-        self.check_preclosed_contracts()?;
+    /// Function to call to accept a DLC funded with a taproot output for which
+    /// an offer was received.
+    #[cfg(feature = "taproot")]
+    pub fn accept_taproot_contract_offer(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<(ContractId, PublicKey, AcceptTaprootDlc), Error> {
+        let _guard = self.id_locks.lock(*contract_id);
+        let offered_contract =
+            get_contract_in_state!(self, contract_id, Offered, None as Option<PublicKey>)?;
+        self.check_offer_not_expired(&offered_contract)?;
+        offered_contract.validate_announcements(&self.secp)?;
+        // Schnorr adaptor signatures are smaller than ECDSA ones, so the
+        // estimate is an upper bound.
+        estimate::estimate_establish_message_sizes(
+            offered_contract.total_collateral,
+            &offered_contract.contract_info,
+        )?
+        .check_sendable()?;
 
-        if check_channels {
-            self.channel_checks()?;
-        }
+        let counter_party = offered_contract.counter_party;
 
-        Ok(())
-    }
+        let cancellation = self.cancellation_registry.register(*contract_id);
+        let (accepted_contract, accept_msg) = crate::taproot_updater::accept_contract(
+            &self.secp,
+            &offered_contract,
+            &&self.funding_wallet(),
+            &self.signer_provider,
+            &self.blockchain,
+            &cancellation,
+        )?;
 
-    fn on_offer_message(
-        &mut self,
-        offered_message: &OfferDlc,
-        counter_party: PublicKey,
-    ) -> Result<(), Error> {
-        offered_message.validate(&self.secp, REFUND_DELAY, REFUND_DELAY * 2)?;
-        let keys_id = self
-            .signer_provider
-            .derive_signer_key_id(false, offered_message.temporary_contract_id);
-        let contract: OfferedContract =
-            OfferedContract::try_from_offer_dlc(offered_message, counter_party, keys_id)?;
-        contract.validate()?;
+        self.import_taproot_fund_address(&accepted_contract)?;
 
-        if self.store.get_contract(&contract.id)?.is_some() {
-            return Err(Error::InvalidParameters(
-                "Contract with identical id already exists".to_string(),
-            ));
-        }
+        let contract_id = accepted_contract.get_contract_id();
 
-        self.store.create_contract(&contract)?;
+        self.update_contract(
+            "offered",
+            &Contract::Accepted(accepted_contract),
+            "accept_taproot_contract_offer",
+        )?;
 
-        Ok(())
+        Ok((contract_id, counter_party, accept_msg))
     }
 
-    fn on_accept_message(
-        &mut self,
-        accept_msg: &AcceptDlc,
-        counter_party: &PublicKey,
-    ) -> Result<DlcMessage, Error> {
-        let offered_contract = get_contract_in_state!(
-            self,
-            &accept_msg.temporary_contract_id,
-            Offered,
-            Some(*counter_party)
-        )?;
+    /// Function to call to accept a DLC for which an offer was received, with
+    /// the signatures of the local party produced by an external signer.
+    /// Funding inputs are selected and reserved, and the returned
+    /// [`ContractSigningRequest`] is to be passed to the signer holding the
+    /// funding key derived from its `keys_id`, the resulting signatures being
+    /// given to [`Self::submit_contract_signatures`] to obtain the
+    /// [`AcceptDlc`] message. Pending requests are not persisted across
+    /// restarts.
+    pub fn prepare_accept_contract_offer(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<ContractSigningRequest, Error> {
+        let _guard = self.id_locks.lock(*contract_id);
+        let offered_contract =
+            get_contract_in_state!(self, contract_id, Offered, None as Option<PublicKey>)?;
+        self.check_offer_not_expired(&offered_contract)?;
+        offered_contract.validate_announcements(&self.secp)?;
+        estimate::estimate_establish_message_sizes(
+            offered_contract.total_collateral,
+            &offered_contract.contract_info,
+        )?
+        .check_sendable()?;
 
-        let (signed_contract, signed_msg) = match verify_accepted_and_sign_contract(
+        if self
+            .pending_signing
+            .lock()
+            .unwrap()
+            .contains_key(contract_id)
+        {
+            return Err(Error::InvalidState(
+                "Contract is already waiting for signatures".to_string(),
+            ));
+        }
+
+        let cancellation = self.cancellation_registry.register(*contract_id);
+        let unsigned_contract = prepare_accept_contract(
             &self.secp,
             &offered_contract,
-            accept_msg,
-            &self.wallet,
+            &&self.funding_wallet(),
             &self.signer_provider,
+            &self.blockchain,
+            &cancellation,
+        )?;
+
+        let request = get_contract_signing_request(
+            &self.secp,
+            &offered_contract,
+            &unsigned_contract.accept_params.fund_pubkey,
+            &unsigned_contract.adaptor_infos,
+            &unsigned_contract.dlc_transactions,
+        )?;
+
+        self.pending_signing.lock().unwrap().insert(
+            *contract_id,
+            (PendingSigning::Accept(unsigned_contract), request.clone()),
+        );
+
+        Ok(request)
+    }
+
+    /// Returns the signing requests of contracts waiting for signatures from
+    /// an external signer.
+    pub fn get_signing_requests(&self) -> Vec<ContractSigningRequest> {
+        self.pending_signing
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(_, request)| request.clone())
+            .collect()
+    }
+
+    /// Completes the contract with the given id using the signatures produced
+    /// by an external signer for its [`ContractSigningRequest`]. Returns the
+    /// id of the contract along with the message to send to the counter party
+    /// and its node id. The request is kept if the signatures are invalid, and
+    /// discarded if the contract is no longer offered.
+    pub fn submit_contract_signatures(
+        &self,
+        contract_id: &ContractId,
+        signatures: &ContractSignatures,
+    ) -> Result<(ContractId, PublicKey, DlcMessage), Error> {
+        let _guard = self.id_locks.lock(*contract_id);
+        if !self
+            .pending_signing
+            .lock()
+            .unwrap()
+            .contains_key(contract_id)
+        {
+            return Err(Error::InvalidParameters(
+                "No signing request for contract".to_string(),
+            ));
+        }
+        // The contract could have been accepted or rejected since the request
+        // was created, in which case the request is stale.
+        if !matches!(
+            self.store.get_contract(contract_id)?,
+            Some(Contract::Offered(_))
         ) {
-            Ok(contract) => contract,
-            Err(e) => return self.accept_fail_on_error(offered_contract, accept_msg.clone(), e),
+            self.discard_signing_request(contract_id)?;
+            return Err(Error::InvalidState(
+                "Contract is no longer in the offered state".to_string(),
+            ));
+        }
+
+        let mut pending_signing = self.pending_signing.lock().unwrap();
+        let (pending, _) = pending_signing.get(contract_id).ok_or_else(|| {
+            Error::InvalidParameters("No signing request for contract".to_string())
+        })?;
+
+        let (previous, contract, msg) = match pending {
+            PendingSigning::Accept(unsigned_contract) => {
+                let (accepted_contract, accept_msg) =
+                    accept_contract_with_signatures(&self.secp, unsigned_contract, signatures)?;
+                (
+                    "offered",
+                    Contract::Accepted(accepted_contract),
+                    DlcMessage::Accept(accept_msg),
+                )
+            }
+            PendingSigning::Sign(accepted_contract) => {
+                let (signed_contract, sign_msg) = sign_contract_with_signatures(
+                    &self.secp,
+                    accepted_contract,
+                    signatures,
+                    &self.wallet,
+                )?;
+                (
+                    "offered",
+                    Contract::Signed(signed_contract),
+                    DlcMessage::Sign(sign_msg),
+                )
+            }
         };
+        pending_signing.remove(contract_id);
+        drop(pending_signing);
 
+        let (funding_script_pubkey, counter_party) = match &contract {
+            Contract::Accepted(c) => (
+                &c.dlc_transactions.funding_script_pubkey,
+                c.offered_contract.counter_party,
+            ),
+            Contract::Signed(c) => (
+                &c.accepted_contract.dlc_transactions.funding_script_pubkey,
+                c.accepted_contract.offered_contract.counter_party,
+            ),
+            _ => unreachable!(),
+        };
         self.wallet.import_address(&Address::p2wsh(
-            &signed_contract
-                .accepted_contract
-                .dlc_transactions
-                .funding_script_pubkey,
+            funding_script_pubkey,
             self.blockchain.get_network()?,
         ))?;
 
-        self.store
-            .update_contract(&Contract::Signed(signed_contract))?;
+        let new_contract_id = contract.get_id();
+        self.update_contract(previous, &contract, "submit_contract_signatures")?;
 
-        Ok(DlcMessage::Sign(signed_msg))
+        Ok((new_contract_id, counter_party, msg))
     }
 
-    fn on_sign_message(
-        &mut self,
-        sign_message: &SignDlc,
-        peer_id: &PublicKey,
-    ) -> Result<(), Error> {
-        let accepted_contract =
-            get_contract_in_state!(self, &sign_message.contract_id, Accepted, Some(*peer_id))?;
-
-        let (signed_contract, fund_tx) = match crate::contract_updater::verify_signed_contract(
-            &self.secp,
-            &accepted_contract,
-            sign_message,
-            &self.wallet,
-        ) {
-            Ok(contract) => contract,
-            Err(e) => return self.sign_fail_on_error(accepted_contract, sign_message.clone(), e),
-        };
-
-        self.store
-            .update_contract(&Contract::Signed(signed_contract))?;
+    /// Discards the signing request of the contract with the given id,
+    /// releasing the funding inputs reserved to accept it. The contract is
+    /// left in the offered state.
+    pub fn discard_signing_request(&self, contract_id: &ContractId) -> Result<(), Error> {
+        let _guard = self.id_locks.lock(*contract_id);
+        let (pending, _) = self
+            .pending_signing
+            .lock()
+            .unwrap()
+            .remove(contract_id)
+            .ok_or_else(|| {
+                Error::InvalidParameters("No signing request for contract".to_string())
+            })?;
 
-        self.blockchain.send_transaction(&fund_tx)?;
+        if let PendingSigning::Accept(unsigned_contract) = pending {
+            release_party_utxos(&self.wallet, &unsigned_contract.accept_params)?;
+        }
 
         Ok(())
     }
 
-    fn get_oracle_announcements(
+    /// Fee bumps the unconfirmed CET of the contract with the given id by
+    /// broadcasting a transaction spending the local party's payout output to
+    /// a new wallet address (child pays for parent). The CET and the returned
+    /// child transaction together pay the given fee rate, in satoshis per
+    /// virtual byte.
+    ///
+    /// Buffer transactions of DLC channels have a single output, only
+    /// spendable by their time locked CET, and cannot be bumped this way.
+    pub fn bump_close_fee(
         &self,
-        oracle_inputs: &OracleInput,
-    ) -> Result<Vec<OracleAnnouncement>, Error> {
-        let mut announcements = Vec::new();
-        for pubkey in &oracle_inputs.public_keys {
-            let oracle = self
-                .oracles
-                .get(pubkey)
-                .ok_or_else(|| Error::InvalidParameters("Unknown oracle public key".to_string()))?;
-            announcements.push(oracle.get_announcement(&oracle_inputs.event_id)?.clone());
+        contract_id: &ContractId,
+        target_fee_rate_per_vb: u64,
+    ) -> Result<Transaction, Error> {
+        self.check_fee_rate(target_fee_rate_per_vb)?;
+        let contract =
+            get_contract_in_state!(self, contract_id, PreClosed, None as Option<PublicKey>)?;
+        let cet = &contract.signed_cet;
+
+        let mut input_value = 0;
+        for input in &cet.input {
+            let prev_tx = self
+                .blockchain
+                .get_transaction(&input.previous_output.txid)?;
+            input_value += prev_tx
+                .output
+                .get(input.previous_output.vout as usize)
+                .ok_or_else(|| Error::InvalidState("CET input not found".to_string()))?
+                .value;
         }
+        let cet_fee = input_value - cet.output.iter().map(|x| x.value).sum::<u64>();
 
-        Ok(announcements)
-    }
+        let accepted_contract = &contract.signed_contract.accepted_contract;
+        let payout_script_pubkey = if accepted_contract.offered_contract.is_offer_party {
+            &accepted_contract
+                .offered_contract
+                .offer_params
+                .payout_script_pubkey
+        } else {
+            &accepted_contract.accept_params.payout_script_pubkey
+        };
 
-    fn sign_fail_on_error<R>(
-        &mut self,
-        accepted_contract: AcceptedContract,
-        sign_message: SignDlc,
-        e: Error,
-    ) -> Result<R, Error> {
-        error!("Error in on_sign {}", e);
-        self.store
-            .update_contract(&Contract::FailedSign(FailedSignContract {
-                accepted_contract,
-                sign_message,
-                error_message: e.to_string(),
-            }))?;
-        Err(e)
-    }
+        let child = create_cpfp_transaction(
+            cet,
+            cet_fee,
+            payout_script_pubkey,
+            target_fee_rate_per_vb,
+            &self.wallet,
+        )?;
+        self.blockchain.send_transaction(&child)?;
 
-    fn accept_fail_on_error<R>(
-        &mut self,
-        offered_contract: OfferedContract,
-        accept_message: AcceptDlc,
-        e: Error,
-    ) -> Result<R, Error> {
-        error!("Error in on_accept {}", e);
-        self.store
-            .update_contract(&Contract::FailedAccept(FailedAcceptContract {
-                offered_contract,
-                accept_message,
-                error_message: e.to_string(),
-            }))?;
-        Err(e)
+        Ok(child)
     }
 
-    fn check_signed_contract(&mut self, contract: &SignedContract) -> Result<(), Error> {
-        let confirmations = self.blockchain.get_transaction_confirmations(
-            &contract.accepted_contract.dlc_transactions.fund.txid(),
-        )?;
-        if confirmations >= NB_CONFIRMATIONS {
-            self.store
-                .update_contract(&Contract::Confirmed(contract.clone()))?;
-        }
-        Ok(())
+    /// Function to call to check the state of the currently executing DLCs and
+    /// update them if possible. The attestations for the matured events of
+    /// confirmed contracts are fetched once at the start of the check.
+    pub fn periodic_check(&self, check_channels: bool) -> Result<(), Error> {
+        enter_span!("periodic_check", check_channels);
+        let attestations = self.fetch_attestations()?;
+        self.periodic_check_with_attestations(check_channels, &attestations)
     }
 
-    fn check_signed_contracts(&mut self) -> Result<(), Error> {
-        for c in self.store.get_signed_contracts()? {
-            if let Err(e) = self.check_signed_contract(&c) {
-                error!(
-                    "Error checking confirmed contract {}: {}",
-                    c.accepted_contract.get_contract_id_string(),
-                    e
-                )
+    /// Runs the periodic check using only the given attestations, so that
+    /// each attestation is fetched once per run even when many contracts
+    /// depend on the same event.
+    fn periodic_check_with_attestations(
+        &self,
+        check_channels: bool,
+        attestations: &AttestationCache,
+    ) -> Result<(), Error> {
+        let deadline = self
+            .periodic_check_budget
+            .map(|budget| Instant::now() + budget);
+
+        // Contracts that can be closed or refunded and channels that timed out
+        // are processed first, as delaying them can result in a loss of funds.
+        self.check_confirmed_contracts(deadline, attestations)?;
+        if check_channels {
+            if let Err(e) = self.check_for_timed_out_channels() {
+                error!("Error checking timed out channels {}", e);
             }
         }
 
+        self.check_signed_contracts(deadline)?;
+        self.check_preclosed_contracts(deadline)?;
+        self.check_expired_offers()?;
+
+        if check_channels {
+            self.channel_checks(attestations)?;
+        }
+
+        self.check_peer_liveness()?;
+
         Ok(())
     }
 
-    fn check_confirmed_contracts(&mut self) -> Result<(), Error> {
-        for c in self.store.get_confirmed_contracts()? {
-            // Confirmed contracts from channel are processed in channel specific methods.
-            if c.channel_id.is_some() {
+    /// Cross-checks the store against the blockchain to repair the
+    /// inconsistencies that a crash can leave behind. Should be called once at
+    /// startup, before processing any message or running the periodic check.
+    /// Contracts whose last update was interrupted are stored again under the
+    /// id derived from their fund transaction, signed contracts whose fund
+    /// transaction confirmed are moved to the confirmed state while the
+    /// unconfirmed ones are broadcast again, the current transactions of signed
+    /// channels are watched again, and confirmed contracts closed or refunded
+    /// while offline are resolved. If the blockchain provider cannot look up
+    /// the spender of an output, the CETs of the contracts that reached their
+    /// maturity are looked up one by one, which can take a while for contracts
+    /// with many outcomes.
+    pub fn recover(&self) -> Result<RecoveryReport, Error> {
+        enter_span!("recover");
+        let mut report = RecoveryReport::default();
+        self.recover_contract_ids(&mut report)?;
+        self.recover_confirmed_contracts(&mut report)?;
+        self.recover_channel_watches(&mut report)?;
+        self.recover_closed_contracts(&mut report)?;
+        info!("Recovery completed: {:?}", report);
+        Ok(report)
+    }
+
+    fn recover_contract_ids(&self, report: &mut RecoveryReport) -> Result<(), Error> {
+        for contract in self.store.get_contracts()? {
+            let accepted_contract = match &contract {
+                Contract::Accepted(c) => c,
+                Contract::Signed(c) => &c.accepted_contract,
+                _ => continue,
+            };
+            let contract_id = accepted_contract.get_contract_id();
+            let temporary_id = accepted_contract.offered_contract.id;
+            // Storing an accepted or signed contract replaces the offer it
+            // originates from, so finding both means the update was interrupted.
+            let is_stored = matches!(
+                self.store.get_contract(&contract_id)?,
+                Some(c) if c.get_temporary_id() == temporary_id
+            );
+            let has_stale_offer = matches!(
+                self.store.get_contract(&temporary_id)?,
+                Some(Contract::Offered(_))
+            );
+            if is_stored && !has_stale_offer {
                 continue;
             }
-            if let Err(e) = self.check_confirmed_contract(&c) {
-                error!(
-                    "Error checking confirmed contract {}: {}",
-                    c.accepted_contract.get_contract_id_string(),
-                    e
-                )
-            }
+
+            warn!(
+                "Restoring contract {} interrupted in state {}",
+                contract_id.to_lower_hex_string(),
+                contract.get_state_name()
+            );
+            // The events of the interrupted update were written along with it.
+            self.update_contract(contract.get_state_name(), &contract, "recovery")?;
+            report.restored_contract_ids.push(contract_id);
         }
 
         Ok(())
     }
 
-    fn get_closable_contract_info<'a>(
-        &'a self,
-        contract: &'a SignedContract,
-    ) -> ClosableContractInfo<'a> {
-        let contract_infos = &contract.accepted_contract.offered_contract.contract_info;
-        let adaptor_infos = &contract.accepted_contract.adaptor_infos;
-        for (contract_info, adaptor_info) in contract_infos.iter().zip(adaptor_infos.iter()) {
-            let matured: Vec<_> = contract_info
-                .oracle_announcements
-                .iter()
-                .filter(|x| {
-                    (x.oracle_event.event_maturity_epoch as u64) <= self.time.unix_time_now()
-                })
-                .enumerate()
-                .collect();
-            if matured.len() >= contract_info.threshold {
-                let attestations: Vec<_> = matured
-                    .iter()
-                    .filter_map(|(i, announcement)| {
-                        let oracle = self.oracles.get(&announcement.oracle_public_key)?;
-                        Some((
-                            *i,
-                            oracle
-                                .get_attestation(&announcement.oracle_event.event_id)
-                                .ok()?,
-                        ))
-                    })
-                    .collect();
-                if attestations.len() >= contract_info.threshold {
-                    return Some((contract_info, adaptor_info, attestations));
+    fn recover_confirmed_contracts(&self, report: &mut RecoveryReport) -> Result<(), Error> {
+        for contract in self.store.get_signed_contracts()? {
+            let contract_id = contract.accepted_contract.get_contract_id();
+            let confirmations = match self.blockchain.get_transaction_confirmations(
+                &contract.accepted_contract.dlc_transactions.fund.txid(),
+            ) {
+                Ok(confirmations) => confirmations,
+                Err(e) => {
+                    error!(
+                        "Error checking fund transaction of contract {}: {}",
+                        contract_id.to_lower_hex_string(),
+                        e
+                    );
+                    continue;
                 }
+            };
+            if confirmations >= NB_CONFIRMATIONS {
+                self.update_contract("signed", &Contract::Confirmed(contract.clone()), "recovery")?;
+                self.check_amended_contract(&contract)?;
+                report.confirmed_contracts.push(contract_id);
+            } else if confirmations == 0 {
+                self.rebroadcast_fund_transaction(&contract, report);
             }
         }
-        None
+
+        Ok(())
     }
 
-    fn check_confirmed_contract(&mut self, contract: &SignedContract) -> Result<(), Error> {
-        let closable_contract_info = self.get_closable_contract_info(contract);
-        if let Some((contract_info, adaptor_info, attestations)) = closable_contract_info {
-            let offer = &contract.accepted_contract.offered_contract;
-            let signer = self.signer_provider.derive_contract_signer(offer.keys_id)?;
-            let cet = crate::contract_updater::get_signed_cet(
-                &self.secp,
-                contract,
-                contract_info,
-                adaptor_info,
-                &attestations,
-                &signer,
-            )?;
-            match self.close_contract(
-                contract,
-                cet,
-                attestations.iter().map(|x| x.1.clone()).collect(),
-            ) {
-                Ok(closed_contract) => {
-                    self.store.update_contract(&closed_contract)?;
-                    return Ok(());
-                }
+    /// Broadcasts again the fund transaction of a signed contract that is not
+    /// confirmed, in case it was dropped from the mempool or the process
+    /// stopped before broadcasting it. Nodes reject the transaction if it is
+    /// already in their mempool, in which case there is nothing to do.
+    fn rebroadcast_fund_transaction(&self, contract: &SignedContract, report: &mut RecoveryReport) {
+        let contract_id = contract.accepted_contract.get_contract_id_string();
+        let fund_tx =
+            match crate::contract_updater::get_signed_fund_transaction(contract, &self.wallet) {
+                Ok(fund_tx) => fund_tx,
                 Err(e) => {
-                    warn!(
-                        "Failed to close contract {}: {}",
-                        contract.accepted_contract.get_contract_id_string(),
-                        e
+                    debug!(
+                        "Not rebroadcasting fund transaction of {}: {}",
+                        contract_id, e
                     );
-                    return Err(e);
+                    return;
                 }
+            };
+        match self.blockchain.send_transaction(&fund_tx) {
+            Ok(()) => {
+                warn!(
+                    "Broadcast again fund transaction of contract {}",
+                    contract_id
+                );
+                report
+                    .rebroadcast_contracts
+                    .push(contract.accepted_contract.get_contract_id());
             }
+            Err(e) => debug!(
+                "Fund transaction of contract {} not broadcast again: {}",
+                contract_id, e
+            ),
         }
-
-        self.check_refund(contract)?;
-
-        Ok(())
     }
 
-    /// Manually close a contract with the oracle attestations.
-    pub fn close_confirmed_contract(
-        &mut self,
-        contract_id: &ContractId,
-        attestations: Vec<(usize, OracleAttestation)>,
-    ) -> Result<Contract, Error> {
-        let contract = get_contract_in_state!(self, contract_id, Confirmed, None::<PublicKey>)?;
-        let contract_infos = &contract.accepted_contract.offered_contract.contract_info;
-        let adaptor_infos = &contract.accepted_contract.adaptor_infos;
+    fn recover_channel_watches(&self, report: &mut RecoveryReport) -> Result<(), Error> {
+        let signed_channels = self.store.get_signed_channels(None)?;
+        let mut chain_monitor = self.chain_monitor.lock().unwrap();
+        for signed_channel in signed_channels {
+            let (txid, tx_type) = match &signed_channel.state {
+                SignedChannelState::Established {
+                    buffer_transaction, ..
+                }
+                | SignedChannelState::ContractsEstablished {
+                    buffer_transaction, ..
+                } => (buffer_transaction.txid(), TxType::Current),
+                SignedChannelState::CollaborativeCloseOffered { close_tx, .. } => {
+                    (close_tx.txid(), TxType::CollaborativeClose)
+                }
+                _ => continue,
+            };
+            if chain_monitor.is_watched(&txid) {
+                continue;
+            }
 
-        // find the contract info that matches the attestations
-        if let Some((contract_info, adaptor_info)) =
-            contract_infos.iter().zip(adaptor_infos).find(|(c, _)| {
-                let matches = attestations
-                    .iter()
-                    .filter(|(i, a)| {
-                        c.oracle_announcements[*i].oracle_event.oracle_nonces == a.nonces()
-                    })
-                    .count();
+            warn!(
+                "Watching again transaction {} of channel {}",
+                txid,
+                signed_channel.channel_id.to_lower_hex_string()
+            );
+            chain_monitor.add_tx(
+                txid,
+                ChannelInfo {
+                    channel_id: signed_channel.channel_id,
+                    tx_type,
+                },
+            );
+            report.rewatched_channels.push(signed_channel.channel_id);
+        }
 
-                matches >= c.threshold
-            })
-        {
-            let offer = &contract.accepted_contract.offered_contract;
-            let signer = self.signer_provider.derive_contract_signer(offer.keys_id)?;
-            let cet = crate::contract_updater::get_signed_cet(
-                &self.secp,
-                &contract,
-                contract_info,
-                adaptor_info,
-                &attestations,
-                &signer,
-            )?;
+        if !report.rewatched_channels.is_empty() {
+            self.store.persist_chain_monitor(&chain_monitor)?;
+        }
 
-            // Check that the lock time has passed
-            let time = bitcoin::absolute::Time::from_consensus(self.time.unix_time_now() as u32)
-                .expect("Time is not in valid range. This should never happen.");
-            let height = Height::from_consensus(self.blockchain.get_blockchain_height()? as u32)
-                .expect("Height is not in valid range. This should never happen.");
-            let locktime = cet.lock_time;
+        Ok(())
+    }
 
-            if !locktime.is_satisfied_by(height, time) {
-                return Err(Error::InvalidState(
-                    "CET lock time has not passed yet".to_string(),
-                ));
+    fn recover_closed_contracts(&self, report: &mut RecoveryReport) -> Result<(), Error> {
+        let now = self.time.unix_time_now();
+        // CETs and refund transactions cannot be included in a block before
+        // their lock time, and the contracts of channels are closed through
+        // their channel.
+        let contracts = self
+            .store
+            .get_confirmed_contracts()?
+            .into_iter()
+            .filter(|c| c.channel_id.is_none() && get_contract_deadline(c) <= now);
+        for contract in contracts {
+            match self.recover_closed_contract(&contract) {
+                Ok(true) => report
+                    .resolved_contracts
+                    .push(contract.accepted_contract.get_contract_id()),
+                Ok(false) => {}
+                Err(e) => error!(
+                    "Error looking for the closing transaction of contract {}: {}",
+                    contract.accepted_contract.get_contract_id_string(),
+                    e
+                ),
             }
+        }
 
-            match self.close_contract(
-                &contract,
-                cet,
-                attestations.into_iter().map(|x| x.1).collect(),
-            ) {
-                Ok(closed_contract) => {
-                    self.store.update_contract(&closed_contract)?;
-                    Ok(closed_contract)
-                }
-                Err(e) => {
+        Ok(())
+    }
+
+    /// Looks for a CET or refund transaction of the given contract in the
+    /// blockchain and updates the contract accordingly if one is found. The
+    /// spender of the fund output is looked up directly if the blockchain
+    /// provider supports it, otherwise each CET is looked up in turn.
+    fn recover_closed_contract(&self, contract: &SignedContract) -> Result<bool, Error> {
+        let dlc_transactions = &contract.accepted_contract.dlc_transactions;
+        let mut closing_txs = dlc_transactions
+            .cets
+            .iter()
+            .chain(std::iter::once(&dlc_transactions.refund));
+        let candidates: Vec<&Transaction> = match self
+            .blockchain
+            .get_output_spender(&dlc_transactions.get_fund_outpoint())
+        {
+            Ok(None) => return Ok(false),
+            Ok(Some(spender)) => match closing_txs.find(|tx| tx.txid() == spender) {
+                Some(tx) => vec![tx],
+                None => {
                     warn!(
-                        "Failed to close contract {}: {e}",
-                        contract.accepted_contract.get_contract_id_string()
+                        "Fund output of contract {} spent by unknown transaction {}",
+                        contract.accepted_contract.get_contract_id_string(),
+                        spender
                     );
-                    Err(e)
+                    return Ok(false);
                 }
+            },
+            Err(e) => {
+                debug!("Could not look up the spender of the fund output: {}", e);
+                closing_txs.collect()
             }
-        } else {
-            Err(Error::InvalidState(
-                "Attestations did not match contract infos".to_string(),
-            ))
+        };
+        for tx in candidates {
+            let txid = tx.txid();
+            let confirmations = self.blockchain.get_transaction_confirmations(&txid)?;
+            if confirmations == 0 {
+                continue;
+            }
+            let closing_tx = self.blockchain.get_transaction(&txid)?;
+            self.on_counterparty_close(contract, closing_tx, confirmations)?;
+            return Ok(true);
         }
+
+        Ok(false)
     }
 
-    fn check_preclosed_contracts(&mut self) -> Result<(), Error> {
-        for c in self.store.get_preclosed_contracts()? {
-            if let Err(e) = self.check_preclosed_contract(&c) {
-                error!(
-                    "Error checking pre-closed contract {}: {}",
-                    c.signed_contract.accepted_contract.get_contract_id_string(),
-                    e
-                )
+    fn check_expired_offers(&self) -> Result<(), Error> {
+        let now = self.time.unix_time_now();
+        for offered_contract in self.store.get_contract_offers()? {
+            if !offered_contract.is_expired(now) {
+                continue;
+            }
+            let (_guard, offered_contract) =
+                match lock_contract_in_state!(self, offered_contract.id, Offered) {
+                    Some(locked) => locked,
+                    None => continue,
+                };
+            if !offered_contract.is_expired(now)
+                || self
+                    .pending_signing
+                    .lock()
+                    .unwrap()
+                    .contains_key(&offered_contract.id)
+            {
+                continue;
+            }
+
+            if offered_contract.is_offer_party {
+                release_party_utxos(&self.wallet, &offered_contract.offer_params)?;
             }
+
+            info!(
+                "Offer {} expired",
+                offered_contract.id.to_lower_hex_string()
+            );
+            self.update_contract(
+                "offered",
+                &Contract::Expired(offered_contract),
+                "periodic_check",
+            )?;
         }
 
         Ok(())
     }
 
-    fn check_preclosed_contract(&mut self, contract: &PreClosedContract) -> Result<(), Error> {
-        let broadcasted_txid = contract.signed_cet.txid();
-        let confirmations = self
-            .blockchain
-            .get_transaction_confirmations(&broadcasted_txid)?;
-        if confirmations >= NB_CONFIRMATIONS {
-            let closed_contract = ClosedContract {
-                attestations: contract.attestations.clone(),
-                signed_cet: Some(contract.signed_cet.clone()),
-                contract_id: contract.signed_contract.accepted_contract.get_contract_id(),
-                temporary_contract_id: contract
-                    .signed_contract
-                    .accepted_contract
-                    .offered_contract
-                    .id,
-                counter_party_id: contract
-                    .signed_contract
-                    .accepted_contract
-                    .offered_contract
-                    .counter_party,
-                pnl: contract
-                    .signed_contract
-                    .accepted_contract
-                    .compute_pnl(&contract.signed_cet),
+    fn check_peer_liveness(&self) -> Result<(), Error> {
+        let config = match self.peer_liveness {
+            Some(config) => config,
+            None => return Ok(()),
+        };
+        let now = self.time.unix_time_now();
+
+        let mut contracts = self.store.get_signed_contracts()?;
+        contracts.extend(self.store.get_confirmed_contracts()?);
+        let mut at_risk: HashMap<PublicKey, Vec<ContractId>> = HashMap::new();
+        for contract in contracts {
+            let offered_contract = &contract.accepted_contract.offered_contract;
+            let maturity = offered_contract
+                .contract_info
+                .iter()
+                .flat_map(|x| &x.oracle_announcements)
+                .map(|x| x.oracle_event.event_maturity_epoch as u64)
+                .min();
+            if maturity.map_or(false, |m| m <= now.saturating_add(config.maturity_window)) {
+                at_risk
+                    .entry(offered_contract.counter_party)
+                    .or_default()
+                    .push(contract.accepted_contract.get_contract_id());
+            }
+        }
+
+        let mut events = Vec::new();
+        let mut reported = self.reported_unreachable_peers.lock().unwrap();
+        reported.retain(|peer_id, _| at_risk.contains_key(peer_id));
+        for (peer_id, contract_ids) in at_risk {
+            // Peers for which the storage did not record any message are not
+            // reported, as storages are not required to track them.
+            let last_seen = match self.store.get_peer_last_seen(&peer_id)? {
+                Some(last_seen) => last_seen,
+                None => continue,
             };
-            self.store
-                .update_contract(&Contract::Closed(closed_contract))?;
+            if now.saturating_sub(last_seen) > config.unreachable_after
+                && reported.insert(peer_id, last_seen) != Some(last_seen)
+            {
+                warn!(
+                    "Peer {} was last seen at {} and has {} contract(s) approaching maturity",
+                    peer_id,
+                    last_seen,
+                    contract_ids.len()
+                );
+                events.push(ManagerEvent::PeerUnreachable {
+                    peer_id,
+                    last_seen,
+                    contract_ids,
+                });
+            }
+        }
+        drop(reported);
+
+        for event in events {
+            self.notify(event);
         }
 
         Ok(())
     }
 
-    fn close_contract(
-        &mut self,
-        contract: &SignedContract,
-        signed_cet: Transaction,
-        attestations: Vec<OracleAttestation>,
-    ) -> Result<Contract, Error> {
-        let confirmations = self
-            .blockchain
-            .get_transaction_confirmations(&signed_cet.txid())?;
-
-        if confirmations < 1 {
-            // TODO(tibo): if this fails because another tx is already in
-            // mempool or blockchain, we might have been cheated. There is
-            // not much to be done apart from possibly extracting a fraud
-            // proof but ideally it should be handled.
-            self.blockchain.send_transaction(&signed_cet)?;
-
-            let preclosed_contract = PreClosedContract {
-                signed_contract: contract.clone(),
-                attestations: Some(attestations),
-                signed_cet,
-            };
+    fn check_message_age(&self, timestamp: Option<u64>) -> Result<(), Error> {
+        if let Some(max_age) = self.max_message_age {
+            let timestamp = timestamp.ok_or_else(|| {
+                Error::InvalidParameters("Offer does not include a timestamp".to_string())
+            })?;
+            if self.time.unix_time_now().saturating_sub(timestamp) > max_age {
+                return Err(Error::InvalidParameters(format!(
+                    "Offer created at {} is older than the maximum allowed age of {} seconds",
+                    timestamp, max_age
+                )));
+            }
+        }
 
-            return Ok(Contract::PreClosed(preclosed_contract));
-        } else if confirmations < NB_CONFIRMATIONS {
-            let preclosed_contract = PreClosedContract {
-                signed_contract: contract.clone(),
-                attestations: Some(attestations),
-                signed_cet,
-            };
+        Ok(())
+    }
 
-            return Ok(Contract::PreClosed(preclosed_contract));
+    fn check_offer_not_expired(&self, offered_contract: &OfferedContract) -> Result<(), Error> {
+        if offered_contract.is_expired(self.time.unix_time_now()) {
+            return Err(Error::InvalidState("Offer has expired".to_string()));
         }
 
-        let closed_contract = ClosedContract {
-            attestations: Some(attestations.to_vec()),
-            pnl: contract.accepted_contract.compute_pnl(&signed_cet),
-            signed_cet: Some(signed_cet),
-            contract_id: contract.accepted_contract.get_contract_id(),
-            temporary_contract_id: contract.accepted_contract.offered_contract.id,
-            counter_party_id: contract.accepted_contract.offered_contract.counter_party,
-        };
-
-        Ok(Contract::Closed(closed_contract))
+        Ok(())
     }
 
-    fn check_refund(&mut self, contract: &SignedContract) -> Result<(), Error> {
-        // TODO(tibo): should check for confirmation of refund before updating state
-        if contract
-            .accepted_contract
-            .dlc_transactions
-            .refund
-            .lock_time
-            .to_consensus_u32() as u64
-            <= self.time.unix_time_now()
-        {
-            let accepted_contract = &contract.accepted_contract;
-            let refund = accepted_contract.dlc_transactions.refund.clone();
-            let confirmations = self
-                .blockchain
-                .get_transaction_confirmations(&refund.txid())?;
-            if confirmations == 0 {
-                let offer = &contract.accepted_contract.offered_contract;
-                let signer = self.signer_provider.derive_contract_signer(offer.keys_id)?;
-                let refund =
-                    crate::contract_updater::get_signed_refund(&self.secp, contract, &signer)?;
-                self.blockchain.send_transaction(&refund)?;
+    fn on_offer_message(
+        &self,
+        offered_message: &OfferDlc,
+        counter_party: PublicKey,
+    ) -> Result<(), Error> {
+        enter_span!(
+            "on_offer_message",
+            contract_id = %offered_message.temporary_contract_id.to_lower_hex_string()
+        );
+        self.check_message_age(offered_message.timestamp)?;
+        self.check_fee_rate(offered_message.fee_rate_per_vb)?;
+        if let Some(node_id) = &self.node_id {
+            offered_message.validate_recipient(node_id).map_err(|_| {
+                Error::InvalidParameters("Offer is bound to a different recipient".to_string())
+            })?;
+        }
+        let keys_id = self
+            .signer_provider
+            .derive_signer_key_id(false, offered_message.temporary_contract_id);
+        let contract: OfferedContract =
+            OfferedContract::try_from_offer_dlc(offered_message, counter_party, keys_id)?;
+        // Validate the announcements first to report why they are invalid.
+        contract.validate_announcements(&self.secp)?;
+        offered_message.validate(&self.secp, REFUND_DELAY, REFUND_DELAY * 2)?;
+        contract.validate()?;
+        self.check_offer_not_expired(&contract)?;
+        self.check_self_dealing(&contract, &offered_message.funding_pubkey)?;
+
+        // Fails with `Error::AlreadyExists` if the offer is replayed.
+        self.create_contract(&contract, "OfferDlc")?;
+
+        // The counter party can resend an offer with a new temporary id, for
+        // example after a reconnection. Only the latest one is kept as it is
+        // the one the counter party expects an answer for.
+        let offer_hash = contract.get_offer_hash();
+        if let Some(previous_id) = self.store.get_contract_id_by_offer_hash(&offer_hash)? {
+            if previous_id != contract.id {
+                // The previous offer is locked so that it is not deleted while
+                // it is being accepted or rejected.
+                if let Some((_guard, _)) = lock_contract_in_state!(self, previous_id, Offered) {
+                    info!(
+                        "Replacing offer {} with identical offer {}",
+                        previous_id.to_lower_hex_string(),
+                        contract.id.to_lower_hex_string()
+                    );
+                    self.store.delete_contract(&previous_id)?;
+                }
             }
-
-            self.store
-                .update_contract(&Contract::Refunded(contract.clone()))?;
         }
+        self.store.upsert_offer_hash(&offer_hash, &contract.id)?;
 
         Ok(())
     }
 
-    /// Function to call when we detect that a contract was closed by our counter party.
-    /// This will update the state of the contract and return the [`Contract`] object.
-    pub fn on_counterparty_close(
-        &mut self,
-        contract: &SignedContract,
-        closing_tx: Transaction,
-        confirmations: u32,
-    ) -> Result<Contract, Error> {
-        // check if the closing tx actually spends the funding output
-        if !closing_tx.input.iter().any(|i| {
-            i.previous_output
-                == contract
-                    .accepted_contract
-                    .dlc_transactions
-                    .get_fund_outpoint()
-        }) {
-            return Err(Error::InvalidParameters(
-                "Closing tx does not spend the funding tx".to_string(),
-            ));
-        }
+    fn on_accept_message(
+        &self,
+        accept_msg: &AcceptDlc,
+        counter_party: &PublicKey,
+    ) -> Result<Option<DlcMessage>, Error> {
+        enter_span!(
+            "on_accept_message",
+            contract_id = %accept_msg.temporary_contract_id.to_lower_hex_string()
+        );
+        let offered_contract = get_contract_in_state!(
+            self,
+            &accept_msg.temporary_contract_id,
+            Offered,
+            Some(*counter_party)
+        )?;
+        self.check_offer_not_expired(&offered_contract)?;
 
-        // check if it is the refund tx (easy case)
-        if contract.accepted_contract.dlc_transactions.refund.txid() == closing_tx.txid() {
-            let refunded = Contract::Refunded(contract.clone());
-            self.store.update_contract(&refunded)?;
-            return Ok(refunded);
+        if self.external_signing {
+            return self.on_accept_message_external(offered_contract, accept_msg);
         }
 
-        let contract = if confirmations < NB_CONFIRMATIONS {
-            Contract::PreClosed(PreClosedContract {
-                signed_contract: contract.clone(),
-                attestations: None, // todo in some cases we can get the attestations from the closing tx
-                signed_cet: closing_tx,
-            })
-        } else {
-            Contract::Closed(ClosedContract {
-                attestations: None, // todo in some cases we can get the attestations from the closing tx
-                pnl: contract.accepted_contract.compute_pnl(&closing_tx),
-                signed_cet: Some(closing_tx),
-                contract_id: contract.accepted_contract.get_contract_id(),
-                temporary_contract_id: contract.accepted_contract.offered_contract.id,
-                counter_party_id: contract.accepted_contract.offered_contract.counter_party,
-            })
+        let (signed_contract, signed_msg) = match verify_accepted_and_sign_contract(
+            &self.secp,
+            &offered_contract,
+            accept_msg,
+            &self.wallet,
+            &self.signer_provider,
+            &CancellationToken::new(),
+        ) {
+            Ok(contract) => contract,
+            Err(e) => return self.accept_fail_on_error(offered_contract, accept_msg.clone(), e),
         };
 
-        self.store.update_contract(&contract)?;
+        self.wallet.import_address(&Address::p2wsh(
+            &signed_contract
+                .accepted_contract
+                .dlc_transactions
+                .funding_script_pubkey,
+            self.blockchain.get_network()?,
+        ))?;
+
+        self.update_contract("offered", &Contract::Signed(signed_contract), "AcceptDlc")?;
 
-        Ok(contract)
+        Ok(Some(DlcMessage::Sign(signed_msg)))
     }
-}
 
-impl<W: Deref, SP: Deref, B: Deref, S: Deref, O: Deref, T: Deref, F: Deref, X: ContractSigner>
-    Manager<W, Arc<CachedContractSignerProvider<SP, X>>, B, S, O, T, F, X>
-where
-    W::Target: Wallet,
-    SP::Target: ContractSignerProvider<Signer = X>,
-    B::Target: Blockchain,
-    S::Target: Storage,
-    O::Target: Oracle,
-    T::Target: Time,
-    F::Target: FeeEstimator,
-{
-    /// Create a new channel offer and return the [`dlc_messages::channel::OfferChannel`]
-    /// message to be sent to the `counter_party`.
-    pub fn offer_channel(
-        &mut self,
-        contract_input: &ContractInput,
-        counter_party: PublicKey,
-    ) -> Result<OfferChannel, Error> {
-        let oracle_announcements = contract_input
-            .contract_infos
-            .iter()
-            .map(|x| self.get_oracle_announcements(&x.oracles))
-            .collect::<Result<Vec<_>, Error>>()?;
+    fn on_accept_message_external(
+        &self,
+        offered_contract: OfferedContract,
+        accept_msg: &AcceptDlc,
+    ) -> Result<Option<DlcMessage>, Error> {
+        let accepted_contract = match verify_accepted_contract(
+            &self.secp,
+            &offered_contract,
+            accept_msg,
+            &CancellationToken::new(),
+        ) {
+            Ok(contract) => contract,
+            Err(e) => return self.accept_fail_on_error(offered_contract, accept_msg.clone(), e),
+        };
 
-        let (offered_channel, offered_contract) = crate::channel_updater::offer_channel(
+        let request = get_contract_signing_request(
             &self.secp,
-            contract_input,
-            &counter_party,
-            &oracle_announcements,
-            CET_NSEQUENCE,
-            REFUND_DELAY,
-            &self.wallet,
-            &self.signer_provider,
-            &self.blockchain,
-            &self.time,
+            &offered_contract,
+            &offered_contract.offer_params.fund_pubkey,
+            &accepted_contract.adaptor_infos,
+            &accepted_contract.dlc_transactions,
         )?;
 
-        let msg = offered_channel.get_offer_channel_msg(&offered_contract);
-
-        self.store.upsert_channel(
-            Channel::Offered(offered_channel),
-            Some(Contract::Offered(offered_contract)),
-        )?;
+        self.pending_signing.lock().unwrap().insert(
+            offered_contract.id,
+            (PendingSigning::Sign(accepted_contract), request),
+        );
 
-        Ok(msg)
+        Ok(None)
     }
 
-    /// Reject a channel that was offered. Returns the [`dlc_messages::channel::Reject`]
-    /// message to be sent as well as the public key of the offering node.
-    pub fn reject_channel(&self, channel_id: &ChannelId) -> Result<(Reject, PublicKey), Error> {
-        let offered_channel =
-            get_channel_in_state!(self, channel_id, Offered, None as Option<PublicKey>)?;
+    fn on_sign_message(&self, sign_message: &SignDlc, peer_id: &PublicKey) -> Result<(), Error> {
+        enter_span!(
+            "on_sign_message",
+            contract_id = %sign_message.contract_id.to_lower_hex_string()
+        );
+        let accepted_contract =
+            get_contract_in_state!(self, &sign_message.contract_id, Accepted, Some(*peer_id))?;
 
-        if offered_channel.is_offer_party {
-            return Err(Error::InvalidState(
-                "Cannot reject channel initiated by us.".to_string(),
-            ));
-        }
+        let cancellation = self
+            .cancellation_registry
+            .register(sign_message.contract_id);
+        let (signed_contract, fund_tx) = match crate::contract_updater::verify_signed_contract(
+            &self.secp,
+            &accepted_contract,
+            sign_message,
+            &self.wallet,
+            &cancellation,
+        ) {
+            Ok(contract) => contract,
+            Err(e) => return self.sign_fail_on_error(accepted_contract, sign_message.clone(), e),
+        };
 
-        let offered_contract = get_contract_in_state!(
-            self,
-            &offered_channel.offered_contract_id,
-            Offered,
-            None as Option<PublicKey>
-        )?;
+        self.update_contract("accepted", &Contract::Signed(signed_contract), "SignDlc")?;
 
-        let counterparty = offered_channel.counter_party;
-        self.store.upsert_channel(
-            Channel::Cancelled(offered_channel),
-            Some(Contract::Rejected(offered_contract)),
-        )?;
+        self.blockchain.send_transaction(&fund_tx)?;
 
-        let msg = Reject {
-            channel_id: *channel_id,
-        };
-        Ok((msg, counterparty))
+        Ok(())
     }
 
-    /// Accept a channel that was offered. Returns the [`dlc_messages::channel::AcceptChannel`]
-    /// message to be sent, the updated [`crate::ChannelId`] and [`crate::ContractId`],
-    /// as well as the public key of the offering node.
-    pub fn accept_channel(
-        &mut self,
-        channel_id: &ChannelId,
-    ) -> Result<(AcceptChannel, ChannelId, ContractId, PublicKey), Error> {
-        let offered_channel =
-            get_channel_in_state!(self, channel_id, Offered, None as Option<PublicKey>)?;
-
-        if offered_channel.is_offer_party {
-            return Err(Error::InvalidState(
-                "Cannot accept channel initiated by us.".to_string(),
-            ));
-        }
-
+    #[cfg(feature = "taproot")]
+    fn on_accept_taproot_message(
+        &self,
+        accept_msg: &AcceptTaprootDlc,
+        counter_party: &PublicKey,
+    ) -> Result<SignTaprootDlc, Error> {
+        enter_span!(
+            "on_accept_taproot_message",
+            contract_id = %accept_msg.temporary_contract_id.to_lower_hex_string()
+        );
         let offered_contract = get_contract_in_state!(
             self,
-            &offered_channel.offered_contract_id,
+            &accept_msg.temporary_contract_id,
             Offered,
-            None as Option<PublicKey>
+            Some(*counter_party)
         )?;
+        self.check_offer_not_expired(&offered_contract)?;
 
-        let (accepted_channel, accepted_contract, accept_channel) =
-            crate::channel_updater::accept_channel_offer(
+        let (signed_contract, signed_msg) =
+            match crate::taproot_updater::verify_accepted_and_sign_contract(
                 &self.secp,
-                &offered_channel,
                 &offered_contract,
+                accept_msg,
                 &self.wallet,
                 &self.signer_provider,
-                &self.blockchain,
-            )?;
-
-        self.wallet.import_address(&Address::p2wsh(
-            &accepted_contract.dlc_transactions.funding_script_pubkey,
-            self.blockchain.get_network()?,
-        ))?;
+                &CancellationToken::new(),
+            ) {
+                Ok(contract) => contract,
+                Err(e) => {
+                    // Failed contracts keep the ECDSA message that caused the
+                    // failure, so the offer is rejected instead.
+                    error!("Error in on_accept_taproot {}", e);
+                    self.release_utxos_on_failure(&offered_contract.offer_params);
+                    self.update_contract(
+                        "offered",
+                        &Contract::Rejected(offered_contract),
+                        "AcceptTaprootDlc",
+                    )?;
+                    return Err(e);
+                }
+            };
 
-        let channel_id = accepted_channel.channel_id;
-        let contract_id = accepted_contract.get_contract_id();
-        let counter_party = accepted_contract.offered_contract.counter_party;
+        self.import_taproot_fund_address(&signed_contract.accepted_contract)?;
 
-        self.store.upsert_channel(
-            Channel::Accepted(accepted_channel),
-            Some(Contract::Accepted(accepted_contract)),
+        self.update_contract(
+            "offered",
+            &Contract::Signed(signed_contract),
+            "AcceptTaprootDlc",
         )?;
 
-        Ok((accept_channel, channel_id, contract_id, counter_party))
+        Ok(signed_msg)
     }
 
-    /// Force close the channel with given [`crate::ChannelId`].
-    pub fn force_close_channel(&mut self, channel_id: &ChannelId) -> Result<(), Error> {
-        let channel = get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+    #[cfg(feature = "taproot")]
+    fn on_sign_taproot_message(
+        &self,
+        sign_message: &SignTaprootDlc,
+        peer_id: &PublicKey,
+    ) -> Result<(), Error> {
+        enter_span!(
+            "on_sign_taproot_message",
+            contract_id = %sign_message.contract_id.to_lower_hex_string()
+        );
+        let accepted_contract =
+            get_contract_in_state!(self, &sign_message.contract_id, Accepted, Some(*peer_id))?;
 
-        self.force_close_channel_internal(channel)
+        let cancellation = self
+            .cancellation_registry
+            .register(sign_message.contract_id);
+        let (signed_contract, fund_tx) = match crate::taproot_updater::verify_signed_contract(
+            &self.secp,
+            &accepted_contract,
+            sign_message,
+            &self.wallet,
+            &cancellation,
+        ) {
+            Ok(contract) => contract,
+            Err(e) => {
+                // Failed contracts keep the ECDSA message that caused the
+                // failure, so the accepted contract is replaced by the
+                // rejected offer, stored under its temporary id.
+                error!("Error in on_sign_taproot {}", e);
+                self.release_utxos_on_failure(&accepted_contract.accept_params);
+                self.store.delete_contract(&sign_message.contract_id)?;
+                self.update_contract(
+                    "accepted",
+                    &Contract::Rejected(accepted_contract.offered_contract),
+                    "SignTaprootDlc",
+                )?;
+                return Err(e);
+            }
+        };
+
+        self.update_contract(
+            "accepted",
+            &Contract::Signed(signed_contract),
+            "SignTaprootDlc",
+        )?;
+
+        self.blockchain.send_transaction(&fund_tx)?;
+
+        Ok(())
     }
 
-    /// Offer to settle the balance of a channel so that the counter party gets
-    /// `counter_payout`. Returns the [`dlc_messages::channel::SettleChannelOffer`]
-    /// message to be sent and the public key of the counter party node.
-    pub fn settle_offer(
-        &mut self,
-        channel_id: &ChannelId,
-        counter_payout: u64,
-    ) -> Result<(SettleOffer, PublicKey), Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+    /// Imports the address of the taproot fund output of the given contract in
+    /// the wallet so that its funding can be tracked.
+    #[cfg(feature = "taproot")]
+    fn import_taproot_fund_address(
+        &self,
+        accepted_contract: &AcceptedContract,
+    ) -> Result<(), Error> {
+        let address = Address::from_script(
+            &accepted_contract
+                .dlc_transactions
+                .get_fund_output_script_pubkey(),
+            self.blockchain.get_network()?,
+        )
+        .map_err(|_| Error::InvalidState("Invalid fund output script.".to_string()))?;
+        self.wallet.import_address(&address)
+    }
 
-        let msg = crate::channel_updater::settle_channel_offer(
-            &self.secp,
-            &mut signed_channel,
-            counter_payout,
-            PEER_TIMEOUT,
-            &self.signer_provider,
-            &self.time,
-        )?;
+    /// Proposes to the counter party of the given signed or confirmed contract
+    /// to replace the local payout script, used in the CETs and refund
+    /// transaction, with the given one, for example because the address it
+    /// pays to was compromised. Returns the [`UpdatePayoutOffer`] message to
+    /// send to the counter party, along with its node id. The contract is only
+    /// updated once the [`UpdatePayoutAccept`] reply is received, and pending
+    /// updates are not persisted across restarts.
+    ///
+    /// Note that the previously signed transactions cannot be revoked: they
+    /// spend the same fund output and remain valid, so that the counter party
+    /// could still use them to close the contract. Updates are therefore
+    /// refused once the CETs or the refund transaction can be broadcast.
+    pub fn update_payout_script(
+        &self,
+        contract_id: &ContractId,
+        payout_spk: ScriptBuf,
+    ) -> Result<(UpdatePayoutOffer, PublicKey), Error> {
+        let (contract, _) = self.get_payout_updatable_contract(contract_id, None)?;
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        let updated =
+            update_payout_script(&contract, offered_contract.is_offer_party, &payout_spk)?;
+        let signer = self
+            .signer_provider
+            .derive_contract_signer(offered_contract.keys_id)?;
+        let (adaptor_sigs, refund_signature) = sign_payout_update(&self.secp, &updated, &signer)?;
 
-        let counter_party = signed_channel.counter_party;
+        self.pending_payout_updates
+            .lock()
+            .unwrap()
+            .insert(*contract_id, payout_spk.clone());
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        let msg = UpdatePayoutOffer {
+            contract_id: *contract_id,
+            payout_spk,
+            cet_adaptor_signatures: adaptor_sigs.as_slice().into(),
+            refund_signature,
+        };
 
-        Ok((msg, counter_party))
+        Ok((msg, offered_contract.counter_party))
     }
 
-    /// Accept a settlement offer, returning the [`SettleAccept`] message to be
-    /// sent to the node with the returned [`PublicKey`] id.
-    pub fn accept_settle_offer(
-        &mut self,
-        channel_id: &ChannelId,
-    ) -> Result<(SettleAccept, PublicKey), Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+    fn on_update_payout_offer(
+        &self,
+        update_offer: &UpdatePayoutOffer,
+        peer_id: &PublicKey,
+    ) -> Result<UpdatePayoutAccept, Error> {
+        if self.reject_payout_updates {
+            return Err(Error::InvalidParameters(
+                "Payout script updates are not accepted".to_string(),
+            ));
+        }
 
-        let msg = crate::channel_updater::settle_channel_accept(
+        let (contract, to_contract) =
+            self.get_payout_updatable_contract(&update_offer.contract_id, Some(*peer_id))?;
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        let updated = update_payout_script(
+            &contract,
+            !offered_contract.is_offer_party,
+            &update_offer.payout_spk,
+        )?;
+        let signer = self
+            .signer_provider
+            .derive_contract_signer(offered_contract.keys_id)?;
+        let (adaptor_sigs, own_refund_signature) =
+            sign_payout_update(&self.secp, &updated, &signer)?;
+        let updated = apply_payout_update(
             &self.secp,
-            &mut signed_channel,
-            CET_NSEQUENCE,
-            0,
-            PEER_TIMEOUT,
-            &self.signer_provider,
-            &self.time,
+            updated,
+            &Vec::from(&update_offer.cet_adaptor_signatures),
+            &update_offer.refund_signature,
+            own_refund_signature,
         )?;
 
-        let counter_party = signed_channel.counter_party;
+        // Payout updates keep the contract in the same state.
+        let updated = to_contract(updated);
+        self.update_contract(updated.get_state_name(), &updated, "UpdatePayoutOffer")?;
+        self.store.flush()?;
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        Ok(UpdatePayoutAccept {
+            contract_id: update_offer.contract_id,
+            cet_adaptor_signatures: adaptor_sigs.as_slice().into(),
+            refund_signature: own_refund_signature,
+        })
+    }
 
-        Ok((msg, counter_party))
+    fn on_update_payout_accept(
+        &self,
+        update_accept: &UpdatePayoutAccept,
+        peer_id: &PublicKey,
+    ) -> Result<(), Error> {
+        let (contract, to_contract) =
+            self.get_payout_updatable_contract(&update_accept.contract_id, Some(*peer_id))?;
+        let payout_spk = self
+            .pending_payout_updates
+            .lock()
+            .unwrap()
+            .remove(&update_accept.contract_id)
+            .ok_or_else(|| {
+                Error::InvalidState("No pending payout update for contract".to_string())
+            })?;
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        let updated =
+            update_payout_script(&contract, offered_contract.is_offer_party, &payout_spk)?;
+        let signer = self
+            .signer_provider
+            .derive_contract_signer(offered_contract.keys_id)?;
+        let (_, own_refund_signature) = sign_payout_update(&self.secp, &updated, &signer)?;
+        let updated = apply_payout_update(
+            &self.secp,
+            updated,
+            &Vec::from(&update_accept.cet_adaptor_signatures),
+            &update_accept.refund_signature,
+            own_refund_signature,
+        )?;
+
+        let updated = to_contract(updated);
+        self.update_contract(updated.get_state_name(), &updated, "UpdatePayoutAccept")
     }
 
-    /// Returns a [`RenewOffer`] message as well as the [`PublicKey`] of the
-    /// counter party's node to offer the establishment of a new contract in the
-    /// channel.
-    pub fn renew_offer(
-        &mut self,
-        channel_id: &ChannelId,
-        counter_payout: u64,
-        contract_input: &ContractInput,
-    ) -> Result<(RenewOffer, PublicKey), Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+    /// Returns the signed or confirmed contract with the given id, along with
+    /// the function to use to store it back in the same state. Contracts
+    /// associated with a channel are rejected.
+    fn get_payout_updatable_contract(
+        &self,
+        contract_id: &ContractId,
+        peer_id: Option<PublicKey>,
+    ) -> Result<(SignedContract, fn(SignedContract) -> Contract), Error> {
+        let contract = self
+            .store
+            .get_contract(contract_id)?
+            .ok_or_else(|| Error::InvalidParameters("Unknown contract id.".to_string()))?;
+        if let Some(p) = peer_id {
+            if contract.get_counter_party_id() != p {
+                return Err(Error::InvalidParameters(format!(
+                    "Peer {:02x?} is not involved with contract {:02x?}.",
+                    p, contract_id
+                )));
+            }
+        }
+        let (signed_contract, to_contract): (_, fn(SignedContract) -> Contract) = match contract {
+            Contract::Signed(s) => (s, Contract::Signed),
+            Contract::Confirmed(s) => (s, Contract::Confirmed),
+            c => {
+                return Err(Error::InvalidState(format!(
+                    "Invalid state {:?} expected Signed or Confirmed.",
+                    c
+                )))
+            }
+        };
+        if signed_contract.channel_id.is_some() {
+            return Err(Error::InvalidState(
+                "Cannot update the payout script of a channel contract".to_string(),
+            ));
+        }
+        // The previous transactions stay valid, so once they can be broadcast
+        // replacing them does not protect the funds anymore.
+        let cet_locktime = signed_contract
+            .accepted_contract
+            .offered_contract
+            .cet_locktime;
+        let cets_broadcastable = if cet_locktime < bitcoin::absolute::LOCK_TIME_THRESHOLD {
+            self.blockchain.get_blockchain_height()? >= cet_locktime as u64
+        } else {
+            self.time.unix_time_now() >= cet_locktime as u64
+        };
+        if cets_broadcastable
+            || self.time.unix_time_now() >= get_contract_deadline(&signed_contract)
+        {
+            return Err(Error::InvalidState(
+                "Cannot update the payout script of a contract whose transactions can be broadcast"
+                    .to_string(),
+            ));
+        }
+        Ok((signed_contract, to_contract))
+    }
 
-        let oracle_announcements = contract_input
-            .contract_infos
-            .iter()
-            .map(|x| self.get_oracle_announcements(&x.oracles))
-            .collect::<Result<Vec<_>, Error>>()?;
+    /// Proposes to the counter party of the given confirmed contract, which
+    /// must have been offered by the local party, to replace it with a
+    /// contract with the payouts and collaterals of the given input, for
+    /// example to top up the margin of a position without closing it. The
+    /// collateral of neither party can decrease, and the oracles of the
+    /// contract are kept. Returns the [`AmendOffer`] message to send to the
+    /// counter party, along with its node id. Pending amendments are not
+    /// persisted across restarts.
+    pub fn amend_contract(
+        &self,
+        contract_id: &ContractId,
+        contract_input: &ContractInput,
+    ) -> Result<(AmendOffer, PublicKey), Error> {
+        let contract =
+            get_contract_in_state!(self, contract_id, Confirmed, None as Option<PublicKey>)?;
+        if self
+            .pending_amendments
+            .lock()
+            .unwrap()
+            .contains_key(contract_id)
+        {
+            return Err(Error::InvalidState(
+                "Contract already has a pending amendment".to_string(),
+            ));
+        }
 
-        let (msg, offered_contract) = crate::channel_updater::renew_offer(
+        let (amended_contract, amend_offer) = amend_contract(
             &self.secp,
-            &mut signed_channel,
+            &contract,
             contract_input,
-            oracle_announcements,
-            counter_payout,
-            REFUND_DELAY,
-            PEER_TIMEOUT,
-            CET_NSEQUENCE,
+            &&self.funding_wallet(),
+            &self.blockchain,
             &self.signer_provider,
-            &self.time,
         )?;
+        let counter_party = amended_contract.counter_party;
 
-        let counter_party = offered_contract.counter_party;
-
-        self.store.upsert_channel(
-            Channel::Signed(signed_channel),
-            Some(Contract::Offered(offered_contract)),
-        )?;
+        self.pending_amendments
+            .lock()
+            .unwrap()
+            .insert(*contract_id, PendingAmendment::Offered(amended_contract));
 
-        Ok((msg, counter_party))
+        Ok((amend_offer, counter_party))
     }
 
-    /// Accept an offer to renew the contract in the channel. Returns the
-    /// [`RenewAccept`] message to be sent to the peer with the returned
-    /// [`PublicKey`] as node id.
-    pub fn accept_renew_offer(
-        &mut self,
-        channel_id: &ChannelId,
-    ) -> Result<(RenewAccept, PublicKey), Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
-        let offered_contract_id = signed_channel.get_contract_id().ok_or_else(|| {
-            Error::InvalidState("Expected to have a contract id but did not.".to_string())
-        })?;
+    /// Returns the amendments proposed by counter parties that are waiting to
+    /// be accepted using [`Self::accept_amendment`], as the id of the contract
+    /// to amend along with the proposed contract.
+    pub fn get_received_amendments(&self) -> Vec<(ContractId, OfferedContract)> {
+        self.pending_amendments
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(id, p)| match p {
+                PendingAmendment::Received(o) => Some((*id, o.clone())),
+                _ => None,
+            })
+            .collect()
+    }
 
-        let offered_contract = get_contract_in_state!(
-            self,
-            &offered_contract_id,
-            Offered,
-            None as Option<PublicKey>
-        )?;
+    /// Accepts the amendment of the given contract proposed by the counter
+    /// party, funding the additional collateral of the local party. Returns
+    /// the [`AmendAccept`] message to send to the counter party, along with
+    /// its node id. The amended contract is stored once the [`AmendSign`]
+    /// reply is received, and replaces the given contract once its fund
+    /// transaction is confirmed.
+    pub fn accept_amendment(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<(AmendAccept, PublicKey), Error> {
+        let contract =
+            get_contract_in_state!(self, contract_id, Confirmed, None as Option<PublicKey>)?;
+        let amended_contract = match self.pending_amendments.lock().unwrap().get(contract_id) {
+            Some(PendingAmendment::Received(o)) => o.clone(),
+            _ => {
+                return Err(Error::InvalidState(
+                    "No amendment received for contract".to_string(),
+                ))
+            }
+        };
 
-        let (accepted_contract, msg) = crate::channel_updater::accept_channel_renewal(
+        let cancellation = self.cancellation_registry.register(*contract_id);
+        let (accepted_contract, amend_accept) = accept_amendment(
             &self.secp,
-            &mut signed_channel,
-            &offered_contract,
-            CET_NSEQUENCE,
-            PEER_TIMEOUT,
+            &contract,
+            &amended_contract,
+            &&self.funding_wallet(),
             &self.signer_provider,
-            &self.time,
+            &self.blockchain,
+            &cancellation,
         )?;
 
-        let counter_party = signed_channel.counter_party;
-
-        self.store.upsert_channel(
-            Channel::Signed(signed_channel),
-            Some(Contract::Accepted(accepted_contract)),
-        )?;
+        self.pending_amendments
+            .lock()
+            .unwrap()
+            .insert(*contract_id, PendingAmendment::Accepted(accepted_contract));
 
-        Ok((msg, counter_party))
+        Ok((amend_accept, amended_contract.counter_party))
     }
 
-    /// Reject an offer to renew the contract in the channel. Returns the
-    /// [`Reject`] message to be sent to the peer with the returned
-    /// [`PublicKey`] node id.
-    pub fn reject_renew_offer(
-        &mut self,
-        channel_id: &ChannelId,
-    ) -> Result<(Reject, PublicKey), Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
-        let offered_contract_id = signed_channel.get_contract_id().ok_or_else(|| {
-            Error::InvalidState(
-                "Expected to be in a state with an associated contract id but was not.".to_string(),
-            )
-        })?;
-
-        let offered_contract = get_contract_in_state!(
-            self,
-            &offered_contract_id,
-            Offered,
-            None as Option<PublicKey>
-        )?;
+    /// Abandons the pending amendment of the given contract, releasing the
+    /// inputs reserved to fund it. The counter party is not notified.
+    pub fn cancel_amendment(&self, contract_id: &ContractId) -> Result<(), Error> {
+        match self.pending_amendments.lock().unwrap().remove(contract_id) {
+            Some(PendingAmendment::Offered(o)) => {
+                release_party_utxos(&self.wallet, &o.offer_params)
+            }
+            Some(PendingAmendment::Accepted(a)) => {
+                release_party_utxos(&self.wallet, &a.accept_params)
+            }
+            Some(PendingAmendment::Received(_)) => Ok(()),
+            None => Err(Error::InvalidParameters(
+                "No pending amendment for contract".to_string(),
+            )),
+        }
+    }
 
-        let reject_msg = crate::channel_updater::reject_renew_offer(&mut signed_channel)?;
+    fn on_amend_offer(&self, amend_offer: &AmendOffer, peer_id: &PublicKey) -> Result<(), Error> {
+        let contract =
+            get_contract_in_state!(self, &amend_offer.contract_id, Confirmed, Some(*peer_id))?;
+        if contract.accepted_contract.offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Only the offer party of a contract can amend it".to_string(),
+            ));
+        }
 
-        let counter_party = signed_channel.counter_party;
+        let amended_contract = get_received_amendment(&contract, amend_offer)?;
 
-        self.store.upsert_channel(
-            Channel::Signed(signed_channel),
-            Some(Contract::Rejected(offered_contract)),
-        )?;
+        let mut pending_amendments = self.pending_amendments.lock().unwrap();
+        if pending_amendments.contains_key(&amend_offer.contract_id) {
+            return Err(Error::InvalidState(
+                "Contract already has a pending amendment".to_string(),
+            ));
+        }
+        pending_amendments.insert(
+            amend_offer.contract_id,
+            PendingAmendment::Received(amended_contract),
+        );
 
-        Ok((reject_msg, counter_party))
+        Ok(())
     }
 
-    /// Returns a [`Reject`] message to be sent to the counter party of the
-    /// channel to inform them that the local party does not wish to accept the
-    /// proposed settle offer.
-    pub fn reject_settle_offer(
-        &mut self,
-        channel_id: &ChannelId,
-    ) -> Result<(Reject, PublicKey), Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
-
-        let msg = crate::channel_updater::reject_settle_offer(&mut signed_channel)?;
+    fn on_amend_accept(
+        &self,
+        amend_accept: &AmendAccept,
+        peer_id: &PublicKey,
+    ) -> Result<AmendSign, Error> {
+        let contract_id = amend_accept.contract_id;
+        let contract = get_contract_in_state!(self, &contract_id, Confirmed, Some(*peer_id))?;
+        let amended_contract = {
+            let mut pending_amendments = self.pending_amendments.lock().unwrap();
+            match pending_amendments.remove(&contract_id) {
+                Some(PendingAmendment::Offered(o)) => o,
+                other => {
+                    if let Some(p) = other {
+                        pending_amendments.insert(contract_id, p);
+                    }
+                    return Err(Error::InvalidState(
+                        "No amendment offered for contract".to_string(),
+                    ));
+                }
+            }
+        };
 
-        let counter_party = signed_channel.counter_party;
+        let cancellation = self.cancellation_registry.register(contract_id);
+        let (signed_contract, amend_sign) = match verify_amendment_accept_and_sign(
+            &self.secp,
+            &contract,
+            &amended_contract,
+            amend_accept,
+            &self.wallet,
+            &self.signer_provider,
+            &cancellation,
+        ) {
+            Ok(res) => res,
+            Err(e) => {
+                self.release_utxos_on_failure(&amended_contract.offer_params);
+                return Err(e);
+            }
+        };
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        // The amended contract is a new contract replacing the confirmed one.
+        self.write_batch(
+            StateUpdate::new().with_contract(None, Contract::Signed(signed_contract)),
+            "AmendAccept",
+        )?;
 
-        Ok((msg, counter_party))
+        Ok(amend_sign)
     }
 
-    /// Returns a [`CollaborativeCloseOffer`] message to be sent to the counter
-    /// party of the channel and update the state of the channel. Note that the
-    /// channel will be forced closed after a timeout if the counter party does
-    /// not broadcast the close transaction.
-    pub fn offer_collaborative_close(
-        &mut self,
-        channel_id: &ChannelId,
-        counter_payout: u64,
-    ) -> Result<CollaborativeCloseOffer, Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+    fn on_amend_sign(&self, amend_sign: &AmendSign, peer_id: &PublicKey) -> Result<(), Error> {
+        let contract_id = amend_sign.contract_id;
+        let contract = get_contract_in_state!(self, &contract_id, Confirmed, Some(*peer_id))?;
+        let accepted_contract = {
+            let mut pending_amendments = self.pending_amendments.lock().unwrap();
+            match pending_amendments.remove(&contract_id) {
+                Some(PendingAmendment::Accepted(a)) => a,
+                other => {
+                    if let Some(p) = other {
+                        pending_amendments.insert(contract_id, p);
+                    }
+                    return Err(Error::InvalidState(
+                        "No amendment accepted for contract".to_string(),
+                    ));
+                }
+            }
+        };
 
-        let (msg, close_tx) = crate::channel_updater::offer_collaborative_close(
+        let cancellation = self.cancellation_registry.register(contract_id);
+        let (signed_contract, fund_tx) = match verify_amendment_sign(
             &self.secp,
-            &mut signed_channel,
-            counter_payout,
+            &contract,
+            &accepted_contract,
+            amend_sign,
+            &self.wallet,
             &self.signer_provider,
-            &self.time,
-        )?;
+            &cancellation,
+        ) {
+            Ok(res) => res,
+            Err(e) => {
+                self.release_utxos_on_failure(&accepted_contract.accept_params);
+                return Err(e);
+            }
+        };
 
-        self.chain_monitor.add_tx(
-            close_tx.txid(),
-            ChannelInfo {
-                channel_id: *channel_id,
-                tx_type: TxType::CollaborativeClose,
-            },
-        );
+        self.write_batch(
+            StateUpdate::new().with_contract(None, Contract::Signed(signed_contract)),
+            "AmendSign",
+        )?;
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
-        self.store.persist_chain_monitor(&self.chain_monitor)?;
+        self.blockchain.send_transaction(&fund_tx)?;
 
-        Ok(msg)
+        Ok(())
     }
 
-    /// Accept an offer to collaboratively close the channel. The close transaction
-    /// will be broadcast and the state of the channel updated.
-    pub fn accept_collaborative_close(&mut self, channel_id: &ChannelId) -> Result<(), Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+    /// Proposes to the counter party of the given confirmed contracts, which
+    /// must be on the same oracle events, to replace them with a single
+    /// contract whose payouts are the sum of theirs, releasing the collateral
+    /// that is not at risk in any outcome (see [`crate::netting`]). The net
+    /// contract is funded by a transaction spending the fund outputs of the
+    /// given contracts, with the given fee rate. Returns the [`NetOffer`]
+    /// message to send to the counter party, along with its node id. Pending
+    /// nettings are identified by the id of the first contract to net, and are
+    /// not persisted across restarts.
+    pub fn offer_netting(
+        &self,
+        contract_ids: &[ContractId],
+        fee_rate_per_vb: u64,
+    ) -> Result<(NetOffer, PublicKey), Error> {
+        let contracts = self.get_netted_contracts(contract_ids, None)?;
+        let mut pending_nettings = self.pending_nettings.lock().unwrap();
+        if pending_nettings.contains_key(&contract_ids[0]) {
+            return Err(Error::InvalidState(
+                "Contract already has a pending netting".to_string(),
+            ));
+        }
 
-        let closed_contract = if let Some(SignedChannelState::Established {
-            signed_contract_id,
-            is_offer,
-            ..
-        }) = &signed_channel.roll_back_state
-        {
-            let counter_payout = get_signed_channel_state!(
-                signed_channel,
-                CollaborativeCloseOffered,
-                counter_payout
-            )?;
-            let contract =
-                get_contract_in_state!(self, signed_contract_id, Confirmed, None::<PublicKey>)?;
-            let own_collateral = if *is_offer {
-                contract
-                    .accepted_contract
-                    .offered_contract
-                    .offer_params
-                    .collateral
-            } else {
-                contract.accepted_contract.accept_params.collateral
-            };
-            let pnl = own_collateral as i64 - counter_payout as i64;
-            Some(ClosedContract {
-                attestations: None,
-                signed_cet: None,
-                contract_id: *signed_contract_id,
-                temporary_contract_id: contract.accepted_contract.offered_contract.id,
-                counter_party_id: signed_channel.counter_party,
-                pnl,
+        let (net_contract, net_offer) = offer_netting(&contracts, fee_rate_per_vb, &self.wallet)?;
+        let counter_party = net_contract.counter_party;
+        pending_nettings.insert(
+            contract_ids[0],
+            PendingNetting::Offered(contract_ids.to_vec(), net_contract),
+        );
+
+        Ok((net_offer, counter_party))
+    }
+
+    /// Returns the nettings proposed by counter parties that are waiting to be
+    /// accepted using [`Self::accept_netting`], as the ids of the contracts to
+    /// net along with the proposed net contract.
+    pub fn get_received_nettings(&self) -> Vec<(Vec<ContractId>, OfferedContract)> {
+        self.pending_nettings
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|p| match p {
+                PendingNetting::Received(ids, o) => Some((ids.clone(), o.clone())),
+                _ => None,
             })
-        } else {
-            None
-        };
+            .collect()
+    }
 
-        let close_tx = crate::channel_updater::accept_collaborative_close_offer(
+    /// Accepts the netting of contracts whose first one is the given contract
+    /// proposed by the counter party. Returns the [`NetAccept`] message to send
+    /// to the counter party, along with its node id. The net contract is
+    /// stored once the [`NetSign`] reply is received, and replaces the netted
+    /// contracts once its fund transaction is confirmed.
+    pub fn accept_netting(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<(NetAccept, PublicKey), Error> {
+        let (contract_ids, net_contract) =
+            match self.pending_nettings.lock().unwrap().get(contract_id) {
+                Some(PendingNetting::Received(ids, o)) => (ids.clone(), o.clone()),
+                _ => {
+                    return Err(Error::InvalidState(
+                        "No netting received for contract".to_string(),
+                    ))
+                }
+            };
+        let contracts = self.get_netted_contracts(&contract_ids, None)?;
+
+        let cancellation = self.cancellation_registry.register(*contract_id);
+        let (accepted_contract, net_accept) = accept_netting(
             &self.secp,
-            &mut signed_channel,
+            &contracts,
+            &net_contract,
+            &self.wallet,
             &self.signer_provider,
+            &cancellation,
         )?;
 
-        self.blockchain.send_transaction(&close_tx)?;
+        self.pending_nettings.lock().unwrap().insert(
+            *contract_id,
+            PendingNetting::Accepted(contract_ids, accepted_contract),
+        );
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        Ok((net_accept, net_contract.counter_party))
+    }
 
-        if let Some(closed_contract) = closed_contract {
-            self.store
-                .update_contract(&Contract::Closed(closed_contract))?;
+    /// Abandons the pending netting of contracts whose first one is the given
+    /// contract. The counter party is not notified.
+    pub fn cancel_netting(&self, contract_id: &ContractId) -> Result<(), Error> {
+        match self.pending_nettings.lock().unwrap().remove(contract_id) {
+            Some(_) => Ok(()),
+            None => Err(Error::InvalidParameters(
+                "No pending netting for contract".to_string(),
+            )),
         }
-
-        Ok(())
     }
 
-    fn try_finalize_closing_established_channel(
-        &mut self,
-        mut signed_channel: SignedChannel,
-    ) -> Result<(), Error> {
-        let (buffer_tx, signed_cet, contract_id, attestations) = get_signed_channel_state!(
-            signed_channel,
-            Closing,
-            buffer_transaction,
-            signed_cet,
-            contract_id,
-            attestations
-        )?;
+    fn on_net_offer(&self, net_offer: &NetOffer, peer_id: &PublicKey) -> Result<(), Error> {
+        let contracts = self.get_netted_contracts(&net_offer.contract_ids, Some(*peer_id))?;
+        let net_contract = get_received_netting(&contracts, net_offer)?;
 
-        if self
-            .blockchain
-            .get_transaction_confirmations(&buffer_tx.txid())?
-            > CET_NSEQUENCE
-        {
-            let confirmed_contract =
-                get_contract_in_state!(self, &contract_id, Confirmed, None as Option<PublicKey>)?;
+        let mut pending_nettings = self.pending_nettings.lock().unwrap();
+        if pending_nettings.contains_key(&net_offer.contract_ids[0]) {
+            return Err(Error::InvalidState(
+                "Contract already has a pending netting".to_string(),
+            ));
+        }
+        pending_nettings.insert(
+            net_offer.contract_ids[0],
+            PendingNetting::Received(net_offer.contract_ids.clone(), net_contract),
+        );
 
-            let closed_contract =
-                self.close_contract(&confirmed_contract, signed_cet, attestations)?;
+        Ok(())
+    }
 
-            signed_channel.state = SignedChannelState::Closed;
+    fn on_net_accept(&self, net_accept: &NetAccept, peer_id: &PublicKey) -> Result<NetSign, Error> {
+        let contract_id = net_accept.contract_id;
+        let (contract_ids, net_contract) = {
+            let mut pending_nettings = self.pending_nettings.lock().unwrap();
+            match pending_nettings.remove(&contract_id) {
+                Some(PendingNetting::Offered(ids, o)) => (ids, o),
+                other => {
+                    if let Some(p) = other {
+                        pending_nettings.insert(contract_id, p);
+                    }
+                    return Err(Error::InvalidState(
+                        "No netting offered for contract".to_string(),
+                    ));
+                }
+            }
+        };
+        let contracts = self.get_netted_contracts(&contract_ids, Some(*peer_id))?;
 
-            self.store
-                .upsert_channel(Channel::Signed(signed_channel), Some(closed_contract))?;
-        }
+        let cancellation = self.cancellation_registry.register(contract_id);
+        let (signed_contract, net_sign) = verify_netting_accept_and_sign(
+            &self.secp,
+            &contracts,
+            &net_contract,
+            net_accept,
+            &self.wallet,
+            &self.signer_provider,
+            &cancellation,
+        )?;
 
-        Ok(())
+        // The netting contract is a new contract replacing the netted ones.
+        self.write_batch(
+            StateUpdate::new().with_contract(None, Contract::Signed(signed_contract)),
+            "NetAccept",
+        )?;
+
+        Ok(net_sign)
     }
 
-    fn on_offer_channel(
-        &mut self,
-        offer_channel: &OfferChannel,
-        counter_party: PublicKey,
-    ) -> Result<(), Error> {
-        offer_channel.validate(
+    fn on_net_sign(&self, net_sign: &NetSign, peer_id: &PublicKey) -> Result<(), Error> {
+        let contract_id = net_sign.contract_id;
+        let (contract_ids, accepted_contract) = {
+            let mut pending_nettings = self.pending_nettings.lock().unwrap();
+            match pending_nettings.remove(&contract_id) {
+                Some(PendingNetting::Accepted(ids, a)) => (ids, a),
+                other => {
+                    if let Some(p) = other {
+                        pending_nettings.insert(contract_id, p);
+                    }
+                    return Err(Error::InvalidState(
+                        "No netting accepted for contract".to_string(),
+                    ));
+                }
+            }
+        };
+        let contracts = self.get_netted_contracts(&contract_ids, Some(*peer_id))?;
+
+        let cancellation = self.cancellation_registry.register(contract_id);
+        let (signed_contract, fund_tx) = verify_netting_sign(
             &self.secp,
-            REFUND_DELAY,
-            REFUND_DELAY * 2,
-            CET_NSEQUENCE,
-            CET_NSEQUENCE * 2,
+            &contracts,
+            &accepted_contract,
+            net_sign,
+            &self.wallet,
+            &self.signer_provider,
+            &cancellation,
         )?;
 
-        let keys_id = self
-            .signer_provider
-            .derive_signer_key_id(false, offer_channel.temporary_contract_id);
-        let (channel, contract) =
-            OfferedChannel::from_offer_channel(offer_channel, counter_party, keys_id)?;
+        self.write_batch(
+            StateUpdate::new().with_contract(None, Contract::Signed(signed_contract)),
+            "NetSign",
+        )?;
 
-        contract.validate()?;
+        self.blockchain.send_transaction(&fund_tx)?;
 
-        if self
-            .store
-            .get_channel(&channel.temporary_channel_id)?
-            .is_some()
-        {
+        Ok(())
+    }
+
+    /// Returns the confirmed contracts with the given ids, in the same order.
+    fn get_netted_contracts(
+        &self,
+        contract_ids: &[ContractId],
+        peer_id: Option<PublicKey>,
+    ) -> Result<Vec<SignedContract>, Error> {
+        if contract_ids.len() < 2 {
             return Err(Error::InvalidParameters(
-                "Channel with identical idea already in store".to_string(),
+                "At least two contracts are required for netting".to_string(),
             ));
         }
-
-        self.store
-            .upsert_channel(Channel::Offered(channel), Some(Contract::Offered(contract)))?;
-
-        Ok(())
+        contract_ids
+            .iter()
+            .map(|id| get_contract_in_state!(self, id, Confirmed, peer_id))
+            .collect()
     }
 
-    fn on_accept_channel(
-        &mut self,
-        accept_channel: &AcceptChannel,
-        peer_id: &PublicKey,
-    ) -> Result<SignChannel, Error> {
-        let offered_channel = get_channel_in_state!(
-            self,
-            &accept_channel.temporary_channel_id,
-            Offered,
-            Some(*peer_id)
-        )?;
-        let offered_contract = get_contract_in_state!(
-            self,
-            &offered_channel.offered_contract_id,
-            Offered,
-            Some(*peer_id)
-        )?;
+    /// Proposes to the counter party of the given confirmed contract to close
+    /// it before its maturity, with the given payout for the counter party and
+    /// the rest of the collateral for the local party. Returns the
+    /// [`MutualCloseOffer`] message to send to the counter party, along with
+    /// its node id. The contract is moved to the
+    /// [`Contract::CollaborativelyClosed`] state once the
+    /// [`MutualCloseAccept`] reply is received, and pending offers are not
+    /// persisted across restarts. Contracts of channels must instead be closed
+    /// using [`Self::offer_collaborative_close`].
+    pub fn offer_contract_collaborative_close(
+        &self,
+        contract_id: &ContractId,
+        counter_payout: u64,
+    ) -> Result<(MutualCloseOffer, PublicKey), Error> {
+        let contract =
+            get_contract_in_state!(self, contract_id, Confirmed, None as Option<PublicKey>)?;
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        let signer = self
+            .signer_provider
+            .derive_contract_signer(offered_contract.keys_id)?;
+        let (close_offer, close_tx) =
+            offer_collaborative_close(&self.secp, &contract, counter_payout, &signer)?;
 
-        let (signed_channel, signed_contract, sign_channel) = {
-            let res = crate::channel_updater::verify_and_sign_accepted_channel(
-                &self.secp,
-                &offered_channel,
-                &offered_contract,
-                accept_channel,
-                //TODO(tibo): this should be parameterizable.
-                CET_NSEQUENCE,
-                &self.wallet,
-                &self.signer_provider,
-            );
+        self.pending_collaborative_closes.lock().unwrap().insert(
+            *contract_id,
+            PendingCollaborativeClose::Offered { close_tx },
+        );
 
-            match res {
-                Ok(res) => res,
-                Err(e) => {
-                    let channel = crate::channel::FailedAccept {
-                        temporary_channel_id: accept_channel.temporary_channel_id,
-                        error_message: format!("Error validating accept channel: {}", e),
-                        accept_message: accept_channel.clone(),
-                        counter_party: *peer_id,
-                    };
-                    self.store
-                        .upsert_channel(Channel::FailedAccept(channel), None)?;
-                    return Err(e);
+        Ok((close_offer, offered_contract.counter_party))
+    }
+
+    /// Returns the offers to collaboratively close a contract received from
+    /// counter parties that are waiting to be accepted using
+    /// [`Self::accept_contract_collaborative_close`], as the id of the contract
+    /// along with the proposed closing transaction.
+    pub fn get_received_collaborative_closes(&self) -> Vec<(ContractId, Transaction)> {
+        self.pending_collaborative_closes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(id, p)| match p {
+                PendingCollaborativeClose::Received { close_tx, .. } => {
+                    Some((*id, close_tx.clone()))
                 }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Accepts the offer to collaboratively close the given contract received
+    /// from the counter party. The closing transaction is broadcast and the
+    /// contract moved to the [`Contract::CollaborativelyClosed`] state. Returns
+    /// the [`MutualCloseAccept`] message to send to the counter party, along
+    /// with its node id.
+    pub fn accept_contract_collaborative_close(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<(MutualCloseAccept, PublicKey), Error> {
+        let contract =
+            get_contract_in_state!(self, contract_id, Confirmed, None as Option<PublicKey>)?;
+        let (close_tx, counter_signature) = match self
+            .pending_collaborative_closes
+            .lock()
+            .unwrap()
+            .get(contract_id)
+        {
+            Some(PendingCollaborativeClose::Received {
+                close_tx,
+                counter_signature,
+            }) => (close_tx.clone(), *counter_signature),
+            _ => {
+                return Err(Error::InvalidState(
+                    "No collaborative close offer received for contract".to_string(),
+                ))
             }
         };
 
-        self.wallet.import_address(&Address::p2wsh(
-            &signed_contract
-                .accepted_contract
-                .dlc_transactions
-                .funding_script_pubkey,
-            self.blockchain.get_network()?,
-        ))?;
-
-        if let SignedChannelState::Established {
-            buffer_transaction, ..
-        } = &signed_channel.state
-        {
-            self.chain_monitor.add_tx(
-                buffer_transaction.txid(),
-                ChannelInfo {
-                    channel_id: signed_channel.channel_id,
-                    tx_type: TxType::Current,
-                },
-            );
-        } else {
-            unreachable!();
-        }
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        let counter_party = offered_contract.counter_party;
+        let signer = self
+            .signer_provider
+            .derive_contract_signer(offered_contract.keys_id)?;
+        let (close_tx, close_signature) = sign_collaborative_close(
+            &self.secp,
+            &contract,
+            &close_tx,
+            &counter_signature,
+            &signer,
+        )?;
 
-        self.store.upsert_channel(
-            Channel::Signed(signed_channel),
-            Some(Contract::Signed(signed_contract)),
+        self.finalize_contract_collaborative_close(
+            contract,
+            close_tx,
+            "accept_contract_collaborative_close",
         )?;
 
-        self.store.persist_chain_monitor(&self.chain_monitor)?;
+        Ok((
+            MutualCloseAccept {
+                contract_id: *contract_id,
+                close_signature,
+            },
+            counter_party,
+        ))
+    }
 
-        Ok(sign_channel)
+    fn on_mutual_close_offer(
+        &self,
+        close_offer: &MutualCloseOffer,
+        peer_id: &PublicKey,
+    ) -> Result<(), Error> {
+        let contract =
+            get_contract_in_state!(self, &close_offer.contract_id, Confirmed, Some(*peer_id))?;
+        let close_tx = on_collaborative_close_offer(&self.secp, &contract, close_offer)?;
+
+        self.pending_collaborative_closes.lock().unwrap().insert(
+            close_offer.contract_id,
+            PendingCollaborativeClose::Received {
+                close_tx,
+                counter_signature: close_offer.close_signature,
+            },
+        );
+
+        Ok(())
     }
 
-    fn on_sign_channel(
-        &mut self,
-        sign_channel: &SignChannel,
+    fn on_mutual_close_accept(
+        &self,
+        close_accept: &MutualCloseAccept,
         peer_id: &PublicKey,
     ) -> Result<(), Error> {
-        let accepted_channel =
-            get_channel_in_state!(self, &sign_channel.channel_id, Accepted, Some(*peer_id))?;
-        let accepted_contract = get_contract_in_state!(
-            self,
-            &accepted_channel.accepted_contract_id,
-            Accepted,
-            Some(*peer_id)
+        let contract =
+            get_contract_in_state!(self, &close_accept.contract_id, Confirmed, Some(*peer_id))?;
+        let close_tx = match self
+            .pending_collaborative_closes
+            .lock()
+            .unwrap()
+            .get(&close_accept.contract_id)
+        {
+            Some(PendingCollaborativeClose::Offered { close_tx }) => close_tx.clone(),
+            _ => {
+                return Err(Error::InvalidState(
+                    "No collaborative close offered for contract".to_string(),
+                ))
+            }
+        };
+
+        let signer = self
+            .signer_provider
+            .derive_contract_signer(contract.accepted_contract.offered_contract.keys_id)?;
+        let (close_tx, _) = sign_collaborative_close(
+            &self.secp,
+            &contract,
+            &close_tx,
+            &close_accept.close_signature,
+            &signer,
         )?;
 
-        let (signed_channel, signed_contract, signed_fund_tx) = {
-            let res = verify_signed_channel(
-                &self.secp,
-                &accepted_channel,
-                &accepted_contract,
-                sign_channel,
-                &self.wallet,
+        self.finalize_contract_collaborative_close(contract, close_tx, "MutualCloseAccept")
+    }
+
+    fn finalize_contract_collaborative_close(
+        &self,
+        contract: SignedContract,
+        close_tx: Transaction,
+        trigger: &str,
+    ) -> Result<(), Error> {
+        self.blockchain.send_transaction(&close_tx)?;
+
+        let contract_id = contract.accepted_contract.get_contract_id();
+        let pnl = contract.accepted_contract.compute_pnl(&close_tx);
+        self.update_contract(
+            "confirmed",
+            &Contract::CollaborativelyClosed(CollaborativelyClosedContract {
+                signed_contract: contract,
+                close_tx,
+                pnl,
+            }),
+            trigger,
+        )?;
+        self.pending_collaborative_closes
+            .lock()
+            .unwrap()
+            .remove(&contract_id);
+
+        Ok(())
+    }
+
+    /// Returns an estimate of the on-chain fees, message sizes and signing
+    /// workload of the given operation, without performing it.
+    ///
+    /// Oracle announcements are fetched from the oracles for operations
+    /// involving a new contract.
+    pub fn estimate_operation(&self, operation: &Operation) -> Result<OperationEstimate, Error> {
+        match operation {
+            Operation::OfferContract(contract_input) => estimate::estimate_contract_offer(
+                contract_input.fee_rate,
+                contract_input.offer_collateral + contract_input.accept_collateral,
+                &self.get_contract_infos(contract_input)?,
+            ),
+            Operation::OfferChannel(contract_input) => estimate::estimate_channel_offer(
+                contract_input.fee_rate,
+                contract_input.offer_collateral + contract_input.accept_collateral,
+                &self.get_contract_infos(contract_input)?,
+            ),
+            Operation::SettleChannel(channel_id) => {
+                let signed_channel =
+                    get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+                estimate::estimate_settle(&signed_channel)
+            }
+            Operation::RenewChannel(channel_id, contract_input) => {
+                let signed_channel =
+                    get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+                estimate::estimate_renew(
+                    &signed_channel,
+                    contract_input.offer_collateral + contract_input.accept_collateral,
+                    &self.get_contract_infos(contract_input)?,
+                )
+            }
+            Operation::CollaborativeCloseChannel(channel_id) => {
+                let signed_channel =
+                    get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+                estimate::estimate_close(&signed_channel, true)
+            }
+            Operation::ForceCloseChannel(channel_id) => {
+                let signed_channel =
+                    get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+                estimate::estimate_close(&signed_channel, false)
+            }
+        }
+    }
+
+    /// Returns the expected funding transaction size, fees, number of CETs,
+    /// message sizes and signing time of a contract with the given input,
+    /// without selecting any UTXO.
+    ///
+    /// Oracle announcements are fetched from the oracles.
+    pub fn estimate_contract(
+        &self,
+        contract_input: &ContractInput,
+    ) -> Result<ContractEstimate, Error> {
+        estimate::estimate_contract(
+            contract_input.fee_rate,
+            contract_input.offer_collateral + contract_input.accept_collateral,
+            &self.get_contract_infos(contract_input)?,
+        )
+    }
+
+    /// Builds the contract input of the given offer builder and returns it,
+    /// together with the expected size of the funding transaction and costs
+    /// of the offer, without sending it. The returned input can then be
+    /// offered using [`Self::send_offer`].
+    pub fn dry_run_offer(&self, offer_builder: &OfferBuilder) -> Result<OfferDryRun, Error> {
+        let contract_input = offer_builder.build()?;
+        let estimate = self.estimate_operation(&Operation::OfferContract(&contract_input))?;
+        let funding_tx_weight = dlc::estimate_fund_tx_weight(1, 1)?;
+
+        Ok(OfferDryRun {
+            contract_input,
+            funding_tx_vsize: (funding_tx_weight + 3) / 4,
+            estimate,
+        })
+    }
+
+    fn get_contract_infos(
+        &self,
+        contract_input: &ContractInput,
+    ) -> Result<Vec<ContractInfo>, Error> {
+        contract_input.validate()?;
+        contract_input
+            .contract_infos
+            .iter()
+            .map(|x| {
+                Ok(ContractInfo {
+                    contract_descriptor: x.get_contract_descriptor(),
+                    oracle_announcements: self.get_oracle_announcements(&x.oracles)?,
+                    threshold: x.oracles.threshold as usize,
+                })
+            })
+            .collect()
+    }
+
+    fn get_oracle_announcements(
+        &self,
+        oracle_inputs: &OracleInput,
+    ) -> Result<Vec<OracleAnnouncement>, Error> {
+        let mut announcements = Vec::new();
+        for pubkey in &oracle_inputs.public_keys {
+            let oracle = self
+                .oracles
+                .get(pubkey)
+                .ok_or_else(|| Error::InvalidParameters("Unknown oracle public key".to_string()))?;
+            announcements.push(oracle.get_announcement(&oracle_inputs.event_id)?.clone());
+        }
+
+        Ok(announcements)
+    }
+
+    /// Releases the utxos reserved for the given party params when a contract
+    /// fails. Errors are only logged so that the failure is still recorded.
+    fn release_utxos_on_failure(&self, party_params: &PartyParams) {
+        if let Err(e) = release_party_utxos(&self.wallet, party_params) {
+            error!("Error releasing utxos of failed contract {}", e);
+        }
+    }
+
+    fn sign_fail_on_error<R>(
+        &self,
+        accepted_contract: AcceptedContract,
+        sign_message: SignDlc,
+        e: Error,
+    ) -> Result<R, Error> {
+        error!("Error in on_sign {}", e);
+        self.release_utxos_on_failure(&accepted_contract.accept_params);
+        self.update_contract(
+            "accepted",
+            &Contract::FailedSign(FailedSignContract {
+                accepted_contract,
+                sign_message,
+                error_message: e.to_string(),
+            }),
+            "SignDlc",
+        )?;
+        Err(e)
+    }
+
+    fn accept_fail_on_error<R>(
+        &self,
+        offered_contract: OfferedContract,
+        accept_message: AcceptDlc,
+        e: Error,
+    ) -> Result<R, Error> {
+        error!("Error in on_accept {}", e);
+        self.release_utxos_on_failure(&offered_contract.offer_params);
+        self.update_contract(
+            "offered",
+            &Contract::FailedAccept(FailedAcceptContract {
+                offered_contract,
+                accept_message,
+                error_message: e.to_string(),
+            }),
+            "AcceptDlc",
+        )?;
+        Err(e)
+    }
+
+    fn check_signed_contract(&self, contract: &SignedContract) -> Result<(), Error> {
+        enter_span!(
+            "check_signed_contract",
+            contract_id = %contract.accepted_contract.get_contract_id_string()
+        );
+        let confirmations = self.blockchain.get_transaction_confirmations(
+            &contract.accepted_contract.dlc_transactions.fund.txid(),
+        )?;
+        if confirmations >= NB_CONFIRMATIONS {
+            self.update_contract(
+                "signed",
+                &Contract::Confirmed(contract.clone()),
+                "funding confirmed",
+            )?;
+            self.check_amended_contract(contract)?;
+        }
+        Ok(())
+    }
+
+    /// Moves the confirmed contracts whose fund outputs are spent by the fund
+    /// transaction of the given contract, if it amends or nets them, to the
+    /// amended state.
+    fn check_amended_contract(&self, contract: &SignedContract) -> Result<(), Error> {
+        let fund_tx = &contract.accepted_contract.dlc_transactions.fund;
+        let counter_party = contract.accepted_contract.offered_contract.counter_party;
+        let amended = self
+            .store
+            .get_confirmed_contracts()?
+            .into_iter()
+            .filter(|c| {
+                let fund_outpoint = c.accepted_contract.dlc_transactions.get_fund_outpoint();
+                c.accepted_contract.offered_contract.counter_party == counter_party
+                    && fund_tx
+                        .input
+                        .iter()
+                        .any(|x| x.previous_output == fund_outpoint)
+            });
+        for c in amended {
+            self.update_contract("confirmed", &Contract::Amended(c), "amendment confirmed")?;
+        }
+        Ok(())
+    }
+
+    fn check_signed_contracts(&self, deadline: Option<Instant>) -> Result<(), Error> {
+        let mut contracts = self.store.get_signed_contracts()?;
+        contracts.sort_by_key(get_contract_deadline);
+        let nb_contracts = contracts.len();
+        for (i, c) in contracts.into_iter().enumerate() {
+            if is_budget_exhausted(deadline) {
+                warn!(
+                    "Periodic check budget exhausted with {} signed contract(s) left to check",
+                    nb_contracts - i
+                );
+                break;
+            }
+            let (_guard, c) = match lock_contract_in_state!(
+                self,
+                c.accepted_contract.get_contract_id(),
+                Signed
+            ) {
+                Some(locked) => locked,
+                None => continue,
+            };
+            if let Err(e) = self.check_signed_contract(&c) {
+                error!(
+                    "Error checking confirmed contract {}: {}",
+                    c.accepted_contract.get_contract_id_string(),
+                    e
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_confirmed_contracts(
+        &self,
+        deadline: Option<Instant>,
+        attestations: &AttestationCache,
+    ) -> Result<(), Error> {
+        let now = self.time.unix_time_now();
+        // Confirmed contracts from channel are processed in channel specific
+        // methods, and contracts that did not reach their maturity or refund
+        // lock time cannot be closed yet.
+        let mut contracts = self
+            .store
+            .get_confirmed_contracts()?
+            .into_iter()
+            .filter(|c| c.channel_id.is_none() && get_contract_deadline(c) <= now)
+            .collect::<Vec<_>>();
+        contracts.sort_by_key(get_contract_deadline);
+        let nb_contracts = contracts.len();
+        for (i, c) in contracts.into_iter().enumerate() {
+            if is_budget_exhausted(deadline) {
+                warn!(
+                    "Periodic check budget exhausted with {} confirmed contract(s) left to check",
+                    nb_contracts - i
+                );
+                break;
+            }
+            let (_guard, c) = match lock_contract_in_state!(
+                self,
+                c.accepted_contract.get_contract_id(),
+                Confirmed
+            ) {
+                Some(locked) => locked,
+                None => continue,
+            };
+            if let Err(e) = self.check_confirmed_contract(&c, attestations) {
+                error!(
+                    "Error checking confirmed contract {}: {}",
+                    c.accepted_contract.get_contract_id_string(),
+                    e
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the information required to close the given contract if enough
+    /// of its events were attested. The attestations are looked up in the
+    /// given ones if any, and requested from the oracles otherwise.
+    fn get_closable_contract_info<'a>(
+        &'a self,
+        contract: &'a SignedContract,
+        prefetched: Option<&AttestationCache>,
+    ) -> ClosableContractInfo<'a> {
+        let contract_infos = &contract.accepted_contract.offered_contract.contract_info;
+        let adaptor_infos = &contract.accepted_contract.adaptor_infos;
+        for (contract_info, adaptor_info) in contract_infos.iter().zip(adaptor_infos.iter()) {
+            let matured: Vec<_> = contract_info
+                .oracle_announcements
+                .iter()
+                .filter(|x| {
+                    (x.oracle_event.event_maturity_epoch as u64) <= self.time.unix_time_now()
+                })
+                .enumerate()
+                .collect();
+            if matured.len() >= contract_info.threshold {
+                let attestations: Vec<_> = matured
+                    .iter()
+                    .filter_map(|(i, announcement)| {
+                        Some((*i, self.get_attestation(announcement, prefetched).ok()?))
+                    })
+                    .collect();
+                if attestations.len() >= contract_info.threshold {
+                    return Some((contract_info, adaptor_info, attestations));
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the attestation for the event of the given announcement. When
+    /// prefetched attestations are given, as during a periodic check, only
+    /// these are used.
+    fn get_attestation(
+        &self,
+        announcement: &OracleAnnouncement,
+        prefetched: Option<&AttestationCache>,
+    ) -> Result<OracleAttestation, Error> {
+        if let Some(attestations) = prefetched {
+            return attestations
+                .get(&(
+                    announcement.oracle_public_key,
+                    announcement.oracle_event.event_id.clone(),
+                ))
+                .cloned()
+                .ok_or_else(|| Error::InvalidState("Attestation is not available".to_string()));
+        }
+        let oracle = self
+            .oracles
+            .get(&announcement.oracle_public_key)
+            .ok_or_else(|| Error::InvalidParameters("Unknown oracle public key".to_string()))?;
+        oracle.get_attestation(&announcement.oracle_event.event_id)
+    }
+
+    /// Returns the ids of the matured events of confirmed contracts, grouped
+    /// by oracle public key.
+    fn get_matured_events(&self) -> Result<HashMap<XOnlyPublicKey, Vec<String>>, Error> {
+        let now = self.time.unix_time_now();
+        let mut events = HashSet::new();
+        for contract in self.store.get_confirmed_contracts()? {
+            let offered_contract = &contract.accepted_contract.offered_contract;
+            for announcement in offered_contract
+                .contract_info
+                .iter()
+                .flat_map(|x| &x.oracle_announcements)
+            {
+                if (announcement.oracle_event.event_maturity_epoch as u64) <= now {
+                    events.insert((
+                        announcement.oracle_public_key,
+                        announcement.oracle_event.event_id.clone(),
+                    ));
+                }
+            }
+        }
+
+        let mut grouped: HashMap<XOnlyPublicKey, Vec<String>> = HashMap::new();
+        for (pubkey, event_id) in events {
+            grouped.entry(pubkey).or_default().push(event_id);
+        }
+        Ok(grouped)
+    }
+
+    /// Fetches the attestations for the matured events of confirmed
+    /// contracts, with a single [`Oracle::get_attestations`] call per oracle.
+    fn fetch_attestations(&self) -> Result<AttestationCache, Error> {
+        let mut attestations = HashMap::new();
+        for (pubkey, event_ids) in self.get_matured_events()? {
+            let oracle = match self.oracles.get(&pubkey) {
+                Some(oracle) => oracle,
+                None => continue,
+            };
+            let ids: Vec<&str> = event_ids.iter().map(|x| x.as_str()).collect();
+            let results = oracle.get_attestations(&ids);
+            // Events that were not attested yet are expected to fail.
+            for (event_id, attestation) in event_ids.into_iter().zip(results) {
+                if let Ok(attestation) = attestation {
+                    attestations.insert((pubkey, event_id), attestation);
+                }
+            }
+        }
+        Ok(attestations)
+    }
+
+    fn check_confirmed_contract(
+        &self,
+        contract: &SignedContract,
+        attestations: &AttestationCache,
+    ) -> Result<(), Error> {
+        enter_span!(
+            "check_confirmed_contract",
+            contract_id = %contract.accepted_contract.get_contract_id_string()
+        );
+        let closable_contract_info = self.get_closable_contract_info(contract, Some(attestations));
+        if let Some((contract_info, adaptor_info, attestations)) = closable_contract_info {
+            let offer = &contract.accepted_contract.offered_contract;
+            let signer = self.signer_provider.derive_contract_signer(offer.keys_id)?;
+            let cet = crate::contract_updater::get_signed_cet(
+                &self.secp,
+                contract,
+                contract_info,
+                adaptor_info,
+                &attestations,
+                &signer,
+            )
+            .context(&contract.accepted_contract.get_contract_id(), "signing CET")?;
+            match self.close_contract(
+                contract,
+                cet,
+                attestations.iter().map(|x| x.1.clone()).collect(),
+            ) {
+                Ok(closed_contract) => {
+                    self.update_contract("confirmed", &closed_contract, "oracle attestation")?;
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to close contract {}: {}",
+                        contract.accepted_contract.get_contract_id_string(),
+                        e
+                    );
+                    return Err(e);
+                }
+            }
+        }
+
+        self.check_refund(contract)?;
+
+        Ok(())
+    }
+
+    /// Manually close a contract with the oracle attestations.
+    pub fn close_confirmed_contract(
+        &self,
+        contract_id: &ContractId,
+        attestations: Vec<(usize, OracleAttestation)>,
+    ) -> Result<Contract, Error> {
+        let contract = get_contract_in_state!(self, contract_id, Confirmed, None::<PublicKey>)?;
+        let contract_infos = &contract.accepted_contract.offered_contract.contract_info;
+        let adaptor_infos = &contract.accepted_contract.adaptor_infos;
+
+        // find the contract info that matches the attestations
+        if let Some((contract_info, adaptor_info)) =
+            contract_infos.iter().zip(adaptor_infos).find(|(c, _)| {
+                let matches = attestations
+                    .iter()
+                    .filter(|(i, a)| {
+                        c.oracle_announcements[*i].oracle_event.oracle_nonces == a.nonces()
+                    })
+                    .count();
+
+                matches >= c.threshold
+            })
+        {
+            let offer = &contract.accepted_contract.offered_contract;
+            let signer = self.signer_provider.derive_contract_signer(offer.keys_id)?;
+            let cet = crate::contract_updater::get_signed_cet(
+                &self.secp,
+                &contract,
+                contract_info,
+                adaptor_info,
+                &attestations,
+                &signer,
+            )?;
+
+            // Check that the lock time has passed
+            let time = bitcoin::absolute::Time::from_consensus(self.time.unix_time_now() as u32)
+                .expect("Time is not in valid range. This should never happen.");
+            let height = Height::from_consensus(self.blockchain.get_blockchain_height()? as u32)
+                .expect("Height is not in valid range. This should never happen.");
+            let locktime = cet.lock_time;
+
+            if !locktime.is_satisfied_by(height, time) {
+                return Err(Error::InvalidState(
+                    "CET lock time has not passed yet".to_string(),
+                ));
+            }
+
+            match self.close_contract(
+                &contract,
+                cet,
+                attestations.into_iter().map(|x| x.1).collect(),
+            ) {
+                Ok(closed_contract) => {
+                    self.update_contract("confirmed", &closed_contract, "oracle attestation")?;
+                    Ok(closed_contract)
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to close contract {}: {e}",
+                        contract.accepted_contract.get_contract_id_string()
+                    );
+                    Err(e)
+                }
+            }
+        } else {
+            Err(Error::InvalidState(
+                "Attestations did not match contract infos".to_string(),
+            ))
+        }
+    }
+
+    /// Manually close a contract using attestations obtained out-of-band, for
+    /// example when the oracle cannot be reached. Unlike
+    /// [`Self::close_confirmed_contract`], the attestations are matched to the
+    /// announcements of the contract using the oracle public key and nonces,
+    /// and their signatures are verified before being used.
+    pub fn close_confirmed_contract_with_attestations(
+        &self,
+        contract_id: &ContractId,
+        attestations: Vec<OracleAttestation>,
+    ) -> Result<Contract, Error> {
+        let contract = get_contract_in_state!(self, contract_id, Confirmed, None::<PublicKey>)?;
+
+        for attestation in &attestations {
+            verify_attestation(&self.secp, attestation).map_err(|_| {
+                Error::InvalidParameters(format!(
+                    "Invalid attestation from oracle {}",
+                    attestation.oracle_public_key
+                ))
+            })?;
+        }
+
+        for contract_info in &contract.accepted_contract.offered_contract.contract_info {
+            let mut indexed_attestations: Vec<_> = attestations
+                .iter()
+                .filter_map(|attestation| {
+                    contract_info
+                        .oracle_announcements
+                        .iter()
+                        .position(|announcement| {
+                            announcement.oracle_public_key == attestation.oracle_public_key
+                                && announcement.oracle_event.oracle_nonces == attestation.nonces()
+                        })
+                        .map(|i| (i, attestation.clone()))
+                })
+                .collect();
+            indexed_attestations.sort_by_key(|x| x.0);
+            indexed_attestations.dedup_by_key(|x| x.0);
+
+            if indexed_attestations.len() >= contract_info.threshold {
+                return self.close_confirmed_contract(contract_id, indexed_attestations);
+            }
+        }
+
+        Err(Error::InvalidParameters(
+            "Attestations did not match contract infos".to_string(),
+        ))
+    }
+
+    fn check_preclosed_contracts(&self, deadline: Option<Instant>) -> Result<(), Error> {
+        let contracts = self.store.get_preclosed_contracts()?;
+        let nb_contracts = contracts.len();
+        for (i, c) in contracts.into_iter().enumerate() {
+            if is_budget_exhausted(deadline) {
+                warn!(
+                    "Periodic check budget exhausted with {} pre-closed contract(s) left to check",
+                    nb_contracts - i
+                );
+                break;
+            }
+            let (_guard, c) = match lock_contract_in_state!(
+                self,
+                c.signed_contract.accepted_contract.get_contract_id(),
+                PreClosed
+            ) {
+                Some(locked) => locked,
+                None => continue,
+            };
+            if let Err(e) = self.check_preclosed_contract(&c) {
+                error!(
+                    "Error checking pre-closed contract {}: {}",
+                    c.signed_contract.accepted_contract.get_contract_id_string(),
+                    e
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_preclosed_contract(&self, contract: &PreClosedContract) -> Result<(), Error> {
+        enter_span!(
+            "check_preclosed_contract",
+            contract_id = %contract.signed_contract.accepted_contract.get_contract_id_string()
+        );
+        let broadcasted_txid = contract.get_cet_txid();
+        let confirmations = self
+            .blockchain
+            .get_transaction_confirmations(&broadcasted_txid)?;
+        if confirmations >= NB_CONFIRMATIONS {
+            let closed_contract = ClosedContract {
+                attestations: contract.attestations.clone(),
+                signed_cet: Some(contract.signed_cet.clone()),
+                contract_id: contract.signed_contract.accepted_contract.get_contract_id(),
+                temporary_contract_id: contract
+                    .signed_contract
+                    .accepted_contract
+                    .offered_contract
+                    .id,
+                metadata: contract
+                    .signed_contract
+                    .accepted_contract
+                    .offered_contract
+                    .metadata
+                    .clone(),
+                counter_party_id: contract
+                    .signed_contract
+                    .accepted_contract
+                    .offered_contract
+                    .counter_party,
+                pnl: contract
+                    .signed_contract
+                    .accepted_contract
+                    .compute_pnl(&contract.signed_cet),
+                announcements: contract.attestations.as_ref().map(|attestations| {
+                    contract
+                        .signed_contract
+                        .get_attested_announcements(attestations)
+                }),
+            };
+            self.update_contract(
+                "pre-closed",
+                &Contract::Closed(closed_contract),
+                "CET confirmed",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn close_contract(
+        &self,
+        contract: &SignedContract,
+        signed_cet: Transaction,
+        attestations: Vec<OracleAttestation>,
+    ) -> Result<Contract, Error> {
+        let confirmations = self
+            .blockchain
+            .get_transaction_confirmations(&signed_cet.txid())?;
+
+        if confirmations < 1 {
+            // TODO(tibo): if this fails because another tx is already in
+            // mempool or blockchain, we might have been cheated. There is
+            // not much to be done apart from possibly extracting a fraud
+            // proof but ideally it should be handled.
+            self.blockchain.send_transaction(&signed_cet)?;
+
+            let preclosed_contract = PreClosedContract {
+                signed_contract: contract.clone(),
+                attestations: Some(attestations),
+                signed_cet,
+            };
+
+            return Ok(Contract::PreClosed(preclosed_contract));
+        } else if confirmations < NB_CONFIRMATIONS {
+            let preclosed_contract = PreClosedContract {
+                signed_contract: contract.clone(),
+                attestations: Some(attestations),
+                signed_cet,
+            };
+
+            return Ok(Contract::PreClosed(preclosed_contract));
+        }
+
+        let closed_contract = ClosedContract {
+            announcements: Some(contract.get_attested_announcements(&attestations)),
+            attestations: Some(attestations.to_vec()),
+            pnl: contract.accepted_contract.compute_pnl(&signed_cet),
+            signed_cet: Some(signed_cet),
+            contract_id: contract.accepted_contract.get_contract_id(),
+            temporary_contract_id: contract.accepted_contract.offered_contract.id,
+            metadata: contract.accepted_contract.offered_contract.metadata.clone(),
+            counter_party_id: contract.accepted_contract.offered_contract.counter_party,
+        };
+
+        Ok(Contract::Closed(closed_contract))
+    }
+
+    fn check_refund(&self, contract: &SignedContract) -> Result<(), Error> {
+        // TODO(tibo): should check for confirmation of refund before updating state
+        if contract
+            .accepted_contract
+            .dlc_transactions
+            .refund
+            .lock_time
+            .to_consensus_u32() as u64
+            <= self.time.unix_time_now()
+        {
+            let accepted_contract = &contract.accepted_contract;
+            let refund = accepted_contract.dlc_transactions.refund.clone();
+            let confirmations = self
+                .blockchain
+                .get_transaction_confirmations(&refund.txid())?;
+            if confirmations == 0 {
+                let offer = &contract.accepted_contract.offered_contract;
+                let signer = self.signer_provider.derive_contract_signer(offer.keys_id)?;
+                let refund =
+                    crate::contract_updater::get_signed_refund(&self.secp, contract, &signer)
+                        .context(
+                            &contract.accepted_contract.get_contract_id(),
+                            "signing refund transaction",
+                        )?;
+                self.blockchain.send_transaction(&refund)?;
+            }
+
+            self.update_contract("confirmed", &Contract::Refunded(contract.clone()), "refund")?;
+        }
+
+        Ok(())
+    }
+
+    /// Function to call when we detect that a contract was closed by our counter party.
+    /// This will update the state of the contract, which is expected to be
+    /// confirmed, and return the [`Contract`] object.
+    pub fn on_counterparty_close(
+        &self,
+        contract: &SignedContract,
+        closing_tx: Transaction,
+        confirmations: u32,
+    ) -> Result<Contract, Error> {
+        // check if the closing tx actually spends the funding output
+        if !closing_tx.input.iter().any(|i| {
+            i.previous_output
+                == contract
+                    .accepted_contract
+                    .dlc_transactions
+                    .get_fund_outpoint()
+        }) {
+            return Err(Error::InvalidParameters(
+                "Closing tx does not spend the funding tx".to_string(),
+            ));
+        }
+
+        // check if it is the refund tx (easy case)
+        if contract.accepted_contract.dlc_transactions.refund.txid() == closing_tx.txid() {
+            let refunded = Contract::Refunded(contract.clone());
+            self.update_contract("confirmed", &refunded, "counter party close")?;
+            return Ok(refunded);
+        }
+
+        let contract = if confirmations < NB_CONFIRMATIONS {
+            Contract::PreClosed(PreClosedContract {
+                signed_contract: contract.clone(),
+                attestations: None, // todo in some cases we can get the attestations from the closing tx
+                signed_cet: closing_tx,
+            })
+        } else {
+            Contract::Closed(ClosedContract {
+                attestations: None, // todo in some cases we can get the attestations from the closing tx
+                pnl: contract.accepted_contract.compute_pnl(&closing_tx),
+                signed_cet: Some(closing_tx),
+                contract_id: contract.accepted_contract.get_contract_id(),
+                temporary_contract_id: contract.accepted_contract.offered_contract.id,
+                metadata: contract.accepted_contract.offered_contract.metadata.clone(),
+                counter_party_id: contract.accepted_contract.offered_contract.counter_party,
+                announcements: None,
+            })
+        };
+
+        self.update_contract("confirmed", &contract, "counter party close")?;
+
+        Ok(contract)
+    }
+}
+
+impl<W: Deref, SP: Deref, B: Deref, S: Deref, O: Deref, T: Deref, F: Deref, X: ContractSigner>
+    Manager<W, Arc<CachedContractSignerProvider<SP, X>>, B, S, O, T, F, X>
+where
+    W::Target: Wallet,
+    SP::Target: ContractSignerProvider<Signer = X>,
+    B::Target: Blockchain,
+    S::Target: Storage,
+    O::Target: Oracle,
+    T::Target: Time,
+    F::Target: FeeEstimator,
+{
+    /// Create a new channel offer and return the [`dlc_messages::channel::OfferChannel`]
+    /// message to be sent to the `counter_party`.
+    pub fn offer_channel(
+        &self,
+        contract_input: &ContractInput,
+        counter_party: PublicKey,
+    ) -> Result<OfferChannel, Error> {
+        self.offer_channel_with_config(contract_input, counter_party, &self.channel_config)
+    }
+
+    /// Create a new channel offer using the given [`ChannelConfig`] and return
+    /// the [`dlc_messages::channel::OfferChannel`] message to be sent to the
+    /// `counter_party`.
+    pub fn offer_channel_with_config(
+        &self,
+        contract_input: &ContractInput,
+        counter_party: PublicKey,
+        config: &ChannelConfig,
+    ) -> Result<OfferChannel, Error> {
+        config.validate()?;
+        let contract_input = self.get_offer_contract_input(contract_input)?;
+        self.check_fee_rate(contract_input.fee_rate)?;
+
+        let oracle_announcements = contract_input
+            .contract_infos
+            .iter()
+            .map(|x| self.get_oracle_announcements(&x.oracles))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let (offered_channel, offered_contract) = crate::channel_updater::offer_channel(
+            &self.secp,
+            &contract_input,
+            &counter_party,
+            &oracle_announcements,
+            config,
+            REFUND_DELAY,
+            &&self.funding_wallet(),
+            &self.signer_provider,
+            &self.blockchain,
+            &self.time,
+        )?;
+
+        let mut msg = offered_channel.get_offer_channel_msg(&offered_contract);
+        msg.timestamp = Some(self.time.unix_time_now());
+
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(None, Channel::Offered(offered_channel))
+                .with_contract(None, Contract::Offered(offered_contract)),
+            "offer_channel",
+        )?;
+
+        Ok(msg)
+    }
+
+    /// Reject a channel that was offered. Returns the [`dlc_messages::channel::Reject`]
+    /// message to be sent as well as the public key of the offering node.
+    pub fn reject_channel(&self, channel_id: &ChannelId) -> Result<(Reject, PublicKey), Error> {
+        let counterparty = self.cancel_channel_offer(channel_id, "reject_channel")?;
+
+        let msg = Reject {
+            channel_id: *channel_id,
+        };
+        Ok((msg, counterparty))
+    }
+
+    /// Reject a channel that was offered, providing the reason of the
+    /// rejection. Returns the [`RejectChannelOffer`] message to be sent as well
+    /// as the public key of the offering node.
+    pub fn reject_channel_offer(
+        &self,
+        channel_id: &ChannelId,
+        reason: &str,
+    ) -> Result<(RejectChannelOffer, PublicKey), Error> {
+        let counterparty = self.cancel_channel_offer(channel_id, "reject_channel_offer")?;
+
+        let msg = RejectChannelOffer {
+            temporary_channel_id: *channel_id,
+            reason: reason.to_string(),
+        };
+        Ok((msg, counterparty))
+    }
+
+    fn cancel_channel_offer(
+        &self,
+        channel_id: &ChannelId,
+        trigger: &str,
+    ) -> Result<PublicKey, Error> {
+        let offered_channel =
+            get_channel_in_state!(self, channel_id, Offered, None as Option<PublicKey>)?;
+
+        if offered_channel.is_offer_party {
+            return Err(Error::InvalidState(
+                "Cannot reject channel initiated by us.".to_string(),
+            ));
+        }
+
+        let offered_contract = get_contract_in_state!(
+            self,
+            &offered_channel.offered_contract_id,
+            Offered,
+            None as Option<PublicKey>
+        )?;
+
+        let counterparty = offered_channel.counter_party;
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some("offered"), Channel::Cancelled(offered_channel))
+                .with_contract(Some("offered"), Contract::Rejected(offered_contract)),
+            trigger,
+        )?;
+
+        Ok(counterparty)
+    }
+
+    /// Accept a channel that was offered. Returns the [`dlc_messages::channel::AcceptChannel`]
+    /// message to be sent, the updated [`crate::ChannelId`] and [`crate::ContractId`],
+    /// as well as the public key of the offering node.
+    pub fn accept_channel(
+        &self,
+        channel_id: &ChannelId,
+    ) -> Result<(AcceptChannel, ChannelId, ContractId, PublicKey), Error> {
+        self.accept_channel_with_config(channel_id, &self.channel_config)
+    }
+
+    /// Accept a channel that was offered, checking that the [`ChannelConfig`]
+    /// offered by the counter party is acceptable given the provided one.
+    /// Returns the same values as [`Manager::accept_channel`].
+    pub fn accept_channel_with_config(
+        &self,
+        channel_id: &ChannelId,
+        config: &ChannelConfig,
+    ) -> Result<(AcceptChannel, ChannelId, ContractId, PublicKey), Error> {
+        let offered_channel =
+            get_channel_in_state!(self, channel_id, Offered, None as Option<PublicKey>)?;
+
+        if offered_channel.is_offer_party {
+            return Err(Error::InvalidState(
+                "Cannot accept channel initiated by us.".to_string(),
+            ));
+        }
+
+        config.check_offered(&offered_channel.get_config())?;
+
+        let offered_contract = get_contract_in_state!(
+            self,
+            &offered_channel.offered_contract_id,
+            Offered,
+            None as Option<PublicKey>
+        )?;
+
+        let cancellation = self.cancellation_registry.register(*channel_id);
+        let (accepted_channel, accepted_contract, accept_channel) =
+            crate::channel_updater::accept_channel_offer(
+                &self.secp,
+                &offered_channel,
+                &offered_contract,
+                &&self.funding_wallet(),
+                &self.signer_provider,
+                &self.blockchain,
+                &cancellation,
+            )?;
+
+        self.wallet.import_address(&Address::p2wsh(
+            &accepted_contract.dlc_transactions.funding_script_pubkey,
+            self.blockchain.get_network()?,
+        ))?;
+
+        let channel_id = accepted_channel.channel_id;
+        let contract_id = accepted_contract.get_contract_id();
+        let counter_party = accepted_contract.offered_contract.counter_party;
+
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some("offered"), Channel::Accepted(accepted_channel))
+                .with_contract(Some("offered"), Contract::Accepted(accepted_contract)),
+            "accept_channel",
+        )?;
+
+        Ok((accept_channel, channel_id, contract_id, counter_party))
+    }
+
+    /// Force close the channel with given [`crate::ChannelId`].
+    pub fn force_close_channel(&self, channel_id: &ChannelId) -> Result<(), Error> {
+        let channel = get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+
+        self.force_close_channel_internal(channel)
+    }
+
+    /// Offer to settle the balance of a channel so that the counter party gets
+    /// `counter_payout`. Returns the [`dlc_messages::channel::SettleChannelOffer`]
+    /// message to be sent and the public key of the counter party node.
+    pub fn settle_offer(
+        &self,
+        channel_id: &ChannelId,
+        counter_payout: u64,
+    ) -> Result<(SettleOffer, PublicKey), Error> {
+        self.check_can_initiate_update(channel_id)?;
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+
+        let config = signed_channel.config;
+        let msg = crate::channel_updater::settle_channel_offer(
+            &self.secp,
+            &mut signed_channel,
+            counter_payout,
+            config.peer_timeout,
+            &self.signer_provider,
+            &self.time,
+        )?;
+
+        let counter_party = signed_channel.counter_party;
+
+        self.upsert_channel(
+            Some(&previous),
+            Channel::Signed(signed_channel),
+            "settle_offer",
+        )?;
+
+        Ok((msg, counter_party))
+    }
+
+    /// Accept a settlement offer, returning the [`SettleAccept`] message to be
+    /// sent to the node with the returned [`PublicKey`] id.
+    pub fn accept_settle_offer(
+        &self,
+        channel_id: &ChannelId,
+    ) -> Result<(SettleAccept, PublicKey), Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+
+        let config = signed_channel.config;
+        let msg = crate::channel_updater::settle_channel_accept(
+            &self.secp,
+            &mut signed_channel,
+            config.cet_nsequence,
+            0,
+            config.peer_timeout,
+            &self.signer_provider,
+            &self.time,
+        )?;
+
+        let counter_party = signed_channel.counter_party;
+
+        self.upsert_channel(
+            Some(&previous),
+            Channel::Signed(signed_channel),
+            "accept_settle_offer",
+        )?;
+
+        Ok((msg, counter_party))
+    }
+
+    /// Returns a [`RenewOffer`] message as well as the [`PublicKey`] of the
+    /// counter party's node to offer the establishment of a new contract in the
+    /// channel. The new contract can use different events, payouts and maturity
+    /// than the current one, but must lock the total collateral of the channel.
+    pub fn renew_offer(
+        &self,
+        channel_id: &ChannelId,
+        counter_payout: u64,
+        contract_input: &ContractInput,
+    ) -> Result<(RenewOffer, PublicKey), Error> {
+        self.check_can_initiate_update(channel_id)?;
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+
+        let oracle_announcements = contract_input
+            .contract_infos
+            .iter()
+            .map(|x| self.get_oracle_announcements(&x.oracles))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let config = signed_channel.config;
+        let (msg, offered_contract) = crate::channel_updater::renew_offer(
+            &self.secp,
+            &mut signed_channel,
+            contract_input,
+            oracle_announcements,
+            counter_payout,
+            REFUND_DELAY,
+            config.peer_timeout,
+            config.cet_nsequence,
+            &self.signer_provider,
+            &self.time,
+        )?;
+
+        let counter_party = offered_contract.counter_party;
+
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some(&previous), Channel::Signed(signed_channel))
+                .with_contract(None, Contract::Offered(offered_contract)),
+            "renew_offer",
+        )?;
+
+        Ok((msg, counter_party))
+    }
+
+    /// Accept an offer to renew the contract in the channel. Returns the
+    /// [`RenewAccept`] message to be sent to the peer with the returned
+    /// [`PublicKey`] as node id.
+    pub fn accept_renew_offer(
+        &self,
+        channel_id: &ChannelId,
+    ) -> Result<(RenewAccept, PublicKey), Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+        let offered_contract_id = signed_channel.get_contract_id().ok_or_else(|| {
+            Error::InvalidState("Expected to have a contract id but did not.".to_string())
+        })?;
+
+        let offered_contract = get_contract_in_state!(
+            self,
+            &offered_contract_id,
+            Offered,
+            None as Option<PublicKey>
+        )?;
+
+        let cancellation = self.cancellation_registry.register(*channel_id);
+        let config = signed_channel.config;
+        let (accepted_contract, msg) = crate::channel_updater::accept_channel_renewal(
+            &self.secp,
+            &mut signed_channel,
+            &offered_contract,
+            config.cet_nsequence,
+            config.peer_timeout,
+            &self.signer_provider,
+            &self.time,
+            &cancellation,
+        )?;
+
+        let counter_party = signed_channel.counter_party;
+
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some(&previous), Channel::Signed(signed_channel))
+                .with_contract(Some("offered"), Contract::Accepted(accepted_contract)),
+            "accept_renew_offer",
+        )?;
+
+        Ok((msg, counter_party))
+    }
+
+    /// Reject an offer to renew the contract in the channel. Returns the
+    /// [`Reject`] message to be sent to the peer with the returned
+    /// [`PublicKey`] node id.
+    pub fn reject_renew_offer(&self, channel_id: &ChannelId) -> Result<(Reject, PublicKey), Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+        let offered_contract_id = signed_channel.get_contract_id().ok_or_else(|| {
+            Error::InvalidState(
+                "Expected to be in a state with an associated contract id but was not.".to_string(),
+            )
+        })?;
+
+        let offered_contract = get_contract_in_state!(
+            self,
+            &offered_contract_id,
+            Offered,
+            None as Option<PublicKey>
+        )?;
+
+        let reject_msg = crate::channel_updater::reject_renew_offer(&mut signed_channel)?;
+        self.quiescence.lock().unwrap().remove(channel_id);
+
+        let counter_party = signed_channel.counter_party;
+
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some(&previous), Channel::Signed(signed_channel))
+                .with_contract(Some("offered"), Contract::Rejected(offered_contract)),
+            "reject_renew_offer",
+        )?;
+
+        Ok((reject_msg, counter_party))
+    }
+
+    /// Returns an [`AddContractOffer`] message as well as the [`PublicKey`] of
+    /// the counter party's node to offer adding a contract to the channel,
+    /// alongside the ones already established in it. The collateral of the
+    /// contract is taken from the balances of the parties in the channel,
+    /// `counter_balance` being the balance of the counter party before the
+    /// update.
+    pub fn add_contract_offer(
+        &self,
+        channel_id: &ChannelId,
+        counter_balance: u64,
+        contract_input: &ContractInput,
+    ) -> Result<(AddContractOffer, PublicKey), Error> {
+        self.check_can_initiate_update(channel_id)?;
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+
+        let oracle_announcements = contract_input
+            .contract_infos
+            .iter()
+            .map(|x| self.get_oracle_announcements(&x.oracles))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let config = signed_channel.config;
+        let (msg, offered_contract) = crate::channel_updater::add_contract_offer(
+            &self.secp,
+            &mut signed_channel,
+            contract_input,
+            oracle_announcements,
+            counter_balance,
+            REFUND_DELAY,
+            config.peer_timeout,
+            config.cet_nsequence,
+            &self.signer_provider,
+            &self.time,
+        )?;
+
+        let counter_party = offered_contract.counter_party;
+
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some(&previous), Channel::Signed(signed_channel))
+                .with_contract(None, Contract::Offered(offered_contract)),
+            "add_contract_offer",
+        )?;
+
+        Ok((msg, counter_party))
+    }
+
+    /// Returns a [`SettleContractOffer`] message as well as the [`PublicKey`]
+    /// of the counter party's node to offer settling one of the contracts
+    /// established in the channel, returning its collateral to the balances of
+    /// the parties with `counter_payout` going to the counter party.
+    pub fn settle_contract_offer(
+        &self,
+        channel_id: &ChannelId,
+        contract_id: &ContractId,
+        counter_payout: u64,
+    ) -> Result<(SettleContractOffer, PublicKey), Error> {
+        self.check_can_initiate_update(channel_id)?;
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+
+        let contract =
+            get_contract_in_state!(self, contract_id, Confirmed, None as Option<PublicKey>)?;
+
+        let config = signed_channel.config;
+        let msg = crate::channel_updater::settle_contract_offer(
+            &self.secp,
+            &mut signed_channel,
+            &contract,
+            counter_payout,
+            config.peer_timeout,
+            &self.signer_provider,
+            &self.time,
+        )?;
+
+        let counter_party = signed_channel.counter_party;
+
+        self.upsert_channel(
+            Some(&previous),
+            Channel::Signed(signed_channel),
+            "settle_contract_offer",
+        )?;
+
+        Ok((msg, counter_party))
+    }
+
+    /// Accept an offer to add or settle a contract of the channel. Returns the
+    /// [`ContractUpdateAccept`] message to be sent to the peer with the
+    /// returned [`PublicKey`] as node id.
+    pub fn accept_contract_update(
+        &self,
+        channel_id: &ChannelId,
+    ) -> Result<(ContractUpdateAccept, PublicKey), Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+
+        let (contracts, added_contract) = self.get_contract_update_contracts(&signed_channel)?;
+
+        let cancellation = self.cancellation_registry.register(*channel_id);
+        let config = signed_channel.config;
+        let msg = crate::channel_updater::accept_contract_update(
+            &self.secp,
+            &mut signed_channel,
+            &contracts,
+            added_contract.as_ref(),
+            config.cet_nsequence,
+            config.peer_timeout,
+            &self.signer_provider,
+            &self.time,
+            &cancellation,
+        )?;
+
+        let counter_party = signed_channel.counter_party;
+
+        self.upsert_channel(
+            Some(&previous),
+            Channel::Signed(signed_channel),
+            "accept_contract_update",
+        )?;
+
+        Ok((msg, counter_party))
+    }
+
+    /// Reject an offer to add or settle a contract of the channel. Returns the
+    /// [`Reject`] message to be sent to the peer with the returned
+    /// [`PublicKey`] node id.
+    pub fn reject_contract_update_offer(
+        &self,
+        channel_id: &ChannelId,
+    ) -> Result<(Reject, PublicKey), Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+
+        let (_, added_contract) = self.get_contract_update_contracts(&signed_channel)?;
+
+        let reject_msg = crate::channel_updater::reject_contract_update_offer(&mut signed_channel)?;
+        self.quiescence.lock().unwrap().remove(channel_id);
+
+        let counter_party = signed_channel.counter_party;
+
+        let mut update =
+            StateUpdate::new().with_channel(Some(&previous), Channel::Signed(signed_channel));
+        if let Some(added_contract) = added_contract {
+            update = update.with_contract(Some("offered"), Contract::Rejected(added_contract));
+        }
+        self.write_batch(update, "reject_contract_update_offer")?;
+
+        Ok((reject_msg, counter_party))
+    }
+
+    /// Returns a [`Reject`] message to be sent to the counter party of the
+    /// channel to inform them that the local party does not wish to accept the
+    /// proposed settle offer.
+    pub fn reject_settle_offer(
+        &self,
+        channel_id: &ChannelId,
+    ) -> Result<(Reject, PublicKey), Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+
+        let msg = crate::channel_updater::reject_settle_offer(&mut signed_channel)?;
+        self.quiescence.lock().unwrap().remove(channel_id);
+
+        let counter_party = signed_channel.counter_party;
+
+        self.upsert_channel(
+            Some(&previous),
+            Channel::Signed(signed_channel),
+            "reject_settle_offer",
+        )?;
+
+        Ok((msg, counter_party))
+    }
+
+    /// Returns a [`Stop`] message to be sent to the counter party of the channel
+    /// to request that no other update be made to the channel until the local
+    /// party performs one. The local party can start a settle, renew or
+    /// collaborative close operation once the counter party replied with its
+    /// own [`Stop`] message.
+    pub fn stop_channel(&self, channel_id: &ChannelId) -> Result<(Stop, PublicKey), Error> {
+        let signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+
+        if !signed_channel.is_in_stable_state() {
+            return Err(Error::InvalidState(format!(
+                "Cannot stop channel in state {}.",
+                signed_channel.state
+            )));
+        }
+
+        let mut quiescence = self.quiescence.lock().unwrap();
+        if quiescence.contains_key(channel_id) {
+            return Err(Error::InvalidState(
+                "Channel is already stopped or being stopped.".to_string(),
+            ));
+        }
+
+        quiescence.insert(*channel_id, Quiescence::StopSent);
+
+        Ok((
+            Stop {
+                channel_id: *channel_id,
+                initiator: true,
+            },
+            signed_channel.counter_party,
+        ))
+    }
+
+    /// Returns a [`Resume`] message to be sent to the counter party of the
+    /// channel to end its quiescence without performing any update.
+    pub fn resume_channel(&self, channel_id: &ChannelId) -> Result<(Resume, PublicKey), Error> {
+        let signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+
+        if !signed_channel.is_in_stable_state() {
+            return Err(Error::InvalidState(
+                "Cannot resume channel while an update is in progress.".to_string(),
+            ));
+        }
+
+        self.quiescence.lock().unwrap().remove(channel_id);
+
+        Ok((
+            Resume {
+                channel_id: *channel_id,
+            },
+            signed_channel.counter_party,
+        ))
+    }
+
+    /// Returns the quiescence status of the channel with given id, or `None` if
+    /// the channel is not stopped.
+    pub fn get_quiescence(&self, channel_id: &ChannelId) -> Option<Quiescence> {
+        self.quiescence.lock().unwrap().get(channel_id).cloned()
+    }
+
+    /// Returns a [`CollaborativeCloseOffer`] message to be sent to the counter
+    /// party of the channel and update the state of the channel. Note that the
+    /// channel will be forced closed after a timeout if the counter party does
+    /// not broadcast the close transaction.
+    pub fn offer_collaborative_close(
+        &self,
+        channel_id: &ChannelId,
+        counter_payout: u64,
+    ) -> Result<CollaborativeCloseOffer, Error> {
+        self.check_can_initiate_update(channel_id)?;
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+
+        let (msg, close_tx) = crate::channel_updater::offer_collaborative_close(
+            &self.secp,
+            &mut signed_channel,
+            counter_payout,
+            &self.signer_provider,
+            &self.time,
+        )?;
+
+        self.chain_monitor.lock().unwrap().add_tx(
+            close_tx.txid(),
+            ChannelInfo {
+                channel_id: *channel_id,
+                tx_type: TxType::CollaborativeClose,
+            },
+        );
+
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some(&previous), Channel::Signed(signed_channel))
+                .with_chain_monitor(self.chain_monitor.lock().unwrap().clone()),
+            "offer_collaborative_close",
+        )?;
+
+        Ok(msg)
+    }
+
+    /// Accept an offer to collaboratively close the channel. The close transaction
+    /// will be broadcast and the state of the channel updated.
+    pub fn accept_collaborative_close(&self, channel_id: &ChannelId) -> Result<(), Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+
+        let closed_contract = self.get_collaboratively_closed_contract(&signed_channel)?;
+
+        let close_tx = crate::channel_updater::accept_collaborative_close_offer(
+            &self.secp,
+            &mut signed_channel,
+            &self.signer_provider,
+        )?;
+
+        self.finalize_collaborative_close(&previous, signed_channel, closed_contract, close_tx)
+    }
+
+    /// Returns a [`PartiallySignedTransaction`] for the collaborative close
+    /// transaction of the channel with given [`crate::ChannelId`], containing
+    /// the counter party's signature. This enables the local signature for the
+    /// funding output to be produced by an external signer, after which the
+    /// close can be completed using [`Self::accept_collaborative_close_with_psbt`].
+    pub fn get_collaborative_close_psbt(
+        &self,
+        channel_id: &ChannelId,
+    ) -> Result<PartiallySignedTransaction, Error> {
+        let signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+
+        crate::channel_updater::get_collaborative_close_psbt(&self.secp, &signed_channel)
+    }
+
+    /// Accept an offer to collaboratively close the channel using a
+    /// [`PartiallySignedTransaction`] containing the local party's signature.
+    /// The close transaction will be broadcast and the state of the channel
+    /// updated.
+    pub fn accept_collaborative_close_with_psbt(
+        &self,
+        channel_id: &ChannelId,
+        psbt: &PartiallySignedTransaction,
+    ) -> Result<(), Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
+        let previous = signed_channel.get_state_name();
+
+        let closed_contract = self.get_collaboratively_closed_contract(&signed_channel)?;
+
+        let close_tx = crate::channel_updater::accept_collaborative_close_offer_with_psbt(
+            &self.secp,
+            &mut signed_channel,
+            psbt,
+        )?;
+
+        self.finalize_collaborative_close(&previous, signed_channel, closed_contract, close_tx)
+    }
+
+    fn get_collaboratively_closed_contract(
+        &self,
+        signed_channel: &SignedChannel,
+    ) -> Result<Option<ClosedContract>, Error> {
+        if let Some(SignedChannelState::Established {
+            signed_contract_id,
+            is_offer,
+            ..
+        }) = &signed_channel.roll_back_state
+        {
+            let counter_payout = get_signed_channel_state!(
+                signed_channel,
+                CollaborativeCloseOffered,
+                counter_payout
+            )?;
+            let contract =
+                get_contract_in_state!(self, signed_contract_id, Confirmed, None::<PublicKey>)?;
+            let own_collateral = if *is_offer {
+                contract
+                    .accepted_contract
+                    .offered_contract
+                    .offer_params
+                    .collateral
+            } else {
+                contract.accepted_contract.accept_params.collateral
+            };
+            let pnl = own_collateral as i64 - counter_payout as i64;
+            Ok(Some(ClosedContract {
+                attestations: None,
+                signed_cet: None,
+                contract_id: *signed_contract_id,
+                temporary_contract_id: contract.accepted_contract.offered_contract.id,
+                metadata: contract.accepted_contract.offered_contract.metadata.clone(),
+                counter_party_id: signed_channel.counter_party,
+                pnl,
+                announcements: None,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn finalize_collaborative_close(
+        &self,
+        previous: &str,
+        signed_channel: SignedChannel,
+        closed_contract: Option<ClosedContract>,
+        close_tx: Transaction,
+    ) -> Result<(), Error> {
+        self.blockchain.send_transaction(&close_tx)?;
+
+        let mut update =
+            StateUpdate::new().with_channel(Some(previous), Channel::Signed(signed_channel));
+
+        if let Some(closed_contract) = closed_contract {
+            update = update.with_contract(Some("confirmed"), Contract::Closed(closed_contract));
+        }
+
+        self.write_batch(update, "collaborative close confirmed")?;
+
+        Ok(())
+    }
+
+    fn try_finalize_closing_established_channel(
+        &self,
+        mut signed_channel: SignedChannel,
+    ) -> Result<(), Error> {
+        let previous = signed_channel.get_state_name();
+        let (buffer_tx, signed_cet, contract_id, attestations) = get_signed_channel_state!(
+            signed_channel,
+            Closing,
+            buffer_transaction,
+            signed_cet,
+            contract_id,
+            attestations
+        )?;
+
+        if self
+            .blockchain
+            .get_transaction_confirmations(&buffer_tx.txid())?
+            > signed_channel.config.cet_nsequence
+        {
+            let confirmed_contract =
+                get_contract_in_state!(self, &contract_id, Confirmed, None as Option<PublicKey>)?;
+
+            let closed_contract =
+                self.close_contract(&confirmed_contract, signed_cet, attestations)?;
+
+            signed_channel.state = SignedChannelState::Closed;
+
+            self.write_batch(
+                StateUpdate::new()
+                    .with_channel(Some(&previous), Channel::Signed(signed_channel))
+                    .with_contract(Some("confirmed"), closed_contract),
+                "closing transaction confirmed",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn try_finalize_closing_contracts_channel(
+        &self,
+        mut signed_channel: SignedChannel,
+        attestations: &AttestationCache,
+    ) -> Result<(), Error> {
+        let previous = signed_channel.get_state_name();
+        let (buffer_txid, contract_ids) = match &signed_channel.state {
+            SignedChannelState::ContractsClosing {
+                buffer_transaction,
+                contract_ids,
+                ..
+            } => (buffer_transaction.txid(), contract_ids.clone()),
+            s => {
+                return Err(Error::InvalidState(format!(
+                    "Expected state ContractsClosing got {:?}",
+                    s
+                )))
+            }
+        };
+
+        if self
+            .blockchain
+            .get_transaction_confirmations(&buffer_txid)?
+            <= signed_channel.config.cet_nsequence
+        {
+            return Ok(());
+        }
+
+        let mut remaining_ids = Vec::new();
+        let mut closed_contracts = Vec::new();
+        for contract_id in contract_ids {
+            let confirmed_contract =
+                get_contract_in_state!(self, &contract_id, Confirmed, None as Option<PublicKey>)?;
+
+            let (contract_info, adaptor_info, attestations) =
+                match self.get_closable_contract_info(&confirmed_contract, Some(attestations)) {
+                    Some(info) => info,
+                    None => {
+                        remaining_ids.push(contract_id);
+                        continue;
+                    }
+                };
+
+            let signed_cet = crate::channel_updater::get_signed_channel_contract_cet(
+                &self.secp,
+                &signed_channel,
+                &confirmed_contract,
+                contract_info,
+                adaptor_info,
+                &attestations,
+                &self.signer_provider,
+            )?;
+
+            closed_contracts.push(self.close_contract(
+                &confirmed_contract,
+                signed_cet,
+                attestations.into_iter().map(|x| x.1).collect(),
+            )?);
+        }
+
+        // A channel whose contracts were all settled is closed as soon as its
+        // buffer transaction is confirmed.
+        if closed_contracts.is_empty() && !remaining_ids.is_empty() {
+            return Ok(());
+        }
+
+        if remaining_ids.is_empty() {
+            signed_channel.state = SignedChannelState::Closed;
+        } else if let SignedChannelState::ContractsClosing { contract_ids, .. } =
+            &mut signed_channel.state
+        {
+            *contract_ids = remaining_ids;
+        }
+
+        let mut update =
+            StateUpdate::new().with_channel(Some(&previous), Channel::Signed(signed_channel));
+        for closed_contract in closed_contracts {
+            update = update.with_contract(Some("confirmed"), closed_contract);
+        }
+
+        self.write_batch(update, "closing transaction confirmed")
+    }
+
+    fn on_offer_channel(
+        &self,
+        offer_channel: &OfferChannel,
+        counter_party: PublicKey,
+    ) -> Result<(), Error> {
+        enter_span!(
+            "on_offer_channel",
+            channel_id = %offer_channel.temporary_channel_id.to_lower_hex_string()
+        );
+        offer_channel.validate(&self.secp, REFUND_DELAY, REFUND_DELAY * 2, 1, u32::MAX)?;
+        self.check_message_age(offer_channel.timestamp)?;
+        self.check_fee_rate(offer_channel.fee_rate_per_vb)?;
+        if offer_channel.contract_flags & (ANCHOR_OUTPUTS_FLAG | TAPROOT_FUNDING_FLAG) != 0 {
+            return Err(Error::InvalidParameters(
+                "Anchor outputs and taproot funding are not supported for channels".to_string(),
+            ));
+        }
+
+        let keys_id = self
+            .signer_provider
+            .derive_signer_key_id(false, offer_channel.temporary_contract_id);
+        let (channel, contract) =
+            OfferedChannel::from_offer_channel(offer_channel, counter_party, keys_id)?;
+        channel.get_config().validate()?;
+
+        contract.validate_announcements(&self.secp)?;
+        contract.validate()?;
+        self.check_self_dealing(&contract, &offer_channel.funding_pubkey)?;
+
+        if self
+            .store
+            .get_channel(&channel.temporary_channel_id)?
+            .is_some()
+        {
+            return Err(Error::InvalidParameters(
+                "Channel with identical idea already in store".to_string(),
+            ));
+        }
+
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(None, Channel::Offered(channel))
+                .with_contract(None, Contract::Offered(contract)),
+            "OfferChannel",
+        )?;
+
+        Ok(())
+    }
+
+    fn on_accept_channel(
+        &self,
+        accept_channel: &AcceptChannel,
+        peer_id: &PublicKey,
+    ) -> Result<SignChannel, Error> {
+        enter_span!(
+            "on_accept_channel",
+            channel_id = %accept_channel.temporary_channel_id.to_lower_hex_string()
+        );
+        let offered_channel = get_channel_in_state!(
+            self,
+            &accept_channel.temporary_channel_id,
+            Offered,
+            Some(*peer_id)
+        )?;
+        let offered_contract = get_contract_in_state!(
+            self,
+            &offered_channel.offered_contract_id,
+            Offered,
+            Some(*peer_id)
+        )?;
+
+        let (signed_channel, signed_contract, sign_channel) = {
+            let res = crate::channel_updater::verify_and_sign_accepted_channel(
+                &self.secp,
+                &offered_channel,
+                &offered_contract,
+                accept_channel,
+                &self.wallet,
+                &self.signer_provider,
+                &CancellationToken::new(),
+            );
+
+            match res {
+                Ok(res) => res,
+                Err(e) => {
+                    self.release_utxos_on_failure(&offered_contract.offer_params);
+                    let channel = crate::channel::FailedAccept {
+                        temporary_channel_id: accept_channel.temporary_channel_id,
+                        error_message: format!("Error validating accept channel: {}", e),
+                        accept_message: accept_channel.clone(),
+                        counter_party: *peer_id,
+                    };
+                    self.upsert_channel(
+                        Some("offered"),
+                        Channel::FailedAccept(channel),
+                        "AcceptChannel",
+                    )?;
+                    return Err(e);
+                }
+            }
+        };
+
+        self.wallet.import_address(&Address::p2wsh(
+            &signed_contract
+                .accepted_contract
+                .dlc_transactions
+                .funding_script_pubkey,
+            self.blockchain.get_network()?,
+        ))?;
+
+        if let SignedChannelState::Established {
+            buffer_transaction, ..
+        } = &signed_channel.state
+        {
+            self.chain_monitor.lock().unwrap().add_tx(
+                buffer_transaction.txid(),
+                ChannelInfo {
+                    channel_id: signed_channel.channel_id,
+                    tx_type: TxType::Current,
+                },
+            );
+        } else {
+            unreachable!();
+        }
+
+        let contract = get_established_channel_contract(&signed_channel, signed_contract);
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some("offered"), Channel::Signed(signed_channel))
+                .with_contract(Some("offered"), contract)
+                .with_chain_monitor(self.chain_monitor.lock().unwrap().clone()),
+            "AcceptChannel",
+        )?;
+
+        Ok(sign_channel)
+    }
+
+    fn on_sign_channel(
+        &self,
+        sign_channel: &SignChannel,
+        peer_id: &PublicKey,
+    ) -> Result<(), Error> {
+        enter_span!(
+            "on_sign_channel",
+            channel_id = %sign_channel.channel_id.to_lower_hex_string()
+        );
+        let accepted_channel =
+            get_channel_in_state!(self, &sign_channel.channel_id, Accepted, Some(*peer_id))?;
+        let accepted_contract = get_contract_in_state!(
+            self,
+            &accepted_channel.accepted_contract_id,
+            Accepted,
+            Some(*peer_id)
+        )?;
+
+        let (signed_channel, signed_contract, signed_fund_tx) = {
+            let res = verify_signed_channel(
+                &self.secp,
+                &accepted_channel,
+                &accepted_contract,
+                sign_channel,
+                &self.wallet,
+                &CancellationToken::new(),
+            );
+
+            match res {
+                Ok(res) => res,
+                Err(e) => {
+                    self.release_utxos_on_failure(&accepted_contract.accept_params);
+                    let channel = crate::channel::FailedSign {
+                        channel_id: sign_channel.channel_id,
+                        error_message: format!("Error validating accept channel: {}", e),
+                        sign_message: sign_channel.clone(),
+                        counter_party: *peer_id,
+                    };
+                    self.upsert_channel(
+                        Some("accepted"),
+                        Channel::FailedSign(channel),
+                        "SignChannel",
+                    )?;
+                    return Err(e);
+                }
+            }
+        };
+
+        if let SignedChannelState::Established {
+            buffer_transaction, ..
+        } = &signed_channel.state
+        {
+            self.chain_monitor.lock().unwrap().add_tx(
+                buffer_transaction.txid(),
+                ChannelInfo {
+                    channel_id: signed_channel.channel_id,
+                    tx_type: TxType::Current,
+                },
+            );
+        } else {
+            unreachable!();
+        }
+
+        self.blockchain.send_transaction(&signed_fund_tx)?;
+
+        let contract = get_established_channel_contract(&signed_channel, signed_contract);
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some("accepted"), Channel::Signed(signed_channel))
+                .with_contract(Some("accepted"), contract)
+                .with_chain_monitor(self.chain_monitor.lock().unwrap().clone()),
+            "SignChannel",
+        )?;
+
+        Ok(())
+    }
+
+    fn on_settle_offer(
+        &self,
+        settle_offer: &SettleOffer,
+        peer_id: &PublicKey,
+    ) -> Result<Option<Reject>, Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, &settle_offer.channel_id, Signed, Some(*peer_id))?;
+        let previous = signed_channel.get_state_name();
+
+        if let SignedChannelState::SettledOffered { .. } = signed_channel.state {
+            return Ok(Some(Reject {
+                channel_id: settle_offer.channel_id,
+            }));
+        }
+
+        // The local party is the one allowed to initiate the next update.
+        if self.is_update_reserved(&settle_offer.channel_id) {
+            return Ok(Some(Reject {
+                channel_id: settle_offer.channel_id,
+            }));
+        }
+
+        match crate::channel_updater::on_settle_offer(
+            &mut signed_channel,
+            settle_offer,
+            &self.channel_policy,
+        ) {
+            Err(Error::ChannelPolicyViolation(reason)) => {
+                warn!(
+                    "Rejecting settle offer for channel {}: {}",
+                    settle_offer.channel_id.to_lower_hex_string(),
+                    reason
+                );
+                return Ok(Some(Reject {
+                    channel_id: settle_offer.channel_id,
+                }));
+            }
+            res => res?,
+        };
+
+        self.upsert_channel(
+            Some(&previous),
+            Channel::Signed(signed_channel),
+            "SettleOffer",
+        )?;
+
+        Ok(None)
+    }
+
+    fn on_settle_accept(
+        &self,
+        settle_accept: &SettleAccept,
+        peer_id: &PublicKey,
+    ) -> Result<SettleConfirm, Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, &settle_accept.channel_id, Signed, Some(*peer_id))?;
+        let previous = signed_channel.get_state_name();
+
+        let config = signed_channel.config;
+        let msg = crate::channel_updater::settle_channel_confirm(
+            &self.secp,
+            &mut signed_channel,
+            settle_accept,
+            config.cet_nsequence,
+            0,
+            config.peer_timeout,
+            &self.signer_provider,
+            &self.time,
+        )?;
+
+        self.upsert_channel(
+            Some(&previous),
+            Channel::Signed(signed_channel),
+            "SettleAccept",
+        )?;
+
+        Ok(msg)
+    }
+
+    fn on_settle_confirm(
+        &self,
+        settle_confirm: &SettleConfirm,
+        peer_id: &PublicKey,
+    ) -> Result<SettleFinalize, Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, &settle_confirm.channel_id, Signed, Some(*peer_id))?;
+        let previous = signed_channel.get_state_name();
+        let own_payout = get_signed_channel_state!(signed_channel, SettledAccepted, own_payout)?;
+        let (prev_buffer_tx, own_buffer_adaptor_signature, is_offer, signed_contract_id) = get_signed_channel_rollback_state!(
+            signed_channel,
+            Established,
+            buffer_transaction,
+            own_buffer_adaptor_signature,
+            is_offer,
+            signed_contract_id
+        )?;
+
+        let prev_buffer_txid = prev_buffer_tx.txid();
+        let own_buffer_adaptor_signature = *own_buffer_adaptor_signature;
+        let is_offer = *is_offer;
+        let signed_contract_id = *signed_contract_id;
+
+        let msg = crate::channel_updater::settle_channel_finalize(
+            &self.secp,
+            &mut signed_channel,
+            settle_confirm,
+            &self.signer_provider,
+        )?;
+
+        self.watch_revoked_tx(
+            &signed_channel,
+            prev_buffer_txid,
+            TxType::Revoked {
+                update_idx: signed_channel.update_idx + 1,
+                own_adaptor_signature: own_buffer_adaptor_signature,
+                is_offer,
+                revoked_tx_type: RevokedTxType::Buffer,
+            },
+        )?;
+
+        let contract =
+            get_contract_in_state!(self, &signed_contract_id, Confirmed, None::<PublicKey>)?;
+
+        let own_collateral = if contract.accepted_contract.offered_contract.is_offer_party {
+            contract
+                .accepted_contract
+                .offered_contract
+                .offer_params
+                .collateral
+        } else {
+            contract.accepted_contract.accept_params.collateral
+        };
+
+        let closed_contract = Contract::Closed(ClosedContract {
+            attestations: None,
+            signed_cet: None,
+            contract_id: signed_contract_id,
+            temporary_contract_id: contract.accepted_contract.offered_contract.id,
+            metadata: contract.accepted_contract.offered_contract.metadata.clone(),
+            counter_party_id: signed_channel.counter_party,
+            pnl: (own_collateral as i64) - (own_payout as i64),
+            announcements: None,
+        });
+
+        self.quiescence
+            .lock()
+            .unwrap()
+            .remove(&signed_channel.channel_id);
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some(&previous), Channel::Signed(signed_channel))
+                .with_contract(Some("confirmed"), closed_contract)
+                .with_chain_monitor(self.chain_monitor.lock().unwrap().clone()),
+            "SettleConfirm",
+        )?;
+
+        Ok(msg)
+    }
+
+    fn on_settle_finalize(
+        &self,
+        settle_finalize: &SettleFinalize,
+        peer_id: &PublicKey,
+    ) -> Result<(), Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, &settle_finalize.channel_id, Signed, Some(*peer_id))?;
+        let previous = signed_channel.get_state_name();
+        let own_payout = get_signed_channel_state!(signed_channel, SettledConfirmed, own_payout)?;
+        let (buffer_tx, own_buffer_adaptor_signature, is_offer, signed_contract_id) = get_signed_channel_rollback_state!(
+            signed_channel,
+            Established,
+            buffer_transaction,
+            own_buffer_adaptor_signature,
+            is_offer,
+            signed_contract_id
+        )?;
+
+        let own_buffer_adaptor_signature = *own_buffer_adaptor_signature;
+        let is_offer = *is_offer;
+        let buffer_txid = buffer_tx.txid();
+        let signed_contract_id = *signed_contract_id;
+
+        crate::channel_updater::settle_channel_on_finalize(
+            &self.secp,
+            &mut signed_channel,
+            settle_finalize,
+        )?;
+
+        self.watch_revoked_tx(
+            &signed_channel,
+            buffer_txid,
+            TxType::Revoked {
+                update_idx: signed_channel.update_idx + 1,
+                own_adaptor_signature: own_buffer_adaptor_signature,
+                is_offer,
+                revoked_tx_type: RevokedTxType::Buffer,
+            },
+        )?;
+
+        let contract =
+            get_contract_in_state!(self, &signed_contract_id, Confirmed, None::<PublicKey>)?;
+
+        let own_collateral = if contract.accepted_contract.offered_contract.is_offer_party {
+            contract
+                .accepted_contract
+                .offered_contract
+                .offer_params
+                .collateral
+        } else {
+            contract.accepted_contract.accept_params.collateral
+        };
+
+        let closed_contract = Contract::Closed(ClosedContract {
+            attestations: None,
+            signed_cet: None,
+            contract_id: signed_contract_id,
+            temporary_contract_id: contract.accepted_contract.offered_contract.id,
+            metadata: contract.accepted_contract.offered_contract.metadata.clone(),
+            counter_party_id: signed_channel.counter_party,
+            pnl: (own_collateral as i64) - (own_payout as i64),
+            announcements: None,
+        });
+
+        self.quiescence
+            .lock()
+            .unwrap()
+            .remove(&signed_channel.channel_id);
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some(&previous), Channel::Signed(signed_channel))
+                .with_contract(Some("confirmed"), closed_contract)
+                .with_chain_monitor(self.chain_monitor.lock().unwrap().clone()),
+            "SettleFinalize",
+        )?;
+
+        Ok(())
+    }
+
+    fn on_renew_offer(
+        &self,
+        renew_offer: &RenewOffer,
+        peer_id: &PublicKey,
+    ) -> Result<Option<Reject>, Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, &renew_offer.channel_id, Signed, Some(*peer_id))?;
+        let previous = signed_channel.get_state_name();
+
+        // Received a renew offer when we already sent one, we reject it.
+        if let SignedChannelState::RenewOffered { is_offer, .. } = signed_channel.state {
+            if is_offer {
+                return Ok(Some(Reject {
+                    channel_id: renew_offer.channel_id,
+                }));
+            }
+        }
+
+        // The local party is the one allowed to initiate the next update.
+        if self.is_update_reserved(&renew_offer.channel_id) {
+            return Ok(Some(Reject {
+                channel_id: renew_offer.channel_id,
+            }));
+        }
+
+        let config = signed_channel.config;
+        let offered_contract = match crate::channel_updater::on_renew_offer(
+            &self.secp,
+            &mut signed_channel,
+            renew_offer,
+            &self.channel_policy,
+            config.peer_timeout,
+            &self.time,
+        ) {
+            Err(Error::ChannelPolicyViolation(reason)) => {
+                warn!(
+                    "Rejecting renew offer for channel {}: {}",
+                    renew_offer.channel_id.to_lower_hex_string(),
+                    reason
+                );
+                return Ok(Some(Reject {
+                    channel_id: renew_offer.channel_id,
+                }));
+            }
+            res => res?,
+        };
+
+        self.write_batch(
+            StateUpdate::new()
+                .with_new_contract(offered_contract)
+                .with_channel(Some(&previous), Channel::Signed(signed_channel)),
+            "RenewOffer",
+        )?;
+
+        Ok(None)
+    }
+
+    fn on_renew_accept(
+        &self,
+        renew_accept: &RenewAccept,
+        peer_id: &PublicKey,
+    ) -> Result<RenewConfirm, Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, &renew_accept.channel_id, Signed, Some(*peer_id))?;
+        let previous = signed_channel.get_state_name();
+        let offered_contract_id = signed_channel.get_contract_id().ok_or_else(|| {
+            Error::InvalidState(
+                "Expected to be in a state with an associated contract id but was not.".to_string(),
+            )
+        })?;
+
+        let offered_contract =
+            get_contract_in_state!(self, &offered_contract_id, Offered, Some(*peer_id))?;
+
+        let config = signed_channel.config;
+        let (signed_contract, msg) = crate::channel_updater::verify_renew_accept_and_confirm(
+            &self.secp,
+            renew_accept,
+            &mut signed_channel,
+            &offered_contract,
+            config.cet_nsequence,
+            config.peer_timeout,
+            &self.wallet,
+            &self.signer_provider,
+            &self.time,
+            &CancellationToken::new(),
+        )?;
+
+        // Directly confirmed as we're in a channel the fund tx is already confirmed.
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some(&previous), Channel::Signed(signed_channel))
+                .with_contract(Some("offered"), Contract::Confirmed(signed_contract)),
+            "RenewAccept",
+        )?;
+
+        Ok(msg)
+    }
+
+    fn on_renew_confirm(
+        &self,
+        renew_confirm: &RenewConfirm,
+        peer_id: &PublicKey,
+    ) -> Result<RenewFinalize, Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, &renew_confirm.channel_id, Signed, Some(*peer_id))?;
+        let previous = signed_channel.get_state_name();
+        let contract_id = signed_channel.get_contract_id().ok_or_else(|| {
+            Error::InvalidState(
+                "Expected to be in a state with an associated contract id but was not.".to_string(),
+            )
+        })?;
+
+        let (tx_type, prev_tx_id, closed_contract) = match signed_channel
+            .roll_back_state
+            .as_ref()
+            .expect("to have a rollback state")
+        {
+            SignedChannelState::Established {
+                own_buffer_adaptor_signature,
+                buffer_transaction,
+                signed_contract_id,
+                ..
+            } => {
+                let contract =
+                    get_contract_in_state!(self, signed_contract_id, Confirmed, None::<PublicKey>)?;
+                let own_collateral = if contract.accepted_contract.offered_contract.is_offer_party {
+                    contract
+                        .accepted_contract
+                        .offered_contract
+                        .offer_params
+                        .collateral
+                } else {
+                    contract.accepted_contract.accept_params.collateral
+                };
+                let pnl = (contract.accepted_contract.offered_contract.total_collateral as i64)
+                    - (own_collateral as i64);
+                let closed_contract = Contract::Closed(ClosedContract {
+                    attestations: None,
+                    signed_cet: None,
+                    contract_id: *signed_contract_id,
+                    temporary_contract_id: contract.accepted_contract.offered_contract.id,
+                    metadata: contract.accepted_contract.offered_contract.metadata.clone(),
+                    counter_party_id: signed_channel.counter_party,
+                    pnl,
+                    announcements: None,
+                });
+                (
+                    TxType::Revoked {
+                        update_idx: signed_channel.update_idx,
+                        own_adaptor_signature: *own_buffer_adaptor_signature,
+                        is_offer: false,
+                        revoked_tx_type: RevokedTxType::Buffer,
+                    },
+                    buffer_transaction.txid(),
+                    Some(closed_contract),
+                )
+            }
+            SignedChannelState::Settled {
+                settle_tx,
+                own_settle_adaptor_signature,
+                ..
+            } => (
+                TxType::Revoked {
+                    update_idx: signed_channel.update_idx,
+                    own_adaptor_signature: *own_settle_adaptor_signature,
+                    is_offer: false,
+                    revoked_tx_type: RevokedTxType::Settle,
+                },
+                settle_tx.txid(),
+                None,
+            ),
+            s => {
+                return Err(Error::InvalidState(format!(
+                    "Expected rollback state Established or Revoked but found {:?}",
+                    s
+                )))
+            }
+        };
+
+        let accepted_contract =
+            get_contract_in_state!(self, &contract_id, Accepted, Some(*peer_id))?;
+
+        let (signed_contract, msg) = crate::channel_updater::verify_renew_confirm_and_finalize(
+            &self.secp,
+            &mut signed_channel,
+            &accepted_contract,
+            renew_confirm,
+            &self.wallet,
+            &self.signer_provider,
+            &CancellationToken::new(),
+        )?;
+
+        self.watch_revoked_tx(&signed_channel, prev_tx_id, tx_type)?;
+
+        let buffer_tx =
+            get_signed_channel_state!(signed_channel, Established, ref buffer_transaction)?;
+
+        self.chain_monitor.lock().unwrap().add_tx(
+            buffer_tx.txid(),
+            ChannelInfo {
+                channel_id: signed_channel.channel_id,
+                tx_type: TxType::Current,
+            },
+        );
+
+        self.quiescence
+            .lock()
+            .unwrap()
+            .remove(&signed_channel.channel_id);
+
+        // Directly confirmed as we're in a channel the fund tx is already confirmed.
+        let mut update = StateUpdate::new()
+            .with_channel(Some(&previous), Channel::Signed(signed_channel))
+            .with_contract(Some("accepted"), Contract::Confirmed(signed_contract))
+            .with_chain_monitor(self.chain_monitor.lock().unwrap().clone());
+
+        if let Some(closed_contract) = closed_contract {
+            update = update.with_contract(Some("confirmed"), closed_contract);
+        }
+
+        self.write_batch(update, "RenewConfirm")?;
+
+        Ok(msg)
+    }
+
+    fn on_renew_finalize(
+        &self,
+        renew_finalize: &RenewFinalize,
+        peer_id: &PublicKey,
+    ) -> Result<(), Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, &renew_finalize.channel_id, Signed, Some(*peer_id))?;
+        let previous = signed_channel.get_state_name();
+
+        let (tx_type, prev_tx_id, closed_contract) = match signed_channel
+            .roll_back_state
+            .as_ref()
+            .expect("to have a rollback state")
+        {
+            SignedChannelState::Established {
+                own_buffer_adaptor_signature,
+                buffer_transaction,
+                signed_contract_id,
+                ..
+            } => {
+                let contract =
+                    get_contract_in_state!(self, signed_contract_id, Confirmed, None::<PublicKey>)?;
+                let own_collateral = if contract.accepted_contract.offered_contract.is_offer_party {
+                    contract
+                        .accepted_contract
+                        .offered_contract
+                        .offer_params
+                        .collateral
+                } else {
+                    contract.accepted_contract.accept_params.collateral
+                };
+                let pnl = (contract.accepted_contract.offered_contract.total_collateral as i64)
+                    - (own_collateral as i64);
+                let closed_contract = Contract::Closed(ClosedContract {
+                    attestations: None,
+                    signed_cet: None,
+                    contract_id: *signed_contract_id,
+                    temporary_contract_id: contract.accepted_contract.offered_contract.id,
+                    metadata: contract.accepted_contract.offered_contract.metadata.clone(),
+                    counter_party_id: signed_channel.counter_party,
+                    pnl,
+                    announcements: None,
+                });
+                (
+                    TxType::Revoked {
+                        update_idx: signed_channel.update_idx,
+                        own_adaptor_signature: *own_buffer_adaptor_signature,
+                        is_offer: false,
+                        revoked_tx_type: RevokedTxType::Buffer,
+                    },
+                    buffer_transaction.txid(),
+                    Some(closed_contract),
+                )
+            }
+            SignedChannelState::Settled {
+                settle_tx,
+                own_settle_adaptor_signature,
+                ..
+            } => (
+                TxType::Revoked {
+                    update_idx: signed_channel.update_idx,
+                    own_adaptor_signature: *own_settle_adaptor_signature,
+                    is_offer: false,
+                    revoked_tx_type: RevokedTxType::Settle,
+                },
+                settle_tx.txid(),
+                None,
+            ),
+            s => {
+                return Err(Error::InvalidState(format!(
+                    "Expected rollback state of Established or Settled but was {:?}",
+                    s
+                )))
+            }
+        };
+
+        crate::channel_updater::renew_channel_on_finalize(&mut signed_channel, renew_finalize)?;
+
+        self.watch_revoked_tx(&signed_channel, prev_tx_id, tx_type)?;
+
+        let buffer_tx =
+            get_signed_channel_state!(signed_channel, Established, ref buffer_transaction)?;
+
+        self.chain_monitor.lock().unwrap().add_tx(
+            buffer_tx.txid(),
+            ChannelInfo {
+                channel_id: signed_channel.channel_id,
+                tx_type: TxType::Current,
+            },
+        );
+
+        self.quiescence
+            .lock()
+            .unwrap()
+            .remove(&signed_channel.channel_id);
+        let mut update = StateUpdate::new()
+            .with_channel(Some(&previous), Channel::Signed(signed_channel))
+            .with_chain_monitor(self.chain_monitor.lock().unwrap().clone());
+
+        if let Some(closed_contract) = closed_contract {
+            update = update.with_contract(Some("confirmed"), closed_contract);
+        }
+
+        self.write_batch(update, "RenewFinalize")?;
+
+        Ok(())
+    }
+
+    /// Returns the contracts remaining in the channel after the update of its
+    /// contracts in progress, as well as the contract added by it, if any.
+    fn get_contract_update_contracts(
+        &self,
+        signed_channel: &SignedChannel,
+    ) -> Result<(Vec<SignedContract>, Option<OfferedContract>), Error> {
+        let added_contract_id = match signed_channel.state {
+            SignedChannelState::ContractUpdateOffered {
+                added_contract_id, ..
+            }
+            | SignedChannelState::ContractUpdateAccepted {
+                added_contract_id, ..
+            }
+            | SignedChannelState::ContractUpdateConfirmed {
+                added_contract_id, ..
+            } => added_contract_id,
+            _ => {
+                return Err(Error::InvalidState(format!(
+                    "Expected a contract update state but found {:?}",
+                    signed_channel.state
+                )))
+            }
+        };
+
+        let contracts = signed_channel
+            .get_contract_ids()
+            .iter()
+            .map(|id| get_contract_in_state!(self, id, Confirmed, None::<PublicKey>))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let added_contract = match added_contract_id {
+            Some(id) => Some(get_contract_in_state!(
+                self,
+                &id,
+                Offered,
+                None::<PublicKey>
+            )?),
+            None => None,
+        };
+
+        Ok((contracts, added_contract))
+    }
+
+    /// Returns the information required to watch the channel state revoked by
+    /// the update of its contracts in progress, as well as the contract settled
+    /// by the update, if any, in closed state. Must be called before the
+    /// update index of the channel is decremented.
+    fn get_contract_update_revoked_state(
+        &self,
+        signed_channel: &SignedChannel,
+    ) -> Result<(TxType, Txid, Option<Contract>), Error> {
+        let (settled_contract_id, own_balance) = match signed_channel.state {
+            SignedChannelState::ContractUpdateAccepted {
+                settled_contract_id,
+                own_balance,
+                ..
+            }
+            | SignedChannelState::ContractUpdateConfirmed {
+                settled_contract_id,
+                own_balance,
+                ..
+            } => (settled_contract_id, own_balance),
+            _ => {
+                return Err(Error::InvalidState(format!(
+                    "Expected an accepted contract update state but found {:?}",
+                    signed_channel.state
+                )))
+            }
+        };
+
+        match signed_channel
+            .roll_back_state
+            .as_ref()
+            .expect("to have a rollback state")
+        {
+            SignedChannelState::ContractsEstablished {
+                own_buffer_adaptor_signature,
+                buffer_transaction,
+                own_balance: prev_own_balance,
+                is_offer,
+                ..
+            } => {
+                let closed_contract = match settled_contract_id {
+                    Some(contract_id) => {
+                        let contract = get_contract_in_state!(
+                            self,
+                            &contract_id,
+                            Confirmed,
+                            None::<PublicKey>
+                        )?;
+                        let own_collateral =
+                            if contract.accepted_contract.offered_contract.is_offer_party {
+                                contract
+                                    .accepted_contract
+                                    .offered_contract
+                                    .offer_params
+                                    .collateral
+                            } else {
+                                contract.accepted_contract.accept_params.collateral
+                            };
+                        let own_payout = own_balance - prev_own_balance;
+                        Some(Contract::Closed(ClosedContract {
+                            attestations: None,
+                            signed_cet: None,
+                            contract_id,
+                            temporary_contract_id: contract.accepted_contract.offered_contract.id,
+                            metadata: contract.accepted_contract.offered_contract.metadata.clone(),
+                            counter_party_id: signed_channel.counter_party,
+                            pnl: (own_collateral as i64) - (own_payout as i64),
+                            announcements: None,
+                        }))
+                    }
+                    None => None,
+                };
+                Ok((
+                    TxType::Revoked {
+                        update_idx: signed_channel.update_idx,
+                        own_adaptor_signature: *own_buffer_adaptor_signature,
+                        is_offer: *is_offer,
+                        revoked_tx_type: RevokedTxType::MultiBuffer,
+                    },
+                    buffer_transaction.txid(),
+                    closed_contract,
+                ))
+            }
+            SignedChannelState::Settled {
+                settle_tx,
+                own_settle_adaptor_signature,
+                ..
+            } => Ok((
+                TxType::Revoked {
+                    update_idx: signed_channel.update_idx,
+                    own_adaptor_signature: *own_settle_adaptor_signature,
+                    is_offer: false,
+                    revoked_tx_type: RevokedTxType::Settle,
+                },
+                settle_tx.txid(),
+                None,
+            )),
+            s => Err(Error::InvalidState(format!(
+                "Expected rollback state of ContractsEstablished or Settled but was {:?}",
+                s
+            ))),
+        }
+    }
+
+    /// Watches the revoked state of a channel whose contracts were updated as
+    /// well as its new buffer transaction, and persists the channel together
+    /// with the given contracts.
+    fn finalize_contract_update(
+        &self,
+        previous: &str,
+        signed_channel: SignedChannel,
+        prev_tx_id: Txid,
+        tx_type: TxType,
+        contracts: Vec<(&str, Contract)>,
+        trigger: &str,
+    ) -> Result<(), Error> {
+        self.watch_revoked_tx(&signed_channel, prev_tx_id, tx_type)?;
+
+        let buffer_tx = get_signed_channel_state!(
+            signed_channel,
+            ContractsEstablished,
+            ref buffer_transaction
+        )?;
+
+        self.chain_monitor.lock().unwrap().add_tx(
+            buffer_tx.txid(),
+            ChannelInfo {
+                channel_id: signed_channel.channel_id,
+                tx_type: TxType::Current,
+            },
+        );
+
+        self.quiescence
+            .lock()
+            .unwrap()
+            .remove(&signed_channel.channel_id);
+
+        let mut update = StateUpdate::new()
+            .with_channel(Some(previous), Channel::Signed(signed_channel))
+            .with_chain_monitor(self.chain_monitor.lock().unwrap().clone());
+
+        for (previous, contract) in contracts {
+            update = update.with_contract(Some(previous), contract);
+        }
+
+        self.write_batch(update, trigger)
+    }
+
+    fn on_add_contract_offer(
+        &self,
+        add_contract_offer: &AddContractOffer,
+        peer_id: &PublicKey,
+    ) -> Result<Option<Reject>, Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, &add_contract_offer.channel_id, Signed, Some(*peer_id))?;
+        let previous = signed_channel.get_state_name();
+
+        // Received a contract update offer when we already sent one, we reject it.
+        if let SignedChannelState::ContractUpdateOffered { is_offer, .. } = signed_channel.state {
+            if is_offer {
+                return Ok(Some(Reject {
+                    channel_id: add_contract_offer.channel_id,
+                }));
+            }
+        }
+
+        // The local party is the one allowed to initiate the next update.
+        if self.is_update_reserved(&add_contract_offer.channel_id) {
+            return Ok(Some(Reject {
+                channel_id: add_contract_offer.channel_id,
+            }));
+        }
+
+        let config = signed_channel.config;
+        let offered_contract = match crate::channel_updater::on_add_contract_offer(
+            &self.secp,
+            &mut signed_channel,
+            add_contract_offer,
+            &self.channel_policy,
+            config.peer_timeout,
+            &self.time,
+        ) {
+            Err(Error::ChannelPolicyViolation(reason)) => {
+                warn!(
+                    "Rejecting add contract offer for channel {}: {}",
+                    add_contract_offer.channel_id.to_lower_hex_string(),
+                    reason
+                );
+                return Ok(Some(Reject {
+                    channel_id: add_contract_offer.channel_id,
+                }));
+            }
+            res => res?,
+        };
+
+        self.write_batch(
+            StateUpdate::new()
+                .with_new_contract(offered_contract)
+                .with_channel(Some(&previous), Channel::Signed(signed_channel)),
+            "AddContractOffer",
+        )?;
+
+        Ok(None)
+    }
+
+    fn on_settle_contract_offer(
+        &self,
+        settle_contract_offer: &SettleContractOffer,
+        peer_id: &PublicKey,
+    ) -> Result<Option<Reject>, Error> {
+        let mut signed_channel = get_channel_in_state!(
+            self,
+            &settle_contract_offer.channel_id,
+            Signed,
+            Some(*peer_id)
+        )?;
+        let previous = signed_channel.get_state_name();
+
+        // Received a contract update offer when we already sent one, we reject it.
+        if let SignedChannelState::ContractUpdateOffered { is_offer, .. } = signed_channel.state {
+            if is_offer {
+                return Ok(Some(Reject {
+                    channel_id: settle_contract_offer.channel_id,
+                }));
+            }
+        }
+
+        // The local party is the one allowed to initiate the next update.
+        if self.is_update_reserved(&settle_contract_offer.channel_id) {
+            return Ok(Some(Reject {
+                channel_id: settle_contract_offer.channel_id,
+            }));
+        }
+
+        let contract = get_contract_in_state!(
+            self,
+            &settle_contract_offer.contract_id,
+            Confirmed,
+            Some(*peer_id)
+        )?;
+
+        let config = signed_channel.config;
+        match crate::channel_updater::on_settle_contract_offer(
+            &mut signed_channel,
+            settle_contract_offer,
+            &contract,
+            &self.channel_policy,
+            config.peer_timeout,
+            &self.time,
+        ) {
+            Err(Error::ChannelPolicyViolation(reason)) => {
+                warn!(
+                    "Rejecting settle contract offer for channel {}: {}",
+                    settle_contract_offer.channel_id.to_lower_hex_string(),
+                    reason
+                );
+                return Ok(Some(Reject {
+                    channel_id: settle_contract_offer.channel_id,
+                }));
+            }
+            res => res?,
+        };
+
+        self.upsert_channel(
+            Some(&previous),
+            Channel::Signed(signed_channel),
+            "SettleContractOffer",
+        )?;
+
+        Ok(None)
+    }
+
+    fn on_contract_update_accept(
+        &self,
+        contract_update_accept: &ContractUpdateAccept,
+        peer_id: &PublicKey,
+    ) -> Result<ContractUpdateConfirm, Error> {
+        let mut signed_channel = get_channel_in_state!(
+            self,
+            &contract_update_accept.channel_id,
+            Signed,
+            Some(*peer_id)
+        )?;
+        let previous = signed_channel.get_state_name();
+
+        let (contracts, added_contract) = self.get_contract_update_contracts(&signed_channel)?;
+        let added_contract_id = added_contract.as_ref().map(|c| c.id);
+
+        let config = signed_channel.config;
+        let (signed_contracts, msg) =
+            crate::channel_updater::verify_contract_update_accept_and_confirm(
+                &self.secp,
+                contract_update_accept,
+                &mut signed_channel,
+                &contracts,
+                added_contract.as_ref(),
+                config.cet_nsequence,
+                config.peer_timeout,
+                &self.signer_provider,
+                &self.time,
+                &CancellationToken::new(),
+            )?;
+
+        // Directly confirmed as we're in a channel the fund tx is already confirmed.
+        let mut update =
+            StateUpdate::new().with_channel(Some(&previous), Channel::Signed(signed_channel));
+        for signed_contract in signed_contracts {
+            let previous = contract_update_previous_state(&signed_contract, added_contract_id);
+            update = update.with_contract(Some(previous), Contract::Confirmed(signed_contract));
+        }
+
+        self.write_batch(update, "ContractUpdateAccept")?;
+
+        Ok(msg)
+    }
+
+    fn on_contract_update_confirm(
+        &self,
+        contract_update_confirm: &ContractUpdateConfirm,
+        peer_id: &PublicKey,
+    ) -> Result<ContractUpdateFinalize, Error> {
+        let mut signed_channel = get_channel_in_state!(
+            self,
+            &contract_update_confirm.channel_id,
+            Signed,
+            Some(*peer_id)
+        )?;
+        let previous = signed_channel.get_state_name();
+
+        let (contracts, added_contract) = self.get_contract_update_contracts(&signed_channel)?;
+        let added_contract_id = added_contract.as_ref().map(|c| c.id);
+        let (tx_type, prev_tx_id, closed_contract) =
+            self.get_contract_update_revoked_state(&signed_channel)?;
+
+        let config = signed_channel.config;
+        let (signed_contracts, msg) =
+            crate::channel_updater::verify_contract_update_confirm_and_finalize(
+                &self.secp,
+                &mut signed_channel,
+                &contracts,
+                added_contract.as_ref(),
+                contract_update_confirm,
+                config.cet_nsequence,
+                &self.signer_provider,
+                &CancellationToken::new(),
+            )?;
+
+        // Directly confirmed as we're in a channel the fund tx is already confirmed.
+        let updated_contracts = signed_contracts
+            .into_iter()
+            .map(|c| {
+                (
+                    contract_update_previous_state(&c, added_contract_id),
+                    Contract::Confirmed(c),
+                )
+            })
+            .chain(closed_contract.map(|c| ("confirmed", c)))
+            .collect();
+
+        self.finalize_contract_update(
+            &previous,
+            signed_channel,
+            prev_tx_id,
+            tx_type,
+            updated_contracts,
+            "ContractUpdateConfirm",
+        )?;
+
+        Ok(msg)
+    }
+
+    fn on_contract_update_finalize(
+        &self,
+        contract_update_finalize: &ContractUpdateFinalize,
+        peer_id: &PublicKey,
+    ) -> Result<(), Error> {
+        let mut signed_channel = get_channel_in_state!(
+            self,
+            &contract_update_finalize.channel_id,
+            Signed,
+            Some(*peer_id)
+        )?;
+        let previous = signed_channel.get_state_name();
+
+        let (tx_type, prev_tx_id, closed_contract) =
+            self.get_contract_update_revoked_state(&signed_channel)?;
+
+        crate::channel_updater::contract_update_on_finalize(
+            &mut signed_channel,
+            contract_update_finalize,
+        )?;
+
+        self.finalize_contract_update(
+            &previous,
+            signed_channel,
+            prev_tx_id,
+            tx_type,
+            closed_contract
+                .map(|c| ("confirmed", c))
+                .into_iter()
+                .collect(),
+            "ContractUpdateFinalize",
+        )
+    }
+
+    fn on_collaborative_close_offer(
+        &self,
+        close_offer: &CollaborativeCloseOffer,
+        peer_id: &PublicKey,
+    ) -> Result<(), Error> {
+        let mut signed_channel =
+            get_channel_in_state!(self, &close_offer.channel_id, Signed, Some(*peer_id))?;
+        let previous = signed_channel.get_state_name();
+
+        if self.is_update_reserved(&close_offer.channel_id) {
+            return Err(Error::InvalidState(
+                "Received collaborative close offer while being the initiator of the next channel update."
+                    .to_string(),
+            ));
+        }
+
+        let config = signed_channel.config;
+        crate::channel_updater::on_collaborative_close_offer(
+            &mut signed_channel,
+            close_offer,
+            config.peer_timeout,
+            &self.time,
+        )?;
+
+        self.upsert_channel(
+            Some(&previous),
+            Channel::Signed(signed_channel),
+            "CollaborativeCloseOffer",
+        )?;
+
+        Ok(())
+    }
+
+    fn on_stop(&self, stop: &Stop, peer_id: &PublicKey) -> Result<Option<DlcMessage>, Error> {
+        let signed_channel = get_channel_in_state!(self, &stop.channel_id, Signed, Some(*peer_id))?;
+
+        let mut quiescence = self.quiescence.lock().unwrap();
+        match quiescence.get(&stop.channel_id) {
+            None => {
+                if !stop.initiator {
+                    return Err(Error::InvalidParameters(
+                        "Received stop acknowledgement without having requested it.".to_string(),
+                    ));
+                }
+                // An update is already in flight, we refuse to stop the channel.
+                if !signed_channel.is_in_stable_state() {
+                    return Ok(Some(DlcMessage::Resume(Resume {
+                        channel_id: stop.channel_id,
+                    })));
+                }
+                quiescence.insert(
+                    stop.channel_id,
+                    Quiescence::Quiescent {
+                        is_initiator: false,
+                    },
+                );
+                Ok(Some(DlcMessage::Stop(Stop {
+                    channel_id: stop.channel_id,
+                    initiator: false,
+                })))
+            }
+            Some(Quiescence::StopSent) => {
+                // If both parties requested to stop the channel at the same time,
+                // the one with the lowest funding public key becomes the initiator.
+                let is_initiator = !stop.initiator
+                    || signed_channel.own_params.fund_pubkey
+                        < signed_channel.counter_params.fund_pubkey;
+                quiescence.insert(stop.channel_id, Quiescence::Quiescent { is_initiator });
+                Ok(None)
+            }
+            Some(Quiescence::Quiescent { .. }) => Err(Error::InvalidState(
+                "Received stop message for an already quiescent channel.".to_string(),
+            )),
+        }
+    }
+
+    fn on_resume(&self, resume: &Resume, peer_id: &PublicKey) -> Result<(), Error> {
+        let signed_channel =
+            get_channel_in_state!(self, &resume.channel_id, Signed, Some(*peer_id))?;
+
+        if !signed_channel.is_in_stable_state() {
+            return Err(Error::InvalidState(
+                "Cannot resume channel while an update is in progress.".to_string(),
+            ));
+        }
+
+        self.quiescence.lock().unwrap().remove(&resume.channel_id);
+
+        Ok(())
+    }
+
+    /// Returns an error if the counter party reserved the next update of the
+    /// channel through the quiescence protocol.
+    fn check_can_initiate_update(&self, channel_id: &ChannelId) -> Result<(), Error> {
+        match self.quiescence.lock().unwrap().get(channel_id) {
+            None | Some(Quiescence::Quiescent { is_initiator: true }) => Ok(()),
+            Some(_) => Err(Error::InvalidState(
+                "Channel is stopped and the local party is not the initiator of the next update."
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Returns whether the local party requested to initiate the next update
+    /// of the channel, in which case updates offered by the counter party
+    /// should be refused.
+    fn is_update_reserved(&self, channel_id: &ChannelId) -> bool {
+        matches!(
+            self.quiescence.lock().unwrap().get(channel_id),
+            Some(Quiescence::StopSent) | Some(Quiescence::Quiescent { is_initiator: true })
+        )
+    }
+
+    fn on_reject(&self, reject: &Reject, counter_party: &PublicKey) -> Result<(), Error> {
+        let channel = self.store.get_channel(&reject.channel_id)?;
+
+        if let Some(channel) = channel {
+            if channel.get_counter_party_id() != *counter_party {
+                return Err(Error::InvalidParameters(format!(
+                    "Peer {:02x?} is not involved with {} {:02x?}.",
+                    counter_party,
+                    stringify!(Channel),
+                    channel.get_id()
+                )));
+            }
+            match channel {
+                Channel::Offered(offered_channel) => {
+                    self.on_channel_offer_rejected(offered_channel, "Reject")?;
+                }
+                Channel::Signed(mut signed_channel) => {
+                    let previous = signed_channel.get_state_name();
+                    let contract = match signed_channel.state {
+                        SignedChannelState::RenewOffered {
+                            offered_contract_id,
+                            ..
+                        } => {
+                            let offered_contract = get_contract_in_state!(
+                                self,
+                                &offered_contract_id,
+                                Offered,
+                                None::<PublicKey>
+                            )?;
+                            Some(Contract::Rejected(offered_contract))
+                        }
+                        SignedChannelState::ContractUpdateOffered {
+                            added_contract_id: Some(added_contract_id),
+                            ..
+                        } => {
+                            let offered_contract = get_contract_in_state!(
+                                self,
+                                &added_contract_id,
+                                Offered,
+                                None::<PublicKey>
+                            )?;
+                            Some(Contract::Rejected(offered_contract))
+                        }
+                        _ => None,
+                    };
+
+                    crate::channel_updater::on_reject(&mut signed_channel)?;
+                    self.quiescence
+                        .lock()
+                        .unwrap()
+                        .remove(&signed_channel.channel_id);
+
+                    let mut update = StateUpdate::new()
+                        .with_channel(Some(&previous), Channel::Signed(signed_channel));
+                    if let Some(contract) = contract {
+                        update = update.with_contract(Some("offered"), contract);
+                    }
+                    self.write_batch(update, "Reject")?;
+                }
+                channel => {
+                    return Err(Error::InvalidState(format!(
+                        "Not in a state adequate to receive a reject message. {:?}",
+                        channel
+                    )))
+                }
+            }
+        } else if let Some(Contract::Offered(offered_contract)) =
+            self.store.get_contract(&reject.channel_id)?
+        {
+            self.on_contract_offer_rejected(offered_contract, counter_party, "Reject")?;
+        } else {
+            warn!(
+                "Couldn't find rejected dlc channel or contract with id: {}",
+                reject.channel_id.to_lower_hex_string()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn on_reject_offer(
+        &self,
+        reject: &RejectOffer,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let offered_contract = get_contract_in_state!(
+            self,
+            &reject.temporary_contract_id,
+            Offered,
+            Some(*counter_party)
+        )?;
+        info!(
+            "Offer {} rejected: {}",
+            reject.temporary_contract_id.to_lower_hex_string(),
+            reject.reason
+        );
+        self.on_contract_offer_rejected(offered_contract, counter_party, "RejectOffer")
+    }
+
+    fn on_reject_channel_offer(
+        &self,
+        reject: &RejectChannelOffer,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let offered_channel = get_channel_in_state!(
+            self,
+            &reject.temporary_channel_id,
+            Offered,
+            Some(*counter_party)
+        )?;
+        info!(
+            "Channel offer {} rejected: {}",
+            reject.temporary_channel_id.to_lower_hex_string(),
+            reject.reason
+        );
+        self.on_channel_offer_rejected(offered_channel, "RejectChannelOffer")
+    }
+
+    fn on_contract_offer_rejected(
+        &self,
+        offered_contract: OfferedContract,
+        counter_party: &PublicKey,
+        trigger: &str,
+    ) -> Result<(), Error> {
+        if offered_contract.counter_party != *counter_party || !offered_contract.is_offer_party {
+            return Err(Error::InvalidParameters(format!(
+                "Peer {:02x?} cannot reject contract {:02x?}.",
+                counter_party, offered_contract.id
+            )));
+        }
+        release_party_utxos(&self.wallet, &offered_contract.offer_params)?;
+        self.update_contract("offered", &Contract::Rejected(offered_contract), trigger)
+    }
+
+    fn on_channel_offer_rejected(
+        &self,
+        offered_channel: OfferedChannel,
+        trigger: &str,
+    ) -> Result<(), Error> {
+        if !offered_channel.is_offer_party {
+            return Err(Error::InvalidState(
+                "Cannot process the rejection of a channel offered by the peer.".to_string(),
+            ));
+        }
+        let offered_contract = get_contract_in_state!(
+            self,
+            &offered_channel.offered_contract_id,
+            Offered,
+            None as Option<PublicKey>
+        )?;
+        release_party_utxos(&self.wallet, &offered_contract.offer_params)?;
+
+        // remove rejected channel, since nothing has been confirmed on chain yet.
+        self.write_batch(
+            StateUpdate::new()
+                .with_channel(Some("offered"), Channel::Cancelled(offered_channel))
+                .with_contract(Some("offered"), Contract::Rejected(offered_contract)),
+            trigger,
+        )
+    }
+
+    fn channel_checks(&self, attestations: &AttestationCache) -> Result<(), Error> {
+        enter_span!("channel_checks");
+        let established_closing_channels = self
+            .store
+            .get_signed_channels(Some(SignedChannelStateType::Closing))?;
+
+        for channel in established_closing_channels {
+            let (_guard, channel) =
+                match lock_signed_channel_in_state!(self, channel.channel_id, Closing) {
+                    Some(locked) => locked,
+                    None => continue,
+                };
+            if let Err(e) = self.try_finalize_closing_established_channel(channel) {
+                error!("Error trying to close established channel: {}", e);
+            }
+        }
+
+        let contracts_closing_channels = self
+            .store
+            .get_signed_channels(Some(SignedChannelStateType::ContractsClosing))?;
+
+        for channel in contracts_closing_channels {
+            let (_guard, channel) =
+                match lock_signed_channel_in_state!(self, channel.channel_id, ContractsClosing) {
+                    Some(locked) => locked,
+                    None => continue,
+                };
+            if let Err(e) = self.try_finalize_closing_contracts_channel(channel, attestations) {
+                error!("Error trying to close channel contracts: {}", e);
+            }
+        }
+
+        if let Err(e) = self.claim_matured_outputs() {
+            error!("Error claiming matured channel outputs: {}", e);
+        }
+
+        for channel in self.get_unconfirmed_zero_conf_channels()? {
+            if let Err(e) = self.check_zero_conf_channel_funding(channel) {
+                error!("Error checking zero-conf channel funding: {}", e);
+            }
+        }
+
+        self.check_for_watched_tx()
+    }
+
+    fn check_for_timed_out_channels(&self) -> Result<(), Error> {
+        check_for_timed_out_channels!(self, RenewOffered);
+        check_for_timed_out_channels!(self, RenewAccepted);
+        check_for_timed_out_channels!(self, RenewConfirmed);
+        check_for_timed_out_channels!(self, SettledOffered);
+        check_for_timed_out_channels!(self, SettledAccepted);
+        check_for_timed_out_channels!(self, SettledConfirmed);
+        check_for_timed_out_channels!(self, ContractUpdateOffered);
+        check_for_timed_out_channels!(self, ContractUpdateAccepted);
+        check_for_timed_out_channels!(self, ContractUpdateConfirmed);
+
+        Ok(())
+    }
+
+    /// Returns the data required to punish the broadcast of the revoked
+    /// transaction with the given id, sending the punished funds to the given
+    /// script.
+    #[allow(clippy::too_many_arguments)]
+    fn get_justice_data(
+        &self,
+        signed_channel: &SignedChannel,
+        update_idx: u64,
+        own_adaptor_signature: EcdsaAdaptorSignature,
+        is_offer: bool,
+        revoked_tx_type: RevokedTxType,
+        counter_per_update_secret: &SecretKey,
+        revoked_txid: Txid,
+        destination_script: ScriptBuf,
+    ) -> Result<JusticeData, Error> {
+        let per_update_seed_sk = self
+            .signer_provider
+            .get_secret_key_for_pubkey(&signed_channel.own_per_update_seed)?;
+
+        let per_update_secret = SecretKey::from_slice(&build_commitment_secret(
+            per_update_seed_sk.as_ref(),
+            update_idx,
+        ))
+        .expect("a valid secret key.");
+
+        let own_per_update_point = PublicKey::from_secret_key(&self.secp, &per_update_secret);
+
+        let base_own_sk = self
+            .signer_provider
+            .get_secret_key_for_pubkey(&signed_channel.own_points.own_basepoint)?;
+
+        let own_revocation_base_secret = self
+            .signer_provider
+            .get_secret_key_for_pubkey(&signed_channel.own_points.revocation_basepoint)?;
+
+        Ok(JusticeData {
+            channel_id: signed_channel.channel_id,
+            revoked_txid,
+            revoked_tx_type,
+            is_offer,
+            own_points: signed_channel.own_points.clone(),
+            counter_points: signed_channel.counter_points.clone(),
+            own_per_update_point,
+            counter_per_update_point: PublicKey::from_secret_key(
+                &self.secp,
+                counter_per_update_secret,
+            ),
+            own_adaptor_signature,
+            own_fund_pk_is_lower: signed_channel.own_params.fund_pubkey
+                < signed_channel.counter_params.fund_pubkey,
+            own_sk: derive_private_key(&self.secp, &own_per_update_point, &base_own_sk),
+            counter_revoke_sk: derive_private_revocation_key(
+                &self.secp,
+                counter_per_update_secret,
+                &own_revocation_base_secret,
+            ),
+            destination_script,
+            csv_timelock: signed_channel.config.cet_nsequence,
+        })
+    }
+
+    /// Starts watching the given revoked transaction of the channel, and stores
+    /// the data required to punish its broadcast if the counter party already
+    /// revealed the corresponding per update secret.
+    fn watch_revoked_tx(
+        &self,
+        signed_channel: &SignedChannel,
+        txid: Txid,
+        tx_type: TxType,
+    ) -> Result<(), Error> {
+        if let TxType::Revoked {
+            update_idx,
+            own_adaptor_signature,
+            is_offer,
+            revoked_tx_type,
+        } = &tx_type
+        {
+            if let Some(secret) = signed_channel
+                .counter_party_commitment_secrets
+                .get_secret(*update_idx)
+            {
+                let counter_per_update_secret = SecretKey::from_slice(&secret)?;
+                self.store.upsert_punishment_data(&PunishmentData {
+                    channel_id: signed_channel.channel_id,
+                    update_idx: *update_idx,
+                    counter_per_update_secret,
+                    revoked_txid: txid,
+                })?;
+                if let Some(watchtower_client) = &self.watchtower_client {
+                    let justice_data = self.get_justice_data(
+                        signed_channel,
+                        *update_idx,
+                        *own_adaptor_signature,
+                        *is_offer,
+                        revoked_tx_type.clone(),
+                        &counter_per_update_secret,
+                        txid,
+                        self.wallet.get_new_address()?.script_pubkey(),
+                    )?;
+                    // The revocation already happened, so failing to reach the
+                    // watchtower should not fail the channel update.
+                    if let Err(e) =
+                        watchtower_client.on_revocation(&JusticeBlob::encrypt(&justice_data)?)
+                    {
+                        error!(
+                            "Could not send justice blob for channel {} to watchtower: {}",
+                            signed_channel.channel_id.to_lower_hex_string(),
+                            e
+                        );
+                    }
+                }
+            }
+        }
+        self.chain_monitor.lock().unwrap().add_tx(
+            txid,
+            ChannelInfo {
+                channel_id: signed_channel.channel_id,
+                tx_type,
+            },
+        );
+        Ok(())
+    }
+
+    fn check_for_watched_tx(&self) -> Result<(), Error> {
+        let cur_height = self.blockchain.get_blockchain_height()?;
+        self.check_for_reorg(cur_height)?;
+        let last_height = self.chain_monitor.lock().unwrap().last_height;
+
+        if cur_height < last_height {
+            return Err(Error::InvalidState(
+                "Current height is lower than last height.".to_string(),
+            ));
+        }
+
+        if self
+            .fast_sync_threshold
+            .map_or(false, |threshold| cur_height - last_height > threshold)
+        {
+            return self.fast_sync_watched_tx(last_height, cur_height);
+        }
+
+        let mut zero_conf_channels = self.get_unconfirmed_zero_conf_channels()?;
+
+        for height in last_height + 1..cur_height {
+            let block = self.blockchain.get_block_at_height(height)?;
+
+            if !zero_conf_channels.is_empty() {
+                zero_conf_channels =
+                    self.check_for_funding_double_spends(&block, height, zero_conf_channels)?;
+            }
+
+            let watch_res = self
+                .chain_monitor
+                .lock()
+                .unwrap()
+                .process_block(&block, height);
+
+            for (tx, channel_info) in watch_res {
+                let processed_tx =
+                    self.get_processed_tx(tx.txid(), &channel_info.channel_id, height)?;
+                self.process_watched_tx(tx, channel_info)?;
+                if let Some(processed_tx) = processed_tx {
+                    self.chain_monitor
+                        .lock()
+                        .unwrap()
+                        .add_processed_tx(processed_tx);
+                }
+            }
+
+            self.chain_monitor
+                .lock()
+                .unwrap()
+                .increment_height(&block.block_hash());
+            self.header_cache
+                .lock()
+                .unwrap()
+                .connect_block(&block.header, height);
+        }
+
+        if last_height + 1 < cur_height {
+            self.store
+                .persist_chain_monitor(&self.chain_monitor.lock().unwrap())?;
+            self.store
+                .persist_header_cache(&self.header_cache.lock().unwrap())?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether the last processed blocks are still part of the best
+    /// chain. If they were reorganized out, the effects of the watched
+    /// transactions they included are reverted, and the chain monitor is
+    /// rewound to the fork point so that the blocks of the new best chain are
+    /// processed, emitting again the events of the transactions they include.
+    /// Funding transactions and CETs are only considered confirmed after
+    /// [`NB_CONFIRMATIONS`] blocks, which is the depth of the header cache, so
+    /// their confirmation is not affected by the reorganizations that can be
+    /// handled. Deeper reorganizations return an error.
+    fn check_for_reorg(&self, cur_height: u64) -> Result<(), Error> {
+        let mut header_cache = self.header_cache.lock().unwrap();
+        let best_chain = header_cache
+            .get_best_chain()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut fork_point = None;
+        for header in &best_chain {
+            if header.height <= cur_height
+                && self
+                    .blockchain
+                    .get_block_at_height(header.height)?
+                    .block_hash()
+                    == header.block_hash
+            {
+                fork_point = Some(header);
+                break;
+            }
+        }
+
+        let fork_point = match (best_chain.first(), fork_point) {
+            (None, _) => return Ok(()),
+            (Some(tip), Some(fork_point)) if tip.block_hash == fork_point.block_hash => {
+                return Ok(())
+            }
+            (Some(_), Some(fork_point)) => fork_point,
+            (Some(tip), None) => {
+                return Err(Error::InvalidState(format!(
+                    "Block {} at height {} was reorganized out deeper than the {} cached headers.",
+                    tip.block_hash,
+                    tip.height,
+                    best_chain.len()
+                )))
+            }
+        };
+
+        warn!(
+            "Chain reorganization detected, rewinding to block {} at height {}",
+            fork_point.block_hash, fork_point.height
+        );
+        let mut chain_monitor = self.chain_monitor.lock().unwrap();
+        for processed_tx in chain_monitor.rewind(fork_point.height) {
+            self.revert_processed_tx(processed_tx)?;
+        }
+        header_cache.disconnect_to(&fork_point.block_hash);
+
+        self.store.persist_chain_monitor(&chain_monitor)?;
+        self.store.persist_header_cache(&header_cache)
+    }
+
+    /// Returns the state of the channel with the given id and of its
+    /// confirmed contracts, to be restored if the block including the given
+    /// transaction is reorganized out of the best chain.
+    fn get_processed_tx(
+        &self,
+        txid: Txid,
+        channel_id: &ChannelId,
+        height: u64,
+    ) -> Result<Option<ProcessedTx>, Error> {
+        let prev_channel = match self.store.get_channel(channel_id)? {
+            Some(Channel::Signed(signed_channel)) => signed_channel,
+            _ => return Ok(None),
+        };
+
+        let mut contract_ids = prev_channel.get_contract_ids();
+        contract_ids.extend(prev_channel.get_contract_id());
+        match &prev_channel.roll_back_state {
+            Some(SignedChannelState::Established {
+                signed_contract_id, ..
+            }) => contract_ids.push(*signed_contract_id),
+            Some(SignedChannelState::ContractsEstablished {
+                contract_ids: ids, ..
+            }) => contract_ids.extend(ids),
+            _ => {}
+        }
+
+        let mut prev_contracts = Vec::new();
+        for contract_id in contract_ids {
+            if let Some(Contract::Confirmed(contract)) = self.store.get_contract(&contract_id)? {
+                prev_contracts.push(contract);
+            }
+        }
+
+        Ok(Some(ProcessedTx {
+            txid,
+            height,
+            prev_channel,
+            prev_contracts,
+        }))
+    }
+
+    /// Restores the channel of the given processed transaction and its
+    /// confirmed contracts to their state before the transaction was
+    /// processed.
+    fn revert_processed_tx(&self, processed_tx: ProcessedTx) -> Result<(), Error> {
+        warn!(
+            "Transaction {} of channel {} was reorganized out, restoring the channel state",
+            processed_tx.txid,
+            processed_tx.prev_channel.channel_id.to_lower_hex_string()
+        );
+        // The restored states are recorded as new entries of the logs, so
+        // that the reorganization appears in the history of the objects.
+        let mut update =
+            StateUpdate::new().with_channel(None, Channel::Signed(processed_tx.prev_channel));
+        for contract in processed_tx.prev_contracts {
+            update = update.with_contract(None, Contract::Confirmed(contract));
+        }
+        self.write_batch(update, "chain reorganization")
+    }
+
+    /// Returns the zero-conf channels whose fund transaction is not yet
+    /// confirmed.
+    fn get_unconfirmed_zero_conf_channels(&self) -> Result<Vec<SignedChannel>, Error> {
+        Ok(self
+            .store
+            .get_signed_channels(None)?
+            .into_iter()
+            .filter(|c| {
+                c.unconfirmed_funding
+                    && !matches!(c.state, SignedChannelState::FundingDoubleSpent { .. })
+            })
+            .collect())
+    }
+
+    /// Clears the unconfirmed funding flag of the given zero-conf channel
+    /// once its fund transaction is confirmed.
+    fn check_zero_conf_channel_funding(
+        &self,
+        mut signed_channel: SignedChannel,
+    ) -> Result<(), Error> {
+        let confirmations = self
+            .blockchain
+            .get_transaction_confirmations(&signed_channel.fund_tx.txid())?;
+        if confirmations < NB_CONFIRMATIONS {
+            return Ok(());
+        }
+
+        info!(
+            "Fund transaction of zero-conf channel {} confirmed",
+            signed_channel.channel_id.to_lower_hex_string()
+        );
+        let previous = signed_channel.get_state_name();
+        signed_channel.unconfirmed_funding = false;
+        self.upsert_channel(
+            Some(&previous),
+            Channel::Signed(signed_channel),
+            "zero-conf funding confirmed",
+        )
+    }
+
+    /// Closes the given zero-conf channels whose fund transaction was double
+    /// spent by a transaction of the given block, returning the other ones.
+    fn check_for_funding_double_spends(
+        &self,
+        block: &Block,
+        height: u64,
+        channels: Vec<SignedChannel>,
+    ) -> Result<Vec<SignedChannel>, Error> {
+        let mut remaining = Vec::new();
+        for mut signed_channel in channels {
+            let fund_txid = signed_channel.fund_tx.txid();
+            let double_spend = block.txdata.iter().find(|tx| {
+                tx.txid() != fund_txid
+                    && tx.input.iter().any(|input| {
+                        signed_channel
+                            .fund_tx
+                            .input
+                            .iter()
+                            .any(|x| x.previous_output == input.previous_output)
+                    })
+            });
+            let double_spend_txid = match double_spend {
+                Some(tx) => tx.txid(),
+                None => {
+                    remaining.push(signed_channel);
+                    continue;
+                }
+            };
+
+            warn!(
+                "Fund transaction of zero-conf channel {} was double spent by transaction {}",
+                signed_channel.channel_id.to_lower_hex_string(),
+                double_spend_txid
             );
+            self.chain_monitor
+                .lock()
+                .unwrap()
+                .add_processed_tx(ProcessedTx {
+                    txid: double_spend_txid,
+                    height,
+                    prev_channel: signed_channel.clone(),
+                    prev_contracts: Vec::new(),
+                });
+            let previous = signed_channel.get_state_name();
+            signed_channel.state = SignedChannelState::FundingDoubleSpent { double_spend_txid };
+            self.upsert_channel(
+                Some(&previous),
+                Channel::Signed(signed_channel),
+                "zero-conf funding double spent",
+            )?;
+        }
+
+        Ok(remaining)
+    }
+
+    /// Processes the watched transactions included in the blocks between
+    /// `last_height` (excluded) and `cur_height` (excluded) by querying their
+    /// confirmations, and marks these blocks as processed.
+    fn fast_sync_watched_tx(&self, last_height: u64, cur_height: u64) -> Result<(), Error> {
+        let watched_txs = self.chain_monitor.lock().unwrap().get_watched_txs();
+        info!(
+            "Fast syncing {} watched transactions from height {} to {}",
+            watched_txs.len(),
+            last_height,
+            cur_height
+        );
+
+        for (txid, channel_info) in watched_txs {
+            let confirmations = self.blockchain.get_transaction_confirmations(&txid)? as u64;
+            // Transactions in earlier blocks were already processed, and the ones
+            // in the current block will be when it is walked through.
+            if confirmations < 2 || confirmations > cur_height - last_height {
+                continue;
+            }
+            let tx = self.blockchain.get_transaction(&txid)?;
+            self.process_watched_tx(tx, channel_info)?;
+        }
+
+        self.chain_monitor
+            .lock()
+            .unwrap()
+            .fast_forward(cur_height - 1);
+        // The skipped blocks are not cached, so reorganizations of the blocks
+        // preceding them cannot be detected.
+        self.header_cache.lock().unwrap().clear();
+
+        Ok(())
+    }
+
+    fn process_watched_tx(&self, tx: Transaction, channel_info: ChannelInfo) -> Result<(), Error> {
+        let mut signed_channel = match get_channel_in_state!(
+            self,
+            &channel_info.channel_id,
+            Signed,
+            None as Option<PublicKey>
+        ) {
+            Ok(c) => c,
+            Err(e) => {
+                error!(
+                    "Could not retrieve channel {:?}: {}",
+                    channel_info.channel_id, e
+                );
+                return Ok(());
+            }
+        };
+        let previous = signed_channel.get_state_name();
+
+        if let TxType::Current = channel_info.tx_type {
+            self.track_claimable_output(&signed_channel, &tx);
+            self.store
+                .persist_chain_monitor(&self.chain_monitor.lock().unwrap())?;
+
+            // The contracts of the channel are closed using their CETs once
+            // their outcome is attested.
+            let established_contracts =
+                match (&signed_channel.state, &signed_channel.roll_back_state) {
+                    (
+                        SignedChannelState::ContractsEstablished {
+                            contract_ids,
+                            is_offer,
+                            keys_id,
+                            ..
+                        },
+                        _,
+                    )
+                    | (
+                        _,
+                        Some(SignedChannelState::ContractsEstablished {
+                            contract_ids,
+                            is_offer,
+                            keys_id,
+                            ..
+                        }),
+                    ) => Some((contract_ids.clone(), *is_offer, *keys_id)),
+                    _ => None,
+                };
+
+            if let Some((contract_ids, is_offer, keys_id)) = established_contracts {
+                signed_channel.state = SignedChannelState::ContractsClosing {
+                    buffer_transaction: tx,
+                    contract_ids,
+                    is_offer,
+                    keys_id,
+                };
+                signed_channel.roll_back_state = None;
+                self.upsert_channel(
+                    Some(&previous),
+                    Channel::Signed(signed_channel),
+                    "watched transaction confirmed",
+                )?;
+                return Ok(());
+            }
+
+            // The previous state is restored by `check_for_reorg` if the
+            // transaction gets reorganized out, though if the counter party
+            // has sent the tx to close the channel it is unlikely that it
+            // will not be part of a future block.
+            let contract = if let Some(contract_id) = signed_channel.get_contract_id() {
+                let contract_opt = self.store.get_contract(&contract_id)?;
+                if let Some(contract) = contract_opt {
+                    match contract {
+                        Contract::Confirmed(c) => Some(Contract::PreClosed(PreClosedContract {
+                            signed_contract: c,
+                            attestations: None,
+                            signed_cet: tx.clone(),
+                        })),
+                        _ => None,
+                    }
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            signed_channel.state = SignedChannelState::CounterClosed;
+            let mut update =
+                StateUpdate::new().with_channel(Some(&previous), Channel::Signed(signed_channel));
+            if let Some(contract) = contract {
+                update = update.with_contract(Some("confirmed"), contract);
+            }
+            self.write_batch(update, "watched transaction confirmed")?;
+            return Ok(());
+        } else if let TxType::Revoked {
+            update_idx,
+            own_adaptor_signature,
+            is_offer,
+            revoked_tx_type,
+        } = channel_info.tx_type
+        {
+            // The revoked transaction might already have been punished by a
+            // `ChainWatcher`.
+            if let SignedChannelState::ClosedPunished { .. } = signed_channel.state {
+                return Ok(());
+            }
+
+            let counter_per_update_secret = match self
+                .store
+                .get_punishment_data(&signed_channel.channel_id, update_idx)?
+            {
+                Some(punishment_data) => punishment_data.counter_per_update_secret,
+                // Channels revoked before punishment data was stored
+                // separately only have it in their commitment secrets.
+                None => {
+                    let secret = signed_channel
+                        .counter_party_commitment_secrets
+                        .get_secret(update_idx)
+                        .expect("to be able to retrieve the per update secret");
+                    SecretKey::from_slice(&secret)
+                        .expect("to be able to parse the counter per update secret.")
+                }
+            };
+
+            let dest_address = self.wallet.get_new_address()?;
+            let justice_data = self.get_justice_data(
+                &signed_channel,
+                update_idx,
+                own_adaptor_signature,
+                is_offer,
+                revoked_tx_type,
+                &counter_per_update_secret,
+                tx.txid(),
+                dest_address.script_pubkey(),
+            )?;
+
+            let fee_rate_per_vb = self.get_estimated_fee_rate(ConfirmationTarget::OnChainSweep)?;
+
+            let signed_tx = crate::chain_watcher::broadcast_justice_transaction(
+                &self.secp,
+                &self.blockchain,
+                &justice_data,
+                &tx,
+                dest_address.network,
+                fee_rate_per_vb,
+            )?;
+
+            signed_channel.state = SignedChannelState::ClosedPunished {
+                punishment_txid: signed_tx.txid(),
+            };
+
+            self.upsert_channel(
+                Some(&previous),
+                Channel::Signed(signed_channel),
+                "watched transaction confirmed",
+            )?;
+        } else if let TxType::CollaborativeClose = channel_info.tx_type {
+            let mut update = StateUpdate::new();
+            if let Some(SignedChannelState::Established {
+                signed_contract_id,
+                is_offer,
+                ..
+            }) = signed_channel.roll_back_state
+            {
+                let counter_payout = get_signed_channel_state!(
+                    signed_channel,
+                    CollaborativeCloseOffered,
+                    counter_payout
+                )?;
+                let contract = get_contract_in_state!(
+                    self,
+                    &signed_contract_id,
+                    Confirmed,
+                    None::<PublicKey>
+                )?;
+                let own_payout =
+                    contract.accepted_contract.offered_contract.total_collateral - counter_payout;
+                let own_collateral = if is_offer {
+                    contract
+                        .accepted_contract
+                        .offered_contract
+                        .offer_params
+                        .collateral
+                } else {
+                    contract.accepted_contract.accept_params.collateral
+                };
+                let pnl = (own_collateral as i64) - (own_payout as i64);
+
+                let closed_contract = ClosedContract {
+                    attestations: None,
+                    signed_cet: None,
+                    contract_id: signed_contract_id,
+                    temporary_contract_id: contract.accepted_contract.offered_contract.id,
+                    metadata: contract.accepted_contract.offered_contract.metadata.clone(),
+                    counter_party_id: signed_channel.counter_party,
+                    pnl,
+                    announcements: None,
+                };
+                update = update.with_contract(Some("confirmed"), Contract::Closed(closed_contract));
+            }
+            signed_channel.state = SignedChannelState::CollaborativelyClosed;
+            self.write_batch(
+                update.with_channel(Some(&previous), Channel::Signed(signed_channel)),
+                "watched transaction confirmed",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn force_close_channel_internal(&self, channel: SignedChannel) -> Result<(), Error> {
+        let previous = channel.get_state_name();
+        self.force_close_channel_from(&previous, channel)
+    }
+
+    /// Force closes the given channel, which was in the state named `previous`
+    /// before any of its updates in progress got rolled back.
+    fn force_close_channel_from(
+        &self,
+        previous: &str,
+        mut channel: SignedChannel,
+    ) -> Result<(), Error> {
+        match channel.state {
+            SignedChannelState::Established { .. } => {
+                self.initiate_unilateral_close_established_channel(previous, channel)
+            }
+            SignedChannelState::Settled { .. } => self.close_settled_channel(previous, channel),
+            SignedChannelState::ContractsEstablished { .. } => {
+                self.initiate_unilateral_close_contracts_established_channel(previous, channel)
+            }
+            // The per update secret of the previous state was already given
+            // to the counter party, so the channel is closed using the new one.
+            SignedChannelState::ContractUpdateConfirmed { .. } => {
+                crate::channel_updater::establish_confirmed_contract_update(&mut channel)?;
+                self.force_close_channel_from(previous, channel)
+            }
+            SignedChannelState::SettledOffered { .. }
+            | SignedChannelState::SettledReceived { .. }
+            | SignedChannelState::SettledAccepted { .. }
+            | SignedChannelState::SettledConfirmed { .. }
+            | SignedChannelState::RenewOffered { .. }
+            | SignedChannelState::RenewAccepted { .. }
+            | SignedChannelState::RenewConfirmed { .. }
+            | SignedChannelState::ContractUpdateOffered { .. }
+            | SignedChannelState::ContractUpdateAccepted { .. }
+            | SignedChannelState::CollaborativeCloseOffered { .. } => {
+                channel.state = channel
+                    .roll_back_state
+                    .take()
+                    .expect("to have a rollback state");
+                self.force_close_channel_from(previous, channel)
+            }
+            SignedChannelState::Closing { .. } | SignedChannelState::ContractsClosing { .. } => {
+                Err(Error::InvalidState(
+                    "Channel is already closing.".to_string(),
+                ))
+            }
+            SignedChannelState::Closed
+            | SignedChannelState::CounterClosed
+            | SignedChannelState::CollaborativelyClosed
+            | SignedChannelState::ClosedPunished { .. }
+            | SignedChannelState::FundingDoubleSpent { .. } => {
+                Err(Error::InvalidState("Channel already closed.".to_string()))
+            }
+        }
+    }
+
+    /// Initiate the unilateral closing of a channel that has been established.
+    fn initiate_unilateral_close_established_channel(
+        &self,
+        previous: &str,
+        mut signed_channel: SignedChannel,
+    ) -> Result<(), Error> {
+        let contract_id = signed_channel.get_contract_id().ok_or_else(|| {
+            Error::InvalidState(
+                "Expected to be in a state with an associated contract id but was not.".to_string(),
+            )
+        })?;
+
+        let confirmed_contract =
+            get_contract_in_state!(self, &contract_id, Confirmed, None as Option<PublicKey>)?;
+
+        let (contract_info, adaptor_info, attestations) = self
+            .get_closable_contract_info(&confirmed_contract, None)
+            .ok_or_else(|| {
+                Error::InvalidState("Could not get closable contract info".to_string())
+            })?;
+
+        crate::channel_updater::initiate_unilateral_close_established_channel(
+            &self.secp,
+            &mut signed_channel,
+            &confirmed_contract,
+            contract_info,
+            &attestations,
+            adaptor_info,
+            &self.signer_provider,
+        )?;
 
-            match res {
-                Ok(res) => res,
-                Err(e) => {
-                    let channel = crate::channel::FailedSign {
-                        channel_id: sign_channel.channel_id,
-                        error_message: format!("Error validating accept channel: {}", e),
-                        sign_message: sign_channel.clone(),
-                        counter_party: *peer_id,
-                    };
-                    self.store
-                        .upsert_channel(Channel::FailedSign(channel), None)?;
-                    return Err(e);
-                }
-            }
-        };
+        let buffer_transaction =
+            get_signed_channel_state!(signed_channel, Closing, ref buffer_transaction)?;
 
-        if let SignedChannelState::Established {
-            buffer_transaction, ..
-        } = &signed_channel.state
-        {
-            self.chain_monitor.add_tx(
-                buffer_transaction.txid(),
-                ChannelInfo {
-                    channel_id: signed_channel.channel_id,
-                    tx_type: TxType::Current,
-                },
-            );
-        } else {
-            unreachable!();
-        }
+        self.blockchain.send_transaction(buffer_transaction)?;
 
-        self.blockchain.send_transaction(&signed_fund_tx)?;
+        self.chain_monitor
+            .lock()
+            .unwrap()
+            .remove_tx(&buffer_transaction.txid());
 
-        self.store.upsert_channel(
+        self.upsert_channel(
+            Some(previous),
             Channel::Signed(signed_channel),
-            Some(Contract::Signed(signed_contract)),
+            "force_close_channel",
         )?;
-        self.store.persist_chain_monitor(&self.chain_monitor)?;
+
+        self.store
+            .persist_chain_monitor(&self.chain_monitor.lock().unwrap())?;
 
         Ok(())
     }
 
-    fn on_settle_offer(
-        &mut self,
-        settle_offer: &SettleOffer,
-        peer_id: &PublicKey,
-    ) -> Result<Option<Reject>, Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, &settle_offer.channel_id, Signed, Some(*peer_id))?;
+    /// Initiate the unilateral closing of a channel holding several
+    /// contracts. The contracts are closed once the buffer transaction is
+    /// confirmed and their outcome attested.
+    fn initiate_unilateral_close_contracts_established_channel(
+        &self,
+        previous: &str,
+        mut signed_channel: SignedChannel,
+    ) -> Result<(), Error> {
+        crate::channel_updater::initiate_unilateral_close_contracts_established_channel(
+            &self.secp,
+            &mut signed_channel,
+            &self.signer_provider,
+        )?;
 
-        if let SignedChannelState::SettledOffered { .. } = signed_channel.state {
-            return Ok(Some(Reject {
-                channel_id: settle_offer.channel_id,
-            }));
-        }
+        let buffer_transaction =
+            get_signed_channel_state!(signed_channel, ContractsClosing, ref buffer_transaction)?;
+
+        self.blockchain.send_transaction(buffer_transaction)?;
 
-        crate::channel_updater::on_settle_offer(&mut signed_channel, settle_offer)?;
+        self.chain_monitor
+            .lock()
+            .unwrap()
+            .remove_tx(&buffer_transaction.txid());
+        self.track_claimable_output(&signed_channel, buffer_transaction);
+
+        self.upsert_channel(
+            Some(previous),
+            Channel::Signed(signed_channel),
+            "force_close_channel",
+        )?;
 
         self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+            .persist_chain_monitor(&self.chain_monitor.lock().unwrap())?;
 
-        Ok(None)
+        Ok(())
     }
 
-    fn on_settle_accept(
-        &mut self,
-        settle_accept: &SettleAccept,
-        peer_id: &PublicKey,
-    ) -> Result<SettleConfirm, Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, &settle_accept.channel_id, Signed, Some(*peer_id))?;
-
-        let msg = crate::channel_updater::settle_channel_confirm(
+    /// Unilaterally close a channel that has been settled.
+    fn close_settled_channel(
+        &self,
+        previous: &str,
+        mut signed_channel: SignedChannel,
+    ) -> Result<(), Error> {
+        let settle_tx = crate::channel_updater::close_settled_channel(
             &self.secp,
             &mut signed_channel,
-            settle_accept,
-            CET_NSEQUENCE,
-            0,
-            PEER_TIMEOUT,
             &self.signer_provider,
-            &self.time,
+        )?;
+
+        self.blockchain.send_transaction(&settle_tx)?;
+        self.track_claimable_output(&signed_channel, &settle_tx);
+
+        self.upsert_channel(
+            Some(previous),
+            Channel::Signed(signed_channel),
+            "force_close_channel",
         )?;
 
         self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+            .persist_chain_monitor(&self.chain_monitor.lock().unwrap())?;
 
-        Ok(msg)
+        Ok(())
     }
 
-    fn on_settle_confirm(
-        &mut self,
-        settle_confirm: &SettleConfirm,
-        peer_id: &PublicKey,
-    ) -> Result<SettleFinalize, Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, &settle_confirm.channel_id, Signed, Some(*peer_id))?;
-        let own_payout = get_signed_channel_state!(signed_channel, SettledAccepted, own_payout)?;
-        let (prev_buffer_tx, own_buffer_adaptor_signature, is_offer, signed_contract_id) = get_signed_channel_rollback_state!(
+    /// Returns the parameters of the outputs paying the local party after a
+    /// relative time lock in the transactions of the given channel state,
+    /// as well as the own public key of the counter party.
+    fn get_claim_params(
+        &self,
+        signed_channel: &SignedChannel,
+        own_per_update_point: &PublicKey,
+        counter_per_update_point: &PublicKey,
+    ) -> (RevokeParams, bitcoin::PublicKey) {
+        let own_params = signed_channel.own_points.get_revokable_params(
+            &self.secp,
+            &signed_channel.counter_points.revocation_basepoint,
+            own_per_update_point,
+        );
+        let counter_params = signed_channel.counter_points.get_revokable_params(
+            &self.secp,
+            &signed_channel.own_points.revocation_basepoint,
+            counter_per_update_point,
+        );
+        (own_params, counter_params.own_pk)
+    }
+
+    /// Starts tracking the output of the given closing transaction of a
+    /// channel paying the local party after a relative time lock, if any, so
+    /// that it gets claimed once the time lock expires. Outputs of buffer
+    /// transactions locking a contract are spent by its CETs and punishment
+    /// transactions pay directly to the wallet, so neither are tracked.
+    fn track_claimable_output(&self, signed_channel: &SignedChannel, tx: &Transaction) {
+        let (own_params, counter_own_pk) = self.get_claim_params(
             signed_channel,
-            Established,
-            buffer_transaction,
-            own_buffer_adaptor_signature,
-            is_offer,
-            signed_contract_id
-        )?;
+            &signed_channel.own_per_update_point,
+            &signed_channel.counter_per_update_point,
+        );
+        let script_pubkey = dlc::channel::settle_descriptor(
+            &own_params,
+            &counter_own_pk,
+            signed_channel.config.cet_nsequence,
+        )
+        .script_pubkey();
 
-        let prev_buffer_txid = prev_buffer_tx.txid();
-        let own_buffer_adaptor_signature = *own_buffer_adaptor_signature;
-        let is_offer = *is_offer;
-        let signed_contract_id = *signed_contract_id;
+        if let Some((vout, output)) = tx
+            .output
+            .iter()
+            .enumerate()
+            .find(|(_, x)| x.script_pubkey == script_pubkey)
+        {
+            self.chain_monitor
+                .lock()
+                .unwrap()
+                .add_claimable_output(ClaimableOutput {
+                    channel_id: signed_channel.channel_id,
+                    txid: tx.txid(),
+                    vout: vout as u32,
+                    value: output.value,
+                    own_per_update_point: signed_channel.own_per_update_point,
+                    counter_per_update_point: signed_channel.counter_per_update_point,
+                    csv_timelock: signed_channel.config.cet_nsequence,
+                });
+        }
+    }
 
-        let msg = crate::channel_updater::settle_channel_finalize(
+    /// Claims to the wallet the tracked channel outputs whose relative time
+    /// lock expired.
+    fn claim_matured_outputs(&self) -> Result<(), Error> {
+        let claimable_outputs = self.chain_monitor.lock().unwrap().get_claimable_outputs();
+        for claimable_output in claimable_outputs {
+            let confirmations = self
+                .blockchain
+                .get_transaction_confirmations(&claimable_output.txid)?;
+            if confirmations < claimable_output.csv_timelock {
+                continue;
+            }
+            if let Err(e) = self.claim_output(&claimable_output) {
+                error!(
+                    "Error claiming output {} of channel {}: {}",
+                    claimable_output.outpoint(),
+                    claimable_output.channel_id.to_lower_hex_string(),
+                    e
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn claim_output(&self, claimable_output: &ClaimableOutput) -> Result<(), Error> {
+        let signed_channel = get_channel_in_state!(
+            self,
+            &claimable_output.channel_id,
+            Signed,
+            None as Option<PublicKey>
+        )?;
+        let (own_params, counter_own_pk) = self.get_claim_params(
+            &signed_channel,
+            &claimable_output.own_per_update_point,
+            &claimable_output.counter_per_update_point,
+        );
+        let base_own_sk = self
+            .signer_provider
+            .get_secret_key_for_pubkey(&signed_channel.own_points.own_basepoint)?;
+        let own_sk = derive_private_key(
             &self.secp,
-            &mut signed_channel,
-            settle_confirm,
-            &self.signer_provider,
+            &claimable_output.own_per_update_point,
+            &base_own_sk,
+        );
+
+        let dest_address = self.wallet.get_new_address()?;
+        let fee_rate_per_vb = self.get_estimated_fee_rate(ConfirmationTarget::OnChainSweep)?;
+        let claim_tx = dlc::channel::create_and_sign_claim_settle_transaction(
+            &self.secp,
+            &own_params,
+            &counter_own_pk,
+            &own_sk,
+            claimable_output.outpoint(),
+            claimable_output.value,
+            &dest_address,
+            claimable_output.csv_timelock,
+            fee_rate_per_vb,
         )?;
+        self.blockchain.send_transaction(&claim_tx)?;
 
-        self.chain_monitor.add_tx(
-            prev_buffer_txid,
-            ChannelInfo {
-                channel_id: signed_channel.channel_id,
-                tx_type: TxType::Revoked {
-                    update_idx: signed_channel.update_idx + 1,
-                    own_adaptor_signature: own_buffer_adaptor_signature,
-                    is_offer,
-                    revoked_tx_type: RevokedTxType::Buffer,
-                },
-            },
+        info!(
+            "Claimed output {} of channel {} with transaction {}",
+            claimable_output.outpoint(),
+            claimable_output.channel_id.to_lower_hex_string(),
+            claim_tx.txid()
         );
 
-        let contract =
-            get_contract_in_state!(self, &signed_contract_id, Confirmed, None::<PublicKey>)?;
+        let mut chain_monitor = self.chain_monitor.lock().unwrap();
+        chain_monitor.remove_claimable_output(&claimable_output.outpoint());
+        self.store.persist_chain_monitor(&chain_monitor)
+    }
+}
 
-        let own_collateral = if contract.accepted_contract.offered_contract.is_offer_party {
-            contract
-                .accepted_contract
-                .offered_contract
-                .offer_params
-                .collateral
-        } else {
-            contract.accepted_contract.accept_params.collateral
-        };
+#[cfg(feature = "async")]
+impl<W: Deref, SP: Deref, B: Deref, S: Deref, O: Deref, T: Deref, F: Deref, X: ContractSigner>
+    Manager<W, Arc<CachedContractSignerProvider<SP, X>>, B, S, O, T, F, X>
+where
+    W::Target: Wallet,
+    SP::Target: ContractSignerProvider<Signer = X>,
+    B::Target: Blockchain,
+    S::Target: Storage,
+    O::Target: Oracle + AsyncOracle,
+    T::Target: Time,
+    F::Target: FeeEstimator,
+{
+    /// Asynchronous version of [`Self::periodic_check`]. The attestations for
+    /// the matured events of confirmed contracts are first fetched
+    /// concurrently through the [`AsyncOracle`] implementation of the oracles,
+    /// after which the check is run without calling the oracles. The
+    /// blockchain and storage are still called synchronously.
+    pub async fn periodic_check_async(&self, check_channels: bool) -> Result<(), Error> {
+        let attestations = self.fetch_attestations_async().await?;
+        self.periodic_check_with_attestations(check_channels, &attestations)
+    }
 
-        let closed_contract = Contract::Closed(ClosedContract {
-            attestations: None,
-            signed_cet: None,
-            contract_id: signed_contract_id,
-            temporary_contract_id: contract.accepted_contract.offered_contract.id,
-            counter_party_id: signed_channel.counter_party,
-            pnl: (own_collateral as i64) - (own_payout as i64),
-        });
+    async fn fetch_attestations_async(&self) -> Result<AttestationCache, Error> {
+        let fetches = self
+            .get_matured_events()?
+            .into_iter()
+            .filter_map(|(pubkey, event_ids)| {
+                let oracle = self.oracles.get(&pubkey)?;
+                Some(async move {
+                    let ids: Vec<&str> = event_ids.iter().map(|x| x.as_str()).collect();
+                    let results = AsyncOracle::get_attestations(&**oracle, &ids).await;
+                    // Events that were not attested yet are expected to fail.
+                    event_ids
+                        .iter()
+                        .zip(results)
+                        .filter_map(|(event_id, attestation)| {
+                            Some(((pubkey, event_id.clone()), attestation.ok()?))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            });
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), Some(closed_contract))?;
-        self.store.persist_chain_monitor(&self.chain_monitor)?;
+        Ok(futures::future::join_all(fetches)
+            .await
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+}
 
-        Ok(msg)
+#[cfg(test)]
+mod test {
+    use dlc_messages::Message;
+    use lightning::chain::chaininterface::ConfirmationTarget;
+    use mocks::{
+        dlc_manager::{
+            channel::ChannelEvent,
+            contract::{ContractEvent, ContractEventType},
+            error::Error,
+            manager::Manager,
+            state_history::StateGraphFormat,
+            CachedContractSignerProvider, Oracle, SimpleSigner, Storage,
+        },
+        memory_storage_provider::MemoryStorage,
+        mock_blockchain::MockBlockchain,
+        mock_oracle_provider::MockOracle,
+        mock_time::MockTime,
+        mock_wallet::MockWallet,
+    };
+    use secp256k1_zkp::{PublicKey, XOnlyPublicKey};
+    use std::{collections::HashMap, rc::Rc, sync::Arc};
+
+    type TestManager = Manager<
+        Rc<MockWallet>,
+        Arc<CachedContractSignerProvider<Rc<MockWallet>, SimpleSigner>>,
+        Rc<MockBlockchain>,
+        Rc<MemoryStorage>,
+        Rc<MockOracle>,
+        Rc<MockTime>,
+        Rc<MockBlockchain>,
+        SimpleSigner,
+    >;
+
+    fn get_manager() -> TestManager {
+        get_manager_with_blockchain(Rc::new(MockBlockchain::new()))
     }
 
-    fn on_settle_finalize(
-        &mut self,
-        settle_finalize: &SettleFinalize,
-        peer_id: &PublicKey,
-    ) -> Result<(), Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, &settle_finalize.channel_id, Signed, Some(*peer_id))?;
-        let own_payout = get_signed_channel_state!(signed_channel, SettledConfirmed, own_payout)?;
-        let (buffer_tx, own_buffer_adaptor_signature, is_offer, signed_contract_id) = get_signed_channel_rollback_state!(
-            signed_channel,
-            Established,
-            buffer_transaction,
-            own_buffer_adaptor_signature,
-            is_offer,
-            signed_contract_id
-        )?;
+    fn get_manager_with_blockchain(blockchain: Rc<MockBlockchain>) -> TestManager {
+        let wallet = Rc::new(MockWallet::new(
+            &blockchain,
+            &(0..100).map(|x| x as u64 * 1000000).collect::<Vec<_>>(),
+        ));
+        get_manager_with_wallet(blockchain, wallet)
+    }
 
-        let own_buffer_adaptor_signature = *own_buffer_adaptor_signature;
-        let is_offer = *is_offer;
-        let buffer_txid = buffer_tx.txid();
-        let signed_contract_id = *signed_contract_id;
+    fn get_manager_with_wallet(
+        blockchain: Rc<MockBlockchain>,
+        wallet: Rc<MockWallet>,
+    ) -> TestManager {
+        let store = Rc::new(MemoryStorage::new());
+
+        let oracle_list = (0..5).map(|_| MockOracle::new()).collect::<Vec<_>>();
+        let oracles: HashMap<XOnlyPublicKey, _> = oracle_list
+            .into_iter()
+            .map(|x| (x.get_public_key(), Rc::new(x)))
+            .collect();
+        let time = Rc::new(MockTime {});
+
+        mocks::mock_time::set_time(0);
+
+        Manager::new(
+            wallet.clone(),
+            wallet,
+            blockchain.clone(),
+            store,
+            oracles,
+            time,
+            blockchain,
+        )
+        .unwrap()
+    }
 
-        crate::channel_updater::settle_channel_on_finalize(
-            &self.secp,
-            &mut signed_channel,
-            settle_finalize,
-        )?;
+    fn pubkey() -> PublicKey {
+        "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166"
+            .parse()
+            .unwrap()
+    }
 
-        self.chain_monitor.add_tx(
-            buffer_txid,
-            ChannelInfo {
-                channel_id: signed_channel.channel_id,
-                tx_type: TxType::Revoked {
-                    update_idx: signed_channel.update_idx + 1,
-                    own_adaptor_signature: own_buffer_adaptor_signature,
-                    is_offer,
-                    revoked_tx_type: RevokedTxType::Buffer,
-                },
-            },
+    #[test]
+    fn reject_offer_with_existing_contract_id() {
+        let offer_message = Message::Offer(
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap(),
         );
 
-        let contract =
-            get_contract_in_state!(self, &signed_contract_id, Confirmed, None::<PublicKey>)?;
+        let manager = get_manager();
 
-        let own_collateral = if contract.accepted_contract.offered_contract.is_offer_party {
-            contract
-                .accepted_contract
-                .offered_contract
-                .offer_params
-                .collateral
-        } else {
-            contract.accepted_contract.accept_params.collateral
-        };
+        manager
+            .on_dlc_message(&offer_message, pubkey())
+            .expect("To accept the first offer message");
 
-        let closed_contract = Contract::Closed(ClosedContract {
-            attestations: None,
-            signed_cet: None,
-            contract_id: signed_contract_id,
-            temporary_contract_id: contract.accepted_contract.offered_contract.id,
-            counter_party_id: signed_channel.counter_party,
-            pnl: (own_collateral as i64) - (own_payout as i64),
-        });
+        manager
+            .on_dlc_message(&offer_message, pubkey())
+            .expect_err("To reject the second offer message");
+    }
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), Some(closed_contract))?;
-        self.store.persist_chain_monitor(&self.chain_monitor)?;
+    #[test]
+    #[cfg(feature = "use-serde")]
+    fn exported_offer_can_be_imported() {
+        let offer_message = Message::Offer(
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap(),
+        );
 
-        Ok(())
+        let manager = get_manager();
+        manager
+            .on_dlc_message(&offer_message, pubkey())
+            .expect("To accept the offer message");
+        let offered_contracts = manager.get_store().get_contract_offers().unwrap();
+        let offered_contract = &offered_contracts[0];
+        let contract_id = offered_contract.id;
+        let exported = manager.export_contract(&contract_id).unwrap();
+        assert_eq!(
+            Some(
+                offered_contract.contract_info[0].oracle_announcements[0]
+                    .oracle_event
+                    .event_id
+                    .as_str()
+            ),
+            exported
+                .pointer("/offered/contractInfo/0/oracleAnnouncements/0/oracleEvent/eventId")
+                .and_then(|x| x.as_str())
+        );
+
+        let other_manager = get_manager();
+        assert_eq!(
+            contract_id,
+            other_manager
+                .import_contract_offer(exported.clone())
+                .unwrap()
+        );
+        assert_eq!(
+            exported,
+            other_manager.export_contract(&contract_id).unwrap()
+        );
+        assert!(matches!(
+            other_manager.import_contract_offer(exported),
+            Err(Error::AlreadyExists(_))
+        ));
     }
 
-    fn on_renew_offer(
-        &mut self,
-        renew_offer: &RenewOffer,
-        peer_id: &PublicKey,
-    ) -> Result<Option<Reject>, Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, &renew_offer.channel_id, Signed, Some(*peer_id))?;
+    #[test]
+    fn offer_can_be_accepted_with_external_signatures() {
+        use mocks::dlc_manager::contract::Contract;
 
-        // Received a renew offer when we already sent one, we reject it.
-        if let SignedChannelState::RenewOffered { is_offer, .. } = signed_channel.state {
-            if is_offer {
-                return Ok(Some(Reject {
-                    channel_id: renew_offer.channel_id,
-                }));
-            }
-        }
+        let offer_message = Message::Offer(
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap(),
+        );
 
-        let offered_contract = crate::channel_updater::on_renew_offer(
-            &mut signed_channel,
-            renew_offer,
-            PEER_TIMEOUT,
-            &self.time,
-        )?;
+        let manager = get_manager();
+        manager
+            .on_dlc_message(&offer_message, pubkey())
+            .expect("To accept the offer message");
+        let contract_id = manager.get_store().get_contract_offers().unwrap()[0].id;
+
+        let request = manager.prepare_accept_contract_offer(&contract_id).unwrap();
+        assert_eq!(contract_id, request.contract_id);
+        assert_eq!(1, manager.get_signing_requests().len());
+
+        let secp = secp256k1_zkp::Secp256k1::new();
+        let mut signatures = request
+            .sign(&secp, &mocks::mock_wallet::get_secret_key())
+            .unwrap();
+        let last = signatures.cet_adaptor_signatures.pop().unwrap();
+        manager
+            .submit_contract_signatures(&contract_id, &signatures)
+            .expect_err("To reject missing signatures");
+        signatures.cet_adaptor_signatures.push(last);
+
+        let (accepted_id, counter_party, msg) = manager
+            .submit_contract_signatures(&contract_id, &signatures)
+            .unwrap();
+        assert_eq!(pubkey(), counter_party);
+        assert!(matches!(msg, Message::Accept(_)));
+        assert!(matches!(
+            manager.get_store().get_contract(&accepted_id).unwrap(),
+            Some(Contract::Accepted(_))
+        ));
+        assert!(manager.get_signing_requests().is_empty());
+    }
 
-        self.store.create_contract(&offered_contract)?;
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+    #[test]
+    fn stale_signing_request_is_discarded() {
+        let offer_message = Message::Offer(
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap(),
+        );
 
-        Ok(None)
+        let manager = get_manager();
+        manager
+            .on_dlc_message(&offer_message, pubkey())
+            .expect("To accept the offer message");
+        let contract_id = manager.get_store().get_contract_offers().unwrap()[0].id;
+
+        let request = manager.prepare_accept_contract_offer(&contract_id).unwrap();
+        manager.reject_contract_offer(&contract_id, "").unwrap();
+
+        let secp = secp256k1_zkp::Secp256k1::new();
+        let signatures = request
+            .sign(&secp, &mocks::mock_wallet::get_secret_key())
+            .unwrap();
+        assert!(matches!(
+            manager.submit_contract_signatures(&contract_id, &signatures),
+            Err(Error::InvalidState(_))
+        ));
+        assert!(manager.get_signing_requests().is_empty());
     }
 
-    fn on_renew_accept(
-        &mut self,
-        renew_accept: &RenewAccept,
-        peer_id: &PublicKey,
-    ) -> Result<RenewConfirm, Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, &renew_accept.channel_id, Signed, Some(*peer_id))?;
-        let offered_contract_id = signed_channel.get_contract_id().ok_or_else(|| {
-            Error::InvalidState(
-                "Expected to be in a state with an associated contract id but was not.".to_string(),
-            )
-        })?;
+    #[test]
+    fn reject_self_dealing_offers() {
+        use mocks::dlc_manager::manager::SelfDealingConfig;
 
-        let offered_contract =
-            get_contract_in_state!(self, &offered_contract_id, Offered, Some(*peer_id))?;
+        let offer_message = Message::Offer(
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap(),
+        );
 
-        let (signed_contract, msg) = crate::channel_updater::verify_renew_accept_and_confirm(
-            &self.secp,
-            renew_accept,
-            &mut signed_channel,
-            &offered_contract,
-            CET_NSEQUENCE,
-            PEER_TIMEOUT,
-            &self.wallet,
-            &self.signer_provider,
-            &self.time,
-        )?;
+        let manager = get_manager();
+        manager
+            .on_dlc_message(&offer_message, pubkey())
+            .expect("To accept the offer message");
+        let oracle_public_key = manager.get_store().get_contract_offers().unwrap()[0].contract_info
+            [0]
+        .oracle_announcements[0]
+            .oracle_public_key;
 
-        // Directly confirmed as we're in a channel the fund tx is already confirmed.
-        self.store.upsert_channel(
-            Channel::Signed(signed_channel),
-            Some(Contract::Confirmed(signed_contract)),
-        )?;
+        let mut manager = get_manager();
+        manager.set_self_dealing_config(SelfDealingConfig {
+            own_oracle_keys: vec![oracle_public_key],
+            reject_own_funding_keys: false,
+        });
+        assert!(matches!(
+            manager.on_dlc_message(&offer_message, pubkey()),
+            Err(Error::InvalidParameters(_))
+        ));
 
-        Ok(msg)
+        // The mock wallet provides a secret key for any public key.
+        let mut manager = get_manager();
+        manager.set_self_dealing_config(SelfDealingConfig {
+            own_oracle_keys: Vec::new(),
+            reject_own_funding_keys: true,
+        });
+        assert!(matches!(
+            manager.on_dlc_message(&offer_message, pubkey()),
+            Err(Error::InvalidParameters(_))
+        ));
     }
 
-    fn on_renew_confirm(
-        &mut self,
-        renew_confirm: &RenewConfirm,
-        peer_id: &PublicKey,
-    ) -> Result<RenewFinalize, Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, &renew_confirm.channel_id, Signed, Some(*peer_id))?;
-        let contract_id = signed_channel.get_contract_id().ok_or_else(|| {
-            Error::InvalidState(
-                "Expected to be in a state with an associated contract id but was not.".to_string(),
-            )
-        })?;
+    #[test]
+    fn received_offer_state_transition_is_exported() {
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let contract_id = offer.temporary_contract_id;
 
-        let (tx_type, prev_tx_id, closed_contract) = match signed_channel
-            .roll_back_state
-            .as_ref()
-            .expect("to have a rollback state")
-        {
-            SignedChannelState::Established {
-                own_buffer_adaptor_signature,
-                buffer_transaction,
-                signed_contract_id,
-                ..
-            } => {
-                let contract =
-                    get_contract_in_state!(self, signed_contract_id, Confirmed, None::<PublicKey>)?;
-                let own_collateral = if contract.accepted_contract.offered_contract.is_offer_party {
-                    contract
-                        .accepted_contract
-                        .offered_contract
-                        .offer_params
-                        .collateral
-                } else {
-                    contract.accepted_contract.accept_params.collateral
-                };
-                let pnl = (contract.accepted_contract.offered_contract.total_collateral as i64)
-                    - (own_collateral as i64);
-                let closed_contract = Contract::Closed(ClosedContract {
-                    attestations: None,
-                    signed_cet: None,
-                    contract_id: *signed_contract_id,
-                    temporary_contract_id: contract.accepted_contract.offered_contract.id,
-                    counter_party_id: signed_channel.counter_party,
-                    pnl,
-                });
-                (
-                    TxType::Revoked {
-                        update_idx: signed_channel.update_idx,
-                        own_adaptor_signature: *own_buffer_adaptor_signature,
-                        is_offer: false,
-                        revoked_tx_type: RevokedTxType::Buffer,
-                    },
-                    buffer_transaction.txid(),
-                    Some(closed_contract),
-                )
-            }
-            SignedChannelState::Settled {
-                settle_tx,
-                own_settle_adaptor_signature,
-                ..
-            } => (
-                TxType::Revoked {
-                    update_idx: signed_channel.update_idx,
-                    own_adaptor_signature: *own_settle_adaptor_signature,
-                    is_offer: false,
-                    revoked_tx_type: RevokedTxType::Settle,
-                },
-                settle_tx.txid(),
-                None,
-            ),
-            s => {
-                return Err(Error::InvalidState(format!(
-                    "Expected rollback state Established or Revoked but found {:?}",
-                    s
-                )))
-            }
-        };
+        let manager = get_manager();
 
-        let accepted_contract =
-            get_contract_in_state!(self, &contract_id, Accepted, Some(*peer_id))?;
+        manager
+            .on_dlc_message(&Message::Offer(offer), pubkey())
+            .expect("To accept the offer message");
 
-        let (signed_contract, msg) = crate::channel_updater::verify_renew_confirm_and_finalize(
-            &self.secp,
-            &mut signed_channel,
-            &accepted_contract,
-            renew_confirm,
-            &self.wallet,
-            &self.signer_provider,
-        )?;
+        let json = manager
+            .export_contract_state_graph(&contract_id, StateGraphFormat::Json)
+            .unwrap();
 
-        self.chain_monitor.add_tx(
-            prev_tx_id,
-            ChannelInfo {
-                channel_id: signed_channel.channel_id,
-                tx_type,
-            },
-        );
+        assert!(json.contains(
+            "{\"from\":null,\"to\":\"OfferReceived\",\"timestamp\":0,\"trigger\":\"OfferDlc\"}"
+        ));
+    }
 
-        let buffer_tx =
-            get_signed_channel_state!(signed_channel, Established, ref buffer_transaction)?;
+    #[test]
+    fn received_offer_is_logged_in_contract_history() {
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let contract_id = offer.temporary_contract_id;
 
-        self.chain_monitor.add_tx(
-            buffer_tx.txid(),
-            ChannelInfo {
-                channel_id: signed_channel.channel_id,
-                tx_type: TxType::Current,
-            },
-        );
+        let manager = get_manager();
 
-        // Directly confirmed as we're in a channel the fund tx is already confirmed.
-        self.store.upsert_channel(
-            Channel::Signed(signed_channel),
-            Some(Contract::Confirmed(signed_contract)),
-        )?;
+        manager
+            .on_dlc_message(&Message::Offer(offer), pubkey())
+            .expect("To accept the offer message");
+
+        assert_eq!(
+            vec![ContractEvent {
+                event_type: ContractEventType::OfferReceived,
+                timestamp: 0,
+                trigger: "OfferDlc".to_string(),
+            }],
+            manager
+                .get_store()
+                .get_contract_history(&contract_id)
+                .unwrap()
+        );
+    }
 
-        self.store.persist_chain_monitor(&self.chain_monitor)?;
+    #[test]
+    fn received_channel_offer_is_logged_in_channel_history() {
+        let offer_channel: dlc_messages::channel::OfferChannel =
+            serde_json::from_str(include_str!("../test_inputs/offer_channel.json")).unwrap();
+        let channel_id = offer_channel.temporary_channel_id;
+        let contract_id = offer_channel.temporary_contract_id;
 
-        if let Some(closed_contract) = closed_contract {
-            self.store.update_contract(&closed_contract)?;
-        }
+        let manager = get_manager();
 
-        Ok(msg)
+        manager
+            .on_dlc_message(&Message::OfferChannel(offer_channel), pubkey())
+            .expect("To accept the offer channel message");
+
+        assert_eq!(
+            vec![ChannelEvent {
+                state: "offered".to_string(),
+                timestamp: 0,
+                trigger: "OfferChannel".to_string(),
+            }],
+            manager
+                .get_store()
+                .get_channel_history(&channel_id)
+                .unwrap()
+        );
+        assert_eq!(
+            1,
+            manager
+                .get_store()
+                .get_contract_history(&contract_id)
+                .unwrap()
+                .len()
+        );
     }
 
-    fn on_renew_finalize(
-        &mut self,
-        renew_finalize: &RenewFinalize,
-        peer_id: &PublicKey,
-    ) -> Result<(), Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, &renew_finalize.channel_id, Signed, Some(*peer_id))?;
+    #[test]
+    fn replayed_offer_is_rejected() {
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
 
-        let (tx_type, prev_tx_id, closed_contract) = match signed_channel
-            .roll_back_state
-            .as_ref()
-            .expect("to have a rollback state")
-        {
-            SignedChannelState::Established {
-                own_buffer_adaptor_signature,
-                buffer_transaction,
-                signed_contract_id,
-                ..
-            } => {
-                let contract =
-                    get_contract_in_state!(self, signed_contract_id, Confirmed, None::<PublicKey>)?;
-                let own_collateral = if contract.accepted_contract.offered_contract.is_offer_party {
-                    contract
-                        .accepted_contract
-                        .offered_contract
-                        .offer_params
-                        .collateral
-                } else {
-                    contract.accepted_contract.accept_params.collateral
-                };
-                let pnl = (contract.accepted_contract.offered_contract.total_collateral as i64)
-                    - (own_collateral as i64);
-                let closed_contract = Contract::Closed(ClosedContract {
-                    attestations: None,
-                    signed_cet: None,
-                    contract_id: *signed_contract_id,
-                    temporary_contract_id: contract.accepted_contract.offered_contract.id,
-                    counter_party_id: signed_channel.counter_party,
-                    pnl,
-                });
-                (
-                    TxType::Revoked {
-                        update_idx: signed_channel.update_idx,
-                        own_adaptor_signature: *own_buffer_adaptor_signature,
-                        is_offer: false,
-                        revoked_tx_type: RevokedTxType::Buffer,
-                    },
-                    buffer_transaction.txid(),
-                    Some(closed_contract),
-                )
-            }
-            SignedChannelState::Settled {
-                settle_tx,
-                own_settle_adaptor_signature,
-                ..
-            } => (
-                TxType::Revoked {
-                    update_idx: signed_channel.update_idx,
-                    own_adaptor_signature: *own_settle_adaptor_signature,
-                    is_offer: false,
-                    revoked_tx_type: RevokedTxType::Settle,
-                },
-                settle_tx.txid(),
-                None,
-            ),
-            s => {
-                return Err(Error::InvalidState(format!(
-                    "Expected rollback state of Established or Settled but was {:?}",
-                    s
-                )))
-            }
-        };
+        let manager = get_manager();
 
-        crate::channel_updater::renew_channel_on_finalize(&mut signed_channel, renew_finalize)?;
+        manager
+            .on_dlc_message(&Message::Offer(offer.clone()), pubkey())
+            .expect("To accept the offer message");
 
-        self.chain_monitor.add_tx(
-            prev_tx_id,
-            ChannelInfo {
-                channel_id: signed_channel.channel_id,
-                tx_type,
-            },
+        let contract_id = offer.temporary_contract_id;
+        assert!(matches!(
+            manager.on_dlc_message(&Message::Offer(offer), pubkey()),
+            Err(Error::AlreadyExists(_))
+        ));
+        assert_eq!(1, manager.get_store().get_contract_offers().unwrap().len());
+        assert_eq!(
+            1,
+            manager
+                .get_store()
+                .get_contract_history(&contract_id)
+                .unwrap()
+                .len()
         );
+    }
 
-        let buffer_tx =
-            get_signed_channel_state!(signed_channel, Established, ref buffer_transaction)?;
+    #[test]
+    fn resent_offer_replaces_previous_one() {
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let mut resent_offer = offer.clone();
+        resent_offer.temporary_contract_id = [42u8; 32];
 
-        self.chain_monitor.add_tx(
-            buffer_tx.txid(),
-            ChannelInfo {
-                channel_id: signed_channel.channel_id,
-                tx_type: TxType::Current,
-            },
-        );
+        let manager = get_manager();
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
-        self.store.persist_chain_monitor(&self.chain_monitor)?;
+        manager
+            .on_dlc_message(&Message::Offer(offer.clone()), pubkey())
+            .expect("To accept the offer message");
+        manager
+            .on_dlc_message(&Message::Offer(resent_offer), pubkey())
+            .expect("To accept the resent offer message");
+
+        let offers = manager.get_store().get_contract_offers().unwrap();
+        assert_eq!(1, offers.len());
+        assert_eq!([42u8; 32], offers[0].id);
+        assert!(manager
+            .get_store()
+            .get_contract(&offer.temporary_contract_id)
+            .unwrap()
+            .is_none());
+    }
 
-        if let Some(closed_contract) = closed_contract {
-            self.store.update_contract(&closed_contract)?;
-        }
+    #[test]
+    fn reject_offer_bound_to_other_recipient() {
+        let mut offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        offer.recipient_node_id = Some(pubkey());
 
-        Ok(())
+        let mut manager = get_manager();
+        manager.set_node_id(
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+                .parse()
+                .unwrap(),
+        );
+
+        manager
+            .on_dlc_message(&Message::Offer(offer), pubkey())
+            .expect_err("To reject an offer bound to another node");
     }
 
-    fn on_collaborative_close_offer(
-        &mut self,
-        close_offer: &CollaborativeCloseOffer,
-        peer_id: &PublicKey,
-    ) -> Result<(), Error> {
-        let mut signed_channel =
-            get_channel_in_state!(self, &close_offer.channel_id, Signed, Some(*peer_id))?;
+    #[test]
+    fn reject_stale_offers() {
+        let mut offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let mut offer_channel: dlc_messages::channel::OfferChannel =
+            serde_json::from_str(include_str!("../test_inputs/offer_channel.json")).unwrap();
 
-        crate::channel_updater::on_collaborative_close_offer(
-            &mut signed_channel,
-            close_offer,
-            PEER_TIMEOUT,
-            &self.time,
-        )?;
+        let mut manager = get_manager();
+        manager.set_max_message_age(600);
+        mocks::mock_time::set_time(1000);
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        manager
+            .on_dlc_message(&Message::Offer(offer.clone()), pubkey())
+            .expect_err("To reject an offer without timestamp");
 
-        Ok(())
+        offer.timestamp = Some(100);
+        offer_channel.timestamp = Some(100);
+        manager
+            .on_dlc_message(&Message::Offer(offer.clone()), pubkey())
+            .expect_err("To reject a stale offer");
+        manager
+            .on_dlc_message(&Message::OfferChannel(offer_channel.clone()), pubkey())
+            .expect_err("To reject a stale channel offer");
+
+        offer.timestamp = Some(900);
+        offer_channel.timestamp = Some(900);
+        manager
+            .on_dlc_message(&Message::Offer(offer), pubkey())
+            .expect("To accept a recent offer");
+        manager
+            .on_dlc_message(&Message::OfferChannel(offer_channel), pubkey())
+            .expect("To accept a recent channel offer");
     }
 
-    fn on_reject(&self, reject: &Reject, counter_party: &PublicKey) -> Result<(), Error> {
-        let channel = self.store.get_channel(&reject.channel_id)?;
+    #[test]
+    fn expired_offers_are_moved_to_expired_state() {
+        let mut offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        offer.expiry = Some(500);
 
-        if let Some(channel) = channel {
-            if channel.get_counter_party_id() != *counter_party {
-                return Err(Error::InvalidParameters(format!(
-                    "Peer {:02x?} is not involved with {} {:02x?}.",
-                    counter_party,
-                    stringify!(Channel),
-                    channel.get_id()
-                )));
-            }
-            match channel {
-                Channel::Offered(offered_channel) => {
-                    let offered_contract = get_contract_in_state!(
-                        self,
-                        &offered_channel.offered_contract_id,
-                        Offered,
-                        None as Option<PublicKey>
-                    )?;
-                    let utxos = offered_contract
-                        .funding_inputs
-                        .iter()
-                        .map(|funding_input| {
-                            let txid = Transaction::consensus_decode(
-                                &mut funding_input.prev_tx.as_slice(),
-                            )
-                            .expect("Transaction Decode Error")
-                            .txid();
-                            let vout = funding_input.prev_tx_vout;
-                            OutPoint { txid, vout }
-                        })
-                        .collect::<Vec<_>>();
+        let manager = get_manager();
 
-                    self.wallet.unreserve_utxos(&utxos)?;
+        manager
+            .on_dlc_message(&Message::Offer(offer.clone()), pubkey())
+            .expect("To accept an offer that has not expired");
 
-                    // remove rejected channel, since nothing has been confirmed on chain yet.
-                    self.store.upsert_channel(
-                        Channel::Cancelled(offered_channel),
-                        Some(Contract::Rejected(offered_contract)),
-                    )?;
-                }
-                Channel::Signed(mut signed_channel) => {
-                    let contract = match signed_channel.state {
-                        SignedChannelState::RenewOffered {
-                            offered_contract_id,
-                            ..
-                        } => {
-                            let offered_contract = get_contract_in_state!(
-                                self,
-                                &offered_contract_id,
-                                Offered,
-                                None::<PublicKey>
-                            )?;
-                            Some(Contract::Rejected(offered_contract))
-                        }
-                        _ => None,
-                    };
+        manager.periodic_check(false).unwrap();
+        assert!(manager.get_store().get_expired_offers().unwrap().is_empty());
 
-                    crate::channel_updater::on_reject(&mut signed_channel)?;
+        mocks::mock_time::set_time(1000);
+        manager
+            .accept_contract_offer(&offer.temporary_contract_id)
+            .expect_err("To refuse accepting an expired offer");
 
-                    self.store
-                        .upsert_channel(Channel::Signed(signed_channel), contract)?;
-                }
-                channel => {
-                    return Err(Error::InvalidState(format!(
-                        "Not in a state adequate to receive a reject message. {:?}",
-                        channel
-                    )))
-                }
-            }
-        } else {
-            warn!(
-                "Couldn't find rejected dlc channel with id: {}",
-                reject.channel_id.to_lower_hex_string()
-            );
-        }
+        manager.periodic_check(false).unwrap();
+        assert!(manager
+            .get_store()
+            .get_contract_offers()
+            .unwrap()
+            .is_empty());
+        let expired = manager.get_store().get_expired_offers().unwrap();
+        assert_eq!(1, expired.len());
+        assert_eq!(offer.temporary_contract_id, expired[0].id);
 
-        Ok(())
+        offer.temporary_contract_id = [42u8; 32];
+        manager
+            .on_dlc_message(&Message::Offer(offer), pubkey())
+            .expect_err("To reject an expired offer");
     }
 
-    fn channel_checks(&mut self) -> Result<(), Error> {
-        let established_closing_channels = self
-            .store
-            .get_signed_channels(Some(SignedChannelStateType::Closing))?;
+    #[test]
+    fn rejected_contract_offer_releases_reserved_utxos() {
+        use mocks::dlc_manager::contract::{offered_contract::OfferedContract, Contract};
+        use mocks::dlc_manager::Wallet;
+
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let mut offered_contract =
+            OfferedContract::try_from_offer_dlc(&offer, pubkey(), [0u8; 32]).unwrap();
+        offered_contract.is_offer_party = true;
+        let outpoints = offered_contract
+            .offer_params
+            .inputs
+            .iter()
+            .map(|x| x.outpoint)
+            .collect::<Vec<_>>();
+        assert!(!outpoints.is_empty());
 
-        for channel in established_closing_channels {
-            if let Err(e) = self.try_finalize_closing_established_channel(channel) {
-                error!("Error trying to close established channel: {}", e);
-            }
-        }
+        let blockchain = Rc::new(MockBlockchain::new());
+        let wallet = Rc::new(MockWallet::new(&blockchain, &[]));
+        let manager = get_manager_with_wallet(blockchain, wallet.clone());
+        wallet.reserve_utxos(&outpoints).unwrap();
+        manager
+            .get_store()
+            .create_contract(&offered_contract)
+            .unwrap();
 
-        if let Err(e) = self.check_for_timed_out_channels() {
-            error!("Error checking timed out channels {}", e);
-        }
-        self.check_for_watched_tx()
+        let reject = dlc_messages::channel::Reject {
+            channel_id: offered_contract.id,
+        };
+        manager
+            .on_dlc_message(&Message::Reject(reject), pubkey())
+            .expect("To process the reject message");
+
+        assert!(wallet.get_reserved_utxos().is_empty());
+        assert!(matches!(
+            manager
+                .get_store()
+                .get_contract(&offered_contract.id)
+                .unwrap(),
+            Some(Contract::Rejected(_))
+        ));
     }
 
-    fn check_for_timed_out_channels(&mut self) -> Result<(), Error> {
-        check_for_timed_out_channels!(self, RenewOffered);
-        check_for_timed_out_channels!(self, RenewAccepted);
-        check_for_timed_out_channels!(self, RenewConfirmed);
-        check_for_timed_out_channels!(self, SettledOffered);
-        check_for_timed_out_channels!(self, SettledAccepted);
-        check_for_timed_out_channels!(self, SettledConfirmed);
+    #[test]
+    fn contract_offer_can_be_rejected() {
+        use mocks::dlc_manager::contract::{offered_contract::OfferedContract, Contract};
+        use mocks::dlc_manager::Wallet;
 
-        Ok(())
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+
+        let manager = get_manager();
+        manager
+            .on_dlc_message(&Message::Offer(offer.clone()), pubkey())
+            .expect("To accept the offer message");
+        let (reject, counter_party) = manager
+            .reject_contract_offer(&offer.temporary_contract_id, "Collateral too high")
+            .expect("To reject the offer");
+        assert_eq!(pubkey(), counter_party);
+        assert_eq!(offer.temporary_contract_id, reject.temporary_contract_id);
+        assert!(matches!(
+            manager
+                .get_store()
+                .get_contract(&offer.temporary_contract_id)
+                .unwrap(),
+            Some(Contract::Rejected(_))
+        ));
+        manager
+            .reject_contract_offer(&offer.temporary_contract_id, "")
+            .expect_err("To only reject offers once");
+
+        let mut offered_contract =
+            OfferedContract::try_from_offer_dlc(&offer, pubkey(), [0u8; 32]).unwrap();
+        offered_contract.is_offer_party = true;
+        let outpoints = offered_contract
+            .offer_params
+            .inputs
+            .iter()
+            .map(|x| x.outpoint)
+            .collect::<Vec<_>>();
+        let blockchain = Rc::new(MockBlockchain::new());
+        let wallet = Rc::new(MockWallet::new(&blockchain, &[]));
+        let offerer = get_manager_with_wallet(blockchain, wallet.clone());
+        wallet.reserve_utxos(&outpoints).unwrap();
+        offerer
+            .get_store()
+            .create_contract(&offered_contract)
+            .unwrap();
+
+        offerer
+            .on_dlc_message(&Message::RejectOffer(reject), pubkey())
+            .expect("To process the reject offer message");
+
+        assert!(wallet.get_reserved_utxos().is_empty());
+        assert!(matches!(
+            offerer
+                .get_store()
+                .get_contract(&offered_contract.id)
+                .unwrap(),
+            Some(Contract::Rejected(_))
+        ));
     }
 
-    fn check_for_watched_tx(&mut self) -> Result<(), Error> {
-        let cur_height = self.blockchain.get_blockchain_height()?;
-        let last_height = self.chain_monitor.last_height;
+    #[test]
+    fn contracts_can_be_queried_by_label() {
+        use mocks::dlc_manager::contract::ContractMetadata;
 
-        if cur_height < last_height {
-            return Err(Error::InvalidState(
-                "Current height is lower than last height.".to_string(),
-            ));
-        }
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let contract_id = offer.temporary_contract_id;
 
-        //todo(tibo): check and deal with reorgs.
+        let manager = get_manager();
+        manager
+            .on_dlc_message(&Message::Offer(offer), pubkey())
+            .expect("To accept the offer message");
+        let metadata = ContractMetadata {
+            labels: vec!["hedge-q3".to_string()],
+            data: vec![1, 2, 3],
+        };
+        manager
+            .set_contract_metadata(&contract_id, Some(metadata.clone()))
+            .unwrap();
+        manager
+            .reject_contract_offer(&contract_id, "")
+            .expect("To reject the offer");
+
+        let labelled = manager
+            .get_store()
+            .get_contracts_by_label("hedge-q3")
+            .unwrap();
+        assert_eq!(1, labelled.len());
+        assert_eq!(contract_id, labelled[0].get_temporary_id());
+        assert_eq!(Some(&metadata), labelled[0].get_metadata());
+        assert!(manager
+            .get_store()
+            .get_contracts_by_label("hedge-q4")
+            .unwrap()
+            .is_empty());
+        manager
+            .set_contract_metadata(&[1u8; 32], None)
+            .expect_err("To fail on unknown contracts");
+    }
 
-        for height in last_height + 1..cur_height {
-            let block = self.blockchain.get_block_at_height(height)?;
+    #[test]
+    fn reject_offers_below_min_relay_fee_rate() {
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let offer_channel: dlc_messages::channel::OfferChannel =
+            serde_json::from_str(include_str!("../test_inputs/offer_channel.json")).unwrap();
 
-            let watch_res = self.chain_monitor.process_block(&block, height);
+        let mut manager = get_manager();
+        manager.set_min_relay_fee_rate(offer.fee_rate_per_vb + 1);
 
-            for (tx, channel_info) in watch_res {
-                let mut signed_channel = match get_channel_in_state!(
-                    self,
-                    &channel_info.channel_id,
-                    Signed,
-                    None as Option<PublicKey>
-                ) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        error!(
-                            "Could not retrieve channel {:?}: {}",
-                            channel_info.channel_id, e
-                        );
-                        continue;
-                    }
-                };
+        manager
+            .on_dlc_message(&Message::Offer(offer), pubkey())
+            .expect_err("To reject an offer below the min relay fee rate");
+        manager
+            .on_dlc_message(&Message::OfferChannel(offer_channel), pubkey())
+            .expect_err("To reject a channel offer below the min relay fee rate");
+    }
 
-                if let TxType::Current = channel_info.tx_type {
-                    // TODO(tibo): should only considered closed after some confirmations.
-                    // Ideally should save previous state, and maybe restore in
-                    // case of reorg, though if the counter party has sent the
-                    // tx to close the channel it is unlikely that the tx will
-                    // not be part of a future block.
-                    let contract = if let Some(contract_id) = signed_channel.get_contract_id() {
-                        let contract_opt = self.store.get_contract(&contract_id)?;
-                        if let Some(contract) = contract_opt {
-                            match contract {
-                                Contract::Confirmed(c) => {
-                                    Some(Contract::PreClosed(PreClosedContract {
-                                        signed_contract: c,
-                                        attestations: None,
-                                        signed_cet: tx.clone(),
-                                    }))
-                                }
-                                _ => None,
-                            }
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    };
+    #[test]
+    fn replacement_fee_rate_respects_relay_policy() {
+        let mut manager = get_manager();
+        manager.set_min_relay_fee_rate(5);
+        manager.set_incremental_relay_fee_rate(2);
 
-                    signed_channel.state = SignedChannelState::CounterClosed;
-                    self.store
-                        .upsert_channel(Channel::Signed(signed_channel), contract)?;
-                    continue;
-                } else if let TxType::Revoked {
-                    update_idx,
-                    own_adaptor_signature,
-                    is_offer,
-                    revoked_tx_type,
-                } = channel_info.tx_type
-                {
-                    let secret = signed_channel
-                        .counter_party_commitment_secrets
-                        .get_secret(update_idx)
-                        .expect("to be able to retrieve the per update secret");
-                    let counter_per_update_secret = SecretKey::from_slice(&secret)
-                        .expect("to be able to parse the counter per update secret.");
+        assert_eq!(5, manager.get_replacement_fee_rate(1).unwrap());
+        assert_eq!(12, manager.get_replacement_fee_rate(10).unwrap());
+    }
 
-                    let per_update_seed_pk = signed_channel.own_per_update_seed;
+    #[test]
+    fn estimated_fee_rate_respects_min_relay_fee_rate() {
+        let blockchain = Rc::new(MockBlockchain::new());
+        blockchain.set_est_fee_rate(2500);
+        let mut manager = get_manager_with_blockchain(blockchain);
+        manager.set_min_relay_fee_rate(1);
+
+        assert_eq!(
+            10,
+            manager
+                .get_estimated_fee_rate(ConfirmationTarget::NonAnchorChannelFee)
+                .unwrap()
+        );
 
-                    let per_update_seed_sk = self
-                        .signer_provider
-                        .get_secret_key_for_pubkey(&per_update_seed_pk)?;
+        manager.set_min_relay_fee_rate(20);
 
-                    let per_update_secret = SecretKey::from_slice(&build_commitment_secret(
-                        per_update_seed_sk.as_ref(),
-                        update_idx,
-                    ))
-                    .expect("a valid secret key.");
+        assert_eq!(
+            20,
+            manager
+                .get_estimated_fee_rate(ConfirmationTarget::NonAnchorChannelFee)
+                .unwrap()
+        );
+    }
 
-                    let per_update_point =
-                        PublicKey::from_secret_key(&self.secp, &per_update_secret);
+    #[test]
+    fn estimate_operation_on_unknown_channel_fails() {
+        let manager = get_manager();
 
-                    let own_revocation_params = signed_channel.own_points.get_revokable_params(
-                        &self.secp,
-                        &signed_channel.counter_points.revocation_basepoint,
-                        &per_update_point,
-                    );
+        manager
+            .estimate_operation(&mocks::dlc_manager::estimate::Operation::SettleChannel(
+                &[1u8; 32],
+            ))
+            .expect_err("To fail estimating an operation on an unknown channel");
+    }
 
-                    let counter_per_update_point =
-                        PublicKey::from_secret_key(&self.secp, &counter_per_update_secret);
+    #[test]
+    fn ping_is_answered_with_pong() {
+        let manager = get_manager();
+        let ping = manager.ping();
 
-                    let base_own_sk = self
-                        .signer_provider
-                        .get_secret_key_for_pubkey(&signed_channel.own_points.own_basepoint)?;
+        let reply = manager
+            .on_dlc_message(&Message::Ping(ping.clone()), pubkey())
+            .expect("To handle the ping message");
 
-                    let own_sk = derive_private_key(&self.secp, &per_update_point, &base_own_sk);
+        match reply {
+            Some(Message::Pong(pong)) => assert_eq!(ping.nonce, pong.nonce),
+            _ => panic!("Expected a pong message"),
+        }
+    }
 
-                    let counter_revocation_params =
-                        signed_channel.counter_points.get_revokable_params(
-                            &self.secp,
-                            &signed_channel.own_points.revocation_basepoint,
-                            &counter_per_update_point,
-                        );
+    #[test]
+    fn unreachable_peer_with_maturing_contract_is_reported() {
+        use mocks::dlc_manager::contract::{
+            ser::Serializable, signed_contract::SignedContract, Contract,
+        };
+        use mocks::dlc_manager::manager::{ManagerEvent, PeerLivenessConfig};
+        use mocks::dlc_manager::Storage;
+
+        let signed_contract = SignedContract::deserialize(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/Signed"),
+        ))
+        .unwrap();
+        let offered_contract = &signed_contract.accepted_contract.offered_contract;
+        let counter_party = offered_contract.counter_party;
+        let maturity = offered_contract.contract_info[0].oracle_announcements[0]
+            .oracle_event
+            .event_maturity_epoch as u64;
 
-                    let witness = if signed_channel.own_params.fund_pubkey
-                        < signed_channel.counter_params.fund_pubkey
-                    {
-                        tx.input[0].witness.to_vec().remove(1)
-                    } else {
-                        tx.input[0].witness.to_vec().remove(2)
-                    };
+        // Keep the contract signed so that no confirmation event is generated.
+        let blockchain = Rc::new(MockBlockchain::new());
+        blockchain.set_confirmations(0);
+        let mut manager = get_manager_with_blockchain(blockchain);
+        manager
+            .get_store()
+            .update_contract(&Contract::Signed(signed_contract.clone()))
+            .unwrap();
+        manager.set_peer_liveness_config(PeerLivenessConfig {
+            unreachable_after: 100,
+            maturity_window: 100,
+        });
+        mocks::mock_time::set_time(maturity - 50);
 
-                    let sig_data = witness
-                        .iter()
-                        .take(witness.len() - 1)
-                        .cloned()
-                        .collect::<Vec<_>>();
-                    let own_sig = Signature::from_der(&sig_data)?;
-
-                    let counter_sk = own_adaptor_signature.recover(
-                        &self.secp,
-                        &own_sig,
-                        &counter_revocation_params.publish_pk.inner,
-                    )?;
+        manager
+            .get_store()
+            .update_peer_last_seen(&counter_party, maturity - 100)
+            .unwrap();
+        manager.periodic_check(false).unwrap();
+        assert!(manager.get_and_clear_pending_events().is_empty());
 
-                    let own_revocation_base_secret =
-                        &self.signer_provider.get_secret_key_for_pubkey(
-                            &signed_channel.own_points.revocation_basepoint,
-                        )?;
+        manager
+            .get_store()
+            .update_peer_last_seen(&counter_party, maturity - 200)
+            .unwrap();
+        manager.periodic_check(false).unwrap();
+        assert_eq!(
+            vec![ManagerEvent::PeerUnreachable {
+                peer_id: counter_party,
+                last_seen: maturity - 200,
+                contract_ids: vec![signed_contract.accepted_contract.get_contract_id()],
+            }],
+            manager.get_and_clear_pending_events()
+        );
+    }
 
-                    let counter_revocation_sk = derive_private_revocation_key(
-                        &self.secp,
-                        &counter_per_update_secret,
-                        own_revocation_base_secret,
-                    );
+    #[test]
+    fn unreachable_peer_is_reported_once_per_last_seen_time() {
+        use mocks::dlc_manager::contract::{
+            ser::Serializable, signed_contract::SignedContract, Contract,
+        };
+        use mocks::dlc_manager::manager::{ManagerEvent, PeerLivenessConfig};
+        use mocks::dlc_manager::Storage;
+
+        let signed_contract = SignedContract::deserialize(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/Signed"),
+        ))
+        .unwrap();
+        let offered_contract = &signed_contract.accepted_contract.offered_contract;
+        let counter_party = offered_contract.counter_party;
+        let maturity = offered_contract.contract_info[0].oracle_announcements[0]
+            .oracle_event
+            .event_maturity_epoch as u64;
 
-                    let (offer_params, accept_params) = if is_offer {
-                        (&own_revocation_params, &counter_revocation_params)
-                    } else {
-                        (&counter_revocation_params, &own_revocation_params)
-                    };
+        let blockchain = Rc::new(MockBlockchain::new());
+        blockchain.set_confirmations(0);
+        let mut manager = get_manager_with_blockchain(blockchain);
+        manager
+            .get_store()
+            .update_contract(&Contract::Signed(signed_contract.clone()))
+            .unwrap();
+        manager.set_peer_liveness_config(PeerLivenessConfig {
+            unreachable_after: 100,
+            maturity_window: 100,
+        });
+        mocks::mock_time::set_time(maturity - 50);
+
+        let get_reported_times = |manager: &TestManager| {
+            manager.periodic_check(false).unwrap();
+            manager
+                .get_and_clear_pending_events()
+                .into_iter()
+                .filter_map(|event| match event {
+                    ManagerEvent::PeerUnreachable { last_seen, .. } => Some(last_seen),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
 
-                    let fee_rate_per_vb: u64 = (self.fee_estimator.get_est_sat_per_1000_weight(
-                        lightning::chain::chaininterface::ConfirmationTarget::OnChainSweep,
-                    ) / 250)
-                        .into();
-
-                    let signed_tx = match revoked_tx_type {
-                        RevokedTxType::Buffer => {
-                            dlc::channel::create_and_sign_punish_buffer_transaction(
-                                &self.secp,
-                                offer_params,
-                                accept_params,
-                                &own_sk,
-                                &counter_sk,
-                                &counter_revocation_sk,
-                                &tx,
-                                &self.wallet.get_new_address()?,
-                                0,
-                                fee_rate_per_vb,
-                            )?
-                        }
-                        RevokedTxType::Settle => {
-                            dlc::channel::create_and_sign_punish_settle_transaction(
-                                &self.secp,
-                                offer_params,
-                                accept_params,
-                                &own_sk,
-                                &counter_sk,
-                                &counter_revocation_sk,
-                                &tx,
-                                &self.wallet.get_new_address()?,
-                                CET_NSEQUENCE,
-                                0,
-                                fee_rate_per_vb,
-                                is_offer,
-                            )?
-                        }
-                    };
+        manager
+            .get_store()
+            .update_peer_last_seen(&counter_party, maturity - 200)
+            .unwrap();
+        assert_eq!(vec![maturity - 200], get_reported_times(&manager));
+        assert!(get_reported_times(&manager).is_empty());
 
-                    self.blockchain.send_transaction(&signed_tx)?;
+        manager
+            .get_store()
+            .update_peer_last_seen(&counter_party, maturity - 180)
+            .unwrap();
+        assert_eq!(vec![maturity - 180], get_reported_times(&manager));
+        assert!(get_reported_times(&manager).is_empty());
+    }
 
-                    signed_channel.state = SignedChannelState::ClosedPunished {
-                        punishment_txid: signed_tx.txid(),
-                    };
+    #[test]
+    fn unreachable_peer_with_maturing_confirmed_contract_is_reported() {
+        use mocks::dlc_manager::contract::{
+            ser::Serializable, signed_contract::SignedContract, Contract,
+        };
+        use mocks::dlc_manager::manager::{ManagerEvent, PeerLivenessConfig};
+        use mocks::dlc_manager::Storage;
+
+        let signed_contract = SignedContract::deserialize(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/Signed"),
+        ))
+        .unwrap();
+        let offered_contract = &signed_contract.accepted_contract.offered_contract;
+        let counter_party = offered_contract.counter_party;
+        let maturity = offered_contract.contract_info[0].oracle_announcements[0]
+            .oracle_event
+            .event_maturity_epoch as u64;
 
-                    self.store
-                        .upsert_channel(Channel::Signed(signed_channel), None)?;
-                } else if let TxType::CollaborativeClose = channel_info.tx_type {
-                    if let Some(SignedChannelState::Established {
-                        signed_contract_id,
-                        is_offer,
-                        ..
-                    }) = signed_channel.roll_back_state
-                    {
-                        let counter_payout = get_signed_channel_state!(
-                            signed_channel,
-                            CollaborativeCloseOffered,
-                            counter_payout
-                        )?;
-                        let contract = get_contract_in_state!(
-                            self,
-                            &signed_contract_id,
-                            Confirmed,
-                            None::<PublicKey>
-                        )?;
-                        let own_payout =
-                            contract.accepted_contract.offered_contract.total_collateral
-                                - counter_payout;
-                        let own_collateral = if is_offer {
-                            contract
-                                .accepted_contract
-                                .offered_contract
-                                .offer_params
-                                .collateral
-                        } else {
-                            contract.accepted_contract.accept_params.collateral
-                        };
-                        let pnl = (own_collateral as i64) - (own_payout as i64);
-
-                        let closed_contract = ClosedContract {
-                            attestations: None,
-                            signed_cet: None,
-                            contract_id: signed_contract_id,
-                            temporary_contract_id: contract.accepted_contract.offered_contract.id,
-                            counter_party_id: signed_channel.counter_party,
-                            pnl,
-                        };
-                        self.store
-                            .update_contract(&Contract::Closed(closed_contract))?;
-                    }
-                    signed_channel.state = SignedChannelState::CollaborativelyClosed;
-                    self.store
-                        .upsert_channel(Channel::Signed(signed_channel), None)?;
-                }
-            }
+        let mut manager = get_manager();
+        manager
+            .get_store()
+            .update_contract(&Contract::Confirmed(signed_contract.clone()))
+            .unwrap();
+        manager.set_peer_liveness_config(PeerLivenessConfig {
+            unreachable_after: 100,
+            maturity_window: 100,
+        });
+        mocks::mock_time::set_time(maturity - 50);
 
-            self.chain_monitor.increment_height(&block.block_hash());
-        }
+        manager
+            .get_store()
+            .update_peer_last_seen(&counter_party, maturity - 100)
+            .unwrap();
+        manager.periodic_check(false).unwrap();
+        assert!(manager.get_and_clear_pending_events().is_empty());
 
-        Ok(())
+        manager
+            .get_store()
+            .update_peer_last_seen(&counter_party, maturity - 200)
+            .unwrap();
+        manager.periodic_check(false).unwrap();
+        assert_eq!(
+            vec![ManagerEvent::PeerUnreachable {
+                peer_id: counter_party,
+                last_seen: maturity - 200,
+                contract_ids: vec![signed_contract.accepted_contract.get_contract_id()],
+            }],
+            manager.get_and_clear_pending_events()
+        );
     }
 
-    fn force_close_channel_internal(&mut self, mut channel: SignedChannel) -> Result<(), Error> {
-        match channel.state {
-            SignedChannelState::Established { .. } => {
-                self.initiate_unilateral_close_established_channel(channel)
-            }
-            SignedChannelState::Settled { .. } => self.close_settled_channel(channel),
-            SignedChannelState::SettledOffered { .. }
-            | SignedChannelState::SettledReceived { .. }
-            | SignedChannelState::SettledAccepted { .. }
-            | SignedChannelState::SettledConfirmed { .. }
-            | SignedChannelState::RenewOffered { .. }
-            | SignedChannelState::RenewAccepted { .. }
-            | SignedChannelState::RenewConfirmed { .. }
-            | SignedChannelState::CollaborativeCloseOffered { .. } => {
-                channel.state = channel
-                    .roll_back_state
-                    .take()
-                    .expect("to have a rollback state");
-                self.force_close_channel_internal(channel)
-            }
-            SignedChannelState::Closing { .. } => Err(Error::InvalidState(
-                "Channel is already closing.".to_string(),
-            )),
-            SignedChannelState::Closed
-            | SignedChannelState::CounterClosed
-            | SignedChannelState::CollaborativelyClosed
-            | SignedChannelState::ClosedPunished { .. } => {
-                Err(Error::InvalidState("Channel already closed.".to_string()))
-            }
+    #[test]
+    fn payout_update_is_refused_once_transactions_can_be_broadcast() {
+        use mocks::dlc_manager::contract::{
+            ser::Serializable, signed_contract::SignedContract, Contract,
+        };
+        use mocks::dlc_manager::Storage;
+
+        let signed_contract = SignedContract::deserialize(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/Signed"),
+        ))
+        .unwrap();
+        let contract_id = signed_contract.accepted_contract.get_contract_id();
+        let offered_contract = &signed_contract.accepted_contract.offered_contract;
+        let maturity = offered_contract.contract_info[0].oracle_announcements[0]
+            .oracle_event
+            .event_maturity_epoch as u64;
+        let payout_spk = offered_contract.offer_params.payout_script_pubkey.clone();
+
+        let manager = get_manager();
+        manager
+            .get_store()
+            .update_contract(&Contract::Confirmed(signed_contract.clone()))
+            .unwrap();
+        mocks::mock_time::set_time(maturity);
+
+        match manager.update_payout_script(&contract_id, payout_spk) {
+            Err(Error::InvalidState(msg)) => assert!(msg.contains("can be broadcast")),
+            res => panic!("Expected an invalid state error, got {:?}", res.err()),
         }
     }
 
-    /// Initiate the unilateral closing of a channel that has been established.
-    fn initiate_unilateral_close_established_channel(
-        &mut self,
-        mut signed_channel: SignedChannel,
-    ) -> Result<(), Error> {
-        let contract_id = signed_channel.get_contract_id().ok_or_else(|| {
-            Error::InvalidState(
-                "Expected to be in a state with an associated contract id but was not.".to_string(),
-            )
-        })?;
-
-        let confirmed_contract =
-            get_contract_in_state!(self, &contract_id, Confirmed, None as Option<PublicKey>)?;
-
-        let (contract_info, adaptor_info, attestations) = self
-            .get_closable_contract_info(&confirmed_contract)
-            .ok_or_else(|| {
-                Error::InvalidState("Could not get closable contract info".to_string())
-            })?;
-
-        crate::channel_updater::initiate_unilateral_close_established_channel(
-            &self.secp,
-            &mut signed_channel,
-            &confirmed_contract,
-            contract_info,
-            &attestations,
-            adaptor_info,
-            &self.signer_provider,
-        )?;
-
-        let buffer_transaction =
-            get_signed_channel_state!(signed_channel, Closing, ref buffer_transaction)?;
+    #[test]
+    fn contract_can_be_found_by_temporary_id() {
+        use mocks::dlc_manager::contract::{
+            compute_contract_id, compute_temporary_contract_id, ser::Serializable,
+            signed_contract::SignedContract, Contract,
+        };
 
-        self.blockchain.send_transaction(buffer_transaction)?;
+        let signed_contract = SignedContract::deserialize(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/Signed"),
+        ))
+        .unwrap();
+        let accepted_contract = &signed_contract.accepted_contract;
+        let temporary_id = accepted_contract.offered_contract.id;
+        let contract_id = accepted_contract.get_contract_id();
+        let fund_txid = accepted_contract.dlc_transactions.fund.txid();
+        let fund_output_index = accepted_contract.dlc_transactions.get_fund_output_index() as u16;
+        assert_eq!(
+            contract_id,
+            compute_contract_id(fund_txid, fund_output_index, &temporary_id)
+        );
+        assert_eq!(
+            temporary_id,
+            compute_temporary_contract_id(fund_txid, fund_output_index, &contract_id)
+        );
 
-        self.chain_monitor.remove_tx(&buffer_transaction.txid());
+        let manager = get_manager();
+        manager
+            .get_store()
+            .update_contract(&Contract::Signed(signed_contract.clone()))
+            .unwrap();
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        let contract = manager
+            .get_store()
+            .get_contract_by_temporary_id(&temporary_id)
+            .unwrap()
+            .expect("To find the contract");
+        assert_eq!(contract_id, contract.get_id());
+        assert!(manager
+            .get_store()
+            .get_contract_by_temporary_id(&contract_id)
+            .unwrap()
+            .is_none());
+    }
 
-        self.store.persist_chain_monitor(&self.chain_monitor)?;
+    #[test]
+    fn events_are_passed_to_event_handlers() {
+        use mocks::dlc_manager::manager::{EventHandler, ManagerEvent};
+        use std::sync::Mutex;
 
-        Ok(())
-    }
+        #[derive(Default)]
+        struct RecordingHandler {
+            events: Mutex<Vec<ManagerEvent>>,
+        }
 
-    /// Unilaterally close a channel that has been settled.
-    fn close_settled_channel(&mut self, mut signed_channel: SignedChannel) -> Result<(), Error> {
-        let settle_tx = crate::channel_updater::close_settled_channel(
-            &self.secp,
-            &mut signed_channel,
-            &self.signer_provider,
-        )?;
+        impl EventHandler for RecordingHandler {
+            fn handle_event(&self, event: &ManagerEvent) {
+                self.events.lock().unwrap().push(event.clone());
+            }
+        }
 
-        self.blockchain.send_transaction(&settle_tx)?;
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let expected = vec![ManagerEvent::OfferReceived {
+            contract_id: offer.temporary_contract_id,
+            counter_party: pubkey(),
+        }];
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        let manager = get_manager();
+        manager
+            .on_dlc_message(&Message::Offer(offer.clone()), pubkey())
+            .expect("To accept the offer message");
+        assert_eq!(expected, manager.get_and_clear_pending_events());
 
-        Ok(())
+        let handler = Arc::new(RecordingHandler::default());
+        let mut manager = get_manager();
+        manager.add_event_handler(handler.clone());
+        manager
+            .on_dlc_message(&Message::Offer(offer), pubkey())
+            .expect("To accept the offer message");
+        assert_eq!(expected, *handler.events.lock().unwrap());
+        assert!(manager.get_and_clear_pending_events().is_empty());
     }
-}
-
-#[cfg(test)]
-mod test {
-    use dlc_messages::Message;
-    use mocks::{
-        dlc_manager::{manager::Manager, CachedContractSignerProvider, Oracle, SimpleSigner},
-        memory_storage_provider::MemoryStorage,
-        mock_blockchain::MockBlockchain,
-        mock_oracle_provider::MockOracle,
-        mock_time::MockTime,
-        mock_wallet::MockWallet,
-    };
-    use secp256k1_zkp::{PublicKey, XOnlyPublicKey};
-    use std::{collections::HashMap, rc::Rc, sync::Arc};
 
-    type TestManager = Manager<
-        Rc<MockWallet>,
-        Arc<CachedContractSignerProvider<Rc<MockWallet>, SimpleSigner>>,
-        Rc<MockBlockchain>,
-        Rc<MemoryStorage>,
-        Rc<MockOracle>,
-        Rc<MockTime>,
-        Rc<MockBlockchain>,
-        SimpleSigner,
-    >;
+    #[test]
+    fn contracts_can_be_processed_concurrently() {
+        use mocks::dlc_manager::contract::Contract;
 
-    fn get_manager() -> TestManager {
-        let blockchain = Rc::new(MockBlockchain::new());
-        let store = Rc::new(MemoryStorage::new());
-        let wallet = Rc::new(MockWallet::new(
+        let blockchain = Arc::new(MockBlockchain::new());
+        let wallet = Arc::new(MockWallet::new(
             &blockchain,
             &(0..100).map(|x| x as u64 * 1000000).collect::<Vec<_>>(),
         ));
-
-        let oracle_list = (0..5).map(|_| MockOracle::new()).collect::<Vec<_>>();
-        let oracles: HashMap<XOnlyPublicKey, _> = oracle_list
-            .into_iter()
-            .map(|x| (x.get_public_key(), Rc::new(x)))
+        let oracles: HashMap<XOnlyPublicKey, _> = (0..5)
+            .map(|_| {
+                let oracle = MockOracle::new();
+                (oracle.get_public_key(), Arc::new(oracle))
+            })
             .collect();
-        let time = Rc::new(MockTime {});
-
-        mocks::mock_time::set_time(0);
-
-        Manager::new(
+        let manager = Manager::new(
             wallet.clone(),
             wallet,
             blockchain.clone(),
-            store,
+            Arc::new(MemoryStorage::new()),
             oracles,
-            time,
+            Arc::new(MockTime {}),
             blockchain,
         )
-        .unwrap()
-    }
+        .unwrap();
+
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let offers = [1u8, 2u8].map(|i| {
+            let mut offer = offer.clone();
+            offer.temporary_contract_id = [i; 32];
+            offer
+        });
 
-    fn pubkey() -> PublicKey {
-        "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166"
-            .parse()
-            .unwrap()
+        std::thread::scope(|s| {
+            for offer in &offers {
+                let manager = &manager;
+                s.spawn(move || {
+                    manager
+                        .on_dlc_message(&Message::Offer(offer.clone()), pubkey())
+                        .expect("To accept the offer message");
+                    manager
+                        .accept_contract_offer(&offer.temporary_contract_id)
+                        .expect("To accept the contract offer")
+                });
+            }
+            s.spawn(|| {
+                for _ in 0..10 {
+                    manager.periodic_check(false).unwrap();
+                }
+            });
+        });
+
+        let contracts = manager.get_store().get_contracts().unwrap();
+        assert_eq!(2, contracts.len());
+        assert!(contracts.iter().all(|c| matches!(c, Contract::Accepted(_))));
+        for offer in &offers {
+            assert!(contracts
+                .iter()
+                .any(|c| c.get_temporary_id() == offer.temporary_contract_id));
+        }
     }
 
     #[test]
-    fn reject_offer_with_existing_contract_id() {
-        let offer_message = Message::Offer(
-            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap(),
-        );
+    fn periodic_check_stops_when_budget_is_exhausted() {
+        use mocks::dlc_manager::contract::{
+            ser::Serializable, signed_contract::SignedContract, Contract,
+        };
+        use std::time::Duration;
+
+        let signed_contract = SignedContract::deserialize(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/Signed"),
+        ))
+        .unwrap();
+        let contract_id = signed_contract.accepted_contract.get_contract_id();
 
         let mut manager = get_manager();
+        manager
+            .get_store()
+            .update_contract(&Contract::Signed(signed_contract))
+            .unwrap();
+
+        manager.set_periodic_check_budget(Duration::ZERO);
+        manager.periodic_check(false).unwrap();
+        assert!(matches!(
+            manager.get_store().get_contract(&contract_id).unwrap(),
+            Some(Contract::Signed(_))
+        ));
+
+        manager.set_periodic_check_budget(Duration::from_secs(60));
+        manager.periodic_check(false).unwrap();
+        assert!(matches!(
+            manager.get_store().get_contract(&contract_id).unwrap(),
+            Some(Contract::Confirmed(_))
+        ));
+    }
+
+    #[test]
+    fn recover_resolves_contracts_closed_while_offline() {
+        use mocks::dlc_manager::contract::{
+            ser::Serializable, signed_contract::SignedContract, Contract,
+        };
+        use mocks::dlc_manager::manager::RecoveryReport;
+        use mocks::dlc_manager::Blockchain;
+
+        let signed_contract = SignedContract::deserialize(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/Signed"),
+        ))
+        .unwrap();
+        let contract_id = signed_contract.accepted_contract.get_contract_id();
+        let offered_contract = signed_contract.accepted_contract.offered_contract.clone();
+        let cet = signed_contract.accepted_contract.dlc_transactions.cets[0].clone();
+        let maturity = offered_contract.contract_info[0].oracle_announcements[0]
+            .oracle_event
+            .event_maturity_epoch as u64;
 
+        let blockchain = Rc::new(MockBlockchain::new());
+        let manager = get_manager_with_blockchain(blockchain.clone());
         manager
-            .on_dlc_message(&offer_message, pubkey())
-            .expect("To accept the first offer message");
+            .get_store()
+            .update_contract(&Contract::Signed(signed_contract))
+            .unwrap();
+        // Simulates a crash that left the offer behind the signed contract.
+        manager
+            .get_store()
+            .update_contract(&Contract::Offered(offered_contract.clone()))
+            .unwrap();
+        blockchain.send_transaction(&cet).unwrap();
+        mocks::mock_time::set_time(maturity);
+
+        let report = manager.recover().unwrap();
+
+        assert_eq!(
+            RecoveryReport {
+                restored_contract_ids: vec![contract_id],
+                confirmed_contracts: vec![contract_id],
+                rewatched_channels: Vec::new(),
+                resolved_contracts: vec![contract_id],
+                rebroadcast_contracts: Vec::new(),
+            },
+            report
+        );
+        assert!(manager
+            .get_store()
+            .get_contract(&offered_contract.id)
+            .unwrap()
+            .is_none());
+        match manager.get_store().get_contract(&contract_id).unwrap() {
+            Some(Contract::Closed(c)) => assert_eq!(Some(cet), c.signed_cet),
+            c => panic!(
+                "Expected closed contract but got {:?}",
+                c.map(|c| c.get_state_name())
+            ),
+        }
+    }
+
+    #[test]
+    fn recover_rebroadcasts_unconfirmed_fund_transactions() {
+        use mocks::dlc_manager::contract::{
+            ser::Serializable, signed_contract::SignedContract, Contract,
+        };
+
+        let mut signed_contract = SignedContract::deserialize(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/Signed"),
+        ))
+        .unwrap();
+        signed_contract
+            .accepted_contract
+            .offered_contract
+            .is_offer_party = false;
+        let contract_id = signed_contract.accepted_contract.get_contract_id();
+        let fund_txid = signed_contract
+            .accepted_contract
+            .dlc_transactions
+            .fund
+            .txid();
 
+        let blockchain = Rc::new(MockBlockchain::new());
+        blockchain.set_confirmations(0);
+        let manager = get_manager_with_blockchain(blockchain.clone());
         manager
-            .on_dlc_message(&offer_message, pubkey())
-            .expect_err("To reject the second offer message");
+            .get_store()
+            .update_contract(&Contract::Signed(signed_contract))
+            .unwrap();
+
+        let report = manager.recover().unwrap();
+
+        assert_eq!(vec![contract_id], report.rebroadcast_contracts);
+        assert!(report.confirmed_contracts.is_empty());
+        assert!(blockchain.was_sent(&fund_txid));
+        assert!(matches!(
+            manager.get_store().get_contract(&contract_id).unwrap(),
+            Some(Contract::Signed(_))
+        ));
+    }
+
+    #[test]
+    fn fast_sync_skips_missed_blocks() {
+        let mut manager = get_manager();
+        manager.set_fast_sync_threshold(5);
+        manager.chain_monitor.lock().unwrap().last_height = 1;
+
+        // The mock blockchain cannot return blocks, so this would fail if the
+        // missed blocks were walked through.
+        manager.check_for_watched_tx().unwrap();
+
+        assert_eq!(9, manager.chain_monitor.lock().unwrap().last_height);
     }
 
     #[test]
@@ -2559,7 +8748,7 @@ mod test {
             serde_json::from_str(include_str!("../test_inputs/offer_channel.json")).unwrap(),
         );
 
-        let mut manager = get_manager();
+        let manager = get_manager();
 
         manager
             .on_dlc_message(&offer_message, pubkey())
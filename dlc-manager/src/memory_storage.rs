@@ -0,0 +1,721 @@
+//! # In memory implementation of the [`Storage`] trait, mainly useful for
+//! testing and for running ephemeral nodes. Nothing is persisted across
+//! restarts.
+
+use crate::chain_monitor::{ChainMonitor, HeaderCache};
+use crate::channel::{
+    offered_channel::OfferedChannel,
+    punishment::PunishmentData,
+    signed_channel::{SignedChannel, SignedChannelStateType},
+    watchtower::{JusticeBlob, LOCATOR_LEN},
+    Channel, ChannelEvent,
+};
+use crate::contract::{
+    offered_contract::OfferedContract, signed_contract::SignedContract, Contract, ContractEvent,
+    PreClosedContract,
+};
+use crate::error::Error;
+use crate::product_catalog::ProductDefinition;
+use crate::{ChannelId, ContractId, Storage, StorageBatch, StorageSnapshot};
+use secp256k1_zkp::PublicKey;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, RwLock};
+
+/// Implementation of the [`Storage`] trait keeping all data in memory.
+pub struct MemoryStorage {
+    contracts: RwLock<HashMap<ContractId, Contract>>,
+    channels: RwLock<HashMap<ChannelId, Channel>>,
+    chain_monitor: RwLock<Option<ChainMonitor>>,
+    header_cache: RwLock<Option<HeaderCache>>,
+    peers_last_seen: RwLock<HashMap<PublicKey, u64>>,
+    offer_hashes: RwLock<HashMap<[u8; 32], ContractId>>,
+    products: RwLock<HashMap<String, ProductDefinition>>,
+    punishments: RwLock<HashMap<ChannelId, BTreeMap<u64, PunishmentData>>>,
+    justice_blobs: RwLock<HashMap<[u8; LOCATOR_LEN], JusticeBlob>>,
+    contract_events: RwLock<HashMap<ContractId, Vec<ContractEvent>>>,
+    channel_events: RwLock<HashMap<ChannelId, Vec<ChannelEvent>>>,
+    contracts_saved: Mutex<Option<HashMap<ContractId, Contract>>>,
+    channels_saved: Mutex<Option<HashMap<ChannelId, Channel>>>,
+}
+
+impl MemoryStorage {
+    /// Creates a new empty [`MemoryStorage`].
+    pub fn new() -> Self {
+        MemoryStorage {
+            contracts: RwLock::new(HashMap::new()),
+            channels: RwLock::new(HashMap::new()),
+            chain_monitor: RwLock::new(None),
+            header_cache: RwLock::new(None),
+            peers_last_seen: RwLock::new(HashMap::new()),
+            offer_hashes: RwLock::new(HashMap::new()),
+            products: RwLock::new(HashMap::new()),
+            punishments: RwLock::new(HashMap::new()),
+            justice_blobs: RwLock::new(HashMap::new()),
+            contract_events: RwLock::new(HashMap::new()),
+            channel_events: RwLock::new(HashMap::new()),
+            contracts_saved: Mutex::new(None),
+            channels_saved: Mutex::new(None),
+        }
+    }
+
+    /// Takes a snapshot of the stored contracts and channels, that can later be
+    /// restored using [`MemoryStorage::rollback`].
+    pub fn save(&self) {
+        let mut contracts_saved = self.contracts_saved.lock().unwrap();
+
+        *contracts_saved = Some(
+            self.contracts
+                .read()
+                .expect("Could not get read lock")
+                .clone(),
+        );
+        let mut channels_saved = self.channels_saved.lock().unwrap();
+        *channels_saved = Some(
+            self.channels
+                .read()
+                .expect("Could not get read lock")
+                .clone(),
+        );
+    }
+
+    /// Restores the contracts and channels to the snapshot taken by the last
+    /// call to [`MemoryStorage::save`].
+    ///
+    /// Panics if no snapshot was taken.
+    pub fn rollback(&self) {
+        let mut contracts = self.contracts.write().unwrap();
+        let mut contracts_saved = self.contracts_saved.lock().unwrap();
+        let mut tmp = None;
+        std::mem::swap(&mut tmp, &mut *contracts_saved);
+        std::mem::swap(&mut *contracts, &mut tmp.unwrap());
+
+        let mut channels = self.channels.write().unwrap();
+        let mut channels_saved = self.channels_saved.lock().unwrap();
+        let mut tmp = None;
+        std::mem::swap(&mut tmp, &mut *channels_saved);
+        std::mem::swap(&mut *channels, &mut tmp.unwrap());
+    }
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn get_contract(&self, id: &ContractId) -> Result<Option<Contract>, Error> {
+        let map = self.contracts.read().expect("Could not get read lock");
+        Ok(map.get(id).cloned())
+    }
+
+    fn get_contracts(&self) -> Result<Vec<Contract>, Error> {
+        Ok(self
+            .contracts
+            .read()
+            .expect("Could not get read lock")
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn create_contract(&self, contract: &OfferedContract) -> Result<(), Error> {
+        let mut map = self.contracts.write().expect("Could not get write lock");
+        if map.contains_key(&contract.id) {
+            return Err(Error::AlreadyExists("Contract already exists".to_string()));
+        }
+        map.insert(contract.id, Contract::Offered(contract.clone()));
+        Ok(())
+    }
+
+    fn delete_contract(&self, id: &ContractId) -> Result<(), Error> {
+        let mut map = self.contracts.write().expect("Could not get write lock");
+        map.remove(id);
+        Ok(())
+    }
+
+    fn update_contract(&self, contract: &Contract) -> Result<(), Error> {
+        let mut map = self.contracts.write().expect("Could not get write lock");
+        insert_contract(&mut map, contract.clone());
+        Ok(())
+    }
+
+    fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        let map = self.contracts.read().expect("Could not get read lock");
+
+        let mut res: Vec<SignedContract> = Vec::new();
+
+        for (_, val) in map.iter() {
+            if let Contract::Signed(c) = val {
+                res.push(c.clone());
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        let map = self.contracts.read().expect("Could not get read lock");
+
+        let mut res: Vec<SignedContract> = Vec::new();
+
+        for (_, val) in map.iter() {
+            if let Contract::Confirmed(c) = val {
+                res.push(c.clone());
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        let map = self.contracts.read().expect("Could not get read lock");
+
+        let mut res: Vec<OfferedContract> = Vec::new();
+
+        for (_, val) in map.iter() {
+            if let Contract::Offered(c) = val {
+                res.push(c.clone());
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn get_expired_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        let map = self.contracts.read().expect("Could not get read lock");
+
+        let mut res: Vec<OfferedContract> = Vec::new();
+
+        for (_, val) in map.iter() {
+            if let Contract::Expired(c) = val {
+                res.push(c.clone());
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, Error> {
+        let map = self.contracts.read().expect("Could not get read lock");
+
+        let mut res: Vec<PreClosedContract> = Vec::new();
+
+        for (_, val) in map.iter() {
+            if let Contract::PreClosed(c) = val {
+                res.push(c.clone());
+            }
+        }
+        Ok(res)
+    }
+
+    fn upsert_channel(&self, channel: Channel, contract: Option<Contract>) -> Result<(), Error> {
+        {
+            let mut map = self.channels.write().expect("Could not get write lock");
+            insert_channel(&mut map, channel);
+        }
+        if let Some(c) = contract {
+            self.update_contract(&c)?;
+        }
+        Ok(())
+    }
+
+    fn delete_channel(&self, channel_id: &ChannelId) -> Result<(), Error> {
+        let mut map = self.channels.write().expect("Could not get write lock");
+        map.remove(channel_id);
+        self.punishments
+            .write()
+            .expect("Could not get write lock")
+            .remove(channel_id);
+        Ok(())
+    }
+
+    fn get_channel(&self, channel_id: &ChannelId) -> Result<Option<Channel>, Error> {
+        let map = self.channels.read().expect("could not get read lock");
+        Ok(map.get(channel_id).cloned())
+    }
+
+    fn get_channels_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Channel>, Error> {
+        let map = self.channels.read().expect("Could not get read lock");
+        Ok(map
+            .values()
+            .filter(|c| &c.get_counter_party_id() == counter_party)
+            .cloned()
+            .collect())
+    }
+
+    fn get_channel_by_contract_id(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<Channel>, Error> {
+        let map = self.channels.read().expect("Could not get read lock");
+        Ok(map
+            .values()
+            .find(|c| c.get_contract_id().as_ref() == Some(contract_id))
+            .cloned())
+    }
+
+    fn get_signed_channels(
+        &self,
+        channel_state: Option<SignedChannelStateType>,
+    ) -> Result<Vec<SignedChannel>, Error> {
+        let map = self.channels.read().expect("Could not get read lock");
+
+        let mut res: Vec<SignedChannel> = Vec::new();
+
+        for (_, val) in map.iter() {
+            if let Channel::Signed(c) = val {
+                match channel_state {
+                    Some(ref state) => {
+                        if c.state.is_of_type(state) {
+                            res.push(c.clone())
+                        }
+                    }
+                    None => res.push(c.clone()),
+                };
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn get_offered_channels(&self) -> Result<Vec<OfferedChannel>, Error> {
+        let map = self.channels.read().expect("Could not get read lock");
+
+        let mut res: Vec<OfferedChannel> = Vec::new();
+
+        for (_, val) in map.iter() {
+            if let Channel::Offered(c) = val {
+                res.push(c.clone())
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn persist_chain_monitor(&self, monitor: &ChainMonitor) -> Result<(), Error> {
+        *self
+            .chain_monitor
+            .write()
+            .expect("Could not get write lock") = Some(monitor.clone());
+        Ok(())
+    }
+
+    fn write_batch(&self, batch: StorageBatch) -> Result<(), Error> {
+        // Holding all the locks while writing makes the batch atomic for readers.
+        let mut contracts = self.contracts.write().expect("Could not get write lock");
+        let mut channels = self.channels.write().expect("Could not get write lock");
+        let mut chain_monitor = self
+            .chain_monitor
+            .write()
+            .expect("Could not get write lock");
+        let mut contract_events = self
+            .contract_events
+            .write()
+            .expect("Could not get write lock");
+        let mut channel_events = self
+            .channel_events
+            .write()
+            .expect("Could not get write lock");
+        if batch
+            .new_contracts
+            .iter()
+            .any(|c| contracts.contains_key(&c.id))
+        {
+            return Err(Error::AlreadyExists("Contract already exists".to_string()));
+        }
+        for contract in batch.new_contracts {
+            contracts.insert(contract.id, Contract::Offered(contract));
+        }
+        for channel in batch.channels {
+            insert_channel(&mut channels, channel);
+        }
+        for contract in batch.contracts {
+            insert_contract(&mut contracts, contract);
+        }
+        for (temporary_id, event) in batch.contract_events {
+            contract_events.entry(temporary_id).or_default().push(event);
+        }
+        for (temporary_id, event) in batch.channel_events {
+            channel_events.entry(temporary_id).or_default().push(event);
+        }
+        if batch.chain_monitor.is_some() {
+            *chain_monitor = batch.chain_monitor;
+        }
+        Ok(())
+    }
+
+    fn read_snapshot(&self) -> Result<StorageSnapshot, Error> {
+        // Locks are taken in the same order as in `write_batch` and held until
+        // all records are copied.
+        let contracts = self.contracts.read().expect("Could not get read lock");
+        let channels = self.channels.read().expect("Could not get read lock");
+        let chain_monitor = self.chain_monitor.read().expect("Could not get read lock");
+        Ok(StorageSnapshot::new(
+            contracts.values().cloned().collect(),
+            channels.values().cloned().collect(),
+            chain_monitor.clone(),
+        ))
+    }
+
+    fn get_chain_monitor(&self) -> Result<Option<ChainMonitor>, Error> {
+        Ok(self
+            .chain_monitor
+            .read()
+            .expect("Could not get read lock")
+            .clone())
+    }
+
+    fn update_peer_last_seen(&self, peer_id: &PublicKey, timestamp: u64) -> Result<(), Error> {
+        self.peers_last_seen
+            .write()
+            .expect("Could not get write lock")
+            .insert(*peer_id, timestamp);
+        Ok(())
+    }
+
+    fn get_peer_last_seen(&self, peer_id: &PublicKey) -> Result<Option<u64>, Error> {
+        Ok(self
+            .peers_last_seen
+            .read()
+            .expect("Could not get read lock")
+            .get(peer_id)
+            .copied())
+    }
+
+    fn upsert_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+        temporary_id: &ContractId,
+    ) -> Result<(), Error> {
+        self.offer_hashes
+            .write()
+            .expect("Could not get write lock")
+            .insert(*offer_hash, *temporary_id);
+        Ok(())
+    }
+
+    fn get_contract_id_by_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+    ) -> Result<Option<ContractId>, Error> {
+        Ok(self
+            .offer_hashes
+            .read()
+            .expect("Could not get read lock")
+            .get(offer_hash)
+            .copied())
+    }
+
+    fn upsert_punishment_data(&self, data: &PunishmentData) -> Result<(), Error> {
+        self.punishments
+            .write()
+            .expect("Could not get write lock")
+            .entry(data.channel_id)
+            .or_default()
+            .insert(data.update_idx, data.clone());
+        Ok(())
+    }
+
+    fn get_punishment_data(
+        &self,
+        channel_id: &ChannelId,
+        update_idx: u64,
+    ) -> Result<Option<PunishmentData>, Error> {
+        Ok(self
+            .punishments
+            .read()
+            .expect("Could not get read lock")
+            .get(channel_id)
+            .and_then(|x| x.get(&update_idx))
+            .cloned())
+    }
+
+    fn get_latest_punishment_data(
+        &self,
+        channel_id: &ChannelId,
+        count: usize,
+    ) -> Result<Vec<PunishmentData>, Error> {
+        // Update indexes decrease with each update, so the most recently revoked
+        // states have the lowest ones.
+        Ok(self
+            .punishments
+            .read()
+            .expect("Could not get read lock")
+            .get(channel_id)
+            .map(|x| x.values().take(count).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn upsert_justice_blob(&self, blob: &JusticeBlob) -> Result<(), Error> {
+        self.justice_blobs
+            .write()
+            .expect("Could not get write lock")
+            .insert(blob.locator, blob.clone());
+        Ok(())
+    }
+
+    fn get_justice_blob(&self, locator: &[u8; LOCATOR_LEN]) -> Result<Option<JusticeBlob>, Error> {
+        Ok(self
+            .justice_blobs
+            .read()
+            .expect("Could not get read lock")
+            .get(locator)
+            .cloned())
+    }
+
+    fn persist_header_cache(&self, header_cache: &HeaderCache) -> Result<(), Error> {
+        *self.header_cache.write().expect("Could not get write lock") = Some(header_cache.clone());
+        Ok(())
+    }
+
+    fn get_header_cache(&self) -> Result<Option<HeaderCache>, Error> {
+        Ok(self
+            .header_cache
+            .read()
+            .expect("Could not get read lock")
+            .clone())
+    }
+
+    fn upsert_product_definition(
+        &self,
+        event_id: &str,
+        product: &ProductDefinition,
+    ) -> Result<(), Error> {
+        self.products
+            .write()
+            .expect("Could not get write lock")
+            .insert(event_id.to_string(), product.clone());
+        Ok(())
+    }
+
+    fn get_product_definition(&self, event_id: &str) -> Result<Option<ProductDefinition>, Error> {
+        Ok(self
+            .products
+            .read()
+            .expect("Could not get read lock")
+            .get(event_id)
+            .cloned())
+    }
+
+    fn append_contract_event(
+        &self,
+        temporary_id: &ContractId,
+        event: &ContractEvent,
+    ) -> Result<(), Error> {
+        self.contract_events
+            .write()
+            .expect("Could not get write lock")
+            .entry(*temporary_id)
+            .or_default()
+            .push(event.clone());
+        Ok(())
+    }
+
+    fn get_contract_history(&self, id: &ContractId) -> Result<Vec<ContractEvent>, Error> {
+        let temporary_id = self.get_contract(id)?.map_or(*id, |c| c.get_temporary_id());
+        Ok(self
+            .contract_events
+            .read()
+            .expect("Could not get read lock")
+            .get(&temporary_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn append_channel_event(
+        &self,
+        temporary_id: &ChannelId,
+        event: &ChannelEvent,
+    ) -> Result<(), Error> {
+        self.channel_events
+            .write()
+            .expect("Could not get write lock")
+            .entry(*temporary_id)
+            .or_default()
+            .push(event.clone());
+        Ok(())
+    }
+
+    fn get_channel_history(&self, id: &ChannelId) -> Result<Vec<ChannelEvent>, Error> {
+        let temporary_id = self.get_channel(id)?.map_or(*id, |c| c.get_history_id());
+        Ok(self
+            .channel_events
+            .read()
+            .expect("Could not get read lock")
+            .get(&temporary_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+fn insert_contract(map: &mut HashMap<ContractId, Contract>, contract: Contract) {
+    match &contract {
+        a @ Contract::Accepted(_) | a @ Contract::Signed(_) => {
+            map.remove(&a.get_temporary_id());
+        }
+        _ => {}
+    };
+    map.insert(contract.get_id(), contract);
+}
+
+fn insert_channel(map: &mut HashMap<ChannelId, Channel>, channel: Channel) {
+    match &channel {
+        a @ Channel::Accepted(_) | a @ Channel::Signed(_) => {
+            map.remove(&a.get_temporary_id());
+        }
+        _ => {}
+    };
+    map.insert(channel.get_id(), channel);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lightning::util::ser::Readable;
+    use std::io::Cursor;
+
+    fn deserialize_test_object<T: Readable>(serialized: &[u8]) -> T {
+        Readable::read(&mut Cursor::new(&serialized)).unwrap()
+    }
+
+    #[test]
+    fn update_contract_replaces_offered_contract() {
+        let storage = MemoryStorage::new();
+        let offered_contract: OfferedContract = deserialize_test_object(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Offered"
+        ));
+        let signed_contract = Contract::Signed(deserialize_test_object(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Signed"
+        )));
+
+        storage
+            .create_contract(&offered_contract)
+            .expect("to be able to create the contract");
+        storage
+            .update_contract(&signed_contract)
+            .expect("to be able to update the contract");
+
+        assert!(storage
+            .get_contract(&signed_contract.get_temporary_id())
+            .unwrap()
+            .is_none());
+        assert_eq!(1, storage.get_signed_contracts().unwrap().len());
+    }
+
+    #[test]
+    fn create_contract_fails_if_contract_exists() {
+        let storage = MemoryStorage::new();
+        let offered_contract: OfferedContract = deserialize_test_object(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Offered"
+        ));
+
+        storage
+            .create_contract(&offered_contract)
+            .expect("to be able to create the contract");
+
+        assert!(matches!(
+            storage.create_contract(&offered_contract),
+            Err(Error::AlreadyExists(_))
+        ));
+        storage
+            .replace_contract(&offered_contract)
+            .expect("to be able to replace the contract");
+        assert_eq!(1, storage.get_contract_offers().unwrap().len());
+    }
+
+    #[test]
+    fn batch_creating_existing_contract_is_not_written() {
+        let storage = MemoryStorage::new();
+        let offered_contract: OfferedContract = deserialize_test_object(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Offered"
+        ));
+        let event = ContractEvent {
+            event_type: crate::contract::ContractEventType::OfferReceived,
+            timestamp: 10,
+            trigger: "OfferDlc".to_string(),
+        };
+        let batch = StorageBatch::new()
+            .with_new_contract(offered_contract.clone())
+            .with_contract_event(offered_contract.id, event.clone());
+
+        storage
+            .write_batch(batch.clone())
+            .expect("to be able to write the batch");
+        assert!(matches!(
+            storage.write_batch(batch),
+            Err(Error::AlreadyExists(_))
+        ));
+
+        assert_eq!(
+            vec![event],
+            storage.get_contract_history(&offered_contract.id).unwrap()
+        );
+    }
+
+    #[test]
+    fn rollback_restores_saved_state() {
+        let storage = MemoryStorage::new();
+        let offered_contract: OfferedContract = deserialize_test_object(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Offered"
+        ));
+
+        storage.save();
+        storage
+            .create_contract(&offered_contract)
+            .expect("to be able to create the contract");
+        storage.rollback();
+
+        assert!(storage.get_contracts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn chain_monitor_is_persisted() {
+        let storage = MemoryStorage::new();
+        let chain_monitor = ChainMonitor::new(123);
+
+        storage
+            .persist_chain_monitor(&chain_monitor)
+            .expect("to be able to persist the chain monitor");
+
+        assert_eq!(Some(chain_monitor), storage.get_chain_monitor().unwrap());
+    }
+
+    #[test]
+    fn header_cache_is_persisted() {
+        let storage = MemoryStorage::new();
+        let header_cache = HeaderCache::new();
+
+        storage
+            .persist_header_cache(&header_cache)
+            .expect("to be able to persist the header cache");
+
+        assert_eq!(Some(header_cache), storage.get_header_cache().unwrap());
+    }
+
+    #[test]
+    fn snapshot_is_not_affected_by_later_writes() {
+        let storage = MemoryStorage::new();
+        let offered_contract: OfferedContract = deserialize_test_object(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Offered"
+        ));
+        let chain_monitor = ChainMonitor::new(123);
+
+        storage
+            .create_contract(&offered_contract)
+            .expect("to be able to create the contract");
+        let snapshot = storage.read_snapshot().unwrap();
+        storage
+            .write_batch(StorageBatch::new().with_chain_monitor(chain_monitor))
+            .expect("to be able to write the batch");
+        storage.delete_contract(&offered_contract.id).unwrap();
+
+        assert_eq!(1, snapshot.get_contract_offers().len());
+        assert!(snapshot.get_contract(&offered_contract.id).is_some());
+        assert!(snapshot.get_chain_monitor().is_none());
+        assert!(storage.get_contracts().unwrap().is_empty());
+    }
+}
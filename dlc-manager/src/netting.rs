@@ -0,0 +1,482 @@
+//! #Netting
+//! Computation of the contract replacing several confirmed contracts with the
+//! same counter party on the same oracle events. The payout of each party in
+//! the net contract is the sum of its payouts in the netted contracts, minus
+//! the amount it receives for all outcomes, which is not at risk and is
+//! returned to it when funding the net contract.
+
+use crate::contract::contract_info::ContractInfo;
+use crate::contract::enum_descriptor::EnumDescriptor;
+use crate::contract::numerical_descriptor::{DifferenceParams, NumericalDescriptor};
+use crate::contract::signed_contract::SignedContract;
+use crate::contract::ContractDescriptor;
+use crate::error::Error;
+use crate::payout_curve::PayoutCurveBuilder;
+use dlc::{EnumerationPayout, Payout};
+
+/// The terms of a contract netting several contracts, from the point of view
+/// of the parties taking the offer and accept roles in the net contract.
+#[derive(Clone, Debug)]
+pub struct NetTerms {
+    /// The contract information of the net contract.
+    pub contract_info: ContractInfo,
+    /// The collateral of the offer party in the net contract.
+    pub offer_collateral: u64,
+    /// The collateral of the accept party in the net contract.
+    pub accept_collateral: u64,
+    /// The collaterals of the offer and accept parties of the net contract in
+    /// each of the netted contracts.
+    pub prev_collaterals: Vec<(u64, u64)>,
+}
+
+impl NetTerms {
+    /// Returns the total collateral of the net contract.
+    pub fn get_total_collateral(&self) -> u64 {
+        self.offer_collateral + self.accept_collateral
+    }
+}
+
+/// A contract to net, along with its total collateral and whether the offer
+/// party of the net contract is also its offer party.
+type NettedInfo<'a> = (&'a ContractInfo, u64, bool);
+
+/// Returns the terms of the contract netting the given contracts, whose offer
+/// party is the local one if `local_is_offer` is true and the counter party
+/// otherwise. The contracts must be plain contracts with the same counter
+/// party, each using a single contract information with the same oracle
+/// announcements.
+pub fn get_net_terms(
+    contracts: &[SignedContract],
+    local_is_offer: bool,
+) -> Result<NetTerms, Error> {
+    let first = match contracts {
+        [first, _, ..] => &first.accepted_contract.offered_contract,
+        _ => {
+            return Err(Error::InvalidParameters(
+                "At least two contracts are required for netting".to_string(),
+            ))
+        }
+    };
+
+    let mut infos = Vec::with_capacity(contracts.len());
+    let mut prev_collaterals = Vec::with_capacity(contracts.len());
+    for (i, contract) in contracts.iter().enumerate() {
+        let accepted_contract = &contract.accepted_contract;
+        let offered_contract = &accepted_contract.offered_contract;
+        if contract.channel_id.is_some() {
+            return Err(Error::InvalidState(
+                "Cannot net a channel contract".to_string(),
+            ));
+        }
+        if offered_contract.taproot {
+            return Err(Error::InvalidState(
+                "Cannot net a contract funded with a taproot output".to_string(),
+            ));
+        }
+        if offered_contract.counter_party != first.counter_party {
+            return Err(Error::InvalidParameters(
+                "Netted contracts must have the same counter party".to_string(),
+            ));
+        }
+        if contracts[..i]
+            .iter()
+            .any(|c| c.accepted_contract.get_contract_id() == accepted_contract.get_contract_id())
+        {
+            return Err(Error::InvalidParameters(
+                "Cannot net a contract with itself".to_string(),
+            ));
+        }
+        let contract_info = match offered_contract.contract_info.as_slice() {
+            [info] if is_same_event(info, &first.contract_info[0]) => info,
+            _ => {
+                return Err(Error::InvalidParameters(
+                    "Netted contracts must use a single contract information with the same oracle announcements"
+                        .to_string(),
+                ))
+            }
+        };
+
+        prev_collaterals.push(get_netted_collaterals(contract, local_is_offer));
+        infos.push((
+            contract_info,
+            offered_contract.total_collateral,
+            offered_contract.is_offer_party == local_is_offer,
+        ));
+    }
+
+    let prev_offer_collateral = prev_collaterals.iter().map(|(o, _)| *o).sum();
+    let prev_accept_collateral = prev_collaterals.iter().map(|(_, a)| *a).sum();
+    let (contract_descriptor, offer_collateral, accept_collateral) =
+        get_net_contract_descriptor(&infos, prev_offer_collateral, prev_accept_collateral)?;
+
+    Ok(NetTerms {
+        contract_info: ContractInfo {
+            contract_descriptor,
+            oracle_announcements: first.contract_info[0].oracle_announcements.clone(),
+            threshold: first.contract_info[0].threshold,
+        },
+        offer_collateral,
+        accept_collateral,
+        prev_collaterals,
+    })
+}
+
+/// Returns the collaterals in the given contract of the parties taking the
+/// offer and accept roles in a contract netting it, whose offer party is the
+/// local one if `local_is_offer` is true.
+pub(crate) fn get_netted_collaterals(
+    contract: &SignedContract,
+    local_is_offer: bool,
+) -> (u64, u64) {
+    let accepted_contract = &contract.accepted_contract;
+    let offer_collateral = accepted_contract.offered_contract.offer_params.collateral;
+    let accept_collateral = accepted_contract.accept_params.collateral;
+    if accepted_contract.offered_contract.is_offer_party == local_is_offer {
+        (offer_collateral, accept_collateral)
+    } else {
+        (accept_collateral, offer_collateral)
+    }
+}
+
+/// Returns the descriptor of the contract netting the given ones, along with
+/// the collaterals of its offer and accept parties.
+fn get_net_contract_descriptor(
+    infos: &[NettedInfo],
+    prev_offer_collateral: u64,
+    prev_accept_collateral: u64,
+) -> Result<(ContractDescriptor, u64, u64), Error> {
+    let total_collateral = prev_offer_collateral + prev_accept_collateral;
+    // The amounts that each party receives for all outcomes are released, but
+    // never more than its collateral.
+    let get_releases = |offer_payouts: &[u64]| -> Result<(u64, u64), Error> {
+        let min_offer_payout = offer_payouts.iter().copied().min().unwrap_or(0);
+        let max_offer_payout = offer_payouts.iter().copied().max().unwrap_or(0);
+        let offer_release = u64::min(min_offer_payout, prev_offer_collateral);
+        let accept_release = u64::min(total_collateral - max_offer_payout, prev_accept_collateral);
+        if offer_release + accept_release == total_collateral {
+            return Err(Error::InvalidParameters(
+                "Contracts offset each other for all outcomes and should be closed instead"
+                    .to_string(),
+            ));
+        }
+        Ok((offer_release, accept_release))
+    };
+
+    let (contract_descriptor, offer_release, accept_release) = match &infos[0].0.contract_descriptor
+    {
+        ContractDescriptor::Enum(e) => {
+            let offer_payouts = sum_enum_payouts(infos, e)?;
+            let (offer_release, accept_release) = get_releases(&offer_payouts)?;
+            let net_total_collateral = total_collateral - offer_release - accept_release;
+            let outcome_payouts = e
+                .outcome_payouts
+                .iter()
+                .zip(offer_payouts.iter())
+                .map(|(outcome_payout, payout)| {
+                    let offer = payout - offer_release;
+                    EnumerationPayout {
+                        outcome: outcome_payout.outcome.clone(),
+                        payout: Payout {
+                            offer,
+                            accept: net_total_collateral - offer,
+                        },
+                    }
+                })
+                .collect();
+            (
+                ContractDescriptor::Enum(EnumDescriptor { outcome_payouts }),
+                offer_release,
+                accept_release,
+            )
+        }
+        ContractDescriptor::Numerical(n) => {
+            let range_payouts = sum_numerical_payouts(infos)?;
+            let offer_payouts = range_payouts.iter().map(|(_, p)| *p).collect::<Vec<_>>();
+            let (offer_release, accept_release) = get_releases(&offer_payouts)?;
+            let net_total_collateral = total_collateral - offer_release - accept_release;
+            let max_outcome = get_max_outcome(n)?;
+            // Payouts are constant over each range, the curve jumping to
+            // the payout of the next range between two consecutive
+            // outcomes.
+            let mut builder = PayoutCurveBuilder::new(
+                net_total_collateral,
+                max_outcome,
+                offer_payouts[0] - offer_release,
+            );
+            for (i, (start, payout)) in range_payouts.iter().enumerate() {
+                if i > 0 {
+                    builder = builder.linear(*start, payout - offer_release);
+                }
+                let end = range_payouts
+                    .get(i + 1)
+                    .map_or(max_outcome, |(next, _)| next - 1);
+                if end > *start {
+                    builder = builder.flat(end);
+                }
+            }
+            let (payout_function, rounding_intervals) = builder.build()?;
+            (
+                ContractDescriptor::Numerical(NumericalDescriptor {
+                    payout_function,
+                    rounding_intervals,
+                    difference_params: n.difference_params.clone(),
+                    oracle_numeric_infos: n.oracle_numeric_infos.clone(),
+                }),
+                offer_release,
+                accept_release,
+            )
+        }
+    };
+
+    Ok((
+        contract_descriptor,
+        prev_offer_collateral - offer_release,
+        prev_accept_collateral - accept_release,
+    ))
+}
+
+/// Returns whether the given contract information are for the same events,
+/// with descriptors of the same kind.
+fn is_same_event(a: &ContractInfo, b: &ContractInfo) -> bool {
+    if a.oracle_announcements != b.oracle_announcements || a.threshold != b.threshold {
+        return false;
+    }
+
+    match (&a.contract_descriptor, &b.contract_descriptor) {
+        (ContractDescriptor::Enum(_), ContractDescriptor::Enum(_)) => true,
+        (ContractDescriptor::Numerical(a), ContractDescriptor::Numerical(b)) => {
+            a.oracle_numeric_infos.base == b.oracle_numeric_infos.base
+                && a.oracle_numeric_infos.nb_digits == b.oracle_numeric_infos.nb_digits
+                && is_same_difference_params(&a.difference_params, &b.difference_params)
+        }
+        _ => false,
+    }
+}
+
+fn is_same_difference_params(a: &Option<DifferenceParams>, b: &Option<DifferenceParams>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => {
+            a.max_error_exp == b.max_error_exp
+                && a.min_support_exp == b.min_support_exp
+                && a.maximize_coverage == b.maximize_coverage
+        }
+        _ => false,
+    }
+}
+
+fn get_offer_payout(payout: &Payout, same_roles: bool) -> u64 {
+    if same_roles {
+        payout.offer
+    } else {
+        payout.accept
+    }
+}
+
+/// Returns the sum of the payouts of the net offer party for each outcome of
+/// the given descriptor.
+fn sum_enum_payouts(infos: &[NettedInfo], first: &EnumDescriptor) -> Result<Vec<u64>, Error> {
+    let mut sums = vec![0; first.outcome_payouts.len()];
+    for (info, _, same_roles) in infos {
+        let outcome_payouts = match &info.contract_descriptor {
+            ContractDescriptor::Enum(e) if e.outcome_payouts.len() == sums.len() => {
+                &e.outcome_payouts
+            }
+            _ => {
+                return Err(Error::InvalidParameters(
+                    "Netted contracts must have payouts for the same outcomes".to_string(),
+                ))
+            }
+        };
+        for (sum, outcome) in sums.iter_mut().zip(first.outcome_payouts.iter()) {
+            let payout = outcome_payouts
+                .iter()
+                .find(|x| x.outcome == outcome.outcome)
+                .ok_or_else(|| {
+                    Error::InvalidParameters(format!(
+                        "Netted contract has no payout for outcome {}",
+                        outcome.outcome
+                    ))
+                })?;
+            *sum += get_offer_payout(&payout.payout, *same_roles);
+        }
+    }
+
+    Ok(sums)
+}
+
+/// Returns the outcomes from which the sum of the payouts of the net offer
+/// party changes, along with the sum of the payouts from each of them.
+fn sum_numerical_payouts(infos: &[NettedInfo]) -> Result<Vec<(u64, u64)>, Error> {
+    let mut range_payouts = Vec::with_capacity(infos.len());
+    for (info, total_collateral, same_roles) in infos {
+        let ranges = match &info.contract_descriptor {
+            ContractDescriptor::Numerical(n) => n.get_range_payouts(*total_collateral)?,
+            ContractDescriptor::Enum(_) => {
+                return Err(Error::InvalidParameters(
+                    "Netted contracts must all be numerical".to_string(),
+                ))
+            }
+        };
+        if ranges.first().map(|r| r.start) != Some(0) {
+            return Err(Error::InvalidParameters(
+                "Netted contracts must have payouts for all outcomes".to_string(),
+            ));
+        }
+        range_payouts.push(
+            ranges
+                .into_iter()
+                .map(|r| (r.start as u64, get_offer_payout(&r.payout, *same_roles)))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let mut starts = range_payouts
+        .iter()
+        .flat_map(|r| r.iter().map(|(start, _)| *start))
+        .collect::<Vec<_>>();
+    starts.sort_unstable();
+    starts.dedup();
+
+    let mut sums: Vec<(u64, u64)> = Vec::with_capacity(starts.len());
+    for start in starts {
+        let payout = range_payouts
+            .iter()
+            .map(|r| r[r.partition_point(|(s, _)| *s <= start) - 1].1)
+            .sum();
+        if sums.last().map(|(_, p)| *p) != Some(payout) {
+            sums.push((start, payout));
+        }
+    }
+
+    Ok(sums)
+}
+
+fn get_max_outcome(descriptor: &NumericalDescriptor) -> Result<u64, Error> {
+    let info = &descriptor.oracle_numeric_infos;
+    (info.base as u64)
+        .checked_pow(info.get_min_nb_digits() as u32)
+        .map(|x| x - 1)
+        .ok_or_else(|| Error::InvalidParameters("Could not compute max value".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dlc_trie::OracleNumericInfo;
+
+    fn enum_info(offer_payouts: &[u64], total_collateral: u64) -> ContractInfo {
+        ContractInfo {
+            contract_descriptor: ContractDescriptor::Enum(EnumDescriptor {
+                outcome_payouts: ["a", "b", "c"]
+                    .iter()
+                    .zip(offer_payouts.iter())
+                    .map(|(outcome, offer)| EnumerationPayout {
+                        outcome: outcome.to_string(),
+                        payout: Payout {
+                            offer: *offer,
+                            accept: total_collateral - offer,
+                        },
+                    })
+                    .collect(),
+            }),
+            oracle_announcements: Vec::new(),
+            threshold: 1,
+        }
+    }
+
+    fn numerical_info(lower: u64, upper: u64, total_collateral: u64) -> ContractInfo {
+        let (payout_function, rounding_intervals) =
+            PayoutCurveBuilder::new(total_collateral, 1023, 0)
+                .flat(lower)
+                .linear(upper, total_collateral)
+                .flat(1023)
+                .build()
+                .unwrap();
+        ContractInfo {
+            contract_descriptor: ContractDescriptor::Numerical(NumericalDescriptor {
+                payout_function,
+                rounding_intervals,
+                difference_params: None,
+                oracle_numeric_infos: OracleNumericInfo {
+                    base: 2,
+                    nb_digits: vec![10],
+                },
+            }),
+            oracle_announcements: Vec::new(),
+            threshold: 1,
+        }
+    }
+
+    fn offer_payouts(descriptor: &ContractDescriptor, total_collateral: u64) -> Vec<u64> {
+        match descriptor {
+            ContractDescriptor::Enum(e) => e.get_payouts().iter().map(|p| p.offer).collect(),
+            ContractDescriptor::Numerical(n) => n
+                .get_range_payouts(total_collateral)
+                .unwrap()
+                .iter()
+                .flat_map(|r| std::iter::repeat(r.payout.offer).take(r.count))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn opposite_enum_positions_release_collateral() {
+        // The roles of the parties are swapped in the second contract, in
+        // which the counter party receives the total collateral on "a".
+        let long = enum_info(&[1000, 0, 500], 1000);
+        let short = enum_info(&[600, 0, 0], 600);
+        let infos = [(&long, 1000, true), (&short, 600, false)];
+
+        let (descriptor, offer_collateral, accept_collateral) =
+            get_net_contract_descriptor(&infos, 500 + 300, 500 + 300).unwrap();
+
+        // Offer party payouts sum to [1000, 600, 1100], so 600 is released to
+        // it, while the accept party always receives at least 500.
+        assert_eq!(200, offer_collateral);
+        assert_eq!(300, accept_collateral);
+        assert_eq!(
+            vec![400, 0, 500],
+            offer_payouts(&descriptor, offer_collateral + accept_collateral)
+        );
+    }
+
+    #[test]
+    fn numerical_payouts_are_summed_for_all_outcomes() {
+        let first = numerical_info(100, 300, 1000);
+        let second = numerical_info(200, 600, 2000);
+        let infos = [(&first, 1000, true), (&second, 2000, false)];
+        let expected = offer_payouts(&first.contract_descriptor, 1000)
+            .iter()
+            .zip(offer_payouts(&second.contract_descriptor, 2000).iter())
+            .map(|(a, b)| a + 2000 - b)
+            .collect::<Vec<_>>();
+
+        let (descriptor, offer_collateral, accept_collateral) =
+            get_net_contract_descriptor(&infos, 500 + 1000, 500 + 1000).unwrap();
+
+        let offer_release = *expected.iter().min().unwrap();
+        assert_eq!(1000, offer_release);
+        assert_eq!(500, offer_collateral);
+        assert_eq!(1000, accept_collateral);
+        let payouts = offer_payouts(&descriptor, offer_collateral + accept_collateral);
+        assert_eq!(1024, payouts.len());
+        for (outcome, (payout, expected)) in payouts.iter().zip(expected.iter()).enumerate() {
+            assert_eq!(
+                expected - offer_release,
+                *payout,
+                "Invalid payout for outcome {}",
+                outcome
+            );
+        }
+    }
+
+    #[test]
+    fn fully_offsetting_contracts_cannot_be_netted() {
+        let long = enum_info(&[1000, 0, 500], 1000);
+        let infos = [(&long, 1000, true), (&long, 1000, false)];
+
+        get_net_contract_descriptor(&infos, 1000, 1000)
+            .expect_err("Offsetting contracts should error.");
+    }
+}
@@ -0,0 +1,368 @@
+//! #OfferBuilder
+//! Helpers to build a [`ContractInput`] from reusable contract templates,
+//! validating the consistency of the collateral and fees before offering it.
+
+use crate::contract::contract_input::{ContractInput, ContractInputInfo, OracleInput};
+use crate::contract::enum_descriptor::EnumDescriptor;
+use crate::contract::numerical_descriptor::NumericalDescriptor;
+use crate::contract::ContractDescriptor;
+use crate::error::Error;
+use crate::estimate::OperationEstimate;
+use crate::payout_curve::PayoutCurveBuilder;
+use dlc::{EnumerationPayout, FeeSplit, Payout};
+use dlc_trie::OracleNumericInfo;
+use secp256k1_zkp::XOnlyPublicKey;
+
+/// Reusable description of the payouts of a contract, independent of its
+/// collateral and oracles.
+#[derive(Clone, Debug)]
+pub enum ContractTemplate {
+    /// A contract on an event with two outcomes, paying the total collateral
+    /// to the offer party if `offer_wins_outcome` is attested and to the
+    /// accept party if `accept_wins_outcome` is.
+    Binary {
+        /// The outcome for which the offer party receives the collateral.
+        offer_wins_outcome: String,
+        /// The outcome for which the accept party receives the collateral.
+        accept_wins_outcome: String,
+    },
+    /// A contract on an event with enumerated outcomes.
+    Enumerated {
+        /// The outcomes of the event, with the payout of the offer party for
+        /// each of them. The accept party receives the rest of the collateral.
+        offer_payouts: Vec<(String, u64)>,
+    },
+    /// A contract on a numerical event paying nothing to the offer party for
+    /// outcomes up to `lower_outcome`, the total collateral for outcomes from
+    /// `upper_outcome`, and a payout linearly increasing between them.
+    NumericalRange {
+        /// The base in which the oracles decompose the outcome.
+        base: usize,
+        /// The number of digits used by the oracles to attest the outcome.
+        nb_digits: usize,
+        /// The outcome from which the payout of the offer party increases.
+        lower_outcome: u64,
+        /// The outcome from which the offer party receives the total
+        /// collateral.
+        upper_outcome: u64,
+        /// The rounding modulus applied to the payouts.
+        rounding_mod: u64,
+    },
+}
+
+impl ContractTemplate {
+    /// Returns the contract descriptor of the template for the given total
+    /// collateral and number of oracles.
+    pub fn get_contract_descriptor(
+        &self,
+        total_collateral: u64,
+        nb_oracles: usize,
+    ) -> Result<ContractDescriptor, Error> {
+        match self {
+            ContractTemplate::Binary {
+                offer_wins_outcome,
+                accept_wins_outcome,
+            } => {
+                if offer_wins_outcome == accept_wins_outcome {
+                    return Err(Error::InvalidParameters(
+                        "Binary contract outcomes must be different.".to_string(),
+                    ));
+                }
+                get_enum_descriptor(
+                    &[
+                        (offer_wins_outcome.clone(), total_collateral),
+                        (accept_wins_outcome.clone(), 0),
+                    ],
+                    total_collateral,
+                )
+            }
+            ContractTemplate::Enumerated { offer_payouts } => {
+                get_enum_descriptor(offer_payouts, total_collateral)
+            }
+            ContractTemplate::NumericalRange {
+                base,
+                nb_digits,
+                lower_outcome,
+                upper_outcome,
+                rounding_mod,
+            } => {
+                let max_outcome = (*base as u64)
+                    .checked_pow(*nb_digits as u32)
+                    .filter(|_| *base > 1)
+                    .ok_or_else(|| {
+                        Error::InvalidParameters(format!(
+                            "Invalid numerical event with base {} and {} digits.",
+                            base, nb_digits
+                        ))
+                    })?
+                    - 1;
+                let mut builder = PayoutCurveBuilder::new(total_collateral, max_outcome, 0);
+                if *lower_outcome > 0 {
+                    builder = builder.flat(*lower_outcome);
+                }
+                builder = builder
+                    .rounding_mod(*rounding_mod)
+                    .linear(*upper_outcome, total_collateral);
+                if *upper_outcome < max_outcome {
+                    builder = builder.flat(max_outcome);
+                }
+                let (payout_function, rounding_intervals) = builder.build()?;
+
+                Ok(ContractDescriptor::Numerical(NumericalDescriptor {
+                    payout_function,
+                    rounding_intervals,
+                    difference_params: None,
+                    oracle_numeric_infos: OracleNumericInfo {
+                        base: *base,
+                        nb_digits: vec![*nb_digits; nb_oracles],
+                    },
+                }))
+            }
+        }
+    }
+}
+
+fn get_enum_descriptor(
+    offer_payouts: &[(String, u64)],
+    total_collateral: u64,
+) -> Result<ContractDescriptor, Error> {
+    if offer_payouts.is_empty() {
+        return Err(Error::InvalidParameters(
+            "Enumerated contract must have at least one outcome.".to_string(),
+        ));
+    }
+
+    let outcome_payouts = offer_payouts
+        .iter()
+        .map(|(outcome, offer)| {
+            if *offer > total_collateral {
+                return Err(Error::InvalidParameters(format!(
+                    "Payout {} for outcome {} is greater than total collateral {}.",
+                    offer, outcome, total_collateral
+                )));
+            }
+            Ok(EnumerationPayout {
+                outcome: outcome.clone(),
+                payout: Payout {
+                    offer: *offer,
+                    accept: total_collateral - offer,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(ContractDescriptor::Enum(EnumDescriptor { outcome_payouts }))
+}
+
+/// Builds a [`ContractInput`] from a [`ContractTemplate`].
+#[derive(Clone, Debug)]
+pub struct OfferBuilder {
+    template: ContractTemplate,
+    oracles: Option<OracleInput>,
+    offer_collateral: u64,
+    accept_collateral: u64,
+    fee_rate: u64,
+    anchor_outputs: bool,
+    fee_split: FeeSplit,
+    taproot: bool,
+}
+
+impl OfferBuilder {
+    /// Create a builder for a contract following the given template.
+    pub fn new(template: ContractTemplate) -> Self {
+        OfferBuilder {
+            template,
+            oracles: None,
+            offer_collateral: 0,
+            accept_collateral: 0,
+            fee_rate: 1,
+            anchor_outputs: false,
+            fee_split: FeeSplit::Equal,
+            taproot: false,
+        }
+    }
+
+    /// Use the event with the given id of a single oracle.
+    pub fn oracle(self, public_key: XOnlyPublicKey, event_id: &str) -> Self {
+        self.oracles(OracleInput {
+            public_keys: vec![public_key],
+            event_id: event_id.to_string(),
+            threshold: 1,
+            difference_params: None,
+        })
+    }
+
+    /// Use the given oracles.
+    pub fn oracles(mut self, oracles: OracleInput) -> Self {
+        self.oracles = Some(oracles);
+        self
+    }
+
+    /// Set the collateral of each party.
+    pub fn collateral(mut self, offer_collateral: u64, accept_collateral: u64) -> Self {
+        self.offer_collateral = offer_collateral;
+        self.accept_collateral = accept_collateral;
+        self
+    }
+
+    /// Set the fee rate, in satoshis per virtual byte, of the transactions.
+    pub fn fee_rate(mut self, fee_rate: u64) -> Self {
+        self.fee_rate = fee_rate;
+        self
+    }
+
+    /// Set whether to add anchor outputs to the CETs.
+    pub fn anchor_outputs(mut self, anchor_outputs: bool) -> Self {
+        self.anchor_outputs = anchor_outputs;
+        self
+    }
+
+    /// Set how the on-chain fees are split between the parties.
+    pub fn fee_split(mut self, fee_split: FeeSplit) -> Self {
+        self.fee_split = fee_split;
+        self
+    }
+
+    /// Set whether to fund the contract with a taproot output.
+    pub fn taproot(mut self, taproot: bool) -> Self {
+        self.taproot = taproot;
+        self
+    }
+
+    /// Returns the contract input, after checking that the collateral of the
+    /// contract is consistent with its payouts and fees.
+    pub fn build(&self) -> Result<ContractInput, Error> {
+        let oracles = self
+            .oracles
+            .clone()
+            .ok_or_else(|| Error::InvalidParameters("No oracle was specified.".to_string()))?;
+        let total_collateral = self
+            .offer_collateral
+            .checked_add(self.accept_collateral)
+            .filter(|x| *x > 0)
+            .ok_or_else(|| {
+                Error::InvalidParameters("Total collateral must be strictly positive.".to_string())
+            })?;
+
+        let (fund_fee, cet_fee) = dlc::estimate_party_fees(self.fee_rate, 1)
+            .map_err(|_| Error::InvalidParameters("Fee rate too high.".to_string()))?;
+        if 2 * (fund_fee + cet_fee) >= total_collateral {
+            return Err(Error::InvalidParameters(format!(
+                "On-chain fees of about {} exceed the total collateral {}.",
+                2 * (fund_fee + cet_fee),
+                total_collateral
+            )));
+        }
+
+        let contract_descriptor = self
+            .template
+            .get_contract_descriptor(total_collateral, oracles.public_keys.len())?;
+        let contract_input = ContractInput {
+            offer_collateral: self.offer_collateral,
+            accept_collateral: self.accept_collateral,
+            fee_rate: self.fee_rate,
+            contract_infos: vec![ContractInputInfo {
+                contract_descriptor,
+                oracles,
+            }],
+            anchor_outputs: self.anchor_outputs,
+            fee_split: self.fee_split,
+            taproot: self.taproot,
+        };
+        contract_input.validate()?;
+
+        Ok(contract_input)
+    }
+}
+
+/// The result of a dry run of an offer, see
+/// [`crate::manager::Manager::dry_run_offer`].
+#[derive(Clone, Debug)]
+pub struct OfferDryRun {
+    /// The contract input that would be offered.
+    pub contract_input: ContractInput,
+    /// The expected virtual size of the funding transaction, assuming each
+    /// party funds the contract using a single P2WPKH input.
+    pub funding_tx_vsize: usize,
+    /// The expected costs of the offer for the local party.
+    pub estimate: OperationEstimate,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use secp256k1_zkp::{Secp256k1, SecretKey};
+
+    fn oracle_public_key() -> XOnlyPublicKey {
+        SecretKey::from_slice(&[1; 32])
+            .unwrap()
+            .x_only_public_key(&Secp256k1::new())
+            .0
+    }
+
+    #[test]
+    fn binary_template_builds_valid_input() {
+        let input = OfferBuilder::new(ContractTemplate::Binary {
+            offer_wins_outcome: "yes".to_string(),
+            accept_wins_outcome: "no".to_string(),
+        })
+        .oracle(oracle_public_key(), "event")
+        .collateral(100000, 50000)
+        .fee_rate(2)
+        .build()
+        .expect("to be able to build the input");
+
+        match &input.contract_infos[0].contract_descriptor {
+            ContractDescriptor::Enum(e) => {
+                assert_eq!(150000, e.outcome_payouts[0].payout.offer);
+                assert_eq!(150000, e.outcome_payouts[1].payout.accept);
+            }
+            _ => panic!("Expected an enum descriptor"),
+        }
+    }
+
+    #[test]
+    fn numerical_range_template_covers_all_outcomes() {
+        let input = OfferBuilder::new(ContractTemplate::NumericalRange {
+            base: 2,
+            nb_digits: 10,
+            lower_outcome: 100,
+            upper_outcome: 500,
+            rounding_mod: 100,
+        })
+        .oracle(oracle_public_key(), "event")
+        .collateral(100000, 100000)
+        .build()
+        .expect("to be able to build the input");
+
+        match &input.contract_infos[0].contract_descriptor {
+            ContractDescriptor::Numerical(n) => {
+                n.validate(1023).expect("the descriptor to be valid");
+                assert_eq!(2, n.rounding_intervals.intervals.len());
+            }
+            _ => panic!("Expected a numerical descriptor"),
+        }
+    }
+
+    #[test]
+    fn inconsistent_offers_are_rejected() {
+        let template = ContractTemplate::Enumerated {
+            offer_payouts: vec![("a".to_string(), 1000), ("b".to_string(), 3000)],
+        };
+        OfferBuilder::new(template.clone())
+            .collateral(1000, 1000)
+            .build()
+            .expect_err("Missing oracle should error.");
+        OfferBuilder::new(template.clone())
+            .oracle(oracle_public_key(), "event")
+            .collateral(1000, 1000)
+            .build()
+            .expect_err("Payout greater than total collateral should error.");
+        OfferBuilder::new(template)
+            .oracle(oracle_public_key(), "event")
+            .collateral(2000, 2000)
+            .fee_rate(100)
+            .build()
+            .expect_err("Fees greater than the collateral should error.");
+    }
+}
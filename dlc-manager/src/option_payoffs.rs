@@ -0,0 +1,247 @@
+//! #Option payoffs
+//! Helpers building the payout functions of common option strategies on top
+//! of [`PayoutCurveBuilder`]. Payoffs are linear in the outcome:
+//! `contract_size` is the number of satoshis paid to the buyer of the option
+//! per unit of outcome it is in the money, and the payout of the buyer is
+//! capped by the total collateral of the contract. Contracts whose payoff is
+//! inverse in the outcome can be described using
+//! [`PayoutCurveBuilder::hyperbolic`].
+
+use crate::error::Error;
+use crate::payout_curve::{PayoutCurveBuilder, PayoutFunction, RoundingIntervals};
+
+/// The position taken in an option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// Buying the option.
+    Long,
+    /// Selling the option.
+    Short,
+}
+
+/// The type of an option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionKind {
+    /// Pays when the outcome is above the strike.
+    Call,
+    /// Pays when the outcome is below the strike.
+    Put,
+}
+
+/// Parameters common to all option payoffs.
+#[derive(Clone, Debug)]
+pub struct OptionTerms {
+    /// The total collateral of the contract.
+    pub total_collateral: u64,
+    /// The maximum outcome that can be attested by the oracle.
+    pub max_outcome: u64,
+    /// The side taken by the offer party of the contract, whose payout is the
+    /// one described by payout functions.
+    pub offer_side: Side,
+    /// The rounding modulus applied to the payouts varying with the outcome.
+    pub rounding_mod: u64,
+}
+
+/// Returns the payout function of a call option with the given strike, and
+/// the rounding intervals to use with it.
+pub fn call(
+    strike: u64,
+    contract_size: u64,
+    terms: &OptionTerms,
+) -> Result<(PayoutFunction, RoundingIntervals), Error> {
+    vertical_spread(
+        OptionKind::Call,
+        strike,
+        terms.max_outcome,
+        contract_size,
+        terms,
+    )
+}
+
+/// Returns the payout function of a put option with the given strike, and
+/// the rounding intervals to use with it.
+pub fn put(
+    strike: u64,
+    contract_size: u64,
+    terms: &OptionTerms,
+) -> Result<(PayoutFunction, RoundingIntervals), Error> {
+    vertical_spread(OptionKind::Put, 0, strike, contract_size, terms)
+}
+
+/// Returns the payout function of a covered call, and the rounding intervals
+/// to use with it. Contrary to [`call`], fails if the total collateral does
+/// not cover the payoff of the option for all outcomes.
+pub fn covered_call(
+    strike: u64,
+    contract_size: u64,
+    terms: &OptionTerms,
+) -> Result<(PayoutFunction, RoundingIntervals), Error> {
+    let max_payoff = (contract_size as u128) * (terms.max_outcome.saturating_sub(strike) as u128);
+    if max_payoff > terms.total_collateral as u128 {
+        return Err(Error::InvalidParameters(format!(
+            "Total collateral {} does not cover the maximum payoff {} of the call.",
+            terms.total_collateral, max_payoff
+        )));
+    }
+
+    call(strike, contract_size, terms)
+}
+
+/// Returns the payout function of a vertical spread, and the rounding
+/// intervals to use with it. A call spread is long a call with strike
+/// `lower_strike` and short a call with strike `upper_strike`, while a put
+/// spread is long a put with strike `upper_strike` and short a put with
+/// strike `lower_strike`.
+pub fn vertical_spread(
+    kind: OptionKind,
+    lower_strike: u64,
+    upper_strike: u64,
+    contract_size: u64,
+    terms: &OptionTerms,
+) -> Result<(PayoutFunction, RoundingIntervals), Error> {
+    if contract_size == 0 {
+        return Err(Error::InvalidParameters(
+            "Contract size must be strictly positive.".to_string(),
+        ));
+    }
+
+    if lower_strike >= upper_strike || upper_strike > terms.max_outcome {
+        return Err(Error::InvalidParameters(format!(
+            "Strikes {} and {} must be increasing and not greater than the maximum outcome {}.",
+            lower_strike, upper_strike, terms.max_outcome
+        )));
+    }
+
+    let total_collateral = terms.total_collateral;
+    let payoff = |outcome: u64| -> u64 {
+        let clamped = outcome.clamp(lower_strike, upper_strike);
+        let in_the_money = match kind {
+            OptionKind::Call => clamped - lower_strike,
+            OptionKind::Put => upper_strike - clamped,
+        };
+        u128::min(
+            (contract_size as u128) * (in_the_money as u128),
+            total_collateral as u128,
+        ) as u64
+    };
+
+    // The payoff is linear between its kinks, which are at the strikes and
+    // where it reaches the total collateral. As the latter is usually not an
+    // integer, the outcomes surrounding it are used instead, so that payouts
+    // are exact for all outcomes.
+    let cap_floor = total_collateral / contract_size;
+    let cap_ceil = cap_floor + u64::from(total_collateral % contract_size != 0);
+    let mut outcomes = vec![0, lower_strike, upper_strike, terms.max_outcome];
+    match kind {
+        OptionKind::Call => {
+            outcomes.push(lower_strike.saturating_add(cap_floor));
+            outcomes.push(lower_strike.saturating_add(cap_ceil));
+        }
+        OptionKind::Put => {
+            outcomes.push(upper_strike.saturating_sub(cap_floor));
+            outcomes.push(upper_strike.saturating_sub(cap_ceil));
+        }
+    }
+    outcomes.retain(|x| *x <= terms.max_outcome);
+    outcomes.sort_unstable();
+    outcomes.dedup();
+
+    let offer_payout = |outcome: u64| match terms.offer_side {
+        Side::Long => payoff(outcome),
+        Side::Short => total_collateral - payoff(outcome),
+    };
+
+    let mut builder = PayoutCurveBuilder::new(total_collateral, terms.max_outcome, offer_payout(0))
+        .rounding_mod(terms.rounding_mod);
+    for window in outcomes.windows(2) {
+        let payout = offer_payout(window[1]);
+        builder = if payout == offer_payout(window[0]) {
+            builder.flat(window[1])
+        } else {
+            builder.linear(window[1], payout)
+        };
+    }
+
+    builder.build()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TOTAL_COLLATERAL: u64 = 1000;
+
+    fn terms(offer_side: Side) -> OptionTerms {
+        OptionTerms {
+            total_collateral: TOTAL_COLLATERAL,
+            max_outcome: 200,
+            offer_side,
+            rounding_mod: 1,
+        }
+    }
+
+    fn offer_payouts(
+        (payout_function, rounding_intervals): (PayoutFunction, RoundingIntervals),
+    ) -> Vec<u64> {
+        payout_function
+            .to_range_payouts(TOTAL_COLLATERAL, &rounding_intervals)
+            .unwrap()
+            .iter()
+            .flat_map(|r| std::iter::repeat(r.payout.offer).take(r.count))
+            .collect()
+    }
+
+    #[test]
+    fn call_payouts_are_exact_test() {
+        let payouts = offer_payouts(call(100, 30, &terms(Side::Long)).unwrap());
+
+        assert_eq!(201, payouts.len());
+        for (outcome, payout) in payouts.iter().enumerate() {
+            let expected = u64::min(30 * (outcome as u64).saturating_sub(100), TOTAL_COLLATERAL);
+            assert_eq!(expected, *payout, "Invalid payout for outcome {}", outcome);
+        }
+    }
+
+    #[test]
+    fn short_put_payouts_are_exact_test() {
+        let payouts = offer_payouts(put(100, 30, &terms(Side::Short)).unwrap());
+
+        assert_eq!(201, payouts.len());
+        for (outcome, payout) in payouts.iter().enumerate() {
+            let long = u64::min(30 * 100u64.saturating_sub(outcome as u64), TOTAL_COLLATERAL);
+            assert_eq!(
+                TOTAL_COLLATERAL - long,
+                *payout,
+                "Invalid payout for outcome {}",
+                outcome
+            );
+        }
+    }
+
+    #[test]
+    fn vertical_spread_payouts_are_exact_test() {
+        let payouts = offer_payouts(
+            vertical_spread(OptionKind::Call, 50, 150, 7, &terms(Side::Long)).unwrap(),
+        );
+
+        assert_eq!(201, payouts.len());
+        for (outcome, payout) in payouts.iter().enumerate() {
+            let expected = 7 * ((outcome as u64).clamp(50, 150) - 50);
+            assert_eq!(expected, *payout, "Invalid payout for outcome {}", outcome);
+        }
+    }
+
+    #[test]
+    fn covered_call_requires_enough_collateral_test() {
+        covered_call(100, 10, &terms(Side::Short)).expect("collateral to cover the call");
+        covered_call(100, 11, &terms(Side::Short)).expect_err("collateral not to cover the call");
+    }
+
+    #[test]
+    fn invalid_strikes_error_test() {
+        vertical_spread(OptionKind::Put, 150, 50, 7, &terms(Side::Long))
+            .expect_err("Decreasing strikes should error.");
+        call(300, 7, &terms(Side::Long)).expect_err("Strike above max outcome should error.");
+        call(100, 0, &terms(Side::Long)).expect_err("Zero contract size should error.");
+    }
+}
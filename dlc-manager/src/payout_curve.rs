@@ -48,6 +48,12 @@ impl PayoutFunction {
             ));
         }
 
+        for piece in &self.payout_function_pieces {
+            if let PayoutFunctionPiece::HyperbolaPayoutCurvePiece(h) = piece {
+                h.validate()?;
+            }
+        }
+
         let covers = {
             let first = self
                 .payout_function_pieces
@@ -107,6 +113,101 @@ impl PayoutFunction {
         }
         Ok(range_payouts)
     }
+
+    /// Returns a step function, together with the rounding intervals to use
+    /// with it, whose range payouts are those of this function compressed
+    /// using [`compress_range_payouts`]. For every outcome, the payout of the
+    /// returned function differs by at most `epsilon` from the one obtained
+    /// with this function and the given rounding intervals, while requiring
+    /// fewer CETs to be created and signed.
+    pub fn compress(
+        &self,
+        total_collateral: u64,
+        rounding_intervals: &RoundingIntervals,
+        epsilon: u64,
+    ) -> Result<(PayoutFunction, RoundingIntervals), Error> {
+        let range_payouts = compress_range_payouts(
+            &self.to_range_payouts(total_collateral, rounding_intervals)?,
+            epsilon,
+        );
+
+        let mut points = Vec::new();
+        for range in &range_payouts {
+            let start = range.start as u64;
+            let end = start + (range.count as u64 - 1);
+            points.push(PayoutPoint {
+                event_outcome: start,
+                outcome_payout: range.payout.offer,
+                extra_precision: 0,
+            });
+            if end > start {
+                points.push(PayoutPoint {
+                    event_outcome: end,
+                    outcome_payout: range.payout.offer,
+                    extra_precision: 0,
+                });
+            }
+        }
+
+        let pieces = points
+            .windows(2)
+            .map(|w| {
+                Ok(PayoutFunctionPiece::PolynomialPayoutCurvePiece(
+                    PolynomialPayoutCurvePiece::new(w.to_vec())?,
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok((
+            PayoutFunction::new(pieces)?,
+            RoundingIntervals {
+                intervals: vec![RoundingInterval {
+                    begin_interval: 0,
+                    rounding_mod: 1,
+                }],
+            },
+        ))
+    }
+}
+
+/// Merges consecutive range payouts whose offer payouts all lie within an
+/// interval of width `2 * epsilon`, assigning to each merged range the middle
+/// of that interval. As every original payout lies within `epsilon` of the
+/// payout of the range it was merged into, the payout error for any outcome is
+/// bounded by `epsilon`. Merging greedily yields the minimum number of ranges
+/// satisfying this bound.
+pub fn compress_range_payouts(range_payouts: &[RangePayout], epsilon: u64) -> Vec<RangePayout> {
+    let max_width = epsilon.saturating_mul(2);
+    let mut groups: Vec<(RangePayout, u64, u64)> = Vec::new();
+    for range in range_payouts {
+        let offer = range.payout.offer;
+        if let Some((group, min, max)) = groups.last_mut() {
+            let (new_min, new_max) = (u64::min(*min, offer), u64::max(*max, offer));
+            if new_max - new_min <= max_width {
+                group.count += range.count;
+                *min = new_min;
+                *max = new_max;
+                continue;
+            }
+        }
+        groups.push((range.clone(), offer, offer));
+    }
+
+    let mut res: Vec<RangePayout> = Vec::with_capacity(groups.len());
+    for (mut group, min, max) in groups {
+        let total_collateral = group.payout.offer + group.payout.accept;
+        let offer = min + (max - min) / 2;
+        group.payout = Payout {
+            offer,
+            accept: total_collateral - offer,
+        };
+        match res.last_mut() {
+            Some(last) if last.payout == group.payout => last.count += group.count,
+            _ => res.push(group),
+        }
+    }
+
+    res
 }
 
 /// A piece of a payout function.
@@ -433,31 +534,112 @@ impl HyperbolaPayoutCurvePiece {
         c: f64,
         d: f64,
     ) -> Result<Self, Error> {
-        if a * d == b * c {
-            Err(Error::InvalidParameters(
+        let piece = HyperbolaPayoutCurvePiece {
+            left_end_point,
+            right_end_point,
+            use_positive_piece,
+            translate_outcome: round_parameter(translate_outcome)?,
+            translate_payout: round_parameter(translate_payout)?,
+            a: round_parameter(a)?,
+            b: round_parameter(b)?,
+            c: round_parameter(c)?,
+            d: round_parameter(d)?,
+        };
+        piece.validate()?;
+        Ok(piece)
+    }
+
+    /// Create a new HyperbolaPayoutCurvePiece whose payout for an outcome `x`
+    /// is `numerator / x + translate_payout`, as used for inverse contracts
+    /// such as contracts for difference priced in satoshis. The payouts of
+    /// the end points are computed from the curve.
+    pub fn inverse(
+        left_outcome: u64,
+        right_outcome: u64,
+        numerator: f64,
+        translate_payout: f64,
+    ) -> Result<Self, Error> {
+        if left_outcome == 0 {
+            return Err(Error::InvalidParameters(
+                "Inverse curve is not defined for outcome 0".to_string(),
+            ));
+        }
+
+        let numerator = round_parameter(numerator)?;
+        let translate_payout = round_parameter(translate_payout)?;
+        let end_point = |outcome: u64| -> Result<PayoutPoint, Error> {
+            let payout = numerator / outcome as f64 + translate_payout;
+            if payout.is_sign_negative() || !payout.is_finite() {
+                return Err(Error::InvalidParameters(format!(
+                    "Inverse curve has payout {} for outcome {}",
+                    payout, outcome
+                )));
+            }
+            let outcome_payout = payout.floor();
+            Ok(PayoutPoint {
+                event_outcome: outcome,
+                outcome_payout: outcome_payout as u64,
+                extra_precision: ((payout - outcome_payout) * ((1 << 16) as f64)).floor() as u16,
+            })
+        };
+
+        HyperbolaPayoutCurvePiece::new(
+            end_point(left_outcome)?,
+            end_point(right_outcome)?,
+            true,
+            0.0,
+            translate_payout,
+            1.0,
+            0.0,
+            0.0,
+            numerator,
+        )
+    }
+
+    /// Validate that the parameters of the piece describe a valid hyperbola
+    /// and can be sent to the counter party without loss of precision.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.a * self.d == self.b * self.c {
+            return Err(Error::InvalidParameters(
                 "a * c cannot equal d * c".to_string(),
-            ))
-        } else if left_end_point.event_outcome >= right_end_point.event_outcome {
-            Err(Error::InvalidParameters(
+            ));
+        }
+
+        if self.left_end_point.event_outcome >= self.right_end_point.event_outcome {
+            return Err(Error::InvalidParameters(
                 "Left end point outcome must be strictly less than right end point outcome"
                     .to_string(),
-            ))
-        } else {
-            Ok(HyperbolaPayoutCurvePiece {
-                left_end_point,
-                right_end_point,
-                use_positive_piece,
-                translate_outcome,
-                translate_payout,
-                a,
-                b,
-                c,
-                d,
-            })
+            ));
+        }
+
+        let parameters = [
+            self.translate_outcome,
+            self.translate_payout,
+            self.a,
+            self.b,
+            self.c,
+            self.d,
+        ];
+        if parameters
+            .iter()
+            .any(|x| dlc_messages::ser_impls::round_f64(*x) != Some(*x))
+        {
+            return Err(Error::InvalidParameters(
+                "Hyperbola parameters must have at most 16 bits of fractional precision."
+                    .to_string(),
+            ));
         }
+
+        Ok(())
     }
 }
 
+/// Rounds a hyperbola parameter to the precision with which it is serialized.
+fn round_parameter(value: f64) -> Result<f64, Error> {
+    dlc_messages::ser_impls::round_f64(value)
+        .ok_or_else(|| Error::InvalidParameters(format!("Invalid hyperbola parameter {}", value)))
+}
+
 impl Evaluable for HyperbolaPayoutCurvePiece {
     fn evaluate(&self, outcome: u64) -> f64 {
         let outcome = outcome as f64;
@@ -567,6 +749,232 @@ impl RoundingIntervals {
     }
 }
 
+/// Composes a [`PayoutFunction`] piece by piece, together with the
+/// [`RoundingIntervals`] to use with it. The function starts at outcome zero
+/// and each piece starts where the previous one ends, so that the function is
+/// continuous by construction. Invalid pieces are reported by
+/// [`PayoutCurveBuilder::build`], indicating the offending piece, rather than
+/// when the payouts of the contract are computed.
+#[derive(Debug)]
+pub struct PayoutCurveBuilder {
+    total_collateral: u64,
+    max_outcome: u64,
+    cur_point: PayoutPoint,
+    rounding_mod: u64,
+    pieces: Vec<PayoutFunctionPiece>,
+    rounding_intervals: Vec<RoundingInterval>,
+    error: Option<Error>,
+}
+
+impl PayoutCurveBuilder {
+    /// Create a builder for a payout function covering the outcomes from 0 to
+    /// `max_outcome`, with payouts between 0 and `total_collateral`, and a
+    /// payout of `start_payout` for outcome 0.
+    pub fn new(total_collateral: u64, max_outcome: u64, start_payout: u64) -> Self {
+        let error = if start_payout > total_collateral {
+            Some(Error::InvalidParameters(format!(
+                "Start payout {} is greater than total collateral {}.",
+                start_payout, total_collateral
+            )))
+        } else {
+            None
+        };
+        PayoutCurveBuilder {
+            total_collateral,
+            max_outcome,
+            cur_point: PayoutPoint {
+                event_outcome: 0,
+                outcome_payout: start_payout,
+                extra_precision: 0,
+            },
+            rounding_mod: 1,
+            pieces: Vec::new(),
+            rounding_intervals: Vec::new(),
+            error,
+        }
+    }
+
+    /// Set the rounding modulus applied to the payouts of the pieces added
+    /// afterwards. Defaults to 1, meaning that payouts are not rounded.
+    pub fn rounding_mod(mut self, rounding_mod: u64) -> Self {
+        if rounding_mod == 0 && self.error.is_none() {
+            self.error = Some(Error::InvalidParameters(
+                "Rounding modulus must be strictly positive.".to_string(),
+            ));
+        }
+        self.rounding_mod = rounding_mod;
+        self
+    }
+
+    /// Add a piece keeping the payout at the end of the previous piece
+    /// constant up to `to_outcome`.
+    pub fn flat(self, to_outcome: u64) -> Self {
+        self.push_piece(to_outcome, false, |start| {
+            let end = PayoutPoint {
+                event_outcome: to_outcome,
+                ..start.clone()
+            };
+            PolynomialPayoutCurvePiece::new(vec![start.clone(), end])
+                .map(PayoutFunctionPiece::PolynomialPayoutCurvePiece)
+        })
+    }
+
+    /// Add a piece linearly interpolating the payout from the end of the
+    /// previous piece to `to_payout` at `to_outcome`.
+    pub fn linear(self, to_outcome: u64, to_payout: u64) -> Self {
+        self.push_piece(to_outcome, true, |start| {
+            let end = PayoutPoint {
+                event_outcome: to_outcome,
+                outcome_payout: to_payout,
+                extra_precision: 0,
+            };
+            PolynomialPayoutCurvePiece::new(vec![start.clone(), end])
+                .map(PayoutFunctionPiece::PolynomialPayoutCurvePiece)
+        })
+    }
+
+    /// Add a linear piece reaching a payout of `cap` at `cap_outcome`, followed
+    /// by a flat piece keeping the payout at `cap` up to `to_outcome`.
+    pub fn capped(self, cap_outcome: u64, cap: u64, to_outcome: u64) -> Self {
+        self.linear(cap_outcome, cap).flat(to_outcome)
+    }
+
+    /// Add a piece whose payout for an outcome `x` is
+    /// `numerator / x + translate_payout` up to `to_outcome` (see
+    /// [`HyperbolaPayoutCurvePiece::inverse`]). The curve must be within one
+    /// unit of the payout at the end of the previous piece at its start.
+    pub fn hyperbolic(self, to_outcome: u64, numerator: f64, translate_payout: f64) -> Self {
+        self.push_piece(to_outcome, true, |start| {
+            let mut hyperbola = HyperbolaPayoutCurvePiece::inverse(
+                start.event_outcome,
+                to_outcome,
+                numerator,
+                translate_payout,
+            )?;
+            let curve_start = hyperbola.left_end_point.get_outcome_payout();
+            if (curve_start - start.get_outcome_payout()).abs() > 1.0 {
+                return Err(Error::InvalidParameters(format!(
+                    "Curve starts at payout {} while the previous piece ends at payout {}.",
+                    curve_start,
+                    start.get_outcome_payout()
+                )));
+            }
+            hyperbola.left_end_point = start.clone();
+            Ok(PayoutFunctionPiece::HyperbolaPayoutCurvePiece(hyperbola))
+        })
+    }
+
+    /// Return the payout function and the rounding intervals to use with it,
+    /// or the first error encountered while adding pieces.
+    pub fn build(self) -> Result<(PayoutFunction, RoundingIntervals), Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        if self.cur_point.event_outcome != self.max_outcome {
+            return Err(Error::InvalidParameters(format!(
+                "Payout curve ends at outcome {} instead of {}.",
+                self.cur_point.event_outcome, self.max_outcome
+            )));
+        }
+
+        let mut intervals = self.rounding_intervals;
+        if intervals.first().map_or(true, |x| x.begin_interval != 0) {
+            intervals.insert(
+                0,
+                RoundingInterval {
+                    begin_interval: 0,
+                    rounding_mod: 1,
+                },
+            );
+        }
+        let rounding_intervals = RoundingIntervals { intervals };
+        rounding_intervals.validate()?;
+
+        let payout_function = PayoutFunction::new(self.pieces)?;
+        payout_function.validate(self.max_outcome)?;
+
+        Ok((payout_function, rounding_intervals))
+    }
+
+    fn push_piece<F>(mut self, to_outcome: u64, is_rounded: bool, make_piece: F) -> Self
+    where
+        F: FnOnce(&PayoutPoint) -> Result<PayoutFunctionPiece, Error>,
+    {
+        if self.error.is_some() {
+            return self;
+        }
+
+        match self.make_piece(to_outcome, make_piece) {
+            Ok(piece) => {
+                let start_outcome = self.cur_point.event_outcome;
+                let cur_mod = self.rounding_intervals.last().map_or(1, |x| x.rounding_mod);
+                if is_rounded && cur_mod != self.rounding_mod {
+                    // The start outcome belongs to the previous piece, except
+                    // for the first one.
+                    let begin_interval = if self.pieces.is_empty() {
+                        0
+                    } else {
+                        start_outcome + 1
+                    };
+                    self.rounding_intervals.push(RoundingInterval {
+                        begin_interval,
+                        rounding_mod: self.rounding_mod,
+                    });
+                }
+                self.cur_point = piece.get_last_point().clone();
+                self.pieces.push(piece);
+            }
+            Err(e) => {
+                let message = match e {
+                    Error::InvalidParameters(m) => m,
+                    e => e.to_string(),
+                };
+                self.error = Some(Error::InvalidParameters(format!(
+                    "Invalid payout curve piece {} from outcome {} to {}: {}",
+                    self.pieces.len(),
+                    self.cur_point.event_outcome,
+                    to_outcome,
+                    message
+                )));
+            }
+        }
+
+        self
+    }
+
+    fn make_piece<F>(&self, to_outcome: u64, make_piece: F) -> Result<PayoutFunctionPiece, Error>
+    where
+        F: FnOnce(&PayoutPoint) -> Result<PayoutFunctionPiece, Error>,
+    {
+        if to_outcome <= self.cur_point.event_outcome {
+            return Err(Error::InvalidParameters(
+                "Pieces must cover strictly increasing outcomes.".to_string(),
+            ));
+        }
+
+        if to_outcome > self.max_outcome {
+            return Err(Error::InvalidParameters(format!(
+                "Outcome is greater than the maximum outcome {}.",
+                self.max_outcome
+            )));
+        }
+
+        let piece = make_piece(&self.cur_point)?;
+        // Pieces are either linear or monotonic hyperbolas, so their payouts
+        // are bounded by the ones of their end points.
+        let end_payout = piece.get_last_point().get_outcome_payout();
+        if end_payout > self.total_collateral as f64 {
+            return Err(Error::InvalidParameters(format!(
+                "Payout {} is greater than total collateral {}.",
+                end_payout, self.total_collateral
+            )));
+        }
+
+        Ok(piece)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -954,6 +1362,135 @@ mod test {
         );
     }
 
+    fn get_linear_payout_function() -> PayoutFunction {
+        PayoutFunction::new(vec![
+            PayoutFunctionPiece::PolynomialPayoutCurvePiece(
+                PolynomialPayoutCurvePiece::new(vec![
+                    PayoutPoint {
+                        event_outcome: 0,
+                        outcome_payout: 0,
+                        extra_precision: 0,
+                    },
+                    PayoutPoint {
+                        event_outcome: 1000,
+                        outcome_payout: 0,
+                        extra_precision: 0,
+                    },
+                ])
+                .unwrap(),
+            ),
+            PayoutFunctionPiece::PolynomialPayoutCurvePiece(
+                PolynomialPayoutCurvePiece::new(vec![
+                    PayoutPoint {
+                        event_outcome: 1000,
+                        outcome_payout: 0,
+                        extra_precision: 0,
+                    },
+                    PayoutPoint {
+                        event_outcome: 2000,
+                        outcome_payout: 100000,
+                        extra_precision: 0,
+                    },
+                ])
+                .unwrap(),
+            ),
+            PayoutFunctionPiece::PolynomialPayoutCurvePiece(
+                PolynomialPayoutCurvePiece::new(vec![
+                    PayoutPoint {
+                        event_outcome: 2000,
+                        outcome_payout: 100000,
+                        extra_precision: 0,
+                    },
+                    PayoutPoint {
+                        event_outcome: 4095,
+                        outcome_payout: 100000,
+                        extra_precision: 0,
+                    },
+                ])
+                .unwrap(),
+            ),
+        ])
+        .unwrap()
+    }
+
+    fn payout_for_outcome(range_payouts: &[RangePayout], outcome: usize) -> u64 {
+        range_payouts
+            .iter()
+            .find(|r| r.start <= outcome && outcome < r.start + r.count)
+            .expect("ranges to cover all outcomes")
+            .payout
+            .offer
+    }
+
+    #[test]
+    fn compressed_payout_function_bounds_payout_error() {
+        let payout_function = get_linear_payout_function();
+        let rounding_intervals = RoundingIntervals {
+            intervals: vec![RoundingInterval {
+                begin_interval: 0,
+                rounding_mod: 10,
+            }],
+        };
+        let original = payout_function
+            .to_range_payouts(100000, &rounding_intervals)
+            .unwrap();
+
+        for epsilon in [0, 50, 500, 5000] {
+            let (compressed_function, compressed_rounding) = payout_function
+                .compress(100000, &rounding_intervals, epsilon)
+                .expect("to be able to compress the payout function");
+            compressed_rounding.validate().unwrap();
+            let compressed = compressed_function
+                .to_range_payouts(100000, &compressed_rounding)
+                .unwrap();
+
+            assert_eq!(compress_range_payouts(&original, epsilon), compressed);
+            assert!(compressed.len() <= original.len());
+            if epsilon == 0 {
+                assert_eq!(original, compressed);
+            } else {
+                assert!(compressed.len() < original.len());
+            }
+
+            for outcome in 0..4096 {
+                let original_payout = payout_for_outcome(&original, outcome);
+                let compressed_payout = payout_for_outcome(&compressed, outcome);
+                assert!(
+                    original_payout.abs_diff(compressed_payout) <= epsilon,
+                    "payout error above epsilon for outcome {}",
+                    outcome
+                );
+            }
+            for range in &compressed {
+                assert_eq!(100000, range.payout.offer + range.payout.accept);
+            }
+        }
+    }
+
+    #[test]
+    fn compress_range_payouts_merges_close_payouts() {
+        let range = |start, count, offer| RangePayout {
+            start,
+            count,
+            payout: Payout {
+                offer,
+                accept: 100 - offer,
+            },
+        };
+        let ranges = vec![
+            range(0, 2, 0),
+            range(2, 3, 4),
+            range(5, 1, 8),
+            range(6, 4, 20),
+            range(10, 1, 22),
+        ];
+
+        assert_eq!(
+            vec![range(0, 5, 2), range(5, 1, 8), range(6, 5, 21)],
+            compress_range_payouts(&ranges, 2)
+        );
+    }
+
     #[test]
     fn polynomial_payout_curve_validity_test() {
         let invalid = vec![
@@ -983,6 +1520,67 @@ mod test {
         }
     }
 
+    #[test]
+    fn hyperbola_parameters_are_rounded_to_serialized_precision_test() {
+        let hyperbola = HyperbolaPayoutCurvePiece::new(
+            PayoutPoint {
+                event_outcome: 1,
+                outcome_payout: 0,
+                extra_precision: 0,
+            },
+            PayoutPoint {
+                event_outcome: 1000,
+                outcome_payout: 0,
+                extra_precision: 0,
+            },
+            true,
+            2.5,
+            0.0,
+            1.0,
+            -1.4,
+            0.0,
+            10.0,
+        )
+        .unwrap();
+
+        let mut buf = Vec::new();
+        dlc_messages::ser_impls::write_f64(hyperbola.b, &mut buf).unwrap();
+        let read =
+            dlc_messages::ser_impls::read_f64(&mut lightning::io::Cursor::new(&buf)).unwrap();
+        assert_eq!(read, hyperbola.b);
+        hyperbola
+            .validate()
+            .expect("rounded parameters to be valid");
+
+        let unrounded = HyperbolaPayoutCurvePiece {
+            b: -1.4,
+            ..hyperbola
+        };
+        PayoutFunction::new(vec![PayoutFunctionPiece::HyperbolaPayoutCurvePiece(
+            unrounded,
+        )])
+        .unwrap()
+        .validate(1000)
+        .expect_err("Parameters that cannot be serialized should error.");
+    }
+
+    #[test]
+    fn hyperbola_inverse_test() {
+        let hyperbola = HyperbolaPayoutCurvePiece::inverse(1, 1000, 1000.0, 0.5).unwrap();
+
+        assert_eq!(1000, hyperbola.left_end_point.outcome_payout);
+        assert_eq!(1 << 15, hyperbola.left_end_point.extra_precision);
+        assert_eq!(1, hyperbola.right_end_point.outcome_payout);
+        assert_eq!(1 << 15, hyperbola.right_end_point.extra_precision);
+        assert_eq!(100.5, hyperbola.evaluate(10));
+        assert_eq!(2.5, hyperbola.evaluate(500));
+
+        HyperbolaPayoutCurvePiece::inverse(0, 1000, 1000.0, 0.0)
+            .expect_err("Inverse curve should not be defined at 0.");
+        HyperbolaPayoutCurvePiece::inverse(1, 1000, 1000.0, -10.0)
+            .expect_err("Negative payouts should error.");
+    }
+
     #[test]
     fn hyperbola_validity_test() {
         HyperbolaPayoutCurvePiece::new(
@@ -1290,4 +1888,79 @@ mod test {
         assert_eq!(polynomial.evaluate(0), 10.0);
         assert_eq!(polynomial.evaluate(1), 8.0);
     }
+
+    #[test]
+    fn payout_curve_builder_test() {
+        let (payout_function, rounding_intervals) = PayoutCurveBuilder::new(1000, 100, 0)
+            .flat(10)
+            .rounding_mod(10)
+            .capped(50, 1000, 80)
+            .rounding_mod(1)
+            .hyperbolic(100, 40000.0, 500.0)
+            .build()
+            .expect("to be able to build the curve");
+
+        assert_eq!(4, payout_function.payout_function_pieces.len());
+        assert_eq!(
+            vec![(0, 1), (11, 10), (81, 1)],
+            rounding_intervals
+                .intervals
+                .iter()
+                .map(|x| (x.begin_interval, x.rounding_mod))
+                .collect::<Vec<_>>()
+        );
+
+        let range_payouts = payout_function
+            .to_range_payouts(1000, &rounding_intervals)
+            .unwrap();
+        let payout_at = |outcome: usize| {
+            range_payouts
+                .iter()
+                .find(|r| r.start <= outcome && outcome < r.start + r.count)
+                .unwrap()
+                .payout
+                .offer
+        };
+        assert_eq!(0, payout_at(5));
+        assert_eq!(500, payout_at(30));
+        assert_eq!(1000, payout_at(70));
+        assert_eq!(1000, payout_at(80));
+        assert_eq!(944, payout_at(90));
+        assert_eq!(900, payout_at(100));
+        assert_eq!(101, range_payouts.iter().map(|r| r.count).sum::<usize>());
+    }
+
+    #[test]
+    fn payout_curve_builder_errors_test() {
+        let err = PayoutCurveBuilder::new(1000, 100, 0)
+            .linear(50, 500)
+            .linear(40, 600)
+            .flat(100)
+            .build()
+            .expect_err("Decreasing outcomes should error.");
+        assert!(err.to_string().contains("piece 1 from outcome 50 to 40"));
+
+        PayoutCurveBuilder::new(1000, 100, 0)
+            .linear(100, 1001)
+            .build()
+            .expect_err("Payout greater than total collateral should error.");
+        PayoutCurveBuilder::new(1000, 100, 1001)
+            .flat(100)
+            .build()
+            .expect_err("Start payout greater than total collateral should error.");
+        PayoutCurveBuilder::new(1000, 100, 0)
+            .linear(50, 1000)
+            .build()
+            .expect_err("Curve not covering all outcomes should error.");
+        PayoutCurveBuilder::new(1000, 100, 0)
+            .linear(50, 500)
+            .hyperbolic(100, 1000.0, 0.0)
+            .build()
+            .expect_err("Discontinuous hyperbola should error.");
+        PayoutCurveBuilder::new(1000, 100, 0)
+            .rounding_mod(0)
+            .flat(100)
+            .build()
+            .expect_err("Zero rounding modulus should error.");
+    }
 }
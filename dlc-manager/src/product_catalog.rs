@@ -0,0 +1,220 @@
+//! #ProductCatalog mapping oracle event ids to the definition of the products
+//! they settle, used to describe contracts in a human readable way.
+
+use crate::contract::Contract;
+use crate::error::Error;
+use crate::{ContractId, Storage};
+use dlc_messages::oracle_msgs::OracleAnnouncement;
+use secp256k1_zkp::PublicKey;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+
+/// Description of the product settled by an oracle event.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct ProductDefinition {
+    /// The name under which the product is displayed, e.g. "BTC-USD 28 Jun".
+    pub name: String,
+    /// The underlying asset or pair, e.g. "BTC-USD".
+    pub underlying: String,
+    /// How the outcomes of the event relate to the price of the underlying,
+    /// e.g. "USD price, rounded down to the nearest dollar".
+    pub strike_convention: String,
+    /// The source used by the oracle to settle the event.
+    pub settlement_source: String,
+}
+
+impl_dlc_writeable!(ProductDefinition, {
+    (name, string),
+    (underlying, string),
+    (strike_convention, string),
+    (settlement_source, string)
+});
+
+/// An oracle event on which a contract depends, along with the definition of
+/// the product it settles if one was registered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventSummary {
+    /// The id of the oracle event.
+    pub event_id: String,
+    /// The maturity of the event, in seconds since the unix epoch.
+    pub maturity: u64,
+    /// The product settled by the event, if known.
+    pub product: Option<ProductDefinition>,
+}
+
+/// A summary of a contract suitable for displaying to users.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContractSummary {
+    /// The id of the contract.
+    pub contract_id: ContractId,
+    /// The public key of the counter party's node.
+    pub counter_party: PublicKey,
+    /// The state of the contract, see [`Contract::get_state_name`].
+    pub state: &'static str,
+    /// The oracle events on which the contract depends. Empty for closed
+    /// contracts that did not keep their announcements.
+    pub events: Vec<EventSummary>,
+}
+
+impl ContractSummary {
+    /// Returns the name under which the contract can be displayed, made of the
+    /// names of the products of its events, using the event id for events
+    /// without a registered product.
+    pub fn get_display_name(&self) -> String {
+        self.events
+            .iter()
+            .map(|e| e.product.as_ref().map_or(&e.event_id, |p| &p.name).as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Stores the definitions of the products settled by oracle events in the
+/// [`Storage`], and joins them with contracts to produce [`ContractSummary`].
+pub struct ProductCatalog<S: Deref>
+where
+    S::Target: Storage,
+{
+    store: S,
+}
+
+impl<S: Deref> ProductCatalog<S>
+where
+    S::Target: Storage,
+{
+    /// Creates a new catalog backed by the given storage.
+    pub fn new(store: S) -> Self {
+        ProductCatalog { store }
+    }
+
+    /// Registers the product settled by the event with the given id, replacing
+    /// any previous definition.
+    pub fn register_product(
+        &self,
+        event_id: &str,
+        product: &ProductDefinition,
+    ) -> Result<(), Error> {
+        self.store.upsert_product_definition(event_id, product)
+    }
+
+    /// Returns the product settled by the event with the given id, if any was
+    /// registered.
+    pub fn get_product(&self, event_id: &str) -> Result<Option<ProductDefinition>, Error> {
+        self.store.get_product_definition(event_id)
+    }
+
+    /// Returns the summary of the given contract.
+    pub fn get_contract_summary(&self, contract: &Contract) -> Result<ContractSummary, Error> {
+        let mut events: Vec<EventSummary> = Vec::new();
+        for announcement in get_announcements(contract) {
+            let event = &announcement.oracle_event;
+            // Events attested by multiple oracles are only listed once.
+            if events.iter().any(|e| e.event_id == event.event_id) {
+                continue;
+            }
+            events.push(EventSummary {
+                event_id: event.event_id.clone(),
+                maturity: event.event_maturity_epoch as u64,
+                product: self.get_product(&event.event_id)?,
+            });
+        }
+
+        Ok(ContractSummary {
+            contract_id: contract.get_id(),
+            counter_party: contract.get_counter_party_id(),
+            state: contract.get_state_name(),
+            events,
+        })
+    }
+
+    /// Returns the summaries of all the stored contracts.
+    pub fn get_contract_summaries(&self) -> Result<Vec<ContractSummary>, Error> {
+        self.store
+            .get_contracts()?
+            .iter()
+            .map(|c| self.get_contract_summary(c))
+            .collect()
+    }
+}
+
+fn get_announcements(contract: &Contract) -> Vec<&OracleAnnouncement> {
+    let offered_contract = match contract {
+        Contract::Offered(o) | Contract::Rejected(o) | Contract::Expired(o) => o,
+        Contract::Accepted(a) => &a.offered_contract,
+        Contract::Signed(s)
+        | Contract::Confirmed(s)
+        | Contract::Refunded(s)
+        | Contract::Amended(s) => &s.accepted_contract.offered_contract,
+        Contract::PreClosed(p) => &p.signed_contract.accepted_contract.offered_contract,
+        Contract::CollaborativelyClosed(c) => &c.signed_contract.accepted_contract.offered_contract,
+        Contract::FailedAccept(f) => &f.offered_contract,
+        Contract::FailedSign(f) => &f.accepted_contract.offered_contract,
+        Contract::Closed(c) => return c.announcements.iter().flatten().collect(),
+    };
+    offered_contract
+        .contract_info
+        .iter()
+        .flat_map(|x| &x.oracle_announcements)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::ser::Serializable;
+    use crate::contract::signed_contract::SignedContract;
+    use crate::memory_storage::MemoryStorage;
+
+    fn product() -> ProductDefinition {
+        ProductDefinition {
+            name: "BTC-USD 28 Jun".to_string(),
+            underlying: "BTC-USD".to_string(),
+            strike_convention: "USD price rounded down".to_string(),
+            settlement_source: "Composite index".to_string(),
+        }
+    }
+
+    #[test]
+    fn product_definition_serialization_round_trip() {
+        let serialized = product().serialize().unwrap();
+        let deserialized =
+            ProductDefinition::deserialize(&mut std::io::Cursor::new(&serialized)).unwrap();
+        assert_eq!(product(), deserialized);
+    }
+
+    #[test]
+    fn contract_summary_includes_registered_products() {
+        let signed_contract = SignedContract::deserialize(&mut std::io::Cursor::new(
+            include_bytes!("../../dlc-sled-storage-provider/test_files/Signed"),
+        ))
+        .unwrap();
+        let event_id = signed_contract
+            .accepted_contract
+            .offered_contract
+            .contract_info[0]
+            .oracle_announcements[0]
+            .oracle_event
+            .event_id
+            .clone();
+        let contract = Contract::Signed(signed_contract);
+
+        let store = MemoryStorage::new();
+        let catalog = ProductCatalog::new(&store);
+
+        let summary = catalog.get_contract_summary(&contract).unwrap();
+        assert_eq!(event_id, summary.get_display_name());
+
+        catalog.register_product(&event_id, &product()).unwrap();
+
+        let summary = catalog.get_contract_summary(&contract).unwrap();
+        assert_eq!("signed", summary.state);
+        assert_eq!(Some(product()), summary.events[0].product);
+        assert_eq!("BTC-USD 28 Jun", summary.get_display_name());
+    }
+}
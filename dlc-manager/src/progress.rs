@@ -0,0 +1,369 @@
+//! #Progress reporting for long running operations such as creating or
+//! verifying the adaptor signatures of a contract with a large number of
+//! outcomes.
+
+use crate::cancellation::CancellationToken;
+use crate::contract::contract_info::ContractInfo;
+use crate::contract::AdaptorInfo;
+use crate::error::Error;
+use bitcoin::{Script, Transaction};
+use secp256k1_zkp::{All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey};
+use std::fmt;
+use std::sync::Arc;
+
+/// The number of adaptor signatures created or verified between two progress
+/// notifications.
+pub const PROGRESS_BATCH_SIZE: usize = 1000;
+
+/// The step of an operation for which progress is reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressStage {
+    /// Creating the local adaptor signatures.
+    CreatingAdaptorSignatures,
+    /// Verifying the adaptor signatures of the counter party.
+    VerifyingAdaptorSignatures,
+}
+
+/// The progress of an operation on a contract or channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// The id of the contract or channel the operation is performed on.
+    pub id: [u8; 32],
+    /// The step of the operation in progress.
+    pub stage: ProgressStage,
+    /// The number of items processed so far.
+    pub done: usize,
+    /// The total number of items to process.
+    pub total: usize,
+}
+
+/// Receives the progress of long running operations. Notifications are sent
+/// from the thread performing the operation between batches of
+/// [`PROGRESS_BATCH_SIZE`] signatures, so that implementations can update a
+/// user interface or yield to other tasks. Operations can be aborted from the
+/// handler using [`crate::manager::Manager::cancel_operation`].
+pub trait ProgressHandler: Send + Sync {
+    /// Called each time a batch of items has been processed.
+    fn on_progress(&self, progress: &Progress);
+}
+
+/// The handler to which a [`CancellationToken`] reports progress, along with
+/// the id of the object the operation is performed on.
+#[derive(Clone)]
+pub(crate) struct ProgressReporter {
+    pub(crate) id: [u8; 32],
+    pub(crate) handler: Arc<dyn ProgressHandler>,
+}
+
+impl fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProgressReporter")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Keeps count of the items processed by an operation, reporting progress
+/// and checking for cancellation after each batch.
+pub(crate) struct ProgressTracker<'a> {
+    token: &'a CancellationToken,
+    stage: ProgressStage,
+    done: usize,
+    total: usize,
+}
+
+impl<'a> ProgressTracker<'a> {
+    pub(crate) fn new(token: &'a CancellationToken, stage: ProgressStage, total: usize) -> Self {
+        ProgressTracker {
+            token,
+            stage,
+            done: 0,
+            total,
+        }
+    }
+
+    /// Returns whether a handler receives the progress of the operation, in
+    /// which case signatures are processed in batches.
+    pub(crate) fn is_reporting(&self) -> bool {
+        self.token.progress_reporter().is_some()
+    }
+
+    fn advance(&mut self, nb_items: usize) -> Result<(), Error> {
+        self.done += nb_items;
+        if let Some(reporter) = self.token.progress_reporter() {
+            reporter.handler.on_progress(&Progress {
+                id: reporter.id,
+                stage: self.stage,
+                done: self.done,
+                total: self.total,
+            });
+        }
+        self.token.check()
+    }
+}
+
+/// Returns the total number of adaptor signatures of the given contract
+/// information, only computing it if progress is reported.
+pub(crate) fn get_total_adaptor_signatures(
+    token: &CancellationToken,
+    contract_infos: &[ContractInfo],
+    total_collateral: u64,
+) -> Result<usize, Error> {
+    if token.progress_reporter().is_none() {
+        return Ok(0);
+    }
+    contract_infos
+        .iter()
+        .map(|c| c.get_adaptor_signature_count(total_collateral))
+        .sum()
+}
+
+/// Generates the adaptor info and adaptor signatures of the given contract
+/// information, in batches if progress is reported.
+pub(crate) fn get_adaptor_info(
+    tracker: &mut ProgressTracker,
+    contract_info: &ContractInfo,
+    secp: &Secp256k1<All>,
+    total_collateral: u64,
+    fund_privkey: &SecretKey,
+    funding_script_pubkey: &Script,
+    fund_output_value: u64,
+    cets: &[Transaction],
+    adaptor_index_start: usize,
+) -> Result<(AdaptorInfo, Vec<EcdsaAdaptorSignature>), Error> {
+    if !tracker.is_reporting() {
+        return contract_info.get_adaptor_info(
+            secp,
+            total_collateral,
+            fund_privkey,
+            funding_script_pubkey,
+            fund_output_value,
+            cets,
+            adaptor_index_start,
+        );
+    }
+
+    let adaptor_info =
+        contract_info.generate_adaptor_info(total_collateral, adaptor_index_start)?;
+    let adaptor_points = contract_info.get_adaptor_points(secp, &adaptor_info)?;
+    let mut adaptor_sigs = Vec::with_capacity(adaptor_points.len());
+    for batch in adaptor_points.chunks(PROGRESS_BATCH_SIZE) {
+        let inputs = batch
+            .iter()
+            .map(|(cet_index, adaptor_point)| (&cets[*cet_index], adaptor_point))
+            .collect::<Vec<_>>();
+        adaptor_sigs.extend(dlc::create_cet_adaptor_sigs_from_points(
+            secp,
+            &inputs,
+            fund_privkey,
+            funding_script_pubkey,
+            fund_output_value,
+        )?);
+        tracker.advance(batch.len())?;
+    }
+
+    Ok((adaptor_info, adaptor_sigs))
+}
+
+/// Verifies the given adaptor signatures against the given adaptor info, in
+/// batches if progress is reported. Returns the index following the last
+/// verified signature.
+pub(crate) fn verify_adaptor_info(
+    tracker: &mut ProgressTracker,
+    contract_info: &ContractInfo,
+    secp: &Secp256k1<All>,
+    fund_pubkey: &PublicKey,
+    funding_script_pubkey: &Script,
+    fund_output_value: u64,
+    cets: &[Transaction],
+    adaptor_sigs: &[EcdsaAdaptorSignature],
+    adaptor_sig_start: usize,
+    adaptor_info: &AdaptorInfo,
+) -> Result<usize, Error> {
+    if !tracker.is_reporting() {
+        return contract_info.verify_adaptor_info(
+            secp,
+            fund_pubkey,
+            funding_script_pubkey,
+            fund_output_value,
+            cets,
+            adaptor_sigs,
+            adaptor_sig_start,
+            adaptor_info,
+        );
+    }
+
+    let adaptor_points = contract_info.get_adaptor_points(secp, adaptor_info)?;
+    let adaptor_sig_end = adaptor_sig_start + adaptor_points.len();
+    if adaptor_sigs.len() < adaptor_sig_end {
+        return Err(Error::InvalidParameters(
+            "Not enough adaptor signatures for the contract.".to_string(),
+        ));
+    }
+
+    for (batch_index, batch) in adaptor_points.chunks(PROGRESS_BATCH_SIZE).enumerate() {
+        let batch_start = adaptor_sig_start + batch_index * PROGRESS_BATCH_SIZE;
+        let inputs = batch
+            .iter()
+            .zip(adaptor_sigs[batch_start..].iter())
+            .map(|((cet_index, adaptor_point), sig)| (sig, &cets[*cet_index], adaptor_point))
+            .collect::<Vec<_>>();
+        dlc::verify_cet_adaptor_sigs_from_points(
+            secp,
+            &inputs,
+            fund_pubkey,
+            funding_script_pubkey,
+            fund_output_value,
+        )?;
+        tracker.advance(batch.len())?;
+    }
+
+    Ok(adaptor_sig_end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conversion_utils::get_contract_info_and_announcements;
+    use bitcoin::{ScriptBuf, TxIn};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingHandler {
+        progress: Mutex<Vec<Progress>>,
+    }
+
+    impl ProgressHandler for RecordingHandler {
+        fn on_progress(&self, progress: &Progress) {
+            self.progress.lock().unwrap().push(progress.clone());
+        }
+    }
+
+    fn get_contract_info_and_cets() -> (ContractInfo, u64, Vec<Transaction>) {
+        let offer: dlc_messages::OfferDlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let total_collateral = offer.contract_info.get_total_collateral();
+        let contract_info = get_contract_info_and_announcements(&offer.contract_info)
+            .unwrap()
+            .remove(0);
+        let cets = dlc::create_cets(
+            &TxIn::default(),
+            &ScriptBuf::new(),
+            0,
+            &ScriptBuf::new(),
+            1,
+            &contract_info.get_payouts(total_collateral).unwrap(),
+            0,
+        );
+        (contract_info, total_collateral, cets)
+    }
+
+    #[test]
+    fn progress_is_reported_while_signing_and_verifying() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let funding_script_pubkey = ScriptBuf::new();
+        let (contract_info, total_collateral, cets) = get_contract_info_and_cets();
+        let handler = Arc::new(RecordingHandler::default());
+        let token = CancellationToken::with_progress_handler([1u8; 32], handler.clone());
+        let total = get_total_adaptor_signatures(
+            &token,
+            std::slice::from_ref(&contract_info),
+            total_collateral,
+        )
+        .unwrap();
+
+        let mut tracker =
+            ProgressTracker::new(&token, ProgressStage::CreatingAdaptorSignatures, total);
+        let (adaptor_info, adaptor_sigs) = get_adaptor_info(
+            &mut tracker,
+            &contract_info,
+            &secp,
+            total_collateral,
+            &secret_key,
+            &funding_script_pubkey,
+            1000,
+            &cets,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(total, adaptor_sigs.len());
+        contract_info
+            .verify_adaptor_info(
+                &secp,
+                &public_key,
+                &funding_script_pubkey,
+                1000,
+                &cets,
+                &adaptor_sigs,
+                0,
+                &adaptor_info,
+            )
+            .expect("signatures created in batches to be valid");
+
+        let mut tracker = ProgressTracker::new(
+            &token,
+            ProgressStage::VerifyingAdaptorSignatures,
+            adaptor_sigs.len(),
+        );
+        let end = verify_adaptor_info(
+            &mut tracker,
+            &contract_info,
+            &secp,
+            &public_key,
+            &funding_script_pubkey,
+            1000,
+            &cets,
+            &adaptor_sigs,
+            0,
+            &adaptor_info,
+        )
+        .unwrap();
+        assert_eq!(adaptor_sigs.len(), end);
+
+        let progress = handler.progress.lock().unwrap();
+        let nb_batches = (total + PROGRESS_BATCH_SIZE - 1) / PROGRESS_BATCH_SIZE;
+        assert_eq!(2 * nb_batches, progress.len());
+        assert!(progress
+            .iter()
+            .all(|p| p.id == [1u8; 32] && p.total == total));
+        assert_eq!(
+            ProgressStage::CreatingAdaptorSignatures,
+            progress[nb_batches - 1].stage
+        );
+        assert_eq!(total, progress[nb_batches - 1].done);
+        assert_eq!(
+            ProgressStage::VerifyingAdaptorSignatures,
+            progress.last().unwrap().stage
+        );
+        assert_eq!(total, progress.last().unwrap().done);
+    }
+
+    #[test]
+    fn cancelled_operation_stops_after_batch() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let (contract_info, total_collateral, cets) = get_contract_info_and_cets();
+        let handler = Arc::new(RecordingHandler::default());
+        let token = CancellationToken::with_progress_handler([1u8; 32], handler.clone());
+        token.cancel();
+
+        let mut tracker = ProgressTracker::new(&token, ProgressStage::CreatingAdaptorSignatures, 0);
+        let res = get_adaptor_info(
+            &mut tracker,
+            &contract_info,
+            &secp,
+            total_collateral,
+            &secret_key,
+            &ScriptBuf::new(),
+            1000,
+            &cets,
+            0,
+        );
+
+        assert!(matches!(res, Err(Error::Cancelled)));
+        assert_eq!(1, handler.progress.lock().unwrap().len());
+    }
+}
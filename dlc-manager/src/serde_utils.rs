@@ -0,0 +1,24 @@
+//! Utility functions to serialize with serde values that only implement the
+//! lightning serialization traits.
+
+use lightning::util::ser::{Readable, Writeable};
+
+/// Serialize a [`Writeable`] value as its hex encoded serialization.
+pub(crate) fn serialize_writeable<S, T>(value: &T, s: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    T: Writeable,
+{
+    dlc_messages::serde_utils::serialize_hex(&value.encode(), s)
+}
+
+/// Deserialize a [`Readable`] value from its hex encoded serialization.
+pub(crate) fn deserialize_writeable<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+    T: Readable,
+{
+    let bytes = dlc_messages::serde_utils::deserialize_hex_string(deserializer)?;
+    Readable::read(&mut std::io::Cursor::new(&bytes))
+        .map_err(|e| serde::de::Error::custom(format!("Error decoding value: {:?}", e)))
+}
@@ -0,0 +1,212 @@
+//! #StateHistory export of the state graph of contracts and channels, built
+//! from their event log, to help debugging them.
+
+use crate::channel::ChannelEvent;
+use crate::contract::ContractEvent;
+use hex::DisplayHex;
+
+/// An edge of a state graph.
+struct Transition<'a> {
+    from: Option<String>,
+    to: String,
+    timestamp: u64,
+    trigger: &'a str,
+}
+
+/// The formats in which a state graph can be exported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateGraphFormat {
+    /// Graphviz DOT format.
+    Dot,
+    /// JSON object containing the id of the object and the list of its
+    /// transitions.
+    Json,
+}
+
+/// Returns the state graph of the contract with the given id, built from its
+/// event log, in the requested format. States are named after the type of the
+/// event that moved the contract into them, and edges are labelled with their
+/// position in the history, their trigger and their timestamp.
+pub fn export_contract_state_graph(
+    id: &[u8; 32],
+    events: &[ContractEvent],
+    format: StateGraphFormat,
+) -> String {
+    let states = events.iter().map(|e| {
+        (
+            format!("{:?}", e.event_type),
+            e.timestamp,
+            e.trigger.as_str(),
+        )
+    });
+    export_state_graph(id, states, format)
+}
+
+/// Returns the state graph of the channel with the given id, built from its
+/// event log, in the requested format. Edges are labelled with their position
+/// in the history, their trigger and their timestamp.
+pub fn export_channel_state_graph(
+    id: &[u8; 32],
+    events: &[ChannelEvent],
+    format: StateGraphFormat,
+) -> String {
+    let states = events
+        .iter()
+        .map(|e| (e.state.clone(), e.timestamp, e.trigger.as_str()));
+    export_state_graph(id, states, format)
+}
+
+/// Returns the state graph going through the given states, each one given
+/// with the time at which it was entered and the trigger of the transition.
+fn export_state_graph<'a>(
+    id: &[u8; 32],
+    states: impl Iterator<Item = (String, u64, &'a str)>,
+    format: StateGraphFormat,
+) -> String {
+    let mut from = None;
+    let mut transitions = Vec::new();
+    for (to, timestamp, trigger) in states {
+        transitions.push(Transition {
+            from: from.replace(to.clone()),
+            to,
+            timestamp,
+            trigger,
+        });
+    }
+    match format {
+        StateGraphFormat::Dot => to_dot(id, &transitions),
+        StateGraphFormat::Json => to_json(id, &transitions),
+    }
+}
+
+fn to_dot(id: &[u8; 32], transitions: &[Transition]) -> String {
+    let mut res = format!("digraph \"{}\" {{\n", id.to_lower_hex_string());
+    res.push_str("    \"start\" [shape=point];\n");
+    for (i, transition) in transitions.iter().enumerate() {
+        res.push_str(&format!(
+            "    {} -> {} [label={}];\n",
+            escape(transition.from.as_deref().unwrap_or("start")),
+            escape(&transition.to),
+            escape(&format!(
+                "{}: {} @ {}",
+                i + 1,
+                transition.trigger,
+                transition.timestamp
+            )),
+        ));
+    }
+    res.push('}');
+    res.push('\n');
+    res
+}
+
+fn to_json(id: &[u8; 32], transitions: &[Transition]) -> String {
+    let transitions = transitions
+        .iter()
+        .map(|t| {
+            format!(
+                "{{\"from\":{},\"to\":{},\"timestamp\":{},\"trigger\":{}}}",
+                t.from.as_deref().map_or("null".to_string(), escape),
+                escape(&t.to),
+                t.timestamp,
+                escape(t.trigger)
+            )
+        })
+        .collect::<Vec<_>>();
+    format!(
+        "{{\"id\":\"{}\",\"transitions\":[{}]}}",
+        id.to_lower_hex_string(),
+        transitions.join(",")
+    )
+}
+
+/// Returns the input as a double quoted string, escaping characters so that it
+/// is valid both in DOT and JSON.
+fn escape(input: &str) -> String {
+    let mut res = String::with_capacity(input.len() + 2);
+    res.push('"');
+    for c in input.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            c if c.is_control() => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::ContractEventType;
+
+    fn events() -> Vec<ContractEvent> {
+        vec![
+            ContractEvent {
+                event_type: ContractEventType::OfferReceived,
+                timestamp: 10,
+                trigger: "OfferDlc".to_string(),
+            },
+            ContractEvent {
+                event_type: ContractEventType::Accepted,
+                timestamp: 20,
+                trigger: "accept_contract_offer".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn export_dot_state_graph() {
+        let dot = export_contract_state_graph(&[0u8; 32], &events(), StateGraphFormat::Dot);
+
+        assert!(dot.starts_with(&format!("digraph \"{}\" {{", "00".repeat(32))));
+        assert!(dot.contains("\"start\" -> \"OfferReceived\" [label=\"1: OfferDlc @ 10\"];"));
+        assert!(dot.contains(
+            "\"OfferReceived\" -> \"Accepted\" [label=\"2: accept_contract_offer @ 20\"];"
+        ));
+    }
+
+    #[test]
+    fn export_json_state_graph() {
+        let json = export_contract_state_graph(&[1u8; 32], &events(), StateGraphFormat::Json);
+
+        assert_eq!(
+            format!(
+                "{{\"id\":\"{}\",\"transitions\":[{},{}]}}",
+                "01".repeat(32),
+                "{\"from\":null,\"to\":\"OfferReceived\",\"timestamp\":10,\"trigger\":\"OfferDlc\"}",
+                "{\"from\":\"OfferReceived\",\"to\":\"Accepted\",\"timestamp\":20,\"trigger\":\"accept_contract_offer\"}"
+            ),
+            json
+        );
+    }
+
+    #[test]
+    fn export_channel_state_graph_uses_channel_states() {
+        let events = vec![
+            ChannelEvent {
+                state: "offered".to_string(),
+                timestamp: 10,
+                trigger: "offer_channel".to_string(),
+            },
+            ChannelEvent {
+                state: "signed (Established)".to_string(),
+                timestamp: 20,
+                trigger: "SignChannel".to_string(),
+            },
+        ];
+
+        let dot = export_channel_state_graph(&[2u8; 32], &events, StateGraphFormat::Dot);
+
+        assert!(dot
+            .contains("\"offered\" -> \"signed (Established)\" [label=\"2: SignChannel @ 20\"];"));
+    }
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!("\"a\\\"b\\\\c\\n\"", escape("a\"b\\c\n"));
+    }
+}
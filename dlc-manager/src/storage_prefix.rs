@@ -0,0 +1,130 @@
+//! #StoragePrefix identifiers of the states of contracts and channels, shared
+//! by the storage providers to tag serialized records and to look them up by
+//! state.
+
+use crate::channel::signed_channel::SignedChannelStateType;
+use crate::channel::Channel;
+use crate::contract::Contract;
+use crate::error::Error;
+
+macro_rules! convertible_enum {
+    (enum $name:ident {
+        $($vname:ident $(= $val:expr)?,)*;
+        $($tname:ident $(= $tval:expr)?,)*
+    }, $input:ident) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #[allow(missing_docs)]
+        pub enum $name {
+            $($vname $(= $val)?,)*
+            $($tname $(= $tval)?,)*
+        }
+
+        impl From<$name> for u8 {
+            fn from(prefix: $name) -> u8 {
+                prefix as u8
+            }
+        }
+
+        impl std::convert::TryFrom<u8> for $name {
+            type Error = Error;
+
+            fn try_from(v: u8) -> Result<Self, Self::Error> {
+                match v {
+                    $(x if x == u8::from($name::$vname) => Ok($name::$vname),)*
+                    $(x if x == u8::from($name::$tname) => Ok($name::$tname),)*
+                    _ => Err(Error::StorageError("Unknown prefix".to_string())),
+                }
+            }
+        }
+
+        impl $name {
+            /// Returns the prefix corresponding to the state of the given value.
+            pub fn get_prefix(input: &$input) -> u8 {
+                let prefix = match input {
+                    $($input::$vname(_) => $name::$vname,)*
+                    $($input::$tname{..} => $name::$tname,)*
+                };
+                prefix.into()
+            }
+        }
+    }
+}
+
+convertible_enum!(
+    enum ContractPrefix {
+        Offered = 1,
+        Accepted,
+        Signed,
+        Confirmed,
+        PreClosed,
+        Closed,
+        FailedAccept,
+        FailedSign,
+        Refunded,
+        Rejected,
+        Expired,
+        Amended,
+        CollaborativelyClosed,;
+    },
+    Contract
+);
+
+convertible_enum!(
+    enum ChannelPrefix {
+        Offered = 100,
+        Accepted,
+        Signed,
+        FailedAccept,
+        FailedSign,
+        Cancelled,;
+    },
+    Channel
+);
+
+convertible_enum!(
+    enum SignedChannelPrefix {;
+        Established = 1,
+        SettledOffered,
+        SettledReceived,
+        SettledAccepted,
+        SettledConfirmed,
+        Settled,
+        Closing,
+        Closed,
+        CounterClosed,
+        ClosedPunished,
+        CollaborativeCloseOffered,
+        CollaborativelyClosed,
+        RenewAccepted,
+        RenewOffered,
+        RenewConfirmed,
+        ContractsEstablished,
+        ContractUpdateOffered,
+        ContractUpdateAccepted,
+        ContractUpdateConfirmed,
+        ContractsClosing,
+        FundingDoubleSpent,
+    },
+    SignedChannelStateType
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn prefixes_round_trip() {
+        for prefix in [
+            ContractPrefix::Offered,
+            ContractPrefix::CollaborativelyClosed,
+        ] {
+            assert_eq!(prefix, ContractPrefix::try_from(u8::from(prefix)).unwrap());
+        }
+        for prefix in [ChannelPrefix::Offered, ChannelPrefix::Cancelled] {
+            assert_eq!(prefix, ChannelPrefix::try_from(u8::from(prefix)).unwrap());
+        }
+        assert_eq!(21, u8::from(SignedChannelPrefix::FundingDoubleSpent));
+        ContractPrefix::try_from(0).expect_err("Unknown prefix");
+    }
+}
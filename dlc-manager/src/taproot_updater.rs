@@ -0,0 +1,479 @@
+//! # This module contains static functions to update the state of a DLC funded
+//! with a taproot output. The CETs and the refund transaction spend the fund
+//! output through the 2-of-2 script path of the output, using Schnorr
+//! signatures and Schnorr adaptor signatures for the CETs.
+
+use std::ops::Deref;
+
+use bitcoin::Transaction;
+use dlc::taproot::adaptor::SchnorrAdaptorSignature;
+use dlc::taproot::TaprootFundingInfo;
+use dlc::{DlcTransactions, PartyParams};
+use dlc_messages::oracle_msgs::OracleAttestation;
+use dlc_messages::taproot::{AcceptTaprootDlc, SignTaprootDlc};
+use secp256k1_zkp::{schnorr::Signature as SchnorrSignature, All, PublicKey, Secp256k1};
+
+use crate::{
+    cancellation::CancellationToken,
+    contract::{
+        accepted_contract::AcceptedContract, contract_info::ContractInfo,
+        offered_contract::OfferedContract, signed_contract::SignedContract, AdaptorInfo,
+        TaprootSignatures,
+    },
+    contract_updater::{
+        check_accept_funding_inputs, get_accept_party_approximate_fee,
+        sign_accepted_contract_internal, sign_fund_transaction,
+    },
+    conversion_utils::{check_accepted_fee_split, get_tx_input_infos, PROTOCOL_VERSION},
+    error::{Error, ResultExt},
+    Blockchain, ContractSigner, ContractSignerProvider, Wallet,
+};
+
+/// Creates an [`AcceptedContract`] for the given contract funded with a
+/// taproot output and produces the accepting party's signatures.
+pub fn accept_contract<W: Deref, X: ContractSigner, SP: Deref, B: Deref>(
+    secp: &Secp256k1<All>,
+    offered_contract: &OfferedContract,
+    wallet: &W,
+    signer_provider: &SP,
+    blockchain: &B,
+    cancellation: &CancellationToken,
+) -> Result<(AcceptedContract, AcceptTaprootDlc), Error>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    check_taproot(offered_contract)?;
+    let total_collateral = offered_contract.total_collateral;
+
+    let signer = signer_provider.derive_contract_signer(offered_contract.keys_id)?;
+    let (accept_params, funding_inputs) = crate::utils::get_party_params(
+        secp,
+        total_collateral - offered_contract.offer_params.collateral,
+        get_accept_party_approximate_fee(offered_contract)?,
+        offered_contract.fee_rate_per_vb,
+        wallet,
+        &signer,
+        blockchain,
+    )?;
+
+    let accept_contract = || {
+        let (dlc_transactions, funding_info) =
+            create_transactions(secp, offered_contract, &accept_params)?;
+        let adaptor_info =
+            offered_contract.contract_info[0].generate_adaptor_info(total_collateral, 0)?;
+        cancellation.check()?;
+        let (cet_adaptor_signatures, refund_signature) = sign_transactions(
+            secp,
+            offered_contract,
+            &adaptor_info,
+            &dlc_transactions,
+            &funding_info,
+            &signer,
+        )?;
+
+        let accepted_contract = AcceptedContract {
+            offered_contract: offered_contract.clone(),
+            accept_params: accept_params.clone(),
+            funding_inputs: funding_inputs.clone(),
+            adaptor_infos: vec![adaptor_info],
+            adaptor_signatures: None,
+            accept_refund_signature: None,
+            dlc_transactions,
+            // Own adaptor signatures are not needed to close the contract.
+            accept_taproot_signatures: Some(TaprootSignatures {
+                adaptor_signatures: None,
+                refund_signature,
+            }),
+        };
+
+        let accept_msg = AcceptTaprootDlc {
+            protocol_version: PROTOCOL_VERSION,
+            temporary_contract_id: offered_contract.id,
+            accept_collateral: accept_params.collateral,
+            funding_pubkey: accept_params.fund_pubkey,
+            payout_spk: accept_params.payout_script_pubkey.clone(),
+            payout_serial_id: accept_params.payout_serial_id,
+            funding_inputs: funding_inputs.clone(),
+            change_spk: accept_params.change_script_pubkey.clone(),
+            change_serial_id: accept_params.change_serial_id,
+            cet_adaptor_signatures,
+            refund_signature,
+            fee_split: crate::conversion_utils::get_fee_split_field(offered_contract.fee_split),
+        };
+
+        Ok((accepted_contract, accept_msg))
+    };
+
+    crate::utils::release_on_cancellation(wallet, &accept_params, accept_contract())
+}
+
+/// Verifies the information of the accepting party
+/// [`AcceptTaprootDlc`] message, creates a [`SignedContract`], and generates
+/// the offering party's signatures.
+pub fn verify_accepted_and_sign_contract<W: Deref, X: ContractSigner, SP: Deref>(
+    secp: &Secp256k1<All>,
+    offered_contract: &OfferedContract,
+    accept_msg: &AcceptTaprootDlc,
+    wallet: &W,
+    signer_provider: &SP,
+    cancellation: &CancellationToken,
+) -> Result<(SignedContract, SignTaprootDlc), Error>
+where
+    W::Target: Wallet,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    check_taproot(offered_contract)?;
+    check_accepted_fee_split(offered_contract.fee_split, accept_msg.fee_split)?;
+    check_accept_funding_inputs(offered_contract, &accept_msg.funding_inputs)?;
+    let (inputs, input_amount) = get_tx_input_infos(&accept_msg.funding_inputs)?;
+
+    let accept_params = PartyParams {
+        fund_pubkey: accept_msg.funding_pubkey,
+        change_script_pubkey: accept_msg.change_spk.clone(),
+        change_serial_id: accept_msg.change_serial_id,
+        payout_script_pubkey: accept_msg.payout_spk.clone(),
+        payout_serial_id: accept_msg.payout_serial_id,
+        inputs,
+        input_amount,
+        collateral: accept_msg.accept_collateral,
+    };
+
+    let (dlc_transactions, funding_info) =
+        create_transactions(secp, offered_contract, &accept_params)?;
+    let adaptor_info = offered_contract.contract_info[0]
+        .generate_adaptor_info(offered_contract.total_collateral, 0)?;
+
+    cancellation.check()?;
+    verify_signatures(
+        secp,
+        offered_contract,
+        &adaptor_info,
+        &dlc_transactions,
+        &funding_info,
+        &accept_params.fund_pubkey,
+        &accept_msg.cet_adaptor_signatures,
+        &accept_msg.refund_signature,
+    )?;
+
+    let signer = signer_provider.derive_contract_signer(offered_contract.keys_id)?;
+    cancellation.check()?;
+    let (cet_adaptor_signatures, refund_signature) = sign_transactions(
+        secp,
+        offered_contract,
+        &adaptor_info,
+        &dlc_transactions,
+        &funding_info,
+        &signer,
+    )?;
+
+    let accepted_contract = AcceptedContract {
+        offered_contract: offered_contract.clone(),
+        accept_params,
+        funding_inputs: accept_msg.funding_inputs.clone(),
+        adaptor_infos: vec![adaptor_info],
+        adaptor_signatures: None,
+        accept_refund_signature: None,
+        dlc_transactions,
+        accept_taproot_signatures: Some(TaprootSignatures {
+            adaptor_signatures: Some(accept_msg.cet_adaptor_signatures.clone()),
+            refund_signature: accept_msg.refund_signature,
+        }),
+    };
+
+    let mut signed_contract =
+        sign_accepted_contract_internal(accepted_contract, None, wallet, None)?;
+    signed_contract.offer_taproot_signatures = Some(TaprootSignatures {
+        adaptor_signatures: None,
+        refund_signature,
+    });
+
+    let sign_msg = SignTaprootDlc {
+        protocol_version: PROTOCOL_VERSION,
+        contract_id: signed_contract.accepted_contract.get_contract_id(),
+        cet_adaptor_signatures,
+        refund_signature,
+        funding_signatures: signed_contract.funding_signatures.clone(),
+    };
+
+    Ok((signed_contract, sign_msg))
+}
+
+/// Verifies the information from the offer party [`SignTaprootDlc`] message,
+/// creates the accepting party's [`SignedContract`] and returns it along with
+/// the signed fund transaction.
+pub fn verify_signed_contract<W: Deref>(
+    secp: &Secp256k1<All>,
+    accepted_contract: &AcceptedContract,
+    sign_msg: &SignTaprootDlc,
+    wallet: &W,
+    cancellation: &CancellationToken,
+) -> Result<(SignedContract, Transaction), Error>
+where
+    W::Target: Wallet,
+{
+    let offered_contract = &accepted_contract.offered_contract;
+    check_taproot(offered_contract)?;
+    let funding_info = get_funding_info(secp, accepted_contract)?;
+
+    cancellation.check()?;
+    verify_signatures(
+        secp,
+        offered_contract,
+        &accepted_contract.adaptor_infos[0],
+        &accepted_contract.dlc_transactions,
+        &funding_info,
+        &offered_contract.offer_params.fund_pubkey,
+        &sign_msg.cet_adaptor_signatures,
+        &sign_msg.refund_signature,
+    )?;
+
+    let fund_tx = sign_fund_transaction(accepted_contract, &sign_msg.funding_signatures, wallet)?;
+
+    let signed_contract = SignedContract {
+        accepted_contract: accepted_contract.clone(),
+        adaptor_signatures: None,
+        offer_refund_signature: None,
+        funding_signatures: sign_msg.funding_signatures.clone(),
+        channel_id: None,
+        offer_taproot_signatures: Some(TaprootSignatures {
+            adaptor_signatures: Some(sign_msg.cet_adaptor_signatures.clone()),
+            refund_signature: sign_msg.refund_signature,
+        }),
+    };
+
+    Ok((signed_contract, fund_tx))
+}
+
+/// Signs and return the CET that can be used to close the given contract
+/// funded with a taproot output.
+pub(crate) fn get_signed_cet<S: Deref>(
+    secp: &Secp256k1<All>,
+    contract: &SignedContract,
+    contract_info: &ContractInfo,
+    adaptor_info: &AdaptorInfo,
+    attestations: &[(usize, OracleAttestation)],
+    signer: S,
+) -> Result<Transaction, Error>
+where
+    S::Target: ContractSigner,
+{
+    let (range_info, sigs) =
+        crate::utils::get_range_info_and_oracle_sigs(contract_info, adaptor_info, attestations)?;
+    let accepted_contract = &contract.accepted_contract;
+    let mut cet = accepted_contract.dlc_transactions.cets[range_info.cet_index].clone();
+    let (counter_signatures, other_pubkey) = get_counter_signatures(contract)?;
+    let adaptor_sigs = counter_signatures
+        .adaptor_signatures
+        .as_ref()
+        .ok_or_else(|| {
+            Error::InvalidState("Missing adaptor signatures of the counter party".to_string())
+        })?;
+    let adaptor_sig = adaptor_sigs.get(range_info.adaptor_index).ok_or_else(|| {
+        Error::InvalidState("Missing adaptor signature for the attested outcome".to_string())
+    })?;
+
+    dlc::taproot::sign_cet(
+        secp,
+        &mut cet,
+        adaptor_sig,
+        &sigs,
+        &signer.get_secret_key()?,
+        other_pubkey,
+        &get_funding_info(secp, accepted_contract)?,
+        accepted_contract.dlc_transactions.get_fund_output().value,
+    )?;
+
+    Ok(cet)
+}
+
+/// Signs and return the refund transaction to refund the given contract funded
+/// with a taproot output.
+pub(crate) fn get_signed_refund<S: Deref>(
+    secp: &Secp256k1<All>,
+    contract: &SignedContract,
+    signer: S,
+) -> Result<Transaction, Error>
+where
+    S::Target: ContractSigner,
+{
+    let accepted_contract = &contract.accepted_contract;
+    let funding_info = get_funding_info(secp, accepted_contract)?;
+    let fund_output_value = accepted_contract.dlc_transactions.get_fund_output().value;
+    let (counter_signatures, other_pubkey) = get_counter_signatures(contract)?;
+
+    let funding_sk = signer.get_secret_key()?;
+    let mut refund = accepted_contract.dlc_transactions.refund.clone();
+    let own_signature = dlc::taproot::sign_script_spend(
+        secp,
+        &refund,
+        &funding_sk,
+        &funding_info,
+        fund_output_value,
+    )?;
+    dlc::taproot::finalize_script_spend(
+        &mut refund,
+        &funding_info,
+        &own_signature,
+        &PublicKey::from_secret_key(secp, &funding_sk),
+        &counter_signatures.refund_signature,
+        other_pubkey,
+    )?;
+    Ok(refund)
+}
+
+fn check_taproot(offered_contract: &OfferedContract) -> Result<(), Error> {
+    if !offered_contract.taproot {
+        return Err(Error::InvalidParameters(
+            "Contract is not funded with a taproot output".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn create_transactions(
+    secp: &Secp256k1<All>,
+    offered_contract: &OfferedContract,
+    accept_params: &PartyParams,
+) -> Result<(DlcTransactions, TaprootFundingInfo), Error> {
+    let total_collateral = offered_contract.total_collateral;
+    let dlc_transactions = dlc::taproot::create_taproot_dlc_transactions(
+        secp,
+        &offered_contract.offer_params,
+        accept_params,
+        &offered_contract.contract_info[0].get_payouts(total_collateral)?,
+        offered_contract.refund_locktime,
+        offered_contract.fee_rate_per_vb,
+        0,
+        offered_contract.cet_locktime,
+        offered_contract.fund_output_serial_id,
+        offered_contract.anchor_outputs,
+        offered_contract.fee_split,
+    )
+    .context(&offered_contract.id, "creating DLC transactions")?;
+    let funding_info = dlc_transactions.funding_info.clone();
+
+    Ok((dlc_transactions.into(), funding_info))
+}
+
+fn get_funding_info(
+    secp: &Secp256k1<All>,
+    accepted_contract: &AcceptedContract,
+) -> Result<TaprootFundingInfo, Error> {
+    Ok(TaprootFundingInfo::new(
+        secp,
+        &accepted_contract.offered_contract.offer_params.fund_pubkey,
+        &accepted_contract.accept_params.fund_pubkey,
+    )?)
+}
+
+/// Returns the signatures of the counter party and its fund public key.
+fn get_counter_signatures(
+    contract: &SignedContract,
+) -> Result<(&TaprootSignatures, &PublicKey), Error> {
+    let accepted_contract = &contract.accepted_contract;
+    let offered_contract = &accepted_contract.offered_contract;
+    let (signatures, pubkey) = if offered_contract.is_offer_party {
+        (
+            &accepted_contract.accept_taproot_signatures,
+            &accepted_contract.accept_params.fund_pubkey,
+        )
+    } else {
+        (
+            &contract.offer_taproot_signatures,
+            &offered_contract.offer_params.fund_pubkey,
+        )
+    };
+    let signatures = signatures.as_ref().ok_or_else(|| {
+        Error::InvalidState("Missing taproot signatures of the counter party".to_string())
+    })?;
+    Ok((signatures, pubkey))
+}
+
+fn sign_transactions<X: ContractSigner>(
+    secp: &Secp256k1<All>,
+    offered_contract: &OfferedContract,
+    adaptor_info: &AdaptorInfo,
+    dlc_transactions: &DlcTransactions,
+    funding_info: &TaprootFundingInfo,
+    signer: &X,
+) -> Result<(Vec<SchnorrAdaptorSignature>, SchnorrSignature), Error> {
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+    let funding_sk = signer.get_secret_key()?;
+
+    let cet_adaptor_signatures = offered_contract.contract_info[0]
+        .get_adaptor_points(secp, adaptor_info)?
+        .into_iter()
+        .map(|(cet_index, adaptor_point)| {
+            Ok(dlc::taproot::create_cet_adaptor_sig_from_point(
+                secp,
+                &dlc_transactions.cets[cet_index],
+                &adaptor_point,
+                &funding_sk,
+                funding_info,
+                fund_output_value,
+            )?)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let refund_signature = dlc::taproot::sign_script_spend(
+        secp,
+        &dlc_transactions.refund,
+        &funding_sk,
+        funding_info,
+        fund_output_value,
+    )
+    .context(&offered_contract.id, "signing refund transaction")?;
+
+    Ok((cet_adaptor_signatures, refund_signature))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn verify_signatures(
+    secp: &Secp256k1<All>,
+    offered_contract: &OfferedContract,
+    adaptor_info: &AdaptorInfo,
+    dlc_transactions: &DlcTransactions,
+    funding_info: &TaprootFundingInfo,
+    counter_pubkey: &PublicKey,
+    cet_adaptor_signatures: &[SchnorrAdaptorSignature],
+    refund_signature: &SchnorrSignature,
+) -> Result<(), Error> {
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+
+    dlc::taproot::verify_script_spend_sig(
+        secp,
+        refund_signature,
+        &dlc_transactions.refund,
+        counter_pubkey,
+        funding_info,
+        fund_output_value,
+    )
+    .context(&offered_contract.id, "verifying refund signature")?;
+
+    let adaptor_points =
+        offered_contract.contract_info[0].get_adaptor_points(secp, adaptor_info)?;
+    if adaptor_points.len() != cet_adaptor_signatures.len() {
+        return Err(Error::InvalidParameters(
+            "Invalid number of CET adaptor signatures".to_string(),
+        ));
+    }
+
+    for ((cet_index, adaptor_point), adaptor_signature) in
+        adaptor_points.iter().zip(cet_adaptor_signatures.iter())
+    {
+        dlc::taproot::verify_cet_adaptor_sig_from_point(
+            secp,
+            adaptor_signature,
+            &dlc_transactions.cets[*cet_index],
+            adaptor_point,
+            counter_pubkey,
+            funding_info,
+            fund_output_value,
+        )
+        .context(&offered_contract.id, "verifying CET adaptor signature")?;
+    }
+
+    Ok(())
+}
@@ -2,20 +2,21 @@
 use std::ops::Deref;
 
 use bitcoin::{consensus::Encodable, Txid};
-use dlc::{PartyParams, TxInputInfo};
+use dlc::{FeeSplit, PartyParams, TxInputInfo};
 use dlc_messages::{
-    oracle_msgs::{OracleAnnouncement, OracleAttestation},
+    oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation},
     FundingInput,
 };
 use dlc_trie::RangeInfo;
+use lightning::util::ser::Writeable;
 #[cfg(not(feature = "fuzztarget"))]
 use secp256k1_zkp::rand::{thread_rng, Rng, RngCore};
-use secp256k1_zkp::{PublicKey, Secp256k1, Signing};
+use secp256k1_zkp::{hashes::sha256, Message, PublicKey, Secp256k1, Signing, Verification};
 
 use crate::{
     channel::party_points::PartyBasePoints,
     contract::{contract_info::ContractInfo, AdaptorInfo},
-    error::Error,
+    error::{AnnouncementError, Error},
     Blockchain, ContractSigner, ContractSignerProvider, Wallet,
 };
 
@@ -59,9 +60,63 @@ pub(crate) fn compute_id(
     res
 }
 
+/// Releases the utxos reserved for the funding inputs of the given party
+/// params, so that they can be used for other contracts.
+pub(crate) fn release_party_utxos<W: Deref>(
+    wallet: &W,
+    party_params: &PartyParams,
+) -> Result<(), Error>
+where
+    W::Target: Wallet,
+{
+    let outpoints = party_params
+        .inputs
+        .iter()
+        .map(|x| x.outpoint)
+        .collect::<Vec<_>>();
+    wallet.release_utxos(&outpoints)
+}
+
+/// Releases the utxos reserved for the given party params if the result is an
+/// [`Error::Cancelled`], so that they can be used for other contracts.
+pub(crate) fn release_on_cancellation<W: Deref, T>(
+    wallet: &W,
+    party_params: &PartyParams,
+    res: Result<T, Error>,
+) -> Result<T, Error>
+where
+    W::Target: Wallet,
+{
+    if let Err(Error::Cancelled) = res {
+        release_party_utxos(wallet, party_params)?;
+    }
+    res
+}
+
+/// Returns the approximate fees that a party funds for a contract with the
+/// given collaterals, whose fees are split according to `fee_split`.
+pub(crate) fn get_approximate_fee(
+    fee_rate: u64,
+    fee_split: FeeSplit,
+    is_offer_party: bool,
+    offer_collateral: u64,
+    accept_collateral: u64,
+) -> Result<u64, Error> {
+    // Base cost of fund tx + CET / 2 and a CET output.
+    let fee = get_half_common_fee(fee_rate)? + dlc::util::weight_to_fee(124, fee_rate)?;
+    let (offer_fee, accept_fee) =
+        fee_split.split_fees(fee, fee, offer_collateral, accept_collateral)?;
+    Ok(if is_offer_party {
+        offer_fee
+    } else {
+        accept_fee
+    })
+}
+
 pub(crate) fn get_party_params<W: Deref, B: Deref, X: ContractSigner, C: Signing>(
     secp: &Secp256k1<C>,
     own_collateral: u64,
+    own_fee: u64,
     fee_rate: u64,
     wallet: &W,
     signer: &X,
@@ -80,10 +135,14 @@ where
     let change_spk = change_addr.script_pubkey();
     let change_serial_id = get_new_serial_id();
 
-    // Add base cost of fund tx + CET / 2 and a CET output to the collateral.
-    let appr_required_amount =
-        own_collateral + get_half_common_fee(fee_rate)? + dlc::util::weight_to_fee(124, fee_rate)?;
-    let utxos = wallet.get_utxos_for_amount(appr_required_amount, fee_rate, true)?;
+    let appr_required_amount = own_collateral + own_fee;
+    // A party that provides neither collateral nor fees, such as the accept
+    // party of a single funded contract, does not contribute any input.
+    let utxos = if appr_required_amount == 0 {
+        Vec::new()
+    } else {
+        wallet.get_utxos_for_amount(appr_required_amount, fee_rate, true)?
+    };
 
     let mut funding_inputs: Vec<FundingInput> = Vec::new();
     let mut funding_tx_info: Vec<TxInputInfo> = Vec::new();
@@ -94,8 +153,9 @@ where
         prev_tx.consensus_encode(&mut writer)?;
         let prev_tx_vout = utxo.outpoint.vout;
         let sequence = 0xffffffff;
-        // TODO(tibo): this assumes P2WPKH with low R
-        let max_witness_len = 107;
+        let max_witness_len = dlc::util::get_single_key_max_witness_len(
+            &prev_tx.output[prev_tx_vout as usize].script_pubkey,
+        ) as u16;
         let funding_input = FundingInput {
             input_serial_id: get_new_serial_id(),
             prev_tx: writer,
@@ -140,6 +200,23 @@ where
     })
 }
 
+/// Checks that a contract funded with a taproot output can be created, which
+/// requires the `taproot` feature and a single contract info, the adaptor
+/// signatures being created for the adaptor points of a single set of CETs.
+pub(crate) fn check_taproot_support(nb_contract_infos: usize) -> Result<(), Error> {
+    if !cfg!(feature = "taproot") {
+        return Err(Error::InvalidParameters(
+            "Contracts funded with a taproot output require the taproot feature".to_string(),
+        ));
+    }
+    if nb_contract_infos != 1 {
+        return Err(Error::InvalidParameters(
+            "Contracts funded with a taproot output must have a single contract info".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 pub(crate) fn get_half_common_fee(fee_rate: u64) -> Result<u64, Error> {
     let common_fee = dlc::util::get_common_fee(fee_rate)?;
     Ok((common_fee as f64 / 2_f64).ceil() as u64)
@@ -184,6 +261,70 @@ pub(crate) fn get_latest_maturity_date(
         })
 }
 
+/// Checks that the given announcement is signed by the oracle, that it
+/// contains a nonce for each outcome digit of the event and that the event
+/// matures between the given CET and refund locktimes.
+pub fn validate_announcement<C: Verification>(
+    secp: &Secp256k1<C>,
+    announcement: &OracleAnnouncement,
+    cet_locktime: u32,
+    refund_locktime: u32,
+) -> Result<(), Error> {
+    let msg = Message::from_hashed_data::<sha256::Hash>(&announcement.oracle_event.encode());
+    secp.verify_schnorr(
+        &announcement.announcement_signature,
+        &msg,
+        &announcement.oracle_public_key,
+    )
+    .map_err(|_| Error::InvalidAnnouncement(AnnouncementError::InvalidSignature))?;
+
+    let event = &announcement.oracle_event;
+    let expected = match &event.event_descriptor {
+        EventDescriptor::EnumEvent(_) => 1,
+        EventDescriptor::DigitDecompositionEvent(d) => d.nb_digits as usize,
+    };
+    if event.oracle_nonces.len() != expected {
+        return Err(Error::InvalidAnnouncement(
+            AnnouncementError::NonceCountMismatch {
+                expected,
+                actual: event.oracle_nonces.len(),
+            },
+        ));
+    }
+
+    if event.event_maturity_epoch < cet_locktime || event.event_maturity_epoch >= refund_locktime {
+        return Err(Error::InvalidAnnouncement(
+            AnnouncementError::MaturityMismatch {
+                event_maturity: event.event_maturity_epoch,
+                cet_locktime,
+                refund_locktime,
+            },
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that the attestation contains a signature from the oracle for each
+/// of its outcomes.
+pub(crate) fn verify_attestation<C: Verification>(
+    secp: &Secp256k1<C>,
+    attestation: &OracleAttestation,
+) -> Result<(), Error> {
+    if attestation.signatures.len() != attestation.outcomes.len() {
+        return Err(Error::InvalidState(
+            "Attestation has mismatching signatures and outcomes.".to_string(),
+        ));
+    }
+
+    for (signature, outcome) in attestation.signatures.iter().zip(&attestation.outcomes) {
+        let msg = Message::from_hashed_data::<sha256::Hash>(outcome.as_bytes());
+        secp.verify_schnorr(signature, &msg, &attestation.oracle_public_key)?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -240,4 +381,71 @@ mod tests {
             oracle_event: OracleEvent { oracle_nonces: vec![xonly_pk], event_maturity_epoch: maturity,event_descriptor: EventDescriptor::EnumEvent(EnumEventDescriptor { outcomes: vec!["1".to_string(), "2".to_string()] }), event_id: "01".to_string() },
         }
     }
+
+    fn get_signed_announcement(
+        keypair: &secp256k1_zkp::KeyPair,
+        nb_nonces: usize,
+        event_maturity_epoch: u32,
+    ) -> OracleAnnouncement {
+        let oracle_event = OracleEvent {
+            oracle_nonces: (0..nb_nonces)
+                .map(|_| keypair.x_only_public_key().0)
+                .collect(),
+            event_maturity_epoch,
+            event_descriptor: EventDescriptor::EnumEvent(EnumEventDescriptor {
+                outcomes: vec!["1".to_string(), "2".to_string()],
+            }),
+            event_id: "test".to_string(),
+        };
+        let msg = Message::from_hashed_data::<sha256::Hash>(&oracle_event.encode());
+        OracleAnnouncement {
+            announcement_signature: secp256k1_zkp::SECP256K1
+                .sign_schnorr_no_aux_rand(&msg, keypair),
+            oracle_public_key: keypair.x_only_public_key().0,
+            oracle_event,
+        }
+    }
+
+    #[test]
+    fn validate_announcement_test() {
+        let secp = Secp256k1::new();
+        let keypair = secp256k1_zkp::KeyPair::new(&secp, &mut thread_rng());
+        let other_keypair = secp256k1_zkp::KeyPair::new(&secp, &mut thread_rng());
+
+        let announcement = get_signed_announcement(&keypair, 1, 100);
+        validate_announcement(&secp, &announcement, 50, 200).expect("to be valid");
+        validate_announcement(&secp, &announcement, 100, 101).expect("to be valid");
+
+        let mut bad_signature = announcement.clone();
+        bad_signature.oracle_public_key = other_keypair.x_only_public_key().0;
+        assert!(matches!(
+            validate_announcement(&secp, &bad_signature, 50, 200),
+            Err(Error::InvalidAnnouncement(
+                AnnouncementError::InvalidSignature
+            ))
+        ));
+
+        assert!(matches!(
+            validate_announcement(&secp, &get_signed_announcement(&keypair, 2, 100), 50, 200),
+            Err(Error::InvalidAnnouncement(
+                AnnouncementError::NonceCountMismatch {
+                    expected: 1,
+                    actual: 2
+                }
+            ))
+        ));
+
+        assert!(matches!(
+            validate_announcement(&secp, &announcement, 150, 200),
+            Err(Error::InvalidAnnouncement(
+                AnnouncementError::MaturityMismatch { .. }
+            ))
+        ));
+        assert!(matches!(
+            validate_announcement(&secp, &announcement, 50, 100),
+            Err(Error::InvalidAnnouncement(
+                AnnouncementError::MaturityMismatch { .. }
+            ))
+        ));
+    }
 }
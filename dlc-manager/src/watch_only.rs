@@ -0,0 +1,224 @@
+//! #WatchOnlyManager a component to audit DLCs without taking part in them.
+
+use super::{Blockchain, Storage};
+use crate::chain_monitor::ChainMonitor;
+use crate::contract::{
+    signed_contract::SignedContract, ClosedContract, Contract, PreClosedContract,
+};
+use crate::contract_updater::verify_contract_messages;
+use crate::error::Error;
+use crate::manager::NB_CONFIRMATIONS;
+use crate::ContractId;
+use bitcoin::{OutPoint, Transaction};
+use dlc_messages::{AcceptDlc, OfferDlc, SignDlc};
+use log::error;
+use secp256k1_zkp::{All, PublicKey, Secp256k1};
+use std::collections::HashMap;
+use std::ops::Deref;
+
+/// Used to verify and track DLCs without holding any key material. Contracts
+/// are imported from the set of messages exchanged by the parties, and their
+/// state is then updated by monitoring the blockchain.
+///
+/// Note that the profit and loss of closed contracts is computed from the
+/// point of view of the accepting party.
+pub struct WatchOnlyManager<B: Deref, S: Deref>
+where
+    B::Target: Blockchain,
+    S::Target: Storage,
+{
+    blockchain: B,
+    store: S,
+    secp: Secp256k1<All>,
+    chain_monitor: ChainMonitor,
+}
+
+impl<B: Deref, S: Deref> WatchOnlyManager<B, S>
+where
+    B::Target: Blockchain,
+    S::Target: Storage,
+{
+    /// Create a new WatchOnlyManager struct.
+    pub fn new(blockchain: B, store: S) -> Result<Self, Error> {
+        let init_height = blockchain.get_blockchain_height()?;
+        let chain_monitor = store
+            .get_chain_monitor()?
+            .unwrap_or(ChainMonitor::new(init_height));
+
+        Ok(WatchOnlyManager {
+            blockchain,
+            store,
+            secp: secp256k1_zkp::Secp256k1::new(),
+            chain_monitor,
+        })
+    }
+
+    /// Get the store from the WatchOnlyManager to access contracts.
+    pub fn get_store(&self) -> &S {
+        &self.store
+    }
+
+    /// Verifies the given set of messages exchanged between the offering party
+    /// (whose node id is given as `offer_party`) and the accepting party, and
+    /// stores the resulting contract in the signed state, returning its id.
+    pub fn import_contract(
+        &mut self,
+        offer_msg: &OfferDlc,
+        accept_msg: &AcceptDlc,
+        sign_msg: &SignDlc,
+        offer_party: PublicKey,
+    ) -> Result<ContractId, Error> {
+        let signed_contract =
+            verify_contract_messages(&self.secp, offer_msg, accept_msg, sign_msg, offer_party)?;
+        let contract_id = signed_contract.accepted_contract.get_contract_id();
+
+        if self.store.get_contract(&contract_id)?.is_some()
+            || self
+                .store
+                .get_contract(&signed_contract.accepted_contract.offered_contract.id)?
+                .is_some()
+        {
+            return Err(Error::InvalidParameters(
+                "Contract with identical id already exists".to_string(),
+            ));
+        }
+
+        self.store
+            .create_contract(&signed_contract.accepted_contract.offered_contract)?;
+        self.store
+            .update_contract(&Contract::Signed(signed_contract))?;
+
+        Ok(contract_id)
+    }
+
+    /// Function to call to update the state of the imported contracts based on
+    /// the transactions observed on the blockchain.
+    pub fn periodic_check(&mut self) -> Result<(), Error> {
+        self.check_signed_contracts()?;
+        self.check_for_closing_tx()?;
+        self.check_preclosed_contracts()?;
+
+        Ok(())
+    }
+
+    fn check_signed_contracts(&mut self) -> Result<(), Error> {
+        for c in self.store.get_signed_contracts()? {
+            let confirmations = match self
+                .blockchain
+                .get_transaction_confirmations(&c.accepted_contract.dlc_transactions.fund.txid())
+            {
+                Ok(confirmations) => confirmations,
+                Err(e) => {
+                    error!(
+                        "Error checking signed contract {}: {}",
+                        c.accepted_contract.get_contract_id_string(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            if confirmations >= NB_CONFIRMATIONS {
+                self.store.update_contract(&Contract::Confirmed(c))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_for_closing_tx(&mut self) -> Result<(), Error> {
+        let cur_height = self.blockchain.get_blockchain_height()?;
+        let last_height = self.chain_monitor.last_height;
+
+        if cur_height < last_height {
+            return Err(Error::InvalidState(
+                "Current height is lower than last height.".to_string(),
+            ));
+        }
+
+        let mut watched: HashMap<OutPoint, SignedContract> = self
+            .store
+            .get_signed_contracts()?
+            .into_iter()
+            .chain(self.store.get_confirmed_contracts()?)
+            .map(|c| (c.accepted_contract.dlc_transactions.get_fund_outpoint(), c))
+            .collect();
+
+        for height in last_height + 1..cur_height {
+            let block = self.blockchain.get_block_at_height(height)?;
+
+            for tx in &block.txdata {
+                let spent = tx
+                    .input
+                    .iter()
+                    .find_map(|i| watched.remove(&i.previous_output));
+                if let Some(contract) = spent {
+                    self.on_closing_tx(contract, tx)?;
+                }
+            }
+
+            self.chain_monitor.increment_height(&block.block_hash());
+        }
+
+        self.store.persist_chain_monitor(&self.chain_monitor)?;
+
+        Ok(())
+    }
+
+    fn on_closing_tx(
+        &mut self,
+        contract: SignedContract,
+        closing_tx: &Transaction,
+    ) -> Result<(), Error> {
+        let updated =
+            if contract.accepted_contract.dlc_transactions.refund.txid() == closing_tx.txid() {
+                Contract::Refunded(contract)
+            } else {
+                Contract::PreClosed(PreClosedContract {
+                    signed_contract: contract,
+                    attestations: None,
+                    signed_cet: closing_tx.clone(),
+                })
+            };
+
+        self.store.update_contract(&updated)
+    }
+
+    fn check_preclosed_contracts(&mut self) -> Result<(), Error> {
+        for c in self.store.get_preclosed_contracts()? {
+            let confirmations = match self
+                .blockchain
+                .get_transaction_confirmations(&c.signed_cet.txid())
+            {
+                Ok(confirmations) => confirmations,
+                Err(e) => {
+                    error!(
+                        "Error checking pre-closed contract {}: {}",
+                        c.signed_contract.accepted_contract.get_contract_id_string(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            if confirmations >= NB_CONFIRMATIONS {
+                let accepted_contract = &c.signed_contract.accepted_contract;
+                let closed_contract = ClosedContract {
+                    pnl: accepted_contract.compute_pnl(&c.signed_cet),
+                    contract_id: accepted_contract.get_contract_id(),
+                    temporary_contract_id: accepted_contract.offered_contract.id,
+                    metadata: accepted_contract.offered_contract.metadata.clone(),
+                    counter_party_id: accepted_contract.offered_contract.counter_party,
+                    announcements: c
+                        .attestations
+                        .as_ref()
+                        .map(|a| c.signed_contract.get_attested_announcements(a)),
+                    attestations: c.attestations,
+                    signed_cet: Some(c.signed_cet),
+                };
+                self.store
+                    .update_contract(&Contract::Closed(closed_contract))?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -7,9 +7,10 @@ use bitcoincore_rpc::RpcApi;
 use dlc_manager::contract::contract_input::ContractInput;
 use dlc_manager::manager::Manager;
 use dlc_manager::{
-    channel::{signed_channel::SignedChannelState, Channel},
+    channel::{signed_channel::SignedChannelState, Channel, Quiescence},
     contract::Contract,
-    Blockchain, CachedContractSignerProvider, Oracle, SimpleSigner, Storage, Wallet,
+    Blockchain, CachedContractSignerProvider, ContractSigner, ContractSignerProvider, Oracle,
+    SimpleSigner, Storage, Wallet,
 };
 use dlc_manager::{ChannelId, ContractId};
 use dlc_messages::Message;
@@ -21,7 +22,7 @@ use mocks::mock_time::MockTime;
 use secp256k1_zkp::rand::{thread_rng, RngCore};
 use secp256k1_zkp::EcdsaAdaptorSignature;
 use simple_wallet::SimpleWallet;
-use test_utils::{get_enum_test_params, TestParams};
+use test_utils::{get_enum_contract_input_with_collaterals, get_enum_test_params, TestParams};
 
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
@@ -89,12 +90,14 @@ enum TestPath {
     RenewedClose,
     SettleCheat,
     CollaborativeClose,
+    CollaborativeClosePsbt,
     SettleRenewSettle,
     SettleOfferTimeout,
     SettleAcceptTimeout,
     SettleConfirmTimeout,
     SettleReject,
     SettleRace,
+    StopRaceSettle,
     RenewOfferTimeout,
     RenewAcceptTimeout,
     RenewConfirmTimeout,
@@ -102,8 +105,19 @@ enum TestPath {
     RenewRace,
     RenewEstablishedClose,
     CancelOffer,
+    MultiContractSettle,
+    MultiContractClose,
+    MultiContractRecover,
 }
 
+/// The collateral of the offer party of the contracts added to a channel.
+const ADDED_OFFER_COLLATERAL: u64 = 400000;
+/// The collateral of the accept party of the contracts added to a channel.
+const ADDED_ACCEPT_COLLATERAL: u64 = 10000000;
+/// The payout of the counter party of the channel settlement preceding the
+/// addition of contracts.
+const SETTLE_COUNTER_PAYOUT: u64 = 100000000;
+
 #[test]
 #[ignore]
 fn channel_established_close_test() {
@@ -170,6 +184,15 @@ fn channel_collaborative_close_test() {
     );
 }
 
+#[test]
+#[ignore]
+fn channel_collaborative_close_psbt_test() {
+    channel_execution_test(
+        get_enum_test_params(1, 1, None),
+        TestPath::CollaborativeClosePsbt,
+    );
+}
+
 #[test]
 #[ignore]
 fn channel_settle_renew_settle_test() {
@@ -218,6 +241,12 @@ fn channel_settle_race_test() {
     channel_execution_test(get_enum_test_params(1, 1, None), TestPath::SettleRace);
 }
 
+#[test]
+#[ignore]
+fn channel_stop_race_settle_test() {
+    channel_execution_test(get_enum_test_params(1, 1, None), TestPath::StopRaceSettle);
+}
+
 #[test]
 #[ignore]
 fn channel_renew_offer_timeout_test() {
@@ -257,6 +286,33 @@ fn channel_renew_race_test() {
     channel_execution_test(get_enum_test_params(1, 1, None), TestPath::RenewRace);
 }
 
+#[test]
+#[ignore]
+fn channel_multi_contract_settle_test() {
+    channel_execution_test(
+        get_enum_test_params(1, 1, None),
+        TestPath::MultiContractSettle,
+    );
+}
+
+#[test]
+#[ignore]
+fn channel_multi_contract_close_test() {
+    channel_execution_test(
+        get_enum_test_params(1, 1, None),
+        TestPath::MultiContractClose,
+    );
+}
+
+#[test]
+#[ignore]
+fn channel_multi_contract_recover_test() {
+    channel_execution_test(
+        get_enum_test_params(1, 1, None),
+        TestPath::MultiContractRecover,
+    );
+}
+
 #[test]
 #[ignore]
 fn channel_offer_reject_test() {
@@ -361,8 +417,8 @@ fn channel_execution_test(test_params: TestParams, path: TestPath) {
             Arc::clone(&alice_wallet),
             Arc::clone(&alice_wallet),
             Arc::clone(&electrs),
-            alice_store,
-            alice_oracles,
+            Arc::clone(&alice_store),
+            alice_oracles.clone(),
             Arc::clone(&mock_time),
             Arc::clone(&electrs),
         )
@@ -378,7 +434,7 @@ fn channel_execution_test(test_params: TestParams, path: TestPath) {
             Arc::clone(&bob_wallet),
             Arc::clone(&electrs),
             Arc::clone(&bob_store),
-            bob_oracles,
+            bob_oracles.clone(),
             Arc::clone(&mock_time),
             Arc::clone(&electrs),
         )
@@ -550,6 +606,28 @@ fn channel_execution_test(test_params: TestParams, path: TestPath) {
             assert_contract_state!(alice_manager_send, contract_id, Confirmed);
             assert_contract_state!(bob_manager_send, contract_id, Confirmed);
 
+            let alice_party = Arc::clone(&alice_manager_send);
+
+            // Replaces the manager of the given party with a new one using the
+            // same wallet and store, as done when restarting the application.
+            let restart = |party: &DlcParty| {
+                let (wallet, store, oracles) = if Arc::ptr_eq(party, &alice_party) {
+                    (&alice_wallet, &alice_store, &alice_oracles)
+                } else {
+                    (&bob_wallet, &bob_store, &bob_oracles)
+                };
+                *party.lock().unwrap() = Manager::new(
+                    Arc::clone(wallet),
+                    Arc::clone(wallet),
+                    Arc::clone(&electrs),
+                    Arc::clone(store),
+                    oracles.clone(),
+                    Arc::clone(&mock_time),
+                    Arc::clone(&electrs),
+                )
+                .unwrap();
+            };
+
             // Select the first one to close or refund randomly
             let (first, first_send, second, second_send) = if thread_rng().next_u32() % 2 == 0 {
                 (alice_manager_send, &alice_send, bob_manager_send, &bob_send)
@@ -571,6 +649,22 @@ fn channel_execution_test(test_params: TestParams, path: TestPath) {
                         &generate_blocks,
                     );
                 }
+                TestPath::CollaborativeClosePsbt => {
+                    let second_wallet = if Arc::ptr_eq(&second, &alice_party) {
+                        &alice_wallet
+                    } else {
+                        &bob_wallet
+                    };
+                    collaborative_close_with_psbt(
+                        first,
+                        first_send,
+                        second,
+                        second_wallet,
+                        channel_id,
+                        &sync_receive,
+                        &generate_blocks,
+                    );
+                }
                 TestPath::SettleOfferTimeout
                 | TestPath::SettleAcceptTimeout
                 | TestPath::SettleConfirmTimeout => {
@@ -604,6 +698,16 @@ fn channel_execution_test(test_params: TestParams, path: TestPath) {
                         &sync_receive,
                     );
                 }
+                TestPath::StopRaceSettle => {
+                    stop_race_settle(
+                        first,
+                        first_send,
+                        second,
+                        second_send,
+                        channel_id,
+                        &sync_receive,
+                    );
+                }
                 _ => {
                     // Shuffle positions
                     let (first, first_send, second, second_send) =
@@ -714,6 +818,99 @@ fn channel_execution_test(test_params: TestParams, path: TestPath) {
                                 cheat_punish(first, second, channel_id, &generate_blocks, false);
                             }
                         }
+                        TestPath::MultiContractSettle => {
+                            let contract_input = get_enum_contract_input_with_collaterals(
+                                &test_params.contract_input,
+                                ADDED_OFFER_COLLATERAL,
+                                ADDED_ACCEPT_COLLATERAL,
+                            );
+                            let first_contract_id = add_channel_contract(
+                                first.clone(),
+                                first_send,
+                                second.clone(),
+                                second_send,
+                                channel_id,
+                                &sync_receive,
+                                &contract_input,
+                                SETTLE_COUNTER_PAYOUT,
+                            );
+                            let second_contract_id = add_channel_contract(
+                                first.clone(),
+                                first_send,
+                                second.clone(),
+                                second_send,
+                                channel_id,
+                                &sync_receive,
+                                &contract_input,
+                                SETTLE_COUNTER_PAYOUT - ADDED_ACCEPT_COLLATERAL,
+                            );
+
+                            settle_channel_contract(
+                                second.clone(),
+                                second_send,
+                                first.clone(),
+                                first_send,
+                                channel_id,
+                                first_contract_id,
+                                &sync_receive,
+                            );
+                            assert_contract_state!(first, second_contract_id, Confirmed);
+                            assert_contract_state!(second, second_contract_id, Confirmed);
+
+                            settle_channel_contract(
+                                first.clone(),
+                                first_send,
+                                second.clone(),
+                                second_send,
+                                channel_id,
+                                second_contract_id,
+                                &sync_receive,
+                            );
+
+                            close_contracts_channel(first, second, channel_id, &generate_blocks);
+                        }
+                        TestPath::MultiContractClose | TestPath::MultiContractRecover => {
+                            let contract_input = get_enum_contract_input_with_collaterals(
+                                &test_params.contract_input,
+                                ADDED_OFFER_COLLATERAL,
+                                ADDED_ACCEPT_COLLATERAL,
+                            );
+                            add_channel_contract(
+                                first.clone(),
+                                first_send,
+                                second.clone(),
+                                second_send,
+                                channel_id,
+                                &sync_receive,
+                                &contract_input,
+                                SETTLE_COUNTER_PAYOUT,
+                            );
+                            add_channel_contract(
+                                first.clone(),
+                                first_send,
+                                second.clone(),
+                                second_send,
+                                channel_id,
+                                &sync_receive,
+                                &contract_input,
+                                SETTLE_COUNTER_PAYOUT - ADDED_ACCEPT_COLLATERAL,
+                            );
+
+                            if let TestPath::MultiContractRecover = path {
+                                // The restarted manager is not watching the
+                                // buffer transaction anymore as the memory
+                                // storage does not persist the chain monitor.
+                                restart(&second);
+                                let report = second
+                                    .lock()
+                                    .unwrap()
+                                    .recover()
+                                    .expect("to be able to recover");
+                                assert_eq!(vec![channel_id], report.rewatched_channels);
+                            }
+
+                            close_contracts_channel(first, second, channel_id, &generate_blocks);
+                        }
                         TestPath::SettleRenewSettle => {
                             renew_channel(
                                 first.clone(),
@@ -863,7 +1060,7 @@ fn settle_channel(
     let (settle_offer, _) = first
         .lock()
         .unwrap()
-        .settle_offer(&channel_id, 100000000)
+        .settle_offer(&channel_id, SETTLE_COUNTER_PAYOUT)
         .expect("to be able to offer a settlement of the contract.");
 
     first_send
@@ -981,6 +1178,285 @@ fn settle_race(
     assert_channel_state!(second, channel_id, Signed, Established);
 }
 
+fn stop_race_settle(
+    first: DlcParty,
+    first_send: &Sender<Option<Message>>,
+    second: DlcParty,
+    second_send: &Sender<Option<Message>>,
+    channel_id: ChannelId,
+    sync_receive: &Receiver<()>,
+) {
+    let (stop, _) = first
+        .lock()
+        .unwrap()
+        .stop_channel(&channel_id)
+        .expect("to be able to stop the channel.");
+
+    let (stop_2, _) = second
+        .lock()
+        .unwrap()
+        .stop_channel(&channel_id)
+        .expect("to be able to stop the channel.");
+
+    first_send.send(Some(Message::Stop(stop))).unwrap();
+
+    second_send.send(Some(Message::Stop(stop_2))).unwrap();
+
+    // Process 2 stop messages
+    sync_receive.recv().expect("Error synchronizing");
+    sync_receive.recv().expect("Error synchronizing");
+
+    let first_is_initiator = match first.lock().unwrap().get_quiescence(&channel_id) {
+        Some(Quiescence::Quiescent { is_initiator }) => is_initiator,
+        q => panic!("Expected channel to be quiescent but was {:?}", q),
+    };
+
+    let (initiator, initiator_send, other, other_send) = if first_is_initiator {
+        (first, first_send, second, second_send)
+    } else {
+        (second, second_send, first, first_send)
+    };
+
+    assert_eq!(
+        Some(Quiescence::Quiescent {
+            is_initiator: false
+        }),
+        other.lock().unwrap().get_quiescence(&channel_id)
+    );
+
+    other
+        .lock()
+        .unwrap()
+        .settle_offer(&channel_id, 100000000)
+        .expect_err("the non initiator not to be able to offer a settlement.");
+
+    settle_channel(
+        initiator.clone(),
+        initiator_send,
+        other.clone(),
+        other_send,
+        channel_id,
+        sync_receive,
+    );
+
+    assert!(initiator
+        .lock()
+        .unwrap()
+        .get_quiescence(&channel_id)
+        .is_none());
+    assert!(other.lock().unwrap().get_quiescence(&channel_id).is_none());
+}
+
+fn get_channel_contract_ids(dlc_party: &DlcParty, channel_id: &ChannelId) -> Vec<ContractId> {
+    let channel = dlc_party
+        .lock()
+        .unwrap()
+        .get_store()
+        .get_channel(channel_id)
+        .unwrap()
+        .unwrap();
+    if let Channel::Signed(s) = channel {
+        return s.get_contract_ids();
+    }
+
+    panic!("Invalid channel state {:?}.", channel);
+}
+
+/// Adds a contract offered by the first party to the channel, returning its
+/// id.
+fn add_channel_contract(
+    first: DlcParty,
+    first_send: &Sender<Option<Message>>,
+    second: DlcParty,
+    second_send: &Sender<Option<Message>>,
+    channel_id: ChannelId,
+    sync_receive: &Receiver<()>,
+    contract_input: &ContractInput,
+    counter_balance: u64,
+) -> ContractId {
+    let prev_contract_ids = get_channel_contract_ids(&first, &channel_id);
+
+    let (add_offer, _) = first
+        .lock()
+        .unwrap()
+        .add_contract_offer(&channel_id, counter_balance, contract_input)
+        .expect("to be able to offer adding a contract");
+
+    first_send
+        .send(Some(Message::AddContractOffer(add_offer)))
+        .unwrap();
+
+    sync_receive.recv().expect("Error synchronizing");
+
+    assert_channel_state!(first, channel_id, Signed, ContractUpdateOffered);
+    assert_channel_state!(second, channel_id, Signed, ContractUpdateOffered);
+
+    let (update_accept, _) = second
+        .lock()
+        .unwrap()
+        .accept_contract_update(&channel_id)
+        .expect("to be able to accept adding a contract");
+
+    second_send
+        .send(Some(Message::ContractUpdateAccept(update_accept)))
+        .unwrap();
+
+    // Process Accept
+    sync_receive.recv().expect("Error synchronizing");
+    // Process Confirm
+    sync_receive.recv().expect("Error synchronizing");
+    // Process Finalize
+    sync_receive.recv().expect("Error synchronizing");
+
+    assert_channel_state!(first, channel_id, Signed, ContractsEstablished);
+    assert_channel_state!(second, channel_id, Signed, ContractsEstablished);
+
+    let contract_ids = get_channel_contract_ids(&first, &channel_id);
+    assert_eq!(contract_ids, get_channel_contract_ids(&second, &channel_id));
+    assert_eq!(prev_contract_ids.len() + 1, contract_ids.len());
+
+    let contract_id = *contract_ids
+        .iter()
+        .find(|x| !prev_contract_ids.contains(x))
+        .expect("to have a new contract");
+
+    for contract_id in contract_ids {
+        assert_contract_state!(first, contract_id, Confirmed);
+        assert_contract_state!(second, contract_id, Confirmed);
+    }
+
+    contract_id
+}
+
+/// Settles a contract of the channel on the first party's offer.
+fn settle_channel_contract(
+    first: DlcParty,
+    first_send: &Sender<Option<Message>>,
+    second: DlcParty,
+    second_send: &Sender<Option<Message>>,
+    channel_id: ChannelId,
+    contract_id: ContractId,
+    sync_receive: &Receiver<()>,
+) {
+    let (settle_offer, _) = first
+        .lock()
+        .unwrap()
+        .settle_contract_offer(&channel_id, &contract_id, ADDED_ACCEPT_COLLATERAL)
+        .expect("to be able to offer settling a contract");
+
+    first_send
+        .send(Some(Message::SettleContractOffer(settle_offer)))
+        .unwrap();
+
+    sync_receive.recv().expect("Error synchronizing");
+
+    assert_channel_state!(first, channel_id, Signed, ContractUpdateOffered);
+    assert_channel_state!(second, channel_id, Signed, ContractUpdateOffered);
+
+    let (update_accept, _) = second
+        .lock()
+        .unwrap()
+        .accept_contract_update(&channel_id)
+        .expect("to be able to accept settling a contract");
+
+    second_send
+        .send(Some(Message::ContractUpdateAccept(update_accept)))
+        .unwrap();
+
+    // Process Accept
+    sync_receive.recv().expect("Error synchronizing");
+    // Process Confirm
+    sync_receive.recv().expect("Error synchronizing");
+    // Process Finalize
+    sync_receive.recv().expect("Error synchronizing");
+
+    assert_channel_state!(first, channel_id, Signed, ContractsEstablished);
+    assert_channel_state!(second, channel_id, Signed, ContractsEstablished);
+    assert_contract_state!(first, contract_id, Closed);
+    assert_contract_state!(second, contract_id, Closed);
+    assert!(!get_channel_contract_ids(&first, &channel_id).contains(&contract_id));
+    assert!(!get_channel_contract_ids(&second, &channel_id).contains(&contract_id));
+}
+
+/// Force closes a channel holding several contracts, and checks that all of
+/// them get closed by both parties.
+fn close_contracts_channel<F>(
+    first: DlcParty,
+    second: DlcParty,
+    channel_id: ChannelId,
+    generate_blocks: &F,
+) where
+    F: Fn(u64),
+{
+    let contract_ids = get_channel_contract_ids(&first, &channel_id);
+
+    first
+        .lock()
+        .unwrap()
+        .force_close_channel(&channel_id)
+        .expect("to be able to unilaterally close.");
+    assert_channel_state!(first, channel_id, Signed, ContractsClosing);
+
+    generate_blocks(1);
+
+    second
+        .lock()
+        .unwrap()
+        .periodic_check(true)
+        .expect("to be able to do the periodic check");
+
+    assert_channel_state!(second, channel_id, Signed, ContractsClosing);
+
+    let wait = dlc_manager::manager::CET_NSEQUENCE;
+
+    generate_blocks(wait as u64 - 1);
+
+    first
+        .lock()
+        .unwrap()
+        .periodic_check(true)
+        .expect("to be able to do the periodic check");
+
+    // Should not have changed state before the CETs are spendable.
+    assert_channel_state!(first, channel_id, Signed, ContractsClosing);
+
+    generate_blocks(1);
+
+    first
+        .lock()
+        .unwrap()
+        .periodic_check(true)
+        .expect("to be able to do the periodic check");
+
+    assert_channel_state!(first, channel_id, Signed, Closed);
+    for contract_id in &contract_ids {
+        assert_contract_state!(first, contract_id, PreClosed);
+    }
+
+    generate_blocks(1);
+
+    second
+        .lock()
+        .unwrap()
+        .periodic_check(true)
+        .expect("to be able to do the periodic check");
+
+    assert_channel_state!(second, channel_id, Signed, Closed);
+    for contract_id in &contract_ids {
+        assert_contract_state!(second, contract_id, PreClosed);
+    }
+
+    generate_blocks(6);
+
+    first.lock().unwrap().periodic_check(true).unwrap();
+    second.lock().unwrap().periodic_check(true).unwrap();
+
+    for contract_id in &contract_ids {
+        assert_contract_state!(first, contract_id, Closed);
+        assert_contract_state!(second, contract_id, Closed);
+    }
+}
+
 fn renew_channel(
     first: DlcParty,
     first_send: &Sender<Option<Message>>,
@@ -1167,6 +1643,93 @@ fn collaborative_close<F: Fn(u64)>(
     assert_contract_state!(first, contract_id, Closed);
 }
 
+/// Same as [`collaborative_close`] except that the accepting party signs the
+/// closing transaction externally, through the PSBT provided by its manager.
+fn collaborative_close_with_psbt<F: Fn(u64)>(
+    first: DlcParty,
+    first_send: &Sender<Option<Message>>,
+    second: DlcParty,
+    second_wallet: &SimpleWallet<Arc<ElectrsBlockchainProvider>, Arc<MemoryStorage>>,
+    channel_id: ChannelId,
+    sync_receive: &Receiver<()>,
+    generate_blocks: &F,
+) {
+    let contract_id = get_established_channel_contract_id(&first, &channel_id);
+    let close_offer = first
+        .lock()
+        .unwrap()
+        .offer_collaborative_close(&channel_id, 100000000)
+        .expect("to be able to propose a collaborative close");
+    first_send
+        .send(Some(Message::CollaborativeCloseOffer(close_offer)))
+        .expect("to be able to send collaborative close");
+    sync_receive.recv().expect("Error synchronizing");
+
+    assert_channel_state!(second, channel_id, Signed, CollaborativeCloseOffered);
+
+    let mut psbt = second
+        .lock()
+        .unwrap()
+        .get_collaborative_close_psbt(&channel_id)
+        .expect("to be able to get the collaborative close PSBT");
+
+    let signed_channel = match second
+        .lock()
+        .unwrap()
+        .get_store()
+        .get_channel(&channel_id)
+        .unwrap()
+        .unwrap()
+    {
+        Channel::Signed(s) => s,
+        c => panic!("Invalid channel state {:?}.", c),
+    };
+    let signer = second_wallet
+        .derive_contract_signer(signed_channel.keys_id().expect("to have a keys id"))
+        .expect("to be able to derive the contract signer");
+    let input = &psbt.inputs[0];
+    let signature = dlc::util::get_raw_sig_for_tx_input(
+        secp256k1_zkp::SECP256K1,
+        &psbt.unsigned_tx,
+        0,
+        input
+            .witness_script
+            .as_ref()
+            .expect("to have a witness script"),
+        input
+            .witness_utxo
+            .as_ref()
+            .expect("to have a witness utxo")
+            .value,
+        &signer.get_secret_key().unwrap(),
+    )
+    .expect("to be able to sign the PSBT");
+    psbt.inputs[0].partial_sigs.insert(
+        bitcoin::PublicKey::new(signed_channel.own_params.fund_pubkey),
+        bitcoin::ecdsa::Signature::sighash_all(signature),
+    );
+
+    second
+        .lock()
+        .unwrap()
+        .accept_collaborative_close_with_psbt(&channel_id, &psbt)
+        .expect("to be able to accept a collaborative close with a PSBT");
+
+    assert_channel_state!(second, channel_id, Signed, CollaborativelyClosed);
+    assert_contract_state!(second, contract_id, Closed);
+
+    generate_blocks(2);
+
+    first
+        .lock()
+        .unwrap()
+        .periodic_check(true)
+        .expect("the check to succeed");
+
+    assert_channel_state!(first, channel_id, Signed, CollaborativelyClosed);
+    assert_contract_state!(first, contract_id, Closed);
+}
+
 fn renew_timeout(
     first: DlcParty,
     first_send: &Sender<Option<Message>>,
@@ -16,25 +16,53 @@ use test_utils::*;
 
 use bitcoin_test_utils::rpc_helpers::init_clients;
 use bitcoincore_rpc::RpcApi;
-use dlc_manager::contract::{numerical_descriptor::DifferenceParams, Contract};
+use dlc_manager::contract::{
+    contract_input::ContractInput, numerical_descriptor::DifferenceParams, Contract,
+};
 use dlc_manager::manager::Manager;
-use dlc_manager::{Blockchain, Oracle, Storage, Wallet};
+use dlc_manager::watch_only::WatchOnlyManager;
+use dlc_manager::{
+    Blockchain, CachedContractSignerProvider, ContractId, Oracle, SimpleSigner, Storage, Wallet,
+};
 use dlc_messages::oracle_msgs::OracleAttestation;
 use dlc_messages::{AcceptDlc, OfferDlc, SignDlc};
 use dlc_messages::{CetAdaptorSignatures, Message};
 use lightning::ln::wire::Type;
 use lightning::util::ser::Writeable;
+use mocks::memory_storage_provider::MemoryStorage;
+use mocks::mock_oracle_provider::MockOracle;
+use mocks::mock_time::MockTime;
 use secp256k1_zkp::rand::{thread_rng, RngCore};
-use secp256k1_zkp::{ecdsa::Signature, EcdsaAdaptorSignature};
+use secp256k1_zkp::{ecdsa::Signature, EcdsaAdaptorSignature, PublicKey};
 use serde_json::{from_str, to_writer_pretty};
 use std::collections::HashMap;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    mpsc::channel,
+    mpsc::{channel, Receiver, Sender},
     Arc, Mutex,
 };
 use std::thread;
 
+type DlcParty = Arc<
+    Mutex<
+        Manager<
+            Arc<SimpleWallet<Arc<ElectrsBlockchainProvider>, Arc<MemoryStorage>>>,
+            Arc<
+                CachedContractSignerProvider<
+                    Arc<SimpleWallet<Arc<ElectrsBlockchainProvider>, Arc<MemoryStorage>>>,
+                    SimpleSigner,
+                >,
+            >,
+            Arc<ElectrsBlockchainProvider>,
+            Arc<MemoryStorage>,
+            Arc<MockOracle>,
+            Arc<MockTime>,
+            Arc<ElectrsBlockchainProvider>,
+            SimpleSigner,
+        >,
+    >,
+>;
+
 #[derive(serde::Serialize, serde::Deserialize)]
 struct TestVectorPart<T> {
     message: T,
@@ -182,8 +210,16 @@ enum TestPath {
     BadAcceptRefundSignature,
     BadSignCetSignature,
     BadSignRefundSignature,
+    Amend,
+    MutualClose,
+    Net,
 }
 
+/// The collateral added by the offer party when amending a contract.
+const AMEND_OFFER_TOP_UP: u64 = 10000000;
+/// The collateral added by the accept party when amending a contract.
+const AMEND_ACCEPT_TOP_UP: u64 = 1000000;
+
 #[test]
 #[ignore]
 fn single_oracle_numerical_test() {
@@ -420,6 +456,28 @@ fn enum_single_oracle_bad_sign_refund_sig_test() {
     );
 }
 
+#[test]
+#[ignore]
+fn enum_single_oracle_amend_test() {
+    manager_execution_test(get_enum_test_params(1, 1, None), TestPath::Amend, false);
+}
+
+#[test]
+#[ignore]
+fn enum_single_oracle_mutual_close_test() {
+    manager_execution_test(
+        get_enum_test_params(1, 1, None),
+        TestPath::MutualClose,
+        false,
+    );
+}
+
+#[test]
+#[ignore]
+fn enum_single_oracle_net_test() {
+    manager_execution_test(get_enum_test_params(1, 1, None), TestPath::Net, false);
+}
+
 #[test]
 #[ignore]
 fn two_of_two_oracle_numerical_diff_nb_digits_test() {
@@ -712,6 +770,15 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
         }
     };
 
+    let sign_message = Arc::new(Mutex::new(None));
+    let bob_sign_message = Arc::clone(&sign_message);
+    let bob_msg_callback = move |msg: &Message| {
+        if let Message::Sign(s) = msg {
+            *bob_sign_message.lock().unwrap() = Some(s.clone());
+        }
+        msg_callback(msg);
+    };
+
     let alice_handle = receive_loop!(
         alice_receive,
         alice_manager_loop,
@@ -729,7 +796,7 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
         bob_expect_error_loop,
         bob_sync_send,
         alter_sign,
-        msg_callback
+        bob_msg_callback
     );
 
     let offer_msg = bob_manager_send
@@ -744,6 +811,7 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
         .expect("Send offer error");
 
     write_message("offer_message", offer_msg.clone());
+    let watched_offer_msg = offer_msg.clone();
     let temporary_contract_id = offer_msg.temporary_contract_id;
     bob_send.send(Some(Message::Offer(offer_msg))).unwrap();
 
@@ -788,7 +856,12 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
             sync_receive.recv().expect("Error synchronizing");
             assert_contract_state!(alice_manager_send, contract_id, FailedSign);
         }
-        TestPath::Close | TestPath::Refund => {
+        TestPath::Close
+        | TestPath::Refund
+        | TestPath::Amend
+        | TestPath::MutualClose
+        | TestPath::Net => {
+            let watched_accept_msg = accept_msg.clone();
             alice_send.send(Some(Message::Accept(accept_msg))).unwrap();
             sync_receive.recv().expect("Error synchronizing");
 
@@ -801,11 +874,99 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
 
             assert_contract_state!(alice_manager_send, contract_id, Signed);
 
+            let mut watch_only_manager = WatchOnlyManager::new(
+                Arc::clone(&electrs),
+                Arc::new(mocks::memory_storage_provider::MemoryStorage::new()),
+            )
+            .unwrap();
+            let watched_contract_id = watch_only_manager
+                .import_contract(
+                    &watched_offer_msg,
+                    &watched_accept_msg,
+                    sign_message
+                        .lock()
+                        .unwrap()
+                        .as_ref()
+                        .expect("to have received the sign message"),
+                    "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166"
+                        .parse()
+                        .unwrap(),
+                )
+                .expect("to be able to import the contract");
+            assert_eq!(contract_id, watched_contract_id);
+
             generate_blocks(6);
 
             periodic_check!(alice_manager_send, contract_id, Confirmed);
             periodic_check!(bob_manager_send, contract_id, Confirmed);
 
+            watch_only_manager
+                .periodic_check()
+                .expect("Periodic check error");
+            assert!(matches!(
+                watch_only_manager.get_store().get_contract(&contract_id),
+                Ok(Some(Contract::Confirmed(_)))
+            ));
+
+            // The contract replacing the initial one is closed instead.
+            let contract_id = match path {
+                TestPath::Amend => {
+                    let contract_input = get_enum_contract_input_with_collaterals(
+                        &test_params.contract_input,
+                        test_params.contract_input.offer_collateral + AMEND_OFFER_TOP_UP,
+                        test_params.contract_input.accept_collateral + AMEND_ACCEPT_TOP_UP,
+                    );
+                    amend_contract(
+                        Arc::clone(&bob_manager_send),
+                        &bob_send,
+                        Arc::clone(&alice_manager_send),
+                        &alice_send,
+                        contract_id,
+                        &sync_receive,
+                        &contract_input,
+                        &generate_blocks,
+                    )
+                }
+                TestPath::Net => {
+                    let other_contract_id = establish_contract(
+                        Arc::clone(&bob_manager_send),
+                        &bob_send,
+                        Arc::clone(&alice_manager_send),
+                        "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166"
+                            .parse()
+                            .unwrap(),
+                        &alice_send,
+                        &sync_receive,
+                        &test_params.contract_input,
+                        &generate_blocks,
+                    );
+                    net_contracts(
+                        Arc::clone(&bob_manager_send),
+                        &bob_send,
+                        Arc::clone(&alice_manager_send),
+                        &alice_send,
+                        &[contract_id, other_contract_id],
+                        &sync_receive,
+                        test_params.contract_input.fee_rate,
+                        &generate_blocks,
+                    )
+                }
+                _ => contract_id,
+            };
+
+            if let TestPath::MutualClose = path {
+                collaboratively_close_contract(
+                    Arc::clone(&alice_manager_send),
+                    &alice_send,
+                    Arc::clone(&bob_manager_send),
+                    &bob_send,
+                    contract_id,
+                    &sync_receive,
+                    &electrs,
+                    &generate_blocks,
+                );
+            }
+
             if !manual_close {
                 mocks::mock_time::set_time((EVENT_MATURITY as u64) + 1);
             }
@@ -818,7 +979,7 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
             };
 
             match path {
-                TestPath::Close => {
+                TestPath::Close | TestPath::Amend | TestPath::Net => {
                     let case = thread_rng().next_u64() % 3;
                     let blocks: Option<u32> = if case == 2 {
                         Some(6)
@@ -834,9 +995,15 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
                         let attestations = get_attestations(&test_params);
 
                         let mut f = first.lock().unwrap();
-                        let contract = f
-                            .close_confirmed_contract(&contract_id, attestations)
-                            .expect("Error closing contract");
+                        let contract = if thread_rng().next_u32() % 2 == 0 {
+                            f.close_confirmed_contract(&contract_id, attestations)
+                        } else {
+                            f.close_confirmed_contract_with_attestations(
+                                &contract_id,
+                                attestations.into_iter().map(|x| x.1).collect(),
+                            )
+                        }
+                        .expect("Error closing contract");
 
                         if let Contract::PreClosed(contract) = contract {
                             let mut s = second.lock().unwrap();
@@ -869,6 +1036,20 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
                         // cet becomes fully confirmed to blockchain
                         periodic_check!(first, contract_id, Closed);
                         periodic_check!(second, contract_id, Closed);
+
+                        let closed = first
+                            .lock()
+                            .unwrap()
+                            .get_store()
+                            .get_contract(&contract_id)
+                            .unwrap();
+                        if let Some(Contract::Closed(closed)) = closed {
+                            closed
+                                .verify_attestations(&secp256k1_zkp::Secp256k1::verification_only())
+                                .expect("archived attestations to verify");
+                        } else {
+                            panic!("Invalid contract state {:?}", closed);
+                        }
                     } else {
                         periodic_check!(first, contract_id, PreClosed);
                         periodic_check!(second, contract_id, PreClosed);
@@ -894,6 +1075,8 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
 
                     periodic_check!(second, contract_id, Refunded);
                 }
+                // Closed before maturity above.
+                TestPath::MutualClose => {}
                 _ => unreachable!(),
             }
         }
@@ -907,3 +1090,251 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
 
     create_test_vector();
 }
+
+/// Returns the id of the contract in signed state of the given party,
+/// expecting a single one.
+fn get_signed_contract_id(dlc_party: &DlcParty) -> ContractId {
+    let signed_contracts = dlc_party
+        .lock()
+        .unwrap()
+        .get_store()
+        .get_signed_contracts()
+        .unwrap();
+    assert_eq!(1, signed_contracts.len());
+    signed_contracts[0].accepted_contract.get_contract_id()
+}
+
+/// Replaces the given confirmed contract with one using the given input,
+/// proposed by its offer party, and returns the id of the new contract once
+/// confirmed.
+fn amend_contract<F: Fn(u64)>(
+    offer_party: DlcParty,
+    offer_send: &Sender<Option<Message>>,
+    accept_party: DlcParty,
+    accept_send: &Sender<Option<Message>>,
+    contract_id: ContractId,
+    sync_receive: &Receiver<()>,
+    contract_input: &ContractInput,
+    generate_blocks: &F,
+) -> ContractId {
+    let (amend_offer, _) = offer_party
+        .lock()
+        .unwrap()
+        .amend_contract(&contract_id, contract_input)
+        .expect("to be able to offer an amendment");
+
+    offer_send
+        .send(Some(Message::AmendOffer(amend_offer)))
+        .unwrap();
+
+    sync_receive.recv().expect("Error synchronizing");
+
+    let received = accept_party.lock().unwrap().get_received_amendments();
+    assert_eq!(1, received.len());
+    assert_eq!(contract_id, received[0].0);
+
+    let (amend_accept, _) = accept_party
+        .lock()
+        .unwrap()
+        .accept_amendment(&contract_id)
+        .expect("to be able to accept the amendment");
+
+    accept_send
+        .send(Some(Message::AmendAccept(amend_accept)))
+        .unwrap();
+
+    // Process Accept
+    sync_receive.recv().expect("Error synchronizing");
+    // Process Sign
+    sync_receive.recv().expect("Error synchronizing");
+
+    let amended_contract_id = get_signed_contract_id(&offer_party);
+    assert_eq!(amended_contract_id, get_signed_contract_id(&accept_party));
+
+    // The amended contract is kept until the new one is confirmed.
+    assert_contract_state!(offer_party, contract_id, Confirmed);
+    assert_contract_state!(accept_party, contract_id, Confirmed);
+
+    generate_blocks(6);
+
+    periodic_check!(offer_party, amended_contract_id, Confirmed);
+    periodic_check!(accept_party, amended_contract_id, Confirmed);
+
+    assert_contract_state!(offer_party, contract_id, Amended);
+    assert_contract_state!(accept_party, contract_id, Amended);
+
+    amended_contract_id
+}
+
+/// Establishes a contract with the given input offered by the first party, and
+/// returns its id once confirmed.
+fn establish_contract<F: Fn(u64)>(
+    offer_party: DlcParty,
+    offer_send: &Sender<Option<Message>>,
+    accept_party: DlcParty,
+    accept_party_id: PublicKey,
+    accept_send: &Sender<Option<Message>>,
+    sync_receive: &Receiver<()>,
+    contract_input: &ContractInput,
+    generate_blocks: &F,
+) -> ContractId {
+    let offer_msg = offer_party
+        .lock()
+        .unwrap()
+        .send_offer(contract_input, accept_party_id)
+        .expect("Send offer error");
+    let temporary_contract_id = offer_msg.temporary_contract_id;
+
+    offer_send.send(Some(Message::Offer(offer_msg))).unwrap();
+
+    sync_receive.recv().expect("Error synchronizing");
+
+    let (contract_id, _, accept_msg) = accept_party
+        .lock()
+        .unwrap()
+        .accept_contract_offer(&temporary_contract_id)
+        .expect("Error accepting contract offer");
+
+    accept_send.send(Some(Message::Accept(accept_msg))).unwrap();
+
+    // Process Accept
+    sync_receive.recv().expect("Error synchronizing");
+    // Process Sign
+    sync_receive.recv().expect("Error synchronizing");
+
+    assert_contract_state!(offer_party, contract_id, Signed);
+    assert_contract_state!(accept_party, contract_id, Signed);
+
+    generate_blocks(6);
+
+    periodic_check!(offer_party, contract_id, Confirmed);
+    periodic_check!(accept_party, contract_id, Confirmed);
+
+    contract_id
+}
+
+/// Replaces the given confirmed contracts with a single one, proposed by the
+/// first party, and returns the id of the net contract once confirmed.
+fn net_contracts<F: Fn(u64)>(
+    offer_party: DlcParty,
+    offer_send: &Sender<Option<Message>>,
+    accept_party: DlcParty,
+    accept_send: &Sender<Option<Message>>,
+    contract_ids: &[ContractId],
+    sync_receive: &Receiver<()>,
+    fee_rate_per_vb: u64,
+    generate_blocks: &F,
+) -> ContractId {
+    let (net_offer, _) = offer_party
+        .lock()
+        .unwrap()
+        .offer_netting(contract_ids, fee_rate_per_vb)
+        .expect("to be able to offer a netting");
+
+    offer_send.send(Some(Message::NetOffer(net_offer))).unwrap();
+
+    sync_receive.recv().expect("Error synchronizing");
+
+    let received = accept_party.lock().unwrap().get_received_nettings();
+    assert_eq!(1, received.len());
+    assert_eq!(contract_ids, received[0].0.as_slice());
+
+    let (net_accept, _) = accept_party
+        .lock()
+        .unwrap()
+        .accept_netting(&contract_ids[0])
+        .expect("to be able to accept the netting");
+
+    accept_send
+        .send(Some(Message::NetAccept(net_accept)))
+        .unwrap();
+
+    // Process Accept
+    sync_receive.recv().expect("Error synchronizing");
+    // Process Sign
+    sync_receive.recv().expect("Error synchronizing");
+
+    let net_contract_id = get_signed_contract_id(&offer_party);
+    assert_eq!(net_contract_id, get_signed_contract_id(&accept_party));
+
+    generate_blocks(6);
+
+    periodic_check!(offer_party, net_contract_id, Confirmed);
+    periodic_check!(accept_party, net_contract_id, Confirmed);
+
+    for contract_id in contract_ids {
+        assert_contract_state!(offer_party, contract_id, Amended);
+        assert_contract_state!(accept_party, contract_id, Amended);
+    }
+
+    net_contract_id
+}
+
+/// Closes the given confirmed contract before its maturity on the offer of the
+/// first party, and checks that the closing transaction gets confirmed.
+fn collaboratively_close_contract<F: Fn(u64)>(
+    offer_party: DlcParty,
+    offer_send: &Sender<Option<Message>>,
+    accept_party: DlcParty,
+    accept_send: &Sender<Option<Message>>,
+    contract_id: ContractId,
+    sync_receive: &Receiver<()>,
+    electrs: &ElectrsBlockchainProvider,
+    generate_blocks: &F,
+) {
+    let (close_offer, _) = offer_party
+        .lock()
+        .unwrap()
+        .offer_contract_collaborative_close(&contract_id, OFFER_COLLATERAL)
+        .expect("to be able to offer a collaborative close");
+
+    offer_send
+        .send(Some(Message::MutualCloseOffer(close_offer)))
+        .unwrap();
+
+    sync_receive.recv().expect("Error synchronizing");
+
+    let received = accept_party
+        .lock()
+        .unwrap()
+        .get_received_collaborative_closes();
+    assert_eq!(1, received.len());
+    assert_eq!(contract_id, received[0].0);
+
+    let (close_accept, _) = accept_party
+        .lock()
+        .unwrap()
+        .accept_contract_collaborative_close(&contract_id)
+        .expect("to be able to accept a collaborative close");
+
+    assert_contract_state!(accept_party, contract_id, CollaborativelyClosed);
+
+    accept_send
+        .send(Some(Message::MutualCloseAccept(close_accept)))
+        .unwrap();
+
+    sync_receive.recv().expect("Error synchronizing");
+
+    assert_contract_state!(offer_party, contract_id, CollaborativelyClosed);
+
+    generate_blocks(1);
+
+    for party in [&offer_party, &accept_party] {
+        let contract = party
+            .lock()
+            .unwrap()
+            .get_store()
+            .get_contract(&contract_id)
+            .unwrap();
+        let close_tx = match contract {
+            Some(Contract::CollaborativelyClosed(c)) => c.close_tx,
+            c => panic!("Invalid contract state {:?}", c),
+        };
+        assert_eq!(
+            1,
+            electrs
+                .get_transaction_confirmations(&close_tx.txid())
+                .unwrap()
+        );
+    }
+}
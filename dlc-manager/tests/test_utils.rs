@@ -8,7 +8,7 @@ extern crate dlc_manager;
 
 use std::ops::Deref;
 
-use dlc::{EnumerationPayout, Payout};
+use dlc::{EnumerationPayout, FeeSplit, Payout};
 use dlc_manager::payout_curve::{
     PayoutFunction, PayoutFunctionPiece, PayoutPoint, PolynomialPayoutCurvePiece, RoundingInterval,
     RoundingIntervals,
@@ -285,6 +285,7 @@ pub fn get_enum_test_params(
             public_keys: oracles.iter().map(|x| x.get_public_key()).collect(),
             event_id: EVENT_ID.to_owned(),
             threshold: threshold as u16,
+            difference_params: None,
         },
     };
 
@@ -293,6 +294,9 @@ pub fn get_enum_test_params(
         accept_collateral: ACCEPT_COLLATERAL,
         fee_rate: 2,
         contract_infos: vec![contract_info],
+        anchor_outputs: false,
+        fee_split: FeeSplit::Equal,
+        taproot: false,
     };
 
     TestParams {
@@ -301,6 +305,34 @@ pub fn get_enum_test_params(
     }
 }
 
+/// Returns a copy of the given enumeration contract input using the given
+/// collaterals, with the payouts updated for the new total collateral.
+pub fn get_enum_contract_input_with_collaterals(
+    contract_input: &ContractInput,
+    offer_collateral: u64,
+    accept_collateral: u64,
+) -> ContractInput {
+    let total_collateral = offer_collateral + accept_collateral;
+    let mut contract_input = contract_input.clone();
+    contract_input.offer_collateral = offer_collateral;
+    contract_input.accept_collateral = accept_collateral;
+    for contract_info in contract_input.contract_infos.iter_mut() {
+        match &mut contract_info.contract_descriptor {
+            ContractDescriptor::Enum(e) => {
+                for outcome_payout in e.outcome_payouts.iter_mut() {
+                    if outcome_payout.payout.offer > 0 {
+                        outcome_payout.payout.offer = total_collateral;
+                    } else {
+                        outcome_payout.payout.accept = total_collateral;
+                    }
+                }
+            }
+            _ => panic!("Expected an enumeration contract descriptor."),
+        }
+    }
+    contract_input
+}
+
 pub fn get_polynomial_payout_curve_pieces(min_nb_digits: usize) -> Vec<PayoutFunctionPiece> {
     vec![
         PayoutFunctionPiece::PolynomialPayoutCurvePiece(
@@ -482,6 +514,7 @@ pub fn get_numerical_test_params(
             public_keys: oracles.iter().map(|x| x.get_public_key()).collect(),
             event_id: EVENT_ID.to_owned(),
             threshold: threshold as u16,
+            difference_params: None,
         },
         contract_descriptor,
     };
@@ -491,6 +524,9 @@ pub fn get_numerical_test_params(
         accept_collateral: ACCEPT_COLLATERAL,
         fee_rate: 2,
         contract_infos: vec![contract_info],
+        anchor_outputs: false,
+        fee_split: FeeSplit::Equal,
+        taproot: false,
     };
 
     TestParams {
@@ -513,6 +549,7 @@ pub fn get_enum_and_numerical_test_params(
             public_keys: enum_oracles.iter().map(|x| x.get_public_key()).collect(),
             event_id: EVENT_ID.to_owned(),
             threshold: threshold as u16,
+            difference_params: None,
         },
         contract_descriptor: enum_contract_descriptor,
     };
@@ -531,6 +568,7 @@ pub fn get_enum_and_numerical_test_params(
                 .collect(),
             event_id: EVENT_ID.to_owned(),
             threshold: threshold as u16,
+            difference_params: None,
         },
         contract_descriptor: numerical_contract_descriptor,
     };
@@ -546,6 +584,9 @@ pub fn get_enum_and_numerical_test_params(
         accept_collateral: ACCEPT_COLLATERAL,
         fee_rate: 2,
         contract_infos,
+        anchor_outputs: false,
+        fee_split: FeeSplit::Equal,
+        taproot: false,
     };
 
     TestParams {
@@ -62,6 +62,13 @@ pub struct OfferChannel {
     pub payout_spk: ScriptBuf,
     /// Serial id used to order outputs.
     pub payout_serial_id: u64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
     /// The collateral input by the offer party in the channel.
     pub offer_collateral: u64,
     /// The inputs that the offer party will use to fund the channel.
@@ -80,6 +87,13 @@ pub struct OfferChannel {
     pub refund_locktime: u32,
     /// The nSequence value to use for the CETs.
     pub cet_nsequence: u32,
+    /// The unix time (in seconds) at which the offer was created.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub timestamp: Option<u64>,
+    /// The time (in seconds) given to each party to answer a channel update
+    /// before the channel is force closed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub peer_timeout: Option<u64>,
 }
 
 impl_dlc_writeable!(OfferChannel, {
@@ -105,6 +119,9 @@ impl_dlc_writeable!(OfferChannel, {
         (cet_locktime, writeable),
         (refund_locktime, writeable),
         (cet_nsequence, writeable)
+}, tlv_stream: {
+        (3, timestamp, option),
+        (5, peer_timeout, option)
 });
 
 impl OfferChannel {
@@ -160,6 +177,13 @@ pub struct AcceptChannel {
     )]
     /// The temporary id of the channel.
     pub temporary_channel_id: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
     /// The collateral input by the accept party.
     pub accept_collateral: u64,
     /// The [`PublicKey`] used for the fund output by the accept party.
@@ -265,6 +289,13 @@ pub struct SettleOffer {
     )]
     /// The id of the channel referred to by the message.
     pub channel_id: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
     /// The payout offered to the receiving party.
     pub counter_payout: u64,
     /// The per update point to be used by the sending party to setup the next
@@ -386,6 +417,13 @@ pub struct RenewOffer {
     pub channel_id: [u8; 32],
     /// The temporary id of the offered contract.
     pub temporary_contract_id: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
     /// The proposed payout for the receiving party for the previous channel
     /// state.
     pub counter_payout: u64,
@@ -514,6 +552,237 @@ impl_dlc_writeable!(RenewFinalize, {
     (per_update_secret, writeable)
 });
 
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+/// Message used to offer to add a contract to the ones already established
+/// within a channel, locking part of the balances of the parties.
+pub struct AddContractOffer {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the channel referred to by the message.
+    pub channel_id: [u8; 32],
+    /// The temporary id of the offered contract.
+    pub temporary_contract_id: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
+    /// The collateral locked in the contract by the sending party.
+    pub offer_collateral: u64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
+    /// The collateral locked in the contract by the receiving party.
+    pub accept_collateral: u64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
+    /// The balance of the receiving party in the channel before the contract
+    /// is added.
+    pub counter_balance: u64,
+    /// The per update point to be used by the sending party to setup the next
+    /// channel state.
+    pub next_per_update_point: PublicKey,
+    /// Information about the offered contract.
+    pub contract_info: ContractInfo,
+    /// Lock time for the CETs.
+    pub cet_locktime: u32,
+    /// Lock time for the refund transaction.
+    pub refund_locktime: u32,
+    /// The nSequence value to use for the CETs.
+    pub cet_nsequence: u32,
+}
+
+impl_dlc_writeable!(AddContractOffer, {
+    (channel_id, writeable),
+    (temporary_contract_id, writeable),
+    (offer_collateral, writeable),
+    (accept_collateral, writeable),
+    (counter_balance, writeable),
+    (next_per_update_point, writeable),
+    (contract_info, writeable),
+    (cet_locktime, writeable),
+    (refund_locktime, writeable),
+    (cet_nsequence, writeable)
+});
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+/// Message used to offer to settle one of the contracts established within a
+/// channel, releasing its collateral to the balances of the parties.
+pub struct SettleContractOffer {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the channel referred to by the message.
+    pub channel_id: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the contract to settle.
+    pub contract_id: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
+    /// The part of the collateral of the contract proposed to be returned to
+    /// the balance of the receiving party.
+    pub counter_payout: u64,
+    /// The per update point to be used by the sending party to setup the next
+    /// channel state.
+    pub next_per_update_point: PublicKey,
+}
+
+impl_dlc_writeable!(SettleContractOffer, {
+    (channel_id, writeable),
+    (contract_id, writeable),
+    (counter_payout, writeable),
+    (next_per_update_point, writeable)
+});
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+/// Message used to accept the addition or the settlement of a contract within
+/// a channel.
+pub struct ContractUpdateAccept {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the channel referred to by the message.
+    pub channel_id: [u8; 32],
+    /// The per update point to be used by the sending party to setup the next
+    /// channel state.
+    pub next_per_update_point: PublicKey,
+    /// The adaptor signature for the buffer transaction generated by the
+    /// sending party.
+    pub buffer_adaptor_signature: EcdsaAdaptorSignature,
+    /// The adaptor signatures for the CETs of each contract of the next channel
+    /// state, in the order of the outputs of the buffer transaction.
+    pub cet_adaptor_signatures: Vec<CetAdaptorSignatures>,
+    /// The refund signatures of each contract of the next channel state, in
+    /// the order of the outputs of the buffer transaction.
+    pub refund_signatures: Vec<Signature>,
+}
+
+impl_dlc_writeable!(ContractUpdateAccept, {
+    (channel_id, writeable),
+    (next_per_update_point, writeable),
+    (buffer_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}),
+    (cet_adaptor_signatures, vec),
+    (refund_signatures, vec)
+});
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+/// Message used to confirm the addition or the settlement of a contract within
+/// a channel.
+pub struct ContractUpdateConfirm {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the channel referred to by the message.
+    pub channel_id: [u8; 32],
+    /// The pre image of the per update point used by the sending party to setup
+    /// the previous channel state.
+    pub per_update_secret: SecretKey,
+    /// The adaptor signature for the buffer transaction generated by the
+    /// sending party.
+    pub buffer_adaptor_signature: EcdsaAdaptorSignature,
+    /// The adaptor signatures for the CETs of each contract of the next channel
+    /// state, in the order of the outputs of the buffer transaction.
+    pub cet_adaptor_signatures: Vec<CetAdaptorSignatures>,
+    /// The refund signatures of each contract of the next channel state, in
+    /// the order of the outputs of the buffer transaction.
+    pub refund_signatures: Vec<Signature>,
+}
+
+impl_dlc_writeable!(ContractUpdateConfirm, {
+    (channel_id, writeable),
+    (per_update_secret, writeable),
+    (buffer_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}),
+    (cet_adaptor_signatures, vec),
+    (refund_signatures, vec)
+});
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+/// Message used to finalize the addition or the settlement of a contract
+/// within a channel.
+pub struct ContractUpdateFinalize {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the channel referred to by the message.
+    pub channel_id: [u8; 32],
+    /// The pre image of the per update point used by the sending party to setup
+    /// the previous channel state.
+    pub per_update_secret: SecretKey,
+}
+
+impl_dlc_writeable!(ContractUpdateFinalize, {
+    (channel_id, writeable),
+    (per_update_secret, writeable)
+});
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(
     feature = "serde",
@@ -531,6 +800,13 @@ pub struct CollaborativeCloseOffer {
     )]
     /// The id of the channel referred to by the message.
     pub channel_id: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
     /// The proposed payout for the receiving party to close the channel with.
     pub counter_payout: u64,
     /// The signature of the sending party for the closing transaction.
@@ -564,3 +840,79 @@ pub struct Reject {
 }
 
 impl_dlc_writeable!(Reject, { (channel_id, writeable) });
+
+/// Message used to decline a channel offer, informing the offering party that
+/// it can release the inputs it reserved for the channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RejectChannelOffer {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The temporary id of the channel that was offered.
+    pub temporary_channel_id: [u8; 32],
+    /// A human readable explanation of the rejection.
+    pub reason: String,
+}
+
+impl_dlc_writeable!(RejectChannelOffer, {
+    (temporary_channel_id, writeable),
+    (reason, string)
+});
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+/// Message used to request that no update be made to a channel until the
+/// ongoing one completes, or to acknowledge such a request. Once both parties
+/// have sent this message, only the initiator can start a settle or renew
+/// operation.
+pub struct Stop {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the channel referred to by the message.
+    pub channel_id: [u8; 32],
+    /// Whether the sending party is requesting to initiate the next channel
+    /// update (`false` when acknowledging a request from the counter party).
+    pub initiator: bool,
+}
+
+impl_dlc_writeable!(Stop, { (channel_id, writeable), (initiator, writeable) });
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+/// Message used to end the quiescence of a channel without performing an
+/// update, or to refuse a [`Stop`] request.
+pub struct Resume {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the channel referred to by the message.
+    pub channel_id: [u8; 32],
+}
+
+impl_dlc_writeable!(Resume, { (channel_id, writeable) });
@@ -435,6 +435,8 @@ fn test_single(case: TestCase, secp: &secp256k1::Secp256k1<secp256k1::All>) {
         0,
         params.contract_maturity_bound,
         0,
+        false,
+        dlc::FeeSplit::Equal,
     )
     .unwrap();
 
@@ -732,6 +734,8 @@ fn test_dlc_txs() {
             0,
             params.contract_maturity_bound,
             0,
+            false,
+            dlc::FeeSplit::Equal,
         )
         .unwrap();
         let test_txs = test_case.txs.unwrap();
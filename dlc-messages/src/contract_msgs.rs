@@ -15,6 +15,13 @@ use oracle_msgs::OracleInfo;
 pub struct ContractOutcome {
     /// The outcome represented as a string.
     pub outcome: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
     /// The payout of the offer party for the outcome.
     pub offer_payout: u64,
 }
@@ -74,6 +81,13 @@ impl ContractInfo {
 /// Information for a contract based on a single event.
 
 pub struct SingleContractInfo {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
     /// The total collateral locked in the contract.
     pub total_collateral: u64,
     /// Information about the contract outcomes, payout and oracles.
@@ -90,6 +104,13 @@ impl_dlc_writeable!(SingleContractInfo, { (total_collateral, writeable), (contra
 )]
 /// Information for a contract based on a multiple events.
 pub struct DisjointContractInfo {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
     /// The total collateral locked in the contract.
     pub total_collateral: u64,
     /// Information about the contract outcomes, payout and oracles.
@@ -241,6 +262,13 @@ impl_dlc_writeable!(PolynomialPayoutCurvePiece, { (payout_points, vec) });
 pub struct PayoutPoint {
     /// The event outcome for this point (X coordinate).
     pub event_outcome: u64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
     /// The payout for this point (Y coordinate).
     pub outcome_payout: u64,
     /// Extra precision to be applied when computing the payout.
@@ -29,6 +29,10 @@ pub mod contract_msgs;
 pub mod message_handler;
 pub mod oracle_msgs;
 pub mod segmentation;
+pub mod signing;
+#[cfg(feature = "taproot")]
+pub mod taproot;
+pub mod view;
 
 #[cfg(any(test, feature = "serde"))]
 pub mod serde_utils;
@@ -39,18 +43,21 @@ use crate::ser_impls::{read_ecdsa_adaptor_signature, write_ecdsa_adaptor_signatu
 use bitcoin::ScriptBuf;
 use bitcoin::{consensus::Decodable, OutPoint, Transaction};
 use channel::{
-    AcceptChannel, CollaborativeCloseOffer, OfferChannel, Reject, RenewAccept, RenewConfirm,
-    RenewFinalize, RenewOffer, SettleAccept, SettleConfirm, SettleFinalize, SettleOffer,
-    SignChannel,
+    AcceptChannel, AddContractOffer, CollaborativeCloseOffer, ContractUpdateAccept,
+    ContractUpdateConfirm, ContractUpdateFinalize, OfferChannel, Reject, RejectChannelOffer,
+    RenewAccept, RenewConfirm, RenewFinalize, RenewOffer, Resume, SettleAccept, SettleConfirm,
+    SettleContractOffer, SettleFinalize, SettleOffer, SignChannel, Stop,
 };
 use contract_msgs::ContractInfo;
-use dlc::{Error, TxInputInfo};
+use dlc::{Error, FeeSplit, TxInputInfo};
 use lightning::ln::msgs::DecodeError;
 use lightning::ln::wire::Type;
 use lightning::util::ser::{Readable, Writeable, Writer};
 use secp256k1_zkp::Verification;
 use secp256k1_zkp::{ecdsa::Signature, EcdsaAdaptorSignature, PublicKey, Secp256k1};
 use segmentation::{SegmentChunk, SegmentStart};
+#[cfg(feature = "taproot")]
+use taproot::{AcceptTaprootDlc, SignTaprootDlc};
 
 macro_rules! impl_type {
     ($const_name: ident, $type_name: ident, $type_val: expr) => {
@@ -85,6 +92,31 @@ impl_type!(
     43022
 );
 impl_type!(REJECT, Reject, 43024);
+impl_type!(STOP_TYPE, Stop, 43026);
+impl_type!(RESUME_TYPE, Resume, 43028);
+impl_type!(PING_TYPE, Ping, 43030);
+impl_type!(PONG_TYPE, Pong, 43032);
+impl_type!(UPDATE_PAYOUT_OFFER_TYPE, UpdatePayoutOffer, 43034);
+impl_type!(UPDATE_PAYOUT_ACCEPT_TYPE, UpdatePayoutAccept, 43036);
+impl_type!(REJECT_OFFER_TYPE, RejectOffer, 43038);
+impl_type!(REJECT_CHANNEL_OFFER_TYPE, RejectChannelOffer, 43040);
+impl_type!(AMEND_OFFER_TYPE, AmendOffer, 43042);
+impl_type!(AMEND_ACCEPT_TYPE, AmendAccept, 43044);
+impl_type!(AMEND_SIGN_TYPE, AmendSign, 43046);
+impl_type!(MUTUAL_CLOSE_OFFER_TYPE, MutualCloseOffer, 43048);
+impl_type!(MUTUAL_CLOSE_ACCEPT_TYPE, MutualCloseAccept, 43050);
+impl_type!(NET_OFFER_TYPE, NetOffer, 43052);
+impl_type!(NET_ACCEPT_TYPE, NetAccept, 43054);
+impl_type!(NET_SIGN_TYPE, NetSign, 43056);
+impl_type!(ADD_CONTRACT_OFFER_TYPE, AddContractOffer, 43058);
+impl_type!(SETTLE_CONTRACT_OFFER_TYPE, SettleContractOffer, 43060);
+impl_type!(CONTRACT_UPDATE_ACCEPT_TYPE, ContractUpdateAccept, 43062);
+impl_type!(CONTRACT_UPDATE_CONFIRM_TYPE, ContractUpdateConfirm, 43064);
+impl_type!(CONTRACT_UPDATE_FINALIZE_TYPE, ContractUpdateFinalize, 43066);
+#[cfg(feature = "taproot")]
+impl_type!(ACCEPT_TAPROOT_TYPE, AcceptTaprootDlc, 43068);
+#[cfg(feature = "taproot")]
+impl_type!(SIGN_TAPROOT_TYPE, SignTaprootDlc, 43070);
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(
@@ -286,6 +318,21 @@ pub struct DisjointNegotiationFields {
 
 impl_dlc_writeable!(DisjointNegotiationFields, { (negotiation_fields, vec) });
 
+/// Flag of [`OfferDlc::contract_flags`] indicating that an anchor output is
+/// added to the CETs of the contract, enabling to fee bump them after they were
+/// signed.
+pub const ANCHOR_OUTPUTS_FLAG: u8 = 1;
+
+/// Flag of [`OfferDlc::contract_flags`] indicating that the contract is funded
+/// with a taproot output, in which case the offer is answered with taproot
+/// accept and sign messages, available with the `taproot` feature.
+pub const TAPROOT_FUNDING_FLAG: u8 = 2;
+
+/// Flag of [`channel::OfferChannel::contract_flags`] indicating that the offer
+/// party wishes to update the channel before its fund transaction is
+/// confirmed.
+pub const ZERO_CONF_CHANNEL_FLAG: u8 = 2;
+
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(
     feature = "serde",
@@ -326,6 +373,13 @@ pub struct OfferDlc {
     pub payout_spk: ScriptBuf,
     /// Serial id to order CET outputs.
     pub payout_serial_id: u64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
     /// Collateral of the offer party.
     pub offer_collateral: u64,
     /// Inputs used by the offer party to fund the contract.
@@ -342,6 +396,24 @@ pub struct OfferDlc {
     pub cet_locktime: u32,
     /// The lock time for the refund transactions.
     pub refund_locktime: u32,
+    /// The node id of the party the offer is intended for. As the offer is not
+    /// signed, this is only a best-effort filter letting an honest recipient
+    /// detect offers that were not meant for it (see
+    /// [`OfferDlc::validate_recipient`]), and not a guarantee that no other
+    /// node can accept the offer.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub recipient_node_id: Option<PublicKey>,
+    /// The unix time (in seconds) at which the offer was created.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub timestamp: Option<u64>,
+    /// The unix time (in seconds) after which the offer should not be
+    /// accepted anymore.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub expiry: Option<u64>,
+    /// How the fees of the contract transactions are split between the
+    /// parties, equally if not set.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fee_split: Option<FeeSplit>,
 }
 
 impl OfferDlc {
@@ -360,29 +432,59 @@ impl OfferDlc {
         min_timeout_interval: u32,
         max_timeout_interval: u32,
     ) -> Result<(), Error> {
-        match &self.contract_info {
-            ContractInfo::SingleContractInfo(s) => s.contract_info.oracle_info.validate(secp)?,
-            ContractInfo::DisjointContractInfo(d) => {
-                if d.contract_infos.len() < 2 {
-                    return Err(Error::InvalidArgument);
-                }
+        validate_offer_terms(
+            &self.contract_info,
+            self.cet_locktime,
+            self.refund_locktime,
+            secp,
+            min_timeout_interval,
+            max_timeout_interval,
+        )
+    }
 
-                for c in &d.contract_infos {
-                    c.oracle_info.validate(secp)?;
-                }
-            }
+    /// Returns an error if the offer names a recipient other than the node
+    /// with the given id. Offers without a recipient are accepted by any node.
+    /// Note that this check is best-effort: the offer is not signed so the
+    /// recipient can be stripped or replaced by whoever relays it, and it is
+    /// only performed by nodes that configured their own id.
+    pub fn validate_recipient(&self, node_id: &PublicKey) -> Result<(), Error> {
+        match &self.recipient_node_id {
+            Some(recipient) if recipient != node_id => Err(Error::InvalidArgument),
+            _ => Ok(()),
         }
+    }
+}
 
-        let closest_maturity_date = self.contract_info.get_closest_maturity_date();
-        let valid_dates = self.cet_locktime <= closest_maturity_date
-            && closest_maturity_date + min_timeout_interval <= self.refund_locktime
-            && self.refund_locktime <= closest_maturity_date + max_timeout_interval;
-        if !valid_dates {
-            return Err(Error::InvalidArgument);
+fn validate_offer_terms<C: Verification>(
+    contract_info: &ContractInfo,
+    cet_locktime: u32,
+    refund_locktime: u32,
+    secp: &Secp256k1<C>,
+    min_timeout_interval: u32,
+    max_timeout_interval: u32,
+) -> Result<(), Error> {
+    match contract_info {
+        ContractInfo::SingleContractInfo(s) => s.contract_info.oracle_info.validate(secp)?,
+        ContractInfo::DisjointContractInfo(d) => {
+            if d.contract_infos.len() < 2 {
+                return Err(Error::InvalidArgument);
+            }
+
+            for c in &d.contract_infos {
+                c.oracle_info.validate(secp)?;
+            }
         }
+    }
 
-        Ok(())
+    let closest_maturity_date = contract_info.get_closest_maturity_date();
+    let valid_dates = cet_locktime <= closest_maturity_date
+        && closest_maturity_date + min_timeout_interval <= refund_locktime
+        && refund_locktime <= closest_maturity_date + max_timeout_interval;
+    if !valid_dates {
+        return Err(Error::InvalidArgument);
     }
+
+    Ok(())
 }
 
 impl_dlc_writeable!(OfferDlc, {
@@ -402,6 +504,11 @@ impl_dlc_writeable!(OfferDlc, {
         (fee_rate_per_vb, writeable),
         (cet_locktime, writeable),
         (refund_locktime, writeable)
+}, tlv_stream: {
+        (1, recipient_node_id, option),
+        (3, timestamp, option),
+        (5, expiry, option),
+        (7, fee_split, {option_cb, ser_impls::write_fee_split, ser_impls::read_fee_split})
 });
 
 /// Contains information about a party wishing to accept a DLC offer. The contained
@@ -426,6 +533,13 @@ pub struct AcceptDlc {
     )]
     /// The temporary contract id for the contract.
     pub temporary_contract_id: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
     /// The collateral input by the accept party.
     pub accept_collateral: u64,
     /// The public key of the accept party to be used to lock the collateral.
@@ -446,6 +560,9 @@ pub struct AcceptDlc {
     pub refund_signature: Signature,
     /// The negotiation fields from the accept party.
     pub negotiation_fields: Option<NegotiationFields>,
+    /// The fee split of the offer, which the accept party agreed to.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fee_split: Option<FeeSplit>,
 }
 
 impl_dlc_writeable!(AcceptDlc, {
@@ -461,6 +578,8 @@ impl_dlc_writeable!(AcceptDlc, {
     (cet_adaptor_signatures, writeable),
     (refund_signature, writeable),
     (negotiation_fields, option)
+}, tlv_stream: {
+    (7, fee_split, {option_cb, ser_impls::write_fee_split, ser_impls::read_fee_split})
 });
 
 /// Contains all the required signatures for the DLC transactions from the offering
@@ -499,8 +618,446 @@ impl_dlc_writeable!(SignDlc, {
     (funding_signatures, writeable)
 });
 
+/// Message used to check that a peer is reachable, to which it should reply
+/// with a [`Pong`] message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct Ping {
+    /// An arbitrary value to be included in the reply.
+    pub nonce: u64,
+}
+
+impl_dlc_writeable!(Ping, { (nonce, writeable) });
+
+/// Reply to a [`Ping`] message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct Pong {
+    /// The nonce of the [`Ping`] message being replied to.
+    pub nonce: u64,
+}
+
+impl_dlc_writeable!(Pong, { (nonce, writeable) });
+
+/// Message sent to request replacing the payout script of the sender in the
+/// CETs and refund transaction of a signed contract. Contains the signatures
+/// of the sender for the updated transactions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct UpdatePayoutOffer {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the contract referred to by this message.
+    pub contract_id: [u8; 32],
+    /// The new payout script of the sender.
+    pub payout_spk: ScriptBuf,
+    /// The adaptor signatures of the sender for the updated CETs.
+    pub cet_adaptor_signatures: CetAdaptorSignatures,
+    /// The signature of the sender for the updated refund transaction.
+    pub refund_signature: Signature,
+}
+
+impl_dlc_writeable!(UpdatePayoutOffer, {
+    (contract_id, writeable),
+    (payout_spk, writeable),
+    (cet_adaptor_signatures, writeable),
+    (refund_signature, writeable)
+});
+
+/// Reply to an [`UpdatePayoutOffer`] message, containing the signatures of
+/// the sender for the updated transactions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct UpdatePayoutAccept {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the contract referred to by this message.
+    pub contract_id: [u8; 32],
+    /// The adaptor signatures of the sender for the updated CETs.
+    pub cet_adaptor_signatures: CetAdaptorSignatures,
+    /// The signature of the sender for the updated refund transaction.
+    pub refund_signature: Signature,
+}
+
+impl_dlc_writeable!(UpdatePayoutAccept, {
+    (contract_id, writeable),
+    (cet_adaptor_signatures, writeable),
+    (refund_signature, writeable)
+});
+
+/// Message used to decline a contract offer, informing the offering party that
+/// it can release the inputs it reserved for the contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RejectOffer {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The temporary id of the contract that was offered.
+    pub temporary_contract_id: [u8; 32],
+    /// A human readable explanation of the rejection.
+    pub reason: String,
+}
+
+impl_dlc_writeable!(RejectOffer, {
+    (temporary_contract_id, writeable),
+    (reason, string)
+});
+
+/// Message sent by the offer party of a confirmed contract to propose
+/// replacing it with a contract with larger collaterals, funded by a
+/// transaction spending the fund output of the existing contract together with
+/// additional inputs from both parties.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct AmendOffer {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the contract to amend.
+    pub contract_id: [u8; 32],
+    /// Information about the contract event, payouts and oracles of the
+    /// amended contract. The oracle announcements must be the ones of the
+    /// contract to amend.
+    pub contract_info: ContractInfo,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
+    /// Collateral of the offer party in the amended contract.
+    pub offer_collateral: u64,
+    /// Additional inputs used by the offer party to fund the amended contract.
+    pub funding_inputs: Vec<FundingInput>,
+    /// The SPK where the offer party will receive their change.
+    pub change_spk: ScriptBuf,
+    /// Serial id to order funding transaction outputs.
+    pub change_serial_id: u64,
+    /// Serial id to order funding transaction outputs.
+    pub fund_output_serial_id: u64,
+    /// The fee rate to use to compute the fees of the amended contract
+    /// transactions.
+    pub fee_rate_per_vb: u64,
+}
+
+impl AmendOffer {
+    /// Returns the total collateral locked in the amended contract.
+    pub fn get_total_collateral(&self) -> u64 {
+        self.contract_info.get_total_collateral()
+    }
+}
+
+impl_dlc_writeable!(AmendOffer, {
+    (contract_id, writeable),
+    (contract_info, writeable),
+    (offer_collateral, writeable),
+    (funding_inputs, vec),
+    (change_spk, writeable),
+    (change_serial_id, writeable),
+    (fund_output_serial_id, writeable),
+    (fee_rate_per_vb, writeable)
+});
+
+/// Reply to an [`AmendOffer`] message, containing the additional inputs of the
+/// accept party and its signatures for the amended contract transactions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct AmendAccept {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the contract to amend.
+    pub contract_id: [u8; 32],
+    /// Additional inputs used by the accept party to fund the amended
+    /// contract.
+    pub funding_inputs: Vec<FundingInput>,
+    /// The SPK where the accept party will receive their change.
+    pub change_spk: ScriptBuf,
+    /// Serial id to order funding transaction outputs.
+    pub change_serial_id: u64,
+    /// The adaptor signatures of the accept party for the amended CETs.
+    pub cet_adaptor_signatures: CetAdaptorSignatures,
+    /// The signature of the accept party for the amended refund transaction.
+    pub refund_signature: Signature,
+}
+
+impl_dlc_writeable!(AmendAccept, {
+    (contract_id, writeable),
+    (funding_inputs, vec),
+    (change_spk, writeable),
+    (change_serial_id, writeable),
+    (cet_adaptor_signatures, writeable),
+    (refund_signature, writeable)
+});
+
+/// Reply to an [`AmendAccept`] message, containing the signatures of the offer
+/// party for the amended contract transactions, including the one spending the
+/// fund output of the contract being amended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct AmendSign {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the contract to amend.
+    pub contract_id: [u8; 32],
+    /// The adaptor signatures of the offer party for the amended CETs.
+    pub cet_adaptor_signatures: CetAdaptorSignatures,
+    /// The signature of the offer party for the amended refund transaction.
+    pub refund_signature: Signature,
+    /// The signatures of the offer party for its additional funding inputs.
+    pub funding_signatures: FundingSignatures,
+    /// The signature of the offer party for the input of the amended fund
+    /// transaction spending the fund output of the contract being amended.
+    pub fund_input_signature: Signature,
+}
+
+impl_dlc_writeable!(AmendSign, {
+    (contract_id, writeable),
+    (cet_adaptor_signatures, writeable),
+    (refund_signature, writeable),
+    (funding_signatures, writeable),
+    (fund_input_signature, writeable)
+});
+
+/// Message sent to propose closing a contract by mutual agreement, without
+/// waiting for its maturity or for the attestation of the oracles.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct MutualCloseOffer {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the contract to close.
+    pub contract_id: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
+    /// The proposed payout for the receiving party to close the contract with.
+    pub counter_payout: u64,
+    /// The signature of the sending party for the closing transaction.
+    pub close_signature: Signature,
+}
+
+impl_dlc_writeable!(MutualCloseOffer, {
+    (contract_id, writeable),
+    (counter_payout, writeable),
+    (close_signature, writeable)
+});
+
+/// Reply to a [`MutualCloseOffer`] message, sent once the closing transaction
+/// was broadcast by the receiving party.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct MutualCloseAccept {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the contract to close.
+    pub contract_id: [u8; 32],
+    /// The signature of the sending party for the closing transaction.
+    pub close_signature: Signature,
+}
+
+impl_dlc_writeable!(MutualCloseAccept, {
+    (contract_id, writeable),
+    (close_signature, writeable)
+});
+
+/// Message sent to propose replacing several confirmed contracts with the
+/// receiving party, on the same oracle events, by a single contract whose
+/// payouts are the sum of theirs. The payouts and collaterals of the net
+/// contract are computed by each party from the contracts to net, and it is
+/// funded by a transaction spending their fund outputs, the collateral that
+/// is not at risk in the net contract being returned to each party as change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct NetOffer {
+    /// The ids of the contracts to net, in the order in which their fund
+    /// outputs are spent by the fund transaction of the net contract.
+    pub contract_ids: Vec<[u8; 32]>,
+    /// The SPK where the offer party will receive their change.
+    pub change_spk: ScriptBuf,
+    /// Serial id to order funding transaction outputs.
+    pub change_serial_id: u64,
+    /// Serial id to order funding transaction outputs.
+    pub fund_output_serial_id: u64,
+    /// The fee rate to use to compute the fees of the net contract
+    /// transactions.
+    pub fee_rate_per_vb: u64,
+}
+
+impl_dlc_writeable!(NetOffer, {
+    (contract_ids, vec),
+    (change_spk, writeable),
+    (change_serial_id, writeable),
+    (fund_output_serial_id, writeable),
+    (fee_rate_per_vb, writeable)
+});
+
+/// Reply to a [`NetOffer`] message, containing the signatures of the accept
+/// party for the net contract transactions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct NetAccept {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the first contract to net.
+    pub contract_id: [u8; 32],
+    /// The SPK where the accept party will receive their change.
+    pub change_spk: ScriptBuf,
+    /// Serial id to order funding transaction outputs.
+    pub change_serial_id: u64,
+    /// The adaptor signatures of the accept party for the net CETs.
+    pub cet_adaptor_signatures: CetAdaptorSignatures,
+    /// The signature of the accept party for the net refund transaction.
+    pub refund_signature: Signature,
+}
+
+impl_dlc_writeable!(NetAccept, {
+    (contract_id, writeable),
+    (change_spk, writeable),
+    (change_serial_id, writeable),
+    (cet_adaptor_signatures, writeable),
+    (refund_signature, writeable)
+});
+
+/// Reply to a [`NetAccept`] message, containing the signatures of the offer
+/// party for the net contract transactions, including the ones spending the
+/// fund outputs of the contracts being netted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct NetSign {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the first contract to net.
+    pub contract_id: [u8; 32],
+    /// The adaptor signatures of the offer party for the net CETs.
+    pub cet_adaptor_signatures: CetAdaptorSignatures,
+    /// The signature of the offer party for the net refund transaction.
+    pub refund_signature: Signature,
+    /// The signatures of the offer party for the inputs of the net fund
+    /// transaction spending the fund outputs of the contracts being netted,
+    /// in the order of the contracts in the [`NetOffer`] message.
+    pub fund_input_signatures: Vec<Signature>,
+}
+
+impl_dlc_writeable!(NetSign, {
+    (contract_id, writeable),
+    (cet_adaptor_signatures, writeable),
+    (refund_signature, writeable),
+    (fund_input_signatures, vec)
+});
+
 #[allow(missing_docs)]
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub enum Message {
     Offer(OfferDlc),
     Accept(AcceptDlc),
@@ -518,14 +1075,39 @@ pub enum Message {
     RenewFinalize(RenewFinalize),
     CollaborativeCloseOffer(CollaborativeCloseOffer),
     Reject(Reject),
+    Stop(Stop),
+    Resume(Resume),
+    Ping(Ping),
+    Pong(Pong),
+    UpdatePayoutOffer(UpdatePayoutOffer),
+    UpdatePayoutAccept(UpdatePayoutAccept),
+    RejectOffer(RejectOffer),
+    RejectChannelOffer(RejectChannelOffer),
+    AmendOffer(AmendOffer),
+    AmendAccept(AmendAccept),
+    AmendSign(AmendSign),
+    MutualCloseOffer(MutualCloseOffer),
+    MutualCloseAccept(MutualCloseAccept),
+    NetOffer(NetOffer),
+    NetAccept(NetAccept),
+    NetSign(NetSign),
+    AddContractOffer(AddContractOffer),
+    SettleContractOffer(SettleContractOffer),
+    ContractUpdateAccept(ContractUpdateAccept),
+    ContractUpdateConfirm(ContractUpdateConfirm),
+    ContractUpdateFinalize(ContractUpdateFinalize),
+    #[cfg(feature = "taproot")]
+    AcceptTaproot(AcceptTaprootDlc),
+    #[cfg(feature = "taproot")]
+    SignTaproot(SignTaprootDlc),
 }
 
 macro_rules! impl_type_writeable_for_enum {
-    ($type_name: ident, {$($variant_name: ident),*}) => {
+    ($type_name: ident, {$($(#[$attr: meta])* $variant_name: ident),*}) => {
        impl Type for $type_name {
            fn type_id(&self) -> u16 {
                match self {
-                   $($type_name::$variant_name(v) => v.type_id(),)*
+                   $($(#[$attr])* $type_name::$variant_name(v) => v.type_id(),)*
                }
            }
        }
@@ -533,7 +1115,7 @@ macro_rules! impl_type_writeable_for_enum {
        impl Writeable for $type_name {
             fn write<W: Writer>(&self, writer: &mut W) -> Result<(), ::lightning::io::Error> {
                 match self {
-                   $($type_name::$variant_name(v) => v.write(writer),)*
+                   $($(#[$attr])* $type_name::$variant_name(v) => v.write(writer),)*
                 }
             }
        }
@@ -557,10 +1139,40 @@ impl_type_writeable_for_enum!(Message,
     RenewConfirm,
     RenewFinalize,
     CollaborativeCloseOffer,
-    Reject
+    Reject,
+    Stop,
+    Resume,
+    Ping,
+    Pong,
+    UpdatePayoutOffer,
+    UpdatePayoutAccept,
+    RejectOffer,
+    RejectChannelOffer,
+    AmendOffer,
+    AmendAccept,
+    AmendSign,
+    MutualCloseOffer,
+    MutualCloseAccept,
+    NetOffer,
+    NetAccept,
+    NetSign,
+    AddContractOffer,
+    SettleContractOffer,
+    ContractUpdateAccept,
+    ContractUpdateConfirm,
+    ContractUpdateFinalize,
+    #[cfg(feature = "taproot")]
+    AcceptTaproot,
+    #[cfg(feature = "taproot")]
+    SignTaproot
 });
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 /// Wrapper for DLC related message and segmentation related messages.
 pub enum WireMessage {
     /// Message related to establishment of a DLC contract.
@@ -617,12 +1229,41 @@ mod tests {
         roundtrip_test!(AcceptDlc, input);
     }
 
+    #[test]
+    fn fee_split_roundtrip() {
+        let offer_input = include_str!("./test_inputs/offer_msg.json");
+        let mut offer: OfferDlc = serde_json::from_str(offer_input).unwrap();
+        offer.fee_split = Some(FeeSplit::OfferPaysAll);
+        test_roundtrip(offer);
+
+        let accept_input = include_str!("./test_inputs/accept_msg.json");
+        let mut accept: AcceptDlc = serde_json::from_str(accept_input).unwrap();
+        accept.fee_split = Some(FeeSplit::Proportional);
+        test_roundtrip(accept);
+    }
+
     #[test]
     fn sign_msg_roundtrip() {
         let input = include_str!("./test_inputs/sign_msg.json");
         roundtrip_test!(SignDlc, input);
     }
 
+    #[test]
+    fn message_json_roundtrip() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let offer: OfferDlc = serde_json::from_str(input).unwrap();
+
+        let json = serde_json::to_value(Message::Offer(offer.clone())).unwrap();
+        assert_eq!(
+            serde_json::Value::String(offer.offer_collateral.to_string()),
+            json["offer"]["offerCollateral"]
+        );
+        match serde_json::from_value(json).unwrap() {
+            Message::Offer(deser) => assert_eq!(offer, deser),
+            _ => panic!("Expected an offer message"),
+        }
+    }
+
     #[test]
     fn valid_offer_message_passes_validation() {
         let input = include_str!("./test_inputs/offer_msg.json");
@@ -681,4 +1322,63 @@ mod tests {
                 .expect_err("Should not pass validation of invalid offer message.");
         }
     }
+
+    #[test]
+    fn offer_bound_to_recipient_fails_validation_for_other_node() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let mut offer: OfferDlc = serde_json::from_str(input).unwrap();
+        let recipient = PublicKey::from_secret_key(
+            SECP256K1,
+            &secp256k1_zkp::SecretKey::from_slice(&[1; 32]).unwrap(),
+        );
+        let other = PublicKey::from_secret_key(
+            SECP256K1,
+            &secp256k1_zkp::SecretKey::from_slice(&[2; 32]).unwrap(),
+        );
+
+        offer
+            .validate_recipient(&other)
+            .expect("unbound offers to be accepted by any node");
+
+        offer.recipient_node_id = Some(recipient);
+        offer
+            .validate_recipient(&recipient)
+            .expect("offer to be valid for its recipient");
+        offer
+            .validate_recipient(&other)
+            .expect_err("offer should not be valid for another node");
+        test_roundtrip(offer);
+    }
+
+    #[test]
+    fn offer_tlv_stream_handles_unknown_records() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let mut offer: OfferDlc = serde_json::from_str(input).unwrap();
+        offer.recipient_node_id = Some(offer.funding_pubkey);
+        offer.timestamp = Some(1_700_000_000);
+        offer.expiry = Some(1_700_086_400);
+        let buf = offer.encode();
+
+        let mut unknown_odd = buf.clone();
+        ser_impls::write_tlv_record(101, &[1, 2, 3], &mut unknown_odd).unwrap();
+        let deser: OfferDlc = Readable::read(&mut std::io::Cursor::new(&unknown_odd))
+            .expect("unknown odd records to be ignored");
+        assert_eq!(offer, deser);
+
+        let mut unknown_even = buf.clone();
+        ser_impls::write_tlv_record(100, &[1, 2, 3], &mut unknown_even).unwrap();
+        assert!(matches!(
+            <OfferDlc as Readable>::read(&mut std::io::Cursor::new(&unknown_even)),
+            Err(DecodeError::UnknownRequiredFeature)
+        ));
+
+        let mut unordered = Vec::new();
+        offer.write_fields(&mut unordered).unwrap();
+        ser_impls::write_tlv_record(101, &[0], &mut unordered).unwrap();
+        offer.write_tlv_stream(&mut unordered).unwrap();
+        assert!(matches!(
+            <OfferDlc as Readable>::read(&mut std::io::Cursor::new(&unordered)),
+            Err(DecodeError::InvalidValue)
+        ));
+    }
 }
@@ -8,7 +8,6 @@ use std::{
 
 use lightning::ln::features::{InitFeatures, NodeFeatures};
 use lightning::{
-    io::Cursor,
     ln::{
         msgs::{DecodeError, LightningError},
         peer_handler::CustomMessageHandler,
@@ -18,17 +17,18 @@ use lightning::{
 };
 use secp256k1_zkp::PublicKey;
 
+use crate::segmentation::segment_reader::{Error as SegmentError, SegmentReader};
 use crate::{
-    segmentation::{get_segments, segment_reader::SegmentReader},
+    segmentation::{segment_message, write_segments, SegmentSizes},
     Message, WireMessage,
 };
 
 /// MessageHandler is used to send and receive messages through the custom
 /// message handling mechanism of the LDK. It also handles message segmentation
 /// by splitting large messages when sending and re-constructing them when
-/// receiving.
+/// receiving. Messages to be sent are queued separately for each peer.
 pub struct MessageHandler {
-    msg_events: Mutex<VecDeque<(PublicKey, WireMessage)>>,
+    msg_events: Mutex<HashMap<PublicKey, VecDeque<WireMessage>>>,
     msg_received: Mutex<Vec<(PublicKey, Message)>>,
     segment_readers: Mutex<HashMap<PublicKey, SegmentReader>>,
 }
@@ -43,7 +43,7 @@ impl MessageHandler {
     /// Creates a new instance of a [`MessageHandler`]
     pub fn new() -> Self {
         MessageHandler {
-            msg_events: Mutex::new(VecDeque::new()),
+            msg_events: Mutex::new(HashMap::new()),
             msg_received: Mutex::new(Vec::new()),
             segment_readers: Mutex::new(HashMap::new()),
         }
@@ -61,31 +61,68 @@ impl MessageHandler {
     /// sent right away, but only when the LDK
     /// [`lightning::ln::peer_handler::PeerManager::process_events`] is next called.
     pub fn send_message(&self, node_id: PublicKey, msg: Message) {
+        let mut msg_events = self.msg_events.lock().unwrap();
+        let peer_events = msg_events.entry(node_id).or_default();
         if msg.serialized_length() > MAX_BUF_SIZE {
-            let (seg_start, seg_chunks) = get_segments(msg.encode(), msg.type_id());
-            let mut msg_events = self.msg_events.lock().unwrap();
-            msg_events.push_back((node_id, WireMessage::SegmentStart(seg_start)));
+            let (seg_start, seg_chunks) = write_segments(&msg, SegmentSizes::default());
+            peer_events.push_back(WireMessage::SegmentStart(seg_start));
             for chunk in seg_chunks {
-                msg_events.push_back((node_id, WireMessage::SegmentChunk(chunk)));
+                peer_events.push_back(WireMessage::SegmentChunk(chunk));
             }
         } else {
-            self.msg_events
-                .lock()
-                .unwrap()
-                .push_back((node_id, WireMessage::Message(msg)));
+            peer_events.push_back(WireMessage::Message(msg));
         }
     }
 
+    /// Send a message to the peer with given node id, returning an error
+    /// without queuing anything if the message is too large to be sent even
+    /// when split into segments (see
+    /// [`crate::segmentation::MAX_SEGMENTED_MESSAGE_SIZE`]).
+    pub fn try_send_message(&self, node_id: PublicKey, msg: Message) -> Result<(), SegmentError> {
+        let wire_messages = segment_message(msg)?;
+        self.msg_events
+            .lock()
+            .unwrap()
+            .entry(node_id)
+            .or_default()
+            .extend(wire_messages);
+        Ok(())
+    }
+
     /// Returns whether the message handler has any message to be sent.
     pub fn has_pending_messages(&self) -> bool {
-        !self.msg_events.lock().unwrap().is_empty()
+        self.msg_events
+            .lock()
+            .unwrap()
+            .values()
+            .any(|x| !x.is_empty())
+    }
+
+    /// Returns whether the message handler has any message to be sent to the
+    /// peer with the given node id.
+    pub fn has_pending_messages_for_peer(&self, node_id: &PublicKey) -> bool {
+        self.msg_events
+            .lock()
+            .unwrap()
+            .get(node_id)
+            .map_or(false, |x| !x.is_empty())
+    }
+
+    /// Discards the messages queued for the peer with the given node id as well
+    /// as any partially received segmented message from it. Should be called
+    /// when the connection to the peer is lost, as segments of a message are
+    /// not resumed on a new connection.
+    pub fn peer_disconnected(&self, node_id: &PublicKey) {
+        self.msg_events.lock().unwrap().remove(node_id);
+        self.segment_readers.lock().unwrap().remove(node_id);
     }
 }
 
 macro_rules! handle_read_dlc_messages {
-    ($msg_type:ident, $buffer:ident, $(($type_id:ident, $variant:ident)),*) => {{
+    ($msg_type:ident, $buffer:ident, $($(#[$attr:meta])* ($type_id:ident, $variant:ident)),*) => {{
         let decoded = match $msg_type {
             $(
+                $(#[$attr])*
                 $crate::$type_id => Message::$variant(Readable::read(&mut $buffer)?),
             )*
             _ => return Ok(None),
@@ -117,7 +154,32 @@ pub fn read_dlc_message<R: ::lightning::io::Read>(
         (RENEW_CHANNEL_CONFIRM_TYPE, RenewConfirm),
         (RENEW_CHANNEL_FINALIZE_TYPE, RenewFinalize),
         (COLLABORATIVE_CLOSE_OFFER_TYPE, CollaborativeCloseOffer),
-        (REJECT, Reject)
+        (REJECT, Reject),
+        (STOP_TYPE, Stop),
+        (RESUME_TYPE, Resume),
+        (PING_TYPE, Ping),
+        (PONG_TYPE, Pong),
+        (UPDATE_PAYOUT_OFFER_TYPE, UpdatePayoutOffer),
+        (UPDATE_PAYOUT_ACCEPT_TYPE, UpdatePayoutAccept),
+        (REJECT_OFFER_TYPE, RejectOffer),
+        (REJECT_CHANNEL_OFFER_TYPE, RejectChannelOffer),
+        (AMEND_OFFER_TYPE, AmendOffer),
+        (AMEND_ACCEPT_TYPE, AmendAccept),
+        (AMEND_SIGN_TYPE, AmendSign),
+        (MUTUAL_CLOSE_OFFER_TYPE, MutualCloseOffer),
+        (MUTUAL_CLOSE_ACCEPT_TYPE, MutualCloseAccept),
+        (NET_OFFER_TYPE, NetOffer),
+        (NET_ACCEPT_TYPE, NetAccept),
+        (NET_SIGN_TYPE, NetSign),
+        (ADD_CONTRACT_OFFER_TYPE, AddContractOffer),
+        (SETTLE_CONTRACT_OFFER_TYPE, SettleContractOffer),
+        (CONTRACT_UPDATE_ACCEPT_TYPE, ContractUpdateAccept),
+        (CONTRACT_UPDATE_CONFIRM_TYPE, ContractUpdateConfirm),
+        (CONTRACT_UPDATE_FINALIZE_TYPE, ContractUpdateFinalize),
+        #[cfg(feature = "taproot")]
+        (ACCEPT_TAPROOT_TYPE, AcceptTaproot),
+        #[cfg(feature = "taproot")]
+        (SIGN_TAPROOT_TYPE, SignTaproot)
     )
 }
 
@@ -155,59 +217,22 @@ impl CustomMessageHandler for MessageHandler {
         let mut segment_readers = self.segment_readers.lock().unwrap();
         let segment_reader = segment_readers.entry(*org).or_default();
 
-        if segment_reader.expecting_chunk() {
-            match msg {
-                WireMessage::SegmentChunk(s) => {
-                    if let Some(msg) = segment_reader
-                        .process_segment_chunk(s)
-                        .map_err(|e| to_ln_error(e, "Error processing segment chunk"))?
-                    {
-                        let mut buf = Cursor::new(msg);
-                        let message_type = <u16 as Readable>::read(&mut buf).map_err(|e| {
-                            to_ln_error(e, "Could not reconstruct message from segments")
-                        })?;
-                        if let WireMessage::Message(m) = self
-                            .read(message_type, &mut buf)
-                            .map_err(|e| {
-                                to_ln_error(e, "Could not reconstruct message from segments")
-                            })?
-                            .expect("to have a message")
-                        {
-                            self.msg_received.lock().unwrap().push((*org, m));
-                        } else {
-                            return Err(to_ln_error(
-                                "Unexpected message type",
-                                &message_type.to_string(),
-                            ));
-                        }
-                    }
-                    return Ok(());
-                }
-                _ => {
-                    // We were expecting a segment chunk but received something
-                    // else, we reset the state.
-                    segment_reader.reset();
-                }
-            }
+        if let Some(m) = segment_reader
+            .process_wire_message(msg)
+            .map_err(|e| to_ln_error(e, "Error processing segmented message"))?
+        {
+            self.msg_received.lock().unwrap().push((*org, m));
         }
-
-        match msg {
-            WireMessage::Message(m) => self.msg_received.lock().unwrap().push((*org, m)),
-            WireMessage::SegmentStart(s) => segment_reader
-                .process_segment_start(s)
-                .map_err(|e| to_ln_error(e, "Error processing segment start"))?,
-            WireMessage::SegmentChunk(_) => {
-                return Err(LightningError {
-                    err: "Received a SegmentChunk while not expecting one.".to_string(),
-                    action: lightning::ln::msgs::ErrorAction::DisconnectPeer { msg: None },
-                });
-            }
-        };
         Ok(())
     }
 
     fn get_and_clear_pending_msg(&self) -> Vec<(PublicKey, Self::CustomMessage)> {
-        self.msg_events.lock().unwrap().drain(..).collect()
+        self.msg_events
+            .lock()
+            .unwrap()
+            .drain()
+            .flat_map(|(node_id, events)| events.into_iter().map(move |e| (node_id, e)))
+            .collect()
     }
 
     fn provided_node_features(&self) -> NodeFeatures {
@@ -229,6 +254,7 @@ fn to_ln_error<T: Display>(e: T, msg: &str) -> LightningError {
 
 #[cfg(test)]
 mod tests {
+    use lightning::io::Cursor;
     use secp256k1_zkp::{SecretKey, SECP256K1};
 
     use crate::{
@@ -298,6 +324,232 @@ mod tests {
         read_test!(SegmentChunk, input);
     }
 
+    #[test]
+    fn read_stop_test() {
+        handler_read_test(crate::channel::Stop {
+            channel_id: [1; 32],
+            initiator: true,
+        });
+    }
+
+    #[test]
+    fn read_ping_pong_test() {
+        handler_read_test(crate::Ping { nonce: 42 });
+        handler_read_test(crate::Pong { nonce: 42 });
+    }
+
+    #[test]
+    fn read_update_payout_test() {
+        let refund_signature =
+            ::secp256k1_zkp::ecdsa::Signature::from_compact(&[1; 64]).expect("a valid signature");
+        let cet_adaptor_signatures = crate::CetAdaptorSignatures {
+            ecdsa_adaptor_signatures: Vec::new(),
+        };
+        handler_read_test(crate::UpdatePayoutOffer {
+            contract_id: [1; 32],
+            payout_spk: ::bitcoin::ScriptBuf::from(vec![0u8; 22]),
+            cet_adaptor_signatures: cet_adaptor_signatures.clone(),
+            refund_signature,
+        });
+        handler_read_test(crate::UpdatePayoutAccept {
+            contract_id: [1; 32],
+            cet_adaptor_signatures,
+            refund_signature,
+        });
+    }
+
+    #[test]
+    fn read_amend_test() {
+        let offer: OfferDlc =
+            serde_json::from_str(include_str!("./test_inputs/offer_msg.json")).unwrap();
+        let sign: SignDlc =
+            serde_json::from_str(include_str!("./test_inputs/sign_msg.json")).unwrap();
+        handler_read_test(crate::AmendOffer {
+            contract_id: [1; 32],
+            contract_info: offer.contract_info,
+            offer_collateral: offer.offer_collateral,
+            funding_inputs: offer.funding_inputs.clone(),
+            change_spk: offer.change_spk.clone(),
+            change_serial_id: offer.change_serial_id,
+            fund_output_serial_id: offer.fund_output_serial_id,
+            fee_rate_per_vb: offer.fee_rate_per_vb,
+        });
+        handler_read_test(crate::AmendAccept {
+            contract_id: [1; 32],
+            funding_inputs: offer.funding_inputs,
+            change_spk: offer.change_spk,
+            change_serial_id: offer.change_serial_id,
+            cet_adaptor_signatures: sign.cet_adaptor_signatures.clone(),
+            refund_signature: sign.refund_signature,
+        });
+        handler_read_test(crate::AmendSign {
+            contract_id: [1; 32],
+            cet_adaptor_signatures: sign.cet_adaptor_signatures,
+            refund_signature: sign.refund_signature,
+            funding_signatures: sign.funding_signatures,
+            fund_input_signature: sign.refund_signature,
+        });
+    }
+
+    #[test]
+    fn read_mutual_close_test() {
+        let sign: SignDlc =
+            serde_json::from_str(include_str!("./test_inputs/sign_msg.json")).unwrap();
+        handler_read_test(crate::MutualCloseOffer {
+            contract_id: [1; 32],
+            counter_payout: 100000,
+            close_signature: sign.refund_signature,
+        });
+        handler_read_test(crate::MutualCloseAccept {
+            contract_id: [1; 32],
+            close_signature: sign.refund_signature,
+        });
+    }
+
+    #[test]
+    fn read_net_test() {
+        let offer: OfferDlc =
+            serde_json::from_str(include_str!("./test_inputs/offer_msg.json")).unwrap();
+        let sign: SignDlc =
+            serde_json::from_str(include_str!("./test_inputs/sign_msg.json")).unwrap();
+        handler_read_test(crate::NetOffer {
+            contract_ids: vec![[1; 32], [2; 32]],
+            change_spk: offer.change_spk.clone(),
+            change_serial_id: offer.change_serial_id,
+            fund_output_serial_id: offer.fund_output_serial_id,
+            fee_rate_per_vb: offer.fee_rate_per_vb,
+        });
+        handler_read_test(crate::NetAccept {
+            contract_id: [1; 32],
+            change_spk: offer.change_spk,
+            change_serial_id: offer.change_serial_id,
+            cet_adaptor_signatures: sign.cet_adaptor_signatures.clone(),
+            refund_signature: sign.refund_signature,
+        });
+        handler_read_test(crate::NetSign {
+            contract_id: [1; 32],
+            cet_adaptor_signatures: sign.cet_adaptor_signatures,
+            refund_signature: sign.refund_signature,
+            fund_input_signatures: vec![sign.refund_signature; 2],
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "taproot")]
+    fn read_taproot_test() {
+        use dlc::taproot::adaptor::SchnorrAdaptorSignature;
+        use secp256k1_zkp::{KeyPair, Message as SecpMessage};
+
+        let accept: AcceptDlc =
+            serde_json::from_str(include_str!("./test_inputs/accept_msg.json")).unwrap();
+        let sign: SignDlc =
+            serde_json::from_str(include_str!("./test_inputs/sign_msg.json")).unwrap();
+        let seckey = SecretKey::from_slice(&[2; 32]).unwrap();
+        let msg = SecpMessage::from_slice(&[3; 32]).unwrap();
+        let adaptor_signature =
+            SchnorrAdaptorSignature::encrypt(SECP256K1, &msg, &seckey, &some_pk()).unwrap();
+        let refund_signature =
+            SECP256K1.sign_schnorr_no_aux_rand(&msg, &KeyPair::from_secret_key(SECP256K1, &seckey));
+        let accept_taproot = crate::taproot::AcceptTaprootDlc {
+            protocol_version: accept.protocol_version,
+            temporary_contract_id: accept.temporary_contract_id,
+            accept_collateral: accept.accept_collateral,
+            funding_pubkey: accept.funding_pubkey,
+            payout_spk: accept.payout_spk,
+            payout_serial_id: accept.payout_serial_id,
+            funding_inputs: accept.funding_inputs,
+            change_spk: accept.change_spk,
+            change_serial_id: accept.change_serial_id,
+            cet_adaptor_signatures: vec![adaptor_signature; 3],
+            refund_signature,
+            fee_split: Some(dlc::FeeSplit::Proportional),
+        };
+        let sign_taproot = crate::taproot::SignTaprootDlc {
+            protocol_version: sign.protocol_version,
+            contract_id: sign.contract_id,
+            cet_adaptor_signatures: vec![adaptor_signature; 3],
+            refund_signature,
+            funding_signatures: sign.funding_signatures,
+        };
+
+        let mut buf = Vec::new();
+        accept_taproot.write(&mut buf).unwrap();
+        assert_eq!(
+            accept_taproot,
+            Readable::read(&mut Cursor::new(&buf)).unwrap()
+        );
+        let mut buf = Vec::new();
+        sign_taproot.write(&mut buf).unwrap();
+        assert_eq!(
+            sign_taproot,
+            Readable::read(&mut Cursor::new(&buf)).unwrap()
+        );
+
+        handler_read_test(accept_taproot);
+        handler_read_test(sign_taproot);
+    }
+
+    #[test]
+    fn read_contract_update_test() {
+        let offer: OfferDlc =
+            serde_json::from_str(include_str!("./test_inputs/offer_msg.json")).unwrap();
+        let accept: AcceptDlc =
+            serde_json::from_str(include_str!("./test_inputs/accept_msg.json")).unwrap();
+        let sign: SignDlc =
+            serde_json::from_str(include_str!("./test_inputs/sign_msg.json")).unwrap();
+        let buffer_adaptor_signature =
+            sign.cet_adaptor_signatures.ecdsa_adaptor_signatures[0].signature;
+        let per_update_secret = secp256k1_zkp::SecretKey::from_slice(&[1; 32]).unwrap();
+        handler_read_test(crate::channel::AddContractOffer {
+            channel_id: [1; 32],
+            temporary_contract_id: [2; 32],
+            offer_collateral: 100000,
+            accept_collateral: 50000,
+            counter_balance: 200000,
+            next_per_update_point: accept.funding_pubkey,
+            contract_info: offer.contract_info,
+            cet_locktime: offer.cet_locktime,
+            refund_locktime: offer.refund_locktime,
+            cet_nsequence: 288,
+        });
+        handler_read_test(crate::channel::SettleContractOffer {
+            channel_id: [1; 32],
+            contract_id: [2; 32],
+            counter_payout: 100000,
+            next_per_update_point: accept.funding_pubkey,
+        });
+        handler_read_test(crate::channel::ContractUpdateAccept {
+            channel_id: [1; 32],
+            next_per_update_point: accept.funding_pubkey,
+            buffer_adaptor_signature,
+            cet_adaptor_signatures: vec![sign.cet_adaptor_signatures.clone(); 2],
+            refund_signatures: vec![sign.refund_signature; 2],
+        });
+        handler_read_test(crate::channel::ContractUpdateConfirm {
+            channel_id: [1; 32],
+            per_update_secret,
+            buffer_adaptor_signature,
+            cet_adaptor_signatures: vec![sign.cet_adaptor_signatures],
+            refund_signatures: vec![sign.refund_signature],
+        });
+        handler_read_test(crate::channel::ContractUpdateFinalize {
+            channel_id: [1; 32],
+            per_update_secret,
+        });
+    }
+
+    #[test]
+    fn read_reject_offer_test() {
+        handler_read_test(crate::RejectOffer {
+            temporary_contract_id: [1; 32],
+            reason: "Collateral too high".to_string(),
+        });
+        handler_read_test(crate::channel::RejectChannelOffer {
+            temporary_channel_id: [1; 32],
+            reason: String::new(),
+        });
+    }
+
     #[test]
     fn read_unknown_message_returns_none() {
         let handler = MessageHandler::new();
@@ -317,7 +569,7 @@ mod tests {
         let msg: OfferDlc = serde_json::from_str(input).unwrap();
         let handler = MessageHandler::new();
         handler.send_message(some_pk(), Message::Offer(msg));
-        assert_eq!(handler.msg_events.lock().unwrap().len(), 1);
+        assert_eq!(handler.msg_events.lock().unwrap()[&some_pk()].len(), 1);
     }
 
     #[test]
@@ -326,7 +578,7 @@ mod tests {
         let msg: AcceptDlc = serde_json::from_str(input).unwrap();
         let handler = MessageHandler::new();
         handler.send_message(some_pk(), Message::Accept(msg));
-        assert!(handler.msg_events.lock().unwrap().len() > 1);
+        assert!(handler.msg_events.lock().unwrap()[&some_pk()].len() > 1);
     }
 
     #[test]
@@ -339,6 +591,54 @@ mod tests {
         assert!(!handler.has_pending_messages());
     }
 
+    #[test]
+    fn messages_are_queued_per_peer_test() {
+        let input = include_str!("./test_inputs/accept_msg.json");
+        let msg: AcceptDlc = serde_json::from_str(input).unwrap();
+        let other_pk =
+            PublicKey::from_secret_key(SECP256K1, &SecretKey::from_slice(&[2; 32]).unwrap());
+        let handler = MessageHandler::new();
+        handler.send_message(some_pk(), Message::Accept(msg.clone()));
+        handler.send_message(other_pk, Message::Accept(msg));
+        assert!(handler.has_pending_messages_for_peer(&other_pk));
+
+        handler.peer_disconnected(&other_pk);
+        assert!(!handler.has_pending_messages_for_peer(&other_pk));
+        assert!(handler.has_pending_messages_for_peer(&some_pk()));
+
+        let msgs = handler.get_and_clear_pending_msg();
+        assert!(msgs.len() > 1);
+        assert!(msgs.iter().all(|(pk, _)| pk == &some_pk()));
+        assert!(matches!(msgs[0].1, WireMessage::SegmentStart(_)));
+        assert!(msgs[1..]
+            .iter()
+            .all(|(_, m)| matches!(m, WireMessage::SegmentChunk(_))));
+    }
+
+    #[test]
+    fn try_send_large_message_round_trip_test() {
+        let input = include_str!("./test_inputs/accept_msg.json");
+        let msg: AcceptDlc = serde_json::from_str(input).unwrap();
+        let sender = MessageHandler::new();
+        let receiver = MessageHandler::new();
+        sender
+            .try_send_message(some_pk(), Message::Accept(msg.clone()))
+            .expect("to be able to send the message");
+
+        for (_, wire_message) in sender.get_and_clear_pending_msg() {
+            receiver
+                .handle_custom_message(wire_message, &some_pk())
+                .expect("to be able to process the message");
+        }
+
+        let received = receiver.get_and_clear_received_messages();
+        assert_eq!(1, received.len());
+        match &received[0].1 {
+            Message::Accept(a) => assert_eq!(&msg, a),
+            _ => panic!("Expected an accept message"),
+        }
+    }
+
     #[test]
     fn rebuilds_segments_properly_test() {
         let input1 = include_str!("./test_inputs/segment_start_msg.json");
@@ -2,7 +2,9 @@
 
 use lightning::ln::msgs::DecodeError;
 use lightning::ln::wire::Type;
-use lightning::util::ser::{Readable, Writeable, Writer};
+use lightning::util::ser::{Readable, Writeable, Writer, MAX_BUF_SIZE};
+
+use crate::{Message, WireMessage};
 
 /// The type of the [`SegmentStart`] message.
 pub const SEGMENT_START_TYPE: u16 = 42900;
@@ -13,13 +15,61 @@ pub const SEGMENT_CHUNK_TYPE: u16 = 42902;
 /// Maximum allowed size by noise protocol: <http://www.noiseprotocol.org/noise.html#message-format>
 pub const MAX_DATA_SIZE: usize = 65535;
 
-// Max data size - 2 for wrapper type - 5 for bigsize length prefix - 2 for nb segments
-const MAX_START_DATA_SIZE: usize = 65526;
+// 2 for wrapper type - 5 for bigsize length prefix - 2 for nb segments
+const SEGMENT_START_OVERHEAD: usize = 9;
+
+// 2 for wrapper type - 5 for bigsize length prefix
+const SEGMENT_CHUNK_OVERHEAD: usize = 7;
+
+const MAX_START_DATA_SIZE: usize = MAX_DATA_SIZE - SEGMENT_START_OVERHEAD;
+
+const MAX_CHUNK_SIZE: usize = MAX_DATA_SIZE - SEGMENT_CHUNK_OVERHEAD;
 
-// Max data size - 2 for wrapper type - 5 for bigsize length prefix
-const MAX_CHUNK_SIZE: usize = 65528;
+/// The maximum number of segments a message can be split into.
+pub const MAX_SEGMENTS: usize = 1000;
 
-const MAX_SEGMENTS: usize = 1000;
+/// The maximum serialized size of a message that can be sent by splitting it
+/// into segments.
+pub const MAX_SEGMENTED_MESSAGE_SIZE: usize =
+    MAX_START_DATA_SIZE - 2 + (MAX_SEGMENTS - 1) * MAX_CHUNK_SIZE;
+
+/// The size of the data carried by segments, derived from the maximum size of
+/// the wire messages of a transport.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SegmentSizes {
+    pub(crate) start: usize,
+    pub(crate) chunk: usize,
+}
+
+impl SegmentSizes {
+    /// Returns the segment sizes for wire messages of at most the given size,
+    /// or an error if they cannot hold any data.
+    pub(crate) fn new(max_data_size: usize) -> Result<Self, segment_reader::Error> {
+        if max_data_size <= SEGMENT_START_OVERHEAD || max_data_size > MAX_DATA_SIZE {
+            return Err(segment_reader::Error::InvalidParameter(format!(
+                "Maximum data size must be greater than {} and at most {}.",
+                SEGMENT_START_OVERHEAD, MAX_DATA_SIZE
+            )));
+        }
+        Ok(SegmentSizes {
+            start: max_data_size - SEGMENT_START_OVERHEAD,
+            chunk: max_data_size - SEGMENT_CHUNK_OVERHEAD,
+        })
+    }
+
+    fn max_message_size(&self) -> usize {
+        self.start - 2 + (MAX_SEGMENTS - 1) * self.chunk
+    }
+}
+
+impl Default for SegmentSizes {
+    fn default() -> Self {
+        SegmentSizes {
+            start: MAX_START_DATA_SIZE,
+            chunk: MAX_CHUNK_SIZE,
+        }
+    }
+}
 
 pub mod segment_reader;
 
@@ -69,43 +119,161 @@ impl Type for SegmentChunk {
     }
 }
 
+/// Writer splitting the data written to it into the data of a
+/// [`SegmentStart`] followed by [`SegmentChunk`]s, so that a message can be
+/// segmented as it is serialized rather than from an intermediate buffer.
+struct SegmentWriter {
+    sizes: SegmentSizes,
+    start_data: Vec<u8>,
+    chunks: Vec<SegmentChunk>,
+}
+
+impl SegmentWriter {
+    /// Creates a writer for a message with the given type and serialized size
+    /// (excluding the type prefix).
+    fn new(msg_type: u16, data_size: usize, sizes: SegmentSizes) -> Self {
+        let nb_chunks =
+            ((data_size + 2).saturating_sub(sizes.start) + sizes.chunk - 1) / sizes.chunk;
+        let mut writer = SegmentWriter {
+            sizes,
+            start_data: Vec::with_capacity(sizes.start),
+            chunks: Vec::with_capacity(nb_chunks),
+        };
+        msg_type
+            .write(&mut writer)
+            .expect("to be able to write the type prefix");
+        writer
+    }
+
+    fn into_segments(self) -> (SegmentStart, Vec<SegmentChunk>) {
+        debug_assert_eq!(self.sizes.start, self.start_data.len());
+
+        let nb_segments = (self.chunks.len() + 1) as u16;
+        debug_assert!(nb_segments > 1);
+
+        let segment_start = SegmentStart {
+            nb_segments,
+            data: self.start_data,
+        };
+
+        (segment_start, self.chunks)
+    }
+}
+
+impl lightning::io::Write for SegmentWriter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, lightning::io::Error> {
+        let chunk_size = self.sizes.chunk;
+        let (data, max_size) = if self.start_data.len() < self.sizes.start {
+            (&mut self.start_data, self.sizes.start)
+        } else {
+            if self
+                .chunks
+                .last()
+                .map_or(true, |c| c.data.len() == chunk_size)
+            {
+                self.chunks.push(SegmentChunk {
+                    data: Vec::with_capacity(chunk_size),
+                });
+            }
+            let chunk = self.chunks.last_mut().expect("to have a chunk");
+            (&mut chunk.data, chunk_size)
+        };
+        let to_take = usize::min(buf.len(), max_size - data.len());
+        data.extend_from_slice(&buf[..to_take]);
+        Ok(to_take)
+    }
+
+    fn flush(&mut self) -> Result<(), lightning::io::Error> {
+        Ok(())
+    }
+}
+
 /// Split the given data into multiple segments, pre-pending the message type
 /// to enable decoding on the receiving side.
-pub fn get_segments(mut data: Vec<u8>, msg_type: u16) -> (SegmentStart, Vec<SegmentChunk>) {
+pub fn get_segments(data: Vec<u8>, msg_type: u16) -> (SegmentStart, Vec<SegmentChunk>) {
     debug_assert!(data.len() > MAX_DATA_SIZE);
 
-    let len_minus_start = data.len() - MAX_START_DATA_SIZE + 2;
-    let mut nb_segments = (len_minus_start / MAX_CHUNK_SIZE + 1) as u16;
+    let mut writer = SegmentWriter::new(msg_type, data.len(), SegmentSizes::default());
+    Writer::write_all(&mut writer, &data).expect("to be able to write to memory");
+    writer.into_segments()
+}
 
-    if len_minus_start % MAX_CHUNK_SIZE != 0 {
-        nb_segments += 1;
+/// Serializes the given message directly into segments, without first
+/// encoding it into a single buffer. The message must not fit in the start
+/// segment.
+pub(crate) fn write_segments(
+    msg: &Message,
+    sizes: SegmentSizes,
+) -> (SegmentStart, Vec<SegmentChunk>) {
+    let data_size = msg.serialized_length();
+    debug_assert!(data_size > sizes.start);
+
+    let mut writer = SegmentWriter::new(msg.type_id(), data_size, sizes);
+    msg.write(&mut writer)
+        .expect("to be able to write to memory");
+    writer.into_segments()
+}
+
+/// Returns the number of wire messages required to send a message with the
+/// given serialized size, or `None` if the message is too large to be sent
+/// even when split into segments.
+pub fn get_nb_segments(message_size: usize) -> Option<usize> {
+    if message_size <= MAX_BUF_SIZE {
+        return Some(1);
     }
 
-    debug_assert!(nb_segments > 1);
+    if message_size > MAX_SEGMENTED_MESSAGE_SIZE {
+        return None;
+    }
 
-    let mut start_data = Vec::with_capacity(MAX_START_DATA_SIZE);
-    msg_type
-        .write(&mut start_data)
-        .expect("to be able to write the type prefix");
-    start_data.append(&mut data.drain(..MAX_START_DATA_SIZE - 2).collect());
+    let len_minus_start = message_size + 2 - MAX_START_DATA_SIZE;
+    Some(1 + (len_minus_start + MAX_CHUNK_SIZE - 1) / MAX_CHUNK_SIZE)
+}
 
-    debug_assert_eq!(MAX_START_DATA_SIZE, start_data.len());
+/// Returns the wire messages to send for the given message, splitting it into
+/// segments if it is too large to be sent as a single message. Returns an
+/// error if the message is too large to be sent even when segmented.
+pub fn segment_message(msg: Message) -> Result<Vec<WireMessage>, segment_reader::Error> {
+    let message_size = msg.serialized_length();
+    split_message(msg, message_size <= MAX_BUF_SIZE, SegmentSizes::default())
+}
 
-    let segment_start = SegmentStart {
-        nb_segments,
-        data: start_data,
-    };
+/// Returns the wire messages to send for the given message over a transport
+/// whose messages, including their type prefix, cannot exceed the given size,
+/// splitting it into segments if needed. The segments must be read using a
+/// [`segment_reader::SegmentReader`] created with the same maximum size.
+pub fn segment_message_with_max_size(
+    msg: Message,
+    max_data_size: usize,
+) -> Result<Vec<WireMessage>, segment_reader::Error> {
+    let sizes = SegmentSizes::new(max_data_size)?;
+    let message_size = msg.serialized_length();
+    split_message(msg, message_size + 2 <= max_data_size, sizes)
+}
 
-    let mut segments = Vec::with_capacity((nb_segments as usize) - 1);
+fn split_message(
+    msg: Message,
+    fits: bool,
+    sizes: SegmentSizes,
+) -> Result<Vec<WireMessage>, segment_reader::Error> {
+    if fits {
+        return Ok(vec![WireMessage::Message(msg)]);
+    }
 
-    for _ in 1..(nb_segments as usize) {
-        let to_take = usize::min(data.len(), MAX_CHUNK_SIZE);
-        segments.push(SegmentChunk {
-            data: data.drain(..to_take).collect(),
-        });
+    let message_size = msg.serialized_length();
+    if message_size > sizes.max_message_size() {
+        return Err(segment_reader::Error::InvalidParameter(format!(
+            "Message of size {} exceeds the maximum segmented message size of {}.",
+            message_size,
+            sizes.max_message_size()
+        )));
     }
 
-    (segment_start, segments)
+    let (segment_start, segment_chunks) = write_segments(&msg, sizes);
+    let mut res = Vec::with_capacity(segment_chunks.len() + 1);
+    res.push(WireMessage::SegmentStart(segment_start));
+    res.extend(segment_chunks.into_iter().map(WireMessage::SegmentChunk));
+    Ok(res)
 }
 
 #[cfg(test)]
@@ -127,4 +295,75 @@ mod tests {
         assert_eq!(MAX_CHUNK_SIZE, segment_chunks[1].data.len());
         assert_eq!(1236, segment_chunks[2].data.len());
     }
+
+    #[test]
+    fn get_nb_segments_matches_segment_message_test() {
+        let accept: crate::AcceptDlc =
+            serde_json::from_str(include_str!("../test_inputs/accept_msg.json")).unwrap();
+        let msg = Message::Accept(accept);
+        let expected = get_nb_segments(msg.serialized_length()).unwrap();
+
+        let wire_messages = segment_message(msg).unwrap();
+
+        assert!(expected > 1);
+        assert_eq!(expected, wire_messages.len());
+        match &wire_messages[0] {
+            WireMessage::SegmentStart(s) => assert_eq!(expected, s.nb_segments as usize),
+            _ => panic!("Expected a segment start"),
+        }
+    }
+
+    #[test]
+    fn write_segments_matches_get_segments_test() {
+        let accept: crate::AcceptDlc =
+            serde_json::from_str(include_str!("../test_inputs/accept_msg.json")).unwrap();
+        let msg = Message::Accept(accept);
+
+        let expected = get_segments(msg.encode(), msg.type_id());
+
+        assert_eq!(expected, write_segments(&msg, SegmentSizes::default()));
+    }
+
+    #[test]
+    fn segment_message_with_max_size_test() {
+        let accept: crate::AcceptDlc =
+            serde_json::from_str(include_str!("../test_inputs/accept_msg.json")).unwrap();
+        let msg = Message::Accept(accept);
+
+        let wire_messages = segment_message_with_max_size(msg.clone(), 1000).unwrap();
+
+        assert!(wire_messages.len() > 2);
+        assert!(wire_messages
+            .iter()
+            .all(|m| m.serialized_length() + 2 <= 1000));
+        let mut reader = segment_reader::SegmentReader::with_max_data_size(1000).unwrap();
+        let (last, segments) = wire_messages.split_last().unwrap();
+        for segment in segments {
+            assert!(reader
+                .process_wire_message(segment.clone())
+                .unwrap()
+                .is_none());
+        }
+        assert_eq!(
+            Some(msg),
+            reader.process_wire_message(last.clone()).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_max_size_is_rejected_test() {
+        assert!(SegmentSizes::new(SEGMENT_START_OVERHEAD).is_err());
+        assert!(SegmentSizes::new(MAX_DATA_SIZE + 1).is_err());
+    }
+
+    #[test]
+    fn get_nb_segments_test() {
+        assert_eq!(Some(1), get_nb_segments(MAX_BUF_SIZE));
+        assert_eq!(Some(2), get_nb_segments(MAX_BUF_SIZE + 1));
+        assert_eq!(
+            Some(MAX_SEGMENTS),
+            get_nb_segments(MAX_SEGMENTED_MESSAGE_SIZE)
+        );
+        assert_eq!(None, get_nb_segments(MAX_SEGMENTED_MESSAGE_SIZE + 1));
+    }
 }
@@ -1,9 +1,16 @@
 //! Module helping with processing message segmentation related messages.
 
-use super::{SegmentChunk, SegmentStart, MAX_CHUNK_SIZE, MAX_SEGMENTS, MAX_START_DATA_SIZE};
+use lightning::io::Cursor;
+use lightning::ln::msgs::DecodeError;
+use lightning::util::ser::Readable;
+
+use super::{SegmentChunk, SegmentSizes, SegmentStart, MAX_SEGMENTS};
+use crate::message_handler::read_dlc_message;
+use crate::{Message, WireMessage};
 
 /// Struct helping with processing message segmentation related messages.
 pub struct SegmentReader {
+    sizes: SegmentSizes,
     cur_data: Vec<u8>,
     remaining_segments: u16,
 }
@@ -15,6 +22,8 @@ pub enum Error {
     InvalidState(String),
     /// A parameter received by the reader was not in accordance with its state.
     InvalidParameter(String),
+    /// The message reconstructed from the segments could not be decoded.
+    Decode(DecodeError),
 }
 
 impl std::fmt::Display for Error {
@@ -22,16 +31,18 @@ impl std::fmt::Display for Error {
         match *self {
             Error::InvalidState(ref s) => write!(f, "Invalid state {}", s),
             Error::InvalidParameter(ref s) => write!(f, "Invalid parameters were provided: {}", s),
+            Error::Decode(ref e) => write!(f, "Could not reconstruct message from segments: {}", e),
         }
     }
 }
 
-#[cfg(not(feature = "no-std"))]
+#[cfg(feature = "std")]
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Error::InvalidState(_) => None,
             Error::InvalidParameter(_) => None,
+            Error::Decode(_) => None,
         }
     }
 }
@@ -46,11 +57,23 @@ impl SegmentReader {
     /// Returns a new instance of [`Self`].
     pub fn new() -> Self {
         SegmentReader {
+            sizes: SegmentSizes::default(),
             cur_data: Vec::new(),
             remaining_segments: 0,
         }
     }
 
+    /// Returns a new instance of [`Self`] reading the segments of messages
+    /// split using [`super::segment_message_with_max_size`] with the given
+    /// maximum size.
+    pub fn with_max_data_size(max_data_size: usize) -> Result<Self, Error> {
+        Ok(SegmentReader {
+            sizes: SegmentSizes::new(max_data_size)?,
+            cur_data: Vec::new(),
+            remaining_segments: 0,
+        })
+    }
+
     /// Reset the state of the reader
     pub fn reset(&mut self) {
         self.cur_data = Vec::new();
@@ -77,7 +100,7 @@ impl SegmentReader {
             ));
         }
 
-        if segment_start.data.len() < MAX_START_DATA_SIZE {
+        if segment_start.data.len() < self.sizes.start {
             return Err(Error::InvalidParameter(
                 "Segment start data should be filled to its maximum capacity.".to_string(),
             ));
@@ -103,7 +126,7 @@ impl SegmentReader {
             ));
         }
 
-        if self.remaining_segments > 1 && segment_chunk.data.len() != MAX_CHUNK_SIZE {
+        if self.remaining_segments > 1 && segment_chunk.data.len() != self.sizes.chunk {
             return Err(Error::InvalidParameter(
                 "Receive non final segment chunk that was not not filled.".to_string(),
             ));
@@ -120,6 +143,47 @@ impl SegmentReader {
             Ok(None)
         }
     }
+
+    /// Process a [`WireMessage`], returning the message it contains or
+    /// completes, if any. Receiving anything else than a segment chunk while
+    /// expecting one discards the partially received message.
+    pub fn process_wire_message(&mut self, msg: WireMessage) -> Result<Option<Message>, Error> {
+        match msg {
+            WireMessage::Message(m) => {
+                self.reset();
+                Ok(Some(m))
+            }
+            WireMessage::SegmentStart(s) => {
+                self.reset();
+                self.process_segment_start(s)?;
+                Ok(None)
+            }
+            WireMessage::SegmentChunk(s) => {
+                if !self.expecting_chunk() {
+                    return Err(Error::InvalidState(
+                        "Received a SegmentChunk while not expecting one.".to_string(),
+                    ));
+                }
+                match self.process_segment_chunk(s)? {
+                    Some(data) => read_segmented_message(data).map(Some),
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+}
+
+/// Decodes the message from the data reconstructed from its segments.
+fn read_segmented_message(data: Vec<u8>) -> Result<Message, Error> {
+    let mut buf = Cursor::new(data);
+    let message_type = <u16 as Readable>::read(&mut buf).map_err(Error::Decode)?;
+    match read_dlc_message(message_type, &mut buf).map_err(Error::Decode)? {
+        Some(WireMessage::Message(m)) => Ok(m),
+        _ => Err(Error::InvalidParameter(format!(
+            "Unexpected message type {} in segments.",
+            message_type
+        ))),
+    }
 }
 
 #[cfg(test)]
@@ -241,4 +305,36 @@ mod tests {
             .process_segment_chunk(segment_chunks[0].clone())
             .expect_err("should not accept not full segment that is not the last one");
     }
+
+    #[test]
+    fn process_wire_messages_rebuilds_message_test() {
+        let accept: crate::AcceptDlc =
+            serde_json::from_str(include_str!("../test_inputs/accept_msg.json")).unwrap();
+        let mut segment_reader = SegmentReader::new();
+        let mut wire_messages = super::super::segment_message(Message::Accept(accept.clone()))
+            .expect("to be able to segment the message");
+        let last = wire_messages.pop().unwrap();
+
+        for wire_message in wire_messages {
+            assert!(segment_reader
+                .process_wire_message(wire_message)
+                .expect("to be able to process the segment")
+                .is_none());
+        }
+
+        match segment_reader.process_wire_message(last) {
+            Ok(Some(Message::Accept(a))) => assert_eq!(accept, a),
+            _ => panic!("Expected the accept message to be rebuilt"),
+        }
+        assert!(!segment_reader.expecting_chunk());
+    }
+
+    #[test]
+    fn process_wire_message_unexpected_chunk_fails_test() {
+        let mut segment_reader = SegmentReader::new();
+        let (_, mut segment_chunks) = segments();
+        segment_reader
+            .process_wire_message(WireMessage::SegmentChunk(segment_chunks.pop().unwrap()))
+            .expect_err("should not process a chunk without a start");
+    }
 }
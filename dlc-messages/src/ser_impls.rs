@@ -2,7 +2,7 @@
 
 use bitcoin::network::constants::Network;
 use bitcoin::Address;
-use dlc::{EnumerationPayout, PartyParams, Payout, TxInputInfo};
+use dlc::{EnumerationPayout, FeeSplit, PartyParams, Payout, TxInputInfo};
 use lightning::io::Read;
 use lightning::ln::msgs::DecodeError;
 use lightning::ln::wire::Type;
@@ -11,7 +11,7 @@ use secp256k1_zkp::{ffi::ECDSA_ADAPTOR_SIGNATURE_LENGTH, EcdsaAdaptorSignature};
 use std::collections::HashMap;
 use std::hash::Hash;
 
-const MAX_VEC_SIZE: u64 = 1000000;
+pub(crate) const MAX_VEC_SIZE: u64 = 1000000;
 
 /// Taken from rust-lightning: <https://github.com/rust-bitcoin/rust-lightning/blob/v0.0.101/lightning/src/util/ser.rs#L295>
 ///
@@ -47,33 +47,39 @@ impl Readable for BigSize {
     #[inline]
     fn read<R: Read>(reader: &mut R) -> Result<BigSize, DecodeError> {
         let n: u8 = Readable::read(reader)?;
-        match n {
-            0xFF => {
-                let x: u64 = Readable::read(reader)?;
-                if x < 0x100000000 {
-                    Err(DecodeError::InvalidValue)
-                } else {
-                    Ok(BigSize(x))
-                }
+        read_big_size_with_prefix(n, reader)
+    }
+}
+
+/// Reads the remainder of a [`BigSize`] value whose first byte was already
+/// consumed from the reader.
+fn read_big_size_with_prefix<R: Read>(n: u8, reader: &mut R) -> Result<BigSize, DecodeError> {
+    match n {
+        0xFF => {
+            let x: u64 = Readable::read(reader)?;
+            if x < 0x100000000 {
+                Err(DecodeError::InvalidValue)
+            } else {
+                Ok(BigSize(x))
             }
-            0xFE => {
-                let x: u32 = Readable::read(reader)?;
-                if x < 0x10000 {
-                    Err(DecodeError::InvalidValue)
-                } else {
-                    Ok(BigSize(x as u64))
-                }
+        }
+        0xFE => {
+            let x: u32 = Readable::read(reader)?;
+            if x < 0x10000 {
+                Err(DecodeError::InvalidValue)
+            } else {
+                Ok(BigSize(x as u64))
             }
-            0xFD => {
-                let x: u16 = Readable::read(reader)?;
-                if x < 0xFD {
-                    Err(DecodeError::InvalidValue)
-                } else {
-                    Ok(BigSize(x as u64))
-                }
+        }
+        0xFD => {
+            let x: u16 = Readable::read(reader)?;
+            if x < 0xFD {
+                Err(DecodeError::InvalidValue)
+            } else {
+                Ok(BigSize(x as u64))
             }
-            n => Ok(BigSize(n as u64)),
         }
+        n => Ok(BigSize(n as u64)),
     }
 }
 
@@ -183,6 +189,22 @@ pub fn write_f64<W: lightning::util::ser::Writer>(
     extra_precision.write(writer)
 }
 
+/// Returns the value obtained when reading back the given `f64` after writing
+/// it with [`write_f64`], or `None` if the value cannot be serialized. Values
+/// used to compute payouts should be rounded with this function before being
+/// used, so that both parties of a contract compute the same payouts.
+pub fn round_f64(input: f64) -> Option<f64> {
+    if !input.is_finite() || f64::abs(input) >= u64::MAX as f64 {
+        return None;
+    }
+    let input_abs = f64::abs(input);
+    let no_precision = f64::floor(input_abs);
+    let extra_precision = f64::floor((input_abs - no_precision) * ((1 << 16) as f64));
+    let mul_sign: f64 = if input >= 0.0 { 1.0 } else { -1.0 };
+
+    Some((no_precision + extra_precision / ((1 << 16) as f64)) * mul_sign)
+}
+
 /// Reads an `f64` value from the given reader.
 pub fn read_f64<R: ::lightning::io::Read>(
     reader: &mut R,
@@ -542,6 +564,47 @@ pub fn read_ecdsa_adaptor_signatures<R: ::lightning::io::Read>(
     read_vec_cb(reader, &read_ecdsa_adaptor_signature)
 }
 
+/// Writes a [`dlc::taproot::adaptor::SchnorrAdaptorSignature`] to the given
+/// writer.
+#[cfg(feature = "taproot")]
+pub fn write_schnorr_adaptor_signature<W: Writer>(
+    sig: &dlc::taproot::adaptor::SchnorrAdaptorSignature,
+    writer: &mut W,
+) -> Result<(), ::lightning::io::Error> {
+    sig.serialize().write(writer)
+}
+
+/// Reads a [`dlc::taproot::adaptor::SchnorrAdaptorSignature`] from the given
+/// reader.
+#[cfg(feature = "taproot")]
+pub fn read_schnorr_adaptor_signature<R: ::lightning::io::Read>(
+    reader: &mut R,
+) -> Result<dlc::taproot::adaptor::SchnorrAdaptorSignature, DecodeError> {
+    let buf: [u8; 64] = Readable::read(reader)?;
+    dlc::taproot::adaptor::SchnorrAdaptorSignature::from_slice(&buf)
+        .map_err(|_| DecodeError::InvalidValue)
+}
+
+/// Writes a set of [`dlc::taproot::adaptor::SchnorrAdaptorSignature`] to the
+/// given writer.
+#[cfg(feature = "taproot")]
+#[allow(clippy::ptr_arg)] // Need to have Vec to work with callbacks.
+pub fn write_schnorr_adaptor_signatures<W: Writer>(
+    sigs: &Vec<dlc::taproot::adaptor::SchnorrAdaptorSignature>,
+    writer: &mut W,
+) -> Result<(), ::lightning::io::Error> {
+    write_vec_cb(sigs, writer, &write_schnorr_adaptor_signature)
+}
+
+/// Reads a set of [`dlc::taproot::adaptor::SchnorrAdaptorSignature`] from the
+/// given reader.
+#[cfg(feature = "taproot")]
+pub fn read_schnorr_adaptor_signatures<R: ::lightning::io::Read>(
+    reader: &mut R,
+) -> Result<Vec<dlc::taproot::adaptor::SchnorrAdaptorSignature>, DecodeError> {
+    read_vec_cb(reader, &read_schnorr_adaptor_signature)
+}
+
 /// Writes an `i32` value to the given writer.
 pub fn write_i32<W: Writer>(i: &i32, writer: &mut W) -> Result<(), ::lightning::io::Error> {
     i.to_be_bytes().write(writer)
@@ -590,6 +653,57 @@ pub fn read_as_tlv<T: Type + Readable, R: Read>(reader: &mut R) -> Result<T, Dec
     Readable::read(reader)
 }
 
+/// Writes a TLV record with the given type and value to the given writer.
+pub fn write_tlv_record<W: Writer>(
+    tlv_type: u64,
+    value: &[u8],
+    writer: &mut W,
+) -> Result<(), ::lightning::io::Error> {
+    BigSize(tlv_type).write(writer)?;
+    BigSize(value.len() as u64).write(writer)?;
+    writer.write_all(value)
+}
+
+/// Reads a stream of TLV records until the end of the given reader, passing
+/// the type and value of each record to `handle`. `handle` returns whether it
+/// knows the type of the record, in which case it must consume the whole value.
+/// As in the lightning specification, records must be ordered by strictly
+/// increasing type, unknown records with an odd type are ignored and unknown
+/// records with an even type are rejected.
+pub fn read_tlv_stream<R: Read, F>(reader: &mut R, mut handle: F) -> Result<(), DecodeError>
+where
+    F: FnMut(u64, &mut &[u8]) -> Result<bool, DecodeError>,
+{
+    let mut last_type: Option<u64> = None;
+    loop {
+        let mut first = [0u8; 1];
+        if reader.read(&mut first)? == 0 {
+            return Ok(());
+        }
+        let tlv_type = read_big_size_with_prefix(first[0], reader)?.0;
+        if last_type.map_or(false, |last| tlv_type <= last) {
+            return Err(DecodeError::InvalidValue);
+        }
+        last_type = Some(tlv_type);
+
+        let len: BigSize = Readable::read(reader)?;
+        if len.0 > MAX_VEC_SIZE {
+            return Err(DecodeError::InvalidValue);
+        }
+        let mut value = vec![0u8; len.0 as usize];
+        reader.read_exact(&mut value)?;
+
+        let mut value_reader = &value[..];
+        if handle(tlv_type, &mut value_reader)? {
+            if !value_reader.is_empty() {
+                return Err(DecodeError::InvalidValue);
+            }
+        } else if tlv_type % 2 == 0 {
+            return Err(DecodeError::UnknownRequiredFeature);
+        }
+    }
+}
+
 /// Writes a [`HashMap`].
 pub fn write_hash_map<W: Writer, T, V>(
     input: &HashMap<T, V>,
@@ -626,6 +740,32 @@ where
     Ok(map)
 }
 
+/// Writes a [`FeeSplit`] as a single byte.
+pub fn write_fee_split<W: Writer>(
+    fee_split: &FeeSplit,
+    writer: &mut W,
+) -> Result<(), ::lightning::io::Error> {
+    let id: u8 = match fee_split {
+        FeeSplit::Equal => 0,
+        FeeSplit::OfferPaysAll => 1,
+        FeeSplit::AcceptPaysAll => 2,
+        FeeSplit::Proportional => 3,
+    };
+    id.write(writer)
+}
+
+/// Reads a [`FeeSplit`] written with [`write_fee_split`].
+pub fn read_fee_split<R: Read>(reader: &mut R) -> Result<FeeSplit, DecodeError> {
+    let id: u8 = Readable::read(reader)?;
+    match id {
+        0 => Ok(FeeSplit::Equal),
+        1 => Ok(FeeSplit::OfferPaysAll),
+        2 => Ok(FeeSplit::AcceptPaysAll),
+        3 => Ok(FeeSplit::Proportional),
+        _ => Err(DecodeError::UnknownRequiredFeature),
+    }
+}
+
 impl_dlc_writeable_external!(Payout, payout, { (offer, writeable), (accept, writeable) });
 impl_dlc_writeable_external!(EnumerationPayout, enum_payout, { (outcome, string), (payout, { cb_writeable, payout::write, payout::read} )});
 impl_dlc_writeable_external!(TxInputInfo, tx_input_info, { (outpoint, writeable), (max_witness_len, usize), (redeem_script, writeable), (serial_id, writeable)});
@@ -76,10 +76,102 @@ macro_rules! field_read {
     };
 }
 
+/// Writes an optional field as a TLV record with the given type, if it is set.
+#[macro_export]
+macro_rules! tlv_field_write {
+    ($stream: expr, $tlv_type: expr, $field: expr, option) => {
+        if let Some(ref v) = $field {
+            $crate::ser_impls::write_tlv_record($tlv_type, &v.encode(), $stream)?;
+        }
+    };
+    ($stream: expr, $tlv_type: expr, $field: expr, {option_cb, $w_cb: expr, $r_cb: expr}) => {
+        if let Some(ref v) = $field {
+            let mut buf = Vec::new();
+            $w_cb(v, &mut buf)?;
+            $crate::ser_impls::write_tlv_record($tlv_type, &buf, $stream)?;
+        }
+    };
+}
+
+/// Reads the value of a TLV record into an optional field.
+#[macro_export]
+macro_rules! tlv_field_read {
+    ($stream: expr, option) => {
+        Some(Readable::read($stream)?)
+    };
+    ($stream: expr, {option_cb, $w_cb: expr, $r_cb: expr}) => {
+        Some($r_cb($stream)?)
+    };
+}
+
 /// Implements the [`lightning::util::ser::Writeable`] trait for a struct available
-/// in this crate.
+/// in this crate. Optional fields listed after `tlv_stream` are written as TLV
+/// records with the given types after the other fields, so that they can be
+/// added without breaking peers that do not know about them. As the stream is
+/// read until the end of the input, such structs must be the last element of
+/// what is being read.
 #[macro_export]
 macro_rules! impl_dlc_writeable {
+    ($st:ident, {$(($field: ident, $fieldty: tt)), *}, tlv_stream: {$(($tlv_type: expr, $tlv_field: ident, $tlv_fieldty: tt)), *} ) => {
+        impl $st {
+            /// Writes the fields of the struct that are not part of its TLV stream.
+            pub fn write_fields<W: Writer>(&self, w: &mut W) -> Result<(), ::lightning::io::Error> {
+                $(
+                    field_write!(w, self.$field, $fieldty);
+                )*
+                Ok(())
+            }
+
+            /// Writes the TLV stream of the struct.
+            pub fn write_tlv_stream<W: Writer>(&self, w: &mut W) -> Result<(), ::lightning::io::Error> {
+                $(
+                    tlv_field_write!(w, $tlv_type, self.$tlv_field, $tlv_fieldty);
+                )*
+                Ok(())
+            }
+
+            /// Reads the fields of the struct that are not part of its TLV
+            /// stream, leaving the fields of the stream unset.
+            pub fn read_fields<R: lightning::io::Read>(r: &mut R) -> Result<Self, DecodeError> {
+                Ok(Self {
+                    $(
+                        $field: field_read!(r, $fieldty),
+                    )*
+                    $(
+                        $tlv_field: None,
+                    )*
+                })
+            }
+
+            /// Reads the TLV stream of the struct until the end of the reader.
+            pub fn read_tlv_stream<R: lightning::io::Read>(&mut self, r: &mut R) -> Result<(), DecodeError> {
+                $crate::ser_impls::read_tlv_stream(r, |tlv_type, value| {
+                    $(
+                        if tlv_type == $tlv_type {
+                            self.$tlv_field = tlv_field_read!(value, $tlv_fieldty);
+                            return Ok(true);
+                        }
+                    )*
+                    Ok(false)
+                })
+            }
+        }
+
+        impl Writeable for $st {
+            fn write<W: Writer>(&self, w: &mut W) -> Result<(), ::lightning::io::Error> {
+                self.write_fields(w)?;
+                self.write_tlv_stream(w)
+            }
+        }
+
+        impl Readable for $st {
+            fn read<R: lightning::io::Read>(r: &mut R) -> Result<Self, DecodeError> {
+                let mut res = Self::read_fields(r)?;
+                res.read_tlv_stream(r)?;
+                Ok(res)
+            }
+        }
+    };
     ($st:ident, {$(($field: ident, $fieldty: tt)), *} ) => {
         impl Writeable for $st {
 			fn write<W: Writer>(&self, w: &mut W) -> Result<(), ::lightning::io::Error> {
@@ -49,6 +49,42 @@ where
     }
 }
 
+/// Serialize an amount in satoshis as a string, so that it can be represented
+/// exactly by JSON parsers using double precision floating point numbers.
+pub fn serialize_amount<S>(amount: &u64, s: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    if s.is_human_readable() {
+        s.serialize_str(&amount.to_string())
+    } else {
+        s.serialize_u64(*amount)
+    }
+}
+
+/// Deserialize an amount in satoshis represented either as a string or as a
+/// number.
+pub fn deserialize_amount<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Amount {
+        Number(u64),
+        String(String),
+    }
+
+    if deserializer.is_human_readable() {
+        match serde::de::Deserialize::deserialize(deserializer)? {
+            Amount::Number(amount) => Ok(amount),
+            Amount::String(amount) => amount.parse().map_err(serde::de::Error::custom),
+        }
+    } else {
+        serde::de::Deserialize::deserialize(deserializer)
+    }
+}
+
 fn from_hex(hex: &str, target: &mut [u8]) -> Result<usize, String> {
     if hex.len() % 2 == 1 || hex.len() > target.len() * 2 {
         return Err("Invalid hex length".to_string());
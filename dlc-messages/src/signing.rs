@@ -0,0 +1,124 @@
+//! Canonical serialization of the data signed by the parties of a DLC.
+//!
+//! The refund, CET and funding signatures exchanged in the offer, accept and
+//! sign messages are all computed over the BIP143 signature hash of the
+//! relevant transaction input using `SIGHASH_ALL`. This module exposes the
+//! exact byte serialization (the pre-image) used to compute that hash so that
+//! external signers (e.g. HSMs) can reproduce and verify the digest they are
+//! asked to sign. Golden vectors for the serialization can be found in
+//! `src/test_inputs/signing_vectors.json`.
+
+use bitcoin::hashes::{sha256d, Hash};
+use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use bitcoin::{Script, Transaction};
+use dlc::Error;
+
+/// Version of the signing serialization implemented by this module. It will be
+/// incremented if the bytes produced by [`sighash_preimage`] for a given set of
+/// inputs ever change.
+pub const SIGNING_SERIALIZATION_VERSION: u16 = 1;
+
+/// Returns the BIP143 pre-image of the `SIGHASH_ALL` signature hash for the
+/// input at `input_index` of `tx`, spending an output of the given `value`
+/// locked by `script_code`. For refund transactions and CETs, `script_code` is
+/// the funding transaction 2-of-2 multisig script and `input_index` is 0.
+pub fn sighash_preimage(
+    tx: &Transaction,
+    input_index: usize,
+    script_code: &Script,
+    value: u64,
+) -> Result<Vec<u8>, Error> {
+    let mut preimage = Vec::new();
+    SighashCache::new(tx).segwit_encode_signing_data_to(
+        &mut preimage,
+        input_index,
+        script_code,
+        value,
+        EcdsaSighashType::All,
+    )?;
+    Ok(preimage)
+}
+
+/// Returns the digest signed by the parties for the given input, that is the
+/// double SHA256 of the output of [`sighash_preimage`].
+pub fn sighash_digest(
+    tx: &Transaction,
+    input_index: usize,
+    script_code: &Script,
+    value: u64,
+) -> Result<[u8; 32], Error> {
+    let preimage = sighash_preimage(tx, input_index, script_code, value)?;
+    Ok(sha256d::Hash::hash(&preimage).to_byte_array())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::ScriptBuf;
+    use secp256k1_zkp::{rand::thread_rng, Message, Secp256k1, SecretKey};
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct SigningVector {
+        version: u16,
+        tx: String,
+        input_index: usize,
+        script_code: String,
+        value: u64,
+        preimage: String,
+        digest: String,
+    }
+
+    fn vectors() -> Vec<SigningVector> {
+        serde_json::from_str(include_str!("./test_inputs/signing_vectors.json")).unwrap()
+    }
+
+    fn decode(vector: &SigningVector) -> (Transaction, ScriptBuf) {
+        let tx =
+            bitcoin::consensus::deserialize(&Vec::<u8>::from_hex(&vector.tx).unwrap()).unwrap();
+        let script_code = ScriptBuf::from_hex(&vector.script_code).unwrap();
+        (tx, script_code)
+    }
+
+    #[test]
+    fn preimage_matches_golden_vectors() {
+        for vector in vectors() {
+            assert_eq!(SIGNING_SERIALIZATION_VERSION, vector.version);
+            let (tx, script_code) = decode(&vector);
+            let preimage =
+                sighash_preimage(&tx, vector.input_index, &script_code, vector.value).unwrap();
+            assert_eq!(Vec::<u8>::from_hex(&vector.preimage).unwrap(), preimage);
+            let digest =
+                sighash_digest(&tx, vector.input_index, &script_code, vector.value).unwrap();
+            assert_eq!(
+                Vec::<u8>::from_hex(&vector.digest).unwrap(),
+                digest.to_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn digest_verifies_library_signatures() {
+        let secp = Secp256k1::new();
+        let sk = SecretKey::new(&mut thread_rng());
+        let pk = sk.public_key(&secp);
+        for vector in vectors() {
+            let (tx, script_code) = decode(&vector);
+            let sig = dlc::util::get_raw_sig_for_tx_input(
+                &secp,
+                &tx,
+                vector.input_index,
+                &script_code,
+                vector.value,
+                &sk,
+            )
+            .unwrap();
+            let digest =
+                sighash_digest(&tx, vector.input_index, &script_code, vector.value).unwrap();
+            secp.verify_ecdsa(&Message::from_slice(&digest).unwrap(), &sig, &pk)
+                .expect("digest to match the one signed by the library");
+        }
+    }
+}
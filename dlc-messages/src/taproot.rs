@@ -0,0 +1,122 @@
+//! Contains the messages used to establish contracts funded with a taproot
+//! output, which are exchanged in place of the [`AcceptDlc`](crate::AcceptDlc)
+//! and [`SignDlc`](crate::SignDlc) messages when the offer has the
+//! [`TAPROOT_FUNDING_FLAG`](crate::TAPROOT_FUNDING_FLAG) set.
+
+use bitcoin::ScriptBuf;
+use dlc::taproot::adaptor::SchnorrAdaptorSignature;
+use dlc::FeeSplit;
+use lightning::ln::msgs::DecodeError;
+use lightning::util::ser::{Readable, Writeable, Writer};
+use secp256k1_zkp::{schnorr::Signature as SchnorrSignature, PublicKey};
+
+use crate::ser_impls::{
+    read_schnorr_adaptor_signatures, read_schnorrsig, write_schnorr_adaptor_signatures,
+    write_schnorrsig,
+};
+use crate::{FundingInput, FundingSignatures};
+
+/// Contains information about a party wishing to accept an offer for a
+/// contract funded with a taproot output. Its content is the same as the one
+/// of an [`AcceptDlc`](crate::AcceptDlc) message, the signatures of the accept
+/// party being Schnorr signatures of the script path spends of the fund
+/// output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct AcceptTaprootDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The temporary contract id for the contract.
+    pub temporary_contract_id: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_amount",
+            deserialize_with = "crate::serde_utils::deserialize_amount"
+        )
+    )]
+    /// The collateral input by the accept party.
+    pub accept_collateral: u64,
+    /// The public key of the accept party to be used to lock the collateral.
+    pub funding_pubkey: PublicKey,
+    /// The SPK where the accept party will receive their payout.
+    pub payout_spk: ScriptBuf,
+    /// Serial id to order CET outputs.
+    pub payout_serial_id: u64,
+    /// Inputs used by the accept party to fund the contract.
+    pub funding_inputs: Vec<FundingInput>,
+    /// The SPK where the accept party will receive their change.
+    pub change_spk: ScriptBuf,
+    /// Serial id to order funding transaction outputs.
+    pub change_serial_id: u64,
+    /// The set of adaptor signatures from the accept party.
+    pub cet_adaptor_signatures: Vec<SchnorrAdaptorSignature>,
+    /// The refund signature of the accept party.
+    pub refund_signature: SchnorrSignature,
+    /// The fee split of the offer, which the accept party agreed to.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fee_split: Option<FeeSplit>,
+}
+
+impl_dlc_writeable!(AcceptTaprootDlc, {
+    (protocol_version, writeable),
+    (temporary_contract_id, writeable),
+    (accept_collateral, writeable),
+    (funding_pubkey, writeable),
+    (payout_spk, writeable),
+    (payout_serial_id, writeable),
+    (funding_inputs, vec),
+    (change_spk, writeable),
+    (change_serial_id, writeable),
+    (cet_adaptor_signatures, {cb_writeable, write_schnorr_adaptor_signatures, read_schnorr_adaptor_signatures}),
+    (refund_signature, {cb_writeable, write_schnorrsig, read_schnorrsig})
+}, tlv_stream: {
+    (7, fee_split, {option_cb, crate::ser_impls::write_fee_split, crate::ser_impls::read_fee_split})
+});
+
+/// Contains all the required signatures for the transactions of a contract
+/// funded with a taproot output from the offering party.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct SignTaprootDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the contract referred to by this message.
+    pub contract_id: [u8; 32],
+    /// The set of adaptor signatures from the offer party.
+    pub cet_adaptor_signatures: Vec<SchnorrAdaptorSignature>,
+    /// The refund signature from the offer party.
+    pub refund_signature: SchnorrSignature,
+    /// The set of funding signatures from the offer party.
+    pub funding_signatures: FundingSignatures,
+}
+
+impl_dlc_writeable!(SignTaprootDlc, {
+    (protocol_version, writeable),
+    (contract_id, writeable),
+    (cet_adaptor_signatures, {cb_writeable, write_schnorr_adaptor_signatures, read_schnorr_adaptor_signatures}),
+    (refund_signature, {cb_writeable, write_schnorrsig, read_schnorrsig}),
+    (funding_signatures, writeable)
+});
@@ -0,0 +1,535 @@
+//! Borrowing views over serialized [`OfferDlc`], [`AcceptDlc`] and [`SignDlc`]
+//! messages. Large byte fields such as the previous transactions of funding
+//! inputs or the CET adaptor signatures are not copied but referenced from the
+//! input buffer, making it cheap to parse and validate messages that are
+//! discarded afterwards. Views can be converted to their owned counterpart
+//! once a message is retained.
+
+use std::convert::TryInto;
+
+use bitcoin::Script;
+use contract_msgs::ContractInfo;
+use dlc::{Error, FeeSplit};
+use lightning::ln::msgs::DecodeError;
+use lightning::util::ser::Readable;
+use secp256k1_zkp::{
+    ecdsa::Signature, ffi::ECDSA_ADAPTOR_SIGNATURE_LENGTH, EcdsaAdaptorSignature, PublicKey,
+    Secp256k1, Verification,
+};
+use ser_impls::{read_fee_split, read_option, read_tlv_stream, BigSize, MAX_VEC_SIZE};
+
+use crate::{
+    AcceptDlc, CetAdaptorSignature, CetAdaptorSignatures, FundingInput, FundingSignature,
+    FundingSignatures, NegotiationFields, OfferDlc, SignDlc, WitnessElement,
+};
+
+/// Reads values from a buffer, returning slices of it for byte fields.
+struct SliceReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> SliceReader<'a> {
+    fn read<T: Readable>(&mut self) -> Result<T, DecodeError> {
+        Readable::read(&mut self.buf)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.buf.len() < len {
+            return Err(DecodeError::ShortRead);
+        }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    fn read_array(&mut self) -> Result<&'a [u8; 32], DecodeError> {
+        self.read_bytes(32)?
+            .try_into()
+            .map_err(|_| DecodeError::ShortRead)
+    }
+
+    fn read_len(&mut self) -> Result<usize, DecodeError> {
+        let len: BigSize = self.read()?;
+        if len.0 > MAX_VEC_SIZE {
+            return Err(DecodeError::InvalidValue);
+        }
+        Ok(len.0 as usize)
+    }
+
+    /// Reads a byte vector prefixed with its `BigSize` length.
+    fn read_var_bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.read_len()?;
+        self.read_bytes(len)
+    }
+
+    /// Reads a script prefixed with its `u16` length.
+    fn read_script(&mut self) -> Result<&'a Script, DecodeError> {
+        let len: u16 = self.read()?;
+        Ok(Script::from_bytes(self.read_bytes(len as usize)?))
+    }
+
+    fn read_funding_inputs(&mut self) -> Result<Vec<FundingInputView<'a>>, DecodeError> {
+        let len = self.read_len()?;
+        let mut res = Vec::new();
+        for _ in 0..len {
+            res.push(FundingInputView {
+                input_serial_id: self.read()?,
+                prev_tx: self.read_var_bytes()?,
+                prev_tx_vout: self.read()?,
+                sequence: self.read()?,
+                max_witness_len: self.read()?,
+                redeem_script: self.read_script()?,
+            });
+        }
+        Ok(res)
+    }
+
+    fn read_cet_adaptor_signatures(&mut self) -> Result<CetAdaptorSignaturesView<'a>, DecodeError> {
+        let len = self.read_len()?;
+        Ok(CetAdaptorSignaturesView {
+            data: self.read_bytes(len * ECDSA_ADAPTOR_SIGNATURE_LENGTH)?,
+        })
+    }
+}
+
+/// View over a serialized [`FundingInput`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FundingInputView<'a> {
+    /// Serial id used for input ordering in the funding transaction.
+    pub input_serial_id: u64,
+    /// The previous transaction used by the associated input in serialized format.
+    pub prev_tx: &'a [u8],
+    /// The vout of the output used by the associated input.
+    pub prev_tx_vout: u32,
+    /// The sequence number to use for the input.
+    pub sequence: u32,
+    /// The maximum witness length that can be used to spend the previous UTXO.
+    pub max_witness_len: u16,
+    /// The redeem script of the previous UTXO.
+    pub redeem_script: &'a Script,
+}
+
+impl FundingInputView<'_> {
+    /// Returns the owned [`FundingInput`] corresponding to the view.
+    pub fn to_funding_input(&self) -> FundingInput {
+        FundingInput {
+            input_serial_id: self.input_serial_id,
+            prev_tx: self.prev_tx.to_vec(),
+            prev_tx_vout: self.prev_tx_vout,
+            sequence: self.sequence,
+            max_witness_len: self.max_witness_len,
+            redeem_script: self.redeem_script.to_owned(),
+        }
+    }
+}
+
+/// View over serialized [`CetAdaptorSignatures`]. The signatures are only
+/// decoded when accessed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CetAdaptorSignaturesView<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> CetAdaptorSignaturesView<'a> {
+    /// Returns the number of signatures.
+    pub fn len(&self) -> usize {
+        self.data.len() / ECDSA_ADAPTOR_SIGNATURE_LENGTH
+    }
+
+    /// Returns whether there are no signatures.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the serialized signature at the given index, if any.
+    pub fn get_bytes(&self, index: usize) -> Option<&'a [u8]> {
+        self.data
+            .chunks_exact(ECDSA_ADAPTOR_SIGNATURE_LENGTH)
+            .nth(index)
+    }
+
+    /// Returns an iterator decoding the signatures.
+    pub fn iter(&self) -> impl Iterator<Item = Result<EcdsaAdaptorSignature, DecodeError>> + 'a {
+        self.data
+            .chunks_exact(ECDSA_ADAPTOR_SIGNATURE_LENGTH)
+            .map(|s| EcdsaAdaptorSignature::from_slice(s).map_err(|_| DecodeError::InvalidValue))
+    }
+
+    /// Decodes all the signatures into owned [`CetAdaptorSignatures`].
+    pub fn to_cet_adaptor_signatures(&self) -> Result<CetAdaptorSignatures, DecodeError> {
+        Ok(CetAdaptorSignatures {
+            ecdsa_adaptor_signatures: self
+                .iter()
+                .map(|s| s.map(|signature| CetAdaptorSignature { signature }))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// View over a serialized [`OfferDlc`]. The contract info is decoded as it is
+/// required to validate the offer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OfferDlcView<'a> {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    /// Feature flags to be used for the offered contract.
+    pub contract_flags: u8,
+    /// The identifier of the chain on which the contract will be settled.
+    pub chain_hash: &'a [u8; 32],
+    /// Temporary contract id to identify the contract.
+    pub temporary_contract_id: &'a [u8; 32],
+    /// Information about the contract event, payouts and oracles.
+    pub contract_info: ContractInfo,
+    /// The public key of the offerer to be used to lock the collateral.
+    pub funding_pubkey: PublicKey,
+    /// The SPK where the offerer will receive their payout.
+    pub payout_spk: &'a Script,
+    /// Serial id to order CET outputs.
+    pub payout_serial_id: u64,
+    /// Collateral of the offer party.
+    pub offer_collateral: u64,
+    /// Inputs used by the offer party to fund the contract.
+    pub funding_inputs: Vec<FundingInputView<'a>>,
+    /// The SPK where the offer party will receive their change.
+    pub change_spk: &'a Script,
+    /// Serial id to order funding transaction outputs.
+    pub change_serial_id: u64,
+    /// Serial id to order funding transaction outputs.
+    pub fund_output_serial_id: u64,
+    /// The fee rate to use to compute transaction fees for this contract.
+    pub fee_rate_per_vb: u64,
+    /// The lock time for the CETs.
+    pub cet_locktime: u32,
+    /// The lock time for the refund transactions.
+    pub refund_locktime: u32,
+    /// The node id of the party the offer is intended for.
+    pub recipient_node_id: Option<PublicKey>,
+    /// The unix time (in seconds) at which the offer was created.
+    pub timestamp: Option<u64>,
+    /// The unix time (in seconds) after which the offer should not be
+    /// accepted anymore.
+    pub expiry: Option<u64>,
+    /// How the fees of the contract transactions are split between the
+    /// parties.
+    pub fee_split: Option<FeeSplit>,
+}
+
+impl<'a> OfferDlcView<'a> {
+    /// Parses the serialized message (without its type prefix) contained in
+    /// the given buffer.
+    pub fn parse(buf: &'a [u8]) -> Result<Self, DecodeError> {
+        let mut reader = SliceReader { buf };
+        let mut view = OfferDlcView {
+            protocol_version: reader.read()?,
+            contract_flags: reader.read()?,
+            chain_hash: reader.read_array()?,
+            temporary_contract_id: reader.read_array()?,
+            contract_info: reader.read()?,
+            funding_pubkey: reader.read()?,
+            payout_spk: reader.read_script()?,
+            payout_serial_id: reader.read()?,
+            offer_collateral: reader.read()?,
+            funding_inputs: reader.read_funding_inputs()?,
+            change_spk: reader.read_script()?,
+            change_serial_id: reader.read()?,
+            fund_output_serial_id: reader.read()?,
+            fee_rate_per_vb: reader.read()?,
+            cet_locktime: reader.read()?,
+            refund_locktime: reader.read()?,
+            recipient_node_id: None,
+            timestamp: None,
+            expiry: None,
+            fee_split: None,
+        };
+        read_tlv_stream(&mut reader.buf, |tlv_type, value| match tlv_type {
+            1 => {
+                view.recipient_node_id = Some(Readable::read(value)?);
+                Ok(true)
+            }
+            3 => {
+                view.timestamp = Some(Readable::read(value)?);
+                Ok(true)
+            }
+            5 => {
+                view.expiry = Some(Readable::read(value)?);
+                Ok(true)
+            }
+            7 => {
+                view.fee_split = Some(read_fee_split(value)?);
+                Ok(true)
+            }
+            _ => Ok(false),
+        })?;
+        Ok(view)
+    }
+
+    /// Returns whether the message satisfies validity requirements, see
+    /// [`OfferDlc::validate`].
+    pub fn validate<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        min_timeout_interval: u32,
+        max_timeout_interval: u32,
+    ) -> Result<(), Error> {
+        super::validate_offer_terms(
+            &self.contract_info,
+            self.cet_locktime,
+            self.refund_locktime,
+            secp,
+            min_timeout_interval,
+            max_timeout_interval,
+        )
+    }
+
+    /// Returns the owned [`OfferDlc`] corresponding to the view.
+    pub fn to_offer_dlc(&self) -> OfferDlc {
+        OfferDlc {
+            protocol_version: self.protocol_version,
+            contract_flags: self.contract_flags,
+            chain_hash: *self.chain_hash,
+            temporary_contract_id: *self.temporary_contract_id,
+            contract_info: self.contract_info.clone(),
+            funding_pubkey: self.funding_pubkey,
+            payout_spk: self.payout_spk.to_owned(),
+            payout_serial_id: self.payout_serial_id,
+            offer_collateral: self.offer_collateral,
+            funding_inputs: self
+                .funding_inputs
+                .iter()
+                .map(|x| x.to_funding_input())
+                .collect(),
+            change_spk: self.change_spk.to_owned(),
+            change_serial_id: self.change_serial_id,
+            fund_output_serial_id: self.fund_output_serial_id,
+            fee_rate_per_vb: self.fee_rate_per_vb,
+            cet_locktime: self.cet_locktime,
+            refund_locktime: self.refund_locktime,
+            recipient_node_id: self.recipient_node_id,
+            timestamp: self.timestamp,
+            expiry: self.expiry,
+            fee_split: self.fee_split,
+        }
+    }
+}
+
+/// View over a serialized [`AcceptDlc`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AcceptDlcView<'a> {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    /// The temporary contract id for the contract.
+    pub temporary_contract_id: &'a [u8; 32],
+    /// The collateral input by the accept party.
+    pub accept_collateral: u64,
+    /// The public key of the accept party to be used to lock the collateral.
+    pub funding_pubkey: PublicKey,
+    /// The SPK where the accept party will receive their payout.
+    pub payout_spk: &'a Script,
+    /// Serial id to order CET outputs.
+    pub payout_serial_id: u64,
+    /// Inputs used by the accept party to fund the contract.
+    pub funding_inputs: Vec<FundingInputView<'a>>,
+    /// The SPK where the accept party will receive their change.
+    pub change_spk: &'a Script,
+    /// Serial id to order funding transaction outputs.
+    pub change_serial_id: u64,
+    /// The set of adaptor signatures from the accept party.
+    pub cet_adaptor_signatures: CetAdaptorSignaturesView<'a>,
+    /// The refund signature of the accept party.
+    pub refund_signature: Signature,
+    /// The negotiation fields from the accept party.
+    pub negotiation_fields: Option<NegotiationFields>,
+    /// The fee split of the offer, which the accept party agreed to.
+    pub fee_split: Option<FeeSplit>,
+}
+
+impl<'a> AcceptDlcView<'a> {
+    /// Parses the serialized message (without its type prefix) contained in
+    /// the given buffer.
+    pub fn parse(buf: &'a [u8]) -> Result<Self, DecodeError> {
+        let mut reader = SliceReader { buf };
+        let mut view = AcceptDlcView {
+            protocol_version: reader.read()?,
+            temporary_contract_id: reader.read_array()?,
+            accept_collateral: reader.read()?,
+            funding_pubkey: reader.read()?,
+            payout_spk: reader.read_script()?,
+            payout_serial_id: reader.read()?,
+            funding_inputs: reader.read_funding_inputs()?,
+            change_spk: reader.read_script()?,
+            change_serial_id: reader.read()?,
+            cet_adaptor_signatures: reader.read_cet_adaptor_signatures()?,
+            refund_signature: reader.read()?,
+            negotiation_fields: read_option(&mut reader.buf)?,
+            fee_split: None,
+        };
+        read_tlv_stream(&mut reader.buf, |tlv_type, value| match tlv_type {
+            7 => {
+                view.fee_split = Some(read_fee_split(value)?);
+                Ok(true)
+            }
+            _ => Ok(false),
+        })?;
+        Ok(view)
+    }
+
+    /// Returns the owned [`AcceptDlc`] corresponding to the view.
+    pub fn to_accept_dlc(&self) -> Result<AcceptDlc, DecodeError> {
+        Ok(AcceptDlc {
+            protocol_version: self.protocol_version,
+            temporary_contract_id: *self.temporary_contract_id,
+            accept_collateral: self.accept_collateral,
+            funding_pubkey: self.funding_pubkey,
+            payout_spk: self.payout_spk.to_owned(),
+            payout_serial_id: self.payout_serial_id,
+            funding_inputs: self
+                .funding_inputs
+                .iter()
+                .map(|x| x.to_funding_input())
+                .collect(),
+            change_spk: self.change_spk.to_owned(),
+            change_serial_id: self.change_serial_id,
+            cet_adaptor_signatures: self.cet_adaptor_signatures.to_cet_adaptor_signatures()?,
+            refund_signature: self.refund_signature,
+            negotiation_fields: self.negotiation_fields.clone(),
+            fee_split: self.fee_split,
+        })
+    }
+}
+
+/// View over a serialized [`SignDlc`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignDlcView<'a> {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    /// The id of the contract referred to by this message.
+    pub contract_id: &'a [u8; 32],
+    /// The set of adaptor signatures from the offer party.
+    pub cet_adaptor_signatures: CetAdaptorSignaturesView<'a>,
+    /// The refund signature from the offer party.
+    pub refund_signature: Signature,
+    /// The witness elements of each funding input of the offer party.
+    pub funding_signatures: Vec<Vec<&'a [u8]>>,
+}
+
+impl<'a> SignDlcView<'a> {
+    /// Parses the serialized message (without its type prefix) contained in
+    /// the given buffer.
+    pub fn parse(buf: &'a [u8]) -> Result<Self, DecodeError> {
+        let mut reader = SliceReader { buf };
+        let protocol_version = reader.read()?;
+        let contract_id = reader.read_array()?;
+        let cet_adaptor_signatures = reader.read_cet_adaptor_signatures()?;
+        let refund_signature = reader.read()?;
+        let mut funding_signatures = Vec::new();
+        for _ in 0..reader.read_len()? {
+            let mut witness_elements = Vec::new();
+            for _ in 0..reader.read_len()? {
+                witness_elements.push(reader.read_var_bytes()?);
+            }
+            funding_signatures.push(witness_elements);
+        }
+        Ok(SignDlcView {
+            protocol_version,
+            contract_id,
+            cet_adaptor_signatures,
+            refund_signature,
+            funding_signatures,
+        })
+    }
+
+    /// Returns the owned [`SignDlc`] corresponding to the view.
+    pub fn to_sign_dlc(&self) -> Result<SignDlc, DecodeError> {
+        Ok(SignDlc {
+            protocol_version: self.protocol_version,
+            contract_id: *self.contract_id,
+            cet_adaptor_signatures: self.cet_adaptor_signatures.to_cet_adaptor_signatures()?,
+            refund_signature: self.refund_signature,
+            funding_signatures: FundingSignatures {
+                funding_signatures: self
+                    .funding_signatures
+                    .iter()
+                    .map(|elements| FundingSignature {
+                        witness_elements: elements
+                            .iter()
+                            .map(|w| WitnessElement {
+                                witness: w.to_vec(),
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lightning::util::ser::Writeable;
+    use secp256k1_zkp::SECP256K1;
+
+    fn serialize<T: Writeable>(msg: &T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        msg.write(&mut buf).expect("Error writing message");
+        buf
+    }
+
+    #[test]
+    fn offer_view_matches_owned_message() {
+        let mut offer: OfferDlc =
+            serde_json::from_str(include_str!("./test_inputs/offer_msg.json")).unwrap();
+        offer.recipient_node_id = Some(offer.funding_pubkey);
+        offer.timestamp = Some(1_700_000_000);
+        offer.expiry = Some(1_700_086_400);
+        offer.fee_split = Some(FeeSplit::Proportional);
+        let buf = serialize(&offer);
+
+        let view = OfferDlcView::parse(&buf).expect("Error parsing offer");
+
+        assert_eq!(
+            offer.funding_inputs[0].prev_tx,
+            view.funding_inputs[0].prev_tx
+        );
+        view.validate(SECP256K1, 86400 * 7, 86400 * 14)
+            .expect("to validate valid offer messages.");
+        assert_eq!(offer, view.to_offer_dlc());
+    }
+
+    #[test]
+    fn accept_view_matches_owned_message() {
+        let mut accept: AcceptDlc =
+            serde_json::from_str(include_str!("./test_inputs/accept_msg.json")).unwrap();
+        accept.fee_split = Some(FeeSplit::OfferPaysAll);
+        let buf = serialize(&accept);
+
+        let view = AcceptDlcView::parse(&buf).expect("Error parsing accept");
+
+        assert_eq!(
+            accept.cet_adaptor_signatures.ecdsa_adaptor_signatures.len(),
+            view.cet_adaptor_signatures.len()
+        );
+        assert_eq!(accept, view.to_accept_dlc().unwrap());
+    }
+
+    #[test]
+    fn sign_view_matches_owned_message() {
+        let sign: SignDlc =
+            serde_json::from_str(include_str!("./test_inputs/sign_msg.json")).unwrap();
+        let buf = serialize(&sign);
+
+        let view = SignDlcView::parse(&buf).expect("Error parsing sign");
+
+        assert_eq!(sign, view.to_sign_dlc().unwrap());
+    }
+
+    #[test]
+    fn truncated_message_fails_to_parse() {
+        let accept: AcceptDlc =
+            serde_json::from_str(include_str!("./test_inputs/accept_msg.json")).unwrap();
+        let buf = serialize(&accept);
+
+        AcceptDlcView::parse(&buf[..buf.len() / 2])
+            .expect_err("Should not parse truncated message");
+    }
+}
@@ -0,0 +1,90 @@
+//! #Event
+//! Nostr events as specified in
+//! [NIP-01](https://github.com/nostr-protocol/nips/blob/master/01.md).
+
+use crate::Error;
+use secp256k1_zkp::hashes::{sha256, Hash};
+use secp256k1_zkp::{schnorr::Signature, KeyPair, Message, XOnlyPublicKey, SECP256K1};
+use serde::{Deserialize, Serialize};
+
+/// A signed Nostr event, serializable to the JSON format expected by relays.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NostrEvent {
+    /// The hex encoded hash of the serialized event data.
+    pub id: String,
+    /// The public key of the author of the event.
+    pub pubkey: XOnlyPublicKey,
+    /// The creation time of the event as a unix timestamp.
+    pub created_at: u64,
+    /// The kind of the event.
+    pub kind: u16,
+    /// The tags of the event.
+    pub tags: Vec<Vec<String>>,
+    /// The content of the event.
+    pub content: String,
+    /// The signature of the event id by the author.
+    pub sig: Signature,
+}
+
+fn compute_id(
+    pubkey: &XOnlyPublicKey,
+    created_at: u64,
+    kind: u16,
+    tags: &[Vec<String>],
+    content: &str,
+) -> sha256::Hash {
+    let serialized = serde_json::json!([0, pubkey, created_at, kind, tags, content]).to_string();
+    sha256::Hash::hash(serialized.as_bytes())
+}
+
+impl NostrEvent {
+    /// Creates an event signed with the given key pair.
+    pub fn new_signed(
+        keypair: &KeyPair,
+        created_at: u64,
+        kind: u16,
+        tags: Vec<Vec<String>>,
+        content: String,
+    ) -> NostrEvent {
+        let pubkey = keypair.x_only_public_key().0;
+        let id = compute_id(&pubkey, created_at, kind, &tags, &content);
+        let msg = Message::from_slice(id.as_ref()).expect("a 32 bytes hash");
+        NostrEvent {
+            id: id.to_string(),
+            pubkey,
+            created_at,
+            kind,
+            tags,
+            content,
+            sig: SECP256K1.sign_schnorr(&msg, keypair),
+        }
+    }
+
+    /// Checks that the id of the event matches its content and that it is
+    /// signed by its author.
+    pub fn verify(&self) -> Result<(), Error> {
+        let id = compute_id(
+            &self.pubkey,
+            self.created_at,
+            self.kind,
+            &self.tags,
+            &self.content,
+        );
+        if id.to_string() != self.id {
+            return Err(Error::InvalidEvent("Event id mismatch".to_string()));
+        }
+        let msg = Message::from_slice(id.as_ref()).expect("a 32 bytes hash");
+        SECP256K1
+            .verify_schnorr(&self.sig, &msg, &self.pubkey)
+            .map_err(|_| Error::InvalidEvent("Invalid event signature".to_string()))
+    }
+
+    /// Returns the values of the first tag with the given name, excluding the
+    /// name itself.
+    pub fn get_tag(&self, name: &str) -> Option<&[String]> {
+        self.tags
+            .iter()
+            .find(|t| t.first().map(|x| x.as_str()) == Some(name))
+            .map(|t| &t[1..])
+    }
+}
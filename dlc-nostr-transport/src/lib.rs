@@ -0,0 +1,573 @@
+//! # dlc-nostr-transport
+//! Conversion of DLC messages to and from Nostr encrypted direct messages,
+//! enabling peers to negotiate contracts through Nostr relays.
+
+#![crate_name = "dlc_nostr_transport"]
+// Coding conventions
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+pub mod event;
+mod nip04;
+mod nip44;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use dlc_messages::message_handler::read_dlc_message;
+use dlc_messages::segmentation::segment_reader::{self, SegmentReader};
+use dlc_messages::segmentation::{
+    segment_message_with_max_size, MAX_SEGMENTS, SEGMENT_CHUNK_TYPE, SEGMENT_START_TYPE,
+};
+use dlc_messages::{Message, WireMessage};
+use event::NostrEvent;
+use lightning::ln::msgs::DecodeError;
+use lightning::ln::wire::Type;
+use lightning::util::ser::{Readable, Writeable};
+use secp256k1_zkp::hashes::{sha256, Hash};
+use secp256k1_zkp::{KeyPair, XOnlyPublicKey};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The kind of the events used to transport DLC messages (encrypted direct
+/// messages).
+pub const ENCRYPTED_DIRECT_MESSAGE_KIND: u16 = 4;
+
+/// The default maximum size of the serialized wire message included in a
+/// single event. Encoding, padding and encryption grow the content of the
+/// event by a factor of about 2.2, keeping it under the 64kB limit of most
+/// relays.
+pub const DEFAULT_MAX_CHUNK_SIZE: usize = 24 * 1024;
+
+/// The largest chunk whose base64 encoding fits in a NIP-44 plaintext.
+const MAX_CHUNK_SIZE: usize = 49_149;
+
+/// The smallest chunk size, leaving room for the segment headers.
+const MIN_CHUNK_SIZE: usize = 64;
+
+/// The name of the tag holding the chunking information of an event.
+const CHUNK_TAG: &str = "dlc-chunk";
+
+/// The scheme used to encrypt the content of the events sent. Received events
+/// can use either.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encryption {
+    /// [NIP-04](https://github.com/nostr-protocol/nips/blob/master/04.md)
+    /// encryption, supported by most clients but deprecated.
+    Nip04,
+    /// Version 2 of
+    /// [NIP-44](https://github.com/nostr-protocol/nips/blob/master/44.md)
+    /// encryption.
+    Nip44,
+}
+
+/// Limits on the chunks of incomplete messages kept in memory, so that peers
+/// cannot exhaust it by sending chunks of messages they never complete.
+#[derive(Clone, Copy, Debug)]
+pub struct PartialMessageLimits {
+    /// The maximum total size of the chunks kept for all senders. Chunks
+    /// received while the limit is reached are rejected.
+    pub max_total_size: usize,
+    /// The maximum number of incomplete messages kept for a single sender.
+    /// Receiving a chunk of a new message discards the oldest ones.
+    pub max_messages_per_sender: usize,
+    /// The duration after which an incomplete message is discarded.
+    pub max_age: Duration,
+}
+
+impl Default for PartialMessageLimits {
+    fn default() -> Self {
+        PartialMessageLimits {
+            max_total_size: 16 * 1024 * 1024,
+            max_messages_per_sender: 2,
+            max_age: Duration::from_secs(600),
+        }
+    }
+}
+
+/// An error that occurred while converting messages to or from events.
+#[derive(Debug)]
+pub enum Error {
+    /// The event is not valid or is not a DLC message for this node.
+    InvalidEvent(String),
+    /// The content of the event could not be encrypted or decrypted.
+    Encryption(String),
+    /// The message could not be decoded.
+    Decode(DecodeError),
+    /// The message could not be split into segments or reassembled from them.
+    Segmentation(segment_reader::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidEvent(s) => write!(f, "Invalid event: {}", s),
+            Error::Encryption(s) => write!(f, "Encryption error: {}", s),
+            Error::Decode(e) => write!(f, "Decode error: {}", e),
+            Error::Segmentation(e) => write!(f, "Segmentation error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<DecodeError> for Error {
+    fn from(e: DecodeError) -> Error {
+        Error::Decode(e)
+    }
+}
+
+impl From<segment_reader::Error> for Error {
+    fn from(e: segment_reader::Error) -> Error {
+        Error::Segmentation(e)
+    }
+}
+
+struct PartialMessage {
+    nb_chunks: usize,
+    max_chunk_size: usize,
+    chunks: BTreeMap<usize, Vec<u8>>,
+    size: usize,
+    received_at: Instant,
+}
+
+/// Converts DLC messages to Nostr events and back. Publishing the events to
+/// relays and subscribing to the events tagged with the public key of the
+/// transport is left to the caller.
+pub struct NostrTransport {
+    keypair: KeyPair,
+    max_chunk_size: usize,
+    encryption: Encryption,
+    limits: PartialMessageLimits,
+    partial_messages: Mutex<HashMap<(XOnlyPublicKey, String), PartialMessage>>,
+}
+
+impl NostrTransport {
+    /// Creates a transport sending and receiving messages using the given key
+    /// pair. Events are encrypted using NIP-44.
+    pub fn new(keypair: KeyPair) -> Self {
+        NostrTransport {
+            keypair,
+            max_chunk_size: DEFAULT_MAX_CHUNK_SIZE,
+            encryption: Encryption::Nip44,
+            limits: PartialMessageLimits::default(),
+            partial_messages: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the maximum size of the wire message included in a single event,
+    /// to adapt to the limits of the relays being used.
+    pub fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.max_chunk_size = max_chunk_size.clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+    }
+
+    /// Sets the scheme used to encrypt the events sent, e.g. to communicate
+    /// with peers that only support NIP-04.
+    pub fn set_encryption(&mut self, encryption: Encryption) {
+        self.encryption = encryption;
+    }
+
+    /// Sets the limits on the chunks of incomplete messages kept in memory.
+    pub fn set_partial_message_limits(&mut self, limits: PartialMessageLimits) {
+        self.limits = limits;
+    }
+
+    /// Returns the public key with which the transport signs its events and
+    /// on which it receives messages.
+    pub fn get_public_key(&self) -> XOnlyPublicKey {
+        self.keypair.x_only_public_key().0
+    }
+
+    /// Returns the events to publish to send the given message to the given
+    /// recipient. Messages larger than the maximum chunk size are split into
+    /// segments sent over multiple events.
+    pub fn message_to_events(
+        &self,
+        recipient: &XOnlyPublicKey,
+        message: &Message,
+        created_at: u64,
+    ) -> Result<Vec<NostrEvent>, Error> {
+        let message_id = sha256::Hash::hash(&encode_with_type(message)).to_string();
+        let wire_messages = segment_message_with_max_size(message.clone(), self.max_chunk_size)?;
+        let nb_chunks = wire_messages.len();
+
+        wire_messages
+            .iter()
+            .enumerate()
+            .map(|(i, wire_message)| {
+                let chunk = BASE64.encode(encode_with_type(wire_message));
+                let content = match self.encryption {
+                    Encryption::Nip04 => nip04::encrypt(&self.keypair, recipient, &chunk)?,
+                    Encryption::Nip44 => nip44::encrypt(&self.keypair, recipient, &chunk)?,
+                };
+                let mut tags = vec![vec!["p".to_string(), recipient.to_string()]];
+                if nb_chunks > 1 {
+                    tags.push(vec![
+                        CHUNK_TAG.to_string(),
+                        message_id.clone(),
+                        i.to_string(),
+                        nb_chunks.to_string(),
+                        self.max_chunk_size.to_string(),
+                    ]);
+                }
+                Ok(NostrEvent::new_signed(
+                    &self.keypair,
+                    created_at,
+                    ENCRYPTED_DIRECT_MESSAGE_KIND,
+                    tags,
+                    content,
+                ))
+            })
+            .collect()
+    }
+
+    /// Processes an event received from a relay, returning the sender and
+    /// the message if the event completes one. Chunks of messages that are
+    /// not yet complete are kept, within the [`PartialMessageLimits`] of the
+    /// transport, until the remaining ones are received.
+    pub fn process_event(
+        &self,
+        event: &NostrEvent,
+    ) -> Result<Option<(XOnlyPublicKey, Message)>, Error> {
+        event.verify()?;
+        if event.kind != ENCRYPTED_DIRECT_MESSAGE_KIND {
+            return Err(Error::InvalidEvent("Unexpected event kind".to_string()));
+        }
+        let recipient = event
+            .get_tag("p")
+            .and_then(|x| x.first())
+            .ok_or_else(|| Error::InvalidEvent("Missing recipient".to_string()))?;
+        if recipient != &self.get_public_key().to_string() {
+            return Err(Error::InvalidEvent(
+                "Event is addressed to a different recipient".to_string(),
+            ));
+        }
+
+        // NIP-04 payloads carry their initialization vector in a query string,
+        // which cannot appear in base64 encoded NIP-44 payloads.
+        let content = if event.content.contains("?iv=") {
+            nip04::decrypt(&self.keypair, &event.pubkey, &event.content)?
+        } else {
+            nip44::decrypt(&self.keypair, &event.pubkey, &event.content)?
+        };
+        let chunk = BASE64
+            .decode(content)
+            .map_err(|e| Error::InvalidEvent(e.to_string()))?;
+
+        let message = match event.get_tag(CHUNK_TAG) {
+            None => match read_wire_message(&chunk)? {
+                WireMessage::Message(message) => message,
+                _ => {
+                    return Err(Error::InvalidEvent(
+                        "Segment received without chunking information".to_string(),
+                    ))
+                }
+            },
+            Some(chunk_info) => match self.add_chunk(&event.pubkey, chunk_info, chunk)? {
+                Some(message) => message,
+                None => return Ok(None),
+            },
+        };
+
+        Ok(Some((event.pubkey, message)))
+    }
+
+    /// Stores the given chunk, returning the message once all its chunks have
+    /// been received.
+    fn add_chunk(
+        &self,
+        sender: &XOnlyPublicKey,
+        chunk_info: &[String],
+        chunk: Vec<u8>,
+    ) -> Result<Option<Message>, Error> {
+        let invalid = || Error::InvalidEvent("Invalid chunk information".to_string());
+        let (message_id, index, nb_chunks, max_chunk_size) = match chunk_info {
+            [message_id, index, nb_chunks, max_chunk_size] => (
+                message_id,
+                index.parse::<usize>().map_err(|_| invalid())?,
+                nb_chunks.parse::<usize>().map_err(|_| invalid())?,
+                max_chunk_size.parse::<usize>().map_err(|_| invalid())?,
+            ),
+            _ => return Err(invalid()),
+        };
+        if index >= nb_chunks
+            || nb_chunks < 2
+            || nb_chunks > MAX_SEGMENTS
+            || !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&max_chunk_size)
+            || chunk.len() > max_chunk_size
+        {
+            return Err(invalid());
+        }
+
+        let mut partial_messages = self.partial_messages.lock().unwrap();
+        self.evict_partial_messages(&mut partial_messages, sender, message_id);
+        let total_size: usize = partial_messages.values().map(|p| p.size).sum();
+        if total_size + chunk.len() > self.limits.max_total_size {
+            return Err(Error::InvalidEvent(
+                "Too many chunks of incomplete messages".to_string(),
+            ));
+        }
+
+        let key = (*sender, message_id.clone());
+        let partial = partial_messages
+            .entry(key.clone())
+            .or_insert_with(|| PartialMessage {
+                nb_chunks,
+                max_chunk_size,
+                chunks: BTreeMap::new(),
+                size: 0,
+                received_at: Instant::now(),
+            });
+        if partial.nb_chunks != nb_chunks || partial.max_chunk_size != max_chunk_size {
+            return Err(invalid());
+        }
+        partial.size += chunk.len();
+        if let Some(previous) = partial.chunks.insert(index, chunk) {
+            partial.size -= previous.len();
+        }
+        if partial.chunks.len() < nb_chunks {
+            return Ok(None);
+        }
+
+        let partial = partial_messages.remove(&key).expect("to have the message");
+        drop(partial_messages);
+        let mut reader = SegmentReader::with_max_data_size(partial.max_chunk_size)?;
+        let mut message = None;
+        for chunk in partial.chunks.values() {
+            message = reader.process_wire_message(read_wire_message(chunk)?)?;
+        }
+        let message = message.ok_or_else(|| {
+            Error::InvalidEvent("Chunks do not form a complete message".to_string())
+        })?;
+        if &sha256::Hash::hash(&encode_with_type(&message)).to_string() != message_id {
+            return Err(Error::InvalidEvent(
+                "Reassembled message does not match its id".to_string(),
+            ));
+        }
+        Ok(Some(message))
+    }
+
+    /// Discards the incomplete messages older than the maximum age, and the
+    /// oldest ones of the sender if receiving a chunk of the given message
+    /// would exceed the number of messages allowed per sender.
+    fn evict_partial_messages(
+        &self,
+        partial_messages: &mut HashMap<(XOnlyPublicKey, String), PartialMessage>,
+        sender: &XOnlyPublicKey,
+        message_id: &str,
+    ) {
+        let max_age = self.limits.max_age;
+        partial_messages.retain(|_, p| p.received_at.elapsed() < max_age);
+
+        if partial_messages.contains_key(&(*sender, message_id.to_string())) {
+            return;
+        }
+        let mut sender_messages: Vec<_> = partial_messages
+            .iter()
+            .filter(|((pubkey, _), _)| pubkey == sender)
+            .map(|(key, p)| (p.received_at, key.clone()))
+            .collect();
+        sender_messages.sort();
+        let nb_to_evict =
+            (sender_messages.len() + 1).saturating_sub(self.limits.max_messages_per_sender);
+        for (_, key) in sender_messages.into_iter().take(nb_to_evict) {
+            partial_messages.remove(&key);
+        }
+    }
+}
+
+fn encode_with_type<T: Type + Writeable>(message: &T) -> Vec<u8> {
+    let mut data = message.type_id().to_be_bytes().to_vec();
+    data.extend(message.encode());
+    data
+}
+
+fn read_wire_message(data: &[u8]) -> Result<WireMessage, Error> {
+    let mut reader = lightning::io::Cursor::new(data);
+    let msg_type: u16 = Readable::read(&mut reader)?;
+    let wire_message = match msg_type {
+        SEGMENT_START_TYPE => WireMessage::SegmentStart(Readable::read(&mut reader)?),
+        SEGMENT_CHUNK_TYPE => WireMessage::SegmentChunk(Readable::read(&mut reader)?),
+        _ => read_dlc_message(msg_type, &mut reader)?
+            .ok_or(Error::Decode(DecodeError::UnknownRequiredFeature))?,
+    };
+    Ok(wire_message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dlc_messages::OfferDlc;
+    use secp256k1_zkp::rand::thread_rng;
+    use secp256k1_zkp::SECP256K1;
+
+    fn offer() -> OfferDlc {
+        serde_json::from_str(include_str!(
+            "../../dlc-messages/src/test_inputs/offer_msg.json"
+        ))
+        .unwrap()
+    }
+
+    fn transports() -> (NostrTransport, NostrTransport) {
+        (
+            NostrTransport::new(KeyPair::new(SECP256K1, &mut thread_rng())),
+            NostrTransport::new(KeyPair::new(SECP256K1, &mut thread_rng())),
+        )
+    }
+
+    fn assert_is_offer(expected: &OfferDlc, message: Message) {
+        match message {
+            Message::Offer(offer) => assert_eq!(expected, &offer),
+            _ => panic!("Expected an offer message"),
+        }
+    }
+
+    #[test]
+    fn message_round_trip_test() {
+        let (alice, bob) = transports();
+        let offer = offer();
+
+        let events = alice
+            .message_to_events(&bob.get_public_key(), &Message::Offer(offer.clone()), 1)
+            .unwrap();
+        assert_eq!(1, events.len());
+        let serialized = serde_json::to_string(&events[0]).unwrap();
+        let event: NostrEvent = serde_json::from_str(&serialized).unwrap();
+
+        let (sender, message) = bob.process_event(&event).unwrap().unwrap();
+        assert_eq!(alice.get_public_key(), sender);
+        assert_is_offer(&offer, message);
+    }
+
+    #[test]
+    fn chunked_message_round_trip_test() {
+        let (mut alice, bob) = transports();
+        alice.set_max_chunk_size(100);
+        let offer = offer();
+
+        let mut events = alice
+            .message_to_events(&bob.get_public_key(), &Message::Offer(offer.clone()), 1)
+            .unwrap();
+        assert!(events.len() > 2);
+        events.reverse();
+        let last = events.pop().unwrap();
+
+        for event in &events {
+            assert!(bob.process_event(event).unwrap().is_none());
+        }
+        let (_, message) = bob.process_event(&last).unwrap().unwrap();
+        assert_is_offer(&offer, message);
+    }
+
+    #[test]
+    fn nip04_message_round_trip_test() {
+        let (mut alice, bob) = transports();
+        alice.set_encryption(Encryption::Nip04);
+        alice.set_max_chunk_size(100);
+        let offer = offer();
+
+        let events = alice
+            .message_to_events(&bob.get_public_key(), &Message::Offer(offer.clone()), 1)
+            .unwrap();
+        assert!(events.iter().all(|e| e.content.contains("?iv=")));
+
+        let mut result = None;
+        for event in &events {
+            result = bob.process_event(event).unwrap();
+        }
+        assert_is_offer(&offer, result.unwrap().1);
+    }
+
+    fn chunked_offer_events(
+        alice: &NostrTransport,
+        bob: &NostrTransport,
+        id: u8,
+    ) -> Vec<NostrEvent> {
+        let mut offer = offer();
+        offer.temporary_contract_id = [id; 32];
+        alice
+            .message_to_events(&bob.get_public_key(), &Message::Offer(offer), 1)
+            .unwrap()
+    }
+
+    #[test]
+    fn oldest_partial_message_of_sender_is_evicted_test() {
+        let (mut alice, mut bob) = transports();
+        alice.set_max_chunk_size(100);
+        bob.set_partial_message_limits(PartialMessageLimits {
+            max_messages_per_sender: 1,
+            ..Default::default()
+        });
+        let first = chunked_offer_events(&alice, &bob, 1);
+        let second = chunked_offer_events(&alice, &bob, 2);
+
+        assert!(bob.process_event(&first[0]).unwrap().is_none());
+        assert!(bob.process_event(&second[0]).unwrap().is_none());
+        for event in &first[1..] {
+            assert!(bob.process_event(event).unwrap().is_none());
+        }
+        assert_eq!(1, bob.partial_messages.lock().unwrap().len());
+    }
+
+    #[test]
+    fn chunks_over_size_limit_are_rejected_test() {
+        let (mut alice, mut bob) = transports();
+        alice.set_max_chunk_size(100);
+        bob.set_partial_message_limits(PartialMessageLimits {
+            max_total_size: 150,
+            ..Default::default()
+        });
+        let events = chunked_offer_events(&alice, &bob, 1);
+
+        assert!(bob.process_event(&events[0]).unwrap().is_none());
+        assert!(bob.process_event(&events[1]).is_err());
+    }
+
+    #[test]
+    fn expired_partial_messages_are_evicted_test() {
+        let (mut alice, mut bob) = transports();
+        alice.set_max_chunk_size(100);
+        bob.set_partial_message_limits(PartialMessageLimits {
+            max_age: Duration::ZERO,
+            ..Default::default()
+        });
+        let events = chunked_offer_events(&alice, &bob, 1);
+
+        for event in &events {
+            assert!(bob.process_event(event).unwrap().is_none());
+        }
+        assert_eq!(1, bob.partial_messages.lock().unwrap().len());
+    }
+
+    #[test]
+    fn tampered_event_is_rejected_test() {
+        let (alice, bob) = transports();
+
+        let mut event = alice
+            .message_to_events(&bob.get_public_key(), &Message::Offer(offer()), 1)
+            .unwrap()
+            .remove(0);
+        event.created_at += 1;
+
+        assert!(bob.process_event(&event).is_err());
+    }
+
+    #[test]
+    fn event_for_other_recipient_is_rejected_test() {
+        let (alice, bob) = transports();
+        let (carol, _) = transports();
+
+        let event = alice
+            .message_to_events(&carol.get_public_key(), &Message::Offer(offer()), 1)
+            .unwrap()
+            .remove(0);
+
+        assert!(bob.process_event(&event).is_err());
+    }
+}
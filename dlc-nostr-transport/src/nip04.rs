@@ -0,0 +1,84 @@
+//! #NIP-04
+//! Encryption of direct message contents as specified in
+//! [NIP-04](https://github.com/nostr-protocol/nips/blob/master/04.md).
+
+use crate::Error;
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use secp256k1_zkp::rand::{thread_rng, RngCore};
+use secp256k1_zkp::{ecdh, KeyPair, Parity, PublicKey, XOnlyPublicKey};
+use std::convert::TryInto;
+
+type Aes256CbcEnc = cbc::Encryptor<aes::Aes256>;
+type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+
+/// The shared key is the unhashed x coordinate of the ECDH point.
+fn shared_key(keypair: &KeyPair, other: &XOnlyPublicKey) -> [u8; 32] {
+    let other = PublicKey::from_x_only_public_key(*other, Parity::Even);
+    let point = ecdh::shared_secret_point(&other, &keypair.secret_key());
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&point[..32]);
+    key
+}
+
+/// Encrypts the given content for the given recipient.
+pub(crate) fn encrypt(
+    keypair: &KeyPair,
+    recipient: &XOnlyPublicKey,
+    content: &str,
+) -> Result<String, Error> {
+    let key = shared_key(keypair, recipient);
+    let mut iv = [0u8; 16];
+    thread_rng().fill_bytes(&mut iv);
+    let cipher_text = Aes256CbcEnc::new(&key.into(), &iv.into())
+        .encrypt_padded_vec_mut::<Pkcs7>(content.as_bytes());
+    Ok(format!(
+        "{}?iv={}",
+        BASE64.encode(cipher_text),
+        BASE64.encode(iv)
+    ))
+}
+
+/// Decrypts the given content received from the given sender.
+pub(crate) fn decrypt(
+    keypair: &KeyPair,
+    sender: &XOnlyPublicKey,
+    content: &str,
+) -> Result<String, Error> {
+    let (cipher_text, iv) = content
+        .split_once("?iv=")
+        .ok_or_else(|| Error::Encryption("Missing initialization vector".to_string()))?;
+    let cipher_text = BASE64
+        .decode(cipher_text)
+        .map_err(|e| Error::Encryption(e.to_string()))?;
+    let iv: [u8; 16] = BASE64
+        .decode(iv)
+        .map_err(|e| Error::Encryption(e.to_string()))?
+        .try_into()
+        .map_err(|_| Error::Encryption("Invalid initialization vector".to_string()))?;
+    let key = shared_key(keypair, sender);
+    let plain_text = Aes256CbcDec::new(&key.into(), &iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(&cipher_text)
+        .map_err(|_| Error::Encryption("Could not decrypt content".to_string()))?;
+    String::from_utf8(plain_text).map_err(|e| Error::Encryption(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::SECP256K1;
+
+    #[test]
+    fn encrypt_decrypt_test() {
+        let alice = KeyPair::new(SECP256K1, &mut thread_rng());
+        let bob = KeyPair::new(SECP256K1, &mut thread_rng());
+
+        let encrypted = encrypt(&alice, &bob.x_only_public_key().0, "hello").unwrap();
+        assert_ne!("hello", encrypted);
+        assert_eq!(
+            "hello",
+            decrypt(&bob, &alice.x_only_public_key().0, &encrypted).unwrap()
+        );
+    }
+}
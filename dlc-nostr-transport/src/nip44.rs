@@ -0,0 +1,245 @@
+//! #NIP-44
+//! Encryption of direct message contents as specified in version 2 of
+//! [NIP-44](https://github.com/nostr-protocol/nips/blob/master/44.md).
+
+use crate::Error;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use secp256k1_zkp::hashes::hmac::{Hmac, HmacEngine};
+use secp256k1_zkp::hashes::{sha256, Hash, HashEngine};
+use secp256k1_zkp::rand::{thread_rng, RngCore};
+use secp256k1_zkp::{ecdh, KeyPair, Parity, PublicKey, XOnlyPublicKey};
+use std::convert::TryInto;
+
+const VERSION: u8 = 2;
+const SALT: &[u8] = b"nip44-v2";
+const NONCE_SIZE: usize = 32;
+const MAC_SIZE: usize = 32;
+const MAX_PLAINTEXT_SIZE: usize = 65535;
+// Version byte, nonce, padded plaintext of at least 32 bytes with its length
+// prefix, and MAC.
+const MIN_PAYLOAD_SIZE: usize = 1 + NONCE_SIZE + 2 + 32 + MAC_SIZE;
+const MAX_PAYLOAD_SIZE: usize = 1 + NONCE_SIZE + 2 + 65536 + MAC_SIZE;
+
+fn hmac(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha256::Hash>::new(key);
+    for d in data {
+        engine.input(d);
+    }
+    Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
+
+/// The conversation key is the HKDF extract of the unhashed x coordinate of
+/// the ECDH point, using the version string as salt.
+fn conversation_key(keypair: &KeyPair, other: &XOnlyPublicKey) -> [u8; 32] {
+    let other = PublicKey::from_x_only_public_key(*other, Parity::Even);
+    let point = ecdh::shared_secret_point(&other, &keypair.secret_key());
+    hmac(SALT, &[&point[..32]])
+}
+
+struct MessageKeys {
+    chacha_key: [u8; 32],
+    chacha_nonce: [u8; 12],
+    hmac_key: [u8; 32],
+}
+
+/// Derives the keys of a message from the HKDF expansion of the conversation
+/// key to 76 bytes, using the message nonce as info.
+fn message_keys(conversation_key: &[u8; 32], nonce: &[u8; NONCE_SIZE]) -> MessageKeys {
+    let t1 = hmac(conversation_key, &[nonce, &[1]]);
+    let t2 = hmac(conversation_key, &[&t1, nonce, &[2]]);
+    let t3 = hmac(conversation_key, &[&t2, nonce, &[3]]);
+    let okm = [t1, t2, t3].concat();
+    MessageKeys {
+        chacha_key: okm[..32].try_into().expect("32 bytes"),
+        chacha_nonce: okm[32..44].try_into().expect("12 bytes"),
+        hmac_key: okm[44..76].try_into().expect("32 bytes"),
+    }
+}
+
+/// Returns the size to which a plaintext of the given size is padded.
+fn padded_len(len: usize) -> usize {
+    if len <= 32 {
+        return 32;
+    }
+    let next_power = 1usize << (usize::BITS - (len - 1).leading_zeros());
+    let chunk = if next_power <= 256 {
+        32
+    } else {
+        next_power / 8
+    };
+    chunk * ((len - 1) / chunk + 1)
+}
+
+fn fixed_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Encrypts the given content for the given recipient.
+pub(crate) fn encrypt(
+    keypair: &KeyPair,
+    recipient: &XOnlyPublicKey,
+    content: &str,
+) -> Result<String, Error> {
+    let mut nonce = [0u8; NONCE_SIZE];
+    thread_rng().fill_bytes(&mut nonce);
+    encrypt_with_nonce(&conversation_key(keypair, recipient), &nonce, content)
+}
+
+fn encrypt_with_nonce(
+    conversation_key: &[u8; 32],
+    nonce: &[u8; NONCE_SIZE],
+    content: &str,
+) -> Result<String, Error> {
+    let plain_text = content.as_bytes();
+    if plain_text.is_empty() || plain_text.len() > MAX_PLAINTEXT_SIZE {
+        return Err(Error::Encryption("Invalid content length".to_string()));
+    }
+    let keys = message_keys(conversation_key, nonce);
+
+    let mut data = Vec::with_capacity(2 + padded_len(plain_text.len()));
+    data.extend_from_slice(&(plain_text.len() as u16).to_be_bytes());
+    data.extend_from_slice(plain_text);
+    data.resize(2 + padded_len(plain_text.len()), 0);
+    ChaCha20::new(&keys.chacha_key.into(), &keys.chacha_nonce.into()).apply_keystream(&mut data);
+    let mac = hmac(&keys.hmac_key, &[nonce, &data]);
+
+    let mut payload = Vec::with_capacity(1 + NONCE_SIZE + data.len() + MAC_SIZE);
+    payload.push(VERSION);
+    payload.extend_from_slice(nonce);
+    payload.extend_from_slice(&data);
+    payload.extend_from_slice(&mac);
+    Ok(BASE64.encode(payload))
+}
+
+/// Decrypts the given content received from the given sender.
+pub(crate) fn decrypt(
+    keypair: &KeyPair,
+    sender: &XOnlyPublicKey,
+    content: &str,
+) -> Result<String, Error> {
+    decrypt_with_key(&conversation_key(keypair, sender), content)
+}
+
+fn decrypt_with_key(conversation_key: &[u8; 32], content: &str) -> Result<String, Error> {
+    // Payloads starting with '#' use a future, non base64 encoding.
+    if content.starts_with('#') {
+        return Err(Error::Encryption(
+            "Unsupported encryption version".to_string(),
+        ));
+    }
+    let payload = BASE64
+        .decode(content)
+        .map_err(|e| Error::Encryption(e.to_string()))?;
+    if payload.len() < MIN_PAYLOAD_SIZE || payload.len() > MAX_PAYLOAD_SIZE {
+        return Err(Error::Encryption("Invalid payload length".to_string()));
+    }
+    if payload[0] != VERSION {
+        return Err(Error::Encryption(
+            "Unsupported encryption version".to_string(),
+        ));
+    }
+    let nonce: [u8; NONCE_SIZE] = payload[1..1 + NONCE_SIZE]
+        .try_into()
+        .expect("payload to hold a nonce");
+    let (cipher_text, mac) =
+        payload[1 + NONCE_SIZE..].split_at(payload.len() - 1 - NONCE_SIZE - MAC_SIZE);
+
+    let keys = message_keys(conversation_key, &nonce);
+    if !fixed_time_eq(&hmac(&keys.hmac_key, &[&nonce, cipher_text]), mac) {
+        return Err(Error::Encryption("Invalid MAC".to_string()));
+    }
+
+    let mut data = cipher_text.to_vec();
+    ChaCha20::new(&keys.chacha_key.into(), &keys.chacha_nonce.into()).apply_keystream(&mut data);
+    let len = u16::from_be_bytes([data[0], data[1]]) as usize;
+    if len == 0 || data.len() != 2 + padded_len(len) {
+        return Err(Error::Encryption("Invalid padding".to_string()));
+    }
+    String::from_utf8(data[2..2 + len].to_vec()).map_err(|e| Error::Encryption(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::SECP256K1;
+
+    #[test]
+    fn encrypt_decrypt_test() {
+        let alice = KeyPair::new(SECP256K1, &mut thread_rng());
+        let bob = KeyPair::new(SECP256K1, &mut thread_rng());
+
+        let encrypted = encrypt(&alice, &bob.x_only_public_key().0, "hello").unwrap();
+        assert_ne!("hello", encrypted);
+        assert_eq!(
+            "hello",
+            decrypt(&bob, &alice.x_only_public_key().0, &encrypted).unwrap()
+        );
+    }
+
+    #[test]
+    fn conversation_key_is_symmetric_test() {
+        let alice = KeyPair::new(SECP256K1, &mut thread_rng());
+        let bob = KeyPair::new(SECP256K1, &mut thread_rng());
+
+        assert_eq!(
+            conversation_key(&alice, &bob.x_only_public_key().0),
+            conversation_key(&bob, &alice.x_only_public_key().0)
+        );
+    }
+
+    #[test]
+    fn padded_len_test() {
+        let expected = [
+            (1, 32),
+            (32, 32),
+            (33, 64),
+            (64, 64),
+            (65, 96),
+            (100, 128),
+            (256, 256),
+            (257, 320),
+            (320, 320),
+            (383, 384),
+            (400, 448),
+            (515, 640),
+            (1020, 1024),
+            (65535, 65536),
+        ];
+        for (len, padded) in expected {
+            assert_eq!(padded, padded_len(len), "for length {}", len);
+        }
+    }
+
+    #[test]
+    fn plaintext_length_is_hidden_test() {
+        let key = [1u8; 32];
+        let nonce = [2u8; NONCE_SIZE];
+
+        assert_eq!(
+            encrypt_with_nonce(&key, &nonce, "a").unwrap().len(),
+            encrypt_with_nonce(&key, &nonce, &"a".repeat(32))
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn invalid_content_is_rejected_test() {
+        let key = [1u8; 32];
+        let nonce = [2u8; NONCE_SIZE];
+
+        assert!(encrypt_with_nonce(&key, &nonce, "").is_err());
+        assert!(encrypt_with_nonce(&key, &nonce, &"a".repeat(MAX_PLAINTEXT_SIZE + 1)).is_err());
+
+        let encrypted = encrypt_with_nonce(&key, &nonce, "hello").unwrap();
+        let mut payload = BASE64.decode(&encrypted).unwrap();
+        let last = payload.len() - 1;
+        payload[last] ^= 1;
+        assert!(decrypt_with_key(&key, &BASE64.encode(&payload)).is_err());
+        assert!(decrypt_with_key(&[3u8; 32], &encrypted).is_err());
+        assert!(decrypt_with_key(&key, &format!("#{}", encrypted)).is_err());
+    }
+}
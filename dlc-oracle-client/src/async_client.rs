@@ -0,0 +1,147 @@
+//! #AsyncOracleClient
+//! Asynchronous version of the [`OracleClient`](crate::OracleClient).
+
+use crate::cache::AnnouncementCache;
+use crate::{
+    announcement_path, attestation_path, check_announcement, is_retryable_error,
+    is_retryable_status, normalize_host, pubkey_path, request_error, status_error, to_attestation,
+    AttestationResponse, OracleClientConfig, PublicKeyResponse,
+};
+use async_trait::async_trait;
+use dlc_manager::async_oracle::AsyncOracle;
+use dlc_manager::error::Error as DlcManagerError;
+use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
+use secp256k1_zkp::XOnlyPublicKey;
+
+/// Asynchronous client for an oracle, to be used within a tokio runtime.
+/// Announcements are cached as configured by
+/// [`OracleClientConfig::cache_dir`].
+pub struct AsyncOracleClient {
+    host: String,
+    public_key: XOnlyPublicKey,
+    client: reqwest::Client,
+    config: OracleClientConfig,
+    cache: AnnouncementCache,
+}
+
+impl AsyncOracleClient {
+    /// Try to create an instance of an oracle client connecting to the
+    /// provided host, retrieving the public key of the oracle. Returns an
+    /// error if the host could not be reached.
+    pub async fn new(
+        host: &str,
+        config: OracleClientConfig,
+    ) -> Result<AsyncOracleClient, DlcManagerError> {
+        let host = normalize_host(host)?;
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(request_error)?;
+        let public_key = get::<PublicKeyResponse>(&client, &config, &pubkey_path(&host))
+            .await?
+            .public_key;
+        let cache = AnnouncementCache::new(config.cache_dir.as_deref(), &public_key)?;
+        Ok(AsyncOracleClient {
+            host,
+            public_key,
+            client,
+            config,
+            cache,
+        })
+    }
+}
+
+async fn get<T>(
+    client: &reqwest::Client,
+    config: &OracleClientConfig,
+    path: &str,
+) -> Result<T, DlcManagerError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let mut attempt = 0;
+    loop {
+        let error = match client.get(path).send().await {
+            Ok(response) if response.status().is_success() => {
+                return response
+                    .json::<T>()
+                    .await
+                    .map_err(|e| DlcManagerError::OracleError(e.to_string()));
+            }
+            Ok(response) if is_retryable_status(response.status()) => {
+                status_error(path, response.status())
+            }
+            Ok(response) => return Err(status_error(path, response.status())),
+            Err(e) if is_retryable_error(&e) => request_error(e),
+            Err(e) => return Err(request_error(e)),
+        };
+        if attempt >= config.max_retries {
+            return Err(error);
+        }
+        tokio::time::sleep(config.backoff(attempt)).await;
+        attempt += 1;
+    }
+}
+
+#[async_trait]
+impl AsyncOracle for AsyncOracleClient {
+    fn get_public_key(&self) -> XOnlyPublicKey {
+        self.public_key
+    }
+
+    async fn get_announcement(
+        &self,
+        event_id: &str,
+    ) -> Result<OracleAnnouncement, DlcManagerError> {
+        if let Some(announcement) = self.cache.get(event_id) {
+            return Ok(announcement);
+        }
+        let announcement: OracleAnnouncement = get(
+            &self.client,
+            &self.config,
+            &announcement_path(&self.host, event_id),
+        )
+        .await?;
+        check_announcement(&self.public_key, event_id, &announcement)?;
+        self.cache.insert(event_id, &announcement)?;
+        Ok(announcement)
+    }
+
+    async fn get_attestation(&self, event_id: &str) -> Result<OracleAttestation, DlcManagerError> {
+        let response = get::<AttestationResponse>(
+            &self.client,
+            &self.config,
+            &attestation_path(&self.host, event_id),
+        )
+        .await?;
+        to_attestation(&self.public_key, event_id, response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{announcement_mock, keypair, pubkey_mock, test_config};
+
+    #[tokio::test]
+    async fn get_announcement_test() {
+        let _pubkey_mock = pubkey_mock();
+        let announcement_mock = announcement_mock("async", 1);
+
+        let client = AsyncOracleClient::new(&mockito::server_url(), test_config())
+            .await
+            .expect("Error creating client instance");
+        assert_eq!(keypair().x_only_public_key().0, client.get_public_key());
+        let fetched = client
+            .get_announcement("async")
+            .await
+            .expect("Error getting announcement");
+        let cached = client
+            .get_announcement("async")
+            .await
+            .expect("Error getting announcement");
+
+        assert_eq!(fetched, cached);
+        announcement_mock.assert();
+    }
+}
@@ -0,0 +1,94 @@
+//! #Cache
+//! Storage of the announcements retrieved from an oracle, both in memory and
+//! optionally on disk so that they are kept across restarts.
+
+use dlc_manager::error::Error as DlcManagerError;
+use dlc_messages::oracle_msgs::OracleAnnouncement;
+use secp256k1_zkp::XOnlyPublicKey;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Cache of the announcements of a single oracle. Announcements are
+/// immutable once published so cached entries never expire.
+pub(crate) struct AnnouncementCache {
+    dir: Option<PathBuf>,
+    announcements: Mutex<HashMap<String, OracleAnnouncement>>,
+}
+
+impl AnnouncementCache {
+    /// Creates a cache storing announcements of the given oracle in a sub
+    /// directory of `dir`, or only in memory if `dir` is `None`.
+    pub(crate) fn new(
+        dir: Option<&Path>,
+        public_key: &XOnlyPublicKey,
+    ) -> Result<AnnouncementCache, DlcManagerError> {
+        let dir = match dir {
+            Some(dir) => {
+                let dir = dir.join(public_key.to_string());
+                fs::create_dir_all(&dir)?;
+                Some(dir)
+            }
+            None => None,
+        };
+        Ok(AnnouncementCache {
+            dir,
+            announcements: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the cached announcement for the given event if any. Cache
+    /// files that cannot be read are ignored so that the announcement gets
+    /// fetched again.
+    pub(crate) fn get(&self, event_id: &str) -> Option<OracleAnnouncement> {
+        if let Some(announcement) = self.announcements.lock().unwrap().get(event_id) {
+            return Some(announcement.clone());
+        }
+
+        let path = self.file_path(event_id)?;
+        let announcement: OracleAnnouncement = fs::read(path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())?;
+        self.announcements
+            .lock()
+            .unwrap()
+            .insert(event_id.to_string(), announcement.clone());
+        Some(announcement)
+    }
+
+    /// Adds the given announcement to the cache. The file is written to a
+    /// temporary location first so that a partially written file is never
+    /// read back.
+    pub(crate) fn insert(
+        &self,
+        event_id: &str,
+        announcement: &OracleAnnouncement,
+    ) -> Result<(), DlcManagerError> {
+        if let Some(path) = self.file_path(event_id) {
+            let data = serde_json::to_vec(announcement)
+                .map_err(|e| DlcManagerError::OracleError(e.to_string()))?;
+            let tmp_path = path.with_extension("tmp");
+            fs::write(&tmp_path, data)?;
+            fs::rename(&tmp_path, &path)?;
+        }
+        self.announcements
+            .lock()
+            .unwrap()
+            .insert(event_id.to_string(), announcement.clone());
+        Ok(())
+    }
+
+    /// Event ids are hex encoded as they can contain characters that are not
+    /// valid in file names.
+    fn file_path(&self, event_id: &str) -> Option<PathBuf> {
+        let file_name: String = event_id
+            .as_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", file_name)))
+    }
+}
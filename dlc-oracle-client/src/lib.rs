@@ -0,0 +1,406 @@
+//! # dlc-oracle-client
+//! Http client for DLC oracles exposing announcements and attestations over
+//! a REST interface, see the crate Readme for the expected endpoints.
+
+#![crate_name = "dlc_oracle_client"]
+// Coding conventions
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+#[cfg(feature = "async")]
+pub mod async_client;
+mod cache;
+
+use cache::AnnouncementCache;
+use dlc_manager::error::Error as DlcManagerError;
+use dlc_manager::Oracle;
+use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
+use reqwest::StatusCode;
+use secp256k1_zkp::{schnorr::Signature, XOnlyPublicKey, SECP256K1};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Configuration of an oracle client.
+#[derive(Clone, Debug)]
+pub struct OracleClientConfig {
+    /// Directory in which announcements are cached. If `None`, announcements
+    /// are only cached in memory.
+    pub cache_dir: Option<PathBuf>,
+    /// The maximum number of times a failed request is retried.
+    pub max_retries: u32,
+    /// The delay before the first retry, doubled after each failed attempt.
+    pub initial_backoff: Duration,
+    /// The maximum delay between two attempts.
+    pub max_backoff: Duration,
+    /// The timeout applied to each request.
+    pub timeout: Duration,
+}
+
+impl Default for OracleClientConfig {
+    fn default() -> Self {
+        OracleClientConfig {
+            cache_dir: None,
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl OracleClientConfig {
+    /// Returns the delay to wait for before retrying after the given number
+    /// of failed attempts.
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(1 << attempt.min(16))
+            .map_or(self.max_backoff, |x| x.min(self.max_backoff))
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PublicKeyResponse {
+    public_key: XOnlyPublicKey,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AttestationResponse {
+    event_id: String,
+    signatures: Vec<Signature>,
+    outcomes: Vec<String>,
+}
+
+fn pubkey_path(host: &str) -> String {
+    format!("{}oracle/publickey", host)
+}
+
+fn announcement_path(host: &str, event_id: &str) -> String {
+    format!("{}announcement/{}", host, event_id)
+}
+
+fn attestation_path(host: &str, event_id: &str) -> String {
+    format!("{}attestation/{}", host, event_id)
+}
+
+fn normalize_host(host: &str) -> Result<String, DlcManagerError> {
+    if host.is_empty() {
+        return Err(DlcManagerError::InvalidParameters(
+            "Invalid host".to_string(),
+        ));
+    }
+    if host.ends_with('/') {
+        Ok(host.to_string())
+    } else {
+        Ok(format!("{}/", host))
+    }
+}
+
+/// Returns whether a request that failed with the given status is likely to
+/// succeed if retried.
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Returns whether a request that failed with the given error is likely to
+/// succeed if retried.
+fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout()
+}
+
+fn status_error(path: &str, status: StatusCode) -> DlcManagerError {
+    DlcManagerError::OracleError(format!("Request to {} failed with status {}", path, status))
+}
+
+fn request_error(error: reqwest::Error) -> DlcManagerError {
+    DlcManagerError::IOError(std::io::Error::new(std::io::ErrorKind::Other, error))
+}
+
+/// Checks that the announcement is validly signed by the oracle for the
+/// requested event.
+fn check_announcement(
+    public_key: &XOnlyPublicKey,
+    event_id: &str,
+    announcement: &OracleAnnouncement,
+) -> Result<(), DlcManagerError> {
+    if &announcement.oracle_public_key != public_key
+        || announcement.oracle_event.event_id != event_id
+    {
+        return Err(DlcManagerError::OracleError(
+            "Announcement does not match requested event".to_string(),
+        ));
+    }
+    announcement
+        .validate(SECP256K1)
+        .map_err(|e| DlcManagerError::OracleError(format!("Invalid announcement: {}", e)))
+}
+
+fn to_attestation(
+    public_key: &XOnlyPublicKey,
+    event_id: &str,
+    response: AttestationResponse,
+) -> Result<OracleAttestation, DlcManagerError> {
+    if response.event_id != event_id || response.signatures.len() != response.outcomes.len() {
+        return Err(DlcManagerError::OracleError(
+            "Invalid attestation received".to_string(),
+        ));
+    }
+
+    Ok(OracleAttestation {
+        oracle_public_key: *public_key,
+        signatures: response.signatures,
+        outcomes: response.outcomes,
+    })
+}
+
+/// Blocking client for an oracle. Announcements are cached as configured by
+/// [`OracleClientConfig::cache_dir`]. Must not be used from within an
+/// asynchronous runtime, see [`async_client::AsyncOracleClient`] for that
+/// purpose (requires the `async` feature).
+pub struct OracleClient {
+    host: String,
+    public_key: XOnlyPublicKey,
+    client: reqwest::blocking::Client,
+    config: OracleClientConfig,
+    cache: AnnouncementCache,
+}
+
+impl OracleClient {
+    /// Try to create an instance of an oracle client connecting to the
+    /// provided host, retrieving the public key of the oracle. Returns an
+    /// error if the host could not be reached.
+    pub fn new(host: &str, config: OracleClientConfig) -> Result<OracleClient, DlcManagerError> {
+        let host = normalize_host(host)?;
+        let client = reqwest::blocking::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(request_error)?;
+        let public_key =
+            get::<PublicKeyResponse>(&client, &config, &pubkey_path(&host))?.public_key;
+        let cache = AnnouncementCache::new(config.cache_dir.as_deref(), &public_key)?;
+        Ok(OracleClient {
+            host,
+            public_key,
+            client,
+            config,
+            cache,
+        })
+    }
+}
+
+fn get<T>(
+    client: &reqwest::blocking::Client,
+    config: &OracleClientConfig,
+    path: &str,
+) -> Result<T, DlcManagerError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let mut attempt = 0;
+    loop {
+        let error = match client.get(path).send() {
+            Ok(response) if response.status().is_success() => {
+                return response
+                    .json::<T>()
+                    .map_err(|e| DlcManagerError::OracleError(e.to_string()));
+            }
+            Ok(response) if is_retryable_status(response.status()) => {
+                status_error(path, response.status())
+            }
+            Ok(response) => return Err(status_error(path, response.status())),
+            Err(e) if is_retryable_error(&e) => request_error(e),
+            Err(e) => return Err(request_error(e)),
+        };
+        if attempt >= config.max_retries {
+            return Err(error);
+        }
+        std::thread::sleep(config.backoff(attempt));
+        attempt += 1;
+    }
+}
+
+impl Oracle for OracleClient {
+    fn get_public_key(&self) -> XOnlyPublicKey {
+        self.public_key
+    }
+
+    fn get_announcement(&self, event_id: &str) -> Result<OracleAnnouncement, DlcManagerError> {
+        if let Some(announcement) = self.cache.get(event_id) {
+            return Ok(announcement);
+        }
+        let announcement: OracleAnnouncement = get(
+            &self.client,
+            &self.config,
+            &announcement_path(&self.host, event_id),
+        )?;
+        check_announcement(&self.public_key, event_id, &announcement)?;
+        self.cache.insert(event_id, &announcement)?;
+        Ok(announcement)
+    }
+
+    fn get_attestation(&self, event_id: &str) -> Result<OracleAttestation, DlcManagerError> {
+        let response = get::<AttestationResponse>(
+            &self.client,
+            &self.config,
+            &attestation_path(&self.host, event_id),
+        )?;
+        to_attestation(&self.public_key, event_id, response)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use dlc_messages::oracle_msgs::{EnumEventDescriptor, EventDescriptor, OracleEvent};
+    use lightning::util::ser::Writeable;
+    use mockito::{mock, Mock};
+    use secp256k1_zkp::{hashes::sha256, KeyPair, Message, SecretKey};
+
+    pub(crate) fn keypair() -> KeyPair {
+        KeyPair::from_secret_key(SECP256K1, &SecretKey::from_slice(&[3; 32]).unwrap())
+    }
+
+    pub(crate) fn pubkey_mock() -> Mock {
+        mock("GET", pubkey_path("/").as_str())
+            .with_body(format!(
+                r#"{{"publicKey":"{}"}}"#,
+                keypair().x_only_public_key().0
+            ))
+            .create()
+    }
+
+    pub(crate) fn announcement(event_id: &str) -> OracleAnnouncement {
+        let keypair = keypair();
+        let oracle_event = OracleEvent {
+            oracle_nonces: vec![keypair.x_only_public_key().0],
+            event_maturity_epoch: 1653865200,
+            event_descriptor: EventDescriptor::EnumEvent(EnumEventDescriptor {
+                outcomes: vec!["a".to_string(), "b".to_string()],
+            }),
+            event_id: event_id.to_string(),
+        };
+        let msg = Message::from_hashed_data::<sha256::Hash>(&oracle_event.encode());
+        OracleAnnouncement {
+            announcement_signature: SECP256K1.sign_schnorr_no_aux_rand(&msg, &keypair),
+            oracle_public_key: keypair.x_only_public_key().0,
+            oracle_event,
+        }
+    }
+
+    pub(crate) fn announcement_mock(event_id: &str, expected_hits: usize) -> Mock {
+        mock("GET", announcement_path("/", event_id).as_str())
+            .with_body(serde_json::to_string(&announcement(event_id)).unwrap())
+            .expect(expected_hits)
+            .create()
+    }
+
+    pub(crate) fn test_config() -> OracleClientConfig {
+        OracleClientConfig {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn get_public_key_test() {
+        let _m = pubkey_mock();
+
+        let client = OracleClient::new(&mockito::server_url(), test_config())
+            .expect("Error creating client instance.");
+
+        assert_eq!(keypair().x_only_public_key().0, client.get_public_key());
+    }
+
+    #[test]
+    fn announcement_is_cached_on_disk_test() {
+        let _pubkey_mock = pubkey_mock();
+        let announcement_mock = announcement_mock("cached", 1);
+        let cache_dir =
+            std::env::temp_dir().join(format!("dlc-oracle-client-test-{}", std::process::id()));
+        let config = OracleClientConfig {
+            cache_dir: Some(cache_dir.clone()),
+            ..test_config()
+        };
+
+        let client = OracleClient::new(&mockito::server_url(), config.clone())
+            .expect("Error creating client instance");
+        let fetched = client
+            .get_announcement("cached")
+            .expect("Error getting announcement");
+        let client =
+            OracleClient::new(&mockito::server_url(), config).expect("Error creating client");
+        let cached = client
+            .get_announcement("cached")
+            .expect("Error getting announcement");
+
+        assert_eq!(fetched, cached);
+        announcement_mock.assert();
+        std::fs::remove_dir_all(cache_dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_announcement_is_rejected_test() {
+        let _pubkey_mock = pubkey_mock();
+        let _m = mock("GET", announcement_path("/", "requested").as_str())
+            .with_body(serde_json::to_string(&announcement("other")).unwrap())
+            .create();
+
+        let client = OracleClient::new(&mockito::server_url(), test_config())
+            .expect("Error creating client instance");
+
+        assert!(client.get_announcement("requested").is_err());
+    }
+
+    #[test]
+    fn get_attestation_test() {
+        let _pubkey_mock = pubkey_mock();
+        let _m = mock("GET", attestation_path("/", "attested").as_str())
+            .with_body(r#"{"eventId":"attested","signatures":["ee05b1211d5f974732b10107dd302da062be47cd18f061c5080a50743412f9fd590cad90cfea762472e6fe865c4223bd388c877b7881a27892e15843ff1ac360"],"outcomes":["a"]}"#)
+            .create();
+
+        let client = OracleClient::new(&mockito::server_url(), test_config())
+            .expect("Error creating client instance");
+        let attestation = client
+            .get_attestation("attested")
+            .expect("Error getting attestation");
+
+        assert_eq!(vec!["a".to_string()], attestation.outcomes);
+    }
+
+    #[test]
+    fn server_errors_are_retried_test() {
+        let _pubkey_mock = pubkey_mock();
+        let m = mock("GET", attestation_path("/", "unavailable").as_str())
+            .with_status(503)
+            .expect(4)
+            .create();
+
+        let client = OracleClient::new(&mockito::server_url(), test_config())
+            .expect("Error creating client instance");
+
+        assert!(client.get_attestation("unavailable").is_err());
+        m.assert();
+    }
+
+    #[test]
+    fn not_found_is_not_retried_test() {
+        let _pubkey_mock = pubkey_mock();
+        let m = mock("GET", attestation_path("/", "pending").as_str())
+            .with_status(404)
+            .expect(1)
+            .create();
+
+        let client = OracleClient::new(&mockito::server_url(), test_config())
+            .expect("Error creating client instance");
+
+        assert!(client.get_attestation("pending").is_err());
+        m.assert();
+    }
+}
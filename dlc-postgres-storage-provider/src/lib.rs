@@ -0,0 +1,1430 @@
+//! # dlc-postgres-storage-provider
+//! Storage provider for dlc-manager using PostgreSQL as underlying storage.
+
+#![crate_name = "dlc_postgres_storage_provider"]
+// Coding conventions
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+extern crate dlc_manager;
+extern crate r2d2_postgres;
+extern crate secp256k1_zkp;
+
+use dlc_manager::chain_monitor::ChainMonitor;
+use dlc_manager::channel::accepted_channel::AcceptedChannel;
+use dlc_manager::channel::offered_channel::OfferedChannel;
+use dlc_manager::channel::signed_channel::{SignedChannel, SignedChannelStateType};
+use dlc_manager::channel::{Channel, ChannelEvent, FailedAccept, FailedSign};
+use dlc_manager::contract::accepted_contract::AcceptedContract;
+use dlc_manager::contract::offered_contract::OfferedContract;
+use dlc_manager::contract::ser::Serializable;
+use dlc_manager::contract::signed_contract::SignedContract;
+use dlc_manager::contract::{
+    ClosedContract, CollaborativelyClosedContract, Contract, ContractEvent, FailedAcceptContract,
+    FailedSignContract, PreClosedContract,
+};
+use dlc_manager::product_catalog::ProductDefinition;
+use dlc_manager::storage_prefix::{ChannelPrefix, ContractPrefix, SignedChannelPrefix};
+use dlc_manager::{error::Error, ChannelId, ContractId, Storage, StorageBatch, StorageSnapshot};
+use r2d2_postgres::postgres::{Client, Config, GenericClient, IsolationLevel, NoTls, Transaction};
+use r2d2_postgres::r2d2::{Pool, PooledConnection};
+use r2d2_postgres::PostgresConnectionManager;
+use secp256k1_zkp::PublicKey;
+use std::convert::TryInto;
+use std::io::Cursor;
+
+/// Statements used to create and upgrade the database schema. The schema
+/// version of a database corresponds to the number of migrations that were
+/// applied to it, and is stored in the `schema_version` table.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE contracts (
+        id BYTEA PRIMARY KEY NOT NULL,
+        state SMALLINT NOT NULL,
+        counter_party BYTEA NOT NULL,
+        data BYTEA NOT NULL
+    );
+    CREATE INDEX contracts_state_idx ON contracts (state);
+    CREATE TABLE channels (
+        id BYTEA PRIMARY KEY NOT NULL,
+        state SMALLINT NOT NULL,
+        signed_state SMALLINT,
+        counter_party BYTEA NOT NULL,
+        data BYTEA NOT NULL
+    );
+    CREATE INDEX channels_state_idx ON channels (state, signed_state);
+    CREATE TABLE chain_monitor (
+        id SMALLINT PRIMARY KEY NOT NULL CHECK (id = 0),
+        data BYTEA NOT NULL
+    );
+"#,
+    r#"
+    CREATE INDEX contracts_counter_party_idx ON contracts (counter_party);
+"#,
+    r#"
+    CREATE TABLE peers (
+        id BYTEA PRIMARY KEY NOT NULL,
+        last_seen BIGINT NOT NULL
+    );
+"#,
+    r#"
+    CREATE TABLE channel_events (
+        seq BIGSERIAL PRIMARY KEY,
+        temporary_id BYTEA NOT NULL,
+        data BYTEA NOT NULL
+    );
+    CREATE INDEX channel_events_temporary_id_idx ON channel_events (temporary_id, seq);
+"#,
+    r#"
+    CREATE TABLE contract_events (
+        seq BIGSERIAL PRIMARY KEY,
+        temporary_id BYTEA NOT NULL,
+        data BYTEA NOT NULL
+    );
+    CREATE INDEX contract_events_temporary_id_idx ON contract_events (temporary_id, seq);
+"#,
+    r#"
+    CREATE TABLE offer_hashes (
+        hash BYTEA PRIMARY KEY NOT NULL,
+        temporary_id BYTEA NOT NULL
+    );
+"#,
+    r#"
+    CREATE INDEX channels_counter_party_idx ON channels (counter_party);
+"#,
+    r#"
+    CREATE TABLE products (
+        event_id TEXT PRIMARY KEY NOT NULL,
+        data BYTEA NOT NULL
+    );
+"#,
+];
+
+/// Key of the advisory lock taken while migrating the database schema, to
+/// prevent multiple instances from applying the same migrations concurrently.
+const MIGRATION_LOCK_KEY: i64 = 0x646c_6373_746f_7265;
+
+type ConnectionPool = Pool<PostgresConnectionManager<NoTls>>;
+
+/// Implementation of Storage interface using the PostgreSQL DB backend.
+/// Connections are pooled with r2d2, the blocking counterpart of deadpool and
+/// bb8, as the [`Storage`] trait is synchronous and async pools would require
+/// blocking on a runtime for every call.
+pub struct PostgresStorageProvider {
+    pool: ConnectionPool,
+}
+
+fn to_storage_error<T>(e: T) -> Error
+where
+    T: std::fmt::Display,
+{
+    Error::StorageError(e.to_string())
+}
+
+impl PostgresStorageProvider {
+    /// Creates a new instance of a PostgresStorageProvider connecting to the
+    /// database described by the given connection string, using a connection
+    /// pool with default settings.
+    pub fn new(connection_string: &str) -> Result<Self, Error> {
+        let config: Config = connection_string.parse().map_err(to_storage_error)?;
+        let pool =
+            Pool::new(PostgresConnectionManager::new(config, NoTls)).map_err(to_storage_error)?;
+        Self::from_pool(pool)
+    }
+
+    /// Creates a new instance of a PostgresStorageProvider using the given
+    /// connection pool, enabling the pool settings to be customized.
+    pub fn from_pool(pool: ConnectionPool) -> Result<Self, Error> {
+        let provider = PostgresStorageProvider { pool };
+        migrate(&mut provider.connection()?).map_err(to_storage_error)?;
+        Ok(provider)
+    }
+
+    /// Returns the version of the schema of the underlying database.
+    pub fn get_schema_version(&self) -> Result<u32, Error> {
+        get_schema_version(&mut *self.connection()?).map_err(to_storage_error)
+    }
+
+    fn connection(&self) -> Result<PooledConnection<PostgresConnectionManager<NoTls>>, Error> {
+        self.pool.get().map_err(to_storage_error)
+    }
+
+    fn get_contracts_in_state<T: Serializable>(
+        &self,
+        state: ContractPrefix,
+    ) -> Result<Vec<T>, Error> {
+        let rows = self
+            .connection()?
+            .query(
+                "SELECT data FROM contracts WHERE state = $1",
+                &[&to_db_state(state.into())],
+            )
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for row in rows {
+            res.push(deserialize_object(row.get::<_, &[u8]>(0))?);
+        }
+        Ok(res)
+    }
+
+    fn get_channels_in_state<T: Serializable>(
+        &self,
+        state: ChannelPrefix,
+        signed_state: Option<u8>,
+    ) -> Result<Vec<T>, Error> {
+        let rows = self
+            .connection()?
+            .query(
+                "SELECT data FROM channels WHERE state = $1 AND ($2::SMALLINT IS NULL OR signed_state = $2)",
+                &[&to_db_state(state.into()), &signed_state.map(to_db_state)],
+            )
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for row in rows {
+            res.push(deserialize_object(row.get::<_, &[u8]>(0))?);
+        }
+        Ok(res)
+    }
+}
+
+impl Storage for PostgresStorageProvider {
+    fn get_contract(&self, contract_id: &ContractId) -> Result<Option<Contract>, Error> {
+        let row = self
+            .connection()?
+            .query_opt(
+                "SELECT state, data FROM contracts WHERE id = $1",
+                &[&&contract_id[..]],
+            )
+            .map_err(to_storage_error)?;
+        match row {
+            Some(row) => Ok(Some(deserialize_contract(
+                from_db_state(row.get(0))?,
+                row.get(1),
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_contracts(&self) -> Result<Vec<Contract>, Error> {
+        let rows = self
+            .connection()?
+            .query("SELECT state, data FROM contracts", &[])
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for row in rows {
+            res.push(deserialize_contract(
+                from_db_state(row.get(0))?,
+                row.get(1),
+            )?);
+        }
+        Ok(res)
+    }
+
+    fn get_contracts_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Contract>, Error> {
+        let rows = self
+            .connection()?
+            .query(
+                "SELECT state, data FROM contracts WHERE counter_party = $1",
+                &[&&counter_party.serialize()[..]],
+            )
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for row in rows {
+            res.push(deserialize_contract(
+                from_db_state(row.get(0))?,
+                row.get(1),
+            )?);
+        }
+        Ok(res)
+    }
+
+    fn create_contract(&self, contract: &OfferedContract) -> Result<(), Error> {
+        create_contract(&mut *self.connection()?, contract)
+    }
+
+    fn delete_contract(&self, contract_id: &ContractId) -> Result<(), Error> {
+        self.connection()?
+            .execute("DELETE FROM contracts WHERE id = $1", &[&&contract_id[..]])
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn update_contract(&self, contract: &Contract) -> Result<(), Error> {
+        let mut connection = self.connection()?;
+        let mut tx = connection.transaction().map_err(to_storage_error)?;
+        update_contract(&mut tx, contract)?;
+        tx.commit().map_err(to_storage_error)
+    }
+
+    fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        self.get_contracts_in_state(ContractPrefix::Offered)
+    }
+
+    fn get_expired_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        self.get_contracts_in_state(ContractPrefix::Expired)
+    }
+
+    fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        self.get_contracts_in_state(ContractPrefix::Signed)
+    }
+
+    fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        self.get_contracts_in_state(ContractPrefix::Confirmed)
+    }
+
+    fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, Error> {
+        self.get_contracts_in_state(ContractPrefix::PreClosed)
+    }
+
+    fn upsert_channel(&self, channel: Channel, contract: Option<Contract>) -> Result<(), Error> {
+        let mut connection = self.connection()?;
+        let mut tx = connection.transaction().map_err(to_storage_error)?;
+
+        upsert_channel(&mut tx, &channel)?;
+
+        if let Some(c) = contract.as_ref() {
+            update_contract(&mut tx, c)?;
+        }
+
+        tx.commit().map_err(to_storage_error)
+    }
+
+    fn delete_channel(&self, channel_id: &ChannelId) -> Result<(), Error> {
+        self.connection()?
+            .execute("DELETE FROM channels WHERE id = $1", &[&&channel_id[..]])
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_channel(&self, channel_id: &ChannelId) -> Result<Option<Channel>, Error> {
+        let row = self
+            .connection()?
+            .query_opt(
+                "SELECT state, data FROM channels WHERE id = $1",
+                &[&&channel_id[..]],
+            )
+            .map_err(to_storage_error)?;
+        match row {
+            Some(row) => Ok(Some(deserialize_channel(
+                from_db_state(row.get(0))?,
+                row.get(1),
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_channels_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Channel>, Error> {
+        let rows = self
+            .connection()?
+            .query(
+                "SELECT state, data FROM channels WHERE counter_party = $1",
+                &[&&counter_party.serialize()[..]],
+            )
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for row in rows {
+            res.push(deserialize_channel(from_db_state(row.get(0))?, row.get(1))?);
+        }
+        Ok(res)
+    }
+
+    fn get_signed_channels(
+        &self,
+        channel_state: Option<SignedChannelStateType>,
+    ) -> Result<Vec<SignedChannel>, Error> {
+        self.get_channels_in_state(
+            ChannelPrefix::Signed,
+            channel_state.as_ref().map(SignedChannelPrefix::get_prefix),
+        )
+    }
+
+    fn get_offered_channels(&self) -> Result<Vec<OfferedChannel>, Error> {
+        self.get_channels_in_state(ChannelPrefix::Offered, None)
+    }
+
+    fn persist_chain_monitor(&self, monitor: &ChainMonitor) -> Result<(), Error> {
+        persist_chain_monitor(&mut *self.connection()?, monitor)
+    }
+
+    fn write_batch(&self, batch: StorageBatch) -> Result<(), Error> {
+        let mut connection = self.connection()?;
+        let mut tx = connection.transaction().map_err(to_storage_error)?;
+
+        for contract in &batch.new_contracts {
+            create_contract(&mut tx, contract)?;
+        }
+
+        for channel in &batch.channels {
+            upsert_channel(&mut tx, channel)?;
+        }
+
+        for contract in &batch.contracts {
+            update_contract(&mut tx, contract)?;
+        }
+
+        for (temporary_id, event) in &batch.contract_events {
+            append_event(&mut tx, "contract_events", temporary_id, event)?;
+        }
+
+        for (temporary_id, event) in &batch.channel_events {
+            append_event(&mut tx, "channel_events", temporary_id, event)?;
+        }
+
+        if let Some(monitor) = batch.chain_monitor.as_ref() {
+            persist_chain_monitor(&mut tx, monitor)?;
+        }
+
+        tx.commit().map_err(to_storage_error)
+    }
+
+    fn update_peer_last_seen(&self, peer_id: &PublicKey, timestamp: u64) -> Result<(), Error> {
+        self.connection()?
+            .execute(
+                "INSERT INTO peers (id, last_seen) VALUES ($1, $2) \
+                 ON CONFLICT (id) DO UPDATE SET last_seen = EXCLUDED.last_seen",
+                &[&&peer_id.serialize()[..], &(timestamp as i64)],
+            )
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_peer_last_seen(&self, peer_id: &PublicKey) -> Result<Option<u64>, Error> {
+        let row = self
+            .connection()?
+            .query_opt(
+                "SELECT last_seen FROM peers WHERE id = $1",
+                &[&&peer_id.serialize()[..]],
+            )
+            .map_err(to_storage_error)?;
+        Ok(row.map(|row| row.get::<_, i64>(0) as u64))
+    }
+
+    fn upsert_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+        temporary_id: &ContractId,
+    ) -> Result<(), Error> {
+        self.connection()?
+            .execute(
+                "INSERT INTO offer_hashes (hash, temporary_id) VALUES ($1, $2) \
+                 ON CONFLICT (hash) DO UPDATE SET temporary_id = EXCLUDED.temporary_id",
+                &[&&offer_hash[..], &&temporary_id[..]],
+            )
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_contract_id_by_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+    ) -> Result<Option<ContractId>, Error> {
+        let row = self
+            .connection()?
+            .query_opt(
+                "SELECT temporary_id FROM offer_hashes WHERE hash = $1",
+                &[&&offer_hash[..]],
+            )
+            .map_err(to_storage_error)?;
+        row.map(|row| {
+            row.get::<_, &[u8]>(0)
+                .try_into()
+                .map_err(|_| Error::StorageError("Invalid stored contract id".to_string()))
+        })
+        .transpose()
+    }
+
+    fn upsert_product_definition(
+        &self,
+        event_id: &str,
+        product: &ProductDefinition,
+    ) -> Result<(), Error> {
+        self.connection()?
+            .execute(
+                "INSERT INTO products (event_id, data) VALUES ($1, $2) \
+                 ON CONFLICT (event_id) DO UPDATE SET data = EXCLUDED.data",
+                &[&event_id, &product.serialize().map_err(to_storage_error)?],
+            )
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_product_definition(&self, event_id: &str) -> Result<Option<ProductDefinition>, Error> {
+        let row = self
+            .connection()?
+            .query_opt(
+                "SELECT data FROM products WHERE event_id = $1",
+                &[&event_id],
+            )
+            .map_err(to_storage_error)?;
+        row.map(|row| deserialize_object(row.get::<_, &[u8]>(0)))
+            .transpose()
+    }
+
+    fn append_contract_event(
+        &self,
+        temporary_id: &ContractId,
+        event: &ContractEvent,
+    ) -> Result<(), Error> {
+        append_event(
+            &mut *self.connection()?,
+            "contract_events",
+            temporary_id,
+            event,
+        )
+    }
+
+    fn get_contract_history(&self, id: &ContractId) -> Result<Vec<ContractEvent>, Error> {
+        let temporary_id = self.get_contract(id)?.map_or(*id, |c| c.get_temporary_id());
+        let rows = self
+            .connection()?
+            .query(
+                "SELECT data FROM contract_events WHERE temporary_id = $1 ORDER BY seq",
+                &[&&temporary_id[..]],
+            )
+            .map_err(to_storage_error)?;
+        rows.iter()
+            .map(|row| deserialize_object(row.get::<_, &[u8]>(0)))
+            .collect()
+    }
+
+    fn append_channel_event(
+        &self,
+        temporary_id: &ChannelId,
+        event: &ChannelEvent,
+    ) -> Result<(), Error> {
+        append_event(
+            &mut *self.connection()?,
+            "channel_events",
+            temporary_id,
+            event,
+        )
+    }
+
+    fn get_channel_history(&self, id: &ChannelId) -> Result<Vec<ChannelEvent>, Error> {
+        let history_id = self.get_channel(id)?.map_or(*id, |c| c.get_history_id());
+        let rows = self
+            .connection()?
+            .query(
+                "SELECT data FROM channel_events WHERE temporary_id = $1 ORDER BY seq",
+                &[&&history_id[..]],
+            )
+            .map_err(to_storage_error)?;
+        rows.iter()
+            .map(|row| deserialize_object(row.get::<_, &[u8]>(0)))
+            .collect()
+    }
+
+    fn read_snapshot(&self) -> Result<StorageSnapshot, Error> {
+        let mut connection = self.connection()?;
+        // A repeatable read transaction sees all the tables as of its first
+        // query.
+        let mut tx = connection
+            .build_transaction()
+            .isolation_level(IsolationLevel::RepeatableRead)
+            .read_only(true)
+            .start()
+            .map_err(to_storage_error)?;
+        let mut contracts = Vec::new();
+        for row in tx
+            .query("SELECT state, data FROM contracts", &[])
+            .map_err(to_storage_error)?
+        {
+            contracts.push(deserialize_contract(
+                from_db_state(row.get(0))?,
+                row.get(1),
+            )?);
+        }
+        let mut channels = Vec::new();
+        for row in tx
+            .query("SELECT state, data FROM channels", &[])
+            .map_err(to_storage_error)?
+        {
+            channels.push(deserialize_channel(from_db_state(row.get(0))?, row.get(1))?);
+        }
+        let chain_monitor = match tx
+            .query_opt("SELECT data FROM chain_monitor WHERE id = 0", &[])
+            .map_err(|e| Error::StorageError(format!("Error reading chain monitor: {}", e)))?
+        {
+            Some(row) => Some(deserialize_object(row.get::<_, &[u8]>(0))?),
+            None => None,
+        };
+        tx.commit().map_err(to_storage_error)?;
+        Ok(StorageSnapshot::new(contracts, channels, chain_monitor))
+    }
+
+    fn get_chain_monitor(&self) -> Result<Option<ChainMonitor>, Error> {
+        let row = self
+            .connection()?
+            .query_opt("SELECT data FROM chain_monitor WHERE id = 0", &[])
+            .map_err(|e| Error::StorageError(format!("Error reading chain monitor: {}", e)))?;
+        match row {
+            Some(row) => Ok(Some(deserialize_object(row.get::<_, &[u8]>(0))?)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn to_db_state(state: u8) -> i16 {
+    state as i16
+}
+
+fn from_db_state(state: i16) -> Result<u8, Error> {
+    state
+        .try_into()
+        .map_err(|_| Error::StorageError("Invalid state".to_string()))
+}
+
+fn get_schema_version<C: GenericClient>(
+    client: &mut C,
+) -> Result<u32, r2d2_postgres::postgres::Error> {
+    let row = client.query_opt("SELECT version FROM schema_version", &[])?;
+    Ok(row.map(|r| r.get::<_, i32>(0) as u32).unwrap_or(0))
+}
+
+fn migrate(client: &mut Client) -> Result<(), r2d2_postgres::postgres::Error> {
+    let mut tx = client.transaction()?;
+    tx.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_KEY])?;
+    tx.batch_execute("CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)")?;
+    let version = get_schema_version(&mut tx)? as usize;
+    for migration in MIGRATIONS.iter().skip(version) {
+        tx.batch_execute(migration)?;
+    }
+    if version < MIGRATIONS.len() {
+        tx.execute("DELETE FROM schema_version", &[])?;
+        tx.execute(
+            "INSERT INTO schema_version (version) VALUES ($1)",
+            &[&(MIGRATIONS.len() as i32)],
+        )?;
+    }
+    tx.commit()
+}
+
+fn insert_contract<C: GenericClient>(client: &mut C, contract: &Contract) -> Result<(), Error> {
+    client
+        .execute(
+            "INSERT INTO contracts (id, state, counter_party, data) VALUES ($1, $2, $3, $4) \
+             ON CONFLICT (id) DO UPDATE SET state = EXCLUDED.state, \
+             counter_party = EXCLUDED.counter_party, data = EXCLUDED.data",
+            &[
+                &&contract.get_id()[..],
+                &to_db_state(ContractPrefix::get_prefix(contract)),
+                &&contract.get_counter_party_id().serialize()[..],
+                &serialize_contract(contract)?,
+            ],
+        )
+        .map_err(to_storage_error)?;
+    Ok(())
+}
+
+fn create_contract<C: GenericClient>(
+    client: &mut C,
+    contract: &OfferedContract,
+) -> Result<(), Error> {
+    let contract = Contract::Offered(contract.clone());
+    let inserted = client
+        .execute(
+            "INSERT INTO contracts (id, state, counter_party, data) VALUES ($1, $2, $3, $4) \
+             ON CONFLICT (id) DO NOTHING",
+            &[
+                &&contract.get_id()[..],
+                &to_db_state(ContractPrefix::get_prefix(&contract)),
+                &&contract.get_counter_party_id().serialize()[..],
+                &serialize_contract(&contract)?,
+            ],
+        )
+        .map_err(to_storage_error)?;
+    if inserted == 0 {
+        return Err(Error::AlreadyExists("Contract already exists".to_string()));
+    }
+    Ok(())
+}
+
+fn update_contract(tx: &mut Transaction, contract: &Contract) -> Result<(), Error> {
+    // Lock the rows of the contract (under both its temporary and final ids) so
+    // that concurrent updates from other instances are serialized.
+    let temporary_id = contract.get_temporary_id();
+    tx.execute(
+        "SELECT id FROM contracts WHERE id = $1 OR id = $2 FOR UPDATE",
+        &[&&contract.get_id()[..], &&temporary_id[..]],
+    )
+    .map_err(to_storage_error)?;
+
+    match contract {
+        Contract::Accepted(_) | Contract::Signed(_) => {
+            tx.execute("DELETE FROM contracts WHERE id = $1", &[&&temporary_id[..]])
+                .map_err(to_storage_error)?;
+        }
+        _ => {}
+    };
+
+    insert_contract(tx, contract)
+}
+
+fn upsert_channel(tx: &mut Transaction, channel: &Channel) -> Result<(), Error> {
+    // Lock the rows of the channel (under both its temporary and final ids) so
+    // that concurrent updates from other instances are serialized.
+    let temporary_id = channel.get_temporary_id();
+    tx.execute(
+        "SELECT id FROM channels WHERE id = $1 OR id = $2 FOR UPDATE",
+        &[&&channel.get_id()[..], &&temporary_id[..]],
+    )
+    .map_err(to_storage_error)?;
+
+    match channel {
+        Channel::Accepted(_) | Channel::Signed(_) => {
+            tx.execute("DELETE FROM channels WHERE id = $1", &[&&temporary_id[..]])
+                .map_err(to_storage_error)?;
+        }
+        _ => {}
+    };
+
+    let signed_state = match channel {
+        Channel::Signed(s) => Some(to_db_state(SignedChannelPrefix::get_prefix(
+            &s.state.get_type(),
+        ))),
+        _ => None,
+    };
+
+    tx.execute(
+        "INSERT INTO channels (id, state, signed_state, counter_party, data) VALUES ($1, $2, $3, $4, $5) \
+         ON CONFLICT (id) DO UPDATE SET state = EXCLUDED.state, signed_state = EXCLUDED.signed_state, \
+         counter_party = EXCLUDED.counter_party, data = EXCLUDED.data",
+        &[
+            &&channel.get_id()[..],
+            &to_db_state(ChannelPrefix::get_prefix(channel)),
+            &signed_state,
+            &&channel.get_counter_party_id().serialize()[..],
+            &serialize_channel(channel)?,
+        ],
+    )
+    .map_err(to_storage_error)?;
+
+    Ok(())
+}
+
+/// Appends the given event to the log of the contract or channel with the
+/// given temporary id, stored in the given table.
+fn append_event<C: GenericClient, E: Serializable>(
+    client: &mut C,
+    table: &str,
+    temporary_id: &[u8; 32],
+    event: &E,
+) -> Result<(), Error> {
+    client
+        .execute(
+            format!("INSERT INTO {} (temporary_id, data) VALUES ($1, $2)", table).as_str(),
+            &[
+                &&temporary_id[..],
+                &event.serialize().map_err(to_storage_error)?,
+            ],
+        )
+        .map_err(to_storage_error)?;
+    Ok(())
+}
+
+fn persist_chain_monitor<C: GenericClient>(
+    client: &mut C,
+    monitor: &ChainMonitor,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "INSERT INTO chain_monitor (id, data) VALUES (0, $1) \
+             ON CONFLICT (id) DO UPDATE SET data = EXCLUDED.data",
+            &[&monitor.serialize()?],
+        )
+        .map_err(|e| Error::StorageError(format!("Error writing chain monitor: {}", e)))?;
+    Ok(())
+}
+
+fn deserialize_object<T: Serializable>(data: &[u8]) -> Result<T, Error> {
+    T::deserialize(&mut Cursor::new(data)).map_err(to_storage_error)
+}
+
+fn serialize_contract(contract: &Contract) -> Result<Vec<u8>, ::std::io::Error> {
+    match contract {
+        Contract::Offered(o) | Contract::Rejected(o) | Contract::Expired(o) => o.serialize(),
+        Contract::Accepted(o) => o.serialize(),
+        Contract::Signed(o)
+        | Contract::Confirmed(o)
+        | Contract::Refunded(o)
+        | Contract::Amended(o) => o.serialize(),
+        Contract::FailedAccept(c) => c.serialize(),
+        Contract::FailedSign(c) => c.serialize(),
+        Contract::PreClosed(c) => c.serialize(),
+        Contract::CollaborativelyClosed(c) => c.serialize(),
+        Contract::Closed(c) => c.serialize(),
+    }
+}
+
+fn deserialize_contract(state: u8, data: &[u8]) -> Result<Contract, Error> {
+    let contract_state: ContractPrefix = state.try_into()?;
+    let contract = match contract_state {
+        ContractPrefix::Offered => Contract::Offered(deserialize_object::<OfferedContract>(data)?),
+        ContractPrefix::Accepted => {
+            Contract::Accepted(deserialize_object::<AcceptedContract>(data)?)
+        }
+        ContractPrefix::Signed => Contract::Signed(deserialize_object::<SignedContract>(data)?),
+        ContractPrefix::Confirmed => {
+            Contract::Confirmed(deserialize_object::<SignedContract>(data)?)
+        }
+        ContractPrefix::PreClosed => {
+            Contract::PreClosed(deserialize_object::<PreClosedContract>(data)?)
+        }
+        ContractPrefix::Closed => Contract::Closed(deserialize_object::<ClosedContract>(data)?),
+        ContractPrefix::FailedAccept => {
+            Contract::FailedAccept(deserialize_object::<FailedAcceptContract>(data)?)
+        }
+        ContractPrefix::FailedSign => {
+            Contract::FailedSign(deserialize_object::<FailedSignContract>(data)?)
+        }
+        ContractPrefix::Refunded => Contract::Refunded(deserialize_object::<SignedContract>(data)?),
+        ContractPrefix::Rejected => {
+            Contract::Rejected(deserialize_object::<OfferedContract>(data)?)
+        }
+        ContractPrefix::Expired => Contract::Expired(deserialize_object::<OfferedContract>(data)?),
+        ContractPrefix::Amended => Contract::Amended(deserialize_object::<SignedContract>(data)?),
+        ContractPrefix::CollaborativelyClosed => Contract::CollaborativelyClosed(
+            deserialize_object::<CollaborativelyClosedContract>(data)?,
+        ),
+    };
+    Ok(contract)
+}
+
+fn serialize_channel(channel: &Channel) -> Result<Vec<u8>, ::std::io::Error> {
+    match channel {
+        Channel::Offered(o) => o.serialize(),
+        Channel::Accepted(a) => a.serialize(),
+        Channel::Signed(s) => s.serialize(),
+        Channel::FailedAccept(f) => f.serialize(),
+        Channel::FailedSign(f) => f.serialize(),
+        Channel::Cancelled(o) => o.serialize(),
+    }
+}
+
+fn deserialize_channel(state: u8, data: &[u8]) -> Result<Channel, Error> {
+    let channel_state: ChannelPrefix = state.try_into()?;
+    let channel = match channel_state {
+        ChannelPrefix::Offered => Channel::Offered(deserialize_object::<OfferedChannel>(data)?),
+        ChannelPrefix::Accepted => Channel::Accepted(deserialize_object::<AcceptedChannel>(data)?),
+        ChannelPrefix::Signed => Channel::Signed(deserialize_object::<SignedChannel>(data)?),
+        ChannelPrefix::FailedAccept => {
+            Channel::FailedAccept(deserialize_object::<FailedAccept>(data)?)
+        }
+        ChannelPrefix::FailedSign => Channel::FailedSign(deserialize_object::<FailedSign>(data)?),
+        ChannelPrefix::Cancelled => Channel::Cancelled(deserialize_object::<OfferedChannel>(data)?),
+    };
+    Ok(channel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dlc_manager::contract::ContractEventType;
+
+    const DEFAULT_TEST_URL: &str = "host=localhost port=5433 user=postgres password=postgres";
+
+    /// Creates a storage provider using a dedicated schema for the given test,
+    /// so that tests can run concurrently against the same database.
+    fn new_test_storage(name: &str) -> PostgresStorageProvider {
+        let url =
+            std::env::var("DLC_POSTGRES_URL").unwrap_or_else(|_| DEFAULT_TEST_URL.to_string());
+        let mut config: Config = url.parse().expect("a valid connection string");
+        let mut client = config
+            .connect(NoTls)
+            .expect("to be able to connect to the database");
+        client
+            .batch_execute(&format!(
+                "DROP SCHEMA IF EXISTS {0} CASCADE; CREATE SCHEMA {0};",
+                name
+            ))
+            .expect("to be able to create the test schema");
+        config.options(&format!("-c search_path={}", name));
+        let pool = Pool::builder()
+            .max_size(2)
+            .build(PostgresConnectionManager::new(config, NoTls))
+            .expect("to be able to create a connection pool");
+        PostgresStorageProvider::from_pool(pool).expect("Error opening PostgreSQL DB")
+    }
+
+    macro_rules! postgres_test {
+        ($name: ident, $body: expr) => {
+            #[test]
+            #[ignore]
+            fn $name() {
+                let storage = new_test_storage(stringify!($name));
+                #[allow(clippy::redundant_closure_call)]
+                $body(storage);
+            }
+        };
+    }
+
+    fn deserialize_test_object<T>(serialized: &[u8]) -> T
+    where
+        T: Serializable,
+    {
+        let mut cursor = std::io::Cursor::new(&serialized);
+        T::deserialize(&mut cursor).unwrap()
+    }
+
+    fn insert_offered_signed_and_confirmed(storage: &PostgresStorageProvider) {
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+        let offered_contract = deserialize_test_object(serialized);
+        storage
+            .create_contract(&offered_contract)
+            .expect("Error creating contract");
+
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Signed");
+        let signed_contract = Contract::Signed(deserialize_test_object(serialized));
+        storage
+            .update_contract(&signed_contract)
+            .expect("Error creating contract");
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Signed1");
+        let signed_contract = Contract::Signed(deserialize_test_object(serialized));
+        storage
+            .update_contract(&signed_contract)
+            .expect("Error creating contract");
+
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Confirmed");
+        let confirmed_contract = Contract::Confirmed(deserialize_test_object(serialized));
+        storage
+            .update_contract(&confirmed_contract)
+            .expect("Error creating contract");
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Confirmed1");
+        let confirmed_contract = Contract::Confirmed(deserialize_test_object(serialized));
+        storage
+            .update_contract(&confirmed_contract)
+            .expect("Error creating contract");
+
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/PreClosed");
+        let preclosed_contract = Contract::PreClosed(deserialize_test_object(serialized));
+        storage
+            .update_contract(&preclosed_contract)
+            .expect("Error creating contract");
+    }
+
+    fn insert_offered_and_signed_channels(storage: &PostgresStorageProvider) {
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+        let offered_contract = deserialize_test_object(serialized);
+        let serialized =
+            include_bytes!("../../dlc-sled-storage-provider/test_files/OfferedChannel");
+        let offered_channel = deserialize_test_object(serialized);
+        storage
+            .upsert_channel(
+                Channel::Offered(offered_channel),
+                Some(Contract::Offered(offered_contract)),
+            )
+            .expect("Error creating contract");
+
+        let serialized =
+            include_bytes!("../../dlc-sled-storage-provider/test_files/SignedChannelEstablished");
+        let signed_channel = Channel::Signed(deserialize_test_object(serialized));
+        storage
+            .upsert_channel(signed_channel, None)
+            .expect("Error creating contract");
+
+        let serialized =
+            include_bytes!("../../dlc-sled-storage-provider/test_files/SignedChannelSettled");
+        let signed_channel = Channel::Signed(deserialize_test_object(serialized));
+        storage
+            .upsert_channel(signed_channel, None)
+            .expect("Error creating contract");
+    }
+
+    postgres_test!(
+        schema_is_at_latest_version,
+        |storage: PostgresStorageProvider| {
+            assert_eq!(
+                MIGRATIONS.len() as u32,
+                storage
+                    .get_schema_version()
+                    .expect("to be able to read the schema version")
+            );
+        }
+    );
+
+    postgres_test!(
+        offer_hash_is_upserted,
+        |storage: PostgresStorageProvider| {
+            assert_eq!(
+                None,
+                storage.get_contract_id_by_offer_hash(&[1u8; 32]).unwrap()
+            );
+
+            storage.upsert_offer_hash(&[1u8; 32], &[2u8; 32]).unwrap();
+            storage.upsert_offer_hash(&[1u8; 32], &[3u8; 32]).unwrap();
+
+            assert_eq!(
+                Some([3u8; 32]),
+                storage.get_contract_id_by_offer_hash(&[1u8; 32]).unwrap()
+            );
+        }
+    );
+
+    postgres_test!(
+        product_definition_is_upserted,
+        |storage: PostgresStorageProvider| {
+            assert_eq!(None, storage.get_product_definition("btcusd").unwrap());
+
+            let mut product = ProductDefinition {
+                name: "BTC-USD 28 Jun".to_string(),
+                underlying: "BTC-USD".to_string(),
+                strike_convention: "USD price".to_string(),
+                settlement_source: "Index".to_string(),
+            };
+            storage
+                .upsert_product_definition("btcusd", &product)
+                .unwrap();
+            product.name = "BTC-USD 29 Jun".to_string();
+            storage
+                .upsert_product_definition("btcusd", &product)
+                .unwrap();
+
+            assert_eq!(
+                Some(product),
+                storage.get_product_definition("btcusd").unwrap()
+            );
+        }
+    );
+
+    postgres_test!(
+        peer_last_seen_is_updated,
+        |storage: PostgresStorageProvider| {
+            let peer_id = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+                .parse()
+                .unwrap();
+            assert_eq!(None, storage.get_peer_last_seen(&peer_id).unwrap());
+
+            storage.update_peer_last_seen(&peer_id, 10).unwrap();
+            storage.update_peer_last_seen(&peer_id, 20).unwrap();
+
+            assert_eq!(Some(20), storage.get_peer_last_seen(&peer_id).unwrap());
+        }
+    );
+
+    postgres_test!(
+        contract_history_is_appended,
+        |storage: PostgresStorageProvider| {
+            let events = vec![
+                ContractEvent {
+                    event_type: ContractEventType::OfferReceived,
+                    timestamp: 10,
+                    trigger: "OfferDlc".to_string(),
+                },
+                ContractEvent {
+                    event_type: ContractEventType::Accepted,
+                    timestamp: 20,
+                    trigger: "accept_contract_offer".to_string(),
+                },
+            ];
+            assert!(storage.get_contract_history(&[1u8; 32]).unwrap().is_empty());
+
+            for event in &events {
+                storage.append_contract_event(&[1u8; 32], event).unwrap();
+            }
+            storage
+                .append_contract_event(&[2u8; 32], &events[0])
+                .unwrap();
+
+            assert_eq!(events, storage.get_contract_history(&[1u8; 32]).unwrap());
+            assert_eq!(1, storage.get_contract_history(&[2u8; 32]).unwrap().len());
+        }
+    );
+
+    postgres_test!(
+        channel_history_is_appended,
+        |storage: PostgresStorageProvider| {
+            let events = vec![
+                ChannelEvent {
+                    state: "offered".to_string(),
+                    timestamp: 10,
+                    trigger: "OfferChannel".to_string(),
+                },
+                ChannelEvent {
+                    state: "accepted".to_string(),
+                    timestamp: 20,
+                    trigger: "accept_channel".to_string(),
+                },
+            ];
+            assert!(storage.get_channel_history(&[1u8; 32]).unwrap().is_empty());
+
+            for event in &events {
+                storage.append_channel_event(&[1u8; 32], event).unwrap();
+            }
+            storage
+                .append_channel_event(&[2u8; 32], &events[0])
+                .unwrap();
+
+            assert_eq!(events, storage.get_channel_history(&[1u8; 32]).unwrap());
+            assert_eq!(1, storage.get_channel_history(&[2u8; 32]).unwrap().len());
+        }
+    );
+
+    postgres_test!(
+        batch_events_are_not_written_when_batch_fails,
+        |storage: PostgresStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let contract: OfferedContract = deserialize_test_object(serialized);
+            let event = ContractEvent {
+                event_type: ContractEventType::OfferReceived,
+                timestamp: 10,
+                trigger: "OfferDlc".to_string(),
+            };
+            let batch = StorageBatch::new()
+                .with_new_contract(contract.clone())
+                .with_contract_event(contract.id, event.clone());
+
+            storage.write_batch(batch.clone()).unwrap();
+            match storage.write_batch(batch) {
+                Err(Error::AlreadyExists(_)) => {}
+                res => panic!("Expected an already exists error, got {:?}", res.err()),
+            }
+
+            assert_eq!(
+                vec![event],
+                storage.get_contract_history(&contract.id).unwrap()
+            );
+        }
+    );
+
+    postgres_test!(
+        concurrent_updates_are_serialized,
+        |storage: PostgresStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let offered_contract: OfferedContract = deserialize_test_object(serialized);
+            storage
+                .create_contract(&offered_contract)
+                .expect("Error creating contract");
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Accepted");
+            let accepted_contract = Contract::Accepted(deserialize_test_object(serialized));
+
+            let storage = std::sync::Arc::new(storage);
+            let handles = (0..2)
+                .map(|_| {
+                    let storage = storage.clone();
+                    let contract = accepted_contract.clone();
+                    std::thread::spawn(move || storage.update_contract(&contract))
+                })
+                .collect::<Vec<_>>();
+            for handle in handles {
+                handle.join().unwrap().expect("Error updating contract");
+            }
+
+            assert_eq!(
+                1,
+                storage
+                    .get_contracts()
+                    .expect("Error retrieving contracts")
+                    .len()
+            );
+        }
+    );
+
+    postgres_test!(
+        create_contract_can_be_retrieved,
+        |storage: PostgresStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let contract = deserialize_test_object(serialized);
+
+            storage
+                .create_contract(&contract)
+                .expect("Error creating contract");
+
+            let retrieved = storage
+                .get_contract(&contract.id)
+                .expect("Error retrieving contract.");
+
+            if let Some(Contract::Offered(retrieved_offer)) = retrieved {
+                assert_eq!(serialized[..], retrieved_offer.serialize().unwrap()[..]);
+            } else {
+                unreachable!();
+            }
+        }
+    );
+
+    postgres_test!(
+        create_contract_fails_if_contract_exists,
+        |storage: PostgresStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let contract: OfferedContract = deserialize_test_object(serialized);
+
+            storage
+                .create_contract(&contract)
+                .expect("Error creating contract");
+
+            assert!(matches!(
+                storage.create_contract(&contract),
+                Err(Error::AlreadyExists(_))
+            ));
+            storage
+                .replace_contract(&contract)
+                .expect("Error replacing contract");
+            assert_eq!(
+                1,
+                storage
+                    .get_contract_offers()
+                    .expect("Error retrieving contracts")
+                    .len()
+            );
+        }
+    );
+
+    postgres_test!(
+        update_contract_is_updated,
+        |storage: PostgresStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let offered_contract = deserialize_test_object(serialized);
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Accepted");
+            let accepted_contract = Contract::Accepted(deserialize_test_object(serialized));
+
+            storage
+                .create_contract(&offered_contract)
+                .expect("Error creating contract");
+
+            storage
+                .update_contract(&accepted_contract)
+                .expect("Error updating contract.");
+            let retrieved = storage
+                .get_contract(&accepted_contract.get_id())
+                .expect("Error retrieving contract.");
+
+            if let Some(Contract::Accepted(_)) = retrieved {
+            } else {
+                unreachable!();
+            }
+        }
+    );
+
+    postgres_test!(
+        delete_contract_is_deleted,
+        |storage: PostgresStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let contract = deserialize_test_object(serialized);
+            storage
+                .create_contract(&contract)
+                .expect("Error creating contract");
+
+            storage
+                .delete_contract(&contract.id)
+                .expect("Error deleting contract");
+
+            assert!(storage
+                .get_contract(&contract.id)
+                .expect("Error querying contract")
+                .is_none());
+        }
+    );
+
+    postgres_test!(
+        get_contracts_by_state,
+        |storage: PostgresStorageProvider| {
+            insert_offered_signed_and_confirmed(&storage);
+
+            assert_eq!(
+                1,
+                storage
+                    .get_contract_offers()
+                    .expect("Error retrieving offered contracts")
+                    .len()
+            );
+            assert_eq!(
+                2,
+                storage
+                    .get_signed_contracts()
+                    .expect("Error retrieving signed contracts")
+                    .len()
+            );
+            assert_eq!(
+                2,
+                storage
+                    .get_confirmed_contracts()
+                    .expect("Error retrieving confirmed contracts")
+                    .len()
+            );
+            assert_eq!(
+                1,
+                storage
+                    .get_preclosed_contracts()
+                    .expect("Error retrieving preclosed contracts")
+                    .len()
+            );
+            assert_eq!(
+                6,
+                storage
+                    .get_contracts()
+                    .expect("Error retrieving contracts")
+                    .len()
+            );
+        }
+    );
+
+    postgres_test!(get_channels_by_state, |storage: PostgresStorageProvider| {
+        insert_offered_and_signed_channels(&storage);
+
+        assert_eq!(
+            1,
+            storage
+                .get_offered_channels()
+                .expect("Error retrieving offered channels")
+                .len()
+        );
+        assert_eq!(
+            2,
+            storage
+                .get_signed_channels(None)
+                .expect("Error retrieving signed channels")
+                .len()
+        );
+
+        let signed_channels = storage
+            .get_signed_channels(Some(SignedChannelStateType::Established))
+            .expect("Error retrieving established channels");
+        assert_eq!(1, signed_channels.len());
+        if let dlc_manager::channel::signed_channel::SignedChannelState::Established { .. } =
+            &signed_channels[0].state
+        {
+        } else {
+            panic!(
+                "Expected established state got {:?}",
+                &signed_channels[0].state
+            );
+        }
+    });
+
+    postgres_test!(
+        get_channels_by_counterparty_and_contract_id,
+        |storage: PostgresStorageProvider| {
+            insert_offered_and_signed_channels(&storage);
+            let serialized = include_bytes!(
+                "../../dlc-sled-storage-provider/test_files/SignedChannelEstablished"
+            );
+            let signed_channel: SignedChannel = deserialize_test_object(serialized);
+
+            let channels = storage
+                .get_channels_by_counterparty(&signed_channel.counter_party)
+                .expect("Error retrieving channels");
+            assert!(channels
+                .iter()
+                .any(|c| c.get_id() == signed_channel.channel_id));
+            assert!(channels
+                .iter()
+                .all(|c| c.get_counter_party_id() == signed_channel.counter_party));
+
+            let channel = storage
+                .get_channel_by_contract_id(&signed_channel.get_contract_id().unwrap())
+                .expect("Error retrieving channel")
+                .expect("to find the channel");
+            assert_eq!(signed_channel.channel_id, channel.get_id());
+        }
+    );
+
+    postgres_test!(
+        delete_channel_is_not_returned,
+        |storage: PostgresStorageProvider| {
+            insert_offered_and_signed_channels(&storage);
+
+            let serialized =
+                include_bytes!("../../dlc-sled-storage-provider/test_files/AcceptedChannel");
+            let accepted_channel: AcceptedChannel = deserialize_test_object(serialized);
+            let channel_id = accepted_channel.channel_id;
+            storage
+                .upsert_channel(Channel::Accepted(accepted_channel), None)
+                .expect("Error creating channel");
+
+            storage
+                .get_channel(&channel_id)
+                .expect("error retrieving previously inserted channel.")
+                .expect("to have found the previously inserted channel.");
+
+            storage
+                .delete_channel(&channel_id)
+                .expect("to be able to delete the channel");
+
+            assert!(storage
+                .get_channel(&channel_id)
+                .expect("error getting channel.")
+                .is_none());
+        }
+    );
+
+    postgres_test!(
+        write_batch_persists_all_records,
+        |storage: PostgresStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let offered_contract: OfferedContract = deserialize_test_object(serialized);
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Accepted");
+            let accepted_contract: AcceptedContract = deserialize_test_object(serialized);
+            let serialized =
+                include_bytes!("../../dlc-sled-storage-provider/test_files/AcceptedChannel");
+            let accepted_channel: AcceptedChannel = deserialize_test_object(serialized);
+            let contract_id = accepted_contract.get_contract_id();
+            let temporary_contract_id = accepted_contract.offered_contract.id;
+            let channel_id = accepted_channel.channel_id;
+            let chain_monitor = ChainMonitor::new(123);
+
+            storage
+                .create_contract(&offered_contract)
+                .expect("Error creating contract");
+
+            storage
+                .write_batch(
+                    StorageBatch::new()
+                        .with_contract(Contract::Accepted(accepted_contract))
+                        .with_channel(Channel::Accepted(accepted_channel))
+                        .with_chain_monitor(chain_monitor.clone()),
+                )
+                .expect("to be able to write the batch");
+
+            assert!(storage
+                .get_contract(&temporary_contract_id)
+                .expect("error retrieving contract")
+                .is_none());
+            if let Some(Contract::Accepted(_)) = storage
+                .get_contract(&contract_id)
+                .expect("error retrieving contract")
+            {
+            } else {
+                panic!("Expected accepted contract");
+            }
+            assert!(storage
+                .get_channel(&channel_id)
+                .expect("error retrieving channel")
+                .is_some());
+            assert_eq!(
+                chain_monitor,
+                storage
+                    .get_chain_monitor()
+                    .expect("to be able to retrieve the chain monitor.")
+                    .expect("to have a persisted chain monitor.")
+            );
+        }
+    );
+
+    postgres_test!(
+        persist_chain_monitor_test,
+        |storage: PostgresStorageProvider| {
+            let chain_monitor = ChainMonitor::new(123);
+
+            storage
+                .persist_chain_monitor(&chain_monitor)
+                .expect("to be able to persist the chain monitor.");
+
+            let retrieved = storage
+                .get_chain_monitor()
+                .expect("to be able to retrieve the chain monitor.")
+                .expect("to have a persisted chain monitor.");
+
+            assert_eq!(chain_monitor, retrieved);
+        }
+    );
+}
@@ -11,142 +11,229 @@
 #![deny(unused_imports)]
 #![deny(missing_docs)]
 
+extern crate bitcoin;
+extern crate chacha20poly1305;
 extern crate dlc_manager;
+extern crate secp256k1_zkp;
 extern crate sled;
 
+use bitcoin::hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
 #[cfg(feature = "wallet")]
 use bitcoin::{address::NetworkUnchecked, Address, Txid};
-use dlc_manager::chain_monitor::ChainMonitor;
+use chacha20poly1305::aead::{Aead, AeadCore, AeadInPlace, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use dlc_manager::chain_monitor::{ChainMonitor, HeaderCache};
 use dlc_manager::channel::accepted_channel::AcceptedChannel;
 use dlc_manager::channel::offered_channel::OfferedChannel;
+use dlc_manager::channel::punishment::PunishmentData;
 use dlc_manager::channel::signed_channel::{SignedChannel, SignedChannelStateType};
-use dlc_manager::channel::{Channel, FailedAccept, FailedSign};
+use dlc_manager::channel::watchtower::{JusticeBlob, LOCATOR_LEN};
+use dlc_manager::channel::{Channel, ChannelEvent, FailedAccept, FailedSign};
 use dlc_manager::contract::accepted_contract::AcceptedContract;
 use dlc_manager::contract::offered_contract::OfferedContract;
 use dlc_manager::contract::ser::Serializable;
 use dlc_manager::contract::signed_contract::SignedContract;
 use dlc_manager::contract::{
-    ClosedContract, Contract, FailedAcceptContract, FailedSignContract, PreClosedContract,
+    ClosedContract, CollaborativelyClosedContract, Contract, ContractEvent, FailedAcceptContract,
+    FailedSignContract, PreClosedContract,
 };
+use dlc_manager::product_catalog::ProductDefinition;
+use dlc_manager::storage_prefix::{ChannelPrefix, ContractPrefix, SignedChannelPrefix};
 #[cfg(feature = "wallet")]
 use dlc_manager::Utxo;
-use dlc_manager::{error::Error, ContractId, Storage};
+use dlc_manager::{error::Error, ChannelId, ContractId, Storage, StorageBatch, StorageSnapshot};
 #[cfg(feature = "wallet")]
 use lightning::util::ser::{Readable, Writeable};
+use secp256k1_zkp::PublicKey;
 #[cfg(feature = "wallet")]
-use secp256k1_zkp::SecretKey;
+use secp256k1_zkp::{constants::SECRET_KEY_SIZE, SecretKey};
 #[cfg(feature = "wallet")]
 use simple_wallet::WalletStorage;
-use sled::transaction::{ConflictableTransactionResult, UnabortableTransactionError};
+use sled::transaction::{
+    ConflictableTransactionError, ConflictableTransactionResult, TransactionError,
+    UnabortableTransactionError,
+};
 use sled::{Db, Transactional, Tree};
+use std::borrow::Cow;
 use std::convert::TryInto;
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 const CONTRACT_TREE: u8 = 1;
 const CHANNEL_TREE: u8 = 2;
 const CHAIN_MONITOR_TREE: u8 = 3;
 const CHAIN_MONITOR_KEY: u8 = 4;
+const HEADER_CACHE_KEY: u8 = 5;
 #[cfg(feature = "wallet")]
 const UTXO_TREE: u8 = 6;
 #[cfg(feature = "wallet")]
 const KEY_PAIR_TREE: u8 = 7;
 #[cfg(feature = "wallet")]
 const ADDRESS_TREE: u8 = 8;
+const COUNTERPARTY_INDEX_TREE: u8 = 9;
+const ARCHIVE_TREE: u8 = 10;
+const CLOSED_AT_TREE: u8 = 11;
+const JOURNAL_TREE: u8 = 12;
+const PEER_LAST_SEEN_TREE: u8 = 13;
+const CHANNEL_EVENT_TREE: u8 = 14;
+const CONTRACT_EVENT_TREE: u8 = 15;
+const OFFER_HASH_TREE: u8 = 16;
+const CHANNEL_INDEX_TREE: u8 = 17;
+const PRODUCT_TREE: u8 = 18;
+const PUNISHMENT_TREE: u8 = 19;
+const METADATA_TREE: u8 = 20;
+const JUSTICE_BLOB_TREE: u8 = 21;
+const SCHEMA_VERSION_KEY: [u8; 1] = [0];
+/// Key present in the counter party and channel index trees once they contain
+/// an entry for every stored contract or channel. Databases created before the
+/// indexes were introduced are indexed on first use.
+const COUNTERPARTY_INDEX_READY_KEY: [u8; 1] = [0];
+/// Prefix of the keys of the channel index tree mapping a counter party and a
+/// channel id to an empty value.
+const CHANNEL_COUNTERPARTY_PREFIX: u8 = 1;
+/// Prefix of the keys of the channel index tree mapping a contract id to the
+/// id of the channel it belongs to.
+const CHANNEL_CONTRACT_PREFIX: u8 = 2;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const BACKUP_MAGIC: [u8; 4] = *b"DLCB";
+/// Version of the format produced by [`SledStorageProvider::export_backup`].
+pub const BACKUP_VERSION: u16 = 1;
+/// Latest version of the layout of the database, see
+/// [`SledStorageProvider::migrate`]. Databases that never went through a
+/// migration have version 0, in which the counter party and channel indexes
+/// are built on first use. From version 1, the indexes are always present.
+pub const SCHEMA_VERSION: u16 = 1;
+const BACKUP_TREES: [u8; 4] = [
+    CONTRACT_TREE,
+    CHANNEL_TREE,
+    CHAIN_MONITOR_TREE,
+    ARCHIVE_TREE,
+];
 
 /// Implementation of Storage interface using the sled DB backend.
 pub struct SledStorageProvider {
-    db: Db,
+    db: RwLock<Db>,
+    path: Option<PathBuf>,
+    tree_prefix: Vec<u8>,
+    size_limit: Option<u64>,
+    cipher: Option<RecordCipher>,
+    prune_policy: PrunePolicy,
+    journal_depth: usize,
+    metrics: Option<Arc<dyn StorageMetrics>>,
+    flush_every_write: bool,
+    skip_corrupted_records: bool,
+    // Held in shared mode by writes to the contract, channel and chain monitor
+    // trees, and exclusively while reading a snapshot of them.
+    snapshot_lock: RwLock<()>,
 }
 
-macro_rules! convertible_enum {
-    (enum $name:ident {
-        $($vname:ident $(= $val:expr)?,)*;
-        $($tname:ident $(= $tval:expr)?,)*
-    }, $input:ident) => {
-        #[derive(Debug)]
-        enum $name {
-            $($vname $(= $val)?,)*
-            $($tname $(= $tval)?,)*
-        }
+/// Encrypts the records of an encrypted [`SledStorageProvider`] and hides the
+/// sensitive parts of their keys.
+struct RecordCipher {
+    cipher: ChaCha20Poly1305,
+    // Derived from the encryption key to compute the keyed hashes replacing
+    // counter party node ids, addresses, outpoints and key identifiers in
+    // record keys.
+    index_key: [u8; 32],
+}
 
-        impl From<$name> for u8 {
-            fn from(prefix: $name) -> u8 {
-                prefix as u8
-            }
+impl RecordCipher {
+    fn new(key: &[u8; 32]) -> Self {
+        let mut engine = HmacEngine::<sha256::Hash>::new(key);
+        engine.input(b"dlc-sled-storage-provider/index-key");
+        RecordCipher {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+            index_key: Hmac::<sha256::Hash>::from_engine(engine).to_byte_array(),
         }
+    }
+}
+
+/// The records stored in a tree of a [`SledStorageProvider`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeSize {
+    /// The name of the tree.
+    pub name: String,
+    /// The number of records in the tree.
+    pub entries: usize,
+    /// The total size in bytes of the keys and values of the records.
+    pub size: u64,
+}
 
-        impl std::convert::TryFrom<u8> for $name {
-            type Error = Error;
+/// A record that could not be decrypted or deserialized, as returned by
+/// [`SledStorageProvider::get_corrupted_records`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorruptedRecord {
+    /// The name of the tree containing the record.
+    pub tree_name: String,
+    /// The key of the record.
+    pub key: Vec<u8>,
+    /// The error encountered when reading the record.
+    pub error: String,
+}
 
-            fn try_from(v: u8) -> Result<Self, Self::Error> {
-                match v {
-                    $(x if x == u8::from($name::$vname) => Ok($name::$vname),)*
-                    $(x if x == u8::from($name::$tname) => Ok($name::$tname),)*
-                    _ => Err(Error::StorageError("Unknown prefix".to_string())),
-                }
-            }
-        }
+/// Receives measurements of the operations performed by a
+/// [`SledStorageProvider`], for example to export them as Prometheus metrics.
+pub trait StorageMetrics: Send + Sync {
+    /// Called when a [`Storage`] operation completes, with the name of the
+    /// called method, the time it took, the total size in bytes of the records
+    /// read or written as stored in the database, and whether it succeeded.
+    fn on_operation(&self, operation: &str, latency: Duration, record_size: usize, success: bool);
+}
 
-        impl $name {
-            fn get_prefix(input: &$input) -> u8 {
-                let prefix = match input {
-                    $($input::$vname(_) => $name::$vname,)*
-                    $($input::$tname{..} => $name::$tname,)*
-                };
-                prefix.into()
-            }
-        }
-    }
-}
-
-convertible_enum!(
-    enum ContractPrefix {
-        Offered = 1,
-        Accepted,
-        Signed,
-        Confirmed,
-        PreClosed,
-        Closed,
-        FailedAccept,
-        FailedSign,
-        Refunded,
-        Rejected,;
-    },
-    Contract
-);
-
-convertible_enum!(
-    enum ChannelPrefix {
-        Offered = 100,
-        Accepted,
-        Signed,
-        FailedAccept,
-        FailedSign,
-        Cancelled,;
-    },
-    Channel
-);
-
-convertible_enum!(
-    enum SignedChannelPrefix {;
-        Established = 1,
-        SettledOffered,
-        SettledReceived,
-        SettledAccepted,
-        SettledConfirmed,
-        Settled,
-        Closing,
-        Closed,
-        CounterClosed,
-        ClosedPunished,
-        CollaborativeCloseOffered,
-        CollaborativelyClosed,
-        RenewAccepted,
-        RenewOffered,
-        RenewConfirmed,
-    },
-    SignedChannelStateType
-);
+/// What happens to the contracts removed by
+/// [`Storage::prune_closed_contracts`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrunePolicy {
+    /// Contracts are moved to an archive tree. They are not returned when
+    /// listing contracts anymore but can still be retrieved by id or using
+    /// [`SledStorageProvider::get_archived_contracts`].
+    Archive,
+    /// Contracts are permanently deleted.
+    Delete,
+}
+
+fn get_tree_name(tree_id: &[u8]) -> String {
+    let name = match tree_id {
+        [CONTRACT_TREE] => "contracts",
+        [CHANNEL_TREE] => "channels",
+        [CHAIN_MONITOR_TREE] => "chain_monitor",
+        #[cfg(feature = "wallet")]
+        [UTXO_TREE] => "utxos",
+        #[cfg(feature = "wallet")]
+        [KEY_PAIR_TREE] => "key_pairs",
+        #[cfg(feature = "wallet")]
+        [ADDRESS_TREE] => "addresses",
+        [COUNTERPARTY_INDEX_TREE] => "counterparty_index",
+        [ARCHIVE_TREE] => "archive",
+        [CLOSED_AT_TREE] => "closed_at",
+        [JOURNAL_TREE] => "journal",
+        [PEER_LAST_SEEN_TREE] => "peer_last_seen",
+        [CHANNEL_EVENT_TREE] => "channel_events",
+        [CONTRACT_EVENT_TREE] => "contract_events",
+        [OFFER_HASH_TREE] => "offer_hashes",
+        [CHANNEL_INDEX_TREE] => "channel_index",
+        [PRODUCT_TREE] => "products",
+        [PUNISHMENT_TREE] => "punishments",
+        [METADATA_TREE] => "metadata",
+        [JUSTICE_BLOB_TREE] => "justice_blobs",
+        _ => return String::from_utf8_lossy(tree_id).into_owned(),
+    };
+    name.to_string()
+}
+
+fn remove_dir_if_exists(path: &Path) -> Result<(), Error> {
+    match std::fs::remove_dir_all(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(to_storage_error(e)),
+        _ => Ok(()),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
 
 fn to_storage_error<T>(e: T) -> Error
 where
@@ -159,38 +246,879 @@ impl SledStorageProvider {
     /// Creates a new instance of a SledStorageProvider.
     pub fn new(path: &str) -> Result<Self, sled::Error> {
         Ok(SledStorageProvider {
-            db: sled::open(path)?,
+            db: RwLock::new(sled::open(path)?),
+            path: Some(PathBuf::from(path)),
+            tree_prefix: Vec::new(),
+            size_limit: None,
+            cipher: None,
+            prune_policy: PrunePolicy::Archive,
+            journal_depth: 0,
+            metrics: None,
+            flush_every_write: false,
+            skip_corrupted_records: false,
+            snapshot_lock: RwLock::new(()),
         })
     }
 
-    fn get_data_with_prefix<T: Serializable>(
-        &self,
-        tree: &Tree,
-        prefix: &[u8],
-        consume: Option<u64>,
-    ) -> Result<Vec<T>, Error> {
-        let iter = tree.iter();
-        iter.values()
-            .filter_map(|res| {
-                let value = res.unwrap();
-                let mut cursor = Cursor::new(&value);
-                let mut pref = vec![0u8; prefix.len()];
-                cursor.read_exact(&mut pref).expect("Error reading prefix");
-                if pref == prefix {
-                    if let Some(c) = consume {
-                        cursor.set_position(cursor.position() + c);
+    /// Creates a new instance of a SledStorageProvider encrypting all stored
+    /// records (contracts, channels, chain monitor, wallet UTXOs and private
+    /// keys, ...) using ChaCha20-Poly1305 with the given key. A random nonce is
+    /// generated for each written record, and the name of its tree and its key
+    /// are authenticated with it so that records cannot be swapped. Counter
+    /// party node ids, addresses, UTXO outpoints and key identifiers are
+    /// replaced by keyed hashes in the keys of the records and indexes. Contract
+    /// and channel ids are still used as keys in clear.
+    pub fn new_encrypted(path: &str, key: [u8; 32]) -> Result<Self, sled::Error> {
+        Ok(SledStorageProvider {
+            db: RwLock::new(sled::open(path)?),
+            path: Some(PathBuf::from(path)),
+            tree_prefix: Vec::new(),
+            size_limit: None,
+            cipher: Some(RecordCipher::new(&key)),
+            prune_policy: PrunePolicy::Archive,
+            journal_depth: 0,
+            metrics: None,
+            flush_every_write: false,
+            skip_corrupted_records: false,
+            snapshot_lock: RwLock::new(()),
+        })
+    }
+
+    /// Creates a new instance of a SledStorageProvider storing its records in
+    /// the given database, in trees whose names are prefixed with the given
+    /// namespace. This allows multiple logical wallets to share a single
+    /// database, as sled does not allow opening the same files more than
+    /// once per process. Records stored under different namespaces are
+    /// independent from each other. A database shared between namespaces
+    /// cannot be compacted using [`SledStorageProvider::compact`].
+    pub fn with_namespace(db: Db, namespace: &str) -> Self {
+        let mut tree_prefix = namespace.as_bytes().to_vec();
+        tree_prefix.push(b'/');
+        SledStorageProvider {
+            db: RwLock::new(db),
+            path: None,
+            tree_prefix,
+            size_limit: None,
+            cipher: None,
+            prune_policy: PrunePolicy::Archive,
+            journal_depth: 0,
+            metrics: None,
+            flush_every_write: false,
+            skip_corrupted_records: false,
+            snapshot_lock: RwLock::new(()),
+        }
+    }
+
+    /// Sets what happens to the contracts removed when calling
+    /// [`Storage::prune_closed_contracts`]. Defaults to [`PrunePolicy::Archive`].
+    pub fn set_prune_policy(&mut self, policy: PrunePolicy) {
+        self.prune_policy = policy;
+    }
+
+    /// Enables journaling of contract updates, keeping for each contract up to
+    /// `depth` of its previous versions, which can be restored using
+    /// [`SledStorageProvider::rollback_contract`]. Only updates done through
+    /// [`Storage::update_contract`] and [`Storage::create_contract`] are
+    /// journaled. A depth of zero, the default, disables the journal.
+    pub fn set_journal_depth(&mut self, depth: usize) {
+        self.journal_depth = depth;
+    }
+
+    /// Sets the [`StorageMetrics`] notified of the contract, channel and chain
+    /// monitor operations performed through the [`Storage`] interface.
+    pub fn set_metrics(&mut self, metrics: Arc<dyn StorageMetrics>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Sets the size in bytes above which the database is compacted when
+    /// calling [`SledStorageProvider::compact_if_above_size_limit`]. `None`,
+    /// the default, disables size based compaction.
+    pub fn set_size_limit(&mut self, size_limit: Option<u64>) {
+        self.size_limit = size_limit;
+    }
+
+    /// Sets whether the database is flushed to disk after every contract,
+    /// channel and chain monitor write. By default, sled flushes its buffers
+    /// periodically in the background, and records are only guaranteed to be
+    /// durable after a call to [`Storage::flush`], which the manager makes
+    /// after storing signatures. Enabling this trades write throughput for
+    /// durability of every update.
+    pub fn set_flush_every_write(&mut self, flush_every_write: bool) {
+        self.flush_every_write = flush_every_write;
+    }
+
+    /// Sets whether records that cannot be decrypted or deserialized are
+    /// skipped when listing contracts or channels, instead of failing the
+    /// whole operation. Skipped records can be listed using
+    /// [`SledStorageProvider::get_corrupted_records`]. Disabled by default.
+    pub fn set_skip_corrupted_records(&mut self, skip: bool) {
+        self.skip_corrupted_records = skip;
+    }
+
+    /// Returns the size in bytes of the database files.
+    pub fn get_size_on_disk(&self) -> Result<u64, Error> {
+        self.db
+            .read()
+            .unwrap()
+            .size_on_disk()
+            .map_err(to_storage_error)
+    }
+
+    /// Returns the number of records and the total size of their keys and
+    /// values for each tree used by the storage. As sled shares its files
+    /// between trees, this is the space used by the live records of each tree
+    /// and does not account for the space that compaction can reclaim.
+    pub fn get_tree_sizes(&self) -> Result<Vec<TreeSize>, Error> {
+        let db = self.db.read().unwrap();
+        db.tree_names()
+            .into_iter()
+            .filter(|name| {
+                name.len() == self.tree_prefix.len() + 1 && name.starts_with(&self.tree_prefix)
+            })
+            .map(|name| {
+                let tree = db.open_tree(&name).map_err(to_storage_error)?;
+                let mut size = 0;
+                for entry in tree.iter() {
+                    let (key, value) = entry.map_err(to_storage_error)?;
+                    size += (key.len() + value.len()) as u64;
+                }
+                Ok(TreeSize {
+                    name: get_tree_name(&name[self.tree_prefix.len()..]),
+                    entries: tree.len(),
+                    size,
+                })
+            })
+            .collect()
+    }
+
+    /// Rewrites the database into new files containing only the live records,
+    /// reclaiming the space used by overwritten and removed ones. Operations
+    /// are blocked while the compaction is in progress, which takes time
+    /// proportional to the size of the database, so it should be performed
+    /// when the node is idle. Returns the size on disk after compaction.
+    pub fn compact(&self) -> Result<u64, Error> {
+        let path = self.path.as_ref().ok_or_else(|| {
+            Error::StorageError("Cannot compact a database shared between namespaces".to_string())
+        })?;
+        let mut db = self.db.write().unwrap();
+        db.flush().map_err(to_storage_error)?;
+
+        let mut compacted_path = path.clone().into_os_string();
+        compacted_path.push(".compact");
+        let compacted_path = PathBuf::from(compacted_path);
+        remove_dir_if_exists(&compacted_path)?;
+        {
+            let compacted = sled::open(&compacted_path).map_err(to_storage_error)?;
+            compacted.import(db.export());
+            compacted.flush().map_err(to_storage_error)?;
+        }
+
+        // Release the files of the current database before replacing them.
+        *db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .map_err(to_storage_error)?;
+        std::fs::remove_dir_all(path).map_err(to_storage_error)?;
+        std::fs::rename(&compacted_path, path).map_err(to_storage_error)?;
+        *db = sled::open(path).map_err(to_storage_error)?;
+
+        db.size_on_disk().map_err(to_storage_error)
+    }
+
+    /// Compacts the database if its size on disk exceeds the limit set using
+    /// [`SledStorageProvider::set_size_limit`]. Meant to be called on a
+    /// schedule, for example after each call to
+    /// [`dlc_manager::manager::Manager::periodic_check`]. Returns whether the
+    /// database was compacted.
+    pub fn compact_if_above_size_limit(&self) -> Result<bool, Error> {
+        match self.size_limit {
+            Some(limit) if self.get_size_on_disk()? > limit => {
+                self.compact()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn measure<T, F>(&self, operation: &'static str, f: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Result<(T, usize), Error>,
+    {
+        let start = Instant::now();
+        let res = f();
+        if let Some(metrics) = &self.metrics {
+            let record_size = res.as_ref().map_or(0, |(_, size)| *size);
+            metrics.on_operation(operation, start.elapsed(), record_size, res.is_ok());
+        }
+        res.map(|(value, _)| value)
+    }
+
+    fn write_guard(&self) -> RwLockReadGuard<'_, ()> {
+        self.snapshot_lock.read().unwrap()
+    }
+
+    fn flush_if_required(&self) -> Result<(), Error> {
+        if self.flush_every_write {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Reverts the last journaled update of the contract with the given id,
+    /// for example when a protocol step fails after the new state was
+    /// persisted. Returns the restored contract, or `None` if the reverted
+    /// update created the contract, in which case it is removed. Note that
+    /// reverting the acceptance of a contract restores the offered contract
+    /// under its temporary id.
+    pub fn rollback_contract(&self, contract_id: &ContractId) -> Result<Option<Contract>, Error> {
+        let _guard = self.write_guard();
+        let journal_tree = self.open_tree(&[JOURNAL_TREE])?;
+        let (journal_key, previous) = journal_tree
+            .scan_prefix(contract_id)
+            .next_back()
+            .transpose()
+            .map_err(to_storage_error)?
+            .ok_or_else(|| Error::StorageError("No journaled update for contract".to_string()))?;
+        let previous_contract = if previous.is_empty() {
+            None
+        } else {
+            Some(deserialize_contract(&self.decrypt(
+                JOURNAL_TREE,
+                &journal_key,
+                &previous,
+            )?)?)
+        };
+        let previous_serialized = match previous_contract.as_ref() {
+            Some(contract) => Some(self.encode_contract(contract)?),
+            None => None,
+        };
+        let current_index_key = match self
+            .contract_tree()?
+            .get(contract_id)
+            .map_err(to_storage_error)?
+        {
+            Some(current) => {
+                let current =
+                    deserialize_contract(&self.decrypt(CONTRACT_TREE, contract_id, &current)?)?;
+                Some(self.counterparty_index_key(&current.get_counter_party_id(), contract_id))
+            }
+            None => None,
+        };
+        let keep_closed_at = previous_contract.as_ref().map_or(false, is_prunable);
+
+        (
+            &self.contract_tree()?,
+            &self.counterparty_index_tree()?,
+            &self.open_tree(&[CLOSED_AT_TREE])?,
+            &journal_tree,
+        )
+            .transaction::<_, ()>(
+                |(contract_db, index_db, closed_at_db, journal_db)| -> ConflictableTransactionResult<(), UnabortableTransactionError> {
+                    contract_db.remove(contract_id)?;
+                    if let Some(index_key) = current_index_key.as_ref() {
+                        index_db.remove(index_key.as_slice())?;
+                    }
+                    if !keep_closed_at {
+                        closed_at_db.remove(contract_id)?;
                     }
-                    Some(Ok(T::deserialize(&mut cursor).ok()?))
+                    if let (Some(contract), Some(serialized)) =
+                        (previous_contract.as_ref(), previous_serialized.as_ref())
+                    {
+                        self.insert_contract(contract_db, index_db, closed_at_db, serialized.clone(), contract)?;
+                    }
+                    journal_db.remove(&journal_key)?;
+                    Ok(())
+                },
+            )
+            .map_err(to_storage_error)?;
+        Ok(previous_contract)
+    }
+
+    fn delete_contract_inner(&self, contract_id: &ContractId) -> Result<(), Error> {
+        let contract = match self.get_contract(contract_id)? {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+        let index_key = self.counterparty_index_key(&contract.get_counter_party_id(), contract_id);
+        let journal_keys = self.get_journal_keys(contract_id)?;
+        (
+            &self.contract_tree()?,
+            &self.counterparty_index_tree()?,
+            &self.open_tree(&[CLOSED_AT_TREE])?,
+            &self.open_tree(&[ARCHIVE_TREE])?,
+            &self.open_tree(&[JOURNAL_TREE])?,
+        )
+            .transaction::<_, ()>(
+                |(contract_db, index_db, closed_at_db, archive_db, journal_db)| -> ConflictableTransactionResult<(), UnabortableTransactionError> {
+                    contract_db.remove(contract_id)?;
+                    index_db.remove(index_key.as_slice())?;
+                    closed_at_db.remove(contract_id)?;
+                    archive_db.remove(contract_id)?;
+                    for key in &journal_keys {
+                        journal_db.remove(key)?;
+                    }
+                    Ok(())
+                },
+            )
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    /// Returns the given prefix followed by an id generated by the database,
+    /// so that the keys of successive records sharing the prefix are ordered.
+    fn sequenced_key(&self, prefix: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut key = prefix.to_vec();
+        key.extend_from_slice(
+            &self
+                .db
+                .read()
+                .unwrap()
+                .generate_id()
+                .map_err(to_storage_error)?
+                .to_be_bytes(),
+        );
+        Ok(key)
+    }
+
+    /// Writes the records of the given batch in a single transaction, failing
+    /// with [`Error::AlreadyExists`] if one of its new contracts is present.
+    fn write_batch_inner(&self, batch: StorageBatch) -> Result<usize, Error> {
+        let contracts = batch
+            .new_contracts
+            .into_iter()
+            .map(|c| (Contract::Offered(c), true))
+            .chain(batch.contracts.into_iter().map(|c| (c, false)))
+            .map(|(contract, create_only)| {
+                let serialized = self.encode_contract(&contract)?;
+                let journal_key = if self.journal_depth > 0 {
+                    Some(self.sequenced_key(&contract.get_id())?)
                 } else {
                     None
+                };
+                Ok((contract, create_only, serialized, journal_key))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let serialized_channels = batch
+            .channels
+            .iter()
+            .map(|c| self.encode_channel(c))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let contract_events = batch
+            .contract_events
+            .iter()
+            .map(|(id, event)| {
+                let key = self.sequenced_key(id)?;
+                let serialized = self.encrypt(
+                    CONTRACT_EVENT_TREE,
+                    &key,
+                    event.serialize().map_err(to_storage_error)?,
+                )?;
+                Ok((key, serialized))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let channel_events = batch
+            .channel_events
+            .iter()
+            .map(|(id, event)| {
+                let key = self.sequenced_key(id)?;
+                let serialized = self.encrypt(
+                    CHANNEL_EVENT_TREE,
+                    &key,
+                    event.serialize().map_err(to_storage_error)?,
+                )?;
+                Ok((key, serialized))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let serialized_monitor = match batch.chain_monitor.as_ref() {
+            Some(m) => Some(self.encode_record(
+                CHAIN_MONITOR_TREE,
+                &[CHAIN_MONITOR_KEY],
+                m.serialized_size(),
+                |writer| m.serialize_into(writer),
+            )?),
+            None => None,
+        };
+        let size = contracts
+            .iter()
+            .map(|(_, _, serialized, _)| serialized.len())
+            .chain(serialized_channels.iter().map(|x| x.len()))
+            .chain(contract_events.iter().map(|(_, x)| x.len()))
+            .chain(channel_events.iter().map(|(_, x)| x.len()))
+            .chain(serialized_monitor.iter().map(|x| x.len()))
+            .sum();
+        let channel_tree = self.channel_tree()?;
+        let contract_tree = self.contract_tree()?;
+        let chain_monitor_tree = self.open_tree(&[CHAIN_MONITOR_TREE])?;
+        let index_tree = self.counterparty_index_tree()?;
+        let closed_at_tree = self.open_tree(&[CLOSED_AT_TREE])?;
+        let channel_index_tree = self.channel_index_tree()?;
+        let journal_tree = self.open_tree(&[JOURNAL_TREE])?;
+        let contract_event_tree = self.open_tree(&[CONTRACT_EVENT_TREE])?;
+        let channel_event_tree = self.open_tree(&[CHANNEL_EVENT_TREE])?;
+        (
+            &channel_tree,
+            &contract_tree,
+            &chain_monitor_tree,
+            &index_tree,
+            &closed_at_tree,
+            &channel_index_tree,
+            &journal_tree,
+            &contract_event_tree,
+            &channel_event_tree,
+        )
+            .transaction(
+                |(
+                    channel_db,
+                    contract_db,
+                    chain_monitor_db,
+                    index_db,
+                    closed_at_db,
+                    channel_index_db,
+                    journal_db,
+                    contract_event_db,
+                    channel_event_db,
+                )|
+                 -> ConflictableTransactionResult<(), Error> {
+                    for (contract, create_only, serialized, journal_key) in &contracts {
+                        let contract_id = contract.get_id();
+                        if *create_only && contract_db.get(contract_id)?.is_some() {
+                            return Err(ConflictableTransactionError::Abort(Error::AlreadyExists(
+                                "Contract already exists".to_string(),
+                            )));
+                        }
+                        if let Some(journal_key) = journal_key.as_ref() {
+                            // An empty value records that the update created the contract.
+                            let previous = match contract_db.get(contract_id)? {
+                                Some(previous) => Some((contract_id, previous)),
+                                None => match contract {
+                                    Contract::Accepted(_) | Contract::Signed(_) => contract_db
+                                        .get(contract.get_temporary_id())?
+                                        .map(|p| (contract.get_temporary_id(), p)),
+                                    _ => None,
+                                },
+                            };
+                            let previous = match previous {
+                                Some((key, previous)) => self
+                                    .reencrypt(
+                                        &previous,
+                                        CONTRACT_TREE,
+                                        &key,
+                                        JOURNAL_TREE,
+                                        journal_key,
+                                    )
+                                    .map_err(ConflictableTransactionError::Abort)?,
+                                None => Vec::new(),
+                            };
+                            journal_db.insert(journal_key.as_slice(), previous)?;
+                        }
+                        self.insert_contract(
+                            contract_db,
+                            index_db,
+                            closed_at_db,
+                            serialized.clone(),
+                            contract,
+                        )?;
+                    }
+                    for (channel, serialized) in batch.channels.iter().zip(&serialized_channels) {
+                        self.insert_channel(
+                            channel_db,
+                            channel_index_db,
+                            serialized.clone(),
+                            channel,
+                        )?;
+                    }
+                    for (key, serialized) in &contract_events {
+                        contract_event_db.insert(key.as_slice(), serialized.as_slice())?;
+                    }
+                    for (key, serialized) in &channel_events {
+                        channel_event_db.insert(key.as_slice(), serialized.as_slice())?;
+                    }
+                    if let Some(serialized) = serialized_monitor.as_ref() {
+                        chain_monitor_db.insert(&[CHAIN_MONITOR_KEY], serialized.clone())?;
+                    }
+                    Ok(())
+                },
+            )
+            .map_err(|e| match e {
+                TransactionError::Abort(e) => e,
+                TransactionError::Storage(e) => to_storage_error(e),
+            })?;
+
+        if self.journal_depth > 0 {
+            for (contract, _, _, _) in &contracts {
+                let journal_keys = self.get_journal_keys(&contract.get_id())?;
+                for key in journal_keys
+                    .iter()
+                    .take(journal_keys.len().saturating_sub(self.journal_depth))
+                {
+                    journal_tree.remove(key).map_err(to_storage_error)?;
                 }
+            }
+        }
+        Ok(size)
+    }
+
+    fn get_journal_keys(&self, contract_id: &ContractId) -> Result<Vec<sled::IVec>, Error> {
+        self.open_tree(&[JOURNAL_TREE])?
+            .scan_prefix(contract_id)
+            .keys()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(to_storage_error)
+    }
+
+    /// Returns the contracts that were archived when pruning closed contracts.
+    pub fn get_archived_contracts(&self) -> Result<Vec<Contract>, Error> {
+        self.open_tree(&[ARCHIVE_TREE])?
+            .iter()
+            .map(|x| {
+                let (key, value) = x.map_err(to_storage_error)?;
+                deserialize_contract(&self.decrypt(ARCHIVE_TREE, &key, &value)?)
             })
             .collect()
     }
 
+    /// Returns the data authenticated with the record stored under the given
+    /// key of the given tree: the length prefixed name of the tree followed by
+    /// the key.
+    fn record_aad(&self, tree_id: u8, key: &[u8]) -> Vec<u8> {
+        let name_len = self.tree_prefix.len() + 1;
+        let mut aad = Vec::with_capacity(4 + name_len + key.len());
+        aad.extend_from_slice(&(name_len as u32).to_be_bytes());
+        aad.extend_from_slice(&self.tree_prefix);
+        aad.push(tree_id);
+        aad.extend_from_slice(key);
+        aad
+    }
+
+    /// Returns the key to use in the given tree in place of `key`, which is a
+    /// keyed hash of it when the storage is encrypted.
+    fn index_key(&self, tree_id: u8, key: &[u8]) -> Vec<u8> {
+        match &self.cipher {
+            Some(cipher) => {
+                let mut engine = HmacEngine::<sha256::Hash>::new(&cipher.index_key);
+                engine.input(&[tree_id]);
+                engine.input(key);
+                Hmac::<sha256::Hash>::from_engine(engine)
+                    .to_byte_array()
+                    .to_vec()
+            }
+            None => key.to_vec(),
+        }
+    }
+
+    fn encrypt(&self, tree_id: u8, key: &[u8], data: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match &self.cipher {
+            Some(cipher) => {
+                let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+                let aad = self.record_aad(tree_id, key);
+                let ciphertext = cipher
+                    .cipher
+                    .encrypt(
+                        &nonce,
+                        Payload {
+                            msg: &data,
+                            aad: &aad,
+                        },
+                    )
+                    .map_err(|_| Error::StorageError("Could not encrypt record".to_string()))?;
+                let mut res = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+                res.extend_from_slice(&nonce);
+                res.extend(ciphertext);
+                Ok(res)
+            }
+            None => Ok(data),
+        }
+    }
+
+    /// Builds the record stored under the given key of the given tree for an
+    /// object serialized in `serialized_size` bytes by `write`. The object is
+    /// written directly into the stored buffer and encrypted in place, so that
+    /// large contracts are not copied across intermediate buffers.
+    fn encode_record<F>(
+        &self,
+        tree_id: u8,
+        key: &[u8],
+        serialized_size: usize,
+        write: F,
+    ) -> Result<sled::IVec, Error>
+    where
+        F: FnOnce(&mut Vec<u8>) -> Result<(), ::std::io::Error>,
+    {
+        match &self.cipher {
+            Some(cipher) => {
+                let mut res = Vec::with_capacity(NONCE_LEN + serialized_size + TAG_LEN);
+                res.extend_from_slice(&[0u8; NONCE_LEN]);
+                write(&mut res)?;
+                let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+                let tag = cipher
+                    .cipher
+                    .encrypt_in_place_detached(
+                        &nonce,
+                        &self.record_aad(tree_id, key),
+                        &mut res[NONCE_LEN..],
+                    )
+                    .map_err(|_| Error::StorageError("Could not encrypt record".to_string()))?;
+                res[..NONCE_LEN].copy_from_slice(&nonce);
+                res.extend_from_slice(&tag);
+                Ok(res.into())
+            }
+            None => {
+                let mut res = Vec::with_capacity(serialized_size);
+                write(&mut res)?;
+                Ok(res.into())
+            }
+        }
+    }
+
+    fn encode_contract(&self, contract: &Contract) -> Result<sled::IVec, Error> {
+        self.encode_record(
+            CONTRACT_TREE,
+            &contract.get_id(),
+            contract_serialized_size(contract),
+            |writer| write_contract(contract, writer),
+        )
+    }
+
+    fn encode_channel(&self, channel: &Channel) -> Result<sled::IVec, Error> {
+        self.encode_record(
+            CHANNEL_TREE,
+            &channel.get_id(),
+            channel_serialized_size(channel),
+            |writer| write_channel(channel, writer),
+        )
+    }
+
+    /// Returns the plain text of the record stored under the given key of the
+    /// given tree, only copying it if it needs to be decrypted.
+    fn decrypt<'a>(&self, tree_id: u8, key: &[u8], data: &'a [u8]) -> Result<Cow<'a, [u8]>, Error> {
+        match &self.cipher {
+            Some(cipher) => {
+                if data.len() < NONCE_LEN {
+                    return Err(Error::StorageError("Invalid encrypted record".to_string()));
+                }
+                let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+                cipher
+                    .cipher
+                    .decrypt(
+                        Nonce::from_slice(nonce),
+                        Payload {
+                            msg: ciphertext,
+                            aad: &self.record_aad(tree_id, key),
+                        },
+                    )
+                    .map(Cow::Owned)
+                    .map_err(|_| Error::StorageError("Could not decrypt record".to_string()))
+            }
+            None => Ok(Cow::Borrowed(data)),
+        }
+    }
+
+    /// Re-encrypts a record read under `from_key` of the `from_tree` tree so
+    /// that it can be stored under `to_key` of the `to_tree` tree.
+    fn reencrypt(
+        &self,
+        data: &[u8],
+        from_tree: u8,
+        from_key: &[u8],
+        to_tree: u8,
+        to_key: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        match &self.cipher {
+            Some(_) => self.encrypt(
+                to_tree,
+                to_key,
+                self.decrypt(from_tree, from_key, data)?.into_owned(),
+            ),
+            None => Ok(data.to_vec()),
+        }
+    }
+
+    /// Returns the prefix of the keys of the index entries of the given counter
+    /// party, in which its node id is replaced by a keyed hash when the storage
+    /// is encrypted.
+    fn counterparty_prefix(&self, counter_party: &PublicKey) -> Vec<u8> {
+        self.index_key(COUNTERPARTY_INDEX_TREE, &counter_party.serialize())
+    }
+
+    fn counterparty_index_key(
+        &self,
+        counter_party: &PublicKey,
+        contract_id: &ContractId,
+    ) -> Vec<u8> {
+        let mut key = self.counterparty_prefix(counter_party);
+        key.extend_from_slice(contract_id);
+        key
+    }
+
+    fn channel_counterparty_index_key(
+        &self,
+        counter_party: &PublicKey,
+        channel_id: &ChannelId,
+    ) -> Vec<u8> {
+        let mut key = vec![CHANNEL_COUNTERPARTY_PREFIX];
+        key.extend_from_slice(&self.counterparty_prefix(counter_party));
+        key.extend_from_slice(channel_id);
+        key
+    }
+
+    /// Returns the entries of the channel index tree for the given channel.
+    fn channel_index_entries(&self, channel: &Channel) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut res = vec![(
+            self.channel_counterparty_index_key(&channel.get_counter_party_id(), &channel.get_id()),
+            Vec::new(),
+        )];
+        if let Some(contract_id) = channel.get_contract_id() {
+            res.push((
+                channel_contract_index_key(&contract_id),
+                channel.get_id().to_vec(),
+            ));
+        }
+        res
+    }
+
+    fn insert_contract(
+        &self,
+        db: &sled::transaction::TransactionalTree,
+        index_db: &sled::transaction::TransactionalTree,
+        closed_at_db: &sled::transaction::TransactionalTree,
+        serialized: sled::IVec,
+        contract: &Contract,
+    ) -> Result<Option<sled::IVec>, UnabortableTransactionError> {
+        if is_prunable(contract) && closed_at_db.get(contract.get_id())?.is_none() {
+            closed_at_db.insert(&contract.get_id(), unix_time_now().to_be_bytes().to_vec())?;
+        }
+
+        let counter_party = contract.get_counter_party_id();
+        match contract {
+            a @ Contract::Accepted(_) | a @ Contract::Signed(_) => {
+                db.remove(&a.get_temporary_id())?;
+                index_db
+                    .remove(self.counterparty_index_key(&counter_party, &a.get_temporary_id()))?;
+            }
+            _ => {}
+        };
+
+        index_db.insert(
+            self.counterparty_index_key(&counter_party, &contract.get_id()),
+            Vec::new(),
+        )?;
+        db.insert(&contract.get_id(), serialized)
+    }
+
+    fn insert_channel(
+        &self,
+        db: &sled::transaction::TransactionalTree,
+        index_db: &sled::transaction::TransactionalTree,
+        serialized: sled::IVec,
+        channel: &Channel,
+    ) -> Result<Option<sled::IVec>, UnabortableTransactionError> {
+        match channel {
+            a @ Channel::Accepted(_) | a @ Channel::Signed(_) => {
+                db.remove(&a.get_temporary_id())?;
+                index_db.remove(self.channel_counterparty_index_key(
+                    &a.get_counter_party_id(),
+                    &a.get_temporary_id(),
+                ))?;
+            }
+            _ => {}
+        };
+
+        for (key, value) in self.channel_index_entries(channel) {
+            index_db.insert(key, value)?;
+        }
+        db.insert(&channel.get_id(), serialized)
+    }
+
+    fn get_data_with_prefix<T: Serializable>(
+        &self,
+        tree_id: u8,
+        prefix: &[u8],
+        consume: Option<u64>,
+    ) -> Result<(Vec<T>, usize), Error> {
+        let mut size = 0;
+        let mut res = Vec::new();
+        for record in self.open_tree(&[tree_id])?.iter() {
+            let (key, value) = record.map_err(to_storage_error)?;
+            match self.read_record_with_prefix(tree_id, &key, &value, prefix, consume) {
+                Ok(Some(deserialized)) => {
+                    size += value.len();
+                    res.push(deserialized);
+                }
+                Ok(None) => {}
+                Err(_) if self.skip_corrupted_records => {}
+                Err(e) => {
+                    return Err(Error::StorageError(format!(
+                        "Corrupted record {}: {}",
+                        to_hex(&key),
+                        e
+                    )))
+                }
+            }
+        }
+        Ok((res, size))
+    }
+
+    /// Deserializes the given record if it starts with the given prefix,
+    /// skipping `consume` bytes after the prefix.
+    fn read_record_with_prefix<T: Serializable>(
+        &self,
+        tree_id: u8,
+        key: &[u8],
+        value: &[u8],
+        prefix: &[u8],
+        consume: Option<u64>,
+    ) -> Result<Option<T>, Error> {
+        let value = self.decrypt(tree_id, key, value)?;
+        let mut cursor = Cursor::new(&value);
+        let mut pref = vec![0u8; prefix.len()];
+        cursor.read_exact(&mut pref)?;
+        if pref != prefix {
+            return Ok(None);
+        }
+        if let Some(c) = consume {
+            cursor.set_position(cursor.position() + c);
+        }
+        T::deserialize(&mut cursor)
+            .map(Some)
+            .map_err(to_storage_error)
+    }
+
+    /// Returns the contracts, archived contracts and channels that cannot be
+    /// decrypted or deserialized, for example following a disk failure or a
+    /// downgrade to a version using an older serialization format. Listing
+    /// contracts or channels fails when encountering such records, unless
+    /// [`SledStorageProvider::set_skip_corrupted_records`] was enabled.
+    pub fn get_corrupted_records(&self) -> Result<Vec<CorruptedRecord>, Error> {
+        let mut res = Vec::new();
+        for tree_id in &[CONTRACT_TREE, ARCHIVE_TREE, CHANNEL_TREE] {
+            for record in self.open_tree(&[*tree_id])?.iter() {
+                let (key, value) = record.map_err(to_storage_error)?;
+                let check = self.decrypt(*tree_id, &key, &value).and_then(|value| {
+                    if *tree_id == CHANNEL_TREE {
+                        deserialize_channel(&value).map(|_| ())
+                    } else {
+                        deserialize_contract(&value).map(|_| ())
+                    }
+                });
+                if let Err(e) = check {
+                    res.push(CorruptedRecord {
+                        tree_name: get_tree_name(&[*tree_id]),
+                        key: key.to_vec(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(res)
+    }
+
     fn open_tree(&self, tree_id: &[u8; 1]) -> Result<Tree, Error> {
+        let mut name = self.tree_prefix.clone();
+        name.extend_from_slice(tree_id);
         self.db
-            .open_tree(tree_id)
+            .read()
+            .unwrap()
+            .open_tree(name)
             .map_err(|e| Error::StorageError(format!("Error opening contract tree: {}", e)))
     }
 
@@ -201,6 +1129,287 @@ impl SledStorageProvider {
     fn channel_tree(&self) -> Result<Tree, Error> {
         self.open_tree(&[CHANNEL_TREE])
     }
+
+    fn counterparty_index_tree(&self) -> Result<Tree, Error> {
+        let tree = self.open_tree(&[COUNTERPARTY_INDEX_TREE])?;
+        if !tree
+            .contains_key(COUNTERPARTY_INDEX_READY_KEY)
+            .map_err(to_storage_error)?
+        {
+            for entry in self.contract_tree()?.iter() {
+                let (key, value) = entry.map_err(to_storage_error)?;
+                let contract = deserialize_contract(&self.decrypt(CONTRACT_TREE, &key, &value)?)?;
+                tree.insert(
+                    self.counterparty_index_key(
+                        &contract.get_counter_party_id(),
+                        &contract.get_id(),
+                    ),
+                    Vec::new(),
+                )
+                .map_err(to_storage_error)?;
+            }
+            tree.insert(COUNTERPARTY_INDEX_READY_KEY, Vec::new())
+                .map_err(to_storage_error)?;
+        }
+        Ok(tree)
+    }
+
+    fn channel_index_tree(&self) -> Result<Tree, Error> {
+        let tree = self.open_tree(&[CHANNEL_INDEX_TREE])?;
+        if !tree
+            .contains_key(COUNTERPARTY_INDEX_READY_KEY)
+            .map_err(to_storage_error)?
+        {
+            for entry in self.channel_tree()?.iter() {
+                let (key, value) = entry.map_err(to_storage_error)?;
+                let channel = deserialize_channel(&self.decrypt(CHANNEL_TREE, &key, &value)?)?;
+                for (key, value) in self.channel_index_entries(&channel) {
+                    tree.insert(key, value).map_err(to_storage_error)?;
+                }
+            }
+            tree.insert(COUNTERPARTY_INDEX_READY_KEY, Vec::new())
+                .map_err(to_storage_error)?;
+        }
+        Ok(tree)
+    }
+
+    /// Returns the version of the layout of the database, see
+    /// [`SCHEMA_VERSION`].
+    pub fn get_schema_version(&self) -> Result<u16, Error> {
+        match self
+            .open_tree(&[METADATA_TREE])?
+            .get(SCHEMA_VERSION_KEY)
+            .map_err(to_storage_error)?
+        {
+            Some(version) => {
+                Ok(u16::from_be_bytes(version.as_ref().try_into().map_err(
+                    |_| Error::StorageError("Invalid schema version".to_string()),
+                )?))
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Upgrades or downgrades the layout of the database to the given
+    /// version, which must not be greater than [`SCHEMA_VERSION`]. Upgrading
+    /// to version 1 builds the counter party and channel indexes, while
+    /// downgrading to version 0 removes them so that they are rebuilt from
+    /// the stored records on first use.
+    pub fn migrate(&self, target_version: u16) -> Result<(), Error> {
+        if target_version > SCHEMA_VERSION {
+            return Err(Error::InvalidParameters(format!(
+                "Unsupported schema version {}, latest is {}",
+                target_version, SCHEMA_VERSION
+            )));
+        }
+        let _guard = self.write_guard();
+        let metadata_tree = self.open_tree(&[METADATA_TREE])?;
+        if target_version == 0 {
+            let db = self.db.read().unwrap();
+            for tree_id in [COUNTERPARTY_INDEX_TREE, CHANNEL_INDEX_TREE] {
+                let mut name = self.tree_prefix.clone();
+                name.push(tree_id);
+                db.drop_tree(name).map_err(to_storage_error)?;
+            }
+            metadata_tree
+                .remove(SCHEMA_VERSION_KEY)
+                .map_err(to_storage_error)?;
+        } else {
+            self.counterparty_index_tree()?;
+            self.channel_index_tree()?;
+            metadata_tree
+                .insert(SCHEMA_VERSION_KEY, target_version.to_be_bytes().to_vec())
+                .map_err(to_storage_error)?;
+        }
+        self.flush()
+    }
+
+    /// Writes a backup of all the contracts (including archived ones), channels
+    /// and chain monitor to the given writer. The backup has the following format, all integers being
+    /// big endian:
+    /// * the magic bytes `DLCB` and the [`BACKUP_VERSION`] as a u16,
+    /// * the number of trees as a u8,
+    /// * for each tree, its id as a u8 and its number of entries as a u64,
+    /// * for each entry, its key and value, each prefixed with its length as a u32.
+    ///
+    /// Values are written decrypted so that a backup can be restored into a
+    /// storage using a different encryption key (or none), and should
+    /// therefore be stored securely.
+    pub fn export_backup<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_all(&BACKUP_MAGIC).map_err(to_storage_error)?;
+        writer
+            .write_all(&BACKUP_VERSION.to_be_bytes())
+            .map_err(to_storage_error)?;
+        writer
+            .write_all(&[BACKUP_TREES.len() as u8])
+            .map_err(to_storage_error)?;
+        for tree_id in BACKUP_TREES {
+            let entries = self
+                .open_tree(&[tree_id])?
+                .iter()
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(to_storage_error)?;
+            writer.write_all(&[tree_id]).map_err(to_storage_error)?;
+            writer
+                .write_all(&(entries.len() as u64).to_be_bytes())
+                .map_err(to_storage_error)?;
+            for (key, value) in entries {
+                write_backup_bytes(writer, &key)?;
+                write_backup_bytes(writer, &self.decrypt(tree_id, &key, &value)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Restores a backup produced by [`SledStorageProvider::export_backup`],
+    /// replacing all the contracts, channels and chain monitor currently
+    /// stored. The closing time of restored closed contracts is reset to the
+    /// next call to [`Storage::prune_closed_contracts`] and the journal of
+    /// contract updates is cleared. Every record is decoded before anything is written and the
+    /// restoration is done atomically, so that the storage is left untouched
+    /// if the backup is invalid.
+    pub fn import_backup<R: Read>(&self, reader: &mut R) -> Result<(), Error> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic).map_err(to_storage_error)?;
+        if magic != BACKUP_MAGIC {
+            return Err(Error::StorageError(
+                "Invalid backup magic bytes".to_string(),
+            ));
+        }
+        let mut version = [0u8; 2];
+        reader.read_exact(&mut version).map_err(to_storage_error)?;
+        let version = u16::from_be_bytes(version);
+        if version != BACKUP_VERSION {
+            return Err(Error::StorageError(format!(
+                "Unsupported backup version {}",
+                version
+            )));
+        }
+        let mut nb_trees = [0u8; 1];
+        reader.read_exact(&mut nb_trees).map_err(to_storage_error)?;
+
+        let mut index_keys = vec![COUNTERPARTY_INDEX_READY_KEY.to_vec()];
+        let mut restored: Vec<(u8, Vec<(Vec<u8>, Vec<u8>)>)> =
+            BACKUP_TREES.iter().map(|t| (*t, Vec::new())).collect();
+        for _ in 0..nb_trees[0] {
+            let mut tree_id = [0u8; 1];
+            reader.read_exact(&mut tree_id).map_err(to_storage_error)?;
+            let entries = &mut restored
+                .iter_mut()
+                .find(|(t, _)| *t == tree_id[0])
+                .ok_or_else(|| {
+                    Error::StorageError(format!("Unknown tree {} in backup", tree_id[0]))
+                })?
+                .1;
+            let mut nb_entries = [0u8; 8];
+            reader
+                .read_exact(&mut nb_entries)
+                .map_err(to_storage_error)?;
+            for _ in 0..u64::from_be_bytes(nb_entries) {
+                let key = read_backup_bytes(reader)?;
+                let value = read_backup_bytes(reader)?;
+                match tree_id[0] {
+                    CONTRACT_TREE => {
+                        let contract = deserialize_contract(&value)?;
+                        index_keys.push(self.counterparty_index_key(
+                            &contract.get_counter_party_id(),
+                            &contract.get_id(),
+                        ));
+                    }
+                    ARCHIVE_TREE => {
+                        deserialize_contract(&value)?;
+                    }
+                    CHANNEL_TREE => {
+                        deserialize_channel(&value)?;
+                    }
+                    _ => {
+                        ChainMonitor::deserialize(&mut Cursor::new(&value))
+                            .map_err(to_storage_error)?;
+                    }
+                }
+                let value = self.encrypt(tree_id[0], &key, value)?;
+                entries.push((key, value));
+            }
+        }
+
+        let _guard = self.write_guard();
+        let contract_tree = self.contract_tree()?;
+        let channel_tree = self.channel_tree()?;
+        let chain_monitor_tree = self.open_tree(&[CHAIN_MONITOR_TREE])?;
+        let archive_tree = self.open_tree(&[ARCHIVE_TREE])?;
+        let index_tree = self.open_tree(&[COUNTERPARTY_INDEX_TREE])?;
+        let closed_at_tree = self.open_tree(&[CLOSED_AT_TREE])?;
+        let journal_tree = self.open_tree(&[JOURNAL_TREE])?;
+        let mut existing_keys = Vec::new();
+        for tree in [
+            &contract_tree,
+            &channel_tree,
+            &chain_monitor_tree,
+            &archive_tree,
+            &index_tree,
+            &closed_at_tree,
+            &journal_tree,
+        ] {
+            existing_keys.push(
+                tree.iter()
+                    .keys()
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(to_storage_error)?,
+            );
+        }
+
+        (
+            &contract_tree,
+            &channel_tree,
+            &chain_monitor_tree,
+            &archive_tree,
+            &index_tree,
+            &closed_at_tree,
+            &journal_tree,
+        )
+            .transaction::<_, ()>(
+                |(
+                    contract_db,
+                    channel_db,
+                    chain_monitor_db,
+                    archive_db,
+                    index_db,
+                    closed_at_db,
+                    journal_db,
+                )|
+                 -> ConflictableTransactionResult<(), UnabortableTransactionError> {
+                    let dbs = [contract_db, channel_db, chain_monitor_db, archive_db];
+                    for ((db, keys), (_, entries)) in dbs.iter().zip(&existing_keys).zip(&restored)
+                    {
+                        for key in keys {
+                            db.remove(key)?;
+                        }
+                        for (key, value) in entries {
+                            db.insert(key.as_slice(), value.as_slice())?;
+                        }
+                    }
+                    for key in &existing_keys[4] {
+                        index_db.remove(key)?;
+                    }
+                    for key in &index_keys {
+                        index_db.insert(key.as_slice(), Vec::new())?;
+                    }
+                    for key in &existing_keys[5] {
+                        closed_at_db.remove(key)?;
+                    }
+                    for key in &existing_keys[6] {
+                        journal_db.remove(key)?;
+                    }
+                    Ok(())
+                },
+            )
+            .map_err(to_storage_error)?;
+        // The channel index is rebuilt from the restored channels on next use.
+        self.open_tree(&[CHANNEL_INDEX_TREE])?
+            .clear()
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
 }
 
 #[cfg(feature = "wallet")]
@@ -216,193 +1425,633 @@ impl SledStorageProvider {
     fn key_pair_tree(&self) -> Result<Tree, Error> {
         self.open_tree(&[KEY_PAIR_TREE])
     }
+
+    fn utxo_key(&self, txid: &Txid, vout: u32) -> Vec<u8> {
+        self.index_key(UTXO_TREE, &get_utxo_key(txid, vout))
+    }
 }
 
 impl Storage for SledStorageProvider {
     fn get_contract(&self, contract_id: &ContractId) -> Result<Option<Contract>, Error> {
-        match self
-            .contract_tree()?
-            .get(contract_id)
-            .map_err(to_storage_error)?
-        {
-            Some(res) => Ok(Some(deserialize_contract(&res)?)),
-            None => Ok(None),
-        }
+        self.measure("get_contract", || {
+            let res = match self
+                .contract_tree()?
+                .get(contract_id)
+                .map_err(to_storage_error)?
+            {
+                Some(res) => Some((CONTRACT_TREE, res)),
+                None => self
+                    .open_tree(&[ARCHIVE_TREE])?
+                    .get(contract_id)
+                    .map_err(to_storage_error)?
+                    .map(|res| (ARCHIVE_TREE, res)),
+            };
+            match res {
+                Some((tree_id, res)) => Ok((
+                    Some(deserialize_contract(&self.decrypt(
+                        tree_id,
+                        contract_id,
+                        &res,
+                    )?)?),
+                    res.len(),
+                )),
+                None => Ok((None, 0)),
+            }
+        })
     }
 
     fn get_contracts(&self) -> Result<Vec<Contract>, Error> {
-        self.contract_tree()?
-            .iter()
-            .values()
-            .map(|x| deserialize_contract(&x.unwrap()))
-            .collect::<Result<Vec<Contract>, Error>>()
+        self.measure("get_contracts", || {
+            let mut size = 0;
+            let contracts = self
+                .contract_tree()?
+                .iter()
+                .map(|x| {
+                    let (key, value) = x.map_err(to_storage_error)?;
+                    size += value.len();
+                    deserialize_contract(&self.decrypt(CONTRACT_TREE, &key, &value)?)
+                })
+                .collect::<Result<Vec<Contract>, Error>>()?;
+            Ok((contracts, size))
+        })
+    }
+
+    fn get_contracts_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Contract>, Error> {
+        self.measure("get_contracts_by_counterparty", || {
+            let contract_tree = self.contract_tree()?;
+            let mut res = Vec::new();
+            let mut size = 0;
+            let prefix = self.counterparty_prefix(counter_party);
+            for key in self.counterparty_index_tree()?.scan_prefix(&prefix).keys() {
+                let key = key.map_err(to_storage_error)?;
+                let contract_id = &key[prefix.len()..];
+                if let Some(value) = contract_tree.get(contract_id).map_err(to_storage_error)? {
+                    size += value.len();
+                    res.push(deserialize_contract(&self.decrypt(
+                        CONTRACT_TREE,
+                        contract_id,
+                        &value,
+                    )?)?);
+                }
+            }
+            Ok((res, size))
+        })
     }
 
     fn create_contract(&self, contract: &OfferedContract) -> Result<(), Error> {
-        let serialized = serialize_contract(&Contract::Offered(contract.clone()))?;
-        self.contract_tree()?
-            .insert(contract.id, serialized)
+        self.measure("create_contract", || {
+            let _guard = self.write_guard();
+            let size =
+                self.write_batch_inner(StorageBatch::new().with_new_contract(contract.clone()))?;
+            self.flush_if_required()?;
+            Ok(((), size))
+        })
+    }
+
+    fn delete_contract(&self, contract_id: &ContractId) -> Result<(), Error> {
+        self.measure("delete_contract", || {
+            let _guard = self.write_guard();
+            self.delete_contract_inner(contract_id)?;
+            self.flush_if_required()?;
+            Ok(((), 0))
+        })
+    }
+
+    fn update_contract(&self, contract: &Contract) -> Result<(), Error> {
+        self.measure("update_contract", || {
+            let _guard = self.write_guard();
+            let size =
+                self.write_batch_inner(StorageBatch::new().with_contract(contract.clone()))?;
+            self.flush_if_required()?;
+            Ok(((), size))
+        })
+    }
+
+    fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        self.measure("get_signed_contracts", || {
+            self.get_data_with_prefix(CONTRACT_TREE, &[ContractPrefix::Signed.into()], None)
+        })
+    }
+
+    fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        self.measure("get_confirmed_contracts", || {
+            self.get_data_with_prefix(CONTRACT_TREE, &[ContractPrefix::Confirmed.into()], None)
+        })
+    }
+
+    fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        self.measure("get_contract_offers", || {
+            self.get_data_with_prefix(CONTRACT_TREE, &[ContractPrefix::Offered.into()], None)
+        })
+    }
+
+    fn get_expired_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        self.measure("get_expired_offers", || {
+            self.get_data_with_prefix(CONTRACT_TREE, &[ContractPrefix::Expired.into()], None)
+        })
+    }
+
+    fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, Error> {
+        self.measure("get_preclosed_contracts", || {
+            self.get_data_with_prefix(CONTRACT_TREE, &[ContractPrefix::PreClosed.into()], None)
+        })
+    }
+
+    fn upsert_channel(&self, channel: Channel, contract: Option<Contract>) -> Result<(), Error> {
+        self.measure("upsert_channel", || {
+            let _guard = self.write_guard();
+            let mut batch = StorageBatch::new().with_channel(channel);
+            if let Some(contract) = contract {
+                batch = batch.with_contract(contract);
+            }
+            let size = self.write_batch_inner(batch)?;
+            self.flush_if_required()?;
+            Ok(((), size))
+        })
+    }
+
+    fn delete_channel(&self, channel_id: &dlc_manager::ChannelId) -> Result<(), Error> {
+        self.measure("delete_channel", || {
+            let _guard = self.write_guard();
+            if let Some(value) = self
+                .channel_tree()?
+                .remove(channel_id)
+                .map_err(to_storage_error)?
+            {
+                let channel =
+                    deserialize_channel(&self.decrypt(CHANNEL_TREE, channel_id, &value)?)?;
+                self.channel_index_tree()?
+                    .remove(self.channel_counterparty_index_key(
+                        &channel.get_counter_party_id(),
+                        channel_id,
+                    ))
+                    .map_err(to_storage_error)?;
+            }
+            let punishment_tree = self.open_tree(&[PUNISHMENT_TREE])?;
+            for key in punishment_tree.scan_prefix(channel_id).keys() {
+                punishment_tree
+                    .remove(key.map_err(to_storage_error)?)
+                    .map_err(to_storage_error)?;
+            }
+            self.flush_if_required()?;
+            Ok(((), 0))
+        })
+    }
+
+    fn get_channel(&self, channel_id: &dlc_manager::ChannelId) -> Result<Option<Channel>, Error> {
+        self.measure("get_channel", || {
+            match self
+                .channel_tree()?
+                .get(channel_id)
+                .map_err(to_storage_error)?
+            {
+                Some(res) => Ok((
+                    Some(deserialize_channel(&self.decrypt(
+                        CHANNEL_TREE,
+                        channel_id,
+                        &res,
+                    )?)?),
+                    res.len(),
+                )),
+                None => Ok((None, 0)),
+            }
+        })
+    }
+
+    fn get_channels_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Channel>, Error> {
+        self.measure("get_channels_by_counterparty", || {
+            let channel_tree = self.channel_tree()?;
+            let mut res = Vec::new();
+            let mut size = 0;
+            let mut prefix = vec![CHANNEL_COUNTERPARTY_PREFIX];
+            prefix.extend_from_slice(&self.counterparty_prefix(counter_party));
+            for key in self.channel_index_tree()?.scan_prefix(&prefix).keys() {
+                let key = key.map_err(to_storage_error)?;
+                let channel_id = &key[prefix.len()..];
+                if let Some(value) = channel_tree.get(channel_id).map_err(to_storage_error)? {
+                    size += value.len();
+                    res.push(deserialize_channel(&self.decrypt(
+                        CHANNEL_TREE,
+                        channel_id,
+                        &value,
+                    )?)?);
+                }
+            }
+            Ok((res, size))
+        })
+    }
+
+    fn get_channel_by_contract_id(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<Channel>, Error> {
+        self.measure("get_channel_by_contract_id", || {
+            let channel_id = match self
+                .channel_index_tree()?
+                .get(channel_contract_index_key(contract_id))
+                .map_err(to_storage_error)?
+            {
+                Some(channel_id) => channel_id,
+                None => return Ok((None, 0)),
+            };
+            match self
+                .channel_tree()?
+                .get(channel_id)
+                .map_err(to_storage_error)?
+            {
+                Some(value) => {
+                    let channel =
+                        deserialize_channel(&self.decrypt(CHANNEL_TREE, &channel_id, &value)?)?;
+                    // The index is not updated when the channel moves to a new
+                    // contract, so the entry can be stale.
+                    if channel.get_contract_id().as_ref() == Some(contract_id) {
+                        Ok((Some(channel), value.len()))
+                    } else {
+                        Ok((None, 0))
+                    }
+                }
+                None => Ok((None, 0)),
+            }
+        })
+    }
+
+    fn get_signed_channels(
+        &self,
+        channel_state: Option<SignedChannelStateType>,
+    ) -> Result<Vec<SignedChannel>, Error> {
+        let (prefix, consume) = if let Some(state) = &channel_state {
+            (
+                vec![
+                    ChannelPrefix::Signed.into(),
+                    SignedChannelPrefix::get_prefix(state),
+                ],
+                None,
+            )
+        } else {
+            (vec![ChannelPrefix::Signed.into()], Some(1))
+        };
+
+        self.measure("get_signed_channels", || {
+            self.get_data_with_prefix(CHANNEL_TREE, &prefix, consume)
+        })
+    }
+
+    fn get_offered_channels(&self) -> Result<Vec<OfferedChannel>, Error> {
+        self.measure("get_offered_channels", || {
+            self.get_data_with_prefix(CHANNEL_TREE, &[ChannelPrefix::Offered.into()], None)
+        })
+    }
+
+    fn persist_chain_monitor(&self, monitor: &ChainMonitor) -> Result<(), Error> {
+        self.measure("persist_chain_monitor", || {
+            let _guard = self.write_guard();
+            let serialized = self.encrypt(
+                CHAIN_MONITOR_TREE,
+                &[CHAIN_MONITOR_KEY],
+                monitor.serialize()?,
+            )?;
+            let size = serialized.len();
+            self.open_tree(&[CHAIN_MONITOR_TREE])?
+                .insert([CHAIN_MONITOR_KEY], serialized)
+                .map_err(|e| Error::StorageError(format!("Error writing chain monitor: {}", e)))?;
+            self.flush_if_required()?;
+            Ok(((), size))
+        })
+    }
+
+    fn write_batch(&self, batch: StorageBatch) -> Result<(), Error> {
+        self.measure("write_batch", || {
+            let _guard = self.write_guard();
+            let size = self.write_batch_inner(batch)?;
+            self.flush_if_required()?;
+            Ok(((), size))
+        })
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        self.db
+            .read()
+            .unwrap()
+            .flush()
+            .map_err(|e| Error::StorageError(format!("Error flushing database: {}", e)))?;
+        Ok(())
+    }
+
+    fn prune_closed_contracts(&self, older_than: Duration) -> Result<usize, Error> {
+        let _guard = self.write_guard();
+        let now = unix_time_now();
+        let contract_tree = self.contract_tree()?;
+        let closed_at_tree = self.open_tree(&[CLOSED_AT_TREE])?;
+        let mut pruned = Vec::new();
+        for entry in contract_tree.iter() {
+            let (id, value) = entry.map_err(to_storage_error)?;
+            let contract = deserialize_contract(&self.decrypt(CONTRACT_TREE, &id, &value)?)?;
+            if !is_prunable(&contract) {
+                continue;
+            }
+            let closed_at = match closed_at_tree.get(&id).map_err(to_storage_error)? {
+                Some(closed_at) => read_timestamp(&closed_at)?,
+                None => {
+                    // Contracts closed before closing times were recorded are
+                    // considered as closed now.
+                    closed_at_tree
+                        .insert(&id, now.to_be_bytes().to_vec())
+                        .map_err(to_storage_error)?;
+                    now
+                }
+            };
+            if now.saturating_sub(closed_at) >= older_than.as_secs() {
+                let index_key = self
+                    .counterparty_index_key(&contract.get_counter_party_id(), &contract.get_id());
+                let journal_keys = self.get_journal_keys(&contract.get_id())?;
+                let value = self.reencrypt(&value, CONTRACT_TREE, &id, ARCHIVE_TREE, &id)?;
+                pruned.push((id, value, index_key, journal_keys));
+            }
+        }
+
+        let archive = self.prune_policy == PrunePolicy::Archive;
+        (
+            &contract_tree,
+            &closed_at_tree,
+            &self.counterparty_index_tree()?,
+            &self.open_tree(&[ARCHIVE_TREE])?,
+            &self.open_tree(&[JOURNAL_TREE])?,
+        )
+            .transaction::<_, ()>(
+                |(contract_db, closed_at_db, index_db, archive_db, journal_db)| -> ConflictableTransactionResult<(), UnabortableTransactionError> {
+                    for (id, value, index_key, journal_keys) in &pruned {
+                        contract_db.remove(id)?;
+                        closed_at_db.remove(id)?;
+                        index_db.remove(index_key.as_slice())?;
+                        for key in journal_keys {
+                            journal_db.remove(key)?;
+                        }
+                        if archive {
+                            archive_db.insert(id, value.clone())?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+            .map_err(to_storage_error)?;
+        Ok(pruned.len())
+    }
+
+    fn update_peer_last_seen(&self, peer_id: &PublicKey, timestamp: u64) -> Result<(), Error> {
+        self.open_tree(&[PEER_LAST_SEEN_TREE])?
+            .insert(
+                self.index_key(PEER_LAST_SEEN_TREE, &peer_id.serialize()),
+                timestamp.to_be_bytes().to_vec(),
+            )
             .map_err(to_storage_error)?;
         Ok(())
     }
 
-    fn delete_contract(&self, contract_id: &ContractId) -> Result<(), Error> {
-        self.contract_tree()?
-            .remove(contract_id)
+    fn get_peer_last_seen(&self, peer_id: &PublicKey) -> Result<Option<u64>, Error> {
+        self.open_tree(&[PEER_LAST_SEEN_TREE])?
+            .get(self.index_key(PEER_LAST_SEEN_TREE, &peer_id.serialize()))
+            .map_err(to_storage_error)?
+            .map(|x| read_timestamp(&x))
+            .transpose()
+    }
+
+    fn upsert_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+        temporary_id: &ContractId,
+    ) -> Result<(), Error> {
+        self.open_tree(&[OFFER_HASH_TREE])?
+            .insert(offer_hash, temporary_id.to_vec())
             .map_err(to_storage_error)?;
         Ok(())
     }
 
-    fn update_contract(&self, contract: &Contract) -> Result<(), Error> {
-        let serialized = serialize_contract(contract)?;
-        self.contract_tree()?
-            .transaction::<_, _, UnabortableTransactionError>(|db| {
-                match contract {
-                    a @ Contract::Accepted(_) | a @ Contract::Signed(_) => {
-                        db.remove(&a.get_temporary_id())?;
-                    }
-                    _ => {}
-                };
-
-                db.insert(&contract.get_id(), serialized.clone())?;
-                Ok(())
+    fn get_contract_id_by_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+    ) -> Result<Option<ContractId>, Error> {
+        self.open_tree(&[OFFER_HASH_TREE])?
+            .get(offer_hash)
+            .map_err(to_storage_error)?
+            .map(|x| {
+                x.as_ref()
+                    .try_into()
+                    .map_err(|_| Error::StorageError("Invalid stored contract id".to_string()))
             })
+            .transpose()
+    }
+
+    fn upsert_punishment_data(&self, data: &PunishmentData) -> Result<(), Error> {
+        let key = punishment_key(&data.channel_id, data.update_idx);
+        let serialized = self.encrypt(
+            PUNISHMENT_TREE,
+            &key,
+            data.serialize().map_err(to_storage_error)?,
+        )?;
+        self.open_tree(&[PUNISHMENT_TREE])?
+            .insert(key, serialized)
             .map_err(to_storage_error)?;
         Ok(())
     }
 
-    fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
-        self.get_data_with_prefix(
-            &self.contract_tree()?,
-            &[ContractPrefix::Signed.into()],
-            None,
-        )
+    fn get_punishment_data(
+        &self,
+        channel_id: &ChannelId,
+        update_idx: u64,
+    ) -> Result<Option<PunishmentData>, Error> {
+        let key = punishment_key(channel_id, update_idx);
+        self.open_tree(&[PUNISHMENT_TREE])?
+            .get(&key)
+            .map_err(to_storage_error)?
+            .map(|x| deserialize_punishment_data(&self.decrypt(PUNISHMENT_TREE, &key, &x)?))
+            .transpose()
     }
 
-    fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
-        self.get_data_with_prefix(
-            &self.contract_tree()?,
-            &[ContractPrefix::Confirmed.into()],
-            None,
-        )
+    fn get_latest_punishment_data(
+        &self,
+        channel_id: &ChannelId,
+        count: usize,
+    ) -> Result<Vec<PunishmentData>, Error> {
+        // Update indexes decrease with each update and are stored big endian,
+        // so the most recently revoked states come first.
+        self.open_tree(&[PUNISHMENT_TREE])?
+            .scan_prefix(channel_id)
+            .take(count)
+            .map(|x| {
+                let (key, value) = x.map_err(to_storage_error)?;
+                deserialize_punishment_data(&self.decrypt(PUNISHMENT_TREE, &key, &value)?)
+            })
+            .collect()
     }
 
-    fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error> {
-        self.get_data_with_prefix(
-            &self.contract_tree()?,
-            &[ContractPrefix::Offered.into()],
-            None,
-        )
+    fn upsert_justice_blob(&self, blob: &JusticeBlob) -> Result<(), Error> {
+        let serialized = self.encrypt(
+            JUSTICE_BLOB_TREE,
+            &blob.locator,
+            blob.serialize().map_err(to_storage_error)?,
+        )?;
+        self.open_tree(&[JUSTICE_BLOB_TREE])?
+            .insert(blob.locator, serialized)
+            .map_err(to_storage_error)?;
+        Ok(())
     }
 
-    fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, Error> {
-        self.get_data_with_prefix(
-            &self.contract_tree()?,
-            &[ContractPrefix::PreClosed.into()],
-            None,
-        )
+    fn get_justice_blob(&self, locator: &[u8; LOCATOR_LEN]) -> Result<Option<JusticeBlob>, Error> {
+        self.open_tree(&[JUSTICE_BLOB_TREE])?
+            .get(locator)
+            .map_err(to_storage_error)?
+            .map(|x| {
+                JusticeBlob::deserialize(&mut Cursor::new(self.decrypt(
+                    JUSTICE_BLOB_TREE,
+                    locator,
+                    &x,
+                )?))
+                .map_err(to_storage_error)
+            })
+            .transpose()
     }
 
-    fn upsert_channel(&self, channel: Channel, contract: Option<Contract>) -> Result<(), Error> {
-        let serialized = serialize_channel(&channel)?;
-        let serialized_contract = match contract.as_ref() {
-            Some(c) => Some(serialize_contract(c)?),
-            None => None,
-        };
-        let channel_tree = self.channel_tree()?;
-        let contract_tree = self.contract_tree()?;
-        (&channel_tree, &contract_tree)
-            .transaction::<_, ()>(
-                |(channel_db, contract_db)| -> ConflictableTransactionResult<(), UnabortableTransactionError> {
-                    match &channel {
-                        a @ Channel::Accepted(_) | a @ Channel::Signed(_) => {
-                            channel_db.remove(&a.get_temporary_id())?;
-                        }
-                        _ => {}
-                    };
-
-                    channel_db.insert(&channel.get_id(), serialized.clone())?;
+    fn persist_header_cache(&self, header_cache: &HeaderCache) -> Result<(), Error> {
+        let serialized = self.encrypt(
+            CHAIN_MONITOR_TREE,
+            &[HEADER_CACHE_KEY],
+            header_cache.serialize().map_err(to_storage_error)?,
+        )?;
+        self.open_tree(&[CHAIN_MONITOR_TREE])?
+            .insert([HEADER_CACHE_KEY], serialized)
+            .map_err(|e| Error::StorageError(format!("Error writing header cache: {}", e)))?;
+        self.flush_if_required()
+    }
 
-                    if let Some(c) = contract.as_ref() {
-                        insert_contract(
-                            contract_db,
-                            serialized_contract
-                                .clone()
-                                .expect("to have the serialized version"),
-                            c,
-                        )?;
-                    }
-                    Ok(())
-                },
-            )
-        .map_err(to_storage_error)?;
-        Ok(())
+    fn get_header_cache(&self) -> Result<Option<HeaderCache>, Error> {
+        self.open_tree(&[CHAIN_MONITOR_TREE])?
+            .get([HEADER_CACHE_KEY])
+            .map_err(|e| Error::StorageError(format!("Error reading header cache: {}", e)))?
+            .map(|x| {
+                HeaderCache::deserialize(&mut Cursor::new(self.decrypt(
+                    CHAIN_MONITOR_TREE,
+                    &[HEADER_CACHE_KEY],
+                    &x,
+                )?))
+                .map_err(to_storage_error)
+            })
+            .transpose()
     }
 
-    fn delete_channel(&self, channel_id: &dlc_manager::ChannelId) -> Result<(), Error> {
-        self.channel_tree()?
-            .remove(channel_id)
+    fn upsert_product_definition(
+        &self,
+        event_id: &str,
+        product: &ProductDefinition,
+    ) -> Result<(), Error> {
+        let serialized = self.encrypt(
+            PRODUCT_TREE,
+            event_id.as_bytes(),
+            product.serialize().map_err(to_storage_error)?,
+        )?;
+        self.open_tree(&[PRODUCT_TREE])?
+            .insert(event_id.as_bytes(), serialized)
             .map_err(to_storage_error)?;
         Ok(())
     }
 
-    fn get_channel(&self, channel_id: &dlc_manager::ChannelId) -> Result<Option<Channel>, Error> {
-        match self
-            .channel_tree()?
-            .get(channel_id)
+    fn get_product_definition(&self, event_id: &str) -> Result<Option<ProductDefinition>, Error> {
+        self.open_tree(&[PRODUCT_TREE])?
+            .get(event_id.as_bytes())
             .map_err(to_storage_error)?
-        {
-            Some(res) => Ok(Some(deserialize_channel(&res)?)),
-            None => Ok(None),
-        }
+            .map(|x| {
+                let serialized = self.decrypt(PRODUCT_TREE, event_id.as_bytes(), &x)?;
+                ProductDefinition::deserialize(&mut Cursor::new(serialized))
+                    .map_err(to_storage_error)
+            })
+            .transpose()
     }
 
-    fn get_signed_channels(
+    fn append_contract_event(
         &self,
-        channel_state: Option<SignedChannelStateType>,
-    ) -> Result<Vec<SignedChannel>, Error> {
-        let (prefix, consume) = if let Some(state) = &channel_state {
-            (
-                vec![
-                    ChannelPrefix::Signed.into(),
-                    SignedChannelPrefix::get_prefix(state),
-                ],
-                None,
-            )
-        } else {
-            (vec![ChannelPrefix::Signed.into()], Some(1))
-        };
+        temporary_id: &ContractId,
+        event: &ContractEvent,
+    ) -> Result<(), Error> {
+        self.write_batch(StorageBatch::new().with_contract_event(*temporary_id, event.clone()))
+    }
 
-        self.get_data_with_prefix(&self.channel_tree()?, &prefix, consume)
+    fn get_contract_history(&self, id: &ContractId) -> Result<Vec<ContractEvent>, Error> {
+        let temporary_id = self.get_contract(id)?.map_or(*id, |c| c.get_temporary_id());
+        self.open_tree(&[CONTRACT_EVENT_TREE])?
+            .scan_prefix(temporary_id)
+            .map(|x| {
+                let (key, value) = x.map_err(to_storage_error)?;
+                let serialized = self.decrypt(CONTRACT_EVENT_TREE, &key, &value)?;
+                ContractEvent::deserialize(&mut Cursor::new(serialized)).map_err(to_storage_error)
+            })
+            .collect()
     }
 
-    fn get_offered_channels(&self) -> Result<Vec<OfferedChannel>, Error> {
-        self.get_data_with_prefix(
-            &self.channel_tree()?,
-            &[ChannelPrefix::Offered.into()],
-            None,
-        )
+    fn append_channel_event(
+        &self,
+        temporary_id: &ChannelId,
+        event: &ChannelEvent,
+    ) -> Result<(), Error> {
+        self.write_batch(StorageBatch::new().with_channel_event(*temporary_id, event.clone()))
     }
 
-    fn persist_chain_monitor(&self, monitor: &ChainMonitor) -> Result<(), Error> {
-        self.open_tree(&[CHAIN_MONITOR_TREE])?
-            .insert([CHAIN_MONITOR_KEY], monitor.serialize()?)
-            .map_err(|e| Error::StorageError(format!("Error writing chain monitor: {}", e)))?;
-        Ok(())
+    fn get_channel_history(&self, id: &ChannelId) -> Result<Vec<ChannelEvent>, Error> {
+        let history_id = self.get_channel(id)?.map_or(*id, |c| c.get_history_id());
+        self.open_tree(&[CHANNEL_EVENT_TREE])?
+            .scan_prefix(history_id)
+            .map(|x| {
+                let (key, value) = x.map_err(to_storage_error)?;
+                let serialized = self.decrypt(CHANNEL_EVENT_TREE, &key, &value)?;
+                ChannelEvent::deserialize(&mut Cursor::new(serialized)).map_err(to_storage_error)
+            })
+            .collect()
+    }
+
+    fn read_snapshot(&self) -> Result<StorageSnapshot, Error> {
+        let _guard = self.snapshot_lock.write().unwrap();
+        let contracts = self.get_contracts()?;
+        let channels = self.measure("read_snapshot", || {
+            let mut size = 0;
+            let channels = self
+                .channel_tree()?
+                .iter()
+                .map(|x| {
+                    let (key, value) = x.map_err(to_storage_error)?;
+                    size += value.len();
+                    deserialize_channel(&self.decrypt(CHANNEL_TREE, &key, &value)?)
+                })
+                .collect::<Result<Vec<Channel>, Error>>()?;
+            Ok((channels, size))
+        })?;
+        let chain_monitor = self.get_chain_monitor()?;
+        Ok(StorageSnapshot::new(contracts, channels, chain_monitor))
     }
+
     fn get_chain_monitor(&self) -> Result<Option<ChainMonitor>, dlc_manager::error::Error> {
-        let serialized = self
-            .open_tree(&[CHAIN_MONITOR_TREE])?
-            .get([CHAIN_MONITOR_KEY])
-            .map_err(|e| Error::StorageError(format!("Error reading chain monitor: {}", e)))?;
-        let deserialized = match serialized {
-            Some(s) => Some(
-                ChainMonitor::deserialize(&mut ::std::io::Cursor::new(s))
+        self.measure("get_chain_monitor", || {
+            let serialized = self
+                .open_tree(&[CHAIN_MONITOR_TREE])?
+                .get([CHAIN_MONITOR_KEY])
+                .map_err(|e| Error::StorageError(format!("Error reading chain monitor: {}", e)))?;
+            let size = serialized.as_ref().map_or(0, |s| s.len());
+            let deserialized = match serialized {
+                Some(s) => Some(
+                    ChainMonitor::deserialize(&mut ::std::io::Cursor::new(self.decrypt(
+                        CHAIN_MONITOR_TREE,
+                        &[CHAIN_MONITOR_KEY],
+                        &s,
+                    )?))
                     .map_err(to_storage_error)?,
-            ),
-            None => None,
-        };
-        Ok(deserialized)
+                ),
+                None => None,
+            };
+            Ok((deserialized, size))
+        })
     }
 }
 
@@ -410,15 +2059,21 @@ impl Storage for SledStorageProvider {
 impl WalletStorage for SledStorageProvider {
     fn upsert_address(&self, address: &Address, privkey: &SecretKey) -> Result<(), Error> {
         let db = self.address_tree()?;
-        let key = get_address_key(address);
-        db.insert(key, &privkey.secret_bytes())
+        let key = self.index_key(ADDRESS_TREE, &get_address_key(address));
+        let mut value = privkey.secret_bytes().to_vec();
+        if self.cipher.is_some() {
+            // The key does not reveal the address, so it is kept with the
+            // private key to be able to list addresses.
+            value.extend_from_slice(&get_address_key(address));
+        }
+        db.insert(&key, self.encrypt(ADDRESS_TREE, &key, value)?)
             .map_err(to_storage_error)?;
         Ok(())
     }
 
     fn delete_address(&self, address: &Address) -> Result<(), Error> {
         let db = self.address_tree()?;
-        let key = get_address_key(address);
+        let key = self.index_key(ADDRESS_TREE, &get_address_key(address));
         db.remove(key).map_err(to_storage_error)?;
         Ok(())
     }
@@ -426,9 +2081,15 @@ impl WalletStorage for SledStorageProvider {
     fn get_addresses(&self) -> Result<Vec<Address>, Error> {
         self.address_tree()?
             .iter()
-            .keys()
             .map(|x| {
-                Ok(String::from_utf8(x.map_err(to_storage_error)?.to_vec())
+                let (key, value) = x.map_err(to_storage_error)?;
+                let address = match self.cipher {
+                    Some(_) => {
+                        self.decrypt(ADDRESS_TREE, &key, &value)?[SECRET_KEY_SIZE..].to_vec()
+                    }
+                    None => key.to_vec(),
+                };
+                Ok(String::from_utf8(address)
                     .map_err(|e| Error::InvalidState(format!("Could not read address key {}", e)))?
                     .parse::<Address<NetworkUnchecked>>()
                     .expect("to have a valid address as key")
@@ -439,54 +2100,60 @@ impl WalletStorage for SledStorageProvider {
 
     fn get_priv_key_for_address(&self, address: &Address) -> Result<Option<SecretKey>, Error> {
         let db = self.address_tree()?;
-        let key = get_address_key(address);
-        let raw_key = match db.get(key).map_err(to_storage_error)? {
+        let key = self.index_key(ADDRESS_TREE, &get_address_key(address));
+        let raw_key = match db.get(&key).map_err(to_storage_error)? {
             Some(res) => res,
             None => return Ok(None),
         };
 
         Ok(Some(
-            SecretKey::from_slice(&raw_key).expect("a valid secret key"),
+            SecretKey::from_slice(&self.decrypt(ADDRESS_TREE, &key, &raw_key)?[..SECRET_KEY_SIZE])
+                .expect("a valid secret key"),
         ))
     }
 
     fn upsert_key(&self, identifier: &[u8], privkey: &SecretKey) -> Result<(), Error> {
+        let key = self.index_key(KEY_PAIR_TREE, identifier);
+        let value = self.encrypt(KEY_PAIR_TREE, &key, privkey.secret_bytes().to_vec())?;
         self.key_pair_tree()?
-            .insert(identifier, &privkey.secret_bytes())
+            .insert(key, value)
             .map_err(to_storage_error)?;
         Ok(())
     }
 
     fn get_priv_key(&self, identifier: &[u8]) -> Result<Option<SecretKey>, Error> {
         let db = self.key_pair_tree()?;
-        let raw_key = match db.get(identifier).map_err(to_storage_error)? {
+        let key = self.index_key(KEY_PAIR_TREE, identifier);
+        let raw_key = match db.get(&key).map_err(to_storage_error)? {
             Some(res) => res,
             None => return Ok(None),
         };
 
         Ok(Some(
-            SecretKey::from_slice(&raw_key).expect("a valid secret key"),
+            SecretKey::from_slice(&self.decrypt(KEY_PAIR_TREE, &key, &raw_key)?)
+                .expect("a valid secret key"),
         ))
     }
 
     fn upsert_utxo(&self, utxo: &Utxo) -> Result<(), Error> {
-        let key = get_utxo_key(&utxo.outpoint.txid, utxo.outpoint.vout);
+        let key = self.utxo_key(&utxo.outpoint.txid, utxo.outpoint.vout);
         let db = self.utxo_tree()?;
         let mut buf = Vec::new();
         utxo.write(&mut buf)?;
+        let buf = self.encrypt(UTXO_TREE, &key, buf)?;
         db.insert(key, buf).map_err(to_storage_error)?;
         Ok(())
     }
 
     fn has_utxo(&self, utxo: &Utxo) -> Result<bool, Error> {
-        let key = get_utxo_key(&utxo.outpoint.txid, utxo.outpoint.vout);
+        let key = self.utxo_key(&utxo.outpoint.txid, utxo.outpoint.vout);
         self.utxo_tree()?
             .contains_key(key)
             .map_err(to_storage_error)
     }
 
     fn delete_utxo(&self, utxo: &Utxo) -> Result<(), Error> {
-        let key = get_utxo_key(&utxo.outpoint.txid, utxo.outpoint.vout);
+        let key = self.utxo_key(&utxo.outpoint.txid, utxo.outpoint.vout);
         self.utxo_tree()?.remove(key).map_err(to_storage_error)?;
         Ok(())
     }
@@ -494,10 +2161,9 @@ impl WalletStorage for SledStorageProvider {
     fn get_utxos(&self) -> Result<Vec<Utxo>, Error> {
         self.utxo_tree()?
             .iter()
-            .values()
             .map(|x| {
-                let ivec = x.map_err(to_storage_error)?;
-                let mut cursor = Cursor::new(&ivec);
+                let (key, value) = x.map_err(to_storage_error)?;
+                let mut cursor = Cursor::new(self.decrypt(UTXO_TREE, &key, &value)?);
                 let res =
                     Utxo::read(&mut cursor).map_err(|x| Error::InvalidState(format!("{}", x)))?;
                 Ok(res)
@@ -507,9 +2173,9 @@ impl WalletStorage for SledStorageProvider {
 
     fn unreserve_utxo(&self, txid: &Txid, vout: u32) -> Result<(), Error> {
         let utxo_tree = self.utxo_tree()?;
-        let key = get_utxo_key(txid, vout);
+        let key = self.utxo_key(txid, vout);
         let mut utxo = match utxo_tree.get(&key).map_err(to_storage_error)? {
-            Some(res) => Utxo::read(&mut Cursor::new(&res))
+            Some(res) => Utxo::read(&mut Cursor::new(self.decrypt(UTXO_TREE, &key, &res)?))
                 .map_err(|_| Error::InvalidState("Could not read UTXO".to_string()))?,
             None => {
                 return Err(Error::InvalidState(format!(
@@ -522,44 +2188,118 @@ impl WalletStorage for SledStorageProvider {
         utxo.reserved = false;
         let mut buf = Vec::new();
         utxo.write(&mut buf)?;
+        let buf = self.encrypt(UTXO_TREE, &key, buf)?;
         utxo_tree.insert(key, buf).map_err(to_storage_error)?;
         Ok(())
     }
 }
 
-fn insert_contract(
-    db: &sled::transaction::TransactionalTree,
-    serialized: Vec<u8>,
-    contract: &Contract,
-) -> Result<Option<sled::IVec>, UnabortableTransactionError> {
-    match contract {
-        a @ Contract::Accepted(_) | a @ Contract::Signed(_) => {
-            db.remove(&a.get_temporary_id())?;
-        }
-        _ => {}
-    };
+fn write_backup_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), Error> {
+    let len: u32 = bytes
+        .len()
+        .try_into()
+        .map_err(|_| Error::StorageError("Record too large for backup".to_string()))?;
+    writer
+        .write_all(&len.to_be_bytes())
+        .map_err(to_storage_error)?;
+    writer.write_all(bytes).map_err(to_storage_error)
+}
+
+fn read_backup_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len).map_err(to_storage_error)?;
+    let mut bytes = Vec::new();
+    reader
+        .by_ref()
+        .take(u32::from_be_bytes(len) as u64)
+        .read_to_end(&mut bytes)
+        .map_err(to_storage_error)?;
+    if bytes.len() != u32::from_be_bytes(len) as usize {
+        return Err(Error::StorageError("Truncated backup".to_string()));
+    }
+    Ok(bytes)
+}
+
+fn is_prunable(contract: &Contract) -> bool {
+    matches!(
+        contract,
+        Contract::Closed(_)
+            | Contract::Refunded(_)
+            | Contract::Amended(_)
+            | Contract::CollaborativelyClosed(_)
+            | Contract::FailedAccept(_)
+            | Contract::FailedSign(_)
+    )
+}
+
+fn unix_time_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn punishment_key(channel_id: &ChannelId, update_idx: u64) -> Vec<u8> {
+    let mut key = channel_id.to_vec();
+    key.extend_from_slice(&update_idx.to_be_bytes());
+    key
+}
+
+fn deserialize_punishment_data(buff: &[u8]) -> Result<PunishmentData, Error> {
+    PunishmentData::deserialize(&mut Cursor::new(buff)).map_err(to_storage_error)
+}
+
+fn read_timestamp(value: &[u8]) -> Result<u64, Error> {
+    let bytes: [u8; 8] = value
+        .try_into()
+        .map_err(|_| Error::StorageError("Invalid stored timestamp".to_string()))?;
+    Ok(u64::from_be_bytes(bytes))
+}
 
-    db.insert(&contract.get_id(), serialized)
+fn channel_contract_index_key(contract_id: &ContractId) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + contract_id.len());
+    key.push(CHANNEL_CONTRACT_PREFIX);
+    key.extend_from_slice(contract_id);
+    key
 }
 
-fn serialize_contract(contract: &Contract) -> Result<Vec<u8>, ::std::io::Error> {
-    let serialized = match contract {
-        Contract::Offered(o) | Contract::Rejected(o) => o.serialize(),
-        Contract::Accepted(o) => o.serialize(),
-        Contract::Signed(o) | Contract::Confirmed(o) | Contract::Refunded(o) => o.serialize(),
-        Contract::FailedAccept(c) => c.serialize(),
-        Contract::FailedSign(c) => c.serialize(),
-        Contract::PreClosed(c) => c.serialize(),
-        Contract::Closed(c) => c.serialize(),
+fn contract_serialized_size(contract: &Contract) -> usize {
+    let size = match contract {
+        Contract::Offered(o) | Contract::Rejected(o) | Contract::Expired(o) => o.serialized_size(),
+        Contract::Accepted(o) => o.serialized_size(),
+        Contract::Signed(o)
+        | Contract::Confirmed(o)
+        | Contract::Refunded(o)
+        | Contract::Amended(o) => o.serialized_size(),
+        Contract::FailedAccept(c) => c.serialized_size(),
+        Contract::FailedSign(c) => c.serialized_size(),
+        Contract::PreClosed(c) => c.serialized_size(),
+        Contract::CollaborativelyClosed(c) => c.serialized_size(),
+        Contract::Closed(c) => c.serialized_size(),
     };
-    let mut serialized = serialized?;
-    let mut res = Vec::with_capacity(serialized.len() + 1);
-    res.push(ContractPrefix::get_prefix(contract));
-    res.append(&mut serialized);
-    Ok(res)
+    size + 1
+}
+
+fn write_contract<W: Write>(contract: &Contract, writer: &mut W) -> Result<(), ::std::io::Error> {
+    writer.write_all(&[ContractPrefix::get_prefix(contract)])?;
+    match contract {
+        Contract::Offered(o) | Contract::Rejected(o) | Contract::Expired(o) => {
+            o.serialize_into(writer)
+        }
+        Contract::Accepted(o) => o.serialize_into(writer),
+        Contract::Signed(o)
+        | Contract::Confirmed(o)
+        | Contract::Refunded(o)
+        | Contract::Amended(o) => o.serialize_into(writer),
+        Contract::FailedAccept(c) => c.serialize_into(writer),
+        Contract::FailedSign(c) => c.serialize_into(writer),
+        Contract::PreClosed(c) => c.serialize_into(writer),
+        Contract::CollaborativelyClosed(c) => c.serialize_into(writer),
+        Contract::Closed(c) => c.serialize_into(writer),
+    }
 }
 
-fn deserialize_contract(buff: &sled::IVec) -> Result<Contract, Error> {
+fn deserialize_contract(buff: &[u8]) -> Result<Contract, Error> {
     let mut cursor = ::std::io::Cursor::new(buff);
     let mut prefix = [0u8; 1];
     cursor.read_exact(&mut prefix)?;
@@ -595,30 +2335,46 @@ fn deserialize_contract(buff: &sled::IVec) -> Result<Contract, Error> {
         ContractPrefix::Rejected => {
             Contract::Rejected(OfferedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
         }
+        ContractPrefix::Expired => {
+            Contract::Expired(OfferedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ContractPrefix::CollaborativelyClosed => Contract::CollaborativelyClosed(
+            CollaborativelyClosedContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+        ContractPrefix::Amended => {
+            Contract::Amended(SignedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
     };
     Ok(contract)
 }
 
-fn serialize_channel(channel: &Channel) -> Result<Vec<u8>, ::std::io::Error> {
-    let serialized = match channel {
-        Channel::Offered(o) => o.serialize(),
-        Channel::Accepted(a) => a.serialize(),
-        Channel::Signed(s) => s.serialize(),
-        Channel::FailedAccept(f) => f.serialize(),
-        Channel::FailedSign(f) => f.serialize(),
-        Channel::Cancelled(o) => o.serialize(),
-    };
-    let mut serialized = serialized?;
-    let mut res = Vec::with_capacity(serialized.len() + 1);
-    res.push(ChannelPrefix::get_prefix(channel));
-    if let Channel::Signed(s) = channel {
-        res.push(SignedChannelPrefix::get_prefix(&s.state.get_type()))
+fn channel_serialized_size(channel: &Channel) -> usize {
+    match channel {
+        Channel::Offered(o) => o.serialized_size() + 1,
+        Channel::Accepted(a) => a.serialized_size() + 1,
+        Channel::Signed(s) => s.serialized_size() + 2,
+        Channel::FailedAccept(f) => f.serialized_size() + 1,
+        Channel::FailedSign(f) => f.serialized_size() + 1,
+        Channel::Cancelled(o) => o.serialized_size() + 1,
+    }
+}
+
+fn write_channel<W: Write>(channel: &Channel, writer: &mut W) -> Result<(), ::std::io::Error> {
+    writer.write_all(&[ChannelPrefix::get_prefix(channel)])?;
+    match channel {
+        Channel::Offered(o) => o.serialize_into(writer),
+        Channel::Accepted(a) => a.serialize_into(writer),
+        Channel::Signed(s) => {
+            writer.write_all(&[SignedChannelPrefix::get_prefix(&s.state.get_type())])?;
+            s.serialize_into(writer)
+        }
+        Channel::FailedAccept(f) => f.serialize_into(writer),
+        Channel::FailedSign(f) => f.serialize_into(writer),
+        Channel::Cancelled(o) => o.serialize_into(writer),
     }
-    res.append(&mut serialized);
-    Ok(res)
 }
 
-fn deserialize_channel(buff: &sled::IVec) -> Result<Channel, Error> {
+fn deserialize_channel(buff: &[u8]) -> Result<Channel, Error> {
     let mut cursor = ::std::io::Cursor::new(buff);
     let mut prefix = [0u8; 1];
     cursor.read_exact(&mut prefix)?;
@@ -655,8 +2411,6 @@ fn get_address_key(address: &Address) -> Vec<u8> {
 
 #[cfg(feature = "wallet")]
 fn get_utxo_key(txid: &Txid, vout: u32) -> Vec<u8> {
-    use bitcoin::hashes::Hash;
-
     let mut key = txid.to_byte_array().to_vec();
     key.extend_from_slice(&vout.to_be_bytes());
     key
@@ -665,6 +2419,7 @@ fn get_utxo_key(txid: &Txid, vout: u32) -> Vec<u8> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use dlc_manager::contract::ContractEventType;
 
     macro_rules! sled_test {
         ($name: ident, $body: expr) => {
@@ -711,6 +2466,33 @@ mod tests {
         }
     );
 
+    sled_test!(
+        create_contract_fails_if_contract_exists,
+        |storage: SledStorageProvider| {
+            let serialized = include_bytes!("../test_files/Offered");
+            let contract: OfferedContract = deserialize_object(serialized);
+
+            storage
+                .create_contract(&contract)
+                .expect("Error creating contract");
+
+            assert!(matches!(
+                storage.create_contract(&contract),
+                Err(Error::AlreadyExists(_))
+            ));
+            storage
+                .replace_contract(&contract)
+                .expect("Error replacing contract");
+            assert_eq!(
+                1,
+                storage
+                    .get_contract_offers()
+                    .expect("Error retrieving contracts")
+                    .len()
+            );
+        }
+    );
+
     sled_test!(
         update_contract_is_updated,
         |storage: SledStorageProvider| {
@@ -820,56 +2602,411 @@ mod tests {
     }
 
     sled_test!(
-        get_signed_contracts_only_signed,
+        get_signed_contracts_only_signed,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+
+            let signed_contracts = storage
+                .get_signed_contracts()
+                .expect("Error retrieving signed contracts");
+
+            assert_eq!(2, signed_contracts.len());
+        }
+    );
+
+    sled_test!(
+        get_confirmed_contracts_only_confirmed,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+
+            let confirmed_contracts = storage
+                .get_confirmed_contracts()
+                .expect("Error retrieving signed contracts");
+
+            assert_eq!(2, confirmed_contracts.len());
+        }
+    );
+
+    sled_test!(
+        get_offered_contracts_only_offered,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+
+            let offered_contracts = storage
+                .get_contract_offers()
+                .expect("Error retrieving signed contracts");
+
+            assert_eq!(1, offered_contracts.len());
+        }
+    );
+
+    sled_test!(
+        get_preclosed_contracts_only_preclosed,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+
+            let preclosed_contracts = storage
+                .get_preclosed_contracts()
+                .expect("Error retrieving preclosed contracts");
+
+            assert_eq!(1, preclosed_contracts.len());
+        }
+    );
+    sled_test!(
+        rollback_contract_restores_previous_versions,
+        |mut storage: SledStorageProvider| {
+            storage.set_journal_depth(2);
+            let serialized = include_bytes!("../test_files/Accepted");
+            let accepted_contract: AcceptedContract = deserialize_object(serialized);
+            let offered_contract = accepted_contract.offered_contract.clone();
+            let contract_id = accepted_contract.get_contract_id();
+
+            storage
+                .create_contract(&offered_contract)
+                .expect("Error creating contract");
+            storage
+                .update_contract(&Contract::Accepted(accepted_contract))
+                .expect("Error updating contract");
+
+            let restored = storage
+                .rollback_contract(&contract_id)
+                .expect("Error rolling back contract");
+            assert!(matches!(restored, Some(Contract::Offered(_))));
+            assert!(storage
+                .get_contract(&contract_id)
+                .expect("Error retrieving contract")
+                .is_none());
+            assert!(matches!(
+                storage
+                    .get_contract(&offered_contract.id)
+                    .expect("Error retrieving contract"),
+                Some(Contract::Offered(_))
+            ));
+
+            let restored = storage
+                .rollback_contract(&offered_contract.id)
+                .expect("Error rolling back contract");
+            assert!(restored.is_none());
+            assert!(storage
+                .get_contracts()
+                .expect("Error retrieving contracts")
+                .is_empty());
+            storage
+                .rollback_contract(&offered_contract.id)
+                .expect_err("Should not have any journaled update left");
+        }
+    );
+
+    sled_test!(
+        rollback_contract_fails_without_journal,
+        |storage: SledStorageProvider| {
+            let serialized = include_bytes!("../test_files/Offered");
+            let offered_contract: OfferedContract = deserialize_object(serialized);
+            storage
+                .create_contract(&offered_contract)
+                .expect("Error creating contract");
+
+            storage
+                .rollback_contract(&offered_contract.id)
+                .expect_err("Should not journal updates by default");
+        }
+    );
+
+    sled_test!(offer_hash_is_upserted, |storage: SledStorageProvider| {
+        assert_eq!(
+            None,
+            storage.get_contract_id_by_offer_hash(&[1u8; 32]).unwrap()
+        );
+
+        storage.upsert_offer_hash(&[1u8; 32], &[2u8; 32]).unwrap();
+        storage.upsert_offer_hash(&[1u8; 32], &[3u8; 32]).unwrap();
+
+        assert_eq!(
+            Some([3u8; 32]),
+            storage.get_contract_id_by_offer_hash(&[1u8; 32]).unwrap()
+        );
+    });
+
+    sled_test!(
+        punishment_data_is_stored_by_update_idx,
+        |storage: SledStorageProvider| {
+            let signed_channel: SignedChannel =
+                deserialize_object(include_bytes!("../test_files/SignedChannelEstablished"));
+            let punishment_data = |channel_id: u8, update_idx: u64| PunishmentData {
+                channel_id: [channel_id; 32],
+                update_idx,
+                counter_per_update_secret: secp256k1_zkp::SecretKey::from_slice(&[1u8; 32])
+                    .unwrap(),
+                revoked_txid: signed_channel.fund_tx.txid(),
+            };
+            for update_idx in [10, 8, 9] {
+                storage
+                    .upsert_punishment_data(&punishment_data(1, update_idx))
+                    .unwrap();
+            }
+            storage
+                .upsert_punishment_data(&punishment_data(2, 7))
+                .unwrap();
+
+            assert_eq!(
+                Some(punishment_data(1, 9)),
+                storage.get_punishment_data(&[1u8; 32], 9).unwrap()
+            );
+            assert_eq!(None, storage.get_punishment_data(&[1u8; 32], 7).unwrap());
+            assert_eq!(
+                vec![punishment_data(1, 8), punishment_data(1, 9)],
+                storage.get_latest_punishment_data(&[1u8; 32], 2).unwrap()
+            );
+
+            storage.delete_channel(&[1u8; 32]).unwrap();
+            assert!(storage
+                .get_latest_punishment_data(&[1u8; 32], 10)
+                .unwrap()
+                .is_empty());
+            assert_eq!(
+                1,
+                storage
+                    .get_latest_punishment_data(&[2u8; 32], 10)
+                    .unwrap()
+                    .len()
+            );
+        }
+    );
+
+    sled_test!(
+        justice_blob_is_stored_by_locator,
+        |storage: SledStorageProvider| {
+            let blob = JusticeBlob {
+                locator: [1u8; LOCATOR_LEN],
+                encrypted_data: vec![2u8; 64],
+            };
+            storage.upsert_justice_blob(&blob).unwrap();
+
+            assert_eq!(
+                Some(blob),
+                storage.get_justice_blob(&[1u8; LOCATOR_LEN]).unwrap()
+            );
+            assert_eq!(None, storage.get_justice_blob(&[2u8; LOCATOR_LEN]).unwrap());
+        }
+    );
+
+    sled_test!(header_cache_is_persisted, |storage: SledStorageProvider| {
+        assert_eq!(None, storage.get_header_cache().unwrap());
+
+        let header_cache = HeaderCache::new();
+        storage.persist_header_cache(&header_cache).unwrap();
+
+        assert_eq!(Some(header_cache), storage.get_header_cache().unwrap());
+    });
+
+    sled_test!(
+        product_definition_is_upserted,
+        |storage: SledStorageProvider| {
+            assert_eq!(None, storage.get_product_definition("btcusd").unwrap());
+
+            let mut product = ProductDefinition {
+                name: "BTC-USD 28 Jun".to_string(),
+                underlying: "BTC-USD".to_string(),
+                strike_convention: "USD price".to_string(),
+                settlement_source: "Index".to_string(),
+            };
+            storage
+                .upsert_product_definition("btcusd", &product)
+                .unwrap();
+            product.name = "BTC-USD 29 Jun".to_string();
+            storage
+                .upsert_product_definition("btcusd", &product)
+                .unwrap();
+
+            assert_eq!(
+                Some(product),
+                storage.get_product_definition("btcusd").unwrap()
+            );
+        }
+    );
+
+    sled_test!(peer_last_seen_is_updated, |storage: SledStorageProvider| {
+        let peer_id = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+            .parse()
+            .unwrap();
+        assert_eq!(None, storage.get_peer_last_seen(&peer_id).unwrap());
+
+        storage.update_peer_last_seen(&peer_id, 10).unwrap();
+        storage.update_peer_last_seen(&peer_id, 20).unwrap();
+
+        assert_eq!(Some(20), storage.get_peer_last_seen(&peer_id).unwrap());
+    });
+
+    sled_test!(
+        compaction_keeps_records,
         |mut storage: SledStorageProvider| {
             insert_offered_signed_and_confirmed(&mut storage);
+            let contracts = storage.get_contracts().unwrap().len();
+            let tree_sizes = storage.get_tree_sizes().unwrap();
+            assert!(tree_sizes
+                .iter()
+                .any(|t| t.name == "contracts" && t.entries == contracts && t.size > 0));
+
+            storage.set_size_limit(Some(0));
+            assert!(storage.compact_if_above_size_limit().unwrap());
+
+            assert_eq!(contracts, storage.get_contracts().unwrap().len());
+            assert_eq!(tree_sizes, storage.get_tree_sizes().unwrap());
+            assert!(storage.get_size_on_disk().unwrap() > 0);
+        }
+    );
 
-            let signed_contracts = storage
-                .get_signed_contracts()
-                .expect("Error retrieving signed contracts");
+    #[test]
+    fn namespaces_share_a_database() {
+        let path = "test_files/sleddb/namespaces_share_a_database";
+        {
+            let db = sled::open(path).expect("Error opening sled DB");
+            let storage_a = SledStorageProvider::with_namespace(db.clone(), "a");
+            let storage_b = SledStorageProvider::with_namespace(db, "b");
 
-            assert_eq!(2, signed_contracts.len());
+            let serialized = include_bytes!("../test_files/Offered");
+            let offered_contract: OfferedContract = deserialize_object(serialized);
+            storage_a
+                .create_contract(&offered_contract)
+                .expect("Error creating contract");
+
+            assert!(storage_a
+                .get_contract(&offered_contract.id)
+                .unwrap()
+                .is_some());
+            assert!(storage_b
+                .get_contract(&offered_contract.id)
+                .unwrap()
+                .is_none());
+            assert!(storage_b.get_contracts().unwrap().is_empty());
+            assert!(storage_a
+                .get_tree_sizes()
+                .unwrap()
+                .iter()
+                .any(|t| t.name == "contracts" && t.entries == 1));
+            assert!(storage_b
+                .get_tree_sizes()
+                .unwrap()
+                .iter()
+                .all(|t| t.name != "contracts" || t.entries == 0));
+            storage_a
+                .compact()
+                .expect_err("Should not compact a shared database");
         }
-    );
+        std::fs::remove_dir_all(path).unwrap();
+    }
 
     sled_test!(
-        get_confirmed_contracts_only_confirmed,
-        |mut storage: SledStorageProvider| {
-            insert_offered_signed_and_confirmed(&mut storage);
+        contract_history_is_appended,
+        |storage: SledStorageProvider| {
+            let events = vec![
+                ContractEvent {
+                    event_type: ContractEventType::OfferReceived,
+                    timestamp: 10,
+                    trigger: "OfferDlc".to_string(),
+                },
+                ContractEvent {
+                    event_type: ContractEventType::Accepted,
+                    timestamp: 20,
+                    trigger: "accept_contract_offer".to_string(),
+                },
+            ];
+            assert!(storage.get_contract_history(&[1u8; 32]).unwrap().is_empty());
 
-            let confirmed_contracts = storage
-                .get_confirmed_contracts()
-                .expect("Error retrieving signed contracts");
+            for event in &events {
+                storage.append_contract_event(&[1u8; 32], event).unwrap();
+            }
+            storage
+                .append_contract_event(&[2u8; 32], &events[0])
+                .unwrap();
 
-            assert_eq!(2, confirmed_contracts.len());
+            assert_eq!(events, storage.get_contract_history(&[1u8; 32]).unwrap());
+            assert_eq!(1, storage.get_contract_history(&[2u8; 32]).unwrap().len());
         }
     );
 
     sled_test!(
-        get_offered_contracts_only_offered,
-        |mut storage: SledStorageProvider| {
-            insert_offered_signed_and_confirmed(&mut storage);
+        channel_history_is_appended,
+        |storage: SledStorageProvider| {
+            let events = vec![
+                ChannelEvent {
+                    state: "offered".to_string(),
+                    timestamp: 10,
+                    trigger: "OfferChannel".to_string(),
+                },
+                ChannelEvent {
+                    state: "accepted".to_string(),
+                    timestamp: 20,
+                    trigger: "accept_channel".to_string(),
+                },
+            ];
+            assert!(storage.get_channel_history(&[1u8; 32]).unwrap().is_empty());
 
-            let offered_contracts = storage
-                .get_contract_offers()
-                .expect("Error retrieving signed contracts");
+            for event in &events {
+                storage.append_channel_event(&[1u8; 32], event).unwrap();
+            }
+            storage
+                .append_channel_event(&[2u8; 32], &events[0])
+                .unwrap();
 
-            assert_eq!(1, offered_contracts.len());
+            assert_eq!(events, storage.get_channel_history(&[1u8; 32]).unwrap());
+            assert_eq!(1, storage.get_channel_history(&[2u8; 32]).unwrap().len());
         }
     );
 
     sled_test!(
-        get_preclosed_contracts_only_preclosed,
-        |mut storage: SledStorageProvider| {
-            insert_offered_signed_and_confirmed(&mut storage);
-
-            let preclosed_contracts = storage
-                .get_preclosed_contracts()
-                .expect("Error retrieving preclosed contracts");
+        batch_events_are_not_written_when_batch_fails,
+        |storage: SledStorageProvider| {
+            let serialized = include_bytes!("../test_files/Offered");
+            let contract: OfferedContract = deserialize_object(serialized);
+            let event = ContractEvent {
+                event_type: ContractEventType::OfferReceived,
+                timestamp: 10,
+                trigger: "OfferDlc".to_string(),
+            };
+            let batch = StorageBatch::new()
+                .with_new_contract(contract.clone())
+                .with_contract_event(contract.id, event.clone());
+
+            storage.write_batch(batch.clone()).unwrap();
+            match storage.write_batch(batch) {
+                Err(Error::AlreadyExists(_)) => {}
+                res => panic!("Expected an already exists error, got {:?}", res.err()),
+            }
 
-            assert_eq!(1, preclosed_contracts.len());
+            assert_eq!(
+                vec![event],
+                storage.get_contract_history(&contract.id).unwrap()
+            );
         }
     );
+
+    #[test]
+    fn preclosed_contracts_retrieved_after_restart() {
+        let path = "test_files/sleddb/preclosed_contracts_retrieved_after_restart";
+        let serialized = include_bytes!("../test_files/PreClosed");
+        let preclosed_contract: PreClosedContract = deserialize_object(serialized);
+        {
+            let storage = SledStorageProvider::new(path).expect("Error opening sled DB");
+            storage
+                .update_contract(&Contract::PreClosed(preclosed_contract.clone()))
+                .expect("Error updating contract");
+        }
+
+        let storage = SledStorageProvider::new(path).expect("Error opening sled DB");
+        let preclosed_contracts = storage
+            .get_preclosed_contracts()
+            .expect("Error retrieving preclosed contracts");
+
+        assert_eq!(1, preclosed_contracts.len());
+        assert_eq!(
+            preclosed_contract.get_cet_txid(),
+            preclosed_contracts[0].get_cet_txid()
+        );
+        drop(storage);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
     sled_test!(
         get_contracts_all_returned,
         |mut storage: SledStorageProvider| {
@@ -936,6 +3073,57 @@ mod tests {
         }
     );
 
+    sled_test!(
+        channels_are_indexed_by_counterparty_and_contract,
+        |mut storage: SledStorageProvider| {
+            insert_offered_and_signed_channels(&mut storage);
+            let serialized = include_bytes!("../test_files/SignedChannelEstablished");
+            let signed_channel: SignedChannel = deserialize_object(serialized);
+            let contract_id = signed_channel.get_contract_id().unwrap();
+
+            let check = |storage: &SledStorageProvider| {
+                let channels = storage
+                    .get_channels_by_counterparty(&signed_channel.counter_party)
+                    .expect("Error retrieving channels");
+                assert!(channels
+                    .iter()
+                    .any(|c| c.get_id() == signed_channel.channel_id));
+                assert!(channels
+                    .iter()
+                    .all(|c| c.get_counter_party_id() == signed_channel.counter_party));
+                let channel = storage
+                    .get_channel_by_contract_id(&contract_id)
+                    .expect("Error retrieving channel")
+                    .expect("to find the channel");
+                assert_eq!(signed_channel.channel_id, channel.get_id());
+            };
+
+            check(&storage);
+
+            // Databases created before the index existed are indexed on first use.
+            storage
+                .db
+                .read()
+                .unwrap()
+                .drop_tree([CHANNEL_INDEX_TREE])
+                .unwrap();
+            check(&storage);
+
+            storage
+                .delete_channel(&signed_channel.channel_id)
+                .expect("Error deleting channel");
+            assert!(storage
+                .get_channel_by_contract_id(&contract_id)
+                .expect("Error retrieving channel")
+                .is_none());
+            assert!(storage
+                .get_channels_by_counterparty(&signed_channel.counter_party)
+                .expect("Error retrieving channels")
+                .iter()
+                .all(|c| c.get_id() != signed_channel.channel_id));
+        }
+    );
+
     sled_test!(
         delete_channel_is_not_returned,
         |mut storage: SledStorageProvider| {
@@ -963,6 +3151,209 @@ mod tests {
         }
     );
 
+    sled_test!(
+        write_batch_persists_all_records,
+        |storage: SledStorageProvider| {
+            let serialized = include_bytes!("../test_files/Offered");
+            let offered_contract: OfferedContract = deserialize_object(serialized);
+            let serialized = include_bytes!("../test_files/Accepted");
+            let accepted_contract: AcceptedContract = deserialize_object(serialized);
+            let serialized = include_bytes!("../test_files/AcceptedChannel");
+            let accepted_channel: AcceptedChannel = deserialize_object(serialized);
+            let contract_id = accepted_contract.get_contract_id();
+            let temporary_contract_id = accepted_contract.offered_contract.id;
+            let channel_id = accepted_channel.channel_id;
+            let chain_monitor = ChainMonitor::new(123);
+
+            storage
+                .create_contract(&offered_contract)
+                .expect("Error creating contract");
+
+            storage
+                .write_batch(
+                    StorageBatch::new()
+                        .with_contract(Contract::Accepted(accepted_contract))
+                        .with_channel(Channel::Accepted(accepted_channel))
+                        .with_chain_monitor(chain_monitor.clone()),
+                )
+                .expect("to be able to write the batch");
+
+            assert!(storage
+                .get_contract(&temporary_contract_id)
+                .expect("error retrieving contract")
+                .is_none());
+            if let Some(Contract::Accepted(_)) = storage
+                .get_contract(&contract_id)
+                .expect("error retrieving contract")
+            {
+            } else {
+                panic!("Expected accepted contract");
+            }
+            assert!(storage
+                .get_channel(&channel_id)
+                .expect("error retrieving channel")
+                .is_some());
+            assert_eq!(
+                chain_monitor,
+                storage
+                    .get_chain_monitor()
+                    .expect("to be able to retrieve the chain monitor.")
+                    .expect("to have a persisted chain monitor.")
+            );
+        }
+    );
+
+    sled_test!(
+        get_contracts_by_counterparty_test,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+            let contracts = storage.get_contracts().unwrap();
+            let counter_party = contracts[0].get_counter_party_id();
+            let expected = contracts
+                .iter()
+                .filter(|c| c.get_counter_party_id() == counter_party)
+                .count();
+
+            assert_eq!(
+                expected,
+                storage
+                    .get_contracts_by_counterparty(&counter_party)
+                    .expect("to be able to query contracts by counter party")
+                    .len()
+            );
+
+            let other: PublicKey =
+                "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+                    .parse()
+                    .unwrap();
+            assert!(storage
+                .get_contracts_by_counterparty(&other)
+                .unwrap()
+                .is_empty());
+
+            storage
+                .delete_contract(&contracts[0].get_id())
+                .expect("to be able to delete the contract");
+            assert_eq!(
+                expected - 1,
+                storage
+                    .get_contracts_by_counterparty(&counter_party)
+                    .unwrap()
+                    .len()
+            );
+        }
+    );
+
+    sled_test!(
+        counterparty_index_is_built_for_existing_databases,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+            storage
+                .db
+                .read()
+                .unwrap()
+                .drop_tree([COUNTERPARTY_INDEX_TREE])
+                .expect("to be able to drop the index");
+
+            let contracts = storage.get_contracts().unwrap();
+            let counter_party = contracts[0].get_counter_party_id();
+            let expected = contracts
+                .iter()
+                .filter(|c| c.get_counter_party_id() == counter_party)
+                .count();
+            assert_eq!(
+                expected,
+                storage
+                    .get_contracts_by_counterparty(&counter_party)
+                    .unwrap()
+                    .len()
+            );
+        }
+    );
+
+    sled_test!(
+        schema_can_be_upgraded_and_downgraded,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+            assert_eq!(0, storage.get_schema_version().unwrap());
+
+            storage
+                .migrate(SCHEMA_VERSION)
+                .expect("to be able to upgrade");
+            assert_eq!(SCHEMA_VERSION, storage.get_schema_version().unwrap());
+            assert!(storage
+                .open_tree(&[COUNTERPARTY_INDEX_TREE])
+                .unwrap()
+                .contains_key(COUNTERPARTY_INDEX_READY_KEY)
+                .unwrap());
+
+            storage.migrate(0).expect("to be able to downgrade");
+            assert_eq!(0, storage.get_schema_version().unwrap());
+            assert!(storage
+                .open_tree(&[COUNTERPARTY_INDEX_TREE])
+                .unwrap()
+                .is_empty());
+
+            assert!(storage.migrate(SCHEMA_VERSION + 1).is_err());
+        }
+    );
+
+    fn insert_closed(storage: &mut SledStorageProvider) -> ContractId {
+        insert_offered_signed_and_confirmed(storage);
+        let closed = Contract::Closed(deserialize_object(include_bytes!("../test_files/Closed")));
+        storage
+            .update_contract(&closed)
+            .expect("Error updating contract");
+        closed.get_id()
+    }
+
+    sled_test!(
+        prune_closed_contracts_archives_contracts,
+        |mut storage: SledStorageProvider| {
+            let closed_id = insert_closed(&mut storage);
+            let nb_contracts = storage.get_contracts().unwrap().len();
+
+            assert_eq!(
+                0,
+                storage
+                    .prune_closed_contracts(Duration::from_secs(3600))
+                    .expect("to be able to prune contracts")
+            );
+            assert_eq!(
+                1,
+                storage
+                    .prune_closed_contracts(Duration::from_secs(0))
+                    .expect("to be able to prune contracts")
+            );
+
+            let contracts = storage.get_contracts().unwrap();
+            assert_eq!(nb_contracts - 1, contracts.len());
+            assert!(contracts.iter().all(|c| c.get_id() != closed_id));
+            if let Some(Contract::Closed(_)) = storage.get_contract(&closed_id).unwrap() {
+            } else {
+                panic!("Expected archived contract to be retrievable");
+            }
+            assert_eq!(1, storage.get_archived_contracts().unwrap().len());
+        }
+    );
+
+    sled_test!(
+        prune_closed_contracts_deletes_contracts,
+        |mut storage: SledStorageProvider| {
+            storage.set_prune_policy(PrunePolicy::Delete);
+            let closed_id = insert_closed(&mut storage);
+
+            assert_eq!(
+                1,
+                storage
+                    .prune_closed_contracts(Duration::from_secs(0))
+                    .expect("to be able to prune contracts")
+            );
+            assert!(storage.get_contract(&closed_id).unwrap().is_none());
+            assert!(storage.get_archived_contracts().unwrap().is_empty());
+        }
+    );
+
     sled_test!(
         persist_chain_monitor_test,
         |storage: SledStorageProvider| {
@@ -980,4 +3371,365 @@ mod tests {
             assert_eq!(chain_monitor, retrieved);
         }
     );
+
+    const TEST_KEY: [u8; 32] = [3; 32];
+
+    macro_rules! encrypted_sled_test {
+        ($name: ident, $body: expr) => {
+            #[test]
+            fn $name() {
+                let path = format!("{}{}", "test_files/sleddb/", std::stringify!($name));
+                {
+                    #[allow(clippy::redundant_closure_call)]
+                    $body(&path);
+                }
+                std::fs::remove_dir_all(path).unwrap();
+            }
+        };
+    }
+
+    encrypted_sled_test!(encrypted_contract_can_be_retrieved, |path: &str| {
+        let serialized = include_bytes!("../test_files/Offered");
+        let contract: OfferedContract = deserialize_object(serialized);
+
+        let storage =
+            SledStorageProvider::new_encrypted(path, TEST_KEY).expect("Error opening sled DB");
+        storage
+            .create_contract(&contract)
+            .expect("Error creating contract");
+
+        let stored = storage
+            .contract_tree()
+            .unwrap()
+            .get(contract.id)
+            .unwrap()
+            .expect("to have a stored record");
+        assert!(!stored
+            .windows(serialized.len())
+            .any(|w| w == &serialized[..]));
+
+        let retrieved = storage
+            .get_contract(&contract.id)
+            .expect("Error retrieving contract.");
+        if let Some(Contract::Offered(retrieved_offer)) = retrieved {
+            assert_eq!(serialized[..], retrieved_offer.serialize().unwrap()[..]);
+        } else {
+            unreachable!();
+        }
+        assert_eq!(1, storage.get_contract_offers().unwrap().len());
+    });
+
+    encrypted_sled_test!(encoded_record_is_written_in_place, |path: &str| {
+        let serialized = include_bytes!("../test_files/Offered");
+        let contract = Contract::Offered(deserialize_object(serialized));
+        let mut expected = vec![ContractPrefix::get_prefix(&contract)];
+        expected.extend_from_slice(serialized);
+        assert_eq!(expected.len(), contract_serialized_size(&contract));
+
+        let storage =
+            SledStorageProvider::new_encrypted(path, TEST_KEY).expect("Error opening sled DB");
+        let encoded = storage.encode_contract(&contract).unwrap();
+        assert_eq!(NONCE_LEN + expected.len() + TAG_LEN, encoded.len());
+        assert_eq!(
+            expected[..],
+            storage
+                .decrypt(CONTRACT_TREE, &contract.get_id(), &encoded)
+                .unwrap()[..]
+        );
+    });
+
+    encrypted_sled_test!(encrypted_records_cannot_be_swapped, |path: &str| {
+        let mut storage =
+            SledStorageProvider::new_encrypted(path, TEST_KEY).expect("Error opening sled DB");
+        insert_offered_signed_and_confirmed(&mut storage);
+        let contract_tree = storage.contract_tree().unwrap();
+        let mut records = contract_tree.iter().collect::<Result<Vec<_>, _>>().unwrap();
+        let (first_key, first_value) = records.remove(0);
+        let (second_key, second_value) = records.remove(0);
+        contract_tree.insert(&first_key, second_value).unwrap();
+        contract_tree
+            .insert(&second_key, first_value.clone())
+            .unwrap();
+        storage
+            .get_contract(&first_key.as_ref().try_into().unwrap())
+            .expect_err("Should not be able to decrypt a record moved to another key");
+
+        contract_tree
+            .insert(&first_key, first_value.clone())
+            .unwrap();
+        storage
+            .open_tree(&[ARCHIVE_TREE])
+            .unwrap()
+            .insert(&second_key, first_value)
+            .unwrap();
+        contract_tree.remove(&second_key).unwrap();
+        storage
+            .get_contract(&second_key.as_ref().try_into().unwrap())
+            .expect_err("Should not be able to decrypt a record moved to another tree");
+    });
+
+    encrypted_sled_test!(encrypted_index_keys_hide_counter_party, |path: &str| {
+        let mut storage =
+            SledStorageProvider::new_encrypted(path, TEST_KEY).expect("Error opening sled DB");
+        insert_offered_signed_and_confirmed(&mut storage);
+        insert_offered_and_signed_channels(&mut storage);
+        let counter_party = storage.get_contracts().unwrap()[0].get_counter_party_id();
+        storage.update_peer_last_seen(&counter_party, 123).unwrap();
+
+        let serialized = counter_party.serialize();
+        for tree_id in [
+            COUNTERPARTY_INDEX_TREE,
+            CHANNEL_INDEX_TREE,
+            PEER_LAST_SEEN_TREE,
+        ] {
+            for key in storage.open_tree(&[tree_id]).unwrap().iter().keys() {
+                assert!(!key
+                    .unwrap()
+                    .windows(serialized.len())
+                    .any(|w| w == &serialized[..]));
+            }
+        }
+
+        assert!(!storage
+            .get_contracts_by_counterparty(&counter_party)
+            .unwrap()
+            .is_empty());
+        let channel = storage.get_signed_channels(None).unwrap().remove(0);
+        assert!(!storage
+            .get_channels_by_counterparty(&channel.counter_party)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            Some(123),
+            storage.get_peer_last_seen(&counter_party).unwrap()
+        );
+    });
+
+    encrypted_sled_test!(encrypted_storage_rejects_wrong_key, |path: &str| {
+        let serialized = include_bytes!("../test_files/Offered");
+        let contract: OfferedContract = deserialize_object(serialized);
+
+        {
+            let storage =
+                SledStorageProvider::new_encrypted(path, TEST_KEY).expect("Error opening sled DB");
+            storage
+                .create_contract(&contract)
+                .expect("Error creating contract");
+            storage
+                .persist_chain_monitor(&ChainMonitor::new(123))
+                .expect("to be able to persist the chain monitor.");
+        }
+
+        let storage =
+            SledStorageProvider::new_encrypted(path, [4; 32]).expect("Error opening sled DB");
+        storage
+            .get_contract(&contract.id)
+            .expect_err("Should not be able to decrypt with another key");
+        storage
+            .get_chain_monitor()
+            .expect_err("Should not be able to decrypt with another key");
+    });
+
+    encrypted_sled_test!(backup_restores_into_encrypted_storage, |path: &str| {
+        let mut storage = SledStorageProvider::new(path).expect("Error opening sled DB");
+        insert_offered_signed_and_confirmed(&mut storage);
+        insert_offered_and_signed_channels(&mut storage);
+        let chain_monitor = ChainMonitor::new(123);
+        storage
+            .persist_chain_monitor(&chain_monitor)
+            .expect("to be able to persist the chain monitor.");
+
+        let mut backup = Vec::new();
+        storage
+            .export_backup(&mut backup)
+            .expect("to be able to export a backup");
+
+        let restored_path = format!("{}_restored", path);
+        {
+            let restored = SledStorageProvider::new_encrypted(&restored_path, TEST_KEY)
+                .expect("Error opening sled DB");
+            restored
+                .create_contract(&deserialize_object(include_bytes!("../test_files/Offered")))
+                .expect("Error creating contract");
+            restored
+                .import_backup(&mut backup.as_slice())
+                .expect("to be able to import the backup");
+
+            assert_eq!(
+                storage.get_contracts().unwrap().len(),
+                restored.get_contracts().unwrap().len()
+            );
+            assert_eq!(
+                storage.get_signed_channels(None).unwrap().len(),
+                restored.get_signed_channels(None).unwrap().len()
+            );
+            assert_eq!(
+                storage.get_offered_channels().unwrap().len(),
+                restored.get_offered_channels().unwrap().len()
+            );
+            assert_eq!(
+                chain_monitor,
+                restored
+                    .get_chain_monitor()
+                    .expect("to be able to retrieve the chain monitor.")
+                    .expect("to have a restored chain monitor.")
+            );
+
+            let mut second_backup = Vec::new();
+            restored
+                .export_backup(&mut second_backup)
+                .expect("to be able to export a backup");
+            assert_eq!(backup, second_backup);
+        }
+        std::fs::remove_dir_all(restored_path).unwrap();
+    });
+
+    sled_test!(
+        invalid_backup_is_rejected,
+        |storage: SledStorageProvider| {
+            let contract: OfferedContract =
+                deserialize_object(include_bytes!("../test_files/Offered"));
+            storage
+                .create_contract(&contract)
+                .expect("Error creating contract");
+            let mut backup = Vec::new();
+            storage
+                .export_backup(&mut backup)
+                .expect("to be able to export a backup");
+
+            let mut bad_magic = backup.clone();
+            bad_magic[0] ^= 1;
+            storage
+                .import_backup(&mut bad_magic.as_slice())
+                .expect_err("backup with invalid magic should be rejected");
+            storage
+                .import_backup(&mut &backup[..backup.len() - 1])
+                .expect_err("truncated backup should be rejected");
+
+            assert!(storage.get_contract(&contract.id).unwrap().is_some());
+        }
+    );
+
+    #[derive(Default)]
+    struct RecordingMetrics {
+        operations: std::sync::Mutex<Vec<(String, usize, bool)>>,
+    }
+
+    impl StorageMetrics for RecordingMetrics {
+        fn on_operation(
+            &self,
+            operation: &str,
+            _latency: Duration,
+            record_size: usize,
+            success: bool,
+        ) {
+            self.operations
+                .lock()
+                .unwrap()
+                .push((operation.to_string(), record_size, success));
+        }
+    }
+
+    sled_test!(
+        corrupted_records_are_reported,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+            let corrupted_key = [9u8; 32];
+            storage
+                .contract_tree()
+                .unwrap()
+                .insert(corrupted_key, vec![ContractPrefix::Signed.into(), 1, 2, 3])
+                .unwrap();
+
+            assert!(matches!(
+                storage.get_signed_contracts(),
+                Err(Error::StorageError(_))
+            ));
+
+            let corrupted = storage.get_corrupted_records().unwrap();
+            assert_eq!(1, corrupted.len());
+            assert_eq!("contracts", corrupted[0].tree_name);
+            assert_eq!(corrupted_key.to_vec(), corrupted[0].key);
+
+            storage.set_skip_corrupted_records(true);
+            assert_eq!(2, storage.get_signed_contracts().unwrap().len());
+        }
+    );
+
+    sled_test!(
+        snapshot_includes_all_channels_and_contracts,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+            insert_offered_and_signed_channels(&mut storage);
+            let chain_monitor = ChainMonitor::new(123);
+            storage
+                .persist_chain_monitor(&chain_monitor)
+                .expect("Error persisting chain monitor");
+
+            let snapshot = storage.read_snapshot().expect("Error reading snapshot");
+            storage
+                .delete_channel(&snapshot.get_offered_channels()[0].temporary_channel_id)
+                .expect("Error deleting channel");
+
+            assert_eq!(
+                storage.get_contracts().unwrap().len(),
+                snapshot.get_contracts().len()
+            );
+            assert_eq!(2, snapshot.get_signed_contracts().len());
+            assert_eq!(3, snapshot.get_channels().len());
+            assert_eq!(1, snapshot.get_offered_channels().len());
+            assert_eq!(2, snapshot.get_signed_channels(None).len());
+            assert_eq!(Some(&chain_monitor), snapshot.get_chain_monitor());
+            assert!(storage.get_offered_channels().unwrap().is_empty());
+        }
+    );
+
+    sled_test!(
+        writes_are_flushed_when_required,
+        |mut storage: SledStorageProvider| {
+            storage.set_flush_every_write(true);
+            let serialized = include_bytes!("../test_files/Offered");
+            let contract: OfferedContract = deserialize_object(serialized);
+
+            storage
+                .create_contract(&contract)
+                .expect("Error creating contract");
+
+            // Nothing is left to write if the contract was flushed along with
+            // its creation.
+            assert_eq!(0, storage.db.read().unwrap().flush().unwrap());
+        }
+    );
+
+    sled_test!(
+        metrics_are_notified_of_operations,
+        |mut storage: SledStorageProvider| {
+            let metrics = Arc::new(RecordingMetrics::default());
+            storage.set_metrics(metrics.clone());
+            let serialized = include_bytes!("../test_files/Offered");
+            let contract: OfferedContract = deserialize_object(serialized);
+
+            storage
+                .create_contract(&contract)
+                .expect("Error creating contract");
+            storage
+                .get_contract(&contract.id)
+                .expect("Error retrieving contract");
+            storage
+                .get_contract(&[0u8; 32])
+                .expect("Error retrieving contract");
+
+            let operations = metrics.operations.lock().unwrap();
+            // Stored records start with a prefix identifying the contract state.
+            let record_size = serialized.len() + 1;
+            assert_eq!(
+                vec![
+                    ("create_contract".to_string(), record_size, true),
+                    ("get_contract".to_string(), record_size, true),
+                    ("get_contract".to_string(), 0, true),
+                ],
+                *operations
+            );
+        }
+    );
 }
@@ -0,0 +1,1380 @@
+//! # dlc-sqlite-storage-provider
+//! Storage provider for dlc-manager using SQLite as underlying storage.
+
+#![crate_name = "dlc_sqlite_storage_provider"]
+// Coding conventions
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+extern crate dlc_manager;
+extern crate rusqlite;
+extern crate secp256k1_zkp;
+
+use dlc_manager::chain_monitor::ChainMonitor;
+use dlc_manager::channel::accepted_channel::AcceptedChannel;
+use dlc_manager::channel::offered_channel::OfferedChannel;
+use dlc_manager::channel::signed_channel::{SignedChannel, SignedChannelStateType};
+use dlc_manager::channel::{Channel, ChannelEvent, FailedAccept, FailedSign};
+use dlc_manager::contract::accepted_contract::AcceptedContract;
+use dlc_manager::contract::offered_contract::OfferedContract;
+use dlc_manager::contract::ser::Serializable;
+use dlc_manager::contract::signed_contract::SignedContract;
+use dlc_manager::contract::{
+    ClosedContract, CollaborativelyClosedContract, Contract, ContractEvent, FailedAcceptContract,
+    FailedSignContract, PreClosedContract,
+};
+use dlc_manager::product_catalog::ProductDefinition;
+use dlc_manager::storage_prefix::{ChannelPrefix, ContractPrefix, SignedChannelPrefix};
+use dlc_manager::{error::Error, ChannelId, ContractId, Storage, StorageBatch, StorageSnapshot};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use secp256k1_zkp::PublicKey;
+use std::convert::TryInto;
+use std::io::Cursor;
+use std::sync::{Mutex, MutexGuard};
+
+/// Statements used to create and upgrade the database schema. The schema
+/// version of a database corresponds to the number of migrations that were
+/// applied to it, and is stored using the `user_version` pragma.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE contracts (
+        id BLOB PRIMARY KEY NOT NULL,
+        state INTEGER NOT NULL,
+        counter_party BLOB NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE INDEX contracts_state_idx ON contracts (state);
+    CREATE TABLE channels (
+        id BLOB PRIMARY KEY NOT NULL,
+        state INTEGER NOT NULL,
+        signed_state INTEGER,
+        counter_party BLOB NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE INDEX channels_state_idx ON channels (state, signed_state);
+    CREATE TABLE chain_monitor (
+        id INTEGER PRIMARY KEY NOT NULL CHECK (id = 0),
+        data BLOB NOT NULL
+    );
+"#,
+    r#"
+    CREATE INDEX contracts_counter_party_idx ON contracts (counter_party);
+"#,
+    r#"
+    CREATE TABLE peers (
+        id BLOB PRIMARY KEY NOT NULL,
+        last_seen INTEGER NOT NULL
+    );
+"#,
+    r#"
+    CREATE TABLE channel_events (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        temporary_id BLOB NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE INDEX channel_events_temporary_id_idx ON channel_events (temporary_id, seq);
+"#,
+    r#"
+    CREATE TABLE contract_events (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        temporary_id BLOB NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE INDEX contract_events_temporary_id_idx ON contract_events (temporary_id, seq);
+"#,
+    r#"
+    CREATE TABLE offer_hashes (
+        hash BLOB PRIMARY KEY NOT NULL,
+        temporary_id BLOB NOT NULL
+    );
+"#,
+    r#"
+    CREATE INDEX channels_counter_party_idx ON channels (counter_party);
+"#,
+    r#"
+    CREATE TABLE products (
+        event_id TEXT PRIMARY KEY NOT NULL,
+        data BLOB NOT NULL
+    );
+"#,
+];
+
+/// Implementation of Storage interface using the SQLite DB backend.
+pub struct SqliteStorageProvider {
+    connection: Mutex<Connection>,
+}
+
+fn to_storage_error<T>(e: T) -> Error
+where
+    T: std::fmt::Display,
+{
+    Error::StorageError(e.to_string())
+}
+
+impl SqliteStorageProvider {
+    /// Creates a new instance of a SqliteStorageProvider using the database
+    /// file at the given path, creating it if needed.
+    pub fn new(path: &str) -> Result<Self, rusqlite::Error> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Creates a new instance of a SqliteStorageProvider backed by an in memory
+    /// database.
+    pub fn new_in_memory() -> Result<Self, rusqlite::Error> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut connection: Connection) -> Result<Self, rusqlite::Error> {
+        migrate(&mut connection)?;
+        Ok(SqliteStorageProvider {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Returns the version of the schema of the underlying database.
+    pub fn get_schema_version(&self) -> Result<u32, Error> {
+        get_schema_version(&self.connection()?).map_err(to_storage_error)
+    }
+
+    fn connection(&self) -> Result<MutexGuard<Connection>, Error> {
+        self.connection
+            .lock()
+            .map_err(|_| Error::StorageError("Could not acquire database lock".to_string()))
+    }
+
+    fn get_contracts_in_state<T: Serializable>(
+        &self,
+        state: ContractPrefix,
+    ) -> Result<Vec<T>, Error> {
+        let connection = self.connection()?;
+        let mut statement = connection
+            .prepare_cached("SELECT data FROM contracts WHERE state = ?1")
+            .map_err(to_storage_error)?;
+        let rows = statement
+            .query_map(params![u8::from(state)], |row| row.get::<_, Vec<u8>>(0))
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for data in rows {
+            res.push(deserialize_object(&data.map_err(to_storage_error)?)?);
+        }
+        Ok(res)
+    }
+
+    fn get_channels_in_state<T: Serializable>(
+        &self,
+        state: ChannelPrefix,
+        signed_state: Option<u8>,
+    ) -> Result<Vec<T>, Error> {
+        let connection = self.connection()?;
+        let mut statement = connection
+            .prepare_cached(
+                "SELECT data FROM channels WHERE state = ?1 AND (?2 IS NULL OR signed_state = ?2)",
+            )
+            .map_err(to_storage_error)?;
+        let rows = statement
+            .query_map(params![u8::from(state), signed_state], |row| {
+                row.get::<_, Vec<u8>>(0)
+            })
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for data in rows {
+            res.push(deserialize_object(&data.map_err(to_storage_error)?)?);
+        }
+        Ok(res)
+    }
+}
+
+impl Storage for SqliteStorageProvider {
+    fn get_contract(&self, contract_id: &ContractId) -> Result<Option<Contract>, Error> {
+        let res = self
+            .connection()?
+            .query_row(
+                "SELECT state, data FROM contracts WHERE id = ?1",
+                params![&contract_id[..]],
+                |row| Ok((row.get::<_, u8>(0)?, row.get::<_, Vec<u8>>(1)?)),
+            )
+            .optional()
+            .map_err(to_storage_error)?;
+        match res {
+            Some((state, data)) => Ok(Some(deserialize_contract(state, &data)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_contracts(&self) -> Result<Vec<Contract>, Error> {
+        let connection = self.connection()?;
+        let mut statement = connection
+            .prepare_cached("SELECT state, data FROM contracts")
+            .map_err(to_storage_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, u8>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for row in rows {
+            let (state, data) = row.map_err(to_storage_error)?;
+            res.push(deserialize_contract(state, &data)?);
+        }
+        Ok(res)
+    }
+
+    fn get_contracts_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Contract>, Error> {
+        let connection = self.connection()?;
+        let mut statement = connection
+            .prepare_cached("SELECT state, data FROM contracts WHERE counter_party = ?1")
+            .map_err(to_storage_error)?;
+        let rows = statement
+            .query_map(params![&counter_party.serialize()[..]], |row| {
+                Ok((row.get::<_, u8>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for row in rows {
+            let (state, data) = row.map_err(to_storage_error)?;
+            res.push(deserialize_contract(state, &data)?);
+        }
+        Ok(res)
+    }
+
+    fn create_contract(&self, contract: &OfferedContract) -> Result<(), Error> {
+        create_contract(&self.connection()?, contract)
+    }
+
+    fn delete_contract(&self, contract_id: &ContractId) -> Result<(), Error> {
+        self.connection()?
+            .execute(
+                "DELETE FROM contracts WHERE id = ?1",
+                params![&contract_id[..]],
+            )
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn update_contract(&self, contract: &Contract) -> Result<(), Error> {
+        let mut connection = self.connection()?;
+        let tx = connection.transaction().map_err(to_storage_error)?;
+        update_contract(&tx, contract)?;
+        tx.commit().map_err(to_storage_error)
+    }
+
+    fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        self.get_contracts_in_state(ContractPrefix::Offered)
+    }
+
+    fn get_expired_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        self.get_contracts_in_state(ContractPrefix::Expired)
+    }
+
+    fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        self.get_contracts_in_state(ContractPrefix::Signed)
+    }
+
+    fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        self.get_contracts_in_state(ContractPrefix::Confirmed)
+    }
+
+    fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, Error> {
+        self.get_contracts_in_state(ContractPrefix::PreClosed)
+    }
+
+    fn upsert_channel(&self, channel: Channel, contract: Option<Contract>) -> Result<(), Error> {
+        let mut connection = self.connection()?;
+        let tx = connection.transaction().map_err(to_storage_error)?;
+
+        upsert_channel(&tx, &channel)?;
+
+        if let Some(c) = contract.as_ref() {
+            update_contract(&tx, c)?;
+        }
+
+        tx.commit().map_err(to_storage_error)
+    }
+
+    fn delete_channel(&self, channel_id: &ChannelId) -> Result<(), Error> {
+        self.connection()?
+            .execute(
+                "DELETE FROM channels WHERE id = ?1",
+                params![&channel_id[..]],
+            )
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_channel(&self, channel_id: &ChannelId) -> Result<Option<Channel>, Error> {
+        let res = self
+            .connection()?
+            .query_row(
+                "SELECT state, data FROM channels WHERE id = ?1",
+                params![&channel_id[..]],
+                |row| Ok((row.get::<_, u8>(0)?, row.get::<_, Vec<u8>>(1)?)),
+            )
+            .optional()
+            .map_err(to_storage_error)?;
+        match res {
+            Some((state, data)) => Ok(Some(deserialize_channel(state, &data)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_channels_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Channel>, Error> {
+        let connection = self.connection()?;
+        let mut statement = connection
+            .prepare_cached("SELECT state, data FROM channels WHERE counter_party = ?1")
+            .map_err(to_storage_error)?;
+        let rows = statement
+            .query_map(params![&counter_party.serialize()[..]], |row| {
+                Ok((row.get::<_, u8>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for row in rows {
+            let (state, data) = row.map_err(to_storage_error)?;
+            res.push(deserialize_channel(state, &data)?);
+        }
+        Ok(res)
+    }
+
+    fn get_signed_channels(
+        &self,
+        channel_state: Option<SignedChannelStateType>,
+    ) -> Result<Vec<SignedChannel>, Error> {
+        self.get_channels_in_state(
+            ChannelPrefix::Signed,
+            channel_state.as_ref().map(SignedChannelPrefix::get_prefix),
+        )
+    }
+
+    fn get_offered_channels(&self) -> Result<Vec<OfferedChannel>, Error> {
+        self.get_channels_in_state(ChannelPrefix::Offered, None)
+    }
+
+    fn persist_chain_monitor(&self, monitor: &ChainMonitor) -> Result<(), Error> {
+        persist_chain_monitor(&self.connection()?, monitor)
+    }
+
+    fn write_batch(&self, batch: StorageBatch) -> Result<(), Error> {
+        let mut connection = self.connection()?;
+        let tx = connection.transaction().map_err(to_storage_error)?;
+
+        for contract in &batch.new_contracts {
+            create_contract(&tx, contract)?;
+        }
+
+        for channel in &batch.channels {
+            upsert_channel(&tx, channel)?;
+        }
+
+        for contract in &batch.contracts {
+            update_contract(&tx, contract)?;
+        }
+
+        for (temporary_id, event) in &batch.contract_events {
+            append_contract_event(&tx, temporary_id, event)?;
+        }
+
+        for (temporary_id, event) in &batch.channel_events {
+            append_channel_event(&tx, temporary_id, event)?;
+        }
+
+        if let Some(monitor) = batch.chain_monitor.as_ref() {
+            persist_chain_monitor(&tx, monitor)?;
+        }
+
+        tx.commit().map_err(to_storage_error)
+    }
+
+    fn update_peer_last_seen(&self, peer_id: &PublicKey, timestamp: u64) -> Result<(), Error> {
+        self.connection()?
+            .execute(
+                "INSERT OR REPLACE INTO peers (id, last_seen) VALUES (?1, ?2)",
+                params![&peer_id.serialize()[..], timestamp as i64],
+            )
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_peer_last_seen(&self, peer_id: &PublicKey) -> Result<Option<u64>, Error> {
+        let last_seen = self
+            .connection()?
+            .query_row(
+                "SELECT last_seen FROM peers WHERE id = ?1",
+                params![&peer_id.serialize()[..]],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .map_err(to_storage_error)?;
+        Ok(last_seen.map(|x| x as u64))
+    }
+
+    fn upsert_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+        temporary_id: &ContractId,
+    ) -> Result<(), Error> {
+        self.connection()?
+            .execute(
+                "INSERT OR REPLACE INTO offer_hashes (hash, temporary_id) VALUES (?1, ?2)",
+                params![&offer_hash[..], &temporary_id[..]],
+            )
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_contract_id_by_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+    ) -> Result<Option<ContractId>, Error> {
+        let temporary_id = self
+            .connection()?
+            .query_row(
+                "SELECT temporary_id FROM offer_hashes WHERE hash = ?1",
+                params![&offer_hash[..]],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()
+            .map_err(to_storage_error)?;
+        temporary_id
+            .map(|x| {
+                x.as_slice()
+                    .try_into()
+                    .map_err(|_| Error::StorageError("Invalid stored contract id".to_string()))
+            })
+            .transpose()
+    }
+
+    fn upsert_product_definition(
+        &self,
+        event_id: &str,
+        product: &ProductDefinition,
+    ) -> Result<(), Error> {
+        self.connection()?
+            .execute(
+                "INSERT OR REPLACE INTO products (event_id, data) VALUES (?1, ?2)",
+                params![event_id, product.serialize().map_err(to_storage_error)?],
+            )
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_product_definition(&self, event_id: &str) -> Result<Option<ProductDefinition>, Error> {
+        let data = self
+            .connection()?
+            .query_row(
+                "SELECT data FROM products WHERE event_id = ?1",
+                params![event_id],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()
+            .map_err(to_storage_error)?;
+        data.map(|x| deserialize_object(&x)).transpose()
+    }
+
+    fn append_contract_event(
+        &self,
+        temporary_id: &ContractId,
+        event: &ContractEvent,
+    ) -> Result<(), Error> {
+        append_contract_event(&self.connection()?, temporary_id, event)
+    }
+
+    fn get_contract_history(&self, id: &ContractId) -> Result<Vec<ContractEvent>, Error> {
+        let temporary_id = self.get_contract(id)?.map_or(*id, |c| c.get_temporary_id());
+        let connection = self.connection()?;
+        let mut statement = connection
+            .prepare_cached("SELECT data FROM contract_events WHERE temporary_id = ?1 ORDER BY seq")
+            .map_err(to_storage_error)?;
+        let rows = statement
+            .query_map(params![&temporary_id[..]], |row| row.get::<_, Vec<u8>>(0))
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for data in rows {
+            res.push(deserialize_object(&data.map_err(to_storage_error)?)?);
+        }
+        Ok(res)
+    }
+
+    fn append_channel_event(
+        &self,
+        temporary_id: &ChannelId,
+        event: &ChannelEvent,
+    ) -> Result<(), Error> {
+        append_channel_event(&self.connection()?, temporary_id, event)
+    }
+
+    fn get_channel_history(&self, id: &ChannelId) -> Result<Vec<ChannelEvent>, Error> {
+        let history_id = self.get_channel(id)?.map_or(*id, |c| c.get_history_id());
+        let connection = self.connection()?;
+        let mut statement = connection
+            .prepare_cached("SELECT data FROM channel_events WHERE temporary_id = ?1 ORDER BY seq")
+            .map_err(to_storage_error)?;
+        let rows = statement
+            .query_map(params![&history_id[..]], |row| row.get::<_, Vec<u8>>(0))
+            .map_err(to_storage_error)?;
+        let mut res = Vec::new();
+        for data in rows {
+            res.push(deserialize_object(&data.map_err(to_storage_error)?)?);
+        }
+        Ok(res)
+    }
+
+    fn read_snapshot(&self) -> Result<StorageSnapshot, Error> {
+        let mut connection = self.connection()?;
+        let tx = connection.transaction().map_err(to_storage_error)?;
+        let mut contracts = Vec::new();
+        {
+            let mut statement = tx
+                .prepare_cached("SELECT state, data FROM contracts")
+                .map_err(to_storage_error)?;
+            let rows = statement
+                .query_map([], |row| {
+                    Ok((row.get::<_, u8>(0)?, row.get::<_, Vec<u8>>(1)?))
+                })
+                .map_err(to_storage_error)?;
+            for row in rows {
+                let (state, data) = row.map_err(to_storage_error)?;
+                contracts.push(deserialize_contract(state, &data)?);
+            }
+        }
+        let mut channels = Vec::new();
+        {
+            let mut statement = tx
+                .prepare_cached("SELECT state, data FROM channels")
+                .map_err(to_storage_error)?;
+            let rows = statement
+                .query_map([], |row| {
+                    Ok((row.get::<_, u8>(0)?, row.get::<_, Vec<u8>>(1)?))
+                })
+                .map_err(to_storage_error)?;
+            for row in rows {
+                let (state, data) = row.map_err(to_storage_error)?;
+                channels.push(deserialize_channel(state, &data)?);
+            }
+        }
+        let chain_monitor = match tx
+            .query_row("SELECT data FROM chain_monitor WHERE id = 0", [], |row| {
+                row.get::<_, Vec<u8>>(0)
+            })
+            .optional()
+            .map_err(|e| Error::StorageError(format!("Error reading chain monitor: {}", e)))?
+        {
+            Some(s) => Some(deserialize_object(&s)?),
+            None => None,
+        };
+        tx.commit().map_err(to_storage_error)?;
+        Ok(StorageSnapshot::new(contracts, channels, chain_monitor))
+    }
+
+    fn get_chain_monitor(&self) -> Result<Option<ChainMonitor>, Error> {
+        let serialized = self
+            .connection()?
+            .query_row("SELECT data FROM chain_monitor WHERE id = 0", [], |row| {
+                row.get::<_, Vec<u8>>(0)
+            })
+            .optional()
+            .map_err(|e| Error::StorageError(format!("Error reading chain monitor: {}", e)))?;
+        match serialized {
+            Some(s) => Ok(Some(deserialize_object(&s)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn get_schema_version(connection: &Connection) -> Result<u32, rusqlite::Error> {
+    connection.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), rusqlite::Error> {
+    let version = get_schema_version(connection)? as usize;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = connection.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", (i + 1) as u32)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn insert_contract(connection: &Connection, contract: &Contract) -> Result<(), Error> {
+    connection
+        .execute(
+            "INSERT OR REPLACE INTO contracts (id, state, counter_party, data) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                &contract.get_id()[..],
+                ContractPrefix::get_prefix(contract),
+                &contract.get_counter_party_id().serialize()[..],
+                serialize_contract(contract)?,
+            ],
+        )
+        .map_err(to_storage_error)?;
+    Ok(())
+}
+
+fn create_contract(connection: &Connection, contract: &OfferedContract) -> Result<(), Error> {
+    let contract = Contract::Offered(contract.clone());
+    let inserted = connection
+        .execute(
+            "INSERT OR IGNORE INTO contracts (id, state, counter_party, data) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                &contract.get_id()[..],
+                ContractPrefix::get_prefix(&contract),
+                &contract.get_counter_party_id().serialize()[..],
+                serialize_contract(&contract)?,
+            ],
+        )
+        .map_err(to_storage_error)?;
+    if inserted == 0 {
+        return Err(Error::AlreadyExists("Contract already exists".to_string()));
+    }
+    Ok(())
+}
+
+fn update_contract(tx: &Transaction, contract: &Contract) -> Result<(), Error> {
+    match contract {
+        a @ Contract::Accepted(_) | a @ Contract::Signed(_) => {
+            tx.execute(
+                "DELETE FROM contracts WHERE id = ?1",
+                params![&a.get_temporary_id()[..]],
+            )
+            .map_err(to_storage_error)?;
+        }
+        _ => {}
+    };
+
+    insert_contract(tx, contract)
+}
+
+fn upsert_channel(tx: &Transaction, channel: &Channel) -> Result<(), Error> {
+    match channel {
+        a @ Channel::Accepted(_) | a @ Channel::Signed(_) => {
+            tx.execute(
+                "DELETE FROM channels WHERE id = ?1",
+                params![&a.get_temporary_id()[..]],
+            )
+            .map_err(to_storage_error)?;
+        }
+        _ => {}
+    };
+
+    let signed_state = match channel {
+        Channel::Signed(s) => Some(SignedChannelPrefix::get_prefix(&s.state.get_type())),
+        _ => None,
+    };
+
+    tx.execute(
+        "INSERT OR REPLACE INTO channels (id, state, signed_state, counter_party, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            &channel.get_id()[..],
+            ChannelPrefix::get_prefix(channel),
+            signed_state,
+            &channel.get_counter_party_id().serialize()[..],
+            serialize_channel(channel)?,
+        ],
+    )
+    .map_err(to_storage_error)?;
+
+    Ok(())
+}
+
+fn append_contract_event(
+    connection: &Connection,
+    temporary_id: &ContractId,
+    event: &ContractEvent,
+) -> Result<(), Error> {
+    connection
+        .execute(
+            "INSERT INTO contract_events (temporary_id, data) VALUES (?1, ?2)",
+            params![
+                &temporary_id[..],
+                event.serialize().map_err(to_storage_error)?
+            ],
+        )
+        .map_err(to_storage_error)?;
+    Ok(())
+}
+
+fn append_channel_event(
+    connection: &Connection,
+    temporary_id: &ChannelId,
+    event: &ChannelEvent,
+) -> Result<(), Error> {
+    connection
+        .execute(
+            "INSERT INTO channel_events (temporary_id, data) VALUES (?1, ?2)",
+            params![
+                &temporary_id[..],
+                event.serialize().map_err(to_storage_error)?
+            ],
+        )
+        .map_err(to_storage_error)?;
+    Ok(())
+}
+
+fn persist_chain_monitor(connection: &Connection, monitor: &ChainMonitor) -> Result<(), Error> {
+    connection
+        .execute(
+            "INSERT OR REPLACE INTO chain_monitor (id, data) VALUES (0, ?1)",
+            params![monitor.serialize()?],
+        )
+        .map_err(|e| Error::StorageError(format!("Error writing chain monitor: {}", e)))?;
+    Ok(())
+}
+
+fn deserialize_object<T: Serializable>(data: &[u8]) -> Result<T, Error> {
+    T::deserialize(&mut Cursor::new(data)).map_err(to_storage_error)
+}
+
+fn serialize_contract(contract: &Contract) -> Result<Vec<u8>, ::std::io::Error> {
+    match contract {
+        Contract::Offered(o) | Contract::Rejected(o) | Contract::Expired(o) => o.serialize(),
+        Contract::Accepted(o) => o.serialize(),
+        Contract::Signed(o)
+        | Contract::Confirmed(o)
+        | Contract::Refunded(o)
+        | Contract::Amended(o) => o.serialize(),
+        Contract::FailedAccept(c) => c.serialize(),
+        Contract::FailedSign(c) => c.serialize(),
+        Contract::PreClosed(c) => c.serialize(),
+        Contract::CollaborativelyClosed(c) => c.serialize(),
+        Contract::Closed(c) => c.serialize(),
+    }
+}
+
+fn deserialize_contract(state: u8, data: &[u8]) -> Result<Contract, Error> {
+    let contract_state: ContractPrefix = state.try_into()?;
+    let contract = match contract_state {
+        ContractPrefix::Offered => Contract::Offered(deserialize_object::<OfferedContract>(data)?),
+        ContractPrefix::Accepted => {
+            Contract::Accepted(deserialize_object::<AcceptedContract>(data)?)
+        }
+        ContractPrefix::Signed => Contract::Signed(deserialize_object::<SignedContract>(data)?),
+        ContractPrefix::Confirmed => {
+            Contract::Confirmed(deserialize_object::<SignedContract>(data)?)
+        }
+        ContractPrefix::PreClosed => {
+            Contract::PreClosed(deserialize_object::<PreClosedContract>(data)?)
+        }
+        ContractPrefix::Closed => Contract::Closed(deserialize_object::<ClosedContract>(data)?),
+        ContractPrefix::FailedAccept => {
+            Contract::FailedAccept(deserialize_object::<FailedAcceptContract>(data)?)
+        }
+        ContractPrefix::FailedSign => {
+            Contract::FailedSign(deserialize_object::<FailedSignContract>(data)?)
+        }
+        ContractPrefix::Refunded => Contract::Refunded(deserialize_object::<SignedContract>(data)?),
+        ContractPrefix::Rejected => {
+            Contract::Rejected(deserialize_object::<OfferedContract>(data)?)
+        }
+        ContractPrefix::Expired => Contract::Expired(deserialize_object::<OfferedContract>(data)?),
+        ContractPrefix::Amended => Contract::Amended(deserialize_object::<SignedContract>(data)?),
+        ContractPrefix::CollaborativelyClosed => Contract::CollaborativelyClosed(
+            deserialize_object::<CollaborativelyClosedContract>(data)?,
+        ),
+    };
+    Ok(contract)
+}
+
+fn serialize_channel(channel: &Channel) -> Result<Vec<u8>, ::std::io::Error> {
+    match channel {
+        Channel::Offered(o) => o.serialize(),
+        Channel::Accepted(a) => a.serialize(),
+        Channel::Signed(s) => s.serialize(),
+        Channel::FailedAccept(f) => f.serialize(),
+        Channel::FailedSign(f) => f.serialize(),
+        Channel::Cancelled(o) => o.serialize(),
+    }
+}
+
+fn deserialize_channel(state: u8, data: &[u8]) -> Result<Channel, Error> {
+    let channel_state: ChannelPrefix = state.try_into()?;
+    let channel = match channel_state {
+        ChannelPrefix::Offered => Channel::Offered(deserialize_object::<OfferedChannel>(data)?),
+        ChannelPrefix::Accepted => Channel::Accepted(deserialize_object::<AcceptedChannel>(data)?),
+        ChannelPrefix::Signed => Channel::Signed(deserialize_object::<SignedChannel>(data)?),
+        ChannelPrefix::FailedAccept => {
+            Channel::FailedAccept(deserialize_object::<FailedAccept>(data)?)
+        }
+        ChannelPrefix::FailedSign => Channel::FailedSign(deserialize_object::<FailedSign>(data)?),
+        ChannelPrefix::Cancelled => Channel::Cancelled(deserialize_object::<OfferedChannel>(data)?),
+    };
+    Ok(channel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dlc_manager::contract::ContractEventType;
+
+    macro_rules! sqlite_test {
+        ($name: ident, $body: expr) => {
+            #[test]
+            fn $name() {
+                let storage =
+                    SqliteStorageProvider::new_in_memory().expect("Error opening SQLite DB");
+                #[allow(clippy::redundant_closure_call)]
+                $body(storage);
+            }
+        };
+    }
+
+    fn deserialize_test_object<T>(serialized: &[u8]) -> T
+    where
+        T: Serializable,
+    {
+        let mut cursor = std::io::Cursor::new(&serialized);
+        T::deserialize(&mut cursor).unwrap()
+    }
+
+    fn insert_offered_signed_and_confirmed(storage: &SqliteStorageProvider) {
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+        let offered_contract = deserialize_test_object(serialized);
+        storage
+            .create_contract(&offered_contract)
+            .expect("Error creating contract");
+
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Signed");
+        let signed_contract = Contract::Signed(deserialize_test_object(serialized));
+        storage
+            .update_contract(&signed_contract)
+            .expect("Error creating contract");
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Signed1");
+        let signed_contract = Contract::Signed(deserialize_test_object(serialized));
+        storage
+            .update_contract(&signed_contract)
+            .expect("Error creating contract");
+
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Confirmed");
+        let confirmed_contract = Contract::Confirmed(deserialize_test_object(serialized));
+        storage
+            .update_contract(&confirmed_contract)
+            .expect("Error creating contract");
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Confirmed1");
+        let confirmed_contract = Contract::Confirmed(deserialize_test_object(serialized));
+        storage
+            .update_contract(&confirmed_contract)
+            .expect("Error creating contract");
+
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/PreClosed");
+        let preclosed_contract = Contract::PreClosed(deserialize_test_object(serialized));
+        storage
+            .update_contract(&preclosed_contract)
+            .expect("Error creating contract");
+    }
+
+    fn insert_offered_and_signed_channels(storage: &SqliteStorageProvider) {
+        let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+        let offered_contract = deserialize_test_object(serialized);
+        let serialized =
+            include_bytes!("../../dlc-sled-storage-provider/test_files/OfferedChannel");
+        let offered_channel = deserialize_test_object(serialized);
+        storage
+            .upsert_channel(
+                Channel::Offered(offered_channel),
+                Some(Contract::Offered(offered_contract)),
+            )
+            .expect("Error creating contract");
+
+        let serialized =
+            include_bytes!("../../dlc-sled-storage-provider/test_files/SignedChannelEstablished");
+        let signed_channel = Channel::Signed(deserialize_test_object(serialized));
+        storage
+            .upsert_channel(signed_channel, None)
+            .expect("Error creating contract");
+
+        let serialized =
+            include_bytes!("../../dlc-sled-storage-provider/test_files/SignedChannelSettled");
+        let signed_channel = Channel::Signed(deserialize_test_object(serialized));
+        storage
+            .upsert_channel(signed_channel, None)
+            .expect("Error creating contract");
+    }
+
+    sqlite_test!(
+        schema_is_at_latest_version,
+        |storage: SqliteStorageProvider| {
+            assert_eq!(
+                MIGRATIONS.len() as u32,
+                storage
+                    .get_schema_version()
+                    .expect("to be able to read the schema version")
+            );
+        }
+    );
+
+    sqlite_test!(offer_hash_is_upserted, |storage: SqliteStorageProvider| {
+        assert_eq!(
+            None,
+            storage.get_contract_id_by_offer_hash(&[1u8; 32]).unwrap()
+        );
+
+        storage.upsert_offer_hash(&[1u8; 32], &[2u8; 32]).unwrap();
+        storage.upsert_offer_hash(&[1u8; 32], &[3u8; 32]).unwrap();
+
+        assert_eq!(
+            Some([3u8; 32]),
+            storage.get_contract_id_by_offer_hash(&[1u8; 32]).unwrap()
+        );
+    });
+
+    sqlite_test!(
+        product_definition_is_upserted,
+        |storage: SqliteStorageProvider| {
+            assert_eq!(None, storage.get_product_definition("btcusd").unwrap());
+
+            let mut product = ProductDefinition {
+                name: "BTC-USD 28 Jun".to_string(),
+                underlying: "BTC-USD".to_string(),
+                strike_convention: "USD price".to_string(),
+                settlement_source: "Index".to_string(),
+            };
+            storage
+                .upsert_product_definition("btcusd", &product)
+                .unwrap();
+            product.name = "BTC-USD 29 Jun".to_string();
+            storage
+                .upsert_product_definition("btcusd", &product)
+                .unwrap();
+
+            assert_eq!(
+                Some(product),
+                storage.get_product_definition("btcusd").unwrap()
+            );
+        }
+    );
+
+    sqlite_test!(
+        peer_last_seen_is_updated,
+        |storage: SqliteStorageProvider| {
+            let peer_id = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+                .parse()
+                .unwrap();
+            assert_eq!(None, storage.get_peer_last_seen(&peer_id).unwrap());
+
+            storage.update_peer_last_seen(&peer_id, 10).unwrap();
+            storage.update_peer_last_seen(&peer_id, 20).unwrap();
+
+            assert_eq!(Some(20), storage.get_peer_last_seen(&peer_id).unwrap());
+        }
+    );
+
+    sqlite_test!(
+        contract_history_is_appended,
+        |storage: SqliteStorageProvider| {
+            let events = vec![
+                ContractEvent {
+                    event_type: ContractEventType::OfferReceived,
+                    timestamp: 10,
+                    trigger: "OfferDlc".to_string(),
+                },
+                ContractEvent {
+                    event_type: ContractEventType::Accepted,
+                    timestamp: 20,
+                    trigger: "accept_contract_offer".to_string(),
+                },
+            ];
+            assert!(storage.get_contract_history(&[1u8; 32]).unwrap().is_empty());
+
+            for event in &events {
+                storage.append_contract_event(&[1u8; 32], event).unwrap();
+            }
+            storage
+                .append_contract_event(&[2u8; 32], &events[0])
+                .unwrap();
+
+            assert_eq!(events, storage.get_contract_history(&[1u8; 32]).unwrap());
+            assert_eq!(1, storage.get_contract_history(&[2u8; 32]).unwrap().len());
+        }
+    );
+
+    sqlite_test!(
+        channel_history_is_appended,
+        |storage: SqliteStorageProvider| {
+            let events = vec![
+                ChannelEvent {
+                    state: "offered".to_string(),
+                    timestamp: 10,
+                    trigger: "OfferChannel".to_string(),
+                },
+                ChannelEvent {
+                    state: "accepted".to_string(),
+                    timestamp: 20,
+                    trigger: "accept_channel".to_string(),
+                },
+            ];
+            assert!(storage.get_channel_history(&[1u8; 32]).unwrap().is_empty());
+
+            for event in &events {
+                storage.append_channel_event(&[1u8; 32], event).unwrap();
+            }
+            storage
+                .append_channel_event(&[2u8; 32], &events[0])
+                .unwrap();
+
+            assert_eq!(events, storage.get_channel_history(&[1u8; 32]).unwrap());
+            assert_eq!(1, storage.get_channel_history(&[2u8; 32]).unwrap().len());
+        }
+    );
+
+    sqlite_test!(
+        batch_events_are_not_written_when_batch_fails,
+        |storage: SqliteStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let contract: OfferedContract = deserialize_test_object(serialized);
+            let event = ContractEvent {
+                event_type: ContractEventType::OfferReceived,
+                timestamp: 10,
+                trigger: "OfferDlc".to_string(),
+            };
+            let batch = StorageBatch::new()
+                .with_new_contract(contract.clone())
+                .with_contract_event(contract.id, event.clone());
+
+            storage.write_batch(batch.clone()).unwrap();
+            match storage.write_batch(batch) {
+                Err(Error::AlreadyExists(_)) => {}
+                res => panic!("Expected an already exists error, got {:?}", res.err()),
+            }
+
+            assert_eq!(
+                vec![event],
+                storage.get_contract_history(&contract.id).unwrap()
+            );
+        }
+    );
+
+    sqlite_test!(
+        create_contract_can_be_retrieved,
+        |storage: SqliteStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let contract = deserialize_test_object(serialized);
+
+            storage
+                .create_contract(&contract)
+                .expect("Error creating contract");
+
+            let retrieved = storage
+                .get_contract(&contract.id)
+                .expect("Error retrieving contract.");
+
+            if let Some(Contract::Offered(retrieved_offer)) = retrieved {
+                assert_eq!(serialized[..], retrieved_offer.serialize().unwrap()[..]);
+            } else {
+                unreachable!();
+            }
+        }
+    );
+
+    sqlite_test!(
+        create_contract_fails_if_contract_exists,
+        |storage: SqliteStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let contract: OfferedContract = deserialize_test_object(serialized);
+
+            storage
+                .create_contract(&contract)
+                .expect("Error creating contract");
+
+            assert!(matches!(
+                storage.create_contract(&contract),
+                Err(Error::AlreadyExists(_))
+            ));
+            storage
+                .replace_contract(&contract)
+                .expect("Error replacing contract");
+            assert_eq!(
+                1,
+                storage
+                    .get_contract_offers()
+                    .expect("Error retrieving contracts")
+                    .len()
+            );
+        }
+    );
+
+    sqlite_test!(
+        update_contract_is_updated,
+        |storage: SqliteStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let offered_contract = deserialize_test_object(serialized);
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Accepted");
+            let accepted_contract = Contract::Accepted(deserialize_test_object(serialized));
+
+            storage
+                .create_contract(&offered_contract)
+                .expect("Error creating contract");
+
+            storage
+                .update_contract(&accepted_contract)
+                .expect("Error updating contract.");
+            let retrieved = storage
+                .get_contract(&accepted_contract.get_id())
+                .expect("Error retrieving contract.");
+
+            if let Some(Contract::Accepted(_)) = retrieved {
+            } else {
+                unreachable!();
+            }
+        }
+    );
+
+    sqlite_test!(
+        delete_contract_is_deleted,
+        |storage: SqliteStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let contract = deserialize_test_object(serialized);
+            storage
+                .create_contract(&contract)
+                .expect("Error creating contract");
+
+            storage
+                .delete_contract(&contract.id)
+                .expect("Error deleting contract");
+
+            assert!(storage
+                .get_contract(&contract.id)
+                .expect("Error querying contract")
+                .is_none());
+        }
+    );
+
+    sqlite_test!(get_contracts_by_state, |storage: SqliteStorageProvider| {
+        insert_offered_signed_and_confirmed(&storage);
+
+        assert_eq!(
+            1,
+            storage
+                .get_contract_offers()
+                .expect("Error retrieving offered contracts")
+                .len()
+        );
+        assert_eq!(
+            2,
+            storage
+                .get_signed_contracts()
+                .expect("Error retrieving signed contracts")
+                .len()
+        );
+        assert_eq!(
+            2,
+            storage
+                .get_confirmed_contracts()
+                .expect("Error retrieving confirmed contracts")
+                .len()
+        );
+        assert_eq!(
+            1,
+            storage
+                .get_preclosed_contracts()
+                .expect("Error retrieving preclosed contracts")
+                .len()
+        );
+        assert_eq!(
+            6,
+            storage
+                .get_contracts()
+                .expect("Error retrieving contracts")
+                .len()
+        );
+    });
+
+    sqlite_test!(get_channels_by_state, |storage: SqliteStorageProvider| {
+        insert_offered_and_signed_channels(&storage);
+
+        assert_eq!(
+            1,
+            storage
+                .get_offered_channels()
+                .expect("Error retrieving offered channels")
+                .len()
+        );
+        assert_eq!(
+            2,
+            storage
+                .get_signed_channels(None)
+                .expect("Error retrieving signed channels")
+                .len()
+        );
+
+        let signed_channels = storage
+            .get_signed_channels(Some(SignedChannelStateType::Established))
+            .expect("Error retrieving established channels");
+        assert_eq!(1, signed_channels.len());
+        if let dlc_manager::channel::signed_channel::SignedChannelState::Established { .. } =
+            &signed_channels[0].state
+        {
+        } else {
+            panic!(
+                "Expected established state got {:?}",
+                &signed_channels[0].state
+            );
+        }
+    });
+
+    sqlite_test!(
+        get_channels_by_counterparty_and_contract_id,
+        |storage: SqliteStorageProvider| {
+            insert_offered_and_signed_channels(&storage);
+            let serialized = include_bytes!(
+                "../../dlc-sled-storage-provider/test_files/SignedChannelEstablished"
+            );
+            let signed_channel: SignedChannel = deserialize_test_object(serialized);
+
+            let channels = storage
+                .get_channels_by_counterparty(&signed_channel.counter_party)
+                .expect("Error retrieving channels");
+            assert!(channels
+                .iter()
+                .any(|c| c.get_id() == signed_channel.channel_id));
+            assert!(channels
+                .iter()
+                .all(|c| c.get_counter_party_id() == signed_channel.counter_party));
+
+            let channel = storage
+                .get_channel_by_contract_id(&signed_channel.get_contract_id().unwrap())
+                .expect("Error retrieving channel")
+                .expect("to find the channel");
+            assert_eq!(signed_channel.channel_id, channel.get_id());
+        }
+    );
+
+    sqlite_test!(
+        delete_channel_is_not_returned,
+        |storage: SqliteStorageProvider| {
+            insert_offered_and_signed_channels(&storage);
+
+            let serialized =
+                include_bytes!("../../dlc-sled-storage-provider/test_files/AcceptedChannel");
+            let accepted_channel: AcceptedChannel = deserialize_test_object(serialized);
+            let channel_id = accepted_channel.channel_id;
+            storage
+                .upsert_channel(Channel::Accepted(accepted_channel), None)
+                .expect("Error creating channel");
+
+            storage
+                .get_channel(&channel_id)
+                .expect("error retrieving previously inserted channel.")
+                .expect("to have found the previously inserted channel.");
+
+            storage
+                .delete_channel(&channel_id)
+                .expect("to be able to delete the channel");
+
+            assert!(storage
+                .get_channel(&channel_id)
+                .expect("error getting channel.")
+                .is_none());
+        }
+    );
+
+    sqlite_test!(
+        write_batch_persists_all_records,
+        |storage: SqliteStorageProvider| {
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Offered");
+            let offered_contract: OfferedContract = deserialize_test_object(serialized);
+            let serialized = include_bytes!("../../dlc-sled-storage-provider/test_files/Accepted");
+            let accepted_contract: AcceptedContract = deserialize_test_object(serialized);
+            let serialized =
+                include_bytes!("../../dlc-sled-storage-provider/test_files/AcceptedChannel");
+            let accepted_channel: AcceptedChannel = deserialize_test_object(serialized);
+            let contract_id = accepted_contract.get_contract_id();
+            let temporary_contract_id = accepted_contract.offered_contract.id;
+            let channel_id = accepted_channel.channel_id;
+            let chain_monitor = ChainMonitor::new(123);
+
+            storage
+                .create_contract(&offered_contract)
+                .expect("Error creating contract");
+
+            storage
+                .write_batch(
+                    StorageBatch::new()
+                        .with_contract(Contract::Accepted(accepted_contract))
+                        .with_channel(Channel::Accepted(accepted_channel))
+                        .with_chain_monitor(chain_monitor.clone()),
+                )
+                .expect("to be able to write the batch");
+
+            assert!(storage
+                .get_contract(&temporary_contract_id)
+                .expect("error retrieving contract")
+                .is_none());
+            if let Some(Contract::Accepted(_)) = storage
+                .get_contract(&contract_id)
+                .expect("error retrieving contract")
+            {
+            } else {
+                panic!("Expected accepted contract");
+            }
+            assert!(storage
+                .get_channel(&channel_id)
+                .expect("error retrieving channel")
+                .is_some());
+            assert_eq!(
+                chain_monitor,
+                storage
+                    .get_chain_monitor()
+                    .expect("to be able to retrieve the chain monitor.")
+                    .expect("to have a persisted chain monitor.")
+            );
+        }
+    );
+
+    sqlite_test!(
+        get_contracts_by_counterparty_test,
+        |storage: SqliteStorageProvider| {
+            insert_offered_signed_and_confirmed(&storage);
+            let contracts = storage.get_contracts().unwrap();
+            let counter_party = contracts[0].get_counter_party_id();
+            let expected = contracts
+                .iter()
+                .filter(|c| c.get_counter_party_id() == counter_party)
+                .count();
+
+            assert_eq!(
+                expected,
+                storage
+                    .get_contracts_by_counterparty(&counter_party)
+                    .expect("to be able to query contracts by counter party")
+                    .len()
+            );
+
+            let other: PublicKey =
+                "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+                    .parse()
+                    .unwrap();
+            assert!(storage
+                .get_contracts_by_counterparty(&other)
+                .unwrap()
+                .is_empty());
+        }
+    );
+
+    sqlite_test!(
+        persist_chain_monitor_test,
+        |storage: SqliteStorageProvider| {
+            let chain_monitor = ChainMonitor::new(123);
+
+            storage
+                .persist_chain_monitor(&chain_monitor)
+                .expect("to be able to persist the chain monitor.");
+
+            let retrieved = storage
+                .get_chain_monitor()
+                .expect("to be able to retrieve the chain monitor.")
+                .expect("to have a persisted chain monitor.");
+
+            assert_eq!(chain_monitor, retrieved);
+        }
+    );
+}
@@ -0,0 +1,111 @@
+//! # Command line tool to inspect, migrate and dump the sled database of a
+//! DLC node. The node must be stopped while the tool is running, as sled does
+//! not allow opening the same database from multiple processes.
+
+use dlc_manager::error::Error;
+use dlc_manager::Storage;
+use dlc_sled_storage_provider::{SledStorageProvider, SCHEMA_VERSION};
+use serde_json::json;
+use std::env;
+use std::process;
+
+const USAGE: &str = "Usage: dlc-storage-migrate <db-path> [--key <hex-key>] <command>
+
+Commands:
+    info                Print the schema version and the size of the database
+    migrate <version>   Upgrade or downgrade the database to the given schema version
+    dump                Print the contracts and channels of the database as JSON";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let (path, rest) = args.split_first().ok_or(USAGE)?;
+    let (key, command) = match rest {
+        [flag, key, command @ ..] if flag == "--key" => (Some(parse_key(key)?), command),
+        command => (None, command),
+    };
+
+    let storage = match key {
+        Some(key) => SledStorageProvider::new_encrypted(path, key),
+        None => SledStorageProvider::new(path),
+    }
+    .map_err(|e| format!("Could not open database {}: {}", path, e))?;
+
+    match command {
+        [command] if command == "info" => info(&storage),
+        [command, version] if command == "migrate" => {
+            let version = version
+                .parse()
+                .map_err(|_| format!("Invalid schema version {}", version))?;
+            migrate(&storage, version)
+        }
+        [command] if command == "dump" => dump(&storage),
+        _ => Err(USAGE.to_string()),
+    }
+    .map_err(|e| e.to_string())
+}
+
+fn info(storage: &SledStorageProvider) -> Result<(), Error> {
+    println!(
+        "Schema version: {} (latest supported: {})",
+        storage.get_schema_version()?,
+        SCHEMA_VERSION
+    );
+    println!("Size on disk: {} bytes", storage.get_size_on_disk()?);
+    for tree in storage.get_tree_sizes()? {
+        println!(
+            "  {}: {} records, {} bytes",
+            tree.name, tree.entries, tree.size
+        );
+    }
+    let corrupted = storage.get_corrupted_records()?;
+    if !corrupted.is_empty() {
+        println!("Corrupted records: {}", corrupted.len());
+        for record in corrupted {
+            println!(
+                "  {} {:02x?}: {}",
+                record.tree_name, record.key, record.error
+            );
+        }
+    }
+    Ok(())
+}
+
+fn migrate(storage: &SledStorageProvider, version: u16) -> Result<(), Error> {
+    let previous = storage.get_schema_version()?;
+    storage.migrate(version)?;
+    println!("Migrated schema from version {} to {}", previous, version);
+    Ok(())
+}
+
+fn dump(storage: &SledStorageProvider) -> Result<(), Error> {
+    let snapshot = storage.read_snapshot()?;
+    let dump = json!({
+        "schemaVersion": storage.get_schema_version()?,
+        "contracts": snapshot.get_contracts(),
+        "archivedContracts": storage.get_archived_contracts()?,
+        "channels": snapshot.get_channels(),
+    });
+    let serialized = serde_json::to_string_pretty(&dump)
+        .map_err(|e| Error::InvalidState(format!("Could not serialize dump: {}", e)))?;
+    println!("{}", serialized);
+    Ok(())
+}
+
+fn parse_key(hex: &str) -> Result<[u8; 32], String> {
+    let invalid = || "The key must be 32 bytes encoded in hexadecimal".to_string();
+    if hex.len() != 64 {
+        return Err(invalid());
+    }
+    let mut key = [0u8; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(key)
+}
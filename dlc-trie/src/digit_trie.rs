@@ -15,6 +15,11 @@ pub struct DigitTrie<T> {
 }
 
 /// Container for a dump of a DigitTrie used for serialization purpose.
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct DigitTrieDump<T>
 where
     T: Clone,
@@ -54,6 +59,11 @@ where
 }
 
 /// External representation of a node used for serialization purpose.
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct DigitNodeData<T> {
     /// The data contained in the node.
     pub data: Option<T>,
@@ -35,6 +35,7 @@ pub mod multi_oracle;
 pub mod multi_oracle_trie;
 pub mod multi_oracle_trie_with_diff;
 pub mod multi_trie;
+pub mod precomputed_points;
 #[cfg(test)]
 mod test_utils;
 mod utils;
@@ -64,6 +65,11 @@ pub enum Node<TLeaf, TNode> {
 }
 
 #[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
 /// Structure that stores the indexes at which the CET and adaptor signature
 /// related to a given outcome are located in CET and adaptor signatures arrays
 /// respectively.
@@ -213,6 +219,17 @@ pub trait DlcTrie<'a, TrieIterator: Iterator<Item = TrieIterInfo>> {
             trie_info,
         )
     }
+
+    /// Returns the index of the CET and the adaptor point of each adaptor
+    /// signature related to the trie, ordered by adaptor signature index. This
+    /// enables the signatures to be produced by a signer that does not have
+    /// access to the trie.
+    fn get_adaptor_points(
+        &'a self,
+        precomputed_points: &[Vec<Vec<PublicKey>>],
+    ) -> Result<Vec<(usize, PublicKey)>, Error> {
+        adaptor_points_helper(precomputed_points, self.iter())
+    }
 }
 
 #[derive(Debug)]
@@ -223,6 +240,46 @@ pub struct TrieIterInfo {
     value: RangeInfo,
 }
 
+#[cfg(not(feature = "parallel"))]
+fn adaptor_points_helper<T: Iterator<Item = TrieIterInfo>>(
+    precomputed_points: &[Vec<Vec<PublicKey>>],
+    trie_info: T,
+) -> Result<Vec<(usize, PublicKey)>, Error> {
+    let mut unsorted = trie_info
+        .map(|x| {
+            let adaptor_point = utils::get_adaptor_point_for_indexed_paths(
+                &x.indexes,
+                &x.paths,
+                precomputed_points,
+            )?;
+            Ok((x.value.adaptor_index, x.value.cet_index, adaptor_point))
+        })
+        .collect::<Result<Vec<(usize, usize, PublicKey)>, Error>>()?;
+    unsorted.sort_by_key(|x| x.0);
+    Ok(unsorted.into_iter().map(|(_, x, y)| (x, y)).collect())
+}
+
+#[cfg(feature = "parallel")]
+fn adaptor_points_helper<T: Iterator<Item = TrieIterInfo>>(
+    precomputed_points: &[Vec<Vec<PublicKey>>],
+    trie_info: T,
+) -> Result<Vec<(usize, PublicKey)>, Error> {
+    let trie_info: Vec<TrieIterInfo> = trie_info.collect();
+    let mut unsorted = trie_info
+        .par_iter()
+        .map(|x| {
+            let adaptor_point = utils::get_adaptor_point_for_indexed_paths(
+                &x.indexes,
+                &x.paths,
+                precomputed_points,
+            )?;
+            Ok((x.value.adaptor_index, x.value.cet_index, adaptor_point))
+        })
+        .collect::<Result<Vec<(usize, usize, PublicKey)>, Error>>()?;
+    unsorted.sort_by_key(|x| x.0);
+    Ok(unsorted.into_iter().map(|(_, x, y)| (x, y)).collect())
+}
+
 #[cfg(not(feature = "parallel"))]
 fn sign_helper<T: Iterator<Item = TrieIterInfo>>(
     secp: &Secp256k1<All>,
@@ -24,6 +24,11 @@ pub struct MultiOracleTrie {
 }
 
 /// Container for a dump of a MultiOracleTrie used for serialization purpose.
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct MultiOracleTrieDump {
     /// A dump of the underlying digit trie.
     pub digit_trie_dump: DigitTrieDump<Vec<RangeInfo>>,
@@ -35,6 +40,28 @@ pub struct MultiOracleTrieDump {
     pub extra_cover_trie_dump: Option<MultiTrieDump<RangeInfo>>,
 }
 
+#[cfg(feature = "use-serde")]
+impl serde::Serialize for MultiOracleTrie {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(&self.dump(), serializer)
+    }
+}
+
+#[cfg(feature = "use-serde")]
+impl<'de> serde::Deserialize<'de> for MultiOracleTrie {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(MultiOracleTrie::from_dump(serde::Deserialize::deserialize(
+            deserializer,
+        )?))
+    }
+}
+
 impl MultiOracleTrie {
     /// Dump the trie information.
     pub fn dump(&self) -> MultiOracleTrieDump {
@@ -106,6 +106,11 @@ impl<'a> DlcTrie<'a, MultiOracleTrieWithDiffIter<'a>> for MultiOracleTrieWithDif
 }
 
 /// Container for a dump of a MultiOracleTrieWithDiff used for serialization purpose.
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct MultiOracleTrieWithDiffDump {
     /// The dump of the underlying MultiTrie.
     pub multi_trie_dump: MultiTrieDump<RangeInfo>,
@@ -113,6 +118,28 @@ pub struct MultiOracleTrieWithDiffDump {
     pub oracle_numeric_infos: OracleNumericInfo,
 }
 
+#[cfg(feature = "use-serde")]
+impl serde::Serialize for MultiOracleTrieWithDiff {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(&self.dump(), serializer)
+    }
+}
+
+#[cfg(feature = "use-serde")]
+impl<'de> serde::Deserialize<'de> for MultiOracleTrieWithDiff {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(MultiOracleTrieWithDiff::from_dump(
+            serde::Deserialize::deserialize(deserializer)?,
+        ))
+    }
+}
+
 impl MultiOracleTrieWithDiff {
     /// Dump the content of the trie for the purpose of serialization.
     pub fn dump(&self) -> MultiOracleTrieWithDiffDump {
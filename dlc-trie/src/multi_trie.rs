@@ -11,6 +11,11 @@ use dlc::Error;
 use multi_oracle::compute_outcome_combinations;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 /// Information stored in a node.
 pub struct TrieNodeInfo {
     /// The index of the sub-trie.
@@ -465,6 +470,11 @@ fn reorder_to_min_first(oracle_digit_infos: &mut Vec<usize>) -> usize {
 }
 
 /// Container for a dump of a MultiTrie used for serialization purpose.
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct MultiTrieDump<T>
 where
     T: Clone,
@@ -533,6 +543,11 @@ where
 }
 
 /// Holds the data of a multi trie node. Used for serialization purpose.
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub enum MultiTrieNodeData<T>
 where
     T: Clone,
@@ -0,0 +1,301 @@
+//! # Precomputed points
+//! Cache of the signature points anticipated for each possible value of a digit
+//! attested by an oracle, so that they are only computed once per oracle event
+//! across contracts.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use bitcoin::hashes::{sha256, Hash};
+use dlc::Error;
+use secp256k1_zkp::{Message, PublicKey, Secp256k1, Verification, XOnlyPublicKey};
+
+/// Default maximum number of digits for which points are kept by the global
+/// cache.
+pub const DEFAULT_MAX_CACHED_DIGITS: usize = 10_000;
+
+/// Identifies a digit of an oracle event.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DigitKey {
+    /// The public key of the oracle attesting the event.
+    pub oracle_public_key: XOnlyPublicKey,
+    /// The id of the event.
+    pub event_id: String,
+    /// The index of the digit within the event outcome.
+    pub digit_index: usize,
+}
+
+/// The signature points anticipated for each value of a digit, together with
+/// the parameters used to compute them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DigitPoints {
+    /// The nonce that the oracle will use to attest the digit.
+    pub nonce: XOnlyPublicKey,
+    /// The base in which the event outcome is decomposed.
+    pub base: usize,
+    /// The signature point for each value of the digit, from 0 to `base - 1`.
+    pub points: Arc<Vec<PublicKey>>,
+}
+
+/// Storage for anticipated signature points. Entries are only used if their
+/// nonce and base match the ones of the announcement being processed, so that
+/// an oracle reusing an event id cannot lead to invalid points being used.
+pub trait PointsCache: Send + Sync {
+    /// Returns the points cached for the given digit, if any.
+    fn get(&self, key: &DigitKey) -> Option<DigitPoints>;
+    /// Stores the points of the given digit.
+    fn insert(&self, key: DigitKey, points: DigitPoints);
+    /// Removes all the cached points.
+    fn clear(&self);
+}
+
+#[derive(Default)]
+struct LruState {
+    entries: HashMap<DigitKey, (DigitPoints, u64)>,
+    recency: BTreeMap<u64, DigitKey>,
+    counter: u64,
+}
+
+impl LruState {
+    fn touch(&mut self, key: &DigitKey) -> Option<DigitPoints> {
+        self.counter += 1;
+        let counter = self.counter;
+        let (points, last_used) = self.entries.get_mut(key)?;
+        self.recency.remove(&*last_used);
+        *last_used = counter;
+        self.recency.insert(counter, key.clone());
+        Some(points.clone())
+    }
+}
+
+/// Thread safe [`PointsCache`] evicting the least recently used digits once
+/// its capacity is reached.
+pub struct LruPointsCache {
+    state: Mutex<LruState>,
+    max_digits: usize,
+}
+
+impl LruPointsCache {
+    /// Creates a new cache storing the points of at most `max_digits` digits.
+    pub fn new(max_digits: usize) -> Self {
+        Self {
+            state: Mutex::new(LruState::default()),
+            max_digits,
+        }
+    }
+
+    /// Returns the number of digits for which points are cached.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl PointsCache for LruPointsCache {
+    fn get(&self, key: &DigitKey) -> Option<DigitPoints> {
+        self.state.lock().unwrap().touch(key)
+    }
+
+    fn insert(&self, key: DigitKey, points: DigitPoints) {
+        if self.max_digits == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.touch(&key).is_some() {
+            state.entries.get_mut(&key).expect("to have the entry").0 = points;
+            return;
+        }
+
+        while state.entries.len() >= self.max_digits {
+            let (_, evicted) = state
+                .recency
+                .pop_first()
+                .expect("to have as many recency entries as entries");
+            state.entries.remove(&evicted);
+        }
+
+        let counter = state.counter;
+        state.recency.insert(counter, key.clone());
+        state.entries.insert(key, (points, counter));
+    }
+
+    fn clear(&self) {
+        *self.state.lock().unwrap() = LruState::default();
+    }
+}
+
+fn global_cache() -> &'static RwLock<Arc<dyn PointsCache>> {
+    static GLOBAL: OnceLock<RwLock<Arc<dyn PointsCache>>> = OnceLock::new();
+    GLOBAL.get_or_init(|| RwLock::new(Arc::new(LruPointsCache::new(DEFAULT_MAX_CACHED_DIGITS))))
+}
+
+/// Returns the process wide cache, by default an [`LruPointsCache`] holding up
+/// to [`DEFAULT_MAX_CACHED_DIGITS`] digits.
+pub fn global() -> Arc<dyn PointsCache> {
+    global_cache().read().unwrap().clone()
+}
+
+/// Replaces the process wide cache, for example to share points across
+/// processes or to change the capacity of the cache.
+pub fn set_global(cache: Arc<dyn PointsCache>) {
+    *global_cache().write().unwrap() = cache;
+}
+
+/// Returns the signature points for all the digits of an event attested by
+/// the given oracle using the given nonces, in the format expected by
+/// [`crate::DlcTrie`] methods. Points missing from the cache, or cached with a
+/// different nonce or base, are computed and stored in the cache.
+pub fn get_event_points<C: Verification>(
+    cache: &dyn PointsCache,
+    secp: &Secp256k1<C>,
+    oracle_public_key: &XOnlyPublicKey,
+    event_id: &str,
+    nonces: &[XOnlyPublicKey],
+    base: usize,
+) -> Result<Vec<Vec<PublicKey>>, Error> {
+    nonces
+        .iter()
+        .enumerate()
+        .map(|(digit_index, nonce)| {
+            let key = DigitKey {
+                oracle_public_key: *oracle_public_key,
+                event_id: event_id.to_string(),
+                digit_index,
+            };
+            if let Some(cached) = cache.get(&key) {
+                if &cached.nonce == nonce && cached.base == base {
+                    return Ok(cached.points.as_ref().clone());
+                }
+            }
+
+            let points = compute_digit_points(secp, oracle_public_key, nonce, base)?;
+            cache.insert(
+                key,
+                DigitPoints {
+                    nonce: *nonce,
+                    base,
+                    points: Arc::new(points.clone()),
+                },
+            );
+            Ok(points)
+        })
+        .collect()
+}
+
+fn compute_digit_points<C: Verification>(
+    secp: &Secp256k1<C>,
+    oracle_public_key: &XOnlyPublicKey,
+    nonce: &XOnlyPublicKey,
+    base: usize,
+) -> Result<Vec<PublicKey>, Error> {
+    (0..base)
+        .map(|j| {
+            let hash = sha256::Hash::hash(j.to_string().as_bytes());
+            let msg = Message::from_slice(hash.as_ref())?;
+            dlc::secp_utils::schnorrsig_compute_sig_point(secp, oracle_public_key, nonce, &msg)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::{All, SecretKey};
+
+    fn xonly(secp: &Secp256k1<All>, i: u8) -> XOnlyPublicKey {
+        SecretKey::from_slice(&[i; 32])
+            .unwrap()
+            .x_only_public_key(secp)
+            .0
+    }
+
+    fn key(secp: &Secp256k1<All>, digit_index: usize) -> DigitKey {
+        DigitKey {
+            oracle_public_key: xonly(secp, 1),
+            event_id: "event".to_string(),
+            digit_index,
+        }
+    }
+
+    #[test]
+    fn cached_points_match_computed_points() {
+        let secp = Secp256k1::new();
+        let oracle_public_key = xonly(&secp, 1);
+        let nonces = vec![xonly(&secp, 2), xonly(&secp, 3)];
+        let cache = LruPointsCache::new(10);
+
+        let first =
+            get_event_points(&cache, &secp, &oracle_public_key, "event", &nonces, 2).unwrap();
+        let second =
+            get_event_points(&cache, &secp, &oracle_public_key, "event", &nonces, 2).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(
+            compute_digit_points(&secp, &oracle_public_key, &nonces[1], 2).unwrap(),
+            first[1]
+        );
+        assert_eq!(2, cache.len());
+    }
+
+    #[test]
+    fn points_with_different_nonce_are_recomputed() {
+        let secp = Secp256k1::new();
+        let oracle_public_key = xonly(&secp, 1);
+        let cache = LruPointsCache::new(10);
+
+        get_event_points(
+            &cache,
+            &secp,
+            &oracle_public_key,
+            "event",
+            &[xonly(&secp, 2)],
+            2,
+        )
+        .unwrap();
+        let points = get_event_points(
+            &cache,
+            &secp,
+            &oracle_public_key,
+            "event",
+            &[xonly(&secp, 3)],
+            2,
+        )
+        .unwrap();
+
+        assert_eq!(
+            compute_digit_points(&secp, &oracle_public_key, &xonly(&secp, 3), 2).unwrap(),
+            points[0]
+        );
+        assert_eq!(xonly(&secp, 3), cache.get(&key(&secp, 0)).unwrap().nonce);
+        assert_eq!(1, cache.len());
+    }
+
+    #[test]
+    fn least_recently_used_digit_is_evicted() {
+        let secp = Secp256k1::new();
+        let cache = LruPointsCache::new(2);
+        let points = |i| DigitPoints {
+            nonce: xonly(&secp, i),
+            base: 2,
+            points: Arc::new(Vec::new()),
+        };
+
+        cache.insert(key(&secp, 0), points(2));
+        cache.insert(key(&secp, 1), points(3));
+        assert!(cache.get(&key(&secp, 0)).is_some());
+        cache.insert(key(&secp, 2), points(4));
+
+        assert_eq!(2, cache.len());
+        assert!(cache.get(&key(&secp, 0)).is_some());
+        assert!(cache.get(&key(&secp, 1)).is_none());
+        assert!(cache.get(&key(&secp, 2)).is_some());
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
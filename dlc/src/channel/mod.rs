@@ -88,6 +88,16 @@ const PUNISH_BUFFER_INPUT_WEIGHT: usize = 758;
  */
 const PUNISH_SETTLE_INPUT_WEIGHT: usize = 575;
 
+/**
+ * The witness input is (+1 is added to each witness for size parameter):
+ *
+ * 1 signature (own one) -> 73
+ * Script -> 147
+ * TOTAL: 222
+ *
+ */
+const CLAIM_SETTLE_INPUT_WEIGHT: usize = 430;
+
 const N_VALUE_WEIGHT: usize = 8 * 4;
 
 #[derive(Clone, Debug)]
@@ -103,6 +113,34 @@ pub struct RevokeParams {
     pub revoke_pk: PublicKey,
 }
 
+/// Parameters of one of the contracts set up in a DLC channel holding several
+/// contracts.
+pub struct ChannelContractParams<'a> {
+    /// The parameters of the offer party of the contract, with the collateral
+    /// it locked in the contract.
+    pub offer_params: &'a PartyParams,
+    /// The parameters of the accept party of the contract, with the collateral
+    /// it locked in the contract.
+    pub accept_params: &'a PartyParams,
+    /// The payouts of the contract.
+    pub payouts: &'a [Payout],
+    /// The lock time of the refund transaction of the contract.
+    pub refund_lock_time: u32,
+    /// The lock time of the CETs of the contract.
+    pub cet_lock_time: u32,
+}
+
+/// Transactions used to setup several contracts within a DLC channel.
+pub struct MultiContractChannelTransactions {
+    /// The set of transactions used to setup each contract, in the order of
+    /// the buffer transaction outputs they spend.
+    pub dlc_transactions: Vec<DlcTransactions>,
+    /// The buffer transaction enabling revocation of the contracts.
+    pub buffer_transaction: Transaction,
+    /// Script pubkey of the buffer transaction outputs locking the contracts.
+    pub buffer_script_pubkey: ScriptBuf,
+}
+
 /// Transactions used to setup a DLC channel.
 pub struct DlcChannelTransactions {
     /// The set of transactions used to setup the DLC within the channel.
@@ -231,6 +269,21 @@ pub fn create_settle_transaction(
     })
 }
 
+/// Returns the extra fee that each party pays when establishing or renewing a
+/// channel, covering the buffer transaction and the additional weight of the
+/// CETs spending it.
+pub fn get_channel_extra_fee(fee_rate_per_vb: u64) -> Result<u64, Error> {
+    super::util::weight_to_fee(BUFFER_TX_WEIGHT + CET_EXTRA_WEIGHT, fee_rate_per_vb)
+}
+
+/// Returns the fee of a settle transaction with two outputs.
+pub fn get_settle_transaction_fee(fee_rate_per_vb: u64) -> Result<u64, Error> {
+    super::util::weight_to_fee(
+        SETTLE_INPUT_WEIGHT + 2 * SETTLE_OUTPUT_WEIGHT,
+        fee_rate_per_vb,
+    )
+}
+
 /// Returns the transactions necessary to establish a DLC channel.
 pub fn create_channel_transactions(
     offer_params: &PartyParams,
@@ -245,8 +298,7 @@ pub fn create_channel_transactions(
     fund_output_serial_id: u64,
     cet_nsequence: Sequence,
 ) -> Result<DlcChannelTransactions, Error> {
-    let extra_fee =
-        super::util::weight_to_fee(BUFFER_TX_WEIGHT + CET_EXTRA_WEIGHT, fee_rate_per_vb)?;
+    let extra_fee = get_channel_extra_fee(fee_rate_per_vb)?;
     let (fund, funding_script_pubkey) = super::create_fund_transaction_with_fees(
         offer_params,
         accept_params,
@@ -254,6 +306,7 @@ pub fn create_channel_transactions(
         fund_lock_time,
         fund_output_serial_id,
         extra_fee,
+        super::FeeSplit::Equal,
     )?;
 
     create_renewal_channel_transactions(
@@ -286,8 +339,7 @@ pub fn create_renewal_channel_transactions(
     cet_lock_time: u32,
     cet_nsequence: Sequence,
 ) -> Result<DlcChannelTransactions, Error> {
-    let extra_fee =
-        super::util::weight_to_fee(BUFFER_TX_WEIGHT + CET_EXTRA_WEIGHT, fee_rate_per_vb)?;
+    let extra_fee = get_channel_extra_fee(fee_rate_per_vb)?;
 
     let (fund_vout, fund_output) =
         super::util::get_output_for_script_pubkey(fund_tx, &funding_script_pubkey.to_v0_p2wsh())
@@ -341,6 +393,185 @@ pub fn create_renewal_channel_transactions(
     })
 }
 
+/// Returns the fee of the CETs of a contract set up in a DLC channel holding
+/// several contracts, which the buffer transaction output locking the contract
+/// must cover in addition to its collateral.
+pub fn get_channel_contract_cet_fee(
+    offer_params: &PartyParams,
+    accept_params: &PartyParams,
+    fee_rate_per_vb: u64,
+) -> Result<u64, Error> {
+    let payouts_weight =
+        (offer_params.payout_script_pubkey.len() + accept_params.payout_script_pubkey.len()) * 4;
+    super::util::weight_to_fee(
+        super::CET_BASE_WEIGHT + CET_EXTRA_WEIGHT + payouts_weight,
+        fee_rate_per_vb,
+    )
+}
+
+/// Returns the transactions necessary to set up several contracts in a DLC
+/// channel. The buffer transaction has one output for each contract, spent by
+/// the CETs and refund transaction of the contract, followed by one revocable
+/// output for the balance of each party that is not locked in any contract.
+/// The fees of the buffer transaction and CETs are paid using the funds of the
+/// channel that are not part of its collateral, any remaining amount being
+/// split between the balance outputs, and any missing one being deducted from
+/// them.
+pub fn create_multi_contract_channel_transactions(
+    offer_revoke_params: &RevokeParams,
+    accept_revoke_params: &RevokeParams,
+    fund_tx: &Transaction,
+    funding_script_pubkey: &Script,
+    contracts: &[ChannelContractParams],
+    offer_balance: u64,
+    accept_balance: u64,
+    fee_rate_per_vb: u64,
+    cet_nsequence: Sequence,
+) -> Result<MultiContractChannelTransactions, Error> {
+    let (fund_vout, fund_output) =
+        super::util::get_output_for_script_pubkey(fund_tx, &funding_script_pubkey.to_v0_p2wsh())
+            .ok_or(Error::InvalidArgument)?;
+
+    let buffer_descriptor = buffer_descriptor(offer_revoke_params, accept_revoke_params);
+
+    let mut outputs = Vec::with_capacity(contracts.len() + 2);
+    let mut total_collateral = offer_balance
+        .checked_add(accept_balance)
+        .ok_or(Error::InvalidArgument)?;
+    let mut fees = 0;
+    for contract in contracts {
+        let collateral = contract
+            .offer_params
+            .collateral
+            .checked_add(contract.accept_params.collateral)
+            .ok_or(Error::InvalidArgument)?;
+        let cet_fee = get_channel_contract_cet_fee(
+            contract.offer_params,
+            contract.accept_params,
+            fee_rate_per_vb,
+        )?;
+        total_collateral = total_collateral
+            .checked_add(collateral)
+            .ok_or(Error::InvalidArgument)?;
+        fees += cet_fee;
+        outputs.push(TxOut {
+            value: collateral + cet_fee,
+            script_pubkey: buffer_descriptor.script_pubkey(),
+        });
+    }
+
+    let mut balance_outputs: Vec<TxOut> = [
+        (
+            offer_balance,
+            settle_descriptor(
+                offer_revoke_params,
+                &accept_revoke_params.own_pk,
+                cet_nsequence.0,
+            ),
+        ),
+        (
+            accept_balance,
+            settle_descriptor(
+                accept_revoke_params,
+                &offer_revoke_params.own_pk,
+                cet_nsequence.0,
+            ),
+        ),
+    ]
+    .iter()
+    .filter(|(value, _)| *value > 0)
+    .map(|(value, descriptor)| TxOut {
+        value: *value,
+        script_pubkey: descriptor.script_pubkey(),
+    })
+    .collect();
+
+    // The buffer transaction weight includes a single output.
+    let buffer_weight = BUFFER_TX_WEIGHT
+        + (outputs.len() + balance_outputs.len()).saturating_sub(1) * SETTLE_OUTPUT_WEIGHT;
+    fees += super::util::weight_to_fee(buffer_weight, fee_rate_per_vb)?;
+
+    let available_fee = fund_output
+        .value
+        .checked_sub(total_collateral)
+        .ok_or(Error::InvalidArgument)?;
+
+    if available_fee >= fees {
+        if !balance_outputs.is_empty() {
+            let remaining = (available_fee - fees) / (balance_outputs.len() as u64);
+            for output in &mut balance_outputs {
+                output.value += remaining;
+            }
+        }
+    } else {
+        if balance_outputs.is_empty() {
+            return Err(Error::InvalidArgument);
+        }
+        let nb_balances = balance_outputs.len() as u64;
+        let missing = (fees - available_fee + nb_balances - 1) / nb_balances;
+        for output in &mut balance_outputs {
+            output.value = output
+                .value
+                .checked_sub(missing)
+                .ok_or(Error::InvalidArgument)?;
+        }
+    }
+
+    outputs.extend(crate::util::discard_dust(
+        balance_outputs,
+        crate::DUST_LIMIT,
+    ));
+
+    // The contracts can have different maturities, so the buffer transaction
+    // is not time locked.
+    let buffer_transaction = Transaction {
+        version: super::TX_VERSION,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: fund_tx.txid(),
+                vout: fund_vout as u32,
+            },
+            sequence: super::util::get_sequence(0),
+            script_sig: ScriptBuf::default(),
+            witness: Witness::default(),
+        }],
+        output: outputs,
+    };
+
+    let buffer_txid = buffer_transaction.txid();
+    let dlc_transactions = contracts
+        .iter()
+        .enumerate()
+        .map(|(i, contract)| {
+            let (cets, refund) = super::create_cets_and_refund_tx(
+                contract.offer_params,
+                contract.accept_params,
+                OutPoint {
+                    txid: buffer_txid,
+                    vout: i as u32,
+                },
+                contract.payouts,
+                contract.refund_lock_time,
+                contract.cet_lock_time,
+                Some(cet_nsequence),
+            )?;
+            Ok(DlcTransactions {
+                fund: fund_tx.clone(),
+                cets,
+                refund,
+                funding_script_pubkey: funding_script_pubkey.to_owned(),
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(MultiContractChannelTransactions {
+        dlc_transactions,
+        buffer_transaction,
+        buffer_script_pubkey: buffer_descriptor.script_code()?,
+    })
+}
+
 /// Sign a CET within a DLC channel.
 pub fn sign_cet<C: Signing>(
     secp: &Secp256k1<C>,
@@ -553,6 +784,196 @@ pub fn create_and_sign_punish_settle_transaction<C: Signing>(
     Ok(tx)
 }
 
+/// Returns a signed transaction to punish the publication of a revoked buffer
+/// transaction of a channel holding several contracts, spending all the
+/// outputs locking the contracts as well as the balance output of the counter
+/// party.
+pub fn create_and_sign_punish_multi_contract_buffer_transaction<C: Signing>(
+    secp: &Secp256k1<C>,
+    offer_params: &RevokeParams,
+    accept_params: &RevokeParams,
+    own_sk: &SecretKey,
+    counter_publish_sk: &SecretKey,
+    counter_revoke_sk: &SecretKey,
+    prev_tx: &Transaction,
+    dest_address: &Address,
+    csv_timelock: u32,
+    lock_time: u32,
+    fee_rate_per_vb: u64,
+    is_offer: bool,
+) -> Result<Transaction, Error> {
+    let (own_params, counter_params) = if is_offer {
+        (offer_params, accept_params)
+    } else {
+        (accept_params, offer_params)
+    };
+
+    let buffer_descriptor = buffer_descriptor(offer_params, accept_params);
+    let buffer_script_pubkey = buffer_descriptor.script_pubkey();
+    let settle_descriptor = settle_descriptor(counter_params, &own_params.own_pk, csv_timelock);
+    let settle_script_pubkey = settle_descriptor.script_pubkey();
+
+    let prev_txid = prev_tx.txid();
+    // The outputs to spend, with whether they lock a contract or the balance
+    // of the counter party.
+    let spent_outputs = prev_tx
+        .output
+        .iter()
+        .enumerate()
+        .filter_map(|(vout, output)| {
+            if output.script_pubkey == buffer_script_pubkey {
+                Some((vout, output.value, true))
+            } else if output.script_pubkey == settle_script_pubkey {
+                Some((vout, output.value, false))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if spent_outputs.is_empty() {
+        return Err(Error::InvalidArgument);
+    }
+
+    let dest_script_pk_len = dest_address.script_pubkey().len();
+    let var_int_prefix_len = crate::util::compute_var_int_prefix_size(dest_script_pk_len);
+    let output_weight = N_VALUE_WEIGHT + var_int_prefix_len + dest_script_pk_len * 4;
+    let inputs_weight: usize = spent_outputs
+        .iter()
+        .map(|(_, _, is_buffer)| {
+            if *is_buffer {
+                PUNISH_BUFFER_INPUT_WEIGHT
+            } else {
+                PUNISH_SETTLE_INPUT_WEIGHT
+            }
+        })
+        .sum();
+    let tx_fee = crate::util::weight_to_fee(inputs_weight + output_weight, fee_rate_per_vb)?;
+    let input_value: u64 = spent_outputs.iter().map(|(_, value, _)| value).sum();
+
+    let mut tx = Transaction {
+        version: super::TX_VERSION,
+        lock_time: LockTime::from_consensus(lock_time),
+        input: spent_outputs
+            .iter()
+            .map(|(vout, _, _)| TxIn {
+                previous_output: OutPoint {
+                    txid: prev_txid,
+                    vout: *vout as u32,
+                },
+                sequence: Sequence::ZERO,
+                script_sig: ScriptBuf::default(),
+                witness: Witness::default(),
+            })
+            .collect(),
+        output: vec![TxOut {
+            value: input_value
+                .checked_sub(tx_fee)
+                .ok_or(Error::InvalidArgument)?,
+            script_pubkey: dest_address.script_pubkey(),
+        }],
+    };
+
+    let buffer_script_code = buffer_descriptor.script_code()?;
+    let settle_script_code = settle_descriptor.script_code()?;
+    for (input_index, (_, value, is_buffer)) in spent_outputs.iter().enumerate() {
+        let script_code = if *is_buffer {
+            &buffer_script_code
+        } else {
+            &settle_script_code
+        };
+        let mut buffer_sigs = HashMap::new();
+        let mut settle_sigs = HashMap::new();
+        for sk in &[&own_sk, &counter_publish_sk, &counter_revoke_sk] {
+            let pk = PublicKey {
+                inner: SecpPublicKey::from_secret_key(secp, sk),
+                compressed: true,
+            };
+            let sig = Signature::sighash_all(super::util::get_raw_sig_for_tx_input(
+                secp,
+                &tx,
+                input_index,
+                script_code,
+                *value,
+                sk,
+            )?);
+            // The buffer descriptor uses public key hashes on its revocation
+            // path, which requires looking up signatures by hash.
+            buffer_sigs.insert(pk.pubkey_hash().to_raw_hash(), (pk, sig));
+            settle_sigs.insert(pk, sig);
+        }
+
+        let res = if *is_buffer {
+            buffer_descriptor.satisfy(&mut tx.input[input_index], buffer_sigs)
+        } else {
+            settle_descriptor.satisfy(&mut tx.input[input_index], settle_sigs)
+        };
+        res.map_err(|_| Error::InvalidArgument)?;
+    }
+
+    Ok(tx)
+}
+
+/// Create and sign a transaction claiming an output locked with a settle
+/// descriptor paying the local party, such as an output of a settle
+/// transaction, once its relative time lock has expired.
+pub fn create_and_sign_claim_settle_transaction<C: Signing>(
+    secp: &Secp256k1<C>,
+    own_params: &RevokeParams,
+    counter_pk: &PublicKey,
+    own_sk: &SecretKey,
+    outpoint: OutPoint,
+    value: u64,
+    dest_address: &Address,
+    csv_timelock: u32,
+    fee_rate_per_vb: u64,
+) -> Result<Transaction, Error> {
+    let descriptor = settle_descriptor(own_params, counter_pk, csv_timelock);
+
+    let tx_in = TxIn {
+        previous_output: outpoint,
+        sequence: Sequence(csv_timelock),
+        script_sig: ScriptBuf::default(),
+        witness: Witness::default(),
+    };
+
+    let dest_script_pk_len = dest_address.script_pubkey().len();
+    let var_int_prefix_len = crate::util::compute_var_int_prefix_size(dest_script_pk_len);
+    let output_weight = N_VALUE_WEIGHT + var_int_prefix_len + dest_script_pk_len * 4;
+    let tx_fee =
+        crate::util::weight_to_fee(CLAIM_SETTLE_INPUT_WEIGHT + output_weight, fee_rate_per_vb)?;
+
+    let mut tx = Transaction {
+        version: super::TX_VERSION,
+        lock_time: LockTime::ZERO,
+        input: vec![tx_in],
+        output: vec![TxOut {
+            value: value.checked_sub(tx_fee).ok_or(Error::InvalidArgument)?,
+            script_pubkey: dest_address.script_pubkey(),
+        }],
+    };
+
+    let own_pk = PublicKey {
+        inner: SecpPublicKey::from_secret_key(secp, own_sk),
+        compressed: true,
+    };
+    let sig = Signature::sighash_all(super::util::get_raw_sig_for_tx_input(
+        secp,
+        &tx,
+        0,
+        &descriptor.script_code()?,
+        value,
+        own_sk,
+    )?);
+    let sigs = HashMap::from_iter(vec![(own_pk, sig)]);
+
+    descriptor
+        .satisfy(&mut tx.input[0], (sigs, tx.input[0].sequence))
+        .map_err(|_| Error::InvalidArgument)?;
+
+    Ok(tx)
+}
+
 /// Create a transaction for collaboratively closing a channel.
 pub fn create_collaborative_close_transaction(
     offer_params: &PartyParams,
@@ -770,6 +1191,243 @@ mod tests {
         .is_err());
     }
 
+    fn get_party_params(collateral: u64, payout_serial_id: u64) -> PartyParams {
+        let address = Address::p2wpkh(
+            &PublicKey::from_private_key(
+                SECP256K1,
+                &PrivateKey::new(SecretKey::new(&mut thread_rng()), Network::Regtest),
+            ),
+            Network::Regtest,
+        )
+        .unwrap();
+        PartyParams {
+            fund_pubkey: SecpPublicKey::from_secret_key(
+                SECP256K1,
+                &SecretKey::new(&mut thread_rng()),
+            ),
+            change_script_pubkey: address.script_pubkey(),
+            change_serial_id: 0,
+            payout_script_pubkey: address.script_pubkey(),
+            payout_serial_id,
+            inputs: Vec::new(),
+            input_amount: 0,
+            collateral,
+        }
+    }
+
+    #[test]
+    fn create_multi_contract_channel_transactions_test() {
+        let offer_priv_params = RevokePrivateParams::new(Network::Regtest);
+        let accept_priv_params = RevokePrivateParams::new(Network::Regtest);
+        let offer_params = offer_priv_params.public_params(SECP256K1);
+        let accept_params = accept_priv_params.public_params(SECP256K1);
+        let funding_script_pubkey = buffer_descriptor(&offer_params, &accept_params)
+            .script_code()
+            .unwrap();
+        let fund_tx = Transaction {
+            version: crate::TX_VERSION,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut {
+                value: 200010000,
+                script_pubkey: funding_script_pubkey.to_v0_p2wsh(),
+            }],
+        };
+
+        let first_offer = get_party_params(30000000, 1);
+        let first_accept = get_party_params(20000000, 2);
+        let first_payouts = vec![
+            Payout {
+                offer: 50000000,
+                accept: 0,
+            },
+            Payout {
+                offer: 0,
+                accept: 50000000,
+            },
+        ];
+        let second_offer = get_party_params(10000000, 1);
+        let second_accept = get_party_params(40000000, 2);
+        let second_payouts = vec![Payout {
+            offer: 25000000,
+            accept: 25000000,
+        }];
+        let contracts = [
+            ChannelContractParams {
+                offer_params: &first_offer,
+                accept_params: &first_accept,
+                payouts: &first_payouts,
+                refund_lock_time: 100,
+                cet_lock_time: 0,
+            },
+            ChannelContractParams {
+                offer_params: &second_offer,
+                accept_params: &second_accept,
+                payouts: &second_payouts,
+                refund_lock_time: 200,
+                cet_lock_time: 0,
+            },
+        ];
+
+        let txs = create_multi_contract_channel_transactions(
+            &offer_params,
+            &accept_params,
+            &fund_tx,
+            &funding_script_pubkey,
+            &contracts,
+            60000000,
+            40000000,
+            FEE_RATE_PER_VB,
+            Sequence(288),
+        )
+        .expect("to be able to create the transactions");
+
+        let buffer_tx = &txs.buffer_transaction;
+        assert_eq!(4, buffer_tx.output.len());
+        assert_eq!(2, txs.dlc_transactions.len());
+        let buffer_txid = buffer_tx.txid();
+        for (i, dlc_transactions) in txs.dlc_transactions.iter().enumerate() {
+            let collateral =
+                contracts[i].offer_params.collateral + contracts[i].accept_params.collateral;
+            let cet_fee = get_channel_contract_cet_fee(
+                contracts[i].offer_params,
+                contracts[i].accept_params,
+                FEE_RATE_PER_VB,
+            )
+            .unwrap();
+            assert_eq!(collateral + cet_fee, buffer_tx.output[i].value);
+            for tx in dlc_transactions
+                .cets
+                .iter()
+                .chain(std::iter::once(&dlc_transactions.refund))
+            {
+                assert_eq!(
+                    OutPoint {
+                        txid: buffer_txid,
+                        vout: i as u32
+                    },
+                    tx.input[0].previous_output
+                );
+            }
+        }
+        let total_output: u64 = buffer_tx.output.iter().map(|x| x.value).sum();
+        assert!(total_output < fund_tx.output[0].value);
+        // The remaining fee is split evenly between the balance outputs.
+        assert_eq!(
+            buffer_tx.output[2].value - 60000000,
+            buffer_tx.output[3].value - 40000000
+        );
+
+        // The revoked buffer transaction can be punished by both parties.
+        let dest_address = Address::p2pkh(
+            &PublicKey::from_private_key(
+                SECP256K1,
+                &PrivateKey::new(SecretKey::new(&mut thread_rng()), Network::Regtest),
+            ),
+            Network::Regtest,
+        );
+        for (own, counter, is_offer) in [
+            (&offer_priv_params, &accept_priv_params, true),
+            (&accept_priv_params, &offer_priv_params, false),
+        ] {
+            let punish_tx = create_and_sign_punish_multi_contract_buffer_transaction(
+                SECP256K1,
+                &offer_params,
+                &accept_params,
+                &own.own_priv.inner,
+                &counter.publish_priv.inner,
+                &counter.revoke_priv.inner,
+                buffer_tx,
+                &dest_address,
+                288,
+                0,
+                FEE_RATE_PER_VB,
+                is_offer,
+            )
+            .expect("to be able to create and sign the punish transaction");
+            // Both contract outputs and the balance of the counter party.
+            assert_eq!(3, punish_tx.input.len());
+        }
+    }
+
+    #[test]
+    fn multi_contract_channel_fees_are_deducted_from_balances_test() {
+        let offer_priv_params = RevokePrivateParams::new(Network::Regtest);
+        let accept_priv_params = RevokePrivateParams::new(Network::Regtest);
+        let offer_params = offer_priv_params.public_params(SECP256K1);
+        let accept_params = accept_priv_params.public_params(SECP256K1);
+        let funding_script_pubkey = buffer_descriptor(&offer_params, &accept_params)
+            .script_code()
+            .unwrap();
+        let fund_tx = Transaction {
+            version: crate::TX_VERSION,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut {
+                value: 100000000,
+                script_pubkey: funding_script_pubkey.to_v0_p2wsh(),
+            }],
+        };
+        let offer = get_party_params(25000000, 1);
+        let accept = get_party_params(25000000, 2);
+        let payouts = vec![Payout {
+            offer: 50000000,
+            accept: 0,
+        }];
+        let contracts = [ChannelContractParams {
+            offer_params: &offer,
+            accept_params: &accept,
+            payouts: &payouts,
+            refund_lock_time: 100,
+            cet_lock_time: 0,
+        }];
+
+        let txs = create_multi_contract_channel_transactions(
+            &offer_params,
+            &accept_params,
+            &fund_tx,
+            &funding_script_pubkey,
+            &contracts,
+            25000000,
+            25000000,
+            FEE_RATE_PER_VB,
+            Sequence(288),
+        )
+        .expect("to be able to create the transactions");
+        assert!(txs.buffer_transaction.output[1].value < 25000000);
+        assert_eq!(
+            txs.buffer_transaction.output[1].value,
+            txs.buffer_transaction.output[2].value
+        );
+
+        // Without balances, the fees cannot be paid.
+        let payouts = vec![Payout {
+            offer: 100000000,
+            accept: 0,
+        }];
+        let offer = get_party_params(50000000, 1);
+        let accept = get_party_params(50000000, 2);
+        let contracts = [ChannelContractParams {
+            offer_params: &offer,
+            accept_params: &accept,
+            payouts: &payouts,
+            refund_lock_time: 100,
+            cet_lock_time: 0,
+        }];
+        assert!(create_multi_contract_channel_transactions(
+            &offer_params,
+            &accept_params,
+            &fund_tx,
+            &funding_script_pubkey,
+            &contracts,
+            0,
+            0,
+            FEE_RATE_PER_VB,
+            Sequence(288),
+        )
+        .is_err());
+    }
+
     #[test]
     fn two_parties_sigs_satisfy_buffer_descriptor_test() {
         let offer_priv_params = RevokePrivateParams::new(Network::Regtest);
@@ -899,6 +1557,68 @@ mod tests {
         .is_err());
     }
 
+    #[test]
+    fn create_and_sign_claim_settle_transaction_test() {
+        let offer_priv_params = RevokePrivateParams::new(Network::Regtest);
+        let accept_priv_params = RevokePrivateParams::new(Network::Regtest);
+        let offer_params = offer_priv_params.public_params(SECP256K1);
+        let accept_params = accept_priv_params.public_params(SECP256K1);
+        let dest_address = Address::p2pkh(
+            &PublicKey::from_private_key(
+                SECP256K1,
+                &PrivateKey::new(SecretKey::new(&mut thread_rng()), Network::Regtest),
+            ),
+            Network::Regtest,
+        );
+        let payout = 100000000;
+        let csv_timelock = 100;
+        let settle_tx = create_settle_transaction(
+            &TxIn::default(),
+            &offer_params,
+            &accept_params,
+            payout,
+            payout,
+            csv_timelock,
+            0,
+            200020000,
+            FEE_RATE_PER_VB,
+        )
+        .unwrap();
+        let outpoint = OutPoint {
+            txid: settle_tx.txid(),
+            vout: 0,
+        };
+
+        let claim_tx = create_and_sign_claim_settle_transaction(
+            SECP256K1,
+            &offer_params,
+            &accept_params.own_pk,
+            &offer_priv_params.own_priv.inner,
+            outpoint,
+            settle_tx.output[0].value,
+            &dest_address,
+            csv_timelock,
+            FEE_RATE_PER_VB,
+        )
+        .expect("to be able to create and sign the claim transaction");
+        assert_eq!(Sequence(csv_timelock), claim_tx.input[0].sequence);
+        assert!(claim_tx.output[0].value < settle_tx.output[0].value);
+
+        // Accepter cannot claim the output of the offerer.
+        assert!(create_and_sign_claim_settle_transaction(
+            SECP256K1,
+            &offer_params,
+            &accept_params.own_pk,
+            &accept_priv_params.own_priv.inner,
+            outpoint,
+            settle_tx.output[0].value,
+            &dest_address,
+            csv_timelock,
+            FEE_RATE_PER_VB,
+        )
+        .is_err());
+    }
+
     #[test]
     fn one_party_sig_satisfies_settle_descriptor_test() {
         let offer_priv_params = RevokePrivateParams::new(Network::Regtest);
@@ -15,6 +15,8 @@
 extern crate bitcoin;
 extern crate core;
 extern crate miniscript;
+#[cfg(feature = "parallel")]
+extern crate rayon;
 extern crate secp256k1_sys;
 pub extern crate secp256k1_zkp;
 #[cfg(feature = "serde")]
@@ -30,6 +32,8 @@ use bitcoin::{
     },
     Sequence, Witness,
 };
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use secp256k1_zkp::schnorr::Signature as SchnorrSignature;
 use secp256k1_zkp::{
     ecdsa::Signature, EcdsaAdaptorSignature, Message, PublicKey, Secp256k1, SecretKey,
@@ -41,6 +45,10 @@ use std::fmt;
 
 pub mod channel;
 pub mod secp_utils;
+#[cfg(feature = "taproot")]
+pub mod taproot;
+#[cfg(test)]
+mod test_utils;
 pub mod util;
 
 /// Minimum value that can be included in a transaction output. Under this value,
@@ -68,6 +76,17 @@ const TX_INPUT_BASE_WEIGHT: usize = 164;
 /// See: <https://github.com/discreetlogcontracts/dlcspecs/blob/master/Transactions.md#fees>
 pub const P2WPKH_WITNESS_SIZE: usize = 107;
 
+/// The value of the anchor output added to CETs when anchor outputs are used,
+/// equal to the dust limit of pay-to-anchor outputs.
+pub const ANCHOR_OUTPUT_VALUE: u64 = 240;
+
+/// The weight of an input spending the fund output of a contract, computed as:
+/// TX_INPUT_BASE_WEIGHT + witness(items count(1) + empty item(1) + 2 signatures(2 * 73) + multisig script(72))
+const FUND_OUTPUT_INPUT_WEIGHT: usize = TX_INPUT_BASE_WEIGHT + 220;
+
+/// The weight of an anchor output computed as: (value(8) + scriptPubKeySize(1) + scriptPubKey(4)) * 4
+const ANCHOR_OUTPUT_WEIGHT: usize = 52;
+
 macro_rules! checked_add {
     ($a: expr, $b: expr) => {
         $a.checked_add($b).ok_or(Error::InvalidArgument)
@@ -115,6 +134,11 @@ pub struct EnumerationPayout {
 
 /// Contains the necessary transactions for establishing a DLC
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct DlcTransactions {
     /// The fund transaction locking both parties collaterals
     pub fund: Transaction,
@@ -125,27 +149,36 @@ pub struct DlcTransactions {
     /// case of an oracle misbehavior
     pub refund: Transaction,
 
-    /// The script pubkey of the fund output in the fund transaction
+    /// The script of the fund output in the fund transaction, which is the
+    /// witness script of the P2WSH fund output, or the script pubkey itself
+    /// for a P2TR fund output.
     pub funding_script_pubkey: ScriptBuf,
 }
 
 impl DlcTransactions {
     /// Get the fund output in the fund transaction
     pub fn get_fund_output(&self) -> &TxOut {
-        let v0_witness_fund_script = self.funding_script_pubkey.to_v0_p2wsh();
-        util::get_output_for_script_pubkey(&self.fund, &v0_witness_fund_script)
+        util::get_output_for_script_pubkey(&self.fund, &self.get_fund_output_script_pubkey())
             .unwrap()
             .1
     }
 
     /// Get the fund output in the fund transaction
     pub fn get_fund_output_index(&self) -> usize {
-        let v0_witness_fund_script = self.funding_script_pubkey.to_v0_p2wsh();
-        util::get_output_for_script_pubkey(&self.fund, &v0_witness_fund_script)
+        util::get_output_for_script_pubkey(&self.fund, &self.get_fund_output_script_pubkey())
             .unwrap()
             .0
     }
 
+    /// Get the script pubkey of the fund output in the fund transaction
+    pub fn get_fund_output_script_pubkey(&self) -> ScriptBuf {
+        if self.funding_script_pubkey.is_v1_p2tr() {
+            self.funding_script_pubkey.clone()
+        } else {
+            self.funding_script_pubkey.to_v0_p2wsh()
+        }
+    }
+
     /// Get the outpoint for the fund output in the fund transaction
     pub fn get_fund_outpoint(&self) -> OutPoint {
         OutPoint {
@@ -243,6 +276,54 @@ impl std::error::Error for Error {
     }
 }
 
+/// Defines how the fees of the transactions of a contract are split between
+/// the parties.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum FeeSplit {
+    /// Each party pays half of the fees for the base transaction weights, and
+    /// the fees for its own inputs and outputs.
+    #[default]
+    Equal,
+    /// The offer party pays all the fees.
+    OfferPaysAll,
+    /// The accept party pays all the fees.
+    AcceptPaysAll,
+    /// The fees are split proportionally to the collateral of each party.
+    Proportional,
+}
+
+impl FeeSplit {
+    /// Returns the fees paid by the offer and accept parties, given the fees
+    /// that they respectively pay with an equal split and their collaterals.
+    pub fn split_fees(
+        &self,
+        offer_fee: u64,
+        accept_fee: u64,
+        offer_collateral: u64,
+        accept_collateral: u64,
+    ) -> Result<(u64, u64), Error> {
+        let total_fee = checked_add!(offer_fee, accept_fee)?;
+        let offer_share = match self {
+            FeeSplit::Equal => offer_fee,
+            FeeSplit::OfferPaysAll => total_fee,
+            FeeSplit::AcceptPaysAll => 0,
+            FeeSplit::Proportional => {
+                let total_collateral = checked_add!(offer_collateral, accept_collateral)?;
+                if total_collateral == 0 {
+                    return Err(Error::InvalidArgument);
+                }
+                (total_fee as u128 * offer_collateral as u128 / total_collateral as u128) as u64
+            }
+        };
+        Ok((offer_share, total_fee - offer_share))
+    }
+}
+
 /// Contains the parameters required for creating DLC transactions for a single
 /// party. Specifically these are the common fields between Offer and Accept
 /// messages.
@@ -282,6 +363,17 @@ impl PartyParams {
         fee_rate_per_vb: u64,
         extra_fee: u64,
     ) -> Result<(TxOut, u64, u64), Error> {
+        let (fund_fee, cet_or_refund_fee) = self.get_fees(fee_rate_per_vb)?;
+        let change_output =
+            self.get_change_output(checked_add!(fund_fee, cet_or_refund_fee, extra_fee)?)?;
+
+        Ok((change_output, fund_fee, cet_or_refund_fee))
+    }
+
+    /// Returns the fees that the party is required to pay for the fund
+    /// transaction and the cet or refund transaction when fees are split
+    /// equally.
+    pub(crate) fn get_fees(&self, fee_rate_per_vb: u64) -> Result<(u64, u64), Error> {
         let mut inputs_weight: usize = 0;
 
         for w in &self.inputs {
@@ -326,18 +418,22 @@ impl PartyParams {
             .ok_or(Error::InvalidArgument)?;
         let total_cet_weight = checked_add!(this_party_cet_base_weight, output_spk_weight)?;
         let cet_or_refund_fee = util::weight_to_fee(total_cet_weight, fee_rate_per_vb)?;
-        let required_input_funds =
-            checked_add!(self.collateral, fund_fee, cet_or_refund_fee, extra_fee)?;
+
+        Ok((fund_fee, cet_or_refund_fee))
+    }
+
+    /// Returns the change output of the party when it pays the given fees,
+    /// or an error if its input amount does not cover them and its collateral.
+    fn get_change_output(&self, fees: u64) -> Result<TxOut, Error> {
+        let required_input_funds = checked_add!(self.collateral, fees)?;
         if self.input_amount < required_input_funds {
             return Err(Error::InvalidArgument);
         }
 
-        let change_output = TxOut {
+        Ok(TxOut {
             value: self.input_amount - required_input_funds,
             script_pubkey: self.change_script_pubkey.clone(),
-        };
-
-        Ok((change_output, fund_fee, cet_or_refund_fee))
+        })
     }
 
     fn get_unsigned_tx_inputs_and_serial_ids(&self, sequence: Sequence) -> (Vec<TxIn>, Vec<u64>) {
@@ -359,7 +455,29 @@ impl PartyParams {
     }
 }
 
-/// Create the transactions for a DLC contract based on the provided parameters
+/// Returns a pay-to-anchor output, which anyone can spend without providing
+/// a signature. Included in a transaction, it enables fee bumping it through
+/// a child transaction after it was signed.
+pub fn get_anchor_output() -> TxOut {
+    TxOut {
+        value: ANCHOR_OUTPUT_VALUE,
+        script_pubkey: ScriptBuf::from(vec![0x51, 0x02, 0x4e, 0x73]),
+    }
+}
+
+/// Returns the extra fee that each party pays to fund the anchor output of
+/// the CETs, covering its value and weight.
+pub fn get_anchor_extra_fee(fee_rate_per_vb: u64) -> Result<u64, Error> {
+    checked_add!(
+        ANCHOR_OUTPUT_VALUE,
+        util::weight_to_fee(ANCHOR_OUTPUT_WEIGHT, fee_rate_per_vb)?
+    )
+}
+
+/// Create the transactions for a DLC contract based on the provided parameters.
+/// When `anchor_outputs` is set, an anchor output is added to each CET. The
+/// fees of the transactions are split between the parties according to
+/// `fee_split`.
 pub fn create_dlc_transactions(
     offer_params: &PartyParams,
     accept_params: &PartyParams,
@@ -369,22 +487,176 @@ pub fn create_dlc_transactions(
     fund_lock_time: u32,
     cet_lock_time: u32,
     fund_output_serial_id: u64,
+    anchor_outputs: bool,
+    fee_split: FeeSplit,
 ) -> Result<DlcTransactions, Error> {
+    let extra_fee = if anchor_outputs {
+        get_anchor_extra_fee(fee_rate_per_vb)?
+    } else {
+        0
+    };
     let (fund_tx, funding_script_pubkey) = create_fund_transaction_with_fees(
         offer_params,
         accept_params,
         fee_rate_per_vb,
         fund_lock_time,
         fund_output_serial_id,
-        0,
+        extra_fee,
+        fee_split,
+    )?;
+    create_dlc_transactions_from_fund(
+        offer_params,
+        accept_params,
+        fund_tx,
+        funding_script_pubkey,
+        payouts,
+        refund_lock_time,
+        cet_lock_time,
+        anchor_outputs,
+    )
+}
+
+/// Create the transactions of a contract amending the one with the given
+/// transactions, whose fund transaction spends the fund output of the previous
+/// contract in addition to the inputs of the given party parameters. The
+/// collateral of the party parameters is the one of the amended contract, while
+/// their input amount only accounts for their additional inputs. The value of
+/// the previous fund output is credited to each party according to its previous
+/// collateral, the fees reserved for the previous CETs and the fee for spending
+/// the previous fund output being split equally among them.
+pub fn create_amended_dlc_transactions(
+    prev_dlc_transactions: &DlcTransactions,
+    prev_offer_collateral: u64,
+    prev_accept_collateral: u64,
+    offer_params: &PartyParams,
+    accept_params: &PartyParams,
+    payouts: &[Payout],
+    refund_lock_time: u32,
+    fee_rate_per_vb: u64,
+    fund_lock_time: u32,
+    cet_lock_time: u32,
+    fund_output_serial_id: u64,
+    anchor_outputs: bool,
+    fee_split: FeeSplit,
+) -> Result<DlcTransactions, Error> {
+    create_netted_dlc_transactions(
+        &[(
+            prev_dlc_transactions,
+            prev_offer_collateral,
+            prev_accept_collateral,
+        )],
+        offer_params,
+        accept_params,
+        payouts,
+        refund_lock_time,
+        fee_rate_per_vb,
+        fund_lock_time,
+        cet_lock_time,
+        fund_output_serial_id,
+        anchor_outputs,
+        fee_split,
+    )
+}
+
+/// Create the transactions of a contract replacing the ones with the given
+/// transactions, as in [`create_amended_dlc_transactions`] but with a fund
+/// transaction spending the fund outputs of all the previous contracts, in the
+/// given order. The collaterals given with each previous contract are the ones
+/// of the parties taking the offer and accept roles in the new contract, which
+/// can differ from their roles in the previous one.
+pub fn create_netted_dlc_transactions(
+    prev_contracts: &[(&DlcTransactions, u64, u64)],
+    offer_params: &PartyParams,
+    accept_params: &PartyParams,
+    payouts: &[Payout],
+    refund_lock_time: u32,
+    fee_rate_per_vb: u64,
+    fund_lock_time: u32,
+    cet_lock_time: u32,
+    fund_output_serial_id: u64,
+    anchor_outputs: bool,
+    fee_split: FeeSplit,
+) -> Result<DlcTransactions, Error> {
+    let prev_fund_input_fee = util::weight_to_fee(FUND_OUTPUT_INPUT_WEIGHT, fee_rate_per_vb)?;
+    let mut offer_credit = 0;
+    let mut accept_credit = 0;
+    for (prev_dlc_transactions, prev_offer_collateral, prev_accept_collateral) in prev_contracts {
+        let prev_fund_value = prev_dlc_transactions.get_fund_output().value;
+        let prev_total_collateral = checked_add!(*prev_offer_collateral, *prev_accept_collateral)?;
+        let prev_fee_reserve = prev_fund_value
+            .checked_sub(prev_total_collateral)
+            .ok_or(Error::InvalidArgument)?;
+        let prev_offer_credit = checked_add!(*prev_offer_collateral, prev_fee_reserve / 2)?
+            .checked_sub(prev_fund_input_fee / 2)
+            .ok_or(Error::InvalidArgument)?;
+        let prev_accept_credit = prev_fund_value
+            .checked_sub(prev_fund_input_fee)
+            .and_then(|x| x.checked_sub(prev_offer_credit))
+            .ok_or(Error::InvalidArgument)?;
+        offer_credit = checked_add!(offer_credit, prev_offer_credit)?;
+        accept_credit = checked_add!(accept_credit, prev_accept_credit)?;
+    }
+
+    let mut credited_offer_params = offer_params.clone();
+    credited_offer_params.input_amount = checked_add!(offer_params.input_amount, offer_credit)?;
+    let mut credited_accept_params = accept_params.clone();
+    credited_accept_params.input_amount = checked_add!(accept_params.input_amount, accept_credit)?;
+
+    let extra_fee = if anchor_outputs {
+        get_anchor_extra_fee(fee_rate_per_vb)?
+    } else {
+        0
+    };
+    let (mut fund_tx, funding_script_pubkey) = create_fund_transaction_with_fees(
+        &credited_offer_params,
+        &credited_accept_params,
+        fee_rate_per_vb,
+        fund_lock_time,
+        fund_output_serial_id,
+        extra_fee,
+        fee_split,
     )?;
+    for (i, (prev_dlc_transactions, _, _)) in prev_contracts.iter().enumerate() {
+        fund_tx.input.insert(
+            i,
+            TxIn {
+                previous_output: prev_dlc_transactions.get_fund_outpoint(),
+                script_sig: ScriptBuf::new(),
+                sequence: util::get_sequence(fund_lock_time),
+                witness: Witness::new(),
+            },
+        );
+    }
+
+    create_dlc_transactions_from_fund(
+        offer_params,
+        accept_params,
+        fund_tx,
+        funding_script_pubkey,
+        payouts,
+        refund_lock_time,
+        cet_lock_time,
+        anchor_outputs,
+    )
+}
+
+fn create_dlc_transactions_from_fund(
+    offer_params: &PartyParams,
+    accept_params: &PartyParams,
+    fund_tx: Transaction,
+    funding_script_pubkey: ScriptBuf,
+    payouts: &[Payout],
+    refund_lock_time: u32,
+    cet_lock_time: u32,
+    anchor_outputs: bool,
+) -> Result<DlcTransactions, Error> {
     let fund_outpoint = OutPoint {
         txid: fund_tx.txid(),
         vout: util::get_output_for_script_pubkey(&fund_tx, &funding_script_pubkey.to_v0_p2wsh())
             .expect("to find the funding script pubkey")
             .0 as u32,
     };
-    let (cets, refund_tx) = create_cets_and_refund_tx(
+    let (mut cets, refund_tx) = create_cets_and_refund_tx(
         offer_params,
         accept_params,
         fund_outpoint,
@@ -394,6 +666,12 @@ pub fn create_dlc_transactions(
         None,
     )?;
 
+    if anchor_outputs {
+        for cet in &mut cets {
+            cet.output.push(get_anchor_output());
+        }
+    }
+
     Ok(DlcTransactions {
         fund: fund_tx,
         cets,
@@ -402,6 +680,47 @@ pub fn create_dlc_transactions(
     })
 }
 
+/// Weight of a P2WPKH script pubkey.
+const P2WPKH_SCRIPT_PUBKEY_WEIGHT: usize = 22 * 4;
+
+/// Returns an estimate of the weight of a fund transaction for which the offer
+/// and accept parties respectively use the given number of P2WPKH inputs, each
+/// receiving a P2WPKH change output.
+pub fn estimate_fund_tx_weight(
+    nb_offer_inputs: usize,
+    nb_accept_inputs: usize,
+) -> Result<usize, Error> {
+    let inputs_weight = (TX_INPUT_BASE_WEIGHT + P2WPKH_WITNESS_SIZE)
+        .checked_mul(checked_add!(nb_offer_inputs, nb_accept_inputs)?)
+        .ok_or(Error::InvalidArgument)?;
+    checked_add!(
+        FUND_TX_BASE_WEIGHT,
+        inputs_weight,
+        2 * (P2WPKH_SCRIPT_PUBKEY_WEIGHT + 36)
+    )
+}
+
+/// Returns an estimate of the fees that a party pays for the fund transaction
+/// and for the CET or refund transaction when funding a contract with the given
+/// number of P2WPKH inputs and using P2WPKH change and payout outputs.
+pub fn estimate_party_fees(fee_rate_per_vb: u64, nb_inputs: usize) -> Result<(u64, u64), Error> {
+    let inputs_weight = (TX_INPUT_BASE_WEIGHT + P2WPKH_WITNESS_SIZE)
+        .checked_mul(nb_inputs)
+        .ok_or(Error::InvalidArgument)?;
+    let fund_weight = checked_add!(
+        FUND_TX_BASE_WEIGHT / 2,
+        inputs_weight,
+        P2WPKH_SCRIPT_PUBKEY_WEIGHT,
+        36
+    )?;
+    let cet_weight = checked_add!(CET_BASE_WEIGHT / 2, P2WPKH_SCRIPT_PUBKEY_WEIGHT)?;
+
+    Ok((
+        util::weight_to_fee(fund_weight, fee_rate_per_vb)?,
+        util::weight_to_fee(cet_weight, fee_rate_per_vb)?,
+    ))
+}
+
 pub(crate) fn create_fund_transaction_with_fees(
     offer_params: &PartyParams,
     accept_params: &PartyParams,
@@ -409,13 +728,20 @@ pub(crate) fn create_fund_transaction_with_fees(
     fund_lock_time: u32,
     fund_output_serial_id: u64,
     extra_fee: u64,
+    fee_split: FeeSplit,
 ) -> Result<(Transaction, ScriptBuf), Error> {
     let total_collateral = checked_add!(offer_params.collateral, accept_params.collateral)?;
 
-    let (offer_change_output, offer_fund_fee, offer_cet_fee) =
-        offer_params.get_change_output_and_fees(fee_rate_per_vb, extra_fee)?;
-    let (accept_change_output, accept_fund_fee, accept_cet_fee) =
-        accept_params.get_change_output_and_fees(fee_rate_per_vb, extra_fee)?;
+    let (offer_fund_fee, offer_cet_fee) = offer_params.get_fees(fee_rate_per_vb)?;
+    let (accept_fund_fee, accept_cet_fee) = accept_params.get_fees(fee_rate_per_vb)?;
+    let (offer_fees, accept_fees) = fee_split.split_fees(
+        checked_add!(offer_fund_fee, offer_cet_fee, extra_fee)?,
+        checked_add!(accept_fund_fee, accept_cet_fee, extra_fee)?,
+        offer_params.collateral,
+        accept_params.collateral,
+    )?;
+    let offer_change_output = offer_params.get_change_output(offer_fees)?;
+    let accept_change_output = accept_params.get_change_output(accept_fees)?;
 
     let fund_output_value = checked_add!(offer_params.input_amount, accept_params.input_amount)?
         - offer_change_output.value
@@ -755,6 +1081,7 @@ pub fn create_cet_adaptor_sig_from_oracle_info(
 }
 
 /// Crerate a set of adaptor signatures for the given cet/message pairs.
+/// Signatures are created in parallel when the `parallel` feature is enabled.
 pub fn create_cet_adaptor_sigs_from_points<C: secp256k1_zkp::Signing>(
     secp: &secp256k1_zkp::Secp256k1<C>,
     inputs: &[(&Transaction, &PublicKey)],
@@ -762,22 +1089,26 @@ pub fn create_cet_adaptor_sigs_from_points<C: secp256k1_zkp::Signing>(
     funding_script_pubkey: &Script,
     fund_output_value: u64,
 ) -> Result<Vec<EcdsaAdaptorSignature>, Error> {
-    inputs
-        .iter()
-        .map(|(cet, adaptor_point)| {
-            create_cet_adaptor_sig_from_point(
-                secp,
-                cet,
-                adaptor_point,
-                funding_sk,
-                funding_script_pubkey,
-                fund_output_value,
-            )
-        })
-        .collect()
+    #[cfg(not(feature = "parallel"))]
+    let iter = inputs.iter();
+    #[cfg(feature = "parallel")]
+    let iter = inputs.par_iter();
+
+    iter.map(|(cet, adaptor_point)| {
+        create_cet_adaptor_sig_from_point(
+            secp,
+            cet,
+            adaptor_point,
+            funding_sk,
+            funding_script_pubkey,
+            fund_output_value,
+        )
+    })
+    .collect()
 }
 
 /// Crerate a set of adaptor signatures for the given cet/message pairs.
+/// Signatures are created in parallel when the `parallel` feature is enabled.
 pub fn create_cet_adaptor_sigs_from_oracle_info(
     secp: &secp256k1_zkp::Secp256k1<secp256k1_zkp::All>,
     cets: &[Transaction],
@@ -791,20 +1122,23 @@ pub fn create_cet_adaptor_sigs_from_oracle_info(
         return Err(Error::InvalidArgument);
     }
 
-    cets.iter()
-        .zip(msgs.iter())
-        .map(|(cet, msg)| {
-            create_cet_adaptor_sig_from_oracle_info(
-                secp,
-                cet,
-                oracle_infos,
-                funding_sk,
-                funding_script_pubkey,
-                fund_output_value,
-                msg,
-            )
-        })
-        .collect()
+    #[cfg(not(feature = "parallel"))]
+    let iter = cets.iter().zip(msgs.iter());
+    #[cfg(feature = "parallel")]
+    let iter = cets.par_iter().zip(msgs.par_iter());
+
+    iter.map(|(cet, msg)| {
+        create_cet_adaptor_sig_from_oracle_info(
+            secp,
+            cet,
+            oracle_infos,
+            funding_sk,
+            funding_script_pubkey,
+            fund_output_value,
+            msg,
+        )
+    })
+    .collect()
 }
 
 fn signatures_to_secret(signatures: &[Vec<SchnorrSignature>]) -> Result<SecretKey, Error> {
@@ -872,6 +1206,34 @@ pub fn verify_cet_adaptor_sig_from_point(
     Ok(())
 }
 
+/// Verify that each of the given adaptor signatures is valid for its cet with
+/// respect to its adaptor point. Signatures are verified in parallel when the
+/// `parallel` feature is enabled.
+pub fn verify_cet_adaptor_sigs_from_points(
+    secp: &Secp256k1<secp256k1_zkp::All>,
+    inputs: &[(&EcdsaAdaptorSignature, &Transaction, &PublicKey)],
+    pubkey: &PublicKey,
+    funding_script_pubkey: &Script,
+    total_collateral: u64,
+) -> Result<(), Error> {
+    #[cfg(not(feature = "parallel"))]
+    let mut iter = inputs.iter();
+    #[cfg(feature = "parallel")]
+    let iter = inputs.par_iter();
+
+    iter.try_for_each(|(adaptor_sig, cet, adaptor_point)| {
+        verify_cet_adaptor_sig_from_point(
+            secp,
+            adaptor_sig,
+            cet,
+            adaptor_point,
+            pubkey,
+            funding_script_pubkey,
+            total_collateral,
+        )
+    })
+}
+
 /// Verify that a given adaptor signature for a given cet is valid with respect
 /// to an oracle public key, nonce and a given message.
 pub fn verify_cet_adaptor_sig_from_oracle_info(
@@ -918,13 +1280,11 @@ mod tests {
     use bitcoin::blockdata::transaction::OutPoint;
     use bitcoin::consensus::encode::Encodable;
     use bitcoin::sighash::EcdsaSighashType;
-    use bitcoin::{network::constants::Network, Address, Txid};
-    use secp256k1_zkp::{
-        rand::{Rng, RngCore},
-        KeyPair, PublicKey, Secp256k1, SecretKey, Signing,
-    };
+    use bitcoin::{Address, Txid};
+    use secp256k1_zkp::{rand::RngCore, KeyPair, PublicKey, Secp256k1, SecretKey};
     use std::fmt::Write;
     use std::str::FromStr;
+    use test_utils::{get_party_params, payouts};
     use util;
 
     fn create_txin_vec(sequence: Sequence) -> Vec<TxIn> {
@@ -1183,69 +1543,6 @@ mod tests {
         assert_eq!(expected_serialized, serialized);
     }
 
-    fn get_p2wpkh_script_pubkey<C: Signing, R: Rng + ?Sized>(
-        secp: &Secp256k1<C>,
-        rng: &mut R,
-    ) -> ScriptBuf {
-        let sk = bitcoin::PrivateKey {
-            inner: SecretKey::new(rng),
-            network: Network::Testnet,
-            compressed: true,
-        };
-        let pk = bitcoin::PublicKey::from_private_key(secp, &sk);
-        Address::p2wpkh(&pk, Network::Testnet)
-            .unwrap()
-            .script_pubkey()
-    }
-
-    fn get_party_params(
-        input_amount: u64,
-        collateral: u64,
-        serial_id: Option<u64>,
-    ) -> (PartyParams, SecretKey) {
-        let secp = Secp256k1::new();
-        let mut rng = secp256k1_zkp::rand::thread_rng();
-        let fund_privkey = SecretKey::new(&mut rng);
-        let serial_id = serial_id.unwrap_or(1);
-        (
-            PartyParams {
-                fund_pubkey: PublicKey::from_secret_key(&secp, &fund_privkey),
-                change_script_pubkey: get_p2wpkh_script_pubkey(&secp, &mut rng),
-                change_serial_id: serial_id,
-                payout_script_pubkey: get_p2wpkh_script_pubkey(&secp, &mut rng),
-                payout_serial_id: serial_id,
-                input_amount,
-                collateral,
-                inputs: vec![TxInputInfo {
-                    max_witness_len: 108,
-                    redeem_script: ScriptBuf::new(),
-                    outpoint: OutPoint {
-                        txid: Txid::from_str(
-                            "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456",
-                        )
-                        .unwrap(),
-                        vout: serial_id as u32,
-                    },
-                    serial_id,
-                }],
-            },
-            fund_privkey,
-        )
-    }
-
-    fn payouts() -> Vec<Payout> {
-        vec![
-            Payout {
-                offer: 200000000,
-                accept: 0,
-            },
-            Payout {
-                offer: 0,
-                accept: 200000000,
-            },
-        ]
-    }
-
     #[test]
     fn get_change_output_and_fees_enough_funds() {
         // Arrange
@@ -1272,6 +1569,34 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn estimate_party_fees_matches_p2wpkh_party_fees() {
+        // Arrange
+        let (party_params, _) = get_party_params(100000, 10000, None);
+
+        // Act
+        let (_, fund_fee, cet_fee) = party_params.get_change_output_and_fees(4, 0).unwrap();
+        let (estimated_fund_fee, estimated_cet_fee) = estimate_party_fees(4, 1).unwrap();
+
+        // Assert
+        // The test inputs use a witness one byte larger than the P2WPKH estimate.
+        assert!(estimated_fund_fee <= fund_fee && fund_fee <= estimated_fund_fee + 4);
+        assert_eq!(cet_fee, estimated_cet_fee);
+    }
+
+    #[test]
+    fn estimate_fund_tx_weight_matches_party_fees() {
+        // Arrange
+        let (fund_fee, _) = estimate_party_fees(1, 2).unwrap();
+
+        // Act
+        let fee = util::weight_to_fee(estimate_fund_tx_weight(2, 2).unwrap(), 1).unwrap();
+
+        // Assert
+        // Each party rounds its share of the weight up.
+        assert!(fee <= 2 * fund_fee && 2 * fund_fee <= fee + 1);
+    }
+
     #[test]
     fn create_dlc_transactions_no_error() {
         // Arrange
@@ -1288,6 +1613,8 @@ mod tests {
             10,
             10,
             0,
+            false,
+            FeeSplit::Equal,
         )
         .unwrap();
 
@@ -1300,6 +1627,334 @@ mod tests {
             .all(|x| x.lock_time.to_consensus_u32() == 10));
     }
 
+    #[test]
+    fn split_fees_test() {
+        assert_eq!((3, 5), FeeSplit::Equal.split_fees(3, 5, 10, 30).unwrap());
+        assert_eq!(
+            (8, 0),
+            FeeSplit::OfferPaysAll.split_fees(3, 5, 10, 30).unwrap()
+        );
+        assert_eq!(
+            (0, 8),
+            FeeSplit::AcceptPaysAll.split_fees(3, 5, 10, 30).unwrap()
+        );
+        assert_eq!(
+            (2, 6),
+            FeeSplit::Proportional.split_fees(3, 5, 10, 30).unwrap()
+        );
+        assert!(FeeSplit::Proportional.split_fees(3, 5, 0, 0).is_err());
+    }
+
+    #[test]
+    fn create_dlc_transactions_with_offer_paying_all_fees() {
+        // Arrange
+        let (offer_party_params, _) = get_party_params(1000000000, 100000000, None);
+        // The accept party only provides its collateral.
+        let (accept_party_params, _) = get_party_params(100000000, 100000000, Some(2));
+        let get_change = |tx: &Transaction, params: &PartyParams| {
+            tx.output
+                .iter()
+                .find(|x| x.script_pubkey == params.change_script_pubkey)
+                .map(|x| x.value)
+        };
+        let create = |fee_split| {
+            create_dlc_transactions(
+                &offer_party_params,
+                &accept_party_params,
+                &payouts(),
+                100,
+                4,
+                10,
+                10,
+                0,
+                false,
+                fee_split,
+            )
+        };
+
+        // Act
+        let equal_split = create(FeeSplit::Equal);
+        let accept_pays_all = create(FeeSplit::AcceptPaysAll);
+        let dlc_txs = create(FeeSplit::OfferPaysAll).unwrap();
+
+        // Assert
+        assert!(equal_split.is_err());
+        assert!(accept_pays_all.is_err());
+        let (offer_fund_fee, offer_cet_fee) = offer_party_params.get_fees(4).unwrap();
+        let (accept_fund_fee, accept_cet_fee) = accept_party_params.get_fees(4).unwrap();
+        assert_eq!(None, get_change(&dlc_txs.fund, &accept_party_params));
+        assert_eq!(
+            Some(
+                offer_party_params.input_amount
+                    - offer_party_params.collateral
+                    - offer_fund_fee
+                    - offer_cet_fee
+                    - accept_fund_fee
+                    - accept_cet_fee
+            ),
+            get_change(&dlc_txs.fund, &offer_party_params)
+        );
+        assert_eq!(
+            offer_party_params.collateral
+                + accept_party_params.collateral
+                + offer_cet_fee
+                + accept_cet_fee,
+            dlc_txs.get_fund_output().value
+        );
+    }
+
+    #[test]
+    fn create_single_funded_dlc_transactions() {
+        // Arrange
+        let (offer_party_params, _) = get_party_params(1000000000, 200000000, None);
+        let (mut accept_party_params, _) = get_party_params(0, 0, Some(2));
+        accept_party_params.inputs.clear();
+
+        // Act
+        let dlc_txs = create_dlc_transactions(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+            false,
+            FeeSplit::OfferPaysAll,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(1, dlc_txs.fund.input.len());
+        assert_eq!(2, dlc_txs.fund.output.len());
+        assert!(dlc_txs
+            .fund
+            .output
+            .iter()
+            .all(|x| x.script_pubkey != accept_party_params.change_script_pubkey));
+    }
+
+    #[test]
+    fn create_dlc_transactions_with_anchors_funds_anchor_outputs() {
+        let (offer_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let (accept_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let create = |anchor_outputs| {
+            create_dlc_transactions(
+                &offer_party_params,
+                &accept_party_params,
+                &payouts(),
+                100,
+                4,
+                10,
+                10,
+                0,
+                anchor_outputs,
+                FeeSplit::Equal,
+            )
+            .unwrap()
+        };
+
+        let dlc_txs = create(false);
+        let anchor_dlc_txs = create(true);
+
+        assert_eq!(
+            dlc_txs.get_fund_output().value + get_anchor_extra_fee(4).unwrap(),
+            anchor_dlc_txs.get_fund_output().value
+        );
+        for (cet, anchor_cet) in dlc_txs.cets.iter().zip(anchor_dlc_txs.cets.iter()) {
+            assert_eq!(Some(&get_anchor_output()), anchor_cet.output.last());
+            let fee = |tx: &Transaction, input_value: u64| {
+                input_value - tx.output.iter().map(|x| x.value).sum::<u64>()
+            };
+            assert!(
+                fee(anchor_cet, anchor_dlc_txs.get_fund_output().value)
+                    > fee(cet, dlc_txs.get_fund_output().value)
+            );
+        }
+    }
+
+    #[test]
+    fn create_amended_dlc_transactions_spends_previous_fund_output() {
+        // Arrange
+        let (offer_party_params, _) = get_party_params(1000000000, 100000000, Some(1));
+        let (accept_party_params, _) = get_party_params(1000000000, 100000000, Some(2));
+        let prev_dlc_txs = create_dlc_transactions(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+            false,
+            FeeSplit::Equal,
+        )
+        .unwrap();
+        let (mut amended_offer_params, _) = get_party_params(100000000, 150000000, Some(3));
+        amended_offer_params.fund_pubkey = offer_party_params.fund_pubkey;
+        let (mut amended_accept_params, _) = get_party_params(100000000, 150000000, Some(4));
+        amended_accept_params.fund_pubkey = accept_party_params.fund_pubkey;
+        let amended_payouts = vec![
+            Payout {
+                offer: 300000000,
+                accept: 0,
+            },
+            Payout {
+                offer: 0,
+                accept: 300000000,
+            },
+        ];
+
+        // Act
+        let dlc_txs = create_amended_dlc_transactions(
+            &prev_dlc_txs,
+            100000000,
+            100000000,
+            &amended_offer_params,
+            &amended_accept_params,
+            &amended_payouts,
+            100,
+            4,
+            10,
+            10,
+            0,
+            false,
+            FeeSplit::Equal,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(3, dlc_txs.fund.input.len());
+        assert_eq!(
+            prev_dlc_txs.get_fund_outpoint(),
+            dlc_txs.fund.input[0].previous_output
+        );
+        assert_eq!(
+            prev_dlc_txs.funding_script_pubkey,
+            dlc_txs.funding_script_pubkey
+        );
+        let (_, cet_fee) = estimate_party_fees(4, 0).unwrap();
+        assert_eq!(300000000 + 2 * cet_fee, dlc_txs.get_fund_output().value);
+        let input_value = prev_dlc_txs.get_fund_output().value + 2 * 100000000;
+        let output_value = dlc_txs.fund.output.iter().map(|x| x.value).sum::<u64>();
+        assert!(output_value < input_value);
+        assert!(dlc_txs
+            .cets
+            .iter()
+            .all(|x| x.input[0].previous_output == dlc_txs.get_fund_outpoint()));
+    }
+
+    #[test]
+    fn create_amended_dlc_transactions_not_enough_funds() {
+        let (offer_party_params, _) = get_party_params(1000000000, 100000000, Some(1));
+        let (accept_party_params, _) = get_party_params(1000000000, 100000000, Some(2));
+        let prev_dlc_txs = create_dlc_transactions(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+            false,
+            FeeSplit::Equal,
+        )
+        .unwrap();
+        let (amended_offer_params, _) = get_party_params(10000000, 150000000, Some(3));
+        let (amended_accept_params, _) = get_party_params(10000000, 100000000, Some(4));
+
+        assert!(create_amended_dlc_transactions(
+            &prev_dlc_txs,
+            100000000,
+            100000000,
+            &amended_offer_params,
+            &amended_accept_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+            false,
+            FeeSplit::Equal,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn create_netted_dlc_transactions_releases_collateral() {
+        let prev_dlc_txs = (0..2)
+            .map(|i| {
+                let (offer_party_params, _) = get_party_params(1000000000, 100000000, Some(2 * i));
+                let (accept_party_params, _) =
+                    get_party_params(1000000000, 100000000, Some(2 * i + 1));
+                create_dlc_transactions(
+                    &offer_party_params,
+                    &accept_party_params,
+                    &payouts(),
+                    100,
+                    4,
+                    10,
+                    10,
+                    0,
+                    false,
+                    FeeSplit::Equal,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let without_inputs = |serial_id| {
+            let (mut params, _) = get_party_params(0, 50000000, Some(serial_id));
+            params.inputs.clear();
+            params.input_amount = 0;
+            params
+        };
+
+        let dlc_txs = create_netted_dlc_transactions(
+            &[
+                (&prev_dlc_txs[0], 100000000, 100000000),
+                (&prev_dlc_txs[1], 100000000, 100000000),
+            ],
+            &without_inputs(3),
+            &without_inputs(4),
+            &[
+                Payout {
+                    offer: 100000000,
+                    accept: 0,
+                },
+                Payout {
+                    offer: 0,
+                    accept: 100000000,
+                },
+            ],
+            100,
+            4,
+            10,
+            10,
+            0,
+            false,
+            FeeSplit::Equal,
+        )
+        .unwrap();
+
+        assert_eq!(2, dlc_txs.fund.input.len());
+        for (input, prev) in dlc_txs.fund.input.iter().zip(prev_dlc_txs.iter()) {
+            assert_eq!(prev.get_fund_outpoint(), input.previous_output);
+        }
+        let (_, cet_fee) = estimate_party_fees(4, 0).unwrap();
+        assert_eq!(100000000 + 2 * cet_fee, dlc_txs.get_fund_output().value);
+        assert_eq!(3, dlc_txs.fund.output.len());
+        assert!(dlc_txs
+            .fund
+            .output
+            .iter()
+            .filter(|x| x.script_pubkey != dlc_txs.funding_script_pubkey.to_v0_p2wsh())
+            .all(|x| x.value > 149000000));
+    }
+
     #[test]
     fn create_cet_adaptor_sig_is_valid() {
         // Arrange
@@ -1317,6 +1972,8 @@ mod tests {
             10,
             10,
             0,
+            false,
+            FeeSplit::Equal,
         )
         .unwrap();
 
@@ -1420,6 +2077,24 @@ mod tests {
                 &messages[i],
             )
             .is_ok()));
+        let adaptor_points: Vec<_> = messages
+            .iter()
+            .map(|m| get_adaptor_point_from_oracle_info(&secp, &oracle_infos, m).unwrap())
+            .collect();
+        let verify_inputs: Vec<_> = cet_sigs
+            .iter()
+            .zip(cets.iter())
+            .zip(adaptor_points.iter())
+            .map(|((sig, cet), point)| (sig, cet, point))
+            .collect();
+        verify_cet_adaptor_sigs_from_points(
+            &secp,
+            &verify_inputs,
+            &offer_party_params.fund_pubkey,
+            &funding_script_pubkey,
+            fund_output_value,
+        )
+        .expect("Invalid adaptor signatures");
         sign_res.expect("Error signing CET");
         verify_tx_input_sig(
             &secp,
@@ -1487,6 +2162,8 @@ mod tests {
                 10,
                 10,
                 case.serials[0],
+                false,
+                FeeSplit::Equal,
             )
             .unwrap();
 
@@ -0,0 +1,210 @@
+//! Schnorr adaptor signatures, used to make the signatures of the CETs spending
+//! a taproot fund output conditional on the attestation of the oracles.
+//!
+//! An adaptor signature for a point `T = t*G` is a pair `(R, s')` such that
+//! `s'*G = R - T + e*P`. Adding `t` to `s'` produces a BIP340 signature `(R, s)`,
+//! and `t` can in turn be recovered from `s` and `s'`.
+
+use secp256k1_zkp::{
+    schnorr::Signature as SchnorrSignature, KeyPair, Message, Parity, PublicKey, Scalar, Secp256k1,
+    SecretKey, Signing, Verification, XOnlyPublicKey,
+};
+
+use super::musig::{hash_to_scalar, incorrect_signature, tagged_hash};
+use crate::Error;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A BIP340 signature encrypted with an adaptor point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SchnorrAdaptorSignature {
+    /// The x-only nonce of the decrypted signature, whose point has an even y
+    /// coordinate.
+    r: XOnlyPublicKey,
+    s: SecretKey,
+}
+
+impl SchnorrAdaptorSignature {
+    /// Creates an adaptor signature of the given message with the given key,
+    /// encrypted with the given adaptor point.
+    pub fn encrypt<C: Signing>(
+        secp: &Secp256k1<C>,
+        msg: &Message,
+        seckey: &SecretKey,
+        adaptor_point: &PublicKey,
+    ) -> Result<Self, Error> {
+        let (pubkey, parity) = KeyPair::from_secret_key(secp, seckey).x_only_public_key();
+        let x = if parity == Parity::Odd {
+            seckey.negate()
+        } else {
+            *seckey
+        };
+        // The nonce is derived deterministically, retrying until the nonce
+        // point of the decrypted signature has an even y coordinate, which
+        // happens half of the time.
+        for counter in 0u32.. {
+            let k = hash_to_scalar(tagged_hash(
+                b"DLC/adaptor/nonce",
+                &[
+                    &x.secret_bytes()[..],
+                    &adaptor_point.serialize()[..],
+                    &msg[..],
+                    &counter.to_be_bytes()[..],
+                ],
+            ))?;
+            let r = PublicKey::from_secret_key(secp, &k).combine(adaptor_point)?;
+            let (r, parity) = r.x_only_public_key();
+            if parity == Parity::Odd {
+                continue;
+            }
+            let e = challenge(&r, &pubkey, msg)?;
+            let s = k.add_tweak(&Scalar::from(x.mul_tweak(&Scalar::from(e))?))?;
+            return Ok(SchnorrAdaptorSignature { r, s });
+        }
+        unreachable!()
+    }
+
+    /// Verifies that the adaptor signature decrypts to a valid signature of
+    /// the given message for the given public key, once decrypted with the
+    /// discrete logarithm of the given adaptor point.
+    pub fn verify<C: Signing + Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        msg: &Message,
+        pubkey: &XOnlyPublicKey,
+        adaptor_point: &PublicKey,
+    ) -> Result<(), Error> {
+        let e = challenge(&self.r, pubkey, msg)?;
+        let ep = pubkey
+            .public_key(Parity::Even)
+            .mul_tweak(secp, &Scalar::from(e))?;
+        let expected = PublicKey::combine_keys(&[
+            &self.r.public_key(Parity::Even),
+            &adaptor_point.negate(secp),
+            &ep,
+        ])?;
+        if PublicKey::from_secret_key(secp, &self.s) != expected {
+            return Err(incorrect_signature());
+        }
+        Ok(())
+    }
+
+    /// Decrypts the adaptor signature using the discrete logarithm of its
+    /// adaptor point.
+    pub fn decrypt(&self, adaptor_secret: &SecretKey) -> Result<SchnorrSignature, Error> {
+        let s = self.s.add_tweak(&Scalar::from(*adaptor_secret))?;
+        let mut sig = [0u8; 64];
+        sig[..32].copy_from_slice(&self.r.serialize());
+        sig[32..].copy_from_slice(&s.secret_bytes());
+        Ok(SchnorrSignature::from_slice(&sig)?)
+    }
+
+    /// Recovers the discrete logarithm of the adaptor point from the decrypted
+    /// signature.
+    pub fn recover<C: Signing>(
+        &self,
+        secp: &Secp256k1<C>,
+        sig: &SchnorrSignature,
+        adaptor_point: &PublicKey,
+    ) -> Result<SecretKey, Error> {
+        let bytes = sig.as_ref();
+        if bytes[..32] != self.r.serialize()[..] {
+            return Err(Error::InvalidArgument);
+        }
+        let s = SecretKey::from_slice(&bytes[32..])?;
+        let t = s.add_tweak(&Scalar::from(self.s.negate()))?;
+        if &PublicKey::from_secret_key(secp, &t) != adaptor_point {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(t)
+    }
+
+    /// Serializes the adaptor signature as the x coordinate of its nonce
+    /// followed by its scalar.
+    pub fn serialize(&self) -> [u8; 64] {
+        let mut res = [0u8; 64];
+        res[..32].copy_from_slice(&self.r.serialize());
+        res[32..].copy_from_slice(&self.s.secret_bytes());
+        res
+    }
+
+    /// Parses an adaptor signature serialized with
+    /// [`SchnorrAdaptorSignature::serialize`].
+    pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != 64 {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(SchnorrAdaptorSignature {
+            r: XOnlyPublicKey::from_slice(&data[..32])?,
+            s: SecretKey::from_slice(&data[32..])?,
+        })
+    }
+}
+
+fn challenge(
+    r: &XOnlyPublicKey,
+    pubkey: &XOnlyPublicKey,
+    msg: &Message,
+) -> Result<SecretKey, Error> {
+    hash_to_scalar(tagged_hash(
+        b"BIP0340/challenge",
+        &[&r.serialize()[..], &pubkey.serialize()[..], &msg[..]],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::rand::thread_rng;
+
+    #[test]
+    fn decrypted_adaptor_signature_is_valid() {
+        let secp = Secp256k1::new();
+        let msg = Message::from_slice(&[1u8; 32]).unwrap();
+        for _ in 0..8 {
+            let seckey = SecretKey::new(&mut thread_rng());
+            let pubkey = KeyPair::from_secret_key(&secp, &seckey)
+                .x_only_public_key()
+                .0;
+            let adaptor_secret = SecretKey::new(&mut thread_rng());
+            let adaptor_point = PublicKey::from_secret_key(&secp, &adaptor_secret);
+
+            let adaptor_sig =
+                SchnorrAdaptorSignature::encrypt(&secp, &msg, &seckey, &adaptor_point).unwrap();
+            adaptor_sig
+                .verify(&secp, &msg, &pubkey, &adaptor_point)
+                .expect("a valid adaptor signature");
+            assert_eq!(
+                adaptor_sig,
+                SchnorrAdaptorSignature::from_slice(&adaptor_sig.serialize()).unwrap()
+            );
+
+            let sig = adaptor_sig.decrypt(&adaptor_secret).unwrap();
+            secp.verify_schnorr(&sig, &msg, &pubkey)
+                .expect("a valid signature");
+            assert_eq!(
+                adaptor_secret,
+                adaptor_sig.recover(&secp, &sig, &adaptor_point).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn adaptor_signature_for_other_point_is_rejected() {
+        let secp = Secp256k1::new();
+        let msg = Message::from_slice(&[1u8; 32]).unwrap();
+        let seckey = SecretKey::new(&mut thread_rng());
+        let pubkey = KeyPair::from_secret_key(&secp, &seckey)
+            .x_only_public_key()
+            .0;
+        let adaptor_point = PublicKey::from_secret_key(&secp, &SecretKey::new(&mut thread_rng()));
+        let other_point = PublicKey::from_secret_key(&secp, &SecretKey::new(&mut thread_rng()));
+
+        let adaptor_sig =
+            SchnorrAdaptorSignature::encrypt(&secp, &msg, &seckey, &adaptor_point).unwrap();
+        adaptor_sig
+            .verify(&secp, &msg, &pubkey, &other_point)
+            .expect_err("an adaptor signature for another point");
+    }
+}
@@ -0,0 +1,527 @@
+//! # Taproot fund outputs
+//! Creation, signing and verification of DLC transactions whose fund output is
+//! a P2TR output, smaller and more private than the P2WSH multisig output:
+//! * its internal key is the MuSig2 aggregate of the fund public keys of the
+//!   parties, so that the output can be cooperatively spent through the key
+//!   path, looking like a single key spend,
+//! * its single script leaf is a 2-of-2 multisig script, through which the
+//!   CETs and refund transaction are spent, the CET signatures being made
+//!   conditional on the oracle attestations using Schnorr adaptor signatures.
+
+pub mod adaptor;
+pub mod musig;
+
+use bitcoin::blockdata::{opcodes, script::Builder};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{LeafVersion, TapLeafHash, TaprootBuilder, TaprootSpendInfo};
+use bitcoin::{OutPoint, ScriptBuf, Transaction, TxOut, Witness};
+use secp256k1_zkp::{
+    schnorr::Signature as SchnorrSignature, KeyPair, Message, PublicKey, Secp256k1, SecretKey,
+    Signing, Verification, XOnlyPublicKey,
+};
+
+use self::adaptor::SchnorrAdaptorSignature;
+use self::musig::{key_sort, KeyAggContext};
+use crate::{
+    create_cets_and_refund_tx, create_fund_transaction_with_fees, get_anchor_extra_fee,
+    get_anchor_output, signatures_to_secret, util, DlcTransactions, Error, FeeSplit, PartyParams,
+    Payout, FUND_OUTPUT_INPUT_WEIGHT, TX_INPUT_BASE_WEIGHT,
+};
+
+/// The weight of an input spending a taproot fund output through its script
+/// path, computed as: TX_INPUT_BASE_WEIGHT + witness(items count(1) +
+/// 2 signatures(2 * 65) + script(69) + control block(34))
+pub const TAPROOT_SCRIPT_SPEND_INPUT_WEIGHT: usize = TX_INPUT_BASE_WEIGHT + 234;
+
+/// The weight of an input spending a taproot fund output through its key path,
+/// computed as: TX_INPUT_BASE_WEIGHT + witness(items count(1) + signature(65))
+pub const TAPROOT_KEY_SPEND_INPUT_WEIGHT: usize = TX_INPUT_BASE_WEIGHT + 66;
+
+/// Additional weight of CETs and refund transactions spending a taproot fund
+/// output, compared to the P2WSH input accounted for in the CET base weight.
+const TAPROOT_EXTRA_CET_WEIGHT: usize =
+    TAPROOT_SCRIPT_SPEND_INPUT_WEIGHT - FUND_OUTPUT_INPUT_WEIGHT;
+
+/// The information required to spend a taproot fund output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaprootFundingInfo {
+    /// The 2-of-2 multisig script of the single leaf of the output.
+    pub script: ScriptBuf,
+    /// The key aggregation context for cooperatively spending the output
+    /// through its key path, including the taproot tweak.
+    pub key_agg_ctx: KeyAggContext,
+    /// The taproot spending information of the output.
+    pub spend_info: TaprootSpendInfo,
+}
+
+impl TaprootFundingInfo {
+    /// Creates the funding information for the given fund public keys, whose
+    /// order does not matter.
+    pub fn new<C: Verification>(
+        secp: &Secp256k1<C>,
+        a: &PublicKey,
+        b: &PublicKey,
+    ) -> Result<Self, Error> {
+        let script = make_taproot_funding_script(a, b);
+        let untweaked = KeyAggContext::new(secp, &key_sort(&[*a, *b]))?;
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(0, script.clone())
+            .map_err(|_| Error::InvalidArgument)?
+            .finalize(secp, untweaked.agg_pk())
+            .map_err(|_| Error::InvalidArgument)?;
+        let key_agg_ctx = untweaked.with_taproot_tweak(secp, spend_info.merkle_root())?;
+        Ok(TaprootFundingInfo {
+            script,
+            key_agg_ctx,
+            spend_info,
+        })
+    }
+
+    /// Returns the script pubkey of the fund output.
+    pub fn script_pubkey(&self) -> ScriptBuf {
+        ScriptBuf::new_v1_p2tr_tweaked(self.spend_info.output_key())
+    }
+
+    /// Returns the output key of the fund output, for which the signatures
+    /// produced by the key aggregation context are valid.
+    pub fn output_key(&self) -> XOnlyPublicKey {
+        self.spend_info.output_key().to_inner()
+    }
+
+    fn leaf_hash(&self) -> TapLeafHash {
+        TapLeafHash::from_script(&self.script, LeafVersion::TapScript)
+    }
+}
+
+/// Create the 2-of-2 multisig leaf script of a taproot fund output.
+pub fn make_taproot_funding_script(a: &PublicKey, b: &PublicKey) -> ScriptBuf {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+
+    Builder::new()
+        .push_x_only_key(&first.x_only_public_key().0)
+        .push_opcode(opcodes::all::OP_CHECKSIGVERIFY)
+        .push_x_only_key(&second.x_only_public_key().0)
+        .push_opcode(opcodes::all::OP_CHECKSIG)
+        .into_script()
+}
+
+/// The transactions of a DLC funded with a taproot output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaprootDlcTransactions {
+    /// The fund transaction locking both parties collaterals
+    pub fund: Transaction,
+    /// The contract execution transactions for closing the contract on a
+    /// certain outcome
+    pub cets: Vec<Transaction>,
+    /// The refund transaction for returning the collateral for each party in
+    /// case of an oracle misbehavior
+    pub refund: Transaction,
+    /// The information required to spend the fund output
+    pub funding_info: TaprootFundingInfo,
+}
+
+impl TaprootDlcTransactions {
+    /// Get the fund output in the fund transaction
+    pub fn get_fund_output(&self) -> &TxOut {
+        &self.fund.output[self.get_fund_output_index()]
+    }
+
+    /// Get the index of the fund output in the fund transaction
+    pub fn get_fund_output_index(&self) -> usize {
+        util::get_output_for_script_pubkey(&self.fund, &self.funding_info.script_pubkey())
+            .expect("to find the fund output")
+            .0
+    }
+
+    /// Get the outpoint for the fund output in the fund transaction
+    pub fn get_fund_outpoint(&self) -> OutPoint {
+        OutPoint {
+            txid: self.fund.txid(),
+            vout: self.get_fund_output_index() as u32,
+        }
+    }
+}
+
+impl From<TaprootDlcTransactions> for DlcTransactions {
+    /// Converts the transactions, using the script pubkey of the fund output
+    /// as funding script pubkey. The funding information can be recreated
+    /// from the fund public keys of the parties.
+    fn from(txs: TaprootDlcTransactions) -> DlcTransactions {
+        DlcTransactions {
+            funding_script_pubkey: txs.funding_info.script_pubkey(),
+            fund: txs.fund,
+            cets: txs.cets,
+            refund: txs.refund,
+        }
+    }
+}
+
+/// Create the transactions for a DLC funded with a taproot output, with the
+/// same semantic as [`create_dlc_transactions`](crate::create_dlc_transactions).
+/// The additional weight of the script path spend of the fund output is
+/// accounted for in the CET fee.
+pub fn create_taproot_dlc_transactions<C: Verification>(
+    secp: &Secp256k1<C>,
+    offer_params: &PartyParams,
+    accept_params: &PartyParams,
+    payouts: &[Payout],
+    refund_lock_time: u32,
+    fee_rate_per_vb: u64,
+    fund_lock_time: u32,
+    cet_lock_time: u32,
+    fund_output_serial_id: u64,
+    anchor_outputs: bool,
+    fee_split: FeeSplit,
+) -> Result<TaprootDlcTransactions, Error> {
+    let anchor_fee = if anchor_outputs {
+        get_anchor_extra_fee(fee_rate_per_vb)?
+    } else {
+        0
+    };
+    let extra_fee = anchor_fee
+        .checked_add(util::weight_to_fee(
+            TAPROOT_EXTRA_CET_WEIGHT,
+            fee_rate_per_vb,
+        )?)
+        .ok_or(Error::InvalidArgument)?;
+    let (mut fund_tx, funding_redeem_script) = create_fund_transaction_with_fees(
+        offer_params,
+        accept_params,
+        fee_rate_per_vb,
+        fund_lock_time,
+        fund_output_serial_id,
+        extra_fee,
+        fee_split,
+    )?;
+    let funding_info =
+        TaprootFundingInfo::new(secp, &offer_params.fund_pubkey, &accept_params.fund_pubkey)?;
+    let fund_vout =
+        util::get_output_for_script_pubkey(&fund_tx, &funding_redeem_script.to_v0_p2wsh())
+            .expect("to find the funding script pubkey")
+            .0;
+    // Both script pubkeys have the same size, so that the fees of the fund
+    // transaction are unchanged.
+    fund_tx.output[fund_vout].script_pubkey = funding_info.script_pubkey();
+
+    let fund_outpoint = OutPoint {
+        txid: fund_tx.txid(),
+        vout: fund_vout as u32,
+    };
+    let (mut cets, refund_tx) = create_cets_and_refund_tx(
+        offer_params,
+        accept_params,
+        fund_outpoint,
+        payouts,
+        refund_lock_time,
+        cet_lock_time,
+        None,
+    )?;
+
+    if anchor_outputs {
+        for cet in &mut cets {
+            cet.output.push(get_anchor_output());
+        }
+    }
+
+    Ok(TaprootDlcTransactions {
+        fund: fund_tx,
+        cets,
+        refund: refund_tx,
+        funding_info,
+    })
+}
+
+/// Returns the message to sign for spending the fund output through its script
+/// path with the given transaction, whose only input spends the fund output.
+pub fn get_script_spend_sighash(
+    tx: &Transaction,
+    funding_info: &TaprootFundingInfo,
+    fund_output_value: u64,
+) -> Result<Message, Error> {
+    if tx.input.len() != 1 {
+        return Err(Error::InvalidArgument);
+    }
+    let prevout = TxOut {
+        value: fund_output_value,
+        script_pubkey: funding_info.script_pubkey(),
+    };
+    let sig_hash = SighashCache::new(tx).taproot_script_spend_signature_hash(
+        0,
+        &Prevouts::All(&[prevout]),
+        funding_info.leaf_hash(),
+        TapSighashType::Default,
+    )?;
+    Ok(Message::from_slice(sig_hash.as_ref()).unwrap())
+}
+
+/// Returns the message to sign for cooperatively spending the fund output
+/// through its key path with the input at the given index of the given
+/// transaction. `prevouts` must contain the outputs spent by all the inputs of
+/// the transaction, in order.
+pub fn get_key_spend_sighash(
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[TxOut],
+) -> Result<Message, Error> {
+    if prevouts.len() != tx.input.len() {
+        return Err(Error::InvalidArgument);
+    }
+    let sig_hash = SighashCache::new(tx).taproot_key_spend_signature_hash(
+        input_index,
+        &Prevouts::All(prevouts),
+        TapSighashType::Default,
+    )?;
+    Ok(Message::from_slice(sig_hash.as_ref()).unwrap())
+}
+
+/// Places the given aggregate signature on the witness stack of the input at
+/// the given index, spending the fund output through its key path.
+pub fn finalize_key_spend(tx: &mut Transaction, input_index: usize, sig: &SchnorrSignature) {
+    tx.input[input_index].witness = Witness::from_slice(&[sig.as_ref().to_vec()]);
+}
+
+/// Create the signature of a transaction spending the fund output through its
+/// script path, such as the refund transaction.
+pub fn sign_script_spend<C: Signing>(
+    secp: &Secp256k1<C>,
+    tx: &Transaction,
+    funding_sk: &SecretKey,
+    funding_info: &TaprootFundingInfo,
+    fund_output_value: u64,
+) -> Result<SchnorrSignature, Error> {
+    let msg = get_script_spend_sighash(tx, funding_info, fund_output_value)?;
+    Ok(secp.sign_schnorr_no_aux_rand(&msg, &KeyPair::from_secret_key(secp, funding_sk)))
+}
+
+/// Verify the signature of a transaction spending the fund output through its
+/// script path.
+pub fn verify_script_spend_sig<C: Verification>(
+    secp: &Secp256k1<C>,
+    signature: &SchnorrSignature,
+    tx: &Transaction,
+    pubkey: &PublicKey,
+    funding_info: &TaprootFundingInfo,
+    fund_output_value: u64,
+) -> Result<(), Error> {
+    let msg = get_script_spend_sighash(tx, funding_info, fund_output_value)?;
+    secp.verify_schnorr(signature, &msg, &pubkey.x_only_public_key().0)?;
+    Ok(())
+}
+
+/// Places the given signatures on the witness stack of the only input of the
+/// given transaction, spending the fund output through its script path.
+pub fn finalize_script_spend(
+    tx: &mut Transaction,
+    funding_info: &TaprootFundingInfo,
+    own_sig: &SchnorrSignature,
+    own_pk: &PublicKey,
+    other_sig: &SchnorrSignature,
+    other_pk: &PublicKey,
+) -> Result<(), Error> {
+    let control_block = funding_info
+        .spend_info
+        .control_block(&(funding_info.script.clone(), LeafVersion::TapScript))
+        .ok_or(Error::InvalidArgument)?;
+    // The signature for the first key of the script is checked first, so it
+    // must be on top of the stack.
+    let (first_sig, second_sig) = if own_pk <= other_pk {
+        (own_sig, other_sig)
+    } else {
+        (other_sig, own_sig)
+    };
+    tx.input[0].witness = Witness::from_slice(&[
+        second_sig.as_ref().to_vec(),
+        first_sig.as_ref().to_vec(),
+        funding_info.script.to_bytes(),
+        control_block.serialize(),
+    ]);
+    Ok(())
+}
+
+/// Create an adaptor signature for the given cet using the provided adaptor
+/// point.
+pub fn create_cet_adaptor_sig_from_point<C: Signing>(
+    secp: &Secp256k1<C>,
+    cet: &Transaction,
+    adaptor_point: &PublicKey,
+    funding_sk: &SecretKey,
+    funding_info: &TaprootFundingInfo,
+    fund_output_value: u64,
+) -> Result<SchnorrAdaptorSignature, Error> {
+    let msg = get_script_spend_sighash(cet, funding_info, fund_output_value)?;
+    SchnorrAdaptorSignature::encrypt(secp, &msg, funding_sk, adaptor_point)
+}
+
+/// Verify that a given adaptor signature for a given cet is valid with respect
+/// to an adaptor point.
+pub fn verify_cet_adaptor_sig_from_point<C: Signing + Verification>(
+    secp: &Secp256k1<C>,
+    adaptor_sig: &SchnorrAdaptorSignature,
+    cet: &Transaction,
+    adaptor_point: &PublicKey,
+    pubkey: &PublicKey,
+    funding_info: &TaprootFundingInfo,
+    fund_output_value: u64,
+) -> Result<(), Error> {
+    let msg = get_script_spend_sighash(cet, funding_info, fund_output_value)?;
+    adaptor_sig.verify(secp, &msg, &pubkey.x_only_public_key().0, adaptor_point)
+}
+
+/// Sign the given cet using own private key, adapt the counter party signature
+/// and place both signatures and the script path information on the witness
+/// stack.
+pub fn sign_cet<C: Signing>(
+    secp: &Secp256k1<C>,
+    cet: &mut Transaction,
+    adaptor_signature: &SchnorrAdaptorSignature,
+    oracle_signatures: &[Vec<SchnorrSignature>],
+    funding_sk: &SecretKey,
+    other_pk: &PublicKey,
+    funding_info: &TaprootFundingInfo,
+    fund_output_value: u64,
+) -> Result<(), Error> {
+    let adaptor_secret = signatures_to_secret(oracle_signatures)?;
+    let other_sig = adaptor_signature.decrypt(&adaptor_secret)?;
+    let own_sig = sign_script_spend(secp, cet, funding_sk, funding_info, fund_output_value)?;
+
+    finalize_script_spend(
+        cet,
+        funding_info,
+        &own_sig,
+        &PublicKey::from_secret_key(secp, funding_sk),
+        &other_sig,
+        other_pk,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::musig::{PubNonce, SecNonce, SigningSession};
+    use super::*;
+    use crate::test_utils::{get_party_params, payouts};
+    use secp256k1_zkp::rand::{thread_rng, RngCore};
+
+    fn get_transactions() -> (TaprootDlcTransactions, SecretKey, SecretKey) {
+        let secp = Secp256k1::new();
+        let (offer_params, offer_sk) = get_party_params(1000000000, 100000000, None);
+        let (accept_params, accept_sk) = get_party_params(1000000000, 100000000, None);
+        let dlc_txs = create_taproot_dlc_transactions(
+            &secp,
+            &offer_params,
+            &accept_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+            false,
+            FeeSplit::Equal,
+        )
+        .unwrap();
+        (dlc_txs, offer_sk, accept_sk)
+    }
+
+    #[test]
+    fn musig_key_is_taproot_output_key() {
+        let (dlc_txs, _, _) = get_transactions();
+
+        assert_eq!(
+            dlc_txs.funding_info.output_key(),
+            dlc_txs.funding_info.key_agg_ctx.agg_pk()
+        );
+        assert!(dlc_txs.get_fund_output().script_pubkey.is_v1_p2tr());
+    }
+
+    #[test]
+    fn cet_fee_accounts_for_script_spend() {
+        let (dlc_txs, offer_sk, accept_sk) = get_transactions();
+        let secp = Secp256k1::new();
+        let fund_output_value = dlc_txs.get_fund_output().value;
+        let mut cet = dlc_txs.cets[0].clone();
+        let oracle_sk = SecretKey::new(&mut thread_rng());
+        let oracle_kp = KeyPair::from_secret_key(&secp, &oracle_sk);
+        let oracle_sig =
+            secp.sign_schnorr_no_aux_rand(&Message::from_slice(&[1u8; 32]).unwrap(), &oracle_kp);
+        let adaptor_secret = signatures_to_secret(&[vec![oracle_sig]]).unwrap();
+        let adaptor_point = PublicKey::from_secret_key(&secp, &adaptor_secret);
+
+        let adaptor_sig = create_cet_adaptor_sig_from_point(
+            &secp,
+            &cet,
+            &adaptor_point,
+            &accept_sk,
+            &dlc_txs.funding_info,
+            fund_output_value,
+        )
+        .unwrap();
+        verify_cet_adaptor_sig_from_point(
+            &secp,
+            &adaptor_sig,
+            &cet,
+            &adaptor_point,
+            &PublicKey::from_secret_key(&secp, &accept_sk),
+            &dlc_txs.funding_info,
+            fund_output_value,
+        )
+        .expect("a valid adaptor signature");
+
+        sign_cet(
+            &secp,
+            &mut cet,
+            &adaptor_sig,
+            &[vec![oracle_sig]],
+            &offer_sk,
+            &PublicKey::from_secret_key(&secp, &accept_sk),
+            &dlc_txs.funding_info,
+            fund_output_value,
+        )
+        .unwrap();
+
+        let weight = cet.weight().to_wu() as usize;
+        let fee = fund_output_value - cet.output.iter().map(|x| x.value).sum::<u64>();
+        assert!(fee >= util::weight_to_fee(weight, 4).unwrap());
+    }
+
+    #[test]
+    fn key_spend_with_aggregate_signature_is_valid() {
+        let (dlc_txs, offer_sk, accept_sk) = get_transactions();
+        let secp = Secp256k1::new();
+        let ctx = &dlc_txs.funding_info.key_agg_ctx;
+        let msg = get_key_spend_sighash(&dlc_txs.refund, 0, &[dlc_txs.get_fund_output().clone()])
+            .unwrap();
+        let sec_nonces = [offer_sk, accept_sk]
+            .iter()
+            .map(|sk| {
+                let mut session_rand = [0u8; 32];
+                thread_rng().fill_bytes(&mut session_rand);
+                SecNonce::new(
+                    &secp,
+                    sk,
+                    &session_rand,
+                    Some(&ctx.agg_pk()),
+                    Some(&msg[..]),
+                    &[],
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let agg_nonce = PubNonce::aggregate(
+            &sec_nonces
+                .iter()
+                .map(|x| x.public_nonce(&secp))
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let session = SigningSession::new(&secp, ctx, &agg_nonce, &msg).unwrap();
+        let partial_sigs = sec_nonces
+            .into_iter()
+            .zip([offer_sk, accept_sk].iter())
+            .map(|(nonce, sk)| session.partial_sign(&secp, ctx, nonce, sk).unwrap())
+            .collect::<Vec<_>>();
+        let sig = session.aggregate_partial_sigs(ctx, &partial_sigs).unwrap();
+
+        secp.verify_schnorr(&sig, &msg, &dlc_txs.funding_info.output_key())
+            .expect("a valid key spend signature");
+    }
+}
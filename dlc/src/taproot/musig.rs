@@ -0,0 +1,758 @@
+//! Two-round multi-signature scheme following BIP327 (MuSig2), used to
+//! cooperatively spend taproot fund outputs through their key path.
+//!
+//! Nonces must never be reused across signing sessions: a [`SecNonce`] is
+//! consumed when producing a partial signature, and must be generated from
+//! fresh randomness for each session.
+//!
+//! Aggregate and final nonces at infinity, which cannot be produced on
+//! purpose by a signer, are rejected instead of being handled as specified.
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::taproot::{TapNodeHash, TapTweakHash};
+use secp256k1_zkp::{
+    constants::CURVE_ORDER, schnorr::Signature as SchnorrSignature, Message, Parity, PublicKey,
+    Scalar, Secp256k1, SecretKey, Signing, UpstreamError, Verification, XOnlyPublicKey,
+};
+
+use crate::Error;
+
+/// Computes a BIP340 tagged hash of the concatenation of the given data.
+pub(crate) fn tagged_hash(tag: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = sha256::Hash::hash(tag);
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag_hash.as_ref());
+    engine.input(tag_hash.as_ref());
+    for d in data {
+        engine.input(d);
+    }
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// Interprets the given hash as a scalar reduced modulo the curve order.
+/// Scalars that are zero happen with negligible probability and are
+/// rejected.
+pub(crate) fn hash_to_scalar(mut hash: [u8; 32]) -> Result<SecretKey, Error> {
+    if hash >= CURVE_ORDER {
+        // The hash is lower than twice the curve order, so that a single
+        // subtraction reduces it.
+        let mut borrow = 0;
+        for (h, n) in hash.iter_mut().zip(CURVE_ORDER.iter()).rev() {
+            let diff = *h as i16 - *n as i16 - borrow;
+            borrow = (diff < 0) as i16;
+            *h = diff.rem_euclid(256) as u8;
+        }
+    }
+    SecretKey::from_slice(&hash).map_err(|_| Error::InvalidArgument)
+}
+
+fn one() -> SecretKey {
+    let mut bytes = [0u8; 32];
+    bytes[31] = 1;
+    SecretKey::from_slice(&bytes).expect("one to be a valid scalar")
+}
+
+fn negate_if(sk: SecretKey, negate: bool) -> SecretKey {
+    if negate {
+        sk.negate()
+    } else {
+        sk
+    }
+}
+
+pub(crate) fn incorrect_signature() -> Error {
+    Error::Secp256k1(secp256k1_zkp::Error::Upstream(
+        UpstreamError::IncorrectSignature,
+    ))
+}
+
+/// The aggregate public key of a set of signers, along with the information
+/// required to produce signatures valid for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyAggContext {
+    pubkeys: Vec<PublicKey>,
+    list_hash: [u8; 32],
+    second_key: Option<PublicKey>,
+    agg_pk: PublicKey,
+    /// Whether the aggregate key was negated when applying tweaks.
+    negated: bool,
+    /// The accumulated tweak, `None` if zero.
+    tweak: Option<SecretKey>,
+}
+
+/// Sorts the given public keys following the KeySort algorithm, so that the
+/// aggregate of the returned keys does not depend on their initial order.
+pub fn key_sort(pubkeys: &[PublicKey]) -> Vec<PublicKey> {
+    let mut pubkeys = pubkeys.to_vec();
+    pubkeys.sort_by_key(|x| x.serialize());
+    pubkeys
+}
+
+impl KeyAggContext {
+    /// Aggregates the given public keys following the KeyAgg algorithm. The
+    /// aggregate key depends on the order of the keys, which can be sorted
+    /// with [`key_sort`] first.
+    pub fn new<C: Verification>(secp: &Secp256k1<C>, pubkeys: &[PublicKey]) -> Result<Self, Error> {
+        if pubkeys.is_empty() {
+            return Err(Error::InvalidArgument);
+        }
+        let pubkeys = pubkeys.to_vec();
+        let serialized = pubkeys.iter().map(|x| x.serialize()).collect::<Vec<_>>();
+        let list_hash = tagged_hash(
+            b"KeyAgg list",
+            &serialized.iter().map(|x| &x[..]).collect::<Vec<_>>(),
+        );
+        let second_key = pubkeys.iter().find(|x| *x != &pubkeys[0]).cloned();
+        let mut ctx = KeyAggContext {
+            agg_pk: pubkeys[0],
+            pubkeys,
+            list_hash,
+            second_key,
+            negated: false,
+            tweak: None,
+        };
+        let tweaked = ctx
+            .pubkeys
+            .iter()
+            .map(|x| Ok(x.mul_tweak(secp, &Scalar::from(ctx.key_agg_coef(x)?))?))
+            .collect::<Result<Vec<_>, Error>>()?;
+        ctx.agg_pk = PublicKey::combine_keys(&tweaked.iter().collect::<Vec<_>>())?;
+        Ok(ctx)
+    }
+
+    /// Returns a copy of the context with the aggregate key tweaked for being
+    /// used as the internal key of a taproot output with the given script tree
+    /// merkle root.
+    pub fn with_taproot_tweak<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        merkle_root: Option<TapNodeHash>,
+    ) -> Result<Self, Error> {
+        let tweak = TapTweakHash::from_key_and_tweak(self.agg_pk(), merkle_root).to_scalar();
+        self.with_xonly_tweak(secp, &tweak)
+    }
+
+    /// Returns a copy of the context with the given x-only tweak applied to the
+    /// aggregate key.
+    pub fn with_xonly_tweak<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        tweak: &Scalar,
+    ) -> Result<Self, Error> {
+        let odd = self.agg_pk.x_only_public_key().1 == Parity::Odd;
+        let agg_pk = negate_if_pk(secp, self.agg_pk, odd).add_exp_tweak(secp, tweak)?;
+        let tweak_key = SecretKey::from_slice(&tweak.to_be_bytes()).ok();
+        let acc = match (tweak_key, self.tweak) {
+            (Some(t), Some(acc)) => negate_if(acc, odd).add_tweak(&Scalar::from(t)).ok(),
+            (Some(t), None) => Some(t),
+            (None, Some(acc)) => Some(negate_if(acc, odd)),
+            (None, None) => None,
+        };
+        Ok(KeyAggContext {
+            agg_pk,
+            negated: self.negated ^ odd,
+            tweak: acc,
+            ..self.clone()
+        })
+    }
+
+    /// Returns the x-only aggregate public key.
+    pub fn agg_pk(&self) -> XOnlyPublicKey {
+        self.agg_pk.x_only_public_key().0
+    }
+
+    /// Returns the public keys of the signers, in aggregation order.
+    pub fn pubkeys(&self) -> &[PublicKey] {
+        &self.pubkeys
+    }
+
+    fn key_agg_coef(&self, pubkey: &PublicKey) -> Result<SecretKey, Error> {
+        if !self.pubkeys.contains(pubkey) {
+            return Err(Error::InvalidArgument);
+        }
+        if Some(pubkey) == self.second_key.as_ref() {
+            return Ok(one());
+        }
+        hash_to_scalar(tagged_hash(
+            b"KeyAgg coefficient",
+            &[&self.list_hash[..], &pubkey.serialize()[..]],
+        ))
+    }
+
+    fn has_odd_y(&self) -> bool {
+        self.agg_pk.x_only_public_key().1 == Parity::Odd
+    }
+}
+
+fn negate_if_pk<C: Verification>(secp: &Secp256k1<C>, pk: PublicKey, negate: bool) -> PublicKey {
+    if negate {
+        pk.negate(secp)
+    } else {
+        pk
+    }
+}
+
+/// The secret nonce of a signer for a single signing session. It is consumed
+/// when producing a partial signature so that it cannot be reused.
+pub struct SecNonce {
+    k1: SecretKey,
+    k2: SecretKey,
+    pubkey: PublicKey,
+}
+
+impl SecNonce {
+    /// Derives the secret nonce of the signer with the given key following the
+    /// NonceGen algorithm, from the given randomness which must never be
+    /// reused. The aggregate key, message and extra input are optional, but
+    /// provide additional protection against nonce reuse when known.
+    pub fn new<C: Signing>(
+        secp: &Secp256k1<C>,
+        seckey: &SecretKey,
+        session_rand: &[u8; 32],
+        agg_pk: Option<&XOnlyPublicKey>,
+        msg: Option<&[u8]>,
+        extra_in: &[u8],
+    ) -> Result<Self, Error> {
+        let pubkey = PublicKey::from_secret_key(secp, seckey);
+        let aux = tagged_hash(b"MuSig/aux", &[&session_rand[..]]);
+        let mut rand = seckey.secret_bytes();
+        for (r, a) in rand.iter_mut().zip(aux.iter()) {
+            *r ^= a;
+        }
+        let pubkey_ser = pubkey.serialize();
+        let agg_pk_ser = agg_pk.map(|x| x.serialize());
+        let agg_pk_ser = agg_pk_ser.as_ref().map_or(&[][..], |x| &x[..]);
+        let msg_prefixed = match msg {
+            Some(msg) => {
+                let mut res = Vec::with_capacity(9 + msg.len());
+                res.push(1);
+                res.extend_from_slice(&(msg.len() as u64).to_be_bytes());
+                res.extend_from_slice(msg);
+                res
+            }
+            None => vec![0],
+        };
+        if extra_in.len() > u32::MAX as usize {
+            return Err(Error::InvalidArgument);
+        }
+        let extra_in_len = extra_in.len() as u32;
+        let derive = |i: u8| {
+            hash_to_scalar(tagged_hash(
+                b"MuSig/nonce",
+                &[
+                    &rand[..],
+                    &[pubkey_ser.len() as u8][..],
+                    &pubkey_ser[..],
+                    &[agg_pk_ser.len() as u8][..],
+                    agg_pk_ser,
+                    &msg_prefixed[..],
+                    &extra_in_len.to_be_bytes()[..],
+                    extra_in,
+                    &[i][..],
+                ],
+            ))
+        };
+        Ok(SecNonce {
+            k1: derive(0)?,
+            k2: derive(1)?,
+            pubkey,
+        })
+    }
+
+    /// Returns the public nonce to share with the other signers.
+    pub fn public_nonce<C: Signing>(&self, secp: &Secp256k1<C>) -> PubNonce {
+        PubNonce {
+            r1: PublicKey::from_secret_key(secp, &self.k1),
+            r2: PublicKey::from_secret_key(secp, &self.k2),
+        }
+    }
+}
+
+/// A public nonce, or the aggregate of the public nonces of all the signers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PubNonce {
+    /// The first nonce point.
+    pub r1: PublicKey,
+    /// The second nonce point.
+    pub r2: PublicKey,
+}
+
+impl PubNonce {
+    /// Aggregates the public nonces of all the signers.
+    pub fn aggregate(nonces: &[PubNonce]) -> Result<Self, Error> {
+        if nonces.is_empty() {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(PubNonce {
+            r1: PublicKey::combine_keys(&nonces.iter().map(|x| &x.r1).collect::<Vec<_>>())?,
+            r2: PublicKey::combine_keys(&nonces.iter().map(|x| &x.r2).collect::<Vec<_>>())?,
+        })
+    }
+
+    /// Serializes the nonce as the concatenation of its compressed points.
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut res = [0u8; 66];
+        res[..33].copy_from_slice(&self.r1.serialize());
+        res[33..].copy_from_slice(&self.r2.serialize());
+        res
+    }
+
+    /// Parses a nonce serialized with [`PubNonce::serialize`].
+    pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != 66 {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(PubNonce {
+            r1: PublicKey::from_slice(&data[..33])?,
+            r2: PublicKey::from_slice(&data[33..])?,
+        })
+    }
+}
+
+/// The partial signature of a signer, to be aggregated with the ones of the
+/// other signers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartialSignature(SecretKey);
+
+impl PartialSignature {
+    /// Serializes the partial signature as a 32 bytes scalar.
+    pub fn serialize(&self) -> [u8; 32] {
+        self.0.secret_bytes()
+    }
+
+    /// Parses a partial signature serialized with
+    /// [`PartialSignature::serialize`].
+    pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+        Ok(PartialSignature(SecretKey::from_slice(data)?))
+    }
+}
+
+/// The state of a signing session of a given message, once the public nonces
+/// of all the signers have been aggregated.
+pub struct SigningSession {
+    /// The nonce coefficient.
+    b: SecretKey,
+    /// The x-only final nonce.
+    r: XOnlyPublicKey,
+    /// Whether the final nonce had to be negated.
+    r_negated: bool,
+    /// The challenge.
+    e: SecretKey,
+}
+
+impl SigningSession {
+    /// Starts a session for signing the given message with the given aggregate
+    /// nonce and key.
+    pub fn new<C: Verification>(
+        secp: &Secp256k1<C>,
+        ctx: &KeyAggContext,
+        agg_nonce: &PubNonce,
+        msg: &Message,
+    ) -> Result<Self, Error> {
+        let agg_pk = ctx.agg_pk().serialize();
+        let b = hash_to_scalar(tagged_hash(
+            b"MuSig/noncecoef",
+            &[&agg_nonce.serialize()[..], &agg_pk[..], &msg[..]],
+        ))?;
+        let r = agg_nonce
+            .r1
+            .combine(&agg_nonce.r2.mul_tweak(secp, &Scalar::from(b))?)?;
+        let (r, parity) = r.x_only_public_key();
+        let e = hash_to_scalar(tagged_hash(
+            b"BIP0340/challenge",
+            &[&r.serialize()[..], &agg_pk[..], &msg[..]],
+        ))?;
+        Ok(SigningSession {
+            b,
+            r,
+            r_negated: parity == Parity::Odd,
+            e,
+        })
+    }
+
+    /// Produces the partial signature of the signer with the given key,
+    /// consuming its secret nonce.
+    pub fn partial_sign<C: Signing>(
+        &self,
+        secp: &Secp256k1<C>,
+        ctx: &KeyAggContext,
+        sec_nonce: SecNonce,
+        seckey: &SecretKey,
+    ) -> Result<PartialSignature, Error> {
+        let pubkey = PublicKey::from_secret_key(secp, seckey);
+        if pubkey != sec_nonce.pubkey {
+            return Err(Error::InvalidArgument);
+        }
+        let a = ctx.key_agg_coef(&pubkey)?;
+        let d = negate_if(*seckey, ctx.has_odd_y() ^ ctx.negated);
+        let k1 = negate_if(sec_nonce.k1, self.r_negated);
+        let k2 = negate_if(sec_nonce.k2, self.r_negated);
+        let bk2 = k2.mul_tweak(&Scalar::from(self.b))?;
+        let ead = d
+            .mul_tweak(&Scalar::from(self.e))?
+            .mul_tweak(&Scalar::from(a))?;
+        Ok(PartialSignature(
+            k1.add_tweak(&Scalar::from(bk2))?
+                .add_tweak(&Scalar::from(ead))?,
+        ))
+    }
+
+    /// Verifies the partial signature of the signer with the given public key
+    /// and public nonce.
+    pub fn verify_partial_sig<C: Signing + Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        ctx: &KeyAggContext,
+        partial_sig: &PartialSignature,
+        pub_nonce: &PubNonce,
+        pubkey: &PublicKey,
+    ) -> Result<(), Error> {
+        let a = ctx.key_agg_coef(pubkey)?;
+        let re = pub_nonce
+            .r1
+            .combine(&pub_nonce.r2.mul_tweak(secp, &Scalar::from(self.b))?)?;
+        let re = negate_if_pk(secp, re, self.r_negated);
+        let ep = negate_if_pk(secp, *pubkey, ctx.has_odd_y() ^ ctx.negated)
+            .mul_tweak(secp, &Scalar::from(self.e))?
+            .mul_tweak(secp, &Scalar::from(a))?;
+        if PublicKey::from_secret_key(secp, &partial_sig.0) != re.combine(&ep)? {
+            return Err(incorrect_signature());
+        }
+        Ok(())
+    }
+
+    /// Aggregates the partial signatures of all the signers into a BIP340
+    /// signature valid for the aggregate key.
+    pub fn aggregate_partial_sigs(
+        &self,
+        ctx: &KeyAggContext,
+        partial_sigs: &[PartialSignature],
+    ) -> Result<SchnorrSignature, Error> {
+        let (first, others) = partial_sigs.split_first().ok_or(Error::InvalidArgument)?;
+        let mut s = first.0;
+        for partial_sig in others {
+            s = s.add_tweak(&Scalar::from(partial_sig.0))?;
+        }
+        if let Some(tweak) = ctx.tweak {
+            let et = negate_if(tweak.mul_tweak(&Scalar::from(self.e))?, ctx.has_odd_y());
+            s = s.add_tweak(&Scalar::from(et))?;
+        }
+        let mut sig = [0u8; 64];
+        sig[..32].copy_from_slice(&self.r.serialize());
+        sig[32..].copy_from_slice(&s.secret_bytes());
+        Ok(SchnorrSignature::from_slice(&sig)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use secp256k1_zkp::rand::{thread_rng, RngCore};
+
+    fn sign(
+        secp: &Secp256k1<secp256k1_zkp::All>,
+        ctx: &KeyAggContext,
+        seckeys: &[SecretKey],
+        msg: &Message,
+    ) -> SchnorrSignature {
+        let sec_nonces = seckeys
+            .iter()
+            .map(|sk| {
+                let mut session_rand = [0u8; 32];
+                thread_rng().fill_bytes(&mut session_rand);
+                SecNonce::new(
+                    secp,
+                    sk,
+                    &session_rand,
+                    Some(&ctx.agg_pk()),
+                    Some(&msg[..]),
+                    &[],
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let pub_nonces = sec_nonces
+            .iter()
+            .map(|x| x.public_nonce(secp))
+            .collect::<Vec<_>>();
+        let agg_nonce = PubNonce::aggregate(&pub_nonces).unwrap();
+        let session = SigningSession::new(secp, ctx, &agg_nonce, msg).unwrap();
+        let partial_sigs = sec_nonces
+            .into_iter()
+            .zip(seckeys.iter())
+            .map(|(nonce, sk)| session.partial_sign(secp, ctx, nonce, sk).unwrap())
+            .collect::<Vec<_>>();
+        for ((partial_sig, pub_nonce), sk) in partial_sigs
+            .iter()
+            .zip(pub_nonces.iter())
+            .zip(seckeys.iter())
+        {
+            session
+                .verify_partial_sig(
+                    secp,
+                    ctx,
+                    partial_sig,
+                    pub_nonce,
+                    &PublicKey::from_secret_key(secp, sk),
+                )
+                .expect("a valid partial signature");
+        }
+        session.aggregate_partial_sigs(ctx, &partial_sigs).unwrap()
+    }
+
+    #[test]
+    fn aggregate_signature_is_valid() {
+        let secp = Secp256k1::new();
+        let msg = Message::from_slice(&[1u8; 32]).unwrap();
+        for _ in 0..8 {
+            let seckeys = [
+                SecretKey::new(&mut thread_rng()),
+                SecretKey::new(&mut thread_rng()),
+            ];
+            let pubkeys = seckeys
+                .iter()
+                .map(|x| PublicKey::from_secret_key(&secp, x))
+                .collect::<Vec<_>>();
+            let ctx = KeyAggContext::new(&secp, &pubkeys).unwrap();
+            let sig = sign(&secp, &ctx, &seckeys, &msg);
+            secp.verify_schnorr(&sig, &msg, &ctx.agg_pk())
+                .expect("a valid aggregate signature");
+
+            let tweaked = ctx
+                .with_taproot_tweak(&secp, Some(TapNodeHash::from_byte_array([2u8; 32])))
+                .unwrap();
+            let sig = sign(&secp, &tweaked, &seckeys, &msg);
+            secp.verify_schnorr(&sig, &msg, &tweaked.agg_pk())
+                .expect("a valid aggregate signature for the tweaked key");
+        }
+    }
+
+    #[test]
+    fn aggregate_of_sorted_keys_does_not_depend_on_key_order() {
+        let secp = Secp256k1::new();
+        let pk1 = PublicKey::from_secret_key(&secp, &SecretKey::new(&mut thread_rng()));
+        let pk2 = PublicKey::from_secret_key(&secp, &SecretKey::new(&mut thread_rng()));
+
+        assert_eq!(
+            KeyAggContext::new(&secp, &key_sort(&[pk1, pk2]))
+                .unwrap()
+                .agg_pk(),
+            KeyAggContext::new(&secp, &key_sort(&[pk2, pk1]))
+                .unwrap()
+                .agg_pk()
+        );
+    }
+
+    #[test]
+    fn invalid_partial_signature_is_rejected() {
+        let secp = Secp256k1::new();
+        let msg = Message::from_slice(&[1u8; 32]).unwrap();
+        let seckeys = [
+            SecretKey::new(&mut thread_rng()),
+            SecretKey::new(&mut thread_rng()),
+        ];
+        let pubkeys = seckeys
+            .iter()
+            .map(|x| PublicKey::from_secret_key(&secp, x))
+            .collect::<Vec<_>>();
+        let ctx = KeyAggContext::new(&secp, &pubkeys).unwrap();
+        let sec_nonces = seckeys
+            .iter()
+            .map(|sk| SecNonce::new(&secp, sk, &[3u8; 32], None, None, &[]).unwrap())
+            .collect::<Vec<_>>();
+        let pub_nonces = sec_nonces
+            .iter()
+            .map(|x| x.public_nonce(&secp))
+            .collect::<Vec<_>>();
+        let agg_nonce = PubNonce::aggregate(&pub_nonces).unwrap();
+        let session = SigningSession::new(&secp, &ctx, &agg_nonce, &msg).unwrap();
+        let mut sec_nonces = sec_nonces.into_iter();
+        let partial_sig = session
+            .partial_sign(&secp, &ctx, sec_nonces.next().unwrap(), &seckeys[0])
+            .unwrap();
+
+        session
+            .verify_partial_sig(&secp, &ctx, &partial_sig, &pub_nonces[1], &pubkeys[1])
+            .expect_err("the partial signature of another signer");
+    }
+
+    // Test vectors from BIP327.
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn pubkey(hex: &str) -> PublicKey {
+        PublicKey::from_slice(&from_hex(hex)).unwrap()
+    }
+
+    fn pub_nonce(hex: &str) -> PubNonce {
+        PubNonce::from_slice(&from_hex(hex)).unwrap()
+    }
+
+    #[test]
+    fn key_agg_vectors() {
+        let secp = Secp256k1::new();
+        let pubkeys = [
+            pubkey("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            pubkey("03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659"),
+            pubkey("023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66"),
+        ];
+        let cases: [(&[usize], &str); 4] = [
+            (
+                &[0, 1, 2],
+                "90539EEDE565F5D054F32CC0C220126889ED1E5D193BAF15AEF344FE59D4610C",
+            ),
+            (
+                &[2, 1, 0],
+                "6204DE8B083426DC6EAF9502D27024D53FC826BF7D2012148A0575435DF54B2B",
+            ),
+            (
+                &[0, 0, 0],
+                "B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935",
+            ),
+            (
+                &[0, 0, 1, 1],
+                "69BC22BFA5D106306E48A20679DE1D7389386124D07571D0D872686028C26A3E",
+            ),
+        ];
+
+        for (indices, expected) in cases.iter() {
+            let keys = indices.iter().map(|i| pubkeys[*i]).collect::<Vec<_>>();
+            let ctx = KeyAggContext::new(&secp, &keys).unwrap();
+            assert_eq!(from_hex(expected), ctx.agg_pk().serialize().to_vec());
+        }
+    }
+
+    struct SignVectors {
+        seckey: SecretKey,
+        sec_nonce: [u8; 64],
+        pub_nonces: [PubNonce; 3],
+        agg_nonce: PubNonce,
+        msg: Message,
+    }
+
+    impl SignVectors {
+        fn new() -> Self {
+            let mut sec_nonce = [0u8; 64];
+            sec_nonce.copy_from_slice(&from_hex(
+                "508B81A611F100A6B2B6B29656590898AF488BCF2E1F55CF22E5CFB84421FE61\
+                 FA27FD49B1D50085B481285E1CA205D55C82CC1B31FF5CD54A489829355901F7",
+            ));
+            SignVectors {
+                seckey: SecretKey::from_slice(&from_hex(
+                    "7FB9E0E687ADA1EEBF7ECFE2F21E73EBDB51A7D450948DFE8D76D7F2D1007671",
+                ))
+                .unwrap(),
+                sec_nonce,
+                pub_nonces: [
+                    pub_nonce("0337C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA0287BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480"),
+                    pub_nonce("0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F817980279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798"),
+                    pub_nonce("032DE2662628C90B03F5E720284EB52FF7D71F4284F627B68A853D78C78E1FFE9303E4C5524E83FFE1493B9077CF1CA6BEB2090C93D930321071AD40B2F44E599046"),
+                ],
+                agg_nonce: pub_nonce("028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9"),
+                msg: Message::from_slice(&from_hex(
+                    "F95466D086770E689964664219266FE5ED215C92AE20BAB5C9D79ADDDDF3C0CF",
+                ))
+                .unwrap(),
+            }
+        }
+
+        fn sec_nonce(&self, secp: &Secp256k1<secp256k1_zkp::All>) -> SecNonce {
+            SecNonce {
+                k1: SecretKey::from_slice(&self.sec_nonce[..32]).unwrap(),
+                k2: SecretKey::from_slice(&self.sec_nonce[32..]).unwrap(),
+                pubkey: PublicKey::from_secret_key(secp, &self.seckey),
+            }
+        }
+
+        /// Checks that the partial signature of the signer of the vectors for
+        /// the given key aggregation context matches the expected one, and
+        /// that it is valid.
+        fn check(&self, secp: &Secp256k1<secp256k1_zkp::All>, ctx: &KeyAggContext, expected: &str) {
+            let session = SigningSession::new(secp, ctx, &self.agg_nonce, &self.msg).unwrap();
+            let partial_sig = session
+                .partial_sign(secp, ctx, self.sec_nonce(secp), &self.seckey)
+                .unwrap();
+
+            assert_eq!(from_hex(expected), partial_sig.serialize().to_vec());
+            session
+                .verify_partial_sig(
+                    secp,
+                    ctx,
+                    &partial_sig,
+                    &self.pub_nonces[0],
+                    &PublicKey::from_secret_key(secp, &self.seckey),
+                )
+                .expect("a valid partial signature");
+        }
+    }
+
+    #[test]
+    fn sign_verify_vectors() {
+        let secp = Secp256k1::new();
+        let vectors = SignVectors::new();
+        let pubkeys = [
+            PublicKey::from_secret_key(&secp, &vectors.seckey),
+            pubkey("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            pubkey("02DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA661"),
+        ];
+        assert_eq!(
+            vectors.agg_nonce,
+            PubNonce::aggregate(&vectors.pub_nonces).unwrap()
+        );
+        assert_eq!(
+            vectors.pub_nonces[0],
+            vectors.sec_nonce(&secp).public_nonce(&secp)
+        );
+        let cases: [(&[usize], &str); 3] = [
+            (
+                &[0, 1, 2],
+                "012ABBCB52B3016AC03AD82395A1A415C48B93DEF78718E62A7A90052FE224FB",
+            ),
+            (
+                &[1, 0, 2],
+                "9FF2F7AAA856150CC8819254218D3ADEEB0535269051897724F9DB3789513A52",
+            ),
+            (
+                &[1, 2, 0],
+                "FA23C359F6FAC4E7796BB93BC9F0532A95468C539BA20FF86D7C76ED92227900",
+            ),
+        ];
+
+        for (indices, expected) in cases.iter() {
+            let keys = indices.iter().map(|i| pubkeys[*i]).collect::<Vec<_>>();
+            let ctx = KeyAggContext::new(&secp, &keys).unwrap();
+            vectors.check(&secp, &ctx, expected);
+        }
+    }
+
+    #[test]
+    fn tweak_vectors() {
+        let secp = Secp256k1::new();
+        let vectors = SignVectors::new();
+        let keys = [
+            pubkey("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            pubkey("02DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659"),
+            PublicKey::from_secret_key(&secp, &vectors.seckey),
+        ];
+        let mut tweak = [0u8; 32];
+        tweak.copy_from_slice(&from_hex(
+            "E8F791FF9225A2AF0102AFFF4A9A723D9612A682A25EBE79802B263CDFCD83BB",
+        ));
+        let ctx = KeyAggContext::new(&secp, &keys)
+            .unwrap()
+            .with_xonly_tweak(&secp, &Scalar::from_be_bytes(tweak).unwrap())
+            .unwrap();
+
+        vectors.check(
+            &secp,
+            &ctx,
+            "E28A5C66E61E178C2BA19DB77B6CF9F7E2F0F56C17918CD13135E60CC848FE91",
+        );
+    }
+}
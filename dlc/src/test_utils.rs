@@ -0,0 +1,69 @@
+use crate::{PartyParams, Payout, TxInputInfo};
+use bitcoin::blockdata::script::ScriptBuf;
+use bitcoin::blockdata::transaction::OutPoint;
+use bitcoin::{network::constants::Network, Address, Txid};
+use secp256k1_zkp::{rand::Rng, PublicKey, Secp256k1, SecretKey, Signing};
+use std::str::FromStr;
+
+fn get_p2wpkh_script_pubkey<C: Signing, R: Rng + ?Sized>(
+    secp: &Secp256k1<C>,
+    rng: &mut R,
+) -> ScriptBuf {
+    let sk = bitcoin::PrivateKey {
+        inner: SecretKey::new(rng),
+        network: Network::Testnet,
+        compressed: true,
+    };
+    let pk = bitcoin::PublicKey::from_private_key(secp, &sk);
+    Address::p2wpkh(&pk, Network::Testnet)
+        .unwrap()
+        .script_pubkey()
+}
+
+pub(crate) fn get_party_params(
+    input_amount: u64,
+    collateral: u64,
+    serial_id: Option<u64>,
+) -> (PartyParams, SecretKey) {
+    let secp = Secp256k1::new();
+    let mut rng = secp256k1_zkp::rand::thread_rng();
+    let fund_privkey = SecretKey::new(&mut rng);
+    let serial_id = serial_id.unwrap_or(1);
+    (
+        PartyParams {
+            fund_pubkey: PublicKey::from_secret_key(&secp, &fund_privkey),
+            change_script_pubkey: get_p2wpkh_script_pubkey(&secp, &mut rng),
+            change_serial_id: serial_id,
+            payout_script_pubkey: get_p2wpkh_script_pubkey(&secp, &mut rng),
+            payout_serial_id: serial_id,
+            input_amount,
+            collateral,
+            inputs: vec![TxInputInfo {
+                max_witness_len: 108,
+                redeem_script: ScriptBuf::new(),
+                outpoint: OutPoint {
+                    txid: Txid::from_str(
+                        "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456",
+                    )
+                    .unwrap(),
+                    vout: serial_id as u32,
+                },
+                serial_id,
+            }],
+        },
+        fund_privkey,
+    )
+}
+
+pub(crate) fn payouts() -> Vec<Payout> {
+    vec![
+        Payout {
+            offer: 200000000,
+            accept: 0,
+        },
+        Payout {
+            offer: 0,
+            accept: 200000000,
+        },
+    ]
+}
@@ -1,13 +1,16 @@
 //! Utility functions not uniquely related to DLC
 
 use bitcoin::address::{WitnessProgram, WitnessVersion};
+use bitcoin::key::TapTweak;
 use bitcoin::script::PushBytesBuf;
-use bitcoin::sighash::SighashCache;
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
 use bitcoin::{
     address::Payload, hash_types::PubkeyHash, sighash::EcdsaSighashType, Script, Transaction, TxOut,
 };
 use bitcoin::{ScriptBuf, Sequence, Witness};
-use secp256k1_zkp::{ecdsa::Signature, Message, PublicKey, Secp256k1, SecretKey, Signing};
+use secp256k1_zkp::{
+    ecdsa::Signature, KeyPair, Message, PublicKey, Secp256k1, SecretKey, Signing, Verification,
+};
 
 use crate::Error;
 
@@ -95,6 +98,19 @@ pub fn get_sig_for_p2wpkh_input<C: Signing>(
     )
 }
 
+/// Returns the maximum length of the witness required to spend an output with
+/// the given script pubkey using a single key, assuming a low R signature for
+/// P2WPKH outputs and a key path spend for P2TR ones.
+pub fn get_single_key_max_witness_len(script_pubkey: &Script) -> usize {
+    if script_pubkey.is_v1_p2tr() {
+        // Length prefix + 64 bytes schnorr signature with default sighash.
+        65
+    } else {
+        // Length prefixes + 72 bytes signature + 33 bytes public key.
+        107
+    }
+}
+
 /// Returns the fee for the given weight at given fee rate.
 pub fn weight_to_fee(weight: usize, fee_rate: u64) -> Result<u64, Error> {
     (f64::ceil((weight as f64) / 4.0) as u64)
@@ -152,6 +168,37 @@ pub fn get_witness_for_p2wpkh_input<C: Signing>(
     ]))
 }
 
+/// Create a BIP341 key path spend signature for a p2tr transaction input whose
+/// output key was derived from the provided secret key without a script tree,
+/// and places it on the witness stack. `prevouts` must contain the outputs
+/// spent by all the inputs of the transaction, in order.
+pub fn sign_p2tr_key_spend_input<C: Signing + Verification>(
+    secp: &Secp256k1<C>,
+    sk: &SecretKey,
+    tx: &mut Transaction,
+    input_index: usize,
+    prevouts: &[TxOut],
+) -> Result<(), Error> {
+    if prevouts.len() != tx.input.len() {
+        return Err(Error::InvalidArgument);
+    }
+    let sig_hash = SighashCache::new(&*tx).taproot_key_spend_signature_hash(
+        input_index,
+        &Prevouts::All(prevouts),
+        TapSighashType::Default,
+    )?;
+    let msg = Message::from_slice(sig_hash.as_ref()).unwrap();
+    let tweaked = KeyPair::from_secret_key(secp, sk)
+        .tap_tweak(secp, None)
+        .to_inner();
+    let sig = bitcoin::taproot::Signature {
+        sig: secp.sign_schnorr_no_aux_rand(&msg, &tweaked),
+        hash_ty: TapSighashType::Default,
+    };
+    tx.input[input_index].witness = Witness::from_slice(&[sig.to_vec()]);
+    Ok(())
+}
+
 /// Generates a signature for a given p2wsh transaction input using the given secret
 /// key and info, and places the generated and provided signatures on the input's
 /// witness stack, ordering the signatures based on the ordering of the associated
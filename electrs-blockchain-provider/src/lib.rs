@@ -166,6 +166,24 @@ impl Blockchain for ElectrsBlockchainProvider {
 
         Ok(0)
     }
+
+    fn get_output_spender(
+        &self,
+        outpoint: &OutPoint,
+    ) -> Result<Option<Txid>, dlc_manager::error::Error> {
+        let spent_resp: SpentResp =
+            self.get_from_json(&format!("tx/{}/outspend/{}", outpoint.txid, outpoint.vout))?;
+        match spent_resp.txid {
+            Some(txid) if spent_resp.spent => {
+                txid.parse()
+                    .map(Some)
+                    .map_err(|e: <bitcoin::Txid as FromStr>::Err| {
+                        Error::BlockchainError(e.to_string())
+                    })
+            }
+            _ => Ok(None),
+        }
+    }
 }
 
 impl simple_wallet::WalletBlockchainProvider for ElectrsBlockchainProvider {
@@ -375,6 +393,7 @@ pub enum UtxoStatus {
 #[derive(Serialize, Deserialize, Debug)]
 struct SpentResp {
     spent: bool,
+    txid: Option<String>,
 }
 
 type FeeEstimates = std::collections::HashMap<u16, f32>;
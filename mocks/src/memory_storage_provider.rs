@@ -2,24 +2,25 @@ use bitcoin::{Address, OutPoint, Txid};
 use dlc_manager::chain_monitor::ChainMonitor;
 use dlc_manager::channel::{
     offered_channel::OfferedChannel,
+    punishment::PunishmentData,
     signed_channel::{SignedChannel, SignedChannelStateType},
-    Channel,
+    watchtower::{JusticeBlob, LOCATOR_LEN},
+    Channel, ChannelEvent,
 };
 use dlc_manager::contract::{
-    offered_contract::OfferedContract, signed_contract::SignedContract, Contract, PreClosedContract,
+    offered_contract::OfferedContract, signed_contract::SignedContract, Contract, ContractEvent,
+    PreClosedContract,
 };
-use dlc_manager::Storage;
+use dlc_manager::product_catalog::ProductDefinition;
 use dlc_manager::{error::Error as DaemonError, ChannelId, ContractId, Utxo};
-use secp256k1_zkp::SecretKey;
+use dlc_manager::{Storage, StorageBatch};
+use secp256k1_zkp::{PublicKey, SecretKey};
 use simple_wallet::WalletStorage;
 use std::collections::HashMap;
-use std::sync::{Mutex, RwLock};
+use std::sync::RwLock;
 
 pub struct MemoryStorage {
-    contracts: RwLock<HashMap<ContractId, Contract>>,
-    channels: RwLock<HashMap<ChannelId, Channel>>,
-    contracts_saved: Mutex<Option<HashMap<ContractId, Contract>>>,
-    channels_saved: Mutex<Option<HashMap<ChannelId, Channel>>>,
+    storage: dlc_manager::memory_storage::MemoryStorage,
     addresses: RwLock<HashMap<Address, SecretKey>>,
     utxos: RwLock<HashMap<OutPoint, Utxo>>,
     key_pairs: RwLock<HashMap<Vec<u8>, SecretKey>>,
@@ -28,10 +29,7 @@ pub struct MemoryStorage {
 impl MemoryStorage {
     pub fn new() -> Self {
         MemoryStorage {
-            contracts: RwLock::new(HashMap::new()),
-            channels: RwLock::new(HashMap::new()),
-            contracts_saved: Mutex::new(None),
-            channels_saved: Mutex::new(None),
+            storage: dlc_manager::memory_storage::MemoryStorage::new(),
             addresses: RwLock::new(HashMap::new()),
             utxos: RwLock::new(HashMap::new()),
             key_pairs: RwLock::new(HashMap::new()),
@@ -39,35 +37,11 @@ impl MemoryStorage {
     }
 
     pub fn save(&self) {
-        let mut contracts_saved = self.contracts_saved.lock().unwrap();
-
-        *contracts_saved = Some(
-            self.contracts
-                .read()
-                .expect("Could not get read lock")
-                .clone(),
-        );
-        let mut channels_saved = self.channels_saved.lock().unwrap();
-        *channels_saved = Some(
-            self.channels
-                .read()
-                .expect("Could not get read lock")
-                .clone(),
-        );
+        self.storage.save();
     }
 
     pub fn rollback(&self) {
-        let mut contracts = self.contracts.write().unwrap();
-        let mut contracts_saved = self.contracts_saved.lock().unwrap();
-        let mut tmp = None;
-        std::mem::swap(&mut tmp, &mut *contracts_saved);
-        std::mem::swap(&mut *contracts, &mut tmp.unwrap());
-
-        let mut channels = self.channels.write().unwrap();
-        let mut channels_saved = self.channels_saved.lock().unwrap();
-        let mut tmp = None;
-        std::mem::swap(&mut tmp, &mut *channels_saved);
-        std::mem::swap(&mut *channels, &mut tmp.unwrap());
+        self.storage.rollback();
     }
 }
 
@@ -79,171 +53,84 @@ impl Default for MemoryStorage {
 
 impl Storage for MemoryStorage {
     fn get_contract(&self, id: &ContractId) -> Result<Option<Contract>, DaemonError> {
-        let map = self.contracts.read().expect("Could not get read lock");
-        Ok(map.get(id).cloned())
+        self.storage.get_contract(id)
     }
 
     fn get_contracts(&self) -> Result<Vec<Contract>, DaemonError> {
-        Ok(self
-            .contracts
-            .read()
-            .expect("Could not get read lock")
-            .values()
-            .cloned()
-            .collect())
+        self.storage.get_contracts()
     }
 
     fn create_contract(&self, contract: &OfferedContract) -> Result<(), DaemonError> {
-        let mut map = self.contracts.write().expect("Could not get write lock");
-        let res = map.insert(contract.id, Contract::Offered(contract.clone()));
-        match res {
-            None => Ok(()),
-            Some(_) => Err(DaemonError::StorageError(
-                "Contract already exists".to_string(),
-            )),
-        }
+        self.storage.create_contract(contract)
+    }
+
+    fn replace_contract(&self, contract: &OfferedContract) -> Result<(), DaemonError> {
+        self.storage.replace_contract(contract)
     }
 
     fn delete_contract(&self, id: &ContractId) -> Result<(), DaemonError> {
-        let mut map = self.contracts.write().expect("Could not get write lock");
-        map.remove(id);
-        Ok(())
+        self.storage.delete_contract(id)
     }
 
     fn update_contract(&self, contract: &Contract) -> Result<(), DaemonError> {
-        let mut map = self.contracts.write().expect("Could not get write lock");
-        match contract {
-            a @ Contract::Accepted(_) | a @ Contract::Signed(_) => {
-                map.remove(&a.get_temporary_id());
-            }
-            _ => {}
-        };
-        map.insert(contract.get_id(), contract.clone());
-        Ok(())
+        self.storage.update_contract(contract)
     }
 
     fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, DaemonError> {
-        let map = self.contracts.read().expect("Could not get read lock");
-
-        let mut res: Vec<SignedContract> = Vec::new();
-
-        for (_, val) in map.iter() {
-            if let Contract::Signed(c) = val {
-                res.push(c.clone());
-            }
-        }
-
-        Ok(res)
+        self.storage.get_signed_contracts()
     }
 
     fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, DaemonError> {
-        let map = self.contracts.read().expect("Could not get read lock");
-
-        let mut res: Vec<SignedContract> = Vec::new();
-
-        for (_, val) in map.iter() {
-            if let Contract::Confirmed(c) = val {
-                res.push(c.clone());
-            }
-        }
-
-        Ok(res)
+        self.storage.get_confirmed_contracts()
     }
 
     fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, DaemonError> {
-        let map = self.contracts.read().expect("Could not get read lock");
-
-        let mut res: Vec<OfferedContract> = Vec::new();
-
-        for (_, val) in map.iter() {
-            if let Contract::Offered(c) = val {
-                res.push(c.clone());
-            }
-        }
-
-        Ok(res)
+        self.storage.get_contract_offers()
     }
 
     fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, DaemonError> {
-        let map = self.contracts.read().expect("Could not get read lock");
-
-        let mut res: Vec<PreClosedContract> = Vec::new();
-
-        for (_, val) in map.iter() {
-            if let Contract::PreClosed(c) = val {
-                res.push(c.clone());
-            }
-        }
-        Ok(res)
+        self.storage.get_preclosed_contracts()
     }
+
     fn upsert_channel(
         &self,
         channel: Channel,
         contract: Option<Contract>,
     ) -> Result<(), DaemonError> {
-        {
-            let mut map = self.channels.write().expect("Could not get write lock");
-            match &channel {
-                a @ Channel::Accepted(_) | a @ Channel::Signed(_) => {
-                    map.remove(&a.get_temporary_id());
-                }
-                _ => {}
-            };
-            map.insert(channel.get_id(), channel);
-        }
-        if let Some(c) = contract {
-            self.update_contract(&c)?;
-        }
-        Ok(())
+        self.storage.upsert_channel(channel, contract)
     }
 
     fn delete_channel(&self, channel_id: &ChannelId) -> Result<(), DaemonError> {
-        let mut map = self.channels.write().expect("Could not get write lock");
-        map.remove(channel_id);
-        Ok(())
+        self.storage.delete_channel(channel_id)
     }
 
     fn get_channel(&self, channel_id: &ChannelId) -> Result<Option<Channel>, DaemonError> {
-        let map = self.channels.read().expect("could not get read lock");
-        Ok(map.get(channel_id).cloned())
+        self.storage.get_channel(channel_id)
+    }
+
+    fn get_channels_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Channel>, DaemonError> {
+        self.storage.get_channels_by_counterparty(counter_party)
+    }
+
+    fn get_channel_by_contract_id(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<Channel>, DaemonError> {
+        self.storage.get_channel_by_contract_id(contract_id)
     }
 
     fn get_signed_channels(
         &self,
         channel_state: Option<SignedChannelStateType>,
     ) -> Result<Vec<SignedChannel>, DaemonError> {
-        let map = self.channels.read().expect("Could not get read lock");
-
-        let mut res: Vec<SignedChannel> = Vec::new();
-
-        for (_, val) in map.iter() {
-            if let Channel::Signed(c) = val {
-                match channel_state {
-                    Some(ref state) => {
-                        if c.state.is_of_type(state) {
-                            res.push(c.clone())
-                        }
-                    }
-                    None => res.push(c.clone()),
-                };
-            }
-        }
-
-        Ok(res)
+        self.storage.get_signed_channels(channel_state)
     }
 
     fn get_offered_channels(&self) -> Result<Vec<OfferedChannel>, DaemonError> {
-        let map = self.channels.read().expect("Could not get read lock");
-
-        let mut res: Vec<OfferedChannel> = Vec::new();
-
-        for (_, val) in map.iter() {
-            if let Channel::Offered(c) = val {
-                res.push(c.clone())
-            }
-        }
-
-        Ok(res)
+        self.storage.get_offered_channels()
     }
 
     fn persist_chain_monitor(&self, _: &ChainMonitor) -> Result<(), DaemonError> {
@@ -254,6 +141,107 @@ impl Storage for MemoryStorage {
     fn get_chain_monitor(&self) -> Result<Option<ChainMonitor>, DaemonError> {
         Ok(None)
     }
+
+    fn update_peer_last_seen(
+        &self,
+        peer_id: &PublicKey,
+        timestamp: u64,
+    ) -> Result<(), DaemonError> {
+        self.storage.update_peer_last_seen(peer_id, timestamp)
+    }
+
+    fn get_peer_last_seen(&self, peer_id: &PublicKey) -> Result<Option<u64>, DaemonError> {
+        self.storage.get_peer_last_seen(peer_id)
+    }
+
+    fn upsert_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+        temporary_id: &ContractId,
+    ) -> Result<(), DaemonError> {
+        self.storage.upsert_offer_hash(offer_hash, temporary_id)
+    }
+
+    fn get_contract_id_by_offer_hash(
+        &self,
+        offer_hash: &[u8; 32],
+    ) -> Result<Option<ContractId>, DaemonError> {
+        self.storage.get_contract_id_by_offer_hash(offer_hash)
+    }
+
+    fn upsert_punishment_data(&self, data: &PunishmentData) -> Result<(), DaemonError> {
+        self.storage.upsert_punishment_data(data)
+    }
+
+    fn get_punishment_data(
+        &self,
+        channel_id: &ChannelId,
+        update_idx: u64,
+    ) -> Result<Option<PunishmentData>, DaemonError> {
+        self.storage.get_punishment_data(channel_id, update_idx)
+    }
+
+    fn get_latest_punishment_data(
+        &self,
+        channel_id: &ChannelId,
+        count: usize,
+    ) -> Result<Vec<PunishmentData>, DaemonError> {
+        self.storage.get_latest_punishment_data(channel_id, count)
+    }
+
+    fn upsert_justice_blob(&self, blob: &JusticeBlob) -> Result<(), DaemonError> {
+        self.storage.upsert_justice_blob(blob)
+    }
+
+    fn get_justice_blob(
+        &self,
+        locator: &[u8; LOCATOR_LEN],
+    ) -> Result<Option<JusticeBlob>, DaemonError> {
+        self.storage.get_justice_blob(locator)
+    }
+
+    fn upsert_product_definition(
+        &self,
+        event_id: &str,
+        product: &ProductDefinition,
+    ) -> Result<(), DaemonError> {
+        self.storage.upsert_product_definition(event_id, product)
+    }
+
+    fn get_product_definition(
+        &self,
+        event_id: &str,
+    ) -> Result<Option<ProductDefinition>, DaemonError> {
+        self.storage.get_product_definition(event_id)
+    }
+
+    fn append_contract_event(
+        &self,
+        temporary_id: &ContractId,
+        event: &ContractEvent,
+    ) -> Result<(), DaemonError> {
+        self.storage.append_contract_event(temporary_id, event)
+    }
+
+    fn get_contract_history(&self, id: &ContractId) -> Result<Vec<ContractEvent>, DaemonError> {
+        self.storage.get_contract_history(id)
+    }
+
+    fn append_channel_event(
+        &self,
+        temporary_id: &ChannelId,
+        event: &ChannelEvent,
+    ) -> Result<(), DaemonError> {
+        self.storage.append_channel_event(temporary_id, event)
+    }
+
+    fn get_channel_history(&self, id: &ChannelId) -> Result<Vec<ChannelEvent>, DaemonError> {
+        self.storage.get_channel_history(id)
+    }
+
+    fn write_batch(&self, batch: StorageBatch) -> Result<(), DaemonError> {
+        self.storage.write_batch(batch)
+    }
 }
 
 impl WalletStorage for MemoryStorage {
@@ -1,20 +1,40 @@
 use std::sync::Mutex;
 
-use bitcoin::{Block, Transaction, Txid};
+use bitcoin::{Block, OutPoint, Transaction, Txid};
 use dlc_manager::{error::Error, Blockchain, Utxo};
 use lightning::chain::chaininterface::FeeEstimator;
 use simple_wallet::WalletBlockchainProvider;
 
 pub struct MockBlockchain {
     transactions: Mutex<Vec<Transaction>>,
+    est_fee_rate_per_kw: Mutex<u32>,
+    confirmations: Mutex<u32>,
 }
 
 impl MockBlockchain {
     pub fn new() -> Self {
         Self {
             transactions: Mutex::new(Vec::new()),
+            est_fee_rate_per_kw: Mutex::new(253),
+            confirmations: Mutex::new(6),
         }
     }
+
+    pub fn set_est_fee_rate(&self, fee_rate_per_kw: u32) {
+        *self.est_fee_rate_per_kw.lock().unwrap() = fee_rate_per_kw;
+    }
+
+    pub fn set_confirmations(&self, confirmations: u32) {
+        *self.confirmations.lock().unwrap() = confirmations;
+    }
+
+    pub fn was_sent(&self, tx_id: &Txid) -> bool {
+        self.transactions
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|x| &x.txid() == tx_id)
+    }
 }
 
 impl Default for MockBlockchain {
@@ -48,7 +68,16 @@ impl Blockchain for MockBlockchain {
             .clone())
     }
     fn get_transaction_confirmations(&self, _tx_id: &Txid) -> Result<u32, Error> {
-        Ok(6)
+        Ok(*self.confirmations.lock().unwrap())
+    }
+    fn get_output_spender(&self, outpoint: &OutPoint) -> Result<Option<Txid>, Error> {
+        Ok(self
+            .transactions
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|x| x.input.iter().any(|i| &i.previous_output == outpoint))
+            .map(|x| x.txid()))
     }
 }
 
@@ -67,6 +96,6 @@ impl FeeEstimator for MockBlockchain {
         &self,
         _confirmation_target: lightning::chain::chaininterface::ConfirmationTarget,
     ) -> u32 {
-        unimplemented!()
+        *self.est_fee_rate_per_kw.lock().unwrap()
     }
 }
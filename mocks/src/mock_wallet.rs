@@ -1,4 +1,5 @@
-use std::rc::Rc;
+use std::collections::HashSet;
+use std::sync::Mutex;
 
 use bitcoin::psbt::PartiallySignedTransaction;
 use bitcoin::{absolute::LockTime, Address, OutPoint, ScriptBuf, Transaction, TxOut};
@@ -9,10 +10,11 @@ use crate::mock_blockchain::MockBlockchain;
 
 pub struct MockWallet {
     utxos: Vec<Utxo>,
+    reserved: Mutex<HashSet<OutPoint>>,
 }
 
 impl MockWallet {
-    pub fn new(blockchain: &Rc<MockBlockchain>, utxo_values: &[u64]) -> Self {
+    pub fn new(blockchain: &MockBlockchain, utxo_values: &[u64]) -> Self {
         let mut utxos = Vec::with_capacity(utxo_values.len());
 
         for utxo_value in utxo_values {
@@ -41,7 +43,15 @@ impl MockWallet {
             utxos.push(utxo);
         }
 
-        Self { utxos }
+        Self {
+            utxos,
+            reserved: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Returns the outpoints of the UTXOs that are currently reserved.
+    pub fn get_reserved_utxos(&self) -> HashSet<OutPoint> {
+        self.reserved.lock().unwrap().clone()
     }
 }
 
@@ -78,7 +88,7 @@ impl Wallet for MockWallet {
         &self,
         amount: u64,
         _fee_rate: u64,
-        _lock_utxos: bool,
+        lock_utxos: bool,
     ) -> Result<Vec<dlc_manager::Utxo>, Error> {
         let mut utxo_pool = self.utxos.clone();
         let seed = 1;
@@ -88,7 +98,7 @@ impl Wallet for MockWallet {
 
         let mut sum = 0;
 
-        let res = utxo_pool
+        let res: Vec<Utxo> = utxo_pool
             .iter()
             .take_while(|x| {
                 if sum >= amount {
@@ -101,12 +111,26 @@ impl Wallet for MockWallet {
             .collect();
 
         if sum >= amount {
+            if lock_utxos {
+                let outpoints = res.iter().map(|x| x.outpoint).collect::<Vec<_>>();
+                self.reserve_utxos(&outpoints)?;
+            }
             return Ok(res);
         }
 
         Err(Error::InvalidParameters("Not enought UTXOs".to_string()))
     }
 
+    fn list_utxos(&self) -> Result<Vec<Utxo>, Error> {
+        let reserved = self.reserved.lock().unwrap();
+        Ok(self
+            .utxos
+            .iter()
+            .filter(|x| !reserved.contains(&x.outpoint))
+            .cloned()
+            .collect())
+    }
+
     fn import_address(&self, _address: &Address) -> Result<(), dlc_manager::error::Error> {
         Ok(())
     }
@@ -115,7 +139,19 @@ impl Wallet for MockWallet {
         Ok(())
     }
 
-    fn unreserve_utxos(&self, _outpoints: &[OutPoint]) -> Result<(), Error> {
+    fn reserve_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error> {
+        self.reserved
+            .lock()
+            .unwrap()
+            .extend(outpoints.iter().cloned());
+        Ok(())
+    }
+
+    fn release_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error> {
+        let mut reserved = self.reserved.lock().unwrap();
+        for outpoint in outpoints {
+            reserved.remove(outpoint);
+        }
         Ok(())
     }
 }
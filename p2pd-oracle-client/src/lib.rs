@@ -24,10 +24,13 @@ use dlc_manager::Oracle;
 use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
 use secp256k1_zkp::{schnorr::Signature, XOnlyPublicKey};
 
-/// Enables interacting with a DLC oracle.
+/// Enables interacting with a DLC oracle. Requests share a single HTTP
+/// client so that connections to the oracle are reused, which matters when
+/// fetching many attestations at once.
 pub struct P2PDOracleClient {
     host: String,
     public_key: XOnlyPublicKey,
+    client: reqwest::blocking::Client,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -69,11 +72,13 @@ struct AttestationResponse {
     values: Vec<String>,
 }
 
-fn get<T>(path: &str) -> Result<T, DlcManagerError>
+fn get<T>(client: &reqwest::blocking::Client, path: &str) -> Result<T, DlcManagerError>
 where
     T: serde::de::DeserializeOwned,
 {
-    reqwest::blocking::get(path)
+    client
+        .get(path)
+        .send()
         .map_err(|x| {
             dlc_manager::error::Error::IOError(std::io::Error::new(std::io::ErrorKind::Other, x))
         })?
@@ -118,9 +123,14 @@ impl P2PDOracleClient {
         } else {
             host.to_string()
         };
+        let client = reqwest::blocking::Client::new();
         let path = pubkey_path(&host);
-        let public_key = get::<PublicKeyResponse>(&path)?.public_key;
-        Ok(P2PDOracleClient { host, public_key })
+        let public_key = get::<PublicKeyResponse>(&client, &path)?.public_key;
+        Ok(P2PDOracleClient {
+            host,
+            public_key,
+            client,
+        })
     }
 }
 
@@ -150,7 +160,7 @@ impl Oracle for P2PDOracleClient {
     fn get_announcement(&self, event_id: &str) -> Result<OracleAnnouncement, DlcManagerError> {
         let (asset_id, date_time) = parse_event_id(event_id)?;
         let path = announcement_path(&self.host, &asset_id, &date_time);
-        let announcement = get(&path)?;
+        let announcement = get(&self.client, &path)?;
         Ok(announcement)
     }
 
@@ -164,7 +174,7 @@ impl Oracle for P2PDOracleClient {
             event_id: _,
             signatures,
             values,
-        } = get::<AttestationResponse>(&path)?;
+        } = get::<AttestationResponse>(&self.client, &path)?;
 
         Ok(OracleAttestation {
             oracle_public_key: self.public_key,
@@ -253,4 +263,48 @@ mod tests {
             .get_attestation("btcusd1624943400")
             .expect("Error getting attestation");
     }
+
+    #[test]
+    fn get_attestations_test() {
+        let url = &mockito::server_url();
+        let _pubkey_mock = pubkey_mock();
+        let attested_path: &str = &attestation_path(
+            "/",
+            "btcusd",
+            &DateTime::parse_from_rfc3339("2021-06-29T05:10:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+        );
+        let pending_path: &str = &attestation_path(
+            "/",
+            "btcusd",
+            &DateTime::parse_from_rfc3339("2021-06-29T05:11:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+        );
+        let attested_mock = mock("GET", attested_path)
+            .with_body(r#"{"eventId":"btcusd1624943400","signatures":["ee05b1211d5f974732b10107dd302da062be47cd18f061c5080a50743412f9fd590cad90cfea762472e6fe865c4223bd388c877b7881a27892e15843ff1ac360"],"values":["0"]}"#)
+            .expect(1)
+            .create();
+        let pending_mock = mock("GET", pending_path)
+            .with_status(404)
+            .expect(1)
+            .create();
+
+        let client = P2PDOracleClient::new(url).expect("Error creating client instance");
+
+        let attestations = client.get_attestations(&["btcusd1624943400", "btcusd1624943460"]);
+
+        assert_eq!(2, attestations.len());
+        assert_eq!(
+            vec!["0".to_string()],
+            attestations[0]
+                .as_ref()
+                .expect("Error getting attestation")
+                .outcomes
+        );
+        assert!(attestations[1].is_err());
+        attested_mock.assert();
+        pending_mock.assert();
+    }
 }
@@ -247,6 +247,18 @@ pub(crate) async fn poll_for_user_input(
                     dlc_message_handler.send_message(node_id, DlcMessage::Accept(msg));
                     peer_manager.process_events();
                 }
+                r @ "rejectoffer" => {
+                    let contract_id = read_id_or_continue!(words, r, "contract id");
+                    let reason = words.collect::<Vec<_>>().join(" ");
+
+                    let (msg, node_id) = dlc_manager
+                        .lock()
+                        .unwrap()
+                        .reject_contract_offer(&contract_id, &reason)
+                        .expect("Error rejecting contract.");
+                    dlc_message_handler.send_message(node_id, DlcMessage::RejectOffer(msg));
+                    peer_manager.process_events();
+                }
                 "listcontracts" => {
                     let manager_clone = dlc_manager.clone();
                     // Because the oracle client is currently blocking we need to use `spawn_blocking` here.
@@ -293,10 +305,18 @@ pub(crate) async fn poll_for_user_input(
                                 Contract::Refunded(_) => {
                                     println!("Refunded contract: {}", id);
                                 }
+                                Contract::Amended(_) => {
+                                    println!("Amended contract: {}", id);
+                                }
+                                Contract::CollaborativelyClosed(closed) => {
+                                    println!("Collaboratively closed contract: {}", id);
+                                    println!("PnL: {} sats", closed.pnl)
+                                }
                                 Contract::FailedAccept(_) | Contract::FailedSign(_) => {
                                     println!("Failed contract: {}", id);
                                 }
                                 Contract::Rejected(_) => println!("Rejected contract: {}", id),
+                                Contract::Expired(_) => println!("Expired contract: {}", id),
                                 Contract::PreClosed(_) => println!("Pre-closed contract: {}", id),
                             }
                         }
@@ -339,6 +359,26 @@ pub(crate) async fn poll_for_user_input(
                     dlc_message_handler.send_message(node_id, DlcMessage::AcceptChannel(msg));
                     peer_manager.process_events();
                 }
+                l @ "stopchannel" => {
+                    let channel_id = read_id_or_continue!(words, l, "channel id");
+                    let (msg, node_id) = dlc_manager
+                        .lock()
+                        .unwrap()
+                        .stop_channel(&channel_id)
+                        .expect("Error stopping channel.");
+                    dlc_message_handler.send_message(node_id, DlcMessage::Stop(msg));
+                    peer_manager.process_events();
+                }
+                l @ "resumechannel" => {
+                    let channel_id = read_id_or_continue!(words, l, "channel id");
+                    let (msg, node_id) = dlc_manager
+                        .lock()
+                        .unwrap()
+                        .resume_channel(&channel_id)
+                        .expect("Error resuming channel.");
+                    dlc_message_handler.send_message(node_id, DlcMessage::Resume(msg));
+                    peer_manager.process_events();
+                }
                 s @ "offersettlechannel" => {
                     let channel_id = read_id_or_continue!(words, s, "channel id");
                     let counter_payout: u64 = match words.next().map(|w| w.parse().ok()) {
@@ -518,10 +558,13 @@ fn help() {
     println!("offercontract <pubkey@host:port> <path_to_contract_input_json>");
     println!("listoffers");
     println!("acceptoffer <contract_id>");
+    println!("rejectoffer <contract_id> [reason]");
     println!("listcontracts");
     println!("offerchannel <pubkey@host:port> <path_to_contract_input_json>");
     println!("listchanneloffers");
     println!("acceptchannel <channel_id>");
+    println!("stopchannel <channel_id>");
+    println!("resumechannel <channel_id>");
     println!("offersettlechannel <channel_id> <counter_payout>");
     println!("listsettlechanneloffers");
     println!("acceptsettlechanneloffer <channel_id>");
@@ -129,10 +129,9 @@ async fn main() {
         time.as_nanos() as u32,
     ));
 
-    println!(
-        "Node public key: {}",
-        km.get_node_id(lightning::sign::Recipient::Node).unwrap()
-    );
+    let node_id = km.get_node_id(lightning::sign::Recipient::Node).unwrap();
+    println!("Node public key: {}", node_id);
+    dlc_manager.lock().unwrap().set_node_id(node_id);
 
     // The peer manager helps us establish connections and communicate with our peers.
     let peer_manager: Arc<PeerManager> = Arc::new(PeerManager::new(
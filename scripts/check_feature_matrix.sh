@@ -0,0 +1,34 @@
+#!/bin/bash
+
+# Builds the core crates with the feature combinations that are expected to
+# compose, so that a feature leaking into (or missing from) another crate is
+# caught without having to enable it workspace wide.
+
+set -e
+
+check() {
+    echo "Checking $1 with: ${@:2}"
+    cargo check -p $1 "${@:2}"
+}
+
+check dlc
+check dlc --no-default-features --features no-std
+check dlc --features use-serde
+check dlc --no-default-features --features no-std,use-serde
+
+check dlc-messages
+check dlc-messages --no-default-features --features no-std
+check dlc-messages --features use-serde
+check dlc-messages --no-default-features --features no-std,use-serde
+
+check dlc-trie
+check dlc-trie --no-default-features --features no-std
+check dlc-trie --features parallel
+check dlc-trie --features use-serde,parallel
+check dlc-trie --no-default-features --features no-std,use-serde
+
+check dlc-manager
+check dlc-manager --features async
+check dlc-manager --features parallel
+check dlc-manager --features use-serde
+check dlc-manager --features async,parallel,use-serde,memory-storage
@@ -2,6 +2,7 @@ use std::ops::Deref;
 
 use bdk::{
     database::{BatchOperations, Database},
+    miniscript::Descriptor,
     wallet::coin_selection::{BranchAndBoundCoinSelection, CoinSelectionAlgorithm},
     FeeRate, KeychainKind, LocalUtxo, Utxo as BdkUtxo, WeightedUtxo,
 };
@@ -40,6 +41,16 @@ pub trait WalletStorage {
     fn unreserve_utxo(&self, txid: &Txid, vout: u32) -> Result<()>;
 }
 
+/// The type of the addresses generated by a [`SimpleWallet`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressType {
+    /// Native segwit v0 addresses, tracked as `wpkh()` descriptors.
+    P2wpkh,
+    /// Taproot addresses spent through the key path, tracked as `tr()`
+    /// descriptors.
+    P2tr,
+}
+
 /// Basic wallet mainly meant for testing purposes.
 pub struct SimpleWallet<B: Deref, W: Deref>
 where
@@ -50,6 +61,7 @@ where
     storage: W,
     secp_ctx: Secp256k1<All>,
     network: Network,
+    address_type: AddressType,
 }
 
 impl<B: Deref, W: Deref> SimpleWallet<B, W>
@@ -64,7 +76,50 @@ where
             storage,
             secp_ctx: Secp256k1::new(),
             network,
+            address_type: AddressType::P2wpkh,
+        }
+    }
+
+    /// Set the type of the payout and change addresses generated by the
+    /// wallet. Defaults to [`AddressType::P2wpkh`].
+    pub fn set_address_type(&mut self, address_type: AddressType) {
+        self.address_type = address_type;
+    }
+
+    /// Returns the descriptors of all the addresses tracked by the wallet.
+    pub fn get_descriptors(&self) -> Result<Vec<String>> {
+        let mut res = Vec::new();
+        for address in self.storage.get_addresses()? {
+            let seckey = self
+                .storage
+                .get_priv_key_for_address(&address)?
+                .ok_or_else(|| {
+                    Error::InvalidState(format!("No private key for address {}", address))
+                })?;
+            let address_type = if address.script_pubkey().is_v1_p2tr() {
+                AddressType::P2tr
+            } else {
+                AddressType::P2wpkh
+            };
+            res.push(self.get_descriptor(&seckey, address_type)?.to_string());
+        }
+        Ok(res)
+    }
+
+    fn get_descriptor(
+        &self,
+        seckey: &SecretKey,
+        address_type: AddressType,
+    ) -> Result<Descriptor<bitcoin::PublicKey>> {
+        let pubkey = bitcoin::PublicKey {
+            inner: PublicKey::from_secret_key(&self.secp_ctx, seckey),
+            compressed: true,
+        };
+        match address_type {
+            AddressType::P2wpkh => Descriptor::new_wpkh(pubkey),
+            AddressType::P2tr => Descriptor::new_tr(pubkey, None),
         }
+        .map_err(|e| Error::WalletError(Box::new(e)))
     }
 
     /// Refresh the wallet checking and updating the UTXO states.
@@ -149,8 +204,11 @@ where
             input,
             output,
         };
-        // Signature + pubkey size assuming P2WPKH.
-        let weight = tx.weight().to_wu() + tx.input.len() as u64 * (74 + 33);
+        let weight = tx.weight().to_wu()
+            + utxos
+                .iter()
+                .map(|x| dlc::util::get_single_key_max_witness_len(&x.tx_out.script_pubkey) as u64)
+                .sum::<u64>();
         let fee_rate = self
             .blockchain
             .get_est_sat_per_1000_weight(ConfirmationTarget::NonAnchorChannelFee)
@@ -222,15 +280,10 @@ where
 {
     fn get_new_address(&self) -> Result<Address> {
         let seckey = SecretKey::new(&mut thread_rng());
-        let pubkey = PublicKey::from_secret_key(&self.secp_ctx, &seckey);
-        let address = Address::p2wpkh(
-            &bitcoin::PublicKey {
-                inner: pubkey,
-                compressed: true,
-            },
-            self.network,
-        )
-        .map_err(|x| Error::WalletError(Box::new(x)))?;
+        let address = self
+            .get_descriptor(&seckey, self.address_type)?
+            .address(self.network)
+            .map_err(|x| Error::WalletError(Box::new(x)))?;
         self.storage.upsert_address(&address, &seckey)?;
         Ok(address)
     }
@@ -256,7 +309,9 @@ where
                     keychain: KeychainKind::External,
                     is_spent: false,
                 }),
-                satisfaction_weight: 107,
+                satisfaction_weight: dlc::util::get_single_key_max_witness_len(
+                    &x.tx_out.script_pubkey,
+                ),
             })
             .collect::<Vec<_>>();
         let coin_selection = BranchAndBoundCoinSelection::default();
@@ -293,11 +348,36 @@ where
         Ok(res)
     }
 
+    fn list_utxos(&self) -> Result<Vec<Utxo>> {
+        Ok(self
+            .storage
+            .get_utxos()?
+            .into_iter()
+            .filter(|x| !x.reserved)
+            .collect())
+    }
+
     fn import_address(&self, _: &Address) -> Result<()> {
         Ok(())
     }
 
-    fn unreserve_utxos(&self, outputs: &[OutPoint]) -> std::result::Result<(), Error> {
+    fn reserve_utxos(&self, outputs: &[OutPoint]) -> std::result::Result<(), Error> {
+        let utxos = self.storage.get_utxos()?;
+        for outpoint in outputs {
+            let utxo = utxos
+                .iter()
+                .find(|x| &x.outpoint == outpoint)
+                .ok_or_else(|| Error::InvalidState(format!("Unknown utxo {}", outpoint)))?;
+            self.storage.upsert_utxo(&Utxo {
+                reserved: true,
+                ..utxo.clone()
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn release_utxos(&self, outputs: &[OutPoint]) -> std::result::Result<(), Error> {
         for outpoint in outputs {
             self.storage.unreserve_utxo(&outpoint.txid, outpoint.vout)?;
         }
@@ -310,24 +390,7 @@ where
         psbt: &mut PartiallySignedTransaction,
         input_index: usize,
     ) -> std::result::Result<(), Error> {
-        let tx_out = if let Some(input) = psbt.inputs.get(input_index) {
-            if let Some(wit_utxo) = &input.witness_utxo {
-                Ok(wit_utxo.clone())
-            } else if let Some(in_tx) = &input.non_witness_utxo {
-                Ok(
-                    in_tx.output[psbt.unsigned_tx.input[input_index].previous_output.vout as usize]
-                        .clone(),
-                )
-            } else {
-                Err(Error::InvalidParameters(
-                    "No TxOut for PSBT inout".to_string(),
-                ))
-            }
-        } else {
-            Err(Error::InvalidParameters(
-                "No TxOut for PSBT inout".to_string(),
-            ))
-        }?;
+        let tx_out = get_psbt_input_tx_out(psbt, input_index)?;
         let address = Address::from_script(&tx_out.script_pubkey, self.network)
             .expect("a valid scriptpubkey");
         let seckey = self
@@ -336,14 +399,27 @@ where
             .expect("to have the requested private key");
 
         let mut tx = psbt.unsigned_tx.clone();
-        dlc::util::sign_p2wpkh_input(
-            &self.secp_ctx,
-            &seckey,
-            &mut tx,
-            input_index,
-            bitcoin::sighash::EcdsaSighashType::All,
-            tx_out.value,
-        )?;
+        if tx_out.script_pubkey.is_v1_p2tr() {
+            let prevouts = (0..psbt.inputs.len())
+                .map(|i| get_psbt_input_tx_out(psbt, i))
+                .collect::<Result<Vec<_>>>()?;
+            dlc::util::sign_p2tr_key_spend_input(
+                &self.secp_ctx,
+                &seckey,
+                &mut tx,
+                input_index,
+                &prevouts,
+            )?;
+        } else {
+            dlc::util::sign_p2wpkh_input(
+                &self.secp_ctx,
+                &seckey,
+                &mut tx,
+                input_index,
+                bitcoin::sighash::EcdsaSighashType::All,
+                tx_out.value,
+            )?;
+        }
 
         let tx_input = tx.input[input_index].clone();
         psbt.inputs[input_index].final_script_sig = Some(tx_input.script_sig);
@@ -352,6 +428,27 @@ where
     }
 }
 
+fn get_psbt_input_tx_out(psbt: &PartiallySignedTransaction, input_index: usize) -> Result<TxOut> {
+    if let Some(input) = psbt.inputs.get(input_index) {
+        if let Some(wit_utxo) = &input.witness_utxo {
+            Ok(wit_utxo.clone())
+        } else if let Some(in_tx) = &input.non_witness_utxo {
+            Ok(
+                in_tx.output[psbt.unsigned_tx.input[input_index].previous_output.vout as usize]
+                    .clone(),
+            )
+        } else {
+            Err(Error::InvalidParameters(
+                "No TxOut for PSBT inout".to_string(),
+            ))
+        }
+    } else {
+        Err(Error::InvalidParameters(
+            "No TxOut for PSBT inout".to_string(),
+        ))
+    }
+}
+
 impl<B: Deref, W: Deref> BatchOperations for SimpleWallet<B, W>
 where
     B::Target: WalletBlockchainProvider,
@@ -524,10 +621,13 @@ where
 mod tests {
     use std::rc::Rc;
 
-    use dlc_manager::ContractSignerProvider;
-    use mocks::simple_wallet::SimpleWallet;
+    use bitcoin::psbt::PartiallySignedTransaction;
+    use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+    use bitcoin::{OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness};
+    use dlc_manager::{ContractSignerProvider, Wallet};
+    use mocks::simple_wallet::{AddressType, SimpleWallet};
     use mocks::{memory_storage_provider::MemoryStorage, mock_blockchain::MockBlockchain};
-    use secp256k1_zkp::{PublicKey, SECP256K1};
+    use secp256k1_zkp::{schnorr::Signature, Message, PublicKey, XOnlyPublicKey, SECP256K1};
 
     fn get_wallet() -> SimpleWallet<Rc<MockBlockchain>, Rc<MemoryStorage>> {
         let blockchain = Rc::new(MockBlockchain::new());
@@ -545,4 +645,51 @@ mod tests {
 
         assert_eq!(sk, sk2);
     }
+
+    #[test]
+    fn taproot_input_is_signed_with_key_path() {
+        let mut wallet = get_wallet();
+        wallet.set_address_type(AddressType::P2tr);
+        let address = wallet.get_new_address().unwrap();
+        assert!(address.script_pubkey().is_v1_p2tr());
+        assert!(wallet.get_descriptors().unwrap()[0].starts_with("tr("));
+
+        let prev_out = TxOut {
+            value: 100000,
+            script_pubkey: address.script_pubkey(),
+        };
+        let tx = Transaction {
+            version: 2,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::default(),
+            }],
+            output: vec![TxOut {
+                value: 90000,
+                script_pubkey: address.script_pubkey(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx.clone()).unwrap();
+        psbt.inputs[0].witness_utxo = Some(prev_out.clone());
+
+        wallet.sign_psbt_input(&mut psbt, 0).unwrap();
+
+        let witness = psbt.inputs[0].final_script_witness.clone().unwrap();
+        assert_eq!(1, witness.len());
+        let sig = Signature::from_slice(witness.nth(0).unwrap()).unwrap();
+        let sig_hash = SighashCache::new(&tx)
+            .taproot_key_spend_signature_hash(
+                0,
+                &Prevouts::All(&[prev_out]),
+                TapSighashType::Default,
+            )
+            .unwrap();
+        let msg = Message::from_slice(sig_hash.as_ref()).unwrap();
+        let output_key =
+            XOnlyPublicKey::from_slice(&address.script_pubkey().as_bytes()[2..]).unwrap();
+        SECP256K1.verify_schnorr(&sig, &msg, &output_key).unwrap();
+    }
 }